@@ -0,0 +1,137 @@
+/*
+   Copyright 2021 JFrog Ltd
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+*/
+
+pub mod encrypted;
+pub mod local;
+pub mod memory;
+pub mod s3;
+
+use std::io::{self, Read};
+use std::path::PathBuf;
+
+/// A reader of the bytes of a stored artifact.
+pub type BlobReader = Box<dyn Read + Send>;
+
+/// A store for the bytes of artifacts, keyed by artifact id. The
+/// `ArtifactStorage` of the `pyrsia` crate delegates to a blob store, so
+/// that large deployments can keep their artifacts in object storage instead
+/// of on a single local volume.
+///
+/// Implementations must never expose a partially written artifact: an
+/// artifact either is stored completely or not at all.
+pub trait BlobStore: Send + Sync {
+    /// Store the bytes read from `reader` as the artifact with `artifact_id`.
+    /// Fails with [`io::ErrorKind::AlreadyExists`] when the artifact is
+    /// already stored.
+    fn push(&self, reader: &mut dyn Read, artifact_id: &str) -> io::Result<()>;
+
+    /// Read a stored artifact.
+    fn pull(&self, artifact_id: &str) -> io::Result<BlobReader>;
+
+    /// Read `len` bytes of a stored artifact starting at `offset`. Fails with
+    /// [`io::ErrorKind::InvalidInput`] when the range exceeds the artifact.
+    fn pull_range(&self, artifact_id: &str, offset: u64, len: u64) -> io::Result<BlobReader>;
+
+    /// The size of a stored artifact in bytes.
+    fn size(&self, artifact_id: &str) -> io::Result<u64>;
+
+    /// Remove a stored artifact.
+    fn remove(&self, artifact_id: &str) -> io::Result<()>;
+
+    /// Move an artifact to `new_artifact_id`. When an artifact with
+    /// `new_artifact_id` is already stored, the artifact is removed instead.
+    fn rename(&self, artifact_id: &str, new_artifact_id: &str) -> io::Result<()>;
+
+    /// Returns true if the artifact is stored.
+    fn contains(&self, artifact_id: &str) -> bool;
+
+    /// Iterate over the ids of all stored artifacts. Implementations read
+    /// the ids lazily, so that stores with a very large number of artifacts
+    /// can be processed in batches.
+    fn ids(&self) -> io::Result<Box<dyn Iterator<Item = String> + Send>>;
+
+    /// The path of the local file that holds the plain bytes of a stored
+    /// artifact, for stores that keep artifacts in such files. Artifacts
+    /// with a file can be served from a memory mapping.
+    fn file_path(&self, _artifact_id: &str) -> Option<PathBuf> {
+        None
+    }
+
+    /// The number of bytes that can still be stored, for stores that know
+    /// it, e.g. the free space of the disk that holds the artifacts.
+    fn available_space(&self) -> Option<u64> {
+        None
+    }
+}
+
+impl<S: BlobStore + ?Sized> BlobStore for Box<S> {
+    fn push(&self, reader: &mut dyn Read, artifact_id: &str) -> io::Result<()> {
+        (**self).push(reader, artifact_id)
+    }
+
+    fn pull(&self, artifact_id: &str) -> io::Result<BlobReader> {
+        (**self).pull(artifact_id)
+    }
+
+    fn pull_range(&self, artifact_id: &str, offset: u64, len: u64) -> io::Result<BlobReader> {
+        (**self).pull_range(artifact_id, offset, len)
+    }
+
+    fn size(&self, artifact_id: &str) -> io::Result<u64> {
+        (**self).size(artifact_id)
+    }
+
+    fn remove(&self, artifact_id: &str) -> io::Result<()> {
+        (**self).remove(artifact_id)
+    }
+
+    fn rename(&self, artifact_id: &str, new_artifact_id: &str) -> io::Result<()> {
+        (**self).rename(artifact_id, new_artifact_id)
+    }
+
+    fn contains(&self, artifact_id: &str) -> bool {
+        (**self).contains(artifact_id)
+    }
+
+    fn ids(&self) -> io::Result<Box<dyn Iterator<Item = String> + Send>> {
+        (**self).ids()
+    }
+
+    fn file_path(&self, artifact_id: &str) -> Option<PathBuf> {
+        (**self).file_path(artifact_id)
+    }
+
+    fn available_space(&self) -> Option<u64> {
+        (**self).available_space()
+    }
+}
+
+fn range_exceeds_artifact(artifact_id: &str, offset: u64, len: u64) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidInput,
+        format!(
+            "Range of {} bytes at offset {} exceeds the size of artifact {}",
+            len, offset, artifact_id
+        ),
+    )
+}
+
+fn artifact_already_exists(artifact_id: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::AlreadyExists,
+        format!("Artifact {} already exists", artifact_id),
+    )
+}
@@ -0,0 +1,657 @@
+/*
+   Copyright 2021 JFrog Ltd
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+*/
+
+use super::{artifact_already_exists, range_exceeds_artifact, BlobReader, BlobStore};
+use aes_gcm::aead::{Aead, NewAead};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use sha2::{Digest, Sha256};
+use std::fmt;
+use std::io::{self, Read};
+
+const KEY_DERIVATION_CONTEXT: &[u8] = b"pyrsia-artifact-encryption";
+const MARKER_SUFFIX: &str = "-encryption";
+const MARKER_FORMAT: &[u8] = b"PYRSENC1";
+const NONCE_PREFIX_LEN: usize = 7;
+const MARKER_LEN: usize = MARKER_FORMAT.len() + NONCE_PREFIX_LEN;
+const SEGMENT_SIZE: u64 = 64 * 1024;
+const TAG_LEN: u64 = 16;
+const ENCRYPTED_SEGMENT_SIZE: u64 = SEGMENT_SIZE + TAG_LEN;
+
+/// The AES-256 key that a node encrypts its artifacts with. The key is
+/// derived from the node's keypair, so it never leaves the node.
+#[derive(Clone)]
+pub struct ArtifactEncryptionKey([u8; 32]);
+
+impl fmt::Debug for ArtifactEncryptionKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ArtifactEncryptionKey(..)")
+    }
+}
+
+impl ArtifactEncryptionKey {
+    pub fn derive(key_material: &[u8]) -> ArtifactEncryptionKey {
+        let mut hasher = Sha256::new();
+        hasher.update(KEY_DERIVATION_CONTEXT);
+        hasher.update(key_material);
+        ArtifactEncryptionKey(hasher.finalize().into())
+    }
+}
+
+/// A [`BlobStore`] that encrypts the artifacts with AES-256-GCM before they
+/// are written to another blob store, for operators that must keep their
+/// data encrypted at rest. Only the stored bytes are encrypted: artifact
+/// ids, sizes and ranges refer to the plaintext, so the hashes in the
+/// transparency log keep matching the artifacts.
+///
+/// An artifact is encrypted in segments of 64 KiB that are authenticated
+/// separately, so artifacts can be streamed and ranges can be read without
+/// decrypting the complete artifact.
+///
+/// Every encrypted artifact has a marker next to it in the other blob store,
+/// which holds the format and the nonce prefix of the artifact. Artifacts
+/// without a marker were stored before encryption was enabled and are read
+/// as they are, whatever their content.
+pub struct EncryptedBlobStore<S> {
+    inner: S,
+    cipher: Aes256Gcm,
+}
+
+impl<S: BlobStore> EncryptedBlobStore<S> {
+    pub fn new(inner: S, key: &ArtifactEncryptionKey) -> EncryptedBlobStore<S> {
+        EncryptedBlobStore {
+            inner,
+            cipher: Aes256Gcm::new(Key::from_slice(&key.0)),
+        }
+    }
+
+    // The nonce prefix of an encrypted artifact from its marker, or None for
+    // an artifact that was stored in plaintext.
+    fn read_marker(&self, artifact_id: &str) -> io::Result<Option<[u8; NONCE_PREFIX_LEN]>> {
+        let mut marker = vec![];
+        match self.inner.pull(&marker_id(artifact_id)) {
+            Ok(mut reader) => reader.read_to_end(&mut marker)?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        if marker.len() != MARKER_LEN || &marker[..MARKER_FORMAT.len()] != MARKER_FORMAT {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Encryption marker of artifact {} is invalid", artifact_id),
+            ));
+        }
+        let mut nonce_prefix = [0; NONCE_PREFIX_LEN];
+        nonce_prefix.copy_from_slice(&marker[MARKER_FORMAT.len()..]);
+        Ok(Some(nonce_prefix))
+    }
+
+    // Remove the marker of an artifact, if it has one.
+    fn remove_marker(&self, artifact_id: &str) -> io::Result<()> {
+        match self.inner.remove(&marker_id(artifact_id)) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+
+    // The number of segments of an encrypted artifact.
+    fn segment_count(&self, artifact_id: &str) -> io::Result<u64> {
+        let encrypted_len = self.inner.size(artifact_id)?;
+        let segment_count = (encrypted_len + ENCRYPTED_SEGMENT_SIZE - 1) / ENCRYPTED_SEGMENT_SIZE;
+        if segment_count == 0 || encrypted_len < segment_count * TAG_LEN {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Encrypted artifact {} is truncated", artifact_id),
+            ));
+        }
+        Ok(segment_count)
+    }
+
+    fn decrypting_reader(
+        &self,
+        reader: BlobReader,
+        nonce_prefix: [u8; NONCE_PREFIX_LEN],
+        first_segment: u64,
+        segment_count: u64,
+    ) -> DecryptingReader {
+        DecryptingReader {
+            reader,
+            cipher: self.cipher.clone(),
+            nonce_prefix,
+            segment: first_segment,
+            last_segment: segment_count - 1,
+            plaintext: vec![],
+            position: 0,
+            skip: 0,
+            remaining: u64::MAX,
+        }
+    }
+}
+
+impl<S: BlobStore> BlobStore for EncryptedBlobStore<S> {
+    // The marker is stored before the artifact, so an artifact is never
+    // visible without it. A marker that is left without an artifact, e.g. by
+    // a failed push, is replaced.
+    fn push(&self, reader: &mut dyn Read, artifact_id: &str) -> io::Result<()> {
+        if self.inner.contains(artifact_id) {
+            return Err(artifact_already_exists(artifact_id));
+        }
+        let nonce_prefix: [u8; NONCE_PREFIX_LEN] = rand::random();
+        let mut marker = MARKER_FORMAT.to_vec();
+        marker.extend_from_slice(&nonce_prefix);
+        self.remove_marker(artifact_id)?;
+        self.inner
+            .push(&mut marker.as_slice(), &marker_id(artifact_id))?;
+
+        let result = self.inner.push(
+            &mut EncryptingReader {
+                reader,
+                cipher: self.cipher.clone(),
+                nonce_prefix,
+                segment: 0,
+                lookahead: None,
+                ciphertext: vec![],
+                position: 0,
+                finished: false,
+            },
+            artifact_id,
+        );
+        if result.is_err() && !self.inner.contains(artifact_id) {
+            let _ = self.remove_marker(artifact_id);
+        }
+        result
+    }
+
+    fn pull(&self, artifact_id: &str) -> io::Result<BlobReader> {
+        let nonce_prefix = match self.read_marker(artifact_id)? {
+            Some(nonce_prefix) => nonce_prefix,
+            None => return self.inner.pull(artifact_id),
+        };
+        let segment_count = self.segment_count(artifact_id)?;
+        Ok(Box::new(self.decrypting_reader(
+            self.inner.pull(artifact_id)?,
+            nonce_prefix,
+            0,
+            segment_count,
+        )))
+    }
+
+    fn pull_range(&self, artifact_id: &str, offset: u64, len: u64) -> io::Result<BlobReader> {
+        let nonce_prefix = match self.read_marker(artifact_id)? {
+            Some(nonce_prefix) => nonce_prefix,
+            None => return self.inner.pull_range(artifact_id, offset, len),
+        };
+        if offset.saturating_add(len) > self.size(artifact_id)? {
+            return Err(range_exceeds_artifact(artifact_id, offset, len));
+        }
+        if len == 0 {
+            return Ok(Box::new(io::empty()));
+        }
+
+        let segment_count = self.segment_count(artifact_id)?;
+        let first_segment = offset / SEGMENT_SIZE;
+        let last_segment = (offset + len - 1) / SEGMENT_SIZE;
+        let encrypted_offset = first_segment * ENCRYPTED_SEGMENT_SIZE;
+        let encrypted_end =
+            ((last_segment + 1) * ENCRYPTED_SEGMENT_SIZE).min(self.inner.size(artifact_id)?);
+        let reader = self.inner.pull_range(
+            artifact_id,
+            encrypted_offset,
+            encrypted_end - encrypted_offset,
+        )?;
+        Ok(Box::new(DecryptingReader {
+            skip: offset - first_segment * SEGMENT_SIZE,
+            remaining: len,
+            ..self.decrypting_reader(reader, nonce_prefix, first_segment, segment_count)
+        }))
+    }
+
+    fn size(&self, artifact_id: &str) -> io::Result<u64> {
+        match self.read_marker(artifact_id)? {
+            Some(_) => {
+                let encrypted_len = self.inner.size(artifact_id)?;
+                Ok(encrypted_len - self.segment_count(artifact_id)? * TAG_LEN)
+            }
+            None => self.inner.size(artifact_id),
+        }
+    }
+
+    fn remove(&self, artifact_id: &str) -> io::Result<()> {
+        self.inner.remove(artifact_id)?;
+        self.remove_marker(artifact_id)
+    }
+
+    fn rename(&self, artifact_id: &str, new_artifact_id: &str) -> io::Result<()> {
+        if self.inner.contains(new_artifact_id) {
+            return self.remove(artifact_id);
+        }
+        // a marker without an artifact is left over and must not be taken
+        // over by the renamed artifact
+        self.remove_marker(new_artifact_id)?;
+        if self.inner.contains(&marker_id(artifact_id)) {
+            self.inner
+                .rename(&marker_id(artifact_id), &marker_id(new_artifact_id))?;
+        }
+        self.inner.rename(artifact_id, new_artifact_id)
+    }
+
+    fn contains(&self, artifact_id: &str) -> bool {
+        self.inner.contains(artifact_id)
+    }
+
+    fn ids(&self) -> io::Result<Box<dyn Iterator<Item = String> + Send>> {
+        Ok(Box::new(self.inner.ids()?.filter(|artifact_id| {
+            !artifact_id.ends_with(MARKER_SUFFIX)
+        })))
+    }
+
+    fn available_space(&self) -> Option<u64> {
+        self.inner.available_space()
+    }
+}
+
+// The id of the blob that marks `artifact_id` as encrypted.
+fn marker_id(artifact_id: &str) -> String {
+    format!("{}{}", artifact_id, MARKER_SUFFIX)
+}
+
+// The nonce of a segment: the random prefix of the artifact, the index of the
+// segment and whether it is the last segment, so segments can neither be
+// reordered nor truncated without failing authentication.
+fn segment_nonce(nonce_prefix: &[u8; NONCE_PREFIX_LEN], segment: u64, last: bool) -> [u8; 12] {
+    let mut nonce = [0; 12];
+    nonce[..NONCE_PREFIX_LEN].copy_from_slice(nonce_prefix);
+    nonce[NONCE_PREFIX_LEN..11].copy_from_slice(&(segment as u32).to_be_bytes());
+    nonce[11] = u8::from(last);
+    nonce
+}
+
+// Read until `buf` is full or the reader is exhausted.
+fn read_full(reader: &mut dyn Read, buf: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(filled)
+}
+
+struct EncryptingReader<'a> {
+    reader: &'a mut dyn Read,
+    cipher: Aes256Gcm,
+    nonce_prefix: [u8; NONCE_PREFIX_LEN],
+    segment: u64,
+    lookahead: Option<u8>,
+    ciphertext: Vec<u8>,
+    position: usize,
+    finished: bool,
+}
+
+impl EncryptingReader<'_> {
+    fn encrypt_next_segment(&mut self) -> io::Result<()> {
+        let mut plaintext = vec![0; SEGMENT_SIZE as usize];
+        let mut len = 0;
+        if let Some(byte) = self.lookahead.take() {
+            plaintext[0] = byte;
+            len = 1;
+        }
+        len += read_full(self.reader, &mut plaintext[len..])?;
+        plaintext.truncate(len);
+
+        // the last segment is only known once the reader is exhausted
+        let mut next_byte = [0; 1];
+        let last = len < SEGMENT_SIZE as usize || read_full(self.reader, &mut next_byte)? == 0;
+        if !last {
+            self.lookahead = Some(next_byte[0]);
+        }
+
+        let nonce = segment_nonce(&self.nonce_prefix, self.segment, last);
+        self.ciphertext = self
+            .cipher
+            .encrypt(Nonce::from_slice(&nonce), plaintext.as_slice())
+            .map_err(|_| io::Error::new(io::ErrorKind::Other, "Failed to encrypt artifact"))?;
+        self.position = 0;
+        self.segment += 1;
+        self.finished = last;
+        Ok(())
+    }
+}
+
+impl Read for EncryptingReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.position == self.ciphertext.len() {
+            if self.finished {
+                return Ok(0);
+            }
+            self.encrypt_next_segment()?;
+        }
+        let n = buf.len().min(self.ciphertext.len() - self.position);
+        buf[..n].copy_from_slice(&self.ciphertext[self.position..self.position + n]);
+        self.position += n;
+        Ok(n)
+    }
+}
+
+struct DecryptingReader {
+    reader: BlobReader,
+    cipher: Aes256Gcm,
+    nonce_prefix: [u8; NONCE_PREFIX_LEN],
+    segment: u64,
+    last_segment: u64,
+    plaintext: Vec<u8>,
+    position: usize,
+    // the number of bytes of the first segment before the requested range
+    skip: u64,
+    remaining: u64,
+}
+
+impl DecryptingReader {
+    fn decrypt_next_segment(&mut self) -> io::Result<()> {
+        if self.segment > self.last_segment {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        let mut ciphertext = vec![0; ENCRYPTED_SEGMENT_SIZE as usize];
+        let len = read_full(&mut self.reader, &mut ciphertext)?;
+        ciphertext.truncate(len);
+
+        let nonce = segment_nonce(
+            &self.nonce_prefix,
+            self.segment,
+            self.segment == self.last_segment,
+        );
+        self.plaintext = self
+            .cipher
+            .decrypt(Nonce::from_slice(&nonce), ciphertext.as_slice())
+            .map_err(|_| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "Failed to decrypt artifact, it was modified or another key was used",
+                )
+            })?;
+        self.position = (self.skip as usize).min(self.plaintext.len());
+        self.skip = 0;
+        self.segment += 1;
+        Ok(())
+    }
+}
+
+impl Read for DecryptingReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.position == self.plaintext.len() {
+            if self.remaining == 0 || self.segment > self.last_segment {
+                return Ok(0);
+            }
+            self.decrypt_next_segment()?;
+        }
+        let n = buf
+            .len()
+            .min(self.plaintext.len() - self.position)
+            .min(usize::try_from(self.remaining).unwrap_or(usize::MAX));
+        buf[..n].copy_from_slice(&self.plaintext[self.position..self.position + n]);
+        self.position += n;
+        self.remaining -= n as u64;
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+#[cfg(not(tarpaulin_include))]
+mod tests {
+    use super::*;
+    use crate::blob_store::local::LocalBlobStore;
+    use crate::blob_store::memory::MemoryBlobStore;
+
+    fn test_artifact(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i % 251) as u8).collect()
+    }
+
+    fn read_all(mut reader: BlobReader) -> io::Result<Vec<u8>> {
+        let mut content = vec![];
+        reader.read_to_end(&mut content)?;
+        Ok(content)
+    }
+
+    #[test]
+    fn test_push_then_pull() {
+        let inner = MemoryBlobStore::new();
+        let blob_store =
+            EncryptedBlobStore::new(inner.clone(), &ArtifactEncryptionKey::derive(b"keypair"));
+
+        for len in [0, 10, SEGMENT_SIZE as usize, 2 * SEGMENT_SIZE as usize + 5] {
+            let artifact = test_artifact(len);
+            let artifact_id = format!("artifact_{}", len);
+            blob_store
+                .push(&mut artifact.as_slice(), &artifact_id)
+                .unwrap();
+
+            assert_eq!(
+                read_all(blob_store.pull(&artifact_id).unwrap()).unwrap(),
+                artifact
+            );
+            assert_eq!(blob_store.size(&artifact_id).unwrap(), len as u64);
+            let stored = read_all(inner.pull(&artifact_id).unwrap()).unwrap();
+            assert!(len < 10 || !stored.windows(10).any(|window| window == &artifact[..10]));
+        }
+    }
+
+    #[test]
+    fn test_pull_range() {
+        let blob_store = EncryptedBlobStore::new(
+            MemoryBlobStore::new(),
+            &ArtifactEncryptionKey::derive(b"keypair"),
+        );
+        let artifact = test_artifact(3 * SEGMENT_SIZE as usize + 100);
+        blob_store.push(&mut artifact.as_slice(), "id").unwrap();
+
+        for (offset, len) in [
+            (0, 1),
+            (10, 100),
+            (SEGMENT_SIZE - 5, 10),
+            (SEGMENT_SIZE, SEGMENT_SIZE),
+            (100, 2 * SEGMENT_SIZE + 50),
+            (3 * SEGMENT_SIZE, 100),
+            (3 * SEGMENT_SIZE + 100, 0),
+        ] {
+            assert_eq!(
+                read_all(blob_store.pull_range("id", offset, len).unwrap()).unwrap(),
+                &artifact[offset as usize..(offset + len) as usize]
+            );
+        }
+        assert_eq!(
+            blob_store
+                .pull_range("id", 3 * SEGMENT_SIZE, 101)
+                .err()
+                .map(|error| error.kind()),
+            Some(io::ErrorKind::InvalidInput)
+        );
+    }
+
+    #[test]
+    fn test_tampered_artifact_is_rejected() {
+        let inner = MemoryBlobStore::new();
+        let blob_store =
+            EncryptedBlobStore::new(inner.clone(), &ArtifactEncryptionKey::derive(b"keypair"));
+        let artifact = test_artifact(2 * SEGMENT_SIZE as usize);
+        blob_store.push(&mut artifact.as_slice(), "id").unwrap();
+        let stored = read_all(inner.pull("id").unwrap()).unwrap();
+        let marker = read_all(inner.pull(&marker_id("id")).unwrap()).unwrap();
+
+        let mut modified = stored.clone();
+        modified[1] ^= 1;
+        inner.push(&mut modified.as_slice(), "modified").unwrap();
+        inner
+            .push(&mut marker.as_slice(), &marker_id("modified"))
+            .unwrap();
+        assert_eq!(
+            read_all(blob_store.pull("modified").unwrap())
+                .unwrap_err()
+                .kind(),
+            io::ErrorKind::InvalidData
+        );
+
+        let truncated = &stored[..ENCRYPTED_SEGMENT_SIZE as usize];
+        inner.push(&mut &truncated[..], "truncated").unwrap();
+        inner
+            .push(&mut marker.as_slice(), &marker_id("truncated"))
+            .unwrap();
+        assert_eq!(
+            read_all(blob_store.pull("truncated").unwrap())
+                .unwrap_err()
+                .kind(),
+            io::ErrorKind::InvalidData
+        );
+
+        let other_key_store = EncryptedBlobStore::new(
+            inner.clone(),
+            &ArtifactEncryptionKey::derive(b"other keypair"),
+        );
+        assert!(read_all(other_key_store.pull("id").unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_plaintext_artifacts_are_read_as_they_are() {
+        let inner = MemoryBlobStore::new();
+        inner
+            .push(&mut "plaintext artifact".as_bytes(), "id")
+            .unwrap();
+        let blob_store = EncryptedBlobStore::new(inner, &ArtifactEncryptionKey::derive(b"keypair"));
+
+        assert_eq!(
+            read_all(blob_store.pull("id").unwrap()).unwrap(),
+            b"plaintext artifact"
+        );
+        assert_eq!(
+            read_all(blob_store.pull_range("id", 0, 9).unwrap()).unwrap(),
+            b"plaintext"
+        );
+        assert_eq!(blob_store.size("id").unwrap(), 18);
+    }
+
+    #[test]
+    fn test_plaintext_artifact_that_looks_encrypted_is_read_as_it_is() {
+        let inner = MemoryBlobStore::new();
+        let plaintext = [MARKER_FORMAT, b"1234567 plaintext artifact"].concat();
+        inner.push(&mut plaintext.as_slice(), "id").unwrap();
+        let blob_store = EncryptedBlobStore::new(inner, &ArtifactEncryptionKey::derive(b"keypair"));
+
+        assert_eq!(read_all(blob_store.pull("id").unwrap()).unwrap(), plaintext);
+        assert_eq!(blob_store.size("id").unwrap(), plaintext.len() as u64);
+    }
+
+    #[test]
+    fn test_invalid_marker_is_rejected() {
+        let inner = MemoryBlobStore::new();
+        let blob_store =
+            EncryptedBlobStore::new(inner.clone(), &ArtifactEncryptionKey::derive(b"keypair"));
+        inner.push(&mut "artifact".as_bytes(), "id").unwrap();
+        inner
+            .push(&mut "not a marker".as_bytes(), &marker_id("id"))
+            .unwrap();
+
+        assert_eq!(
+            blob_store.pull("id").err().map(|error| error.kind()),
+            Some(io::ErrorKind::InvalidData)
+        );
+    }
+
+    #[test]
+    fn test_markers_follow_their_artifacts() {
+        let inner = MemoryBlobStore::new();
+        let blob_store =
+            EncryptedBlobStore::new(inner.clone(), &ArtifactEncryptionKey::derive(b"keypair"));
+        let artifact = test_artifact(100);
+        blob_store.push(&mut artifact.as_slice(), "id").unwrap();
+        inner.push(&mut "plaintext".as_bytes(), "plain").unwrap();
+
+        assert!(inner.contains(&marker_id("id")));
+        let mut ids: Vec<String> = blob_store.ids().unwrap().collect();
+        ids.sort();
+        assert_eq!(ids, vec!["id", "plain"]);
+        assert_eq!(
+            blob_store
+                .push(&mut artifact.as_slice(), "id")
+                .err()
+                .map(|error| error.kind()),
+            Some(io::ErrorKind::AlreadyExists)
+        );
+
+        // a marker that was left over is not taken over by another artifact
+        inner
+            .push(&mut "stale marker".as_bytes(), &marker_id("new_id"))
+            .unwrap();
+        blob_store.rename("id", "new_id").unwrap();
+        assert!(!inner.contains(&marker_id("id")));
+        assert_eq!(
+            read_all(blob_store.pull("new_id").unwrap()).unwrap(),
+            artifact
+        );
+
+        blob_store.rename("plain", "new_plain").unwrap();
+        assert_eq!(
+            read_all(blob_store.pull("new_plain").unwrap()).unwrap(),
+            b"plaintext"
+        );
+
+        blob_store.remove("new_id").unwrap();
+        assert!(!inner.contains(&marker_id("new_id")));
+        blob_store.remove("new_plain").unwrap();
+        assert_eq!(blob_store.ids().unwrap().count(), 0);
+    }
+
+    #[test]
+    fn test_push_then_pull_with_local_blob_store() {
+        let tmp_dir = tempfile::tempdir().unwrap().into_path();
+        let blob_store = EncryptedBlobStore::new(
+            LocalBlobStore::new(&tmp_dir).unwrap(),
+            &ArtifactEncryptionKey::derive(b"keypair"),
+        );
+        let artifact = test_artifact(100);
+        blob_store.push(&mut artifact.as_slice(), "id").unwrap();
+
+        assert_eq!(read_all(blob_store.pull("id").unwrap()).unwrap(), artifact);
+        assert_eq!(blob_store.ids().unwrap().collect::<Vec<_>>(), vec!["id"]);
+
+        std::fs::remove_dir_all(&tmp_dir).unwrap();
+    }
+
+    #[test]
+    fn test_push_replaces_marker_without_artifact() {
+        let inner = MemoryBlobStore::new();
+        let blob_store =
+            EncryptedBlobStore::new(inner.clone(), &ArtifactEncryptionKey::derive(b"keypair"));
+        inner
+            .push(&mut "stale marker".as_bytes(), &marker_id("id"))
+            .unwrap();
+
+        let artifact = test_artifact(100);
+        blob_store.push(&mut artifact.as_slice(), "id").unwrap();
+        assert_eq!(read_all(blob_store.pull("id").unwrap()).unwrap(), artifact);
+    }
+
+    #[test]
+    fn test_derive_key() {
+        assert_eq!(
+            ArtifactEncryptionKey::derive(b"keypair").0,
+            ArtifactEncryptionKey::derive(b"keypair").0
+        );
+        assert_ne!(
+            ArtifactEncryptionKey::derive(b"keypair").0,
+            ArtifactEncryptionKey::derive(b"other keypair").0
+        );
+    }
+}
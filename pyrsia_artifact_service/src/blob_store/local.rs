@@ -0,0 +1,507 @@
+/*
+   Copyright 2021 JFrog Ltd
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+*/
+
+use super::{artifact_already_exists, range_exceeds_artifact, BlobReader, BlobStore};
+use anyhow::anyhow;
+use log::{error, info};
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use sysinfo::{DiskExt, System, SystemExt};
+use uuid::Uuid;
+
+pub const FILE_EXTENSION: &str = "file";
+const TEMP_FILE_EXTENSION: &str = "file.tmp";
+/// The directory below the repository path that contains the shards.
+pub const BLOBS_DIR: &str = "blobs";
+/// The prefix of artifact ids that are derived from the artifact hash, which
+/// are the hex encoded sha2-256 multihash of the hash.
+const MULTIHASH_PREFIX: &str = "1220";
+/// The number of characters of the digest that name the shard of an artifact.
+const SHARD_PREFIX_LEN: usize = 2;
+
+/// A blob store that keeps every artifact in a file in a local directory.
+///
+/// Artifacts are sharded by the first characters of the digest in their
+/// artifact id, like git and docker do, so that no directory holds more than
+/// a fraction of the artifacts: `repo_root_dir/blobs/ab/1220ab...file`.
+/// Artifacts that are stored as flat files in the repository directory by
+/// earlier versions are moved to their shard when the store is created.
+pub struct LocalBlobStore {
+    blobs_path: PathBuf,
+}
+
+impl LocalBlobStore {
+    pub fn new<P: AsRef<Path>>(repository_path: P) -> Result<LocalBlobStore, anyhow::Error> {
+        let absolute_path = repository_path.as_ref().to_path_buf().canonicalize()?;
+        if absolute_path.is_dir() {
+            let blobs_path = absolute_path.join(BLOBS_DIR);
+            std::fs::create_dir_all(&blobs_path)?;
+            let local_blob_store = LocalBlobStore { blobs_path };
+            local_blob_store.migrate_flat_layout(&absolute_path)?;
+            local_blob_store.remove_temp_files()?;
+            Ok(local_blob_store)
+        } else {
+            error!(
+                "Unable to create ArtifactManager with inaccessible directory: {:?}",
+                absolute_path
+            );
+            Err(anyhow!("Not an accessible directory: {:?}", absolute_path))
+        }
+    }
+
+    // Move the artifacts that were stored directly in the repository directory to their
+    // shard. Temporary files of pushes that were interrupted by a restart are removed.
+    fn migrate_flat_layout(&self, repository_path: &Path) -> io::Result<()> {
+        let mut migrated = 0;
+        for entry in std::fs::read_dir(repository_path)? {
+            let path = entry?.path();
+            if !path.is_file() {
+                continue;
+            }
+            if path.to_string_lossy().ends_with(TEMP_FILE_EXTENSION) {
+                std::fs::remove_file(&path)?;
+            } else if let Some(artifact_id) = artifact_id_of(&path) {
+                self.create_shard_dir(&artifact_id)?;
+                let artifact_file_path = self.artifact_file_path(&artifact_id);
+                if artifact_file_path.exists() {
+                    std::fs::remove_file(&path)?;
+                } else {
+                    std::fs::rename(&path, artifact_file_path)?;
+                }
+                migrated += 1;
+            }
+        }
+        if migrated > 0 {
+            info!(
+                "Moved {} artifacts to the sharded storage layout in {:?}",
+                migrated, self.blobs_path
+            );
+        }
+        Ok(())
+    }
+
+    // Remove the temporary files of pushes that were interrupted by a restart.
+    fn remove_temp_files(&self) -> io::Result<()> {
+        for shard in std::fs::read_dir(&self.blobs_path)? {
+            let shard_path = shard?.path();
+            if !shard_path.is_dir() {
+                continue;
+            }
+            for entry in std::fs::read_dir(shard_path)? {
+                let path = entry?.path();
+                if path.to_string_lossy().ends_with(TEMP_FILE_EXTENSION) {
+                    std::fs::remove_file(&path)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn shard_path(&self, artifact_id: &str) -> PathBuf {
+        self.blobs_path.join(shard(artifact_id))
+    }
+
+    fn create_shard_dir(&self, artifact_id: &str) -> io::Result<()> {
+        std::fs::create_dir_all(self.shard_path(artifact_id))
+    }
+
+    // The base file path (no extension on the file name) that will correspond to this artifact id.
+    // The structure of the path is: `repo_root_dir/blobs/shard/artifact_id`. For example:
+    // `pyrsia-artifacts/blobs/3f/12203f5c...`
+    fn base_file_path(&self, artifact_id: &str) -> PathBuf {
+        self.shard_path(artifact_id).join(artifact_id)
+    }
+
+    fn artifact_file_path(&self, artifact_id: &str) -> PathBuf {
+        let mut base_file_path: PathBuf = self.base_file_path(artifact_id);
+        base_file_path.set_extension(FILE_EXTENSION);
+        base_file_path
+    }
+
+    // Artifacts are first written to a temporary file next to their final location. Keeping
+    // both files in the repository directory guarantees they are on the same filesystem, so
+    // publishing the artifact is atomic on every platform we support. Every push gets its own
+    // temporary file, so concurrent pushes of the same artifact do not write to the same file.
+    fn temp_artifact_file_path(&self, artifact_id: &str) -> PathBuf {
+        self.shard_path(artifact_id).join(format!(
+            "{}.{}.{}",
+            artifact_id,
+            Uuid::new_v4(),
+            TEMP_FILE_EXTENSION
+        ))
+    }
+
+    fn write_temp_artifact_file(
+        &self,
+        reader: &mut dyn Read,
+        temp_file_path: &Path,
+    ) -> io::Result<()> {
+        let artifact_file = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(temp_file_path)?;
+        let mut writer = BufWriter::new(artifact_file);
+        io::copy(reader, &mut writer)?;
+        writer.flush()?;
+        writer.get_ref().sync_all()
+    }
+}
+
+// Publish a completely written temporary file as the artifact. Unlike a rename, a hard link
+// fails when the artifact exists, so an artifact that was pushed concurrently is never
+// replaced. File systems without hard links, like FAT, exFAT and some network mounts, refuse
+// to create one and publish the artifact by renaming the temporary file instead.
+fn publish_temp_artifact_file(
+    temp_file_path: &Path,
+    artifact_file_path: &Path,
+    artifact_id: &str,
+) -> io::Result<()> {
+    match std::fs::hard_link(temp_file_path, artifact_file_path) {
+        Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+            Err(artifact_already_exists(artifact_id))
+        }
+        Err(e)
+            if matches!(
+                e.kind(),
+                io::ErrorKind::Unsupported | io::ErrorKind::PermissionDenied
+            ) =>
+        {
+            rename_temp_artifact_file(temp_file_path, artifact_file_path, artifact_id)
+        }
+        result => result,
+    }
+}
+
+// Publish a temporary file as the artifact without a hard link. The artifact file is created
+// exclusively before the temporary file replaces it, so that an artifact that was pushed
+// concurrently is still never replaced. Until then the artifact file is empty.
+fn rename_temp_artifact_file(
+    temp_file_path: &Path,
+    artifact_file_path: &Path,
+    artifact_id: &str,
+) -> io::Result<()> {
+    match OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(artifact_file_path)
+    {
+        Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+            return Err(artifact_already_exists(artifact_id))
+        }
+        result => drop(result?),
+    }
+    std::fs::rename(temp_file_path, artifact_file_path).map_err(|e| {
+        let _ = std::fs::remove_file(artifact_file_path);
+        e
+    })
+}
+
+impl BlobStore for LocalBlobStore {
+    fn push(&self, reader: &mut dyn Read, artifact_id: &str) -> io::Result<()> {
+        let artifact_file_path = self.artifact_file_path(artifact_id);
+        if artifact_file_path.exists() {
+            return Err(artifact_already_exists(artifact_id));
+        }
+
+        self.create_shard_dir(artifact_id)?;
+        let temp_file_path = self.temp_artifact_file_path(artifact_id);
+        let result = self
+            .write_temp_artifact_file(reader, &temp_file_path)
+            .and_then(|_| {
+                publish_temp_artifact_file(&temp_file_path, &artifact_file_path, artifact_id)
+            });
+        let _ = std::fs::remove_file(&temp_file_path);
+        result
+    }
+
+    fn pull(&self, artifact_id: &str) -> io::Result<BlobReader> {
+        Ok(Box::new(File::open(self.artifact_file_path(artifact_id))?))
+    }
+
+    fn pull_range(&self, artifact_id: &str, offset: u64, len: u64) -> io::Result<BlobReader> {
+        let mut artifact_file = File::open(self.artifact_file_path(artifact_id))?;
+        if offset.saturating_add(len) > artifact_file.metadata()?.len() {
+            return Err(range_exceeds_artifact(artifact_id, offset, len));
+        }
+        artifact_file.seek(SeekFrom::Start(offset))?;
+        Ok(Box::new(artifact_file.take(len)))
+    }
+
+    fn size(&self, artifact_id: &str) -> io::Result<u64> {
+        Ok(std::fs::metadata(self.artifact_file_path(artifact_id))?.len())
+    }
+
+    fn remove(&self, artifact_id: &str) -> io::Result<()> {
+        std::fs::remove_file(self.artifact_file_path(artifact_id))
+    }
+
+    fn rename(&self, artifact_id: &str, new_artifact_id: &str) -> io::Result<()> {
+        let artifact_file_path = self.artifact_file_path(artifact_id);
+        let new_artifact_file_path = self.artifact_file_path(new_artifact_id);
+        if new_artifact_file_path.exists() {
+            std::fs::remove_file(artifact_file_path)
+        } else {
+            self.create_shard_dir(new_artifact_id)?;
+            std::fs::rename(artifact_file_path, new_artifact_file_path)
+        }
+    }
+
+    fn contains(&self, artifact_id: &str) -> bool {
+        self.artifact_file_path(artifact_id).is_file()
+    }
+
+    fn file_path(&self, artifact_id: &str) -> Option<PathBuf> {
+        Some(self.artifact_file_path(artifact_id)).filter(|path| path.is_file())
+    }
+
+    fn ids(&self) -> io::Result<Box<dyn Iterator<Item = String> + Send>> {
+        Ok(Box::new(
+            std::fs::read_dir(&self.blobs_path)?
+                .filter_map(|shard| Some(shard.ok()?.path()))
+                .filter(|shard_path| shard_path.is_dir())
+                .flat_map(|shard_path| std::fs::read_dir(shard_path).into_iter().flatten())
+                .filter_map(|entry| artifact_id_of(&entry.ok()?.path())),
+        ))
+    }
+
+    fn available_space(&self) -> Option<u64> {
+        let mut system = System::new();
+        system.refresh_disks_list();
+        // the disk with the most specific mount point holds the blobs
+        system
+            .disks()
+            .iter()
+            .filter(|disk| self.blobs_path.starts_with(disk.mount_point()))
+            .max_by_key(|disk| disk.mount_point().as_os_str().len())
+            .map(|disk| disk.available_space())
+    }
+}
+
+// The shard of an artifact is named after the first characters of the digest in the
+// artifact id. Artifact ids that are not derived from a hash are sharded by their own
+// first characters.
+fn shard(artifact_id: &str) -> &str {
+    let digest = artifact_id
+        .strip_prefix(MULTIHASH_PREFIX)
+        .filter(|digest| !digest.is_empty())
+        .unwrap_or(artifact_id);
+    match digest.char_indices().nth(SHARD_PREFIX_LEN) {
+        Some((end, _)) => &digest[..end],
+        None => digest,
+    }
+}
+
+fn artifact_id_of(path: &Path) -> Option<String> {
+    match path.extension() {
+        Some(ext) if ext.eq(FILE_EXTENSION) => path.file_stem()?.to_str().map(str::to_owned),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+#[cfg(not(tarpaulin_include))]
+mod tests {
+    use super::*;
+    use sha2::{Digest, Sha256};
+    use std::sync::Arc;
+
+    fn derive_artifact_id(artifact_hash: &str) -> String {
+        format!(
+            "{}{}",
+            MULTIHASH_PREFIX,
+            hex::encode(Sha256::digest(artifact_hash.as_bytes()))
+        )
+    }
+
+    #[test]
+    fn test_shard() {
+        let artifact_id = derive_artifact_id("artifact_hash");
+        assert_eq!(shard(&artifact_id), &artifact_id[4..6]);
+        assert_eq!(shard("e131322a-0c72-454d"), "e1");
+        assert_eq!(shard("1220"), "12");
+        assert_eq!(shard("a"), "a");
+    }
+
+    #[test]
+    fn test_artifacts_are_sharded() {
+        let tmp_dir = tempfile::tempdir().unwrap().into_path();
+
+        let blob_store = LocalBlobStore::new(&tmp_dir).unwrap();
+        let artifact_ids = [
+            derive_artifact_id("hash1"),
+            derive_artifact_id("hash2"),
+            derive_artifact_id("hash3"),
+        ];
+        for artifact_id in &artifact_ids {
+            blob_store
+                .push(&mut artifact_id.as_bytes(), artifact_id)
+                .unwrap();
+            assert!(tmp_dir
+                .join(BLOBS_DIR)
+                .join(&artifact_id[4..6])
+                .join(format!("{}.{}", artifact_id, FILE_EXTENSION))
+                .is_file());
+        }
+
+        let mut stored_artifact_ids: Vec<String> = blob_store.ids().unwrap().collect();
+        stored_artifact_ids.sort();
+        let mut expected_artifact_ids = artifact_ids.to_vec();
+        expected_artifact_ids.sort();
+        assert_eq!(stored_artifact_ids, expected_artifact_ids);
+
+        std::fs::remove_dir_all(&tmp_dir).unwrap();
+    }
+
+    #[test]
+    fn test_migrate_flat_layout() {
+        let tmp_dir = tempfile::tempdir().unwrap().into_path();
+
+        let artifact_id = derive_artifact_id("artifact_hash");
+        std::fs::write(
+            tmp_dir.join(format!("{}.{}", artifact_id, FILE_EXTENSION)),
+            "artifact",
+        )
+        .unwrap();
+        std::fs::write(
+            tmp_dir.join(format!("other_id.{}", TEMP_FILE_EXTENSION)),
+            "partial artifact",
+        )
+        .unwrap();
+        std::fs::write(tmp_dir.join("transparency_log.db"), "").unwrap();
+
+        let blob_store = LocalBlobStore::new(&tmp_dir).unwrap();
+
+        let mut artifact = String::new();
+        blob_store
+            .pull(&artifact_id)
+            .unwrap()
+            .read_to_string(&mut artifact)
+            .unwrap();
+        assert_eq!(artifact, "artifact");
+        assert_eq!(
+            blob_store.ids().unwrap().collect::<Vec<_>>(),
+            vec![artifact_id.clone()]
+        );
+        assert!(!tmp_dir
+            .join(format!("{}.{}", artifact_id, FILE_EXTENSION))
+            .exists());
+        assert!(!tmp_dir
+            .join(format!("other_id.{}", TEMP_FILE_EXTENSION))
+            .exists());
+        assert!(tmp_dir.join("transparency_log.db").exists());
+
+        std::fs::remove_dir_all(&tmp_dir).unwrap();
+    }
+
+    #[test]
+    fn test_concurrent_pushes_of_an_artifact() {
+        let tmp_dir = tempfile::tempdir().unwrap().into_path();
+
+        let blob_store = Arc::new(LocalBlobStore::new(&tmp_dir).unwrap());
+        let artifact_id = derive_artifact_id("artifact_hash");
+        let pushes: Vec<_> = (0..8)
+            .map(|i| {
+                let blob_store = blob_store.clone();
+                let artifact_id = artifact_id.clone();
+                std::thread::spawn(move || {
+                    let content = format!("artifact {}", i).repeat(10_000);
+                    blob_store
+                        .push(&mut content.as_bytes(), &artifact_id)
+                        .map(|_| content)
+                })
+            })
+            .collect();
+        let results: Vec<io::Result<String>> = pushes
+            .into_iter()
+            .map(|push| push.join().unwrap())
+            .collect();
+
+        // the artifact of the push that was published first is kept, whole
+        let published: Vec<&String> = results.iter().flatten().collect();
+        assert_eq!(published.len(), 1);
+        assert!(results.iter().all(|result| match result {
+            Ok(_) => true,
+            Err(e) => e.kind() == io::ErrorKind::AlreadyExists,
+        }));
+        let mut artifact = String::new();
+        blob_store
+            .pull(&artifact_id)
+            .unwrap()
+            .read_to_string(&mut artifact)
+            .unwrap();
+        assert_eq!(&artifact, published[0]);
+
+        // no temporary files are left behind
+        let shard_path = tmp_dir.join(BLOBS_DIR).join(shard(&artifact_id));
+        assert_eq!(std::fs::read_dir(shard_path).unwrap().count(), 1);
+
+        std::fs::remove_dir_all(&tmp_dir).unwrap();
+    }
+
+    #[test]
+    fn test_publish_without_hard_link() {
+        let tmp_dir = tempfile::tempdir().unwrap().into_path();
+
+        let artifact_id = derive_artifact_id("artifact_hash");
+        let artifact_file_path = tmp_dir.join(format!("{}.{}", artifact_id, FILE_EXTENSION));
+        let temp_file_path = tmp_dir.join(format!("first.{}", TEMP_FILE_EXTENSION));
+        std::fs::write(&temp_file_path, "artifact").unwrap();
+        rename_temp_artifact_file(&temp_file_path, &artifact_file_path, &artifact_id).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&artifact_file_path).unwrap(),
+            "artifact"
+        );
+        assert!(!temp_file_path.exists());
+
+        // an artifact that was published concurrently is not replaced
+        let other_temp_file_path = tmp_dir.join(format!("second.{}", TEMP_FILE_EXTENSION));
+        std::fs::write(&other_temp_file_path, "other artifact").unwrap();
+        let error =
+            rename_temp_artifact_file(&other_temp_file_path, &artifact_file_path, &artifact_id)
+                .unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::AlreadyExists);
+        assert_eq!(
+            std::fs::read_to_string(&artifact_file_path).unwrap(),
+            "artifact"
+        );
+
+        std::fs::remove_dir_all(&tmp_dir).unwrap();
+    }
+
+    #[test]
+    fn test_temp_files_of_interrupted_pushes_are_removed() {
+        let tmp_dir = tempfile::tempdir().unwrap().into_path();
+
+        let artifact_id = derive_artifact_id("artifact_hash");
+        let shard_path = tmp_dir.join(BLOBS_DIR).join(shard(&artifact_id));
+        std::fs::create_dir_all(&shard_path).unwrap();
+        let temp_file_path = shard_path.join(format!(
+            "{}.{}.{}",
+            artifact_id,
+            Uuid::new_v4(),
+            TEMP_FILE_EXTENSION
+        ));
+        std::fs::write(&temp_file_path, "partial artifact").unwrap();
+
+        let blob_store = LocalBlobStore::new(&tmp_dir).unwrap();
+        assert!(!temp_file_path.exists());
+        assert!(!blob_store.contains(&artifact_id));
+        assert_eq!(blob_store.ids().unwrap().count(), 0);
+
+        std::fs::remove_dir_all(&tmp_dir).unwrap();
+    }
+}
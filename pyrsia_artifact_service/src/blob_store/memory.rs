@@ -0,0 +1,185 @@
+/*
+   Copyright 2021 JFrog Ltd
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+*/
+
+use super::{artifact_already_exists, range_exceeds_artifact, BlobReader, BlobStore};
+use std::collections::BTreeMap;
+use std::io::{self, Cursor, Read};
+use std::sync::{Arc, RwLock};
+
+/// A [`BlobStore`] that keeps the artifacts in memory, for testing code that
+/// uses an `ArtifactStorage` of the `pyrsia` crate without touching the
+/// disk. Clones share the same artifacts.
+#[derive(Clone, Default)]
+pub struct MemoryBlobStore {
+    blobs: Arc<RwLock<BTreeMap<String, Arc<Vec<u8>>>>>,
+}
+
+impl MemoryBlobStore {
+    pub fn new() -> MemoryBlobStore {
+        Default::default()
+    }
+
+    fn blob(&self, artifact_id: &str) -> io::Result<Arc<Vec<u8>>> {
+        self.blobs
+            .read()
+            .unwrap()
+            .get(artifact_id)
+            .cloned()
+            .ok_or_else(|| artifact_not_found(artifact_id))
+    }
+}
+
+fn artifact_not_found(artifact_id: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::NotFound,
+        format!("Artifact {} not found", artifact_id),
+    )
+}
+
+struct BlobSlice {
+    blob: Arc<Vec<u8>>,
+    position: usize,
+    end: usize,
+}
+
+impl Read for BlobSlice {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = Cursor::new(&self.blob[self.position..self.end]).read(buf)?;
+        self.position += n;
+        Ok(n)
+    }
+}
+
+impl BlobStore for MemoryBlobStore {
+    fn push(&self, reader: &mut dyn Read, artifact_id: &str) -> io::Result<()> {
+        if self.contains(artifact_id) {
+            return Err(artifact_already_exists(artifact_id));
+        }
+        let mut blob = vec![];
+        reader.read_to_end(&mut blob)?;
+        let mut blobs = self.blobs.write().unwrap();
+        if blobs.contains_key(artifact_id) {
+            return Err(artifact_already_exists(artifact_id));
+        }
+        blobs.insert(artifact_id.to_owned(), Arc::new(blob));
+        Ok(())
+    }
+
+    fn pull(&self, artifact_id: &str) -> io::Result<BlobReader> {
+        let blob = self.blob(artifact_id)?;
+        let end = blob.len();
+        Ok(Box::new(BlobSlice {
+            blob,
+            position: 0,
+            end,
+        }))
+    }
+
+    fn pull_range(&self, artifact_id: &str, offset: u64, len: u64) -> io::Result<BlobReader> {
+        let blob = self.blob(artifact_id)?;
+        match offset.checked_add(len) {
+            Some(end) if end <= blob.len() as u64 => Ok(Box::new(BlobSlice {
+                blob,
+                position: offset as usize,
+                end: end as usize,
+            })),
+            _ => Err(range_exceeds_artifact(artifact_id, offset, len)),
+        }
+    }
+
+    fn size(&self, artifact_id: &str) -> io::Result<u64> {
+        Ok(self.blob(artifact_id)?.len() as u64)
+    }
+
+    fn remove(&self, artifact_id: &str) -> io::Result<()> {
+        self.blobs
+            .write()
+            .unwrap()
+            .remove(artifact_id)
+            .map(|_| ())
+            .ok_or_else(|| artifact_not_found(artifact_id))
+    }
+
+    fn rename(&self, artifact_id: &str, new_artifact_id: &str) -> io::Result<()> {
+        let mut blobs = self.blobs.write().unwrap();
+        let blob = blobs
+            .remove(artifact_id)
+            .ok_or_else(|| artifact_not_found(artifact_id))?;
+        blobs.entry(new_artifact_id.to_owned()).or_insert(blob);
+        Ok(())
+    }
+
+    fn contains(&self, artifact_id: &str) -> bool {
+        self.blobs.read().unwrap().contains_key(artifact_id)
+    }
+
+    fn ids(&self) -> io::Result<Box<dyn Iterator<Item = String> + Send>> {
+        let ids: Vec<String> = self.blobs.read().unwrap().keys().cloned().collect();
+        Ok(Box::new(ids.into_iter()))
+    }
+}
+
+#[cfg(test)]
+#[cfg(not(tarpaulin_include))]
+mod tests {
+    use super::*;
+
+    fn read_all(mut reader: BlobReader) -> String {
+        let mut content = String::new();
+        reader.read_to_string(&mut content).unwrap();
+        content
+    }
+
+    #[test]
+    fn test_push_then_pull() {
+        let blob_store = MemoryBlobStore::new();
+        blob_store.push(&mut "artifact".as_bytes(), "id").unwrap();
+
+        assert_eq!(read_all(blob_store.pull("id").unwrap()), "artifact");
+        assert_eq!(read_all(blob_store.pull_range("id", 2, 3).unwrap()), "tif");
+        assert_eq!(blob_store.size("id").unwrap(), 8);
+        assert_eq!(
+            blob_store
+                .pull_range("id", 6, 3)
+                .err()
+                .map(|error| error.kind()),
+            Some(io::ErrorKind::InvalidInput)
+        );
+        assert_eq!(
+            blob_store
+                .push(&mut "other".as_bytes(), "id")
+                .unwrap_err()
+                .kind(),
+            io::ErrorKind::AlreadyExists
+        );
+    }
+
+    #[test]
+    fn test_rename_and_remove() {
+        let blob_store = MemoryBlobStore::new();
+        blob_store.push(&mut "artifact".as_bytes(), "id").unwrap();
+        blob_store.rename("id", "new_id").unwrap();
+
+        assert!(!blob_store.contains("id"));
+        assert_eq!(
+            blob_store.ids().unwrap().collect::<Vec<_>>(),
+            vec!["new_id"]
+        );
+
+        blob_store.remove("new_id").unwrap();
+        assert_eq!(blob_store.ids().unwrap().count(), 0);
+    }
+}
@@ -0,0 +1,196 @@
+/*
+   Copyright 2021 JFrog Ltd
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+*/
+
+use log::debug;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use thiserror::Error;
+
+/// How long clients are asked to wait before retrying a transfer that was
+/// refused because the budget was exhausted.
+pub const DEFAULT_RETRY_AFTER: Duration = Duration::from_secs(5);
+
+/// A transfer was refused because the node has no budget left for it. The
+/// transfer can be retried after `retry_after`.
+#[derive(Clone, Debug, Error, Eq, PartialEq)]
+#[error("The node is too busy to transfer the artifact: {reason}")]
+pub struct BudgetExceeded {
+    pub reason: String,
+    pub retry_after: Duration,
+}
+
+/// Global limits on the resources that artifact transfers may use at the same
+/// time: the number of open artifact files and the number of bytes that are
+/// being transferred. A transfer that would exceed a limit is refused right
+/// away, so that a node under heavy load sheds requests instead of running
+/// out of file descriptors or memory. Clones share the same budget.
+#[derive(Clone, Debug)]
+pub struct TransferBudget {
+    max_open_files: Option<usize>,
+    max_in_flight_bytes: Option<u64>,
+    retry_after: Duration,
+    usage: Arc<Mutex<TransferUsage>>,
+}
+
+#[derive(Debug, Default)]
+struct TransferUsage {
+    open_files: usize,
+    in_flight_bytes: u64,
+}
+
+impl Default for TransferBudget {
+    fn default() -> Self {
+        TransferBudget::new(None, None)
+    }
+}
+
+impl TransferBudget {
+    /// Create a budget with the specified limits, `None` means unlimited.
+    pub fn new(max_open_files: Option<usize>, max_in_flight_bytes: Option<u64>) -> Self {
+        TransferBudget {
+            max_open_files,
+            max_in_flight_bytes,
+            retry_after: DEFAULT_RETRY_AFTER,
+            usage: Default::default(),
+        }
+    }
+
+    /// Set how long clients are asked to wait before retrying a refused
+    /// transfer.
+    pub fn with_retry_after(mut self, retry_after: Duration) -> Self {
+        self.retry_after = retry_after;
+        self
+    }
+
+    /// Admit a transfer of `len` bytes from one open artifact file. The
+    /// resources are released when the returned permit is dropped. A single
+    /// transfer that is larger than the maximum number of in-flight bytes is
+    /// admitted when no other transfer is in flight, so it is delayed but
+    /// never refused forever.
+    pub fn try_admit(&self, len: u64) -> Result<TransferPermit, BudgetExceeded> {
+        let mut usage = self.usage.lock().unwrap();
+        if let Some(max_open_files) = self.max_open_files {
+            if usage.open_files >= max_open_files {
+                return Err(self.exceeded(format!(
+                    "all {} artifact file handles are in use",
+                    max_open_files
+                )));
+            }
+        }
+        if let Some(max_in_flight_bytes) = self.max_in_flight_bytes {
+            if usage.in_flight_bytes > 0
+                && usage.in_flight_bytes.saturating_add(len) > max_in_flight_bytes
+            {
+                return Err(self.exceeded(format!(
+                    "{} of the maximum of {} bytes are in flight",
+                    usage.in_flight_bytes, max_in_flight_bytes
+                )));
+            }
+        }
+
+        usage.open_files += 1;
+        usage.in_flight_bytes += len;
+        Ok(TransferPermit {
+            len,
+            usage: self.usage.clone(),
+        })
+    }
+
+    /// The number of artifact files that are currently open for transfers.
+    pub fn open_files(&self) -> usize {
+        self.usage.lock().unwrap().open_files
+    }
+
+    /// The number of bytes that are currently being transferred.
+    pub fn in_flight_bytes(&self) -> u64 {
+        self.usage.lock().unwrap().in_flight_bytes
+    }
+
+    fn exceeded(&self, reason: String) -> BudgetExceeded {
+        debug!("Refusing an artifact transfer: {}", reason);
+        BudgetExceeded {
+            reason,
+            retry_after: self.retry_after,
+        }
+    }
+}
+
+/// The resources of an admitted transfer, which are given back to the
+/// [`TransferBudget`] when the permit is dropped.
+#[derive(Debug)]
+pub struct TransferPermit {
+    len: u64,
+    usage: Arc<Mutex<TransferUsage>>,
+}
+
+impl Drop for TransferPermit {
+    fn drop(&mut self) {
+        let mut usage = self.usage.lock().unwrap();
+        usage.open_files -= 1;
+        usage.in_flight_bytes -= self.len;
+    }
+}
+
+#[cfg(test)]
+#[cfg(not(tarpaulin_include))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unlimited_budget_admits_every_transfer() {
+        let budget = TransferBudget::default();
+
+        let permits: Vec<TransferPermit> = (0..100)
+            .map(|_| budget.try_admit(u64::MAX / 1000).unwrap())
+            .collect();
+
+        assert_eq!(budget.open_files(), 100);
+        drop(permits);
+        assert_eq!(budget.open_files(), 0);
+        assert_eq!(budget.in_flight_bytes(), 0);
+    }
+
+    #[test]
+    fn budget_refuses_transfers_beyond_max_open_files() {
+        let budget = TransferBudget::new(Some(2), None).with_retry_after(Duration::from_secs(1));
+
+        let first = budget.try_admit(10).unwrap();
+        let _second = budget.clone().try_admit(10).unwrap();
+        let error = budget.try_admit(10).unwrap_err();
+        assert_eq!(error.retry_after, Duration::from_secs(1));
+
+        drop(first);
+        assert!(budget.try_admit(10).is_ok());
+    }
+
+    #[test]
+    fn budget_refuses_transfers_beyond_max_in_flight_bytes() {
+        let budget = TransferBudget::new(None, Some(100));
+
+        let first = budget.try_admit(60).unwrap();
+        assert!(budget.try_admit(50).is_err());
+        let second = budget.try_admit(40).unwrap();
+        assert_eq!(budget.in_flight_bytes(), 100);
+
+        drop(first);
+        drop(second);
+        // a transfer larger than the budget is admitted when nothing else is in flight
+        let large = budget.try_admit(1000).unwrap();
+        assert!(budget.try_admit(1).is_err());
+        drop(large);
+        assert_eq!(budget.in_flight_bytes(), 0);
+    }
+}
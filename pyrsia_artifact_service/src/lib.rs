@@ -0,0 +1,30 @@
+/*
+   Copyright 2021 JFrog Ltd
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+*/
+
+//! The blob stores that keep the bytes of artifacts, and the bookkeeping
+//! around them that does not depend on the other services of a Pyrsia node:
+//! quotas, transfer budgets, progress reporting, the pace of provider
+//! records, metadata caching, memory mapping and tag policies. The `pyrsia` crate re-exports these modules under
+//! `pyrsia::artifact_service`.
+
+pub mod blob_store;
+pub mod budget;
+pub mod metadata_cache;
+pub mod mmap;
+pub mod progress;
+pub mod provide;
+pub mod quota;
+pub mod tag_policy;
@@ -0,0 +1,134 @@
+/*
+   Copyright 2021 JFrog Ltd
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+*/
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+
+/// The maximum total size of the metadata artifacts kept in memory.
+const DEFAULT_MAX_CACHE_SIZE: usize = 16 * 1024 * 1024;
+/// Artifacts larger than this are never cached, even if they are metadata.
+const DEFAULT_MAX_ENTRY_SIZE: usize = 1024 * 1024;
+
+/// A bounded in-memory cache for metadata artifacts, keyed by artifact id.
+/// When the cache is full, the artifacts that were added first are evicted.
+/// Clones share the same cache.
+#[derive(Clone)]
+pub struct MetadataCache {
+    inner: Arc<Mutex<MetadataCacheInner>>,
+    max_size: usize,
+    max_entry_size: usize,
+}
+
+#[derive(Default)]
+struct MetadataCacheInner {
+    entries: HashMap<String, Vec<u8>>,
+    insertion_order: VecDeque<String>,
+    size: usize,
+}
+
+impl Default for MetadataCache {
+    fn default() -> Self {
+        MetadataCache::new(DEFAULT_MAX_CACHE_SIZE, DEFAULT_MAX_ENTRY_SIZE)
+    }
+}
+
+impl MetadataCache {
+    pub fn new(max_size: usize, max_entry_size: usize) -> Self {
+        MetadataCache {
+            inner: Default::default(),
+            max_size,
+            max_entry_size: max_entry_size.min(max_size),
+        }
+    }
+
+    pub fn get(&self, artifact_id: &str) -> Option<Vec<u8>> {
+        self.inner.lock().unwrap().entries.get(artifact_id).cloned()
+    }
+
+    pub fn insert(&self, artifact_id: &str, artifact: &[u8]) {
+        if artifact.len() > self.max_entry_size {
+            return;
+        }
+
+        let mut inner = self.inner.lock().unwrap();
+        if inner.entries.contains_key(artifact_id) {
+            return;
+        }
+
+        while inner.size + artifact.len() > self.max_size {
+            match inner.insertion_order.pop_front() {
+                Some(evicted_id) => {
+                    if let Some(evicted) = inner.entries.remove(&evicted_id) {
+                        inner.size -= evicted.len();
+                    }
+                }
+                None => break,
+            }
+        }
+
+        inner.size += artifact.len();
+        inner
+            .entries
+            .insert(artifact_id.to_owned(), artifact.to_vec());
+        inner.insertion_order.push_back(artifact_id.to_owned());
+    }
+
+    pub fn remove(&self, artifact_id: &str) {
+        let mut inner = self.inner.lock().unwrap();
+        if let Some(removed) = inner.entries.remove(artifact_id) {
+            inner.size -= removed.len();
+            inner.insertion_order.retain(|id| id != artifact_id);
+        }
+    }
+}
+
+#[cfg(test)]
+#[cfg(not(tarpaulin_include))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_insert_get_and_remove() {
+        let cache = MetadataCache::new(16, 8);
+
+        cache.insert("a", b"manifest");
+        assert_eq!(cache.get("a"), Some(b"manifest".to_vec()));
+
+        cache.remove("a");
+        assert_eq!(cache.get("a"), None);
+    }
+
+    #[test]
+    fn test_large_artifacts_are_not_cached() {
+        let cache = MetadataCache::new(16, 8);
+
+        cache.insert("a", b"large manifest");
+        assert_eq!(cache.get("a"), None);
+    }
+
+    #[test]
+    fn test_oldest_artifacts_are_evicted() {
+        let cache = MetadataCache::new(16, 8);
+
+        cache.insert("a", b"aaaaaaaa");
+        cache.insert("b", b"bbbbbbbb");
+        cache.insert("c", b"cccc");
+
+        assert_eq!(cache.get("a"), None);
+        assert_eq!(cache.get("b"), Some(b"bbbbbbbb".to_vec()));
+        assert_eq!(cache.get("c"), Some(b"cccc".to_vec()));
+    }
+}
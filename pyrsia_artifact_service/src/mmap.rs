@@ -0,0 +1,462 @@
+/*
+   Copyright 2021 JFrog Ltd
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+*/
+
+use crate::blob_store::BlobReader;
+use log::{debug, warn};
+use multihash::Hasher;
+use rand::Rng;
+use std::collections::HashMap;
+use std::io::{self, Read};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// The size of the pages of a mapped artifact that are checksummed
+/// separately, so that a spot-check only has to hash a single page.
+pub const CHECKSUM_PAGE_SIZE: usize = 1024 * 1024;
+
+/// Configures which artifacts are served from memory-mapped files.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MmapConfig {
+    /// Only artifacts of at least this number of bytes are mapped.
+    pub min_size: u64,
+    /// The maximum number of artifacts that are mapped at the same time, the
+    /// least recently served mapping is dropped when it is exceeded.
+    pub max_mapped_artifacts: usize,
+    /// A random page of a mapped artifact is checked against its checksum
+    /// when the artifact is served and it was not checked for this long.
+    pub spot_check_interval: Duration,
+}
+
+impl Default for MmapConfig {
+    fn default() -> Self {
+        MmapConfig {
+            min_size: 16 * 1024 * 1024,
+            max_mapped_artifacts: 32,
+            spot_check_interval: Duration::from_secs(60),
+        }
+    }
+}
+
+/// The artifacts that are served from memory-mapped files. Serving a hot
+/// large artifact from its mapping avoids reading the file again for every
+/// request. The sha256 hash and the checksums of the pages of an artifact
+/// are calculated once when it is mapped, and pages are spot-checked
+/// periodically after that, so that the artifact does not have to be hashed
+/// completely for every request.
+pub struct MappedArtifacts {
+    config: MmapConfig,
+    mapped: Mutex<MappedLru>,
+}
+
+#[derive(Default)]
+struct MappedLru {
+    clock: u64,
+    artifacts: HashMap<String, (u64, Arc<MappedArtifact>)>,
+}
+
+impl MappedArtifacts {
+    pub fn new(config: MmapConfig) -> Self {
+        MappedArtifacts {
+            config,
+            mapped: Default::default(),
+        }
+    }
+
+    /// The mapped artifact with `artifact_id`, which is mapped from the file
+    /// at `path` when it is at least the minimum size and not mapped yet.
+    /// Returns `None` for artifacts that are too small to be mapped, and for
+    /// artifacts whose mapping failed a spot-check, which are unmapped.
+    pub fn get_or_map(
+        &self,
+        artifact_id: &str,
+        path: &Path,
+        size: u64,
+    ) -> io::Result<Option<Arc<MappedArtifact>>> {
+        if size == 0 || size < self.config.min_size {
+            return Ok(None);
+        }
+
+        let mapped_artifact = match self.touch(artifact_id) {
+            Some(mapped_artifact) => mapped_artifact,
+            None => {
+                let mapped_artifact = Arc::new(MappedArtifact::map(path)?);
+                debug!(
+                    "Mapped artifact {} of {} bytes",
+                    artifact_id,
+                    mapped_artifact.len()
+                );
+                self.insert(artifact_id, mapped_artifact.clone());
+                mapped_artifact
+            }
+        };
+
+        if mapped_artifact.spot_check_if_due(self.config.spot_check_interval) {
+            Ok(Some(mapped_artifact))
+        } else {
+            warn!(
+                "A page of mapped artifact {} does not match its checksum, the artifact is unmapped",
+                artifact_id
+            );
+            self.forget(artifact_id);
+            Ok(None)
+        }
+    }
+
+    /// Drop the mapping of an artifact, when it is removed or moved.
+    pub fn forget(&self, artifact_id: &str) {
+        self.mapped.lock().unwrap().artifacts.remove(artifact_id);
+    }
+
+    /// The number of artifacts that are currently mapped.
+    pub fn len(&self) -> usize {
+        self.mapped.lock().unwrap().artifacts.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn touch(&self, artifact_id: &str) -> Option<Arc<MappedArtifact>> {
+        let mut mapped = self.mapped.lock().unwrap();
+        mapped.clock += 1;
+        let clock = mapped.clock;
+        mapped
+            .artifacts
+            .get_mut(artifact_id)
+            .map(|(last_use, mapped_artifact)| {
+                *last_use = clock;
+                mapped_artifact.clone()
+            })
+    }
+
+    fn insert(&self, artifact_id: &str, mapped_artifact: Arc<MappedArtifact>) {
+        let mut mapped = self.mapped.lock().unwrap();
+        mapped.clock += 1;
+        let clock = mapped.clock;
+        mapped
+            .artifacts
+            .insert(artifact_id.to_owned(), (clock, mapped_artifact));
+        while mapped.artifacts.len() > self.config.max_mapped_artifacts.max(1) {
+            let least_recently_used = mapped
+                .artifacts
+                .iter()
+                .min_by_key(|(_, (last_use, _))| *last_use)
+                .map(|(artifact_id, _)| artifact_id.clone());
+            if let Some(artifact_id) = least_recently_used {
+                mapped.artifacts.remove(&artifact_id);
+            }
+        }
+    }
+}
+
+/// An artifact file that is mapped into memory, with the sha256 hash of its
+/// content and the checksums of its pages as they were when it was mapped.
+/// Stored artifact files are never modified in place, so a page that no
+/// longer matches its checksum points at corruption of the file.
+pub struct MappedArtifact {
+    mapping: Mapping,
+    sha256: String,
+    page_checksums: Vec<[u8; 32]>,
+    last_spot_check: Mutex<Instant>,
+}
+
+impl MappedArtifact {
+    fn map(path: &Path) -> io::Result<MappedArtifact> {
+        let mapping = Mapping::new(path)?;
+        let mut sha256 = multihash::Sha2_256::default();
+        let page_checksums = mapping
+            .as_slice()
+            .chunks(CHECKSUM_PAGE_SIZE)
+            .map(|page| {
+                sha256.update(page);
+                page_checksum(page)
+            })
+            .collect();
+        Ok(MappedArtifact {
+            mapping,
+            sha256: hex::encode(sha256.finalize()),
+            page_checksums,
+            last_spot_check: Mutex::new(Instant::now()),
+        })
+    }
+
+    /// The size of the artifact in bytes.
+    pub fn len(&self) -> u64 {
+        self.mapping.as_slice().len() as u64
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The hex encoded sha256 hash of the artifact when it was mapped.
+    pub fn sha256(&self) -> &str {
+        &self.sha256
+    }
+
+    /// Check that every page still matches its checksum.
+    pub fn check_all_pages(&self) -> bool {
+        (0..self.page_checksums.len()).all(|page| self.check_page(page))
+    }
+
+    /// Read `len` bytes of the artifact starting at `offset`.
+    pub fn reader(self: &Arc<Self>, offset: u64, len: u64) -> io::Result<BlobReader> {
+        if offset.saturating_add(len) > self.len() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "Range of {} bytes at offset {} exceeds the size of the mapped artifact",
+                    len, offset
+                ),
+            ));
+        }
+        Ok(Box::new(MappedReader {
+            mapped_artifact: self.clone(),
+            position: offset as usize,
+            end: (offset + len) as usize,
+        }))
+    }
+
+    fn spot_check_if_due(&self, spot_check_interval: Duration) -> bool {
+        {
+            let mut last_spot_check = self.last_spot_check.lock().unwrap();
+            if last_spot_check.elapsed() < spot_check_interval {
+                return true;
+            }
+            *last_spot_check = Instant::now();
+        }
+        let page = rand::thread_rng().gen_range(0..self.page_checksums.len());
+        self.check_page(page)
+    }
+
+    fn check_page(&self, page: usize) -> bool {
+        let content = self.mapping.as_slice();
+        let start = page * CHECKSUM_PAGE_SIZE;
+        let end = (start + CHECKSUM_PAGE_SIZE).min(content.len());
+        page_checksum(&content[start..end]) == self.page_checksums[page]
+    }
+}
+
+fn page_checksum(page: &[u8]) -> [u8; 32] {
+    let mut sha256 = multihash::Sha2_256::default();
+    sha256.update(page);
+    let mut checksum = [0; 32];
+    checksum.copy_from_slice(sha256.finalize());
+    checksum
+}
+
+struct MappedReader {
+    mapped_artifact: Arc<MappedArtifact>,
+    position: usize,
+    end: usize,
+}
+
+impl Read for MappedReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let content = &self.mapped_artifact.mapping.as_slice()[self.position..self.end];
+        let len = content.len().min(buf.len());
+        buf[..len].copy_from_slice(&content[..len]);
+        self.position += len;
+        Ok(len)
+    }
+}
+
+/// A read-only private mapping of a complete file.
+struct Mapping {
+    ptr: *mut u8,
+    len: usize,
+}
+
+// The mapping is read-only and is only unmapped when it is dropped.
+unsafe impl Send for Mapping {}
+unsafe impl Sync for Mapping {}
+
+impl Mapping {
+    #[cfg(unix)]
+    fn new(path: &Path) -> io::Result<Mapping> {
+        use std::os::unix::io::AsRawFd;
+
+        let file = std::fs::File::open(path)?;
+        let len = usize::try_from(file.metadata()?.len())
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        if len == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "An empty file cannot be mapped",
+            ));
+        }
+        // SAFETY: the file is mapped read-only, the mapping stays valid after
+        // the file is closed and is unmapped on drop. The mapping is private,
+        // so this process never writes through it, but pages that were not
+        // read yet are still loaded from the file. Reading a page beyond the
+        // end of the file raises SIGBUS, so the file must not be truncated
+        // while it is mapped. Blob stores publish an artifact file only once it
+        // is completely written and never write to it afterwards: removing or
+        // renaming it keeps the mapped content, so only a file that is
+        // truncated outside of the node can crash it.
+        let ptr = unsafe {
+            libc::mmap(
+                std::ptr::null_mut(),
+                len,
+                libc::PROT_READ,
+                libc::MAP_PRIVATE,
+                file.as_raw_fd(),
+                0,
+            )
+        };
+        if ptr == libc::MAP_FAILED {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(Mapping {
+            ptr: ptr as *mut u8,
+            len,
+        })
+    }
+
+    #[cfg(not(unix))]
+    fn new(_path: &Path) -> io::Result<Mapping> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "Memory-mapped artifacts are only supported on unix",
+        ))
+    }
+
+    fn as_slice(&self) -> &[u8] {
+        // SAFETY: ptr points at len readable bytes for the lifetime of self.
+        unsafe { std::slice::from_raw_parts(self.ptr, self.len) }
+    }
+}
+
+impl Drop for Mapping {
+    fn drop(&mut self) {
+        #[cfg(unix)]
+        // SAFETY: ptr and len are those of a mapping created by Mapping::new.
+        unsafe {
+            libc::munmap(self.ptr as *mut libc::c_void, self.len);
+        }
+    }
+}
+
+#[cfg(test)]
+#[cfg(not(tarpaulin_include))]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn config(spot_check_interval: Duration) -> MmapConfig {
+        MmapConfig {
+            min_size: 10,
+            max_mapped_artifacts: 2,
+            spot_check_interval,
+        }
+    }
+
+    fn sha256_hex(content: &[u8]) -> String {
+        let mut sha256 = multihash::Sha2_256::default();
+        sha256.update(content);
+        hex::encode(sha256.finalize())
+    }
+
+    #[test]
+    fn mapped_artifacts_are_read_from_the_mapping() {
+        let tmp_dir = tempfile::tempdir().unwrap().into_path();
+        let content: Vec<u8> = (0..CHECKSUM_PAGE_SIZE * 2 + 100)
+            .map(|i| (i % 251) as u8)
+            .collect();
+        let path = tmp_dir.join("artifact");
+        fs::write(&path, &content).unwrap();
+
+        let mapped_artifacts = MappedArtifacts::new(config(Duration::ZERO));
+        assert!(mapped_artifacts
+            .get_or_map("small", &path, 9)
+            .unwrap()
+            .is_none());
+
+        let mapped_artifact = mapped_artifacts
+            .get_or_map("artifact", &path, content.len() as u64)
+            .unwrap()
+            .unwrap();
+        assert_eq!(mapped_artifact.len(), content.len() as u64);
+        assert_eq!(mapped_artifact.sha256(), sha256_hex(&content));
+        assert!(mapped_artifact.check_all_pages());
+
+        let mut range = Vec::new();
+        mapped_artifact
+            .reader(CHECKSUM_PAGE_SIZE as u64 - 10, 20)
+            .unwrap()
+            .read_to_end(&mut range)
+            .unwrap();
+        assert_eq!(
+            range,
+            content[CHECKSUM_PAGE_SIZE - 10..CHECKSUM_PAGE_SIZE + 10]
+        );
+        assert!(mapped_artifact.reader(content.len() as u64, 1).is_err());
+
+        std::fs::remove_dir_all(&tmp_dir).unwrap();
+    }
+
+    #[test]
+    fn least_recently_used_mappings_are_dropped() {
+        let tmp_dir = tempfile::tempdir().unwrap().into_path();
+        let path = tmp_dir.join("artifact");
+        fs::write(&path, "mapped artifact content").unwrap();
+
+        let mapped_artifacts = MappedArtifacts::new(config(Duration::from_secs(60)));
+        for artifact_id in ["first", "second", "first", "third"] {
+            mapped_artifacts
+                .get_or_map(artifact_id, &path, 23)
+                .unwrap()
+                .unwrap();
+        }
+        assert_eq!(mapped_artifacts.len(), 2);
+        let mapped = mapped_artifacts.mapped.lock().unwrap();
+        assert!(mapped.artifacts.contains_key("first"));
+        assert!(mapped.artifacts.contains_key("third"));
+        drop(mapped);
+
+        mapped_artifacts.forget("first");
+        assert_eq!(mapped_artifacts.len(), 1);
+
+        std::fs::remove_dir_all(&tmp_dir).unwrap();
+    }
+
+    #[test]
+    fn corrupt_pages_fail_the_spot_check() {
+        let tmp_dir = tempfile::tempdir().unwrap().into_path();
+        let path = tmp_dir.join("artifact");
+        fs::write(&path, "mapped artifact content").unwrap();
+
+        let mapped_artifacts = MappedArtifacts::new(config(Duration::ZERO));
+        let mapped_artifact = mapped_artifacts
+            .get_or_map("artifact", &path, 23)
+            .unwrap()
+            .unwrap();
+
+        // simulate a page that no longer matches the file it was mapped from
+        let corrupt_artifact = MappedArtifact {
+            mapping: Mapping::new(&path).unwrap(),
+            sha256: mapped_artifact.sha256().to_owned(),
+            page_checksums: vec![[0; 32]],
+            last_spot_check: Mutex::new(Instant::now()),
+        };
+        assert!(!corrupt_artifact.check_all_pages());
+        assert!(!corrupt_artifact.spot_check_if_due(Duration::ZERO));
+        assert!(mapped_artifact.spot_check_if_due(Duration::ZERO));
+
+        std::fs::remove_dir_all(&tmp_dir).unwrap();
+    }
+}
@@ -0,0 +1,236 @@
+/*
+   Copyright 2021 JFrog Ltd
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+*/
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::{self, Read};
+use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast;
+
+/// Progress is reported at most once per this number of bytes.
+const REPORT_INTERVAL_BYTES: u64 = 1024 * 1024;
+const PROGRESS_CHANNEL_CAPACITY: usize = 256;
+
+/// What is happening to an artifact.
+#[derive(
+    Clone, Copy, Debug, Deserialize, Serialize, Eq, Hash, PartialEq, strum_macros::Display,
+)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum TransferDirection {
+    /// the artifact is downloaded from peers on the p2p network
+    Download,
+    /// the artifact is written to the local storage
+    Store,
+}
+
+/// The progress of a transfer of an artifact.
+#[derive(Clone, Debug, Deserialize, Serialize, Eq, PartialEq)]
+pub struct TransferProgress {
+    pub artifact_id: String,
+    pub direction: TransferDirection,
+    pub bytes_transferred: u64,
+    /// The size of the artifact, when it is known.
+    pub total_bytes: Option<u64>,
+    /// The peer that the latest bytes were downloaded from.
+    pub peer: Option<String>,
+    pub finished: bool,
+}
+
+/// Keeps track of the artifacts that are being transferred. Every change is
+/// sent to the subscribers of the tracker, and the transfers that are in
+/// progress can be listed at any time, e.g. for the CLI.
+#[derive(Clone)]
+pub struct TransferProgressTracker {
+    transfers: Arc<Mutex<HashMap<(String, TransferDirection), TransferProgress>>>,
+    sender: broadcast::Sender<TransferProgress>,
+}
+
+impl Default for TransferProgressTracker {
+    fn default() -> Self {
+        let (sender, _) = broadcast::channel(PROGRESS_CHANNEL_CAPACITY);
+        TransferProgressTracker {
+            transfers: Default::default(),
+            sender,
+        }
+    }
+}
+
+impl TransferProgressTracker {
+    /// Receive the progress of all transfers that change after subscribing.
+    pub fn subscribe(&self) -> broadcast::Receiver<TransferProgress> {
+        self.sender.subscribe()
+    }
+
+    /// The transfers that are in progress, ordered by artifact id.
+    pub fn active_transfers(&self) -> Vec<TransferProgress> {
+        let mut transfers: Vec<TransferProgress> =
+            self.transfers.lock().unwrap().values().cloned().collect();
+        transfers.sort_by(|a, b| a.artifact_id.cmp(&b.artifact_id));
+        transfers
+    }
+
+    /// Start tracking a transfer. The transfer is finished when the returned
+    /// handle is dropped.
+    pub fn start(
+        &self,
+        artifact_id: &str,
+        direction: TransferDirection,
+        total_bytes: Option<u64>,
+    ) -> TransferHandle {
+        let progress = TransferProgress {
+            artifact_id: artifact_id.to_owned(),
+            direction,
+            bytes_transferred: 0,
+            total_bytes,
+            peer: None,
+            finished: false,
+        };
+        self.update(progress.clone());
+        TransferHandle {
+            tracker: self.clone(),
+            progress,
+        }
+    }
+
+    fn update(&self, progress: TransferProgress) {
+        let key = (progress.artifact_id.clone(), progress.direction);
+        let mut transfers = self.transfers.lock().unwrap();
+        if progress.finished {
+            transfers.remove(&key);
+        } else {
+            transfers.insert(key, progress.clone());
+        }
+        // there may be no subscribers
+        let _ = self.sender.send(progress);
+    }
+}
+
+/// Reports the progress of a single transfer.
+pub struct TransferHandle {
+    tracker: TransferProgressTracker,
+    progress: TransferProgress,
+}
+
+impl TransferHandle {
+    pub fn set_total_bytes(&mut self, total_bytes: u64) {
+        self.progress.total_bytes = Some(total_bytes);
+        self.tracker.update(self.progress.clone());
+    }
+
+    /// Record that `bytes` more bytes were transferred, from `peer` if they
+    /// were downloaded.
+    pub fn add_bytes(&mut self, bytes: u64, peer: Option<String>) {
+        self.progress.bytes_transferred += bytes;
+        if peer.is_some() {
+            self.progress.peer = peer;
+        }
+        self.tracker.update(self.progress.clone());
+    }
+}
+
+impl Drop for TransferHandle {
+    fn drop(&mut self) {
+        self.progress.finished = true;
+        self.tracker.update(self.progress.clone());
+    }
+}
+
+/// A reader that reports the bytes that are read from it as the progress of
+/// a transfer.
+pub struct ProgressReader<R> {
+    inner: R,
+    handle: TransferHandle,
+    unreported_bytes: u64,
+}
+
+impl<R: Read> ProgressReader<R> {
+    pub fn new(inner: R, handle: TransferHandle) -> Self {
+        ProgressReader {
+            inner,
+            handle,
+            unreported_bytes: 0,
+        }
+    }
+}
+
+impl<R: Read> Read for ProgressReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = self.inner.read(buf)?;
+        self.unreported_bytes += len as u64;
+        if self.unreported_bytes >= REPORT_INTERVAL_BYTES || (len == 0 && self.unreported_bytes > 0)
+        {
+            self.handle.add_bytes(self.unreported_bytes, None);
+            self.unreported_bytes = 0;
+        }
+        Ok(len)
+    }
+}
+
+#[cfg(test)]
+#[cfg(not(tarpaulin_include))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_track_transfer() {
+        let tracker = TransferProgressTracker::default();
+        let mut receiver = tracker.subscribe();
+
+        let mut handle = tracker.start("artifact_id", TransferDirection::Download, None);
+        handle.set_total_bytes(10);
+        handle.add_bytes(4, Some("peer".to_owned()));
+
+        assert_eq!(
+            tracker.active_transfers(),
+            vec![TransferProgress {
+                artifact_id: "artifact_id".to_owned(),
+                direction: TransferDirection::Download,
+                bytes_transferred: 4,
+                total_bytes: Some(10),
+                peer: Some("peer".to_owned()),
+                finished: false,
+            }]
+        );
+
+        drop(handle);
+        assert!(tracker.active_transfers().is_empty());
+
+        let updates: Vec<TransferProgress> =
+            std::iter::from_fn(|| receiver.try_recv().ok()).collect();
+        assert_eq!(updates.len(), 4);
+        assert!(updates[3].finished);
+        assert_eq!(updates[3].bytes_transferred, 4);
+    }
+
+    #[test]
+    fn test_progress_reader() {
+        let tracker = TransferProgressTracker::default();
+        let mut receiver = tracker.subscribe();
+        let content = vec![0u8; REPORT_INTERVAL_BYTES as usize + 10];
+
+        let handle = tracker.start("artifact_id", TransferDirection::Store, None);
+        let mut reader = ProgressReader::new(content.as_slice(), handle);
+        io::copy(&mut reader, &mut io::sink()).unwrap();
+        drop(reader);
+
+        let reported_bytes: Vec<u64> = std::iter::from_fn(|| receiver.try_recv().ok())
+            .map(|progress| progress.bytes_transferred)
+            .collect();
+        assert_eq!(reported_bytes.first(), Some(&0));
+        assert_eq!(reported_bytes.last(), Some(&(content.len() as u64)));
+    }
+}
@@ -0,0 +1,147 @@
+/*
+   Copyright 2021 JFrog Ltd
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+*/
+
+use rand::Rng;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Controls the pace at which the locally stored artifacts are provided on
+/// the p2p network, so that a node with many artifacts does not flood the DHT
+/// and its peers with provider records.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ProvideSchedule {
+    /// The number of artifacts that are provided in one batch.
+    pub batch_size: usize,
+    /// The maximum number of artifacts that are provided per second, or 0 for no limit.
+    pub max_rate: u32,
+    /// The maximum random delay that is added after each batch, so that
+    /// nodes that start at the same time do not provide in lockstep.
+    pub max_jitter: Duration,
+    /// How often all local artifacts are provided again, if at all.
+    pub reprovide_interval: Option<Duration>,
+}
+
+impl Default for ProvideSchedule {
+    fn default() -> Self {
+        ProvideSchedule {
+            batch_size: 256,
+            max_rate: 100,
+            max_jitter: Duration::from_secs(1),
+            reprovide_interval: None,
+        }
+    }
+}
+
+impl ProvideSchedule {
+    /// The time to wait after providing a batch of `batch_len` artifacts,
+    /// which keeps the provide rate below the maximum, plus a random jitter.
+    pub fn delay_after_batch(&self, batch_len: usize) -> Duration {
+        let rate_delay = match self.max_rate {
+            0 => Duration::ZERO,
+            max_rate => Duration::from_secs_f64(batch_len as f64 / max_rate as f64),
+        };
+        rate_delay + random_jitter(self.max_jitter)
+    }
+
+    /// The time to wait before the next round of providing all artifacts.
+    pub fn delay_before_reprovide(&self) -> Option<Duration> {
+        self.reprovide_interval
+            .map(|interval| interval + random_jitter(self.max_jitter))
+    }
+}
+
+fn random_jitter(max_jitter: Duration) -> Duration {
+    match max_jitter.as_millis() as u64 {
+        0 => Duration::ZERO,
+        max_millis => Duration::from_millis(rand::thread_rng().gen_range(0..=max_millis)),
+    }
+}
+
+/// Counts how often each locally stored artifact is requested, so the most
+/// popular artifacts can be provided first.
+#[derive(Clone, Debug, Default)]
+pub struct ArtifactPopularity {
+    request_counts: Arc<Mutex<HashMap<String, u64>>>,
+}
+
+impl ArtifactPopularity {
+    pub fn record_request(&self, artifact_id: &str) {
+        *self
+            .request_counts
+            .lock()
+            .unwrap()
+            .entry(artifact_id.to_owned())
+            .or_default() += 1;
+    }
+
+    /// The ids of all requested artifacts, the most requested first.
+    pub fn most_popular(&self) -> Vec<String> {
+        let request_counts = self.request_counts.lock().unwrap();
+        let mut artifact_ids: Vec<(&String, &u64)> = request_counts.iter().collect();
+        artifact_ids.sort_by(|(id1, count1), (id2, count2)| count2.cmp(count1).then(id1.cmp(id2)));
+        artifact_ids
+            .into_iter()
+            .map(|(artifact_id, _)| artifact_id.clone())
+            .collect()
+    }
+
+    pub fn forget(&self, artifact_id: &str) {
+        self.request_counts.lock().unwrap().remove(artifact_id);
+    }
+}
+
+#[cfg(test)]
+#[cfg(not(tarpaulin_include))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_delay_after_batch_respects_max_rate() {
+        let schedule = ProvideSchedule {
+            batch_size: 50,
+            max_rate: 100,
+            max_jitter: Duration::from_millis(10),
+            reprovide_interval: None,
+        };
+
+        let delay = schedule.delay_after_batch(50);
+        assert!(delay >= Duration::from_millis(500));
+        assert!(delay <= Duration::from_millis(510));
+        assert_eq!(schedule.delay_before_reprovide(), None);
+
+        let unlimited = ProvideSchedule {
+            max_rate: 0,
+            max_jitter: Duration::ZERO,
+            ..schedule
+        };
+        assert_eq!(unlimited.delay_after_batch(50), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_most_popular_artifacts_first() {
+        let popularity = ArtifactPopularity::default();
+        popularity.record_request("a");
+        popularity.record_request("b");
+        popularity.record_request("b");
+        popularity.record_request("c");
+
+        assert_eq!(popularity.most_popular(), vec!["b", "a", "c"]);
+
+        popularity.forget("b");
+        assert_eq!(popularity.most_popular(), vec!["a", "c"]);
+    }
+}
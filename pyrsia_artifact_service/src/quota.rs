@@ -0,0 +1,241 @@
+/*
+   Copyright 2021 JFrog Ltd
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+*/
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::sync::Mutex;
+use tokio::sync::Notify;
+
+/// What a node does before it evicts an artifact that has a transparency
+/// log and that no other peer provides, i.e. the last copy of the artifact
+/// on the p2p network.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SoleCopyPolicy {
+    /// Ask the connected peers to take a replica first, and keep the
+    /// artifact when none of them does.
+    #[default]
+    Replicate,
+    /// Keep the artifact.
+    Keep,
+    /// Evict the artifact anyway.
+    Evict,
+}
+
+/// A maximum size for the artifacts kept by a node. The quota tracks the
+/// size and the last use of every stored artifact, so that the least
+/// recently used artifacts can be evicted when the maximum is exceeded.
+pub struct StorageQuota {
+    max_size: u64,
+    usage: Mutex<StorageUsage>,
+    exceeded: Notify,
+}
+
+impl StorageQuota {
+    pub fn new(max_size: u64) -> StorageQuota {
+        StorageQuota {
+            max_size,
+            usage: Mutex::new(StorageUsage::default()),
+            exceeded: Notify::new(),
+        }
+    }
+
+    pub fn max_size(&self) -> u64 {
+        self.max_size
+    }
+
+    /// The total size of the stored artifacts in bytes.
+    pub fn total_size(&self) -> u64 {
+        self.usage.lock().unwrap().total_size
+    }
+
+    /// Record that an artifact of `size` bytes was stored. Waiters of
+    /// [`StorageQuota::wait_until_exceeded`] are woken up when this takes
+    /// the total size above the maximum.
+    pub fn record(&self, artifact_id: &str, size: u64) {
+        let total_size = {
+            let mut usage = self.usage.lock().unwrap();
+            usage.record(artifact_id, size);
+            usage.total_size
+        };
+        if total_size > self.max_size {
+            self.exceeded.notify_one();
+        }
+    }
+
+    /// Mark an artifact as the most recently used one.
+    pub fn touch(&self, artifact_id: &str) {
+        self.usage.lock().unwrap().touch(artifact_id);
+    }
+
+    /// Record that an artifact is no longer stored.
+    pub fn forget(&self, artifact_id: &str) {
+        self.usage.lock().unwrap().forget(artifact_id);
+    }
+
+    /// Protect an artifact from eviction.
+    pub fn pin(&self, artifact_id: &str) {
+        self.usage
+            .lock()
+            .unwrap()
+            .pinned
+            .insert(artifact_id.to_owned());
+    }
+
+    pub fn unpin(&self, artifact_id: &str) {
+        self.usage.lock().unwrap().pinned.remove(artifact_id);
+    }
+
+    pub fn is_pinned(&self, artifact_id: &str) -> bool {
+        self.usage.lock().unwrap().pinned.contains(artifact_id)
+    }
+
+    /// The unpinned artifacts that must be evicted to get the total size
+    /// back under the maximum, least recently used first.
+    pub fn eviction_candidates(&self) -> Vec<String> {
+        self.usage
+            .lock()
+            .unwrap()
+            .eviction_candidates(self.max_size)
+    }
+
+    /// Wait until an artifact was stored while the total size exceeds the
+    /// maximum.
+    pub async fn wait_until_exceeded(&self) {
+        self.exceeded.notified().await
+    }
+}
+
+#[derive(Default)]
+struct StorageUsage {
+    clock: u64,
+    total_size: u64,
+    entries: HashMap<String, (u64, u64)>,
+    lru: BTreeSet<(u64, String)>,
+    pinned: HashSet<String>,
+}
+
+impl StorageUsage {
+    fn tick(&mut self) -> u64 {
+        self.clock += 1;
+        self.clock
+    }
+
+    fn record(&mut self, artifact_id: &str, size: u64) {
+        self.forget(artifact_id);
+        let tick = self.tick();
+        self.entries.insert(artifact_id.to_owned(), (tick, size));
+        self.lru.insert((tick, artifact_id.to_owned()));
+        self.total_size += size;
+    }
+
+    fn touch(&mut self, artifact_id: &str) {
+        if let Some(&(_, size)) = self.entries.get(artifact_id) {
+            self.record(artifact_id, size);
+        }
+    }
+
+    fn forget(&mut self, artifact_id: &str) {
+        if let Some((tick, size)) = self.entries.remove(artifact_id) {
+            self.lru.remove(&(tick, artifact_id.to_owned()));
+            self.total_size -= size;
+        }
+    }
+
+    fn eviction_candidates(&self, max_size: u64) -> Vec<String> {
+        let mut total_size = self.total_size;
+        self.lru
+            .iter()
+            .filter(|(_, artifact_id)| !self.pinned.contains(artifact_id))
+            .take_while(|(_, artifact_id)| {
+                let exceeded = total_size > max_size;
+                if exceeded {
+                    total_size -= self.entries[artifact_id.as_str()].1;
+                }
+                exceeded
+            })
+            .map(|(_, artifact_id)| artifact_id.clone())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+#[cfg(not(tarpaulin_include))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_total_size() {
+        let quota = StorageQuota::new(100);
+        quota.record("a", 10);
+        quota.record("b", 20);
+        quota.record("a", 30);
+        assert_eq!(quota.total_size(), 50);
+
+        quota.forget("b");
+        quota.forget("c");
+        assert_eq!(quota.total_size(), 30);
+    }
+
+    #[test]
+    fn test_eviction_candidates_are_least_recently_used() {
+        let quota = StorageQuota::new(50);
+        quota.record("a", 20);
+        quota.record("b", 20);
+        quota.record("c", 20);
+        assert_eq!(quota.eviction_candidates(), vec!["a"]);
+
+        quota.touch("a");
+        assert_eq!(quota.eviction_candidates(), vec!["b"]);
+
+        quota.record("d", 30);
+        assert_eq!(quota.eviction_candidates(), vec!["b", "c"]);
+    }
+
+    #[test]
+    fn test_pinned_artifacts_are_not_evicted() {
+        let quota = StorageQuota::new(30);
+        quota.record("a", 20);
+        quota.record("b", 20);
+        quota.pin("a");
+        assert_eq!(quota.eviction_candidates(), vec!["b"]);
+
+        quota.pin("b");
+        assert!(quota.eviction_candidates().is_empty());
+
+        quota.unpin("a");
+        assert!(!quota.is_pinned("a"));
+        assert_eq!(quota.eviction_candidates(), vec!["a"]);
+    }
+
+    #[test]
+    fn test_no_eviction_candidates_under_max_size() {
+        let quota = StorageQuota::new(40);
+        quota.record("a", 20);
+        quota.record("b", 20);
+        assert!(quota.eviction_candidates().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_wait_until_exceeded() {
+        let quota = StorageQuota::new(10);
+        quota.record("a", 20);
+        tokio::time::timeout(
+            std::time::Duration::from_secs(1),
+            quota.wait_until_exceeded(),
+        )
+        .await
+        .unwrap();
+    }
+}
@@ -0,0 +1,179 @@
+/*
+   Copyright 2021 JFrog Ltd
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+*/
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum TagPolicyError {
+    #[error("Invalid tag immutability policy: {0}")]
+    ConfigurationFailure(String),
+    #[error("Tag {package_specific_artifact_id} is immutable in namespace {namespace}, it was published with hash {published_hash} and cannot be published with hash {artifact_hash}")]
+    ImmutableTag {
+        package_specific_artifact_id: String,
+        namespace: String,
+        published_hash: String,
+        artifact_hash: String,
+    },
+}
+
+/// The tag immutability rule of a namespace of docker images. The namespace
+/// is a prefix of the image name. Once a tag in the namespace was published,
+/// it always refers to the same manifest, unless it is one of the mutable
+/// tags, e.g.
+///
+/// ```json
+/// { "namespace": "myorg/", "mutable_tags": ["latest"] }
+/// ```
+#[derive(Clone, Debug, Deserialize, Serialize, Eq, PartialEq)]
+pub struct TagImmutabilityRule {
+    pub namespace: String,
+    #[serde(default)]
+    pub mutable_tags: Vec<String>,
+}
+
+/// The policy that decides which docker tags may be moved to another
+/// manifest when they are published again. The rule with the longest
+/// namespace that matches an image applies. Tags of images that match no
+/// rule are mutable.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct TagImmutabilityPolicy {
+    rules: Vec<TagImmutabilityRule>,
+}
+
+impl TagImmutabilityPolicy {
+    pub fn new(rules: Vec<TagImmutabilityRule>) -> Result<Self, TagPolicyError> {
+        if rules.iter().any(|rule| rule.namespace.is_empty()) {
+            return Err(TagPolicyError::ConfigurationFailure(
+                "a rule has an empty namespace".to_owned(),
+            ));
+        }
+        Ok(TagImmutabilityPolicy { rules })
+    }
+
+    /// Load the policy from a JSON file that contains a list of
+    /// [`TagImmutabilityRule`].
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, TagPolicyError> {
+        let content =
+            fs::read(path).map_err(|e| TagPolicyError::ConfigurationFailure(e.to_string()))?;
+        let rules = serde_json::from_slice(&content)
+            .map_err(|e| TagPolicyError::ConfigurationFailure(e.to_string()))?;
+        Self::new(rules)
+    }
+
+    /// The namespace that makes the docker manifest with
+    /// `package_specific_artifact_id` immutable, or `None` when it is not a
+    /// tag or the tag may be moved.
+    pub fn immutable_namespace(&self, package_specific_artifact_id: &str) -> Option<&str> {
+        let (image, tag) = split_tag(package_specific_artifact_id)?;
+        let rule = self
+            .rules
+            .iter()
+            .filter(|rule| image.starts_with(&rule.namespace))
+            .max_by_key(|rule| rule.namespace.len())?;
+        if rule
+            .mutable_tags
+            .iter()
+            .any(|mutable_tag| mutable_tag == tag)
+        {
+            None
+        } else {
+            Some(&rule.namespace)
+        }
+    }
+}
+
+// Splits a docker manifest id like `library/alpine:3.16` into the image and
+// the tag. Manifests that are referenced by digest have no tag.
+fn split_tag(package_specific_artifact_id: &str) -> Option<(&str, &str)> {
+    if package_specific_artifact_id.contains('@') {
+        return None;
+    }
+    let (image, tag) = package_specific_artifact_id.rsplit_once(':')?;
+    if tag.contains('/') {
+        // the colon separates the port of a registry host
+        None
+    } else {
+        Some((image, tag))
+    }
+}
+
+#[cfg(test)]
+#[cfg(not(tarpaulin_include))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_tag_immutability_rules() {
+        let rules: Vec<TagImmutabilityRule> = serde_json::from_str(
+            r#"[
+                { "namespace": "myorg/", "mutable_tags": ["latest", "nightly"] },
+                { "namespace": "library/" }
+            ]"#,
+        )
+        .unwrap();
+
+        assert_eq!(rules[0].mutable_tags, vec!["latest", "nightly"]);
+        assert!(rules[1].mutable_tags.is_empty());
+        assert!(TagImmutabilityPolicy::new(rules).is_ok());
+
+        let invalid_rule = TagImmutabilityRule {
+            namespace: "".to_owned(),
+            mutable_tags: vec![],
+        };
+        assert!(TagImmutabilityPolicy::new(vec![invalid_rule]).is_err());
+    }
+
+    #[test]
+    fn test_immutable_namespace() {
+        let policy = TagImmutabilityPolicy::new(vec![
+            TagImmutabilityRule {
+                namespace: "myorg/".to_owned(),
+                mutable_tags: vec!["latest".to_owned()],
+            },
+            TagImmutabilityRule {
+                namespace: "myorg/sandbox/".to_owned(),
+                mutable_tags: vec!["latest".to_owned(), "1.2.3".to_owned()],
+            },
+        ])
+        .unwrap();
+
+        assert_eq!(
+            policy.immutable_namespace("myorg/app:1.2.3"),
+            Some("myorg/")
+        );
+        assert_eq!(policy.immutable_namespace("myorg/app:latest"), None);
+        // the longest namespace applies
+        assert_eq!(policy.immutable_namespace("myorg/sandbox/app:1.2.3"), None);
+        assert_eq!(
+            policy.immutable_namespace("myorg/sandbox/app:1.2.4"),
+            Some("myorg/sandbox/")
+        );
+        // images without rule and digests are never immutable tags
+        assert_eq!(policy.immutable_namespace("library/alpine:3.16"), None);
+        assert_eq!(policy.immutable_namespace("myorg/app@sha256:1234"), None);
+        assert_eq!(policy.immutable_namespace("myorg/app"), None);
+    }
+
+    #[test]
+    fn test_default_policy_has_mutable_tags() {
+        let policy = TagImmutabilityPolicy::default();
+
+        assert_eq!(policy.immutable_namespace("myorg/app:1.2.3"), None);
+    }
+}
@@ -0,0 +1,321 @@
+/*
+   Copyright 2021 JFrog Ltd
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+*/
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::{Arc, Mutex};
+use tokio::sync::oneshot;
+use uuid::Uuid;
+
+/// The number of queued builds whose pipeline build id is remembered.
+pub const MAX_PIPELINE_BUILD_IDS: usize = 1024;
+
+/// The build queue limits the number of builds that run in the pipeline at
+/// the same time, so that a burst of build requests can not exhaust the
+/// machine of an authorized node. Builds that can not start right away wait
+/// until a running build finishes, in the order in which they were requested.
+///
+/// A queued build gets its build id from the queue instead of the pipeline.
+/// The queue remembers the id that the pipeline assigns once it starts, for
+/// the last [`MAX_PIPELINE_BUILD_IDS`] builds that were queued.
+///
+/// A build that holds a slot, but that the pipeline refused to start because
+/// it is rate limited, is reported ahead of the queued builds until the
+/// pipeline starts it.
+#[derive(Clone)]
+pub struct BuildQueue {
+    max_concurrent_builds: Option<usize>,
+    state: Arc<Mutex<QueueState>>,
+}
+
+#[derive(Default)]
+struct QueueState {
+    running: usize,
+    queued: VecDeque<(String, oneshot::Sender<BuildSlot>)>,
+    rate_limited: HashSet<String>,
+    pipeline_build_ids: HashMap<String, String>,
+    pipeline_build_id_order: VecDeque<String>,
+}
+
+/// How a build was admitted by the build queue.
+pub enum BuildAdmission {
+    /// The build starts right away with the given slot.
+    Started(BuildSlot),
+    /// The build was queued with the given build id, its slot is sent to
+    /// the receiver when it is the build's turn.
+    Queued(String, oneshot::Receiver<BuildSlot>),
+}
+
+/// A build in the pipeline holds a slot of the build queue until it
+/// finishes. Dropping the slot hands it to the next queued build.
+pub struct BuildSlot {
+    state: Arc<Mutex<QueueState>>,
+}
+
+impl Default for BuildQueue {
+    fn default() -> Self {
+        BuildQueue::new(None)
+    }
+}
+
+impl BuildQueue {
+    /// A queue that runs at most `max_concurrent_builds` builds at the same
+    /// time, at least one, or any number of builds when it is not set.
+    pub fn new(max_concurrent_builds: Option<usize>) -> Self {
+        BuildQueue {
+            max_concurrent_builds: max_concurrent_builds.map(|max| max.max(1)),
+            state: Default::default(),
+        }
+    }
+
+    pub fn max_concurrent_builds(&self) -> Option<usize> {
+        self.max_concurrent_builds
+    }
+
+    /// Take a slot for a build that starts right away, or queue the build
+    /// when all slots are taken or when other builds are queued, so that a
+    /// new build does not overtake them. Both happen under the same lock, so
+    /// a slot that is freed in the meantime can not miss the queued build.
+    pub fn start_or_enqueue(&self) -> BuildAdmission {
+        let mut state = self.state.lock().unwrap();
+        let all_slots_taken =
+            matches!(self.max_concurrent_builds, Some(max) if state.running >= max);
+        if state.queued.is_empty() && !all_slots_taken {
+            state.running += 1;
+            return BuildAdmission::Started(BuildSlot {
+                state: self.state.clone(),
+            });
+        }
+
+        let build_id = Uuid::new_v4().to_string();
+        let (sender, receiver) = oneshot::channel();
+        state.queued.push_back((build_id.clone(), sender));
+        BuildAdmission::Queued(build_id, receiver)
+    }
+
+    /// The position of the queued build with `build_id` in the queue, 0 for
+    /// a rate limited build, 1 for the next build to start, or `None` when it
+    /// is not queued.
+    pub fn position(&self, build_id: &str) -> Option<usize> {
+        let state = self.state.lock().unwrap();
+        if state.rate_limited.contains(build_id) {
+            return Some(0);
+        }
+        state
+            .queued
+            .iter()
+            .position(|(queued_build_id, _)| queued_build_id == build_id)
+            .map(|index| index + 1)
+    }
+
+    /// Remember that the build with `build_id` holds a slot, but waits until
+    /// the pipeline is no longer rate limited.
+    pub fn record_rate_limited(&self, build_id: &str) {
+        self.state
+            .lock()
+            .unwrap()
+            .rate_limited
+            .insert(build_id.to_owned());
+    }
+
+    /// Forget that the build with `build_id` was rate limited, once the
+    /// pipeline started it or the build gave up.
+    pub fn clear_rate_limited(&self, build_id: &str) {
+        self.state.lock().unwrap().rate_limited.remove(build_id);
+    }
+
+    /// The number of builds that are running and that are queued.
+    pub fn counts(&self) -> (usize, usize) {
+        let state = self.state.lock().unwrap();
+        (state.running, state.queued.len())
+    }
+
+    /// Remember the id that the pipeline assigned to a build that was queued.
+    /// The id of the oldest build is forgotten when more than
+    /// [`MAX_PIPELINE_BUILD_IDS`] ids are remembered.
+    pub fn record_pipeline_build_id(&self, build_id: &str, pipeline_build_id: &str) {
+        let mut state = self.state.lock().unwrap();
+        if state
+            .pipeline_build_ids
+            .insert(build_id.to_owned(), pipeline_build_id.to_owned())
+            .is_none()
+        {
+            state.pipeline_build_id_order.push_back(build_id.to_owned());
+        }
+        while state.pipeline_build_id_order.len() > MAX_PIPELINE_BUILD_IDS {
+            if let Some(oldest_build_id) = state.pipeline_build_id_order.pop_front() {
+                state.pipeline_build_ids.remove(&oldest_build_id);
+            }
+        }
+    }
+
+    /// The id of the build with `build_id` in the pipeline, which is the same
+    /// id unless the build was queued.
+    pub fn pipeline_build_id(&self, build_id: &str) -> String {
+        self.state
+            .lock()
+            .unwrap()
+            .pipeline_build_ids
+            .get(build_id)
+            .cloned()
+            .unwrap_or_else(|| build_id.to_owned())
+    }
+}
+
+impl Drop for BuildSlot {
+    fn drop(&mut self) {
+        let next_build = {
+            let mut state = self.state.lock().unwrap();
+            let next_build = state.queued.pop_front();
+            if next_build.is_none() {
+                state.running -= 1;
+            }
+            next_build
+        };
+        // the slot of a queued build that was given up is handed on when it
+        // is dropped, outside of the lock
+        if let Some((_, sender)) = next_build {
+            let _ = sender.send(BuildSlot {
+                state: self.state.clone(),
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+#[cfg(not(tarpaulin_include))]
+mod tests {
+    use super::*;
+
+    impl BuildAdmission {
+        fn started(self) -> Option<BuildSlot> {
+            match self {
+                BuildAdmission::Started(build_slot) => Some(build_slot),
+                BuildAdmission::Queued(..) => None,
+            }
+        }
+
+        fn queued(self) -> (String, oneshot::Receiver<BuildSlot>) {
+            match self {
+                BuildAdmission::Started(_) => panic!("Build should have been queued."),
+                BuildAdmission::Queued(build_id, receiver) => (build_id, receiver),
+            }
+        }
+    }
+
+    #[test]
+    fn test_builds_are_queued_when_all_slots_are_taken() {
+        let build_queue = BuildQueue::new(Some(1));
+
+        let slot = build_queue.start_or_enqueue().started().unwrap();
+        let (first_build_id, mut first_slot_receiver) = build_queue.start_or_enqueue().queued();
+        let (second_build_id, mut second_slot_receiver) = build_queue.start_or_enqueue().queued();
+        assert_eq!(build_queue.position(&first_build_id), Some(1));
+        assert_eq!(build_queue.position(&second_build_id), Some(2));
+        assert_eq!(build_queue.counts(), (1, 2));
+
+        drop(slot);
+        let first_slot = first_slot_receiver.try_recv().unwrap();
+        assert!(second_slot_receiver.try_recv().is_err());
+        assert_eq!(build_queue.position(&first_build_id), None);
+        assert_eq!(build_queue.position(&second_build_id), Some(1));
+        // a new build does not overtake the queued build
+        let (third_build_id, mut third_slot_receiver) = build_queue.start_or_enqueue().queued();
+        assert_eq!(build_queue.position(&third_build_id), Some(2));
+
+        drop(first_slot);
+        drop(second_slot_receiver.try_recv().unwrap());
+        drop(third_slot_receiver.try_recv().unwrap());
+        assert_eq!(build_queue.counts(), (0, 0));
+        assert!(build_queue.start_or_enqueue().started().is_some());
+    }
+
+    #[test]
+    fn test_slot_of_abandoned_build_is_handed_on() {
+        let build_queue = BuildQueue::new(Some(1));
+
+        let slot = build_queue.start_or_enqueue().started().unwrap();
+        let (_, abandoned_slot_receiver) = build_queue.start_or_enqueue().queued();
+        let (_, mut slot_receiver) = build_queue.start_or_enqueue().queued();
+        drop(abandoned_slot_receiver);
+
+        drop(slot);
+        assert!(slot_receiver.try_recv().is_ok());
+        assert_eq!(build_queue.counts(), (0, 0));
+    }
+
+    #[test]
+    fn test_unlimited_build_queue() {
+        let build_queue = BuildQueue::default();
+
+        let slots: Vec<BuildSlot> = (0..10)
+            .map(|_| build_queue.start_or_enqueue().started().unwrap())
+            .collect();
+        assert_eq!(build_queue.counts(), (10, 0));
+        drop(slots);
+        assert_eq!(build_queue.counts(), (0, 0));
+    }
+
+    #[test]
+    fn test_slot_freed_concurrently_is_handed_to_queued_build() {
+        let build_queue = BuildQueue::new(Some(1));
+        let slot = build_queue.start_or_enqueue().started().unwrap();
+
+        let queue = build_queue.clone();
+        let admission = std::thread::spawn(move || queue.start_or_enqueue());
+        drop(slot);
+        // whichever came first, the new build holds the only slot
+        match admission.join().unwrap() {
+            BuildAdmission::Started(slot) => drop(slot),
+            BuildAdmission::Queued(_, mut slot_receiver) => drop(slot_receiver.try_recv().unwrap()),
+        }
+        assert_eq!(build_queue.counts(), (0, 0));
+    }
+
+    #[test]
+    fn test_rate_limited_build_is_ahead_of_queued_builds() {
+        let build_queue = BuildQueue::new(Some(1));
+
+        let _slot = build_queue.start_or_enqueue().started().unwrap();
+        let (queued_build_id, _slot_receiver) = build_queue.start_or_enqueue().queued();
+        build_queue.record_rate_limited("rate-limited");
+        assert_eq!(build_queue.position("rate-limited"), Some(0));
+        assert_eq!(build_queue.position(&queued_build_id), Some(1));
+        assert_eq!(build_queue.counts(), (1, 1));
+
+        build_queue.clear_rate_limited("rate-limited");
+        assert_eq!(build_queue.position("rate-limited"), None);
+    }
+
+    #[test]
+    fn test_pipeline_build_ids_are_bounded() {
+        let build_queue = BuildQueue::default();
+
+        for i in 0..=MAX_PIPELINE_BUILD_IDS {
+            build_queue
+                .record_pipeline_build_id(&format!("queued-{}", i), &format!("pipeline-{}", i));
+        }
+        assert_eq!(build_queue.pipeline_build_id("queued-0"), "queued-0");
+        assert_eq!(build_queue.pipeline_build_id("queued-1"), "pipeline-1");
+        assert_eq!(
+            build_queue.pipeline_build_id(&format!("queued-{}", MAX_PIPELINE_BUILD_IDS)),
+            format!("pipeline-{}", MAX_PIPELINE_BUILD_IDS)
+        );
+        assert_eq!(
+            build_queue.state.lock().unwrap().pipeline_build_ids.len(),
+            MAX_PIPELINE_BUILD_IDS
+        );
+    }
+}
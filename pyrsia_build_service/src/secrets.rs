@@ -0,0 +1,307 @@
+/*
+   Copyright 2021 JFrog Ltd
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+*/
+
+use chacha20poly1305::aead::{Aead, NewAead};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use thiserror::Error;
+
+const NONCE_LENGTH: usize = 12;
+const KEY_DERIVATION_CONTEXT: &[u8] = b"pyrsia-build-secrets";
+
+#[derive(Debug, Error)]
+pub enum SecretStoreError {
+    #[error("Failure while accessing the secret store: {0}")]
+    StorageFailure(#[from] io::Error),
+    #[error("Failure while (de)serializing secrets: {0}")]
+    SerializationFailure(#[from] serde_json::Error),
+    #[error("Failed to encrypt or decrypt the secret store")]
+    EncryptionFailure,
+    #[error("Invalid secret name or namespace: {0}")]
+    InvalidName(String),
+}
+
+/// A secret value, like a token or a password. Its value is never included
+/// in debug output, so it does not end up in logs.
+#[derive(Clone, Deserialize, Serialize, Eq, PartialEq)]
+#[serde(transparent)]
+pub struct Secret(String);
+
+impl Secret {
+    pub fn new(value: &str) -> Self {
+        Secret(value.to_owned())
+    }
+
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Secret(***)")
+    }
+}
+
+/// Describes a stored secret without revealing its value.
+#[derive(Clone, Debug, Deserialize, Serialize, Eq, PartialEq)]
+pub struct SecretDescriptor {
+    pub namespace: String,
+    pub name: String,
+}
+
+// namespace -> secret name -> secret
+type Secrets = BTreeMap<String, BTreeMap<String, Secret>>;
+
+/// The secret store keeps the credentials that builds need, like tokens for
+/// private Git repositories. Secrets are registered per package namespace:
+/// a build for a package receives the secrets of every namespace that is a
+/// prefix of its package specific id.
+///
+/// The secrets are encrypted at rest with a key that is derived from the
+/// node's keypair.
+#[derive(Clone)]
+pub struct SecretStore {
+    path: PathBuf,
+    key: [u8; 32],
+    lock: Arc<Mutex<()>>,
+}
+
+impl SecretStore {
+    pub fn new<P: AsRef<Path>>(path: P, key_material: &[u8]) -> Self {
+        let mut hasher = Sha256::new();
+        hasher.update(KEY_DERIVATION_CONTEXT);
+        hasher.update(key_material);
+        SecretStore {
+            path: path.as_ref().to_path_buf(),
+            key: hasher.finalize().into(),
+            lock: Default::default(),
+        }
+    }
+
+    /// Store a secret under `name` for the specified `namespace`, replacing
+    /// the secret that was stored before.
+    pub fn set(&self, namespace: &str, name: &str, secret: Secret) -> Result<(), SecretStoreError> {
+        validate_name(namespace)?;
+        validate_name(name)?;
+
+        let _guard = self.lock.lock().unwrap();
+        let mut secrets = self.load()?;
+        secrets
+            .entry(namespace.to_owned())
+            .or_default()
+            .insert(name.to_owned(), secret);
+        self.save(&secrets)
+    }
+
+    /// Remove a secret. Returns false if no such secret existed.
+    pub fn remove(&self, namespace: &str, name: &str) -> Result<bool, SecretStoreError> {
+        let _guard = self.lock.lock().unwrap();
+        let mut secrets = self.load()?;
+        let removed = match secrets.get_mut(namespace) {
+            Some(namespace_secrets) => {
+                let removed = namespace_secrets.remove(name).is_some();
+                if namespace_secrets.is_empty() {
+                    secrets.remove(namespace);
+                }
+                removed
+            }
+            None => false,
+        };
+        if removed {
+            self.save(&secrets)?;
+        }
+        Ok(removed)
+    }
+
+    /// List the namespaces and names of all stored secrets.
+    pub fn list(&self) -> Result<Vec<SecretDescriptor>, SecretStoreError> {
+        let _guard = self.lock.lock().unwrap();
+        Ok(self
+            .load()?
+            .into_iter()
+            .flat_map(|(namespace, namespace_secrets)| {
+                namespace_secrets
+                    .into_keys()
+                    .map(move |name| SecretDescriptor {
+                        namespace: namespace.clone(),
+                        name,
+                    })
+            })
+            .collect())
+    }
+
+    /// Get the secrets for a build of the package with the specified
+    /// `package_specific_id`. When several namespaces define a secret with
+    /// the same name, the most specific namespace wins.
+    pub fn secrets_for(
+        &self,
+        package_specific_id: &str,
+    ) -> Result<HashMap<String, Secret>, SecretStoreError> {
+        let _guard = self.lock.lock().unwrap();
+        let mut matching_namespaces: Vec<(String, BTreeMap<String, Secret>)> = self
+            .load()?
+            .into_iter()
+            .filter(|(namespace, _)| package_specific_id.starts_with(namespace.as_str()))
+            .collect();
+        matching_namespaces.sort_by_key(|(namespace, _)| namespace.len());
+
+        Ok(matching_namespaces
+            .into_iter()
+            .flat_map(|(_, namespace_secrets)| namespace_secrets)
+            .collect())
+    }
+
+    fn load(&self) -> Result<Secrets, SecretStoreError> {
+        let encrypted = match fs::read(&self.path) {
+            Ok(encrypted) => encrypted,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Secrets::new()),
+            Err(e) => return Err(e.into()),
+        };
+        if encrypted.len() < NONCE_LENGTH {
+            return Err(SecretStoreError::EncryptionFailure);
+        }
+
+        let (nonce, ciphertext) = encrypted.split_at(NONCE_LENGTH);
+        let plaintext = self
+            .cipher()
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| SecretStoreError::EncryptionFailure)?;
+        Ok(serde_json::from_slice(&plaintext)?)
+    }
+
+    fn save(&self, secrets: &Secrets) -> Result<(), SecretStoreError> {
+        let plaintext = serde_json::to_vec(secrets)?;
+        let nonce: [u8; NONCE_LENGTH] = rand::random();
+        let mut encrypted = nonce.to_vec();
+        encrypted.extend(
+            self.cipher()
+                .encrypt(Nonce::from_slice(&nonce), plaintext.as_ref())
+                .map_err(|_| SecretStoreError::EncryptionFailure)?,
+        );
+
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let tmp_path = self.path.with_extension("tmp");
+        fs::write(&tmp_path, encrypted)?;
+        fs::rename(&tmp_path, &self.path)?;
+        Ok(())
+    }
+
+    fn cipher(&self) -> ChaCha20Poly1305 {
+        ChaCha20Poly1305::new(Key::from_slice(&self.key))
+    }
+}
+
+fn validate_name(name: &str) -> Result<(), SecretStoreError> {
+    if name.is_empty() || name.chars().any(char::is_whitespace) {
+        Err(SecretStoreError::InvalidName(name.to_owned()))
+    } else {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+#[cfg(not(tarpaulin_include))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_set_list_and_remove_secrets() {
+        let tmp_dir = tempfile::tempdir().unwrap().into_path();
+        let secret_store = SecretStore::new(tmp_dir.join("secrets"), b"key_material");
+
+        secret_store
+            .set("com.myorg", "GIT_TOKEN", Secret::new("s3cr3t"))
+            .unwrap();
+        assert_eq!(
+            secret_store.list().unwrap(),
+            vec![SecretDescriptor {
+                namespace: "com.myorg".to_owned(),
+                name: "GIT_TOKEN".to_owned(),
+            }]
+        );
+
+        assert!(secret_store.remove("com.myorg", "GIT_TOKEN").unwrap());
+        assert!(!secret_store.remove("com.myorg", "GIT_TOKEN").unwrap());
+        assert!(secret_store.list().unwrap().is_empty());
+
+        std::fs::remove_dir_all(&tmp_dir).unwrap();
+    }
+
+    #[test]
+    fn test_secrets_are_encrypted_at_rest() {
+        let tmp_dir = tempfile::tempdir().unwrap().into_path();
+        let secrets_path = tmp_dir.join("secrets");
+        let secret_store = SecretStore::new(&secrets_path, b"key_material");
+
+        secret_store
+            .set("com.myorg", "GIT_TOKEN", Secret::new("s3cr3t"))
+            .unwrap();
+
+        let stored = fs::read(&secrets_path).unwrap();
+        assert!(!String::from_utf8_lossy(&stored).contains("s3cr3t"));
+
+        let other_secret_store = SecretStore::new(&secrets_path, b"other_key_material");
+        assert!(other_secret_store.list().is_err());
+
+        std::fs::remove_dir_all(&tmp_dir).unwrap();
+    }
+
+    #[test]
+    fn test_secrets_for_package() {
+        let tmp_dir = tempfile::tempdir().unwrap().into_path();
+        let secret_store = SecretStore::new(tmp_dir.join("secrets"), b"key_material");
+
+        secret_store
+            .set("com.myorg", "GIT_TOKEN", Secret::new("org_token"))
+            .unwrap();
+        secret_store
+            .set(
+                "com.myorg:internal",
+                "GIT_TOKEN",
+                Secret::new("internal_token"),
+            )
+            .unwrap();
+        secret_store
+            .set("com.other", "NPM_TOKEN", Secret::new("other_token"))
+            .unwrap();
+
+        let secrets = secret_store.secrets_for("com.myorg:internal:1.0").unwrap();
+        assert_eq!(secrets.len(), 1);
+        assert_eq!(secrets["GIT_TOKEN"].expose(), "internal_token");
+
+        assert!(secret_store
+            .secrets_for("org.public:library:1.0")
+            .unwrap()
+            .is_empty());
+
+        std::fs::remove_dir_all(&tmp_dir).unwrap();
+    }
+
+    #[test]
+    fn test_secret_debug_output_is_redacted() {
+        assert_eq!(format!("{:?}", Secret::new("s3cr3t")), "Secret(***)");
+    }
+}
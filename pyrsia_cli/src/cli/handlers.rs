@@ -15,14 +15,39 @@
 */
 
 use crate::CONF_FILE_PATH_MSG_STARTER;
+use anyhow::{bail, Context};
+use indicatif::{ProgressBar, ProgressStyle};
+use pyrsia::artifact_service::model::{CheckOutcome, PackageType, SeedSyncProgress, SeedSyncState};
+use pyrsia::artifact_service::progress::{TransferDirection, TransferProgress};
+use pyrsia::blockchain_service::explorer::BlockListQuery;
+use pyrsia::build_service::history::{BuildHistoryQuery, BuildOutcome};
+use pyrsia::build_service::secrets::Secret;
+use pyrsia::cli_commands::artifact::{self, ArtifactCoordinates};
 use pyrsia::cli_commands::config;
+use pyrsia::cli_commands::error::{CliError, ErrorKind, ErrorReport};
+use pyrsia::cli_commands::format::{
+    format_bytes, format_duration, format_timestamp, parse_timestamp, TimeZone,
+};
+use pyrsia::cli_commands::import;
+use pyrsia::cli_commands::migrate::{MigrationReport, MigrationState, RemoteRepository};
 use pyrsia::cli_commands::model::BuildResultResponse;
 use pyrsia::cli_commands::node;
+use pyrsia::cli_commands::upgrade;
+use pyrsia::docker::tag_history::TagHistoryQuery;
+use pyrsia::network::peer_alias::{display_peer, PeerAliasSource};
 use pyrsia::node_api::model::request::*;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::io;
 use std::io::BufRead;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
 
+const TRANSFERS_WATCH_INTERVAL: Duration = Duration::from_secs(1);
+const SEED_SYNC_POLL_INTERVAL: Duration = Duration::from_secs(1);
+const SEED_SYNC_PROGRESS_TEMPLATE: &str = "{wide_bar} {pos}/{len} artifacts ({msg})";
+const DOWNLOAD_PROGRESS_TEMPLATE: &str =
+    "{wide_bar} {bytes}/{total_bytes} ({bytes_per_sec}, {eta})";
 const CONF_REMINDER_MESSAGE: &str = "Please make sure the pyrsia CLI config is up to date and matches the node configuration. For more information, run 'pyrsia config --show'";
 
 pub fn config_add() -> anyhow::Result<()> {
@@ -91,162 +116,1065 @@ pub fn config_show() {
     };
 }
 
-pub async fn authorize(peer_id: &str) {
-    match node::add_authorized_node(RequestAddAuthorizedNode {
-        peer_id: peer_id.to_owned(),
-    })
-    .await
-    {
-        Ok(()) => println!("Authorize request successfully handled."),
-        Err(error) => println!("Authorize request failed with error: {}", error),
-    };
+pub async fn artifact_get(coordinates: &str, output: Option<PathBuf>) -> anyhow::Result<()> {
+    let coordinates = ArtifactCoordinates::parse(coordinates)
+        .map_err(|error| CliError::new(ErrorKind::UsageError, error.to_string()))?;
+    let output = output.unwrap_or_else(|| PathBuf::from(coordinates.file_name()));
+    let expected_hash = coordinates
+        .expected_hash()
+        .await
+        .context("Looking up the hash of the artifact failed")?;
+
+    let progress_bar = ProgressBar::new(0);
+    if let Ok(style) = ProgressStyle::with_template(DOWNLOAD_PROGRESS_TEMPLATE) {
+        progress_bar.set_style(style);
+    }
+    let result = artifact::download_artifact(
+        &format!("http://{}{}", node::get_url(), coordinates.path()),
+        &expected_hash,
+        &output,
+        |downloaded, total| {
+            if let Some(total) = total {
+                progress_bar.set_length(total);
+            }
+            progress_bar.set_position(downloaded);
+        },
+    )
+    .await;
+    progress_bar.finish_and_clear();
+
+    match result {
+        Ok(size) => {
+            println!(
+                "Downloaded {} to {} and verified sha256 {}",
+                format_bytes(size),
+                output.display(),
+                expected_hash
+            );
+            Ok(())
+        }
+        Err(error) if artifact::partial_download_path(&output).exists() => {
+            Err(error.context("Download failed, run the command again to resume the download"))
+        }
+        Err(error) => Err(error.context("Download failed")),
+    }
+}
+
+pub async fn authorize(peer_id: &str, remove: bool) -> anyhow::Result<()> {
+    let proposal = if remove {
+        node::remove_authorized_node(RequestRemoveAuthorizedNode {
+            peer_id: peer_id.to_owned(),
+        })
+        .await
+    } else {
+        node::add_authorized_node(RequestAddAuthorizedNode {
+            peer_id: peer_id.to_owned(),
+        })
+        .await
+    }
+    .context("Authorize request failed")?;
+
+    match proposal {
+        None => println!("Authorize request successfully handled."),
+        Some(proposal) => println!(
+            "Approved {} of node {}, it has {}/{} approvals of authorized nodes so far.",
+            proposal.operation,
+            proposal.node_id,
+            proposal.approvals.len(),
+            proposal.quorum
+        ),
+    }
+    Ok(())
+}
+
+pub async fn authorize_proposals() -> anyhow::Result<()> {
+    let proposals = node::node_change_proposals()
+        .await
+        .context("Listing the proposals failed")?;
+    if proposals.is_empty() {
+        println!("No changes to the authorized nodes are waiting for approval.");
+    }
+    for proposal in proposals {
+        println!(
+            "{}\t{}\t{}/{} approvals",
+            proposal.operation,
+            proposal.node_id,
+            proposal.approvals.len(),
+            proposal.quorum
+        );
+    }
+    Ok(())
+}
+
+pub async fn block_list(limit: Option<usize>, time_zone: TimeZone) -> anyhow::Result<()> {
+    let blocks = node::list_blocks(&BlockListQuery { limit })
+        .await
+        .context("Listing the blocks failed")?;
+    if blocks.is_empty() {
+        println!("The local blockchain has no blocks.");
+    }
+    for block in blocks {
+        println!(
+            "#{}\t{}\t{}\t{}\t{} transparency logs",
+            block.ordinal,
+            format_timestamp(block.timestamp, time_zone),
+            block.hash,
+            block.committer.as_deref().unwrap_or("-"),
+            block.transparency_logs.len(),
+        );
+    }
+    Ok(())
 }
 
-pub async fn request_docker_build(image: &str) {
+pub async fn block_show(id: &str, time_zone: TimeZone) -> anyhow::Result<()> {
+    let block = node::get_block(id)
+        .await
+        .with_context(|| format!("Block '{}' was not found", id))?;
+
+    println!("Ordinal:     {}", block.ordinal);
+    println!("Hash:        {}", block.hash);
+    println!("Parent hash: {}", block.parent_hash);
+    println!("Committer:   {}", block.committer.as_deref().unwrap_or("-"));
+    println!(
+        "Created:     {}",
+        format_timestamp(block.timestamp, time_zone)
+    );
+    println!(
+        "Signature:   {}",
+        if block.verified { "valid" } else { "INVALID" }
+    );
+    println!("Transparency logs:");
+    for transparency_log in &block.transparency_logs {
+        println!(
+            "  {}\t{}\t{}\t{}",
+            transparency_log.id,
+            transparency_log.operation,
+            transparency_log
+                .package_type
+                .map_or_else(|| "-".to_owned(), |package_type| package_type.to_string()),
+            if transparency_log.package_specific_artifact_id.is_empty() {
+                &transparency_log.node_id
+            } else {
+                &transparency_log.package_specific_artifact_id
+            },
+        );
+    }
+    for payload in &block.undecoded_payloads {
+        println!("  undecoded payload: {}", payload);
+    }
+    Ok(())
+}
+
+pub async fn block_export(path: &Path) -> anyhow::Result<()> {
+    let export = node::export_blocks()
+        .await
+        .context("Exporting the blocks failed")?;
+    std::fs::write(path, &export)
+        .with_context(|| format!("Writing the export to {} failed", path.display()))?;
+    println!(
+        "Exported the blockchain ({} bytes) to {}",
+        export.len(),
+        path.display()
+    );
+    Ok(())
+}
+
+pub async fn block_audit() -> anyhow::Result<()> {
+    let audit = node::audit_blocks()
+        .await
+        .context("Auditing the blocks failed")?;
+    match (audit.first_ordinal, audit.last_ordinal) {
+        (Some(first_ordinal), Some(last_ordinal)) => {
+            println!("Audited blocks {} to {}", first_ordinal, last_ordinal)
+        }
+        _ => println!("The local blockchain has no blocks."),
+    }
+    match audit.first_invalid_block {
+        Some(invalid_block) => bail!(
+            "Block #{} ({}) is invalid: {}. The {} blocks before it are valid.",
+            invalid_block.ordinal,
+            invalid_block.hash,
+            invalid_block.problem,
+            audit.valid_blocks
+        ),
+        None => {
+            println!("All {} blocks are valid.", audit.valid_blocks);
+            Ok(())
+        }
+    }
+}
+
+pub async fn request_docker_build(image: &str) -> anyhow::Result<()> {
     let build_result = node::request_docker_build(RequestDockerBuild {
         image: image.to_owned(),
     })
     .await;
-    handle_request_build_result(build_result);
+    handle_request_build_result(build_result)
 }
 
-pub async fn request_maven_build(gav: &str) {
+pub async fn request_maven_build(gav: &str) -> anyhow::Result<()> {
     let build_result = node::request_maven_build(RequestMavenBuild {
         gav: gav.to_owned(),
     })
     .await;
-    handle_request_build_result(build_result);
+    handle_request_build_result(build_result)
 }
 
-pub async fn request_build_status(build_id: &str) {
-    let result = node::request_build_status(RequestBuildStatus {
+pub async fn request_build_status(build_id: &str) -> anyhow::Result<()> {
+    let build_status = node::request_build_status(RequestBuildStatus {
         build_id: String::from(build_id),
     })
-    .await;
+    .await
+    .with_context(|| {
+        format!(
+            "Build status for '{}' was not found. Additional info related to the build might be available via 'pyrsia inspect-log' command",
+            build_id
+        )
+    })?;
 
-    match result {
-        Ok(build_status) => println!("Build status for '{}' is '{}'", build_id, build_status),
-        Err(e) => {
+    println!("Build status for '{}' is '{}'", build_id, build_status);
+    if build_status.starts_with("FAILED") || build_status.starts_with("PARTIAL SUCCESS") {
+        bail!(CliError::new(
+            ErrorKind::BuildFailed,
+            format!("Build '{}' failed", build_id)
+        ));
+    }
+    Ok(())
+}
+
+pub async fn request_build_history(
+    package: Option<String>,
+    status: Option<String>,
+    since: Option<u64>,
+    time_zone: TimeZone,
+) -> anyhow::Result<()> {
+    let status = status
+        .map(|status| BuildOutcome::from_str(&status))
+        .transpose()
+        .map_err(|error| {
+            CliError::new(
+                ErrorKind::UsageError,
+                format!("Invalid build status: {}", error),
+            )
+        })?;
+    let build_records = node::get_build_history(&BuildHistoryQuery {
+        package,
+        status,
+        since,
+    })
+    .await
+    .context("Fetching the build history failed")?;
+
+    if build_records.is_empty() {
+        println!("No builds found.");
+    }
+    for build_record in build_records {
+        println!(
+            "{}\t{}\t{}\t{}\t{}\t{}\t{}",
+            build_record.build_id,
+            build_record.package_type,
+            build_record.package_specific_id,
+            build_record.outcome,
+            format_timestamp(build_record.started_at, time_zone),
+            build_record
+                .duration()
+                .map_or_else(|| "-".to_owned(), format_duration),
+            build_record.requester.as_deref().unwrap_or("-"),
+        );
+        if let Some(previous_attempt) = build_record.previous_attempt {
             println!(
-                "Build status for '{}' was not found: {}
-Additional info related to the build might be available via 'pyrsia inspect-log' command",
-                build_id, e
+                "  attempt {}, rerun of {}",
+                build_record.attempt, previous_attempt
             );
         }
+        if let Some(failure) = build_record.failure {
+            println!("  {}", failure);
+        }
     }
+    Ok(())
 }
 
-fn handle_request_build_result(result: Result<BuildResultResponse, anyhow::Error>) {
-    match result {
-        Ok(build_result_response) => {
-            if let Some(build_id) = build_result_response.build_id {
+pub async fn tag_history(
+    image: &str,
+    at: Option<String>,
+    time_zone: TimeZone,
+) -> anyhow::Result<()> {
+    let Some((name, tag)) = image
+        .rsplit_once(':')
+        .filter(|(name, _)| !name.contains('@'))
+    else {
+        bail!(CliError::new(
+            ErrorKind::UsageError,
+            format!(
+                "'{}' is not a docker image with a tag (e.g. alpine:latest)",
+                image
+            ),
+        ));
+    };
+    let name = name.strip_prefix("library/").unwrap_or(name);
+    if name.contains('/') {
+        bail!(CliError::new(
+            ErrorKind::UsageError,
+            "Only images of the official docker library are supported",
+        ));
+    }
+    let at = at
+        .map(|at| {
+            parse_timestamp(&at).ok_or_else(|| {
+                CliError::new(
+                    ErrorKind::UsageError,
+                    format!(
+                        "Invalid time '{}', expected RFC3339 or seconds since the unix epoch",
+                        at
+                    ),
+                )
+            })
+        })
+        .transpose()?;
+
+    let history = node::get_tag_history(name, tag, &TagHistoryQuery { at })
+        .await
+        .with_context(|| format!("Fetching the history of '{}' failed", image))?;
+
+    if history.is_empty() {
+        println!("No history found.");
+    }
+    for entry in history {
+        println!(
+            "{}\t{}\t{}\t{}\t{}",
+            format_timestamp(entry.since, time_zone),
+            entry.until.map_or_else(
+                || "-".to_owned(),
+                |until| format_timestamp(until, time_zone)
+            ),
+            entry.operation,
+            entry.digest,
+            entry.node_id,
+        );
+    }
+    Ok(())
+}
+
+pub async fn request_build_reproduce(pinning_snapshot: &str) -> anyhow::Result<()> {
+    let build_id = node::request_build_reproduce(RequestBuildReproduce {
+        pinning_snapshot: pinning_snapshot.to_owned(),
+    })
+    .await
+    .with_context(|| {
+        format!(
+            "Reproducing the build of pinning snapshot '{}' failed",
+            pinning_snapshot
+        )
+    })?;
+
+    println!(
+        "Build with ID '{}' has been started to reproduce pinning snapshot '{}'.",
+        build_id, pinning_snapshot
+    );
+    println!(
+        "Run 'pyrsia build status --id {}' to see whether the artifacts match.",
+        build_id
+    );
+    Ok(())
+}
+
+pub async fn request_build_rerun(build_id: &str) -> anyhow::Result<()> {
+    let new_build_id = node::request_build_rerun(RequestBuildRerun {
+        build_id: build_id.to_owned(),
+    })
+    .await
+    .with_context(|| format!("Rerunning build '{}' failed", build_id))?;
+
+    println!(
+        "Build '{}' has been rerun. Build with ID '{}' has been started.",
+        build_id, new_build_id
+    );
+    Ok(())
+}
+
+pub async fn request_replay_failed_builds() -> anyhow::Result<()> {
+    let build_ids = node::request_replay_failed_builds()
+        .await
+        .context("Replaying failed builds failed")?;
+
+    if build_ids.is_empty() {
+        println!("No failed builds were replayed.");
+    } else {
+        println!("Successfully replayed the following failed builds:");
+        build_ids
+            .iter()
+            .for_each(|build_id| println!("{}", build_id));
+    }
+    Ok(())
+}
+
+fn handle_request_build_result(result: anyhow::Result<BuildResultResponse>) -> anyhow::Result<()> {
+    let build_result_response = result.context("Build request failed")?;
+    if let Some(build_id) = build_result_response.build_id {
+        println!(
+            "Build request successfully handled. Build with ID '{}' has been started.",
+            build_id
+        );
+    }
+    if let Some(message) = build_result_response.message {
+        println!("{}", message);
+    }
+    Ok(())
+}
+
+pub async fn check_package(
+    package_type: PackageType,
+    package_specific_id: &str,
+    retrieve: bool,
+) -> anyhow::Result<()> {
+    let artifact_checks = node::check_package(RequestCheckPackage {
+        package_type,
+        package_specific_id: package_specific_id.to_owned(),
+        retrieve,
+    })
+    .await
+    .context("Checking package failed")?;
+
+    for artifact_check in &artifact_checks {
+        println!("{}", artifact_check.package_specific_artifact_id);
+        println!("  artifact id:      {}", artifact_check.artifact_id);
+        for (name, outcome) in [
+            ("local hash", &artifact_check.local_hash),
+            ("dht availability", &artifact_check.dht_availability),
+            ("peer retrieval", &artifact_check.peer_retrieval),
+            ("provenance", &artifact_check.provenance),
+        ] {
+            let (status, detail) = match outcome {
+                CheckOutcome::Passed(detail) => ("PASSED", detail),
+                CheckOutcome::Failed(detail) => ("FAILED", detail),
+                CheckOutcome::Skipped(detail) => ("SKIPPED", detail),
+            };
+            println!("  {:<17} {:<8} {}", format!("{}:", name), status, detail);
+        }
+    }
+    let passed = artifact_checks
+        .iter()
+        .filter(|artifact_check| artifact_check.is_passed())
+        .count();
+    println!(
+        "{} of {} artifacts of {} passed all checks",
+        passed,
+        artifact_checks.len(),
+        package_specific_id
+    );
+    if passed < artifact_checks.len() {
+        bail!(CliError::new(
+            ErrorKind::VerificationFailed,
+            format!(
+                "{} of {} artifacts of {} failed a check",
+                artifact_checks.len() - passed,
+                artifact_checks.len(),
+                package_specific_id
+            )
+        ));
+    }
+    Ok(())
+}
+
+pub async fn deprecate_package(
+    package_type: PackageType,
+    package_specific_id: &str,
+    successor: Option<String>,
+) -> anyhow::Result<()> {
+    let deprecation = node::deprecate_package(RequestDeprecatePackage {
+        package_type,
+        package_specific_id: package_specific_id.to_owned(),
+        successor,
+    })
+    .await
+    .context("Deprecating package failed")?;
+
+    println!(
+        "{}",
+        deprecation
+            .deprecation_warning()
+            .unwrap_or_else(|| format!("{} is deprecated", package_specific_id))
+    );
+    Ok(())
+}
+
+pub async fn import_docker_daemon_image(image: &str) -> anyhow::Result<()> {
+    let request = import::collect_docker_daemon_image(image)
+        .with_context(|| format!("Reading image {} failed", image))?;
+
+    let package_specific_id = request.package_specific_id.clone();
+    node::import_artifacts(request)
+        .await
+        .context("Import failed")?;
+    println!(
+        "Imported {} from the local Docker daemon",
+        package_specific_id
+    );
+    Ok(())
+}
+
+pub async fn import_maven_repository(repository_path: &str) -> anyhow::Result<()> {
+    let packages = import::find_maven_packages(Path::new(repository_path))
+        .with_context(|| format!("Reading maven repository {} failed", repository_path))?;
+
+    let total = packages.len();
+    let mut imported = 0;
+    for package in packages {
+        let package_specific_id = package.package_specific_id.clone();
+        let result = match package.into_request() {
+            Ok(request) => node::import_artifacts(request).await,
+            Err(error) => Err(error),
+        };
+
+        match result {
+            Ok(()) => {
+                imported += 1;
+                println!("Imported {}", package_specific_id);
+            }
+            Err(error) => println!(
+                "Import of {} failed with error: {}",
+                package_specific_id, error
+            ),
+        }
+    }
+
+    println!(
+        "Imported {} of {} packages from {}",
+        imported, total, repository_path
+    );
+    if imported < total {
+        bail!(
+            "{} of {} packages from {} failed to import",
+            total - imported,
+            total,
+            repository_path
+        );
+    }
+    Ok(())
+}
+
+pub async fn migrate_repository(
+    repository: RemoteRepository,
+    state_path: &Path,
+    report_path: Option<&Path>,
+) -> anyhow::Result<()> {
+    let mut state = MigrationState::load(state_path)
+        .with_context(|| format!("Reading migration state {:?} failed", state_path))?;
+
+    let packages = repository
+        .list_packages()
+        .await
+        .context("Listing repository failed")?;
+
+    let mut report = MigrationReport {
+        source_packages: packages.len(),
+        ..Default::default()
+    };
+    for (index, package) in packages.iter().enumerate() {
+        let package_specific_id = package.package_specific_id.clone();
+        let progress = format!("[{}/{}]", index + 1, packages.len());
+        if state.completed.contains(&package_specific_id) {
+            println!("{} {} was already migrated", progress, package_specific_id);
+            report.already_migrated.push(package_specific_id);
+            continue;
+        }
+
+        let result = match repository.download_package(package).await {
+            Ok(request) => node::import_artifacts(request).await,
+            Err(error) => Err(error),
+        };
+
+        match result {
+            Ok(()) => {
+                println!("{} Migrated {}", progress, package_specific_id);
+                report.imported.push(package_specific_id.clone());
+            }
+            Err(error) if error.to_string().contains("already exists") => {
+                println!("{} {} is already in Pyrsia", progress, package_specific_id);
+                report.already_present.push(package_specific_id.clone());
+            }
+            Err(error) => {
                 println!(
-                    "Build request successfully handled. Build with ID '{}' has been started.",
-                    build_id
+                    "{} Migrating {} failed with error: {}",
+                    progress, package_specific_id, error
                 );
-            }
-            if let Some(message) = build_result_response.message {
-                println!("{}", message);
+                report.failed.insert(package_specific_id, error.to_string());
+                continue;
             }
         }
-        Err(error_message) => {
-            println!("Build request failed with error: {}", error_message);
+
+        state.completed.insert(package_specific_id);
+        if let Err(error) = state.save(state_path) {
+            println!(
+                "Saving migration state {:?} failed with error: {}",
+                state_path, error
+            );
         }
     }
+
+    println!(
+        "Migration finished: {} of {} packages are available in Pyrsia ({} migrated, {} already migrated, {} already present), {} missing",
+        report.available(),
+        report.source_packages,
+        report.imported.len(),
+        report.already_migrated.len(),
+        report.already_present.len(),
+        report.missing()
+    );
+    for (package_specific_id, error) in report.failed.iter() {
+        println!("  missing {}: {}", package_specific_id, error);
+    }
+
+    if let Some(report_path) = report_path {
+        serde_json::to_vec_pretty(&report)
+            .map_err(anyhow::Error::from)
+            .and_then(|json| std::fs::write(report_path, json).map_err(anyhow::Error::from))
+            .with_context(|| format!("Writing migration report {:?} failed", report_path))?;
+    }
+    if report.missing() > 0 {
+        bail!(
+            "{} of {} packages are missing in Pyrsia",
+            report.missing(),
+            report.source_packages
+        );
+    }
+    Ok(())
 }
 
-pub async fn node_ping() {
-    let result = node::ping().await;
-    match result {
-        Ok(_resp) => {
-            println!("Connection Successful !!")
+pub async fn set_secret(namespace: &str, name: &str, value: &str) -> anyhow::Result<()> {
+    node::set_secret(RequestSetSecret {
+        namespace: namespace.to_owned(),
+        name: name.to_owned(),
+        value: Secret::new(value),
+    })
+    .await
+    .context("Setting secret failed")?;
+    println!("Secret '{}' set for namespace '{}'", name, namespace);
+    Ok(())
+}
+
+pub async fn remove_secret(namespace: &str, name: &str) -> anyhow::Result<()> {
+    node::remove_secret(RequestRemoveSecret {
+        namespace: namespace.to_owned(),
+        name: name.to_owned(),
+    })
+    .await
+    .context("Removing secret failed")?;
+    println!("Secret '{}' removed from namespace '{}'", name, namespace);
+    Ok(())
+}
+
+pub async fn list_secrets() -> anyhow::Result<()> {
+    let secrets = node::list_secrets()
+        .await
+        .context("Listing secrets failed")?;
+    if secrets.is_empty() {
+        println!("No secrets found.");
+    } else {
+        println!("Secrets:");
+        secrets
+            .iter()
+            .for_each(|secret| println!("{}\t{}", secret.namespace, secret.name));
+    }
+    Ok(())
+}
+
+pub async fn node_ping() -> anyhow::Result<()> {
+    node::ping().await?;
+    println!("Connection Successful !!");
+    Ok(())
+}
+
+pub async fn node_status() -> anyhow::Result<()> {
+    let resp = node::status().await?;
+    println!("Connected Peers Count:       {}", resp.peers_count);
+    if let Some(alias) = resp.alias {
+        println!("Alias:                       {}", alias);
+    }
+    if !resp.version.is_empty() {
+        println!("Version:                     {}", resp.version);
+    }
+    println!(
+        "Package Types:               {}",
+        resp.capabilities
+            .package_types
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+    println!(
+        "Roles:                       {}",
+        resp.capabilities
+            .roles
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+    if !resp.peer_versions.is_empty() {
+        println!("Connected Peers per Version:");
+        for (version, count) in resp.peer_versions {
+            println!("  {:<26} {}", version, count);
         }
-        Err(error) => {
-            println!("Error: {}. {}", error, CONF_REMINDER_MESSAGE);
+    }
+    Ok(())
+}
+
+pub async fn node_transfers(watch: bool) -> anyhow::Result<()> {
+    loop {
+        let transfers = node::transfers().await?;
+        if transfers.is_empty() {
+            println!("No artifacts are being transferred");
+            return Ok(());
         }
+        transfers
+            .iter()
+            .for_each(|transfer| println!("{}", describe_transfer(transfer)));
+        if !watch {
+            return Ok(());
+        }
+        tokio::time::sleep(TRANSFERS_WATCH_INTERVAL).await;
+        println!();
+    }
+}
+
+fn describe_transfer(transfer: &TransferProgress) -> String {
+    let action = match (transfer.direction, &transfer.peer) {
+        (TransferDirection::Download, Some(peer)) => {
+            format!("Downloading {} from {}", transfer.artifact_id, peer)
+        }
+        (TransferDirection::Download, None) => format!("Downloading {}", transfer.artifact_id),
+        (TransferDirection::Store, _) => format!("Storing {}", transfer.artifact_id),
     };
+    match transfer.total_bytes {
+        Some(total_bytes) if total_bytes > 0 => format!(
+            "{}: {} of {} ({}%)",
+            action,
+            format_bytes(transfer.bytes_transferred),
+            format_bytes(total_bytes),
+            transfer.bytes_transferred * 100 / total_bytes
+        ),
+        _ => format!("{}: {}", action, format_bytes(transfer.bytes_transferred)),
+    }
 }
 
-pub async fn node_status() {
-    let result = node::status().await;
-    match result {
-        Ok(resp) => {
-            println!("Connected Peers Count:       {}", resp.peers_count);
+pub async fn node_init(seed: &str) -> anyhow::Result<()> {
+    node::start_seed_sync(RequestSeedSync {
+        seed: seed.to_owned(),
+    })
+    .await
+    .context("Starting the sync from the seed node failed")?;
+    println!("Syncing from seed node {}", seed);
+
+    let progress_bar = ProgressBar::new(0);
+    if let Ok(style) = ProgressStyle::with_template(SEED_SYNC_PROGRESS_TEMPLATE) {
+        progress_bar.set_style(style);
+    }
+    let progress = loop {
+        let progress = node::seed_sync_status().await?;
+        progress_bar.set_length(progress.artifacts_total as u64);
+        progress_bar.set_position(progress.artifacts_done() as u64);
+        progress_bar.set_message(describe_seed_sync(&progress));
+        if progress.state != SeedSyncState::Running {
+            break progress;
         }
-        Err(error) => {
-            println!("Error: {}. {}", error, CONF_REMINDER_MESSAGE);
+        tokio::time::sleep(SEED_SYNC_POLL_INTERVAL).await;
+    };
+    progress_bar.finish();
+
+    match progress.state {
+        SeedSyncState::Failed => bail!(
+            "Syncing from seed node {} failed: {}",
+            seed,
+            progress.error.unwrap_or_default()
+        ),
+        _ => {
+            println!(
+                "Synced from seed node {}: {} transparency logs imported, {} artifacts copied, {} already present, {} failed",
+                seed,
+                progress.transparency_logs_imported,
+                progress.artifacts_synced,
+                progress.artifacts_skipped,
+                progress.artifacts_failed
+            );
+            Ok(())
         }
     }
 }
 
-pub async fn node_list() {
-    let result = node::peers_connected().await;
-    match result {
-        Ok(resp) => {
-            println!("Connected Peers:");
-            let peers_split = resp.split(',');
-            let mut unique_peers = HashSet::new();
-            for peer in peers_split {
-                unique_peers.insert(peer);
-            }
-            unique_peers.iter().for_each(|p| println!("{}", p));
+fn describe_seed_sync(progress: &SeedSyncProgress) -> String {
+    format!(
+        "{} transparency logs, {} copied, {} failed",
+        progress.transparency_logs_imported,
+        format_bytes(progress.bytes_synced),
+        progress.artifacts_failed
+    )
+}
+
+pub async fn node_upgrade(
+    manifest_url: &str,
+    release_key: Option<String>,
+    binary: Option<PathBuf>,
+    restart_command: Option<String>,
+    force: bool,
+) -> anyhow::Result<()> {
+    let release_key = release_key
+        .or_else(|| upgrade::pinned_release_key().map(str::to_owned))
+        .ok_or_else(|| {
+            CliError::new(
+                ErrorKind::UsageError,
+                "This build of the CLI has no pinned release key, please provide one with --release-key",
+            )
+        })?;
+    let binary_path = binary
+        .map(Ok)
+        .unwrap_or_else(upgrade::default_node_binary)?;
+
+    let manifest = upgrade::fetch_release_manifest(manifest_url, &release_key)
+        .await
+        .context("Fetching the latest release failed")?;
+    if !manifest.is_newer() && !force {
+        println!("The node is up to date with release {}", manifest.version);
+        return Ok(());
+    }
+
+    let platform = upgrade::current_platform();
+    match manifest.binary(&platform) {
+        Ok(release_binary) => upgrade::download_release_binary(release_binary).await,
+        Err(error) => Err(error),
+    }
+    .and_then(|content| upgrade::install_binary(&content, &binary_path))
+    .context("Upgrade failed")?;
+    println!(
+        "Upgraded {} to release {}",
+        binary_path.display(),
+        manifest.version
+    );
+
+    match restart_command {
+        Some(restart_command) => {
+            upgrade::restart_node(&restart_command)
+                .context("Restarting the node failed, please restart it manually")?;
+            println!("The node was restarted");
         }
-        Err(error) => {
-            println!("Error: {}. {}", error, CONF_REMINDER_MESSAGE);
+        None => println!("Please restart the node to run the new release"),
+    }
+    Ok(())
+}
+
+pub async fn node_list() -> anyhow::Result<()> {
+    let resp = node::peers_connected().await?;
+    // aliases are shown when the node knows them
+    let aliases: HashMap<String, String> = node::peer_aliases()
+        .await
+        .unwrap_or_default()
+        .into_iter()
+        .map(|peer_alias| (peer_alias.peer_id, peer_alias.alias))
+        .collect();
+    println!("Connected Peers:");
+    let peers: Vec<String> = serde_json::from_str(&resp)
+        .unwrap_or_else(|_| resp.split(',').map(str::to_owned).collect());
+    let unique_peers: HashSet<String> = peers.into_iter().collect();
+    unique_peers
+        .iter()
+        .for_each(|p| println!("{}", display_peer(p, aliases.get(p).map(String::as_str))));
+    Ok(())
+}
+
+pub async fn network_debug_lookup(artifact_id: &str) -> anyhow::Result<()> {
+    let trace = node::debug_lookup(artifact_id)
+        .await
+        .context("Looking up the providers failed")?;
+    for step in &trace.steps {
+        println!(
+            "Step {:<3} {:>7}ms  {} requests, {} answered, {} failed",
+            step.step, step.elapsed_ms, step.requests, step.successes, step.failures
+        );
+        for provider in &step.providers {
+            println!("           found provider {}", provider);
         }
     }
+    println!(
+        "Found {} providers of {} in {}",
+        trace.providers.len(),
+        trace.artifact_id,
+        format_duration_ms(trace.duration_ms)
+    );
+    if let Some(error) = trace.error {
+        println!("The lookup ended early: {}", error);
+    }
+    Ok(())
+}
+
+pub async fn network_query_stats() -> anyhow::Result<()> {
+    let query_stats = node::query_stats()
+        .await
+        .context("Getting the query statistics failed")?;
+    if query_stats.is_empty() {
+        println!("No Kademlia queries have finished yet");
+        return Ok(());
+    }
+    println!(
+        "{:<16} {:>8} {:>8} {:>10} {:>10} {:>13} {:>13}",
+        "QUERY", "COUNT", "FAILED", "AVG TIME", "MAX TIME", "AVG REQUESTS", "MAX REQUESTS"
+    );
+    for (kind, stats) in query_stats {
+        println!(
+            "{:<16} {:>8} {:>8} {:>10} {:>10} {:>13.1} {:>13}",
+            kind.to_string(),
+            stats.queries,
+            stats.failures,
+            format_duration_ms(stats.average_duration_ms()),
+            format_duration_ms(stats.max_duration_ms),
+            stats.average_requests(),
+            stats.max_requests
+        );
+    }
+    Ok(())
+}
+
+fn format_duration_ms(duration_ms: u64) -> String {
+    if duration_ms < 1000 {
+        format!("{}ms", duration_ms)
+    } else {
+        format_duration(duration_ms / 1000)
+    }
+}
+
+pub async fn list_peer_aliases() -> anyhow::Result<()> {
+    let peer_aliases = node::peer_aliases()
+        .await
+        .context("Listing peer aliases failed")?;
+    if peer_aliases.is_empty() {
+        println!("No peer aliases are known");
+    }
+    for peer_alias in peer_aliases {
+        let source = match peer_alias.source {
+            PeerAliasSource::Local => "local",
+            PeerAliasSource::Advertised => "advertised",
+        };
+        println!("{}\t{}\t{}", peer_alias.alias, peer_alias.peer_id, source);
+    }
+    Ok(())
+}
+
+pub async fn set_peer_alias(peer_id: &str, alias: Option<String>) -> anyhow::Result<()> {
+    node::set_peer_alias(RequestSetPeerAlias {
+        peer_id: peer_id.to_owned(),
+        alias: alias.clone(),
+    })
+    .await
+    .context("Setting the peer alias failed")?;
+
+    match alias {
+        Some(alias) => println!("Peer {} is now known as {}", peer_id, alias),
+        None => println!("Alias of peer {} removed", peer_id),
+    }
+    Ok(())
+}
+
+pub async fn list_draining_peers() -> anyhow::Result<()> {
+    let draining_peers = node::draining_peers()
+        .await
+        .context("Listing draining peers failed")?;
+    if draining_peers.is_empty() {
+        println!("No peers are draining for maintenance");
+    }
+    for draining_peer in draining_peers {
+        println!(
+            "{}\tuntil {}\t{}",
+            draining_peer.peer_id,
+            format_timestamp(draining_peer.announcement.until, TimeZone::Utc),
+            draining_peer.announcement.reason.unwrap_or_default()
+        );
+    }
+    Ok(())
+}
+
+pub async fn announce_maintenance(ttl_secs: u64, reason: Option<String>) -> anyhow::Result<()> {
+    node::announce_maintenance(RequestAnnounceMaintenance { ttl_secs, reason })
+        .await
+        .context("Announcing the maintenance failed")?;
+
+    if ttl_secs == 0 {
+        println!("Maintenance ended, peers route new builds to the node again");
+    } else {
+        println!(
+            "Node is draining for maintenance for {}, peers stop routing new builds to it",
+            format_duration(ttl_secs)
+        );
+    }
+    Ok(())
 }
 
 pub async fn inspect_docker_transparency_log(
     image: &str,
     arg_format: Option<String>,
     arg_fields: Option<String>,
-) {
+    time_zone: TimeZone,
+) -> anyhow::Result<()> {
     let content_type = ContentType::from(arg_format.as_ref()).unwrap();
     let fields = parse_arg_fields(arg_fields).unwrap();
 
-    let result = node::inspect_docker_transparency_log(RequestDockerLog {
+    let response = node::inspect_docker_transparency_log(RequestDockerLog {
         image: image.to_owned(),
         output_params: Some(TransparencyLogOutputParams {
             format: Some(content_type),
             content: fields,
         }),
     })
-    .await;
-    match result {
-        Ok(logs) => {
-            content_type.print_logs(logs);
-        }
-        Err(error) => {
-            println!("Inspect log request failed with error: {:?}", error);
-        }
-    };
+    .await
+    .context("Inspect log request failed")?;
+    content_type.print_logs(response.logs, time_zone);
+    if let Some(warning) = response.warning {
+        println!("Warning: {}", warning);
+    }
+    Ok(())
 }
 
 pub async fn inspect_maven_transparency_log(
     gav: &str,
     arg_format: Option<String>,
     arg_fields: Option<String>,
-) {
+    time_zone: TimeZone,
+) -> anyhow::Result<()> {
     let content_type = ContentType::from(arg_format.as_ref()).unwrap();
     let content = parse_arg_fields(arg_fields).unwrap();
 
-    let result = node::inspect_maven_transparency_log(RequestMavenLog {
+    let response = node::inspect_maven_transparency_log(RequestMavenLog {
         gav: gav.to_owned(),
         output_params: Some(TransparencyLogOutputParams {
             format: Some(content_type),
             content,
         }),
     })
-    .await;
-    match result {
-        Ok(logs) => {
-            content_type.print_logs(logs);
-        }
-        Err(error) => {
-            println!("Inspect log request failed with error: {:?}", error);
+    .await
+    .context("Inspect log request failed")?;
+    content_type.print_logs(response.logs, time_zone);
+    if let Some(warning) = response.warning {
+        println!("Warning: {}", warning);
+    }
+    Ok(())
+}
+
+/// Print the error of a failed command in the requested format and return
+/// the exit code of its cause. Errors are printed to stderr, so the output
+/// of a command can still be parsed.
+pub fn report_error(error: &anyhow::Error, json: bool) -> i32 {
+    let report = ErrorReport::from(error);
+    if json {
+        eprintln!(
+            "{}",
+            serde_json::to_string(&report).unwrap_or_else(|_| report.message.clone())
+        );
+    } else {
+        eprintln!("Error: {}", report.message);
+        if report.error == ErrorKind::NodeUnreachable {
+            eprintln!("{}", CONF_REMINDER_MESSAGE);
         }
-    };
+    }
+    report.exit_code
 }
 
 fn parse_arg_fields(
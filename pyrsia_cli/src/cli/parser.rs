@@ -14,25 +14,80 @@
    limitations under the License.
 */
 
-use clap::{arg, command, crate_version, ArgGroup, ArgMatches, Command};
+use clap::error::ErrorKind;
+use clap::{arg, command, crate_version, Arg, ArgGroup, ArgMatches, Command};
 use const_format::formatcp;
-use pyrsia::node_api::model::request::Content;
+use pyrsia::cli_commands::error::{CliError, ErrorKind as CliErrorKind, ErrorReport};
+use pyrsia::cli_commands::upgrade;
+use pyrsia::node_api::model::request::{Content, TransparencyLogField};
 
 pub fn cli_parser() -> ArgMatches {
     let version_string: &str = formatcp!("{} ({})", crate_version!(), env!("VERGEN_GIT_SHA"));
     command!()
         .arg_required_else_help(true)
         .propagate_version(false)
+        .arg(
+            arg!(--"error-format" <FORMAT> "The format in which errors are printed to stderr")
+                .value_parser(["text", "json"])
+                .default_value("text"),
+        )
         // Config subcommand
         .subcommands(vec![
+            Command::new("artifact")
+                .about("Download artifacts via the node")
+                .subcommand_required(true)
+                .arg_required_else_help(true)
+                .subcommands(vec![
+                    Command::new("get")
+                        .about("Download an artifact and verify its hash, resuming an interrupted download")
+                        .arg_required_else_help(true)
+                        .args(&[
+                            arg!(<COORDINATES> "The docker blob (e.g. alpine@sha256:<digest>) or maven artifact (e.g. org.myorg:my-artifact:1.1.0[:classifier][@extension])"),
+                            arg!(-o --output <FILE> "The file to save the artifact to (defaults to the artifact file name)")
+                                .required(false),
+                        ]),
+                ]),
             Command::new("authorize")
-                .about("Add an authorized node")
+                .about("Approve adding or removing an authorized node, which takes effect once a quorum of the authorized nodes approved it")
                 .arg_required_else_help(true)
                 .args(&[
-                    arg!(-p --peer <PEER_ID>      "Peer ID of the node to authorize"),
+                    arg!(-p --peer <PEER_ID>      "Peer ID of the node to authorize")
+                        .required_unless_present("proposals"),
+                    arg!(--remove                 "Approve removing the node instead of adding it"),
+                    arg!(--proposals              "Show the changes to the authorized nodes that wait for approvals")
+                        .conflicts_with_all(["peer", "remove"]),
+                ]),
+            Command::new("block")
+                .visible_alias("blocks")
+                .about("Explore the blocks of the local blockchain")
+                .subcommand_required(true)
+                .arg_required_else_help(true)
+                .subcommands(vec![
+                    Command::new("list")
+                        .about("Show the most recent blocks, newest first")
+                        .args(&[
+                            arg!(-l --limit <LIMIT> "The maximum number of blocks to show (defaults to 20)")
+                                .required(false)
+                                .value_parser(clap::value_parser!(usize)),
+                            arg!(--"local-time" "Show times in the local time zone instead of UTC"),
+                        ]),
+                    Command::new("show")
+                        .about("Show a block and the transparency logs it published")
+                        .arg_required_else_help(true)
+                        .args(&[
+                            arg!(<BLOCK> "The ordinal or the hash of the block"),
+                            arg!(--"local-time" "Show times in the local time zone instead of UTC"),
+                        ]),
+                    Command::new("export")
+                        .about("Export all blocks to a file, to back up the blockchain or to seed a node with --import-chain")
+                        .arg_required_else_help(true)
+                        .arg(arg!(<FILE> "The file to export the blocks to")),
+                    Command::new("audit")
+                        .about("Verify the hash links, signatures and payloads of all blocks and report the first invalid block"),
                 ]),
             Command::new("build")
                 .short_flag('b')
+                .visible_alias("builds")
                 .about("Request a new build")
                 .subcommand_required(true)
                 .arg_required_else_help(true)
@@ -55,6 +110,53 @@ pub fn cli_parser() -> ArgMatches {
                         .args(&[
                             arg!(--id <ID> "The build ID"),
                         ]),
+                    Command::new("history")
+                        .about("Show the history of the builds started by the node")
+                        .args(&[
+                            arg!(--package <PACKAGE> "Only show builds of this package, with or without version (e.g. alpine or alpine:3.15.3)")
+                                .required(false),
+                            arg!(--status <STATUS> "Only show builds with this outcome")
+                                .required(false)
+                                .value_parser(["running", "success", "partial_success", "failure"]),
+                            arg!(--since <SECONDS> "Only show builds started since this time, in seconds since the unix epoch")
+                                .required(false)
+                                .value_parser(clap::value_parser!(u64)),
+                            arg!(--"local-time" "Show start times in the local time zone instead of UTC"),
+                        ]),
+                    Command::new("rerun")
+                        .about("Rerun a failed build with the same parameters")
+                        .arg_required_else_help(true)
+                        .args(&[
+                            arg!(<BUILD_ID> "The ID of the failed build"),
+                        ]),
+                    Command::new("reproduce")
+                        .about("Rebuild a package with the exact dependencies of a pinning snapshot and compare the artifacts with the published artifacts")
+                        .arg_required_else_help(true)
+                        .args(&[
+                            arg!(<PINNING_SNAPSHOT> "The ID of the pinning snapshot, as shown in the transparency log"),
+                        ]),
+                    Command::new("replay-failed")
+                        .about("Retry publishing the artifacts of builds that failed to be added to the transparency log"),
+                ]),
+            Command::new("check")
+                .about("Verify the integrity of a package end to end")
+                .subcommand_required(true)
+                .arg_required_else_help(true)
+                .subcommands(vec![
+                    Command::new("docker")
+                        .about("Verify the integrity of a Docker image")
+                        .arg_required_else_help(true)
+                        .args(&[
+                            arg!(--image <IMAGE> "The docker image to check (e.g. alpine:3.15.3)"),
+                            arg!(--retrieve "Also retrieve every artifact from a remote provider"),
+                        ]),
+                    Command::new("maven")
+                        .about("Verify the integrity of a maven artifact")
+                        .arg_required_else_help(true)
+                        .args(&[
+                            arg!(--gav <GAV> "The maven GAV to check (e.g. org.myorg:my-artifact:1.1.0)"),
+                            arg!(--retrieve "Also retrieve every artifact from a remote provider"),
+                        ]),
                 ]),
             Command::new("config")
                 .short_flag('c')
@@ -73,6 +175,46 @@ pub fn cli_parser() -> ArgMatches {
                     arg!(-r --remove   "Removes the stored node configuration").visible_alias("rm"),
                     arg!(-s --show     "Shows the stored node configuration"),
                 ]),
+            Command::new("deprecate")
+                .about("Mark a package as deprecated (requires PYRSIA_ADMIN_TOKEN)")
+                .subcommand_required(true)
+                .arg_required_else_help(true)
+                .subcommands(vec![
+                    Command::new("docker")
+                        .about("Deprecate a Docker image")
+                        .arg_required_else_help(true)
+                        .args(&[
+                            arg!(--image <IMAGE> "The docker image to deprecate (e.g. alpine:3.15.3)"),
+                            arg!(--successor <IMAGE> "The docker image that replaces it (e.g. alpine:3.17.1)")
+                                .required(false),
+                        ]),
+                    Command::new("maven")
+                        .about("Deprecate a maven artifact")
+                        .arg_required_else_help(true)
+                        .args(&[
+                            arg!(--gav <GAV> "The maven GAV to deprecate (e.g. org.myorg:my-artifact:1.1.0)"),
+                            arg!(--successor <GAV> "The maven GAV that replaces it (e.g. org.myorg:my-artifact:1.2.0)")
+                                .required(false),
+                        ]),
+                ]),
+            Command::new("import")
+                .about("Import existing artifacts into Pyrsia without building them (requires PYRSIA_ADMIN_TOKEN)")
+                .subcommand_required(true)
+                .arg_required_else_help(true)
+                .subcommands(vec![
+                    Command::new("docker-daemon")
+                        .about("Import an image from the local Docker daemon")
+                        .arg_required_else_help(true)
+                        .args(&[
+                            arg!(<IMAGE> "The docker image to import (e.g. alpine:3.15.3)"),
+                        ]),
+                    Command::new("maven-repo")
+                        .about("Import all artifacts from a local maven repository")
+                        .arg_required_else_help(true)
+                        .args(&[
+                            arg!(<PATH> "The path of the maven repository (e.g. ~/.m2/repository)"),
+                        ]),
+                ]),
             Command::new("inspect-log")
                 .about("Show transparency logs")
                 .subcommand_required(true)
@@ -89,6 +231,7 @@ pub fn cli_parser() -> ArgMatches {
                                 .default_value("json"),
                             arg!(--fields <FIELDS>)
                                 .help(inspect_log_fields_help_string()),
+                            arg!(--"local-time" "Show timestamps in the local time zone instead of UTC"),
                         ]),
                     Command::new("maven")
                         .about("Show transparency logs for a maven artifact")
@@ -101,26 +244,204 @@ pub fn cli_parser() -> ArgMatches {
                                 .default_value("json"),
                             arg!(--fields <FIELDS>)
                                 .help(inspect_log_fields_help_string()),
+                            arg!(--"local-time" "Show timestamps in the local time zone instead of UTC"),
                         ]),
                 ]),
             Command::new("list")
                 .short_flag('l')
                 .about("Show a list of connected peers"),
+            Command::new("migrate")
+                .about("Migrate the packages of a maven repository from a repository manager (requires PYRSIA_ADMIN_TOKEN)")
+                .subcommand_required(true)
+                .arg_required_else_help(true)
+                .subcommands(vec![
+                    Command::new("artifactory")
+                        .about("Migrate a repository from Artifactory")
+                        .arg_required_else_help(true)
+                        .args(migration_args("https://myorg.jfrog.io/artifactory")),
+                    Command::new("nexus")
+                        .about("Migrate a repository from Nexus")
+                        .arg_required_else_help(true)
+                        .args(migration_args("https://nexus.myorg.com")),
+                ]),
+            Command::new("node")
+                .about("Manage the Pyrsia node installed on this machine")
+                .subcommand_required(true)
+                .arg_required_else_help(true)
+                .subcommands(vec![
+                    Command::new("init")
+                        .about("Copy the transparency logs and the popular and pinned artifacts of a seed node to the node")
+                        .arg_required_else_help(true)
+                        .args(&[
+                            arg!(--seed <ADDR> "The multiaddr of the seed node, including its peer id (e.g. /ip4/1.2.3.4/tcp/44000/p2p/12D3KooW...)")
+                                .required(true),
+                        ]),
+                    Command::new("upgrade")
+                        .about("Upgrade the node to the latest signed release and restart it")
+                        .args(&[
+                            arg!(--"manifest-url" <URL> "The URL of the release manifest")
+                                .required(false)
+                                .default_value(upgrade::DEFAULT_RELEASE_MANIFEST_URL),
+                            arg!(--"release-key" <KEY> "The base64 encoded ed25519 public key that releases are signed with, instead of the pinned key")
+                                .required(false),
+                            arg!(--binary <PATH> "The node binary to replace (defaults to pyrsia_node next to this executable)")
+                                .required(false),
+                            arg!(--"restart-command" <COMMAND> "The command that restarts the node")
+                                .required(false)
+                                .default_value(upgrade::DEFAULT_RESTART_COMMAND),
+                            arg!(--"no-restart" "Do not restart the node after the upgrade"),
+                            arg!(--force "Install the latest release even if it is not newer than this version"),
+                        ]),
+                ]),
+            Command::new("network")
+                .about("Inspect how the node finds peers and artifacts on the p2p network")
+                .subcommand_required(true)
+                .arg_required_else_help(true)
+                .subcommands(vec![
+                    Command::new("debug-lookup")
+                        .about("Look up the providers of an artifact and show every step of the lookup")
+                        .arg_required_else_help(true)
+                        .args(&[arg!(<ARTIFACT_ID> "The id of the artifact")]),
+                    Command::new("query-stats")
+                        .about("Show the duration, requests and failures of the Kademlia queries of the node"),
+                ]),
+            Command::new("peers")
+                .about("Show peers and manage their aliases")
+                .subcommand_required(true)
+                .arg_required_else_help(true)
+                .subcommands(vec![
+                    Command::new("list").about("Show a list of connected peers with their aliases"),
+                    Command::new("aliases").about("Show all known peer aliases"),
+                    Command::new("maintenance").about("Show the peers that are draining for maintenance"),
+                    Command::new("alias")
+                        .about("Assign a local alias to a peer (requires PYRSIA_ADMIN_TOKEN)")
+                        .arg_required_else_help(true)
+                        .args(&[
+                            arg!(<PEER_ID> "The peer ID of the node"),
+                            arg!([ALIAS] "The alias of the node (e.g. build-node-1)"),
+                            arg!(--remove "Remove the local alias of the peer"),
+                        ])
+                        .group(ArgGroup::new("alias_or_remove").args(["ALIAS", "remove"]).required(true)),
+                ]),
+            Command::new("maintenance")
+                .about("Announce maintenance of the node to its peers (requires PYRSIA_ADMIN_TOKEN)")
+                .subcommand_required(true)
+                .arg_required_else_help(true)
+                .subcommands(vec![
+                    Command::new("start")
+                        .about("Stop peers from routing new builds to the node while transfers in progress complete")
+                        .arg_required_else_help(true)
+                        .args(&[
+                            arg!(--ttl <SECONDS> "How long the maintenance lasts, at most 7 days")
+                                .value_parser(clap::value_parser!(u64).range(1..)),
+                            arg!(--reason <REASON> "Why the node is taken down").required(false),
+                        ]),
+                    Command::new("end").about("End the maintenance of the node"),
+                ]),
             Command::new("ping").about("Pings configured pyrsia node"),
+            Command::new("secret")
+                .about("Manage the secrets that are passed to builds (requires PYRSIA_ADMIN_TOKEN)")
+                .subcommand_required(true)
+                .arg_required_else_help(true)
+                .subcommands(vec![
+                    Command::new("set")
+                        .about("Set a secret for builds of packages in a namespace")
+                        .arg_required_else_help(true)
+                        .args(&[
+                            arg!(--namespace <NAMESPACE> "The package specific id prefix (e.g. com.myorg:)"),
+                            arg!(--name <NAME> "The name of the secret (e.g. GIT_TOKEN)"),
+                            arg!(--value <VALUE> "The value of the secret"),
+                        ]),
+                    Command::new("rm")
+                        .about("Remove a secret")
+                        .arg_required_else_help(true)
+                        .args(&[
+                            arg!(--namespace <NAMESPACE> "The package specific id prefix"),
+                            arg!(--name <NAME> "The name of the secret"),
+                        ]),
+                    Command::new("list").about("List the namespaces and names of all secrets"),
+                ]),
             Command::new("status")
                 .short_flag('s')
                 .about("Show information about the Pyrsia node"),
+            Command::new("tag")
+                .about("Inspect the history of docker tags")
+                .subcommand_required(true)
+                .arg_required_else_help(true)
+                .subcommands(vec![
+                    Command::new("history")
+                        .about("Show every digest a docker tag has pointed to")
+                        .arg_required_else_help(true)
+                        .args(&[
+                            arg!(--image <IMAGE> "The docker image with tag (e.g. alpine:latest)"),
+                            arg!(--at <TIME> "Only show the digest the tag pointed to at this time, in RFC3339 (e.g. 2023-02-14T09:30:00Z) or seconds since the unix epoch")
+                                .required(false),
+                            arg!(--"local-time" "Show times in the local time zone instead of UTC"),
+                        ]),
+                ]),
+            Command::new("transfers")
+                .about("Show the progress of the artifacts that the node is transferring")
+                .args(&[
+                    arg!(--watch "Keep showing the progress until all transfers are finished"),
+                ]),
         ])
         .version(version_string)
-        .get_matches()
+        .try_get_matches()
+        .unwrap_or_else(|error| {
+            if error.use_stderr()
+                && error.kind() != ErrorKind::DisplayHelpOnMissingArgumentOrSubcommand
+                && json_error_format_requested(std::env::args())
+            {
+                let error = anyhow::Error::from(CliError::new(
+                    CliErrorKind::UsageError,
+                    error.to_string().trim(),
+                ));
+                let report = ErrorReport::from(&error);
+                eprintln!("{}", serde_json::to_string(&report).unwrap());
+                std::process::exit(report.exit_code);
+            }
+            error.exit()
+        })
+}
+
+/// Whether errors should be printed as JSON, for errors that happen before
+/// the arguments are parsed.
+fn json_error_format_requested(args: impl Iterator<Item = String>) -> bool {
+    let args: Vec<String> = args.collect();
+    args.iter().any(|arg| arg == "--error-format=json")
+        || args
+            .windows(2)
+            .any(|pair| pair[0] == "--error-format" && pair[1] == "json")
+}
+
+fn migration_args(example_url: &str) -> Vec<Arg> {
+    vec![
+        arg!(--url <URL>).help(format!(
+            "The base URL of the repository manager (e.g. {})",
+            example_url
+        )),
+        arg!(--repository <REPOSITORY> "The name of the repository to migrate"),
+        arg!(--user <USER> "The credentials for the repository manager as user:password")
+            .required(false),
+        arg!(--state <FILE> "The file that tracks migrated packages, to resume an interrupted migration")
+            .required(false),
+        arg!(--report <FILE> "The file to write the reconciliation report to as JSON")
+            .required(false),
+    ]
 }
 
 fn inspect_log_fields_help_string() -> String {
     let content: Content = Default::default();
     let mut res = String::new();
-    for field in content.fields {
+    // the successor of deprecated packages, the provenance of artifacts and
+    // the pinning snapshot of their build are only shown when requested
+    for field in content.fields.into_iter().chain([
+        TransparencyLogField::Successor,
+        TransparencyLogField::Provenance,
+        TransparencyLogField::PinningSnapshot,
+    ]) {
         let (name, description) = field.aaa();
-        res = res + format!("\t- field: '{}',\tdescription: {}\n", name, description).as_str();
+        res += format!("\t- field: '{}',\tdescription: {}\n", name, description).as_str();
     }
 
     format!(
@@ -16,8 +16,14 @@
 
 pub mod cli;
 
+use anyhow::Context;
+use clap::ArgMatches;
 use cli::handlers::*;
 use cli::parser::*;
+use pyrsia::artifact_service::model::PackageType;
+use pyrsia::cli_commands::format::TimeZone;
+use pyrsia::cli_commands::migrate::{RemoteRepository, RepositoryManager};
+use std::path::{Path, PathBuf};
 
 const CONF_FILE_PATH_MSG_STARTER: &str = "Config file path:";
 
@@ -26,6 +32,16 @@ async fn main() {
     // parsing command line arguments
     let matches = cli_parser();
 
+    if let Err(error) = run(&matches).await {
+        let json = matches
+            .get_one::<String>("error-format")
+            .map(String::as_str)
+            == Some("json");
+        std::process::exit(report_error(&error, json));
+    }
+}
+
+async fn run(matches: &ArgMatches) -> anyhow::Result<()> {
     // checking and preparing responses for each command and its arguments if applicable
 
     match matches.subcommand() {
@@ -38,62 +54,308 @@ async fn main() {
                     let host_name = edit_config_matches.get_one::<String>("host");
                     let port = edit_config_matches.get_one::<String>("port");
                     let diskspace = edit_config_matches.get_one::<String>("diskspace");
-                    match config_edit(host_name.cloned(), port.cloned(), diskspace.cloned()) {
-                        Ok(_) => {
-                            println!("Node configuration saved !!");
-                        }
-                        Err(error) => {
-                            eprintln!("Error saving node configuration: {}", error);
-                        }
-                    }
+                    config_edit(host_name.cloned(), port.cloned(), diskspace.cloned())
+                        .context("Saving node configuration failed")?;
                 } else {
-                    match config_add() {
-                        Ok(_) => {
-                            println!("Node configuration saved !!");
-                        }
-                        Err(error) => {
-                            eprintln!("Error saving node configuration: {}", error);
-                        }
-                    }
+                    config_add().context("Saving node configuration failed")?;
                 }
+                println!("Node configuration saved !!");
             }
             if *config_matches.get_one::<bool>("remove").unwrap_or(&false) {
-                match config_remove() {
-                    Ok(_) => {
-                        println!("Node configuration removed !!");
-                    }
-                    Err(error) => {
-                        eprintln!("Error removing node configuration: {}", error);
-                    }
-                }
+                config_remove().context("Removing node configuration failed")?;
+                println!("Node configuration removed !!");
             }
             if *config_matches.get_one::<bool>("show").unwrap_or(&false) {
                 config_show();
             }
         }
+        Some(("artifact", artifact_matches)) => {
+            if let Some(("get", get_matches)) = artifact_matches.subcommand() {
+                artifact_get(
+                    get_matches.get_one::<String>("COORDINATES").unwrap(),
+                    get_matches.get_one::<String>("output").map(PathBuf::from),
+                )
+                .await?;
+            }
+        }
         Some(("authorize", authorize_matches)) => {
-            authorize(authorize_matches.get_one::<String>("peer").unwrap()).await;
+            if *authorize_matches
+                .get_one::<bool>("proposals")
+                .unwrap_or(&false)
+            {
+                authorize_proposals().await?;
+            } else {
+                authorize(
+                    authorize_matches.get_one::<String>("peer").unwrap(),
+                    *authorize_matches
+                        .get_one::<bool>("remove")
+                        .unwrap_or(&false),
+                )
+                .await?;
+            }
         }
+        Some(("block", block_matches)) => match block_matches.subcommand() {
+            Some(("list", list_matches)) => {
+                block_list(
+                    list_matches.get_one::<usize>("limit").copied(),
+                    TimeZone::from_local_time(
+                        *list_matches.get_one::<bool>("local-time").unwrap_or(&false),
+                    ),
+                )
+                .await?;
+            }
+            Some(("show", show_matches)) => {
+                block_show(
+                    show_matches.get_one::<String>("BLOCK").unwrap(),
+                    TimeZone::from_local_time(
+                        *show_matches.get_one::<bool>("local-time").unwrap_or(&false),
+                    ),
+                )
+                .await?;
+            }
+            Some(("export", export_matches)) => {
+                block_export(Path::new(export_matches.get_one::<String>("FILE").unwrap())).await?;
+            }
+            Some(("audit", _)) => {
+                block_audit().await?;
+            }
+            _ => {}
+        },
         Some(("build", build_matches)) => match build_matches.subcommand() {
             Some(("docker", docker_matches)) => {
-                request_docker_build(docker_matches.get_one::<String>("image").unwrap()).await;
+                request_docker_build(docker_matches.get_one::<String>("image").unwrap()).await?;
             }
             Some(("maven", maven_matches)) => {
-                request_maven_build(maven_matches.get_one::<String>("gav").unwrap()).await;
+                request_maven_build(maven_matches.get_one::<String>("gav").unwrap()).await?;
             }
             Some(("status", status_matches)) => {
-                request_build_status(status_matches.get_one::<String>("id").unwrap()).await;
+                request_build_status(status_matches.get_one::<String>("id").unwrap()).await?;
+            }
+            Some(("history", history_matches)) => {
+                request_build_history(
+                    history_matches.get_one::<String>("package").cloned(),
+                    history_matches.get_one::<String>("status").cloned(),
+                    history_matches.get_one::<u64>("since").copied(),
+                    TimeZone::from_local_time(
+                        *history_matches
+                            .get_one::<bool>("local-time")
+                            .unwrap_or(&false),
+                    ),
+                )
+                .await?;
+            }
+            Some(("rerun", rerun_matches)) => {
+                request_build_rerun(rerun_matches.get_one::<String>("BUILD_ID").unwrap()).await?;
+            }
+            Some(("reproduce", reproduce_matches)) => {
+                request_build_reproduce(
+                    reproduce_matches
+                        .get_one::<String>("PINNING_SNAPSHOT")
+                        .unwrap(),
+                )
+                .await?;
+            }
+            Some(("replay-failed", _replay_failed_matches)) => {
+                request_replay_failed_builds().await?;
+            }
+            _ => {}
+        },
+        Some(("secret", secret_matches)) => match secret_matches.subcommand() {
+            Some(("set", set_matches)) => {
+                set_secret(
+                    set_matches.get_one::<String>("namespace").unwrap(),
+                    set_matches.get_one::<String>("name").unwrap(),
+                    set_matches.get_one::<String>("value").unwrap(),
+                )
+                .await?;
+            }
+            Some(("rm", rm_matches)) => {
+                remove_secret(
+                    rm_matches.get_one::<String>("namespace").unwrap(),
+                    rm_matches.get_one::<String>("name").unwrap(),
+                )
+                .await?;
+            }
+            Some(("list", _list_matches)) => {
+                list_secrets().await?;
+            }
+            _ => {}
+        },
+        Some(("check", check_matches)) => match check_matches.subcommand() {
+            Some(("docker", docker_matches)) => {
+                check_package(
+                    PackageType::Docker,
+                    docker_matches.get_one::<String>("image").unwrap(),
+                    *docker_matches.get_one::<bool>("retrieve").unwrap_or(&false),
+                )
+                .await?;
+            }
+            Some(("maven", maven_matches)) => {
+                check_package(
+                    PackageType::Maven2,
+                    maven_matches.get_one::<String>("gav").unwrap(),
+                    *maven_matches.get_one::<bool>("retrieve").unwrap_or(&false),
+                )
+                .await?;
             }
             _ => {}
         },
+        Some(("deprecate", deprecate_matches)) => match deprecate_matches.subcommand() {
+            Some(("docker", docker_matches)) => {
+                deprecate_package(
+                    PackageType::Docker,
+                    docker_matches.get_one::<String>("image").unwrap(),
+                    docker_matches.get_one::<String>("successor").cloned(),
+                )
+                .await?;
+            }
+            Some(("maven", maven_matches)) => {
+                deprecate_package(
+                    PackageType::Maven2,
+                    maven_matches.get_one::<String>("gav").unwrap(),
+                    maven_matches.get_one::<String>("successor").cloned(),
+                )
+                .await?;
+            }
+            _ => {}
+        },
+        Some(("import", import_matches)) => match import_matches.subcommand() {
+            Some(("docker-daemon", docker_matches)) => {
+                import_docker_daemon_image(docker_matches.get_one::<String>("IMAGE").unwrap())
+                    .await?;
+            }
+            Some(("maven-repo", maven_matches)) => {
+                import_maven_repository(maven_matches.get_one::<String>("PATH").unwrap()).await?;
+            }
+            _ => {}
+        },
+        Some(("migrate", migrate_matches)) => {
+            let (manager, matches) = match migrate_matches.subcommand() {
+                Some(("artifactory", matches)) => (RepositoryManager::Artifactory, matches),
+                Some(("nexus", matches)) => (RepositoryManager::Nexus, matches),
+                _ => return Ok(()),
+            };
+            let repository_name = matches.get_one::<String>("repository").unwrap();
+            let state_path = matches
+                .get_one::<String>("state")
+                .map(PathBuf::from)
+                .unwrap_or_else(|| {
+                    PathBuf::from(format!(
+                        "pyrsia-migration-{}-{}.json",
+                        manager, repository_name
+                    ))
+                });
+            migrate_repository(
+                RemoteRepository::new(
+                    manager,
+                    matches.get_one::<String>("url").unwrap(),
+                    repository_name,
+                    matches.get_one::<String>("user").map(String::as_str),
+                ),
+                &state_path,
+                matches.get_one::<String>("report").map(Path::new),
+            )
+            .await?;
+        }
         Some(("list", _config_matches)) => {
-            node_list().await;
+            node_list().await?;
         }
+        Some(("node", node_matches)) => match node_matches.subcommand() {
+            Some(("init", init_matches)) => {
+                node_init(init_matches.get_one::<String>("seed").unwrap()).await?;
+            }
+            Some(("upgrade", upgrade_matches)) => {
+                node_upgrade(
+                    upgrade_matches.get_one::<String>("manifest-url").unwrap(),
+                    upgrade_matches.get_one::<String>("release-key").cloned(),
+                    upgrade_matches
+                        .get_one::<String>("binary")
+                        .map(PathBuf::from),
+                    if *upgrade_matches
+                        .get_one::<bool>("no-restart")
+                        .unwrap_or(&false)
+                    {
+                        None
+                    } else {
+                        upgrade_matches
+                            .get_one::<String>("restart-command")
+                            .cloned()
+                    },
+                    *upgrade_matches.get_one::<bool>("force").unwrap_or(&false),
+                )
+                .await?;
+            }
+            _ => {}
+        },
+        Some(("network", network_matches)) => match network_matches.subcommand() {
+            Some(("debug-lookup", debug_lookup_matches)) => {
+                network_debug_lookup(
+                    debug_lookup_matches
+                        .get_one::<String>("ARTIFACT_ID")
+                        .unwrap(),
+                )
+                .await?;
+            }
+            Some(("query-stats", _query_stats_matches)) => {
+                network_query_stats().await?;
+            }
+            _ => {}
+        },
+        Some(("peers", peers_matches)) => match peers_matches.subcommand() {
+            Some(("list", _list_matches)) => {
+                node_list().await?;
+            }
+            Some(("aliases", _aliases_matches)) => {
+                list_peer_aliases().await?;
+            }
+            Some(("maintenance", _maintenance_matches)) => {
+                list_draining_peers().await?;
+            }
+            Some(("alias", alias_matches)) => {
+                let alias = if *alias_matches.get_one::<bool>("remove").unwrap_or(&false) {
+                    None
+                } else {
+                    alias_matches.get_one::<String>("ALIAS").cloned()
+                };
+                set_peer_alias(alias_matches.get_one::<String>("PEER_ID").unwrap(), alias).await?;
+            }
+            _ => {}
+        },
+        Some(("maintenance", maintenance_matches)) => match maintenance_matches.subcommand() {
+            Some(("start", start_matches)) => {
+                announce_maintenance(
+                    *start_matches.get_one::<u64>("ttl").unwrap(),
+                    start_matches.get_one::<String>("reason").cloned(),
+                )
+                .await?;
+            }
+            Some(("end", _end_matches)) => {
+                announce_maintenance(0, None).await?;
+            }
+            _ => {}
+        },
         Some(("ping", _config_matches)) => {
-            node_ping().await;
+            node_ping().await?;
         }
         Some(("status", _config_matches)) => {
-            node_status().await;
+            node_status().await?;
+        }
+        Some(("tag", tag_matches)) => {
+            if let Some(("history", history_matches)) = tag_matches.subcommand() {
+                tag_history(
+                    history_matches.get_one::<String>("image").unwrap(),
+                    history_matches.get_one::<String>("at").cloned(),
+                    TimeZone::from_local_time(
+                        *history_matches
+                            .get_one::<bool>("local-time")
+                            .unwrap_or(&false),
+                    ),
+                )
+                .await?;
+            }
+        }
+        Some(("transfers", transfers_matches)) => {
+            node_transfers(*transfers_matches.get_one::<bool>("watch").unwrap_or(&false)).await?;
         }
         Some(("inspect-log", build_matches)) => match build_matches.subcommand() {
             Some(("docker", docker_matches)) => {
@@ -101,19 +363,30 @@ async fn main() {
                     docker_matches.get_one::<String>("image").unwrap(),
                     docker_matches.get_one::<String>("format").cloned(),
                     docker_matches.get_one::<String>("fields").cloned(),
+                    TimeZone::from_local_time(
+                        *docker_matches
+                            .get_one::<bool>("local-time")
+                            .unwrap_or(&false),
+                    ),
                 )
-                .await;
+                .await?;
             }
             Some(("maven", maven_matches)) => {
                 inspect_maven_transparency_log(
                     maven_matches.get_one::<String>("gav").unwrap(),
                     maven_matches.get_one::<String>("format").cloned(),
                     maven_matches.get_one::<String>("fields").cloned(),
+                    TimeZone::from_local_time(
+                        *maven_matches
+                            .get_one::<bool>("local-time")
+                            .unwrap_or(&false),
+                    ),
                 )
-                .await;
+                .await?;
             }
             _ => {}
         },
         _ => {} //this should be handled by clap arg_required_else_help
     }
+    Ok(())
 }
@@ -0,0 +1,199 @@
+/*
+   Copyright 2021 JFrog Ltd
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+*/
+
+use crate::peer_throughput::TRANSFER_STALL_TIMEOUT;
+use async_trait::async_trait;
+use futures::prelude::*;
+use libp2p::core::upgrade::{
+    read_length_prefixed, read_varint, write_length_prefixed, ProtocolName,
+};
+use libp2p::request_response::RequestResponseCodec;
+use log::debug;
+use serde::{Deserialize, Serialize};
+use std::io;
+
+const MAX_RESPONSE_SIZE: usize = 100_000_000;
+/// Responses are read in blocks of this size, every block that is received
+/// extends the deadline of the transfer by the stall timeout.
+const RESPONSE_BLOCK_SIZE: usize = 64 * 1024;
+
+#[derive(Debug, Clone)]
+pub struct ArtifactExchangeProtocol();
+/// The `ArtifactExchangeCodec` defines the request and response types
+/// for the [`RequestResponse`](crate::RequestResponse) protocol for
+/// exchanging artifacts. A request is either for a complete artifact or for
+/// a chunk of it, so that large artifacts can be downloaded from several
+/// peers at once. Every response contains the size of the complete artifact.
+/// A response that makes no progress for [`TRANSFER_STALL_TIMEOUT`] is
+/// aborted, the overall timeout of a request is up to the requester.
+#[derive(Clone)]
+pub struct ArtifactExchangeCodec();
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub struct ArtifactRequest {
+    pub artifact_id: String,
+    pub chunk: Option<ArtifactChunk>,
+}
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArtifactResponse {
+    pub artifact: Vec<u8>,
+    pub artifact_size: u64,
+}
+
+/// A chunk of `len` bytes of an artifact at `offset`. A chunk that exceeds
+/// the end of the artifact is truncated.
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq)]
+pub struct ArtifactChunk {
+    pub offset: u64,
+    pub len: u64,
+}
+
+impl ArtifactChunk {
+    /// The number of bytes of this chunk of an artifact of `artifact_size`
+    /// bytes.
+    pub fn len_in(&self, artifact_size: u64) -> u64 {
+        self.len.min(artifact_size.saturating_sub(self.offset))
+    }
+}
+
+impl ArtifactResponse {
+    /// A response with a complete artifact.
+    pub fn complete(artifact: Vec<u8>) -> Self {
+        let artifact_size = artifact.len() as u64;
+        ArtifactResponse {
+            artifact,
+            artifact_size,
+        }
+    }
+}
+
+impl ProtocolName for ArtifactExchangeProtocol {
+    fn protocol_name(&self) -> &[u8] {
+        "/artifact-exchange/2".as_bytes()
+    }
+}
+
+#[async_trait]
+impl RequestResponseCodec for ArtifactExchangeCodec {
+    type Protocol = ArtifactExchangeProtocol;
+    type Request = ArtifactRequest;
+    type Response = ArtifactResponse;
+
+    async fn read_request<T>(
+        &mut self,
+        _: &ArtifactExchangeProtocol,
+        io: &mut T,
+    ) -> io::Result<Self::Request>
+    where
+        T: AsyncRead + Unpin + Send,
+    {
+        let request_vec = read_length_prefixed(io, 1_000_000).await?;
+        if request_vec.is_empty() {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+
+        let request: ArtifactRequest = serde_json::from_slice(&request_vec)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        debug!("Read ArtifactRequest: {:?}", request);
+
+        Ok(request)
+    }
+
+    async fn read_response<T>(
+        &mut self,
+        _: &ArtifactExchangeProtocol,
+        io: &mut T,
+    ) -> io::Result<Self::Response>
+    where
+        T: AsyncRead + Unpin + Send,
+    {
+        let mut artifact_size = [0u8; 8];
+        unless_stalled(io.read_exact(&mut artifact_size)).await?;
+        let len = unless_stalled(read_varint(io)).await?;
+        if len == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        if len > MAX_RESPONSE_SIZE {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "Received data size ({} bytes) exceeds maximum ({} bytes)",
+                    len, MAX_RESPONSE_SIZE
+                ),
+            ));
+        }
+
+        let mut artifact = vec![0; len];
+        for block in artifact.chunks_mut(RESPONSE_BLOCK_SIZE) {
+            unless_stalled(io.read_exact(block)).await?;
+        }
+
+        Ok(ArtifactResponse {
+            artifact,
+            artifact_size: u64::from_be_bytes(artifact_size),
+        })
+    }
+
+    async fn write_request<T>(
+        &mut self,
+        _: &ArtifactExchangeProtocol,
+        io: &mut T,
+        request: ArtifactRequest,
+    ) -> io::Result<()>
+    where
+        T: AsyncWrite + Unpin + Send,
+    {
+        debug!("Write ArtifactRequest: {:?}", request);
+
+        let request_vec = serde_json::to_vec(&request)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        write_length_prefixed(io, request_vec).await?;
+        io.close().await?;
+
+        Ok(())
+    }
+
+    async fn write_response<T>(
+        &mut self,
+        _: &ArtifactExchangeProtocol,
+        io: &mut T,
+        ArtifactResponse {
+            artifact,
+            artifact_size,
+        }: ArtifactResponse,
+    ) -> io::Result<()>
+    where
+        T: AsyncWrite + Unpin + Send,
+    {
+        io.write_all(&artifact_size.to_be_bytes()).await?;
+        write_length_prefixed(io, artifact).await?;
+        io.close().await?;
+
+        Ok(())
+    }
+}
+
+// Fail with a TimedOut error when `read` does not complete within the stall
+// timeout.
+async fn unless_stalled<T>(read: impl Future<Output = io::Result<T>>) -> io::Result<T> {
+    tokio::time::timeout(TRANSFER_STALL_TIMEOUT, read)
+        .await
+        .map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::TimedOut,
+                "Transfer made no progress within the stall timeout",
+            )
+        })?
+}
@@ -0,0 +1,28 @@
+/*
+   Copyright 2021 JFrog Ltd
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+*/
+
+//! The libp2p protocols and peer bookkeeping of a Pyrsia node that do not
+//! depend on the other services of the node. The `pyrsia` crate re-exports
+//! these modules under `pyrsia::network`, next to the swarm and the p2p
+//! client that tie them together.
+
+pub mod artifact_protocol;
+pub mod idle_metric_protocol;
+pub mod peer_alias;
+pub mod peer_exchange_protocol;
+pub mod peer_throughput;
+pub mod peer_version;
+pub mod query_metrics;
@@ -0,0 +1,330 @@
+/*
+   Copyright 2021 JFrog Ltd
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+*/
+
+use libp2p::PeerId;
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use thiserror::Error;
+
+pub(crate) const AGENT_VERSION_PREFIX: &str = "pyrsia/";
+const ALIAS_TOKEN_PREFIX: &str = "alias/";
+const MAX_ALIAS_LENGTH: usize = 64;
+
+#[derive(Debug, Error)]
+pub enum PeerAliasError {
+    #[error("Invalid alias: {0}")]
+    InvalidAlias(String),
+    #[error("Failure while accessing the peer aliases: {0}")]
+    StorageFailure(#[from] io::Error),
+    #[error("Failure while (de)serializing the peer aliases: {0}")]
+    SerializationFailure(#[from] serde_json::Error),
+}
+
+/// Where the alias of a peer comes from.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, Eq, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum PeerAliasSource {
+    /// assigned by the operator of this node
+    Local,
+    /// advertised by the peer itself via identify
+    Advertised,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, Eq, PartialEq)]
+pub struct PeerAlias {
+    pub peer_id: String,
+    pub alias: String,
+    pub source: PeerAliasSource,
+}
+
+#[derive(Debug, Default)]
+struct Aliases {
+    local: BTreeMap<PeerId, String>,
+    advertised: BTreeMap<PeerId, String>,
+}
+
+/// A registry of human-friendly names for peers. Peers advertise the alias
+/// they are configured with in the agent version of the identify protocol,
+/// and operators can assign local aliases that take precedence over the
+/// advertised ones. Local aliases are persisted when the registry has a path.
+#[derive(Clone, Debug, Default)]
+pub struct PeerAliases {
+    own_alias: Option<String>,
+    path: Option<PathBuf>,
+    aliases: Arc<Mutex<Aliases>>,
+}
+
+impl PeerAliases {
+    /// Create a registry that persists local aliases in the file at `path`.
+    pub fn new<P: AsRef<Path>>(path: P) -> Self {
+        let path = path.as_ref().to_path_buf();
+        let local = load(&path).unwrap_or_else(|e| {
+            warn!("Failed to load peer aliases from {:?}: {}", path, e);
+            Default::default()
+        });
+
+        PeerAliases {
+            own_alias: None,
+            path: Some(path),
+            aliases: Arc::new(Mutex::new(Aliases {
+                local,
+                advertised: Default::default(),
+            })),
+        }
+    }
+
+    /// The alias that this node advertises to its peers.
+    pub fn with_own_alias(mut self, own_alias: &str) -> Result<Self, PeerAliasError> {
+        validate_alias(own_alias)?;
+        self.own_alias = Some(own_alias.to_owned());
+        Ok(self)
+    }
+
+    pub fn own_alias(&self) -> Option<String> {
+        self.own_alias.clone()
+    }
+
+    /// The agent version that is sent to peers with the identify protocol.
+    pub fn agent_version(&self) -> String {
+        let agent_version = format!("{}{}", AGENT_VERSION_PREFIX, env!("CARGO_PKG_VERSION"));
+        match &self.own_alias {
+            Some(own_alias) => format!("{} {}{}", agent_version, ALIAS_TOKEN_PREFIX, own_alias),
+            None => agent_version,
+        }
+    }
+
+    /// Record the alias that `peer_id` advertised in its agent version, if any.
+    pub fn record_agent_version(&self, peer_id: &PeerId, agent_version: &str) {
+        let advertised_alias = agent_version
+            .split_whitespace()
+            .filter_map(|token| token.strip_prefix(ALIAS_TOKEN_PREFIX))
+            .find(|alias| validate_alias(alias).is_ok());
+
+        let mut aliases = self.aliases.lock().unwrap();
+        match advertised_alias {
+            Some(alias) => aliases.advertised.insert(*peer_id, alias.to_owned()),
+            None => aliases.advertised.remove(peer_id),
+        };
+    }
+
+    /// Assign a local alias to `peer_id`, or remove it when `alias` is `None`.
+    pub fn set_local_alias(
+        &self,
+        peer_id: &PeerId,
+        alias: Option<&str>,
+    ) -> Result<(), PeerAliasError> {
+        if let Some(alias) = alias {
+            validate_alias(alias)?;
+        }
+
+        let mut aliases = self.aliases.lock().unwrap();
+        let mut local = aliases.local.clone();
+        match alias {
+            Some(alias) => local.insert(*peer_id, alias.to_owned()),
+            None => local.remove(peer_id),
+        };
+        if let Some(path) = &self.path {
+            save(path, &local)?;
+        }
+        aliases.local = local;
+        Ok(())
+    }
+
+    /// The alias of `peer_id`. A local alias takes precedence over the alias
+    /// the peer advertised.
+    pub fn alias(&self, peer_id: &PeerId) -> Option<String> {
+        let aliases = self.aliases.lock().unwrap();
+        aliases
+            .local
+            .get(peer_id)
+            .or_else(|| aliases.advertised.get(peer_id))
+            .cloned()
+    }
+
+    /// All known aliases, with the local aliases first.
+    pub fn list(&self) -> Vec<PeerAlias> {
+        let aliases = self.aliases.lock().unwrap();
+        let local = aliases
+            .local
+            .iter()
+            .map(|(peer_id, alias)| (peer_id, alias, PeerAliasSource::Local));
+        let advertised = aliases
+            .advertised
+            .iter()
+            .filter(|(peer_id, _)| !aliases.local.contains_key(peer_id))
+            .map(|(peer_id, alias)| (peer_id, alias, PeerAliasSource::Advertised));
+
+        local
+            .chain(advertised)
+            .map(|(peer_id, alias, source)| PeerAlias {
+                peer_id: peer_id.to_string(),
+                alias: alias.clone(),
+                source,
+            })
+            .collect()
+    }
+
+    /// Render `peer_id` with its alias for log and CLI output.
+    pub fn display(&self, peer_id: &PeerId) -> String {
+        display_peer(&peer_id.to_string(), self.alias(peer_id).as_deref())
+    }
+}
+
+/// Render a peer id with its alias, if it has one.
+pub fn display_peer(peer_id: &str, alias: Option<&str>) -> String {
+    match alias {
+        Some(alias) => format!("{} ({})", alias, peer_id),
+        None => peer_id.to_owned(),
+    }
+}
+
+/// Aliases are limited to letters, digits, '-', '_' and '.' so they can be
+/// embedded in the agent version and cannot be mistaken for a peer id.
+fn validate_alias(alias: &str) -> Result<(), PeerAliasError> {
+    if alias.is_empty() || alias.len() > MAX_ALIAS_LENGTH {
+        return Err(PeerAliasError::InvalidAlias(format!(
+            "'{}' must be between 1 and {} characters",
+            alias, MAX_ALIAS_LENGTH
+        )));
+    }
+    if !alias
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+    {
+        return Err(PeerAliasError::InvalidAlias(format!(
+            "'{}' may only contain letters, digits, '-', '_' and '.'",
+            alias
+        )));
+    }
+    if PeerId::from_str(alias).is_ok() {
+        return Err(PeerAliasError::InvalidAlias(format!(
+            "'{}' is a peer id",
+            alias
+        )));
+    }
+    Ok(())
+}
+
+fn load(path: &Path) -> Result<BTreeMap<PeerId, String>, PeerAliasError> {
+    let stored: BTreeMap<String, String> = match fs::read(path) {
+        Ok(content) => serde_json::from_slice(&content)?,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Default::default()),
+        Err(e) => return Err(e.into()),
+    };
+
+    Ok(stored
+        .into_iter()
+        .filter_map(|(peer_id, alias)| Some((PeerId::from_str(&peer_id).ok()?, alias)))
+        .collect())
+}
+
+fn save(path: &Path, local: &BTreeMap<PeerId, String>) -> Result<(), PeerAliasError> {
+    let stored: BTreeMap<String, &String> = local
+        .iter()
+        .map(|(peer_id, alias)| (peer_id.to_string(), alias))
+        .collect();
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let tmp_path = path.with_extension("tmp");
+    fs::write(&tmp_path, serde_json::to_vec(&stored)?)?;
+    fs::rename(&tmp_path, path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+#[cfg(not(tarpaulin_include))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_advertised_alias_is_parsed_from_agent_version() {
+        let own = PeerAliases::default().with_own_alias("node-a").unwrap();
+        assert_eq!(
+            own.agent_version(),
+            format!("pyrsia/{} alias/node-a", env!("CARGO_PKG_VERSION"))
+        );
+
+        let peer_aliases = PeerAliases::default();
+        let peer_id = PeerId::random();
+        peer_aliases.record_agent_version(&peer_id, &own.agent_version());
+        assert_eq!(peer_aliases.alias(&peer_id), Some("node-a".to_owned()));
+        assert_eq!(
+            peer_aliases.display(&peer_id),
+            format!("node-a ({})", peer_id)
+        );
+
+        peer_aliases.record_agent_version(&peer_id, "rust-libp2p/0.40.0");
+        assert_eq!(peer_aliases.alias(&peer_id), None);
+        assert_eq!(peer_aliases.display(&peer_id), peer_id.to_string());
+    }
+
+    #[test]
+    fn test_local_alias_takes_precedence_and_is_persisted() {
+        let tmp_dir = tempfile::tempdir().unwrap().into_path();
+        let path = tmp_dir.join("peer_aliases.json");
+
+        let peer_aliases = PeerAliases::new(&path);
+        let peer_id = PeerId::random();
+        peer_aliases.record_agent_version(&peer_id, "pyrsia/0.2.4 alias/advertised");
+        peer_aliases
+            .set_local_alias(&peer_id, Some("local"))
+            .unwrap();
+        assert_eq!(peer_aliases.alias(&peer_id), Some("local".to_owned()));
+        assert_eq!(
+            peer_aliases.list(),
+            vec![PeerAlias {
+                peer_id: peer_id.to_string(),
+                alias: "local".to_owned(),
+                source: PeerAliasSource::Local,
+            }]
+        );
+
+        let reloaded = PeerAliases::new(&path);
+        assert_eq!(reloaded.alias(&peer_id), Some("local".to_owned()));
+
+        peer_aliases.set_local_alias(&peer_id, None).unwrap();
+        assert_eq!(peer_aliases.alias(&peer_id), Some("advertised".to_owned()));
+        assert_eq!(PeerAliases::new(&path).alias(&peer_id), None);
+
+        std::fs::remove_dir_all(&tmp_dir).unwrap();
+    }
+
+    #[test]
+    fn test_invalid_aliases_are_rejected() {
+        let peer_aliases = PeerAliases::default();
+        let peer_id = PeerId::random();
+        for alias in [
+            "",
+            "with space",
+            &"a".repeat(65),
+            &PeerId::random().to_string(),
+        ] {
+            assert!(matches!(
+                peer_aliases.set_local_alias(&peer_id, Some(alias)),
+                Err(PeerAliasError::InvalidAlias(_))
+            ));
+        }
+        assert!(PeerAliases::default().with_own_alias("my node").is_err());
+    }
+}
@@ -0,0 +1,239 @@
+/*
+   Copyright 2021 JFrog Ltd
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+*/
+
+use async_trait::async_trait;
+use futures::prelude::*;
+use libp2p::core::upgrade::{read_length_prefixed, write_length_prefixed, ProtocolName};
+use libp2p::request_response::RequestResponseCodec;
+use libp2p::{Multiaddr, PeerId};
+use log::debug;
+use rand::seq::SliceRandom;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::io;
+
+/// The maximum number of peers that are shared in one peer exchange.
+pub const PEER_EXCHANGE_SAMPLE_SIZE: usize = 16;
+/// The number of connected peers that known peers are exchanged with in
+/// one round.
+pub const PEER_EXCHANGE_FANOUT: usize = 3;
+const PEER_EXCHANGE_MAX_SIZE_PER_MESSAGE: usize = 64 * 1024;
+
+/// A peer and the addresses that it can be dialed on.
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
+pub struct PeerAddresses {
+    pub peer_id: PeerId,
+    pub addresses: Vec<Multiaddr>,
+}
+
+#[derive(Debug, Clone)]
+pub struct PeerExchangeProtocol();
+
+#[derive(Clone)]
+pub struct PeerExchangeCodec();
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PeerExchangeRequest();
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PeerExchangeResponse(pub Vec<PeerAddresses>);
+
+impl ProtocolName for PeerExchangeProtocol {
+    fn protocol_name(&self) -> &[u8] {
+        "/pyrsia-peer-exchange/1".as_bytes()
+    }
+}
+
+#[async_trait]
+impl RequestResponseCodec for PeerExchangeCodec {
+    type Protocol = PeerExchangeProtocol;
+    type Request = PeerExchangeRequest;
+    type Response = PeerExchangeResponse;
+
+    ///This method reads the peer exchange request from the peer.
+    async fn read_request<T>(
+        &mut self,
+        _: &PeerExchangeProtocol,
+        _io: &mut T,
+    ) -> io::Result<Self::Request>
+    where
+        T: AsyncRead + Unpin + Send,
+    {
+        debug!("PeerExchange::read_request received");
+
+        Ok(PeerExchangeRequest())
+    }
+
+    ///This method reads the sample of known peers from the peer.
+    async fn read_response<T>(
+        &mut self,
+        _: &PeerExchangeProtocol,
+        io: &mut T,
+    ) -> io::Result<Self::Response>
+    where
+        T: AsyncRead + Unpin + Send,
+    {
+        let buffer = read_length_prefixed(io, PEER_EXCHANGE_MAX_SIZE_PER_MESSAGE).await?;
+        let peers: Vec<PeerAddresses> = serde_json::from_slice(&buffer)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+        debug!("PeerExchange::read_response received {} peers", peers.len());
+
+        Ok(PeerExchangeResponse(peers))
+    }
+
+    ///This method sends a peer exchange request to the peer.
+    async fn write_request<T>(
+        &mut self,
+        _: &PeerExchangeProtocol,
+        io: &mut T,
+        PeerExchangeRequest(): PeerExchangeRequest,
+    ) -> io::Result<()>
+    where
+        T: AsyncWrite + Unpin + Send,
+    {
+        debug!("PeerExchange::write_request sent");
+
+        io.close().await?;
+
+        Ok(())
+    }
+
+    ///This method sends a sample of known peers to the peer.
+    async fn write_response<T>(
+        &mut self,
+        _: &PeerExchangeProtocol,
+        io: &mut T,
+        PeerExchangeResponse(peers): PeerExchangeResponse,
+    ) -> io::Result<()>
+    where
+        T: AsyncWrite + Unpin + Send,
+    {
+        debug!("PeerExchange::write_response sent {} peers", peers.len());
+
+        let data = serde_json::to_vec(&peers)?;
+        write_length_prefixed(io, data).await?;
+        io.close().await?;
+
+        Ok(())
+    }
+}
+
+/// Select the peers to dial from the peers that were received in peer
+/// exchanges. Peers that this node is already connected to are skipped,
+/// the `preferred_peers`, like the authorized nodes, are dialed first and
+/// the others are picked at random, up to `max_dials` peers.
+pub fn select_peers_to_dial(
+    received_peers: Vec<PeerAddresses>,
+    local_peer_id: &PeerId,
+    connected_peers: &HashSet<PeerId>,
+    preferred_peers: &HashSet<PeerId>,
+    max_dials: usize,
+) -> Vec<PeerAddresses> {
+    let mut candidates: HashMap<PeerId, Vec<Multiaddr>> = HashMap::new();
+    for peer in received_peers {
+        if peer.peer_id == *local_peer_id || connected_peers.contains(&peer.peer_id) {
+            continue;
+        }
+        let addresses = candidates.entry(peer.peer_id).or_default();
+        for address in peer.addresses {
+            if !addresses.contains(&address) {
+                addresses.push(address);
+            }
+        }
+    }
+
+    let mut candidates: Vec<PeerAddresses> = candidates
+        .into_iter()
+        .filter(|(_, addresses)| !addresses.is_empty())
+        .map(|(peer_id, addresses)| PeerAddresses { peer_id, addresses })
+        .collect();
+    candidates.shuffle(&mut rand::thread_rng());
+    // a stable sort keeps the random order within both groups
+    candidates.sort_by_key(|peer| !preferred_peers.contains(&peer.peer_id));
+    candidates.truncate(max_dials);
+    candidates
+}
+
+#[cfg(test)]
+#[cfg(not(tarpaulin_include))]
+mod tests {
+    use super::*;
+
+    fn peer_addresses(peer_id: PeerId, port: u16) -> PeerAddresses {
+        PeerAddresses {
+            peer_id,
+            addresses: vec![format!("/ip4/10.0.0.1/tcp/{}", port).parse().unwrap()],
+        }
+    }
+
+    #[test]
+    fn test_select_peers_to_dial_skips_connected_peers() {
+        let local_peer_id = PeerId::random();
+        let connected_peer_id = PeerId::random();
+        let new_peer_id = PeerId::random();
+
+        let selected = select_peers_to_dial(
+            vec![
+                peer_addresses(local_peer_id, 1000),
+                peer_addresses(connected_peer_id, 1001),
+                peer_addresses(new_peer_id, 1002),
+                peer_addresses(new_peer_id, 1003),
+                peer_addresses(new_peer_id, 1002),
+            ],
+            &local_peer_id,
+            &HashSet::from([connected_peer_id]),
+            &HashSet::new(),
+            PEER_EXCHANGE_SAMPLE_SIZE,
+        );
+
+        assert_eq!(
+            selected,
+            vec![PeerAddresses {
+                peer_id: new_peer_id,
+                addresses: vec![
+                    "/ip4/10.0.0.1/tcp/1002".parse().unwrap(),
+                    "/ip4/10.0.0.1/tcp/1003".parse().unwrap()
+                ],
+            }]
+        );
+    }
+
+    #[test]
+    fn test_select_peers_to_dial_prefers_authorized_nodes() {
+        let local_peer_id = PeerId::random();
+        let authorized_peer_ids: Vec<PeerId> = (0..2).map(|_| PeerId::random()).collect();
+        let received_peers: Vec<PeerAddresses> = (0..10)
+            .map(|_| PeerId::random())
+            .chain(authorized_peer_ids.iter().copied())
+            .enumerate()
+            .map(|(i, peer_id)| peer_addresses(peer_id, 2000 + i as u16))
+            .collect();
+
+        let selected = select_peers_to_dial(
+            received_peers,
+            &local_peer_id,
+            &HashSet::new(),
+            &authorized_peer_ids.iter().copied().collect(),
+            3,
+        );
+
+        assert_eq!(selected.len(), 3);
+        let mut preferred: Vec<PeerId> = selected[..2].iter().map(|peer| peer.peer_id).collect();
+        preferred.sort();
+        let mut expected = authorized_peer_ids;
+        expected.sort();
+        assert_eq!(preferred, expected);
+    }
+}
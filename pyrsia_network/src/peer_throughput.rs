@@ -0,0 +1,130 @@
+/*
+   Copyright 2021 JFrog Ltd
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+*/
+
+use libp2p::PeerId;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// The time every transfer gets on top of the time the expected number of
+/// bytes takes, to cover the round trip and the lookup of the artifact.
+pub const MIN_TRANSFER_TIMEOUT: Duration = Duration::from_secs(5);
+/// No transfer gets more time than this, however slow the peer.
+pub const MAX_TRANSFER_TIMEOUT: Duration = Duration::from_secs(600);
+/// A transfer that makes no progress for this long is aborted, even when
+/// its timeout did not expire yet.
+pub const TRANSFER_STALL_TIMEOUT: Duration = Duration::from_secs(30);
+/// The throughput that is assumed for peers without measured transfers, in
+/// bytes per second.
+const DEFAULT_THROUGHPUT: f64 = 256.0 * 1024.0;
+/// The throughput of a peer is never assumed to be lower than this, so that
+/// a single slow transfer does not make the timeouts excessive.
+const MIN_THROUGHPUT: f64 = 16.0 * 1024.0;
+/// The expected duration of a transfer is multiplied by this factor, to
+/// allow for variations in throughput.
+const TIMEOUT_FACTOR: f64 = 3.0;
+/// The weight of the latest transfer in the measured throughput of a peer.
+const THROUGHPUT_SMOOTHING: f64 = 0.3;
+/// Transfers smaller than this are dominated by latency and are not used to
+/// measure throughput.
+const MIN_MEASURED_BYTES: u64 = 64 * 1024;
+
+/// The measured throughput of the artifact transfers from every peer, a
+/// moving average in bytes per second. It is used to give every transfer a
+/// timeout that fits the number of bytes that is expected and the peer that
+/// sends them.
+#[derive(Clone, Debug, Default)]
+pub struct PeerThroughput {
+    throughput: Arc<Mutex<HashMap<PeerId, f64>>>,
+}
+
+impl PeerThroughput {
+    /// Record that `bytes` were received from `peer_id` in `elapsed`.
+    pub fn record(&self, peer_id: &PeerId, bytes: u64, elapsed: Duration) {
+        if bytes < MIN_MEASURED_BYTES || elapsed.is_zero() {
+            return;
+        }
+        let measured = bytes as f64 / elapsed.as_secs_f64();
+        self.throughput
+            .lock()
+            .unwrap()
+            .entry(*peer_id)
+            .and_modify(|throughput| *throughput += THROUGHPUT_SMOOTHING * (measured - *throughput))
+            .or_insert(measured);
+    }
+
+    /// The measured throughput of `peer_id` in bytes per second, if any
+    /// transfer from it was measured.
+    pub fn get(&self, peer_id: &PeerId) -> Option<f64> {
+        self.throughput.lock().unwrap().get(peer_id).copied()
+    }
+
+    /// The timeout of a transfer of `expected_bytes` from `peer_id`.
+    pub fn transfer_timeout(&self, peer_id: &PeerId, expected_bytes: u64) -> Duration {
+        transfer_timeout(expected_bytes, self.get(peer_id))
+    }
+}
+
+/// The timeout of a transfer of `expected_bytes` from a peer with the
+/// specified `throughput` in bytes per second, or an unknown throughput.
+pub fn transfer_timeout(expected_bytes: u64, throughput: Option<f64>) -> Duration {
+    let throughput = throughput.unwrap_or(DEFAULT_THROUGHPUT).max(MIN_THROUGHPUT);
+    let expected_secs = expected_bytes as f64 / throughput * TIMEOUT_FACTOR;
+    (MIN_TRANSFER_TIMEOUT + Duration::from_secs_f64(expected_secs)).min(MAX_TRANSFER_TIMEOUT)
+}
+
+#[cfg(test)]
+#[cfg(not(tarpaulin_include))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transfer_timeout() {
+        assert_eq!(transfer_timeout(0, None), MIN_TRANSFER_TIMEOUT);
+        assert_eq!(
+            transfer_timeout(4 * 1024 * 1024, None),
+            Duration::from_secs(53)
+        );
+        assert_eq!(
+            transfer_timeout(4 * 1024 * 1024, Some(4.0 * 1024.0 * 1024.0)),
+            Duration::from_secs(8)
+        );
+        assert_eq!(
+            transfer_timeout(4 * 1024 * 1024 * 1024, Some(1.0)),
+            MAX_TRANSFER_TIMEOUT
+        );
+    }
+
+    #[test]
+    fn test_record_throughput() {
+        let peer_throughput = PeerThroughput::default();
+        let peer_id = PeerId::random();
+
+        peer_throughput.record(&peer_id, 1024, Duration::from_millis(1));
+        assert_eq!(peer_throughput.get(&peer_id), None);
+
+        peer_throughput.record(&peer_id, 1024 * 1024, Duration::from_secs(1));
+        assert_eq!(peer_throughput.get(&peer_id), Some(1024.0 * 1024.0));
+
+        peer_throughput.record(&peer_id, 2 * 1024 * 1024, Duration::from_secs(1));
+        let throughput = peer_throughput.get(&peer_id).unwrap();
+        assert!((throughput - 1.3 * 1024.0 * 1024.0).abs() < 1.0);
+        assert!(
+            peer_throughput.transfer_timeout(&peer_id, 4 * 1024 * 1024)
+                < transfer_timeout(4 * 1024 * 1024, None)
+        );
+    }
+}
@@ -0,0 +1,225 @@
+/*
+   Copyright 2021 JFrog Ltd
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+*/
+
+use crate::peer_alias::AGENT_VERSION_PREFIX;
+use libp2p::PeerId;
+use log::warn;
+pub use semver::Version;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use thiserror::Error;
+
+const UNKNOWN_VERSION: &str = "unknown";
+
+/// The protocol features that can be refused to peers that run a version
+/// older than the minimum version.
+#[derive(
+    Clone, Copy, Debug, Eq, Hash, PartialEq, strum_macros::Display, strum_macros::EnumString,
+)]
+#[strum(serialize_all = "kebab-case")]
+pub enum ProtocolFeature {
+    /// requesting artifacts and chunks of artifacts
+    ArtifactExchange,
+    /// requesting builds from authorized nodes
+    BuildRequests,
+    /// requesting the status of builds from authorized nodes
+    BuildStatus,
+    /// requesting blocks of the blockchain
+    BlockchainSync,
+}
+
+#[derive(Debug, Error, Eq, PartialEq)]
+#[error(
+    "Peer {peer_id} runs version {version}, {feature} requires at least version {minimum_version}"
+)]
+pub struct IncompatiblePeer {
+    pub peer_id: String,
+    pub version: String,
+    pub minimum_version: String,
+    pub feature: ProtocolFeature,
+}
+
+/// The oldest version of the software that peers are expected to run, and
+/// the protocol features that are refused to peers running an older one.
+/// Talking to older peers is only logged for the other features.
+#[derive(Clone, Debug, Default)]
+pub struct CompatibilityGate {
+    pub minimum_version: Option<Version>,
+    pub refused_features: HashSet<ProtocolFeature>,
+}
+
+/// A registry of the software versions of peers. Peers advertise their
+/// version in the agent version of the identify protocol.
+#[derive(Clone, Debug, Default)]
+pub struct PeerVersions {
+    compatibility_gate: CompatibilityGate,
+    versions: Arc<Mutex<HashMap<PeerId, Version>>>,
+}
+
+impl PeerVersions {
+    pub fn with_compatibility_gate(mut self, compatibility_gate: CompatibilityGate) -> Self {
+        self.compatibility_gate = compatibility_gate;
+        self
+    }
+
+    /// The version of this node.
+    pub fn own_version() -> String {
+        env!("CARGO_PKG_VERSION").to_owned()
+    }
+
+    /// Record the version that `peer_id` advertised in its agent version,
+    /// and warn when it is older than the minimum version.
+    pub fn record_agent_version(&self, peer_id: &PeerId, agent_version: &str) {
+        let version = agent_version
+            .split_whitespace()
+            .next()
+            .and_then(|token| token.strip_prefix(AGENT_VERSION_PREFIX))
+            .and_then(|version| Version::parse(version).ok());
+
+        let mut versions = self.versions.lock().unwrap();
+        let version = match version {
+            Some(version) => version,
+            None => {
+                versions.remove(peer_id);
+                return;
+            }
+        };
+        if versions.get(peer_id) == Some(&version) {
+            return;
+        }
+
+        match &self.compatibility_gate.minimum_version {
+            Some(minimum_version) if version < *minimum_version => warn!(
+                "Peer {} runs version {}, which is older than the minimum version {}",
+                peer_id, version, minimum_version
+            ),
+            _ => {}
+        }
+        versions.insert(*peer_id, version);
+    }
+
+    /// The version that `peer_id` advertised, if it is known.
+    pub fn version(&self, peer_id: &PeerId) -> Option<Version> {
+        self.versions.lock().unwrap().get(peer_id).cloned()
+    }
+
+    /// Verify that `feature` may be used with `peer_id`. Peers of which the
+    /// version is not known yet are not refused.
+    pub fn check(
+        &self,
+        peer_id: &PeerId,
+        feature: ProtocolFeature,
+    ) -> Result<(), IncompatiblePeer> {
+        let minimum_version = match &self.compatibility_gate.minimum_version {
+            Some(minimum_version)
+                if self.compatibility_gate.refused_features.contains(&feature) =>
+            {
+                minimum_version
+            }
+            _ => return Ok(()),
+        };
+
+        match self.version(peer_id) {
+            Some(version) if version < *minimum_version => Err(IncompatiblePeer {
+                peer_id: peer_id.to_string(),
+                version: version.to_string(),
+                minimum_version: minimum_version.to_string(),
+                feature,
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// The number of `peers` per version. Peers that did not advertise a
+    /// version are counted as unknown.
+    pub fn histogram<'a>(
+        &self,
+        peers: impl Iterator<Item = &'a PeerId>,
+    ) -> BTreeMap<String, usize> {
+        let versions = self.versions.lock().unwrap();
+        let mut histogram = BTreeMap::new();
+        for peer_id in peers {
+            let version = versions
+                .get(peer_id)
+                .map_or_else(|| UNKNOWN_VERSION.to_owned(), Version::to_string);
+            *histogram.entry(version).or_insert(0) += 1;
+        }
+        histogram
+    }
+}
+
+#[cfg(test)]
+#[cfg(not(tarpaulin_include))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_agent_version() {
+        let peer_versions = PeerVersions::default();
+        let peer_id = PeerId::random();
+
+        peer_versions.record_agent_version(&peer_id, "pyrsia/0.2.5 alias/build-node-1");
+        assert_eq!(peer_versions.version(&peer_id), Some(Version::new(0, 2, 5)));
+
+        peer_versions.record_agent_version(&peer_id, "rust-libp2p/0.40.0");
+        assert_eq!(peer_versions.version(&peer_id), None);
+    }
+
+    #[test]
+    fn test_histogram() {
+        let peer_versions = PeerVersions::default();
+        let peers = [PeerId::random(), PeerId::random(), PeerId::random()];
+        peer_versions.record_agent_version(&peers[0], "pyrsia/0.2.5");
+        peer_versions.record_agent_version(&peers[1], "pyrsia/0.2.5");
+
+        assert_eq!(
+            peer_versions.histogram(peers.iter()),
+            BTreeMap::from([("0.2.5".to_owned(), 2), (UNKNOWN_VERSION.to_owned(), 1)])
+        );
+    }
+
+    #[test]
+    fn test_compatibility_gate() {
+        let peer_versions = PeerVersions::default().with_compatibility_gate(CompatibilityGate {
+            minimum_version: Some(Version::new(0, 3, 0)),
+            refused_features: HashSet::from([ProtocolFeature::BuildRequests]),
+        });
+        let old_peer_id = PeerId::random();
+        let new_peer_id = PeerId::random();
+        let unknown_peer_id = PeerId::random();
+        peer_versions.record_agent_version(&old_peer_id, "pyrsia/0.2.5");
+        peer_versions.record_agent_version(&new_peer_id, "pyrsia/0.3.0");
+
+        assert_eq!(
+            peer_versions.check(&old_peer_id, ProtocolFeature::BuildRequests),
+            Err(IncompatiblePeer {
+                peer_id: old_peer_id.to_string(),
+                version: "0.2.5".to_owned(),
+                minimum_version: "0.3.0".to_owned(),
+                feature: ProtocolFeature::BuildRequests,
+            })
+        );
+        assert!(peer_versions
+            .check(&old_peer_id, ProtocolFeature::ArtifactExchange)
+            .is_ok());
+        assert!(peer_versions
+            .check(&new_peer_id, ProtocolFeature::BuildRequests)
+            .is_ok());
+        assert!(peer_versions
+            .check(&unknown_peer_id, ProtocolFeature::BuildRequests)
+            .is_ok());
+    }
+}
@@ -0,0 +1,198 @@
+/*
+   Copyright 2021 JFrog Ltd
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+*/
+
+use libp2p::kad::{self, QueryResult};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Kademlia queries that take longer than this are logged as slow.
+pub const SLOW_QUERY_THRESHOLD: Duration = Duration::from_secs(10);
+
+/// The kinds of Kademlia queries that the node starts.
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Deserialize,
+    Eq,
+    Hash,
+    Ord,
+    PartialEq,
+    PartialOrd,
+    Serialize,
+    strum_macros::Display,
+)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum QueryKind {
+    Bootstrap,
+    GetProviders,
+    StartProviding,
+    Other,
+}
+
+impl QueryKind {
+    pub fn of(result: &QueryResult) -> QueryKind {
+        match result {
+            QueryResult::Bootstrap(_) => QueryKind::Bootstrap,
+            QueryResult::GetProviders(_) => QueryKind::GetProviders,
+            QueryResult::StartProviding(_) => QueryKind::StartProviding,
+            _ => QueryKind::Other,
+        }
+    }
+}
+
+/// Whether a finished query succeeded.
+pub fn query_succeeded(result: &QueryResult) -> bool {
+    match result {
+        QueryResult::Bootstrap(result) => result.is_ok(),
+        QueryResult::GetClosestPeers(result) => result.is_ok(),
+        QueryResult::GetProviders(result) => result.is_ok(),
+        QueryResult::StartProviding(result) | QueryResult::RepublishProvider(result) => {
+            result.is_ok()
+        }
+        QueryResult::GetRecord(result) => result.is_ok(),
+        QueryResult::PutRecord(result) | QueryResult::RepublishRecord(result) => result.is_ok(),
+    }
+}
+
+/// The totals of the finished Kademlia queries of one kind. Kademlia
+/// queries are iterative, so the number of peers that a query sent a request
+/// to is reported in place of a hop count.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct QueryKindStats {
+    pub queries: u64,
+    pub failures: u64,
+    pub total_duration_ms: u64,
+    pub max_duration_ms: u64,
+    pub total_requests: u64,
+    pub max_requests: u64,
+}
+
+impl QueryKindStats {
+    pub fn record(&mut self, duration: Duration, requests: u32, succeeded: bool) {
+        let duration_ms = duration.as_millis() as u64;
+        self.queries += 1;
+        if !succeeded {
+            self.failures += 1;
+        }
+        self.total_duration_ms += duration_ms;
+        self.max_duration_ms = self.max_duration_ms.max(duration_ms);
+        self.total_requests += requests as u64;
+        self.max_requests = self.max_requests.max(requests as u64);
+    }
+
+    pub fn average_duration_ms(&self) -> u64 {
+        self.total_duration_ms
+            .checked_div(self.queries)
+            .unwrap_or(0)
+    }
+
+    pub fn average_requests(&self) -> f64 {
+        if self.queries == 0 {
+            0.0
+        } else {
+            self.total_requests as f64 / self.queries as f64
+        }
+    }
+}
+
+/// The metrics of the Kademlia queries of this node, per kind of query.
+/// They are recorded by the event loop and shared with the clients.
+#[derive(Clone, Debug, Default)]
+pub struct QueryMetrics {
+    stats: Arc<Mutex<BTreeMap<QueryKind, QueryKindStats>>>,
+}
+
+impl QueryMetrics {
+    pub fn record(&self, kind: QueryKind, stats: &kad::QueryStats, succeeded: bool) {
+        self.stats.lock().unwrap().entry(kind).or_default().record(
+            stats.duration().unwrap_or_default(),
+            stats.num_requests(),
+            succeeded,
+        );
+    }
+
+    pub fn snapshot(&self) -> BTreeMap<QueryKind, QueryKindStats> {
+        self.stats.lock().unwrap().clone()
+    }
+}
+
+/// One progress event of a traced provider lookup.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct LookupStep {
+    pub step: usize,
+    pub elapsed_ms: u64,
+    /// The providers that were found in this step.
+    pub providers: Vec<String>,
+    /// The number of peers that the lookup sent a request to so far, and how
+    /// many of them answered or failed.
+    pub requests: u32,
+    pub successes: u32,
+    pub failures: u32,
+}
+
+/// The step by step trace of a lookup of the providers of an artifact.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct LookupTrace {
+    pub artifact_id: String,
+    pub duration_ms: u64,
+    pub providers: Vec<String>,
+    pub steps: Vec<LookupStep>,
+    /// Why the lookup ended early, e.g. because it timed out.
+    pub error: Option<String>,
+}
+
+#[cfg(test)]
+#[cfg(not(tarpaulin_include))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_query_kind_stats_record() {
+        let mut stats = QueryKindStats::default();
+        assert_eq!(stats.average_duration_ms(), 0);
+
+        stats.record(Duration::from_millis(300), 4, true);
+        stats.record(Duration::from_millis(1500), 9, false);
+        stats.record(Duration::from_millis(600), 2, true);
+
+        assert_eq!(
+            stats,
+            QueryKindStats {
+                queries: 3,
+                failures: 1,
+                total_duration_ms: 2400,
+                max_duration_ms: 1500,
+                total_requests: 15,
+                max_requests: 9,
+            }
+        );
+        assert_eq!(stats.average_duration_ms(), 800);
+        assert_eq!(stats.average_requests(), 5.0);
+    }
+
+    #[test]
+    fn test_query_kind_serialization() {
+        assert_eq!(
+            serde_json::to_string(&QueryKind::GetProviders).unwrap(),
+            "\"get_providers\""
+        );
+        assert_eq!(QueryKind::StartProviding.to_string(), "start_providing");
+    }
+}
@@ -14,8 +14,29 @@
    limitations under the License.
 */
 
-use clap::Parser;
-use libp2p::Multiaddr;
+use clap::{Parser, ValueEnum};
+use libp2p::{Multiaddr, PeerId};
+use pyrsia::accounting_service::service::{UsageAccounting, UsageLimits};
+use pyrsia::artifact_service::authorization::ArtifactRequestPolicy;
+use pyrsia::artifact_service::budget::TransferBudget;
+use pyrsia::artifact_service::load_test::LoadTestConfig;
+use pyrsia::artifact_service::mmap::MmapConfig;
+use pyrsia::artifact_service::model::{FetchRetryPolicy, PackageType};
+use pyrsia::artifact_service::provide::ProvideSchedule;
+use pyrsia::artifact_service::quota::SoleCopyPolicy;
+use pyrsia::blockchain_service::pruning::BlockPruning;
+use pyrsia::build_service::history::BuildHistoryRetention;
+use pyrsia::build_service::mapping::internal::InternalPackages;
+use pyrsia::build_service::model::PartialBuildPolicy;
+use pyrsia::build_service::queue::BuildQueue;
+use pyrsia::network::peer_capabilities::{NodeCapabilities, NodeRole};
+use pyrsia::network::peer_version::{CompatibilityGate, ProtocolFeature, Version};
+use pyrsia::util::http_server::HttpServerConfig;
+use pyrsia::util::reverse_proxy::ReverseProxyConfig;
+use std::net::IpAddr;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Duration;
 
 const DEFAULT_HOST: &str = "127.0.0.1";
 const DEFAULT_LISTEN_ADDRESS: &str = "/ip4/0.0.0.0/tcp/0";
@@ -25,6 +46,26 @@ const DEFAULT_MAPPING_SERVICE_ENDPOINT: &str =
 const DEFAULT_PIPELINE_SERVICE_ENDPOINT: &str = "http://localhost:8080";
 const DEFAULT_PORT: &str = "7888";
 const DEFAULT_BOOTSTRAP_URL: &str = "http://boot.pyrsia.link/status";
+const DEFAULT_PEER_EXCHANGE_INTERVAL_SECS: &str = "60";
+const DEFAULT_PEER_EXCHANGE_MAX_DIALS: &str = "4";
+const DEFAULT_AVAILABILITY_SAMPLE_SIZE: &str = "10";
+const DEFAULT_PROVIDE_BATCH_SIZE: &str = "256";
+const DEFAULT_PROVIDE_RATE: &str = "100";
+const DEFAULT_PROVIDE_JITTER_MS: &str = "1000";
+const DEFAULT_HTTP2_MAX_CONCURRENT_STREAMS: &str = "256";
+const DEFAULT_FETCH_RETRIES: &str = "3";
+const DEFAULT_FETCH_BACKOFF_MS: &str = "500";
+const DEFAULT_FETCH_MAX_BACKOFF_MS: &str = "8000";
+const DEFAULT_BUILD_HISTORY_MAX_AGE_DAYS: &str = "90";
+const DEFAULT_BUILD_HISTORY_MAX_BUILDS: &str = "10000";
+const DEFAULT_S3_REGION: &str = "us-east-1";
+const DEFAULT_MMAP_MAX_ARTIFACTS: &str = "32";
+const DEFAULT_TRANSPARENCY_LOG_CACHE_SIZE: &str = "1024";
+const DEFAULT_MMAP_SPOT_CHECK_INTERVAL_SECS: &str = "60";
+const DEFAULT_LOAD_TEST_RATE: &str = "10";
+const DEFAULT_LOAD_TEST_DURATION_SECS: &str = "60";
+const DEFAULT_LOAD_TEST_ARTIFACT_SIZE: &str = "64 KB";
+const DEFAULT_LOAD_TEST_PULL_RATIO: &str = "0.8";
 
 /// Application to connect to and participate in the Pyrsia network
 #[derive(Clone, Debug, Parser)]
@@ -36,15 +77,48 @@ pub struct PyrsiaNodeArgs {
     /// The address to listen to for incoming requests from other pyrsia nodes
     #[clap(long = "listen", short = 'L', default_value = DEFAULT_LISTEN_ADDRESS)]
     pub listen_address: Multiaddr,
-    /// the port to listen to for the Docker API
+    /// the port to listen to for the package manager facades and the node API
     #[clap(long, short, default_value = DEFAULT_PORT)]
     pub port: String,
+    /// A package manager facade that is not served by this node (can be repeated)
+    #[clap(long = "disable-facade", value_enum)]
+    pub disabled_facades: Vec<FacadeArg>,
+    /// Serve the Docker facade on this port instead of the main port
+    #[clap(long)]
+    pub docker_port: Option<u16>,
+    /// Serve the Maven facade on this port instead of the main port
+    #[clap(long)]
+    pub maven_port: Option<u16>,
+    /// Serve the node API on this port instead of the main port
+    #[clap(long)]
+    pub node_api_port: Option<u16>,
+    /// The external path prefix that a reverse proxy serves this node under (e.g. /pyrsia)
+    #[clap(long, default_value = "")]
+    pub base_path: String,
+    /// The address of a reverse proxy whose X-Forwarded-For header is honored (can be repeated)
+    #[clap(long = "trusted-proxy")]
+    pub trusted_proxies: Vec<IpAddr>,
+    /// The maximum number of requests that a client may send at once on a single HTTP/2 connection
+    #[clap(long, default_value = DEFAULT_HTTP2_MAX_CONCURRENT_STREAMS)]
+    pub http2_max_concurrent_streams: u32,
+    /// Ping idle HTTP/2 connections after this number of seconds, to close connections of clients that went away
+    #[clap(long)]
+    pub http2_keep_alive_secs: Option<u64>,
     /// An address to connect with another Pyrsia Node (eg /ip4/127.0.0.1/tcp/45153/p2p/12D3KooWKsHbKbcVgyiRRgeXGCK4bp3MngnSU7ioeKTfQzd18B2v)
     #[clap(long, short = 'P')]
     pub peer: Option<Multiaddr>,
     /// Initialization mode, used only for the first authorized node in the Pyrsia network to initialize the Pyrsia network
     #[clap(long)]
     pub init_blockchain: bool,
+    /// A JSON file with the id and name of the Pyrsia network and its initial authorized nodes, the node only connects to peers and accepts blocks of the same network
+    #[clap(long)]
+    pub genesis_config: Option<PathBuf>,
+    /// Instead of pulling the whole blockchain from the peer, import a signed snapshot of its transparency logs and only pull the blocks after it
+    #[clap(long, conflicts_with = "init_blockchain")]
+    pub fast_sync: bool,
+    /// A blockchain export, created with `pyrsia block export`, whose blocks are verified and imported at startup, e.g. to restore a backup or to seed a node without access to the network
+    #[clap(long, conflicts_with = "fast_sync")]
+    pub import_chain: Option<PathBuf>,
     /// An address to use for probing AutoNAT connections
     #[clap(long, short = 'R')]
     pub probe: Option<Multiaddr>,
@@ -59,7 +133,542 @@ pub struct PyrsiaNodeArgs {
     /// The http endpoint where the mapping service will fetch mapping info from.
     #[clap(long, default_value = DEFAULT_MAPPING_SERVICE_ENDPOINT)]
     pub mapping_service_endpoint: String,
+    /// A package specific id pattern of internal packages, which are only built from the internal mapping service (can be repeated, e.g. com.myorg.*)
+    #[clap(long = "internal-package")]
+    pub internal_packages: Vec<String>,
+    /// The http endpoint of the mapping service that maps the internal packages.
+    #[clap(long)]
+    pub internal_mapping_service_endpoint: Option<String>,
     /// The http endpoint of the external build pipeline that the pipeline service will use to communicate with.
     #[clap(long, default_value = DEFAULT_PIPELINE_SERVICE_ENDPOINT)]
     pub pipeline_service_endpoint: String,
+    /// A registry mirror that caches the base images of docker builds, so that the builds on this node share them instead of each pulling them from Docker Hub (e.g. http://localhost:5000)
+    #[clap(long)]
+    pub base_image_cache: Option<String>,
+    /// The policy that decides which peers may request artifacts from this node.
+    #[clap(long, value_enum, default_value_t = ArtifactRequestPolicyArg::Public)]
+    pub artifact_request_policy: ArtifactRequestPolicyArg,
+    /// A peer that is allowed to request restricted artifacts (can be repeated)
+    #[clap(long = "allowed-peer")]
+    pub allowed_peers: Vec<PeerId>,
+    /// A package specific artifact id prefix that is restricted to the allowed peers when using the namespace policy (can be repeated)
+    #[clap(long = "restricted-namespace")]
+    pub restricted_namespaces: Vec<String>,
+    /// Publish the successfully produced artifacts of builds of which some artifacts failed
+    #[clap(long)]
+    pub publish_partial_builds: bool,
+    /// Remove builds from the build history after this number of days
+    #[clap(long, default_value = DEFAULT_BUILD_HISTORY_MAX_AGE_DAYS)]
+    pub build_history_max_age_days: u64,
+    /// The maximum number of builds that are kept in the build history
+    #[clap(long, default_value = DEFAULT_BUILD_HISTORY_MAX_BUILDS)]
+    pub build_history_max_builds: usize,
+    /// The maximum number of builds that run at the same time, more builds are queued until a running build finishes
+    #[clap(long)]
+    pub max_concurrent_builds: Option<usize>,
+    /// A JSON file with the packages for which new upstream versions are built automatically
+    #[clap(long)]
+    pub version_watch_config: Option<PathBuf>,
+    /// A JSON file with the hooks that run at points in the lifecycle of artifacts
+    #[clap(long)]
+    pub lifecycle_hooks: Option<PathBuf>,
+    /// A JSON file with the namespaces of docker images whose tags cannot be moved to another manifest once published, except for the listed mutable tags (e.g. latest)
+    #[clap(long)]
+    pub tag_immutability_policy: Option<PathBuf>,
+    /// A transparency log export in JSON lines or CBOR that is imported at startup, e.g. to seed a new node
+    #[clap(long)]
+    pub import_log: Option<PathBuf>,
+    /// The number of transparency log lookups of artifacts that are cached in memory, 0 disables the cache
+    #[clap(long, default_value = DEFAULT_TRANSPARENCY_LOG_CACHE_SIZE)]
+    pub transparency_log_cache_size: usize,
+    /// The database that the transparency logs are stored in, the postgres backend connects to PYRSIA_TRANSPARENCY_LOG_DATABASE_URL
+    #[clap(long, value_enum, default_value_t = LogStoreBackendArg::Sqlite)]
+    pub transparency_log_backend: LogStoreBackendArg,
+    /// Where the artifacts of this node are stored
+    #[clap(long, value_enum, default_value_t = StorageBackendArg::Local)]
+    pub storage_backend: StorageBackendArg,
+    /// The endpoint of the S3 compatible object storage when using the s3 storage backend (e.g. https://s3.eu-west-1.amazonaws.com)
+    #[clap(long)]
+    pub s3_endpoint: Option<String>,
+    /// The bucket that the artifacts are stored in when using the s3 storage backend
+    #[clap(long)]
+    pub s3_bucket: Option<String>,
+    /// The region of the bucket
+    #[clap(long, default_value = DEFAULT_S3_REGION)]
+    pub s3_region: String,
+    /// The prefix of the object keys of the artifacts in the bucket (e.g. pyrsia/artifacts/)
+    #[clap(long, default_value = "")]
+    pub s3_key_prefix: String,
+    /// Encrypt the stored artifacts with a key that is derived from the node's keypair
+    #[clap(long)]
+    pub encrypt_artifacts: bool,
+    /// The maximum size of the stored artifacts (e.g. 50 GB), the least recently used artifacts are evicted when it is exceeded
+    #[clap(long, value_parser = parse_byte_size)]
+    pub max_storage_size: Option<u64>,
+    /// A package whose artifacts are never evicted, as <package type>:<package specific id> (e.g. Docker:alpine:3.16, can be repeated)
+    #[clap(long = "pinned-package", value_parser = parse_pinned_package)]
+    pub pinned_packages: Vec<(PackageType, String)>,
+    /// What happens before the last copy of an artifact on the p2p network is evicted
+    #[clap(long, value_enum, default_value_t = SoleCopyPolicyArg::Replicate)]
+    pub sole_copy_policy: SoleCopyPolicyArg,
+    /// Serve stored artifacts of at least this size (e.g. 16 MB) from memory-mapped files, not with encrypted or S3 storage
+    #[clap(long, value_parser = parse_byte_size)]
+    pub mmap_min_artifact_size: Option<u64>,
+    /// The maximum number of artifacts that are memory-mapped at the same time
+    #[clap(long, default_value = DEFAULT_MMAP_MAX_ARTIFACTS)]
+    pub mmap_max_artifacts: usize,
+    /// Check a random page of a memory-mapped artifact against its checksum when it was not checked for this number of seconds
+    #[clap(long, default_value = DEFAULT_MMAP_SPOT_CHECK_INTERVAL_SECS)]
+    pub mmap_spot_check_interval_secs: u64,
+    /// The maximum number of artifact files that are open for transfers at the same time, more requests are refused with 503
+    #[clap(long)]
+    pub max_open_artifact_files: Option<usize>,
+    /// The maximum size of the artifacts that are transferred at the same time (e.g. 2 GB), more requests are refused with 503
+    #[clap(long, value_parser = parse_byte_size)]
+    pub max_in_flight_transfer_size: Option<u64>,
+    /// The maximum number of requests per minute of every API key, more requests are refused with 429
+    #[clap(long)]
+    pub api_key_requests_per_minute: Option<u64>,
+    /// The maximum number of build minutes per day of every API key, more build requests are refused with 429
+    #[clap(long)]
+    pub api_key_build_minutes_per_day: Option<u64>,
+    /// The maximum size of the responses per day (e.g. 10 GB) of every API key, more requests are refused with 429
+    #[clap(long, value_parser = parse_byte_size)]
+    pub api_key_bytes_served_per_day: Option<u64>,
+    /// The maximum number of requests per minute for the packages of every namespace, more requests are refused with 429
+    #[clap(long)]
+    pub namespace_requests_per_minute: Option<u64>,
+    /// The maximum number of build minutes per day of the packages of every namespace, more build requests are refused with 429
+    #[clap(long)]
+    pub namespace_build_minutes_per_day: Option<u64>,
+    /// The maximum size of the responses per day (e.g. 10 GB) for the packages of every namespace, more requests are refused with 429
+    #[clap(long, value_parser = parse_byte_size)]
+    pub namespace_bytes_served_per_day: Option<u64>,
+    /// Check the hashes of the stored artifacts against their transparency logs at this interval in seconds, corrupt artifacts are removed
+    #[clap(long)]
+    pub scrub_interval_secs: Option<u64>,
+    /// Fetch a valid copy of the corrupt artifacts that are found by a scrub from other peers
+    #[clap(long, requires = "scrub_interval_secs")]
+    pub scrub_refetch: bool,
+    /// Try to retrieve a sample of the artifacts in the transparency log from the network at this interval in seconds, to report their availability
+    #[clap(long)]
+    pub availability_sample_interval_secs: Option<u64>,
+    /// The number of artifacts that are sampled by every availability check
+    #[clap(long, default_value = DEFAULT_AVAILABILITY_SAMPLE_SIZE, requires = "availability_sample_interval_secs")]
+    pub availability_sample_size: usize,
+    /// Sign a checkpoint of the transparency log at this interval in seconds, so monitors can verify that its history is not rewritten
+    #[clap(long)]
+    pub checkpoint_interval_secs: Option<u64>,
+    /// Keep other authorized nodes from building a package that a node claimed to build for this number of seconds, unless its artifacts are published earlier (defaults to an hour)
+    #[clap(long)]
+    pub build_claim_ttl_secs: Option<u64>,
+    /// Keep only this number of the most recent blocks of the blockchain locally, older blocks are pruned once their transparency logs are applied
+    #[clap(long)]
+    pub keep_blocks: Option<u64>,
+    /// Export the pruned blocks to this directory before they are removed, e.g. a mount of cold storage
+    #[clap(long, requires = "keep_blocks")]
+    pub block_archive_dir: Option<PathBuf>,
+    /// Exchange known peers with connected peers at this interval in seconds, 0 disables peer exchange
+    #[clap(long, default_value = DEFAULT_PEER_EXCHANGE_INTERVAL_SECS)]
+    pub peer_exchange_interval_secs: u64,
+    /// The maximum number of peers found by a peer exchange that are dialed at once
+    #[clap(long, default_value = DEFAULT_PEER_EXCHANGE_MAX_DIALS)]
+    pub peer_exchange_max_dials: usize,
+    /// The number of local artifacts that are provided on the network in one batch
+    #[clap(long, default_value = DEFAULT_PROVIDE_BATCH_SIZE)]
+    pub provide_batch_size: usize,
+    /// The maximum number of local artifacts that are provided per second (0 for no limit)
+    #[clap(long, default_value = DEFAULT_PROVIDE_RATE)]
+    pub provide_rate: u32,
+    /// The maximum random delay in milliseconds that is added after each batch of provided artifacts
+    #[clap(long, default_value = DEFAULT_PROVIDE_JITTER_MS)]
+    pub provide_jitter_ms: u64,
+    /// Provide all local artifacts again after this number of seconds
+    #[clap(long)]
+    pub reprovide_interval_secs: Option<u64>,
+    /// The number of times fetching an artifact from all providers is retried
+    #[clap(long, default_value = DEFAULT_FETCH_RETRIES)]
+    pub fetch_retries: u32,
+    /// The backoff in milliseconds before the first retry of a fetch, which doubles for every retry
+    #[clap(long, default_value = DEFAULT_FETCH_BACKOFF_MS)]
+    pub fetch_backoff_ms: u64,
+    /// The maximum backoff in milliseconds between two retries of a fetch
+    #[clap(long, default_value = DEFAULT_FETCH_MAX_BACKOFF_MS)]
+    pub fetch_max_backoff_ms: u64,
+    /// The oldest version of Pyrsia that peers are expected to run, talking to older peers is logged
+    #[clap(long)]
+    pub minimum_peer_version: Option<Version>,
+    /// A protocol feature that is refused to peers older than the minimum peer version (can be repeated)
+    #[clap(long = "refuse-outdated-peer-feature", value_parser = ["artifact-exchange", "build-requests", "build-status", "blockchain-sync"])]
+    pub refused_outdated_peer_features: Vec<String>,
+    /// A human-friendly name for this node that is advertised to its peers
+    #[clap(long)]
+    pub alias: Option<String>,
+    /// A URL that alerts about suspicious transparency log activity are posted to
+    #[clap(long = "alert-webhook")]
+    pub alert_webhooks: Vec<String>,
+    /// A regular expression of a secret that is masked in the logs, in addition to credentials, tokens and API keys (can be repeated)
+    #[clap(long = "redact-pattern")]
+    pub redact_patterns: Vec<String>,
+    /// A node that is expected to be authorized, other authorizations raise an alert
+    #[clap(long = "expected-authorized-node")]
+    pub expected_authorized_nodes: Vec<PeerId>,
+    /// Only follow the blockchain and the transparency log, audit every new block and raise alerts on inconsistencies, without serving the facades, storing artifacts or building
+    #[clap(long)]
+    pub monitor: bool,
+    /// The address of a trusted seed node to copy the transparency logs and the popular and pinned artifacts from on startup (eg /ip4/10.0.0.1/tcp/44000/p2p/12D3KooWKsHbKbcVgyiRRgeXGCK4bp3MngnSU7ioeKTfQzd18B2v)
+    #[clap(long)]
+    pub seed: Option<Multiaddr>,
+    /// Generate a synthetic publish and pull workload, report its throughput and error rates and exit
+    #[clap(long, hide = true)]
+    pub load_test: bool,
+    /// The number of load test operations that are started per second
+    #[clap(long, hide = true, default_value = DEFAULT_LOAD_TEST_RATE)]
+    pub load_test_rate: u32,
+    /// The duration of the load test in seconds
+    #[clap(long, hide = true, default_value = DEFAULT_LOAD_TEST_DURATION_SECS)]
+    pub load_test_duration_secs: u64,
+    /// The size of the artifacts that the load test publishes (e.g. 1 MB)
+    #[clap(long, hide = true, default_value = DEFAULT_LOAD_TEST_ARTIFACT_SIZE, value_parser = parse_byte_size)]
+    pub load_test_artifact_size: u64,
+    /// The fraction of the load test operations that are pulls
+    #[clap(long, hide = true, default_value = DEFAULT_LOAD_TEST_PULL_RATIO)]
+    pub load_test_pull_ratio: f64,
+    /// A peer that the load test pulls artifacts from, instead of pulling from this node (can be repeated)
+    #[clap(long = "load-test-peer", hide = true)]
+    pub load_test_peers: Vec<PeerId>,
+}
+
+/// The package manager facades that are served over HTTP. Each facade is
+/// routed by its own path prefix, so they can share a listener.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum FacadeArg {
+    Docker,
+    Maven,
+}
+
+/// The stores that the artifacts of the node can be kept in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum StorageBackendArg {
+    /// a directory below the artifact path
+    Local,
+    /// a bucket in S3 or an S3 compatible object storage
+    S3,
+}
+
+/// The databases that the transparency logs of the node can be kept in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum LogStoreBackendArg {
+    /// an embedded SQLite database below the artifact path
+    Sqlite,
+    /// a PostgreSQL database, requires the postgres-log-store feature
+    Postgres,
+}
+
+/// What a node does before it evicts the last copy of an artifact on the
+/// p2p network.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum SoleCopyPolicyArg {
+    /// ask the connected peers to take a replica, keep the artifact when none does
+    Replicate,
+    /// keep the artifact
+    Keep,
+    /// evict the artifact anyway
+    Evict,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum ArtifactRequestPolicyArg {
+    Public,
+    Allowlist,
+    Namespace,
+}
+
+impl PyrsiaNodeArgs {
+    pub fn main_port(&self) -> u16 {
+        self.port.parse::<u16>().unwrap()
+    }
+
+    /// The port that serves the specified facade, or `None` if the facade
+    /// is disabled. Facades without a port of their own are served on the
+    /// main port. A node in monitor mode serves no facades.
+    pub fn facade_port(&self, facade: FacadeArg) -> Option<u16> {
+        if self.monitor || self.disabled_facades.contains(&facade) {
+            return None;
+        }
+        let port = match facade {
+            FacadeArg::Docker => self.docker_port,
+            FacadeArg::Maven => self.maven_port,
+        };
+        Some(port.unwrap_or_else(|| self.main_port()))
+    }
+
+    /// The package types and roles that this node advertises to its peers:
+    /// the package types of the facades it serves, and whether it builds or
+    /// only monitors.
+    pub fn node_capabilities(&self) -> NodeCapabilities {
+        NodeCapabilities {
+            package_types: [
+                (FacadeArg::Docker, PackageType::Docker),
+                (FacadeArg::Maven, PackageType::Maven2),
+            ]
+            .into_iter()
+            .filter(|(facade, _)| self.facade_port(*facade).is_some())
+            .map(|(_, package_type)| package_type)
+            .collect(),
+            roles: [if self.monitor {
+                NodeRole::Monitor
+            } else {
+                NodeRole::Builder
+            }]
+            .into(),
+        }
+    }
+
+    pub fn node_api_listener_port(&self) -> u16 {
+        self.node_api_port.unwrap_or_else(|| self.main_port())
+    }
+
+    pub fn reverse_proxy_config(&self) -> ReverseProxyConfig {
+        ReverseProxyConfig::new(self.trusted_proxies.clone(), &self.base_path)
+    }
+
+    pub fn http_server_config(&self) -> HttpServerConfig {
+        HttpServerConfig {
+            http2_max_concurrent_streams: self.http2_max_concurrent_streams,
+            http2_keep_alive_interval: self.http2_keep_alive_secs.map(Duration::from_secs),
+        }
+    }
+
+    pub fn build_history_retention(&self) -> BuildHistoryRetention {
+        BuildHistoryRetention {
+            max_age: Duration::from_secs(self.build_history_max_age_days * 24 * 60 * 60),
+            max_builds: self.build_history_max_builds,
+        }
+    }
+
+    pub fn build_queue(&self) -> BuildQueue {
+        BuildQueue::new(self.max_concurrent_builds)
+    }
+
+    pub fn compatibility_gate(&self) -> CompatibilityGate {
+        CompatibilityGate {
+            minimum_version: self.minimum_peer_version.clone(),
+            refused_features: self
+                .refused_outdated_peer_features
+                .iter()
+                .map(|feature| ProtocolFeature::from_str(feature).unwrap())
+                .collect(),
+        }
+    }
+
+    pub fn internal_packages(&self) -> InternalPackages {
+        InternalPackages::new(&self.internal_packages)
+    }
+
+    pub fn partial_build_policy(&self) -> PartialBuildPolicy {
+        if self.publish_partial_builds {
+            PartialBuildPolicy::Publish
+        } else {
+            PartialBuildPolicy::Reject
+        }
+    }
+
+    pub fn provide_schedule(&self) -> ProvideSchedule {
+        ProvideSchedule {
+            batch_size: self.provide_batch_size,
+            max_rate: self.provide_rate,
+            max_jitter: Duration::from_millis(self.provide_jitter_ms),
+            reprovide_interval: self.reprovide_interval_secs.map(Duration::from_secs),
+        }
+    }
+
+    pub fn mmap_config(&self) -> Option<MmapConfig> {
+        self.mmap_min_artifact_size
+            .map(|mmap_min_artifact_size| MmapConfig {
+                min_size: mmap_min_artifact_size,
+                max_mapped_artifacts: self.mmap_max_artifacts,
+                spot_check_interval: Duration::from_secs(self.mmap_spot_check_interval_secs),
+            })
+    }
+
+    pub fn transfer_budget(&self) -> TransferBudget {
+        TransferBudget::new(
+            self.max_open_artifact_files,
+            self.max_in_flight_transfer_size,
+        )
+    }
+
+    pub fn usage_accounting(&self) -> UsageAccounting {
+        UsageAccounting::new(
+            UsageLimits {
+                requests_per_minute: self.api_key_requests_per_minute,
+                build_minutes_per_day: self.api_key_build_minutes_per_day,
+                bytes_served_per_day: self.api_key_bytes_served_per_day,
+            },
+            UsageLimits {
+                requests_per_minute: self.namespace_requests_per_minute,
+                build_minutes_per_day: self.namespace_build_minutes_per_day,
+                bytes_served_per_day: self.namespace_bytes_served_per_day,
+            },
+        )
+    }
+
+    pub fn fetch_retry_policy(&self) -> FetchRetryPolicy {
+        FetchRetryPolicy {
+            max_retries: self.fetch_retries,
+            initial_backoff: Duration::from_millis(self.fetch_backoff_ms),
+            max_backoff: Duration::from_millis(self.fetch_max_backoff_ms),
+        }
+    }
+
+    pub fn load_test_config(&self) -> LoadTestConfig {
+        LoadTestConfig {
+            rate: self.load_test_rate,
+            duration: Duration::from_secs(self.load_test_duration_secs),
+            artifact_size: self.load_test_artifact_size as usize,
+            pull_ratio: self.load_test_pull_ratio,
+            peers: self.load_test_peers.clone(),
+        }
+    }
+
+    pub fn sole_copy_policy(&self) -> SoleCopyPolicy {
+        match self.sole_copy_policy {
+            SoleCopyPolicyArg::Replicate => SoleCopyPolicy::Replicate,
+            SoleCopyPolicyArg::Keep => SoleCopyPolicy::Keep,
+            SoleCopyPolicyArg::Evict => SoleCopyPolicy::Evict,
+        }
+    }
+
+    pub fn block_pruning(&self) -> Option<BlockPruning> {
+        self.keep_blocks.map(|keep_blocks| {
+            let block_pruning = BlockPruning::new(keep_blocks);
+            match &self.block_archive_dir {
+                Some(block_archive_dir) => block_pruning.with_archive_path(block_archive_dir),
+                None => block_pruning,
+            }
+        })
+    }
+
+    pub fn artifact_request_policy(&self) -> ArtifactRequestPolicy {
+        let allowed_peers = self.allowed_peers.iter().copied().collect();
+        match self.artifact_request_policy {
+            ArtifactRequestPolicyArg::Public => ArtifactRequestPolicy::Public,
+            ArtifactRequestPolicyArg::Allowlist => ArtifactRequestPolicy::Allowlist(allowed_peers),
+            ArtifactRequestPolicyArg::Namespace => ArtifactRequestPolicy::NamespaceRestricted {
+                namespaces: self.restricted_namespaces.clone(),
+                allowed_peers,
+            },
+        }
+    }
+}
+
+fn parse_byte_size(size: &str) -> Result<u64, String> {
+    byte_unit::Byte::from_str(size)
+        .map(|byte| byte.get_bytes())
+        .map_err(|e| e.to_string())
+}
+
+fn parse_pinned_package(pinned_package: &str) -> Result<(PackageType, String), String> {
+    let (package_type, package_specific_id) = pinned_package
+        .split_once(':')
+        .ok_or_else(|| "expected <package type>:<package specific id>".to_owned())?;
+    let package_type = PackageType::from_str(package_type).map_err(|e| e.to_string())?;
+    Ok((package_type, package_specific_id.to_owned()))
+}
+
+#[cfg(test)]
+#[cfg(not(tarpaulin_include))]
+mod tests {
+    use super::*;
+    use std::iter;
+
+    fn parse_args(args: &[&str]) -> PyrsiaNodeArgs {
+        PyrsiaNodeArgs::try_parse_from(iter::once("pyrsia_node").chain(args.iter().copied()))
+            .expect("Arguments should have been parsed.")
+    }
+
+    #[test]
+    fn test_facades_share_the_main_port() {
+        let args = parse_args(&["--port", "8000"]);
+
+        assert_eq!(args.facade_port(FacadeArg::Docker), Some(8000));
+        assert_eq!(args.facade_port(FacadeArg::Maven), Some(8000));
+        assert_eq!(args.node_api_listener_port(), 8000);
+    }
+
+    #[test]
+    fn test_facades_on_separate_listeners() {
+        let args = parse_args(&[
+            "--port",
+            "8000",
+            "--docker-port",
+            "8001",
+            "--maven-port",
+            "8002",
+            "--node-api-port",
+            "8003",
+        ]);
+
+        assert_eq!(args.facade_port(FacadeArg::Docker), Some(8001));
+        assert_eq!(args.facade_port(FacadeArg::Maven), Some(8002));
+        assert_eq!(args.node_api_listener_port(), 8003);
+    }
+
+    #[test]
+    fn test_one_facade_on_a_separate_listener() {
+        let args = parse_args(&["--port", "8000", "--maven-port", "8002"]);
+
+        assert_eq!(args.facade_port(FacadeArg::Docker), Some(8000));
+        assert_eq!(args.facade_port(FacadeArg::Maven), Some(8002));
+        assert_eq!(args.node_api_listener_port(), 8000);
+    }
+
+    #[test]
+    fn test_disable_facade() {
+        let args = parse_args(&[
+            "--port",
+            "8000",
+            "--docker-port",
+            "8001",
+            "--disable-facade",
+            "docker",
+        ]);
+
+        assert_eq!(args.facade_port(FacadeArg::Docker), None);
+        assert_eq!(args.facade_port(FacadeArg::Maven), Some(8000));
+        assert_eq!(args.node_api_listener_port(), 8000);
+        assert_eq!(
+            args.node_capabilities().package_types,
+            [PackageType::Maven2].into()
+        );
+    }
+
+    #[test]
+    fn test_disable_all_facades() {
+        let args = parse_args(&["--disable-facade", "docker", "--disable-facade", "maven"]);
+
+        assert_eq!(args.facade_port(FacadeArg::Docker), None);
+        assert_eq!(args.facade_port(FacadeArg::Maven), None);
+        assert_eq!(
+            args.node_api_listener_port(),
+            DEFAULT_PORT.parse::<u16>().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_monitor_mode_serves_no_facades() {
+        let args = parse_args(&["--monitor", "--docker-port", "8001"]);
+
+        assert_eq!(args.facade_port(FacadeArg::Docker), None);
+        assert_eq!(args.facade_port(FacadeArg::Maven), None);
+        assert!(args.node_capabilities().package_types.is_empty());
+    }
+
+    #[test]
+    fn test_invalid_facade() {
+        assert!(
+            PyrsiaNodeArgs::try_parse_from(["pyrsia_node", "--disable-facade", "npm"]).is_err()
+        );
+    }
 }
@@ -0,0 +1,181 @@
+/*
+   Copyright 2021 JFrog Ltd
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+*/
+
+//! The package manager facades that are served by the node. A facade that is
+//! compiled out with its cargo feature is replaced by a filter that rejects
+//! every request, so the remaining routes keep working as before. Facades
+//! can also be disabled or moved to a listener of their own at runtime, see
+//! [`mount`].
+
+#[cfg(feature = "docker-facade")]
+pub use pyrsia::docker::v2::routes::make_docker_routes;
+#[cfg(feature = "maven-facade")]
+pub use pyrsia::java::maven2::routes::make_maven_routes;
+
+#[cfg(not(all(feature = "docker-facade", feature = "maven-facade")))]
+use pyrsia::artifact_service::provider::ArtifactProvider;
+use warp::Filter;
+
+#[cfg(not(feature = "docker-facade"))]
+pub fn make_docker_routes<A: ArtifactProvider>(
+    _artifact_service: A,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    disabled_routes()
+}
+
+#[cfg(not(feature = "maven-facade"))]
+pub fn make_maven_routes<A: ArtifactProvider>(
+    _artifact_service: A,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    disabled_routes()
+}
+
+/// A filter that rejects every request, which replaces the routes of the
+/// parts of the node that are compiled out.
+#[cfg(not(all(
+    feature = "docker-facade",
+    feature = "maven-facade",
+    feature = "web-ui"
+)))]
+pub fn disabled_routes(
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    warp::any().and_then(|| async { Err::<String, _>(warp::reject::not_found()) })
+}
+
+/// Pass requests on to `routes` only if they are `served` on the listener
+/// the filter is mounted on, so the same routes can be combined into the
+/// filters of every listener.
+pub fn mount<F, R>(
+    served: bool,
+    routes: F,
+) -> impl Filter<Extract = (R,), Error = warp::Rejection> + Clone
+where
+    F: Filter<Extract = (R,), Error = warp::Rejection> + Clone + Send + Sync,
+    R: warp::Reply,
+{
+    warp::any()
+        .and_then(move || async move {
+            if served {
+                Ok(())
+            } else {
+                Err(warp::reject::not_found())
+            }
+        })
+        .untuple_one()
+        .and(routes)
+}
+
+#[cfg(test)]
+#[cfg(not(tarpaulin_include))]
+mod tests {
+    use super::*;
+
+    fn facade_routes(
+        name: &'static str,
+    ) -> impl Filter<Extract = (String,), Error = warp::Rejection> + Clone {
+        warp::path(name).map(move || name.to_owned())
+    }
+
+    // The routes of one listener, combined like the listeners of the node.
+    fn listener_routes(
+        port: u16,
+        docker_port: Option<u16>,
+        maven_port: Option<u16>,
+    ) -> impl Filter<Extract = (String,), Error = warp::Rejection> + Clone {
+        mount(docker_port == Some(port), facade_routes("v2"))
+            .or(mount(maven_port == Some(port), facade_routes("maven2")))
+            .unify()
+    }
+
+    #[tokio::test]
+    async fn test_mount_served() {
+        let filter = mount(true, facade_routes("v2"));
+
+        let response = warp::test::request().path("/v2").reply(&filter).await;
+        assert_eq!(response.status(), 200);
+        assert_eq!(response.body().as_ref(), b"v2");
+    }
+
+    #[tokio::test]
+    async fn test_mount_not_served() {
+        let filter = mount(false, facade_routes("v2"));
+
+        let response = warp::test::request().path("/v2").reply(&filter).await;
+        assert_eq!(response.status(), 404);
+    }
+
+    #[tokio::test]
+    async fn test_facades_on_separate_listeners() {
+        let docker_listener = listener_routes(8001, Some(8001), Some(8002));
+        let maven_listener = listener_routes(8002, Some(8001), Some(8002));
+
+        let response = warp::test::request()
+            .path("/v2")
+            .reply(&docker_listener)
+            .await;
+        assert_eq!(response.status(), 200);
+        let response = warp::test::request()
+            .path("/maven2")
+            .reply(&docker_listener)
+            .await;
+        assert_eq!(response.status(), 404);
+
+        let response = warp::test::request()
+            .path("/maven2")
+            .reply(&maven_listener)
+            .await;
+        assert_eq!(response.status(), 200);
+        let response = warp::test::request()
+            .path("/v2")
+            .reply(&maven_listener)
+            .await;
+        assert_eq!(response.status(), 404);
+    }
+
+    #[tokio::test]
+    async fn test_facades_on_one_listener() {
+        let filter = listener_routes(8000, Some(8000), Some(8000));
+
+        let response = warp::test::request().path("/v2").reply(&filter).await;
+        assert_eq!(response.body().as_ref(), b"v2");
+        let response = warp::test::request().path("/maven2").reply(&filter).await;
+        assert_eq!(response.body().as_ref(), b"maven2");
+    }
+
+    #[tokio::test]
+    async fn test_disabled_facade() {
+        let filter = listener_routes(8000, None, Some(8000));
+
+        let response = warp::test::request().path("/v2").reply(&filter).await;
+        assert_eq!(response.status(), 404);
+        let response = warp::test::request().path("/maven2").reply(&filter).await;
+        assert_eq!(response.status(), 200);
+    }
+
+    #[cfg(not(all(
+        feature = "docker-facade",
+        feature = "maven-facade",
+        feature = "web-ui"
+    )))]
+    #[tokio::test]
+    async fn test_disabled_routes() {
+        let response = warp::test::request()
+            .path("/v2")
+            .reply(&disabled_routes())
+            .await;
+        assert_eq!(response.status(), 404);
+    }
+}
@@ -14,63 +14,131 @@
    limitations under the License.
 */
 
+// the HTTP routes of the facades and the node API form a deeply nested type
+#![recursion_limit = "256"]
+
 pub mod args;
+pub mod facade;
 pub mod network;
+pub mod node_api;
 
-use anyhow::Result;
-use args::parser::PyrsiaNodeArgs;
-use libp2p::identity::Keypair;
+use anyhow::{bail, Result};
+use args::parser::{FacadeArg, LogStoreBackendArg, PyrsiaNodeArgs, StorageBackendArg};
+use facade::{make_docker_routes, make_maven_routes, mount};
+use libp2p::identity::{ed25519, Keypair};
 use libp2p::PeerId;
 use network::handlers;
+use node_api::make_node_api_routes;
+use pyrsia::accounting_service::filter::{enforce_usage_quotas, MeteredRequest};
+use pyrsia::accounting_service::service::UsageAccounting;
+use pyrsia::alert_service::service::AlertService;
+use pyrsia::artifact_service::access_stats::{self, AccessStats};
+use pyrsia::artifact_service::blob_store::encrypted::{ArtifactEncryptionKey, EncryptedBlobStore};
+use pyrsia::artifact_service::blob_store::local::LocalBlobStore;
+use pyrsia::artifact_service::blob_store::s3::{S3BlobStore, S3Config, S3Credentials};
+use pyrsia::artifact_service::blob_store::BlobStore;
+use pyrsia::artifact_service::hooks::LifecycleHooks;
+use pyrsia::artifact_service::load_test::LoadTest;
 use pyrsia::artifact_service::service::ArtifactService;
-use pyrsia::artifact_service::storage::ARTIFACTS_DIR;
+use pyrsia::artifact_service::storage::{ArtifactStorage, ARTIFACTS_DIR};
+use pyrsia::artifact_service::tag_policy::TagImmutabilityPolicy;
 use pyrsia::blockchain_service::event::{BlockchainEventClient, BlockchainEventLoop};
+use pyrsia::blockchain_service::genesis::GenesisConfig;
 use pyrsia::blockchain_service::service::BlockchainService;
-use pyrsia::build_service::event::{BuildEventClient, BuildEventLoop};
+#[cfg(not(feature = "build-service"))]
+use pyrsia::build_service::event::DisabledBuildEventLoop;
+use pyrsia::build_service::event::{BuildEvent, BuildEventClient};
+#[cfg(feature = "build-service")]
+use pyrsia::build_service::event_loop::BuildEventLoop;
+use pyrsia::build_service::secrets::SecretStore;
+#[cfg(feature = "build-service")]
 use pyrsia::build_service::service::BuildService;
+#[cfg(feature = "build-service")]
+use pyrsia::build_service::version_watcher::{VersionWatcher, VersionWatcherConfig};
 use pyrsia::docker::error_util::*;
-use pyrsia::docker::v2::routes::make_docker_routes;
-use pyrsia::java::maven2::routes::make_maven_routes;
+use pyrsia::logging::redact::{self, Redactor};
 use pyrsia::logging::*;
 use pyrsia::network::client::Client;
 use pyrsia::network::p2p;
-use pyrsia::node_api::routes::make_node_routes;
+use pyrsia::network::peer_alias::PeerAliases;
+use pyrsia::network::peer_capabilities::PeerCapabilities;
+use pyrsia::network::peer_store::PeerStore;
+use pyrsia::network::peer_version::PeerVersions;
 use pyrsia::peer_metrics::metrics::PeerMetrics;
+use pyrsia::subscription_service::service::SubscriptionService;
+use pyrsia::transparency_log::log::TransparencyLogService;
+#[cfg(feature = "postgres-log-store")]
+use pyrsia::transparency_log::store::postgres::PostgresLogStore;
 use pyrsia::util::env_util::read_var;
+use pyrsia::util::http_server;
 use pyrsia::util::keypair_util::{self, KEYPAIR_FILENAME};
+#[cfg(feature = "build-service")]
 use pyrsia::verification_service::service::VerificationService;
 
 use clap::Parser;
 use log::{debug, info, warn};
+use std::collections::BTreeSet;
 use std::error::Error;
 use std::net::{IpAddr, Ipv4Addr, SocketAddr};
 use std::path::{Path, PathBuf};
+use std::time::Duration;
 use tokio::sync::mpsc;
 use tokio_stream::StreamExt;
 use warp::Filter;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn Error>> {
-    pretty_env_logger::init_timed();
-
-    debug!("Parse CLI arguments");
     let args = PyrsiaNodeArgs::parse();
+    setup_logging(&args)?;
 
     let mut peer_metrics = PeerMetrics::new();
 
+    debug!("Load genesis configuration");
+    let genesis_config = setup_genesis_config(&args)?;
+
     debug!("Create p2p components");
-    let (mut p2p_client, local_keypair, mut p2p_events, event_loop) =
-        p2p::setup_libp2p_swarm(args.max_provided_keys)?;
+    let (mut p2p_client, local_keypair, mut p2p_events, event_loop) = p2p::setup_libp2p_swarm(
+        &genesis_config.network_id,
+        args.max_provided_keys,
+        setup_peer_aliases(&args)?,
+        PeerVersions::default().with_compatibility_gate(args.compatibility_gate()),
+        PeerCapabilities::default().with_own_capabilities(args.node_capabilities()),
+        setup_peer_store(),
+    )?;
 
     debug!("Start p2p event loop");
     tokio::spawn(event_loop.run());
 
+    debug!("Create build secret store");
+    let secret_store = setup_secret_store(&local_keypair);
+
+    debug!("Create subscription service");
+    let subscription_service = setup_subscription_service();
+
+    debug!("Create alert service");
+    let alert_service = setup_alert_service(&args)?;
+
     debug!("Create pyrsia services");
-    let (blockchain_event_client, build_event_client, artifact_service) =
-        setup_pyrsia_services(p2p_client.clone(), local_keypair, &args).await?;
+    let (blockchain_event_client, build_event_client, artifact_service) = setup_pyrsia_services(
+        p2p_client.clone(),
+        local_keypair,
+        secret_store.clone(),
+        subscription_service.clone(),
+        alert_service.clone(),
+        &genesis_config,
+        &args,
+    )
+    .await?;
 
     debug!("Setup HTTP server");
-    setup_http(&args, artifact_service.clone(), p2p_client.clone());
+    setup_http(
+        &args,
+        artifact_service.clone(),
+        p2p_client.clone(),
+        secret_store,
+        subscription_service,
+        alert_service,
+    );
 
     debug!("Establishing connection with p2p network");
     establish_connection_with_p2p_network(
@@ -81,8 +149,32 @@ async fn main() -> Result<(), Box<dyn Error>> {
     )
     .await;
 
-    debug!("Provide local artifacts");
-    artifact_service.clone().provide_local_artifacts().await?;
+    if args.load_test {
+        let report = LoadTest::new(artifact_service, args.load_test_config())
+            .run()
+            .await?;
+        println!("{}", report);
+        return Ok(());
+    }
+
+    if args.monitor {
+        info!("Pyrsia Node runs in monitor mode, it audits the blockchain without storing artifacts or building");
+    } else {
+        setup_artifact_schedulers(&args, &artifact_service)?;
+    }
+
+    if args.peer_exchange_interval_secs > 0 {
+        debug!(
+            "Exchange known peers every {} seconds",
+            args.peer_exchange_interval_secs
+        );
+        tokio::spawn(handlers::run_peer_exchange_scheduler(
+            p2p_client.clone(),
+            artifact_service.clone(),
+            Duration::from_secs(args.peer_exchange_interval_secs),
+            args.peer_exchange_max_dials,
+        ));
+    }
 
     debug!("Listen for p2p events");
     loop {
@@ -90,23 +182,30 @@ async fn main() -> Result<(), Box<dyn Error>> {
             match event {
                 // Reply with the content of the artifact on incoming requests.
                 pyrsia::network::event_loop::PyrsiaEvent::RequestArtifact {
+                    peer,
                     artifact_id,
+                    chunk,
                     channel,
                 } => {
                     if let Err(error) = handlers::handle_request_artifact(
                         artifact_service.clone(),
+                        &peer,
                         &artifact_id,
+                        chunk,
                         channel,
                     )
                     .await
                     {
                         warn!(
-                            "This node failed to provide artifact with id {}. Error: {:?}",
-                            artifact_id, error
+                            "This node failed to provide artifact with id {} to peer {}. Error: {:?}",
+                            artifact_id,
+                            p2p_client.peer_aliases.display(&peer),
+                            error
                         );
                     }
                 }
                 pyrsia::network::event_loop::PyrsiaEvent::RequestBuild {
+                    peer,
                     package_type,
                     package_specific_id,
                     channel,
@@ -115,9 +214,15 @@ async fn main() -> Result<(), Box<dyn Error>> {
                         "Main::p2p request build: {:?} : {}",
                         package_type, package_specific_id
                     );
-                    if let Err(error) = handlers::handle_request_build(
+                    if args.monitor {
+                        warn!(
+                            "This node runs in monitor mode and refused to build package type {:?} and id {}",
+                            package_type, package_specific_id
+                        );
+                    } else if let Err(error) = handlers::handle_request_build(
                         p2p_client.clone(),
                         build_event_client.clone(),
+                        &peer,
                         package_type,
                         &package_specific_id,
                         channel,
@@ -190,6 +295,33 @@ async fn main() -> Result<(), Box<dyn Error>> {
                         );
                     }
                 }
+                pyrsia::network::event_loop::PyrsiaEvent::RequestSeedSync {
+                    peer,
+                    request,
+                    channel,
+                } => {
+                    // replica requests are answered after the artifact was
+                    // fetched, which must not hold up the other requests
+                    let p2p_client = p2p_client.clone();
+                    let artifact_service = artifact_service.clone();
+                    tokio::spawn(async move {
+                        if let Err(error) = handlers::handle_request_seed_sync(
+                            p2p_client.clone(),
+                            artifact_service,
+                            &peer,
+                            request,
+                            channel,
+                        )
+                        .await
+                        {
+                            warn!(
+                                "This node failed to answer the seed sync request of peer {}. Error: {:?}",
+                                p2p_client.peer_aliases.display(&peer),
+                                error
+                            );
+                        }
+                    });
+                }
             }
         }
     }
@@ -209,18 +341,31 @@ async fn establish_connection_with_p2p_network(
                 None
             })
         {
-            if !args.init_blockchain {
-                if let Err(err) = pull_block_from_other_nodes(
+            if args.fast_sync {
+                if let Err(err) = fast_sync_from_other_node(
                     artifact_service.clone(),
                     blockchain_event_client,
                     &other_peer_id,
                 )
                 .await
+                {
+                    panic!("Failed to fast sync from p2p network: {:?}", err);
+                }
+            } else if !args.init_blockchain {
+                if let Err(err) =
+                    pull_block_from_other_nodes(blockchain_event_client, &other_peer_id).await
                 {
                     panic!("Failed to pull blocks from p2p network: {:?}", err);
                 }
             }
         }
+
+        if let Some(seed) = &args.seed {
+            info!("Syncing from seed node {}", seed);
+            if let Err(err) = artifact_service.clone().sync_from_seed(seed).await {
+                warn!("Failed to sync from seed node {}: {:?}", seed, err);
+            }
+        }
     });
 }
 
@@ -298,6 +443,10 @@ async fn load_peer_addrs(peer_url: &str) -> anyhow::Result<String> {
 async fn setup_pyrsia_services(
     p2p_client: Client,
     local_keypair: Keypair,
+    secret_store: SecretStore,
+    subscription_service: SubscriptionService,
+    alert_service: AlertService,
+    genesis_config: &GenesisConfig,
     args: &PyrsiaNodeArgs,
 ) -> Result<(BlockchainEventClient, BuildEventClient, ArtifactService)> {
     let Keypair::Ed25519(local_ed25519_keypair) = local_keypair;
@@ -306,6 +455,10 @@ async fn setup_pyrsia_services(
 
     let pyrsia_blockchain_path = read_var("PYRSIA_BLOCKCHAIN_PATH", "pyrsia/blockchain");
 
+    if cfg!(not(feature = "blockchain-producer")) && args.init_blockchain {
+        bail!("Initializing the blockchain requires a node built with the blockchain-producer feature");
+    }
+
     debug!("Create blockchain service");
     let blockchain_service = if args.init_blockchain {
         let blockchain_keypair =
@@ -317,6 +470,7 @@ async fn setup_pyrsia_services(
         BlockchainService::init_first_blockchain_node(
             &local_ed25519_keypair,
             &blockchain_ed25519_keypair,
+            genesis_config,
             p2p_client.clone(),
             pyrsia_blockchain_path,
         )
@@ -324,10 +478,15 @@ async fn setup_pyrsia_services(
     } else {
         BlockchainService::init_other_blockchain_node(
             &local_ed25519_keypair,
+            genesis_config,
             p2p_client.clone(),
             pyrsia_blockchain_path,
         )
     }?;
+    let blockchain_service = match args.block_pruning() {
+        Some(block_pruning) => blockchain_service.with_pruning(block_pruning),
+        None => blockchain_service,
+    };
 
     debug!("Create blockchain event client");
     let (blockchain_event_sender, blockchain_event_receiver) = mpsc::channel(32);
@@ -337,19 +496,42 @@ async fn setup_pyrsia_services(
     let (build_event_sender, build_event_receiver) = mpsc::channel(32);
     let build_event_client = BuildEventClient::new(build_event_sender);
 
+    debug!("Create artifact storage");
+    let artifact_storage = setup_artifact_storage(&artifact_path, &local_ed25519_keypair, args)?;
+
     debug!("Create artifact service");
-    let artifact_service = setup_artifact_service(
-        &artifact_path,
+    let mut artifact_service = setup_artifact_service(
+        artifact_storage,
         blockchain_event_client.clone(),
         build_event_client.clone(),
         p2p_client,
-    )?;
-
-    debug!("Create build service");
-    let build_service = setup_build_service(&artifact_path, build_event_client.clone(), args)?;
+        subscription_service,
+        alert_service.clone(),
+        args,
+    )
+    .await?
+    .with_snapshot_keypair(local_ed25519_keypair.clone());
+    artifact_service.transparency_log_service = artifact_service
+        .transparency_log_service
+        .clone()
+        .with_node_keypair(local_ed25519_keypair.clone())
+        .with_genesis_nodes(genesis_config.authorized_node_ids());
 
-    debug!("Create verification service");
-    let verification_service = VerificationService::new(build_event_client.clone())?;
+    if let Some(checkpoint_interval_secs) = args.checkpoint_interval_secs {
+        debug!(
+            "Checkpoint the transparency log every {} seconds",
+            checkpoint_interval_secs
+        );
+        tokio::spawn(
+            artifact_service
+                .transparency_log_service
+                .clone()
+                .run_checkpoint_scheduler(
+                    local_ed25519_keypair.clone(),
+                    Duration::from_secs(checkpoint_interval_secs),
+                ),
+        );
+    }
 
     debug!("Start blockchain event loop");
     let blockchain_event_loop = BlockchainEventLoop::new(
@@ -359,14 +541,26 @@ async fn setup_pyrsia_services(
     );
     tokio::spawn(blockchain_event_loop.run());
 
-    debug!("Start build event loop");
-    let build_event_loop = BuildEventLoop::new(
+    if let Some(import_chain) = &args.import_chain {
+        let imported = blockchain_event_client
+            .import_blocks(std::fs::read(import_chain)?)
+            .await?;
+        info!(
+            "Imported {} blocks from {}",
+            imported,
+            import_chain.display()
+        );
+    }
+
+    start_build_event_loop(
+        &artifact_path,
         artifact_service.clone(),
-        build_service,
-        verification_service,
+        build_event_client.clone(),
         build_event_receiver,
-    );
-    tokio::spawn(build_event_loop.run());
+        secret_store,
+        alert_service,
+        args,
+    )?;
 
     Ok((
         blockchain_event_client,
@@ -375,90 +569,508 @@ async fn setup_pyrsia_services(
     ))
 }
 
-fn setup_artifact_service(
-    artifact_path: &Path,
+async fn setup_artifact_service(
+    artifact_storage: ArtifactStorage,
     blockchain_event_client: BlockchainEventClient,
     build_event_client: BuildEventClient,
     p2p_client: Client,
+    subscription_service: SubscriptionService,
+    alert_service: AlertService,
+    args: &PyrsiaNodeArgs,
 ) -> Result<ArtifactService> {
-    let artifact_service = ArtifactService::new(
-        artifact_path,
+    let artifact_path = PathBuf::from(ARTIFACTS_DIR.as_str());
+    let access_stats = AccessStats::new(artifact_path.join("access_stats.json"))?;
+    tokio::spawn(
+        access_stats
+            .clone()
+            .run_flush_scheduler(access_stats::DEFAULT_FLUSH_INTERVAL),
+    );
+
+    let mut artifact_service = ArtifactService::new(
+        &artifact_path,
         blockchain_event_client,
         build_event_client,
         p2p_client,
-    )?;
+    )?
+    .with_artifact_storage(artifact_storage)
+    .with_artifact_request_policy(args.artifact_request_policy())
+    .with_provide_schedule(args.provide_schedule())
+    .with_fetch_retry_policy(args.fetch_retry_policy())
+    .with_transfer_budget(args.transfer_budget())
+    .with_usage_accounting(args.usage_accounting())
+    .with_pinned_packages(args.pinned_packages.clone())
+    .with_sole_copy_policy(args.sole_copy_policy())
+    .with_lifecycle_hooks(match &args.lifecycle_hooks {
+        Some(lifecycle_hooks) => LifecycleHooks::load(lifecycle_hooks)?,
+        None => Default::default(),
+    })
+    .with_tag_immutability_policy(match &args.tag_immutability_policy {
+        Some(tag_immutability_policy) => TagImmutabilityPolicy::load(tag_immutability_policy)?,
+        None => Default::default(),
+    })
+    .with_subscription_service(subscription_service)
+    .with_alert_service(alert_service)
+    .with_access_stats(access_stats)
+    .with_monitor_mode(args.monitor);
+
+    if args.transparency_log_backend == LogStoreBackendArg::Postgres {
+        artifact_service.transparency_log_service =
+            setup_postgres_log_store(artifact_service.transparency_log_service)?;
+    }
+    artifact_service.transparency_log_service = artifact_service
+        .transparency_log_service
+        .with_lookup_cache_capacity(args.transparency_log_cache_size);
+    if let Some(build_claim_ttl_secs) = args.build_claim_ttl_secs {
+        artifact_service.transparency_log_service = artifact_service
+            .transparency_log_service
+            .with_build_claim_ttl(Duration::from_secs(build_claim_ttl_secs));
+    }
+
+    let privacy_salt = read_var("PYRSIA_TRANSPARENCY_LOG_PRIVACY_SALT", "");
+    if !privacy_salt.is_empty() {
+        info!("Transparency log privacy mode is enabled");
+        artifact_service.transparency_log_service = artifact_service
+            .transparency_log_service
+            .with_privacy_salt(&privacy_salt);
+    }
+
+    artifact_service.migrate_artifact_ids().await?;
+
+    if let Some(import_log) = &args.import_log {
+        let imported = artifact_service
+            .transparency_log_service
+            .import_log(import_log)?;
+        info!(
+            "Imported {} transparency logs from {}",
+            imported,
+            import_log.display()
+        );
+    }
 
     Ok(artifact_service)
 }
 
+#[cfg(feature = "postgres-log-store")]
+fn setup_postgres_log_store(
+    transparency_log_service: TransparencyLogService,
+) -> Result<TransparencyLogService> {
+    let database_url = read_var("PYRSIA_TRANSPARENCY_LOG_DATABASE_URL", "");
+    if database_url.is_empty() {
+        bail!(
+            "The postgres transparency log backend requires PYRSIA_TRANSPARENCY_LOG_DATABASE_URL"
+        );
+    }
+    info!("Transparency logs are stored in a PostgreSQL database");
+    Ok(transparency_log_service.with_log_store(PostgresLogStore::connect(&database_url)?))
+}
+
+#[cfg(not(feature = "postgres-log-store"))]
+fn setup_postgres_log_store(
+    _transparency_log_service: TransparencyLogService,
+) -> Result<TransparencyLogService> {
+    bail!("The postgres transparency log backend requires a node built with the postgres-log-store feature");
+}
+
+#[cfg(feature = "build-service")]
+fn start_version_watcher(
+    version_watch_config: &Path,
+    artifact_service: &ArtifactService,
+) -> Result<()> {
+    debug!("Start version watcher");
+    let config = VersionWatcherConfig::load(version_watch_config)?;
+    tokio::spawn(VersionWatcher::new(artifact_service.clone(), config).run());
+    Ok(())
+}
+
+#[cfg(not(feature = "build-service"))]
+fn start_version_watcher(
+    _version_watch_config: &Path,
+    _artifact_service: &ArtifactService,
+) -> Result<()> {
+    bail!("The version watcher requires a node built with the build-service feature");
+}
+
+fn setup_artifact_schedulers(
+    args: &PyrsiaNodeArgs,
+    artifact_service: &ArtifactService,
+) -> Result<()> {
+    if let Some(version_watch_config) = &args.version_watch_config {
+        start_version_watcher(version_watch_config, artifact_service)?;
+    }
+
+    debug!("Provide local artifacts in the background");
+    tokio::spawn(artifact_service.clone().run_provide_scheduler());
+
+    if args.max_storage_size.is_some() {
+        debug!("Evict the least recently used artifacts when the maximum storage size is exceeded");
+        tokio::spawn(artifact_service.clone().run_eviction());
+    }
+
+    if let Some(scrub_interval_secs) = args.scrub_interval_secs {
+        debug!(
+            "Scrub the local artifacts every {} seconds",
+            scrub_interval_secs
+        );
+        tokio::spawn(
+            artifact_service
+                .clone()
+                .run_scrub_scheduler(Duration::from_secs(scrub_interval_secs), args.scrub_refetch),
+        );
+    }
+
+    if let Some(availability_sample_interval_secs) = args.availability_sample_interval_secs {
+        debug!(
+            "Sample the availability of {} artifacts every {} seconds",
+            args.availability_sample_size, availability_sample_interval_secs
+        );
+        tokio::spawn(artifact_service.clone().run_availability_monitor(
+            Duration::from_secs(availability_sample_interval_secs),
+            args.availability_sample_size,
+        ));
+    }
+
+    Ok(())
+}
+
+fn setup_artifact_storage(
+    artifact_path: &Path,
+    local_keypair: &ed25519::Keypair,
+    args: &PyrsiaNodeArgs,
+) -> Result<ArtifactStorage> {
+    let blob_store: Box<dyn BlobStore> = match args.storage_backend {
+        StorageBackendArg::Local => Box::new(LocalBlobStore::new(artifact_path)?),
+        StorageBackendArg::S3 => {
+            let (Some(endpoint), Some(bucket)) = (&args.s3_endpoint, &args.s3_bucket) else {
+                bail!("The s3 storage backend requires --s3-endpoint and --s3-bucket");
+            };
+            info!(
+                "Artifacts are stored in S3 bucket {} at {}",
+                bucket, endpoint
+            );
+            let s3_blob_store = S3BlobStore::new(S3Config {
+                endpoint: endpoint.clone(),
+                bucket: bucket.clone(),
+                region: args.s3_region.clone(),
+                key_prefix: args.s3_key_prefix.clone(),
+                credentials: S3Credentials {
+                    access_key_id: read_var("AWS_ACCESS_KEY_ID", ""),
+                    secret_access_key: read_var("AWS_SECRET_ACCESS_KEY", ""),
+                    session_token: Some(read_var("AWS_SESSION_TOKEN", ""))
+                        .filter(|token| !token.is_empty()),
+                },
+            })?;
+            Box::new(s3_blob_store)
+        }
+    };
+    let artifact_storage = if args.encrypt_artifacts {
+        info!("Artifacts are encrypted at rest");
+        ArtifactStorage::with_blob_store(EncryptedBlobStore::new(
+            blob_store,
+            &ArtifactEncryptionKey::derive(&local_keypair.encode()),
+        ))
+    } else {
+        ArtifactStorage::with_blob_store(blob_store)
+    };
+    let artifact_storage = match args.mmap_config() {
+        Some(mmap_config) => {
+            info!(
+                "Artifacts of at least {} bytes are served from memory-mapped files",
+                mmap_config.min_size
+            );
+            artifact_storage.with_memory_mapping(mmap_config)
+        }
+        None => artifact_storage,
+    };
+    match args.max_storage_size {
+        Some(max_storage_size) => artifact_storage.with_max_size(max_storage_size),
+        None => Ok(artifact_storage),
+    }
+}
+
+#[cfg(feature = "build-service")]
+fn start_build_event_loop(
+    artifact_path: &Path,
+    artifact_service: ArtifactService,
+    build_event_client: BuildEventClient,
+    build_event_receiver: mpsc::Receiver<BuildEvent>,
+    secret_store: SecretStore,
+    alert_service: AlertService,
+    args: &PyrsiaNodeArgs,
+) -> Result<()> {
+    debug!("Create build service");
+    let build_service = setup_build_service(
+        artifact_path,
+        build_event_client.clone(),
+        secret_store,
+        alert_service,
+        artifact_service.usage_accounting(),
+        args,
+    )?;
+
+    debug!("Create verification service");
+    let verification_service = VerificationService::new(build_event_client)?;
+
+    debug!("Start build event loop");
+    let build_event_loop = BuildEventLoop::new(
+        artifact_service,
+        build_service,
+        verification_service,
+        build_event_receiver,
+    );
+    tokio::spawn(build_event_loop.run());
+
+    Ok(())
+}
+
+#[cfg(not(feature = "build-service"))]
+fn start_build_event_loop(
+    _artifact_path: &Path,
+    _artifact_service: ArtifactService,
+    _build_event_client: BuildEventClient,
+    build_event_receiver: mpsc::Receiver<BuildEvent>,
+    _secret_store: SecretStore,
+    _alert_service: AlertService,
+    _args: &PyrsiaNodeArgs,
+) -> Result<()> {
+    info!("Pyrsia Node was built without the build service, build requests are rejected");
+    tokio::spawn(DisabledBuildEventLoop::new(build_event_receiver).run());
+
+    Ok(())
+}
+
+#[cfg(feature = "build-service")]
 fn setup_build_service(
     artifact_path: &Path,
     build_event_client: BuildEventClient,
+    secret_store: SecretStore,
+    alert_service: AlertService,
+    usage_accounting: UsageAccounting,
     args: &PyrsiaNodeArgs,
 ) -> Result<BuildService> {
-    let build_service = BuildService::new(
+    let mut build_service = BuildService::new(
         artifact_path,
         build_event_client,
         &args.mapping_service_endpoint,
         &args.pipeline_service_endpoint,
-    )?;
+    )?
+    .with_internal_packages(
+        args.internal_packages(),
+        args.internal_mapping_service_endpoint.as_deref(),
+    )
+    .with_alert_service(alert_service)
+    .with_usage_accounting(usage_accounting)
+    .with_partial_build_policy(args.partial_build_policy())
+    .with_build_history_retention(args.build_history_retention())
+    .with_build_queue(args.build_queue())
+    .with_secret_store(secret_store);
+    if let Some(base_image_cache) = &args.base_image_cache {
+        info!(
+            "Docker builds pull their base images through {}",
+            base_image_cache
+        );
+        build_service = build_service.with_base_image_cache(base_image_cache);
+    }
 
     Ok(build_service)
 }
 
-fn setup_http(args: &PyrsiaNodeArgs, artifact_service: ArtifactService, p2p_client: Client) {
+fn setup_genesis_config(args: &PyrsiaNodeArgs) -> Result<GenesisConfig> {
+    let genesis_config = match &args.genesis_config {
+        Some(genesis_config) => GenesisConfig::load(genesis_config)?,
+        None => GenesisConfig::default(),
+    };
+    info!(
+        "Pyrsia Node joins network {}",
+        genesis_config
+            .network_name
+            .as_deref()
+            .unwrap_or(&genesis_config.network_id)
+    );
+    Ok(genesis_config)
+}
+
+fn setup_logging(args: &PyrsiaNodeArgs) -> Result<()> {
+    let mut builder = pretty_env_logger::formatted_timed_builder();
+    if let Ok(filters) = std::env::var("RUST_LOG") {
+        builder.parse_filters(&filters);
+    }
+    let logger = builder.build();
+    let max_level = logger.filter();
+
+    let mut redactor = args
+        .alert_webhooks
+        .iter()
+        .fold(Redactor::default(), |redactor, webhook_url| {
+            redactor.with_secret_url(webhook_url)
+        });
+    for variable_name in [
+        "PYRSIA_ADMIN_TOKEN",
+        "AWS_SECRET_ACCESS_KEY",
+        "AWS_SESSION_TOKEN",
+        "PYRSIA_TRANSPARENCY_LOG_DATABASE_URL",
+    ] {
+        redactor = redactor.with_secret(&read_var(variable_name, ""));
+    }
+    for pattern in &args.redact_patterns {
+        redactor = redactor.with_pattern(pattern)?;
+    }
+
+    redact::init(logger, max_level, redactor)?;
+    Ok(())
+}
+
+fn setup_secret_store(local_keypair: &Keypair) -> SecretStore {
+    let Keypair::Ed25519(local_ed25519_keypair) = local_keypair;
+    let secrets_path = PathBuf::from(ARTIFACTS_DIR.as_str())
+        .join("secrets")
+        .join("build_secrets");
+    SecretStore::new(secrets_path, &local_ed25519_keypair.encode())
+}
+
+fn setup_peer_aliases(args: &PyrsiaNodeArgs) -> Result<PeerAliases> {
+    let peer_aliases_path = PathBuf::from(ARTIFACTS_DIR.as_str())
+        .join("peers")
+        .join("aliases.json");
+    let peer_aliases = PeerAliases::new(peer_aliases_path);
+    match &args.alias {
+        Some(alias) => Ok(peer_aliases.with_own_alias(alias)?),
+        None => Ok(peer_aliases),
+    }
+}
+
+fn setup_peer_store() -> PeerStore {
+    let peer_store_path = PathBuf::from(ARTIFACTS_DIR.as_str())
+        .join("peers")
+        .join("peer_store.json");
+    PeerStore::new(peer_store_path)
+}
+
+fn setup_alert_service(args: &PyrsiaNodeArgs) -> Result<AlertService> {
+    Ok(AlertService::new(args.alert_webhooks.clone())?
+        .with_expected_authorized_nodes(args.expected_authorized_nodes.clone()))
+}
+
+fn setup_subscription_service() -> SubscriptionService {
+    let subscriptions_path = PathBuf::from(ARTIFACTS_DIR.as_str())
+        .join("subscriptions")
+        .join("subscriptions.json");
+    SubscriptionService::new(subscriptions_path)
+}
+
+fn setup_http(
+    args: &PyrsiaNodeArgs,
+    artifact_service: ArtifactService,
+    p2p_client: Client,
+    secret_store: SecretStore,
+    subscription_service: SubscriptionService,
+    alert_service: AlertService,
+) {
     // Get host and port from the settings. Defaults to DEFAULT_HOST and DEFAULT_PORT
     debug!(
         "Pyrsia Node will bind to host = {}, port = {}",
         args.host, args.port
     );
 
-    let address = SocketAddr::new(
-        IpAddr::V4(args.host.parse::<Ipv4Addr>().unwrap()),
-        args.port.parse::<u16>().unwrap(),
-    );
+    let docker_port = args.facade_port(FacadeArg::Docker);
+    let maven_port = args.facade_port(FacadeArg::Maven);
+    let node_api_port = Some(args.node_api_listener_port()).filter(|_| cfg!(feature = "web-ui"));
+    let reverse_proxy = args.reverse_proxy_config();
+    let http_server_config = args.http_server_config();
+    if !reverse_proxy.base_path().is_empty() {
+        info!("Pyrsia Node is served under {}", reverse_proxy.base_path());
+    }
 
     debug!("Setup HTTP routing");
     let docker_routes = make_docker_routes(artifact_service.clone());
     let maven_routes = make_maven_routes(artifact_service.clone());
-    let node_api_routes = make_node_routes(artifact_service, p2p_client);
-    let all_routes = docker_routes.or(maven_routes).or(node_api_routes);
+    let usage_accounting = artifact_service.usage_accounting();
+    let node_api_routes = make_node_api_routes(
+        artifact_service,
+        p2p_client,
+        secret_store,
+        subscription_service,
+        alert_service,
+    );
 
-    debug!("Setup HTTP server");
-    let (addr, server) = warp::serve(
-        all_routes
-            .and(http::log_headers())
-            .recover(custom_recover)
-            .with(warp::log("pyrsia_registry")),
-    )
-    .bind_ephemeral(address);
+    // every facade is routed by its own path prefix, so all of them and the
+    // node API share the main listener unless they are given a port of their own
+    let ports: BTreeSet<u16> = [docker_port, maven_port, node_api_port]
+        .into_iter()
+        .flatten()
+        .collect();
+    for port in ports {
+        let routes = reverse_proxy
+            .base_path_filter()
+            .and(enforce_usage_quotas(usage_accounting.clone()))
+            .and(
+                mount(docker_port == Some(port), docker_routes.clone())
+                    .or(mount(maven_port == Some(port), maven_routes.clone()))
+                    .or(mount(node_api_port == Some(port), node_api_routes.clone())),
+            )
+            .map(|metered_request: MeteredRequest, reply| metered_request.record_response(reply));
 
-    info!(
-        "Pyrsia Node will start running on {}:{}",
-        addr.ip(),
-        addr.port()
-    );
+        debug!("Setup HTTP server on port {}", port);
+        let address = SocketAddr::new(IpAddr::V4(args.host.parse::<Ipv4Addr>().unwrap()), port);
+        let (addr, server) = http_server::bind(
+            routes.and(http::log_headers()).recover(custom_recover),
+            address,
+            &http_server_config,
+            reverse_proxy.access_log(),
+        )
+        .unwrap_or_else(|error| panic!("Unable to bind HTTP server to {}: {}", address, error));
+
+        let served: Vec<&str> = [
+            (docker_port == Some(port), "Docker facade"),
+            (maven_port == Some(port), "Maven facade"),
+            (node_api_port == Some(port), "node API"),
+        ]
+        .into_iter()
+        .filter_map(|(served, name)| served.then_some(name))
+        .collect();
+        info!(
+            "Pyrsia Node will start running on {}:{} serving the {}",
+            addr.ip(),
+            addr.port(),
+            served.join(", ")
+        );
 
-    tokio::spawn(server);
+        tokio::spawn(server);
+    }
 }
 
+// The blockchain event loop applies the pulled blocks to the transparency
+// log.
 async fn pull_block_from_other_nodes(
-    mut artifact_service: ArtifactService,
     blockchain_event_client: BlockchainEventClient,
     other_peer_id: &PeerId,
 ) -> anyhow::Result<()> {
     debug!("Blockchain start pulling blocks from other peers");
 
-    let ordinal = blockchain_event_client
+    blockchain_event_client
         .pull_blocks_from_peer(other_peer_id)
         .await?;
 
+    Ok(())
+}
+
+// Import a signed transparency log snapshot of the other node and handle the
+// blocks after it. The local blockchain stays empty, new blocks that are
+// broadcast are still handled.
+async fn fast_sync_from_other_node(
+    mut artifact_service: ArtifactService,
+    blockchain_event_client: BlockchainEventClient,
+    other_peer_id: &PeerId,
+) -> anyhow::Result<()> {
+    debug!("Fast sync the transparency log from other peers");
+
+    let snapshot = artifact_service.fast_sync(other_peer_id).await?;
+
     for block in blockchain_event_client
-        .pull_blocks_local(1, ordinal)
+        .pull_blocks_since_from_peer(other_peer_id, snapshot.block_ordinal + 1)
         .await?
     {
-        let payloads = block.fetch_payload();
-        artifact_service.handle_block_added(payloads).await?;
+        artifact_service.audit_block(&block)?;
+        artifact_service.handle_block_added(&block).await?;
     }
 
     Ok(())
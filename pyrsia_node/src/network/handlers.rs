@@ -18,18 +18,21 @@ use bincode::{deserialize, serialize};
 use libp2p::multiaddr::Protocol;
 use libp2p::request_response::ResponseChannel;
 use libp2p::{Multiaddr, PeerId};
-use log::debug;
+use log::{debug, info, warn};
+use std::collections::HashSet;
+use std::time::Duration;
 
 use pyrsia::artifact_service::model::PackageType;
 use pyrsia::artifact_service::service::ArtifactService;
 use pyrsia::blockchain_service::event::BlockchainEventClient;
 use pyrsia::blockchain_service::service::BlockchainCommand;
 use pyrsia::build_service::event::BuildEventClient;
-use pyrsia::network::artifact_protocol::ArtifactResponse;
+use pyrsia::network::artifact_protocol::{ArtifactChunk, ArtifactResponse};
 use pyrsia::network::build_protocol::BuildResponse;
 use pyrsia::network::build_status_protocol::BuildStatusResponse;
 use pyrsia::network::client::Client;
 use pyrsia::network::idle_metric_protocol::{IdleMetricResponse, PeerMetrics};
+use pyrsia::network::seed_sync_protocol::{SeedSyncRequest, SeedSyncResponse};
 use pyrsia::peer_metrics::metrics;
 use pyrsia_blockchain_network::structures::block::Block;
 use pyrsia_blockchain_network::structures::header::Ordinal;
@@ -56,21 +59,88 @@ pub async fn probe_other_peer(mut p2p_client: Client, to_probe: &Multiaddr) -> a
     }
 }
 
-/// Respond to a RequestArtifact event by getting the artifact
-/// based on the provided artifact id.
+/// Exchange known peers with a few connected peers at every interval and
+/// dial the received peers that this node is not connected to yet,
+/// preferring the authorized nodes.
+pub async fn run_peer_exchange_scheduler(
+    mut p2p_client: Client,
+    artifact_service: ArtifactService,
+    interval: Duration,
+    max_dials: usize,
+) {
+    loop {
+        tokio::time::sleep(interval).await;
+        let authorized_nodes: HashSet<PeerId> = artifact_service
+            .transparency_log_service
+            .get_authorized_nodes()
+            .unwrap_or_else(|error| {
+                warn!("Failed to get the authorized nodes: {:?}", error);
+                vec![]
+            })
+            .into_iter()
+            .collect();
+        match p2p_client
+            .exchange_peers(&authorized_nodes, max_dials)
+            .await
+        {
+            Ok(connected_peers) if !connected_peers.is_empty() => info!(
+                "Connected to {} peers found by peer exchange",
+                connected_peers.len()
+            ),
+            Ok(_) => {}
+            Err(error) => warn!("Failed to exchange peers: {:?}", error),
+        }
+    }
+}
+
+/// Respond to a RequestArtifact event by getting the artifact, or
+/// the requested chunk of it, based on the provided artifact id, if
+/// the requesting peer is authorized to request it.
 pub async fn handle_request_artifact(
     mut artifact_service: ArtifactService,
+    peer_id: &PeerId,
     artifact_id: &str,
+    chunk: Option<ArtifactChunk>,
     channel: ResponseChannel<ArtifactResponse>,
 ) -> anyhow::Result<()> {
-    debug!("Handling request artifact: {:?}", artifact_id);
+    debug!("Handling request artifact: {:?} {:?}", artifact_id, chunk);
 
-    let content = artifact_service.get_artifact_locally(artifact_id).await?;
+    artifact_service.authorize_artifact_request(peer_id, artifact_id)?;
+
+    let response = match chunk {
+        Some(chunk) => {
+            let (content, artifact_size) = artifact_service
+                .get_artifact_chunk_locally(artifact_id, chunk)
+                .await?;
+            ArtifactResponse {
+                artifact: content,
+                artifact_size,
+            }
+        }
+        None => {
+            ArtifactResponse::complete(artifact_service.get_artifact_locally(artifact_id).await?)
+        }
+    };
 
     artifact_service
         .p2p_client
-        .respond_artifact(content, channel)
-        .await
+        .respond_artifact(response, channel)
+        .await?;
+
+    // a download in chunks is only counted once
+    if chunk.is_some_and(|chunk| chunk.offset > 0) {
+        return Ok(());
+    }
+
+    artifact_service
+        .record_artifact_served(peer_id, artifact_id)
+        .unwrap_or_else(|e| {
+            warn!(
+                "Failed to record access statistics of artifact {}: {:?}",
+                artifact_id, e
+            )
+        });
+    Ok(())
 }
 
 /// Respond to a RequestBuild event by getting the build
@@ -78,6 +148,7 @@ pub async fn handle_request_artifact(
 pub async fn handle_request_build(
     mut p2p_client: Client,
     build_event_client: BuildEventClient,
+    peer: &PeerId,
     package_type: PackageType,
     package_specific_id: &str,
     channel: ResponseChannel<BuildResponse>,
@@ -88,7 +159,11 @@ pub async fn handle_request_build(
     );
 
     let build_id = build_event_client
-        .start_build(package_type, package_specific_id.to_string())
+        .start_build(
+            package_type,
+            package_specific_id.to_string(),
+            Some(peer.to_string()),
+        )
         .await?;
 
     p2p_client.respond_build(&build_id, channel).await
@@ -156,3 +231,18 @@ pub async fn handle_request_build_status(
 
     p2p_client.respond_build_status(&build_id, channel).await
 }
+
+/// Answer a node that syncs from this node as its seed node, or that asks
+/// this node to take a replica of an artifact.
+pub async fn handle_request_seed_sync(
+    mut p2p_client: Client,
+    mut artifact_service: ArtifactService,
+    peer: &PeerId,
+    request: SeedSyncRequest,
+    channel: ResponseChannel<SeedSyncResponse>,
+) -> anyhow::Result<()> {
+    debug!("Handling seed sync request: {:?}", request);
+    let response = artifact_service.respond_seed_sync(peer, request).await?;
+
+    p2p_client.respond_seed_sync(response, channel).await
+}
@@ -0,0 +1,85 @@
+/*
+   Copyright 2021 JFrog Ltd
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+*/
+
+//! The node API that is used by the CLI and the web UI. A node that is built
+//! without the `web-ui` feature does not open a listener for it.
+
+use pyrsia::alert_service::service::AlertService;
+use pyrsia::artifact_service::service::ArtifactService;
+use pyrsia::build_service::secrets::SecretStore;
+use pyrsia::network::client::Client;
+use pyrsia::subscription_service::service::SubscriptionService;
+use warp::Filter;
+
+#[cfg(feature = "web-ui")]
+pub fn make_node_api_routes(
+    artifact_service: ArtifactService,
+    p2p_client: Client,
+    secret_store: SecretStore,
+    subscription_service: SubscriptionService,
+    alert_service: AlertService,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    use log::info;
+    use pyrsia::node_api::routes::{
+        make_alert_routes, make_maintenance_routes, make_network_routes, make_node_routes,
+        make_peer_alias_routes, make_publisher_routes, make_secret_routes, make_seed_sync_routes,
+        make_stats_routes, make_subscription_routes, make_transparency_log_routes,
+        make_usage_routes,
+    };
+    use pyrsia::util::env_util::read_var;
+
+    let peer_aliases = p2p_client.peer_aliases.clone();
+    let usage_accounting = artifact_service.usage_accounting();
+    let node_routes = make_node_routes(artifact_service.clone(), p2p_client.clone());
+    let admin_token = Some(read_var("PYRSIA_ADMIN_TOKEN", "")).filter(|t| !t.is_empty());
+    if admin_token.is_none() {
+        info!("No admin token configured, secret management, package deprecation, peer aliases, maintenance announcements, alerts and seed sync are disabled");
+    }
+    let secret_routes = make_secret_routes(secret_store, admin_token.clone());
+    let peer_alias_routes = make_peer_alias_routes(peer_aliases, admin_token.clone());
+    let alert_routes = make_alert_routes(alert_service, admin_token.clone());
+    let transparency_log_routes = make_transparency_log_routes(artifact_service.clone());
+    let stats_routes = make_stats_routes(artifact_service.clone());
+    let usage_routes = make_usage_routes(usage_accounting);
+    let maintenance_routes = make_maintenance_routes(p2p_client.clone(), admin_token.clone());
+    let network_routes = make_network_routes(p2p_client);
+    let seed_sync_routes = make_seed_sync_routes(artifact_service.clone(), admin_token.clone());
+    let publisher_routes = make_publisher_routes(artifact_service, admin_token);
+    let subscription_routes = make_subscription_routes(subscription_service);
+    node_routes
+        .or(secret_routes)
+        .or(peer_alias_routes)
+        .or(alert_routes)
+        .or(transparency_log_routes)
+        .or(network_routes)
+        .or(maintenance_routes)
+        .or(stats_routes)
+        .or(usage_routes)
+        .or(publisher_routes)
+        .or(seed_sync_routes)
+        .or(subscription_routes)
+}
+
+#[cfg(not(feature = "web-ui"))]
+pub fn make_node_api_routes(
+    _artifact_service: ArtifactService,
+    _p2p_client: Client,
+    _secret_store: SecretStore,
+    _subscription_service: SubscriptionService,
+    _alert_service: AlertService,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    crate::facade::disabled_routes()
+}
@@ -0,0 +1,247 @@
+/*
+   Copyright 2021 JFrog Ltd
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+*/
+
+//! A minimal CBOR (RFC 8949) encoding of JSON values, as used by the
+//! export of the transparency log. Only the data model of JSON is
+//! supported: integers, floats, text, arrays, maps with text keys, booleans
+//! and null. Indefinite lengths, byte strings and tags are rejected when
+//! decoding.
+
+use serde_json::{Map, Number, Value};
+use std::io::{self, Read, Write};
+
+const MAJOR_UNSIGNED: u8 = 0;
+const MAJOR_NEGATIVE: u8 = 1;
+const MAJOR_TEXT: u8 = 3;
+const MAJOR_ARRAY: u8 = 4;
+const MAJOR_MAP: u8 = 5;
+const MAJOR_SIMPLE: u8 = 7;
+
+const SIMPLE_FALSE: u8 = 20;
+const SIMPLE_TRUE: u8 = 21;
+const SIMPLE_NULL: u8 = 22;
+const FLOAT_32: u8 = 26;
+const FLOAT_64: u8 = 27;
+
+/// Nested arrays and maps deeper than this are rejected when decoding.
+const MAX_DEPTH: usize = 64;
+
+/// Write `value` as a CBOR data item.
+pub fn write_value<W: Write>(writer: &mut W, value: &Value) -> io::Result<()> {
+    match value {
+        Value::Null => writer.write_all(&[(MAJOR_SIMPLE << 5) | SIMPLE_NULL]),
+        Value::Bool(false) => writer.write_all(&[(MAJOR_SIMPLE << 5) | SIMPLE_FALSE]),
+        Value::Bool(true) => writer.write_all(&[(MAJOR_SIMPLE << 5) | SIMPLE_TRUE]),
+        Value::Number(number) => {
+            if let Some(unsigned) = number.as_u64() {
+                write_head(writer, MAJOR_UNSIGNED, unsigned)
+            } else if let Some(signed) = number.as_i64() {
+                write_head(writer, MAJOR_NEGATIVE, !(signed as u64))
+            } else {
+                let float = number.as_f64().unwrap_or_default();
+                writer.write_all(&[(MAJOR_SIMPLE << 5) | FLOAT_64])?;
+                writer.write_all(&float.to_be_bytes())
+            }
+        }
+        Value::String(text) => {
+            write_head(writer, MAJOR_TEXT, text.len() as u64)?;
+            writer.write_all(text.as_bytes())
+        }
+        Value::Array(items) => {
+            write_head(writer, MAJOR_ARRAY, items.len() as u64)?;
+            items.iter().try_for_each(|item| write_value(writer, item))
+        }
+        Value::Object(entries) => {
+            write_head(writer, MAJOR_MAP, entries.len() as u64)?;
+            entries.iter().try_for_each(|(key, value)| {
+                write_head(writer, MAJOR_TEXT, key.len() as u64)?;
+                writer.write_all(key.as_bytes())?;
+                write_value(writer, value)
+            })
+        }
+    }
+}
+
+/// Whether `initial_byte` starts a CBOR map.
+pub fn starts_map(initial_byte: u8) -> bool {
+    initial_byte >> 5 == MAJOR_MAP
+}
+
+/// Read the next CBOR data item, or `None` at the end of `reader`.
+pub fn read_value<R: Read>(reader: &mut R) -> io::Result<Option<Value>> {
+    let mut initial_byte = [0u8; 1];
+    if reader.read(&mut initial_byte)? == 0 {
+        return Ok(None);
+    }
+    read_item(reader, initial_byte[0], 0).map(Some)
+}
+
+fn write_head<W: Write>(writer: &mut W, major_type: u8, argument: u64) -> io::Result<()> {
+    let major_type = major_type << 5;
+    if argument < 24 {
+        writer.write_all(&[major_type | argument as u8])
+    } else if argument <= u8::MAX as u64 {
+        writer.write_all(&[major_type | 24, argument as u8])
+    } else if argument <= u16::MAX as u64 {
+        writer.write_all(&[major_type | 25])?;
+        writer.write_all(&(argument as u16).to_be_bytes())
+    } else if argument <= u32::MAX as u64 {
+        writer.write_all(&[major_type | 26])?;
+        writer.write_all(&(argument as u32).to_be_bytes())
+    } else {
+        writer.write_all(&[major_type | 27])?;
+        writer.write_all(&argument.to_be_bytes())
+    }
+}
+
+fn read_item<R: Read>(reader: &mut R, initial_byte: u8, depth: usize) -> io::Result<Value> {
+    if depth > MAX_DEPTH {
+        return Err(invalid_data("CBOR data item is nested too deeply"));
+    }
+
+    let major_type = initial_byte >> 5;
+    let additional_info = initial_byte & 0x1f;
+    if major_type == MAJOR_SIMPLE {
+        return match additional_info {
+            SIMPLE_FALSE => Ok(Value::Bool(false)),
+            SIMPLE_TRUE => Ok(Value::Bool(true)),
+            SIMPLE_NULL => Ok(Value::Null),
+            FLOAT_32 => float_value(f32::from_be_bytes(read_array(reader)?) as f64),
+            FLOAT_64 => float_value(f64::from_be_bytes(read_array(reader)?)),
+            _ => Err(invalid_data("unsupported CBOR simple value")),
+        };
+    }
+
+    let argument = read_argument(reader, additional_info)?;
+    match major_type {
+        MAJOR_UNSIGNED => Ok(Value::from(argument)),
+        MAJOR_NEGATIVE => i64::try_from(argument)
+            .map(|argument| Value::from(-1 - argument))
+            .map_err(|_| invalid_data("CBOR negative integer is out of range")),
+        MAJOR_TEXT => read_text(reader, argument).map(Value::String),
+        MAJOR_ARRAY => (0..argument)
+            .map(|_| read_nested(reader, depth))
+            .collect::<io::Result<Vec<_>>>()
+            .map(Value::Array),
+        MAJOR_MAP => {
+            let mut entries = Map::new();
+            for _ in 0..argument {
+                let key = match read_nested(reader, depth)? {
+                    Value::String(key) => key,
+                    _ => return Err(invalid_data("CBOR map key is not a text string")),
+                };
+                entries.insert(key, read_nested(reader, depth)?);
+            }
+            Ok(Value::Object(entries))
+        }
+        _ => Err(invalid_data("unsupported CBOR major type")),
+    }
+}
+
+fn read_nested<R: Read>(reader: &mut R, depth: usize) -> io::Result<Value> {
+    let [initial_byte] = read_array(reader)?;
+    read_item(reader, initial_byte, depth + 1)
+}
+
+fn read_argument<R: Read>(reader: &mut R, additional_info: u8) -> io::Result<u64> {
+    match additional_info {
+        0..=23 => Ok(additional_info as u64),
+        24 => Ok(u8::from_be_bytes(read_array(reader)?) as u64),
+        25 => Ok(u16::from_be_bytes(read_array(reader)?) as u64),
+        26 => Ok(u32::from_be_bytes(read_array(reader)?) as u64),
+        27 => Ok(u64::from_be_bytes(read_array(reader)?)),
+        _ => Err(invalid_data(
+            "indefinite length CBOR items are not supported",
+        )),
+    }
+}
+
+fn read_text<R: Read>(reader: &mut R, len: u64) -> io::Result<String> {
+    // do not trust the length to allocate the buffer up front
+    let mut bytes = Vec::new();
+    reader.take(len).read_to_end(&mut bytes)?;
+    if (bytes.len() as u64) < len {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    String::from_utf8(bytes).map_err(|_| invalid_data("CBOR text string is not valid UTF-8"))
+}
+
+fn read_array<R: Read, const N: usize>(reader: &mut R) -> io::Result<[u8; N]> {
+    let mut bytes = [0u8; N];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+fn float_value(float: f64) -> io::Result<Value> {
+    Number::from_f64(float)
+        .map(Value::Number)
+        .ok_or_else(|| invalid_data("CBOR float is not a finite number"))
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+#[cfg(not(tarpaulin_include))]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_write_value() {
+        let mut bytes = Vec::new();
+        write_value(&mut bytes, &json!({"a": [1, -1, 500, true, null]})).unwrap();
+        assert_eq!(
+            bytes,
+            vec![0xa1, 0x61, b'a', 0x85, 0x01, 0x20, 0x19, 0x01, 0xf4, 0xf5, 0xf6]
+        );
+    }
+
+    #[test]
+    fn test_read_written_values() {
+        let values = vec![
+            json!({
+                "id": "1",
+                "timestamp": 1676367000u64,
+                "nested": {"list": ["x", 1.5, -70000, false]},
+                "successor": null,
+            }),
+            json!("a text of more than twenty-three bytes"),
+        ];
+        let mut bytes = Vec::new();
+        for value in &values {
+            write_value(&mut bytes, value).unwrap();
+        }
+
+        let mut reader = bytes.as_slice();
+        assert_eq!(read_value(&mut reader).unwrap(), Some(values[0].clone()));
+        assert_eq!(read_value(&mut reader).unwrap(), Some(values[1].clone()));
+        assert_eq!(read_value(&mut reader).unwrap(), None);
+    }
+
+    #[test]
+    fn test_read_invalid_values() {
+        for bytes in [
+            vec![0x5f],
+            vec![0x7f],
+            vec![0xa1, 0x01, 0x01],
+            vec![0x63, b'a'],
+        ] {
+            assert!(read_value(&mut bytes.as_slice()).is_err(), "{:?}", bytes);
+        }
+    }
+}
@@ -0,0 +1,413 @@
+/*
+   Copyright 2021 JFrog Ltd
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+*/
+
+//! A Merkle tree over the transparency logs, as used by Certificate
+//! Transparency (RFC 6962). The leaves and inner nodes are hashed with
+//! sha256 and different prefixes, so that a leaf can never be passed off as
+//! an inner node:
+//!
+//! * leaf hash: `sha256(0x00 || leaf)`
+//! * node hash: `sha256(0x01 || left || right)`
+//!
+//! An inclusion proof lets an auditor verify that a leaf is part of a tree
+//! with a known root hash from only `log2(tree size)` hashes, without
+//! downloading the whole log. A consistency proof lets a monitor verify that
+//! a tree only appended leaves to an older tree, i.e. that no history was
+//! rewritten in between.
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+pub type Hash = [u8; 32];
+
+const LEAF_PREFIX: u8 = 0x00;
+const NODE_PREFIX: u8 = 0x01;
+
+/// The size and root hash of a Merkle tree. Nodes with the same tree head
+/// have the same transparency logs in the same order.
+#[derive(Clone, Debug, Deserialize, Serialize, Eq, PartialEq)]
+pub struct TreeHead {
+    pub tree_size: u64,
+    /// The hex encoded root hash.
+    pub root_hash: String,
+}
+
+/// Proves that the leaf at `leaf_index` is included in the tree with
+/// `tree_size` leaves and `root_hash`.
+#[derive(Clone, Debug, Deserialize, Serialize, Eq, PartialEq)]
+pub struct InclusionProof {
+    pub leaf_index: u64,
+    pub tree_size: u64,
+    /// The hex encoded root hash.
+    pub root_hash: String,
+    /// The hex encoded hashes of the siblings on the path from the leaf to
+    /// the root, starting at the leaf.
+    pub audit_path: Vec<String>,
+}
+
+impl InclusionProof {
+    /// Verify that `leaf` is included in the tree of this proof.
+    pub fn verify(&self, leaf: &[u8]) -> bool {
+        let (Ok(root_hash), Ok(audit_path)) = (
+            decode_hash(&self.root_hash),
+            self.audit_path
+                .iter()
+                .map(|hash| decode_hash(hash))
+                .collect::<Result<Vec<Hash>, _>>(),
+        ) else {
+            return false;
+        };
+        verify_inclusion(
+            &leaf_hash(leaf),
+            self.leaf_index,
+            self.tree_size,
+            &audit_path,
+            &root_hash,
+        )
+    }
+}
+
+/// Proves that the tree with `new_size` leaves and `new_root_hash` extends
+/// the tree with `old_size` leaves and `old_root_hash`.
+#[derive(Clone, Debug, Deserialize, Serialize, Eq, PartialEq)]
+pub struct ConsistencyProof {
+    pub old_size: u64,
+    /// The hex encoded root hash of the old tree.
+    pub old_root_hash: String,
+    pub new_size: u64,
+    /// The hex encoded root hash of the new tree.
+    pub new_root_hash: String,
+    /// The hex encoded hashes of the subtrees that prove the consistency.
+    pub proof: Vec<String>,
+}
+
+impl ConsistencyProof {
+    pub fn verify(&self) -> bool {
+        let (Ok(old_root_hash), Ok(new_root_hash), Ok(proof)) = (
+            decode_hash(&self.old_root_hash),
+            decode_hash(&self.new_root_hash),
+            self.proof
+                .iter()
+                .map(|hash| decode_hash(hash))
+                .collect::<Result<Vec<Hash>, _>>(),
+        ) else {
+            return false;
+        };
+        verify_consistency(
+            self.old_size,
+            self.new_size,
+            &old_root_hash,
+            &new_root_hash,
+            &proof,
+        )
+    }
+}
+
+pub fn leaf_hash(leaf: &[u8]) -> Hash {
+    let mut hasher = Sha256::new();
+    hasher.update([LEAF_PREFIX]);
+    hasher.update(leaf);
+    hasher.finalize().into()
+}
+
+fn node_hash(left: &Hash, right: &Hash) -> Hash {
+    let mut hasher = Sha256::new();
+    hasher.update([NODE_PREFIX]);
+    hasher.update(left);
+    hasher.update(right);
+    hasher.finalize().into()
+}
+
+/// The root hash of the tree with the leaves with `leaf_hashes`.
+pub fn root_hash(leaf_hashes: &[Hash]) -> Hash {
+    match leaf_hashes.len() {
+        0 => Sha256::digest([]).into(),
+        1 => leaf_hashes[0],
+        size => {
+            let split = split_point(size);
+            node_hash(
+                &root_hash(&leaf_hashes[..split]),
+                &root_hash(&leaf_hashes[split..]),
+            )
+        }
+    }
+}
+
+/// The audit path of the leaf at `leaf_index`, or `None` when the tree has
+/// no such leaf.
+pub fn audit_path(leaf_hashes: &[Hash], leaf_index: usize) -> Option<Vec<Hash>> {
+    if leaf_index >= leaf_hashes.len() {
+        return None;
+    }
+    let (mut leaf_hashes, mut leaf_index) = (leaf_hashes, leaf_index);
+    let mut siblings = vec![];
+    while leaf_hashes.len() > 1 {
+        let split = split_point(leaf_hashes.len());
+        if leaf_index < split {
+            siblings.push(&leaf_hashes[split..]);
+            leaf_hashes = &leaf_hashes[..split];
+        } else {
+            siblings.push(&leaf_hashes[..split]);
+            leaf_hashes = &leaf_hashes[split..];
+            leaf_index -= split;
+        }
+    }
+    // the siblings were collected from the root down, the path starts at the leaf
+    Some(siblings.into_iter().rev().map(root_hash).collect())
+}
+
+/// Verify an audit path as described in RFC 9162, section 2.1.3.2.
+pub fn verify_inclusion(
+    leaf_hash: &Hash,
+    leaf_index: u64,
+    tree_size: u64,
+    audit_path: &[Hash],
+    root_hash: &Hash,
+) -> bool {
+    if leaf_index >= tree_size {
+        return false;
+    }
+    let (mut index, mut last_index) = (leaf_index, tree_size - 1);
+    let mut hash = *leaf_hash;
+    for sibling in audit_path {
+        if last_index == 0 {
+            return false;
+        }
+        if index & 1 == 1 || index == last_index {
+            hash = node_hash(sibling, &hash);
+            while index & 1 == 0 && index != 0 {
+                index >>= 1;
+                last_index >>= 1;
+            }
+        } else {
+            hash = node_hash(&hash, sibling);
+        }
+        index >>= 1;
+        last_index >>= 1;
+    }
+    last_index == 0 && hash == *root_hash
+}
+
+/// The consistency proof of the tree with the first `old_size` of
+/// `leaf_hashes` and the tree with all of them, as described in RFC 6962,
+/// section 2.1.2, or `None` when the new tree is smaller than the old one.
+pub fn consistency_proof(leaf_hashes: &[Hash], old_size: usize) -> Option<Vec<Hash>> {
+    if old_size > leaf_hashes.len() {
+        return None;
+    }
+    if old_size == 0 {
+        return Some(vec![]);
+    }
+    let mut proof = vec![];
+    subproof(old_size, leaf_hashes, true, &mut proof);
+    Some(proof)
+}
+
+fn subproof(old_size: usize, leaf_hashes: &[Hash], complete: bool, proof: &mut Vec<Hash>) {
+    if old_size == leaf_hashes.len() {
+        // the root of the old tree is known to the verifier
+        if !complete {
+            proof.push(root_hash(leaf_hashes));
+        }
+        return;
+    }
+    let split = split_point(leaf_hashes.len());
+    if old_size <= split {
+        subproof(old_size, &leaf_hashes[..split], complete, proof);
+        proof.push(root_hash(&leaf_hashes[split..]));
+    } else {
+        subproof(old_size - split, &leaf_hashes[split..], false, proof);
+        proof.push(root_hash(&leaf_hashes[..split]));
+    }
+}
+
+/// Verify a consistency proof as described in RFC 9162, section 2.1.4.2.
+pub fn verify_consistency(
+    old_size: u64,
+    new_size: u64,
+    old_root_hash: &Hash,
+    new_root_hash: &Hash,
+    proof: &[Hash],
+) -> bool {
+    if old_size > new_size {
+        return false;
+    }
+    if old_size == new_size {
+        return proof.is_empty() && old_root_hash == new_root_hash;
+    }
+    if old_size == 0 {
+        return proof.is_empty();
+    }
+
+    let mut proof = proof.to_vec();
+    if old_size.is_power_of_two() {
+        proof.insert(0, *old_root_hash);
+    }
+    let (mut index, mut last_index) = (old_size - 1, new_size - 1);
+    while index & 1 == 1 {
+        index >>= 1;
+        last_index >>= 1;
+    }
+    let Some((first, rest)) = proof.split_first() else {
+        return false;
+    };
+    let (mut old_hash, mut new_hash) = (*first, *first);
+    for hash in rest {
+        if last_index == 0 {
+            return false;
+        }
+        if index & 1 == 1 || index == last_index {
+            old_hash = node_hash(hash, &old_hash);
+            new_hash = node_hash(hash, &new_hash);
+            while index & 1 == 0 && index != 0 {
+                index >>= 1;
+                last_index >>= 1;
+            }
+        } else {
+            new_hash = node_hash(&new_hash, hash);
+        }
+        index >>= 1;
+        last_index >>= 1;
+    }
+    last_index == 0 && old_hash == *old_root_hash && new_hash == *new_root_hash
+}
+
+// The largest power of two smaller than size.
+fn split_point(size: usize) -> usize {
+    let mut split = 1;
+    while split * 2 < size {
+        split *= 2;
+    }
+    split
+}
+
+fn decode_hash(hash: &str) -> Result<Hash, hex::FromHexError> {
+    let mut decoded = [0; 32];
+    hex::decode_to_slice(hash, &mut decoded)?;
+    Ok(decoded)
+}
+
+#[cfg(test)]
+#[cfg(not(tarpaulin_include))]
+mod tests {
+    use super::*;
+
+    fn leaves(size: usize) -> Vec<Hash> {
+        (0..size)
+            .map(|leaf| leaf_hash(leaf.to_string().as_bytes()))
+            .collect()
+    }
+
+    #[test]
+    fn test_root_hash() {
+        assert_eq!(
+            hex::encode(root_hash(&[])),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        let leaf_hashes = leaves(3);
+        assert_eq!(root_hash(&leaf_hashes[..1]), leaf_hashes[0]);
+        assert_eq!(
+            root_hash(&leaf_hashes),
+            node_hash(
+                &node_hash(&leaf_hashes[0], &leaf_hashes[1]),
+                &leaf_hashes[2]
+            )
+        );
+    }
+
+    #[test]
+    fn test_audit_path_verifies_for_every_leaf() {
+        for size in 1..=17 {
+            let leaf_hashes = leaves(size);
+            let root = root_hash(&leaf_hashes);
+            for (index, leaf) in leaf_hashes.iter().enumerate() {
+                let path = audit_path(&leaf_hashes, index).unwrap();
+                assert!(verify_inclusion(
+                    leaf,
+                    index as u64,
+                    size as u64,
+                    &path,
+                    &root
+                ));
+                assert!(!verify_inclusion(
+                    &leaf_hash(b"other"),
+                    index as u64,
+                    size as u64,
+                    &path,
+                    &root
+                ));
+            }
+        }
+        assert_eq!(audit_path(&leaves(3), 3), None);
+    }
+
+    #[test]
+    fn test_consistency_proof_verifies_for_every_old_size() {
+        let leaf_hashes = leaves(17);
+        for new_size in 1..=leaf_hashes.len() {
+            let new_tree = &leaf_hashes[..new_size];
+            let new_root = root_hash(new_tree);
+            for old_size in 0..=new_size {
+                let old_root = root_hash(&leaf_hashes[..old_size]);
+                let proof = consistency_proof(new_tree, old_size).unwrap();
+                assert!(verify_consistency(
+                    old_size as u64,
+                    new_size as u64,
+                    &old_root,
+                    &new_root,
+                    &proof
+                ));
+                if old_size > 0 && old_size < new_size {
+                    let rewritten_root = root_hash(&leaves(old_size + 1)[1..]);
+                    assert!(!verify_consistency(
+                        old_size as u64,
+                        new_size as u64,
+                        &rewritten_root,
+                        &new_root,
+                        &proof
+                    ));
+                }
+            }
+        }
+        assert_eq!(consistency_proof(&leaves(3), 4), None);
+    }
+
+    #[test]
+    fn test_inclusion_proof_verify() {
+        let leaf_hashes: Vec<Hash> = ["a", "b", "c", "d", "e"]
+            .iter()
+            .map(|leaf| leaf_hash(leaf.as_bytes()))
+            .collect();
+        let proof = InclusionProof {
+            leaf_index: 2,
+            tree_size: 5,
+            root_hash: hex::encode(root_hash(&leaf_hashes)),
+            audit_path: audit_path(&leaf_hashes, 2)
+                .unwrap()
+                .iter()
+                .map(hex::encode)
+                .collect(),
+        };
+
+        assert!(proof.verify(b"c"));
+        assert!(!proof.verify(b"d"));
+        assert!(!InclusionProof {
+            tree_size: 4,
+            ..proof.clone()
+        }
+        .verify(b"c"));
+    }
+}
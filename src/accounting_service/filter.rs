@@ -0,0 +1,157 @@
+/*
+   Copyright 2021 JFrog Ltd
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+*/
+
+use super::service::{UsageAccounting, UsageSubject};
+use crate::docker::error_util::{RegistryError, BUILD_ID_HEADER};
+use hyper::body::HttpBody;
+use warp::filters::path::Peek;
+use warp::http::header::CONTENT_LENGTH;
+use warp::reply::Response;
+use warp::{Filter, Rejection, Reply};
+
+/// A request that was admitted by [`enforce_usage_quotas`], whose response
+/// is accounted to the API key of the request and the namespace of the
+/// requested package.
+#[derive(Clone, Debug)]
+pub struct MeteredRequest {
+    usage_accounting: UsageAccounting,
+    api_key: Option<UsageSubject>,
+    subjects: Vec<UsageSubject>,
+}
+
+impl MeteredRequest {
+    /// Account the bytes of a successful response and the build it started,
+    /// if any, and return the response.
+    pub fn record_response(self, reply: impl Reply) -> Response {
+        let response = reply.into_response();
+        if response.status().is_success() {
+            let bytes = response.body().size_hint().exact().or_else(|| {
+                response
+                    .headers()
+                    .get(CONTENT_LENGTH)
+                    .and_then(|value| value.to_str().ok())
+                    .and_then(|value| value.parse().ok())
+            });
+            if let Some(bytes) = bytes.filter(|bytes| *bytes > 0) {
+                self.usage_accounting
+                    .record_bytes_served(&self.subjects, bytes);
+            }
+        }
+        if let (Some(build_id), Some(api_key)) = (
+            response
+                .headers()
+                .get(BUILD_ID_HEADER)
+                .and_then(|value| value.to_str().ok()),
+            self.api_key,
+        ) {
+            self.usage_accounting
+                .record_build_requested(build_id, api_key);
+        }
+        response
+    }
+}
+
+/// A filter that refuses requests with 429 Too Many Requests when a quota of
+/// the API key that is passed as bearer token, or of the namespace of the
+/// requested package, is used up. Requests to the build endpoints are also
+/// refused when a build minutes quota is used up.
+pub fn enforce_usage_quotas(
+    usage_accounting: UsageAccounting,
+) -> impl Filter<Extract = (MeteredRequest,), Error = Rejection> + Clone {
+    warp::header::optional::<String>("authorization")
+        .and(warp::path::peek())
+        .and_then(move |authorization: Option<String>, path: Peek| {
+            let usage_accounting = usage_accounting.clone();
+            async move {
+                let segments: Vec<&str> = path.segments().collect();
+                let api_key = authorization
+                    .as_deref()
+                    .and_then(|authorization| authorization.strip_prefix("Bearer "))
+                    .filter(|api_key| !api_key.trim().is_empty())
+                    .map(UsageSubject::api_key);
+                let subjects: Vec<UsageSubject> = api_key
+                    .iter()
+                    .cloned()
+                    .chain(UsageSubject::namespace_of_path(&segments))
+                    .collect();
+                let starts_build = segments.first() == Some(&"build");
+
+                usage_accounting
+                    .try_admit(&subjects, starts_build)
+                    .map_err(|e| warp::reject::custom(RegistryError::from(e)))?;
+                Ok::<_, Rejection>(MeteredRequest {
+                    usage_accounting,
+                    api_key,
+                    subjects,
+                })
+            }
+        })
+}
+
+#[cfg(test)]
+#[cfg(not(tarpaulin_include))]
+mod tests {
+    use super::*;
+    use crate::accounting_service::service::UsageLimits;
+    use crate::docker::error_util::custom_recover;
+
+    #[tokio::test]
+    async fn test_enforce_usage_quotas() {
+        let usage_accounting = UsageAccounting::new(
+            UsageLimits {
+                requests_per_minute: Some(100),
+                ..Default::default()
+            },
+            UsageLimits {
+                bytes_served_per_day: Some(10),
+                ..Default::default()
+            },
+        );
+        let routes = enforce_usage_quotas(usage_accounting.clone())
+            .and(warp::path!("v2" / String / "blobs" / String))
+            .map(|metered_request: MeteredRequest, _name, _digest| {
+                metered_request.record_response("0123456789")
+            })
+            .recover(custom_recover);
+
+        let response = warp::test::request()
+            .path("/v2/alpine/blobs/sha256:1234")
+            .header("Authorization", "Bearer key")
+            .reply(&routes)
+            .await;
+        assert_eq!(response.status(), 200);
+
+        let response = warp::test::request()
+            .path("/v2/alpine/blobs/sha256:1234")
+            .header("Authorization", "Bearer other_key")
+            .reply(&routes)
+            .await;
+        assert_eq!(response.status(), 429);
+        assert!(response.headers().contains_key("Retry-After"));
+        assert!(String::from_utf8_lossy(response.body()).contains("namespace:library"));
+
+        let metrics = usage_accounting.metrics();
+        assert_eq!(metrics.admitted_requests, 1);
+        assert_eq!(
+            metrics
+                .usage
+                .get("namespace:library")
+                .unwrap()
+                .bytes_served_today,
+            10
+        );
+    }
+}
@@ -0,0 +1,513 @@
+/*
+   Copyright 2021 JFrog Ltd
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+*/
+
+use crate::artifact_service::model::PackageType;
+use crate::util::time_util::now;
+use log::debug;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use thiserror::Error;
+
+const SECS_PER_MINUTE: u64 = 60;
+const SECS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// The namespace of Docker images without an organization, like `alpine`.
+const DEFAULT_DOCKER_NAMESPACE: &str = "library";
+
+/// The maximum usage of a single API key or namespace, `None` means
+/// unlimited.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, Eq, PartialEq)]
+pub struct UsageLimits {
+    pub requests_per_minute: Option<u64>,
+    pub build_minutes_per_day: Option<u64>,
+    pub bytes_served_per_day: Option<u64>,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[serde(rename_all = "snake_case")]
+pub enum Quota {
+    RequestsPerMinute,
+    BuildMinutesPerDay,
+    BytesServedPerDay,
+}
+
+impl fmt::Display for Quota {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let quota = match self {
+            Quota::RequestsPerMinute => "requests per minute",
+            Quota::BuildMinutesPerDay => "build minutes per day",
+            Quota::BytesServedPerDay => "bytes served per day",
+        };
+        write!(f, "{}", quota)
+    }
+}
+
+/// Who the usage is accounted to: the holder of an API key, which is only
+/// known by its hash, or the namespace of the requested packages.
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum UsageSubject {
+    ApiKey(String),
+    Namespace(String),
+}
+
+impl UsageSubject {
+    pub fn api_key(api_key: &str) -> Self {
+        let digest = hex::encode(Sha256::digest(api_key.as_bytes()));
+        UsageSubject::ApiKey(digest[..16].to_owned())
+    }
+
+    pub fn namespace(namespace: &str) -> Self {
+        UsageSubject::Namespace(namespace.to_owned())
+    }
+
+    /// The namespace of a package: the organization of a Docker image or the
+    /// group id of a Maven artifact.
+    pub fn namespace_of_package(package_type: PackageType, package_specific_id: &str) -> Self {
+        let namespace = match package_type {
+            PackageType::Docker => package_specific_id
+                .split_once('/')
+                .map_or(DEFAULT_DOCKER_NAMESPACE, |(namespace, _)| namespace),
+            PackageType::Maven2 => package_specific_id
+                .split_once(':')
+                .map_or(package_specific_id, |(group_id, _)| group_id),
+        };
+        UsageSubject::namespace(namespace)
+    }
+
+    /// The namespace of the package that a request to a facade of the node
+    /// is about, given the segments of the request path.
+    pub fn namespace_of_path(segments: &[&str]) -> Option<Self> {
+        match segments {
+            // /v2/<name>/(manifests|blobs|tags)/<reference>
+            ["v2", name @ .., kind, _] if ["manifests", "blobs", "tags"].contains(kind) => {
+                match name {
+                    [] => None,
+                    [_] => Some(UsageSubject::namespace(DEFAULT_DOCKER_NAMESPACE)),
+                    [namespace, ..] => Some(UsageSubject::namespace(namespace)),
+                }
+            }
+            // /maven2/<group path>/<artifact id>/<version>/<file>
+            ["maven2", group @ .., _, _, _] if !group.is_empty() => {
+                Some(UsageSubject::namespace(&group.join(".")))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for UsageSubject {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UsageSubject::ApiKey(key_hash) => write!(f, "api-key:{}", key_hash),
+            UsageSubject::Namespace(namespace) => write!(f, "namespace:{}", namespace),
+        }
+    }
+}
+
+/// A request was refused because a quota of `subject` is used up until the
+/// current minute or day ends, after `retry_after`.
+#[derive(Clone, Debug, Error, Eq, PartialEq)]
+#[error("The quota of {limit} {quota} of {subject} is exceeded with {used} used, retry after {} seconds", .retry_after.as_secs())]
+pub struct QuotaExceeded {
+    pub subject: UsageSubject,
+    pub quota: Quota,
+    pub used: u64,
+    pub limit: u64,
+    pub retry_after: Duration,
+}
+
+/// The usage of a subject in the current minute and day.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, Eq, PartialEq)]
+pub struct SubjectUsage {
+    pub requests_this_minute: u64,
+    pub build_minutes_today: u64,
+    pub bytes_served_today: u64,
+}
+
+/// The configured quotas, the number of admitted and refused requests and
+/// the current usage of every subject.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, Eq, PartialEq)]
+pub struct UsageMetrics {
+    pub per_api_key: UsageLimits,
+    pub per_namespace: UsageLimits,
+    pub admitted_requests: u64,
+    pub refused_requests: BTreeMap<Quota, u64>,
+    pub usage: BTreeMap<String, SubjectUsage>,
+}
+
+#[derive(Clone, Debug, Default)]
+struct UsageWindows {
+    minute: u64,
+    requests: u64,
+    day: u64,
+    build_secs: u64,
+    bytes_served: u64,
+}
+
+impl UsageWindows {
+    fn roll(&mut self, now: u64) {
+        if self.minute != now / SECS_PER_MINUTE {
+            self.minute = now / SECS_PER_MINUTE;
+            self.requests = 0;
+        }
+        if self.day != now / SECS_PER_DAY {
+            self.day = now / SECS_PER_DAY;
+            self.build_secs = 0;
+            self.bytes_served = 0;
+        }
+    }
+
+    /// The usage in the windows of `now`, without rolling them.
+    fn current(&self, now: u64) -> SubjectUsage {
+        let mut windows = self.clone();
+        windows.roll(now);
+        SubjectUsage {
+            requests_this_minute: windows.used(Quota::RequestsPerMinute),
+            build_minutes_today: windows.used(Quota::BuildMinutesPerDay),
+            bytes_served_today: windows.used(Quota::BytesServedPerDay),
+        }
+    }
+
+    fn used(&self, quota: Quota) -> u64 {
+        match quota {
+            Quota::RequestsPerMinute => self.requests,
+            Quota::BuildMinutesPerDay => self.build_secs / SECS_PER_MINUTE,
+            Quota::BytesServedPerDay => self.bytes_served,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct AccountingState {
+    usage: BTreeMap<UsageSubject, UsageWindows>,
+    day: u64,
+    admitted_requests: u64,
+    refused_requests: BTreeMap<Quota, u64>,
+    /// The API keys that requested the builds that are running.
+    build_requesters: HashMap<String, UsageSubject>,
+}
+
+impl AccountingState {
+    fn windows(&mut self, subject: &UsageSubject, now: u64) -> &mut UsageWindows {
+        // the subjects of previous days are forgotten once a day
+        if self.day != now / SECS_PER_DAY {
+            self.day = now / SECS_PER_DAY;
+            self.usage
+                .retain(|_, windows| windows.day == now / SECS_PER_DAY);
+        }
+        let windows = self.usage.entry(subject.clone()).or_default();
+        windows.roll(now);
+        windows
+    }
+}
+
+/// Tracks the requests, build minutes and served bytes of every API key and
+/// namespace, and enforces the quotas of a node that is shared by several
+/// teams. The usage is counted in fixed windows of a minute and a day. Clones
+/// share the same usage.
+#[derive(Clone, Debug, Default)]
+pub struct UsageAccounting {
+    per_api_key: UsageLimits,
+    per_namespace: UsageLimits,
+    state: Arc<Mutex<AccountingState>>,
+}
+
+impl UsageAccounting {
+    /// Create the accounting with the limits of every API key and of every
+    /// namespace.
+    pub fn new(per_api_key: UsageLimits, per_namespace: UsageLimits) -> Self {
+        UsageAccounting {
+            per_api_key,
+            per_namespace,
+            state: Default::default(),
+        }
+    }
+
+    /// Admit a request that is accounted to `subjects` and count it, unless
+    /// one of their request or served bytes quotas is used up. A request
+    /// that `starts_build` is also refused when a build minutes quota is used
+    /// up.
+    pub fn try_admit(
+        &self,
+        subjects: &[UsageSubject],
+        starts_build: bool,
+    ) -> Result<(), QuotaExceeded> {
+        self.try_admit_at(subjects, starts_build, now())
+    }
+
+    /// Check that none of `subjects` used up its build minutes quota, before
+    /// a build is requested for them.
+    pub fn check_build_quota(&self, subjects: &[UsageSubject]) -> Result<(), QuotaExceeded> {
+        let mut state = self.state.lock().unwrap();
+        self.check(&mut state, subjects, &[Quota::BuildMinutesPerDay], now())
+    }
+
+    /// Account `bytes` that were served to `subjects`.
+    pub fn record_bytes_served(&self, subjects: &[UsageSubject], bytes: u64) {
+        self.record_bytes_served_at(subjects, bytes, now())
+    }
+
+    /// Remember that the holder of an API key requested a build, so that the
+    /// duration of the build is accounted to it when it is finished.
+    pub fn record_build_requested(&self, build_id: &str, api_key: UsageSubject) {
+        self.state
+            .lock()
+            .unwrap()
+            .build_requesters
+            .insert(build_id.to_owned(), api_key);
+    }
+
+    /// Account the duration of a finished build to the namespace of the built
+    /// package and to the API key that requested it.
+    pub fn record_build_finished(&self, build_id: &str, namespace: UsageSubject, duration: u64) {
+        self.record_build_finished_at(build_id, namespace, duration, now())
+    }
+
+    pub fn metrics(&self) -> UsageMetrics {
+        let now = now();
+        let state = self.state.lock().unwrap();
+        let usage = state
+            .usage
+            .iter()
+            .filter(|(_, windows)| windows.day == now / SECS_PER_DAY)
+            .map(|(subject, windows)| (subject.to_string(), windows.current(now)))
+            .collect();
+
+        UsageMetrics {
+            per_api_key: self.per_api_key,
+            per_namespace: self.per_namespace,
+            admitted_requests: state.admitted_requests,
+            refused_requests: state.refused_requests.clone(),
+            usage,
+        }
+    }
+
+    fn try_admit_at(
+        &self,
+        subjects: &[UsageSubject],
+        starts_build: bool,
+        now: u64,
+    ) -> Result<(), QuotaExceeded> {
+        let quotas: &[Quota] = if starts_build {
+            &[
+                Quota::RequestsPerMinute,
+                Quota::BytesServedPerDay,
+                Quota::BuildMinutesPerDay,
+            ]
+        } else {
+            &[Quota::RequestsPerMinute, Quota::BytesServedPerDay]
+        };
+
+        let mut state = self.state.lock().unwrap();
+        self.check(&mut state, subjects, quotas, now)?;
+        for subject in subjects {
+            state.windows(subject, now).requests += 1;
+        }
+        state.admitted_requests += 1;
+        Ok(())
+    }
+
+    fn record_bytes_served_at(&self, subjects: &[UsageSubject], bytes: u64, now: u64) {
+        let mut state = self.state.lock().unwrap();
+        for subject in subjects {
+            let windows = state.windows(subject, now);
+            windows.bytes_served = windows.bytes_served.saturating_add(bytes);
+        }
+    }
+
+    fn record_build_finished_at(
+        &self,
+        build_id: &str,
+        namespace: UsageSubject,
+        duration: u64,
+        now: u64,
+    ) {
+        let mut state = self.state.lock().unwrap();
+        let subjects = std::iter::once(namespace).chain(state.build_requesters.remove(build_id));
+        for subject in subjects.collect::<Vec<_>>() {
+            state.windows(&subject, now).build_secs += duration;
+        }
+    }
+
+    fn check(
+        &self,
+        state: &mut AccountingState,
+        subjects: &[UsageSubject],
+        quotas: &[Quota],
+        now: u64,
+    ) -> Result<(), QuotaExceeded> {
+        for subject in subjects {
+            let limits = match subject {
+                UsageSubject::ApiKey(_) => &self.per_api_key,
+                UsageSubject::Namespace(_) => &self.per_namespace,
+            };
+            let windows = state.windows(subject, now);
+            let exceeded = quotas.iter().find_map(|quota| {
+                let limit = match quota {
+                    Quota::RequestsPerMinute => limits.requests_per_minute,
+                    Quota::BuildMinutesPerDay => limits.build_minutes_per_day,
+                    Quota::BytesServedPerDay => limits.bytes_served_per_day,
+                }?;
+                let used = windows.used(*quota);
+                let window = match quota {
+                    Quota::RequestsPerMinute => SECS_PER_MINUTE,
+                    _ => SECS_PER_DAY,
+                };
+                (used >= limit).then(|| QuotaExceeded {
+                    subject: subject.clone(),
+                    quota: *quota,
+                    used,
+                    limit,
+                    retry_after: Duration::from_secs(window - now % window),
+                })
+            });
+            if let Some(exceeded) = exceeded {
+                debug!("Refusing a request: {}", exceeded);
+                *state.refused_requests.entry(exceeded.quota).or_default() += 1;
+                return Err(exceeded);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+#[cfg(not(tarpaulin_include))]
+mod tests {
+    use super::*;
+
+    const NOW: u64 = 1_700_000_000;
+
+    #[test]
+    fn test_requests_per_minute() {
+        let usage_accounting = UsageAccounting::new(
+            UsageLimits {
+                requests_per_minute: Some(2),
+                ..Default::default()
+            },
+            Default::default(),
+        );
+        let subjects = [
+            UsageSubject::api_key("key"),
+            UsageSubject::namespace("myorg"),
+        ];
+        let minute_start = NOW - NOW % SECS_PER_MINUTE;
+
+        assert!(usage_accounting
+            .try_admit_at(&subjects, false, minute_start)
+            .is_ok());
+        assert!(usage_accounting
+            .try_admit_at(&subjects, false, minute_start + 1)
+            .is_ok());
+        let exceeded = usage_accounting
+            .try_admit_at(&subjects, false, minute_start + 20)
+            .unwrap_err();
+        assert_eq!(exceeded.subject, UsageSubject::api_key("key"));
+        assert_eq!(exceeded.quota, Quota::RequestsPerMinute);
+        assert_eq!(exceeded.retry_after, Duration::from_secs(40));
+
+        // other keys and the next minute are not affected
+        assert!(usage_accounting
+            .try_admit_at(&[UsageSubject::api_key("other")], false, minute_start + 20)
+            .is_ok());
+        assert!(usage_accounting
+            .try_admit_at(&subjects, false, minute_start + SECS_PER_MINUTE)
+            .is_ok());
+
+        let metrics = usage_accounting.metrics();
+        assert_eq!(metrics.admitted_requests, 4);
+        assert_eq!(
+            metrics.refused_requests.get(&Quota::RequestsPerMinute),
+            Some(&1)
+        );
+    }
+
+    #[test]
+    fn test_bytes_served_and_build_minutes_per_day() {
+        let usage_accounting = UsageAccounting::new(
+            Default::default(),
+            UsageLimits {
+                build_minutes_per_day: Some(10),
+                bytes_served_per_day: Some(1000),
+                ..Default::default()
+            },
+        );
+        let api_key = UsageSubject::api_key("key");
+        let namespace = UsageSubject::namespace("myorg");
+        let subjects = [api_key.clone(), namespace.clone()];
+
+        usage_accounting.record_build_requested("build", api_key.clone());
+        usage_accounting.record_build_finished_at("build", namespace.clone(), 10 * 60, NOW);
+        assert!(usage_accounting.try_admit_at(&subjects, false, NOW).is_ok());
+        let exceeded = usage_accounting
+            .try_admit_at(&subjects, true, NOW)
+            .unwrap_err();
+        assert_eq!(exceeded.subject, namespace);
+        assert_eq!(exceeded.quota, Quota::BuildMinutesPerDay);
+
+        usage_accounting.record_bytes_served_at(&subjects, 1000, NOW);
+        let exceeded = usage_accounting
+            .try_admit_at(&subjects, false, NOW)
+            .unwrap_err();
+        assert_eq!(exceeded.quota, Quota::BytesServedPerDay);
+        assert_eq!(exceeded.used, 1000);
+
+        assert!(usage_accounting
+            .try_admit_at(&subjects, true, NOW + SECS_PER_DAY)
+            .is_ok());
+    }
+
+    #[test]
+    fn test_namespaces() {
+        assert_eq!(
+            UsageSubject::namespace_of_path(&["v2", "alpine", "manifests", "3.16"]),
+            Some(UsageSubject::namespace("library"))
+        );
+        assert_eq!(
+            UsageSubject::namespace_of_path(&["v2", "myorg", "app", "blobs", "sha256:1234"]),
+            Some(UsageSubject::namespace("myorg"))
+        );
+        assert_eq!(
+            UsageSubject::namespace_of_path(&[
+                "maven2",
+                "com",
+                "google",
+                "guava",
+                "guava",
+                "31.1-jre",
+                "guava-31.1-jre.jar"
+            ]),
+            Some(UsageSubject::namespace("com.google.guava"))
+        );
+        assert_eq!(UsageSubject::namespace_of_path(&["status"]), None);
+
+        assert_eq!(
+            UsageSubject::namespace_of_package(PackageType::Docker, "library/alpine:3.16"),
+            UsageSubject::namespace("library")
+        );
+        assert_eq!(
+            UsageSubject::namespace_of_package(
+                PackageType::Maven2,
+                "com.google.guava:guava:31.1-jre"
+            ),
+            UsageSubject::namespace("com.google.guava")
+        );
+    }
+}
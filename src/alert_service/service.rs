@@ -0,0 +1,584 @@
+/*
+   Copyright 2021 JFrog Ltd
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+*/
+
+use crate::transparency_log::audit::Inconsistency;
+use crate::transparency_log::log::{Operation, TransparencyLog};
+use crate::util::time_util::now;
+use libp2p::PeerId;
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use thiserror::Error;
+
+const MAX_RECENT_ALERTS: usize = 100;
+const VOLUME_HISTORY_WINDOWS: usize = 6;
+
+#[derive(Debug, Error)]
+pub enum AlertError {
+    #[error("Invalid webhook url: {0}")]
+    InvalidWebhookUrl(String),
+}
+
+/// The kinds of suspicious ledger activity that raise an alert.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, Eq, PartialEq, strum_macros::Display)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum AnomalyKind {
+    /// a node was authorized that is not in the list of expected nodes
+    UnexpectedAuthorizedNode,
+    /// many artifacts or nodes were removed in a short period of time
+    MassRevocation,
+    /// multiple peers served content that does not match the logged hash
+    HashMismatch,
+    /// the number of transparency logs is far above the recent average
+    VolumeSpike,
+    /// a build was requested for an internal package that the internal
+    /// mapping source does not map
+    DependencyConfusion,
+    /// a block or transparency log on the blockchain failed the audit of a
+    /// node in monitor mode
+    LogInconsistency,
+    /// the publication of a docker tag was rejected because it would move an
+    /// immutable tag to another manifest
+    TagImmutabilityViolation,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct Alert {
+    pub id: String,
+    pub kind: AnomalyKind,
+    pub message: String,
+    pub timestamp: u64,
+    /// the ids of the transparency logs, artifacts or peers involved
+    pub details: Vec<String>,
+}
+
+/// The thresholds of the anomaly heuristics. Activity is counted in windows
+/// of `window`; transparency logs that are older than one window when they
+/// arrive, like those replayed while syncing the blockchain, are ignored.
+#[derive(Clone, Debug)]
+pub struct AnomalyThresholds {
+    pub window: Duration,
+    /// the number of revocations within a window that raises an alert
+    pub max_revocations: usize,
+    /// the number of distinct peers serving a mismatching artifact that raises an alert
+    pub min_mismatch_peers: usize,
+    /// how many times the average volume of the previous windows is a spike
+    pub spike_factor: f64,
+    /// the minimum number of logs within a window to be considered a spike
+    pub min_spike_volume: usize,
+}
+
+impl Default for AnomalyThresholds {
+    fn default() -> Self {
+        AnomalyThresholds {
+            window: Duration::from_secs(600),
+            max_revocations: 20,
+            min_mismatch_peers: 2,
+            spike_factor: 5.0,
+            min_spike_volume: 100,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct DetectorState {
+    revocations: VecDeque<(u64, String)>,
+    revocation_alerted_at: Option<u64>,
+    hash_mismatches: HashMap<String, HashSet<PeerId>>,
+    volume_window_start: u64,
+    volume_window_count: usize,
+    volume_history: VecDeque<usize>,
+    volume_alerted: bool,
+    recent_alerts: VecDeque<Alert>,
+}
+
+/// The alert service watches the transparency logs that arrive on the ledger
+/// and the integrity failures seen by this node for suspicious activity.
+/// Alerts are logged, posted to the configured webhooks and kept in memory
+/// so security teams can review the most recent ones.
+#[derive(Clone)]
+pub struct AlertService {
+    thresholds: AnomalyThresholds,
+    expected_authorized_nodes: HashSet<PeerId>,
+    webhook_urls: Vec<String>,
+    state: Arc<Mutex<DetectorState>>,
+    http_client: reqwest::Client,
+}
+
+impl AlertService {
+    pub fn new(webhook_urls: Vec<String>) -> Result<Self, AlertError> {
+        for webhook_url in &webhook_urls {
+            match url::Url::parse(webhook_url) {
+                Ok(url) if url.scheme() == "http" || url.scheme() == "https" => {}
+                _ => return Err(AlertError::InvalidWebhookUrl(webhook_url.clone())),
+            }
+        }
+
+        Ok(AlertService {
+            thresholds: Default::default(),
+            expected_authorized_nodes: Default::default(),
+            webhook_urls,
+            state: Default::default(),
+            http_client: reqwest::Client::new(),
+        })
+    }
+
+    pub fn with_thresholds(mut self, thresholds: AnomalyThresholds) -> Self {
+        self.thresholds = thresholds;
+        self
+    }
+
+    /// The nodes that are expected to be authorized. When no nodes are
+    /// expected, every authorization raises an alert.
+    pub fn with_expected_authorized_nodes(
+        mut self,
+        expected_authorized_nodes: impl IntoIterator<Item = PeerId>,
+    ) -> Self {
+        self.expected_authorized_nodes = expected_authorized_nodes.into_iter().collect();
+        self
+    }
+
+    /// Inspect a transparency log that arrived on the ledger and raise the
+    /// alerts it triggers.
+    pub fn inspect_transparency_log(&self, transparency_log: &TransparencyLog) -> Vec<Alert> {
+        let alerts = self.detect(transparency_log, now());
+        for alert in &alerts {
+            self.raise(alert.clone());
+        }
+        alerts
+    }
+
+    /// Record that `peer_id` served a copy of `artifact_id` that does not
+    /// match the hash in the transparency log. An alert is raised once
+    /// enough distinct peers served a mismatching copy.
+    pub fn record_hash_mismatch(&self, artifact_id: &str, peer_id: &PeerId) -> Option<Alert> {
+        let alert = {
+            let mut state = self.state.lock().unwrap();
+            let peers = state
+                .hash_mismatches
+                .entry(artifact_id.to_owned())
+                .or_default();
+            if !peers.insert(*peer_id) || peers.len() != self.thresholds.min_mismatch_peers {
+                return None;
+            }
+
+            new_alert(
+                AnomalyKind::HashMismatch,
+                format!(
+                    "{} peers served artifact {} with a hash that does not match the transparency log",
+                    peers.len(),
+                    artifact_id
+                ),
+                now(),
+                std::iter::once(artifact_id.to_owned())
+                    .chain(peers.iter().map(PeerId::to_string))
+                    .collect(),
+            )
+        };
+
+        self.raise(alert.clone());
+        Some(alert)
+    }
+
+    /// Record that a build of an internal package was refused because it
+    /// could have been resolved from a public source. Every refusal raises an
+    /// alert.
+    pub fn record_dependency_confusion(
+        &self,
+        package_specific_id: &str,
+        requester: Option<&str>,
+        reason: &str,
+    ) -> Alert {
+        let alert = new_alert(
+            AnomalyKind::DependencyConfusion,
+            format!(
+                "Build of internal package {} was refused: {}",
+                package_specific_id, reason
+            ),
+            now(),
+            std::iter::once(package_specific_id.to_owned())
+                .chain(requester.map(str::to_owned))
+                .collect(),
+        );
+
+        self.raise(alert.clone());
+        alert
+    }
+
+    /// Record that the publication of an immutable docker tag with another
+    /// hash than it was published with before was rejected. Every rejection
+    /// raises an alert.
+    pub fn record_tag_immutability_violation(
+        &self,
+        package_specific_artifact_id: &str,
+        published_hash: &str,
+        artifact_hash: &str,
+    ) -> Alert {
+        let alert = new_alert(
+            AnomalyKind::TagImmutabilityViolation,
+            format!(
+                "Publication of immutable tag {} with hash {} was rejected, it was published with hash {}",
+                package_specific_artifact_id, artifact_hash, published_hash
+            ),
+            now(),
+            vec![
+                package_specific_artifact_id.to_owned(),
+                published_hash.to_owned(),
+                artifact_hash.to_owned(),
+            ],
+        );
+
+        self.raise(alert.clone());
+        alert
+    }
+
+    /// Record an inconsistency that the audit of the blockchain found. Every
+    /// inconsistency raises an alert.
+    pub fn record_inconsistency(&self, inconsistency: &Inconsistency) -> Alert {
+        let alert = new_alert(
+            AnomalyKind::LogInconsistency,
+            inconsistency.to_string(),
+            now(),
+            inconsistency.details(),
+        );
+
+        self.raise(alert.clone());
+        alert
+    }
+
+    /// The most recent alerts, oldest first.
+    pub fn recent_alerts(&self) -> Vec<Alert> {
+        self.state
+            .lock()
+            .unwrap()
+            .recent_alerts
+            .iter()
+            .cloned()
+            .collect()
+    }
+
+    fn detect(&self, transparency_log: &TransparencyLog, now: u64) -> Vec<Alert> {
+        let window = self.thresholds.window.as_secs().max(1);
+        if transparency_log.timestamp + window < now {
+            return vec![];
+        }
+
+        let mut alerts = Vec::new();
+        let mut state = self.state.lock().unwrap();
+
+        if transparency_log.operation == Operation::AddNode {
+            let expected = PeerId::from_str(&transparency_log.node_id).map_or(false, |peer_id| {
+                self.expected_authorized_nodes.contains(&peer_id)
+            });
+            if !expected {
+                alerts.push(new_alert(
+                    AnomalyKind::UnexpectedAuthorizedNode,
+                    format!(
+                        "Node {} was authorized but is not an expected authorized node",
+                        transparency_log.node_id
+                    ),
+                    now,
+                    vec![
+                        transparency_log.id.clone(),
+                        transparency_log.node_id.clone(),
+                    ],
+                ));
+            }
+        }
+
+        if matches!(
+            transparency_log.operation,
+            Operation::RemoveArtifact | Operation::RevokeArtifact | Operation::RemoveNode
+        ) {
+            state
+                .revocations
+                .push_back((transparency_log.timestamp, transparency_log.id.clone()));
+            while matches!(state.revocations.front(), Some((timestamp, _)) if timestamp + window < now)
+            {
+                state.revocations.pop_front();
+            }
+            let recently_alerted = matches!(state.revocation_alerted_at, Some(alerted_at) if alerted_at + window > now);
+            if state.revocations.len() >= self.thresholds.max_revocations && !recently_alerted {
+                state.revocation_alerted_at = Some(now);
+                alerts.push(new_alert(
+                    AnomalyKind::MassRevocation,
+                    format!(
+                        "{} artifacts or nodes were removed within {} seconds",
+                        state.revocations.len(),
+                        window
+                    ),
+                    now,
+                    state.revocations.iter().map(|(_, id)| id.clone()).collect(),
+                ));
+            }
+        }
+
+        // close the windows that have passed since the last log
+        if state.volume_window_start == 0 {
+            state.volume_window_start = now;
+        }
+        while now >= state.volume_window_start + window {
+            let count = std::mem::take(&mut state.volume_window_count);
+            state.volume_history.push_back(count);
+            if state.volume_history.len() > VOLUME_HISTORY_WINDOWS {
+                state.volume_history.pop_front();
+            }
+            state.volume_window_start += window;
+            state.volume_alerted = false;
+        }
+        state.volume_window_count += 1;
+
+        let baseline = if state.volume_history.is_empty() {
+            0.0
+        } else {
+            state.volume_history.iter().sum::<usize>() as f64 / state.volume_history.len() as f64
+        };
+        let count = state.volume_window_count;
+        if !state.volume_alerted
+            && count >= self.thresholds.min_spike_volume
+            && count as f64 > self.thresholds.spike_factor * baseline
+        {
+            state.volume_alerted = true;
+            alerts.push(new_alert(
+                AnomalyKind::VolumeSpike,
+                format!(
+                    "{} transparency logs arrived within {} seconds, the recent average is {:.1}",
+                    count, window, baseline
+                ),
+                now,
+                vec![],
+            ));
+        }
+
+        alerts
+    }
+
+    fn raise(&self, alert: Alert) {
+        warn!("Anomaly detected ({}): {}", alert.kind, alert.message);
+
+        {
+            let mut state = self.state.lock().unwrap();
+            state.recent_alerts.push_back(alert.clone());
+            if state.recent_alerts.len() > MAX_RECENT_ALERTS {
+                state.recent_alerts.pop_front();
+            }
+        }
+
+        for webhook_url in self.webhook_urls.clone() {
+            let http_client = self.http_client.clone();
+            let alert = alert.clone();
+            tokio::spawn(async move {
+                if let Err(e) = http_client
+                    .post(&webhook_url)
+                    .json(&alert)
+                    .send()
+                    .await
+                    .and_then(|response| response.error_for_status())
+                {
+                    warn!(
+                        "Failed to deliver alert {} to {}: {}",
+                        alert.id, webhook_url, e
+                    );
+                }
+            });
+        }
+    }
+}
+
+fn new_alert(kind: AnomalyKind, message: String, timestamp: u64, details: Vec<String>) -> Alert {
+    Alert {
+        id: uuid::Uuid::new_v4().to_string(),
+        kind,
+        message,
+        timestamp,
+        details,
+    }
+}
+
+#[cfg(test)]
+#[cfg(not(tarpaulin_include))]
+mod tests {
+    use super::*;
+    use crate::artifact_service::model::PackageType;
+    use crate::transparency_log::log::AddArtifactRequest;
+    use httptest::{matchers, responders, Expectation, Server};
+
+    fn transparency_log(operation: Operation, timestamp: u64) -> TransparencyLog {
+        TransparencyLog {
+            operation,
+            timestamp,
+            ..TransparencyLog::from(AddArtifactRequest {
+                package_type: PackageType::Docker,
+                package_specific_id: "alpine:3.15.2".to_owned(),
+                num_artifacts: 1,
+                package_specific_artifact_id: "alpine:3.15.2".to_owned(),
+                artifact_hash: "hash".to_owned(),
+            })
+        }
+    }
+
+    fn kinds(alerts: &[Alert]) -> Vec<AnomalyKind> {
+        alerts.iter().map(|alert| alert.kind).collect()
+    }
+
+    #[tokio::test]
+    async fn test_unexpected_authorized_node_raises_alert() {
+        let expected_peer_id = PeerId::random();
+        let alert_service = AlertService::new(vec![])
+            .unwrap()
+            .with_expected_authorized_nodes([expected_peer_id]);
+
+        let mut add_node = transparency_log(Operation::AddNode, now());
+        add_node.node_id = expected_peer_id.to_string();
+        assert!(alert_service.inspect_transparency_log(&add_node).is_empty());
+
+        add_node.node_id = PeerId::random().to_string();
+        assert_eq!(
+            kinds(&alert_service.inspect_transparency_log(&add_node)),
+            vec![AnomalyKind::UnexpectedAuthorizedNode]
+        );
+
+        // logs replayed from the history of the ledger are not inspected
+        add_node.timestamp = now() - 3600;
+        assert!(alert_service.inspect_transparency_log(&add_node).is_empty());
+
+        assert_eq!(alert_service.recent_alerts().len(), 1);
+    }
+
+    #[test]
+    fn test_mass_revocation_raises_alert_once_per_window() {
+        let alert_service = AlertService::new(vec![])
+            .unwrap()
+            .with_thresholds(AnomalyThresholds {
+                max_revocations: 3,
+                ..Default::default()
+            });
+
+        let now = now();
+        let remove_artifact = transparency_log(Operation::RemoveArtifact, now);
+        let alerts: Vec<Alert> = (0..5)
+            .flat_map(|_| alert_service.detect(&remove_artifact, now))
+            .collect();
+        assert_eq!(kinds(&alerts), vec![AnomalyKind::MassRevocation]);
+        assert_eq!(alerts[0].details.len(), 3);
+
+        // revocations that happened more than a window ago no longer count
+        let later = now + 601;
+        let remove_artifact = transparency_log(Operation::RemoveArtifact, later);
+        assert!(alert_service.detect(&remove_artifact, later).is_empty());
+    }
+
+    #[test]
+    fn test_volume_spike_raises_alert() {
+        let alert_service = AlertService::new(vec![])
+            .unwrap()
+            .with_thresholds(AnomalyThresholds {
+                min_spike_volume: 10,
+                ..Default::default()
+            });
+
+        let start = now();
+        let add_artifact = |timestamp| transparency_log(Operation::AddArtifact, timestamp);
+        // a steady volume of 4 logs per window
+        for window in 0..3 {
+            let timestamp = start + window * 600;
+            for _ in 0..4 {
+                assert!(alert_service
+                    .detect(&add_artifact(timestamp), timestamp)
+                    .is_empty());
+            }
+        }
+
+        let timestamp = start + 3 * 600;
+        let alerts: Vec<Alert> = (0..30)
+            .flat_map(|_| alert_service.detect(&add_artifact(timestamp), timestamp))
+            .collect();
+        assert_eq!(kinds(&alerts), vec![AnomalyKind::VolumeSpike]);
+    }
+
+    #[tokio::test]
+    async fn test_hash_mismatch_from_multiple_peers_is_posted_to_webhook() {
+        let server = Server::run();
+        server.expect(
+            Expectation::matching(matchers::all_of![
+                matchers::request::method_path("POST", "/alerts"),
+                matchers::request::body(matchers::json_decoded(|alert: &serde_json::Value| {
+                    alert["kind"] == "hash_mismatch"
+                }))
+            ])
+            .respond_with(responders::status_code(200)),
+        );
+
+        let alert_service = AlertService::new(vec![server.url_str("/alerts")]).unwrap();
+        let peer_id = PeerId::random();
+
+        assert!(alert_service
+            .record_hash_mismatch("artifact_id", &peer_id)
+            .is_none());
+        // the same peer serving the corrupt copy again is not counted twice
+        assert!(alert_service
+            .record_hash_mismatch("artifact_id", &peer_id)
+            .is_none());
+        let alert = alert_service
+            .record_hash_mismatch("artifact_id", &PeerId::random())
+            .unwrap();
+        assert_eq!(alert.kind, AnomalyKind::HashMismatch);
+        assert_eq!(alert.details[0], "artifact_id");
+
+        // the webhook is posted in the background, the server verifies it was called when dropped
+        tokio::time::sleep(Duration::from_secs(1)).await;
+    }
+
+    #[tokio::test]
+    async fn test_dependency_confusion_raises_alert() {
+        let alert_service = AlertService::new(vec![]).unwrap();
+
+        let alert = alert_service.record_dependency_confusion(
+            "com.myorg:tools:1.0",
+            Some("peer"),
+            "no internal mapping source is configured",
+        );
+
+        assert_eq!(alert.kind, AnomalyKind::DependencyConfusion);
+        assert_eq!(alert.details, vec!["com.myorg:tools:1.0", "peer"]);
+        assert_eq!(alert_service.recent_alerts(), vec![alert]);
+    }
+
+    #[tokio::test]
+    async fn test_inconsistency_raises_alert() {
+        let alert_service = AlertService::new(vec![]).unwrap();
+
+        let alert = alert_service.record_inconsistency(&Inconsistency::ConflictingLog {
+            id: "log-1".to_owned(),
+        });
+
+        assert_eq!(alert.kind, AnomalyKind::LogInconsistency);
+        assert_eq!(alert.details, vec!["log-1"]);
+        assert_eq!(alert_service.recent_alerts(), vec![alert]);
+    }
+
+    #[test]
+    fn test_invalid_webhook_url_is_rejected() {
+        assert!(matches!(
+            AlertService::new(vec!["ftp://alerts.myorg.com".to_owned()]),
+            Err(AlertError::InvalidWebhookUrl(_))
+        ));
+    }
+}
@@ -14,6 +14,22 @@
    limitations under the License.
 */
 
+pub mod access_stats;
+pub mod authorization;
+pub mod availability;
+pub mod coordinates;
+pub mod hooks;
+pub mod load_test;
 pub mod model;
+pub mod provider;
 pub mod service;
 pub mod storage;
+
+pub use pyrsia_artifact_service::blob_store;
+pub use pyrsia_artifact_service::budget;
+pub use pyrsia_artifact_service::metadata_cache;
+pub use pyrsia_artifact_service::mmap;
+pub use pyrsia_artifact_service::progress;
+pub use pyrsia_artifact_service::provide;
+pub use pyrsia_artifact_service::quota;
+pub use pyrsia_artifact_service::tag_policy;
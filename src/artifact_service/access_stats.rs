@@ -0,0 +1,290 @@
+/*
+   Copyright 2021 JFrog Ltd
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+*/
+
+use super::model::PackageType;
+use crate::util::time_util::now;
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// How often the access statistics are written to disk by
+/// [`AccessStats::run_flush_scheduler`].
+pub const DEFAULT_FLUSH_INTERVAL: Duration = Duration::from_secs(60);
+
+/// The access statistics of a single package.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct PackageAccessStats {
+    /// The number of times an artifact of the package was served, either to
+    /// a client of this node or to another peer.
+    pub pull_count: u64,
+    /// The time of the last pull in seconds since the unix epoch.
+    pub last_pulled: Option<u64>,
+    /// The peers that this node served artifacts of the package to.
+    pub peers_served: BTreeSet<String>,
+}
+
+/// The access statistics of a package as they are reported by the node API.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct PackageAccessSummary {
+    pub package_type: PackageType,
+    pub package_specific_id: String,
+    pub pull_count: u64,
+    pub last_pulled: Option<u64>,
+    pub unique_peers_served: usize,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct StoredPackageAccessStats {
+    package_type: PackageType,
+    package_specific_id: String,
+    #[serde(flatten)]
+    stats: PackageAccessStats,
+}
+
+#[derive(Debug, Default)]
+struct AccessStatsState {
+    packages: HashMap<(PackageType, String), PackageAccessStats>,
+    dirty: bool,
+}
+
+/// Keeps track of how often the packages stored on this node are pulled, to
+/// help identify unused packages. Pulls are recorded in memory and written
+/// to disk periodically with [`AccessStats::flush`], so serving an artifact
+/// never waits for the disk.
+#[derive(Clone, Debug, Default)]
+pub struct AccessStats {
+    path: Option<PathBuf>,
+    state: Arc<Mutex<AccessStatsState>>,
+}
+
+impl AccessStats {
+    /// Load the access statistics that were persisted at `path`.
+    pub fn new<P: AsRef<Path>>(path: P) -> anyhow::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let packages = match fs::read(&path) {
+            Ok(content) => serde_json::from_slice::<Vec<StoredPackageAccessStats>>(&content)?
+                .into_iter()
+                .map(|stored| {
+                    (
+                        (stored.package_type, stored.package_specific_id),
+                        stored.stats,
+                    )
+                })
+                .collect(),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Default::default(),
+            Err(e) => return Err(e.into()),
+        };
+
+        Ok(AccessStats {
+            path: Some(path),
+            state: Arc::new(Mutex::new(AccessStatsState {
+                packages,
+                dirty: false,
+            })),
+        })
+    }
+
+    /// Record a pull of an artifact of the package by a client of this node.
+    pub fn record_pull(&self, package_type: PackageType, package_specific_id: &str) {
+        self.record(package_type, package_specific_id, None);
+    }
+
+    /// Record that an artifact of the package was served to another peer.
+    pub fn record_served(&self, package_type: PackageType, package_specific_id: &str, peer: &str) {
+        self.record(package_type, package_specific_id, Some(peer));
+    }
+
+    fn record(&self, package_type: PackageType, package_specific_id: &str, peer: Option<&str>) {
+        let mut state = self.state.lock().unwrap();
+        let stats = state
+            .packages
+            .entry((package_type, package_specific_id.to_owned()))
+            .or_default();
+        stats.pull_count += 1;
+        stats.last_pulled = Some(now());
+        if let Some(peer) = peer {
+            stats.peers_served.insert(peer.to_owned());
+        }
+        state.dirty = true;
+    }
+
+    pub fn get(
+        &self,
+        package_type: PackageType,
+        package_specific_id: &str,
+    ) -> PackageAccessSummary {
+        let state = self.state.lock().unwrap();
+        let stats = state
+            .packages
+            .get(&(package_type, package_specific_id.to_owned()))
+            .cloned()
+            .unwrap_or_default();
+
+        PackageAccessSummary {
+            package_type,
+            package_specific_id: package_specific_id.to_owned(),
+            pull_count: stats.pull_count,
+            last_pulled: stats.last_pulled,
+            unique_peers_served: stats.peers_served.len(),
+        }
+    }
+
+    /// The packages that were pulled, the most pulled package first.
+    pub fn most_pulled(&self) -> Vec<(PackageType, String)> {
+        let state = self.state.lock().unwrap();
+        let mut packages: Vec<(&(PackageType, String), &PackageAccessStats)> = state
+            .packages
+            .iter()
+            .filter(|(_, stats)| stats.pull_count > 0)
+            .collect();
+        packages.sort_by(|(_, a), (_, b)| {
+            b.pull_count
+                .cmp(&a.pull_count)
+                .then(b.last_pulled.cmp(&a.last_pulled))
+        });
+        packages
+            .into_iter()
+            .map(|(package, _)| package.clone())
+            .collect()
+    }
+
+    /// Write the access statistics to disk if they changed since the last
+    /// flush. In-memory statistics are never written.
+    pub fn flush(&self) -> anyhow::Result<()> {
+        let path = match &self.path {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+
+        let content = {
+            let mut state = self.state.lock().unwrap();
+            if !state.dirty {
+                return Ok(());
+            }
+            let stored: Vec<StoredPackageAccessStats> = state
+                .packages
+                .iter()
+                .map(
+                    |((package_type, package_specific_id), stats)| StoredPackageAccessStats {
+                        package_type: *package_type,
+                        package_specific_id: package_specific_id.clone(),
+                        stats: stats.clone(),
+                    },
+                )
+                .collect();
+            state.dirty = false;
+            serde_json::to_vec(&stored)?
+        };
+
+        let result = write_atomically(path, &content);
+        if result.is_err() {
+            self.state.lock().unwrap().dirty = true;
+        }
+        result
+    }
+
+    /// Flush the access statistics every `interval`, forever.
+    pub async fn run_flush_scheduler(self, interval: Duration) {
+        loop {
+            tokio::time::sleep(interval).await;
+            if let Err(e) = self.flush() {
+                warn!("Failed to persist the artifact access statistics: {:?}", e);
+            }
+        }
+    }
+}
+
+fn write_atomically(path: &Path, content: &[u8]) -> anyhow::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let tmp_path = path.with_extension("tmp");
+    fs::write(&tmp_path, content)?;
+    fs::rename(&tmp_path, path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+#[cfg(not(tarpaulin_include))]
+mod tests {
+    use super::*;
+    use crate::util::test_util;
+
+    #[test]
+    fn test_record_pulls_and_peers_served() {
+        let access_stats = AccessStats::default();
+        access_stats.record_pull(PackageType::Maven2, "com.company:test:1.0");
+        access_stats.record_served(PackageType::Maven2, "com.company:test:1.0", "peer1");
+        access_stats.record_served(PackageType::Maven2, "com.company:test:1.0", "peer1");
+        access_stats.record_served(PackageType::Maven2, "com.company:test:1.0", "peer2");
+
+        let summary = access_stats.get(PackageType::Maven2, "com.company:test:1.0");
+        assert_eq!(summary.pull_count, 4);
+        assert!(summary.last_pulled.is_some());
+        assert_eq!(summary.unique_peers_served, 2);
+
+        let unused = access_stats.get(PackageType::Docker, "library/alpine:3.15");
+        assert_eq!(unused.pull_count, 0);
+        assert_eq!(unused.last_pulled, None);
+        assert_eq!(unused.unique_peers_served, 0);
+    }
+
+    #[test]
+    fn test_most_pulled() {
+        let access_stats = AccessStats::default();
+        access_stats.record_pull(PackageType::Maven2, "com.company:test:1.0");
+        for _ in 0..3 {
+            access_stats.record_pull(PackageType::Docker, "library/alpine:3.15");
+        }
+
+        assert_eq!(
+            access_stats.most_pulled(),
+            vec![
+                (PackageType::Docker, "library/alpine:3.15".to_owned()),
+                (PackageType::Maven2, "com.company:test:1.0".to_owned())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_flush_persists_access_stats() {
+        let tmp_dir = test_util::tests::setup();
+        let path = tmp_dir.join("access_stats.json");
+
+        let access_stats = AccessStats::new(&path).unwrap();
+        access_stats.record_served(PackageType::Docker, "library/alpine:3.15", "peer1");
+        assert!(!path.exists());
+
+        access_stats.flush().unwrap();
+        let reloaded = AccessStats::new(&path).unwrap();
+        assert_eq!(
+            reloaded.get(PackageType::Docker, "library/alpine:3.15"),
+            access_stats.get(PackageType::Docker, "library/alpine:3.15")
+        );
+
+        // nothing is written when nothing changed
+        fs::remove_file(&path).unwrap();
+        access_stats.flush().unwrap();
+        assert!(!path.exists());
+
+        test_util::tests::teardown(tmp_dir);
+    }
+}
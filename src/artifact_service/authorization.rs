@@ -0,0 +1,118 @@
+/*
+   Copyright 2021 JFrog Ltd
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+*/
+
+use crate::transparency_log::log::TransparencyLog;
+use libp2p::PeerId;
+use std::collections::HashSet;
+
+/// The policy that decides which peers are allowed to request the artifacts
+/// that this node provides on the p2p network.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum ArtifactRequestPolicy {
+    /// Every peer may request every artifact.
+    #[default]
+    Public,
+    /// Only the listed peers may request artifacts.
+    Allowlist(HashSet<PeerId>),
+    /// Artifacts whose package specific artifact id starts with one of the
+    /// namespaces are only served to the listed peers. All other artifacts
+    /// are served to every peer.
+    NamespaceRestricted {
+        namespaces: Vec<String>,
+        allowed_peers: HashSet<PeerId>,
+    },
+}
+
+impl ArtifactRequestPolicy {
+    /// Returns true if `peer_id` is allowed to request the artifact that is
+    /// described by `transparency_log`.
+    pub fn is_authorized(&self, peer_id: &PeerId, transparency_log: &TransparencyLog) -> bool {
+        match self {
+            ArtifactRequestPolicy::Public => true,
+            ArtifactRequestPolicy::Allowlist(allowed_peers) => allowed_peers.contains(peer_id),
+            ArtifactRequestPolicy::NamespaceRestricted {
+                namespaces,
+                allowed_peers,
+            } => {
+                let restricted = namespaces.iter().any(|namespace| {
+                    transparency_log
+                        .package_specific_artifact_id
+                        .starts_with(namespace)
+                });
+                !restricted || allowed_peers.contains(peer_id)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+#[cfg(not(tarpaulin_include))]
+mod tests {
+    use super::*;
+    use crate::artifact_service::model::PackageType;
+    use crate::transparency_log::log::AddArtifactRequest;
+    use libp2p::identity::Keypair;
+
+    fn create_transparency_log(package_specific_artifact_id: &str) -> TransparencyLog {
+        TransparencyLog::from(AddArtifactRequest {
+            package_type: PackageType::Docker,
+            package_specific_id: "package_specific_id".to_owned(),
+            num_artifacts: 1,
+            package_specific_artifact_id: package_specific_artifact_id.to_owned(),
+            artifact_hash: "artifact_hash".to_owned(),
+        })
+    }
+
+    fn random_peer_id() -> PeerId {
+        Keypair::generate_ed25519().public().to_peer_id()
+    }
+
+    #[test]
+    fn test_public_policy_authorizes_every_peer() {
+        let policy = ArtifactRequestPolicy::Public;
+
+        assert!(policy.is_authorized(
+            &random_peer_id(),
+            &create_transparency_log("library/alpine@sha256:1234")
+        ));
+    }
+
+    #[test]
+    fn test_allowlist_policy_authorizes_listed_peers_only() {
+        let allowed_peer = random_peer_id();
+        let policy = ArtifactRequestPolicy::Allowlist(HashSet::from([allowed_peer]));
+        let transparency_log = create_transparency_log("library/alpine@sha256:1234");
+
+        assert!(policy.is_authorized(&allowed_peer, &transparency_log));
+        assert!(!policy.is_authorized(&random_peer_id(), &transparency_log));
+    }
+
+    #[test]
+    fn test_namespace_restricted_policy() {
+        let allowed_peer = random_peer_id();
+        let other_peer = random_peer_id();
+        let policy = ArtifactRequestPolicy::NamespaceRestricted {
+            namespaces: vec!["myorg/".to_owned()],
+            allowed_peers: HashSet::from([allowed_peer]),
+        };
+        let restricted_log = create_transparency_log("myorg/private@sha256:1234");
+        let public_log = create_transparency_log("library/alpine@sha256:1234");
+
+        assert!(policy.is_authorized(&allowed_peer, &restricted_log));
+        assert!(!policy.is_authorized(&other_peer, &restricted_log));
+        assert!(policy.is_authorized(&other_peer, &public_log));
+    }
+}
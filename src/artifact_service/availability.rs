@@ -0,0 +1,201 @@
+/*
+   Copyright 2021 JFrog Ltd
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+*/
+
+use super::model::PackageType;
+use crate::util::time_util::now;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::{Arc, Mutex};
+
+/// The time windows in seconds that availability is reported for by
+/// default: the last hour, day, week and 30 days.
+pub const DEFAULT_REPORT_WINDOWS: [u64; 4] = [3600, 86400, 7 * 86400, 30 * 86400];
+/// Samples older than the largest report window are discarded.
+const SAMPLE_RETENTION_SECS: u64 = 30 * 86400;
+/// The maximum number of samples that are kept, the oldest are discarded.
+const MAX_SAMPLES: usize = 100_000;
+/// The number of the most recent failed samples in a report.
+const MAX_RECENT_FAILURES: usize = 20;
+
+/// The outcome of an attempt to retrieve a logged artifact from the network.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct AvailabilitySample {
+    /// The time of the attempt in seconds since the unix epoch.
+    pub timestamp: u64,
+    pub package_type: PackageType,
+    pub package_specific_artifact_id: String,
+    pub available: bool,
+    /// Why the artifact could not be retrieved.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+/// The number of sampled and available artifacts.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct AvailabilityStats {
+    pub samples: u64,
+    pub available: u64,
+    /// The percentage of available samples, `None` without samples.
+    pub availability_percent: Option<f64>,
+}
+
+impl AvailabilityStats {
+    fn record(&mut self, available: bool) {
+        self.samples += 1;
+        if available {
+            self.available += 1;
+        }
+        self.availability_percent = Some(self.available as f64 * 100.0 / self.samples as f64);
+    }
+}
+
+/// The availability of the sampled artifacts within one time window.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct AvailabilityWindow {
+    pub window_secs: u64,
+    pub overall: AvailabilityStats,
+    pub package_types: BTreeMap<PackageType, AvailabilityStats>,
+}
+
+/// The availability of the artifacts in the transparency log as measured by
+/// the availability monitor.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct AvailabilityReport {
+    /// The time of the report in seconds since the unix epoch.
+    pub generated_at: u64,
+    pub windows: Vec<AvailabilityWindow>,
+    /// The most recent samples of unavailable artifacts, newest first.
+    pub recent_failures: Vec<AvailabilitySample>,
+}
+
+/// Keeps the samples of the availability monitor, which periodically tries
+/// to retrieve a random selection of the artifacts in the transparency log
+/// from the network, see `ArtifactService::sample_availability`. The samples
+/// are kept in memory for the largest report window.
+#[derive(Clone, Debug, Default)]
+pub struct AvailabilityMonitor {
+    samples: Arc<Mutex<VecDeque<AvailabilitySample>>>,
+}
+
+impl AvailabilityMonitor {
+    /// Record a sample, discarding the samples that are older than the
+    /// largest report window.
+    pub fn record(&self, sample: AvailabilitySample) {
+        let mut samples = self.samples.lock().unwrap();
+        let oldest = sample.timestamp.saturating_sub(SAMPLE_RETENTION_SECS);
+        while matches!(samples.front(), Some(front) if front.timestamp < oldest)
+            || samples.len() >= MAX_SAMPLES
+        {
+            samples.pop_front();
+        }
+        samples.push_back(sample);
+    }
+
+    /// Report the availability within the windows of `windows` seconds
+    /// before now.
+    pub fn report(&self, windows: &[u64]) -> AvailabilityReport {
+        self.report_at(windows, now())
+    }
+
+    fn report_at(&self, windows: &[u64], now: u64) -> AvailabilityReport {
+        let samples = self.samples.lock().unwrap();
+        let windows = windows
+            .iter()
+            .map(|window_secs| {
+                let mut window = AvailabilityWindow {
+                    window_secs: *window_secs,
+                    ..Default::default()
+                };
+                let since = now.saturating_sub(*window_secs);
+                for sample in samples.iter().filter(|sample| sample.timestamp >= since) {
+                    window.overall.record(sample.available);
+                    window
+                        .package_types
+                        .entry(sample.package_type)
+                        .or_default()
+                        .record(sample.available);
+                }
+                window
+            })
+            .collect();
+
+        AvailabilityReport {
+            generated_at: now,
+            windows,
+            recent_failures: samples
+                .iter()
+                .rev()
+                .filter(|sample| !sample.available)
+                .take(MAX_RECENT_FAILURES)
+                .cloned()
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+#[cfg(not(tarpaulin_include))]
+mod tests {
+    use super::*;
+
+    fn sample(timestamp: u64, package_type: PackageType, available: bool) -> AvailabilitySample {
+        AvailabilitySample {
+            timestamp,
+            package_type,
+            package_specific_artifact_id: format!("artifact-{}", timestamp),
+            available,
+            reason: (!available).then(|| "no providers".to_owned()),
+        }
+    }
+
+    #[test]
+    fn test_report() {
+        let monitor = AvailabilityMonitor::default();
+        let now = 100_000;
+        monitor.record(sample(now - 7200, PackageType::Docker, false));
+        monitor.record(sample(now - 60, PackageType::Docker, true));
+        monitor.record(sample(now - 30, PackageType::Maven2, true));
+        monitor.record(sample(now - 10, PackageType::Docker, true));
+
+        let report = monitor.report_at(&[3600, 86400], now);
+
+        assert_eq!(report.windows[0].overall.samples, 3);
+        assert_eq!(report.windows[0].overall.availability_percent, Some(100.0));
+        assert_eq!(report.windows[1].overall.samples, 4);
+        assert_eq!(report.windows[1].overall.availability_percent, Some(75.0));
+        let docker = &report.windows[1].package_types[&PackageType::Docker];
+        assert_eq!((docker.samples, docker.available), (3, 2));
+        assert_eq!(
+            report.recent_failures,
+            vec![sample(now - 7200, PackageType::Docker, false)]
+        );
+        assert_eq!(
+            monitor.report_at(&[3600], now + 86400).windows[0].overall,
+            AvailabilityStats::default()
+        );
+    }
+
+    #[test]
+    fn test_record_discards_old_samples() {
+        let monitor = AvailabilityMonitor::default();
+        monitor.record(sample(1, PackageType::Docker, false));
+        monitor.record(sample(SAMPLE_RETENTION_SECS + 2, PackageType::Docker, true));
+
+        let report = monitor.report_at(&[u64::MAX], SAMPLE_RETENTION_SECS + 2);
+        assert_eq!(report.windows[0].overall.samples, 1);
+        assert!(report.recent_failures.is_empty());
+    }
+}
@@ -0,0 +1,248 @@
+/*
+   Copyright 2021 JFrog Ltd
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+*/
+
+//! The canonical forms of the coordinates of packages and artifacts.
+//!
+//! Users refer to the same package in different ways, e.g. `ubuntu:latest`,
+//! `library/ubuntu:latest`, `docker.io/library/ubuntu:latest` or
+//! `ubuntu`. The transparency logs only know the canonical form, so every
+//! coordinate is normalized before it is looked up or built:
+//!
+//! * Docker images on Docker Hub lose the registry host, which has several
+//!   aliases, official images get the `library/` namespace, the repository
+//!   name is lowercase and a package without tag or digest is `latest`,
+//!   e.g. `library/ubuntu:latest`. Images of other registries keep their
+//!   host, e.g. `localhost:5000/myorg/app:1.0`.
+//! * Maven packages are `<group>:<artifact>:<version>` without the gradle
+//!   style `@<extension>`, and maven artifacts are
+//!   `<group>/<artifact>/<version>/<file>` with a dotted group, also when
+//!   the group is given as a repository path like `com/myorg`.
+
+use super::model::PackageType;
+
+/// The hosts that Docker Hub is known by.
+const DOCKER_HUB_ALIASES: [&str; 4] = [
+    "docker.io",
+    "index.docker.io",
+    "registry-1.docker.io",
+    "registry.hub.docker.com",
+];
+
+const DEFAULT_DOCKER_TAG: &str = "latest";
+
+/// The canonical form of the id of a package, like the coordinates of a
+/// build request.
+pub fn normalize_package_specific_id(
+    package_type: PackageType,
+    package_specific_id: &str,
+) -> String {
+    match package_type {
+        PackageType::Docker => normalize_docker_reference(package_specific_id),
+        PackageType::Maven2 => normalize_maven_package(package_specific_id),
+    }
+}
+
+/// The canonical form of the id of an artifact of a package, like a docker
+/// manifest or blob, or a maven file.
+pub fn normalize_package_specific_artifact_id(
+    package_type: PackageType,
+    package_specific_artifact_id: &str,
+) -> String {
+    match package_type {
+        PackageType::Docker => normalize_docker_reference(package_specific_artifact_id),
+        PackageType::Maven2 => normalize_maven_artifact(package_specific_artifact_id),
+    }
+}
+
+/// The canonical form of a package name, or the start of one, as used to
+/// search packages. Unlike ids, names do not get a default version.
+pub fn normalize_package_name(package_type: PackageType, name: &str) -> String {
+    match package_type {
+        PackageType::Docker => normalize_docker_name(name),
+        PackageType::Maven2 => normalize_maven_package(name),
+    }
+}
+
+fn normalize_docker_reference(reference: &str) -> String {
+    let reference = reference.trim();
+    match reference.split_once('@') {
+        Some((name, digest)) => format!(
+            "{}@{}",
+            normalize_docker_name(name),
+            digest.trim().to_lowercase()
+        ),
+        None => match reference.rfind(':') {
+            // a colon followed by a slash belongs to the port of a registry
+            Some(position) if !reference[position..].contains('/') => format!(
+                "{}:{}",
+                normalize_docker_name(&reference[..position]),
+                &reference[position + 1..]
+            ),
+            _ => format!(
+                "{}:{}",
+                normalize_docker_name(reference),
+                DEFAULT_DOCKER_TAG
+            ),
+        },
+    }
+}
+
+fn normalize_docker_name(name: &str) -> String {
+    let name = name.trim().trim_start_matches('/').to_lowercase();
+    let name = DOCKER_HUB_ALIASES
+        .iter()
+        .find_map(|host| name.strip_prefix(host)?.strip_prefix('/'))
+        .unwrap_or(&name);
+    // the docker hub website refers to official images as `_/<name>`
+    let name = name.strip_prefix("_/").unwrap_or(name);
+
+    if name.contains('/') {
+        name.to_owned()
+    } else {
+        format!("library/{}", name)
+    }
+}
+
+fn normalize_maven_package(package: &str) -> String {
+    let package = package.trim();
+    let package = package
+        .split_once('@')
+        .map_or(package, |(coordinates, _extension)| coordinates);
+    package
+        .split(':')
+        .map(str::trim)
+        .collect::<Vec<_>>()
+        .join(":")
+}
+
+fn normalize_maven_artifact(artifact: &str) -> String {
+    let artifact = artifact.trim().trim_start_matches('/');
+    let artifact = artifact.strip_prefix("maven2/").unwrap_or(artifact);
+    let pieces: Vec<&str> = artifact.split('/').collect();
+    if pieces.len() <= 4 {
+        return artifact.to_owned();
+    }
+
+    let (group, rest) = pieces.split_at(pieces.len() - 3);
+    format!("{}/{}", group.join("."), rest.join("/"))
+}
+
+#[cfg(test)]
+#[cfg(not(tarpaulin_include))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_docker_package_specific_id() {
+        for package_specific_id in [
+            "ubuntu:latest",
+            "ubuntu",
+            "library/ubuntu:latest",
+            "docker.io/library/ubuntu:latest",
+            "docker.io/ubuntu",
+            "index.docker.io/library/ubuntu",
+            "registry-1.docker.io/library/ubuntu:latest",
+            "registry.hub.docker.com/_/ubuntu",
+            " Ubuntu:latest ",
+        ] {
+            assert_eq!(
+                normalize_package_specific_id(PackageType::Docker, package_specific_id),
+                "library/ubuntu:latest",
+                "{}",
+                package_specific_id
+            );
+        }
+
+        assert_eq!(
+            normalize_package_specific_id(PackageType::Docker, "bitnami/redis:7.0-RC1"),
+            "bitnami/redis:7.0-RC1"
+        );
+        assert_eq!(
+            normalize_package_specific_id(PackageType::Docker, "localhost:5000/myorg/app"),
+            "localhost:5000/myorg/app:latest"
+        );
+        assert_eq!(
+            normalize_package_specific_id(PackageType::Docker, "localhost:5000/app:1.0"),
+            "localhost:5000/app:1.0"
+        );
+        // docker.io is only a registry host at the start of the name
+        assert_eq!(
+            normalize_package_specific_id(PackageType::Docker, "myorg/docker.io/app:1"),
+            "myorg/docker.io/app:1"
+        );
+        assert_eq!(
+            normalize_package_specific_id(PackageType::Docker, "docker.iox/app:1"),
+            "docker.iox/app:1"
+        );
+    }
+
+    #[test]
+    fn test_normalize_docker_package_specific_artifact_id() {
+        assert_eq!(
+            normalize_package_specific_artifact_id(
+                PackageType::Docker,
+                "docker.io/alpine@SHA256:ABCD"
+            ),
+            "library/alpine@sha256:abcd"
+        );
+        assert_eq!(
+            normalize_package_specific_artifact_id(PackageType::Docker, "library/alpine:3.16"),
+            "library/alpine:3.16"
+        );
+    }
+
+    #[test]
+    fn test_normalize_maven_coordinates() {
+        assert_eq!(
+            normalize_package_specific_id(PackageType::Maven2, " com.myorg : my-artifact:1.0@jar"),
+            "com.myorg:my-artifact:1.0"
+        );
+        assert_eq!(
+            normalize_package_specific_artifact_id(
+                PackageType::Maven2,
+                "/maven2/com/myorg/my-artifact/1.0/my-artifact-1.0.jar"
+            ),
+            "com.myorg/my-artifact/1.0/my-artifact-1.0.jar"
+        );
+        assert_eq!(
+            normalize_package_specific_artifact_id(
+                PackageType::Maven2,
+                "com.myorg/my-artifact/1.0/my-artifact-1.0.jar"
+            ),
+            "com.myorg/my-artifact/1.0/my-artifact-1.0.jar"
+        );
+        assert_eq!(
+            normalize_package_specific_artifact_id(PackageType::Maven2, "my-artifact-1.0.jar"),
+            "my-artifact-1.0.jar"
+        );
+    }
+
+    #[test]
+    fn test_normalize_package_name() {
+        assert_eq!(
+            normalize_package_name(PackageType::Docker, "docker.io/alp"),
+            "library/alp"
+        );
+        assert_eq!(
+            normalize_package_name(PackageType::Docker, "bitnami/"),
+            "bitnami/"
+        );
+        assert_eq!(
+            normalize_package_name(PackageType::Maven2, "com.myorg:"),
+            "com.myorg:"
+        );
+    }
+}
@@ -0,0 +1,432 @@
+/*
+   Copyright 2021 JFrog Ltd
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+*/
+
+use crate::artifact_service::model::PackageType;
+use crate::transparency_log::log::TransparencyLog;
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::time::Duration;
+use thiserror::Error;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+
+#[derive(Debug, Error)]
+pub enum HookError {
+    #[error("Invalid lifecycle hook configuration: {0}")]
+    ConfigurationFailure(String),
+    #[error("Lifecycle hook {hook} rejected artifact {package_specific_artifact_id}: {reason}")]
+    Rejected {
+        hook: String,
+        package_specific_artifact_id: String,
+        reason: String,
+    },
+}
+
+/// The points in the lifecycle of an artifact at which hooks run.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, Eq, PartialEq, strum_macros::Display)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum HookPoint {
+    /// before the artifacts of a build or import are stored and published,
+    /// the artifact file is passed to the hook
+    PrePublication,
+    /// after the artifacts of a build or import were published
+    PostPublication,
+    /// before an artifact is served to a client of a package manager facade
+    PreServe,
+}
+
+impl HookPoint {
+    /// Whether the hooks at this point decide if the lifecycle continues. A
+    /// gate that fails, or does not answer in time, rejects the artifact.
+    /// Post-publication hooks are only notified, their failures are logged.
+    pub fn is_gate(&self) -> bool {
+        *self != HookPoint::PostPublication
+    }
+}
+
+/// What a hook does when it runs.
+#[derive(Clone, Debug, Deserialize, Serialize, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum HookAction {
+    /// Run a command with the [`ArtifactHookEvent`] as JSON on its standard
+    /// input. The hook passes when the command exits successfully.
+    Command {
+        program: String,
+        #[serde(default)]
+        args: Vec<String>,
+    },
+    /// Post the [`ArtifactHookEvent`] as JSON to a URL. The hook passes when
+    /// the response has a success status.
+    Http { url: String },
+}
+
+fn default_timeout_secs() -> u64 {
+    30
+}
+
+/// A hook that runs at a point in the lifecycle of every artifact, e.g.
+///
+/// ```json
+/// { "name": "scanner", "point": "pre_publication", "command": { "program": "/opt/scan", "args": ["--strict"] } }
+/// ```
+#[derive(Clone, Debug, Deserialize, Serialize, Eq, PartialEq)]
+pub struct HookConfig {
+    pub name: String,
+    pub point: HookPoint,
+    #[serde(flatten)]
+    pub action: HookAction,
+    /// The number of seconds to wait for the hook to finish.
+    #[serde(default = "default_timeout_secs")]
+    pub timeout_secs: u64,
+}
+
+/// The metadata of an artifact that is passed to a hook.
+#[derive(Clone, Debug, Deserialize, Serialize, Eq, PartialEq)]
+pub struct ArtifactHookEvent {
+    pub point: HookPoint,
+    pub package_type: Option<PackageType>,
+    pub package_specific_id: String,
+    pub package_specific_artifact_id: String,
+    pub artifact_hash: String,
+    pub artifact_id: String,
+    pub source_id: String,
+    pub source_hash: String,
+    /// The file with the content of the artifact, for pre-publication hooks.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub artifact_path: Option<PathBuf>,
+}
+
+impl ArtifactHookEvent {
+    pub fn new(
+        point: HookPoint,
+        transparency_log: &TransparencyLog,
+        artifact_path: Option<&Path>,
+    ) -> Self {
+        ArtifactHookEvent {
+            point,
+            package_type: transparency_log.package_type,
+            package_specific_id: transparency_log.package_specific_id.clone(),
+            package_specific_artifact_id: transparency_log.package_specific_artifact_id.clone(),
+            artifact_hash: transparency_log.artifact_hash.clone(),
+            artifact_id: transparency_log.artifact_id.clone(),
+            source_id: transparency_log.source_id.clone(),
+            source_hash: transparency_log.source_hash.clone(),
+            artifact_path: artifact_path.map(Path::to_path_buf),
+        }
+    }
+}
+
+/// The lifecycle hooks let external systems, like corporate scanners or
+/// notarization services, take part in the lifecycle of artifacts without
+/// modifying the node. Hooks run in the order in which they are configured.
+#[derive(Clone, Default)]
+pub struct LifecycleHooks {
+    hooks: Vec<HookConfig>,
+    http_client: reqwest::Client,
+}
+
+impl LifecycleHooks {
+    pub fn new(hooks: Vec<HookConfig>) -> Result<Self, HookError> {
+        for hook in &hooks {
+            if let HookAction::Http { url } = &hook.action {
+                match url::Url::parse(url) {
+                    Ok(url) if url.scheme() == "http" || url.scheme() == "https" => {}
+                    _ => {
+                        return Err(HookError::ConfigurationFailure(format!(
+                            "invalid url {} of hook {}",
+                            url, hook.name
+                        )))
+                    }
+                }
+            }
+        }
+
+        Ok(LifecycleHooks {
+            hooks,
+            http_client: reqwest::Client::new(),
+        })
+    }
+
+    /// Load the hooks from a JSON file that contains a list of [`HookConfig`].
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, HookError> {
+        let content = fs::read(path).map_err(|e| HookError::ConfigurationFailure(e.to_string()))?;
+        let hooks = serde_json::from_slice(&content)
+            .map_err(|e| HookError::ConfigurationFailure(e.to_string()))?;
+        Self::new(hooks)
+    }
+
+    /// Whether any hook runs at `point`.
+    pub fn has_hooks(&self, point: HookPoint) -> bool {
+        self.hooks.iter().any(|hook| hook.point == point)
+    }
+
+    /// Run the hooks at the point of `event`. The first gate that rejects the
+    /// artifact stops the hooks and its rejection is returned. Hooks that are
+    /// not gates are run in the background and never fail.
+    pub async fn run(&self, event: &ArtifactHookEvent) -> Result<(), HookError> {
+        for hook in self.hooks.iter().filter(|hook| hook.point == event.point) {
+            if event.point.is_gate() {
+                self.run_hook(hook, event).await?;
+            } else {
+                let hooks = self.clone();
+                let hook = hook.clone();
+                let event = event.clone();
+                tokio::spawn(async move {
+                    if let Err(error) = hooks.run_hook(&hook, &event).await {
+                        warn!("{}", error);
+                    }
+                });
+            }
+        }
+        Ok(())
+    }
+
+    async fn run_hook(
+        &self,
+        hook: &HookConfig,
+        event: &ArtifactHookEvent,
+    ) -> Result<(), HookError> {
+        debug!(
+            "Running lifecycle hook {} for artifact {}",
+            hook.name, event.package_specific_artifact_id
+        );
+        let rejected = |reason: String| HookError::Rejected {
+            hook: hook.name.clone(),
+            package_specific_artifact_id: event.package_specific_artifact_id.clone(),
+            reason,
+        };
+        let payload = serde_json::to_vec(event).map_err(|e| rejected(e.to_string()))?;
+
+        let result = tokio::time::timeout(Duration::from_secs(hook.timeout_secs), async {
+            match &hook.action {
+                HookAction::Command { program, args } => run_command(program, args, &payload).await,
+                HookAction::Http { url } => self.post(url, payload).await,
+            }
+        })
+        .await
+        .unwrap_or_else(|_| Err(format!("no answer within {} seconds", hook.timeout_secs)));
+
+        match result {
+            Ok(()) => {
+                info!(
+                    "Lifecycle hook {} passed artifact {}",
+                    hook.name, event.package_specific_artifact_id
+                );
+                Ok(())
+            }
+            Err(reason) => Err(rejected(reason)),
+        }
+    }
+
+    async fn post(&self, url: &str, payload: Vec<u8>) -> Result<(), String> {
+        let response = self
+            .http_client
+            .post(url)
+            .header("Content-Type", "application/json")
+            .body(payload)
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+        if response.status().is_success() {
+            Ok(())
+        } else {
+            Err(format!(
+                "the hook answered with status {}",
+                response.status()
+            ))
+        }
+    }
+}
+
+async fn run_command(program: &str, args: &[String], payload: &[u8]) -> Result<(), String> {
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| format!("unable to run {}: {}", program, e))?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        // a command that does not read the event may close its input early
+        let _ = stdin.write_all(payload).await;
+    }
+
+    let output = child
+        .wait_with_output()
+        .await
+        .map_err(|e| format!("unable to run {}: {}", program, e))?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(format!(
+            "{} exited with {}: {}",
+            program,
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ))
+    }
+}
+
+#[cfg(test)]
+#[cfg(not(tarpaulin_include))]
+mod tests {
+    use super::*;
+    use std::net::SocketAddr;
+    use warp::http::StatusCode;
+    use warp::Filter;
+
+    fn command_hook(name: &str, point: HookPoint, script: &str, timeout_secs: u64) -> HookConfig {
+        HookConfig {
+            name: name.to_owned(),
+            point,
+            action: HookAction::Command {
+                program: "sh".to_owned(),
+                args: vec!["-c".to_owned(), script.to_owned()],
+            },
+            timeout_secs,
+        }
+    }
+
+    fn hook_event(point: HookPoint) -> ArtifactHookEvent {
+        ArtifactHookEvent {
+            point,
+            package_type: Some(PackageType::Maven2),
+            package_specific_id: "com.myorg:my-artifact:1.0.0".to_owned(),
+            package_specific_artifact_id: "com/myorg/my-artifact/1.0.0/my-artifact-1.0.0.jar"
+                .to_owned(),
+            artifact_hash: "artifact_hash".to_owned(),
+            artifact_id: "artifact_id".to_owned(),
+            source_id: "source_id".to_owned(),
+            source_hash: "source_hash".to_owned(),
+            artifact_path: None,
+        }
+    }
+
+    #[test]
+    fn test_parse_hook_config() {
+        let hooks: Vec<HookConfig> = serde_json::from_str(
+            r#"[
+                { "name": "scanner", "point": "pre_publication", "command": { "program": "/opt/scan", "args": ["--strict"] } },
+                { "name": "notary", "point": "post_publication", "http": { "url": "https://notary.myorg.com" }, "timeout_secs": 5 }
+            ]"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            hooks[0].action,
+            HookAction::Command {
+                program: "/opt/scan".to_owned(),
+                args: vec!["--strict".to_owned()]
+            }
+        );
+        assert_eq!(hooks[0].timeout_secs, 30);
+        assert_eq!(hooks[1].point, HookPoint::PostPublication);
+        assert_eq!(hooks[1].timeout_secs, 5);
+        assert!(LifecycleHooks::new(hooks).is_ok());
+
+        let invalid_hook = HookConfig {
+            name: "invalid".to_owned(),
+            point: HookPoint::PreServe,
+            action: HookAction::Http {
+                url: "ftp://notary.myorg.com".to_owned(),
+            },
+            timeout_secs: 5,
+        };
+        assert!(LifecycleHooks::new(vec![invalid_hook]).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_command_hooks_gate_artifacts() {
+        let hooks = LifecycleHooks::new(vec![
+            command_hook(
+                "metadata",
+                HookPoint::PrePublication,
+                "grep -q '\"package_specific_id\":\"com.myorg:my-artifact:1.0.0\"'",
+                5,
+            ),
+            command_hook("release-only", HookPoint::PreServe, "exit 3", 5),
+            command_hook("notify", HookPoint::PostPublication, "exit 1", 5),
+        ])
+        .unwrap();
+
+        assert!(hooks
+            .run(&hook_event(HookPoint::PrePublication))
+            .await
+            .is_ok());
+        match hooks.run(&hook_event(HookPoint::PreServe)).await {
+            Err(HookError::Rejected { hook, .. }) => assert_eq!(hook, "release-only"),
+            other => panic!("Expected a rejection, was: {:?}", other),
+        }
+        // failing post-publication hooks do not fail the publication
+        assert!(hooks
+            .run(&hook_event(HookPoint::PostPublication))
+            .await
+            .is_ok());
+
+        let slow_hooks = LifecycleHooks::new(vec![command_hook(
+            "slow",
+            HookPoint::PreServe,
+            "sleep 5",
+            1,
+        )])
+        .unwrap();
+        assert!(slow_hooks
+            .run(&hook_event(HookPoint::PreServe))
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_http_hooks_gate_artifacts() {
+        let routes = warp::path("allow")
+            .and(warp::body::json())
+            .map(|event: ArtifactHookEvent| {
+                assert_eq!(event.point, HookPoint::PreServe);
+                StatusCode::NO_CONTENT
+            })
+            .or(warp::path("deny").map(|| StatusCode::FORBIDDEN));
+        let (addr, server) =
+            warp::serve(routes).bind_ephemeral(SocketAddr::from(([127, 0, 0, 1], 0)));
+        tokio::spawn(server);
+        let http_hook = |name: &str| HookConfig {
+            name: name.to_owned(),
+            point: HookPoint::PreServe,
+            action: HookAction::Http {
+                url: format!("http://{}/{}", addr, name),
+            },
+            timeout_secs: 5,
+        };
+
+        let allowing_hooks = LifecycleHooks::new(vec![http_hook("allow")]).unwrap();
+        assert!(allowing_hooks
+            .run(&hook_event(HookPoint::PreServe))
+            .await
+            .is_ok());
+
+        let denying_hooks =
+            LifecycleHooks::new(vec![http_hook("allow"), http_hook("deny")]).unwrap();
+        assert!(denying_hooks
+            .run(&hook_event(HookPoint::PreServe))
+            .await
+            .is_err());
+    }
+}
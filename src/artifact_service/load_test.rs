@@ -0,0 +1,440 @@
+/*
+   Copyright 2021 JFrog Ltd
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+*/
+
+use super::service::ArtifactService;
+use crate::transparency_log::log::derive_artifact_id;
+use libp2p::PeerId;
+use log::{debug, info, warn};
+use rand::seq::SliceRandom;
+use rand::{Rng, RngCore};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fmt;
+use std::io;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// The synthetic workload of a load test. Every operation is either a
+/// publish, which stores a random artifact and provides it on the network,
+/// or a pull. Pulls read the published artifacts back from the local
+/// storage, or, when peers are given, request artifacts of the transparency
+/// log from a random peer.
+#[derive(Clone, Debug)]
+pub struct LoadTestConfig {
+    /// The number of operations that are started per second.
+    pub rate: u32,
+    pub duration: Duration,
+    /// The size of the published artifacts in bytes.
+    pub artifact_size: usize,
+    /// The fraction of the operations that are pulls, between 0 and 1.
+    pub pull_ratio: f64,
+    pub peers: Vec<PeerId>,
+}
+
+/// The results of one kind of operation of a load test.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct OperationReport {
+    pub count: u64,
+    pub errors: u64,
+    pub bytes: u64,
+    pub throughput_per_second: f64,
+    pub error_rate: f64,
+    pub latency_p50_ms: u64,
+    pub latency_p99_ms: u64,
+    pub latency_max_ms: u64,
+}
+
+/// The results of a load test.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct LoadTestReport {
+    pub elapsed_ms: u64,
+    pub publish: OperationReport,
+    pub pull: OperationReport,
+}
+
+impl fmt::Display for LoadTestReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (name, operation) in [("publish", &self.publish), ("pull", &self.pull)] {
+            writeln!(
+                f,
+                "{}: {} operations, {} errors ({:.2}%), {:.1} ops/s, {} bytes, latency p50 {} ms, p99 {} ms, max {} ms",
+                name,
+                operation.count,
+                operation.errors,
+                operation.error_rate * 100.0,
+                operation.throughput_per_second,
+                operation.bytes,
+                operation.latency_p50_ms,
+                operation.latency_p99_ms,
+                operation.latency_max_ms
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+struct OperationStats {
+    errors: u64,
+    bytes: u64,
+    latencies: Vec<Duration>,
+}
+
+impl OperationStats {
+    fn record(&mut self, latency: Duration, result: anyhow::Result<u64>) {
+        self.latencies.push(latency);
+        match result {
+            Ok(bytes) => self.bytes += bytes,
+            Err(error) => {
+                debug!("Load test operation failed: {:?}", error);
+                self.errors += 1;
+            }
+        }
+    }
+
+    fn report(&self, elapsed: Duration) -> OperationReport {
+        let mut latencies = self.latencies.clone();
+        latencies.sort();
+        let percentile = |p: usize| {
+            latencies
+                .get((latencies.len() * p / 100).min(latencies.len().saturating_sub(1)))
+                .map(|latency| latency.as_millis() as u64)
+                .unwrap_or_default()
+        };
+        let count = latencies.len() as u64;
+        OperationReport {
+            count,
+            errors: self.errors,
+            bytes: self.bytes,
+            throughput_per_second: count as f64 / elapsed.as_secs_f64().max(f64::EPSILON),
+            error_rate: if count == 0 {
+                0.0
+            } else {
+                self.errors as f64 / count as f64
+            },
+            latency_p50_ms: percentile(50),
+            latency_p99_ms: percentile(99),
+            latency_max_ms: latencies
+                .last()
+                .map(|latency| latency.as_millis() as u64)
+                .unwrap_or_default(),
+        }
+    }
+}
+
+#[derive(Default)]
+struct LoadTestState {
+    published_artifact_ids: Vec<String>,
+    publish: OperationStats,
+    pull: OperationStats,
+}
+
+/// Generates a synthetic workload against an artifact service to measure
+/// its throughput and error rates. Published artifacts are not added to the
+/// transparency log and are removed when the load test ends.
+pub struct LoadTest {
+    artifact_service: ArtifactService,
+    config: LoadTestConfig,
+    state: Arc<Mutex<LoadTestState>>,
+}
+
+impl LoadTest {
+    pub fn new(artifact_service: ArtifactService, config: LoadTestConfig) -> LoadTest {
+        LoadTest {
+            artifact_service,
+            config,
+            state: Default::default(),
+        }
+    }
+
+    pub async fn run(self) -> anyhow::Result<LoadTestReport> {
+        info!(
+            "Starting a load test of {} operations per second for {:?}",
+            self.config.rate, self.config.duration
+        );
+        let peer_artifact_ids: Vec<String> = if self.config.peers.is_empty() {
+            vec![]
+        } else {
+            self.artifact_service
+                .transparency_log_service
+                .find_artifacts(None, "")?
+                .into_iter()
+                .map(|transparency_log| transparency_log.artifact_id)
+                .collect()
+        };
+
+        let start = Instant::now();
+        let mut interval = tokio::time::interval(Duration::from_secs(1) / self.config.rate.max(1));
+        let mut operations = vec![];
+        while start.elapsed() < self.config.duration {
+            interval.tick().await;
+            let pull = rand::thread_rng().gen_bool(self.config.pull_ratio.clamp(0.0, 1.0));
+            let artifact_service = self.artifact_service.clone();
+            let state = self.state.clone();
+            let operation = if !pull {
+                tokio::spawn(publish(artifact_service, state, self.config.artifact_size))
+            } else if self.config.peers.is_empty() {
+                tokio::spawn(pull_locally(artifact_service, state))
+            } else {
+                let peer = *self.config.peers.choose(&mut rand::thread_rng()).unwrap();
+                let artifact_id = peer_artifact_ids.choose(&mut rand::thread_rng()).cloned();
+                tokio::spawn(pull_from_peer(artifact_service, state, peer, artifact_id))
+            };
+            operations.push(operation);
+        }
+        for operation in operations {
+            operation.await?;
+        }
+        let elapsed = start.elapsed();
+
+        let report = {
+            let state = self.state.lock().unwrap();
+            LoadTestReport {
+                elapsed_ms: elapsed.as_millis() as u64,
+                publish: state.publish.report(elapsed),
+                pull: state.pull.report(elapsed),
+            }
+        };
+        self.remove_published_artifacts().await;
+        info!("Load test finished\n{}", report);
+        Ok(report)
+    }
+
+    async fn remove_published_artifacts(&self) {
+        let published_artifact_ids =
+            std::mem::take(&mut self.state.lock().unwrap().published_artifact_ids);
+        let mut artifact_service = self.artifact_service.clone();
+        for artifact_id in published_artifact_ids {
+            if let Err(error) = artifact_service.remove_artifact_locally(&artifact_id).await {
+                warn!(
+                    "Failed to remove load test artifact {}: {:?}",
+                    artifact_id, error
+                );
+            }
+        }
+    }
+}
+
+async fn publish(
+    mut artifact_service: ArtifactService,
+    state: Arc<Mutex<LoadTestState>>,
+    artifact_size: usize,
+) {
+    let start = Instant::now();
+    let mut artifact = vec![0; artifact_size];
+    rand::thread_rng().fill_bytes(&mut artifact);
+    let artifact_id = derive_artifact_id(&hex::encode(Sha256::digest(&artifact)));
+
+    let result = async {
+        artifact_service
+            .artifact_storage
+            .push_artifact(io::Cursor::new(artifact), &artifact_id)
+            .await?;
+        state
+            .lock()
+            .unwrap()
+            .published_artifact_ids
+            .push(artifact_id.clone());
+        artifact_service.p2p_client.provide(&artifact_id).await?;
+        Ok(artifact_size as u64)
+    }
+    .await;
+    state
+        .lock()
+        .unwrap()
+        .publish
+        .record(start.elapsed(), result);
+}
+
+async fn pull_locally(mut artifact_service: ArtifactService, state: Arc<Mutex<LoadTestState>>) {
+    let artifact_id = state
+        .lock()
+        .unwrap()
+        .published_artifact_ids
+        .choose(&mut rand::thread_rng())
+        .cloned();
+    // there is nothing to pull until the first artifact was published
+    let Some(artifact_id) = artifact_id else {
+        return;
+    };
+
+    let start = Instant::now();
+    let result = artifact_service
+        .get_artifact_locally(&artifact_id)
+        .await
+        .and_then(|artifact| verify_artifact_id(&artifact_id, &artifact));
+    state.lock().unwrap().pull.record(start.elapsed(), result);
+}
+
+async fn pull_from_peer(
+    mut artifact_service: ArtifactService,
+    state: Arc<Mutex<LoadTestState>>,
+    peer: PeerId,
+    artifact_id: Option<String>,
+) {
+    let start = Instant::now();
+    let result = match artifact_id {
+        Some(artifact_id) => artifact_service
+            .p2p_client
+            .request_artifact(&peer, &artifact_id)
+            .await
+            .and_then(|artifact| verify_artifact_id(&artifact_id, &artifact)),
+        None => Err(anyhow::anyhow!(
+            "There are no artifacts in the transparency log to pull"
+        )),
+    };
+    state.lock().unwrap().pull.record(start.elapsed(), result);
+}
+
+fn verify_artifact_id(artifact_id: &str, artifact: &[u8]) -> anyhow::Result<u64> {
+    let actual_artifact_id = derive_artifact_id(&hex::encode(Sha256::digest(artifact)));
+    if actual_artifact_id != artifact_id {
+        anyhow::bail!(
+            "Pulled artifact {} has artifact id {}",
+            artifact_id,
+            actual_artifact_id
+        );
+    }
+    Ok(artifact.len() as u64)
+}
+
+#[cfg(test)]
+#[cfg(not(tarpaulin_include))]
+mod tests {
+    use super::*;
+    use crate::artifact_service::model::PackageType;
+    use crate::build_service::event::BuildEventClient;
+    use crate::test_support::{
+        fake_transparency_log_service, in_memory_artifact_storage, FakeNetwork,
+    };
+    use crate::transparency_log::log::AddArtifactRequest;
+    use crate::util::test_util;
+    use std::path::Path;
+    use tokio::sync::mpsc;
+
+    fn create_artifact_service(tmp_dir: &Path, network: &FakeNetwork) -> ArtifactService {
+        let (transparency_log_service, ledger) = fake_transparency_log_service(tmp_dir).unwrap();
+        let mut artifact_service = ArtifactService::new(
+            tmp_dir,
+            ledger.blockchain_event_client(),
+            BuildEventClient::new(mpsc::channel(1).0),
+            network.client(),
+        )
+        .unwrap()
+        .with_artifact_storage(in_memory_artifact_storage());
+        artifact_service.transparency_log_service = transparency_log_service;
+        artifact_service
+    }
+
+    fn config(pull_ratio: f64, peers: Vec<PeerId>) -> LoadTestConfig {
+        LoadTestConfig {
+            rate: 200,
+            duration: Duration::from_millis(200),
+            artifact_size: 1024,
+            pull_ratio,
+            peers,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_load_test_against_itself() {
+        let tmp_dir = test_util::tests::setup();
+
+        let network = FakeNetwork::new();
+        let artifact_service = create_artifact_service(&tmp_dir, &network);
+
+        let report = LoadTest::new(artifact_service.clone(), config(0.5, vec![]))
+            .run()
+            .await
+            .unwrap();
+
+        assert!(report.publish.count > 0);
+        assert_eq!(report.publish.errors, 0);
+        assert_eq!(report.publish.bytes, report.publish.count * 1024);
+        assert_eq!(report.pull.errors, 0);
+        assert_eq!(
+            artifact_service
+                .artifact_storage
+                .list_artifacts()
+                .await
+                .unwrap()
+                .len(),
+            0
+        );
+        assert!(network.provided_artifact_ids().is_empty());
+
+        test_util::tests::teardown(tmp_dir);
+    }
+
+    #[tokio::test]
+    async fn test_load_test_against_peers() {
+        let tmp_dir = test_util::tests::setup();
+
+        let artifact = b"artifact";
+        let artifact_hash = hex::encode(Sha256::digest(artifact));
+        let peer = PeerId::random();
+        let network = FakeNetwork::new()
+            .with_artifact(peer, &derive_artifact_id(&artifact_hash), artifact)
+            .with_peer(PeerId::random());
+        let artifact_service = create_artifact_service(&tmp_dir, &network);
+        artifact_service
+            .transparency_log_service
+            .add_artifact(AddArtifactRequest {
+                package_type: PackageType::Docker,
+                package_specific_id: "alpine:3.16".to_owned(),
+                num_artifacts: 1,
+                package_specific_artifact_id: "alpine@sha256:1".to_owned(),
+                artifact_hash,
+            })
+            .await
+            .unwrap();
+
+        let report = LoadTest::new(artifact_service.clone(), config(1.0, vec![peer]))
+            .run()
+            .await
+            .unwrap();
+
+        assert_eq!(report.publish.count, 0);
+        assert!(report.pull.count > 0);
+        assert_eq!(report.pull.errors, 0);
+        assert_eq!(report.pull.bytes, report.pull.count * artifact.len() as u64);
+
+        let report = LoadTest::new(artifact_service, config(1.0, vec![PeerId::random()]))
+            .run()
+            .await
+            .unwrap();
+        assert_eq!(report.pull.error_rate, 1.0);
+
+        test_util::tests::teardown(tmp_dir);
+    }
+
+    #[test]
+    fn test_operation_report() {
+        let mut stats = OperationStats::default();
+        for latency in 1..=100 {
+            stats.record(Duration::from_millis(latency), Ok(10));
+        }
+        stats.record(Duration::from_millis(500), Err(anyhow::anyhow!("failure")));
+
+        let report = stats.report(Duration::from_secs(2));
+        assert_eq!(report.count, 101);
+        assert_eq!(report.errors, 1);
+        assert_eq!(report.bytes, 1000);
+        assert_eq!(report.latency_p50_ms, 51);
+        assert_eq!(report.latency_max_ms, 500);
+        assert!((report.throughput_per_second - 50.5).abs() < f64::EPSILON);
+    }
+}
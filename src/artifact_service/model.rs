@@ -14,9 +14,34 @@
    limitations under the License.
 */
 
+use super::blob_store::BlobReader;
+use super::budget::TransferPermit;
+use bytes::Bytes;
+use futures::stream::{self, BoxStream};
+use futures::{future, Stream, StreamExt};
 use rusqlite::types::ToSqlOutput;
 use rusqlite::ToSql;
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+use std::io::{self, Read};
+use std::time::Duration;
+use thiserror::Error;
+
+const METADATA_FETCH_TIMEOUT: Duration = Duration::from_secs(10);
+const BLOB_FETCH_TIMEOUT: Duration = Duration::from_secs(120);
+/// The size of the chunks in which blobs are downloaded from several peers
+/// at once.
+pub const ARTIFACT_CHUNK_SIZE: u64 = 4 * 1024 * 1024;
+/// The maximum number of chunks of a blob that are downloaded at once.
+pub const MAX_PARALLEL_CHUNK_DOWNLOADS: usize = 8;
+/// The size of the chunks in which stored artifacts are streamed to clients.
+const STREAM_CHUNK_SIZE: usize = 64 * 1024;
+
+/// File extensions of maven artifacts that describe other artifacts.
+const MAVEN_METADATA_EXTENSIONS: [&str; 8] = [
+    "pom", "xml", "module", "sha1", "md5", "sha256", "sha512", "asc",
+];
 
 #[derive(
     Clone,
@@ -25,7 +50,9 @@ use serde::{Deserialize, Serialize};
     Deserialize,
     Eq,
     Hash,
+    Ord,
     PartialEq,
+    PartialOrd,
     Serialize,
     strum_macros::Display,
     strum_macros::EnumString,
@@ -40,3 +67,655 @@ impl ToSql for PackageType {
         Ok(ToSqlOutput::from(self.to_string()))
     }
 }
+
+/// The kind of an artifact. Metadata artifacts are small files that describe
+/// a package, like Docker manifests and maven POMs. They are requested first
+/// and often, so they are cached in memory and replicated more eagerly than
+/// blobs, which hold the actual package content.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ArtifactKind {
+    Metadata,
+    Blob,
+}
+
+impl ArtifactKind {
+    /// Classify an artifact by its package specific artifact id. Docker
+    /// artifacts referenced by tag are manifests, artifacts referenced by
+    /// digest are treated as blobs.
+    pub fn of(package_type: PackageType, package_specific_artifact_id: &str) -> ArtifactKind {
+        let is_metadata = match package_type {
+            PackageType::Docker => !package_specific_artifact_id.contains('@'),
+            PackageType::Maven2 => {
+                let file_name = package_specific_artifact_id
+                    .rsplit('/')
+                    .next()
+                    .unwrap_or(package_specific_artifact_id);
+                match file_name.rsplit_once('.') {
+                    Some((_, extension)) => MAVEN_METADATA_EXTENSIONS.contains(&extension),
+                    None => false,
+                }
+            }
+        };
+
+        if is_metadata {
+            ArtifactKind::Metadata
+        } else {
+            ArtifactKind::Blob
+        }
+    }
+
+    /// The maximum time to wait for a peer to respond with an artifact of this kind.
+    pub fn fetch_timeout(&self) -> Duration {
+        match self {
+            ArtifactKind::Metadata => METADATA_FETCH_TIMEOUT,
+            ArtifactKind::Blob => BLOB_FETCH_TIMEOUT,
+        }
+    }
+}
+
+/// Controls how often fetching an artifact from the providers on the p2p
+/// network is retried. A failed request is first retried with the next
+/// provider. When all providers failed, the fetch waits for an exponentially
+/// growing backoff and tries all providers again, up to `max_retries` times.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FetchRetryPolicy {
+    /// The number of times all providers are tried again, or 0 to not retry.
+    pub max_retries: u32,
+    /// The backoff before the first retry, which doubles for every retry.
+    pub initial_backoff: Duration,
+    /// The maximum backoff between two retries.
+    pub max_backoff: Duration,
+}
+
+impl Default for FetchRetryPolicy {
+    fn default() -> Self {
+        FetchRetryPolicy {
+            max_retries: 3,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(8),
+        }
+    }
+}
+
+impl FetchRetryPolicy {
+    /// The time to wait before retry number `retry`, starting at 1.
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let factor = 2u32.saturating_pow(retry.saturating_sub(1));
+        self.initial_backoff
+            .saturating_mul(factor)
+            .min(self.max_backoff)
+    }
+}
+
+/// The progress of providing the artifacts that are stored locally on the p2p
+/// network, which happens in the background when a node starts.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, Eq, PartialEq)]
+pub struct ProvideProgress {
+    pub provided: usize,
+    pub failed: usize,
+    pub finished: bool,
+}
+
+/// The state of a sync from a seed node.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SeedSyncState {
+    #[default]
+    NotStarted,
+    Running,
+    Finished,
+    Failed,
+}
+
+/// The progress of copying the transparency logs and the popular and pinned
+/// artifacts of a seed node to a new node.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, Eq, PartialEq)]
+pub struct SeedSyncProgress {
+    /// The address of the seed node.
+    pub seed: Option<String>,
+    pub state: SeedSyncState,
+    /// The transparency logs that were new to this node.
+    pub transparency_logs_imported: usize,
+    /// The artifacts that the seed node offered.
+    pub artifacts_total: usize,
+    pub artifacts_synced: usize,
+    /// The artifacts that this node already stored, e.g. from an
+    /// interrupted sync.
+    pub artifacts_skipped: usize,
+    /// The artifacts that could not be fetched or failed verification.
+    pub artifacts_failed: usize,
+    pub bytes_synced: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl SeedSyncProgress {
+    /// The number of offered artifacts that were handled so far.
+    pub fn artifacts_done(&self) -> usize {
+        self.artifacts_synced + self.artifacts_skipped + self.artifacts_failed
+    }
+}
+
+/// The result of one scrub of the locally stored artifacts against the hashes
+/// in their transparency logs.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, Eq, PartialEq)]
+pub struct ScrubReport {
+    /// The number of artifacts whose hash matched their transparency log.
+    pub verified: usize,
+    /// The ids of the artifacts whose hash did not match, or that could not
+    /// be read.
+    pub corrupt: Vec<String>,
+    /// The ids of the corrupt artifacts that were fetched again from peers.
+    pub repaired: Vec<String>,
+    /// The number of artifacts without a transparency log to check against.
+    pub unverifiable: usize,
+}
+
+/// The totals of all scrubs since the node started, for monitoring.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, Eq, PartialEq)]
+pub struct ScrubStats {
+    pub scrubs: usize,
+    pub verified: usize,
+    pub corrupt: usize,
+    pub repaired: usize,
+    pub unverifiable: usize,
+}
+
+impl ScrubStats {
+    pub fn record(&mut self, report: &ScrubReport) {
+        self.scrubs += 1;
+        self.verified += report.verified;
+        self.corrupt += report.corrupt.len();
+        self.repaired += report.repaired.len();
+        self.unverifiable += report.unverifiable;
+    }
+}
+
+/// How much the local storage saves by storing identical artifacts that are
+/// published under several packages only once.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, Eq, PartialEq)]
+pub struct DedupStats {
+    /// The number of stored artifacts.
+    pub stored_artifacts: usize,
+    /// The number of bytes of the stored artifacts.
+    pub stored_bytes: u64,
+    /// The number of package artifacts that reference a stored artifact.
+    pub references: usize,
+    /// The number of stored artifacts that are referenced by more than one
+    /// package artifact.
+    pub shared_artifacts: usize,
+    /// The number of stored artifacts that no package artifact references
+    /// anymore.
+    pub unreferenced_artifacts: usize,
+    /// The number of bytes that storing each reference separately would take
+    /// in addition.
+    pub saved_bytes: u64,
+}
+
+impl DedupStats {
+    pub fn record(&mut self, size: u64, references: usize) {
+        self.stored_artifacts += 1;
+        self.stored_bytes += size;
+        self.references += references;
+        match references {
+            0 => self.unreferenced_artifacts += 1,
+            1 => {}
+            _ => {
+                self.shared_artifacts += 1;
+                self.saved_bytes += size * (references as u64 - 1);
+            }
+        }
+    }
+}
+
+/// How much space the stored artifacts take up, by package type, and how much
+/// space is left for new artifacts.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, Eq, PartialEq)]
+pub struct StorageStats {
+    /// The number of stored artifacts.
+    pub artifacts: usize,
+    /// The number of bytes of the stored artifacts.
+    pub total_bytes: u64,
+    /// The number of bytes of the stored artifacts of every package type. An
+    /// artifact that is shared by packages of several types counts for each
+    /// of them.
+    pub bytes_per_package_type: BTreeMap<PackageType, u64>,
+    /// The number of bytes of the stored artifacts that no package
+    /// references anymore.
+    pub unreferenced_bytes: u64,
+    /// The number of bytes that can still be stored, when it is known.
+    pub free_bytes: Option<u64>,
+}
+
+impl StorageStats {
+    pub fn record(&mut self, size: u64, package_types: Option<&HashSet<PackageType>>) {
+        self.artifacts += 1;
+        self.total_bytes += size;
+        match package_types {
+            Some(package_types) if !package_types.is_empty() => {
+                for package_type in package_types {
+                    *self
+                        .bytes_per_package_type
+                        .entry(*package_type)
+                        .or_insert(0) += size;
+                }
+            }
+            _ => self.unreferenced_bytes += size,
+        }
+    }
+}
+
+/// The outcome of a single integrity check of an artifact, with a human
+/// readable detail about what was checked.
+#[derive(Clone, Debug, Deserialize, Serialize, Eq, PartialEq)]
+#[serde(tag = "status", content = "detail", rename_all = "lowercase")]
+pub enum CheckOutcome {
+    Passed(String),
+    Failed(String),
+    Skipped(String),
+}
+
+impl CheckOutcome {
+    /// A skipped check does not count as a failure.
+    pub fn is_failed(&self) -> bool {
+        matches!(self, CheckOutcome::Failed(_))
+    }
+}
+
+/// The result of the end-to-end integrity check of an artifact of a package.
+#[derive(Clone, Debug, Deserialize, Serialize, Eq, PartialEq)]
+pub struct ArtifactCheck {
+    pub package_specific_artifact_id: String,
+    pub artifact_id: String,
+    /// the locally stored copy matches the hash in the transparency log
+    pub local_hash: CheckOutcome,
+    /// the artifact is provided by at least one peer on the p2p network
+    pub dht_availability: CheckOutcome,
+    /// the artifact can be retrieved from a remote provider
+    pub peer_retrieval: CheckOutcome,
+    /// the transparency log is part of a valid block committed by an authorized node
+    pub provenance: CheckOutcome,
+}
+
+impl ArtifactCheck {
+    pub fn is_passed(&self) -> bool {
+        ![
+            &self.local_hash,
+            &self.dht_availability,
+            &self.peer_retrieval,
+            &self.provenance,
+        ]
+        .iter()
+        .any(|outcome| outcome.is_failed())
+    }
+}
+
+/// The number of artifacts in a page of artifact search results by default.
+pub const DEFAULT_ARTIFACT_PAGE_SIZE: usize = 100;
+/// The maximum number of artifacts in a page of artifact search results.
+pub const MAX_ARTIFACT_PAGE_SIZE: usize = 1000;
+
+/// A filter on the artifacts that are stored on this node. The name is a
+/// prefix of the package specific id, the version must match exactly.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, Eq, PartialEq)]
+pub struct ArtifactQuery {
+    pub package_type: Option<PackageType>,
+    pub name: Option<String>,
+    pub version: Option<String>,
+    #[serde(default)]
+    pub offset: usize,
+    pub limit: Option<usize>,
+}
+
+impl ArtifactQuery {
+    /// The number of artifacts in the requested page, capped at
+    /// [`MAX_ARTIFACT_PAGE_SIZE`].
+    pub fn page_size(&self) -> usize {
+        self.limit
+            .unwrap_or(DEFAULT_ARTIFACT_PAGE_SIZE)
+            .min(MAX_ARTIFACT_PAGE_SIZE)
+    }
+}
+
+/// An artifact that is stored on this node.
+#[derive(Clone, Debug, Deserialize, Serialize, Eq, PartialEq)]
+pub struct ArtifactSummary {
+    pub package_type: PackageType,
+    pub package_specific_id: String,
+    pub package_specific_artifact_id: String,
+    pub artifact_id: String,
+    pub artifact_hash: String,
+    pub size: u64,
+}
+
+/// A page of artifact search results. `total` is the number of artifacts
+/// that match the query over all pages.
+#[derive(Clone, Debug, Deserialize, Serialize, Eq, PartialEq)]
+pub struct ArtifactPage {
+    pub total: usize,
+    pub offset: usize,
+    pub artifacts: Vec<ArtifactSummary>,
+}
+
+/// The version part of a package specific id, e.g. `3.15.2` of
+/// `library/alpine:3.15.2` or `1.0` of `com.myorg:my-artifact:1.0`.
+/// Docker images referenced by digest have the digest as version.
+pub fn package_version(package_specific_id: &str) -> Option<&str> {
+    match package_specific_id.split_once('@') {
+        Some((_, digest)) => Some(digest),
+        None => package_specific_id
+            .rsplit_once(':')
+            .map(|(_, version)| version),
+    }
+}
+
+/// A single range of bytes of an artifact as requested with an HTTP `Range`
+/// header, see RFC 7233.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ByteRange {
+    /// The bytes from the first to the last offset, inclusive.
+    FromTo(u64, u64),
+    /// The bytes from the offset to the end of the artifact.
+    From(u64),
+    /// The last number of bytes of the artifact.
+    Suffix(u64),
+}
+
+impl ByteRange {
+    /// Parse the value of a `Range` header. Multiple ranges and units other
+    /// than bytes are not supported.
+    pub fn parse(range: &str) -> Option<ByteRange> {
+        let (first, last) = range.trim().strip_prefix("bytes=")?.split_once('-')?;
+        match (first.trim(), last.trim()) {
+            ("", last) => Some(ByteRange::Suffix(last.parse().ok()?)),
+            (first, "") => Some(ByteRange::From(first.parse().ok()?)),
+            (first, last) => {
+                let (first, last) = (first.parse().ok()?, last.parse().ok()?);
+                (first <= last).then_some(ByteRange::FromTo(first, last))
+            }
+        }
+    }
+
+    /// The range of bytes of an artifact of `len` bytes, or `None` if the
+    /// range does not overlap with the artifact.
+    pub fn content_range(&self, len: u64) -> Option<ContentRange> {
+        let (start, end) = match *self {
+            ByteRange::FromTo(first, last) => (first, last.min(len.checked_sub(1)?)),
+            ByteRange::From(first) => (first, len.checked_sub(1)?),
+            ByteRange::Suffix(0) => return None,
+            ByteRange::Suffix(suffix) => (len.saturating_sub(suffix), len.checked_sub(1)?),
+        };
+        (start <= end).then_some(ContentRange {
+            start,
+            end,
+            total: len,
+        })
+    }
+}
+
+/// The range of bytes of an artifact that is served, formatted as the value
+/// of an HTTP `Content-Range` header.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ContentRange {
+    pub start: u64,
+    /// The offset of the last byte, inclusive.
+    pub end: u64,
+    /// The size of the complete artifact in bytes.
+    pub total: u64,
+}
+
+impl ContentRange {
+    pub fn length(&self) -> u64 {
+        self.end - self.start + 1
+    }
+}
+
+impl fmt::Display for ContentRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "bytes {}-{}/{}", self.start, self.end, self.total)
+    }
+}
+
+/// The requested range of bytes does not overlap with the artifact.
+#[derive(Debug, Error, Eq, PartialEq)]
+#[error("Requested range not satisfiable for an artifact of {len} bytes")]
+pub struct RangeNotSatisfiable {
+    pub len: u64,
+}
+
+/// The outcome of retrieving an artifact that is built when it is not
+/// available.
+#[derive(Debug)]
+pub enum ArtifactOrBuild<T> {
+    Artifact(T),
+    /// The artifact is not available and a build of its package was
+    /// requested. The status of the build can be followed with its id.
+    BuildRequested {
+        build_id: String,
+    },
+}
+
+/// The content of an artifact as a stream of chunks, so that large artifacts
+/// can be served without holding them in memory as a whole.
+pub struct ArtifactStream {
+    /// The number of bytes in the stream.
+    pub len: u64,
+    /// The range of the artifact in the stream, if only part of it is served.
+    pub content_range: Option<ContentRange>,
+    pub chunks: BoxStream<'static, io::Result<Bytes>>,
+}
+
+impl ArtifactStream {
+    pub fn from_bytes(artifact: Vec<u8>) -> Self {
+        ArtifactStream {
+            len: artifact.len() as u64,
+            content_range: None,
+            chunks: stream::once(future::ready(Ok(Bytes::from(artifact)))).boxed(),
+        }
+    }
+
+    /// Stream the part of `artifact` in `content_range`.
+    pub fn from_bytes_range(
+        artifact: Vec<u8>,
+        content_range: ContentRange,
+    ) -> Result<Self, RangeNotSatisfiable> {
+        let len = artifact.len() as u64;
+        if content_range.total != len || content_range.end >= len {
+            return Err(RangeNotSatisfiable { len });
+        }
+        let mut artifact_stream = ArtifactStream::from_bytes(
+            artifact[content_range.start as usize..=content_range.end as usize].to_vec(),
+        );
+        artifact_stream.content_range = Some(content_range);
+        Ok(artifact_stream)
+    }
+
+    /// Stream an artifact of `len` bytes from a reader of a
+    /// [`BlobStore`](super::blob_store::BlobStore).
+    pub fn from_reader(reader: BlobReader, len: u64) -> Self {
+        ArtifactStream {
+            len,
+            content_range: None,
+            chunks: read_chunks(reader).boxed(),
+        }
+    }
+
+    /// Stream the part of an artifact that `reader` is limited to, see
+    /// [`ArtifactStorage::pull_artifact_range`](super::storage::ArtifactStorage::pull_artifact_range).
+    pub fn from_reader_range(reader: BlobReader, content_range: ContentRange) -> Self {
+        ArtifactStream {
+            len: content_range.length(),
+            content_range: Some(content_range),
+            chunks: read_chunks(reader).boxed(),
+        }
+    }
+
+    /// Keep the transfer `permit` until the stream is dropped, so that the
+    /// transfer counts against the [`TransferBudget`](super::budget::TransferBudget)
+    /// for as long as it is being served.
+    pub fn with_transfer_permit(mut self, permit: TransferPermit) -> Self {
+        self.chunks = self
+            .chunks
+            .inspect(move |_| {
+                let _permit = &permit;
+            })
+            .boxed();
+        self
+    }
+
+    /// Read the complete artifact into memory.
+    pub async fn into_bytes(mut self) -> io::Result<Vec<u8>> {
+        let mut artifact = Vec::with_capacity(self.len as usize);
+        while let Some(chunk) = self.chunks.next().await {
+            artifact.extend_from_slice(&chunk?);
+        }
+        Ok(artifact)
+    }
+}
+
+// Blob stores are read with blocking IO, so every chunk is read on a thread
+// where blocking is allowed.
+fn read_chunks(reader: BlobReader) -> impl Stream<Item = io::Result<Bytes>> {
+    stream::try_unfold(reader, |mut reader| async move {
+        let (reader, chunk) = tokio::task::spawn_blocking(move || {
+            let mut chunk = vec![0; STREAM_CHUNK_SIZE];
+            let len = reader.read(&mut chunk)?;
+            chunk.truncate(len);
+            Ok::<_, io::Error>((reader, chunk))
+        })
+        .await
+        .map_err(|error| io::Error::new(io::ErrorKind::Other, error))??;
+        Ok((!chunk.is_empty()).then(|| (Bytes::from(chunk), reader)))
+    })
+}
+
+#[cfg(test)]
+#[cfg(not(tarpaulin_include))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_package_version() {
+        assert_eq!(package_version("library/alpine:3.15.2"), Some("3.15.2"));
+        assert_eq!(package_version("com.myorg:my-artifact:1.0"), Some("1.0"));
+        assert_eq!(
+            package_version("library/alpine@sha256:1e01"),
+            Some("sha256:1e01")
+        );
+        assert_eq!(package_version("library/alpine"), None);
+    }
+
+    #[test]
+    fn test_fetch_retry_policy_backoff() {
+        let retry_policy = FetchRetryPolicy {
+            max_retries: 10,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(1),
+        };
+
+        assert_eq!(retry_policy.backoff(1), Duration::from_millis(100));
+        assert_eq!(retry_policy.backoff(2), Duration::from_millis(200));
+        assert_eq!(retry_policy.backoff(4), Duration::from_millis(800));
+        assert_eq!(retry_policy.backoff(5), Duration::from_secs(1));
+        assert_eq!(retry_policy.backoff(40), Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_artifact_query_page_size() {
+        assert_eq!(
+            ArtifactQuery::default().page_size(),
+            DEFAULT_ARTIFACT_PAGE_SIZE
+        );
+        let query = ArtifactQuery {
+            limit: Some(MAX_ARTIFACT_PAGE_SIZE + 1),
+            ..Default::default()
+        };
+        assert_eq!(query.page_size(), MAX_ARTIFACT_PAGE_SIZE);
+    }
+
+    #[test]
+    fn test_artifact_kind_of_docker_artifacts() {
+        assert_eq!(
+            ArtifactKind::of(PackageType::Docker, "library/alpine:3.15.2"),
+            ArtifactKind::Metadata
+        );
+        assert_eq!(
+            ArtifactKind::of(
+                PackageType::Docker,
+                "library/alpine@sha256:1e014f84205d569a5cc3be4e108ca614055f7e21d11928946113ab3f36054801"
+            ),
+            ArtifactKind::Blob
+        );
+    }
+
+    #[test]
+    fn test_artifact_kind_of_maven_artifacts() {
+        assert_eq!(
+            ArtifactKind::of(PackageType::Maven2, "com/company/test/1.0/test-1.0.pom"),
+            ArtifactKind::Metadata
+        );
+        assert_eq!(
+            ArtifactKind::of(
+                PackageType::Maven2,
+                "com/company/test/1.0/test-1.0.jar.sha1"
+            ),
+            ArtifactKind::Metadata
+        );
+        assert_eq!(
+            ArtifactKind::of(PackageType::Maven2, "com/company/test/1.0/test-1.0.jar"),
+            ArtifactKind::Blob
+        );
+    }
+
+    #[test]
+    fn test_parse_byte_range() {
+        assert_eq!(
+            ByteRange::parse("bytes=0-99"),
+            Some(ByteRange::FromTo(0, 99))
+        );
+        assert_eq!(ByteRange::parse("bytes=100-"), Some(ByteRange::From(100)));
+        assert_eq!(ByteRange::parse("bytes=-50"), Some(ByteRange::Suffix(50)));
+        assert_eq!(ByteRange::parse("bytes=99-0"), None);
+        assert_eq!(ByteRange::parse("bytes=0-1,5-9"), None);
+        assert_eq!(ByteRange::parse("items=0-1"), None);
+    }
+
+    #[test]
+    fn test_byte_range_content_range() {
+        let content_range = |start, end| {
+            Some(ContentRange {
+                start,
+                end,
+                total: 100,
+            })
+        };
+        assert_eq!(
+            ByteRange::FromTo(0, 9).content_range(100),
+            content_range(0, 9)
+        );
+        assert_eq!(
+            ByteRange::FromTo(90, 199).content_range(100),
+            content_range(90, 99)
+        );
+        assert_eq!(
+            ByteRange::From(40).content_range(100),
+            content_range(40, 99)
+        );
+        assert_eq!(
+            ByteRange::Suffix(10).content_range(100),
+            content_range(90, 99)
+        );
+        assert_eq!(
+            ByteRange::Suffix(200).content_range(100),
+            content_range(0, 99)
+        );
+        assert_eq!(ByteRange::From(100).content_range(100), None);
+        assert_eq!(ByteRange::Suffix(0).content_range(100), None);
+        assert_eq!(ByteRange::From(0).content_range(0), None);
+
+        assert_eq!(
+            content_range(90, 99).unwrap().to_string(),
+            "bytes 90-99/100"
+        );
+    }
+}
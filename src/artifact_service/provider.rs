@@ -0,0 +1,326 @@
+/*
+   Copyright 2021 JFrog Ltd
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+*/
+
+//! The operations on artifacts that the frontends of a node, like the
+//! Docker and Maven registry facades, depend on.
+//!
+//! The frontends only use an [`ArtifactProvider`], so that another
+//! implementation than the [`ArtifactService`] of the node, like a caching
+//! proxy, a federated resolver or a fake in tests, can serve them.
+
+use super::model::{ArtifactOrBuild, ArtifactStream, ByteRange, PackageType};
+use super::service::ArtifactService;
+use crate::build_service::error::BuildError;
+use crate::transparency_log::log::TransparencyLog;
+use async_trait::async_trait;
+
+#[async_trait]
+pub trait ArtifactProvider: Clone + Send + Sync + 'static {
+    /// Get the content of an artifact.
+    async fn get_artifact(
+        &mut self,
+        package_type: PackageType,
+        package_specific_artifact_id: &str,
+    ) -> anyhow::Result<Vec<u8>>;
+
+    /// Get the content of an artifact, or request a build of its package
+    /// when the artifact does not exist.
+    async fn get_artifact_or_build(
+        &mut self,
+        package_type: PackageType,
+        package_specific_id: &str,
+        package_specific_artifact_id: &str,
+    ) -> anyhow::Result<ArtifactOrBuild<Vec<u8>>>;
+
+    /// Stream the content of an artifact, or the part of it in `range`, or
+    /// request a build of its package when the artifact does not exist.
+    async fn get_artifact_stream_or_build(
+        &mut self,
+        package_type: PackageType,
+        package_specific_id: &str,
+        package_specific_artifact_id: &str,
+        range: Option<ByteRange>,
+    ) -> anyhow::Result<ArtifactOrBuild<ArtifactStream>>;
+
+    /// Publish the artifacts of a package that were obtained elsewhere than
+    /// from a build.
+    async fn put_artifacts(
+        &mut self,
+        package_type: PackageType,
+        package_specific_id: &str,
+        source: &str,
+        artifacts: Vec<(String, Vec<u8>)>,
+    ) -> Result<(), BuildError>;
+
+    /// Request a build of a package and return the id of the build.
+    async fn request_build(
+        &self,
+        package_type: PackageType,
+        package_specific_id: String,
+    ) -> Result<String, BuildError>;
+
+    async fn get_build_status(&mut self, build_id: &str) -> Result<String, BuildError>;
+
+    /// The transparency logs of the artifacts of a package.
+    async fn get_logs_for_artifact(
+        &mut self,
+        package_type: PackageType,
+        package_specific_id: &str,
+    ) -> anyhow::Result<Vec<TransparencyLog>>;
+
+    /// All transparency logs of an artifact, oldest first, including the
+    /// ones of artifacts it was replaced by.
+    async fn get_artifact_history(
+        &self,
+        package_type: PackageType,
+        package_specific_artifact_id: &str,
+    ) -> anyhow::Result<Vec<TransparencyLog>>;
+
+    /// The warning to show to users of an artifact of a deprecated package.
+    fn get_deprecation_warning(
+        &mut self,
+        package_type: PackageType,
+        package_specific_artifact_id: &str,
+    ) -> Option<String>;
+}
+
+#[async_trait]
+impl ArtifactProvider for ArtifactService {
+    async fn get_artifact(
+        &mut self,
+        package_type: PackageType,
+        package_specific_artifact_id: &str,
+    ) -> anyhow::Result<Vec<u8>> {
+        ArtifactService::get_artifact(self, package_type, package_specific_artifact_id).await
+    }
+
+    async fn get_artifact_or_build(
+        &mut self,
+        package_type: PackageType,
+        package_specific_id: &str,
+        package_specific_artifact_id: &str,
+    ) -> anyhow::Result<ArtifactOrBuild<Vec<u8>>> {
+        ArtifactService::get_artifact_or_build(
+            self,
+            package_type,
+            package_specific_id,
+            package_specific_artifact_id,
+        )
+        .await
+    }
+
+    async fn get_artifact_stream_or_build(
+        &mut self,
+        package_type: PackageType,
+        package_specific_id: &str,
+        package_specific_artifact_id: &str,
+        range: Option<ByteRange>,
+    ) -> anyhow::Result<ArtifactOrBuild<ArtifactStream>> {
+        ArtifactService::get_artifact_stream_or_build(
+            self,
+            package_type,
+            package_specific_id,
+            package_specific_artifact_id,
+            range,
+        )
+        .await
+    }
+
+    async fn put_artifacts(
+        &mut self,
+        package_type: PackageType,
+        package_specific_id: &str,
+        source: &str,
+        artifacts: Vec<(String, Vec<u8>)>,
+    ) -> Result<(), BuildError> {
+        self.import_artifacts(package_type, package_specific_id, source, artifacts)
+            .await
+    }
+
+    async fn request_build(
+        &self,
+        package_type: PackageType,
+        package_specific_id: String,
+    ) -> Result<String, BuildError> {
+        ArtifactService::request_build(self, package_type, package_specific_id).await
+    }
+
+    async fn get_build_status(&mut self, build_id: &str) -> Result<String, BuildError> {
+        ArtifactService::get_build_status(self, build_id).await
+    }
+
+    async fn get_logs_for_artifact(
+        &mut self,
+        package_type: PackageType,
+        package_specific_id: &str,
+    ) -> anyhow::Result<Vec<TransparencyLog>> {
+        ArtifactService::get_logs_for_artifact(self, package_type, package_specific_id).await
+    }
+
+    async fn get_artifact_history(
+        &self,
+        package_type: PackageType,
+        package_specific_artifact_id: &str,
+    ) -> anyhow::Result<Vec<TransparencyLog>> {
+        Ok(self
+            .transparency_log_service
+            .find_artifact_history(&package_type, package_specific_artifact_id)?)
+    }
+
+    fn get_deprecation_warning(
+        &mut self,
+        package_type: PackageType,
+        package_specific_artifact_id: &str,
+    ) -> Option<String> {
+        ArtifactService::get_deprecation_warning(self, package_type, package_specific_artifact_id)
+    }
+}
+
+// the fake provider is only served through the Docker facade
+#[cfg(test)]
+#[cfg(not(tarpaulin_include))]
+#[cfg(feature = "docker-facade")]
+mod tests {
+    use super::*;
+    use crate::docker::v2::routes::make_docker_routes;
+    use anyhow::anyhow;
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Clone, Default)]
+    struct FakeArtifactProvider {
+        artifacts: Arc<Mutex<HashMap<String, Vec<u8>>>>,
+    }
+
+    #[async_trait]
+    impl ArtifactProvider for FakeArtifactProvider {
+        async fn get_artifact(
+            &mut self,
+            _package_type: PackageType,
+            package_specific_artifact_id: &str,
+        ) -> anyhow::Result<Vec<u8>> {
+            self.artifacts
+                .lock()
+                .unwrap()
+                .get(package_specific_artifact_id)
+                .cloned()
+                .ok_or_else(|| anyhow!("artifact not found"))
+        }
+
+        async fn get_artifact_or_build(
+            &mut self,
+            package_type: PackageType,
+            _package_specific_id: &str,
+            package_specific_artifact_id: &str,
+        ) -> anyhow::Result<ArtifactOrBuild<Vec<u8>>> {
+            match self
+                .get_artifact(package_type, package_specific_artifact_id)
+                .await
+            {
+                Ok(artifact) => Ok(ArtifactOrBuild::Artifact(artifact)),
+                Err(_) => Ok(ArtifactOrBuild::BuildRequested {
+                    build_id: "fake_build".to_owned(),
+                }),
+            }
+        }
+
+        async fn get_artifact_stream_or_build(
+            &mut self,
+            _package_type: PackageType,
+            _package_specific_id: &str,
+            _package_specific_artifact_id: &str,
+            _range: Option<ByteRange>,
+        ) -> anyhow::Result<ArtifactOrBuild<ArtifactStream>> {
+            Err(anyhow!("not supported"))
+        }
+
+        async fn put_artifacts(
+            &mut self,
+            _package_type: PackageType,
+            _package_specific_id: &str,
+            _source: &str,
+            artifacts: Vec<(String, Vec<u8>)>,
+        ) -> Result<(), BuildError> {
+            self.artifacts.lock().unwrap().extend(artifacts);
+            Ok(())
+        }
+
+        async fn request_build(
+            &self,
+            _package_type: PackageType,
+            _package_specific_id: String,
+        ) -> Result<String, BuildError> {
+            Ok("fake_build".to_owned())
+        }
+
+        async fn get_build_status(&mut self, _build_id: &str) -> Result<String, BuildError> {
+            Ok("Success".to_owned())
+        }
+
+        async fn get_logs_for_artifact(
+            &mut self,
+            _package_type: PackageType,
+            _package_specific_id: &str,
+        ) -> anyhow::Result<Vec<TransparencyLog>> {
+            Ok(vec![])
+        }
+
+        async fn get_artifact_history(
+            &self,
+            _package_type: PackageType,
+            _package_specific_artifact_id: &str,
+        ) -> anyhow::Result<Vec<TransparencyLog>> {
+            Ok(vec![])
+        }
+
+        fn get_deprecation_warning(
+            &mut self,
+            _package_type: PackageType,
+            _package_specific_artifact_id: &str,
+        ) -> Option<String> {
+            None
+        }
+    }
+
+    #[tokio::test]
+    async fn test_docker_routes_with_other_provider() {
+        let mut provider = FakeArtifactProvider::default();
+        provider
+            .put_artifacts(
+                PackageType::Docker,
+                "library/alpine:3.16",
+                "test",
+                vec![("library/alpine:3.16".to_owned(), b"manifest".to_vec())],
+            )
+            .await
+            .unwrap();
+
+        let filter = make_docker_routes(provider);
+
+        let response = warp::test::request()
+            .path("/v2/library/alpine/manifests/3.16")
+            .reply(&filter)
+            .await;
+        assert_eq!(response.status(), 200);
+        assert_eq!(response.body().as_ref(), b"manifest");
+
+        let response = warp::test::request()
+            .path("/v2/library/alpine/manifests/3.17")
+            .reply(&filter)
+            .await;
+        assert_eq!(response.status(), 404);
+    }
+}
@@ -14,25 +14,64 @@
    limitations under the License.
 */
 
-use super::model::PackageType;
+use super::access_stats::{AccessStats, PackageAccessSummary};
+use super::authorization::ArtifactRequestPolicy;
+use super::availability::{AvailabilityMonitor, AvailabilityReport, AvailabilitySample};
+use super::budget::{BudgetExceeded, TransferBudget};
+use super::coordinates::{
+    normalize_package_name, normalize_package_specific_artifact_id, normalize_package_specific_id,
+};
+use super::hooks::{ArtifactHookEvent, HookError, HookPoint, LifecycleHooks};
+use super::metadata_cache::MetadataCache;
+use super::model::{
+    package_version, ArtifactCheck, ArtifactKind, ArtifactOrBuild, ArtifactPage, ArtifactQuery,
+    ArtifactStream, ArtifactSummary, ByteRange, CheckOutcome, DedupStats, FetchRetryPolicy,
+    PackageType, ProvideProgress, RangeNotSatisfiable, ScrubReport, ScrubStats, SeedSyncProgress,
+    SeedSyncState, StorageStats, ARTIFACT_CHUNK_SIZE, MAX_PARALLEL_CHUNK_DOWNLOADS,
+};
+use super::progress::{
+    ProgressReader, TransferDirection, TransferProgress, TransferProgressTracker,
+};
+use super::provide::{ArtifactPopularity, ProvideSchedule};
+use super::quota::SoleCopyPolicy;
 use super::storage::ArtifactStorage;
+use super::tag_policy::{TagImmutabilityPolicy, TagPolicyError};
+use crate::accounting_service::service::{UsageAccounting, UsageSubject};
+use crate::alert_service::service::AlertService;
 use crate::blockchain_service::event::BlockchainEventClient;
 use crate::build_service::error::BuildError;
 use crate::build_service::event::BuildEventClient;
-use crate::build_service::model::BuildResult;
+use crate::build_service::history::{BuildHistoryQuery, BuildRecord};
+use crate::build_service::model::{BuildResult, BuildResultArtifact, BuildSource};
+use crate::network::artifact_protocol::{ArtifactChunk, ArtifactResponse};
 use crate::network::client::Client;
+use crate::network::seed_sync_protocol::{
+    SeedSyncRequest, SeedSyncResponse, SEED_SYNC_MAX_ARTIFACTS,
+};
+use crate::subscription_service::service::SubscriptionService;
+use crate::transparency_log::audit::{self, Inconsistency};
 use crate::transparency_log::log::{
-    AddArtifactRequest, TransparencyLog, TransparencyLogError, TransparencyLogService,
+    AddArtifactRequest, LogSnapshot, Operation, TransparencyLog, TransparencyLogError,
+    TransparencyLogService, IMPORTED_SOURCE_PREFIX, MAX_LOG_PAGE_SIZE,
 };
 use anyhow::{bail, Context};
+use futures::{stream, StreamExt};
 use itertools::Itertools;
-use libp2p::PeerId;
+use libp2p::identity::ed25519;
+use libp2p::{Multiaddr, PeerId};
 use log::{debug, info, warn};
 use multihash::Hasher;
-use std::fs::File;
-use std::io::{BufReader, Read};
+use pyrsia_blockchain_network::structures::block::Block;
+use pyrsia_blockchain_network::structures::header::Address;
+use rand::seq::IteratorRandom;
+use std::collections::HashSet;
+use std::io::{self, BufReader, ErrorKind, Read};
 use std::path::Path;
-use std::str;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use std::{env, str};
+use tokio::sync::broadcast;
+use uuid::Uuid;
 
 /// The artifact service is the component that handles everything related to
 /// pyrsia artifacts. It allows artifacts to be retrieved and added to the
@@ -43,6 +82,27 @@ pub struct ArtifactService {
     build_event_client: BuildEventClient,
     pub transparency_log_service: TransparencyLogService,
     pub p2p_client: Client,
+    artifact_request_policy: ArtifactRequestPolicy,
+    metadata_cache: MetadataCache,
+    subscription_service: Option<SubscriptionService>,
+    alert_service: Option<AlertService>,
+    provide_progress: Arc<Mutex<ProvideProgress>>,
+    provide_schedule: ProvideSchedule,
+    artifact_popularity: ArtifactPopularity,
+    access_stats: AccessStats,
+    fetch_retry_policy: FetchRetryPolicy,
+    lifecycle_hooks: LifecycleHooks,
+    tag_immutability_policy: TagImmutabilityPolicy,
+    transfer_progress: TransferProgressTracker,
+    pinned_packages: Vec<(PackageType, String)>,
+    sole_copy_policy: SoleCopyPolicy,
+    scrub_stats: Arc<Mutex<ScrubStats>>,
+    transfer_budget: TransferBudget,
+    usage_accounting: UsageAccounting,
+    availability_monitor: AvailabilityMonitor,
+    monitor_mode: bool,
+    seed_sync_progress: Arc<Mutex<SeedSyncProgress>>,
+    snapshot_keypair: Option<ed25519::Keypair>,
 }
 
 impl ArtifactService {
@@ -61,19 +121,175 @@ impl ArtifactService {
                 blockchain_event_client,
             )?,
             p2p_client,
+            artifact_request_policy: Default::default(),
+            metadata_cache: Default::default(),
+            subscription_service: None,
+            alert_service: None,
+            provide_progress: Default::default(),
+            provide_schedule: Default::default(),
+            artifact_popularity: Default::default(),
+            access_stats: Default::default(),
+            fetch_retry_policy: Default::default(),
+            lifecycle_hooks: Default::default(),
+            tag_immutability_policy: Default::default(),
+            transfer_progress: Default::default(),
+            pinned_packages: vec![],
+            sole_copy_policy: Default::default(),
+            scrub_stats: Default::default(),
+            transfer_budget: Default::default(),
+            usage_accounting: Default::default(),
+            availability_monitor: Default::default(),
+            monitor_mode: false,
+            seed_sync_progress: Default::default(),
+            snapshot_keypair: None,
         })
     }
 
+    /// Set the storage that keeps the artifacts of this node, instead of the
+    /// artifact path.
+    pub fn with_artifact_storage(mut self, artifact_storage: ArtifactStorage) -> Self {
+        self.artifact_storage = artifact_storage;
+        self
+    }
+
+    /// Set the policy that decides which peers are allowed to request the
+    /// artifacts that this node provides.
+    pub fn with_artifact_request_policy(mut self, policy: ArtifactRequestPolicy) -> Self {
+        self.artifact_request_policy = policy;
+        self
+    }
+
+    /// Set the schedule that controls the pace at which local artifacts are
+    /// provided on the p2p network.
+    pub fn with_provide_schedule(mut self, provide_schedule: ProvideSchedule) -> Self {
+        self.provide_schedule = provide_schedule;
+        self
+    }
+
+    /// Set the budget that limits the open files and in-flight bytes of the
+    /// artifacts that are served at the same time.
+    pub fn with_transfer_budget(mut self, transfer_budget: TransferBudget) -> Self {
+        self.transfer_budget = transfer_budget;
+        self
+    }
+
+    /// Set the subscription service that is notified of newly arrived
+    /// transparency logs.
+    pub fn with_subscription_service(mut self, subscription_service: SubscriptionService) -> Self {
+        self.subscription_service = Some(subscription_service);
+        self
+    }
+
+    /// Set the alert service that inspects the transparency logs arriving on
+    /// the ledger and the integrity failures of artifacts for anomalies.
+    pub fn with_alert_service(mut self, alert_service: AlertService) -> Self {
+        self.alert_service = Some(alert_service);
+        self
+    }
+
+    /// Set the accounting that enforces the build minutes quota of the
+    /// namespaces of the packages that builds are requested for.
+    pub fn with_usage_accounting(mut self, usage_accounting: UsageAccounting) -> Self {
+        self.usage_accounting = usage_accounting;
+        self
+    }
+
+    /// The accounting of the usage of the node per API key and namespace.
+    pub fn usage_accounting(&self) -> UsageAccounting {
+        self.usage_accounting.clone()
+    }
+
+    /// Set the access statistics that record the pulls of packages. Without
+    /// them, the statistics are only kept in memory.
+    pub fn with_access_stats(mut self, access_stats: AccessStats) -> Self {
+        self.access_stats = access_stats;
+        self
+    }
+
+    /// Set the policy that controls how fetching an artifact from the
+    /// providers on the p2p network is retried.
+    pub fn with_fetch_retry_policy(mut self, fetch_retry_policy: FetchRetryPolicy) -> Self {
+        self.fetch_retry_policy = fetch_retry_policy;
+        self
+    }
+
+    /// Set the hooks that external systems run at points in the lifecycle of
+    /// artifacts.
+    pub fn with_lifecycle_hooks(mut self, lifecycle_hooks: LifecycleHooks) -> Self {
+        self.lifecycle_hooks = lifecycle_hooks;
+        self
+    }
+
+    /// Set the policy that decides which docker tags can not be moved to
+    /// another manifest once they are published.
+    pub fn with_tag_immutability_policy(mut self, policy: TagImmutabilityPolicy) -> Self {
+        self.tag_immutability_policy = policy;
+        self
+    }
+
+    /// Set the packages whose artifacts are never evicted when the artifact
+    /// storage exceeds its maximum size.
+    pub fn with_pinned_packages(mut self, pinned_packages: Vec<(PackageType, String)>) -> Self {
+        self.pinned_packages = pinned_packages;
+        self
+    }
+
+    /// Set what happens before the last copy of an artifact on the p2p
+    /// network is evicted.
+    pub fn with_sole_copy_policy(mut self, sole_copy_policy: SoleCopyPolicy) -> Self {
+        self.sole_copy_policy = sole_copy_policy;
+        self
+    }
+
+    /// Run in monitor mode: the node only follows the blockchain and the
+    /// transparency log, audits every block that arrives and does not build.
+    pub fn with_monitor_mode(mut self, monitor_mode: bool) -> Self {
+        self.monitor_mode = monitor_mode;
+        self
+    }
+
+    /// Set the keypair that signs the snapshots of the transparency logs that
+    /// this node serves to nodes that fast sync from it.
+    pub fn with_snapshot_keypair(mut self, keypair: ed25519::Keypair) -> Self {
+        self.snapshot_keypair = Some(keypair);
+        self
+    }
+
+    /// The artifacts that are being downloaded from peers or written to the
+    /// local storage.
+    pub fn active_transfers(&self) -> Vec<TransferProgress> {
+        self.transfer_progress.active_transfers()
+    }
+
+    /// Receive the progress of the artifact transfers as they happen.
+    pub fn subscribe_transfer_progress(&self) -> broadcast::Receiver<TransferProgress> {
+        self.transfer_progress.subscribe()
+    }
+
     pub async fn request_build(
         &self,
         package_type: PackageType,
         package_specific_id: String,
     ) -> Result<String, BuildError> {
+        let package_specific_id = normalize_package_specific_id(package_type, &package_specific_id);
         debug!(
             "Request build of {:?} {:?}",
             package_type, package_specific_id
         );
 
+        if self.monitor_mode {
+            return Err(BuildError::InitializationFailed(String::from(
+                "This node runs in monitor mode and does not build",
+            )));
+        }
+
+        self.usage_accounting
+            .check_build_quota(&[UsageSubject::namespace_of_package(
+                package_type,
+                &package_specific_id,
+            )])
+            .map_err(BuildError::QuotaExceeded)?;
+
         let local_peer_id = self.p2p_client.local_peer_id;
         debug!("Got local node with peer_id: {:?}", local_peer_id.clone());
 
@@ -89,8 +305,21 @@ impl ArtifactService {
             )));
         }
 
+        // only authorized nodes that advertise to build the package type
+        // and are not draining for maintenance are asked, this node is
+        // preferred
+        let peer_capabilities = &self.p2p_client.peer_capabilities;
+        let peer_maintenance = &self.p2p_client.peer_maintenance;
         let peer_id = match nodes
             .iter()
+            .filter(|auth_peer_id| !peer_maintenance.is_draining(auth_peer_id))
+            .filter(|auth_peer_id| {
+                if local_peer_id.eq(*auth_peer_id) {
+                    peer_capabilities.own_capabilities().builds(package_type)
+                } else {
+                    peer_capabilities.builds(auth_peer_id, package_type)
+                }
+            })
             .find_or_last(|&auth_peer_id| local_peer_id.eq(auth_peer_id))
         {
             Some(auth_peer_id) => {
@@ -100,18 +329,41 @@ impl ArtifactService {
                 );
                 auth_peer_id
             }
-            None => panic!("Error unexpected looking for authorized nodes"),
+            None => {
+                warn!("No authorized nodes build {} packages", package_type);
+                return Err(BuildError::InitializationFailed(format!(
+                    "No authorized nodes build {} packages",
+                    package_type
+                )));
+            }
         };
 
-        // prevent duplicated builds
-        self.transparency_log_service
-            .verify_package_can_be_added_to_transparency_logs(&package_type, &package_specific_id)
-            .map_err(|t| BuildError::ArtifactAlreadyExists(t.to_string()))?;
+        // prevent duplicated builds, also when authorized nodes are asked to
+        // build the same package at the same time: only the node that
+        // claimed the build first builds it
+        let claim = self
+            .transparency_log_service
+            .claim_build(&package_type, &package_specific_id, *peer_id)
+            .await
+            .map_err(|e| match e {
+                TransparencyLogError::ArtifactAlreadyExists { .. } => {
+                    BuildError::ArtifactAlreadyExists(e.to_string())
+                }
+                _ => BuildError::InitializationFailed(e.to_string()),
+            })?;
+        let peer_id = nodes
+            .iter()
+            .find(|auth_peer_id| auth_peer_id.to_string() == claim.node_id)
+            .unwrap_or(peer_id);
 
         if local_peer_id.eq(peer_id) {
             debug!("Start local build in authorized node");
             self.build_event_client
-                .start_build(package_type, package_specific_id)
+                .start_build(
+                    package_type,
+                    package_specific_id,
+                    Some(local_peer_id.to_string()),
+                )
                 .await
         } else {
             debug!("Request build in authorized node from p2p network");
@@ -123,6 +375,193 @@ impl ArtifactService {
         }
     }
 
+    /// Release the claim of this node on the build of a package whose build
+    /// failed, so that the build can be requested again right away.
+    pub async fn release_build_claim(
+        &self,
+        package_type: PackageType,
+        package_specific_id: &str,
+    ) -> Result<(), TransparencyLogError> {
+        self.transparency_log_service
+            .release_build_claim(
+                &package_type,
+                package_specific_id,
+                self.p2p_client.local_peer_id,
+            )
+            .await
+    }
+
+    /// Mark a package as deprecated, optionally naming the package that
+    /// replaces it. Only authorized nodes can deprecate packages.
+    pub async fn deprecate_package(
+        &self,
+        package_type: PackageType,
+        package_specific_id: &str,
+        successor: Option<String>,
+    ) -> Result<TransparencyLog, TransparencyLogError> {
+        let local_peer_id = self.p2p_client.local_peer_id;
+        if !self
+            .transparency_log_service
+            .get_authorized_nodes()?
+            .contains(&local_peer_id)
+        {
+            return Err(TransparencyLogError::NodeDoesNotExistOrRemoved {
+                node_id: local_peer_id.to_string(),
+            });
+        }
+
+        let deprecation = self
+            .transparency_log_service
+            .deprecate_package(&package_type, package_specific_id, successor, local_peer_id)
+            .await?;
+
+        info!(
+            "Package {} was deprecated{}",
+            package_specific_id,
+            deprecation
+                .successor
+                .as_ref()
+                .map(|successor| format!(" in favor of {}", successor))
+                .unwrap_or_default()
+        );
+
+        Ok(deprecation)
+    }
+
+    /// Get the warning that is shown to clients when the package that contains
+    /// the specified artifact is deprecated.
+    pub fn get_deprecation_warning(
+        &mut self,
+        package_type: PackageType,
+        package_specific_artifact_id: &str,
+    ) -> Option<String> {
+        let package_specific_id = self
+            .transparency_log_service
+            .get_artifact(&package_type, package_specific_artifact_id)
+            .ok()?
+            .package_specific_id;
+
+        match self
+            .transparency_log_service
+            .find_deprecation(&package_type, &package_specific_id)
+        {
+            Ok(deprecation) => {
+                deprecation.and_then(|deprecation| deprecation.deprecation_warning())
+            }
+            Err(error) => {
+                warn!(
+                    "Failed to look up deprecation of {}: {:?}",
+                    package_specific_id, error
+                );
+                None
+            }
+        }
+    }
+
+    /// Import the artifacts of a package that already exist in another
+    /// repository, like a local Docker daemon or Maven repository. The
+    /// artifacts are published in the same way as the result of a build, but
+    /// their transparency logs record that they were imported from `source`
+    /// instead of built from source. Only authorized nodes can import packages.
+    pub async fn import_artifacts(
+        &mut self,
+        package_type: PackageType,
+        package_specific_id: &str,
+        source: &str,
+        artifacts: Vec<(String, Vec<u8>)>,
+    ) -> Result<(), BuildError> {
+        let local_peer_id = self.p2p_client.local_peer_id;
+        if !self
+            .transparency_log_service
+            .get_authorized_nodes()
+            .map_err(|e| BuildError::InitializationFailed(e.to_string()))?
+            .contains(&local_peer_id)
+        {
+            return Err(BuildError::InitializationFailed(format!(
+                "Node {} is not authorized to import packages",
+                local_peer_id
+            )));
+        }
+
+        if artifacts.is_empty() {
+            return Err(BuildError::InitializationFailed(format!(
+                "No artifacts to import for package {}",
+                package_specific_id
+            )));
+        }
+
+        self.transparency_log_service
+            .verify_package_can_be_added_to_transparency_logs(&package_type, package_specific_id)
+            .map_err(|t| BuildError::ArtifactAlreadyExists(t.to_string()))?;
+
+        let import_id = format!("import-{}", Uuid::new_v4());
+        let import_path = env::temp_dir().join(&import_id);
+        let result = self
+            .publish_imported_artifacts(
+                &import_id,
+                &import_path,
+                package_type,
+                package_specific_id,
+                source,
+                artifacts,
+            )
+            .await
+            .map_err(|e| match e.downcast_ref::<TagPolicyError>() {
+                Some(TagPolicyError::ImmutableTag { .. }) => {
+                    BuildError::ImmutableTag(import_id.clone(), e.to_string())
+                }
+                _ => BuildError::Failure(import_id.clone(), e.to_string()),
+            });
+
+        if let Err(error) = tokio::fs::remove_dir_all(&import_path).await {
+            warn!(
+                "Failed to clean up imported artifacts in {:?}: {:?}",
+                import_path, error
+            );
+        }
+
+        result
+    }
+
+    async fn publish_imported_artifacts(
+        &mut self,
+        import_id: &str,
+        import_path: &Path,
+        package_type: PackageType,
+        package_specific_id: &str,
+        source: &str,
+        artifacts: Vec<(String, Vec<u8>)>,
+    ) -> Result<(), anyhow::Error> {
+        tokio::fs::create_dir_all(import_path).await?;
+
+        let mut build_result_artifacts = Vec::with_capacity(artifacts.len());
+        for (index, (package_specific_artifact_id, content)) in artifacts.into_iter().enumerate() {
+            let mut sha256 = multihash::Sha2_256::default();
+            sha256.update(&content);
+            let artifact_location = import_path.join(index.to_string());
+            tokio::fs::write(&artifact_location, content).await?;
+            build_result_artifacts.push(BuildResultArtifact {
+                artifact_specific_id: package_specific_artifact_id,
+                artifact_location,
+                artifact_hash: hex::encode(sha256.finalize()),
+            });
+        }
+
+        let build_result = BuildResult {
+            package_type,
+            package_specific_id: package_specific_id.to_owned(),
+            artifacts: build_result_artifacts,
+            failed_artifacts: vec![],
+            source: Some(BuildSource {
+                url: format!("{}{}", IMPORTED_SOURCE_PREFIX, source),
+                commit: "".to_owned(),
+            }),
+            pinning_snapshot: None,
+        };
+
+        self.handle_build_result(import_id, build_result).await
+    }
+
     pub async fn handle_build_result(
         &mut self,
         build_id: &str,
@@ -135,50 +574,173 @@ impl ArtifactService {
             build_id, build_result.package_type, package_specific_id
         );
 
-        let mut payloads: Vec<String> = Vec::new();
-        for artifact in build_result.artifacts.iter() {
-            let add_artifact_request = AddArtifactRequest {
+        let add_artifact_requests = build_result
+            .artifacts
+            .iter()
+            .map(|artifact| AddArtifactRequest {
                 package_type: build_result.package_type,
                 package_specific_id: package_specific_id.to_owned(),
                 num_artifacts: build_result.artifacts.len() as u32,
                 package_specific_artifact_id: artifact.artifact_specific_id.clone(),
                 artifact_hash: artifact.artifact_hash.clone(),
-            };
+            })
+            .collect();
 
-            info!(
-                "Adding artifact to transparency log: {:?}",
-                add_artifact_request
-            );
+        if build_result.package_type == PackageType::Docker {
+            self.verify_tag_immutability(&build_result)?;
+        }
 
-            let add_artifact_transparency_tuple = self
-                .transparency_log_service
-                .add_artifact(add_artifact_request)
+        // The artifacts of a build are published as a single unit: all
+        // artifacts are stored and all transparency logs are committed before
+        // they are broadcast in one batch. Only then are the artifacts
+        // provided on the p2p network. Any failure along the way undoes the
+        // steps that were already taken.
+        let (transparency_logs, payload) = self.transparency_log_service.stage_artifacts(
+            add_artifact_requests,
+            build_result.source.as_ref(),
+            build_result.pinning_snapshot.as_deref(),
+        )?;
+
+        for (artifact, transparency_log) in build_result.artifacts.iter().zip(&transparency_logs) {
+            self.lifecycle_hooks
+                .run(&ArtifactHookEvent::new(
+                    HookPoint::PrePublication,
+                    transparency_log,
+                    Some(&artifact.artifact_location),
+                ))
                 .await?;
+        }
 
-            let add_artifact_transparency_log = add_artifact_transparency_tuple.0;
-            payloads.push(add_artifact_transparency_tuple.1);
-            info!(
-                "Transparency Log for build with ID {} successfully created.",
-                build_id
-            );
+        let mut stored_artifact_ids: Vec<&str> = Vec::new();
+        for (artifact, transparency_log) in build_result.artifacts.iter().zip(&transparency_logs) {
+            // an artifact with the same hash is already stored under its artifact_id
+            if self
+                .artifact_storage
+                .contains_artifact(&transparency_log.artifact_id)
+                .await
+            {
+                continue;
+            }
+            if let Err(error) = self
+                .put_artifact_from_build_result(
+                    &artifact.artifact_location,
+                    &transparency_log.artifact_id,
+                )
+                .await
+            {
+                self.discard_stored_artifacts(&stored_artifact_ids).await;
+                return Err(error);
+            }
+            stored_artifact_ids.push(&transparency_log.artifact_id);
+        }
 
-            self.put_artifact_from_build_result(
-                &artifact.artifact_location,
-                &add_artifact_transparency_log.artifact_id,
-            )
-            .await?;
+        if let Err(error) = self
+            .transparency_log_service
+            .commit_transparency_logs(&transparency_logs)
+        {
+            self.discard_stored_artifacts(&stored_artifact_ids).await;
+            return Err(error.into());
+        }
+
+        info!(
+            "Transparency Logs for build with ID {} successfully created.",
+            build_id
+        );
+
+        if let Err(error) = self
+            .transparency_log_service
+            .broadcast_artifacts(vec![payload])
+            .await
+        {
+            if let Err(rollback_error) = self
+                .transparency_log_service
+                .rollback_transparency_logs(&transparency_logs)
+            {
+                warn!(
+                    "Failed to roll back transparency logs for build with ID {}: {:?}",
+                    build_id, rollback_error
+                );
+            }
+            self.discard_stored_artifacts(&stored_artifact_ids).await;
+            return Err(error.into());
+        }
 
+        for transparency_log in transparency_logs.iter() {
+            self.notify_subscribers(transparency_log);
             self.p2p_client
-                .provide(&add_artifact_transparency_log.artifact_id)
+                .provide(&transparency_log.artifact_id)
+                .await?;
+            self.lifecycle_hooks
+                .run(&ArtifactHookEvent::new(
+                    HookPoint::PostPublication,
+                    transparency_log,
+                    None,
+                ))
                 .await?;
         }
 
-        self.transparency_log_service
-            .broadcast_artifacts(payloads)
-            .await?;
         Ok(())
     }
 
+    // Rejects the publication of a build that moves an immutable docker tag
+    // to another manifest. The rejection is recorded as an alert.
+    fn verify_tag_immutability(&self, build_result: &BuildResult) -> anyhow::Result<()> {
+        for artifact in &build_result.artifacts {
+            let namespace = match self
+                .tag_immutability_policy
+                .immutable_namespace(&artifact.artifact_specific_id)
+            {
+                Some(namespace) => namespace,
+                None => continue,
+            };
+            let published_hash = self
+                .transparency_log_service
+                .find_artifact_hashes(&PackageType::Docker, &artifact.artifact_specific_id)?
+                .into_iter()
+                .find(|published_hash| *published_hash != artifact.artifact_hash);
+            if let Some(published_hash) = published_hash {
+                if let Some(alert_service) = &self.alert_service {
+                    alert_service.record_tag_immutability_violation(
+                        &artifact.artifact_specific_id,
+                        &published_hash,
+                        &artifact.artifact_hash,
+                    );
+                }
+                return Err(TagPolicyError::ImmutableTag {
+                    package_specific_artifact_id: artifact.artifact_specific_id.clone(),
+                    namespace: namespace.to_owned(),
+                    published_hash,
+                    artifact_hash: artifact.artifact_hash.clone(),
+                }
+                .into());
+            }
+        }
+        Ok(())
+    }
+
+    fn notify_subscribers(&self, transparency_log: &TransparencyLog) {
+        if let Some(subscription_service) = &self.subscription_service {
+            if let Err(error) = subscription_service.notify(transparency_log) {
+                warn!(
+                    "Failed to notify subscribers of transparency log {}: {:?}",
+                    transparency_log.id, error
+                );
+            }
+        }
+    }
+
+    // Remove artifacts that were stored for a publication that did not complete.
+    async fn discard_stored_artifacts(&self, artifact_ids: &[&str]) {
+        for artifact_id in artifact_ids {
+            if let Err(error) = self.artifact_storage.remove_artifact(artifact_id).await {
+                warn!(
+                    "Failed to remove unpublished artifact {}: {:?}",
+                    artifact_id, error
+                );
+            }
+        }
+    }
+
     pub async fn get_build_status(&mut self, build_id: &str) -> Result<String, BuildError> {
         let local_peer_id = self.p2p_client.local_peer_id;
         debug!("Got local node with peer_id: {:?}", local_peer_id.clone());
@@ -215,101 +777,877 @@ impl ArtifactService {
         }
     }
 
-    pub async fn handle_block_added(
-        &mut self,
-        payloads: Vec<Vec<u8>>,
-    ) -> Result<(), anyhow::Error> {
-        if payloads.len() == 1 {
-            let transparency_log: TransparencyLog = serde_json::from_slice(&payloads[0])?;
-            self.transparency_log_service
-                .write_if_not_exists(&transparency_log)
-                .await?;
+    /// Get the builds that were started by this node and match `query`.
+    pub async fn get_build_history(
+        &self,
+        query: BuildHistoryQuery,
+    ) -> Result<Vec<BuildRecord>, BuildError> {
+        self.build_event_client.get_build_history(query).await
+    }
+
+    /// Rerun a failed build of this node with the same parameters. The new
+    /// build is linked to the failed build in the build history.
+    pub async fn rerun_build(&self, build_id: &str) -> Result<String, BuildError> {
+        self.build_event_client
+            .rerun_build(build_id, Some(self.p2p_client.local_peer_id.to_string()))
+            .await
+    }
+
+    /// Rebuild a package of this node with the dependencies of a pinning
+    /// snapshot, to check that its published artifacts can be reproduced
+    /// exactly. Returns the ID of the new build.
+    pub async fn reproduce_build(&self, pinning_snapshot_id: &str) -> Result<String, BuildError> {
+        self.build_event_client
+            .reproduce_build(
+                pinning_snapshot_id,
+                Some(self.p2p_client.local_peer_id.to_string()),
+            )
+            .await
+    }
+
+    /// Retry publishing the artifacts of successful builds that could not be
+    /// added to the transparency log. Returns the IDs of the replayed builds.
+    pub async fn replay_failed_builds(&self) -> Result<Vec<String>, BuildError> {
+        self.build_event_client.replay_failed_builds().await
+    }
+
+    /// Apply the payloads of a block that was added to the blockchain. The
+    /// transparency logs of all payloads are applied as a whole: when a
+    /// payload can not be parsed nothing is applied, and when a transparency
+    /// log can not be written the ones of the block that were written
+    /// before it are rolled back. The block is recorded as the one that
+    /// published the transparency logs.
+    pub async fn handle_block_added(&mut self, block: &Block) -> Result<(), anyhow::Error> {
+        let mut transparency_logs = vec![];
+        for payload in &block.fetch_payload() {
+            transparency_logs.extend(TransparencyLogService::parse_payload(payload)?);
+        }
+
+        let written_logs = self
+            .transparency_log_service
+            .write_all_if_not_exists(&transparency_logs)
+            .await?;
+        self.transparency_log_service.record_block(block)?;
+        for transparency_log in written_logs {
+            self.notify_subscribers(&transparency_log);
+            if let Some(alert_service) = &self.alert_service {
+                alert_service.inspect_transparency_log(&transparency_log);
+            }
+            if matches!(
+                transparency_log.operation,
+                Operation::RemoveArtifact | Operation::RevokeArtifact
+            ) {
+                self.remove_tombstoned_artifact(&transparency_log).await?;
+            }
         }
 
         Ok(())
     }
 
+    /// Roll back the transparency logs of the `rolled_back` blocks of the
+    /// losing branch of a fork of the blockchain, except the ones that the
+    /// `applied` blocks of the winning branch contain as well. Returns the
+    /// number of transparency logs that were rolled back.
+    pub fn rollback_blocks(
+        &mut self,
+        rolled_back: &[Block],
+        applied: &[Block],
+    ) -> Result<usize, anyhow::Error> {
+        let mut applied_ids = HashSet::new();
+        for payload in applied.iter().flat_map(Block::fetch_payload) {
+            for transparency_log in TransparencyLogService::parse_payload(&payload)? {
+                applied_ids.insert(transparency_log.id);
+            }
+        }
+
+        let mut transparency_logs = vec![];
+        for payload in rolled_back.iter().flat_map(Block::fetch_payload) {
+            transparency_logs.extend(
+                TransparencyLogService::parse_payload(&payload)?
+                    .into_iter()
+                    .filter(|transparency_log| !applied_ids.contains(&transparency_log.id)),
+            );
+        }
+        self.transparency_log_service
+            .rollback_transparency_logs(&transparency_logs)?;
+        Ok(transparency_logs.len())
+    }
+
+    /// Audit a block that arrived on the blockchain before its payloads are
+    /// handled, when in monitor mode. Every inconsistency raises an alert.
+    /// Returns the inconsistencies that were found.
+    pub fn audit_block(&self, block: &Block) -> anyhow::Result<Vec<Inconsistency>> {
+        if !self.monitor_mode {
+            return Ok(vec![]);
+        }
+
+        let inconsistencies = audit::audit_block(&self.transparency_log_service, block)?;
+        for inconsistency in &inconsistencies {
+            match &self.alert_service {
+                Some(alert_service) => {
+                    alert_service.record_inconsistency(inconsistency);
+                }
+                None => warn!("Audit of block failed: {}", inconsistency),
+            }
+        }
+        Ok(inconsistencies)
+    }
+
     async fn put_artifact_from_build_result(
         &self,
         artifact_location: &Path,
         artifact_id: &str,
     ) -> Result<(), anyhow::Error> {
-        let artifact_file = File::open(artifact_location)?;
-        let mut artifact_reader = BufReader::new(artifact_file);
-        self.put_artifact(artifact_id, &mut artifact_reader)
+        let artifact_file = tokio::fs::File::open(artifact_location).await?;
+        self.put_artifact(artifact_id, BufReader::new(artifact_file.into_std().await))
+            .await
     }
 
     /// Given artifact_id & reader, push artifact to artifact_storage
-    fn put_artifact(&self, artifact_id: &str, reader: &mut impl Read) -> Result<(), anyhow::Error> {
+    async fn put_artifact(
+        &self,
+        artifact_id: &str,
+        reader: impl Read + Send + 'static,
+    ) -> Result<(), anyhow::Error> {
         info!("put_artifact with id: {}", artifact_id);
+        let transfer = self
+            .transfer_progress
+            .start(artifact_id, TransferDirection::Store, None);
         self.artifact_storage
-            .push_artifact(reader, artifact_id)
+            .push_artifact(ProgressReader::new(reader, transfer), artifact_id)
+            .await
             .context("Error from put_artifact")
     }
 
     /// Retrieve the artifact data for the specified package. If the artifact
     /// is not available locally, the service will try to fetch the artifact
-    /// from the p2p network.
+    /// from the p2p network. Metadata artifacts are served from memory when
+    /// possible and are provided to the network as soon as they are fetched.
     pub async fn get_artifact(
         &mut self,
         package_type: PackageType,
         package_specific_artifact_id: &str,
     ) -> anyhow::Result<Vec<u8>> {
-        let transparency_log = self
-            .transparency_log_service
-            .get_artifact(&package_type, package_specific_artifact_id)?;
+        let transparency_log =
+            self.find_artifact_log(package_type, package_specific_artifact_id)?;
+        self.lifecycle_hooks
+            .run(&ArtifactHookEvent::new(
+                HookPoint::PreServe,
+                &transparency_log,
+                None,
+            ))
+            .await?;
 
-        let artifact = match self
+        let artifact_kind = ArtifactKind::of(package_type, package_specific_artifact_id);
+        if artifact_kind == ArtifactKind::Metadata {
+            if let Some(artifact) = self.metadata_cache.get(&transparency_log.artifact_id) {
+                return Ok(artifact);
+            }
+        }
+
+        let (artifact, fetched_from_peer) = match self
             .get_artifact_locally(&transparency_log.artifact_id)
             .await
         {
-            Ok(artifact) => Ok(artifact),
+            Ok(artifact) => (artifact, None),
+            Err(error) if error.is::<BudgetExceeded>() => return Err(error),
             Err(_) => {
-                self.get_artifact_from_peers(&transparency_log.artifact_id)
-                    .await
+                let (artifact, peer_id) = self
+                    .get_artifact_from_peers(&transparency_log.artifact_id, artifact_kind)
+                    .await?;
+                (artifact, Some(peer_id))
             }
-        }?;
+        };
+
+        if let Err(error) = self.verify_artifact(&transparency_log, &artifact).await {
+            self.discard_corrupt_artifact(&transparency_log, fetched_from_peer)
+                .await;
+            return Err(error.into());
+        }
+
+        self.access_stats
+            .record_pull(package_type, &transparency_log.package_specific_id);
 
-        self.verify_artifact(&transparency_log, &artifact).await?;
+        if artifact_kind == ArtifactKind::Metadata {
+            self.metadata_cache
+                .insert(&transparency_log.artifact_id, &artifact);
+            if fetched_from_peer.is_some() {
+                self.p2p_client
+                    .provide(&transparency_log.artifact_id)
+                    .await
+                    .unwrap_or_else(|e| {
+                        warn!(
+                            "Failed to provide metadata artifact {}: {:?}",
+                            transparency_log.artifact_id, e
+                        )
+                    });
+            }
+        }
 
         Ok(artifact)
     }
 
-    /// Retrieve the artifact data for the specified package. If the artifact
-    /// is not found, the service start a request to build it on an authorized
-    /// node.
-    pub async fn get_artifact_or_build(
+    /// Retrieve the artifact data for the specified package as a stream.
+    /// Blobs are verified and then streamed from the local storage in chunks,
+    /// after fetching them from the p2p network if needed, so the service
+    /// never holds a blob in memory as a whole. Metadata artifacts are small
+    /// and are retrieved like with [`get_artifact`](Self::get_artifact).
+    ///
+    /// When a `range` is specified, only that part of the artifact is
+    /// streamed. A range that does not overlap with the artifact fails with
+    /// [`RangeNotSatisfiable`]. A blob counts against the transfer budget
+    /// until the stream is dropped, and fails with [`BudgetExceeded`] when
+    /// the budget is exhausted.
+    pub async fn get_artifact_stream(
         &mut self,
         package_type: PackageType,
-        package_specific_id: &str,
         package_specific_artifact_id: &str,
-    ) -> anyhow::Result<Vec<u8>> {
-        self.get_artifact(package_type, package_specific_artifact_id).await.map_err(|e| {
-                warn!("Error looking for artifact: {:?}. A new build will be started. Try again later", e);
-                let new_artifact_service = self.clone();
-                let new_package_specific_id = package_specific_id.to_string();
-                tokio::spawn(async move {
-                    debug!("Spawning a build...");
-                    let build_result = new_artifact_service.clone().request_build(package_type, new_package_specific_id).await;
-                    debug!("Build result {:?}", build_result);
-                });
-                // in any case, return the error
-                e
-            })
-    }
+        range: Option<ByteRange>,
+    ) -> anyhow::Result<ArtifactStream> {
+        let artifact_kind = ArtifactKind::of(package_type, package_specific_artifact_id);
+        if artifact_kind == ArtifactKind::Metadata {
+            let artifact = self
+                .get_artifact(package_type, package_specific_artifact_id)
+                .await?;
+            return match range {
+                None => Ok(ArtifactStream::from_bytes(artifact)),
+                Some(range) => {
+                    let len = artifact.len() as u64;
+                    let content_range = range
+                        .content_range(len)
+                        .ok_or(RangeNotSatisfiable { len })?;
+                    Ok(ArtifactStream::from_bytes_range(artifact, content_range)?)
+                }
+            };
+        }
 
-    /// Retrieve the artifact data specified by `artifact_id` from the local storage.
+        let transparency_log =
+            self.find_artifact_log(package_type, package_specific_artifact_id)?;
+        self.lifecycle_hooks
+            .run(&ArtifactHookEvent::new(
+                HookPoint::PreServe,
+                &transparency_log,
+                None,
+            ))
+            .await?;
+        let artifact_id = &transparency_log.artifact_id;
+
+        let fetched_from_peer = if self.artifact_storage.contains_artifact(artifact_id).await {
+            None
+        } else {
+            Some(
+                self.fetch_artifact_from_peers(artifact_id, artifact_kind)
+                    .await?,
+            )
+        };
+
+        let artifact_size = self.artifact_storage.artifact_size(artifact_id).await?;
+        let transfer_permit = self.transfer_budget.try_admit(artifact_size)?;
+        let calculated_hash = match self
+            .artifact_storage
+            .mapped_artifact_hash(artifact_id)
+            .await
+        {
+            Some(mapped_artifact_hash) => mapped_artifact_hash,
+            None => self.artifact_storage.hash_artifact(artifact_id).await?,
+        };
+        if transparency_log.artifact_hash != calculated_hash {
+            self.discard_corrupt_artifact(&transparency_log, fetched_from_peer)
+                .await;
+            return Err(TransparencyLogError::InvalidHash {
+                id: transparency_log.package_specific_artifact_id.clone(),
+                invalid_hash: calculated_hash,
+                actual_hash: transparency_log.artifact_hash.clone(),
+            }
+            .into());
+        }
+
+        let artifact_stream = match range {
+            None => ArtifactStream::from_reader(
+                self.artifact_storage.pull_artifact(artifact_id).await?,
+                artifact_size,
+            ),
+            Some(range) => {
+                let content_range = range
+                    .content_range(artifact_size)
+                    .ok_or(RangeNotSatisfiable { len: artifact_size })?;
+                ArtifactStream::from_reader_range(
+                    self.artifact_storage
+                        .pull_artifact_range(
+                            artifact_id,
+                            content_range.start,
+                            content_range.length(),
+                        )
+                        .await?,
+                    content_range,
+                )
+            }
+        }
+        .with_transfer_permit(transfer_permit);
+        self.artifact_popularity.record_request(artifact_id);
+        self.access_stats
+            .record_pull(package_type, &transparency_log.package_specific_id);
+        Ok(artifact_stream)
+    }
+
+    /// Retrieve the artifact data for the specified package. If the artifact
+    /// is not found, the service requests a build of the package on an
+    /// authorized node and returns the id of that build.
+    pub async fn get_artifact_or_build(
+        &mut self,
+        package_type: PackageType,
+        package_specific_id: &str,
+        package_specific_artifact_id: &str,
+    ) -> anyhow::Result<ArtifactOrBuild<Vec<u8>>> {
+        let result = self
+            .get_artifact(package_type, package_specific_artifact_id)
+            .await;
+        self.request_build_on_error(result, package_type, package_specific_id)
+            .await
+    }
+
+    /// Retrieve the artifact data for the specified package as a stream. If
+    /// the artifact is not found, the service requests a build of the
+    /// package on an authorized node and returns the id of that build.
+    pub async fn get_artifact_stream_or_build(
+        &mut self,
+        package_type: PackageType,
+        package_specific_id: &str,
+        package_specific_artifact_id: &str,
+        range: Option<ByteRange>,
+    ) -> anyhow::Result<ArtifactOrBuild<ArtifactStream>> {
+        let result = self
+            .get_artifact_stream(package_type, package_specific_artifact_id, range)
+            .await;
+        self.request_build_on_error(result, package_type, package_specific_id)
+            .await
+    }
+
+    // The transparency log of an artifact by the canonical form of its id,
+    // or by the id as it is for artifacts that were published before their
+    // ids were normalized.
+    fn find_artifact_log(
+        &mut self,
+        package_type: PackageType,
+        package_specific_artifact_id: &str,
+    ) -> Result<TransparencyLog, TransparencyLogError> {
+        let canonical_id =
+            normalize_package_specific_artifact_id(package_type, package_specific_artifact_id);
+        match self
+            .transparency_log_service
+            .get_artifact(&package_type, &canonical_id)
+        {
+            Err(_) if canonical_id != package_specific_artifact_id => self
+                .transparency_log_service
+                .get_artifact(&package_type, package_specific_artifact_id),
+            result => result,
+        }
+    }
+
+    // The original error is returned when no build could be requested.
+    async fn request_build_on_error<T>(
+        &self,
+        result: anyhow::Result<T>,
+        package_type: PackageType,
+        package_specific_id: &str,
+    ) -> anyhow::Result<ArtifactOrBuild<T>> {
+        let error = match result {
+            Ok(artifact) => return Ok(ArtifactOrBuild::Artifact(artifact)),
+            Err(error) => error,
+        };
+        if error.is::<RangeNotSatisfiable>() {
+            // the artifact exists, only the requested range is invalid
+            return Err(error);
+        }
+        if error.is::<HookError>() {
+            // the artifact exists, a lifecycle hook refused to serve it
+            return Err(error);
+        }
+        if error.is::<BudgetExceeded>() {
+            // the artifact exists, the node is too busy to serve it right now
+            return Err(error);
+        }
+
+        warn!(
+            "Error looking for artifact: {:?}. A new build will be requested",
+            error
+        );
+        match self
+            .request_build(package_type, package_specific_id.to_owned())
+            .await
+        {
+            Ok(build_id) => {
+                debug!(
+                    "Requested build {} of {} {}",
+                    build_id, package_type, package_specific_id
+                );
+                Ok(ArtifactOrBuild::BuildRequested { build_id })
+            }
+            Err(BuildError::QuotaExceeded(quota_exceeded)) => Err(quota_exceeded.into()),
+            Err(build_error) => {
+                warn!(
+                    "Failed to request a build of {} {}: {}",
+                    package_type, package_specific_id, build_error
+                );
+                Err(error)
+            }
+        }
+    }
+
+    /// Remove a local copy of an artifact that failed verification and report
+    /// the peer it was fetched from, if any.
+    async fn discard_corrupt_artifact(
+        &mut self,
+        transparency_log: &TransparencyLog,
+        fetched_from_peer: Option<PeerId>,
+    ) {
+        if let (Some(alert_service), Some(peer_id)) = (&self.alert_service, fetched_from_peer) {
+            alert_service.record_hash_mismatch(&transparency_log.artifact_id, &peer_id);
+        }
+        // a corrupt copy must neither be served again nor be advertised to peers
+        self.remove_artifact_locally(&transparency_log.artifact_id)
+            .await
+            .unwrap_or_else(|e| {
+                warn!(
+                    "Failed to remove corrupt artifact {}: {:?}",
+                    transparency_log.artifact_id, e
+                )
+            });
+    }
+
+    /// Verify that the peer with the specified `peer_id` is allowed to request
+    /// the artifact specified by `artifact_id` from this node.
+    pub fn authorize_artifact_request(
+        &self,
+        peer_id: &PeerId,
+        artifact_id: &str,
+    ) -> anyhow::Result<()> {
+        if self.artifact_request_policy == ArtifactRequestPolicy::Public {
+            return Ok(());
+        }
+
+        // the artifact can be requested for any package that references it
+        let transparency_logs = self
+            .transparency_log_service
+            .find_artifact_references(artifact_id)?;
+        if transparency_logs.iter().any(|transparency_log| {
+            self.artifact_request_policy
+                .is_authorized(peer_id, transparency_log)
+        }) {
+            Ok(())
+        } else {
+            bail!(
+                "Peer {} is not authorized to request artifact with id {}",
+                peer_id,
+                artifact_id
+            )
+        }
+    }
+
+    /// Record that the artifact specified by `artifact_id` was served to the
+    /// peer with the specified `peer_id`.
+    pub fn record_artifact_served(
+        &self,
+        peer_id: &PeerId,
+        artifact_id: &str,
+    ) -> anyhow::Result<()> {
+        let transparency_log = self
+            .transparency_log_service
+            .find_transparency_log_by_artifact_id(artifact_id)?;
+        if let Some(package_type) = transparency_log.package_type {
+            self.access_stats.record_served(
+                package_type,
+                &transparency_log.package_specific_id,
+                &peer_id.to_string(),
+            );
+        }
+        Ok(())
+    }
+
+    /// The access statistics of the specified package.
+    pub fn get_access_stats(
+        &self,
+        package_type: PackageType,
+        package_specific_id: &str,
+    ) -> PackageAccessSummary {
+        self.access_stats.get(package_type, package_specific_id)
+    }
+
+    /// List the artifacts that are stored on this node and match `query`,
+    /// ordered by package type, package specific id and package specific
+    /// artifact id.
+    pub async fn search_artifacts(&self, query: &ArtifactQuery) -> anyhow::Result<ArtifactPage> {
+        let name = match (query.package_type, query.name.as_deref()) {
+            (Some(package_type), Some(name)) => normalize_package_name(package_type, name),
+            (_, name) => name.unwrap_or_default().to_owned(),
+        };
+        let mut transparency_logs = Vec::new();
+        for transparency_log in self
+            .transparency_log_service
+            .find_artifacts(query.package_type.as_ref(), &name)?
+            .into_iter()
+            .filter(|transparency_log| match &query.version {
+                Some(version) => {
+                    package_version(&transparency_log.package_specific_id) == Some(version)
+                }
+                None => true,
+            })
+        {
+            if self
+                .artifact_storage
+                .contains_artifact(&transparency_log.artifact_id)
+                .await
+            {
+                transparency_logs.push(transparency_log);
+            }
+        }
+        transparency_logs.sort_by(|a, b| {
+            (
+                a.package_type,
+                &a.package_specific_id,
+                &a.package_specific_artifact_id,
+            )
+                .cmp(&(
+                    b.package_type,
+                    &b.package_specific_id,
+                    &b.package_specific_artifact_id,
+                ))
+        });
+
+        let total = transparency_logs.len();
+        let mut artifacts = Vec::new();
+        for transparency_log in transparency_logs
+            .into_iter()
+            .skip(query.offset)
+            .take(query.page_size())
+        {
+            let Some(package_type) = transparency_log.package_type else {
+                continue;
+            };
+            let Ok(size) = self
+                .artifact_storage
+                .artifact_size(&transparency_log.artifact_id)
+                .await
+            else {
+                continue;
+            };
+            artifacts.push(ArtifactSummary {
+                package_type,
+                size,
+                package_specific_id: transparency_log.package_specific_id,
+                package_specific_artifact_id: transparency_log.package_specific_artifact_id,
+                artifact_id: transparency_log.artifact_id,
+                artifact_hash: transparency_log.artifact_hash,
+            });
+        }
+
+        Ok(ArtifactPage {
+            total,
+            offset: query.offset,
+            artifacts,
+        })
+    }
+
+    /// Retrieve the artifact data specified by `artifact_id` from the local storage.
     pub async fn get_artifact_locally(
         &mut self,
         artifact_id: &str,
     ) -> Result<Vec<u8>, anyhow::Error> {
-        let artifact = self.artifact_storage.pull_artifact(artifact_id)?;
-        let mut buf_reader = BufReader::new(artifact);
-        let mut blob_content = Vec::new();
-        buf_reader.read_to_end(&mut blob_content)?;
+        let _transfer_permit = self
+            .transfer_budget
+            .try_admit(self.artifact_storage.artifact_size(artifact_id).await?)?;
+        let blob_content = self.artifact_storage.read_artifact(artifact_id).await?;
+        self.artifact_popularity.record_request(artifact_id);
         Ok(blob_content)
     }
 
+    /// Read a chunk of a locally stored artifact for a peer that downloads it
+    /// in chunks. Returns the chunk and the size of the complete artifact.
+    /// When the transfer budget is exhausted the request fails, and the peer
+    /// retries the chunk with another provider.
+    pub async fn get_artifact_chunk_locally(
+        &self,
+        artifact_id: &str,
+        chunk: ArtifactChunk,
+    ) -> anyhow::Result<(Vec<u8>, u64)> {
+        let artifact_size = self.artifact_storage.artifact_size(artifact_id).await?;
+        let _transfer_permit = self
+            .transfer_budget
+            .try_admit(chunk.len_in(artifact_size))?;
+        let content = self
+            .artifact_storage
+            .read_artifact_range(artifact_id, chunk.offset, chunk.len_in(artifact_size))
+            .await?;
+        if chunk.offset == 0 {
+            self.artifact_popularity.record_request(artifact_id);
+        }
+        Ok((content, artifact_size))
+    }
+
+    /// Remove the artifact specified by `artifact_id` from the local storage
+    /// and stop advertising this node as a provider of it on the p2p network.
+    pub async fn remove_artifact_locally(&mut self, artifact_id: &str) -> anyhow::Result<()> {
+        self.metadata_cache.remove(artifact_id);
+        self.artifact_popularity.forget(artifact_id);
+        self.p2p_client.stop_providing(artifact_id).await?;
+        self.artifact_storage
+            .remove_artifact(artifact_id)
+            .await
+            .context("Error from remove_artifact")
+    }
+
+    /// Remove the least recently used artifacts from the local storage until
+    /// it is back under its maximum size. Artifacts of pinned packages are
+    /// kept, and so are the transparency logs of the evicted artifacts, which
+    /// can be fetched again from other peers when they are requested. The
+    /// last copy of an artifact on the p2p network is only evicted as the
+    /// sole copy policy allows, see [`SoleCopyPolicy`]. Artifacts that are
+    /// kept are not evicted again until the node restarts.
+    pub async fn evict_least_recently_used(&mut self) -> anyhow::Result<Vec<String>> {
+        let mut evicted_artifact_ids = vec![];
+        loop {
+            let eviction_candidates = self.artifact_storage.eviction_candidates();
+            if eviction_candidates.is_empty() {
+                break;
+            }
+            for artifact_id in eviction_candidates {
+                if self.is_artifact_of_pinned_package(&artifact_id)? {
+                    self.artifact_storage.pin_artifact(&artifact_id);
+                    continue;
+                }
+                match self.ensure_other_copy(&artifact_id).await {
+                    Ok(true) => {}
+                    Ok(false) => {
+                        self.artifact_storage.pin_artifact(&artifact_id);
+                        continue;
+                    }
+                    Err(error) => {
+                        warn!(
+                            "Kept artifact {} because its other copies could not be checked: {:?}",
+                            artifact_id, error
+                        );
+                        self.artifact_storage.pin_artifact(&artifact_id);
+                        continue;
+                    }
+                }
+                self.remove_artifact_locally(&artifact_id).await?;
+                evicted_artifact_ids.push(artifact_id);
+            }
+        }
+        if !evicted_artifact_ids.is_empty() {
+            info!(
+                "Evicted {} artifacts to stay under the maximum storage size",
+                evicted_artifact_ids.len()
+            );
+        }
+        Ok(evicted_artifact_ids)
+    }
+
+    /// Evict the least recently used artifacts every time the artifact
+    /// storage exceeds its maximum size, forever.
+    pub async fn run_eviction(mut self) {
+        loop {
+            if let Err(error) = self.evict_least_recently_used().await {
+                warn!("Failed to evict artifacts: {:?}", error);
+            }
+            self.artifact_storage.wait_until_max_size_exceeded().await;
+        }
+    }
+
+    // Whether another peer provides the artifact, or the artifact has no
+    // transparency log, so that evicting it does not lose the last copy on
+    // the p2p network. When this node has the only copy, a connected peer is
+    // asked to take a replica if the sole copy policy says so.
+    async fn ensure_other_copy(&mut self, artifact_id: &str) -> anyhow::Result<bool> {
+        if self.sole_copy_policy == SoleCopyPolicy::Evict
+            || self
+                .transparency_log_service
+                .find_artifact_references(artifact_id)?
+                .is_empty()
+        {
+            return Ok(true);
+        }
+
+        let local_peer_id = self.p2p_client.local_peer_id;
+        let providers = self.p2p_client.list_providers(artifact_id).await?;
+        if providers.iter().any(|peer_id| *peer_id != local_peer_id) {
+            return Ok(true);
+        }
+        if self.sole_copy_policy == SoleCopyPolicy::Keep {
+            info!(
+                "Kept artifact {}, this node has the only copy on the p2p network",
+                artifact_id
+            );
+            return Ok(false);
+        }
+
+        for peer_id in self.p2p_client.list_peers().await? {
+            let peer = self.p2p_client.peer_aliases.display(&peer_id);
+            match self
+                .p2p_client
+                .request_seed_sync(
+                    &peer_id,
+                    SeedSyncRequest::Replicate {
+                        artifact_id: artifact_id.to_owned(),
+                    },
+                )
+                .await
+            {
+                Ok(SeedSyncResponse::Replicated(true)) => {
+                    info!(
+                        "Peer {} took a replica of artifact {} before it was evicted",
+                        peer, artifact_id
+                    );
+                    return Ok(true);
+                }
+                Ok(response) => debug!(
+                    "Peer {} declined a replica of artifact {}: {:?}",
+                    peer, artifact_id, response
+                ),
+                Err(error) => debug!(
+                    "Peer {} failed to take a replica of artifact {}: {:?}",
+                    peer, artifact_id, error
+                ),
+            }
+        }
+
+        warn!(
+            "Kept artifact {}, this node has the only copy on the p2p network and no peer took a replica",
+            artifact_id
+        );
+        Ok(false)
+    }
+
+    fn is_artifact_of_pinned_package(&self, artifact_id: &str) -> anyhow::Result<bool> {
+        if self.pinned_packages.is_empty() {
+            return Ok(false);
+        }
+        let references = self
+            .transparency_log_service
+            .find_artifact_references(artifact_id)?;
+        Ok(references.iter().any(|transparency_log| {
+            self.pinned_packages
+                .iter()
+                .any(|(package_type, package_specific_id)| {
+                    transparency_log.package_type == Some(*package_type)
+                        && transparency_log.package_specific_id == *package_specific_id
+                })
+        }))
+    }
+
+    /// Remove the artifact specified by `package_type` and
+    /// `package_specific_artifact_id` from the network: a RemoveArtifact
+    /// transparency log is published as a tombstone, the local copy is deleted
+    /// and this node stops providing it. Other nodes remove their copies when
+    /// they receive the tombstone.
+    pub async fn remove_artifact(
+        &mut self,
+        package_type: PackageType,
+        package_specific_artifact_id: &str,
+    ) -> anyhow::Result<TransparencyLog> {
+        let transparency_log = self
+            .transparency_log_service
+            .remove_artifact(
+                &package_type,
+                package_specific_artifact_id,
+                self.p2p_client.local_peer_id,
+            )
+            .await?;
+        self.notify_subscribers(&transparency_log);
+
+        self.remove_tombstoned_artifact(&transparency_log).await?;
+        Ok(transparency_log)
+    }
+
+    /// Revoke the artifact specified by `package_type` and
+    /// `package_specific_artifact_id`, e.g. because it was compromised: a
+    /// RevokeArtifact transparency log is published, after which no node serves
+    /// the artifact under any package that references it. The local copy is
+    /// deleted and this node stops providing it.
+    pub async fn revoke_artifact(
+        &mut self,
+        package_type: PackageType,
+        package_specific_artifact_id: &str,
+    ) -> anyhow::Result<TransparencyLog> {
+        let transparency_log = self
+            .transparency_log_service
+            .revoke_artifact(
+                &package_type,
+                package_specific_artifact_id,
+                self.p2p_client.local_peer_id,
+            )
+            .await?;
+        self.notify_subscribers(&transparency_log);
+
+        self.remove_tombstoned_artifact(&transparency_log).await?;
+        Ok(transparency_log)
+    }
+
+    // Remove the local copy of an artifact that has a RemoveArtifact or
+    // RevokeArtifact transparency log, unless the artifact is still referenced
+    // by another package. Revoked artifacts are never referenced.
+    async fn remove_tombstoned_artifact(
+        &mut self,
+        transparency_log: &TransparencyLog,
+    ) -> anyhow::Result<()> {
+        let references = self
+            .transparency_log_service
+            .find_artifact_references(&transparency_log.artifact_id)?;
+        if !references.is_empty() {
+            debug!(
+                "Artifact {} is kept, it is still referenced by {} other artifacts",
+                transparency_log.artifact_id,
+                references.len()
+            );
+            return Ok(());
+        }
+
+        if self
+            .artifact_storage
+            .contains_artifact(&transparency_log.artifact_id)
+            .await
+        {
+            self.remove_artifact_locally(&transparency_log.artifact_id)
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Count the references of the locally stored artifacts. Identical
+    /// artifacts of several packages share one stored artifact, which is only
+    /// removed when its last reference is removed.
+    pub async fn dedup_stats(&self) -> anyhow::Result<DedupStats> {
+        let reference_counts = self.transparency_log_service.artifact_reference_counts()?;
+        let mut dedup_stats = DedupStats::default();
+        for artifact_id in self.artifact_storage.list_artifacts().await? {
+            // the artifact was removed while counting
+            let Ok(size) = self.artifact_storage.artifact_size(&artifact_id).await else {
+                continue;
+            };
+            dedup_stats.record(
+                size,
+                reference_counts.get(&artifact_id).copied().unwrap_or(0),
+            );
+        }
+        Ok(dedup_stats)
+    }
+
+    /// Report how much space the stored artifacts take up, by package type,
+    /// and how much space is left, for capacity planning.
+    pub async fn storage_stats(&self) -> anyhow::Result<StorageStats> {
+        let package_types = self.transparency_log_service.artifact_package_types()?;
+        let mut storage_stats = StorageStats::default();
+        for artifact_id in self.artifact_storage.list_artifacts().await? {
+            // the artifact was removed while counting
+            let Ok(size) = self.artifact_storage.artifact_size(&artifact_id).await else {
+                continue;
+            };
+            storage_stats.record(size, package_types.get(&artifact_id));
+        }
+        storage_stats.free_bytes = self.artifact_storage.available_space().await;
+        Ok(storage_stats)
+    }
+
     /// Retrieve the artifact logs for the specified package.
     pub async fn get_logs_for_artifact(
         &mut self,
@@ -323,119 +1661,2934 @@ impl ArtifactService {
         Ok(transparency_logs)
     }
 
-    pub async fn provide_local_artifacts(&self) -> anyhow::Result<()> {
-        for path in self.artifact_storage.list_artifacts()? {
-            if let Some(artifact_id) = path.file_stem() {
-                debug!("Providing artifact_id: {:?}", artifact_id);
-                self.p2p_client
-                    .clone()
-                    .provide(artifact_id.to_str().expect("error getting artifact_id"))
-                    .await?
-            }
+    /// Check the integrity of all artifacts of the specified package end to
+    /// end: the hash of the local copy, the availability on the p2p network,
+    /// the provenance of the transparency log on the blockchain and, when
+    /// `retrieve` is set, whether a remote provider serves a valid copy.
+    pub async fn check_package(
+        &mut self,
+        package_type: PackageType,
+        package_specific_id: &str,
+        retrieve: bool,
+    ) -> Result<Vec<ArtifactCheck>, TransparencyLogError> {
+        let transparency_logs: Vec<TransparencyLog> = self
+            .transparency_log_service
+            .search_transparency_logs(&package_type, package_specific_id)?
+            .into_iter()
+            .filter(|log| log.operation == Operation::AddArtifact)
+            .collect();
+        if transparency_logs.is_empty() {
+            return Err(TransparencyLogError::ArtifactNotFound {
+                package_type,
+                package_specific_artifact_id: package_specific_id.to_owned(),
+            });
         }
-        Ok(())
+
+        let transparency_log_ids = transparency_logs.iter().map(|log| log.id.clone()).collect();
+        let blocks = self
+            .transparency_log_service
+            .find_blocks(&transparency_log_ids)
+            .await?;
+        let authorized_nodes = self.transparency_log_service.get_authorized_nodes()?;
+
+        let mut artifact_checks = Vec::new();
+        for transparency_log in transparency_logs {
+            let providers = self
+                .p2p_client
+                .list_providers(&transparency_log.artifact_id)
+                .await
+                .unwrap_or_default();
+
+            artifact_checks.push(ArtifactCheck {
+                package_specific_artifact_id: transparency_log.package_specific_artifact_id.clone(),
+                artifact_id: transparency_log.artifact_id.clone(),
+                local_hash: self.check_local_hash(&transparency_log).await,
+                dht_availability: check_dht_availability(&providers),
+                peer_retrieval: if retrieve {
+                    self.check_peer_retrieval(package_type, &transparency_log, providers)
+                        .await
+                } else {
+                    CheckOutcome::Skipped("not requested".to_owned())
+                },
+                provenance: check_provenance(
+                    &transparency_log,
+                    blocks.get(&transparency_log.id),
+                    &authorized_nodes,
+                ),
+            });
+        }
+
+        Ok(artifact_checks)
     }
 
-    async fn get_artifact_from_peers(
-        &mut self,
-        artifact_id: &str,
-    ) -> Result<Vec<u8>, anyhow::Error> {
-        let providers = self.p2p_client.list_providers(artifact_id).await?;
+    /// Re-hash all locally stored artifacts and compare them with the hashes
+    /// in their transparency logs. Corrupt artifacts are logged and removed,
+    /// so they are neither served nor advertised anymore. When `refetch` is
+    /// set, a valid copy of every corrupt artifact is fetched from peers.
+    pub async fn scrub_local_artifacts(&mut self, refetch: bool) -> anyhow::Result<ScrubReport> {
+        let mut report = ScrubReport::default();
+        let artifact_ids = self.artifact_storage.list_artifacts().await?;
+        for artifact_id in artifact_ids {
+            let transparency_log = match self
+                .transparency_log_service
+                .find_artifact_references(&artifact_id)?
+                .into_iter()
+                .next()
+            {
+                Some(transparency_log) => transparency_log,
+                None => {
+                    report.unverifiable += 1;
+                    continue;
+                }
+            };
 
-        match self.p2p_client.get_idle_peer(providers).await? {
-            Some(peer_id) => self.get_artifact_from_peer(&peer_id, artifact_id).await,
-            None => {
-                bail!(
-                    "Artifact with id {} is not available on the p2p network.",
-                    artifact_id
-                )
+            match self.artifact_storage.hash_artifact(&artifact_id).await {
+                Ok(hash) if hash == transparency_log.artifact_hash => {
+                    report.verified += 1;
+                    continue;
+                }
+                Ok(hash) => warn!(
+                    "Stored artifact {} is corrupt: sha256 {} does not match {}",
+                    artifact_id, hash, transparency_log.artifact_hash
+                ),
+                // the artifact was removed while scrubbing
+                Err(error) if error.kind() == ErrorKind::NotFound => continue,
+                Err(error) => warn!(
+                    "Stored artifact {} is corrupt: unable to read it: {}",
+                    artifact_id, error
+                ),
+            }
+            self.discard_corrupt_artifact(&transparency_log, None).await;
+            if refetch && self.refetch_artifact(&transparency_log).await {
+                report.repaired.push(artifact_id.clone());
+            }
+            report.corrupt.push(artifact_id);
+        }
+
+        info!(
+            "Scrubbed local artifacts: {} verified, {} corrupt, {} repaired, {} without transparency log",
+            report.verified,
+            report.corrupt.len(),
+            report.repaired.len(),
+            report.unverifiable
+        );
+        self.scrub_stats.lock().unwrap().record(&report);
+        Ok(report)
+    }
+
+    /// Scrub the locally stored artifacts every `interval`, forever.
+    pub async fn run_scrub_scheduler(mut self, interval: Duration, refetch: bool) {
+        loop {
+            tokio::time::sleep(interval).await;
+            if let Err(error) = self.scrub_local_artifacts(refetch).await {
+                warn!("Failed to scrub local artifacts: {:?}", error);
             }
         }
     }
 
-    async fn get_artifact_from_peer(
+    /// The totals of all scrubs of the locally stored artifacts.
+    pub fn scrub_stats(&self) -> ScrubStats {
+        self.scrub_stats.lock().unwrap().clone()
+    }
+
+    /// Try to retrieve `sample_size` random artifacts of the transparency log
+    /// from the network and record whether they were available, see
+    /// [`AvailabilityMonitor`]. An artifact is available when a remote
+    /// provider returns it with the hash of its transparency log, or when
+    /// this node provides a valid copy itself. Returns the recorded samples.
+    pub async fn sample_availability(
+        &mut self,
+        sample_size: usize,
+    ) -> anyhow::Result<Vec<AvailabilitySample>> {
+        let transparency_logs = {
+            let mut rng = rand::thread_rng();
+            self.transparency_log_service
+                .find_artifacts(None, "")?
+                .into_iter()
+                .choose_multiple(&mut rng, sample_size)
+        };
+
+        let mut samples = Vec::new();
+        for transparency_log in transparency_logs {
+            let Some(package_type) = transparency_log.package_type else {
+                continue;
+            };
+            let outcome = match self
+                .p2p_client
+                .list_providers(&transparency_log.artifact_id)
+                .await
+            {
+                Ok(providers) if providers.is_empty() => {
+                    CheckOutcome::Failed("no providers found".to_owned())
+                }
+                Ok(providers) => {
+                    let provided_locally = providers.contains(&self.p2p_client.local_peer_id);
+                    match self
+                        .check_peer_retrieval(package_type, &transparency_log, providers)
+                        .await
+                    {
+                        CheckOutcome::Skipped(_) if provided_locally => {
+                            self.check_local_hash(&transparency_log).await
+                        }
+                        outcome => outcome,
+                    }
+                }
+                Err(error) => CheckOutcome::Failed(format!("provider lookup failed: {}", error)),
+            };
+
+            let sample = AvailabilitySample {
+                timestamp: SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap()
+                    .as_secs(),
+                package_type,
+                package_specific_artifact_id: transparency_log.package_specific_artifact_id,
+                available: matches!(outcome, CheckOutcome::Passed(_)),
+                reason: match outcome {
+                    CheckOutcome::Passed(_) => None,
+                    CheckOutcome::Failed(reason) | CheckOutcome::Skipped(reason) => Some(reason),
+                },
+            };
+            if !sample.available {
+                warn!(
+                    "Artifact {} is not available: {}",
+                    sample.package_specific_artifact_id,
+                    sample.reason.as_deref().unwrap_or_default()
+                );
+            }
+            self.availability_monitor.record(sample.clone());
+            samples.push(sample);
+        }
+
+        Ok(samples)
+    }
+
+    /// Sample the availability of `sample_size` artifacts after every
+    /// `interval`, see [`Self::sample_availability`].
+    pub async fn run_availability_monitor(mut self, interval: Duration, sample_size: usize) {
+        loop {
+            tokio::time::sleep(interval).await;
+            if let Err(error) = self.sample_availability(sample_size).await {
+                warn!("Failed to sample artifact availability: {:?}", error);
+            }
+        }
+    }
+
+    /// The availability of the sampled artifacts within the windows of
+    /// `windows` seconds before now.
+    pub fn availability_report(&self, windows: &[u64]) -> AvailabilityReport {
+        self.availability_monitor.report(windows)
+    }
+
+    /// Fetch a valid copy of a discarded artifact from peers and provide it
+    /// again. Returns false when no peer serves a valid copy.
+    async fn refetch_artifact(&mut self, transparency_log: &TransparencyLog) -> bool {
+        let artifact_id = &transparency_log.artifact_id;
+        let artifact_kind = transparency_log
+            .package_type
+            .map(|package_type| {
+                ArtifactKind::of(package_type, &transparency_log.package_specific_artifact_id)
+            })
+            .unwrap_or(ArtifactKind::Blob);
+        let peer_id = match self
+            .fetch_artifact_from_peers(artifact_id, artifact_kind)
+            .await
+        {
+            Ok(peer_id) => peer_id,
+            Err(error) => {
+                warn!(
+                    "Failed to fetch corrupt artifact {} again: {:?}",
+                    artifact_id, error
+                );
+                return false;
+            }
+        };
+
+        match self.artifact_storage.hash_artifact(artifact_id).await {
+            Ok(hash) if hash == transparency_log.artifact_hash => {}
+            _ => {
+                warn!(
+                    "Peer {} provided a corrupt copy of artifact {}",
+                    self.p2p_client.peer_aliases.display(&peer_id),
+                    artifact_id
+                );
+                self.discard_corrupt_artifact(transparency_log, Some(peer_id))
+                    .await;
+                return false;
+            }
+        }
+
+        info!("Repaired corrupt artifact {}", artifact_id);
+        self.p2p_client
+            .provide(artifact_id)
+            .await
+            .unwrap_or_else(|e| warn!("Failed to provide artifact {}: {:?}", artifact_id, e));
+        true
+    }
+
+    /// Migrate artifacts that were added before artifact ids were derived from
+    /// the artifact hash: their transparency logs get the derived artifact_id
+    /// and their stored files are moved accordingly. This must run before the
+    /// local artifacts are provided on the p2p network.
+    pub async fn migrate_artifact_ids(&self) -> anyhow::Result<()> {
+        for (artifact_id, new_artifact_id) in
+            self.transparency_log_service.migrate_artifact_ids()?
+        {
+            self.artifact_popularity.forget(&artifact_id);
+            if self.artifact_storage.contains_artifact(&artifact_id).await {
+                self.artifact_storage
+                    .move_artifact(&artifact_id, &new_artifact_id)
+                    .await
+                    .context("Error from move_artifact")?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Provide all locally stored artifacts on the p2p network. Artifacts are
+    /// provided in batches at the pace of the provide schedule, so this can
+    /// run in the background while the node serves requests. The artifacts of
+    /// the pinned packages are provided first, then the artifacts of the most
+    /// pulled packages according to the persisted access statistics and the
+    /// most requested artifacts since the node started. The progress is
+    /// available with [`provide_progress`](Self::provide_progress).
+    pub async fn provide_local_artifacts(&self) -> anyhow::Result<()> {
+        *self.provide_progress.lock().unwrap() = Default::default();
+
+        let mut candidates = Vec::new();
+        for (package_type, package_specific_id) in self
+            .pinned_packages
+            .iter()
+            .cloned()
+            .chain(self.access_stats.most_pulled())
+        {
+            candidates.extend(self.package_artifact_ids(&package_type, &package_specific_id)?);
+        }
+        candidates.extend(self.artifact_popularity.most_popular());
+
+        let mut prioritized_artifact_ids = Vec::new();
+        for artifact_id in candidates.into_iter().unique() {
+            if self.artifact_storage.contains_artifact(&artifact_id).await {
+                prioritized_artifact_ids.push(artifact_id);
+            }
+        }
+        let prioritized_artifact_id_set: HashSet<String> =
+            prioritized_artifact_ids.iter().cloned().collect();
+
+        let batch_size = self.provide_schedule.batch_size.max(1);
+        let mut prioritized_batches = prioritized_artifact_ids.chunks(batch_size);
+        let mut stored_batches = self.artifact_storage.artifact_id_batches(batch_size)?;
+        loop {
+            let batch: Vec<String> = match prioritized_batches.next() {
+                Some(batch) => batch.to_vec(),
+                None => match stored_batches.next().await? {
+                    Some(batch) => batch
+                        .into_iter()
+                        .filter(|artifact_id| !prioritized_artifact_id_set.contains(artifact_id))
+                        .collect(),
+                    None => break,
+                },
+            };
+            if batch.is_empty() {
+                continue;
+            }
+            let batch_len = batch.len();
+
+            for artifact_id in batch {
+                debug!("Providing artifact_id: {:?}", artifact_id);
+                let result = self.p2p_client.clone().provide(&artifact_id).await;
+
+                let mut provide_progress = self.provide_progress.lock().unwrap();
+                match result {
+                    Ok(()) => provide_progress.provided += 1,
+                    Err(error) => {
+                        warn!("Failed to provide artifact {}: {:?}", artifact_id, error);
+                        provide_progress.failed += 1;
+                    }
+                }
+            }
+
+            info!(
+                "Provided {} local artifacts so far",
+                self.provide_progress.lock().unwrap().provided
+            );
+            let delay = self.provide_schedule.delay_after_batch(batch_len);
+            if delay.is_zero() {
+                tokio::task::yield_now().await;
+            } else {
+                tokio::time::sleep(delay).await;
+            }
+        }
+
+        let mut provide_progress = self.provide_progress.lock().unwrap();
+        provide_progress.finished = true;
+        info!(
+            "Finished providing local artifacts: {} provided, {} failed",
+            provide_progress.provided, provide_progress.failed
+        );
+        Ok(())
+    }
+
+    /// Provide all locally stored artifacts, and provide them again after
+    /// every re-provide interval of the provide schedule, if it has one.
+    pub async fn run_provide_scheduler(self) {
+        loop {
+            if let Err(error) = self.provide_local_artifacts().await {
+                warn!("Failed to provide local artifacts: {:?}", error);
+            }
+
+            match self.provide_schedule.delay_before_reprovide() {
+                Some(delay) => tokio::time::sleep(delay).await,
+                None => return,
+            }
+        }
+    }
+
+    /// The progress of providing the locally stored artifacts.
+    pub fn provide_progress(&self) -> ProvideProgress {
+        self.provide_progress.lock().unwrap().clone()
+    }
+
+    /// Answer a node that syncs from this node as its seed node, see
+    /// [`sync_from_seed`](Self::sync_from_seed), or the peer `peer_id` that
+    /// asks this node to take a replica before it evicts an artifact.
+    pub async fn respond_seed_sync(
         &mut self,
         peer_id: &PeerId,
-        artifact_id: &str,
-    ) -> Result<Vec<u8>, anyhow::Error> {
-        let artifact = self
+        request: SeedSyncRequest,
+    ) -> anyhow::Result<SeedSyncResponse> {
+        Ok(match request {
+            SeedSyncRequest::TransparencyLogs { offset } => SeedSyncResponse::TransparencyLogs(
+                self.transparency_log_service
+                    .transparency_log_page(offset, MAX_LOG_PAGE_SIZE)?,
+            ),
+            SeedSyncRequest::Artifacts => {
+                SeedSyncResponse::Artifacts(self.seed_artifact_ids().await?)
+            }
+            SeedSyncRequest::Snapshot => match &self.snapshot_keypair {
+                Some(keypair) => SeedSyncResponse::Snapshot(
+                    self.transparency_log_service
+                        .create_snapshot(keypair)
+                        .await?,
+                ),
+                None => bail!("This node does not sign transparency log snapshots"),
+            },
+            SeedSyncRequest::Replicate { artifact_id } => {
+                SeedSyncResponse::Replicated(self.take_replica(peer_id, &artifact_id).await)
+            }
+        })
+    }
+
+    // Fetch a replica of an artifact with a transparency log from the peer
+    // that is about to evict it, unless this node is a monitor or has no room
+    // for it. Returns whether this node stores and provides a verified copy.
+    async fn take_replica(&mut self, peer_id: &PeerId, artifact_id: &str) -> bool {
+        if self.monitor_mode || !self.artifact_storage.eviction_candidates().is_empty() {
+            return false;
+        }
+
+        match self.sync_seed_artifact(peer_id, artifact_id).await {
+            Ok(_) => true,
+            Err(error) => {
+                warn!(
+                    "Failed to take a replica of artifact {} from peer {}: {:?}",
+                    artifact_id,
+                    self.p2p_client.peer_aliases.display(peer_id),
+                    error
+                );
+                false
+            }
+        }
+    }
+
+    /// Import a signed snapshot of the transparency logs of the peer
+    /// `peer_id`, instead of replaying its whole blockchain. The snapshot
+    /// has to be signed by the peer itself, which has to be an authorized
+    /// node, and its transparency logs are verified against the root hash of
+    /// the snapshot before they are imported, see
+    /// [`TransparencyLogService::import_snapshot`]. Returns the imported
+    /// snapshot, the blocks after its block ordinal still have to be pulled.
+    pub async fn fast_sync(&mut self, peer_id: &PeerId) -> anyhow::Result<LogSnapshot> {
+        let snapshot = match self
+            .p2p_client
+            .request_seed_sync(peer_id, SeedSyncRequest::Snapshot)
+            .await?
+        {
+            SeedSyncResponse::Snapshot(snapshot) => snapshot,
+            response => bail!("Unexpected response from peer {}: {:?}", peer_id, response),
+        };
+        if snapshot.signer() != Some(*peer_id) {
+            bail!(
+                "The transparency log snapshot of peer {} is not signed by it",
+                peer_id
+            );
+        }
+
+        let mut transparency_logs = Vec::new();
+        while (transparency_logs.len() as u64) < snapshot.tree_size {
+            let page = match self
+                .p2p_client
+                .request_seed_sync(
+                    peer_id,
+                    SeedSyncRequest::TransparencyLogs {
+                        offset: transparency_logs.len(),
+                    },
+                )
+                .await?
+            {
+                SeedSyncResponse::TransparencyLogs(page) => page,
+                response => bail!("Unexpected response from peer {}: {:?}", peer_id, response),
+            };
+            if page.transparency_logs.is_empty() {
+                break;
+            }
+            transparency_logs.extend(page.transparency_logs);
+        }
+        transparency_logs.truncate(snapshot.tree_size as usize);
+
+        let imported = self
+            .transparency_log_service
+            .import_snapshot(&snapshot, &transparency_logs)?;
+        info!(
+            "Imported {} transparency logs from the snapshot of peer {} up to block {}",
+            imported, peer_id, snapshot.block_ordinal
+        );
+        Ok(snapshot)
+    }
+
+    // The ids of the artifacts of a package according to the transparency
+    // logs, whether they are stored locally or not.
+    fn package_artifact_ids(
+        &self,
+        package_type: &PackageType,
+        package_specific_id: &str,
+    ) -> anyhow::Result<Vec<String>> {
+        Ok(self
+            .transparency_log_service
+            .find_artifacts(Some(package_type), package_specific_id)?
+            .into_iter()
+            .filter(|transparency_log| transparency_log.package_specific_id == package_specific_id)
+            .map(|transparency_log| transparency_log.artifact_id)
+            .collect())
+    }
+
+    // The locally stored artifacts that are offered to nodes that sync from
+    // this node: the most requested artifacts first, then the artifacts of
+    // the pinned packages.
+    async fn seed_artifact_ids(&self) -> anyhow::Result<Vec<String>> {
+        let mut candidates = self.artifact_popularity.most_popular();
+        for (package_type, package_specific_id) in &self.pinned_packages {
+            candidates.extend(self.package_artifact_ids(package_type, package_specific_id)?);
+        }
+
+        let mut artifact_ids = Vec::new();
+        for artifact_id in candidates.into_iter().unique() {
+            if artifact_ids.len() >= SEED_SYNC_MAX_ARTIFACTS {
+                break;
+            }
+            if self.artifact_storage.contains_artifact(&artifact_id).await {
+                artifact_ids.push(artifact_id);
+            }
+        }
+        Ok(artifact_ids)
+    }
+
+    /// Copy the transparency logs and the popular and pinned artifacts of the
+    /// seed node at `seed` to this node, so that a new node is useful right
+    /// away. The artifacts are fetched from the seed node only and are
+    /// verified against their transparency logs like any fetched artifact.
+    /// Transparency logs and verified artifacts that this node already has
+    /// are skipped, so an interrupted sync resumes where it stopped when it
+    /// is started again. The progress is available with
+    /// [`seed_sync_progress`](Self::seed_sync_progress).
+    pub async fn sync_from_seed(&mut self, seed: &Multiaddr) -> anyhow::Result<SeedSyncProgress> {
+        self.begin_seed_sync(seed)?;
+        let result = self.copy_seed_snapshot(seed).await;
+        self.finish_seed_sync(seed, result)
+    }
+
+    /// Start a sync from the seed node at `seed` in the background, see
+    /// [`sync_from_seed`](Self::sync_from_seed). Fails when a sync is
+    /// already running.
+    pub fn start_seed_sync(&self, seed: Multiaddr) -> anyhow::Result<SeedSyncProgress> {
+        let progress = self.begin_seed_sync(&seed)?;
+        let mut artifact_service = self.clone();
+        tokio::spawn(async move {
+            let result = artifact_service.copy_seed_snapshot(&seed).await;
+            if let Err(error) = artifact_service.finish_seed_sync(&seed, result) {
+                warn!("Failed to sync from seed node {}: {:?}", seed, error);
+            }
+        });
+        Ok(progress)
+    }
+
+    /// The progress of the last sync from a seed node.
+    pub fn seed_sync_progress(&self) -> SeedSyncProgress {
+        self.seed_sync_progress.lock().unwrap().clone()
+    }
+
+    fn begin_seed_sync(&self, seed: &Multiaddr) -> anyhow::Result<SeedSyncProgress> {
+        let mut progress = self.seed_sync_progress.lock().unwrap();
+        if progress.state == SeedSyncState::Running {
+            bail!(
+                "A sync from seed node {} is already running",
+                progress.seed.as_deref().unwrap_or_default()
+            );
+        }
+        *progress = SeedSyncProgress {
+            seed: Some(seed.to_string()),
+            state: SeedSyncState::Running,
+            ..Default::default()
+        };
+        Ok(progress.clone())
+    }
+
+    fn finish_seed_sync(
+        &self,
+        seed: &Multiaddr,
+        result: anyhow::Result<()>,
+    ) -> anyhow::Result<SeedSyncProgress> {
+        let mut progress = self.seed_sync_progress.lock().unwrap();
+        match result {
+            Ok(()) => {
+                progress.state = SeedSyncState::Finished;
+                info!(
+                    "Finished syncing from seed node {}: {} transparency logs imported, {} artifacts synced, {} skipped, {} failed",
+                    seed,
+                    progress.transparency_logs_imported,
+                    progress.artifacts_synced,
+                    progress.artifacts_skipped,
+                    progress.artifacts_failed
+                );
+                Ok(progress.clone())
+            }
+            Err(error) => {
+                progress.state = SeedSyncState::Failed;
+                progress.error = Some(error.to_string());
+                Err(error)
+            }
+        }
+    }
+
+    async fn copy_seed_snapshot(&mut self, seed: &Multiaddr) -> anyhow::Result<()> {
+        let seed_peer_id = PeerId::try_from_multiaddr(seed)
+            .with_context(|| format!("Seed node address {} does not contain a peer id", seed))?;
+        self.p2p_client.dial(&seed_peer_id, seed).await?;
+
+        let mut offset = 0;
+        loop {
+            let page = match self
+                .p2p_client
+                .request_seed_sync(&seed_peer_id, SeedSyncRequest::TransparencyLogs { offset })
+                .await?
+            {
+                SeedSyncResponse::TransparencyLogs(page) => page,
+                response => bail!("Unexpected response from seed node: {:?}", response),
+            };
+            if page.transparency_logs.is_empty() {
+                break;
+            }
+            let imported = self
+                .transparency_log_service
+                .import_transparency_logs(&page.transparency_logs)?;
+            offset += page.transparency_logs.len();
+            self.seed_sync_progress
+                .lock()
+                .unwrap()
+                .transparency_logs_imported += imported;
+            if offset >= page.total {
+                break;
+            }
+        }
+
+        let artifact_ids = match self
             .p2p_client
-            .request_artifact(peer_id, artifact_id)
+            .request_seed_sync(&seed_peer_id, SeedSyncRequest::Artifacts)
+            .await?
+        {
+            SeedSyncResponse::Artifacts(artifact_ids) => artifact_ids,
+            response => bail!("Unexpected response from seed node: {:?}", response),
+        };
+        self.seed_sync_progress.lock().unwrap().artifacts_total = artifact_ids.len();
+
+        for artifact_id in artifact_ids {
+            let result = self.sync_seed_artifact(&seed_peer_id, &artifact_id).await;
+            let mut progress = self.seed_sync_progress.lock().unwrap();
+            match result {
+                Ok(Some(artifact_size)) => {
+                    progress.artifacts_synced += 1;
+                    progress.bytes_synced += artifact_size;
+                }
+                Ok(None) => progress.artifacts_skipped += 1,
+                Err(error) => {
+                    warn!(
+                        "Failed to sync artifact {} from seed node: {:?}",
+                        artifact_id, error
+                    );
+                    progress.artifacts_failed += 1;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Fetch an artifact from the seed node, or from a peer that asked for a
+    // replica, unless this node already stores a verified copy. Returns the
+    // size of the fetched artifact, or `None` when it was skipped.
+    async fn sync_seed_artifact(
+        &mut self,
+        seed_peer_id: &PeerId,
+        artifact_id: &str,
+    ) -> anyhow::Result<Option<u64>> {
+        let transparency_log = self
+            .transparency_log_service
+            .find_transparency_log_by_artifact_id(artifact_id)?;
+        if self.artifact_storage.contains_artifact(artifact_id).await {
+            if self.artifact_storage.hash_artifact(artifact_id).await?
+                == transparency_log.artifact_hash
+            {
+                return Ok(None);
+            }
+            self.discard_corrupt_artifact(&transparency_log, None).await;
+        }
+
+        let artifact_kind = transparency_log
+            .package_type
+            .map(|package_type| {
+                ArtifactKind::of(package_type, &transparency_log.package_specific_artifact_id)
+            })
+            .unwrap_or(ArtifactKind::Blob);
+        if artifact_kind == ArtifactKind::Blob {
+            self.fetch_artifact_in_chunks(&[*seed_peer_id], artifact_id, ARTIFACT_CHUNK_SIZE)
+                .await?;
+        } else {
+            self.fetch_artifact_from_peer(seed_peer_id, artifact_id, artifact_kind)
+                .await?;
+        }
+
+        let calculated_hash = self.artifact_storage.hash_artifact(artifact_id).await?;
+        if calculated_hash != transparency_log.artifact_hash {
+            self.discard_corrupt_artifact(&transparency_log, Some(*seed_peer_id))
+                .await;
+            return Err(TransparencyLogError::InvalidHash {
+                id: transparency_log.package_specific_artifact_id.clone(),
+                invalid_hash: calculated_hash,
+                actual_hash: transparency_log.artifact_hash.clone(),
+            }
+            .into());
+        }
+
+        self.p2p_client
+            .provide(artifact_id)
+            .await
+            .unwrap_or_else(|e| warn!("Failed to provide artifact {}: {:?}", artifact_id, e));
+        Ok(Some(
+            self.artifact_storage.artifact_size(artifact_id).await?,
+        ))
+    }
+
+    async fn get_artifact_from_peers(
+        &mut self,
+        artifact_id: &str,
+        artifact_kind: ArtifactKind,
+    ) -> Result<(Vec<u8>, PeerId), anyhow::Error> {
+        let peer_id = self
+            .fetch_artifact_from_peers(artifact_id, artifact_kind)
             .await?;
+        let artifact = self.get_artifact_locally(artifact_id).await?;
+        Ok((artifact, peer_id))
+    }
+
+    /// Fetch the artifact from idle providers on the p2p network into the
+    /// local storage and return the peer that provided it. Metadata is
+    /// fetched from a single provider, blobs are fetched in chunks from all
+    /// providers at once and the peer that provided the first chunk is
+    /// returned. Failed requests are retried according to the fetch retry
+    /// policy, the most idle providers are tried first.
+    async fn fetch_artifact_from_peers(
+        &mut self,
+        artifact_id: &str,
+        artifact_kind: ArtifactKind,
+    ) -> Result<PeerId, anyhow::Error> {
+        let providers = self.p2p_client.list_providers(artifact_id).await?;
+        let peers = self.p2p_client.get_idle_peers(providers).await?;
+        if peers.is_empty() {
+            bail!(
+                "Artifact with id {} is not available on the p2p network.",
+                artifact_id
+            )
+        }
+
+        if artifact_kind == ArtifactKind::Blob {
+            return self
+                .fetch_artifact_in_chunks(&peers, artifact_id, ARTIFACT_CHUNK_SIZE)
+                .await;
+        }
+
+        let mut errors = Vec::new();
+        for retry in 0..=self.fetch_retry_policy.max_retries {
+            if retry > 0 {
+                let backoff = self.fetch_retry_policy.backoff(retry);
+                debug!(
+                    "Retrying to fetch artifact {} in {:?} (retry {} of {})",
+                    artifact_id, backoff, retry, self.fetch_retry_policy.max_retries
+                );
+                tokio::time::sleep(backoff).await;
+                errors.clear();
+            }
+            for peer_id in &peers {
+                match self
+                    .fetch_artifact_from_peer(peer_id, artifact_id, artifact_kind)
+                    .await
+                {
+                    Ok(()) => return Ok(*peer_id),
+                    Err(error) => errors.push(format!(
+                        "peer {}: {}",
+                        self.p2p_client.peer_aliases.display(peer_id),
+                        error
+                    )),
+                }
+            }
+        }
+
+        bail!(
+            "Failed to fetch artifact {}: {}",
+            artifact_id,
+            errors.join(", ")
+        )
+    }
+
+    /// Download the artifact in chunks of `chunk_size` bytes, spreading the
+    /// chunks over `peers`, and store it once all chunks are downloaded. The
+    /// first chunk tells the size of the artifact. A chunk that cannot be
+    /// downloaded from one peer is requested from the next one, and from all
+    /// peers again according to the fetch retry policy. The
+    /// reassembled artifact is verified against its transparency log by the
+    /// caller.
+    async fn fetch_artifact_in_chunks(
+        &self,
+        peers: &[PeerId],
+        artifact_id: &str,
+        chunk_size: u64,
+    ) -> Result<PeerId, anyhow::Error> {
+        let mut transfer =
+            self.transfer_progress
+                .start(artifact_id, TransferDirection::Download, None);
+        let first_chunk = ArtifactChunk {
+            offset: 0,
+            len: chunk_size,
+        };
+        let (peer_id, first_response) = fetch_chunk(
+            self.p2p_client.clone(),
+            peers,
+            0,
+            artifact_id,
+            first_chunk,
+            None,
+            &self.fetch_retry_policy,
+        )
+        .await?;
+        let artifact_size = first_response.artifact_size;
+        let mut artifact = first_response.artifact;
+        transfer.set_total_bytes(artifact_size);
+        transfer.add_bytes(
+            artifact.len() as u64,
+            Some(self.p2p_client.peer_aliases.display(&peer_id)),
+        );
+
+        let chunks = (1..)
+            .map(|index| ArtifactChunk {
+                offset: index * chunk_size,
+                len: chunk_size,
+            })
+            .take_while(|chunk| chunk.offset < artifact_size);
+        let mut responses = stream::iter(chunks.enumerate())
+            .map(|(index, chunk)| {
+                fetch_chunk(
+                    self.p2p_client.clone(),
+                    peers,
+                    (index + 1) % peers.len(),
+                    artifact_id,
+                    chunk,
+                    Some(artifact_size),
+                    &self.fetch_retry_policy,
+                )
+            })
+            .buffered(MAX_PARALLEL_CHUNK_DOWNLOADS);
+        while let Some(result) = responses.next().await {
+            let (chunk_peer_id, response) = result?;
+            transfer.add_bytes(
+                response.artifact.len() as u64,
+                Some(self.p2p_client.peer_aliases.display(&chunk_peer_id)),
+            );
+            artifact.extend(response.artifact);
+        }
+
+        debug!(
+            "Fetched artifact {} of {} bytes from {} peers",
+            artifact_id,
+            artifact_size,
+            peers.len()
+        );
+        self.put_fetched_artifact(artifact_id, artifact).await?;
+        Ok(peer_id)
+    }
+
+    async fn fetch_artifact_from_peer(
+        &mut self,
+        peer_id: &PeerId,
+        artifact_id: &str,
+        artifact_kind: ArtifactKind,
+    ) -> Result<(), anyhow::Error> {
+        let mut transfer =
+            self.transfer_progress
+                .start(artifact_id, TransferDirection::Download, None);
+        let artifact = tokio::time::timeout(
+            artifact_kind.fetch_timeout(),
+            self.p2p_client.request_artifact(peer_id, artifact_id),
+        )
+        .await
+        .with_context(|| {
+            format!(
+                "Request for artifact {} to peer {} timed out",
+                artifact_id,
+                self.p2p_client.peer_aliases.display(peer_id)
+            )
+        })??;
+        transfer.set_total_bytes(artifact.len() as u64);
+        transfer.add_bytes(
+            artifact.len() as u64,
+            Some(self.p2p_client.peer_aliases.display(peer_id)),
+        );
+        drop(transfer);
+
+        self.put_fetched_artifact(artifact_id, artifact).await
+    }
+
+    // An artifact that was fetched by a concurrent request in the meantime
+    // is already stored, it is verified like the fetched one when it is read.
+    async fn put_fetched_artifact(
+        &self,
+        artifact_id: &str,
+        artifact: Vec<u8>,
+    ) -> Result<(), anyhow::Error> {
+        match self
+            .put_artifact(artifact_id, io::Cursor::new(artifact))
+            .await
+        {
+            Err(error)
+                if error
+                    .downcast_ref::<io::Error>()
+                    .map_or(false, |e| e.kind() == ErrorKind::AlreadyExists) =>
+            {
+                debug!("Artifact {} was stored concurrently", artifact_id);
+                Ok(())
+            }
+            result => result,
+        }
+    }
+
+    async fn check_local_hash(&self, transparency_log: &TransparencyLog) -> CheckOutcome {
+        if !self
+            .artifact_storage
+            .contains_artifact(&transparency_log.artifact_id)
+            .await
+        {
+            return CheckOutcome::Skipped("not stored locally".to_owned());
+        }
+
+        match self
+            .artifact_storage
+            .read_artifact(&transparency_log.artifact_id)
+            .await
+        {
+            Ok(artifact) => check_hash(transparency_log, &artifact),
+            Err(error) => CheckOutcome::Failed(format!("unable to read local copy: {}", error)),
+        }
+    }
+
+    async fn check_peer_retrieval(
+        &mut self,
+        package_type: PackageType,
+        transparency_log: &TransparencyLog,
+        providers: HashSet<PeerId>,
+    ) -> CheckOutcome {
+        let remote_providers: Vec<PeerId> = providers
+            .into_iter()
+            .filter(|peer_id| *peer_id != self.p2p_client.local_peer_id)
+            .collect();
+        if remote_providers.is_empty() {
+            return CheckOutcome::Skipped("no remote providers".to_owned());
+        }
+
+        let artifact_kind =
+            ArtifactKind::of(package_type, &transparency_log.package_specific_artifact_id);
+        let mut errors = Vec::new();
+        for peer_id in remote_providers {
+            let peer = self.p2p_client.peer_aliases.display(&peer_id);
+            let result = tokio::time::timeout(
+                artifact_kind.fetch_timeout(),
+                self.p2p_client
+                    .request_artifact(&peer_id, &transparency_log.artifact_id),
+            )
+            .await;
+            match result {
+                Ok(Ok(artifact)) => match check_hash(transparency_log, &artifact) {
+                    CheckOutcome::Passed(_) => {
+                        return CheckOutcome::Passed(format!("retrieved from peer {}", peer))
+                    }
+                    outcome => {
+                        if let Some(alert_service) = &self.alert_service {
+                            alert_service
+                                .record_hash_mismatch(&transparency_log.artifact_id, &peer_id);
+                        }
+                        errors.push(format!("peer {}: {:?}", peer, outcome))
+                    }
+                },
+                Ok(Err(error)) => errors.push(format!("peer {}: {}", peer, error)),
+                Err(_) => errors.push(format!("peer {}: request timed out", peer)),
+            }
+        }
+
+        CheckOutcome::Failed(errors.join(", "))
+    }
+
+    async fn verify_artifact(
+        &mut self,
+        transparency_log: &TransparencyLog,
+        artifact: &[u8],
+    ) -> Result<(), TransparencyLogError> {
+        let mut sha256 = multihash::Sha2_256::default();
+        sha256.update(artifact);
+        let calculated_hash = hex::encode(sha256.finalize());
+
+        if transparency_log.artifact_hash == calculated_hash {
+            Ok(())
+        } else {
+            Err(TransparencyLogError::InvalidHash {
+                id: transparency_log.package_specific_artifact_id.clone(),
+                invalid_hash: calculated_hash,
+                actual_hash: transparency_log.artifact_hash.clone(),
+            })
+        }
+    }
+}
+
+/// Download a chunk of an artifact from the peer at index `first_peer` of
+/// `peers`, trying the other peers in turn when a peer fails and all peers
+/// again according to `retry_policy`. A chunk must have the expected length
+/// for the size of the artifact, which is only known after the first chunk.
+async fn fetch_chunk(
+    p2p_client: Client,
+    peers: &[PeerId],
+    first_peer: usize,
+    artifact_id: &str,
+    chunk: ArtifactChunk,
+    artifact_size: Option<u64>,
+    retry_policy: &FetchRetryPolicy,
+) -> Result<(PeerId, ArtifactResponse), anyhow::Error> {
+    let mut errors = Vec::new();
+    for retry in 0..=retry_policy.max_retries {
+        if retry > 0 {
+            let backoff = retry_policy.backoff(retry);
+            debug!(
+                "Retrying to fetch {} bytes at offset {} of artifact {} in {:?} (retry {} of {})",
+                chunk.len, chunk.offset, artifact_id, backoff, retry, retry_policy.max_retries
+            );
+            tokio::time::sleep(backoff).await;
+            errors.clear();
+        }
+        match fetch_chunk_from_peers(
+            p2p_client.clone(),
+            peers,
+            first_peer,
+            artifact_id,
+            chunk,
+            artifact_size,
+        )
+        .await
+        {
+            Ok(result) => return Ok(result),
+            Err(peer_errors) => errors = peer_errors,
+        }
+    }
+
+    bail!(
+        "Failed to fetch {} bytes at offset {} of artifact {}: {}",
+        chunk.len,
+        chunk.offset,
+        artifact_id,
+        errors.join(", ")
+    )
+}
+
+// Try each peer in turn, starting at index `first_peer`, until one responds
+// with the chunk. Every request times out according to the expected length of
+// the chunk and the measured throughput of the peer. Returns the error of
+// every peer when none did.
+async fn fetch_chunk_from_peers(
+    mut p2p_client: Client,
+    peers: &[PeerId],
+    first_peer: usize,
+    artifact_id: &str,
+    chunk: ArtifactChunk,
+    artifact_size: Option<u64>,
+) -> Result<(PeerId, ArtifactResponse), Vec<String>> {
+    let expected_bytes = artifact_size.map_or(chunk.len, |size| chunk.len_in(size));
+    let mut errors = Vec::new();
+    for peer_id in peers.iter().cycle().skip(first_peer).take(peers.len()) {
+        let peer = p2p_client.peer_aliases.display(peer_id);
+        let timeout = p2p_client
+            .peer_throughput
+            .transfer_timeout(peer_id, expected_bytes);
+        let result = tokio::time::timeout(
+            timeout,
+            p2p_client.request_artifact_chunk(peer_id, artifact_id, chunk),
+        )
+        .await;
+        match result {
+            Ok(Ok(response)) => {
+                let expected_size = artifact_size.unwrap_or(response.artifact_size);
+                if response.artifact_size == expected_size
+                    && response.artifact.len() as u64 == chunk.len_in(expected_size)
+                {
+                    return Ok((*peer_id, response));
+                }
+                errors.push(format!(
+                    "peer {}: unexpected chunk of {} bytes of an artifact of {} bytes",
+                    peer,
+                    response.artifact.len(),
+                    response.artifact_size
+                ));
+            }
+            Ok(Err(error)) => errors.push(format!("peer {}: {}", peer, error)),
+            Err(_) => errors.push(format!(
+                "peer {}: request timed out after {:?}",
+                peer, timeout
+            )),
+        }
+    }
+
+    Err(errors)
+}
+
+fn check_hash(transparency_log: &TransparencyLog, artifact: &[u8]) -> CheckOutcome {
+    let mut sha256 = multihash::Sha2_256::default();
+    sha256.update(artifact);
+    let calculated_hash = hex::encode(sha256.finalize());
+
+    if transparency_log.artifact_hash == calculated_hash {
+        CheckOutcome::Passed(format!("sha256 {}", calculated_hash))
+    } else {
+        CheckOutcome::Failed(format!(
+            "sha256 {} does not match {}",
+            calculated_hash, transparency_log.artifact_hash
+        ))
+    }
+}
+
+fn check_dht_availability(providers: &HashSet<PeerId>) -> CheckOutcome {
+    if providers.is_empty() {
+        CheckOutcome::Failed("no providers found".to_owned())
+    } else {
+        CheckOutcome::Passed(format!("{} provider(s) found", providers.len()))
+    }
+}
+
+fn check_provenance(
+    transparency_log: &TransparencyLog,
+    block: Option<&Block>,
+    authorized_nodes: &[PeerId],
+) -> CheckOutcome {
+    let block = match block {
+        Some(block) => block,
+        None => {
+            return CheckOutcome::Failed("transparency log not found on the blockchain".to_owned())
+        }
+    };
+
+    if !block.verify() {
+        return CheckOutcome::Failed(format!(
+            "invalid signature of block {}",
+            block.header.ordinal
+        ));
+    }
+
+    if block.signer() != Some(block.header.committer) {
+        return CheckOutcome::Failed(format!(
+            "block {} is not signed by its committer",
+            block.header.ordinal
+        ));
+    }
+
+    match authorized_nodes
+        .iter()
+        .find(|peer_id| Address::from(**peer_id) == block.header.committer)
+    {
+        Some(peer_id) => CheckOutcome::Passed(format!(
+            "{} by authorized node {} in block {}",
+            transparency_log.provenance(),
+            peer_id,
+            block.header.ordinal
+        )),
+        None => CheckOutcome::Failed(format!(
+            "block {} is not committed by an authorized node",
+            block.header.ordinal
+        )),
+    }
+}
+
+#[cfg(test)]
+#[cfg(not(tarpaulin_include))]
+mod tests {
+    use super::*;
+    use crate::alert_service::service::AnomalyKind;
+    use crate::artifact_service::hooks::{HookAction, HookConfig};
+    use crate::artifact_service::tag_policy::TagImmutabilityRule;
+    use crate::blockchain_service::event::BlockchainEvent;
+    use crate::build_service::event::BuildEvent;
+    use crate::build_service::model::BuildResultArtifact;
+    use crate::network::client::command::Command;
+    use crate::network::idle_metric_protocol::PeerMetrics;
+    use crate::network::peer_maintenance::MaintenanceAnnouncement;
+    use crate::test_support::{
+        fake_transparency_log_service, in_memory_artifact_storage, FakeNetwork,
+    };
+    use crate::transparency_log::log::derive_artifact_id;
+    use crate::transparency_log::log::Provenance;
+    use crate::util::test_util;
+    use libp2p::identity::ed25519::Keypair;
+    use libp2p::identity::PublicKey;
+    use pyrsia_blockchain_network::crypto::hash_algorithm::HashDigest;
+    use pyrsia_blockchain_network::error::BlockchainError;
+    use pyrsia_blockchain_network::structures::transaction::{Transaction, TransactionType};
+    use sha2::{Digest, Sha256};
+    use std::collections::{BTreeMap, HashSet};
+    use std::env;
+    use std::fs::File;
+    use std::path::PathBuf;
+    use std::time::Duration;
+    use tokio::task;
+
+    const VALID_ARTIFACT_HASH: [u8; 32] = [
+        0x86, 0x5c, 0x8d, 0x98, 0x8b, 0xe4, 0x66, 0x9f, 0x3e, 0x48, 0xf7, 0x3b, 0x98, 0xf9, 0xbc,
+        0x25, 0x7, 0xbe, 0x2, 0x46, 0xea, 0x35, 0xe0, 0x9, 0x8c, 0xf6, 0x5, 0x4d, 0x36, 0x44, 0xc1,
+        0x4f,
+    ];
+
+    #[tokio::test]
+    async fn test_put_and_get_artifact() {
+        let tmp_dir = test_util::tests::setup();
+
+        let (mut artifact_service, mut blockchain_event_receiver, _, mut p2p_command_receiver) =
+            test_util::tests::create_artifact_service(&tmp_dir);
+
+        tokio::spawn(async move {
+            loop {
+                match p2p_command_receiver.recv().await {
+                    Some(Command::ListPeers { sender, .. }) => {
+                        let _ = sender.send(HashSet::new());
+                    }
+                    _ => panic!("Command must match Command::ListPeers"),
+                }
+            }
+        });
+
+        tokio::spawn(async move {
+            loop {
+                match blockchain_event_receiver.recv().await {
+                    Some(BlockchainEvent::AddBlock { sender, .. }) => {
+                        let _ = sender.send(Ok(()));
+                    }
+                    _ => panic!("BlockchainEvent must match BlockchainEvent::AddBlock"),
+                }
+            }
+        });
+
+        let package_type = PackageType::Docker;
+        let package_specific_id = "package_specific_id";
+        let package_specific_artifact_id = "package_specific_artifact_id";
+        let transparency_log_tuple = artifact_service
+            .transparency_log_service
+            .add_artifact(AddArtifactRequest {
+                package_type,
+                package_specific_id: package_specific_id.to_owned(),
+                num_artifacts: 8,
+                package_specific_artifact_id: package_specific_artifact_id.to_owned(),
+                artifact_hash: hex::encode(VALID_ARTIFACT_HASH),
+            })
+            .await
+            .unwrap();
+        let transparency_log = transparency_log_tuple.0;
+
+        //put the artifact
+        artifact_service
+            .put_artifact(&transparency_log.artifact_id, get_file_reader().unwrap())
+            .await
+            .context("Error from put_artifact")
+            .unwrap();
+
+        // pull artifact
+        let future = {
+            artifact_service
+                .get_artifact(package_type, package_specific_artifact_id)
+                .await
+                .context("Error from get_artifact")
+        };
+        let file = task::spawn_blocking(|| future).await.unwrap().unwrap();
+
+        //validate pulled artifact with the actual data
+        let mut s = String::new();
+        get_file_reader().unwrap().read_to_string(&mut s).unwrap();
+
+        let s1 = match str::from_utf8(file.as_slice()) {
+            Ok(v) => v,
+            Err(e) => panic!("Invalid UTF-8 sequence: {}", e),
+        };
+        assert_eq!(s, s1);
+
+        test_util::tests::teardown(tmp_dir);
+    }
+
+    #[tokio::test]
+    async fn test_handle_block_added_with_multiple_payloads() {
+        let tmp_dir = test_util::tests::setup();
+
+        let (mut artifact_service, ..) = test_util::tests::create_artifact_service(&tmp_dir);
+
+        let transparency_log = |package_specific_artifact_id: &str| {
+            TransparencyLog::from(AddArtifactRequest {
+                package_type: PackageType::Docker,
+                package_specific_id: "alpine:3.16".to_owned(),
+                num_artifacts: 2,
+                package_specific_artifact_id: package_specific_artifact_id.to_owned(),
+                artifact_hash: hex::encode(Sha256::digest(package_specific_artifact_id)),
+            })
+        };
+        let manifest_log = transparency_log("alpine:3.16");
+        let blob_log = transparency_log("alpine@sha256:1234");
+        let batch_log = transparency_log("alpine@sha256:5678");
+
+        let keypair = Keypair::generate();
+        let committer = PublicKey::Ed25519(keypair.public()).to_peer_id();
+        let block = |ordinal, payloads: Vec<Vec<u8>>| {
+            let transactions = payloads
+                .into_iter()
+                .map(|payload| {
+                    Transaction::new(
+                        TransactionType::Create,
+                        Address::from(committer),
+                        payload,
+                        &keypair,
+                    )
+                })
+                .collect();
+            Block::new(HashDigest::new(b""), ordinal, transactions, &keypair)
+        };
+
+        let added_block = block(
+            1,
+            vec![
+                serde_json::to_vec(&manifest_log).unwrap(),
+                serde_json::to_vec(&vec![blob_log.clone(), batch_log.clone()]).unwrap(),
+            ],
+        );
+        artifact_service
+            .handle_block_added(&added_block)
+            .await
+            .unwrap();
+        for transparency_log in [&manifest_log, &blob_log, &batch_log] {
+            assert!(artifact_service
+                .transparency_log_service
+                .find_transparency_log(&transparency_log.id)
+                .is_ok());
+            let block_metadata = artifact_service
+                .transparency_log_service
+                .find_block_metadata(&transparency_log.id)
+                .unwrap()
+                .unwrap();
+            assert_eq!(block_metadata.ordinal, 1);
+            assert_eq!(block_metadata.committer, Some(committer.to_string()));
+            assert_eq!(block_metadata.timestamp, added_block.header.timestamp);
+        }
+
+        let other_log = transparency_log("alpine:3.17");
+        assert!(artifact_service
+            .handle_block_added(&block(
+                2,
+                vec![
+                    serde_json::to_vec(&other_log).unwrap(),
+                    b"not a transparency log".to_vec(),
+                ]
+            ))
+            .await
+            .is_err());
+        assert!(artifact_service
+            .transparency_log_service
+            .find_transparency_log(&other_log.id)
+            .is_err());
+
+        test_util::tests::teardown(tmp_dir);
+    }
+
+    #[tokio::test]
+    async fn test_put_and_list_artifact() {
+        let tmp_dir = test_util::tests::setup();
+
+        let (artifact_service, mut blockchain_event_receiver, _, mut p2p_command_receiver) =
+            test_util::tests::create_artifact_service(&tmp_dir);
+
+        tokio::spawn(async move {
+            loop {
+                match p2p_command_receiver.recv().await {
+                    Some(Command::ListPeers { sender, .. }) => {
+                        let _ = sender.send(HashSet::new());
+                    }
+                    Some(Command::Provide { sender, .. }) => {
+                        let _ = sender.send(());
+                    }
+                    _ => panic!("Command must match Command::ListPeers or Command::Provide"),
+                }
+            }
+        });
+
+        tokio::spawn(async move {
+            loop {
+                match blockchain_event_receiver.recv().await {
+                    Some(BlockchainEvent::AddBlock { sender, .. }) => {
+                        let _ = sender.send(Ok(()));
+                    }
+                    _ => panic!("BlockchainEvent must match BlockchainEvent::AddBlock"),
+                }
+            }
+        });
+
+        let package_type = PackageType::Docker;
+        let package_specific_id = "package_specific_id";
+        let package_specific_artifact_id = "package_specific_artifact_id";
+        let transparency_log_tuple = artifact_service
+            .transparency_log_service
+            .add_artifact(AddArtifactRequest {
+                package_type,
+                package_specific_id: package_specific_id.to_owned(),
+                num_artifacts: 8,
+                package_specific_artifact_id: package_specific_artifact_id.to_owned(),
+                artifact_hash: hex::encode(VALID_ARTIFACT_HASH),
+            })
+            .await
+            .unwrap();
+
+        let transparency_log = transparency_log_tuple.0;
+        //put the artifact
+        artifact_service
+            .put_artifact(&transparency_log.artifact_id, get_file_reader().unwrap())
+            .await
+            .context("Error from put_artifact")
+            .unwrap();
+
+        // provide artifacts
+        let future = {
+            artifact_service
+                .provide_local_artifacts()
+                .await
+                .context("Error from provide_local_artifacts")
+        };
+        let files = task::spawn_blocking(|| future).await.unwrap();
+        assert!(files.is_ok());
+        assert_eq!(
+            artifact_service.provide_progress(),
+            ProvideProgress {
+                provided: 1,
+                failed: 0,
+                finished: true,
+            }
+        );
+
+        test_util::tests::teardown(tmp_dir);
+    }
+
+    #[tokio::test]
+    async fn test_provide_local_artifacts_provides_popular_artifacts_first() {
+        let tmp_dir = test_util::tests::setup();
+
+        let (artifact_service, _, _, mut p2p_command_receiver) =
+            test_util::tests::create_artifact_service(&tmp_dir);
+        let mut artifact_service = artifact_service.with_provide_schedule(ProvideSchedule {
+            batch_size: 2,
+            max_rate: 0,
+            max_jitter: Duration::ZERO,
+            reprovide_interval: None,
+        });
+
+        let (provided_sender, mut provided_receiver) = tokio::sync::mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            loop {
+                match p2p_command_receiver.recv().await {
+                    Some(Command::Provide {
+                        artifact_id,
+                        sender,
+                    }) => {
+                        let _ = provided_sender.send(artifact_id);
+                        let _ = sender.send(());
+                    }
+                    _ => panic!("Command must match Command::Provide"),
+                }
+            }
+        });
+
+        let artifact_ids: Vec<String> = (0..5).map(|i| format!("artifact_{}", i)).collect();
+        for artifact_id in artifact_ids.iter() {
+            artifact_service
+                .put_artifact(artifact_id, get_file_reader().unwrap())
+                .await
+                .unwrap();
+        }
+        artifact_service
+            .get_artifact_locally("artifact_3")
+            .await
+            .unwrap();
+
+        artifact_service.provide_local_artifacts().await.unwrap();
+
+        let mut provided_artifact_ids = Vec::new();
+        while let Ok(artifact_id) = provided_receiver.try_recv() {
+            provided_artifact_ids.push(artifact_id);
+        }
+        assert_eq!(provided_artifact_ids.len(), 5);
+        assert_eq!(provided_artifact_ids[0], "artifact_3");
+        assert_eq!(
+            provided_artifact_ids.iter().collect::<HashSet<_>>(),
+            artifact_ids.iter().collect::<HashSet<_>>()
+        );
+
+        test_util::tests::teardown(tmp_dir);
+    }
+
+    #[tokio::test]
+    async fn test_provide_local_artifacts_provides_pinned_artifacts_first() {
+        let tmp_dir = test_util::tests::setup();
+
+        // the access statistics of an earlier run of the node
+        let access_stats_path = tmp_dir.join("access_stats.json");
+        let access_stats = AccessStats::new(&access_stats_path).unwrap();
+        access_stats.record_pull(PackageType::Docker, "pulled");
+        access_stats.flush().unwrap();
+
+        let (artifact_service, mut blockchain_event_receiver, _, mut p2p_command_receiver) =
+            test_util::tests::create_artifact_service(&tmp_dir);
+        let artifact_service = artifact_service
+            .with_provide_schedule(ProvideSchedule {
+                batch_size: 2,
+                max_rate: 0,
+                max_jitter: Duration::ZERO,
+                reprovide_interval: None,
+            })
+            .with_pinned_packages(vec![(PackageType::Docker, "pinned".to_owned())])
+            .with_access_stats(AccessStats::new(&access_stats_path).unwrap());
+
+        let (provided_sender, mut provided_receiver) = tokio::sync::mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            while let Some(command) = p2p_command_receiver.recv().await {
+                match command {
+                    Command::Provide {
+                        artifact_id,
+                        sender,
+                    } => {
+                        let _ = provided_sender.send(artifact_id);
+                        let _ = sender.send(());
+                    }
+                    _ => panic!("Command must match Command::Provide"),
+                }
+            }
+        });
+        tokio::spawn(async move {
+            while let Some(event) = blockchain_event_receiver.recv().await {
+                match event {
+                    BlockchainEvent::AddBlock { sender, .. } => {
+                        let _ = sender.send(Ok(()));
+                    }
+                    _ => panic!("BlockchainEvent must match BlockchainEvent::AddBlock"),
+                }
+            }
+        });
+
+        for i in 0..3 {
+            artifact_service
+                .put_artifact(&format!("artifact_{}", i), get_file_reader().unwrap())
+                .await
+                .unwrap();
+        }
+        let mut package_artifact_ids = vec![];
+        for (package_specific_id, artifact_hash) in [("pulled", [1u8; 32]), ("pinned", [2u8; 32])] {
+            let transparency_log = artifact_service
+                .transparency_log_service
+                .add_artifact(AddArtifactRequest {
+                    package_type: PackageType::Docker,
+                    package_specific_id: package_specific_id.to_owned(),
+                    num_artifacts: 1,
+                    package_specific_artifact_id: format!("{}@artifact", package_specific_id),
+                    artifact_hash: hex::encode(artifact_hash),
+                })
+                .await
+                .unwrap()
+                .0;
+            artifact_service
+                .put_artifact(&transparency_log.artifact_id, get_file_reader().unwrap())
+                .await
+                .unwrap();
+            package_artifact_ids.push(transparency_log.artifact_id);
+        }
+
+        artifact_service.provide_local_artifacts().await.unwrap();
+
+        let mut provided_artifact_ids = Vec::new();
+        while let Ok(artifact_id) = provided_receiver.try_recv() {
+            provided_artifact_ids.push(artifact_id);
+        }
+        assert_eq!(provided_artifact_ids.len(), 5);
+        // the first batch has the pinned artifact, then the pulled artifact
+        assert_eq!(
+            provided_artifact_ids[..2],
+            [
+                package_artifact_ids[1].clone(),
+                package_artifact_ids[0].clone()
+            ]
+        );
+
+        test_util::tests::teardown(tmp_dir);
+    }
+
+    #[tokio::test]
+    async fn test_provide_local_artifacts_batch_boundaries() {
+        for (num_artifacts, batch_size, num_popular) in
+            [(4, 2, 0), (5, 2, 0), (5, 2, 3), (3, 0, 0), (2, 8, 1)]
+        {
+            let tmp_dir = test_util::tests::setup();
+
+            let (artifact_service, _, _, mut p2p_command_receiver) =
+                test_util::tests::create_artifact_service(&tmp_dir);
+            let mut artifact_service = artifact_service.with_provide_schedule(ProvideSchedule {
+                batch_size,
+                max_rate: 0,
+                max_jitter: Duration::ZERO,
+                reprovide_interval: None,
+            });
+
+            let (provided_sender, mut provided_receiver) = tokio::sync::mpsc::unbounded_channel();
+            tokio::spawn(async move {
+                while let Some(command) = p2p_command_receiver.recv().await {
+                    match command {
+                        Command::Provide {
+                            artifact_id,
+                            sender,
+                        } => {
+                            let _ = provided_sender.send(artifact_id);
+                            let _ = sender.send(());
+                        }
+                        _ => panic!("Command must match Command::Provide"),
+                    }
+                }
+            });
+
+            let artifact_ids: Vec<String> = (0..num_artifacts)
+                .map(|i| format!("artifact_{}", i))
+                .collect();
+            for artifact_id in artifact_ids.iter() {
+                artifact_service
+                    .put_artifact(artifact_id, get_file_reader().unwrap())
+                    .await
+                    .unwrap();
+            }
+            for artifact_id in artifact_ids.iter().take(num_popular) {
+                artifact_service
+                    .get_artifact_locally(artifact_id)
+                    .await
+                    .unwrap();
+            }
+
+            artifact_service.provide_local_artifacts().await.unwrap();
+
+            let mut provided_artifact_ids = Vec::new();
+            while let Ok(artifact_id) = provided_receiver.try_recv() {
+                provided_artifact_ids.push(artifact_id);
+            }
+            // every artifact is provided exactly once, whichever batch it is in
+            assert_eq!(
+                provided_artifact_ids.len(),
+                num_artifacts,
+                "{} artifacts in batches of {}",
+                num_artifacts,
+                batch_size
+            );
+            assert_eq!(
+                provided_artifact_ids.iter().collect::<HashSet<_>>(),
+                artifact_ids.iter().collect::<HashSet<_>>()
+            );
+            assert_eq!(
+                artifact_service.provide_progress(),
+                ProvideProgress {
+                    provided: num_artifacts,
+                    failed: 0,
+                    finished: true,
+                }
+            );
+
+            test_util::tests::teardown(tmp_dir);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_provide_local_artifacts_reports_progress() {
+        let tmp_dir = test_util::tests::setup();
+
+        let (artifact_service, _, _, mut p2p_command_receiver) =
+            test_util::tests::create_artifact_service(&tmp_dir);
+        let artifact_service = artifact_service.with_provide_schedule(ProvideSchedule {
+            batch_size: 2,
+            max_rate: 0,
+            max_jitter: Duration::ZERO,
+            reprovide_interval: None,
+        });
+
+        let (progress_sender, mut progress_receiver) = tokio::sync::mpsc::unbounded_channel();
+        let observed_artifact_service = artifact_service.clone();
+        tokio::spawn(async move {
+            while let Some(command) = p2p_command_receiver.recv().await {
+                match command {
+                    Command::Provide {
+                        artifact_id,
+                        sender,
+                    } => {
+                        // the progress before the artifact is provided
+                        let _ = progress_sender.send(observed_artifact_service.provide_progress());
+                        // a dropped sender fails to provide the artifact
+                        if artifact_id != "artifact_1" {
+                            let _ = sender.send(());
+                        }
+                    }
+                    _ => panic!("Command must match Command::Provide"),
+                }
+            }
+        });
+
+        for i in 0..3 {
+            artifact_service
+                .put_artifact(&format!("artifact_{}", i), get_file_reader().unwrap())
+                .await
+                .unwrap();
+        }
+
+        artifact_service.provide_local_artifacts().await.unwrap();
+
+        let mut progress = Vec::new();
+        while let Ok(provide_progress) = progress_receiver.try_recv() {
+            progress.push(provide_progress);
+        }
+        assert_eq!(progress.len(), 3);
+        for (i, provide_progress) in progress.iter().enumerate() {
+            assert_eq!(provide_progress.provided + provide_progress.failed, i);
+            assert!(!provide_progress.finished);
+        }
+        assert_eq!(
+            artifact_service.provide_progress(),
+            ProvideProgress {
+                provided: 2,
+                failed: 1,
+                finished: true,
+            }
+        );
+
+        // a new run starts counting from scratch
+        artifact_service.provide_local_artifacts().await.unwrap();
+        assert_eq!(
+            artifact_service.provide_progress(),
+            ProvideProgress {
+                provided: 2,
+                failed: 1,
+                finished: true,
+            }
+        );
+
+        test_util::tests::teardown(tmp_dir);
+    }
+
+    #[tokio::test]
+    async fn test_provide_local_artifacts_without_artifacts() {
+        let tmp_dir = test_util::tests::setup();
+
+        let (artifact_service, _, _, _p2p_command_receiver) =
+            test_util::tests::create_artifact_service(&tmp_dir);
+
+        artifact_service.provide_local_artifacts().await.unwrap();
+        assert_eq!(
+            artifact_service.provide_progress(),
+            ProvideProgress {
+                provided: 0,
+                failed: 0,
+                finished: true,
+            }
+        );
+
+        test_util::tests::teardown(tmp_dir);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_get_from_peers() {
+        let tmp_dir = test_util::tests::setup();
+
+        let (p2p_client, mut p2p_command_receiver) = test_util::tests::create_p2p_client();
+        let (mut artifact_service, mut blockchain_event_receiver, _) =
+            test_util::tests::create_artifact_service_with_p2p_client(&tmp_dir, p2p_client.clone());
+
+        tokio::spawn(async move {
+            loop {
+                match p2p_command_receiver.recv().await {
+                    Some(Command::ListPeers { sender, .. }) => {
+                        let _ = sender.send(HashSet::new());
+                    },
+                    Some(Command::ListProviders { sender, .. }) => {
+                        let mut set = HashSet::new();
+                        set.insert(p2p_client.local_peer_id);
+                        let _ = sender.send(set);
+                    },
+                    Some(Command::RequestIdleMetric { sender, .. }) => {
+                        let _ = sender.send(Ok(PeerMetrics {
+                            idle_metric: (0.1_f64).to_le_bytes()
+                        }));
+                    },
+                    Some(Command::RequestArtifact { sender, .. }) => {
+                        let _ = sender.send(Ok(ArtifactResponse::complete(b"SAMPLE_DATA".to_vec())));
+                    },
+                    _ => panic!("Command must match Command::ListPeers, Command::ListProviders, Command::RequestIdleMetric, Command::RequestArtifact"),
+                }
+            }
+        });
+
+        tokio::spawn(async move {
+            loop {
+                match blockchain_event_receiver.recv().await {
+                    Some(BlockchainEvent::AddBlock { sender, .. }) => {
+                        let _ = sender.send(Ok(()));
+                    }
+                    _ => panic!("BlockchainEvent must match BlockchainEvent::AddBlock"),
+                }
+            }
+        });
+
+        let mut hasher = Sha256::new();
+        hasher.update(b"SAMPLE_DATA");
+        let random_hash = hex::encode(hasher.finalize());
+
+        let package_type = PackageType::Docker;
+        let package_specific_id = "package_specific_id";
+        let package_specific_artifact_id = "package_specific_artifact_id";
+        artifact_service
+            .transparency_log_service
+            .add_artifact(AddArtifactRequest {
+                package_type,
+                package_specific_id: package_specific_id.to_owned(),
+                num_artifacts: 8,
+                package_specific_artifact_id: package_specific_artifact_id.to_owned(),
+                artifact_hash: random_hash.clone(),
+            })
+            .await
+            .unwrap();
+
+        let future = {
+            artifact_service
+                .get_artifact(package_type, package_specific_artifact_id)
+                .await
+        };
+        let result = task::spawn_blocking(|| future).await.unwrap();
+        assert!(result.is_ok());
+
+        test_util::tests::teardown(tmp_dir);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_artifact_in_chunks() {
+        let tmp_dir = test_util::tests::setup();
+
+        let (p2p_client, mut p2p_command_receiver) = test_util::tests::create_p2p_client();
+        let (artifact_service, _, _) =
+            test_util::tests::create_artifact_service_with_p2p_client(&tmp_dir, p2p_client);
+
+        let artifact = b"SAMPLE_DATA_IN_SIX_CHUNKS".to_vec();
+        let healthy_peer_id = PeerId::random();
+        let failing_peer_id = PeerId::random();
+        let served_artifact = artifact.clone();
+        let served_chunks = Arc::new(Mutex::new(Vec::new()));
+        let recorded_chunks = served_chunks.clone();
+        tokio::spawn(async move {
+            while let Some(command) = p2p_command_receiver.recv().await {
+                match command {
+                    Command::RequestArtifact {
+                        peer,
+                        chunk: Some(chunk),
+                        sender,
+                        ..
+                    } => {
+                        recorded_chunks.lock().unwrap().push((peer, chunk.offset));
+                        let result = if peer == healthy_peer_id {
+                            let start = chunk.offset as usize;
+                            let end = start + chunk.len_in(served_artifact.len() as u64) as usize;
+                            Ok(ArtifactResponse {
+                                artifact: served_artifact[start..end].to_vec(),
+                                artifact_size: served_artifact.len() as u64,
+                            })
+                        } else {
+                            Err(anyhow::anyhow!("connection closed"))
+                        };
+                        let _ = sender.send(result);
+                    }
+                    _ => panic!("Command must match Command::RequestArtifact with a chunk"),
+                }
+            }
+        });
+
+        let mut transfer_progress = artifact_service.subscribe_transfer_progress();
+        let peer_id = artifact_service
+            .fetch_artifact_in_chunks(&[healthy_peer_id, failing_peer_id], "artifact_id", 5)
+            .await
+            .unwrap();
+
+        assert_eq!(peer_id, healthy_peer_id);
+        let downloads: Vec<TransferProgress> =
+            std::iter::from_fn(|| transfer_progress.try_recv().ok())
+                .filter(|progress| progress.direction == TransferDirection::Download)
+                .collect();
+        let last_download = downloads.last().unwrap();
+        assert!(last_download.finished);
+        assert_eq!(last_download.bytes_transferred, artifact.len() as u64);
+        assert_eq!(last_download.total_bytes, Some(artifact.len() as u64));
+        assert_eq!(
+            last_download.peer,
+            Some(
+                artifact_service
+                    .p2p_client
+                    .peer_aliases
+                    .display(&healthy_peer_id)
+            )
+        );
+        assert!(artifact_service.active_transfers().is_empty());
+        let mut stored_artifact = Vec::new();
+        artifact_service
+            .artifact_storage
+            .pull_artifact("artifact_id")
+            .await
+            .unwrap()
+            .read_to_end(&mut stored_artifact)
+            .unwrap();
+        assert_eq!(stored_artifact, artifact);
+
+        // every other chunk is requested from the failing peer first
+        let served_chunks = served_chunks.lock().unwrap();
+        let offsets = |peer_id| {
+            served_chunks
+                .iter()
+                .filter(|(peer, _)| *peer == peer_id)
+                .map(|(_, offset)| *offset)
+                .sorted()
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(offsets(healthy_peer_id), vec![0, 5, 10, 15, 20]);
+        assert_eq!(offsets(failing_peer_id), vec![5, 15]);
+
+        test_util::tests::teardown(tmp_dir);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_artifact_in_chunks_retries_with_backoff() {
+        let tmp_dir = test_util::tests::setup();
+
+        let (p2p_client, mut p2p_command_receiver) = test_util::tests::create_p2p_client();
+        let (artifact_service, _, _) =
+            test_util::tests::create_artifact_service_with_p2p_client(&tmp_dir, p2p_client);
+        let artifact_service = artifact_service.with_fetch_retry_policy(FetchRetryPolicy {
+            max_retries: 2,
+            initial_backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(10),
+        });
+
+        let artifact = b"SAMPLE_DATA".to_vec();
+        let peer_id = PeerId::random();
+        let served_artifact = artifact.clone();
+        let request_count = Arc::new(Mutex::new(0));
+        let recorded_request_count = request_count.clone();
+        tokio::spawn(async move {
+            while let Some(command) = p2p_command_receiver.recv().await {
+                match command {
+                    Command::RequestArtifact {
+                        artifact_id,
+                        sender,
+                        ..
+                    } => {
+                        let mut request_count = recorded_request_count.lock().unwrap();
+                        *request_count += 1;
+                        // the peer drops the first two requests for the artifact
+                        let result = if artifact_id == "artifact_id" && *request_count > 2 {
+                            Ok(ArtifactResponse::complete(served_artifact.clone()))
+                        } else {
+                            Err(anyhow::anyhow!("connection closed"))
+                        };
+                        let _ = sender.send(result);
+                    }
+                    _ => panic!("Command must match Command::RequestArtifact"),
+                }
+            }
+        });
+
+        let fetched_peer_id = artifact_service
+            .fetch_artifact_in_chunks(&[peer_id], "artifact_id", 64)
+            .await
+            .unwrap();
+
+        assert_eq!(fetched_peer_id, peer_id);
+        assert_eq!(*request_count.lock().unwrap(), 3);
+        let mut stored_artifact = Vec::new();
+        artifact_service
+            .artifact_storage
+            .pull_artifact("artifact_id")
+            .await
+            .unwrap()
+            .read_to_end(&mut stored_artifact)
+            .unwrap();
+        assert_eq!(stored_artifact, artifact);
+
+        // all retries are used up before giving up
+        *request_count.lock().unwrap() = 0;
+        assert!(artifact_service
+            .fetch_artifact_in_chunks(&[peer_id], "unknown_artifact_id", 64)
+            .await
+            .is_err());
+        assert_eq!(*request_count.lock().unwrap(), 3);
+
+        test_util::tests::teardown(tmp_dir);
+    }
+
+    #[tokio::test]
+    async fn test_get_artifact_chunk_locally() {
+        let tmp_dir = test_util::tests::setup();
+
+        let (artifact_service, ..) = test_util::tests::create_artifact_service(&tmp_dir);
+        artifact_service
+            .put_artifact("artifact_id", b"SAMPLE_DATA".as_slice())
+            .await
+            .unwrap();
+
+        let chunk = |offset, len| {
+            artifact_service
+                .get_artifact_chunk_locally("artifact_id", ArtifactChunk { offset, len })
+        };
+        assert_eq!(chunk(0, 6).await.unwrap(), (b"SAMPLE".to_vec(), 11));
+        assert_eq!(chunk(7, 6).await.unwrap(), (b"DATA".to_vec(), 11));
+        assert!(chunk(12, 6).await.is_err());
+
+        test_util::tests::teardown(tmp_dir);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_get_from_peers_with_no_providers() {
+        let tmp_dir = test_util::tests::setup();
+
+        let (mut artifact_service, _, _, mut p2p_command_receiver) =
+            test_util::tests::create_artifact_service(&tmp_dir);
+
+        tokio::spawn(async move {
+            tokio::select! {
+                command = p2p_command_receiver.recv() => {
+                    match command {
+                        Some(Command::ListProviders { sender, .. }) => {
+                            let _ = sender.send(Default::default());
+                        },
+                        _ => panic!("Command must match Command::ListProviders"),
+                    }
+                }
+            }
+        });
+
+        let mut hasher = Sha256::new();
+        hasher.update(b"SAMPLE_DATA");
+        let hash_bytes = hasher.finalize();
+        let artifact_id = hex::encode(hash_bytes);
+
+        let future = {
+            artifact_service
+                .get_artifact_from_peers(&artifact_id, ArtifactKind::Blob)
+                .await
+        };
+        let result = task::spawn_blocking(|| future).await.unwrap();
+        assert!(result.is_err());
+
+        test_util::tests::teardown(tmp_dir);
+    }
+
+    #[tokio::test]
+    async fn test_verify_artifact_succeeds_when_hashes_same() {
+        let tmp_dir = test_util::tests::setup();
+
+        let (mut artifact_service, mut blockchain_event_receiver, _, mut p2p_command_receiver) =
+            test_util::tests::create_artifact_service(&tmp_dir);
+
+        tokio::spawn(async move {
+            loop {
+                match p2p_command_receiver.recv().await {
+                    Some(Command::ListPeers { sender, .. }) => {
+                        let _ = sender.send(HashSet::new());
+                    }
+                    _ => panic!("Command must match Command::ListPeers"),
+                }
+            }
+        });
+
+        tokio::spawn(async move {
+            loop {
+                match blockchain_event_receiver.recv().await {
+                    Some(BlockchainEvent::AddBlock { sender, .. }) => {
+                        let _ = sender.send(Ok(()));
+                    }
+                    _ => panic!("BlockchainEvent must match BlockchainEvent::AddBlock"),
+                }
+            }
+        });
+
+        let mut hasher1 = Sha256::new();
+        hasher1.update(b"SAMPLE_DATA");
+        let random_hash = hex::encode(hasher1.finalize());
+
+        let package_type = PackageType::Docker;
+        let package_specific_id = "package_specific_id";
+        let package_specific_artifact_id = "package_specific_artifact_id";
+        artifact_service
+            .transparency_log_service
+            .add_artifact(AddArtifactRequest {
+                package_type,
+                package_specific_id: package_specific_id.to_owned(),
+                num_artifacts: 8,
+                package_specific_artifact_id: package_specific_artifact_id.to_owned(),
+                artifact_hash: random_hash,
+            })
+            .await
+            .unwrap();
+
+        let transparency_log = artifact_service
+            .transparency_log_service
+            .get_artifact(&package_type, package_specific_artifact_id)
+            .unwrap();
+
+        let result = artifact_service
+            .verify_artifact(&transparency_log, b"SAMPLE_DATA")
+            .await;
+        assert!(result.is_ok());
+
+        test_util::tests::teardown(tmp_dir);
+    }
+
+    #[tokio::test]
+    async fn test_verify_artifact_fails_when_hashes_differ() {
+        let tmp_dir = test_util::tests::setup();
+
+        let (mut artifact_service, mut blockchain_event_receiver, _, mut p2p_command_receiver) =
+            test_util::tests::create_artifact_service(&tmp_dir);
+
+        tokio::spawn(async move {
+            loop {
+                match p2p_command_receiver.recv().await {
+                    Some(Command::ListPeers { sender, .. }) => {
+                        let _ = sender.send(HashSet::new());
+                    }
+                    _ => panic!("Command must match Command::ListPeers"),
+                }
+            }
+        });
+
+        tokio::spawn(async move {
+            loop {
+                match blockchain_event_receiver.recv().await {
+                    Some(BlockchainEvent::AddBlock { sender, .. }) => {
+                        let _ = sender.send(Ok(()));
+                    }
+                    _ => panic!("BlockchainEvent must match BlockchainEvent::AddBlock"),
+                }
+            }
+        });
+
+        let mut hasher1 = Sha256::new();
+        hasher1.update(b"SAMPLE_DATA");
+        let random_hash = hex::encode(hasher1.finalize());
+
+        let mut hasher2 = Sha256::new();
+        hasher2.update(b"OTHER_SAMPLE_DATA");
+        let random_other_hash = hex::encode(hasher2.finalize());
+
+        let package_type = PackageType::Docker;
+        let package_specific_id = "package_specific_id";
+        let package_specific_artifact_id = "package_specific_artifact_id";
+        artifact_service
+            .transparency_log_service
+            .add_artifact(AddArtifactRequest {
+                package_type,
+                package_specific_id: package_specific_id.to_owned(),
+                num_artifacts: 8,
+                package_specific_artifact_id: package_specific_artifact_id.to_owned(),
+                artifact_hash: random_hash.clone(),
+            })
+            .await
+            .unwrap();
+
+        let transparency_log = artifact_service
+            .transparency_log_service
+            .get_artifact(&package_type, package_specific_artifact_id)
+            .unwrap();
+
+        let verify_error = artifact_service
+            .verify_artifact(&transparency_log, b"OTHER_SAMPLE_DATA")
+            .await
+            .expect_err("Verify artifact should have failed.");
+        match verify_error {
+            TransparencyLogError::InvalidHash {
+                id,
+                invalid_hash,
+                actual_hash,
+            } => {
+                assert_eq!(id, package_specific_artifact_id.to_string());
+                assert_eq!(invalid_hash, random_other_hash);
+                assert_eq!(actual_hash, random_hash);
+            }
+            e => {
+                panic!("Invalid Error encountered: {:?}", e);
+            }
+        }
+
+        test_util::tests::teardown(tmp_dir);
+    }
+
+    #[tokio::test]
+    async fn test_handle_build_result_rolls_back_when_broadcast_fails() {
+        let tmp_dir = test_util::tests::setup();
+
+        let (mut artifact_service, mut blockchain_event_receiver, _, _) =
+            test_util::tests::create_artifact_service(&tmp_dir);
+
+        let (payload_sender, payload_receiver) = tokio::sync::oneshot::channel();
+        tokio::spawn(async move {
+            match blockchain_event_receiver.recv().await {
+                Some(BlockchainEvent::AddBlock { payload, sender }) => {
+                    let _ = sender.send(Err(BlockchainError::InvalidBlockchainCmd));
+                    let _ = payload_sender.send(payload);
+                }
+                _ => panic!("BlockchainEvent must match BlockchainEvent::AddBlock"),
+            }
+        });
+
+        let artifact_location =
+            PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/resources/artifact_test.json");
+        let build_result = BuildResult {
+            package_type: PackageType::Docker,
+            package_specific_id: "alpine:3.15.2".to_owned(),
+            artifacts: (0..2)
+                .map(|i| BuildResultArtifact {
+                    artifact_specific_id: format!("alpine@sha256:{}", i),
+                    artifact_location: artifact_location.clone(),
+                    artifact_hash: "artifact_hash".to_owned(),
+                })
+                .collect(),
+            failed_artifacts: vec![],
+            source: None,
+            pinning_snapshot: None,
+        };
+
+        artifact_service
+            .handle_build_result("build_id", build_result)
+            .await
+            .expect_err("Handle build result should have failed.");
+
+        let published_logs =
+            TransparencyLogService::parse_payload(&payload_receiver.await.unwrap()).unwrap();
+        assert_eq!(published_logs.len(), 2);
+        for published_log in published_logs {
+            assert!(artifact_service
+                .transparency_log_service
+                .find_transparency_log(&published_log.id)
+                .is_err());
+            assert!(artifact_service
+                .artifact_storage
+                .pull_artifact(&published_log.artifact_id)
+                .await
+                .is_err());
+        }
+
+        test_util::tests::teardown(tmp_dir);
+    }
+
+    #[tokio::test]
+    async fn test_handle_build_result_rejected_by_pre_publication_hook() {
+        let tmp_dir = test_util::tests::setup();
+
+        let (artifact_service, mut blockchain_event_receiver, _, _) =
+            test_util::tests::create_artifact_service(&tmp_dir);
+        let mut artifact_service = artifact_service.with_lifecycle_hooks(
+            LifecycleHooks::new(vec![HookConfig {
+                name: "scanner".to_owned(),
+                point: HookPoint::PrePublication,
+                action: HookAction::Command {
+                    program: "sh".to_owned(),
+                    args: vec!["-c".to_owned(), "exit 1".to_owned()],
+                },
+                timeout_secs: 5,
+            }])
+            .unwrap(),
+        );
+
+        let artifact_location =
+            PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/resources/artifact_test.json");
+        let build_result = BuildResult {
+            package_type: PackageType::Docker,
+            package_specific_id: "alpine:3.15.2".to_owned(),
+            artifacts: vec![BuildResultArtifact {
+                artifact_specific_id: "alpine@sha256:1".to_owned(),
+                artifact_location,
+                artifact_hash: "artifact_hash".to_owned(),
+            }],
+            failed_artifacts: vec![],
+            source: None,
+            pinning_snapshot: None,
+        };
+
+        let error = artifact_service
+            .handle_build_result("build_id", build_result)
+            .await
+            .expect_err("Handle build result should have been rejected.");
+
+        assert!(error.is::<HookError>());
+        assert!(blockchain_event_receiver.try_recv().is_err());
+        assert!(artifact_service
+            .transparency_log_service
+            .get_artifact(&PackageType::Docker, "alpine@sha256:1")
+            .is_err());
+        assert_eq!(
+            artifact_service
+                .artifact_storage
+                .artifact_ids()
+                .unwrap()
+                .count(),
+            0
+        );
+
+        test_util::tests::teardown(tmp_dir);
+    }
+
+    #[tokio::test]
+    async fn test_handle_build_result_rejects_moved_immutable_tag() {
+        let tmp_dir = test_util::tests::setup();
+
+        let (artifact_service, mut blockchain_event_receiver, _, _) =
+            test_util::tests::create_artifact_service(&tmp_dir);
+        let alert_service = AlertService::new(vec![]).unwrap();
+        let mut artifact_service = artifact_service
+            .with_alert_service(alert_service.clone())
+            .with_tag_immutability_policy(
+                TagImmutabilityPolicy::new(vec![TagImmutabilityRule {
+                    namespace: "myorg/".to_owned(),
+                    mutable_tags: vec!["latest".to_owned()],
+                }])
+                .unwrap(),
+            );
+
+        artifact_service
+            .transparency_log_service
+            .add_artifact(AddArtifactRequest {
+                package_type: PackageType::Docker,
+                package_specific_id: "myorg/app:1.2.3".to_owned(),
+                num_artifacts: 1,
+                package_specific_artifact_id: "myorg/app:1.2.3".to_owned(),
+                artifact_hash: "published_hash".to_owned(),
+            })
+            .await
+            .unwrap();
+
+        let artifact_location =
+            PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/resources/artifact_test.json");
+        let build_result = BuildResult {
+            package_type: PackageType::Docker,
+            package_specific_id: "myorg/app:1.2.3".to_owned(),
+            artifacts: vec![BuildResultArtifact {
+                artifact_specific_id: "myorg/app:1.2.3".to_owned(),
+                artifact_location,
+                artifact_hash: "artifact_hash".to_owned(),
+            }],
+            failed_artifacts: vec![],
+            source: None,
+            pinning_snapshot: None,
+        };
+
+        let error = artifact_service
+            .handle_build_result("build_id", build_result)
+            .await
+            .expect_err("Handle build result should have been rejected.");
+
+        assert!(matches!(
+            error.downcast_ref::<TagPolicyError>(),
+            Some(TagPolicyError::ImmutableTag { namespace, .. }) if namespace == "myorg/"
+        ));
+        assert!(blockchain_event_receiver.try_recv().is_err());
+        let alerts = alert_service.recent_alerts();
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].kind, AnomalyKind::TagImmutabilityViolation);
+        assert_eq!(
+            alerts[0].details,
+            vec!["myorg/app:1.2.3", "published_hash", "artifact_hash"]
+        );
+
+        test_util::tests::teardown(tmp_dir);
+    }
+
+    #[tokio::test]
+    async fn test_get_artifact_removes_corrupt_local_artifact() {
+        let tmp_dir = test_util::tests::setup();
+
+        let (mut artifact_service, mut blockchain_event_receiver, _, mut p2p_command_receiver) =
+            test_util::tests::create_artifact_service(&tmp_dir);
+
+        let (stop_providing_sender, stop_providing_receiver) = tokio::sync::oneshot::channel();
+        tokio::spawn(async move {
+            match p2p_command_receiver.recv().await {
+                Some(Command::StopProviding {
+                    artifact_id,
+                    sender,
+                }) => {
+                    let _ = sender.send(());
+                    let _ = stop_providing_sender.send(artifact_id);
+                }
+                _ => panic!("Command must match Command::StopProviding"),
+            }
+        });
+
+        tokio::spawn(async move {
+            loop {
+                match blockchain_event_receiver.recv().await {
+                    Some(BlockchainEvent::AddBlock { sender, .. }) => {
+                        let _ = sender.send(Ok(()));
+                    }
+                    _ => panic!("BlockchainEvent must match BlockchainEvent::AddBlock"),
+                }
+            }
+        });
+
+        let package_type = PackageType::Docker;
+        let package_specific_artifact_id = "package_specific_artifact_id";
+        let transparency_log = artifact_service
+            .transparency_log_service
+            .add_artifact(AddArtifactRequest {
+                package_type,
+                package_specific_id: "package_specific_id".to_owned(),
+                num_artifacts: 8,
+                package_specific_artifact_id: package_specific_artifact_id.to_owned(),
+                artifact_hash: hex::encode(VALID_ARTIFACT_HASH),
+            })
+            .await
+            .unwrap()
+            .0;
+
+        artifact_service
+            .put_artifact(&transparency_log.artifact_id, "corrupt artifact".as_bytes())
+            .await
+            .unwrap();
+
+        let result = artifact_service
+            .get_artifact(package_type, package_specific_artifact_id)
+            .await;
+
+        assert!(result.is_err());
+        assert_eq!(
+            stop_providing_receiver.await.unwrap(),
+            transparency_log.artifact_id
+        );
+        assert!(artifact_service
+            .artifact_storage
+            .pull_artifact(&transparency_log.artifact_id)
+            .await
+            .is_err());
+
+        test_util::tests::teardown(tmp_dir);
+    }
+
+    fn create_fake_artifact_service(tmp_dir: &Path, network: &FakeNetwork) -> ArtifactService {
+        let (transparency_log_service, ledger) = fake_transparency_log_service(tmp_dir).unwrap();
+        let mut artifact_service = ArtifactService::new(
+            tmp_dir,
+            ledger.blockchain_event_client(),
+            BuildEventClient::new(tokio::sync::mpsc::channel(1).0),
+            network.client(),
+        )
+        .unwrap()
+        .with_artifact_storage(in_memory_artifact_storage());
+        artifact_service.transparency_log_service = transparency_log_service;
+        artifact_service
+    }
+
+    async fn add_maven_artifact(
+        artifact_service: &ArtifactService,
+        package_specific_artifact_id: &str,
+        artifact: &[u8],
+    ) -> TransparencyLog {
+        artifact_service
+            .transparency_log_service
+            .add_artifact(AddArtifactRequest {
+                package_type: PackageType::Maven2,
+                package_specific_id: "booster:booster:1.0".to_owned(),
+                num_artifacts: 2,
+                package_specific_artifact_id: package_specific_artifact_id.to_owned(),
+                artifact_hash: hex::encode(Sha256::digest(artifact)),
+            })
+            .await
+            .unwrap()
+            .0
+    }
+
+    #[tokio::test]
+    async fn test_sync_from_seed() {
+        let seed_tmp_dir = test_util::tests::setup();
+        let tmp_dir = test_util::tests::setup();
+
+        let mut seed_service =
+            create_fake_artifact_service(&seed_tmp_dir, &FakeNetwork::new()).with_pinned_packages(
+                vec![(PackageType::Maven2, "booster:booster:1.0".to_owned())],
+            );
+        let jar_log = add_maven_artifact(&seed_service, "booster-1.0.jar", b"jar").await;
+        let pom_log = add_maven_artifact(&seed_service, "booster-1.0.pom", b"pom").await;
+        for (transparency_log, artifact) in [(&jar_log, b"jar"), (&pom_log, b"pom")] {
+            seed_service
+                .put_artifact(&transparency_log.artifact_id, &artifact[..])
+                .await
+                .unwrap();
+        }
+        seed_service
+            .get_artifact_locally(&jar_log.artifact_id)
+            .await
+            .unwrap();
+
+        let transparency_logs = match seed_service
+            .respond_seed_sync(
+                &PeerId::random(),
+                SeedSyncRequest::TransparencyLogs { offset: 0 },
+            )
+            .await
+            .unwrap()
+        {
+            SeedSyncResponse::TransparencyLogs(page) => page.transparency_logs,
+            response => panic!("Unexpected response {:?}", response),
+        };
+        assert_eq!(transparency_logs, vec![jar_log.clone(), pom_log.clone()]);
+        let artifact_ids = vec![jar_log.artifact_id.clone(), pom_log.artifact_id.clone()];
+        assert_eq!(
+            seed_service
+                .respond_seed_sync(&PeerId::random(), SeedSyncRequest::Artifacts)
+                .await
+                .unwrap(),
+            SeedSyncResponse::Artifacts(artifact_ids.clone())
+        );
+
+        let seed_peer_id = PeerId::random();
+        let network = FakeNetwork::new()
+            .with_seed_snapshot(seed_peer_id, transparency_logs, artifact_ids)
+            .with_artifact(seed_peer_id, &jar_log.artifact_id, b"jar")
+            .with_artifact(seed_peer_id, &pom_log.artifact_id, b"corrupt pom");
+        let mut artifact_service = create_fake_artifact_service(&tmp_dir, &network);
+        let seed: Multiaddr = format!("/ip4/127.0.0.1/tcp/44000/p2p/{}", seed_peer_id)
+            .parse()
+            .unwrap();
+
+        let progress = artifact_service.sync_from_seed(&seed).await.unwrap();
+        assert_eq!(
+            progress,
+            SeedSyncProgress {
+                seed: Some(seed.to_string()),
+                state: SeedSyncState::Finished,
+                transparency_logs_imported: 2,
+                artifacts_total: 2,
+                artifacts_synced: 1,
+                artifacts_skipped: 0,
+                artifacts_failed: 1,
+                bytes_synced: 3,
+                error: None,
+            }
+        );
+        assert_eq!(artifact_service.seed_sync_progress(), progress);
+        assert!(
+            !artifact_service
+                .artifact_storage
+                .contains_artifact(&pom_log.artifact_id)
+                .await
+        );
+        assert_eq!(
+            network.provided_artifact_ids(),
+            HashSet::from([jar_log.artifact_id.clone()])
+        );
+
+        let progress = artifact_service.sync_from_seed(&seed).await.unwrap();
+        assert_eq!(
+            (
+                progress.transparency_logs_imported,
+                progress.artifacts_synced,
+                progress.artifacts_skipped,
+                progress.artifacts_failed
+            ),
+            (0, 0, 1, 1)
+        );
+
+        let unknown_seed: Multiaddr = "/ip4/127.0.0.1/tcp/44000".parse().unwrap();
+        assert!(artifact_service
+            .sync_from_seed(&unknown_seed)
+            .await
+            .is_err());
+        assert_eq!(
+            artifact_service.seed_sync_progress().state,
+            SeedSyncState::Failed
+        );
+
+        test_util::tests::teardown(seed_tmp_dir);
+        test_util::tests::teardown(tmp_dir);
+    }
+
+    #[tokio::test]
+    async fn test_fast_sync() {
+        let peer_tmp_dir = test_util::tests::setup();
+        let tmp_dir = test_util::tests::setup();
+
+        let keypair = Keypair::generate();
+        let peer_id = PeerId::from_public_key(&PublicKey::Ed25519(keypair.public()));
+        let mut peer_service = create_fake_artifact_service(&peer_tmp_dir, &FakeNetwork::new());
+        assert!(peer_service
+            .respond_seed_sync(&PeerId::random(), SeedSyncRequest::Snapshot)
+            .await
+            .is_err());
+        let mut peer_service = peer_service.with_snapshot_keypair(keypair);
+        peer_service
+            .transparency_log_service
+            .add_authorized_node(peer_id)
+            .await
+            .unwrap();
+        add_maven_artifact(&peer_service, "booster-1.0.jar", b"jar").await;
+        let snapshot = match peer_service
+            .respond_seed_sync(&PeerId::random(), SeedSyncRequest::Snapshot)
+            .await
+            .unwrap()
+        {
+            SeedSyncResponse::Snapshot(snapshot) => snapshot,
+            response => panic!("Unexpected response {:?}", response),
+        };
+        let transparency_logs = match peer_service
+            .respond_seed_sync(
+                &PeerId::random(),
+                SeedSyncRequest::TransparencyLogs { offset: 0 },
+            )
+            .await
+            .unwrap()
+        {
+            SeedSyncResponse::TransparencyLogs(page) => page.transparency_logs,
+            response => panic!("Unexpected response {:?}", response),
+        };
+
+        let other_peer_id = PeerId::random();
+        let network = FakeNetwork::new()
+            .with_log_snapshot(peer_id, snapshot.clone(), transparency_logs.clone())
+            .with_log_snapshot(other_peer_id, snapshot.clone(), transparency_logs);
+        let mut artifact_service = create_fake_artifact_service(&tmp_dir, &network);
+
+        assert!(artifact_service.fast_sync(&other_peer_id).await.is_err());
+        assert_eq!(
+            artifact_service.fast_sync(&peer_id).await.unwrap(),
+            snapshot
+        );
+        assert_eq!(
+            artifact_service
+                .transparency_log_service
+                .tree_head()
+                .unwrap()
+                .root_hash,
+            snapshot.root_hash
+        );
+        assert_eq!(
+            artifact_service
+                .transparency_log_service
+                .get_authorized_nodes()
+                .unwrap(),
+            vec![peer_id]
+        );
+
+        test_util::tests::teardown(peer_tmp_dir);
+        test_util::tests::teardown(tmp_dir);
+    }
+
+    #[tokio::test]
+    async fn test_scrub_local_artifacts_removes_corrupt_artifacts() {
+        let tmp_dir = test_util::tests::setup();
+
+        let network = FakeNetwork::new();
+        let mut artifact_service = create_fake_artifact_service(&tmp_dir, &network);
+
+        let valid_log = add_maven_artifact(&artifact_service, "booster-1.0.jar", b"valid").await;
+        let corrupt_log = add_maven_artifact(&artifact_service, "booster-1.0.pom", b"pom").await;
+        artifact_service
+            .put_artifact(&valid_log.artifact_id, "valid".as_bytes())
+            .await
+            .unwrap();
+        artifact_service
+            .put_artifact(&corrupt_log.artifact_id, "corrupt".as_bytes())
+            .await
+            .unwrap();
+        artifact_service
+            .put_artifact("unknown", "unknown".as_bytes())
+            .await
+            .unwrap();
+
+        let report = artifact_service.scrub_local_artifacts(true).await.unwrap();
+
+        assert_eq!(
+            report,
+            ScrubReport {
+                verified: 1,
+                corrupt: vec![corrupt_log.artifact_id.clone()],
+                repaired: vec![],
+                unverifiable: 1,
+            }
+        );
+        assert!(
+            !artifact_service
+                .artifact_storage
+                .contains_artifact(&corrupt_log.artifact_id)
+                .await
+        );
+        assert!(
+            artifact_service
+                .artifact_storage
+                .contains_artifact(&valid_log.artifact_id)
+                .await
+        );
+        assert_eq!(
+            artifact_service.scrub_stats(),
+            ScrubStats {
+                scrubs: 1,
+                verified: 1,
+                corrupt: 1,
+                repaired: 0,
+                unverifiable: 1,
+            }
+        );
+
+        test_util::tests::teardown(tmp_dir);
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_fetches_from_peer_are_stored_on_disk() {
+        let tmp_dir = test_util::tests::setup();
+
+        let peer_id = PeerId::random();
+        let artifact = b"artifact of a peer";
+        let artifact_id = derive_artifact_id(&hex::encode(Sha256::digest(artifact)));
+        let network = FakeNetwork::new().with_artifact(peer_id, &artifact_id, artifact);
+        let artifact_service = create_fake_artifact_service(&tmp_dir, &network)
+            .with_artifact_storage(ArtifactStorage::new(&tmp_dir).unwrap());
+        add_maven_artifact(&artifact_service, "booster-1.0.jar", artifact).await;
+
+        // requests for the same artifact that arrive at the same time all
+        // get it, and it is stored once
+        let fetches = (0..4).map(|_| {
+            let mut artifact_service = artifact_service.clone();
+            async move {
+                artifact_service
+                    .get_artifact(PackageType::Maven2, "booster-1.0.jar")
+                    .await
+            }
+        });
+        for result in futures::future::join_all(fetches).await {
+            assert_eq!(result.unwrap(), artifact);
+        }
+        assert_eq!(
+            artifact_service
+                .artifact_storage
+                .read_artifact(&artifact_id)
+                .await
+                .unwrap(),
+            artifact
+        );
+
+        test_util::tests::teardown(tmp_dir);
+    }
+
+    #[tokio::test]
+    async fn test_scrub_local_artifacts_refetches_corrupt_artifacts() {
+        let tmp_dir = test_util::tests::setup();
+
+        let peer_id = PeerId::random();
+        let artifact = b"valid artifact";
+        let artifact_id = derive_artifact_id(&hex::encode(Sha256::digest(artifact)));
+        let network = FakeNetwork::new().with_artifact(peer_id, &artifact_id, artifact);
+        let mut artifact_service = create_fake_artifact_service(&tmp_dir, &network);
+
+        let transparency_log =
+            add_maven_artifact(&artifact_service, "booster-1.0.jar", artifact).await;
+        assert_eq!(transparency_log.artifact_id, artifact_id);
+        artifact_service
+            .put_artifact(&artifact_id, "corrupt".as_bytes())
+            .await
+            .unwrap();
+
+        let report = artifact_service.scrub_local_artifacts(false).await.unwrap();
+        assert_eq!(report.corrupt, vec![artifact_id.clone()]);
+        assert!(report.repaired.is_empty());
+        assert!(
+            !artifact_service
+                .artifact_storage
+                .contains_artifact(&artifact_id)
+                .await
+        );
+
+        artifact_service
+            .put_artifact(&artifact_id, "corrupt".as_bytes())
+            .await
+            .unwrap();
+        let report = artifact_service.scrub_local_artifacts(true).await.unwrap();
+        assert_eq!(report.corrupt, vec![artifact_id.clone()]);
+        assert_eq!(report.repaired, vec![artifact_id.clone()]);
+        assert_eq!(
+            artifact_service
+                .artifact_storage
+                .hash_artifact(&artifact_id)
+                .await
+                .unwrap(),
+            transparency_log.artifact_hash
+        );
+        assert!(network.provided_artifact_ids().contains(&artifact_id));
+        assert_eq!(artifact_service.scrub_stats().scrubs, 2);
 
-        let mut buf_reader = BufReader::new(artifact.as_slice());
+        let report = artifact_service.scrub_local_artifacts(true).await.unwrap();
+        assert_eq!(report.verified, 1);
+        assert!(report.corrupt.is_empty());
 
-        self.put_artifact(artifact_id, &mut buf_reader)?;
-        self.get_artifact_locally(artifact_id).await
+        test_util::tests::teardown(tmp_dir);
     }
 
-    async fn verify_artifact(
-        &mut self,
-        transparency_log: &TransparencyLog,
-        artifact: &[u8],
-    ) -> Result<(), TransparencyLogError> {
-        let mut sha256 = multihash::Sha2_256::default();
-        sha256.update(artifact);
-        let calculated_hash = hex::encode(sha256.finalize());
+    #[tokio::test]
+    async fn test_remove_artifact() {
+        let tmp_dir = test_util::tests::setup();
 
-        if transparency_log.artifact_hash == calculated_hash {
-            Ok(())
-        } else {
-            Err(TransparencyLogError::InvalidHash {
-                id: transparency_log.package_specific_artifact_id.clone(),
-                invalid_hash: calculated_hash,
-                actual_hash: transparency_log.artifact_hash.clone(),
+        let (mut artifact_service, mut blockchain_event_receiver, _, mut p2p_command_receiver) =
+            test_util::tests::create_artifact_service(&tmp_dir);
+
+        let (stop_providing_sender, stop_providing_receiver) = tokio::sync::oneshot::channel();
+        tokio::spawn(async move {
+            match p2p_command_receiver.recv().await {
+                Some(Command::StopProviding {
+                    artifact_id,
+                    sender,
+                }) => {
+                    let _ = sender.send(());
+                    let _ = stop_providing_sender.send(artifact_id);
+                }
+                _ => panic!("Command must match Command::StopProviding"),
+            }
+        });
+
+        let (payload_sender, payload_receiver) = tokio::sync::oneshot::channel();
+        tokio::spawn(async move {
+            let mut payload_sender = Some(payload_sender);
+            loop {
+                match blockchain_event_receiver.recv().await {
+                    Some(BlockchainEvent::AddBlock {
+                        payload, sender, ..
+                    }) => {
+                        let _ = sender.send(Ok(()));
+                        let tombstones = TransparencyLogService::parse_payload(&payload).unwrap();
+                        if tombstones[0].operation == Operation::RemoveArtifact {
+                            let _ = payload_sender.take().unwrap().send(tombstones);
+                        }
+                    }
+                    _ => panic!("BlockchainEvent must match BlockchainEvent::AddBlock"),
+                }
+            }
+        });
+
+        let package_type = PackageType::Docker;
+        let package_specific_artifact_id = "package_specific_artifact_id";
+        let transparency_log = artifact_service
+            .transparency_log_service
+            .add_artifact(AddArtifactRequest {
+                package_type,
+                package_specific_id: "package_specific_id".to_owned(),
+                num_artifacts: 8,
+                package_specific_artifact_id: package_specific_artifact_id.to_owned(),
+                artifact_hash: hex::encode(VALID_ARTIFACT_HASH),
             })
-        }
-    }
-}
+            .await
+            .unwrap()
+            .0;
+        artifact_service
+            .put_artifact(&transparency_log.artifact_id, get_file_reader().unwrap())
+            .await
+            .unwrap();
 
-#[cfg(test)]
-#[cfg(not(tarpaulin_include))]
-mod tests {
-    use super::*;
-    use crate::blockchain_service::event::BlockchainEvent;
-    use crate::build_service::event::BuildEvent;
-    use crate::network::client::command::Command;
-    use crate::network::idle_metric_protocol::PeerMetrics;
-    use crate::util::test_util;
-    use libp2p::identity::ed25519::Keypair;
-    use libp2p::identity::PublicKey;
-    use sha2::{Digest, Sha256};
-    use std::collections::HashSet;
-    use std::env;
-    use std::path::PathBuf;
-    use tokio::task;
+        let tombstone = artifact_service
+            .remove_artifact(package_type, package_specific_artifact_id)
+            .await
+            .unwrap();
 
-    const VALID_ARTIFACT_HASH: [u8; 32] = [
-        0x86, 0x5c, 0x8d, 0x98, 0x8b, 0xe4, 0x66, 0x9f, 0x3e, 0x48, 0xf7, 0x3b, 0x98, 0xf9, 0xbc,
-        0x25, 0x7, 0xbe, 0x2, 0x46, 0xea, 0x35, 0xe0, 0x9, 0x8c, 0xf6, 0x5, 0x4d, 0x36, 0x44, 0xc1,
-        0x4f,
-    ];
+        assert_eq!(tombstone.operation, Operation::RemoveArtifact);
+        assert_eq!(tombstone.artifact_id, transparency_log.artifact_id);
+        assert_eq!(payload_receiver.await.unwrap(), vec![tombstone]);
+        assert_eq!(
+            stop_providing_receiver.await.unwrap(),
+            transparency_log.artifact_id
+        );
+        assert!(
+            !artifact_service
+                .artifact_storage
+                .contains_artifact(&transparency_log.artifact_id)
+                .await
+        );
+        assert!(artifact_service
+            .get_artifact(package_type, package_specific_artifact_id)
+            .await
+            .is_err());
+
+        test_util::tests::teardown(tmp_dir);
+    }
 
     #[tokio::test]
-    async fn test_put_and_get_artifact() {
+    async fn test_revoke_artifact() {
         let tmp_dir = test_util::tests::setup();
 
         let (mut artifact_service, mut blockchain_event_receiver, _, mut p2p_command_receiver) =
             test_util::tests::create_artifact_service(&tmp_dir);
 
+        let (stop_providing_sender, stop_providing_receiver) = tokio::sync::oneshot::channel();
         tokio::spawn(async move {
-            loop {
-                match p2p_command_receiver.recv().await {
-                    Some(Command::ListPeers { sender, .. }) => {
-                        let _ = sender.send(HashSet::new());
-                    }
-                    _ => panic!("Command must match Command::ListPeers"),
+            match p2p_command_receiver.recv().await {
+                Some(Command::StopProviding {
+                    artifact_id,
+                    sender,
+                }) => {
+                    let _ = sender.send(());
+                    let _ = stop_providing_sender.send(artifact_id);
                 }
+                _ => panic!("Command must match Command::StopProviding"),
             }
         });
 
+        let (payload_sender, payload_receiver) = tokio::sync::oneshot::channel();
         tokio::spawn(async move {
+            let mut payload_sender = Some(payload_sender);
             loop {
                 match blockchain_event_receiver.recv().await {
-                    Some(BlockchainEvent::AddBlock { sender, .. }) => {
+                    Some(BlockchainEvent::AddBlock {
+                        payload, sender, ..
+                    }) => {
                         let _ = sender.send(Ok(()));
+                        let revocations = TransparencyLogService::parse_payload(&payload).unwrap();
+                        if revocations[0].operation == Operation::RevokeArtifact {
+                            let _ = payload_sender.take().unwrap().send(revocations);
+                        }
                     }
                     _ => panic!("BlockchainEvent must match BlockchainEvent::AddBlock"),
                 }
@@ -443,73 +4596,345 @@ mod tests {
         });
 
         let package_type = PackageType::Docker;
-        let package_specific_id = "package_specific_id";
         let package_specific_artifact_id = "package_specific_artifact_id";
-        let transparency_log_tuple = artifact_service
+        let transparency_log = artifact_service
             .transparency_log_service
             .add_artifact(AddArtifactRequest {
                 package_type,
-                package_specific_id: package_specific_id.to_owned(),
+                package_specific_id: "package_specific_id".to_owned(),
                 num_artifacts: 8,
                 package_specific_artifact_id: package_specific_artifact_id.to_owned(),
                 artifact_hash: hex::encode(VALID_ARTIFACT_HASH),
             })
             .await
+            .unwrap()
+            .0;
+        artifact_service
+            .put_artifact(&transparency_log.artifact_id, get_file_reader().unwrap())
+            .await
             .unwrap();
-        let transparency_log = transparency_log_tuple.0;
 
-        //put the artifact
-        artifact_service
-            .put_artifact(
-                &transparency_log.artifact_id,
-                &mut get_file_reader().unwrap(),
-            )
-            .context("Error from put_artifact")
+        let revocation = artifact_service
+            .revoke_artifact(package_type, package_specific_artifact_id)
+            .await
             .unwrap();
 
-        // pull artifact
-        let future = {
-            artifact_service
-                .get_artifact(package_type, package_specific_artifact_id)
+        assert_eq!(revocation.operation, Operation::RevokeArtifact);
+        assert_eq!(revocation.artifact_id, transparency_log.artifact_id);
+        assert_eq!(payload_receiver.await.unwrap(), vec![revocation]);
+        assert_eq!(
+            stop_providing_receiver.await.unwrap(),
+            transparency_log.artifact_id
+        );
+        assert!(
+            !artifact_service
+                .artifact_storage
+                .contains_artifact(&transparency_log.artifact_id)
                 .await
-                .context("Error from get_artifact")
-        };
-        let file = task::spawn_blocking(|| future).await.unwrap().unwrap();
+        );
+        assert!(artifact_service
+            .get_artifact(package_type, package_specific_artifact_id)
+            .await
+            .is_err());
 
-        //validate pulled artifact with the actual data
-        let mut s = String::new();
-        get_file_reader().unwrap().read_to_string(&mut s).unwrap();
+        test_util::tests::teardown(tmp_dir);
+    }
 
-        let s1 = match str::from_utf8(file.as_slice()) {
-            Ok(v) => v,
-            Err(e) => panic!("Invalid UTF-8 sequence: {}", e),
-        };
-        assert_eq!(s, s1);
+    #[tokio::test]
+    async fn test_sample_availability() {
+        let tmp_dir = test_util::tests::setup();
+
+        let (mut artifact_service, _blockchain_event_receiver, _, mut p2p_command_receiver) =
+            test_util::tests::create_artifact_service(&tmp_dir);
+
+        let local_peer_id = artifact_service.p2p_client.local_peer_id;
+        tokio::spawn(async move {
+            loop {
+                match p2p_command_receiver.recv().await {
+                    Some(Command::ListProviders { sender, .. }) => {
+                        let _ = sender.send(HashSet::from([local_peer_id]));
+                    }
+                    other => panic!(
+                        "Command must match Command::ListProviders, was: {:?}",
+                        other
+                    ),
+                }
+            }
+        });
+
+        for (package_type, package_specific_artifact_id, artifact_hash) in [
+            (
+                PackageType::Docker,
+                "stored@artifact",
+                hex::encode(VALID_ARTIFACT_HASH),
+            ),
+            (
+                PackageType::Maven2,
+                "missing/artifact",
+                "missing".to_owned(),
+            ),
+        ] {
+            let transparency_log = artifact_service
+                .transparency_log_service
+                .add_artifact(AddArtifactRequest {
+                    package_type,
+                    package_specific_id: package_specific_artifact_id.to_owned(),
+                    num_artifacts: 1,
+                    package_specific_artifact_id: package_specific_artifact_id.to_owned(),
+                    artifact_hash,
+                })
+                .await
+                .unwrap()
+                .0;
+            if package_type == PackageType::Docker {
+                artifact_service
+                    .put_artifact(&transparency_log.artifact_id, get_file_reader().unwrap())
+                    .await
+                    .unwrap();
+            }
+        }
+
+        let mut samples = artifact_service.sample_availability(10).await.unwrap();
+        samples.sort_by_key(|sample| sample.package_type);
+        assert_eq!(samples.len(), 2);
+        assert!(samples[0].available);
+        assert!(!samples[1].available);
+        assert_eq!(samples[1].reason.as_deref(), Some("not stored locally"));
+
+        let report = artifact_service.availability_report(&[3600]);
+        assert_eq!(report.windows[0].overall.samples, 2);
+        assert_eq!(report.windows[0].overall.availability_percent, Some(50.0));
+        assert_eq!(
+            report.windows[0].package_types[&PackageType::Maven2].available,
+            0
+        );
+        assert_eq!(report.recent_failures, vec![samples[1].clone()]);
 
         test_util::tests::teardown(tmp_dir);
     }
 
     #[tokio::test]
-    async fn test_put_and_list_artifact() {
+    async fn test_evict_least_recently_used() {
         let tmp_dir = test_util::tests::setup();
 
         let (artifact_service, mut blockchain_event_receiver, _, mut p2p_command_receiver) =
             test_util::tests::create_artifact_service(&tmp_dir);
+        let mut artifact_service = artifact_service
+            .with_artifact_storage(
+                ArtifactStorage::new(&tmp_dir)
+                    .unwrap()
+                    .with_max_size(20)
+                    .unwrap(),
+            )
+            .with_pinned_packages(vec![(PackageType::Docker, "pinned".to_owned())]);
 
         tokio::spawn(async move {
             loop {
                 match p2p_command_receiver.recv().await {
-                    Some(Command::ListPeers { sender, .. }) => {
-                        let _ = sender.send(HashSet::new());
-                    }
-                    Some(Command::Provide { sender, .. }) => {
+                    Some(Command::StopProviding { sender, .. }) => {
                         let _ = sender.send(());
                     }
-                    _ => panic!("Command must match Command::ListPeers or Command::Provide"),
+                    Some(Command::ListProviders { sender, .. }) => {
+                        let _ = sender.send(HashSet::from([PeerId::random()]));
+                    }
+                    _ => panic!(
+                        "Command must match Command::StopProviding or Command::ListProviders"
+                    ),
+                }
+            }
+        });
+
+        tokio::spawn(async move {
+            loop {
+                match blockchain_event_receiver.recv().await {
+                    Some(BlockchainEvent::AddBlock { sender, .. }) => {
+                        let _ = sender.send(Ok(()));
+                    }
+                    _ => panic!("BlockchainEvent must match BlockchainEvent::AddBlock"),
                 }
             }
         });
 
+        let mut artifact_ids = vec![];
+        for (package_specific_id, artifact_hash) in [("pinned", [1u8; 32]), ("evicted", [2u8; 32])]
+        {
+            let transparency_log = artifact_service
+                .transparency_log_service
+                .add_artifact(AddArtifactRequest {
+                    package_type: PackageType::Docker,
+                    package_specific_id: package_specific_id.to_owned(),
+                    num_artifacts: 1,
+                    package_specific_artifact_id: format!("{}@artifact", package_specific_id),
+                    artifact_hash: hex::encode(artifact_hash),
+                })
+                .await
+                .unwrap()
+                .0;
+            artifact_service
+                .put_artifact(&transparency_log.artifact_id, "sixteen bytes!!!".as_bytes())
+                .await
+                .unwrap();
+            artifact_ids.push(transparency_log.artifact_id);
+        }
+
+        let evicted_artifact_ids = artifact_service.evict_least_recently_used().await.unwrap();
+
+        assert_eq!(evicted_artifact_ids, vec![artifact_ids[1].clone()]);
+        assert!(
+            artifact_service
+                .artifact_storage
+                .contains_artifact(&artifact_ids[0])
+                .await
+        );
+        assert!(
+            !artifact_service
+                .artifact_storage
+                .contains_artifact(&artifact_ids[1])
+                .await
+        );
+        assert!(artifact_service
+            .transparency_log_service
+            .get_artifact(&PackageType::Docker, "evicted@artifact")
+            .is_ok());
+
+        test_util::tests::teardown(tmp_dir);
+    }
+
+    #[tokio::test]
+    async fn test_storage_stats() {
+        let tmp_dir = test_util::tests::setup();
+
+        let network = FakeNetwork::new();
+        let artifact_service = create_fake_artifact_service(&tmp_dir, &network)
+            .with_artifact_storage(in_memory_artifact_storage().with_max_size(1024).unwrap());
+
+        let maven_log = add_maven_artifact(&artifact_service, "booster-1.0.jar", b"jar").await;
+        artifact_service
+            .put_artifact(&maven_log.artifact_id, b"jar".as_slice())
+            .await
+            .unwrap();
+        let docker_log = artifact_service
+            .transparency_log_service
+            .add_artifact(AddArtifactRequest {
+                package_type: PackageType::Docker,
+                package_specific_id: "alpine:3.16".to_owned(),
+                num_artifacts: 1,
+                package_specific_artifact_id: "alpine@sha256:1234".to_owned(),
+                artifact_hash: hex::encode(Sha256::digest(b"manifest")),
+            })
+            .await
+            .unwrap()
+            .0;
+        artifact_service
+            .put_artifact(&docker_log.artifact_id, b"manifest".as_slice())
+            .await
+            .unwrap();
+        artifact_service
+            .put_artifact("unreferenced_artifact", b"layer".as_slice())
+            .await
+            .unwrap();
+
+        assert_eq!(
+            artifact_service.storage_stats().await.unwrap(),
+            StorageStats {
+                artifacts: 3,
+                total_bytes: 16,
+                bytes_per_package_type: BTreeMap::from([
+                    (PackageType::Docker, 8),
+                    (PackageType::Maven2, 3),
+                ]),
+                unreferenced_bytes: 5,
+                free_bytes: Some(1024 - 16),
+            }
+        );
+
+        test_util::tests::teardown(tmp_dir);
+    }
+
+    #[tokio::test]
+    async fn test_storage_stats_without_artifacts() {
+        let tmp_dir = test_util::tests::setup();
+
+        let network = FakeNetwork::new();
+        let artifact_service = create_fake_artifact_service(&tmp_dir, &network)
+            .with_artifact_storage(in_memory_artifact_storage().with_max_size(1024).unwrap());
+
+        assert_eq!(
+            artifact_service.storage_stats().await.unwrap(),
+            StorageStats {
+                artifacts: 0,
+                total_bytes: 0,
+                bytes_per_package_type: BTreeMap::new(),
+                unreferenced_bytes: 0,
+                free_bytes: Some(1024),
+            }
+        );
+
+        test_util::tests::teardown(tmp_dir);
+    }
+
+    #[tokio::test]
+    async fn test_evict_sole_copy() {
+        let tmp_dir = test_util::tests::setup();
+        let replica_tmp_dir = test_util::tests::setup();
+
+        let network = FakeNetwork::new().with_peer(PeerId::random());
+        let mut artifact_service = create_fake_artifact_service(&tmp_dir, &network)
+            .with_artifact_storage(in_memory_artifact_storage().with_max_size(4).unwrap());
+        let transparency_log =
+            add_maven_artifact(&artifact_service, "booster-1.0.jar", b"jar").await;
+        artifact_service
+            .put_artifact(&transparency_log.artifact_id, b"sole copy".as_slice())
+            .await
+            .unwrap();
+
+        assert!(artifact_service
+            .evict_least_recently_used()
+            .await
+            .unwrap()
+            .is_empty());
+        assert!(
+            artifact_service
+                .artifact_storage
+                .contains_artifact(&transparency_log.artifact_id)
+                .await
+        );
+
+        let replica_peer_id = PeerId::random();
+        let replica_network = FakeNetwork::new().with_replica_peer(replica_peer_id);
+        let mut artifact_service = create_fake_artifact_service(&replica_tmp_dir, &replica_network)
+            .with_artifact_storage(in_memory_artifact_storage().with_max_size(4).unwrap());
+        let transparency_log =
+            add_maven_artifact(&artifact_service, "booster-1.0.jar", b"jar").await;
+        artifact_service
+            .put_artifact(&transparency_log.artifact_id, b"sole copy".as_slice())
+            .await
+            .unwrap();
+
+        assert_eq!(
+            artifact_service.evict_least_recently_used().await.unwrap(),
+            vec![transparency_log.artifact_id.clone()]
+        );
+        assert_eq!(
+            replica_network
+                .client()
+                .list_providers(&transparency_log.artifact_id)
+                .await
+                .unwrap(),
+            HashSet::from([replica_peer_id])
+        );
+
+        test_util::tests::teardown(tmp_dir);
+        test_util::tests::teardown(replica_tmp_dir);
+    }
+
+    #[tokio::test]
+    async fn test_remove_artifact_referenced_by_other_package() {
+        let tmp_dir = test_util::tests::setup();
+
+        let (mut artifact_service, mut blockchain_event_receiver, _, mut p2p_command_receiver) =
+            test_util::tests::create_artifact_service(&tmp_dir);
+
         tokio::spawn(async move {
             loop {
                 match blockchain_event_receiver.recv().await {
@@ -520,76 +4945,219 @@ mod tests {
                 }
             }
         });
+        tokio::spawn(async move {
+            loop {
+                match p2p_command_receiver.recv().await {
+                    Some(Command::StopProviding { sender, .. }) => {
+                        let _ = sender.send(());
+                    }
+                    _ => panic!("Command must match Command::StopProviding"),
+                }
+            }
+        });
 
-        let package_type = PackageType::Docker;
-        let package_specific_id = "package_specific_id";
-        let package_specific_artifact_id = "package_specific_artifact_id";
-        let transparency_log_tuple = artifact_service
-            .transparency_log_service
-            .add_artifact(AddArtifactRequest {
-                package_type,
-                package_specific_id: package_specific_id.to_owned(),
-                num_artifacts: 8,
-                package_specific_artifact_id: package_specific_artifact_id.to_owned(),
-                artifact_hash: hex::encode(VALID_ARTIFACT_HASH),
-            })
+        let package_type = PackageType::Maven2;
+        let mut artifact_ids = HashSet::new();
+        for group_path in ["com/oldorg", "com/neworg"] {
+            let transparency_log = artifact_service
+                .transparency_log_service
+                .add_artifact(AddArtifactRequest {
+                    package_type,
+                    package_specific_id: format!("{}:my-artifact:1.0.0", group_path),
+                    num_artifacts: 1,
+                    package_specific_artifact_id: format!(
+                        "{}/my-artifact/1.0.0/my-artifact-1.0.0.jar",
+                        group_path
+                    ),
+                    artifact_hash: hex::encode(VALID_ARTIFACT_HASH),
+                })
+                .await
+                .unwrap()
+                .0;
+            artifact_ids.insert(transparency_log.artifact_id);
+        }
+        assert_eq!(artifact_ids.len(), 1);
+        let artifact_id = artifact_ids.into_iter().next().unwrap();
+        artifact_service
+            .put_artifact(&artifact_id, get_file_reader().unwrap())
             .await
             .unwrap();
+        let artifact_size = artifact_service
+            .artifact_storage
+            .artifact_size(&artifact_id)
+            .await
+            .unwrap();
+        assert_eq!(
+            artifact_service.dedup_stats().await.unwrap(),
+            DedupStats {
+                stored_artifacts: 1,
+                stored_bytes: artifact_size,
+                references: 2,
+                shared_artifacts: 1,
+                unreferenced_artifacts: 0,
+                saved_bytes: artifact_size,
+            }
+        );
+        let storage_stats = artifact_service.storage_stats().await.unwrap();
+        assert_eq!(storage_stats.artifacts, 1);
+        assert_eq!(storage_stats.total_bytes, artifact_size);
+        assert_eq!(
+            storage_stats.bytes_per_package_type,
+            BTreeMap::from([(package_type, artifact_size)])
+        );
+        assert_eq!(storage_stats.unreferenced_bytes, 0);
 
-        let transparency_log = transparency_log_tuple.0;
-        //put the artifact
         artifact_service
-            .put_artifact(
-                &transparency_log.artifact_id,
-                &mut get_file_reader().unwrap(),
+            .remove_artifact(
+                package_type,
+                "com/oldorg/my-artifact/1.0.0/my-artifact-1.0.0.jar",
             )
-            .context("Error from put_artifact")
+            .await
             .unwrap();
-
-        // provide artifacts
-        let future = {
+        assert!(
             artifact_service
-                .provide_local_artifacts()
+                .artifact_storage
+                .contains_artifact(&artifact_id)
                 .await
-                .context("Error from provide_local_artifacts")
-        };
-        let files = task::spawn_blocking(|| future).await.unwrap();
-        assert!(files.is_ok());
+        );
+        let dedup_stats = artifact_service.dedup_stats().await.unwrap();
+        assert_eq!(dedup_stats.stored_artifacts, 1);
+        assert_eq!(dedup_stats.references, 1);
+        assert_eq!(dedup_stats.saved_bytes, 0);
+        assert!(artifact_service
+            .get_artifact(
+                package_type,
+                "com/neworg/my-artifact/1.0.0/my-artifact-1.0.0.jar"
+            )
+            .await
+            .is_ok());
+
+        artifact_service
+            .remove_artifact(
+                package_type,
+                "com/neworg/my-artifact/1.0.0/my-artifact-1.0.0.jar",
+            )
+            .await
+            .unwrap();
+        assert!(
+            !artifact_service
+                .artifact_storage
+                .contains_artifact(&artifact_id)
+                .await
+        );
 
         test_util::tests::teardown(tmp_dir);
     }
 
-    #[tokio::test(flavor = "multi_thread")]
-    async fn test_get_from_peers() {
+    #[tokio::test]
+    async fn test_search_artifacts() {
         let tmp_dir = test_util::tests::setup();
 
-        let (p2p_client, mut p2p_command_receiver) = test_util::tests::create_p2p_client();
-        let (mut artifact_service, mut blockchain_event_receiver, _) =
-            test_util::tests::create_artifact_service_with_p2p_client(&tmp_dir, p2p_client.clone());
+        let (artifact_service, ..) = test_util::tests::create_artifact_service(&tmp_dir);
 
-        tokio::spawn(async move {
-            loop {
-                match p2p_command_receiver.recv().await {
-                    Some(Command::ListPeers { sender, .. }) => {
-                        let _ = sender.send(HashSet::new());
-                    },
-                    Some(Command::ListProviders { sender, .. }) => {
-                        let mut set = HashSet::new();
-                        set.insert(p2p_client.local_peer_id);
-                        let _ = sender.send(set);
-                    },
-                    Some(Command::RequestIdleMetric { sender, .. }) => {
-                        let _ = sender.send(Ok(PeerMetrics {
-                            idle_metric: (0.1_f64).to_le_bytes()
-                        }));
-                    },
-                    Some(Command::RequestArtifact { sender, .. }) => {
-                        let _ = sender.send(Ok(b"SAMPLE_DATA".to_vec()));
-                    },
-                    _ => panic!("Command must match Command::ListPeers, Command::ListProviders, Command::RequestIdleMetric, Command::RequestArtifact"),
-                }
+        for (package_specific_id, package_specific_artifact_id, stored) in [
+            ("library/alpine:3.15.2", "library/alpine@sha256:1", true),
+            ("library/alpine:3.15.2", "library/alpine@sha256:2", true),
+            ("library/alpine:3.16.0", "library/alpine@sha256:3", true),
+            ("library/alpine:3.16.0", "library/alpine@sha256:4", false),
+            ("library/busybox:1.0", "library/busybox@sha256:5", true),
+        ] {
+            let transparency_log = artifact_service
+                .transparency_log_service
+                .add_artifact(AddArtifactRequest {
+                    package_type: PackageType::Docker,
+                    package_specific_id: package_specific_id.to_owned(),
+                    num_artifacts: 2,
+                    package_specific_artifact_id: package_specific_artifact_id.to_owned(),
+                    // distinct hashes, artifacts with the same hash share their storage
+                    artifact_hash: format!("{}_hash", package_specific_artifact_id),
+                })
+                .await
+                .unwrap()
+                .0;
+            if stored {
+                artifact_service
+                    .put_artifact(&transparency_log.artifact_id, get_file_reader().unwrap())
+                    .await
+                    .unwrap();
             }
-        });
+        }
+
+        let artifact_ids = |artifact_page: &ArtifactPage| {
+            artifact_page
+                .artifacts
+                .iter()
+                .map(|artifact| artifact.package_specific_artifact_id.clone())
+                .collect::<Vec<_>>()
+        };
+
+        let artifact_page = artifact_service
+            .search_artifacts(&ArtifactQuery {
+                package_type: Some(PackageType::Docker),
+                name: Some("library/alpine".to_owned()),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(artifact_page.total, 3);
+        assert_eq!(
+            artifact_ids(&artifact_page),
+            vec![
+                "library/alpine@sha256:1",
+                "library/alpine@sha256:2",
+                "library/alpine@sha256:3"
+            ]
+        );
+        assert_eq!(
+            artifact_page.artifacts[0].size,
+            get_file_reader().unwrap().metadata().unwrap().len()
+        );
+
+        // names are normalized like the coordinates users pull with
+        let artifact_page = artifact_service
+            .search_artifacts(&ArtifactQuery {
+                package_type: Some(PackageType::Docker),
+                name: Some("docker.io/alpine".to_owned()),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(artifact_page.total, 3);
+
+        let artifact_page = artifact_service
+            .search_artifacts(&ArtifactQuery {
+                version: Some("3.15.2".to_owned()),
+                offset: 1,
+                limit: Some(1),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(artifact_page.total, 2);
+        assert_eq!(artifact_page.offset, 1);
+        assert_eq!(
+            artifact_ids(&artifact_page),
+            vec!["library/alpine@sha256:2"]
+        );
+
+        let artifact_page = artifact_service
+            .search_artifacts(&ArtifactQuery {
+                package_type: Some(PackageType::Maven2),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(artifact_page.total, 0);
+
+        test_util::tests::teardown(tmp_dir);
+    }
+
+    #[tokio::test]
+    async fn test_get_artifact_stream() {
+        let tmp_dir = test_util::tests::setup();
+
+        let (mut artifact_service, mut blockchain_event_receiver, ..) =
+            test_util::tests::create_artifact_service(&tmp_dir);
 
         tokio::spawn(async move {
             loop {
@@ -602,83 +5170,154 @@ mod tests {
             }
         });
 
-        let mut hasher = Sha256::new();
-        hasher.update(b"SAMPLE_DATA");
-        let random_hash = hex::encode(hasher.finalize());
-
         let package_type = PackageType::Docker;
-        let package_specific_id = "package_specific_id";
-        let package_specific_artifact_id = "package_specific_artifact_id";
-        artifact_service
+        let package_specific_artifact_id =
+            format!("library/alpine@sha256:{}", hex::encode(VALID_ARTIFACT_HASH));
+        let transparency_log = artifact_service
             .transparency_log_service
             .add_artifact(AddArtifactRequest {
                 package_type,
-                package_specific_id: package_specific_id.to_owned(),
+                package_specific_id: "library/alpine:latest".to_owned(),
                 num_artifacts: 8,
-                package_specific_artifact_id: package_specific_artifact_id.to_owned(),
-                artifact_hash: random_hash.clone(),
+                package_specific_artifact_id: package_specific_artifact_id.clone(),
+                artifact_hash: hex::encode(VALID_ARTIFACT_HASH),
             })
             .await
+            .unwrap()
+            .0;
+
+        let mut artifact = Vec::new();
+        get_file_reader()
+            .unwrap()
+            .read_to_end(&mut artifact)
+            .unwrap();
+        artifact_service
+            .put_artifact(
+                &transparency_log.artifact_id,
+                io::Cursor::new(artifact.clone()),
+            )
+            .await
             .unwrap();
 
-        let future = {
+        let artifact_stream = artifact_service
+            .get_artifact_stream(package_type, &package_specific_artifact_id, None)
+            .await
+            .unwrap();
+
+        assert_eq!(artifact_stream.len, artifact.len() as u64);
+        assert_eq!(artifact_stream.content_range, None);
+        assert_eq!(artifact_stream.into_bytes().await.unwrap(), artifact);
+        assert_eq!(
             artifact_service
-                .get_artifact(package_type, package_specific_artifact_id)
-                .await
-        };
-        let result = task::spawn_blocking(|| future).await.unwrap();
-        assert!(result.is_ok());
+                .get_access_stats(package_type, "library/alpine:latest")
+                .pull_count,
+            1
+        );
+
+        let artifact_stream = artifact_service
+            .get_artifact_stream(
+                package_type,
+                &package_specific_artifact_id,
+                Some(ByteRange::FromTo(10, 19)),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(artifact_stream.len, 10);
+        assert_eq!(
+            artifact_stream.content_range.unwrap().to_string(),
+            format!("bytes 10-19/{}", artifact.len())
+        );
+        assert_eq!(
+            artifact_stream.into_bytes().await.unwrap(),
+            artifact[10..20]
+        );
+
+        let result = artifact_service
+            .get_artifact_stream(
+                package_type,
+                &package_specific_artifact_id,
+                Some(ByteRange::From(artifact.len() as u64)),
+            )
+            .await;
+        assert_eq!(
+            result
+                .err()
+                .unwrap()
+                .downcast::<RangeNotSatisfiable>()
+                .unwrap(),
+            RangeNotSatisfiable {
+                len: artifact.len() as u64
+            }
+        );
 
         test_util::tests::teardown(tmp_dir);
     }
 
-    #[tokio::test(flavor = "multi_thread")]
-    async fn test_get_from_peers_with_no_providers() {
+    #[tokio::test]
+    async fn test_get_artifact_stream_with_exhausted_transfer_budget() {
         let tmp_dir = test_util::tests::setup();
 
-        let (mut artifact_service, _, _, mut p2p_command_receiver) =
-            test_util::tests::create_artifact_service(&tmp_dir);
+        let network = FakeNetwork::new();
+        let transfer_budget = TransferBudget::new(Some(1), None);
+        let mut artifact_service = create_fake_artifact_service(&tmp_dir, &network)
+            .with_transfer_budget(transfer_budget.clone());
 
-        tokio::spawn(async move {
-            tokio::select! {
-                command = p2p_command_receiver.recv() => {
-                    match command {
-                        Some(Command::ListProviders { sender, .. }) => {
-                            let _ = sender.send(Default::default());
-                        },
-                        _ => panic!("Command must match Command::ListProviders"),
-                    }
-                }
-            }
-        });
+        let transparency_log =
+            add_maven_artifact(&artifact_service, "booster-1.0.jar", b"artifact").await;
+        artifact_service
+            .put_artifact(&transparency_log.artifact_id, "artifact".as_bytes())
+            .await
+            .unwrap();
 
-        let mut hasher = Sha256::new();
-        hasher.update(b"SAMPLE_DATA");
-        let hash_bytes = hasher.finalize();
-        let artifact_id = hex::encode(hash_bytes);
+        let artifact_stream = artifact_service
+            .get_artifact_stream(PackageType::Maven2, "booster-1.0.jar", None)
+            .await
+            .unwrap();
+        assert_eq!(transfer_budget.open_files(), 1);
+        assert_eq!(transfer_budget.in_flight_bytes(), 8);
 
-        let future = { artifact_service.get_artifact_from_peers(&artifact_id).await };
-        let result = task::spawn_blocking(|| future).await.unwrap();
-        assert!(result.is_err());
+        let result = artifact_service
+            .get_artifact_stream_or_build(
+                PackageType::Maven2,
+                "booster:booster:1.0",
+                "booster-1.0.jar",
+                None,
+            )
+            .await;
+        assert!(result.err().unwrap().is::<BudgetExceeded>());
+        assert!(network.requested_builds().is_empty());
+        assert!(artifact_service
+            .get_artifact_chunk_locally(
+                &transparency_log.artifact_id,
+                ArtifactChunk { offset: 0, len: 4 }
+            )
+            .await
+            .is_err());
+
+        assert_eq!(artifact_stream.into_bytes().await.unwrap(), b"artifact");
+        assert_eq!(transfer_budget.open_files(), 0);
+        assert!(artifact_service
+            .get_artifact_stream(PackageType::Maven2, "booster-1.0.jar", None)
+            .await
+            .is_ok());
 
         test_util::tests::teardown(tmp_dir);
     }
 
     #[tokio::test]
-    async fn test_verify_artifact_succeeds_when_hashes_same() {
+    async fn test_get_artifact_stream_removes_corrupt_local_artifact() {
         let tmp_dir = test_util::tests::setup();
 
         let (mut artifact_service, mut blockchain_event_receiver, _, mut p2p_command_receiver) =
             test_util::tests::create_artifact_service(&tmp_dir);
 
         tokio::spawn(async move {
-            loop {
-                match p2p_command_receiver.recv().await {
-                    Some(Command::ListPeers { sender, .. }) => {
-                        let _ = sender.send(HashSet::new());
-                    }
-                    _ => panic!("Command must match Command::ListPeers"),
+            match p2p_command_receiver.recv().await {
+                Some(Command::StopProviding { sender, .. }) => {
+                    let _ = sender.send(());
                 }
+                _ => panic!("Command must match Command::StopProviding"),
             }
         });
 
@@ -693,55 +5332,48 @@ mod tests {
             }
         });
 
-        let mut hasher1 = Sha256::new();
-        hasher1.update(b"SAMPLE_DATA");
-        let random_hash = hex::encode(hasher1.finalize());
-
         let package_type = PackageType::Docker;
-        let package_specific_id = "package_specific_id";
-        let package_specific_artifact_id = "package_specific_artifact_id";
-        artifact_service
+        let package_specific_artifact_id =
+            format!("library/alpine@sha256:{}", hex::encode(VALID_ARTIFACT_HASH));
+        let transparency_log = artifact_service
             .transparency_log_service
             .add_artifact(AddArtifactRequest {
                 package_type,
-                package_specific_id: package_specific_id.to_owned(),
+                package_specific_id: "library/alpine:latest".to_owned(),
                 num_artifacts: 8,
-                package_specific_artifact_id: package_specific_artifact_id.to_owned(),
-                artifact_hash: random_hash,
+                package_specific_artifact_id: package_specific_artifact_id.clone(),
+                artifact_hash: hex::encode(VALID_ARTIFACT_HASH),
             })
             .await
-            .unwrap();
+            .unwrap()
+            .0;
 
-        let transparency_log = artifact_service
-            .transparency_log_service
-            .get_artifact(&package_type, package_specific_artifact_id)
+        artifact_service
+            .put_artifact(&transparency_log.artifact_id, "corrupt artifact".as_bytes())
+            .await
             .unwrap();
 
         let result = artifact_service
-            .verify_artifact(&transparency_log, b"SAMPLE_DATA")
+            .get_artifact_stream(package_type, &package_specific_artifact_id, None)
             .await;
-        assert!(result.is_ok());
+
+        assert!(result.is_err());
+        assert!(
+            !artifact_service
+                .artifact_storage
+                .contains_artifact(&transparency_log.artifact_id)
+                .await
+        );
 
         test_util::tests::teardown(tmp_dir);
     }
 
     #[tokio::test]
-    async fn test_verify_artifact_fails_when_hashes_differ() {
-        let tmp_dir = test_util::tests::setup();
-
-        let (mut artifact_service, mut blockchain_event_receiver, _, mut p2p_command_receiver) =
-            test_util::tests::create_artifact_service(&tmp_dir);
-
-        tokio::spawn(async move {
-            loop {
-                match p2p_command_receiver.recv().await {
-                    Some(Command::ListPeers { sender, .. }) => {
-                        let _ = sender.send(HashSet::new());
-                    }
-                    _ => panic!("Command must match Command::ListPeers"),
-                }
-            }
-        });
+    async fn test_authorize_artifact_request_with_allowlist() {
+        let tmp_dir = test_util::tests::setup();
+
+        let (artifact_service, mut blockchain_event_receiver, ..) =
+            test_util::tests::create_artifact_service(&tmp_dir);
 
         tokio::spawn(async move {
             loop {
@@ -754,52 +5386,34 @@ mod tests {
             }
         });
 
-        let mut hasher1 = Sha256::new();
-        hasher1.update(b"SAMPLE_DATA");
-        let random_hash = hex::encode(hasher1.finalize());
-
-        let mut hasher2 = Sha256::new();
-        hasher2.update(b"OTHER_SAMPLE_DATA");
-        let random_other_hash = hex::encode(hasher2.finalize());
+        let allowed_peer_id = PublicKey::Ed25519(Keypair::generate().public()).to_peer_id();
+        let other_peer_id = PublicKey::Ed25519(Keypair::generate().public()).to_peer_id();
+        let artifact_service = artifact_service.with_artifact_request_policy(
+            ArtifactRequestPolicy::Allowlist(HashSet::from([allowed_peer_id])),
+        );
 
-        let package_type = PackageType::Docker;
-        let package_specific_id = "package_specific_id";
-        let package_specific_artifact_id = "package_specific_artifact_id";
-        artifact_service
+        let transparency_log = artifact_service
             .transparency_log_service
             .add_artifact(AddArtifactRequest {
-                package_type,
-                package_specific_id: package_specific_id.to_owned(),
+                package_type: PackageType::Docker,
+                package_specific_id: "package_specific_id".to_owned(),
                 num_artifacts: 8,
-                package_specific_artifact_id: package_specific_artifact_id.to_owned(),
-                artifact_hash: random_hash.clone(),
+                package_specific_artifact_id: "package_specific_artifact_id".to_owned(),
+                artifact_hash: hex::encode(VALID_ARTIFACT_HASH),
             })
             .await
-            .unwrap();
-
-        let transparency_log = artifact_service
-            .transparency_log_service
-            .get_artifact(&package_type, package_specific_artifact_id)
-            .unwrap();
-
-        let verify_error = artifact_service
-            .verify_artifact(&transparency_log, b"OTHER_SAMPLE_DATA")
-            .await
-            .expect_err("Verify artifact should have failed.");
-        match verify_error {
-            TransparencyLogError::InvalidHash {
-                id,
-                invalid_hash,
-                actual_hash,
-            } => {
-                assert_eq!(id, package_specific_artifact_id.to_string());
-                assert_eq!(invalid_hash, random_other_hash);
-                assert_eq!(actual_hash, random_hash);
-            }
-            e => {
-                panic!("Invalid Error encountered: {:?}", e);
-            }
-        }
+            .unwrap()
+            .0;
+
+        assert!(artifact_service
+            .authorize_artifact_request(&allowed_peer_id, &transparency_log.artifact_id)
+            .is_ok());
+        assert!(artifact_service
+            .authorize_artifact_request(&other_peer_id, &transparency_log.artifact_id)
+            .is_err());
+        assert!(artifact_service
+            .authorize_artifact_request(&allowed_peer_id, "unknown_artifact_id")
+            .is_err());
 
         test_util::tests::teardown(tmp_dir);
     }
@@ -889,7 +5503,7 @@ mod tests {
         let tmp_dir = test_util::tests::setup();
 
         let (p2p_client, mut p2p_command_receiver) = test_util::tests::create_p2p_client();
-        let (artifact_service, mut blockchain_event_receiver, mut build_event_receiver) =
+        let (mut artifact_service, mut blockchain_event_receiver, mut build_event_receiver) =
             test_util::tests::create_artifact_service_with_p2p_client(&tmp_dir, p2p_client.clone());
 
         tokio::spawn(async move {
@@ -943,9 +5557,249 @@ mod tests {
 
         assert_eq!(result, String::from("build_start_ok"));
 
+        // an artifact that is not available is built
+        let result = artifact_service
+            .get_artifact_or_build(package_type, package_specific_id, "package_specific_id@1")
+            .await
+            .unwrap();
+        assert!(
+            matches!(result, ArtifactOrBuild::BuildRequested { build_id } if build_id == "build_start_ok")
+        );
+
+        test_util::tests::teardown(tmp_dir);
+    }
+
+    #[tokio::test]
+    async fn test_import_artifacts() {
+        let tmp_dir = test_util::tests::setup();
+
+        let (p2p_client, mut p2p_command_receiver) = test_util::tests::create_p2p_client();
+        let (mut artifact_service, mut blockchain_event_receiver, _) =
+            test_util::tests::create_artifact_service_with_p2p_client(&tmp_dir, p2p_client.clone());
+
+        tokio::spawn(async move {
+            loop {
+                match p2p_command_receiver.recv().await {
+                    Some(Command::Provide { sender, .. }) => {
+                        let _ = sender.send(());
+                    }
+                    _ => panic!("Command must match Command::Provide"),
+                }
+            }
+        });
+
+        tokio::spawn(async move {
+            loop {
+                match blockchain_event_receiver.recv().await {
+                    Some(BlockchainEvent::AddBlock { sender, .. }) => {
+                        let _ = sender.send(Ok(()));
+                    }
+                    _ => panic!("BlockchainEvent must match BlockchainEvent::AddBlock"),
+                }
+            }
+        });
+
+        let package_specific_id = "com.myorg:my-artifact:1.0.0";
+        let artifacts = vec![(
+            "com.myorg/my-artifact/1.0.0/my-artifact-1.0.0.jar".to_owned(),
+            b"jar".to_vec(),
+        )];
+
+        let error = artifact_service
+            .import_artifacts(
+                PackageType::Maven2,
+                package_specific_id,
+                "maven-repo",
+                artifacts.clone(),
+            )
+            .await
+            .unwrap_err();
+        assert!(matches!(error, BuildError::InitializationFailed(_)));
+
+        artifact_service
+            .transparency_log_service
+            .add_authorized_node(p2p_client.local_peer_id)
+            .await
+            .unwrap();
+
+        artifact_service
+            .import_artifacts(
+                PackageType::Maven2,
+                package_specific_id,
+                "maven-repo",
+                artifacts.clone(),
+            )
+            .await
+            .unwrap();
+
+        let transparency_log = artifact_service
+            .transparency_log_service
+            .get_artifact(&PackageType::Maven2, &artifacts[0].0)
+            .unwrap();
+        assert_eq!(transparency_log.provenance(), Provenance::Imported);
+        assert_eq!(transparency_log.source_id, "import:maven-repo");
+        assert_eq!(
+            transparency_log.artifact_hash,
+            hex::encode(Sha256::digest(b"jar"))
+        );
+        assert_eq!(
+            artifact_service
+                .get_artifact_locally(&transparency_log.artifact_id)
+                .await
+                .unwrap(),
+            b"jar".to_vec()
+        );
+
+        let error = artifact_service
+            .import_artifacts(
+                PackageType::Maven2,
+                package_specific_id,
+                "maven-repo",
+                artifacts,
+            )
+            .await
+            .unwrap_err();
+        assert!(matches!(error, BuildError::ArtifactAlreadyExists(_)));
+
+        test_util::tests::teardown(tmp_dir);
+    }
+
+    #[tokio::test]
+    async fn test_check_package() {
+        let tmp_dir = test_util::tests::setup();
+
+        let (p2p_client, mut p2p_command_receiver) = test_util::tests::create_p2p_client();
+        let (mut artifact_service, mut blockchain_event_receiver, _) =
+            test_util::tests::create_artifact_service_with_p2p_client(&tmp_dir, p2p_client.clone());
+
+        let local_peer_id = p2p_client.local_peer_id;
+        tokio::spawn(async move {
+            loop {
+                match p2p_command_receiver.recv().await {
+                    Some(Command::Provide { sender, .. }) => {
+                        let _ = sender.send(());
+                    }
+                    Some(Command::ListProviders { sender, .. }) => {
+                        let _ = sender.send(HashSet::from([local_peer_id]));
+                    }
+                    other => panic!(
+                        "Command must match Command::Provide or Command::ListProviders, was: {:?}",
+                        other
+                    ),
+                }
+            }
+        });
+
+        // the blockchain commits every payload in a block signed by an authorized committer
+        let committer_keypair = Keypair::generate();
+        let committer_peer_id = PublicKey::Ed25519(committer_keypair.public()).to_peer_id();
+        // the committer approves the authorization of this node
+        artifact_service.transparency_log_service = artifact_service
+            .transparency_log_service
+            .clone()
+            .with_node_keypair(committer_keypair.clone());
+        tokio::spawn(async move {
+            let mut blocks: Vec<Block> = Vec::new();
+            loop {
+                match blockchain_event_receiver.recv().await {
+                    Some(BlockchainEvent::AddBlock { payload, sender }) => {
+                        let transaction = Transaction::new(
+                            TransactionType::Create,
+                            Address::from(committer_peer_id),
+                            payload,
+                            &committer_keypair,
+                        );
+                        blocks.push(Block::new(
+                            HashDigest::new(b""),
+                            blocks.len() as u128 + 1,
+                            vec![transaction],
+                            &committer_keypair,
+                        ));
+                        let _ = sender.send(Ok(()));
+                    }
+                    Some(BlockchainEvent::HandleQueryBlockOrdinal { sender }) => {
+                        let _ = sender.send(Ok(blocks.len() as u128));
+                    }
+                    Some(BlockchainEvent::PullBlocksLocal { sender, .. }) => {
+                        let _ = sender.send(Ok(blocks.clone()));
+                    }
+                    other => panic!("Unexpected BlockchainEvent: {:?}", other),
+                }
+            }
+        });
+
+        for peer_id in [committer_peer_id, local_peer_id] {
+            artifact_service
+                .transparency_log_service
+                .add_authorized_node(peer_id)
+                .await
+                .unwrap();
+        }
+
+        let package_specific_id = "com.myorg:my-artifact:1.0.0";
+        let error = artifact_service
+            .check_package(PackageType::Maven2, package_specific_id, true)
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            error,
+            TransparencyLogError::ArtifactNotFound { .. }
+        ));
+
+        artifact_service
+            .import_artifacts(
+                PackageType::Maven2,
+                package_specific_id,
+                "maven-repo",
+                vec![(
+                    "com.myorg/my-artifact/1.0.0/my-artifact-1.0.0.jar".to_owned(),
+                    b"jar".to_vec(),
+                )],
+            )
+            .await
+            .unwrap();
+
+        let artifact_checks = artifact_service
+            .check_package(PackageType::Maven2, package_specific_id, true)
+            .await
+            .unwrap();
+        assert_eq!(artifact_checks.len(), 1);
+        let artifact_check = &artifact_checks[0];
+        assert!(artifact_check.is_passed());
+        assert!(matches!(artifact_check.local_hash, CheckOutcome::Passed(_)));
+        assert!(matches!(
+            artifact_check.dht_availability,
+            CheckOutcome::Passed(_)
+        ));
+        assert!(matches!(
+            artifact_check.peer_retrieval,
+            CheckOutcome::Skipped(_)
+        ));
+        assert!(
+            matches!(&artifact_check.provenance, CheckOutcome::Passed(detail) if detail.starts_with("imported by authorized node"))
+        );
+
         test_util::tests::teardown(tmp_dir);
     }
 
+    #[test]
+    fn test_check_provenance_fails_for_unauthorized_committer() {
+        let keypair = Keypair::generate();
+        let peer_id = PublicKey::Ed25519(keypair.public()).to_peer_id();
+        let block = Block::new(HashDigest::new(b""), 1, vec![], &keypair);
+        let transparency_log = TransparencyLog::from(AddArtifactRequest {
+            package_type: PackageType::Docker,
+            package_specific_id: "alpine:3.15.2".to_owned(),
+            num_artifacts: 1,
+            package_specific_artifact_id: "alpine:3.15.2".to_owned(),
+            artifact_hash: "hash".to_owned(),
+        });
+
+        assert!(check_provenance(&transparency_log, None, &[peer_id]).is_failed());
+        assert!(check_provenance(&transparency_log, Some(&block), &[]).is_failed());
+        assert!(!check_provenance(&transparency_log, Some(&block), &[peer_id]).is_failed());
+    }
+
     #[tokio::test]
     async fn test_request_build_starts_on_other_authorized_node() {
         let tmp_dir = test_util::tests::setup();
@@ -1003,6 +5857,164 @@ mod tests {
         test_util::tests::teardown(tmp_dir);
     }
 
+    #[tokio::test]
+    async fn test_request_build_skips_nodes_without_the_package_type() {
+        let tmp_dir = test_util::tests::setup();
+
+        let (mut artifact_service, mut blockchain_event_receiver, _, mut p2p_command_receiver) =
+            test_util::tests::create_artifact_service(&tmp_dir);
+
+        let docker_keypair = Keypair::generate();
+        let docker_peer_id = PublicKey::Ed25519(docker_keypair.public()).to_peer_id();
+        let maven_peer_id = PeerId::random();
+        artifact_service.transparency_log_service = artifact_service
+            .transparency_log_service
+            .clone()
+            .with_node_keypair(docker_keypair);
+        tokio::spawn(async move {
+            loop {
+                match p2p_command_receiver.recv().await {
+                    Some(Command::ListPeers { sender, .. }) => {
+                        let _ = sender.send(HashSet::new());
+                    }
+                    Some(Command::RequestBuild { peer, sender, .. }) => {
+                        let _ = sender.send(Ok(peer.to_string()));
+                    }
+                    other => panic!(
+                        "Command must match Command::ListPeers or Command::RequestBuild, was: {:?}",
+                        other
+                    ),
+                }
+            }
+        });
+
+        tokio::spawn(async move {
+            loop {
+                match blockchain_event_receiver.recv().await {
+                    Some(BlockchainEvent::AddBlock { sender, .. }) => {
+                        let _ = sender.send(Ok(()));
+                    }
+                    _ => panic!("BlockchainEvent must match BlockchainEvent::AddBlock"),
+                }
+            }
+        });
+
+        for peer_id in [docker_peer_id, maven_peer_id] {
+            artifact_service
+                .transparency_log_service
+                .add_authorized_node(peer_id)
+                .await
+                .unwrap();
+        }
+        let peer_capabilities = &artifact_service.p2p_client.peer_capabilities;
+        peer_capabilities
+            .record_agent_version(&maven_peer_id, "package-types/maven2 roles/builder");
+
+        let result = artifact_service
+            .request_build(PackageType::Docker, "alpine:3.15.2".to_owned())
+            .await
+            .unwrap();
+        assert_eq!(result, docker_peer_id.to_string());
+
+        peer_capabilities.record_agent_version(&docker_peer_id, "package-types/docker roles/");
+        assert!(matches!(
+            artifact_service
+                .request_build(PackageType::Docker, "alpine:3.15.2".to_owned())
+                .await,
+            Err(BuildError::InitializationFailed(_))
+        ));
+
+        test_util::tests::teardown(tmp_dir);
+    }
+
+    #[tokio::test]
+    async fn test_request_build_skips_draining_nodes() {
+        let tmp_dir = test_util::tests::setup();
+
+        let network = FakeNetwork::new();
+        let mut artifact_service = create_fake_artifact_service(&tmp_dir, &network);
+        let draining_keypair = Keypair::generate();
+        let draining_peer_id = PublicKey::Ed25519(draining_keypair.public()).to_peer_id();
+        let other_peer_id = PeerId::random();
+        artifact_service.transparency_log_service = artifact_service
+            .transparency_log_service
+            .clone()
+            .with_node_keypair(draining_keypair);
+        for peer_id in [draining_peer_id, other_peer_id] {
+            artifact_service
+                .transparency_log_service
+                .add_authorized_node(peer_id)
+                .await
+                .unwrap();
+        }
+
+        let peer_maintenance = &artifact_service.p2p_client.peer_maintenance;
+        peer_maintenance.record(
+            &draining_peer_id,
+            MaintenanceAnnouncement::new(Duration::from_secs(3600), None),
+        );
+        artifact_service
+            .request_build(PackageType::Docker, "alpine:3.15.2".to_owned())
+            .await
+            .unwrap();
+
+        peer_maintenance.record(
+            &other_peer_id,
+            MaintenanceAnnouncement::new(Duration::from_secs(3600), None),
+        );
+        assert!(matches!(
+            artifact_service
+                .request_build(PackageType::Docker, "alpine:3.16.0".to_owned())
+                .await,
+            Err(BuildError::InitializationFailed(_))
+        ));
+
+        assert_eq!(
+            network
+                .requested_builds()
+                .into_iter()
+                .map(|(peer_id, _)| peer_id)
+                .collect::<Vec<_>>(),
+            vec![other_peer_id]
+        );
+
+        test_util::tests::teardown(tmp_dir);
+    }
+
+    #[tokio::test]
+    async fn test_monitor_mode_audits_blocks_and_refuses_builds() {
+        let tmp_dir = test_util::tests::setup();
+
+        let (artifact_service, ..) = test_util::tests::create_artifact_service(&tmp_dir);
+        let alert_service = AlertService::new(vec![]).unwrap();
+        let artifact_service = artifact_service
+            .with_alert_service(alert_service.clone())
+            .with_monitor_mode(true);
+
+        assert!(matches!(
+            artifact_service
+                .request_build(PackageType::Docker, "alpine:3.15.2".to_owned())
+                .await,
+            Err(BuildError::InitializationFailed(_))
+        ));
+
+        let keypair = Keypair::generate();
+        let mut block = Block::new(HashDigest::new(b""), 1, vec![], &keypair);
+        assert!(artifact_service.audit_block(&block).unwrap().is_empty());
+
+        block.transactions.push(Transaction::new(
+            TransactionType::Create,
+            block.header.committer,
+            b"not a transparency log".to_vec(),
+            &keypair,
+        ));
+        let inconsistencies = artifact_service.audit_block(&block).unwrap();
+        assert_eq!(inconsistencies.len(), 2);
+        assert_eq!(alert_service.recent_alerts().len(), 2);
+
+        test_util::tests::teardown(tmp_dir);
+    }
+
     fn get_file_reader() -> Result<File, anyhow::Error> {
         // test artifact file in resources/test dir
         let mut curr_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
@@ -14,16 +14,19 @@
    limitations under the License.
 */
 
+use super::blob_store::local::LocalBlobStore;
+use super::blob_store::{BlobReader, BlobStore};
+use super::mmap::{MappedArtifact, MappedArtifacts, MmapConfig};
+use super::quota::StorageQuota;
 use crate::util::env_util::read_var;
-use anyhow::{anyhow, Context, Result};
+use anyhow::{Context, Result};
 use lazy_static::lazy_static;
-use log::{debug, error, info};
-use std::fs::{File, OpenOptions};
-use std::io::{self, BufWriter, Read, Write};
+use log::{debug, error, info, warn};
+use multihash::Hasher;
+use std::io::{self, Read};
 use std::panic::UnwindSafe;
-use std::path::{Path, PathBuf};
-
-const FILE_EXTENSION: &str = "file";
+use std::path::Path;
+use std::sync::Arc;
 
 lazy_static! {
     pub static ref ARTIFACTS_DIR: String = {
@@ -65,108 +68,427 @@ fn log_static_initialization_failure<T: UnwindSafe>(
     }
 }
 
+/// The storage of the artifacts of this node. The bytes of the artifacts are
+/// kept in a [`BlobStore`], which is a local directory unless another store
+/// is configured. When a maximum size is configured, the least recently used
+/// artifacts become candidates for eviction once it is exceeded. When memory
+/// mapping is configured, large artifacts that are kept in local files are
+/// served from memory-mapped files.
 #[derive(Clone)]
 pub struct ArtifactStorage {
-    repository_path: PathBuf,
+    blob_store: Arc<dyn BlobStore>,
+    quota: Option<Arc<StorageQuota>>,
+    mapped_artifacts: Option<Arc<MappedArtifacts>>,
 }
 
 impl ArtifactStorage {
+    /// Create an artifact storage that keeps the artifacts in the directory
+    /// `repository_path`.
     pub fn new<P: AsRef<Path>>(repository_path: P) -> Result<ArtifactStorage, anyhow::Error> {
-        let absolute_path = repository_path.as_ref().to_path_buf().canonicalize()?;
-        if absolute_path.is_dir() {
-            Ok(ArtifactStorage {
-                repository_path: absolute_path,
-            })
-        } else {
-            error!(
-                "Unable to create ArtifactManager with inaccessible directory: {:?}",
-                absolute_path
-            );
-            Err(anyhow!("Not an accessible directory: {:?}", absolute_path))
+        Ok(ArtifactStorage::with_blob_store(LocalBlobStore::new(
+            repository_path,
+        )?))
+    }
+
+    /// Create an artifact storage that keeps the artifacts in `blob_store`.
+    pub fn with_blob_store(blob_store: impl BlobStore + 'static) -> ArtifactStorage {
+        ArtifactStorage {
+            blob_store: Arc::new(blob_store),
+            quota: None,
+            mapped_artifacts: None,
         }
     }
 
-    // The base file path (no extension on the file name) that will correspond to this artifact id.
-    // The structure of the path is: `repo_root_dir/artifact_id`. This consists of the artifact
-    // repository root directory and a file name that is the artifact id. For example:
-    // `pyrsia-artifacts/e131322a-0c72-454d-b7a0-dcdb53c1bbdf
-    //
-    // TODO To support nodes that will store many files, we need a scheme that will start separating
-    // files by subdirectories based on the first n bytes of the artifact id.
-    fn base_file_path(&self, artifact_id: &str) -> PathBuf {
-        let mut path: PathBuf = PathBuf::from(&self.repository_path);
-        path.push(artifact_id);
-        path
+    /// Limit the total size of the stored artifacts to `max_size` bytes. The
+    /// artifacts that are already stored are accounted for in the order in
+    /// which they are listed, as their last use is not known. This scans the
+    /// storage with blocking IO, it is meant to be called when a node starts.
+    pub fn with_max_size(mut self, max_size: u64) -> Result<ArtifactStorage> {
+        let quota = StorageQuota::new(max_size);
+        for artifact_id in self.artifact_ids()? {
+            quota.record(&artifact_id, self.blob_store.size(&artifact_id)?);
+        }
+        info!(
+            "The stored artifacts take up {} of the maximum of {} bytes",
+            quota.total_size(),
+            max_size
+        );
+        self.quota = Some(Arc::new(quota));
+        Ok(self)
     }
 
-    fn artifact_file_path(&self, artifact_id: &str) -> io::Result<PathBuf> {
-        let mut base_file_path: PathBuf = self.base_file_path(artifact_id);
-        base_file_path.set_extension(FILE_EXTENSION);
-        Ok(base_file_path)
+    /// Serve the artifacts that are selected by `config` from memory-mapped
+    /// files. This only applies to blob stores that keep the plain bytes of
+    /// artifacts in local files.
+    pub fn with_memory_mapping(mut self, config: MmapConfig) -> ArtifactStorage {
+        self.mapped_artifacts = Some(Arc::new(MappedArtifacts::new(config)));
+        self
     }
 
-    fn create_artifact_file(&self, artifact_id: &str) -> io::Result<File> {
-        let artifact_file_path = self.artifact_file_path(artifact_id)?;
-        OpenOptions::new()
-            .write(true)
-            .create_new(true)
-            .open(artifact_file_path)
+    /// The configured maximum size of the stored artifacts in bytes.
+    pub fn max_size(&self) -> Option<u64> {
+        self.quota.as_ref().map(|quota| quota.max_size())
     }
 
-    /// Push an artifact to this node's local repository.
+    /// Push an artifact to this node's repository.
     /// Parameters are:
     /// * reader — An object that this method will use to read the bytes of the artifact being
-    ///            pushed.
+    ///   pushed.
     /// * artifact_id — The id that the pushed artifact is expected to have.
-    pub fn push_artifact(&self, reader: &mut impl Read, artifact_id: &str) -> io::Result<()> {
+    ///
+    /// The artifact only becomes visible once all of its bytes were written, a failed push
+    /// never leaves a partial artifact behind.
+    pub async fn push_artifact(
+        &self,
+        mut reader: impl Read + Send + 'static,
+        artifact_id: &str,
+    ) -> io::Result<()> {
         info!(
             "An artifact is being pushed to the artifact manager {}",
             artifact_id
         );
-
-        let artifact_file = self.create_artifact_file(artifact_id)?;
-        let mut writer = BufWriter::new(artifact_file);
-        io::copy(reader, &mut writer)?;
-        writer.flush()
+        let size = self
+            .blocking(artifact_id, move |blob_store, artifact_id| {
+                blob_store.push(&mut reader, artifact_id)?;
+                blob_store.size(artifact_id)
+            })
+            .await?;
+        if let Some(quota) = &self.quota {
+            quota.record(artifact_id, size);
+        }
+        Ok(())
     }
 
-    /// Pull an artifact. The current implementation only looks in the local node's repository.
-    pub fn pull_artifact(&self, artifact_id: &str) -> io::Result<impl Read> {
+    /// Pull an artifact. The current implementation only looks in the node's own repository.
+    /// The returned reader does blocking IO, it must be read where blocking is allowed.
+    pub async fn pull_artifact(&self, artifact_id: &str) -> io::Result<BlobReader> {
         info!(
             "An artifact is being pulled from the artifact manager {}",
             artifact_id
         );
-        let artifact_file_path = self.artifact_file_path(artifact_id)?;
-        File::open(artifact_file_path)
+        let reader = match self.mapped_artifact(artifact_id).await {
+            Some(mapped_artifact) => mapped_artifact.reader(0, mapped_artifact.len())?,
+            None => {
+                self.blocking(artifact_id, |blob_store, artifact_id| {
+                    blob_store.pull(artifact_id)
+                })
+                .await?
+            }
+        };
+        self.touch(artifact_id);
+        Ok(reader)
     }
 
-    /// List all artifacts found in the repository path.
-    /// The current implementation only looks in the local node's repository.
-    pub fn list_artifacts(&self) -> Result<Vec<PathBuf>> {
-        let root: PathBuf = PathBuf::from(&self.repository_path);
-        debug!("Finding stored artifacts");
-        if root.is_dir() {
-            let vec: Vec<PathBuf> = std::fs::read_dir(root)?
-                .filter_map(|entry| {
-                    let path = entry.unwrap().path();
-                    match path.extension() {
-                        Some(ext) if ext.eq(FILE_EXTENSION) => Some(path),
-                        _ => None,
-                    }
+    /// Pull `len` bytes of an artifact starting at `offset`, for serving a
+    /// part of a large artifact.
+    pub async fn pull_artifact_range(
+        &self,
+        artifact_id: &str,
+        offset: u64,
+        len: u64,
+    ) -> io::Result<BlobReader> {
+        debug!(
+            "A range of {} bytes at offset {} of artifact {} is being pulled from the artifact manager",
+            len, offset, artifact_id
+        );
+        let reader = match self.mapped_artifact(artifact_id).await {
+            Some(mapped_artifact) => mapped_artifact.reader(offset, len)?,
+            None => {
+                self.blocking(artifact_id, move |blob_store, artifact_id| {
+                    blob_store.pull_range(artifact_id, offset, len)
                 })
-                .collect();
-            debug!("There are {} stored artifacts ", vec.len());
-            return Ok(vec);
+                .await?
+            }
+        };
+        self.touch(artifact_id);
+        Ok(reader)
+    }
+
+    /// Read a complete artifact into memory.
+    pub async fn read_artifact(&self, artifact_id: &str) -> io::Result<Vec<u8>> {
+        let mut reader = self.pull_artifact(artifact_id).await?;
+        run_blocking(move || {
+            let mut content = Vec::new();
+            reader.read_to_end(&mut content)?;
+            Ok(content)
+        })
+        .await
+    }
+
+    /// Read `len` bytes of an artifact starting at `offset` into memory.
+    pub async fn read_artifact_range(
+        &self,
+        artifact_id: &str,
+        offset: u64,
+        len: u64,
+    ) -> io::Result<Vec<u8>> {
+        let mut reader = self.pull_artifact_range(artifact_id, offset, len).await?;
+        run_blocking(move || {
+            let mut content = Vec::new();
+            reader.read_to_end(&mut content)?;
+            Ok(content)
+        })
+        .await
+    }
+
+    /// The size of an artifact in bytes.
+    pub async fn artifact_size(&self, artifact_id: &str) -> io::Result<u64> {
+        self.blocking(artifact_id, |blob_store, artifact_id| {
+            blob_store.size(artifact_id)
+        })
+        .await
+    }
+
+    /// Remove an artifact from this node's repository.
+    pub async fn remove_artifact(&self, artifact_id: &str) -> io::Result<()> {
+        info!(
+            "An artifact is being removed from the artifact manager {}",
+            artifact_id
+        );
+        self.forget_mapping(artifact_id);
+        self.blocking(artifact_id, |blob_store, artifact_id| {
+            blob_store.remove(artifact_id)
+        })
+        .await?;
+        if let Some(quota) = &self.quota {
+            quota.forget(artifact_id);
         }
-        Ok(Vec::new())
+        Ok(())
     }
+
+    /// Move an artifact to another artifact_id. When an artifact with the new
+    /// artifact_id is already stored, the artifact is removed instead.
+    pub async fn move_artifact(&self, artifact_id: &str, new_artifact_id: &str) -> io::Result<()> {
+        info!(
+            "An artifact is being moved from {} to {} in the artifact manager",
+            artifact_id, new_artifact_id
+        );
+        self.forget_mapping(artifact_id);
+        let new_artifact_id_owned = new_artifact_id.to_owned();
+        let new_size = self
+            .blocking(artifact_id, move |blob_store, artifact_id| {
+                blob_store.rename(artifact_id, &new_artifact_id_owned)?;
+                blob_store.size(&new_artifact_id_owned)
+            })
+            .await?;
+        if let Some(quota) = &self.quota {
+            quota.forget(artifact_id);
+            quota.record(new_artifact_id, new_size);
+        }
+        Ok(())
+    }
+
+    /// Calculate the hex encoded sha256 hash of a stored artifact. The
+    /// artifact is read in chunks, and unlike a pull this does not count as a
+    /// use of the artifact, so background checks leave the eviction order
+    /// untouched.
+    pub async fn hash_artifact(&self, artifact_id: &str) -> io::Result<String> {
+        self.blocking(artifact_id, |blob_store, artifact_id| {
+            let mut reader = blob_store.pull(artifact_id)?;
+            let mut sha256 = multihash::Sha2_256::default();
+            let mut buf = vec![0; 64 * 1024];
+            loop {
+                let len = reader.read(&mut buf)?;
+                if len == 0 {
+                    break;
+                }
+                sha256.update(&buf[..len]);
+            }
+            Ok(hex::encode(sha256.finalize()))
+        })
+        .await
+    }
+
+    /// The sha256 hash of an artifact that is served from a memory-mapped
+    /// file, as calculated when it was mapped. The pages of the mapping are
+    /// spot-checked against their checksums, so the hash does not have to be
+    /// calculated again for every request. Returns `None` for artifacts that
+    /// are not mapped.
+    pub async fn mapped_artifact_hash(&self, artifact_id: &str) -> Option<String> {
+        self.mapped_artifact(artifact_id)
+            .await
+            .map(|mapped_artifact| mapped_artifact.sha256().to_owned())
+    }
+
+    /// Returns true if the artifact is stored in the repository.
+    pub async fn contains_artifact(&self, artifact_id: &str) -> bool {
+        self.blocking(artifact_id, |blob_store, artifact_id| {
+            Ok(blob_store.contains(artifact_id))
+        })
+        .await
+        .unwrap_or(false)
+    }
+
+    /// Iterate over the ids of all artifacts found in the repository.
+    /// The ids are read lazily, so repositories with a very large number
+    /// of artifacts can be processed in batches without listing them first.
+    /// Reading the ids is blocking IO, prefer
+    /// [`list_artifacts`](Self::list_artifacts) in async code.
+    pub fn artifact_ids(&self) -> Result<impl Iterator<Item = String>> {
+        debug!("Iterating over stored artifacts");
+        Ok(self.blob_store.ids()?)
+    }
+
+    /// Read the ids of all artifacts found in the repository in batches of
+    /// `batch_size`, so that repositories with a very large number of
+    /// artifacts can be processed without listing them first.
+    pub fn artifact_id_batches(&self, batch_size: usize) -> Result<ArtifactIdBatches> {
+        Ok(ArtifactIdBatches {
+            artifact_ids: Some(self.blob_store.ids()?),
+            batch_size: batch_size.max(1),
+        })
+    }
+
+    /// List the ids of all artifacts found in the repository.
+    pub async fn list_artifacts(&self) -> Result<Vec<String>> {
+        debug!("Finding stored artifacts");
+        let blob_store = self.blob_store.clone();
+        let artifact_ids: Vec<String> =
+            run_blocking(move || Ok(blob_store.ids()?.collect())).await?;
+        debug!("There are {} stored artifacts ", artifact_ids.len());
+        Ok(artifact_ids)
+    }
+
+    /// The number of bytes that can still be stored: the free space of the
+    /// blob store, limited by the configured maximum size.
+    pub async fn available_space(&self) -> Option<u64> {
+        let blob_store = self.blob_store.clone();
+        let available_space = run_blocking(move || Ok(blob_store.available_space()))
+            .await
+            .ok()
+            .flatten();
+        let remaining_quota = self
+            .quota
+            .as_ref()
+            .map(|quota| quota.max_size().saturating_sub(quota.total_size()));
+        match (available_space, remaining_quota) {
+            (Some(available_space), Some(remaining_quota)) => {
+                Some(available_space.min(remaining_quota))
+            }
+            (available_space, remaining_quota) => available_space.or(remaining_quota),
+        }
+    }
+
+    /// Protect an artifact from being evicted when the maximum size is
+    /// exceeded.
+    pub fn pin_artifact(&self, artifact_id: &str) {
+        if let Some(quota) = &self.quota {
+            quota.pin(artifact_id);
+        }
+    }
+
+    pub fn unpin_artifact(&self, artifact_id: &str) {
+        if let Some(quota) = &self.quota {
+            quota.unpin(artifact_id);
+        }
+    }
+
+    /// The unpinned artifacts that must be removed to get back under the
+    /// maximum size, least recently used first. Nothing is ever evicted when
+    /// no maximum size is configured.
+    pub fn eviction_candidates(&self) -> Vec<String> {
+        self.quota
+            .as_ref()
+            .map(|quota| quota.eviction_candidates())
+            .unwrap_or_default()
+    }
+
+    /// Wait until the maximum size is exceeded by a pushed artifact. Never
+    /// returns when no maximum size is configured.
+    pub async fn wait_until_max_size_exceeded(&self) {
+        match &self.quota {
+            Some(quota) => quota.wait_until_exceeded().await,
+            None => std::future::pending().await,
+        }
+    }
+
+    // Blob stores do blocking IO, so they are only used on threads where
+    // blocking is allowed, to keep the async runtime responsive while large
+    // artifacts are written or read.
+    async fn blocking<T, F>(&self, artifact_id: &str, f: F) -> io::Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&dyn BlobStore, &str) -> io::Result<T> + Send + 'static,
+    {
+        let blob_store = self.blob_store.clone();
+        let artifact_id = artifact_id.to_owned();
+        run_blocking(move || f(blob_store.as_ref(), &artifact_id)).await
+    }
+
+    // Mapping failures are not fatal, the artifact is read from the blob
+    // store instead.
+    async fn mapped_artifact(&self, artifact_id: &str) -> Option<Arc<MappedArtifact>> {
+        let mapped_artifacts = self.mapped_artifacts.clone()?;
+        self.blocking(artifact_id, move |blob_store, artifact_id| {
+            let path = match blob_store.file_path(artifact_id) {
+                Some(path) => path,
+                None => return Ok(None),
+            };
+            let size = blob_store.size(artifact_id)?;
+            mapped_artifacts.get_or_map(artifact_id, &path, size)
+        })
+        .await
+        .unwrap_or_else(|error| {
+            warn!("Failed to map artifact {}: {}", artifact_id, error);
+            None
+        })
+    }
+
+    fn forget_mapping(&self, artifact_id: &str) {
+        if let Some(mapped_artifacts) = &self.mapped_artifacts {
+            mapped_artifacts.forget(artifact_id);
+        }
+    }
+
+    fn touch(&self, artifact_id: &str) {
+        if let Some(quota) = &self.quota {
+            quota.touch(artifact_id);
+        }
+    }
+}
+
+/// The ids of the stored artifacts in batches, see
+/// [`ArtifactStorage::artifact_id_batches`].
+pub struct ArtifactIdBatches {
+    artifact_ids: Option<Box<dyn Iterator<Item = String> + Send>>,
+    batch_size: usize,
+}
+
+impl ArtifactIdBatches {
+    /// The next batch of artifact ids, or `None` when all ids were read.
+    pub async fn next(&mut self) -> io::Result<Option<Vec<String>>> {
+        let Some(mut artifact_ids) = self.artifact_ids.take() else {
+            return Ok(None);
+        };
+        let batch_size = self.batch_size;
+        let (artifact_ids, batch) = run_blocking(move || {
+            let batch: Vec<String> = artifact_ids.by_ref().take(batch_size).collect();
+            Ok((artifact_ids, batch))
+        })
+        .await?;
+        if batch.is_empty() {
+            return Ok(None);
+        }
+        self.artifact_ids = Some(artifact_ids);
+        Ok(Some(batch))
+    }
+}
+
+async fn run_blocking<T: Send + 'static>(
+    f: impl FnOnce() -> io::Result<T> + Send + 'static,
+) -> io::Result<T> {
+    tokio::task::spawn_blocking(f)
+        .await
+        .map_err(|error| io::Error::new(io::ErrorKind::Other, error))?
 }
 
 #[cfg(test)]
 #[cfg(not(tarpaulin_include))]
 mod tests {
     use super::*;
+    use crate::artifact_service::blob_store::local::{BLOBS_DIR, FILE_EXTENSION};
     use crate::util::test_util;
+    use std::fs::File;
     use std::path::PathBuf;
     use stringreader::StringReader;
     use uuid::Uuid;
@@ -198,29 +520,89 @@ mod tests {
         test_util::tests::teardown(tmp_dir);
     }
 
-    #[test]
-    pub fn push_artifact_then_pull_it() {
+    #[tokio::test]
+    pub async fn push_artifact_then_pull_it() {
         let tmp_dir = test_util::tests::setup();
 
-        let mut string_reader = StringReader::new(TEST_ARTIFACT_DATA);
+        let string_reader = StringReader::new(TEST_ARTIFACT_DATA);
         let artifact_id = Uuid::new_v4().to_string();
         let artifact_storage =
             ArtifactStorage::new(&tmp_dir).expect("Error creating ArtifactManager");
 
         artifact_storage
-            .push_artifact(&mut string_reader, &artifact_id)
+            .push_artifact(string_reader, &artifact_id)
+            .await
             .context("Error from push_artifact")
             .unwrap();
 
         check_artifact_is_written_correctly(&tmp_dir, &artifact_id).unwrap();
 
-        check_able_to_pull_artifact(&artifact_id, &artifact_storage).unwrap();
+        check_able_to_pull_artifact(&artifact_id, &artifact_storage)
+            .await
+            .unwrap();
+
+        test_util::tests::teardown(tmp_dir);
+    }
+
+    #[tokio::test]
+    pub async fn push_existing_artifact_fails() {
+        let tmp_dir = test_util::tests::setup();
+
+        let artifact_id = Uuid::new_v4().to_string();
+        let artifact_storage =
+            ArtifactStorage::new(&tmp_dir).expect("Error creating ArtifactManager");
+
+        artifact_storage
+            .push_artifact(StringReader::new(TEST_ARTIFACT_DATA), &artifact_id)
+            .await
+            .unwrap();
+        let result = artifact_storage
+            .push_artifact(StringReader::new("other data"), &artifact_id)
+            .await;
+
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::AlreadyExists);
+        check_artifact_is_written_correctly(&tmp_dir, &artifact_id).unwrap();
+
+        test_util::tests::teardown(tmp_dir);
+    }
+
+    struct FailingReader;
+
+    impl Read for FailingReader {
+        fn read(&mut self, _buf: &mut [u8]) -> io::Result<usize> {
+            Err(io::Error::new(io::ErrorKind::BrokenPipe, "connection lost"))
+        }
+    }
+
+    #[tokio::test]
+    pub async fn push_artifact_with_failing_reader_leaves_no_files() {
+        let tmp_dir = test_util::tests::setup();
+
+        let artifact_id = Uuid::new_v4().to_string();
+        let artifact_storage =
+            ArtifactStorage::new(&tmp_dir).expect("Error creating ArtifactManager");
+
+        assert!(artifact_storage
+            .push_artifact(FailingReader, &artifact_id)
+            .await
+            .is_err());
+        assert!(artifact_storage.pull_artifact(&artifact_id).await.is_err());
+        assert_eq!(
+            walkdir::WalkDir::new(&tmp_dir)
+                .into_iter()
+                .filter(|entry| entry.as_ref().unwrap().file_type().is_file())
+                .count(),
+            0
+        );
 
         test_util::tests::teardown(tmp_dir);
     }
 
     fn check_artifact_is_written_correctly(dir_name: &Path, artifact_id: &str) -> Result<()> {
+        // artifact ids that are not derived from a hash are sharded by their first characters
         let mut dir_name = dir_name.to_path_buf();
+        dir_name.push(BLOBS_DIR);
+        dir_name.push(&artifact_id[..2]);
         dir_name.push(artifact_id);
         dir_name.set_extension(FILE_EXTENSION);
         let content_vec = std::fs::read(dir_name.as_path())
@@ -231,12 +613,13 @@ mod tests {
         Ok(())
     }
 
-    fn check_able_to_pull_artifact(
+    async fn check_able_to_pull_artifact(
         artifact_id: &str,
         artifact_storage: &ArtifactStorage,
     ) -> Result<()> {
         let mut reader = artifact_storage
             .pull_artifact(artifact_id)
+            .await
             .context("Error from pull_artifact")?;
         let mut read_buffer = String::new();
         reader.read_to_string(&mut read_buffer).unwrap();
@@ -245,36 +628,316 @@ mod tests {
         Ok(())
     }
 
-    #[test]
-    pub fn pull_nonexistent_test() {
+    #[tokio::test]
+    pub async fn pull_nonexistent_test() {
         let tmp_dir = test_util::tests::setup();
 
         let artifact_id = Uuid::new_v4().to_string();
         let artifact_storage =
             ArtifactStorage::new(&tmp_dir).expect("Error creating ArtifactManager");
-        assert!(artifact_storage.pull_artifact(&artifact_id).is_err());
+        assert!(artifact_storage.pull_artifact(&artifact_id).await.is_err());
 
         test_util::tests::teardown(tmp_dir);
     }
 
-    #[test]
-    pub fn list_artifacts_test() {
+    #[tokio::test]
+    pub async fn pull_artifact_range_test() {
         let tmp_dir = test_util::tests::setup();
 
-        let mut string_reader = StringReader::new(TEST_ARTIFACT_DATA);
         let artifact_id = Uuid::new_v4().to_string();
         let artifact_storage =
             ArtifactStorage::new(&tmp_dir).expect("Error creating ArtifactManager");
 
         artifact_storage
-            .push_artifact(&mut string_reader, &artifact_id)
+            .push_artifact(StringReader::new(TEST_ARTIFACT_DATA), &artifact_id)
+            .await
+            .unwrap();
+
+        let len = TEST_ARTIFACT_DATA.len() as u64;
+        assert_eq!(
+            artifact_storage.artifact_size(&artifact_id).await.unwrap(),
+            len
+        );
+
+        let mut range = String::new();
+        artifact_storage
+            .pull_artifact_range(&artifact_id, 2, 5)
+            .await
+            .unwrap()
+            .read_to_string(&mut range)
+            .unwrap();
+        assert_eq!(range, TEST_ARTIFACT_DATA[2..7]);
+
+        assert!(artifact_storage
+            .pull_artifact_range(&artifact_id, len - 1, 2)
+            .await
+            .is_err());
+
+        test_util::tests::teardown(tmp_dir);
+    }
+
+    #[tokio::test]
+    pub async fn remove_artifact_test() {
+        let tmp_dir = test_util::tests::setup();
+
+        let artifact_id = Uuid::new_v4().to_string();
+        let artifact_storage =
+            ArtifactStorage::new(&tmp_dir).expect("Error creating ArtifactManager");
+
+        artifact_storage
+            .push_artifact(StringReader::new(TEST_ARTIFACT_DATA), &artifact_id)
+            .await
+            .unwrap();
+        artifact_storage
+            .remove_artifact(&artifact_id)
+            .await
+            .unwrap();
+
+        assert!(artifact_storage.pull_artifact(&artifact_id).await.is_err());
+        assert!(artifact_storage
+            .remove_artifact(&artifact_id)
+            .await
+            .is_err());
+
+        test_util::tests::teardown(tmp_dir);
+    }
+
+    #[tokio::test]
+    pub async fn hash_artifact_test() {
+        let tmp_dir = test_util::tests::setup();
+
+        let artifact_id = Uuid::new_v4().to_string();
+        let artifact_storage =
+            ArtifactStorage::new(&tmp_dir).expect("Error creating ArtifactManager");
+
+        artifact_storage
+            .push_artifact(StringReader::new(TEST_ARTIFACT_DATA), &artifact_id)
+            .await
+            .unwrap();
+
+        let mut sha256 = multihash::Sha2_256::default();
+        sha256.update(TEST_ARTIFACT_DATA.as_bytes());
+        assert_eq!(
+            artifact_storage.hash_artifact(&artifact_id).await.unwrap(),
+            hex::encode(sha256.finalize())
+        );
+        assert!(artifact_storage.hash_artifact("unknown").await.is_err());
+
+        test_util::tests::teardown(tmp_dir);
+    }
+
+    #[tokio::test]
+    pub async fn memory_mapped_artifacts_test() {
+        let tmp_dir = test_util::tests::setup();
+
+        let artifact_id = Uuid::new_v4().to_string();
+        let artifact_storage = ArtifactStorage::new(&tmp_dir)
+            .expect("Error creating ArtifactManager")
+            .with_memory_mapping(MmapConfig {
+                min_size: 10,
+                ..Default::default()
+            });
+
+        artifact_storage
+            .push_artifact(StringReader::new(TEST_ARTIFACT_DATA), &artifact_id)
+            .await
+            .unwrap();
+        artifact_storage
+            .push_artifact(StringReader::new("small"), "small")
+            .await
+            .unwrap();
+
+        assert_eq!(
+            artifact_storage.mapped_artifact_hash(&artifact_id).await,
+            Some(artifact_storage.hash_artifact(&artifact_id).await.unwrap())
+        );
+        assert_eq!(artifact_storage.mapped_artifact_hash("small").await, None);
+
+        let mut content = String::new();
+        artifact_storage
+            .pull_artifact(&artifact_id)
+            .await
+            .unwrap()
+            .read_to_string(&mut content)
+            .unwrap();
+        assert_eq!(content, TEST_ARTIFACT_DATA);
+        let mut range = String::new();
+        artifact_storage
+            .pull_artifact_range(&artifact_id, 10, 8)
+            .await
+            .unwrap()
+            .read_to_string(&mut range)
+            .unwrap();
+        assert_eq!(range, TEST_ARTIFACT_DATA[10..18]);
+
+        artifact_storage
+            .remove_artifact(&artifact_id)
+            .await
+            .unwrap();
+        assert_eq!(
+            artifact_storage.mapped_artifact_hash(&artifact_id).await,
+            None
+        );
+        assert!(artifact_storage.pull_artifact(&artifact_id).await.is_err());
+
+        test_util::tests::teardown(tmp_dir);
+    }
+
+    #[tokio::test]
+    pub async fn move_artifact_test() {
+        let tmp_dir = test_util::tests::setup();
+
+        let artifact_id = Uuid::new_v4().to_string();
+        let new_artifact_id = Uuid::new_v4().to_string();
+        let artifact_storage =
+            ArtifactStorage::new(&tmp_dir).expect("Error creating ArtifactManager");
+
+        artifact_storage
+            .push_artifact(StringReader::new(TEST_ARTIFACT_DATA), &artifact_id)
+            .await
+            .unwrap();
+        artifact_storage
+            .move_artifact(&artifact_id, &new_artifact_id)
+            .await
+            .unwrap();
+        assert!(!artifact_storage.contains_artifact(&artifact_id).await);
+        assert!(artifact_storage.contains_artifact(&new_artifact_id).await);
+
+        artifact_storage
+            .push_artifact(StringReader::new(TEST_ARTIFACT_DATA), &artifact_id)
+            .await
+            .unwrap();
+        artifact_storage
+            .move_artifact(&artifact_id, &new_artifact_id)
+            .await
+            .unwrap();
+        assert!(!artifact_storage.contains_artifact(&artifact_id).await);
+        assert!(artifact_storage.contains_artifact(&new_artifact_id).await);
+
+        test_util::tests::teardown(tmp_dir);
+    }
+
+    #[tokio::test]
+    pub async fn list_artifacts_test() {
+        let tmp_dir = test_util::tests::setup();
+
+        let string_reader = StringReader::new(TEST_ARTIFACT_DATA);
+        let artifact_id = Uuid::new_v4().to_string();
+        let artifact_storage =
+            ArtifactStorage::new(&tmp_dir).expect("Error creating ArtifactManager");
+
+        artifact_storage
+            .push_artifact(string_reader, &artifact_id)
+            .await
             .context("Error from push_artifact")
             .unwrap();
 
-        let result = artifact_storage.list_artifacts();
+        let result = artifact_storage.list_artifacts().await;
         assert!(result.is_ok());
         assert_eq!(result.unwrap().len(), 1);
 
+        assert!(artifact_storage.contains_artifact(&artifact_id).await);
+        assert_eq!(
+            artifact_storage.artifact_ids().unwrap().collect::<Vec<_>>(),
+            vec![artifact_id]
+        );
+
+        test_util::tests::teardown(tmp_dir);
+    }
+
+    #[tokio::test]
+    pub async fn artifact_id_batches_test() {
+        let tmp_dir = test_util::tests::setup();
+
+        let artifact_storage =
+            ArtifactStorage::new(&tmp_dir).expect("Error creating ArtifactManager");
+        for _ in 0..5 {
+            artifact_storage
+                .push_artifact(
+                    StringReader::new(TEST_ARTIFACT_DATA),
+                    &Uuid::new_v4().to_string(),
+                )
+                .await
+                .unwrap();
+        }
+
+        let mut batches = artifact_storage.artifact_id_batches(2).unwrap();
+        let mut batch_lens = Vec::new();
+        while let Some(batch) = batches.next().await.unwrap() {
+            batch_lens.push(batch.len());
+        }
+        assert_eq!(batch_lens, vec![2, 2, 1]);
+        assert!(batches.next().await.unwrap().is_none());
+
+        test_util::tests::teardown(tmp_dir);
+    }
+
+    #[tokio::test]
+    pub async fn max_size_evicts_least_recently_used_artifacts() {
+        let tmp_dir = test_util::tests::setup();
+
+        let artifact_storage =
+            ArtifactStorage::new(&tmp_dir).expect("Error creating ArtifactManager");
+        let artifact_ids: Vec<String> = (0..3).map(|_| Uuid::new_v4().to_string()).collect();
+        artifact_storage
+            .push_artifact(StringReader::new(TEST_ARTIFACT_DATA), &artifact_ids[0])
+            .await
+            .unwrap();
+
+        let artifact_size = TEST_ARTIFACT_DATA.len() as u64;
+        let artifact_storage = artifact_storage.with_max_size(2 * artifact_size).unwrap();
+        assert_eq!(artifact_storage.max_size(), Some(2 * artifact_size));
+        for artifact_id in &artifact_ids[1..] {
+            artifact_storage
+                .push_artifact(StringReader::new(TEST_ARTIFACT_DATA), artifact_id)
+                .await
+                .unwrap();
+        }
+        assert_eq!(
+            artifact_storage.eviction_candidates(),
+            vec![artifact_ids[0].clone()]
+        );
+
+        artifact_storage
+            .pull_artifact(&artifact_ids[0])
+            .await
+            .unwrap();
+        assert_eq!(
+            artifact_storage.eviction_candidates(),
+            vec![artifact_ids[1].clone()]
+        );
+
+        artifact_storage.pin_artifact(&artifact_ids[1]);
+        assert_eq!(
+            artifact_storage.eviction_candidates(),
+            vec![artifact_ids[2].clone()]
+        );
+
+        artifact_storage
+            .remove_artifact(&artifact_ids[2])
+            .await
+            .unwrap();
+        assert!(artifact_storage.eviction_candidates().is_empty());
+
+        test_util::tests::teardown(tmp_dir);
+    }
+
+    #[tokio::test]
+    pub async fn no_eviction_candidates_without_max_size() {
+        let tmp_dir = test_util::tests::setup();
+
+        let artifact_storage =
+            ArtifactStorage::new(&tmp_dir).expect("Error creating ArtifactManager");
+        artifact_storage
+            .push_artifact(
+                StringReader::new(TEST_ARTIFACT_DATA),
+                &Uuid::new_v4().to_string(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(artifact_storage.max_size(), None);
+        assert!(artifact_storage.eviction_candidates().is_empty());
+
         test_util::tests::teardown(tmp_dir);
     }
 }
@@ -18,7 +18,10 @@ use libp2p::identity;
 use libp2p::identity::Keypair::Ed25519;
 use serde::{Deserialize, Serialize};
 use std::fmt::{self, Debug, Formatter};
+use std::io;
+use std::ops::RangeInclusive;
 use std::path::{Path, PathBuf};
+use tokio::fs;
 
 use crate::error::BlockchainError;
 use crate::structures::header::Ordinal;
@@ -58,6 +61,21 @@ impl Blockchain {
     pub async fn new(
         keypair: &identity::ed25519::Keypair,
         blockchain_path: impl AsRef<Path>,
+    ) -> Result<Self, BlockchainError> {
+        Self::new_with_genesis_payload(
+            keypair,
+            blockchain_path,
+            "this is the first reserved transaction".as_bytes().to_vec(),
+        )
+        .await
+    }
+
+    /// Load the blockchain, or create it with a genesis block that has
+    /// `genesis_payload`, e.g. the configuration of the network.
+    pub async fn new_with_genesis_payload(
+        keypair: &identity::ed25519::Keypair,
+        blockchain_path: impl AsRef<Path>,
+        genesis_payload: Vec<u8>,
     ) -> Result<Self, BlockchainError> {
         let mut chain: Chain = Default::default();
         chain.load_blocks(&blockchain_path).await?;
@@ -65,12 +83,8 @@ impl Blockchain {
         // Make the "genesis" block
         if chain.is_empty() {
             let local_id = Address::from(identity::PublicKey::Ed25519(keypair.public()));
-            let transaction = Transaction::new(
-                TransactionType::Create,
-                local_id,
-                "this is the first reserved transaction".as_bytes().to_vec(),
-                keypair,
-            );
+            let transaction =
+                Transaction::new(TransactionType::Create, local_id, genesis_payload, keypair);
 
             let block = Block::new(HashDigest::new(b""), 0, Vec::from([transaction]), keypair);
             Blockchain::save_block(&mut chain, block, &blockchain_path).await?
@@ -119,7 +133,6 @@ impl Blockchain {
             ed25519_key,
         );
 
-        // TODO: Consensus algorithm will be refactored
         self.commit_block(block.clone()).await
     }
 
@@ -140,10 +153,209 @@ impl Blockchain {
         self.chain.last_block()
     }
 
+    /// The ordinal of the oldest block that is kept locally, the blocks
+    /// before it were pruned.
+    pub fn first_ordinal(&self) -> Option<Ordinal> {
+        self.chain.first_ordinal()
+    }
+
+    /// The block with `ordinal`, `None` when it was pruned or does not
+    /// exist yet.
+    pub fn block(&self, ordinal: Ordinal) -> Option<Block> {
+        self.chain.retrieve_blocks(ordinal, ordinal).pop()
+    }
+
+    /// Retrieve the blocks from `start` to `end`, leaving out the blocks that
+    /// were pruned.
     pub fn pull_blocks(&self, start: Ordinal, end: Ordinal) -> Result<Vec<Block>, BlockchainError> {
+        let start = self
+            .first_ordinal()
+            .map_or(start, |first_ordinal| start.max(first_ordinal));
         Ok(self.chain.retrieve_blocks(start, end))
     }
 
+    /// Prune the blocks before the block with ordinal `before`, so that only
+    /// the recent blocks are kept locally. The last block is never pruned.
+    /// When an `archive_path` is given, the pruned blocks are exported to a
+    /// file in that directory first, named after the range of their
+    /// ordinals, see [`read_archive`](Blockchain::read_archive). Returns the
+    /// range of the ordinals of the pruned blocks.
+    pub async fn prune_blocks(
+        &mut self,
+        before: Ordinal,
+        archive_path: Option<&Path>,
+    ) -> Result<Option<RangeInclusive<Ordinal>>, BlockchainError> {
+        let (Some(first_ordinal), Some(last_block)) = (self.first_ordinal(), self.last_block())
+        else {
+            return Ok(None);
+        };
+        let before = before.min(last_block.header.ordinal);
+        if before <= first_ordinal {
+            return Ok(None);
+        }
+        let pruned_ordinals = first_ordinal..=before - 1;
+
+        if let Some(archive_path) = archive_path {
+            let blocks = self.chain.retrieve_blocks(first_ordinal, before - 1);
+            let archive_file = archive_path.join(format!(
+                "{}-{}.ser",
+                pruned_ordinals.start(),
+                pruned_ordinals.end()
+            ));
+            let temp_file = archive_file.with_extension("tmp");
+            fs::create_dir_all(archive_path).await?;
+            fs::write(&temp_file, bincode::serialize(&blocks)?).await?;
+            fs::rename(&temp_file, &archive_file).await?;
+        }
+
+        self.chain.prune(before);
+        // The oldest blocks are removed first, so that an interrupted prune
+        // leaves the remaining blocks without gaps.
+        for ordinal in pruned_ordinals.clone() {
+            match fs::remove_file(self.blockchain_path.join(format!("{}.ser", ordinal))).await {
+                Err(e) if e.kind() != io::ErrorKind::NotFound => {
+                    return Err(BlockchainError::IOError(e))
+                }
+                _ => {}
+            }
+        }
+
+        Ok(Some(pruned_ordinals))
+    }
+
+    /// Replace the blocks from the first block of `branch` on with the blocks
+    /// of `branch`, when the other branch of a fork of the blockchain wins.
+    /// The first block of `branch` must follow the local block before it,
+    /// and every other block the block before it in `branch`. Returns the
+    /// replaced blocks, oldest first.
+    pub async fn replace_blocks(
+        &mut self,
+        branch: Vec<Block>,
+    ) -> Result<Vec<Block>, BlockchainError> {
+        let (Some(first_block), Some(last_block)) = (branch.first(), branch.last()) else {
+            return Ok(vec![]);
+        };
+        let fork_ordinal = first_block.header.ordinal;
+        let parent = fork_ordinal
+            .checked_sub(1)
+            .and_then(|parent_ordinal| self.block(parent_ordinal))
+            .ok_or(BlockchainError::UnresolvableFork(fork_ordinal))?;
+
+        let mut previous = parent.header;
+        for block in &branch {
+            if block.header.ordinal != previous.ordinal + 1
+                || block.header.parent_hash != previous.hash()
+            {
+                return Err(BlockchainError::InvalidBlockchainOrdinal(
+                    block.header.ordinal,
+                ));
+            }
+            previous = block.header;
+        }
+
+        let last_ordinal = last_block.header.ordinal;
+        let replaced = self.chain.truncate(fork_ordinal);
+        // The newest blocks are removed first, so that an interrupted
+        // replacement leaves the remaining blocks without gaps.
+        for block in replaced
+            .iter()
+            .rev()
+            .filter(|block| block.header.ordinal > last_ordinal)
+        {
+            fs::remove_file(
+                self.blockchain_path
+                    .join(format!("{}.ser", block.header.ordinal)),
+            )
+            .await?;
+        }
+        for block in branch {
+            Self::save_block(&mut self.chain, block, self.blockchain_path.as_path()).await?;
+        }
+
+        Ok(replaced)
+    }
+
+    /// Read the blocks that were exported to `archive_file` by
+    /// [`prune_blocks`](Blockchain::prune_blocks).
+    pub async fn read_archive(
+        archive_file: impl AsRef<Path>,
+    ) -> Result<Vec<Block>, BlockchainError> {
+        Ok(bincode::deserialize(&fs::read(archive_file).await?)?)
+    }
+
+    /// Export all blocks of the local blockchain, e.g. as a backup or to
+    /// seed a node without access to the network, in the same format as
+    /// the archives of pruned blocks. See
+    /// [`import_blocks`](Blockchain::import_blocks).
+    pub fn export_blocks(&self) -> Result<Vec<u8>, BlockchainError> {
+        Ok(bincode::serialize(&self.chain.blocks())?)
+    }
+
+    /// Read the blocks of an export of a complete blockchain and verify
+    /// that it starts with a genesis block, that every block follows the
+    /// block before it and that every block is signed by its committer.
+    pub fn read_export(export: &[u8]) -> Result<Vec<Block>, BlockchainError> {
+        let blocks: Vec<Block> = bincode::deserialize(export)?;
+
+        let mut parent_header = None;
+        for block in &blocks {
+            let follows_parent = match parent_header {
+                None => block.header.ordinal == 0,
+                Some(parent_header) => {
+                    block.header.ordinal == parent_header.ordinal + 1
+                        && block.header.parent_hash == parent_header.hash()
+                }
+            };
+            if !follows_parent {
+                return Err(BlockchainError::InvalidBlockchainOrdinal(
+                    block.header.ordinal,
+                ));
+            }
+            if !block.verify() || block.signer() != Some(block.header.committer) {
+                return Err(BlockchainError::InvalidBlockSignature(block.header.ordinal));
+            }
+            parent_header = Some(block.header);
+        }
+
+        Ok(blocks)
+    }
+
+    /// Add the blocks of an export, read with
+    /// [`read_export`](Blockchain::read_export), that follow the last local
+    /// block. The local blocks must be the same as the blocks of the export
+    /// with their ordinals. Returns the added blocks, oldest first.
+    pub async fn import_blocks(
+        &mut self,
+        blocks: Vec<Block>,
+    ) -> Result<Vec<Block>, BlockchainError> {
+        if let Some(conflicting_block) = blocks.iter().find(|block| {
+            self.block(block.header.ordinal)
+                .map_or(false, |local_block| local_block != **block)
+        }) {
+            return Err(BlockchainError::ConflictingBlock(
+                conflicting_block.header.ordinal,
+            ));
+        }
+
+        let next_ordinal = self
+            .last_block()
+            .map_or(0, |last_block| last_block.header.ordinal + 1);
+        let imported: Vec<Block> = blocks
+            .into_iter()
+            .filter(|block| block.header.ordinal >= next_ordinal)
+            .collect();
+        for block in &imported {
+            Self::save_block(
+                &mut self.chain,
+                block.clone(),
+                self.blockchain_path.as_path(),
+            )
+            .await?;
+        }
+
+        Ok(imported)
+    }
+
     async fn save_block(
         chain: &mut Chain,
         block: Block,
@@ -325,4 +537,176 @@ mod tests {
 
         remove_tmp_dir(tmp_dir);
     }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_prune_blocks() {
+        let tmp_dir = create_tmp_dir();
+        let archive_dir = tmp_dir.join("archive");
+        let keypair = identity::Keypair::generate_ed25519();
+        let Ed25519(ed25519_key) = &keypair;
+
+        let mut blockchain = Blockchain::new(ed25519_key, &tmp_dir)
+            .await
+            .expect("Blockchain should have been created.");
+        for payload in ["first", "second", "third"] {
+            blockchain
+                .add_block(payload.as_bytes().to_vec(), &keypair)
+                .await
+                .expect("Block should have been added.");
+        }
+
+        assert_eq!(
+            Some(0..=1),
+            blockchain
+                .prune_blocks(2, Some(&archive_dir))
+                .await
+                .expect("Blocks should have been pruned.")
+        );
+        assert_eq!(Some(2), blockchain.first_ordinal());
+        assert!(!tmp_dir.join("1.ser").exists());
+        assert_eq!(2, blockchain.pull_blocks(0, 3).unwrap().len());
+
+        let archived_blocks = Blockchain::read_archive(archive_dir.join("0-1.ser"))
+            .await
+            .expect("Archive should have been read.");
+        assert_eq!(
+            vec![0, 1],
+            archived_blocks
+                .iter()
+                .map(|block| block.header.ordinal)
+                .collect::<Vec<_>>()
+        );
+
+        // the last block is always kept
+        assert_eq!(
+            Some(2..=2),
+            blockchain.prune_blocks(10, None).await.unwrap()
+        );
+        assert_eq!(None, blockchain.prune_blocks(10, None).await.unwrap());
+
+        let reloaded = Blockchain::new(ed25519_key, &tmp_dir)
+            .await
+            .expect("Blockchain should have been loaded.");
+        assert_eq!(Some(3), reloaded.first_ordinal());
+        assert_eq!(blockchain.last_block(), reloaded.last_block());
+
+        remove_tmp_dir(tmp_dir);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_replace_blocks() {
+        let tmp_dir = create_tmp_dir();
+        let keypair = identity::Keypair::generate_ed25519();
+        let Ed25519(ed25519_key) = &keypair;
+
+        let mut blockchain = Blockchain::new(ed25519_key, &tmp_dir)
+            .await
+            .expect("Blockchain should have been created.");
+        for payload in ["first", "second", "third"] {
+            blockchain
+                .add_block(payload.as_bytes().to_vec(), &keypair)
+                .await
+                .expect("Block should have been added.");
+        }
+        let replaced_blocks = blockchain.pull_blocks(2, 3).unwrap();
+
+        let parent = blockchain.block(1).unwrap();
+        let branch_block = Block::new(parent.header.hash(), 2, vec![], ed25519_key);
+
+        // the branch must follow the local block before it
+        let orphan_block = Block::new(HashDigest::new(b""), 2, vec![], ed25519_key);
+        assert!(matches!(
+            blockchain.replace_blocks(vec![orphan_block]).await,
+            Err(BlockchainError::InvalidBlockchainOrdinal(2))
+        ));
+        assert_eq!(Some(3), blockchain.last_block().map(|b| b.header.ordinal));
+
+        assert_eq!(
+            replaced_blocks,
+            blockchain
+                .replace_blocks(vec![branch_block.clone()])
+                .await
+                .expect("Blocks should have been replaced.")
+        );
+        assert_eq!(Some(branch_block.clone()), blockchain.last_block());
+        assert!(!tmp_dir.join("3.ser").exists());
+
+        let reloaded = Blockchain::new(ed25519_key, &tmp_dir)
+            .await
+            .expect("Blockchain should have been loaded.");
+        assert_eq!(Some(branch_block), reloaded.last_block());
+
+        remove_tmp_dir(tmp_dir);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_export_and_import_blocks() {
+        let tmp_dir = create_tmp_dir();
+        let keypair = identity::Keypair::generate_ed25519();
+        let Ed25519(ed25519_key) = &keypair;
+
+        let source_dir = tmp_dir.join("source");
+        fs::create_dir_all(&source_dir).unwrap();
+        let mut source = Blockchain::new(ed25519_key, &source_dir)
+            .await
+            .expect("Blockchain should have been created.");
+        for payload in ["first", "second"] {
+            source
+                .add_block(payload.as_bytes().to_vec(), &keypair)
+                .await
+                .expect("Block should have been added.");
+        }
+        let export = source.export_blocks().unwrap();
+        let blocks = Blockchain::read_export(&export).unwrap();
+        assert_eq!(blocks, source.pull_blocks(0, 2).unwrap());
+
+        let target_dir = tmp_dir.join("target");
+        fs::create_dir_all(&target_dir).unwrap();
+        let mut target = Blockchain::empty_new(&target_dir);
+        assert_eq!(blocks, target.import_blocks(blocks.clone()).await.unwrap());
+        assert!(target
+            .import_blocks(blocks.clone())
+            .await
+            .unwrap()
+            .is_empty());
+        let reloaded = Blockchain::new(ed25519_key, &target_dir)
+            .await
+            .expect("Blockchain should have been loaded.");
+        assert_eq!(source.last_block(), reloaded.last_block());
+
+        // a block that does not follow the block before it
+        let mut unlinked_blocks = blocks.clone();
+        unlinked_blocks.remove(1);
+        assert!(matches!(
+            Blockchain::read_export(&bincode::serialize(&unlinked_blocks).unwrap()),
+            Err(BlockchainError::InvalidBlockchainOrdinal(2))
+        ));
+
+        // a block signed by another key than the key of its committer
+        let other_key = identity::ed25519::Keypair::generate();
+        let mut forged_blocks = blocks.clone();
+        forged_blocks[2] = Block::new(
+            blocks[2].header.parent_hash,
+            2,
+            blocks[2].transactions.clone(),
+            &other_key,
+        );
+        forged_blocks[2].header.committer = blocks[2].header.committer;
+        assert!(matches!(
+            Blockchain::read_export(&bincode::serialize(&forged_blocks).unwrap()),
+            Err(BlockchainError::InvalidBlockSignature(2))
+        ));
+
+        // an export of another blockchain
+        let other_dir = tmp_dir.join("other");
+        fs::create_dir_all(&other_dir).unwrap();
+        let other = Blockchain::new(&other_key, &other_dir).await.unwrap();
+        let other_blocks = Blockchain::read_export(&other.export_blocks().unwrap()).unwrap();
+        assert!(matches!(
+            target.import_blocks(other_blocks).await,
+            Err(BlockchainError::ConflictingBlock(0))
+        ));
+
+        remove_tmp_dir(tmp_dir);
+    }
 }
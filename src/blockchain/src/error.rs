@@ -44,10 +44,22 @@ pub enum BlockchainError {
     InvalidBlockchainOrdinal(Ordinal),
     #[error("Blockchain: Key {0} is not valid Ed25519 format")]
     InvalidKey(String),
+    #[error("Block {0} is not signed by its committer")]
+    InvalidBlockSignature(Ordinal),
+    #[error("Block {0} is not committed by an authorized node")]
+    UnauthorizedCommitter(Ordinal),
+    #[error("Block {0} conflicts with the block of the local blockchain")]
+    ConflictingBlock(Ordinal),
+    #[error("Blockchain forked at block {0} and the fork can not be resolved")]
+    UnresolvableFork(Ordinal),
+    #[error("Block belongs to network {0}")]
+    InvalidNetwork(String),
     #[error("Lagging Blockchain Data")]
     LaggingBlockchainData,
     #[error("Invalid storage path: {0}")]
     InvalidStoragePath(PathBuf),
     #[error("Could not connect to blockchain topic")]
     InvalidTopic,
+    #[error("This node does not produce blocks")]
+    BlockProductionDisabled,
 }
@@ -28,7 +28,7 @@ pub struct Signature {
     signature: ed25519_dalek::Signature,
 }
 
-#[allow(clippy::derive_hash_xor_eq)] // https://github.com/rust-lang/rust-clippy/issues/7666
+#[allow(clippy::derived_hash_with_manual_eq)] // https://github.com/rust-lang/rust-clippy/issues/7666
 impl Hash for Signature {
     fn hash<H: Hasher>(&self, state: &mut H) {
         self.signature.to_bytes().hash(state);
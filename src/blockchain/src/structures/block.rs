@@ -87,6 +87,14 @@ impl Block {
         }
     }
 
+    /// The address of the node whose key signed this block, if the public key
+    /// of the signature can be decoded.
+    pub fn signer(&self) -> Option<Address> {
+        identity::ed25519::PublicKey::decode(&self.signature().public_key)
+            .ok()
+            .map(|public_key| Address::from(identity::PublicKey::Ed25519(public_key)))
+    }
+
     pub fn fetch_payload(&self) -> Vec<Vec<u8>> {
         let mut result = vec![];
 
@@ -123,7 +131,7 @@ mod tests {
         let keypair = identity::ed25519::Keypair::generate();
         let local_id = Address::from(identity::PublicKey::Ed25519(keypair.public()));
 
-        let transactions = vec![Transaction::new(
+        let transactions = [Transaction::new(
             TransactionType::Create,
             local_id,
             b"Hello First Transaction".to_vec(),
@@ -143,7 +151,7 @@ mod tests {
         let keypair = identity::ed25519::Keypair::generate();
         let local_id = Address::from(identity::PublicKey::Ed25519(keypair.public()));
 
-        let transactions = vec![Transaction::new(
+        let transactions = [Transaction::new(
             TransactionType::Create,
             local_id,
             b"Hello First Transaction".to_vec(),
@@ -163,7 +171,7 @@ mod tests {
         let keypair = identity::ed25519::Keypair::generate();
         let local_id = Address::from(identity::PublicKey::Ed25519(keypair.public()));
 
-        let transactions = vec![Transaction::new(
+        let transactions = [Transaction::new(
             TransactionType::Create,
             local_id,
             b"Hello First Transaction".to_vec(),
@@ -180,7 +188,7 @@ mod tests {
         let keypair = identity::ed25519::Keypair::generate();
         let local_id = Address::from(identity::PublicKey::Ed25519(keypair.public()));
 
-        let transactions = vec![Transaction::new(
+        let transactions = [Transaction::new(
             TransactionType::Create,
             local_id,
             b"Hello First Transaction".to_vec(),
@@ -192,12 +200,24 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_block_signer() -> Result<(), String> {
+        let keypair = identity::ed25519::Keypair::generate();
+        let local_id = Address::from(identity::PublicKey::Ed25519(keypair.public()));
+
+        let block = Block::new(HashDigest::new(b""), 1, vec![], &keypair);
+
+        assert_eq!(block.signer(), Some(local_id));
+        assert_eq!(block.signer(), Some(block.header.committer));
+        Ok(())
+    }
+
     #[test]
     fn test_display() -> Result<(), String> {
         let keypair = identity::ed25519::Keypair::generate();
         let local_id = Address::from(identity::PublicKey::Ed25519(keypair.public()));
 
-        let transactions = vec![Transaction::new(
+        let transactions = [Transaction::new(
             TransactionType::Create,
             local_id,
             b"Hello First Transaction".to_vec(),
@@ -16,6 +16,7 @@
 use codec::{Decode, Encode};
 use log::warn;
 use serde::{Deserialize, Serialize};
+use std::io;
 use std::path::Path;
 use tokio::fs::{self, OpenOptions};
 use tokio::io::AsyncWriteExt;
@@ -51,23 +52,49 @@ impl Chain {
         self.blocks().last().cloned()
     }
 
-    pub fn get_block_position(&self, ordinal: Ordinal) -> Option<usize> {
-        if ordinal >= self.len() as Ordinal {
-            warn!("Blockchain try to get non-exsit block {:?}", ordinal);
-            return None;
-        }
+    /// The ordinal of the oldest block of the chain, the blocks before it
+    /// were pruned.
+    pub fn first_ordinal(&self) -> Option<Ordinal> {
+        self.blocks.first().map(|block| block.header.ordinal)
+    }
 
-        if ordinal > self.last_block().unwrap().header.ordinal {
-            warn!("Blockchain try to get non-exsit block {:?}", ordinal);
-            return None;
+    pub fn get_block_position(&self, ordinal: Ordinal) -> Option<usize> {
+        let position = self
+            .first_ordinal()
+            .and_then(|first_ordinal| ordinal.checked_sub(first_ordinal))
+            .filter(|position| *position < self.len() as Ordinal);
+
+        match position {
+            Some(position) if self.blocks[position as usize].header.ordinal == ordinal => {
+                Some(position as usize)
+            }
+            _ => {
+                warn!("Blockchain try to get non-exsit block {:?}", ordinal);
+                None
+            }
         }
+    }
 
-        if self.blocks[ordinal as usize].header.ordinal != ordinal {
-            warn!("Blockchain try to get non-exsit block {:?}", ordinal);
-            return None;
-        }
+    /// Remove the blocks before the block with ordinal `before` and return
+    /// them, oldest first.
+    pub fn prune(&mut self, before: Ordinal) -> Vec<Block> {
+        let count = self
+            .blocks
+            .iter()
+            .take_while(|block| block.header.ordinal < before)
+            .count();
+        self.blocks.drain(..count).collect()
+    }
 
-        Some(ordinal as usize)
+    /// Remove the blocks from the block with ordinal `from` on and return
+    /// them, oldest first.
+    pub fn truncate(&mut self, from: Ordinal) -> Vec<Block> {
+        let count = self
+            .blocks
+            .iter()
+            .take_while(|block| block.header.ordinal < from)
+            .count();
+        self.blocks.drain(count..).collect()
     }
 
     pub fn retrieve_blocks(&self, start: Ordinal, end: Ordinal) -> Vec<Block> {
@@ -91,11 +118,15 @@ impl Chain {
             .get_block_position(ordinal)
             .ok_or_else(|| BlockchainError::InvalidBlockchainLength(self.len()))?;
 
+        // Write the block to a temporary file in the same directory first, so an interrupted
+        // write never leaves a truncated block behind and the rename stays on one filesystem.
+        let file_path = file_path.as_ref();
+        let temp_file_path = file_path.with_extension("tmp");
         let file = OpenOptions::new()
             .create(true)
             .write(true)
-            .append(false)
-            .open(file_path)
+            .truncate(true)
+            .open(&temp_file_path)
             .await;
 
         match file {
@@ -108,14 +139,19 @@ impl Chain {
             Err(e) => return Err(BlockchainError::IOError(e)),
         }
 
+        fs::rename(&temp_file_path, file_path).await?;
+
         Ok(())
     }
 
     /// Reads a list of blocks from the specified directory path
-    /// and adds them to the chain.
+    /// and adds them to the chain, starting with the oldest block that was
+    /// not pruned.
     pub async fn load_blocks(&mut self, path: impl AsRef<Path>) -> Result<(), BlockchainError> {
         let blockchain_path = path.as_ref().to_path_buf();
-        let mut ordinal = 0;
+        let mut ordinal = Self::first_saved_ordinal(&blockchain_path)
+            .await?
+            .unwrap_or_default();
         loop {
             let block_path = blockchain_path.join(format!("{}.ser", ordinal));
             if let Ok(block_metadata) = fs::metadata(&block_path).await {
@@ -134,6 +170,30 @@ impl Chain {
 
         Ok(())
     }
+
+    async fn first_saved_ordinal(
+        blockchain_path: &Path,
+    ) -> Result<Option<Ordinal>, BlockchainError> {
+        let mut entries = match fs::read_dir(blockchain_path).await {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(BlockchainError::IOError(e)),
+        };
+
+        let mut first_ordinal = None;
+        while let Some(entry) = entries.next_entry().await? {
+            let ordinal = entry
+                .file_name()
+                .to_str()
+                .and_then(|file_name| file_name.strip_suffix(".ser"))
+                .and_then(|ordinal| ordinal.parse::<Ordinal>().ok());
+            if let Some(ordinal) = ordinal {
+                first_ordinal =
+                    Some(first_ordinal.map_or(ordinal, |first: Ordinal| first.min(ordinal)));
+            }
+        }
+        Ok(first_ordinal)
+    }
 }
 
 #[cfg(test)]
@@ -326,6 +386,38 @@ mod tests {
         assert_eq!(2, chain2.len());
     }
 
+    #[tokio::test]
+    async fn test_prune_and_load_blocks() {
+        let temp_dir = tempfile::tempdir().unwrap().into_path();
+
+        let keypair = identity::ed25519::Keypair::generate();
+        let mut chain: Chain = Default::default();
+        for ordinal in 0..4 {
+            chain.add_block(Block::new(HashDigest::new(b""), ordinal, vec![], &keypair));
+            chain
+                .save_block(ordinal, temp_dir.join(format!("{}.ser", ordinal)))
+                .await
+                .expect("block should be saved");
+        }
+
+        let pruned = chain.prune(2);
+        assert_eq!(2, pruned.len());
+        assert_eq!(Some(2), chain.first_ordinal());
+        assert_eq!(None, chain.get_block_position(1));
+        assert_eq!(Some(1), chain.get_block_position(3));
+        assert_eq!(2, chain.retrieve_blocks(2, 3).len());
+
+        fs::remove_file(temp_dir.join("0.ser")).await.unwrap();
+        fs::remove_file(temp_dir.join("1.ser")).await.unwrap();
+        let mut chain2: Chain = Default::default();
+        chain2
+            .load_blocks(&temp_dir)
+            .await
+            .expect("blocks should have been loaded");
+
+        assert_eq!(chain, chain2);
+    }
+
     fn get_temp_file() -> PathBuf {
         tempfile::tempdir()
             .expect("could not create temporary directory")
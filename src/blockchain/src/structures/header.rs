@@ -31,6 +31,13 @@ pub struct Address {
     peer_id: Multihash,
 }
 
+impl Address {
+    /// The peer id of the node with this address, if it is a valid peer id.
+    pub fn peer_id(&self) -> Option<PeerId> {
+        PeerId::from_multihash(self.peer_id).ok()
+    }
+}
+
 impl From<identity::PublicKey> for Address {
     fn from(key: identity::PublicKey) -> Address {
         Self {
@@ -14,5 +14,11 @@
    limitations under the License.
 */
 
+pub mod audit;
+pub mod consensus;
 pub mod event;
+pub mod explorer;
+pub mod fork;
+pub mod genesis;
+pub mod pruning;
 pub mod service;
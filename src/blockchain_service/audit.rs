@@ -0,0 +1,323 @@
+/*
+   Copyright 2021 JFrog Ltd
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+*/
+
+//! A full verification of the local blockchain, for operators that validate
+//! a node after restoring it from a backup. Every block must follow the
+//! block before it, be signed by its committer, have the transactions its
+//! header commits to and payloads that decode to transparency logs, and no
+//! transaction or transparency log may occur twice. The audit stops
+//! at the first invalid block, because the blocks after it can not be
+//! trusted either.
+
+use crate::blockchain_service::explorer::hex_hash;
+use crate::transparency_log::log::TransparencyLogService;
+use pyrsia_blockchain_network::crypto::hash_algorithm::HashDigest;
+use pyrsia_blockchain_network::structures::block::Block;
+use pyrsia_blockchain_network::structures::header::Ordinal;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use thiserror::Error;
+
+/// Why a block of the blockchain is invalid.
+#[derive(Clone, Debug, Deserialize, Error, Serialize, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum BlockProblem {
+    #[error("the block does not follow block {parent_ordinal} with hash {parent_hash}")]
+    BrokenLink {
+        parent_ordinal: Ordinal,
+        parent_hash: String,
+    },
+    #[error("the block has an invalid signature")]
+    InvalidSignature,
+    #[error("the block is not signed by its committer")]
+    CommitterMismatch,
+    #[error("the transactions of the block do not match its transactions hash")]
+    TransactionsHashMismatch,
+    #[error("a payload of the block is not a list of transparency logs: {error}")]
+    UndecodablePayload { error: String },
+    #[error("transaction {hash} was already committed in block {ordinal}")]
+    DuplicateTransaction { hash: String, ordinal: Ordinal },
+    #[error("transparency log {id} was already published in block {ordinal}")]
+    DuplicateTransparencyLog { id: String, ordinal: Ordinal },
+}
+
+/// The first block that failed the audit.
+#[derive(Clone, Debug, Deserialize, Serialize, Eq, PartialEq)]
+pub struct InvalidBlock {
+    pub ordinal: Ordinal,
+    /// The hex encoded hash of the block header.
+    pub hash: String,
+    pub problem: BlockProblem,
+}
+
+/// The result of an audit of the local blockchain.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, Eq, PartialEq)]
+pub struct ChainAudit {
+    /// The ordinal of the oldest block, the blocks before it were pruned.
+    pub first_ordinal: Option<Ordinal>,
+    pub last_ordinal: Option<Ordinal>,
+    /// The number of blocks before the first invalid block, or of all
+    /// blocks when they are all valid.
+    pub valid_blocks: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub first_invalid_block: Option<InvalidBlock>,
+}
+
+impl ChainAudit {
+    pub fn is_valid(&self) -> bool {
+        self.first_invalid_block.is_none()
+    }
+}
+
+/// Audit `blocks`, oldest first. The first block is not checked against its
+/// parent, which may have been pruned.
+pub fn audit_chain(blocks: &[Block]) -> ChainAudit {
+    let mut audit = ChainAudit {
+        first_ordinal: blocks.first().map(|block| block.header.ordinal),
+        last_ordinal: blocks.last().map(|block| block.header.ordinal),
+        ..Default::default()
+    };
+    let mut seen = SeenItems::default();
+    let mut parent: Option<&Block> = None;
+
+    for block in blocks {
+        if let Some(problem) = audit_block(block, parent, &mut seen) {
+            audit.first_invalid_block = Some(InvalidBlock {
+                ordinal: block.header.ordinal,
+                hash: hex_hash(&block.header.hash()),
+                problem,
+            });
+            break;
+        }
+        audit.valid_blocks += 1;
+        parent = Some(block);
+    }
+
+    audit
+}
+
+/// The transactions and transparency logs of the audited blocks,
+/// with the ordinal of the block they occurred in first.
+#[derive(Default)]
+struct SeenItems {
+    transactions: HashMap<HashDigest, Ordinal>,
+    transparency_logs: HashMap<String, Ordinal>,
+}
+
+fn audit_block(
+    block: &Block,
+    parent: Option<&Block>,
+    seen: &mut SeenItems,
+) -> Option<BlockProblem> {
+    let ordinal = block.header.ordinal;
+
+    if let Some(parent) = parent {
+        if ordinal != parent.header.ordinal + 1 || block.header.parent_hash != parent.header.hash()
+        {
+            return Some(BlockProblem::BrokenLink {
+                parent_ordinal: parent.header.ordinal,
+                parent_hash: hex_hash(&parent.header.hash()),
+            });
+        }
+    }
+    if !block.verify() {
+        return Some(BlockProblem::InvalidSignature);
+    }
+    if block.signer() != Some(block.header.committer) {
+        return Some(BlockProblem::CommitterMismatch);
+    }
+    let transactions_hash = bincode::serialize(&block.transactions)
+        .map(|transactions| HashDigest::new(&transactions))
+        .ok();
+    if transactions_hash != Some(block.header.transactions_hash) {
+        return Some(BlockProblem::TransactionsHashMismatch);
+    }
+
+    // the payload of the genesis block is the genesis configuration
+    let mut transparency_log_ids = vec![];
+    if ordinal > 0 {
+        for payload in block.fetch_payload() {
+            match TransparencyLogService::parse_payload(&payload) {
+                Ok(transparency_logs) => {
+                    transparency_log_ids.extend(transparency_logs.into_iter().map(|log| log.id))
+                }
+                Err(error) => {
+                    return Some(BlockProblem::UndecodablePayload {
+                        error: error.to_string(),
+                    })
+                }
+            }
+        }
+    }
+
+    for transaction in &block.transactions {
+        if let Some(first_ordinal) = seen.transactions.get(&transaction.hash()) {
+            return Some(BlockProblem::DuplicateTransaction {
+                hash: hex_hash(&transaction.hash()),
+                ordinal: *first_ordinal,
+            });
+        }
+    }
+    for id in &transparency_log_ids {
+        if let Some(first_ordinal) = seen.transparency_logs.get(id) {
+            return Some(BlockProblem::DuplicateTransparencyLog {
+                id: id.clone(),
+                ordinal: *first_ordinal,
+            });
+        }
+    }
+
+    for transaction in &block.transactions {
+        seen.transactions.insert(transaction.hash(), ordinal);
+    }
+    for id in transparency_log_ids {
+        seen.transparency_logs.insert(id, ordinal);
+    }
+    None
+}
+
+#[cfg(test)]
+#[cfg(not(tarpaulin_include))]
+mod tests {
+    use super::*;
+    use crate::artifact_service::model::PackageType;
+    use crate::transparency_log::log::{AddArtifactRequest, TransparencyLog};
+    use libp2p::identity;
+    use pyrsia_blockchain_network::structures::header::Address;
+    use pyrsia_blockchain_network::structures::transaction::{Transaction, TransactionType};
+
+    fn transaction(keypair: &identity::ed25519::Keypair, payload: Vec<u8>) -> Transaction {
+        Transaction::new(
+            TransactionType::Create,
+            Address::from(identity::PublicKey::Ed25519(keypair.public())),
+            payload,
+            keypair,
+        )
+    }
+
+    fn transparency_log(package_specific_id: &str) -> TransparencyLog {
+        TransparencyLog::from(AddArtifactRequest {
+            package_type: PackageType::Docker,
+            package_specific_id: package_specific_id.to_owned(),
+            num_artifacts: 1,
+            package_specific_artifact_id: format!("{}@sha256:1", package_specific_id),
+            artifact_hash: "e11c16ff163ccc1efe01d2696c626891560fa82123601a5ff196d97b6ab156da"
+                .to_owned(),
+        })
+    }
+
+    fn chain(keypair: &identity::ed25519::Keypair, payloads: Vec<Vec<u8>>) -> Vec<Block> {
+        let mut blocks = vec![Block::new(
+            HashDigest::new(b""),
+            0,
+            vec![transaction(keypair, b"genesis".to_vec())],
+            keypair,
+        )];
+        for payload in payloads {
+            let parent = blocks.last().unwrap();
+            let block = Block::new(
+                parent.header.hash(),
+                parent.header.ordinal + 1,
+                vec![transaction(keypair, payload)],
+                keypair,
+            );
+            blocks.push(block);
+        }
+        blocks
+    }
+
+    #[test]
+    fn test_audit_valid_chain() {
+        let keypair = identity::ed25519::Keypair::generate();
+        let blocks = chain(
+            &keypair,
+            vec![
+                serde_json::to_vec(&[transparency_log("library/alpine:3.16")]).unwrap(),
+                serde_json::to_vec(&[transparency_log("library/alpine:3.17")]).unwrap(),
+            ],
+        );
+
+        let audit = audit_chain(&blocks);
+        assert!(audit.is_valid());
+        assert_eq!(audit.valid_blocks, 3);
+        assert_eq!(audit.first_ordinal, Some(0));
+        assert_eq!(audit.last_ordinal, Some(2));
+
+        // a pruned chain is audited from its first block on
+        assert!(audit_chain(&blocks[1..]).is_valid());
+        assert!(audit_chain(&[]).is_valid());
+    }
+
+    #[test]
+    fn test_audit_reports_first_invalid_block() {
+        let keypair = identity::ed25519::Keypair::generate();
+        let log = transparency_log("library/alpine:3.16");
+        let blocks = chain(
+            &keypair,
+            vec![
+                serde_json::to_vec(&[log.clone()]).unwrap(),
+                b"not a transparency log".to_vec(),
+            ],
+        );
+        let audit = audit_chain(&blocks);
+        assert_eq!(audit.valid_blocks, 2);
+        let invalid_block = audit.first_invalid_block.unwrap();
+        assert_eq!(invalid_block.ordinal, 2);
+        assert!(matches!(
+            invalid_block.problem,
+            BlockProblem::UndecodablePayload { .. }
+        ));
+
+        let blocks = chain(
+            &keypair,
+            vec![
+                serde_json::to_vec(&[log.clone()]).unwrap(),
+                serde_json::to_vec(&[log.clone()]).unwrap(),
+            ],
+        );
+        assert_eq!(
+            audit_chain(&blocks).first_invalid_block.unwrap().problem,
+            BlockProblem::DuplicateTransparencyLog {
+                id: log.id.clone(),
+                ordinal: 1
+            }
+        );
+
+        let mut blocks = chain(
+            &keypair,
+            vec![
+                serde_json::to_vec(&[log]).unwrap(),
+                serde_json::to_vec(&[transparency_log("library/alpine:3.17")]).unwrap(),
+            ],
+        );
+        blocks[1].transactions.clear();
+        let audit = audit_chain(&blocks);
+        assert_eq!(audit.valid_blocks, 1);
+        assert_eq!(
+            audit.first_invalid_block.unwrap().problem,
+            BlockProblem::TransactionsHashMismatch
+        );
+
+        blocks.remove(1);
+        assert!(matches!(
+            audit_chain(&blocks).first_invalid_block.unwrap().problem,
+            BlockProblem::BrokenLink {
+                parent_ordinal: 0,
+                ..
+            }
+        ));
+    }
+}
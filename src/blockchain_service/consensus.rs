@@ -0,0 +1,172 @@
+/*
+   Copyright 2021 JFrog Ltd
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+*/
+
+use async_trait::async_trait;
+use libp2p::identity;
+use log::warn;
+use pyrsia_blockchain_network::blockchain::Blockchain;
+use pyrsia_blockchain_network::error::BlockchainError;
+use pyrsia_blockchain_network::structures::block::Block;
+use pyrsia_blockchain_network::structures::header::Ordinal;
+use std::cmp::Ordering;
+
+/// Decides which blocks are committed to the local blockchain, both the
+/// blocks with payloads of this node and the blocks received from other
+/// nodes. The [`BlockchainService`](super::service::BlockchainService)
+/// delegates to it, so that an algorithm like Raft among the authorized
+/// nodes or proof-of-authority with rotation can replace the default
+/// [`SequentialConsensus`] without changes to the users of the service.
+#[async_trait]
+pub trait Consensus: Send + Sync {
+    /// Propose a block with `payload`, signed with `keypair`, and return it
+    /// once it is committed to `blockchain`, so that it is broadcast to the
+    /// other nodes.
+    async fn propose(
+        &mut self,
+        blockchain: &mut Blockchain,
+        payload: Vec<u8>,
+        keypair: &identity::ed25519::Keypair,
+    ) -> Result<Block, BlockchainError>;
+
+    /// Handle a block with `ordinal` that was broadcast by or pulled from
+    /// another node, and commit it to `blockchain` when it is accepted.
+    async fn receive(
+        &mut self,
+        blockchain: &mut Blockchain,
+        ordinal: Ordinal,
+        block: Box<Block>,
+    ) -> Result<(), BlockchainError>;
+}
+
+#[async_trait]
+impl<C: Consensus + ?Sized> Consensus for Box<C> {
+    async fn propose(
+        &mut self,
+        blockchain: &mut Blockchain,
+        payload: Vec<u8>,
+        keypair: &identity::ed25519::Keypair,
+    ) -> Result<Block, BlockchainError> {
+        (**self).propose(blockchain, payload, keypair).await
+    }
+
+    async fn receive(
+        &mut self,
+        blockchain: &mut Blockchain,
+        ordinal: Ordinal,
+        block: Box<Block>,
+    ) -> Result<(), BlockchainError> {
+        (**self).receive(blockchain, ordinal, block).await
+    }
+}
+
+/// Commits the blocks of this node right away, and the blocks of other
+/// nodes in the order of their ordinals. Duplicate blocks are ignored, and
+/// a block after a gap fails with [`BlockchainError::LaggingBlockchainData`]
+/// so that the missing blocks are pulled first.
+#[derive(Debug, Default)]
+pub struct SequentialConsensus;
+
+#[async_trait]
+impl Consensus for SequentialConsensus {
+    async fn propose(
+        &mut self,
+        blockchain: &mut Blockchain,
+        payload: Vec<u8>,
+        keypair: &identity::ed25519::Keypair,
+    ) -> Result<Block, BlockchainError> {
+        blockchain
+            .add_block(payload, &identity::Keypair::Ed25519(keypair.clone()))
+            .await?;
+
+        blockchain
+            .last_block()
+            .ok_or(BlockchainError::EmptyBlockchain)
+    }
+
+    async fn receive(
+        &mut self,
+        blockchain: &mut Blockchain,
+        ordinal: Ordinal,
+        block: Box<Block>,
+    ) -> Result<(), BlockchainError> {
+        match blockchain.last_block() {
+            None => {
+                if ordinal == 0 {
+                    blockchain.update_block_from_peers(block).await
+                } else {
+                    Ok(())
+                }
+            }
+
+            Some(last_block) => {
+                let expected = last_block.header.ordinal + 1;
+                match ordinal.cmp(&expected) {
+                    Ordering::Greater => Err(BlockchainError::LaggingBlockchainData),
+                    Ordering::Less => {
+                        warn!("Blockchain received a duplicate block!");
+                        Ok(())
+                    }
+                    Ordering::Equal => blockchain.update_block_from_peers(block).await,
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+#[cfg(not(tarpaulin_include))]
+mod tests {
+    use super::*;
+    use crate::util::test_util;
+
+    #[tokio::test]
+    async fn test_sequential_consensus() {
+        let tmp_dir = test_util::tests::setup();
+
+        let keypair = identity::ed25519::Keypair::generate();
+        let mut blockchain = Blockchain::new(&keypair, &tmp_dir).await.unwrap();
+        let mut consensus = SequentialConsensus::default();
+
+        let proposed = consensus
+            .propose(&mut blockchain, b"payload".to_vec(), &keypair)
+            .await
+            .unwrap();
+        assert_eq!(proposed.header.ordinal, 1);
+        assert_eq!(blockchain.last_block(), Some(proposed.clone()));
+
+        // A duplicate block is ignored.
+        consensus
+            .receive(&mut blockchain, 1, Box::new(proposed.clone()))
+            .await
+            .unwrap();
+        assert_eq!(blockchain.last_block(), Some(proposed.clone()));
+
+        let next = Block::new(proposed.header.hash(), 2, vec![], &keypair);
+        assert!(matches!(
+            consensus
+                .receive(&mut blockchain, 3, Box::new(next.clone()))
+                .await,
+            Err(BlockchainError::LaggingBlockchainData)
+        ));
+        consensus
+            .receive(&mut blockchain, 2, Box::new(next.clone()))
+            .await
+            .unwrap();
+        assert_eq!(blockchain.last_block(), Some(next));
+
+        test_util::tests::teardown(tmp_dir);
+    }
+}
@@ -15,6 +15,8 @@
 */
 
 use crate::artifact_service::service::ArtifactService;
+use crate::blockchain_service::audit::ChainAudit;
+use crate::blockchain_service::fork::ForkResolution;
 use crate::blockchain_service::service::BlockchainService;
 use libp2p::PeerId;
 use log::{debug, error, warn};
@@ -33,6 +35,11 @@ pub enum BlockchainEvent {
         peer_id: PeerId,
         sender: oneshot::Sender<Result<Ordinal, BlockchainError>>,
     },
+    PullBlocksSinceFromPeer {
+        peer_id: PeerId,
+        start: Ordinal,
+        sender: oneshot::Sender<Result<Vec<Block>, BlockchainError>>,
+    },
     PullBlocksLocal {
         start: Ordinal,
         end: Ordinal,
@@ -51,6 +58,16 @@ pub enum BlockchainEvent {
     HandleQueryBlockOrdinal {
         sender: oneshot::Sender<anyhow::Result<Ordinal>>,
     },
+    ExportBlocks {
+        sender: oneshot::Sender<Result<Vec<u8>, BlockchainError>>,
+    },
+    ImportBlocks {
+        export: Vec<u8>,
+        sender: oneshot::Sender<Result<usize, BlockchainError>>,
+    },
+    AuditBlocks {
+        sender: oneshot::Sender<Result<ChainAudit, BlockchainError>>,
+    },
 }
 
 #[derive(Clone)]
@@ -93,6 +110,27 @@ impl BlockchainEventClient {
         receiver.await.map_err(BlockchainError::ChannelClosed)?
     }
 
+    /// Pull the blocks of the peer from `start` on without adding them to
+    /// the local blockchain.
+    pub async fn pull_blocks_since_from_peer(
+        &self,
+        peer_id: &PeerId,
+        start: Ordinal,
+    ) -> Result<Vec<Block>, BlockchainError> {
+        let (sender, receiver) = oneshot::channel();
+        self.blockchain_event_sender
+            .send(BlockchainEvent::PullBlocksSinceFromPeer {
+                peer_id: *peer_id,
+                start,
+                sender,
+            })
+            .await
+            .unwrap_or_else(|e| {
+                error!("Error blockchain_event_sender. {:#?}", e);
+            });
+        receiver.await.map_err(BlockchainError::ChannelClosed)?
+    }
+
     pub async fn pull_blocks_local(
         &self,
         start: Ordinal,
@@ -108,6 +146,43 @@ impl BlockchainEventClient {
         receiver.await.map_err(BlockchainError::ChannelClosed)?
     }
 
+    /// Export all blocks of the local blockchain.
+    pub async fn export_blocks(&self) -> Result<Vec<u8>, BlockchainError> {
+        let (sender, receiver) = oneshot::channel();
+        self.blockchain_event_sender
+            .send(BlockchainEvent::ExportBlocks { sender })
+            .await
+            .unwrap_or_else(|e| {
+                error!("Error blockchain_event_sender. {:#?}", e);
+            });
+        receiver.await.map_err(BlockchainError::ChannelClosed)?
+    }
+
+    /// Import the blocks of an export of the blockchain and apply them to
+    /// the transparency log. Returns the number of imported blocks.
+    pub async fn import_blocks(&self, export: Vec<u8>) -> Result<usize, BlockchainError> {
+        let (sender, receiver) = oneshot::channel();
+        self.blockchain_event_sender
+            .send(BlockchainEvent::ImportBlocks { export, sender })
+            .await
+            .unwrap_or_else(|e| {
+                error!("Error blockchain_event_sender. {:#?}", e);
+            });
+        receiver.await.map_err(BlockchainError::ChannelClosed)?
+    }
+
+    /// Audit all blocks of the local blockchain.
+    pub async fn audit_blocks(&self) -> Result<ChainAudit, BlockchainError> {
+        let (sender, receiver) = oneshot::channel();
+        self.blockchain_event_sender
+            .send(BlockchainEvent::AuditBlocks { sender })
+            .await
+            .unwrap_or_else(|e| {
+                error!("Error blockchain_event_sender. {:#?}", e);
+            });
+        receiver.await.map_err(BlockchainError::ChannelClosed)?
+    }
+
     pub async fn handle_broadcast_blockchain(
         &self,
         block_ordinal: Ordinal,
@@ -191,22 +266,152 @@ impl BlockchainEventLoop {
         }
     }
 
+    // The blocks pulled from another node are applied to the transparency
+    // log before the next event is handled, so that no block is pruned
+    // before its transparency logs are written. The genesis block has no
+    // transparency logs.
+    async fn apply_pulled_blocks(&mut self, ordinal: Ordinal) -> anyhow::Result<()> {
+        for block in self.blockchain_service.pull_blocks(1, ordinal).await? {
+            self.artifact_service.audit_block(&block)?;
+            self.artifact_service.handle_block_added(&block).await?;
+        }
+        Ok(())
+    }
+
+    // The imported blocks are applied to the transparency log like pulled
+    // blocks, apart from the genesis block, which has no transparency logs.
+    async fn import_blocks(&mut self, export: &[u8]) -> Result<usize, BlockchainError> {
+        let imported = self.blockchain_service.import_blocks(export).await?;
+        for block in imported.iter().filter(|block| block.header.ordinal > 0) {
+            self.artifact_service.audit_block(block)?;
+            self.artifact_service.handle_block_added(block).await?;
+        }
+        Ok(imported.len())
+    }
+
+    // The transparency logs of a block that this node committed are written
+    // by the service that published them, only the block that published
+    // them is recorded here.
+    fn record_committed_block(&self, block: &Block) {
+        if let Err(e) = self
+            .artifact_service
+            .transparency_log_service
+            .record_block(block)
+        {
+            warn!(
+                "Failed to record block {} as publisher of its transparency logs: {}",
+                block.header.ordinal, e
+            );
+        }
+    }
+
+    // The transparency logs of the losing branch of a fork are rolled back
+    // and the ones of the winning branch applied. The transparency logs that
+    // this node committed on the losing branch are kept, and committed again
+    // on top of the winning branch.
+    async fn apply_fork_resolution(
+        &mut self,
+        fork_resolution: ForkResolution,
+    ) -> anyhow::Result<()> {
+        let ForkResolution {
+            fork_ordinal,
+            rolled_back,
+            applied,
+        } = fork_resolution;
+        let (local_blocks, other_blocks): (Vec<Block>, Vec<Block>) = rolled_back
+            .into_iter()
+            .partition(|block| self.blockchain_service.is_local_block(block));
+
+        let rolled_back_logs = self
+            .artifact_service
+            .rollback_blocks(&other_blocks, &applied)?;
+        debug!(
+            "Rolled back {} transparency logs of the fork at block {}",
+            rolled_back_logs, fork_ordinal
+        );
+
+        let applied_payloads: Vec<Vec<u8>> =
+            applied.iter().flat_map(Block::fetch_payload).collect();
+        for block in applied {
+            self.artifact_service.audit_block(&block)?;
+            self.artifact_service.handle_block_added(&block).await?;
+        }
+
+        for payload in local_blocks.iter().flat_map(Block::fetch_payload) {
+            if !applied_payloads.contains(&payload) {
+                let block = self.blockchain_service.add_payload(payload).await?;
+                self.record_committed_block(&block);
+            }
+        }
+        Ok(())
+    }
+
+    // The nodes that may commit the blocks of a branch that wins a fork.
+    // Without them, no remote branch can replace local blocks.
+    fn authorized_nodes(&self) -> Vec<PeerId> {
+        self.artifact_service
+            .transparency_log_service
+            .get_authorized_nodes()
+            .unwrap_or_else(|e| {
+                warn!("Failed to read the authorized nodes: {}", e);
+                vec![]
+            })
+    }
+
+    async fn prune_blocks(&mut self) {
+        if let Err(e) = self.blockchain_service.prune_blocks().await {
+            warn!("Failed to prune blocks of the local blockchain: {:?}", e);
+        }
+    }
+
     async fn handle_blockchain_event(&mut self, blockchain_event: BlockchainEvent) {
         debug!("Handle BlockchainEvent: {:?}", blockchain_event);
         match blockchain_event {
             BlockchainEvent::AddBlock { payload, sender } => {
-                let result = self.blockchain_service.add_payload(payload).await;
+                let result = match self.blockchain_service.add_payload(payload).await {
+                    Ok(block) => {
+                        self.record_committed_block(&block);
+                        self.prune_blocks().await;
+                        Ok(())
+                    }
+                    Err(e) => Err(e),
+                };
                 sender.send(result).unwrap_or_else(|e| {
                     error!("add block error. {:#?}", e);
                 });
             }
             BlockchainEvent::PullBlocksFromPeer { peer_id, sender } => {
+                let authorized_nodes = self.authorized_nodes();
+                let result = match self
+                    .blockchain_service
+                    .init_pull_from_others(&peer_id, &authorized_nodes)
+                    .await
+                {
+                    Ok(ordinal) => self
+                        .apply_pulled_blocks(ordinal)
+                        .await
+                        .map(|_| ordinal)
+                        .map_err(BlockchainError::AnyhowError),
+                    Err(e) => Err(e),
+                };
+                if result.is_ok() {
+                    self.prune_blocks().await;
+                }
+                sender.send(result).unwrap_or_else(|e| {
+                    error!("pull blocks from peer error. {:#?}", e);
+                });
+            }
+            BlockchainEvent::PullBlocksSinceFromPeer {
+                peer_id,
+                start,
+                sender,
+            } => {
                 let result = self
                     .blockchain_service
-                    .init_pull_from_others(&peer_id)
+                    .pull_blocks_since_from_others(&peer_id, start)
                     .await;
                 sender.send(result).unwrap_or_else(|e| {
-                    error!("pull blocks from peer error. {:#?}", e);
+                    error!("pull blocks since from peer error. {:#?}", e);
                 });
             }
             BlockchainEvent::PullBlocksLocal { start, end, sender } => {
@@ -224,24 +429,28 @@ impl BlockchainEventLoop {
             } => {
                 debug!("Handling broadcast blocks");
 
-                let payloads = block.fetch_payload();
-                if let Err(e) = self
+                if let Err(e) = self.artifact_service.audit_block(&block) {
+                    warn!("Failed to audit block {}: {:?}", block_ordinal, e);
+                }
+                let authorized_nodes = self.authorized_nodes();
+                let result = match self
                     .blockchain_service
-                    .add_block(block_ordinal, block)
+                    .add_block(block_ordinal, block.clone(), &authorized_nodes)
                     .await
                 {
-                    sender.send(Err(e.into())).unwrap_or_else(|e| {
-                        error!("block broadcast error. {:#?}", e);
-                    });
-                } else if let Err(e) = self.artifact_service.handle_block_added(payloads).await {
-                    sender.send(Err(e)).unwrap_or_else(|e| {
-                        error!("block broadcast error. {:#?}", e);
-                    });
-                } else {
-                    sender.send(Ok(())).unwrap_or_else(|e| {
-                        error!("block broadcast error. {:#?}", e);
-                    });
+                    Err(e) => Err(e.into()),
+                    Ok(Some(fork_resolution)) => self.apply_fork_resolution(fork_resolution).await,
+                    // A duplicate block or the losing branch of a fork is
+                    // not part of the local blockchain.
+                    Ok(None) if !self.blockchain_service.contains_block(&block) => Ok(()),
+                    Ok(None) => self.artifact_service.handle_block_added(&block).await,
+                };
+                if result.is_ok() {
+                    self.prune_blocks().await;
                 }
+                sender.send(result).unwrap_or_else(|e| {
+                    error!("block broadcast error. {:#?}", e);
+                });
             }
             BlockchainEvent::HandlePullBlocks { start, end, sender } => {
                 debug!("Handling pull blocks from {:?} to {:?} ", start, end);
@@ -253,6 +462,27 @@ impl BlockchainEventLoop {
                         error!("block broadcast error. {:#?}", e);
                     });
             }
+            BlockchainEvent::ExportBlocks { sender } => {
+                let result = self.blockchain_service.export_blocks();
+                sender.send(result).unwrap_or_else(|e| {
+                    error!("export blocks error. {:#?}", e);
+                });
+            }
+            BlockchainEvent::ImportBlocks { export, sender } => {
+                let result = self.import_blocks(&export).await;
+                if result.is_ok() {
+                    self.prune_blocks().await;
+                }
+                sender.send(result).unwrap_or_else(|e| {
+                    error!("import blocks error. {:#?}", e);
+                });
+            }
+            BlockchainEvent::AuditBlocks { sender } => {
+                let result = self.blockchain_service.audit_blocks();
+                sender.send(result).unwrap_or_else(|e| {
+                    error!("audit blocks error. {:#?}", e);
+                });
+            }
             BlockchainEvent::HandleQueryBlockOrdinal { sender } => {
                 debug!("Handling query block ordinal");
 
@@ -0,0 +1,156 @@
+/*
+   Copyright 2021 JFrog Ltd
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+*/
+
+use crate::transparency_log::log::{TransparencyLog, TransparencyLogService};
+use pyrsia_blockchain_network::crypto::hash_algorithm::HashDigest;
+use pyrsia_blockchain_network::structures::block::Block;
+use pyrsia_blockchain_network::structures::header::Ordinal;
+use serde::{Deserialize, Serialize};
+
+/// The number of blocks that are listed when a query has no limit.
+pub const DEFAULT_BLOCK_LIST_LIMIT: usize = 20;
+
+/// The query parameters of a request to list the most recent blocks.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, Eq, PartialEq)]
+pub struct BlockListQuery {
+    pub limit: Option<usize>,
+}
+
+/// A block of the local blockchain as served by the block explorer, with
+/// its payloads decoded into the transparency logs they published.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct BlockView {
+    pub ordinal: Ordinal,
+    /// The hex encoded hash of the block header.
+    pub hash: String,
+    pub parent_hash: String,
+    /// The peer id of the node that committed the block.
+    pub committer: Option<String>,
+    /// The time the block was created, in seconds since the unix epoch.
+    pub timestamp: u64,
+    /// Whether the signature of the block is valid.
+    pub verified: bool,
+    pub transparency_logs: Vec<TransparencyLog>,
+    /// The payloads that are not transparency logs, lossily decoded as UTF-8.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub undecoded_payloads: Vec<String>,
+}
+
+impl From<&Block> for BlockView {
+    fn from(block: &Block) -> Self {
+        let mut transparency_logs = vec![];
+        let mut undecoded_payloads = vec![];
+        for payload in block.fetch_payload() {
+            match TransparencyLogService::parse_payload(&payload) {
+                Ok(logs) => transparency_logs.extend(logs),
+                Err(_) => undecoded_payloads.push(String::from_utf8_lossy(&payload).into_owned()),
+            }
+        }
+
+        BlockView {
+            ordinal: block.header.ordinal,
+            hash: hex_hash(&block.header.hash()),
+            parent_hash: hex_hash(&block.header.parent_hash),
+            committer: block
+                .header
+                .committer
+                .peer_id()
+                .map(|peer_id| peer_id.to_string()),
+            timestamp: block.header.timestamp,
+            verified: block.verify(),
+            transparency_logs,
+            undecoded_payloads,
+        }
+    }
+}
+
+/// The hex encoding of a block hash, as used by the block explorer.
+pub fn hex_hash(hash: &HashDigest) -> String {
+    hex::encode(hash.to_slice())
+}
+
+/// How a block is identified in a request of the block explorer: by its
+/// ordinal, i.e. its height in the blockchain, or by the hex encoded hash of
+/// its header.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum BlockId {
+    Ordinal(Ordinal),
+    Hash(String),
+}
+
+impl BlockId {
+    pub fn parse(id: &str) -> Option<Self> {
+        if id.len() == 64 && id.chars().all(|c| c.is_ascii_hexdigit()) {
+            Some(BlockId::Hash(id.to_ascii_lowercase()))
+        } else {
+            id.parse().ok().map(BlockId::Ordinal)
+        }
+    }
+}
+
+#[cfg(test)]
+#[cfg(not(tarpaulin_include))]
+mod tests {
+    use super::*;
+    use crate::artifact_service::model::PackageType;
+    use crate::transparency_log::log::AddArtifactRequest;
+    use libp2p::identity;
+    use pyrsia_blockchain_network::structures::header::Address;
+    use pyrsia_blockchain_network::structures::transaction::{Transaction, TransactionType};
+
+    #[test]
+    fn test_block_view() {
+        let keypair = identity::ed25519::Keypair::generate();
+        let public_key = identity::PublicKey::Ed25519(keypair.public());
+        let transparency_log = TransparencyLog::from(AddArtifactRequest {
+            package_type: PackageType::Docker,
+            package_specific_id: "library/alpine:3.17".to_owned(),
+            num_artifacts: 1,
+            package_specific_artifact_id: "library/alpine:3.17".to_owned(),
+            artifact_hash: "aaaa".to_owned(),
+        });
+        let transactions = vec![
+            Transaction::new(
+                TransactionType::Create,
+                Address::from(public_key.clone()),
+                serde_json::to_vec(&transparency_log).unwrap(),
+                &keypair,
+            ),
+            Transaction::new(
+                TransactionType::Create,
+                Address::from(public_key.clone()),
+                b"not a transparency log".to_vec(),
+                &keypair,
+            ),
+        ];
+        let block = Block::new(HashDigest::new(b""), 3, transactions, &keypair);
+
+        let view = BlockView::from(&block);
+        assert_eq!(view.ordinal, 3);
+        assert_eq!(view.hash, hex_hash(&block.header.hash()));
+        assert_eq!(view.committer, Some(public_key.to_peer_id().to_string()));
+        assert!(view.verified);
+        assert_eq!(view.transparency_logs, vec![transparency_log]);
+        assert_eq!(view.undecoded_payloads, vec!["not a transparency log"]);
+
+        assert_eq!(BlockId::parse("3"), Some(BlockId::Ordinal(3)));
+        assert_eq!(
+            BlockId::parse(&view.hash.to_uppercase()),
+            Some(BlockId::Hash(view.hash))
+        );
+        assert_eq!(BlockId::parse("latest"), None);
+    }
+}
@@ -0,0 +1,153 @@
+/*
+   Copyright 2021 JFrog Ltd
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+*/
+
+//! Two authorized nodes that commit a block at the same time both commit
+//! a block with the same ordinal, and their blockchains fork. Every node
+//! resolves a fork with the same rule, so that all nodes end up with the
+//! same blockchain: the branch with the most blocks wins, and of branches
+//! with as many blocks the branch whose first block has the lowest hash.
+
+use libp2p::PeerId;
+use pyrsia_blockchain_network::error::BlockchainError;
+use pyrsia_blockchain_network::structures::block::Block;
+use pyrsia_blockchain_network::structures::header::Ordinal;
+
+/// The blocks of the local blockchain that were replaced by the winning
+/// branch of a fork.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ForkResolution {
+    /// The ordinal of the first block of both branches.
+    pub fork_ordinal: Ordinal,
+    /// The blocks of the losing local branch, oldest first.
+    pub rolled_back: Vec<Block>,
+    /// The blocks of the winning branch that replaced them, oldest first.
+    pub applied: Vec<Block>,
+}
+
+/// Whether the `remote` branch wins over the `local` branch, both starting
+/// right after the last block they have in common.
+pub fn remote_branch_wins(local: &[Block], remote: &[Block]) -> bool {
+    match (local.first(), remote.first()) {
+        (_, None) => false,
+        (None, Some(_)) => true,
+        (Some(local_first), Some(remote_first)) => {
+            remote.len() > local.len()
+                || (remote.len() == local.len()
+                    && remote_first.header.hash().to_slice() < local_first.header.hash().to_slice())
+        }
+    }
+}
+
+/// Verify the blocks of the `remote` branch of a fork before they replace
+/// local blocks: every block must be signed by its committer, and the
+/// committer must be one of the `authorized_nodes`. Otherwise any peer could
+/// rewrite the local blockchain with a longer branch of its own.
+pub fn verify_branch(remote: &[Block], authorized_nodes: &[PeerId]) -> Result<(), BlockchainError> {
+    for block in remote {
+        let ordinal = block.header.ordinal;
+        if !block.verify() || block.signer() != Some(block.header.committer) {
+            return Err(BlockchainError::InvalidBlockSignature(ordinal));
+        }
+        let committer = block.header.committer.peer_id();
+        if !committer.map_or(false, |committer| authorized_nodes.contains(&committer)) {
+            return Err(BlockchainError::UnauthorizedCommitter(ordinal));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+#[cfg(not(tarpaulin_include))]
+mod tests {
+    use super::*;
+    use libp2p::identity;
+    use pyrsia_blockchain_network::crypto::hash_algorithm::HashDigest;
+    use pyrsia_blockchain_network::structures::header::Address;
+
+    fn branch(parent_hash: HashDigest, length: usize) -> Vec<Block> {
+        branch_of(&identity::ed25519::Keypair::generate(), parent_hash, length)
+    }
+
+    fn branch_of(
+        keypair: &identity::ed25519::Keypair,
+        parent_hash: HashDigest,
+        length: usize,
+    ) -> Vec<Block> {
+        let mut blocks: Vec<Block> = vec![];
+        for ordinal in 1..=length as Ordinal {
+            let parent_hash = blocks
+                .last()
+                .map_or(parent_hash, |block| block.header.hash());
+            blocks.push(Block::new(parent_hash, ordinal, vec![], keypair));
+        }
+        blocks
+    }
+
+    #[test]
+    fn test_remote_branch_wins() {
+        let parent_hash = HashDigest::new(b"genesis");
+        let short = branch(parent_hash, 1);
+        let long = branch(parent_hash, 2);
+
+        assert!(remote_branch_wins(&short, &long));
+        assert!(!remote_branch_wins(&long, &short));
+        assert!(remote_branch_wins(&[], &short));
+        assert!(!remote_branch_wins(&short, &[]));
+
+        let other = branch(parent_hash, 1);
+        assert_ne!(
+            remote_branch_wins(&short, &other),
+            remote_branch_wins(&other, &short)
+        );
+        assert!(!remote_branch_wins(&short, &short));
+    }
+
+    #[test]
+    fn test_verify_branch() {
+        let keypair = identity::ed25519::Keypair::generate();
+        let committer = identity::PublicKey::Ed25519(keypair.public()).to_peer_id();
+        let remote = branch_of(&keypair, HashDigest::new(b"genesis"), 2);
+
+        assert!(verify_branch(&remote, &[committer]).is_ok());
+        assert!(verify_branch(&[], &[]).is_ok());
+        assert!(matches!(
+            verify_branch(&remote, &[PeerId::random()]),
+            Err(BlockchainError::UnauthorizedCommitter(1))
+        ));
+
+        // a block that was changed after it was signed
+        let mut forged = remote.clone();
+        forged[1].header.ordinal = 3;
+        assert!(matches!(
+            verify_branch(&forged, &[committer]),
+            Err(BlockchainError::InvalidBlockSignature(3))
+        ));
+
+        // a block signed by another node than its committer
+        let mut forged = remote;
+        forged[0] = Block::new(
+            HashDigest::new(b"genesis"),
+            1,
+            vec![],
+            &identity::ed25519::Keypair::generate(),
+        );
+        forged[0].header.committer = Address::from(identity::PublicKey::Ed25519(keypair.public()));
+        assert!(matches!(
+            verify_branch(&forged, &[committer]),
+            Err(BlockchainError::InvalidBlockSignature(1))
+        ));
+    }
+}
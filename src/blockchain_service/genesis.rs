@@ -0,0 +1,207 @@
+/*
+   Copyright 2021 JFrog Ltd
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+*/
+
+use libp2p::PeerId;
+use pyrsia_blockchain_network::structures::block::Block;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+use thiserror::Error;
+
+/// The id of the network of nodes without a genesis configuration.
+pub const DEFAULT_NETWORK_ID: &str = "pyrsia";
+
+// The payload of the genesis block of the default network, which predates
+// genesis configurations.
+const DEFAULT_GENESIS_PAYLOAD: &str = "this is the first reserved transaction";
+
+#[derive(Debug, Error, Eq, PartialEq)]
+pub enum GenesisError {
+    #[error("Failed to read the genesis configuration: {0}")]
+    ConfigurationFailure(String),
+}
+
+/// The configuration of a Pyrsia network that every node of the network
+/// loads at startup, read from a JSON file. Nodes only connect to peers and
+/// accept blocks of their own network, so that independent networks can
+/// coexist. The node that initializes the network records the configuration
+/// in the genesis block of the blockchain.
+#[derive(Clone, Debug, Deserialize, Serialize, Eq, PartialEq)]
+pub struct GenesisConfig {
+    pub network_id: String,
+    /// A human-friendly name of the network.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub network_name: Option<String>,
+    /// The peer ids of the nodes that are authorized from the start, without
+    /// approvals of other authorized nodes.
+    #[serde(default)]
+    pub authorized_nodes: Vec<String>,
+}
+
+impl Default for GenesisConfig {
+    fn default() -> Self {
+        GenesisConfig {
+            network_id: DEFAULT_NETWORK_ID.to_owned(),
+            network_name: None,
+            authorized_nodes: vec![],
+        }
+    }
+}
+
+impl GenesisConfig {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, GenesisError> {
+        let content =
+            fs::read(path).map_err(|e| GenesisError::ConfigurationFailure(e.to_string()))?;
+        let genesis_config: GenesisConfig = serde_json::from_slice(&content)
+            .map_err(|e| GenesisError::ConfigurationFailure(e.to_string()))?;
+        genesis_config.validate()?;
+        Ok(genesis_config)
+    }
+
+    fn validate(&self) -> Result<(), GenesisError> {
+        if self.network_id.trim().is_empty()
+            || self
+                .network_id
+                .chars()
+                .any(|c| c.is_whitespace() || c == '/')
+        {
+            return Err(GenesisError::ConfigurationFailure(format!(
+                "invalid network id '{}'",
+                self.network_id
+            )));
+        }
+        if let Some(node) = self
+            .authorized_nodes
+            .iter()
+            .find(|node| PeerId::from_str(node).is_err())
+        {
+            return Err(GenesisError::ConfigurationFailure(format!(
+                "invalid authorized node '{}'",
+                node
+            )));
+        }
+        Ok(())
+    }
+
+    /// The peer ids of the authorized nodes of the genesis configuration.
+    pub fn authorized_node_ids(&self) -> Vec<PeerId> {
+        self.authorized_nodes
+            .iter()
+            .flat_map(|node| PeerId::from_str(node))
+            .collect()
+    }
+
+    /// The payload of the genesis block of the network. The default network
+    /// keeps the payload of the genesis blocks that predate genesis
+    /// configurations.
+    pub fn genesis_payload(&self) -> Vec<u8> {
+        if *self == GenesisConfig::default() {
+            return DEFAULT_GENESIS_PAYLOAD.as_bytes().to_vec();
+        }
+        serde_json::to_vec(self).expect("a genesis configuration is always serializable")
+    }
+
+    /// The id of the network that `genesis_block` belongs to.
+    pub fn network_id_of(genesis_block: &Block) -> String {
+        genesis_block
+            .fetch_payload()
+            .first()
+            .and_then(|payload| serde_json::from_slice::<GenesisConfig>(payload).ok())
+            .map_or_else(
+                || DEFAULT_NETWORK_ID.to_owned(),
+                |genesis_config| genesis_config.network_id,
+            )
+    }
+}
+
+#[cfg(test)]
+#[cfg(not(tarpaulin_include))]
+mod tests {
+    use super::*;
+    use crate::util::test_util;
+    use libp2p::identity;
+    use pyrsia_blockchain_network::blockchain::Blockchain;
+
+    #[test]
+    fn test_load() {
+        let tmp_dir = test_util::tests::setup();
+        let peer_id = PeerId::random();
+
+        let path = tmp_dir.join("genesis.json");
+        fs::write(
+            &path,
+            format!(
+                r#"{{"network_id": "acme", "network_name": "ACME", "authorized_nodes": ["{}"]}}"#,
+                peer_id
+            ),
+        )
+        .unwrap();
+        let genesis_config = GenesisConfig::load(&path).unwrap();
+        assert_eq!(genesis_config.network_id, "acme");
+        assert_eq!(genesis_config.authorized_node_ids(), vec![peer_id]);
+
+        fs::write(
+            &path,
+            r#"{"network_id": "acme", "authorized_nodes": ["node"]}"#,
+        )
+        .unwrap();
+        assert!(GenesisConfig::load(&path).is_err());
+
+        fs::write(&path, r#"{"network_id": "ac me"}"#).unwrap();
+        assert!(GenesisConfig::load(&path).is_err());
+
+        test_util::tests::teardown(tmp_dir);
+    }
+
+    #[tokio::test]
+    async fn test_network_id_of_genesis_block() {
+        let tmp_dir = test_util::tests::setup();
+        let keypair = identity::ed25519::Keypair::generate();
+        fs::create_dir_all(tmp_dir.join("default")).unwrap();
+        fs::create_dir_all(tmp_dir.join("acme")).unwrap();
+
+        let default_blockchain = Blockchain::new(&keypair, tmp_dir.join("default"))
+            .await
+            .unwrap();
+        assert_eq!(
+            GenesisConfig::network_id_of(&default_blockchain.last_block().unwrap()),
+            DEFAULT_NETWORK_ID
+        );
+        assert_eq!(
+            GenesisConfig::default().genesis_payload(),
+            DEFAULT_GENESIS_PAYLOAD.as_bytes()
+        );
+
+        let genesis_config = GenesisConfig {
+            network_id: "acme".to_owned(),
+            ..Default::default()
+        };
+        let blockchain = Blockchain::new_with_genesis_payload(
+            &keypair,
+            tmp_dir.join("acme"),
+            genesis_config.genesis_payload(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            GenesisConfig::network_id_of(&blockchain.last_block().unwrap()),
+            "acme"
+        );
+
+        test_util::tests::teardown(tmp_dir);
+    }
+}
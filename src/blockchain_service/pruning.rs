@@ -0,0 +1,74 @@
+/*
+   Copyright 2021 JFrog Ltd
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+*/
+
+use pyrsia_blockchain_network::structures::header::Ordinal;
+use std::path::{Path, PathBuf};
+
+/// Which blocks of the local blockchain are pruned once their transparency
+/// logs are applied, see
+/// [`BlockchainService::with_pruning`](super::service::BlockchainService::with_pruning).
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct BlockPruning {
+    keep_blocks: u64,
+    archive_path: Option<PathBuf>,
+}
+
+impl BlockPruning {
+    /// Keep only the `keep_blocks` most recent blocks, and at least the last
+    /// block.
+    pub fn new(keep_blocks: u64) -> Self {
+        BlockPruning {
+            keep_blocks: keep_blocks.max(1),
+            archive_path: None,
+        }
+    }
+
+    /// Export the pruned blocks to `archive_path` before they are removed,
+    /// e.g. a directory on cold storage.
+    pub fn with_archive_path<P: AsRef<Path>>(mut self, archive_path: P) -> Self {
+        self.archive_path = Some(archive_path.as_ref().to_path_buf());
+        self
+    }
+
+    pub fn archive_path(&self) -> Option<&Path> {
+        self.archive_path.as_deref()
+    }
+
+    /// The ordinal of the oldest block that is kept when `last_ordinal` is
+    /// the ordinal of the last block.
+    pub fn keep_from(&self, last_ordinal: Ordinal) -> Ordinal {
+        (last_ordinal + 1).saturating_sub(Ordinal::from(self.keep_blocks))
+    }
+}
+
+#[cfg(test)]
+#[cfg(not(tarpaulin_include))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keep_from() {
+        let pruning = BlockPruning::new(10);
+        assert_eq!(pruning.keep_from(5), 0);
+        assert_eq!(pruning.keep_from(9), 0);
+        assert_eq!(pruning.keep_from(10), 1);
+        assert_eq!(pruning.keep_from(100), 91);
+
+        let pruning = BlockPruning::new(0).with_archive_path("/mnt/archive");
+        assert_eq!(pruning.keep_from(100), 100);
+        assert_eq!(pruning.archive_path(), Some(Path::new("/mnt/archive")));
+    }
+}
@@ -16,15 +16,18 @@
 
 use bincode::{deserialize, serialize};
 use libp2p::{identity, PeerId};
-use log::warn;
 use pyrsia_blockchain_network::blockchain::Blockchain;
 use pyrsia_blockchain_network::error::BlockchainError;
 use pyrsia_blockchain_network::structures::block::Block;
-use pyrsia_blockchain_network::structures::header::Ordinal;
-use std::cmp::Ordering;
+use pyrsia_blockchain_network::structures::header::{Address, Ordinal};
 use std::fmt::{self, Debug, Formatter};
 use std::path::Path;
 
+use super::audit::{audit_chain, ChainAudit};
+use super::consensus::{Consensus, SequentialConsensus};
+use super::fork::{remote_branch_wins, verify_branch, ForkResolution};
+use super::genesis::GenesisConfig;
+use super::pruning::BlockPruning;
 use crate::network::client::Client;
 
 /// Blockchain command length is 1 byte
@@ -61,6 +64,9 @@ impl TryFrom<u8> for BlockchainCommand {
 
 pub struct BlockchainService {
     blockchain: Blockchain,
+    network_id: String,
+    consensus: Box<dyn Consensus>,
+    pruning: Option<BlockPruning>,
     pub keypair: identity::ed25519::Keypair,
     pub p2p_client: Client,
 }
@@ -78,23 +84,46 @@ unsafe impl Send for BlockchainService {}
 unsafe impl Sync for BlockchainService {}
 
 impl BlockchainService {
+    /// Initialize the blockchain of a new network, with the configuration of
+    /// the network in its genesis block. Fails when the local blockchain
+    /// belongs to another network.
     pub async fn init_first_blockchain_node(
         local_keypair: &identity::ed25519::Keypair,
         blockchain_keypair: &identity::ed25519::Keypair,
+        genesis_config: &GenesisConfig,
         p2p_client: Client,
         blockchain_path: impl AsRef<Path>,
     ) -> Result<Self, BlockchainError> {
         std::fs::create_dir_all(&blockchain_path)?;
 
+        let blockchain = Blockchain::new_with_genesis_payload(
+            blockchain_keypair,
+            blockchain_path,
+            genesis_config.genesis_payload(),
+        )
+        .await?;
+        if let Some(genesis_block) = blockchain.block(0) {
+            let network_id = GenesisConfig::network_id_of(&genesis_block);
+            if network_id != genesis_config.network_id {
+                return Err(BlockchainError::InvalidNetwork(network_id));
+            }
+        }
+
         Ok(Self {
-            blockchain: Blockchain::new(blockchain_keypair, blockchain_path).await?,
+            blockchain,
+            network_id: genesis_config.network_id.clone(),
+            consensus: Box::new(SequentialConsensus),
+            pruning: None,
             keypair: local_keypair.to_owned(),
             p2p_client,
         })
     }
 
+    /// Join the network of `genesis_config`, of which the blocks are
+    /// pulled from other nodes.
     pub fn init_other_blockchain_node(
         local_keypair: &identity::ed25519::Keypair,
+        genesis_config: &GenesisConfig,
         p2p_client: Client,
         blockchain_path: impl AsRef<Path>,
     ) -> Result<Self, BlockchainError> {
@@ -102,20 +131,72 @@ impl BlockchainService {
 
         Ok(Self {
             blockchain: Blockchain::empty_new(blockchain_path),
+            network_id: genesis_config.network_id.clone(),
+            consensus: Box::new(SequentialConsensus),
+            pruning: None,
             keypair: local_keypair.to_owned(),
             p2p_client,
         })
     }
 
+    /// Replace the default [`SequentialConsensus`] that decides which
+    /// blocks are committed.
+    pub fn with_consensus<C: Consensus + 'static>(mut self, consensus: C) -> Self {
+        self.consensus = Box::new(consensus);
+        self
+    }
+
+    /// Prune the old blocks of the local blockchain, which are kept forever
+    /// by default.
+    pub fn with_pruning(mut self, pruning: BlockPruning) -> Self {
+        self.pruning = Some(pruning);
+        self
+    }
+
+    /// Prune the blocks before the most recent blocks that are kept, when
+    /// pruning is enabled. Only called once the transparency logs of all
+    /// blocks of the local blockchain are applied.
+    pub async fn prune_blocks(&mut self) -> Result<(), BlockchainError> {
+        let (Some(pruning), Some(last_block)) = (&self.pruning, self.blockchain.last_block())
+        else {
+            return Ok(());
+        };
+
+        if let Some(pruned_ordinals) = self
+            .blockchain
+            .prune_blocks(
+                pruning.keep_from(last_block.header.ordinal),
+                pruning.archive_path(),
+            )
+            .await?
+        {
+            log::info!(
+                "Pruned blocks {} to {} of the local blockchain",
+                pruned_ordinals.start(),
+                pruned_ordinals.end()
+            );
+        }
+        Ok(())
+    }
+
     /// Add payload to blockchain. It will be called by other services (e.g. transparent logging service)
-    pub async fn add_payload(&mut self, payload: Vec<u8>) -> Result<(), BlockchainError> {
-        self.blockchain
-            .add_block(payload, &identity::Keypair::Ed25519(self.keypair.clone()))
+    /// Returns the block that was committed with the payload.
+    #[cfg(feature = "blockchain-producer")]
+    pub async fn add_payload(&mut self, payload: Vec<u8>) -> Result<Block, BlockchainError> {
+        let block = self
+            .consensus
+            .propose(&mut self.blockchain, payload, &self.keypair)
             .await?;
 
-        self.broadcast_blockchain(Box::new(self.blockchain.last_block().unwrap()))
-            .await?;
-        Ok(())
+        self.broadcast_blockchain(Box::new(block.clone())).await?;
+        Ok(block)
+    }
+
+    /// A node built without the `blockchain-producer` feature only follows
+    /// the blockchain of the other nodes and never adds a block itself.
+    #[cfg(not(feature = "blockchain-producer"))]
+    pub async fn add_payload(&mut self, _payload: Vec<u8>) -> Result<Block, BlockchainError> {
+        Err(BlockchainError::BlockProductionDisabled)
     }
 
     /// Notify other nodes to add a new block.
@@ -202,34 +283,169 @@ impl BlockchainService {
         Ok(blocks)
     }
 
-    /// Add a new block to local blockchain.
+    /// Add a new block to local blockchain, when the consensus accepts it.
+    /// A genesis block of another network is rejected. A block that
+    /// conflicts with the local blockchain is a fork, which is resolved with
+    /// [`remote_branch_wins`]. The branch of the block can only win when all
+    /// its blocks are committed by one of `authorized_nodes`, and when it
+    /// descends from the genesis block of this network.
+    /// Returns the blocks that were replaced when the branch of the block won.
     pub async fn add_block(
         &mut self,
         ordinal: Ordinal,
         block: Box<Block>,
-    ) -> Result<(), BlockchainError> {
-        let last_block = self.blockchain.last_block();
-
-        match last_block {
-            None => {
-                if ordinal == 0 {
-                    self.blockchain.update_block_from_peers(block).await
-                } else {
-                    Ok(())
+        authorized_nodes: &[PeerId],
+    ) -> Result<Option<ForkResolution>, BlockchainError> {
+        if ordinal == 0 {
+            let network_id = GenesisConfig::network_id_of(&block);
+            if network_id != self.network_id {
+                return Err(BlockchainError::InvalidNetwork(network_id));
+            }
+        }
+        if self.is_fork(ordinal, &block) {
+            return self.resolve_fork(ordinal, *block, authorized_nodes).await;
+        }
+
+        self.consensus
+            .receive(&mut self.blockchain, ordinal, block)
+            .await?;
+        Ok(None)
+    }
+
+    /// Whether `block` is part of the local blockchain.
+    pub fn contains_block(&self, block: &Block) -> bool {
+        self.blockchain
+            .block(block.header.ordinal)
+            .map_or(false, |local_block| local_block == *block)
+    }
+
+    /// Whether `block` was committed by this node.
+    pub fn is_local_block(&self, block: &Block) -> bool {
+        block.header.committer == Address::from(identity::PublicKey::Ed25519(self.keypair.public()))
+    }
+
+    // A block conflicts with the local blockchain when the local block with
+    // its ordinal is a different block, or when it follows the last local
+    // block but not as its child. Blocks that were pruned locally are never
+    // a fork.
+    fn is_fork(&self, ordinal: Ordinal, block: &Block) -> bool {
+        let Some(last_block) = self.blockchain.last_block() else {
+            return false;
+        };
+        if ordinal == last_block.header.ordinal + 1 {
+            return block.header.parent_hash != last_block.header.hash();
+        }
+        self.blockchain.block(ordinal).map_or(false, |local_block| {
+            local_block.header.hash() != block.header.hash()
+        })
+    }
+
+    async fn resolve_fork(
+        &mut self,
+        ordinal: Ordinal,
+        block: Block,
+        authorized_nodes: &[PeerId],
+    ) -> Result<Option<ForkResolution>, BlockchainError> {
+        let remote_branch = self.remote_branch(ordinal, block).await?;
+        let fork_ordinal = remote_branch
+            .first()
+            .map(|block| block.header.ordinal)
+            .ok_or(BlockchainError::UnresolvableFork(ordinal))?;
+        let last_ordinal = self
+            .blockchain
+            .last_block()
+            .map_or(fork_ordinal, |block| block.header.ordinal);
+        let local_branch = self.blockchain.pull_blocks(fork_ordinal, last_ordinal)?;
+
+        if !remote_branch_wins(&local_branch, &remote_branch) {
+            log::warn!(
+                "Blockchain forked at block {}, the local branch wins",
+                fork_ordinal
+            );
+            // The other node only learns about the fork from a block of the
+            // winning branch.
+            if let Some(last_block) = local_branch.last() {
+                if let Err(e) = self
+                    .broadcast_blockchain(Box::new(last_block.clone()))
+                    .await
+                {
+                    log::warn!("Failed to broadcast the winning branch of a fork: {:?}", e);
                 }
             }
+            return Ok(None);
+        }
 
-            Some(last_block) => {
-                let expected = last_block.header.ordinal + 1;
-                match ordinal.cmp(&expected) {
-                    Ordering::Greater => Err(BlockchainError::LaggingBlockchainData),
-                    Ordering::Less => {
-                        warn!("Blockchain received a duplicate block!");
-                        Ok(())
-                    }
-                    Ordering::Equal => self.blockchain.update_block_from_peers(block).await,
+        verify_branch(&remote_branch, authorized_nodes)?;
+        log::warn!(
+            "Blockchain forked at block {}, rolling back {} local blocks",
+            fork_ordinal,
+            local_branch.len()
+        );
+        let rolled_back = self
+            .blockchain
+            .replace_blocks(remote_branch.clone())
+            .await?;
+        Ok(Some(ForkResolution {
+            fork_ordinal,
+            rolled_back,
+            applied: remote_branch,
+        }))
+    }
+
+    // The blocks of the other branch of a fork, starting after the last block
+    // that both branches have in common. A block that follows the local block
+    // before it is a branch on its own, otherwise the rest of the branch is
+    // pulled from the node that committed the block. A pulled chain without a
+    // block in common with the local blockchain is rejected: it is the chain
+    // of another network when it already differs in the genesis block, and it
+    // can not be verified when it differs in the first block that was not
+    // pruned locally.
+    async fn remote_branch(
+        &mut self,
+        ordinal: Ordinal,
+        block: Block,
+    ) -> Result<Vec<Block>, BlockchainError> {
+        let parent = ordinal
+            .checked_sub(1)
+            .and_then(|parent_ordinal| self.blockchain.block(parent_ordinal));
+        if parent.map_or(false, |parent| {
+            parent.header.hash() == block.header.parent_hash
+        }) {
+            return Ok(vec![block]);
+        }
+
+        let committer = block
+            .header
+            .committer
+            .peer_id()
+            .ok_or(BlockchainError::UnresolvableFork(ordinal))?;
+        let first_ordinal = self
+            .blockchain
+            .first_ordinal()
+            .ok_or(BlockchainError::EmptyBlockchain)?;
+        let remote_ordinal = self.query_blockchain_ordinal(&committer).await?;
+        let mut remote_blocks = self
+            .pull_block_from_other_nodes(&committer, first_ordinal, remote_ordinal)
+            .await?
+            .into_iter()
+            .peekable();
+        while let Some(remote_block) = remote_blocks.peek() {
+            match self.blockchain.block(remote_block.header.ordinal) {
+                Some(local_block) if local_block.header.hash() == remote_block.header.hash() => {
+                    remote_blocks.next();
                 }
+                _ => break,
+            }
+        }
+        let remote_blocks: Vec<Block> = remote_blocks.collect();
+        match remote_blocks.first() {
+            Some(remote_block) if remote_block.header.ordinal == 0 => Err(
+                BlockchainError::InvalidNetwork(GenesisConfig::network_id_of(remote_block)),
+            ),
+            Some(remote_block) if remote_block.header.ordinal == first_ordinal => {
+                Err(BlockchainError::UnresolvableFork(ordinal))
             }
+            _ => Ok(remote_blocks),
         }
     }
 
@@ -242,13 +458,52 @@ impl BlockchainService {
         self.blockchain.pull_blocks(start, end)
     }
 
+    /// Export all blocks of the local blockchain, see
+    /// [`Blockchain::export_blocks`].
+    pub fn export_blocks(&self) -> Result<Vec<u8>, BlockchainError> {
+        self.blockchain.export_blocks()
+    }
+
+    /// Audit all blocks of the local blockchain, see [`audit_chain`].
+    pub fn audit_blocks(&self) -> Result<ChainAudit, BlockchainError> {
+        let (Some(first_ordinal), Some(last_block)) = (
+            self.blockchain.first_ordinal(),
+            self.blockchain.last_block(),
+        ) else {
+            return Ok(ChainAudit::default());
+        };
+        let blocks = self
+            .blockchain
+            .pull_blocks(first_ordinal, last_block.header.ordinal)?;
+        Ok(audit_chain(&blocks))
+    }
+
+    /// Verify an export of the blockchain of this network and add its
+    /// blocks that follow the last local block, e.g. to restore a backup or
+    /// to seed a node that can not reach the other nodes. Returns the added
+    /// blocks, oldest first.
+    pub async fn import_blocks(&mut self, export: &[u8]) -> Result<Vec<Block>, BlockchainError> {
+        let blocks = Blockchain::read_export(export)?;
+        if let Some(genesis_block) = blocks.first() {
+            let network_id = GenesisConfig::network_id_of(genesis_block);
+            if network_id != self.network_id {
+                return Err(BlockchainError::InvalidNetwork(network_id));
+            }
+        }
+
+        self.blockchain.import_blocks(blocks).await
+    }
+
     pub async fn query_last_block(&self) -> Option<Block> {
         self.blockchain.last_block()
     }
 
+    /// Pull the blocks of another node and add them to the local blockchain,
+    /// see [`add_block`](Self::add_block).
     pub async fn init_pull_from_others(
         &mut self,
         other_peer_id: &PeerId,
+        authorized_nodes: &[PeerId],
     ) -> Result<Ordinal, BlockchainError> {
         // Always start with the genesis block
         let ordinal = self.query_blockchain_ordinal(other_peer_id).await?;
@@ -260,11 +515,30 @@ impl BlockchainService {
         {
             let ordinal = block.header.ordinal;
             let block = block.clone();
-            self.add_block(ordinal, Box::new(block)).await?;
+            self.add_block(ordinal, Box::new(block), authorized_nodes)
+                .await?;
         }
 
         Ok(ordinal)
     }
+
+    /// Pull the blocks of another node from `start` up to its last block,
+    /// without adding them to the local blockchain, e.g. the blocks after a
+    /// transparency log snapshot that a new node imported instead of the
+    /// blocks before it.
+    pub async fn pull_blocks_since_from_others(
+        &mut self,
+        other_peer_id: &PeerId,
+        start: Ordinal,
+    ) -> Result<Vec<Block>, BlockchainError> {
+        let ordinal = self.query_blockchain_ordinal(other_peer_id).await?;
+        if ordinal < start {
+            return Ok(vec![]);
+        }
+
+        self.pull_block_from_other_nodes(other_peer_id, start, ordinal)
+            .await
+    }
 }
 
 #[cfg(test)]
@@ -294,6 +568,7 @@ mod tests {
             BlockchainService::init_first_blockchain_node(
                 &ed25519_keypair,
                 &ed25519_keypair,
+                &GenesisConfig::default(),
                 client,
                 tmp_dir,
             )
@@ -313,10 +588,16 @@ mod tests {
             IdentTopic::new("pyrsia-blockchain-topic"),
         );
 
-        BlockchainService::init_other_blockchain_node(&ed25519_keypair, client, tmp_dir)
-            .expect("BlockchainService should be created.")
+        BlockchainService::init_other_blockchain_node(
+            &ed25519_keypair,
+            &GenesisConfig::default(),
+            client,
+            tmp_dir,
+        )
+        .expect("BlockchainService should be created.")
     }
 
+    #[cfg(feature = "blockchain-producer")]
     #[tokio::test(flavor = "multi_thread")]
     async fn test_add_payload() {
         let tmp_dir = test_util::tests::setup();
@@ -342,6 +623,23 @@ mod tests {
         test_util::tests::teardown(tmp_dir);
     }
 
+    #[cfg(not(feature = "blockchain-producer"))]
+    #[tokio::test]
+    async fn test_add_payload_without_block_production() {
+        let tmp_dir = test_util::tests::setup();
+
+        let (mut blockchain_service, _command_receiver) = create_blockchain_service(&tmp_dir).await;
+
+        let last_block = blockchain_service.blockchain.last_block();
+        assert!(matches!(
+            blockchain_service.add_payload(vec![]).await,
+            Err(BlockchainError::BlockProductionDisabled)
+        ));
+        assert_eq!(blockchain_service.blockchain.last_block(), last_block);
+
+        test_util::tests::teardown(tmp_dir);
+    }
+
     #[tokio::test(flavor = "multi_thread")]
     async fn test_add_block() {
         let tmp_dir = test_util::tests::setup();
@@ -357,7 +655,7 @@ mod tests {
             &blockchain_service.keypair,
         );
         blockchain_service
-            .add_block(1, Box::new(block.clone()))
+            .add_block(1, Box::new(block.clone()), &[])
             .await
             .expect("Block should have been added.");
 
@@ -366,13 +664,155 @@ mod tests {
 
         // Ordinal is not next, return error.
         assert!(blockchain_service
-            .add_block(3, Box::new(block.clone()))
+            .add_block(3, Box::new(block.clone()), &[])
             .await
             .is_err());
 
         test_util::tests::teardown(tmp_dir);
     }
 
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_add_block_resolves_fork() {
+        let tmp_dir = test_util::tests::setup();
+
+        let mut blockchain_service = create_blockchain_service(&tmp_dir).await.0;
+        let genesis_block = blockchain_service.blockchain.last_block().unwrap();
+
+        let local_block = Block::new(
+            genesis_block.header.hash(),
+            1,
+            vec![],
+            &blockchain_service.keypair,
+        );
+        blockchain_service
+            .add_block(1, Box::new(local_block.clone()), &[])
+            .await
+            .expect("Block should have been added.");
+        assert!(blockchain_service.is_local_block(&local_block));
+
+        // A block of another node with the same ordinal and parent forks the
+        // blockchain, the block with the lowest hash wins.
+        let remote_keypair = identity::ed25519::Keypair::generate();
+        let remote_node = identity::PublicKey::Ed25519(remote_keypair.public()).to_peer_id();
+        let remote_block = Block::new(genesis_block.header.hash(), 1, vec![], &remote_keypair);
+        assert!(!blockchain_service.is_local_block(&remote_block));
+        let remote_wins =
+            remote_block.header.hash().to_slice() < local_block.header.hash().to_slice();
+        if remote_wins {
+            // a winning branch of a node that is not authorized is rejected
+            assert!(matches!(
+                blockchain_service
+                    .add_block(1, Box::new(remote_block.clone()), &[])
+                    .await,
+                Err(BlockchainError::UnauthorizedCommitter(1))
+            ));
+            assert!(blockchain_service.contains_block(&local_block));
+        }
+        let fork_resolution = blockchain_service
+            .add_block(1, Box::new(remote_block.clone()), &[remote_node])
+            .await
+            .expect("Fork should have been resolved.");
+        let (winning_block, losing_block) = if remote_wins {
+            assert_eq!(
+                fork_resolution,
+                Some(ForkResolution {
+                    fork_ordinal: 1,
+                    rolled_back: vec![local_block.clone()],
+                    applied: vec![remote_block.clone()],
+                })
+            );
+            (remote_block, local_block)
+        } else {
+            assert_eq!(fork_resolution, None);
+            (local_block, remote_block)
+        };
+        assert!(blockchain_service.contains_block(&winning_block));
+        assert!(!blockchain_service.contains_block(&losing_block));
+
+        // The longer local branch wins over a shorter branch.
+        let next_block = Block::new(
+            winning_block.header.hash(),
+            2,
+            vec![],
+            &blockchain_service.keypair,
+        );
+        blockchain_service
+            .add_block(2, Box::new(next_block.clone()), &[])
+            .await
+            .expect("Block should have been added.");
+        assert_eq!(
+            blockchain_service
+                .add_block(1, Box::new(losing_block), &[])
+                .await
+                .expect("Fork should have been resolved."),
+            None
+        );
+        assert_eq!(blockchain_service.blockchain.last_block(), Some(next_block));
+
+        // A duplicate block is no fork.
+        assert_eq!(
+            blockchain_service
+                .add_block(1, Box::new(winning_block), &[])
+                .await
+                .expect("Duplicate block should have been ignored."),
+            None
+        );
+
+        test_util::tests::teardown(tmp_dir);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_add_block_with_consensus() {
+        struct RejectingConsensus;
+
+        #[async_trait::async_trait]
+        impl Consensus for RejectingConsensus {
+            async fn propose(
+                &mut self,
+                _blockchain: &mut Blockchain,
+                _payload: Vec<u8>,
+                _keypair: &identity::ed25519::Keypair,
+            ) -> Result<Block, BlockchainError> {
+                Err(BlockchainError::InvalidBlockchainArgument)
+            }
+
+            async fn receive(
+                &mut self,
+                _blockchain: &mut Blockchain,
+                ordinal: Ordinal,
+                _block: Box<Block>,
+            ) -> Result<(), BlockchainError> {
+                Err(BlockchainError::InvalidBlockchainOrdinal(ordinal))
+            }
+        }
+
+        let tmp_dir = test_util::tests::setup();
+
+        let mut blockchain_service = create_blockchain_service(&tmp_dir)
+            .await
+            .0
+            .with_consensus(RejectingConsensus);
+
+        let last_block = blockchain_service.blockchain.last_block().unwrap();
+        let block = Block::new(
+            last_block.header.hash(),
+            1,
+            vec![],
+            &blockchain_service.keypair,
+        );
+        assert!(matches!(
+            blockchain_service.add_block(1, Box::new(block), &[]).await,
+            Err(BlockchainError::InvalidBlockchainOrdinal(1))
+        ));
+        assert!(blockchain_service.add_payload(vec![]).await.is_err());
+        assert_eq!(
+            blockchain_service.blockchain.last_block().unwrap(),
+            last_block
+        );
+
+        test_util::tests::teardown(tmp_dir);
+    }
+
     #[tokio::test(flavor = "multi_thread")]
     async fn test_init_first_blockchain_node() {
         let tmp_dir = test_util::tests::setup();
@@ -395,6 +835,214 @@ mod tests {
         test_util::tests::teardown(tmp_dir);
     }
 
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_add_genesis_block_of_other_network() {
+        let tmp_dir = test_util::tests::setup();
+
+        let mut blockchain_service = create_other_blockchain_service(&tmp_dir);
+        let keypair = identity::ed25519::Keypair::generate();
+        std::fs::create_dir_all(tmp_dir.join("other")).unwrap();
+        let other_network = Blockchain::new_with_genesis_payload(
+            &keypair,
+            tmp_dir.join("other"),
+            GenesisConfig {
+                network_id: "other".to_owned(),
+                ..Default::default()
+            }
+            .genesis_payload(),
+        )
+        .await
+        .unwrap();
+
+        assert!(matches!(
+            blockchain_service
+                .add_block(0, Box::new(other_network.last_block().unwrap()), &[])
+                .await,
+            Err(BlockchainError::InvalidNetwork(network_id)) if network_id == "other"
+        ));
+        assert_eq!(None, blockchain_service.query_last_block().await);
+
+        test_util::tests::teardown(tmp_dir);
+    }
+
+    // Answer the blockchain requests of the service as the node that holds
+    // `remote_blocks`.
+    fn respond_with_remote_blocks(
+        mut command_receiver: mpsc::Receiver<Command>,
+        remote_blocks: Vec<Block>,
+    ) {
+        tokio::spawn(async move {
+            while let Some(command) = command_receiver.recv().await {
+                let Command::RequestBlockchain { data, sender, .. } = command else {
+                    panic!("Command must match Command::RequestBlockchain");
+                };
+                let response = match BlockchainCommand::try_from(data[0]).unwrap() {
+                    BlockchainCommand::QueryHighestBlockOrdinal => {
+                        serialize(&remote_blocks.last().unwrap().header.ordinal).unwrap()
+                    }
+                    BlockchainCommand::PullFromPeer => {
+                        let start: Ordinal = deserialize(&data[1..]).unwrap();
+                        let blocks: Vec<Block> = remote_blocks
+                            .iter()
+                            .filter(|block| block.header.ordinal >= start)
+                            .cloned()
+                            .collect();
+                        serialize(&blocks).unwrap()
+                    }
+                    command => panic!("Unexpected blockchain command {:?}", command),
+                };
+                let _ = sender.send(Ok(response));
+            }
+        });
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_add_block_of_chain_of_other_network() {
+        let tmp_dir = test_util::tests::setup();
+
+        let (mut blockchain_service, command_receiver) = create_blockchain_service(&tmp_dir).await;
+        let local_genesis_block = blockchain_service.blockchain.last_block().unwrap();
+
+        let remote_keypair = identity::ed25519::Keypair::generate();
+        std::fs::create_dir_all(tmp_dir.join("other")).unwrap();
+        let other_network = Blockchain::new_with_genesis_payload(
+            &remote_keypair,
+            tmp_dir.join("other"),
+            GenesisConfig {
+                network_id: "other".to_owned(),
+                ..Default::default()
+            }
+            .genesis_payload(),
+        )
+        .await
+        .unwrap();
+        let remote_genesis_block = other_network.last_block().unwrap();
+        let remote_block = Block::new(
+            remote_genesis_block.header.hash(),
+            1,
+            vec![],
+            &remote_keypair,
+        );
+        respond_with_remote_blocks(
+            command_receiver,
+            vec![remote_genesis_block, remote_block.clone()],
+        );
+
+        let remote_node = identity::PublicKey::Ed25519(remote_keypair.public()).to_peer_id();
+        assert!(matches!(
+            blockchain_service
+                .add_block(1, Box::new(remote_block), &[remote_node])
+                .await,
+            Err(BlockchainError::InvalidNetwork(network_id)) if network_id == "other"
+        ));
+        assert_eq!(
+            blockchain_service.blockchain.last_block(),
+            Some(local_genesis_block)
+        );
+
+        test_util::tests::teardown(tmp_dir);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_add_block_of_chain_that_forked_before_pruned_blocks() {
+        let tmp_dir = test_util::tests::setup();
+
+        let (blockchain_service, command_receiver) = create_blockchain_service(&tmp_dir).await;
+        let mut blockchain_service = blockchain_service
+            .with_pruning(BlockPruning::new(2).with_archive_path(tmp_dir.join("archive")));
+
+        for ordinal in 1..=3 {
+            let last_block = blockchain_service.blockchain.last_block().unwrap();
+            let block = Block::new(
+                last_block.header.hash(),
+                ordinal,
+                vec![],
+                &blockchain_service.keypair,
+            );
+            blockchain_service
+                .add_block(ordinal, Box::new(block), &[])
+                .await
+                .expect("Block should have been added.");
+        }
+        let first_block = blockchain_service.blockchain.block(1).unwrap();
+        blockchain_service
+            .prune_blocks()
+            .await
+            .expect("Blocks should have been pruned.");
+        let last_block = blockchain_service.blockchain.last_block().unwrap();
+
+        // The remote chain forked at block 2, the first block that was not
+        // pruned, so the local blockchain has no block in common with it.
+        let remote_keypair = identity::ed25519::Keypair::generate();
+        let remote_parent_block = Block::new(first_block.header.hash(), 2, vec![], &remote_keypair);
+        let remote_block = Block::new(
+            remote_parent_block.header.hash(),
+            3,
+            vec![],
+            &remote_keypair,
+        );
+        respond_with_remote_blocks(
+            command_receiver,
+            vec![remote_parent_block, remote_block.clone()],
+        );
+
+        let remote_node = identity::PublicKey::Ed25519(remote_keypair.public()).to_peer_id();
+        assert!(matches!(
+            blockchain_service
+                .add_block(3, Box::new(remote_block), &[remote_node])
+                .await,
+            Err(BlockchainError::UnresolvableFork(3))
+        ));
+        assert_eq!(blockchain_service.blockchain.last_block(), Some(last_block));
+
+        test_util::tests::teardown(tmp_dir);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_export_and_import_blocks() {
+        let tmp_dir = test_util::tests::setup();
+
+        let blockchain_service = create_blockchain_service(&tmp_dir).await.0;
+        let export = blockchain_service.export_blocks().unwrap();
+
+        let mut other_blockchain_service = create_other_blockchain_service(tmp_dir.join("other"));
+        assert_eq!(
+            1,
+            other_blockchain_service
+                .import_blocks(&export)
+                .await
+                .unwrap()
+                .len()
+        );
+        assert_eq!(
+            blockchain_service.query_last_block().await,
+            other_blockchain_service.query_last_block().await
+        );
+
+        let keypair = identity::ed25519::Keypair::generate();
+        std::fs::create_dir_all(tmp_dir.join("acme")).unwrap();
+        let other_network = Blockchain::new_with_genesis_payload(
+            &keypair,
+            tmp_dir.join("acme"),
+            GenesisConfig {
+                network_id: "acme".to_owned(),
+                ..Default::default()
+            }
+            .genesis_payload(),
+        )
+        .await
+        .unwrap();
+        let mut new_blockchain_service = create_other_blockchain_service(tmp_dir.join("new"));
+        assert!(matches!(
+            new_blockchain_service
+                .import_blocks(&other_network.export_blocks().unwrap())
+                .await,
+            Err(BlockchainError::InvalidNetwork(network_id)) if network_id == "acme"
+        ));
+
+        test_util::tests::teardown(tmp_dir);
+    }
+
     #[tokio::test(flavor = "multi_thread")]
     async fn test_pull_blocks() {
         let tmp_dir = test_util::tests::setup();
@@ -405,6 +1053,52 @@ mod tests {
         test_util::tests::teardown(tmp_dir);
     }
 
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_prune_blocks() {
+        let tmp_dir = test_util::tests::setup();
+
+        let mut blockchain_service = create_blockchain_service(&tmp_dir)
+            .await
+            .0
+            .with_pruning(BlockPruning::new(2).with_archive_path(tmp_dir.join("archive")));
+
+        for ordinal in 1..=3 {
+            let last_block = blockchain_service.blockchain.last_block().unwrap();
+            let block = Block::new(
+                last_block.header.hash(),
+                ordinal,
+                vec![],
+                &blockchain_service.keypair,
+            );
+            blockchain_service
+                .add_block(ordinal, Box::new(block), &[])
+                .await
+                .expect("Block should have been added.");
+        }
+        blockchain_service
+            .prune_blocks()
+            .await
+            .expect("Blocks should have been pruned.");
+
+        let blocks = blockchain_service.pull_blocks(0, 3).await.unwrap();
+        assert_eq!(
+            vec![2, 3],
+            blocks
+                .iter()
+                .map(|block| block.header.ordinal)
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            2,
+            Blockchain::read_archive(tmp_dir.join("archive").join("0-1.ser"))
+                .await
+                .unwrap()
+                .len()
+        );
+
+        test_util::tests::teardown(tmp_dir);
+    }
+
     #[tokio::test(flavor = "multi_thread")]
     async fn test_query_last_block() {
         let tmp_dir = test_util::tests::setup();
@@ -420,7 +1114,7 @@ mod tests {
             &blockchain_service.keypair,
         );
         let _ = blockchain_service
-            .add_block(1, Box::new(block.clone()))
+            .add_block(1, Box::new(block.clone()), &[])
             .await;
 
         assert_eq!(
@@ -461,7 +1155,7 @@ mod tests {
         let other_peer_id = Keypair::generate_ed25519().public().to_peer_id();
 
         assert!(blockchain_service
-            .init_pull_from_others(&other_peer_id)
+            .init_pull_from_others(&other_peer_id, &[])
             .await
             .is_err());
 
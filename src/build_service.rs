@@ -14,9 +14,22 @@
    limitations under the License.
 */
 
+#[cfg(feature = "build-service")]
+pub mod dead_letter;
 pub mod error;
 pub mod event;
+#[cfg(feature = "build-service")]
+pub mod event_loop;
+pub mod history;
 pub mod mapping;
 pub mod model;
+pub mod pinning;
+#[cfg(feature = "build-service")]
 pub mod pipeline;
+#[cfg(feature = "build-service")]
 pub mod service;
+#[cfg(feature = "build-service")]
+pub mod version_watcher;
+
+pub use pyrsia_build_service::queue;
+pub use pyrsia_build_service::secrets;
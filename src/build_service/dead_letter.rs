@@ -0,0 +1,157 @@
+/*
+   Copyright 2021 JFrog Ltd
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+*/
+
+use super::model::BuildResult;
+use log::debug;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+const BUILD_RESULT_FILENAME: &str = "build_result.json";
+
+/// The dead letter store keeps the results of successful builds for which
+/// the artifacts could not be published in the transparency log, so that
+/// publication can be retried later without rebuilding the package.
+///
+/// Each failed build is stored in its own directory, containing the built
+/// artifacts and the serialized [`BuildResult`] that refers to them.
+#[derive(Clone)]
+pub struct DeadLetterStore {
+    path: PathBuf,
+}
+
+impl DeadLetterStore {
+    pub fn new<P: AsRef<Path>>(path: P) -> Self {
+        DeadLetterStore {
+            path: path.as_ref().to_path_buf(),
+        }
+    }
+
+    /// Move the artifacts in `build_path` into the dead letter store and save
+    /// the build result, with its artifact locations pointing to the moved files.
+    pub fn store(
+        &self,
+        build_id: &str,
+        build_path: &Path,
+        build_result: &BuildResult,
+    ) -> io::Result<()> {
+        let failed_build_path = self.path.join(build_id);
+        fs::create_dir_all(&self.path)?;
+        fs::rename(build_path, &failed_build_path)?;
+
+        let mut stored_build_result = build_result.clone();
+        for artifact in stored_build_result.artifacts.iter_mut() {
+            if let Some(file_name) = artifact.artifact_location.file_name() {
+                artifact.artifact_location = failed_build_path.join(file_name);
+            }
+        }
+
+        let json = serde_json::to_vec(&stored_build_result)?;
+        fs::write(failed_build_path.join(BUILD_RESULT_FILENAME), json)?;
+
+        debug!(
+            "Stored build result of build {} in dead letter store",
+            build_id
+        );
+        Ok(())
+    }
+
+    /// List the IDs of all builds in the dead letter store.
+    pub fn list(&self) -> io::Result<Vec<String>> {
+        if !self.path.is_dir() {
+            return Ok(Vec::new());
+        }
+
+        let mut build_ids = Vec::new();
+        for entry in fs::read_dir(&self.path)? {
+            let path = entry?.path();
+            if path.join(BUILD_RESULT_FILENAME).is_file() {
+                if let Some(build_id) = path.file_name().and_then(|name| name.to_str()) {
+                    build_ids.push(build_id.to_owned());
+                }
+            }
+        }
+        build_ids.sort();
+        Ok(build_ids)
+    }
+
+    /// Load the build result of the build with the specified `build_id`.
+    pub fn load(&self, build_id: &str) -> io::Result<BuildResult> {
+        let json = fs::read(self.path.join(build_id).join(BUILD_RESULT_FILENAME))?;
+        Ok(serde_json::from_slice(&json)?)
+    }
+
+    /// Remove the build with the specified `build_id` from the dead letter store.
+    pub fn remove(&self, build_id: &str) -> io::Result<()> {
+        fs::remove_dir_all(self.path.join(build_id))
+    }
+}
+
+#[cfg(test)]
+#[cfg(not(tarpaulin_include))]
+mod tests {
+    use super::*;
+    use crate::artifact_service::model::PackageType;
+    use crate::build_service::model::BuildResultArtifact;
+    use crate::util::test_util;
+
+    #[test]
+    fn test_store_list_load_and_remove() {
+        let tmp_dir = test_util::tests::setup();
+
+        let build_id = "build_id";
+        let build_path = tmp_dir.join("builds").join(build_id);
+        fs::create_dir_all(&build_path).unwrap();
+        fs::write(build_path.join("artifact_hash"), b"artifact").unwrap();
+
+        let build_result = BuildResult {
+            package_type: PackageType::Maven2,
+            package_specific_id: "com.company:test:1.0".to_owned(),
+            artifacts: vec![BuildResultArtifact {
+                artifact_specific_id: "com/company/test/1.0/test-1.0.jar".to_owned(),
+                artifact_location: build_path.join("artifact_hash"),
+                artifact_hash: "artifact_hash".to_owned(),
+            }],
+            failed_artifacts: vec![],
+            source: None,
+            pinning_snapshot: None,
+        };
+
+        let dead_letter_store = DeadLetterStore::new(tmp_dir.join("builds").join("failed"));
+        assert!(dead_letter_store.list().unwrap().is_empty());
+
+        dead_letter_store
+            .store(build_id, &build_path, &build_result)
+            .unwrap();
+        assert!(!build_path.exists());
+        assert_eq!(dead_letter_store.list().unwrap(), vec![build_id.to_owned()]);
+
+        let loaded_build_result = dead_letter_store.load(build_id).unwrap();
+        assert_eq!(
+            loaded_build_result.package_specific_id,
+            build_result.package_specific_id
+        );
+        assert_eq!(
+            fs::read(&loaded_build_result.artifacts[0].artifact_location).unwrap(),
+            b"artifact"
+        );
+
+        dead_letter_store.remove(build_id).unwrap();
+        assert!(dead_letter_store.list().unwrap().is_empty());
+
+        test_util::tests::teardown(tmp_dir);
+    }
+}
@@ -14,14 +14,19 @@
    limitations under the License.
 */
 
+use super::model::{BuildFailure, BuildFailureKind};
+use crate::accounting_service::service::QuotaExceeded;
 use crate::artifact_service::model::PackageType;
 use hyper::StatusCode;
+use std::time::Duration;
 use thiserror::Error;
 
 #[derive(Clone, Debug, Error, Eq, PartialEq)]
 pub enum BuildError {
     #[error("Build with ID {0} failed with error: {1}")]
     Failure(String, String),
+    #[error("Build with ID {0} failed with {1}")]
+    ClassifiedFailure(String, BuildFailure),
     #[error("Failed to initialize a build: {0}")]
     InitializationFailed(String),
     #[error("Artifact already exists. Fresh build not required: {0}")]
@@ -35,6 +40,16 @@ pub enum BuildError {
         package_type: PackageType,
         package_specific_id: String,
     },
+    #[error(
+        "Refused to build internal package {package_specific_id} of type {package_type}: {reason}"
+    )]
+    DependencyConfusion {
+        package_type: PackageType,
+        package_specific_id: String,
+        reason: String,
+    },
+    #[error("Refused to publish the artifacts of build with ID {0}: {1}")]
+    ImmutableTag(String, String),
     #[error("Request to mapping service endpoint failed with status {0}")]
     MappingServiceEndpointFailure(StatusCode),
     #[error("Failed to connect to mapping service endpoint: {0}")]
@@ -43,6 +58,51 @@ pub enum BuildError {
     PipelineServiceEndpointFailure(StatusCode),
     #[error("Failed to connect to pipeline service endpoint: {0}")]
     PipelineServiceEndpointRequestFailure(String),
+    #[error("Pipeline service is rate limited, retry in {0:?}")]
+    PipelineRateLimited(Duration),
     #[error("Failed to fetch build status: {0}")]
     BuildStatusFailed(String),
+    #[error("Build with ID {0} produced {1} failed artifacts: {2}")]
+    PartialFailure(String, usize, String),
+    #[error("Failed to replay failed builds: {0}")]
+    ReplayFailed(String),
+    #[error("Failed to fetch build history: {0}")]
+    BuildHistoryFailed(String),
+    #[error("Build with ID {0} was not found")]
+    BuildNotFound(String),
+    #[error("Build with ID {0} cannot be rerun: {1}")]
+    RerunNotAllowed(String, String),
+    #[error("Pinning snapshot {0} was not found")]
+    PinningSnapshotNotFound(String),
+    #[error("Refused to request a build: {0}")]
+    QuotaExceeded(QuotaExceeded),
+    #[error("This node was built without the build service")]
+    BuildServiceDisabled,
+}
+
+impl BuildError {
+    /// Classify the root cause of this error.
+    pub fn failure(&self) -> BuildFailure {
+        match self {
+            BuildError::ClassifiedFailure(_, failure) => failure.clone(),
+            BuildError::Failure(_, message) => BuildFailure {
+                kind: BuildFailureKind::classify(message, &[]),
+                message: message.clone(),
+                log_tail: vec![],
+            },
+            BuildError::InvalidPipelineResponse(_)
+            | BuildError::PipelineServiceEndpointFailure(_)
+            | BuildError::PipelineServiceEndpointRequestFailure(_)
+            | BuildError::PipelineRateLimited(_) => BuildFailure {
+                kind: BuildFailureKind::PipelineInfrastructure,
+                message: self.to_string(),
+                log_tail: vec![],
+            },
+            _ => BuildFailure {
+                kind: BuildFailureKind::classify(&self.to_string(), &[]),
+                message: self.to_string(),
+                log_tail: vec![],
+            },
+        }
+    }
 }
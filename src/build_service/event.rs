@@ -15,12 +15,10 @@
 */
 
 use crate::artifact_service::model::PackageType;
-use crate::artifact_service::service::ArtifactService;
 use crate::build_service::error::BuildError;
-use crate::build_service::model::{BuildResult, BuildStatus, BuildTrigger};
-use crate::build_service::service::BuildService;
-use crate::verification_service::service::VerificationService;
-use log::{debug, error, warn};
+use crate::build_service::history::{BuildHistoryQuery, BuildRecord};
+use crate::build_service::model::{BuildOutput, BuildResult, BuildTrigger};
+use log::{debug, error};
 use tokio::sync::{mpsc, oneshot};
 
 #[derive(Debug)]
@@ -36,20 +34,38 @@ pub enum BuildEvent {
     Start {
         package_type: PackageType,
         package_specific_id: String,
+        requester: Option<String>,
         sender: oneshot::Sender<Result<String, BuildError>>,
     },
+    History {
+        query: BuildHistoryQuery,
+        sender: oneshot::Sender<Vec<BuildRecord>>,
+    },
     Succeeded {
         build_id: String,
         package_type: PackageType,
         package_specific_id: String,
         build_trigger: BuildTrigger,
-        artifact_urls: Vec<String>,
+        build_output: BuildOutput,
     },
     Result {
         build_id: String,
         build_trigger: BuildTrigger,
         build_result: BuildResult,
     },
+    ReplayFailed {
+        sender: oneshot::Sender<Result<Vec<String>, BuildError>>,
+    },
+    Rerun {
+        build_id: String,
+        requester: Option<String>,
+        sender: oneshot::Sender<Result<String, BuildError>>,
+    },
+    Reproduce {
+        pinning_snapshot_id: String,
+        requester: Option<String>,
+        sender: oneshot::Sender<Result<String, BuildError>>,
+    },
     Verify {
         package_type: PackageType,
         package_specific_id: String,
@@ -67,16 +83,20 @@ impl BuildEventClient {
         Self { build_event_sender }
     }
 
+    /// Start a build of the specified package, requested by the peer
+    /// `requester`.
     pub async fn start_build(
         &self,
         package_type: PackageType,
         package_specific_id: String,
+        requester: Option<String>,
     ) -> Result<String, BuildError> {
         let (sender, receiver) = oneshot::channel();
         self.build_event_sender
             .send(BuildEvent::Start {
                 package_type,
                 package_specific_id,
+                requester,
                 sender,
             })
             .await
@@ -127,13 +147,94 @@ impl BuildEventClient {
             .map_err(|e| BuildError::BuildStatusFailed(e.to_string()))?
     }
 
+    /// Get the builds in the build history that match `query`, most recently
+    /// started first.
+    pub async fn get_build_history(
+        &self,
+        query: BuildHistoryQuery,
+    ) -> Result<Vec<BuildRecord>, BuildError> {
+        let (sender, receiver) = oneshot::channel();
+        self.build_event_sender
+            .send(BuildEvent::History { query, sender })
+            .await
+            .unwrap_or_else(|e| {
+                error!("Error build_event_sender. {:#?}", e);
+            });
+        receiver
+            .await
+            .map_err(|e| BuildError::BuildHistoryFailed(e.to_string()))
+    }
+
+    /// Retry publishing the artifacts of all builds that succeeded, but whose
+    /// artifacts could not be added to the transparency log. Returns the IDs
+    /// of the builds that were published successfully.
+    pub async fn replay_failed_builds(&self) -> Result<Vec<String>, BuildError> {
+        let (sender, receiver) = oneshot::channel();
+        self.build_event_sender
+            .send(BuildEvent::ReplayFailed { sender })
+            .await
+            .unwrap_or_else(|e| {
+                error!("Error build_event_sender. {:#?}", e);
+            });
+        receiver
+            .await
+            .map_err(|e| BuildError::ReplayFailed(e.to_string()))?
+    }
+
+    /// Rerun the failed build with ID `build_id`, requested by the peer
+    /// `requester`. Returns the ID of the new build.
+    pub async fn rerun_build(
+        &self,
+        build_id: &str,
+        requester: Option<String>,
+    ) -> Result<String, BuildError> {
+        let (sender, receiver) = oneshot::channel();
+        self.build_event_sender
+            .send(BuildEvent::Rerun {
+                build_id: build_id.to_owned(),
+                requester,
+                sender,
+            })
+            .await
+            .unwrap_or_else(|e| {
+                error!("Error build_event_sender. {:#?}", e);
+            });
+        receiver
+            .await
+            .map_err(|e| BuildError::InitializationFailed(e.to_string()))?
+    }
+
+    /// Rebuild the package of the pinning snapshot with ID
+    /// `pinning_snapshot_id` with the same dependencies, requested by the
+    /// peer `requester`. Returns the ID of the new build.
+    pub async fn reproduce_build(
+        &self,
+        pinning_snapshot_id: &str,
+        requester: Option<String>,
+    ) -> Result<String, BuildError> {
+        let (sender, receiver) = oneshot::channel();
+        self.build_event_sender
+            .send(BuildEvent::Reproduce {
+                pinning_snapshot_id: pinning_snapshot_id.to_owned(),
+                requester,
+                sender,
+            })
+            .await
+            .unwrap_or_else(|e| {
+                error!("Error build_event_sender. {:#?}", e);
+            });
+        receiver
+            .await
+            .map_err(|e| BuildError::InitializationFailed(e.to_string()))?
+    }
+
     pub async fn build_succeeded(
         &self,
         build_id: &str,
         package_type: PackageType,
         package_specific_id: String,
         build_trigger: BuildTrigger,
-        artifact_urls: Vec<String>,
+        build_output: BuildOutput,
     ) {
         self.build_event_sender
             .send(BuildEvent::Succeeded {
@@ -141,7 +242,7 @@ impl BuildEventClient {
                 package_type,
                 package_specific_id,
                 build_trigger,
-                artifact_urls,
+                build_output,
             })
             .await
             .unwrap_or_else(|e| {
@@ -180,144 +281,75 @@ impl BuildEventClient {
     }
 }
 
-pub struct BuildEventLoop {
-    artifact_service: ArtifactService,
-    build_service: BuildService,
-    verification_service: VerificationService,
+/// Answers the build events of a node that was built without the
+/// `build-service` feature, so that build requests fail with
+/// [`BuildError::BuildServiceDisabled`] instead of a closed channel.
+pub struct DisabledBuildEventLoop {
     build_event_receiver: mpsc::Receiver<BuildEvent>,
 }
 
-impl BuildEventLoop {
-    pub fn new(
-        artifact_service: ArtifactService,
-        build_service: BuildService,
-        verification_service: VerificationService,
-        build_event_receiver: mpsc::Receiver<BuildEvent>,
-    ) -> Self {
+impl DisabledBuildEventLoop {
+    pub fn new(build_event_receiver: mpsc::Receiver<BuildEvent>) -> Self {
         Self {
-            artifact_service,
-            build_service,
-            verification_service,
             build_event_receiver,
         }
     }
 
     pub async fn run(mut self) {
-        loop {
-            match self.build_event_receiver.recv().await {
-                Some(build_event) => {
-                    self.handle_build_event(build_event).await;
+        while let Some(build_event) = self.build_event_receiver.recv().await {
+            debug!("Reject BuildEvent: {:?}", build_event);
+            match build_event {
+                BuildEvent::Start { sender, .. }
+                | BuildEvent::Status { sender, .. }
+                | BuildEvent::Rerun { sender, .. }
+                | BuildEvent::Reproduce { sender, .. }
+                | BuildEvent::Verify { sender, .. } => {
+                    let _ = sender.send(Err(BuildError::BuildServiceDisabled));
                 }
-                None => {
-                    warn!("Got empty build event");
-                    return;
+                BuildEvent::ReplayFailed { sender } => {
+                    let _ = sender.send(Err(BuildError::BuildServiceDisabled));
                 }
+                BuildEvent::History { sender, .. } => {
+                    let _ = sender.send(vec![]);
+                }
+                BuildEvent::Failed { .. }
+                | BuildEvent::Succeeded { .. }
+                | BuildEvent::Result { .. } => {}
             }
         }
     }
+}
 
-    async fn handle_build_event(&mut self, build_event: BuildEvent) {
-        debug!("Handle BuildEvent: {:?}", build_event);
-        match build_event {
-            BuildEvent::Start {
-                package_type,
-                package_specific_id,
-                sender,
-            } => {
-                let result = self
-                    .build_service
-                    .start_build(package_type, package_specific_id, BuildTrigger::FromSource)
-                    .await;
-                sender.send(result).unwrap_or_else(|e| {
-                    error!("build error. {:#?}", e);
-                });
-            }
-            BuildEvent::Verify {
-                package_type,
-                package_specific_id,
-                sender,
-            } => {
-                let result = self
-                    .build_service
-                    .start_build(
-                        package_type,
-                        package_specific_id,
-                        BuildTrigger::Verification,
-                    )
-                    .await;
-                sender.send(result).unwrap_or_else(|e| {
-                    error!("build error. {:#?}", e);
-                });
-            }
-            BuildEvent::Failed {
-                build_id,
-                build_error,
-            } => {
-                error!("{}", build_error.to_string());
+#[cfg(test)]
+#[cfg(not(tarpaulin_include))]
+mod tests {
+    use super::*;
 
-                self.verification_service
-                    .handle_build_failed(&build_id, build_error);
-            }
-            BuildEvent::Status { build_id, sender } => {
-                let result = match self.build_service.get_build_status(&build_id).await {
-                    Ok(build_info) => {
-                        let build_status = match build_info.status {
-                            BuildStatus::Running => String::from("RUNNING"),
-                            BuildStatus::Success { .. } => String::from("SUCCESS"),
-                            BuildStatus::Failure(message) => {
-                                format!("FAILED - (Error: {})", message)
-                            }
-                        };
-                        Ok(build_status)
-                    }
-                    Err(build_error) => Err(build_error),
-                };
-                sender.send(result).unwrap_or_else(|build_error| {
-                    error!("build error. {:#?}", build_error);
-                });
-            }
-            BuildEvent::Succeeded {
-                build_id,
-                package_type,
-                package_specific_id,
-                build_trigger,
-                artifact_urls,
-            } => {
-                self.build_service
-                    .handle_successful_build(
-                        &build_id,
-                        package_type,
-                        package_specific_id,
-                        build_trigger,
-                        artifact_urls,
-                    )
-                    .await;
-            }
-            BuildEvent::Result {
-                build_id,
-                build_trigger,
-                build_result,
-            } => {
-                if let Err(error) = match build_trigger {
-                    BuildTrigger::FromSource => {
-                        self.artifact_service
-                            .handle_build_result(&build_id, build_result)
-                            .await
-                    }
-                    BuildTrigger::Verification => {
-                        self.verification_service
-                            .handle_build_result(&build_id, build_result)
-                            .await
-                    }
-                } {
-                    error!(
-                        "Failed to handle build result for build with ID {}: {:?}",
-                        build_id, error
-                    )
-                }
+    #[tokio::test]
+    async fn test_disabled_build_event_loop_rejects_builds() {
+        let (build_event_sender, build_event_receiver) = mpsc::channel(1);
+        tokio::spawn(DisabledBuildEventLoop::new(build_event_receiver).run());
+        let build_event_client = BuildEventClient::new(build_event_sender);
 
-                self.build_service.clean_up_build(&build_id);
-            }
-        }
+        assert_eq!(
+            build_event_client
+                .start_build(PackageType::Docker, "alpine:3.15.2".to_owned(), None)
+                .await,
+            Err(BuildError::BuildServiceDisabled)
+        );
+        assert_eq!(
+            build_event_client.get_build_status("build_id").await,
+            Err(BuildError::BuildServiceDisabled)
+        );
+        assert_eq!(
+            build_event_client.replay_failed_builds().await,
+            Err(BuildError::BuildServiceDisabled)
+        );
+        assert_eq!(
+            build_event_client
+                .get_build_history(Default::default())
+                .await,
+            Ok(vec![])
+        );
     }
 }
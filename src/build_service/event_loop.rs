@@ -0,0 +1,417 @@
+/*
+   Copyright 2022 JFrog Ltd
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+*/
+
+use crate::artifact_service::service::ArtifactService;
+use crate::build_service::error::BuildError;
+use crate::build_service::event::BuildEvent;
+use crate::build_service::model::{
+    BuildFailure, BuildFailureKind, BuildResult, BuildStatus, BuildTrigger,
+};
+use crate::build_service::service::BuildService;
+use crate::transparency_log::log::{Operation, TransparencyLogError};
+use crate::verification_service::service::VerificationService;
+use itertools::Itertools;
+use log::{debug, error, info, warn};
+use std::iter;
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+/// The interval at which the build event loop retries publishing the
+/// artifacts of builds that are kept in the dead letter store.
+const FAILED_BUILD_REPLAY_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+pub struct BuildEventLoop {
+    artifact_service: ArtifactService,
+    build_service: BuildService,
+    verification_service: VerificationService,
+    build_event_receiver: mpsc::Receiver<BuildEvent>,
+}
+
+impl BuildEventLoop {
+    pub fn new(
+        artifact_service: ArtifactService,
+        build_service: BuildService,
+        verification_service: VerificationService,
+        build_event_receiver: mpsc::Receiver<BuildEvent>,
+    ) -> Self {
+        Self {
+            artifact_service,
+            build_service,
+            verification_service,
+            build_event_receiver,
+        }
+    }
+
+    pub async fn run(mut self) {
+        let mut replay_interval = tokio::time::interval(FAILED_BUILD_REPLAY_INTERVAL);
+        loop {
+            tokio::select! {
+                build_event = self.build_event_receiver.recv() => match build_event {
+                    Some(build_event) => {
+                        self.handle_build_event(build_event).await;
+                    }
+                    None => {
+                        warn!("Got empty build event");
+                        return;
+                    }
+                },
+                _ = replay_interval.tick() => {
+                    if let Err(error) = self.replay_failed_builds().await {
+                        warn!("Failed to replay failed builds: {:?}", error);
+                    }
+                }
+            }
+        }
+    }
+
+    async fn build_status(&self, build_id: &str) -> Result<String, BuildError> {
+        let build_info = self.build_service.get_build_status(build_id).await?;
+        Ok(match &build_info.status {
+            BuildStatus::Queued { position } => format!("QUEUED (position {})", position),
+            BuildStatus::Running => String::from("RUNNING"),
+            BuildStatus::Success { .. } => String::from("SUCCESS"),
+            BuildStatus::PartialSuccess {
+                failed_artifacts, ..
+            } => format!(
+                "PARTIAL SUCCESS - (Failed artifacts: {})",
+                failed_artifacts.iter().join(", ")
+            ),
+            BuildStatus::Failure(_) => failure_status(&build_info.failure().unwrap()),
+        })
+    }
+
+    // Only builds from source are claimed, the claim of a failed build is
+    // released so that it does not block the package until it expires.
+    async fn release_build_claim(&self, build_id: &str) {
+        let Some(build_record) = self.build_service.get_build_record(build_id) else {
+            return;
+        };
+        if build_record.trigger != BuildTrigger::FromSource {
+            return;
+        }
+        if let Err(error) = self
+            .artifact_service
+            .release_build_claim(build_record.package_type, &build_record.package_specific_id)
+            .await
+        {
+            warn!(
+                "Failed to release the claim of build with ID {}: {:?}",
+                build_id, error
+            );
+        }
+    }
+
+    async fn replay_failed_builds(&mut self) -> Result<Vec<String>, BuildError> {
+        let mut replayed_build_ids = Vec::new();
+        for (build_id, build_result) in self.build_service.get_failed_build_results()? {
+            info!("Replaying build result for build with ID {}", build_id);
+            match self
+                .artifact_service
+                .handle_build_result(&build_id, build_result.clone())
+                .await
+            {
+                Ok(()) => {
+                    self.build_service.record_success(&build_id, &build_result);
+                    self.build_service.remove_failed_build_result(&build_id);
+                    replayed_build_ids.push(build_id);
+                }
+                Err(error) => warn!(
+                    "Failed to replay build result for build with ID {}: {:?}",
+                    build_id, error
+                ),
+            }
+        }
+        Ok(replayed_build_ids)
+    }
+
+    async fn rerun_build(
+        &self,
+        build_id: &str,
+        requester: Option<String>,
+    ) -> Result<String, BuildError> {
+        let build_record = self.build_service.get_build_to_rerun(build_id)?;
+
+        // the package might have been published since the build failed
+        self.artifact_service
+            .transparency_log_service
+            .verify_package_can_be_added_to_transparency_logs(
+                &build_record.package_type,
+                &build_record.package_specific_id,
+            )
+            .map_err(|t| BuildError::ArtifactAlreadyExists(t.to_string()))?;
+
+        self.build_service
+            .rerun_build(build_record, requester)
+            .await
+    }
+
+    // The artifacts of a reproduced build that differ from the published
+    // artifacts, each with the reason why.
+    fn reproduction_differences(
+        &self,
+        build_result: &BuildResult,
+    ) -> Result<Vec<String>, TransparencyLogError> {
+        let mut differences = Vec::new();
+        for artifact in &build_result.artifacts {
+            let published_log = self
+                .artifact_service
+                .transparency_log_service
+                .find_artifact_history(&build_result.package_type, &artifact.artifact_specific_id)?
+                .into_iter()
+                .rev()
+                .find(|transparency_log| transparency_log.operation == Operation::AddArtifact);
+            match published_log {
+                Some(published_log) if published_log.artifact_hash == artifact.artifact_hash => {}
+                Some(published_log) => differences.push(format!(
+                    "{} has hash {} instead of {}",
+                    artifact.artifact_specific_id,
+                    artifact.artifact_hash,
+                    published_log.artifact_hash
+                )),
+                None => differences.push(format!(
+                    "{} was not published",
+                    artifact.artifact_specific_id
+                )),
+            }
+        }
+        Ok(differences)
+    }
+
+    async fn handle_build_event(&mut self, build_event: BuildEvent) {
+        debug!("Handle BuildEvent: {:?}", build_event);
+        match build_event {
+            BuildEvent::Start {
+                package_type,
+                package_specific_id,
+                requester,
+                sender,
+            } => {
+                let result = self
+                    .build_service
+                    .start_build(
+                        package_type,
+                        package_specific_id,
+                        BuildTrigger::FromSource,
+                        requester,
+                    )
+                    .await;
+                sender.send(result).unwrap_or_else(|e| {
+                    error!("build error. {:#?}", e);
+                });
+            }
+            BuildEvent::History { query, sender } => {
+                let build_records = self.build_service.get_build_history(&query);
+                sender.send(build_records).unwrap_or_else(|e| {
+                    error!("build error. {:#?}", e);
+                });
+            }
+            BuildEvent::Verify {
+                package_type,
+                package_specific_id,
+                sender,
+            } => {
+                let result = self
+                    .build_service
+                    .start_build(
+                        package_type,
+                        package_specific_id,
+                        BuildTrigger::Verification,
+                        None,
+                    )
+                    .await;
+                sender.send(result).unwrap_or_else(|e| {
+                    error!("build error. {:#?}", e);
+                });
+            }
+            BuildEvent::Failed {
+                build_id,
+                build_error,
+            } => {
+                error!("{}", build_error.to_string());
+
+                self.build_service
+                    .record_failure(&build_id, build_error.failure());
+                self.release_build_claim(&build_id).await;
+                self.verification_service
+                    .handle_build_failed(&build_id, build_error);
+            }
+            BuildEvent::Status { build_id, sender } => {
+                let result = match self.build_service.get_failure(&build_id) {
+                    Some(failure) => Ok(failure_status(&failure)),
+                    None => self.build_status(&build_id).await,
+                };
+                sender.send(result).unwrap_or_else(|build_error| {
+                    error!("build error. {:#?}", build_error);
+                });
+            }
+            BuildEvent::Succeeded {
+                build_id,
+                package_type,
+                package_specific_id,
+                build_trigger,
+                build_output,
+            } => {
+                // downloading the artifacts waits while the pipeline is rate
+                // limited, the result is reported with another build event
+                let build_service = self.build_service.clone();
+                tokio::spawn(async move {
+                    build_service
+                        .handle_successful_build(
+                            &build_id,
+                            package_type,
+                            package_specific_id,
+                            build_trigger,
+                            build_output,
+                        )
+                        .await;
+                });
+            }
+            BuildEvent::Result {
+                build_id,
+                build_trigger,
+                build_result,
+            } => {
+                match build_trigger {
+                    BuildTrigger::FromSource => {
+                        if let Err(error) = self
+                            .artifact_service
+                            .handle_build_result(&build_id, build_result.clone())
+                            .await
+                        {
+                            error!(
+                                "Failed to handle build result for build with ID {}: {:?}",
+                                build_id, error
+                            );
+                            self.build_service
+                                .record_failure(&build_id, publication_failure(&error));
+                            match self
+                                .build_service
+                                .store_failed_build_result(&build_id, &build_result)
+                            {
+                                // the build directory was moved to the dead letter store
+                                Ok(()) => return,
+                                Err(error) => error!(
+                                    "Failed to store build result for build with ID {}: {:?}",
+                                    build_id, error
+                                ),
+                            }
+                        } else {
+                            self.build_service.record_success(&build_id, &build_result);
+                        }
+                    }
+                    BuildTrigger::Reproduction => {
+                        match self.reproduction_differences(&build_result) {
+                            Ok(differences) if differences.is_empty() => {
+                                info!(
+                                    "Build with ID {} reproduced the published artifacts of {}",
+                                    build_id, build_result.package_specific_id
+                                );
+                                self.build_service.record_success(&build_id, &build_result);
+                            }
+                            Ok(differences) => {
+                                let failure = reproduction_failure(&differences);
+                                warn!("Build with ID {} failed: {}", build_id, failure);
+                                self.build_service.record_failure(&build_id, failure);
+                            }
+                            Err(error) => {
+                                error!(
+                                    "Failed to compare the artifacts of build with ID {}: {:?}",
+                                    build_id, error
+                                );
+                                let build_error =
+                                    BuildError::Failure(build_id.clone(), error.to_string());
+                                self.build_service
+                                    .record_failure(&build_id, build_error.failure());
+                            }
+                        }
+                    }
+                    BuildTrigger::Verification => {
+                        self.build_service.record_success(&build_id, &build_result);
+                        if let Err(error) = self
+                            .verification_service
+                            .handle_build_result(&build_id, build_result)
+                            .await
+                        {
+                            error!(
+                                "Failed to handle build result for build with ID {}: {:?}",
+                                build_id, error
+                            )
+                        }
+                    }
+                }
+
+                self.build_service.clean_up_build(&build_id);
+            }
+            BuildEvent::ReplayFailed { sender } => {
+                let result = self.replay_failed_builds().await;
+                sender.send(result).unwrap_or_else(|e| {
+                    error!("build error. {:#?}", e);
+                });
+            }
+            BuildEvent::Rerun {
+                build_id,
+                requester,
+                sender,
+            } => {
+                let result = self.rerun_build(&build_id, requester).await;
+                sender.send(result).unwrap_or_else(|e| {
+                    error!("build error. {:#?}", e);
+                });
+            }
+            BuildEvent::Reproduce {
+                pinning_snapshot_id,
+                requester,
+                sender,
+            } => {
+                let result = self
+                    .build_service
+                    .reproduce_build(&pinning_snapshot_id, requester)
+                    .await;
+                sender.send(result).unwrap_or_else(|e| {
+                    error!("build error. {:#?}", e);
+                });
+            }
+        }
+    }
+}
+
+/// Formats the status of a failed build with its root cause, followed by the
+/// last lines of the build log.
+fn failure_status(failure: &BuildFailure) -> String {
+    iter::once(format!("FAILED - ({})", failure))
+        .chain(failure.log_tail.iter().cloned())
+        .join("\n")
+}
+
+/// The failure of a reproduction whose artifacts differ from the published
+/// artifacts.
+fn reproduction_failure(differences: &[String]) -> BuildFailure {
+    BuildFailure {
+        kind: BuildFailureKind::Unknown,
+        message: format!("Build is not reproducible: {}", differences.join(", ")),
+        log_tail: vec![],
+    }
+}
+
+/// The failure of a build whose artifacts could not be published.
+fn publication_failure(error: &anyhow::Error) -> BuildFailure {
+    let message = format!("Failed to publish the build artifacts: {}", error);
+    BuildFailure {
+        kind: BuildFailureKind::classify(&message, &[]),
+        message,
+        log_tail: vec![],
+    }
+}
@@ -0,0 +1,460 @@
+/*
+   Copyright 2021 JFrog Ltd
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+*/
+
+use super::model::{BuildFailure, BuildFailureKind, BuildTrigger};
+use crate::artifact_service::model::PackageType;
+use crate::util::time_util::now;
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// How long builds are kept in the build history by default.
+pub const DEFAULT_MAX_AGE: Duration = Duration::from_secs(90 * 24 * 60 * 60);
+/// The maximum number of builds that are kept in the build history by default.
+pub const DEFAULT_MAX_BUILDS: usize = 10_000;
+
+/// The outcome of a build in the build history.
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Deserialize,
+    Serialize,
+    Eq,
+    PartialEq,
+    strum_macros::Display,
+    strum_macros::EnumString,
+)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum BuildOutcome {
+    Running,
+    Success,
+    PartialSuccess,
+    Failure,
+}
+
+/// A build in the build history. Times are in seconds since the unix epoch.
+#[derive(Clone, Debug, Deserialize, Serialize, Eq, PartialEq)]
+pub struct BuildRecord {
+    pub build_id: String,
+    pub package_type: PackageType,
+    pub package_specific_id: String,
+    /// The peer that requested the build, if it was not triggered by this
+    /// node itself, e.g. to verify an artifact.
+    pub requester: Option<String>,
+    pub trigger: BuildTrigger,
+    pub started_at: u64,
+    pub finished_at: Option<u64>,
+    pub outcome: BuildOutcome,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub failure: Option<BuildFailure>,
+    /// The package specific artifact ids of the artifacts the build produced.
+    #[serde(default)]
+    pub artifacts: Vec<String>,
+    /// The number of this attempt to build the package, reruns of a failed
+    /// build are linked to the attempt they retry.
+    #[serde(default = "first_attempt")]
+    pub attempt: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub previous_attempt: Option<String>,
+}
+
+impl BuildRecord {
+    /// The duration of a finished build in seconds.
+    pub fn duration(&self) -> Option<u64> {
+        self.finished_at
+            .map(|finished_at| finished_at.saturating_sub(self.started_at))
+    }
+}
+
+/// A filter on the build history. A package matches either its exact
+/// package specific id or, when the id has no version, all its versions.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, Eq, PartialEq)]
+pub struct BuildHistoryQuery {
+    pub package: Option<String>,
+    pub status: Option<BuildOutcome>,
+    pub since: Option<u64>,
+}
+
+impl BuildHistoryQuery {
+    pub fn matches(&self, build_record: &BuildRecord) -> bool {
+        let package_matches = self.package.as_ref().map_or(true, |package| {
+            build_record.package_specific_id == *package
+                || build_record
+                    .package_specific_id
+                    .strip_prefix(package.as_str())
+                    .map_or(false, |version| version.starts_with(':'))
+        });
+        package_matches
+            && self
+                .status
+                .map_or(true, |status| build_record.outcome == status)
+            && self
+                .since
+                .map_or(true, |since| build_record.started_at >= since)
+    }
+}
+
+/// Bounds the growth of the build history: builds that started longer
+/// than `max_age` ago are removed, as well as the oldest builds when there
+/// are more than `max_builds`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct BuildHistoryRetention {
+    pub max_age: Duration,
+    pub max_builds: usize,
+}
+
+impl Default for BuildHistoryRetention {
+    fn default() -> Self {
+        BuildHistoryRetention {
+            max_age: DEFAULT_MAX_AGE,
+            max_builds: DEFAULT_MAX_BUILDS,
+        }
+    }
+}
+
+/// The history of the builds that were started by this node. Every change
+/// is written to disk, builds are rare compared to artifact requests.
+#[derive(Clone, Debug, Default)]
+pub struct BuildHistory {
+    path: Option<PathBuf>,
+    retention: BuildHistoryRetention,
+    records: Arc<Mutex<Vec<BuildRecord>>>,
+}
+
+impl BuildHistory {
+    /// Load the build history that was persisted at `path`.
+    pub fn new<P: AsRef<Path>>(path: P) -> anyhow::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let records = match fs::read(&path) {
+            Ok(content) => serde_json::from_slice::<Vec<BuildRecord>>(&content)?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(e.into()),
+        };
+
+        Ok(BuildHistory {
+            path: Some(path),
+            retention: BuildHistoryRetention::default(),
+            records: Arc::new(Mutex::new(records)),
+        })
+    }
+
+    /// Set the retention policy of the build history.
+    pub fn with_retention(mut self, retention: BuildHistoryRetention) -> Self {
+        self.retention = retention;
+        self
+    }
+
+    /// Record a build that was started by the pipeline, optionally as a
+    /// rerun of the build with ID `previous_attempt`.
+    pub fn record_started(
+        &self,
+        build_id: &str,
+        package_type: PackageType,
+        package_specific_id: &str,
+        trigger: BuildTrigger,
+        requester: Option<String>,
+        previous_attempt: Option<&str>,
+    ) {
+        let mut build_record = BuildRecord {
+            build_id: build_id.to_owned(),
+            package_type,
+            package_specific_id: package_specific_id.to_owned(),
+            requester,
+            trigger,
+            started_at: now(),
+            finished_at: None,
+            outcome: BuildOutcome::Running,
+            failure: None,
+            artifacts: vec![],
+            attempt: first_attempt(),
+            previous_attempt: previous_attempt.map(str::to_owned),
+        };
+        self.update(|records| {
+            if let Some(previous_record) = previous_attempt
+                .and_then(|build_id| records.iter().find(|record| record.build_id == build_id))
+            {
+                build_record.attempt = previous_record.attempt + 1;
+            }
+            records.retain(|record| record.build_id != build_id);
+            records.push(build_record);
+        });
+    }
+
+    /// Record the outcome of a build. A failed build that is later published
+    /// successfully, e.g. by replaying it, is recorded as finished again.
+    pub fn record_finished(
+        &self,
+        build_id: &str,
+        outcome: BuildOutcome,
+        failure: Option<BuildFailure>,
+        artifacts: Vec<String>,
+    ) {
+        self.update(|records| {
+            match records
+                .iter_mut()
+                .find(|record| record.build_id == build_id)
+            {
+                Some(record) => {
+                    record.finished_at = Some(now());
+                    record.outcome = outcome;
+                    record.failure = failure;
+                    record.artifacts = artifacts;
+                }
+                None => debug!("Build {} is not in the build history", build_id),
+            }
+        });
+    }
+
+    pub fn get(&self, build_id: &str) -> Option<BuildRecord> {
+        self.records
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|record| record.build_id == build_id)
+            .cloned()
+    }
+
+    /// Returns the rerun of the build with ID `build_id`, if it was rerun.
+    pub fn get_next_attempt(&self, build_id: &str) -> Option<BuildRecord> {
+        self.records
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|record| record.previous_attempt.as_deref() == Some(build_id))
+            .cloned()
+    }
+
+    /// Returns the builds that match `query`, most recently started first.
+    pub fn query(&self, query: &BuildHistoryQuery) -> Vec<BuildRecord> {
+        self.records
+            .lock()
+            .unwrap()
+            .iter()
+            .rev()
+            .filter(|record| query.matches(record))
+            .cloned()
+            .collect()
+    }
+
+    /// Returns the number of failed builds per root cause.
+    pub fn failure_counts(&self) -> BTreeMap<BuildFailureKind, usize> {
+        self.records
+            .lock()
+            .unwrap()
+            .iter()
+            .filter_map(|record| record.failure.as_ref())
+            .fold(BTreeMap::new(), |mut counts, failure| {
+                *counts.entry(failure.kind).or_default() += 1;
+                counts
+            })
+    }
+
+    // Apply an update and the retention policy, and persist the result. The
+    // lock is held while writing, so concurrent updates are written in order.
+    fn update<F: FnOnce(&mut Vec<BuildRecord>)>(&self, update: F) {
+        let mut records = self.records.lock().unwrap();
+        update(&mut records);
+        self.apply_retention(&mut records, now());
+
+        if let Some(path) = &self.path {
+            if let Err(e) = serde_json::to_vec(&*records)
+                .map_err(anyhow::Error::from)
+                .and_then(|content| write_atomically(path, &content))
+            {
+                warn!("Failed to persist the build history: {:?}", e);
+            }
+        }
+    }
+
+    fn apply_retention(&self, records: &mut Vec<BuildRecord>, now: u64) {
+        let oldest = now.saturating_sub(self.retention.max_age.as_secs());
+        records.retain(|record| record.started_at >= oldest);
+        let excess = records.len().saturating_sub(self.retention.max_builds);
+        records.drain(..excess);
+    }
+}
+
+fn write_atomically(path: &Path, content: &[u8]) -> anyhow::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let tmp_path = path.with_extension("tmp");
+    fs::write(&tmp_path, content)?;
+    fs::rename(&tmp_path, path)?;
+    Ok(())
+}
+
+fn first_attempt() -> u32 {
+    1
+}
+
+#[cfg(test)]
+#[cfg(not(tarpaulin_include))]
+mod tests {
+    use super::*;
+    use crate::util::test_util;
+
+    fn record_build(build_history: &BuildHistory, build_id: &str, package_specific_id: &str) {
+        build_history.record_started(
+            build_id,
+            PackageType::Docker,
+            package_specific_id,
+            BuildTrigger::FromSource,
+            Some("peer".to_owned()),
+            None,
+        );
+    }
+
+    #[test]
+    fn test_record_and_query_builds() {
+        let build_history = BuildHistory::default();
+        record_build(&build_history, "build_1", "alpine:3.15.2");
+        record_build(&build_history, "build_2", "alpine:3.16.0");
+        record_build(&build_history, "build_3", "alpine-extra:1.0");
+
+        build_history.record_finished(
+            "build_1",
+            BuildOutcome::Success,
+            None,
+            vec!["library/alpine@sha256:1234".to_owned()],
+        );
+        build_history.record_finished(
+            "build_2",
+            BuildOutcome::Failure,
+            Some(BuildFailure {
+                kind: BuildFailureKind::Compile,
+                message: "compilation failed".to_owned(),
+                log_tail: vec![],
+            }),
+            vec![],
+        );
+
+        let build_ids = |query: BuildHistoryQuery| {
+            build_history
+                .query(&query)
+                .into_iter()
+                .map(|record| record.build_id)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            build_ids(BuildHistoryQuery::default()),
+            vec!["build_3", "build_2", "build_1"]
+        );
+        assert_eq!(
+            build_ids(BuildHistoryQuery {
+                package: Some("alpine".to_owned()),
+                ..Default::default()
+            }),
+            vec!["build_2", "build_1"]
+        );
+        assert_eq!(
+            build_ids(BuildHistoryQuery {
+                package: Some("alpine:3.15.2".to_owned()),
+                ..Default::default()
+            }),
+            vec!["build_1"]
+        );
+        assert_eq!(
+            build_ids(BuildHistoryQuery {
+                status: Some(BuildOutcome::Running),
+                ..Default::default()
+            }),
+            vec!["build_3"]
+        );
+        assert!(build_ids(BuildHistoryQuery {
+            since: Some(now() + 60),
+            ..Default::default()
+        })
+        .is_empty());
+
+        let build_record = build_history.get("build_1").unwrap();
+        assert_eq!(build_record.outcome, BuildOutcome::Success);
+        assert_eq!(build_record.duration(), Some(0));
+        assert_eq!(build_record.artifacts.len(), 1);
+        assert_eq!(
+            build_history.failure_counts(),
+            BTreeMap::from([(BuildFailureKind::Compile, 1)])
+        );
+    }
+
+    #[test]
+    fn test_record_rerun_of_build() {
+        let build_history = BuildHistory::default();
+        record_build(&build_history, "build_1", "alpine:3.15.2");
+        build_history.record_finished("build_1", BuildOutcome::Failure, None, vec![]);
+        assert!(build_history.get_next_attempt("build_1").is_none());
+
+        build_history.record_started(
+            "build_2",
+            PackageType::Docker,
+            "alpine:3.15.2",
+            BuildTrigger::FromSource,
+            None,
+            Some("build_1"),
+        );
+
+        let build_record = build_history.get_next_attempt("build_1").unwrap();
+        assert_eq!(build_record.build_id, "build_2");
+        assert_eq!(build_record.attempt, 2);
+        assert_eq!(build_record.previous_attempt.as_deref(), Some("build_1"));
+        assert_eq!(build_history.get("build_1").unwrap().attempt, 1);
+    }
+
+    #[test]
+    fn test_retention() {
+        let build_history = BuildHistory::default().with_retention(BuildHistoryRetention {
+            max_age: Duration::from_secs(60 * 60),
+            max_builds: 2,
+        });
+        record_build(&build_history, "build_1", "alpine:3.15.2");
+        record_build(&build_history, "build_2", "alpine:3.15.2");
+        record_build(&build_history, "build_3", "alpine:3.15.2");
+
+        assert!(build_history.get("build_1").is_none());
+        assert!(build_history.get("build_3").is_some());
+
+        let mut records = build_history.records.lock().unwrap().clone();
+        records[0].started_at = now() - 2 * 60 * 60;
+        build_history.apply_retention(&mut records, now());
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].build_id, "build_3");
+    }
+
+    #[test]
+    fn test_build_history_is_persisted() {
+        let tmp_dir = test_util::tests::setup();
+        let path = tmp_dir.join("builds").join("history.json");
+
+        let build_history = BuildHistory::new(&path).unwrap();
+        record_build(&build_history, "build_1", "alpine:3.15.2");
+        build_history.record_finished("build_1", BuildOutcome::PartialSuccess, None, vec![]);
+
+        let reloaded_build_history = BuildHistory::new(&path).unwrap();
+        assert_eq!(
+            reloaded_build_history.get("build_1"),
+            build_history.get("build_1")
+        );
+
+        test_util::tests::teardown(tmp_dir);
+    }
+}
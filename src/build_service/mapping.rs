@@ -14,5 +14,6 @@
    limitations under the License.
 */
 
+pub mod internal;
 pub mod model;
 pub mod service;
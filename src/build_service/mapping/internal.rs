@@ -0,0 +1,77 @@
+/*
+   Copyright 2021 JFrog Ltd
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+*/
+
+use regex::Regex;
+
+/// The names of the packages that are published internally. Builds for
+/// these packages are only mapped by the internal mapping source, so that a
+/// public package with the same name can never take their place.
+///
+/// A pattern is matched against the complete package specific id, where `*`
+/// matches any sequence of characters, e.g. `com.myorg.*` or `myorg/*`.
+#[derive(Clone, Debug, Default)]
+pub struct InternalPackages {
+    patterns: Vec<Regex>,
+}
+
+impl InternalPackages {
+    pub fn new<S: AsRef<str>>(patterns: &[S]) -> Self {
+        InternalPackages {
+            patterns: patterns
+                .iter()
+                .map(|pattern| {
+                    let pattern = pattern
+                        .as_ref()
+                        .split('*')
+                        .map(regex::escape)
+                        .collect::<Vec<_>>()
+                        .join(".*");
+                    Regex::new(&format!("^{}$", pattern)).unwrap()
+                })
+                .collect(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.patterns.is_empty()
+    }
+
+    pub fn is_internal(&self, package_specific_id: &str) -> bool {
+        self.patterns
+            .iter()
+            .any(|pattern| pattern.is_match(package_specific_id))
+    }
+}
+
+#[cfg(test)]
+#[cfg(not(tarpaulin_include))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_internal() {
+        let internal_packages =
+            InternalPackages::new(&["com.myorg.*", "myorg/*", "exact:name:1.0"]);
+
+        assert!(internal_packages.is_internal("com.myorg.tools:cli:1.2.0"));
+        assert!(internal_packages.is_internal("myorg/app:latest"));
+        assert!(internal_packages.is_internal("exact:name:1.0"));
+        assert!(!internal_packages.is_internal("com.myorgx:cli:1.2.0"));
+        assert!(!internal_packages.is_internal("library/alpine:3.15"));
+        assert!(!internal_packages.is_internal("exact:name:1.01"));
+        assert!(!InternalPackages::default().is_internal("com.myorg.tools:cli:1.2.0"));
+    }
+}
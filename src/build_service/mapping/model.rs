@@ -15,18 +15,110 @@
 */
 
 use crate::artifact_service::model::PackageType;
+use crate::build_service::model::BuildSource;
 
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Deserialize, Serialize, Eq, PartialEq)]
+/// The repository that holds the sources of a package. Besides its primary
+/// url, a git repository can declare equivalent mirrors that the pipeline
+/// falls back to, and pin the commit that the tag must resolve to.
+#[derive(Clone, Debug, Deserialize, Serialize, Eq, PartialEq)]
 pub enum SourceRepository {
-    Git { url: String, tag: String },
+    Git {
+        url: String,
+        tag: String,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        mirrors: Vec<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        commit: Option<String>,
+    },
 }
 
-#[derive(Debug, Deserialize, Serialize, Eq, PartialEq)]
+impl SourceRepository {
+    /// Verify that the source that the pipeline used for a build is one of
+    /// the declared source urls and matches the pinned commit.
+    pub fn verify(&self, used_source: Option<&BuildSource>) -> Result<(), String> {
+        match self {
+            SourceRepository::Git {
+                url,
+                mirrors,
+                commit,
+                ..
+            } => {
+                let used_source = match (used_source, commit) {
+                    (Some(used_source), _) => used_source,
+                    (None, Some(_)) => {
+                        return Err("Pipeline did not report the source of the build".to_owned())
+                    }
+                    (None, None) => return Ok(()),
+                };
+
+                if used_source.url != *url && !mirrors.contains(&used_source.url) {
+                    return Err(format!("Build used undeclared source {}", used_source.url));
+                }
+
+                match commit {
+                    Some(commit) if *commit != used_source.commit => Err(format!(
+                        "Source {} resolved to commit {} instead of pinned commit {}",
+                        used_source.url, used_source.commit, commit
+                    )),
+                    _ => Ok(()),
+                }
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, Eq, PartialEq)]
 pub struct MappingInfo {
     pub package_type: PackageType,
     pub package_specific_id: String,
     pub source_repository: Option<SourceRepository>,
     pub build_spec_url: Option<String>,
 }
+
+#[cfg(test)]
+#[cfg(not(tarpaulin_include))]
+mod tests {
+    use super::*;
+
+    fn source_repository(commit: Option<&str>) -> SourceRepository {
+        SourceRepository::Git {
+            url: "https://github.com/apache/maven".to_owned(),
+            tag: "maven-3.8.6".to_owned(),
+            mirrors: vec!["https://gitbox.apache.org/repos/asf/maven.git".to_owned()],
+            commit: commit.map(str::to_owned),
+        }
+    }
+
+    fn build_source(url: &str, commit: &str) -> BuildSource {
+        BuildSource {
+            url: url.to_owned(),
+            commit: commit.to_owned(),
+        }
+    }
+
+    #[test]
+    fn test_verify_source_from_mirror() {
+        let used_source = build_source("https://gitbox.apache.org/repos/asf/maven.git", "abc123");
+        assert!(source_repository(Some("abc123"))
+            .verify(Some(&used_source))
+            .is_ok());
+    }
+
+    #[test]
+    fn test_verify_undeclared_source() {
+        let used_source = build_source("https://example.com/maven.git", "abc123");
+        assert!(source_repository(None).verify(Some(&used_source)).is_err());
+    }
+
+    #[test]
+    fn test_verify_pinned_commit() {
+        let used_source = build_source("https://github.com/apache/maven", "def456");
+        assert!(source_repository(Some("abc123"))
+            .verify(Some(&used_source))
+            .is_err());
+        assert!(source_repository(Some("abc123")).verify(None).is_err());
+        assert!(source_repository(None).verify(None).is_ok());
+    }
+}
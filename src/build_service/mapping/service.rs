@@ -14,13 +14,20 @@
    limitations under the License.
 */
 
+use super::internal::InternalPackages;
 use super::model::MappingInfo;
 use crate::artifact_service::model::PackageType;
 use crate::build_service::error::BuildError;
 
+/// The mapping service looks up the source of packages in the public
+/// mapping repository, except for internal packages: these are only looked
+/// up in the internal mapping repository to protect them from dependency
+/// confusion.
 #[derive(Clone)]
 pub struct MappingService {
     mapping_service_endpoint: String,
+    internal_packages: InternalPackages,
+    internal_mapping_service_endpoint: Option<String>,
 }
 
 fn remove_last_character(mut string: String) -> String {
@@ -28,21 +35,47 @@ fn remove_last_character(mut string: String) -> String {
     string
 }
 
+fn normalize_endpoint(endpoint: &str) -> String {
+    match endpoint.ends_with('/') {
+        true => remove_last_character(endpoint.to_owned()),
+        false => endpoint.to_owned(),
+    }
+}
+
 impl MappingService {
     pub fn new(mapping_service_endpoint: &str) -> Self {
         MappingService {
-            mapping_service_endpoint: match mapping_service_endpoint.ends_with('/') {
-                true => remove_last_character(mapping_service_endpoint.to_owned()),
-                false => mapping_service_endpoint.to_owned(),
-            },
+            mapping_service_endpoint: normalize_endpoint(mapping_service_endpoint),
+            internal_packages: Default::default(),
+            internal_mapping_service_endpoint: None,
         }
     }
 
+    /// Set the internal packages and the endpoint of the internal mapping
+    /// repository. Without an internal endpoint, builds for internal
+    /// packages are always refused.
+    pub fn with_internal_packages(
+        mut self,
+        internal_packages: InternalPackages,
+        internal_mapping_service_endpoint: Option<&str>,
+    ) -> Self {
+        self.internal_packages = internal_packages;
+        self.internal_mapping_service_endpoint =
+            internal_mapping_service_endpoint.map(normalize_endpoint);
+        self
+    }
+
     pub async fn get_mapping(
         &self,
         package_type: PackageType,
         package_specific_id: &str,
     ) -> Result<MappingInfo, BuildError> {
+        if self.internal_packages.is_internal(package_specific_id) {
+            return self
+                .get_internal_mapping(package_type, package_specific_id)
+                .await;
+        }
+
         match package_type {
             PackageType::Docker => Ok(MappingInfo {
                 package_type,
@@ -58,37 +91,94 @@ impl MappingService {
         &self,
         package_specific_id: &str,
     ) -> Result<MappingInfo, BuildError> {
-        let package_specific_pieces: Vec<&str> = package_specific_id.split(':').collect();
-
-        let group_id = package_specific_pieces[0].replace('.', "/");
-        let artifact_id = package_specific_pieces[1];
-        let version = package_specific_pieces[2];
-
         let remote_mapping_url = format!(
-            "{}/Maven2/{}/{}/{}/{}-{}.mapping",
-            self.mapping_service_endpoint, group_id, artifact_id, version, artifact_id, version
+            "{}/{}",
+            self.mapping_service_endpoint,
+            maven_mapping_path(package_specific_id)
         );
 
-        let client = reqwest::Client::new();
-        let response = client
-            .get(remote_mapping_url)
-            .send()
-            .await
-            .map_err(|e| BuildError::MappingServiceEndpointRequestFailure(e.to_string()))?;
-
-        if response.status().is_success() {
-            response
-                .json::<MappingInfo>()
-                .await
-                .map_err(|e| BuildError::InvalidMappingResponse(e.to_string()))
-        } else if response.status() == hyper::StatusCode::NOT_FOUND {
-            Err(BuildError::MappingNotFound {
+        fetch_mapping(remote_mapping_url)
+            .await?
+            .ok_or_else(|| BuildError::MappingNotFound {
                 package_type: PackageType::Maven2,
                 package_specific_id: package_specific_id.to_owned(),
             })
-        } else {
-            Err(BuildError::MappingServiceEndpointFailure(response.status()))
-        }
+    }
+
+    // Internal packages are never looked up in the public mapping repository,
+    // and docker images are not pulled from a public registry by default.
+    async fn get_internal_mapping(
+        &self,
+        package_type: PackageType,
+        package_specific_id: &str,
+    ) -> Result<MappingInfo, BuildError> {
+        let dependency_confusion = |reason: &str| BuildError::DependencyConfusion {
+            package_type,
+            package_specific_id: package_specific_id.to_owned(),
+            reason: reason.to_owned(),
+        };
+
+        let internal_mapping_service_endpoint = self
+            .internal_mapping_service_endpoint
+            .as_ref()
+            .ok_or_else(|| dependency_confusion("no internal mapping source is configured"))?;
+        let mapping_path = match package_type {
+            PackageType::Docker => docker_mapping_path(package_specific_id),
+            PackageType::Maven2 => maven_mapping_path(package_specific_id),
+        };
+
+        fetch_mapping(format!(
+            "{}/{}",
+            internal_mapping_service_endpoint, mapping_path
+        ))
+        .await?
+        .ok_or_else(|| dependency_confusion("it is not mapped by the internal mapping source"))
+    }
+}
+
+fn maven_mapping_path(package_specific_id: &str) -> String {
+    let package_specific_pieces: Vec<&str> = package_specific_id.split(':').collect();
+
+    let group_id = package_specific_pieces[0].replace('.', "/");
+    let artifact_id = package_specific_pieces[1];
+    let version = package_specific_pieces[2];
+
+    format!(
+        "Maven2/{}/{}/{}/{}-{}.mapping",
+        group_id, artifact_id, version, artifact_id, version
+    )
+}
+
+// An image is mapped by its name and its tag or digest, like
+// `Docker/myorg/app/1.0.mapping`.
+fn docker_mapping_path(package_specific_id: &str) -> String {
+    let (name, reference) = package_specific_id
+        .split_once('@')
+        .or_else(|| package_specific_id.rsplit_once(':'))
+        .unwrap_or((package_specific_id, "latest"));
+
+    format!("Docker/{}/{}.mapping", name, reference)
+}
+
+// Fetch the mapping at `mapping_url`, which is `None` if there is no mapping.
+async fn fetch_mapping(mapping_url: String) -> Result<Option<MappingInfo>, BuildError> {
+    let client = reqwest::Client::new();
+    let response = client
+        .get(mapping_url)
+        .send()
+        .await
+        .map_err(|e| BuildError::MappingServiceEndpointRequestFailure(e.to_string()))?;
+
+    if response.status().is_success() {
+        response
+            .json::<MappingInfo>()
+            .await
+            .map(Some)
+            .map_err(|e| BuildError::InvalidMappingResponse(e.to_string()))
+    } else if response.status() == hyper::StatusCode::NOT_FOUND {
+        Ok(None)
+    } else {
+        Err(BuildError::MappingServiceEndpointFailure(response.status()))
     }
 }
 
@@ -152,7 +242,9 @@ mod tests {
             package_specific_id: "org.apache.maven:maven:3.8.6".to_owned(),
             source_repository: Some(SourceRepository::Git {
                 url: "https://github.com/apache/maven".to_owned(),
-                tag: "maven-3.8.6".to_owned()
+                tag: "maven-3.8.6".to_owned(),
+                mirrors: vec![],
+                commit: None,
             }),
             build_spec_url: Some("https://raw.githubusercontent.com/pyrsia/pyrsia-mappings/main/Maven2/org/apache/maven/maven/3.8.6/maven-3.8.6.buildspec".to_owned()),
         };
@@ -255,6 +347,93 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn internal_mapping_info() {
+        let mapping_info = MappingInfo {
+            package_type: PackageType::Maven2,
+            package_specific_id: "com.myorg:tools:1.0".to_owned(),
+            source_repository: Some(SourceRepository::Git {
+                url: "https://git.myorg.com/tools".to_owned(),
+                tag: "tools-1.0".to_owned(),
+                mirrors: vec![],
+                commit: None,
+            }),
+            build_spec_url: None,
+        };
+
+        // the public mapping repository must never be asked for internal packages
+        let public_server = Server::run();
+        let internal_server = Server::run();
+        internal_server.expect(
+            Expectation::matching(matchers::request::method_path(
+                "GET",
+                "/Maven2/com/myorg/tools/1.0/tools-1.0.mapping",
+            ))
+            .respond_with(responders::json_encoded(&mapping_info)),
+        );
+        internal_server.expect(
+            Expectation::matching(matchers::request::method_path(
+                "GET",
+                "/Docker/myorg/app/1.0.mapping",
+            ))
+            .respond_with(responders::status_code(404)),
+        );
+
+        let mapping_service = MappingService::new(&public_server.url("/").to_string())
+            .with_internal_packages(
+                InternalPackages::new(&["com.myorg:*", "myorg/*"]),
+                Some(&internal_server.url("/").to_string()),
+            );
+
+        assert_eq!(
+            mapping_service
+                .get_mapping(PackageType::Maven2, "com.myorg:tools:1.0")
+                .await
+                .unwrap(),
+            mapping_info
+        );
+        assert_eq!(
+            mapping_service
+                .get_mapping(PackageType::Docker, "myorg/app:1.0")
+                .await
+                .unwrap_err(),
+            BuildError::DependencyConfusion {
+                package_type: PackageType::Docker,
+                package_specific_id: "myorg/app:1.0".to_owned(),
+                reason: "it is not mapped by the internal mapping source".to_owned(),
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn internal_mapping_without_internal_endpoint() {
+        let public_server = Server::run();
+        let mapping_service = MappingService::new(&public_server.url("/").to_string())
+            .with_internal_packages(InternalPackages::new(&["com.myorg:*"]), None);
+
+        let error = mapping_service
+            .get_mapping(PackageType::Maven2, "com.myorg:tools:1.0")
+            .await
+            .unwrap_err();
+        assert!(matches!(error, BuildError::DependencyConfusion { .. }));
+    }
+
+    #[test]
+    fn docker_mapping_paths() {
+        assert_eq!(
+            docker_mapping_path("myorg/app:1.0"),
+            "Docker/myorg/app/1.0.mapping"
+        );
+        assert_eq!(
+            docker_mapping_path("myorg/app@sha256:abc"),
+            "Docker/myorg/app/sha256:abc.mapping"
+        );
+        assert_eq!(
+            docker_mapping_path("myorg/app"),
+            "Docker/myorg/app/latest.mapping"
+        );
+    }
+
     #[tokio::test]
     #[should_panic(expected = "MappingServiceEndpointRequestFailure")]
     async fn maven_mapping_http_error() {
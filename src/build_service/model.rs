@@ -15,39 +15,322 @@
 */
 
 use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::iter;
 use std::path::PathBuf;
 
+use super::pinning::PinnedDependency;
 use crate::artifact_service::model::PackageType;
 
 #[derive(Clone, Debug, Deserialize, Serialize, Eq, PartialEq)]
 pub enum BuildStatus {
+    /// The build waits for a slot of the build queue, `position` 1 is the
+    /// next build to start.
+    Queued {
+        position: usize,
+    },
     Running,
-    Success { artifact_urls: Vec<String> },
+    Success {
+        artifact_urls: Vec<String>,
+    },
+    /// The build finished, but some of its artifacts could not be produced.
+    PartialSuccess {
+        artifact_urls: Vec<String>,
+        failed_artifacts: Vec<BuildArtifactFailure>,
+    },
     Failure(String),
 }
 
+/// An artifact of a build that could not be produced or retrieved.
+#[derive(Clone, Debug, Deserialize, Serialize, Eq, PartialEq)]
+pub struct BuildArtifactFailure {
+    pub artifact: String,
+    pub error: String,
+}
+
+impl fmt::Display for BuildArtifactFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.artifact, self.error)
+    }
+}
+
+/// Decides what happens with a build result of which some artifacts failed.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum PartialBuildPolicy {
+    /// The whole build is considered failed and nothing is published.
+    #[default]
+    Reject,
+    /// The artifacts that were produced successfully are published.
+    Publish,
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize, Eq, PartialEq)]
 pub struct BuildInfo {
     pub id: String,
     pub status: BuildStatus,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<BuildSource>,
+    /// The classification of a failed build, when the pipeline knows it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub failure_kind: Option<BuildFailureKind>,
+    /// The (possibly truncated) log output of the build.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub log: Option<String>,
+    /// The exact dependencies that the build resolved, e.g. the maven
+    /// dependency tree or the digest of the docker base image.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub dependencies: Vec<PinnedDependency>,
+}
+
+impl BuildInfo {
+    /// Returns the classified root cause of the build when it failed.
+    pub fn failure(&self) -> Option<BuildFailure> {
+        match &self.status {
+            BuildStatus::Failure(message) => {
+                let log_tail = log_tail(self.log.as_deref().unwrap_or_default());
+                let kind = self
+                    .failure_kind
+                    .unwrap_or_else(|| BuildFailureKind::classify(message, &log_tail));
+                Some(BuildFailure {
+                    kind,
+                    message: message.clone(),
+                    log_tail,
+                })
+            }
+            _ => None,
+        }
+    }
+}
+
+/// The maximum number of log lines that are kept with a build failure.
+pub const LOG_TAIL_LINES: usize = 20;
+
+fn log_tail(log: &str) -> Vec<String> {
+    let lines = log
+        .lines()
+        .map(str::trim_end)
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>();
+    lines[lines.len().saturating_sub(LOG_TAIL_LINES)..]
+        .iter()
+        .map(|line| line.to_string())
+        .collect()
+}
+
+/// The root cause of a failed build.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, Eq, PartialEq, Hash, Ord, PartialOrd)]
+#[serde(rename_all = "snake_case")]
+pub enum BuildFailureKind {
+    /// The sources of the package could not be fetched or verified.
+    SourceFetch,
+    /// The sources of the package did not compile.
+    Compile,
+    /// The package was compiled, but its tests failed.
+    TestFailure,
+    /// The build infrastructure itself failed, e.g. the pipeline was unreachable.
+    PipelineInfrastructure,
+    /// The build did not finish in time.
+    Timeout,
+    /// The failure could not be classified.
+    Unknown,
+}
+
+impl BuildFailureKind {
+    /// Classify a failure based on its message and, when that is
+    /// inconclusive, on the last lines of the build log.
+    pub fn classify(message: &str, log_tail: &[String]) -> Self {
+        iter::once(message)
+            .chain(log_tail.iter().rev().map(String::as_str))
+            .map(Self::classify_line)
+            .find(|kind| *kind != BuildFailureKind::Unknown)
+            .unwrap_or(BuildFailureKind::Unknown)
+    }
+
+    fn classify_line(line: &str) -> Self {
+        let line = line.to_lowercase();
+        let contains_any = |needles: &[&str]| needles.iter().any(|needle| line.contains(needle));
+        if contains_any(&["timed out", "timeout", "deadline exceeded"]) {
+            BuildFailureKind::Timeout
+        } else if contains_any(&[
+            "clone",
+            "checkout",
+            "could not resolve host",
+            "repository not found",
+            "undeclared source",
+            "source of the build",
+            "commit",
+        ]) {
+            BuildFailureKind::SourceFetch
+        } else if contains_any(&[
+            "test failure",
+            "tests failed",
+            "test failed",
+            "failed tests",
+        ]) {
+            BuildFailureKind::TestFailure
+        } else if contains_any(&["compil", "syntax error", "cannot find symbol"]) {
+            BuildFailureKind::Compile
+        } else if contains_any(&[
+            "pipeline",
+            "out of memory",
+            "no space left",
+            "connection refused",
+            "failed to connect",
+        ]) {
+            BuildFailureKind::PipelineInfrastructure
+        } else {
+            BuildFailureKind::Unknown
+        }
+    }
+}
+
+impl fmt::Display for BuildFailureKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            BuildFailureKind::SourceFetch => "Source fetch error",
+            BuildFailureKind::Compile => "Compile error",
+            BuildFailureKind::TestFailure => "Test failure",
+            BuildFailureKind::PipelineInfrastructure => "Pipeline infrastructure error",
+            BuildFailureKind::Timeout => "Timeout",
+            BuildFailureKind::Unknown => "Error",
+        })
+    }
 }
 
-#[derive(Debug)]
+/// A classified build failure together with the last lines of the build log.
+#[derive(Clone, Debug, Deserialize, Serialize, Eq, PartialEq)]
+pub struct BuildFailure {
+    pub kind: BuildFailureKind,
+    pub message: String,
+    #[serde(default)]
+    pub log_tail: Vec<String>,
+}
+
+impl fmt::Display for BuildFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.kind, self.message)
+    }
+}
+
+/// The source that the pipeline actually fetched for a build.
+#[derive(Clone, Debug, Deserialize, Serialize, Eq, PartialEq)]
+pub struct BuildSource {
+    pub url: String,
+    pub commit: String,
+}
+
+/// The output of a finished build as reported by the pipeline.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct BuildOutput {
+    pub artifact_urls: Vec<String>,
+    pub failed_artifacts: Vec<BuildArtifactFailure>,
+    pub source: Option<BuildSource>,
+    pub dependencies: Vec<PinnedDependency>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct BuildResultArtifact {
     pub artifact_specific_id: String,
     pub artifact_location: PathBuf,
     pub artifact_hash: String,
 }
 
-#[derive(Debug)]
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
 pub enum BuildTrigger {
     FromSource,
     Verification,
+    /// A rebuild with the dependencies of a pinning snapshot, to check that
+    /// a published build can be reproduced exactly.
+    Reproduction,
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct BuildResult {
     pub package_type: PackageType,
     pub package_specific_id: String,
     pub artifacts: Vec<BuildResultArtifact>,
+    #[serde(default)]
+    pub failed_artifacts: Vec<BuildArtifactFailure>,
+    #[serde(default)]
+    pub source: Option<BuildSource>,
+    /// The id of the pinning snapshot of the dependencies of the build.
+    #[serde(default)]
+    pub pinning_snapshot: Option<String>,
+}
+
+#[cfg(test)]
+#[cfg(not(tarpaulin_include))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classify_failure_message() {
+        assert_eq!(
+            BuildFailureKind::classify("fatal: repository not found", &[]),
+            BuildFailureKind::SourceFetch
+        );
+        assert_eq!(
+            BuildFailureKind::classify("Build step timed out after 3600s", &[]),
+            BuildFailureKind::Timeout
+        );
+        assert_eq!(
+            BuildFailureKind::classify("COMPILATION ERROR", &[]),
+            BuildFailureKind::Compile
+        );
+        assert_eq!(
+            BuildFailureKind::classify("exit code 1", &[]),
+            BuildFailureKind::Unknown
+        );
+    }
+
+    #[test]
+    fn classify_failure_uses_last_relevant_log_line() {
+        let log_tail = vec![
+            "[ERROR] Failed to compile test sources".to_owned(),
+            "[ERROR] There are test failures.".to_owned(),
+            "[INFO] BUILD FAILURE".to_owned(),
+        ];
+
+        assert_eq!(
+            BuildFailureKind::classify("exit code 1", &log_tail),
+            BuildFailureKind::TestFailure
+        );
+    }
+
+    #[test]
+    fn build_info_failure_keeps_log_tail() {
+        let log = (0..30).map(|i| format!("line {}\n", i)).collect::<String>();
+        let build_info = BuildInfo {
+            id: "build_id".to_owned(),
+            status: BuildStatus::Failure("exit code 1".to_owned()),
+            source: None,
+            failure_kind: Some(BuildFailureKind::PipelineInfrastructure),
+            log: Some(log),
+            dependencies: vec![],
+        };
+
+        let failure = build_info.failure().unwrap();
+        assert_eq!(failure.kind, BuildFailureKind::PipelineInfrastructure);
+        assert_eq!(failure.log_tail.len(), LOG_TAIL_LINES);
+        assert_eq!(failure.log_tail.last().unwrap(), "line 29");
+        assert_eq!(
+            failure.to_string(),
+            "Pipeline infrastructure error: exit code 1"
+        );
+    }
+
+    #[test]
+    fn build_info_without_failure() {
+        let build_info = BuildInfo {
+            id: "build_id".to_owned(),
+            status: BuildStatus::Running,
+            source: None,
+            failure_kind: None,
+            log: Some("line".to_owned()),
+            dependencies: vec![],
+        };
+
+        assert_eq!(build_info.failure(), None);
+    }
 }
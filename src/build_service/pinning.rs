@@ -0,0 +1,195 @@
+/*
+   Copyright 2021 JFrog Ltd
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+*/
+
+use super::model::BuildSource;
+use crate::artifact_service::model::PackageType;
+use log::debug;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A dependency of a build, resolved to an exact version, e.g. a maven
+/// dependency `org.slf4j:slf4j-api:2.0.6` or a docker base image
+/// `library/alpine:3.17`, together with the digest of what was used.
+#[derive(Clone, Debug, Deserialize, Serialize, Eq, PartialEq, Ord, PartialOrd)]
+pub struct PinnedDependency {
+    pub coordinates: String,
+    pub digest: String,
+}
+
+/// The exact dependencies that a build resolved, as reported by the
+/// pipeline, together with the source and build they came from. The
+/// transparency logs of the artifacts of the build refer to the snapshot by
+/// its [id](PinningSnapshot::id), so that the build can be reproduced with
+/// the same dependencies later.
+#[derive(Clone, Debug, Deserialize, Serialize, Eq, PartialEq)]
+pub struct PinningSnapshot {
+    pub build_id: String,
+    pub package_type: PackageType,
+    pub package_specific_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<BuildSource>,
+    pub dependencies: Vec<PinnedDependency>,
+    /// The time the snapshot was taken, in seconds since the unix epoch.
+    pub created_at: u64,
+}
+
+impl PinningSnapshot {
+    pub fn new(
+        build_id: &str,
+        package_type: PackageType,
+        package_specific_id: &str,
+        source: Option<BuildSource>,
+        mut dependencies: Vec<PinnedDependency>,
+    ) -> Self {
+        dependencies.sort();
+        dependencies.dedup();
+        PinningSnapshot {
+            build_id: build_id.to_owned(),
+            package_type,
+            package_specific_id: package_specific_id.to_owned(),
+            source,
+            dependencies,
+            created_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap()
+                .as_secs(),
+        }
+    }
+
+    /// The hex encoded sha256 hash of the JSON encoding of the snapshot.
+    pub fn id(&self) -> String {
+        let json = serde_json::to_vec(self).expect("a pinning snapshot is always serializable");
+        hex::encode(Sha256::digest(json))
+    }
+}
+
+/// Keeps the pinning snapshots of the builds of a node, each in a JSON file
+/// named after its id.
+#[derive(Clone)]
+pub struct PinningSnapshotStore {
+    path: PathBuf,
+}
+
+impl PinningSnapshotStore {
+    pub fn new<P: AsRef<Path>>(path: P) -> Self {
+        PinningSnapshotStore {
+            path: path.as_ref().to_path_buf(),
+        }
+    }
+
+    /// Store `pinning_snapshot` and return its id.
+    pub fn store(&self, pinning_snapshot: &PinningSnapshot) -> io::Result<String> {
+        let id = pinning_snapshot.id();
+        fs::create_dir_all(&self.path)?;
+        fs::write(
+            self.path.join(format!("{}.json", id)),
+            serde_json::to_vec(pinning_snapshot)?,
+        )?;
+
+        debug!(
+            "Stored pinning snapshot {} of build {}",
+            id, pinning_snapshot.build_id
+        );
+        Ok(id)
+    }
+
+    /// Load the pinning snapshot with the specified `id`, and verify that
+    /// its content still matches the id.
+    pub fn load(&self, id: &str) -> io::Result<PinningSnapshot> {
+        if id.is_empty() || !id.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("Invalid pinning snapshot id {}", id),
+            ));
+        }
+
+        let json = fs::read(self.path.join(format!("{}.json", id)))?;
+        let pinning_snapshot: PinningSnapshot = serde_json::from_slice(&json)?;
+        if pinning_snapshot.id() != id {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Pinning snapshot {} was modified", id),
+            ));
+        }
+        Ok(pinning_snapshot)
+    }
+}
+
+#[cfg(test)]
+#[cfg(not(tarpaulin_include))]
+mod tests {
+    use super::*;
+    use crate::util::test_util;
+
+    fn dependency(coordinates: &str, digest: &str) -> PinnedDependency {
+        PinnedDependency {
+            coordinates: coordinates.to_owned(),
+            digest: digest.to_owned(),
+        }
+    }
+
+    #[test]
+    fn test_store_and_load() {
+        let tmp_dir = test_util::tests::setup();
+
+        let pinning_snapshot = PinningSnapshot::new(
+            "build_id",
+            PackageType::Maven2,
+            "com.company:test:1.0",
+            None,
+            vec![
+                dependency("org.slf4j:slf4j-api:2.0.6", "sha256:bbbb"),
+                dependency("com.google.guava:guava:31.1-jre", "sha256:aaaa"),
+                dependency("org.slf4j:slf4j-api:2.0.6", "sha256:bbbb"),
+            ],
+        );
+        assert_eq!(pinning_snapshot.dependencies.len(), 2);
+        assert_eq!(
+            pinning_snapshot.dependencies[0].coordinates,
+            "com.google.guava:guava:31.1-jre"
+        );
+
+        let store = PinningSnapshotStore::new(tmp_dir.join("pinning"));
+        let id = store.store(&pinning_snapshot).unwrap();
+        assert_eq!(id, pinning_snapshot.id());
+        assert_eq!(store.load(&id).unwrap(), pinning_snapshot);
+
+        assert_eq!(
+            store.load("../history").unwrap_err().kind(),
+            io::ErrorKind::NotFound
+        );
+
+        let modified = PinningSnapshot {
+            build_id: "other_build_id".to_owned(),
+            ..pinning_snapshot
+        };
+        fs::write(
+            tmp_dir.join("pinning").join(format!("{}.json", id)),
+            serde_json::to_vec(&modified).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            store.load(&id).unwrap_err().kind(),
+            io::ErrorKind::InvalidData
+        );
+
+        test_util::tests::teardown(tmp_dir);
+    }
+}
@@ -14,14 +14,47 @@
    limitations under the License.
 */
 
+use crate::artifact_service::model::PackageType;
 use crate::build_service::error::BuildError;
 use crate::build_service::mapping::model::MappingInfo;
 use crate::build_service::model::BuildInfo;
+use crate::build_service::pinning::PinnedDependency;
+use crate::build_service::secrets::Secret;
+use log::warn;
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use reqwest::{RequestBuilder, Response, StatusCode};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// The number of times a request is retried when the pipeline reports that
+/// it is rate limited, e.g. because it hit the Docker Hub pull rate limit.
+pub const MAX_RATE_LIMIT_RETRIES: u32 = 3;
+const DEFAULT_RATE_LIMIT_DELAY: Duration = Duration::from_secs(30);
+const MAX_RATE_LIMIT_DELAY: Duration = Duration::from_secs(300);
+const RATE_LIMIT_RESET: &str = "ratelimit-reset";
+
+// The request to start a build. Secrets are only included when the build
+// needs them, pinned dependencies only when the build reproduces an earlier
+// build with exactly the same dependencies and the base image cache only for
+// docker builds.
+#[derive(Serialize)]
+struct StartBuildRequest<'a> {
+    #[serde(flatten)]
+    mapping_info: &'a MappingInfo,
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    secrets: &'a HashMap<String, Secret>,
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    pinned_dependencies: &'a [PinnedDependency],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    base_image_cache: Option<&'a str>,
+}
 
 #[derive(Clone)]
 pub struct PipelineService {
     http_client: reqwest::Client,
     pipeline_service_endpoint: String,
+    base_image_cache: Option<String>,
 }
 
 fn remove_last_character(mut string: String) -> String {
@@ -29,6 +62,50 @@ fn remove_last_character(mut string: String) -> String {
     string
 }
 
+// The time to wait before retrying a rate limited request, based on the
+// Retry-After or RateLimit-Reset response headers.
+fn rate_limit_delay(headers: &HeaderMap) -> Duration {
+    [RETRY_AFTER.as_str(), RATE_LIMIT_RESET]
+        .iter()
+        .filter_map(|header| headers.get(*header))
+        .filter_map(|value| value.to_str().ok())
+        .filter_map(|value| value.trim().parse::<u64>().ok())
+        .map(Duration::from_secs)
+        .next()
+        .unwrap_or(DEFAULT_RATE_LIMIT_DELAY)
+        .min(MAX_RATE_LIMIT_DELAY)
+}
+
+// Send a request to the pipeline and retry it while the pipeline responds
+// with 429 Too Many Requests. This waits for the pipeline, so it must not be
+// awaited by the build event loop.
+async fn send_with_rate_limit_retry(
+    request_builder: impl Fn() -> RequestBuilder,
+) -> Result<Response, BuildError> {
+    let mut retries = 0;
+    loop {
+        let response = request_builder()
+            .send()
+            .await
+            .map_err(|e| BuildError::PipelineServiceEndpointRequestFailure(e.to_string()))?;
+
+        if response.status() != StatusCode::TOO_MANY_REQUESTS || retries == MAX_RATE_LIMIT_RETRIES {
+            return Ok(response);
+        }
+
+        retries += 1;
+        let delay = rate_limit_delay(response.headers());
+        warn!(
+            "Pipeline service is rate limited, retrying request to {} in {:?} (attempt {} of {})",
+            response.url(),
+            delay,
+            retries,
+            MAX_RATE_LIMIT_RETRIES
+        );
+        tokio::time::sleep(delay).await;
+    }
+}
+
 impl PipelineService {
     pub fn new(pipeline_service_endpoint: &str) -> Self {
         PipelineService {
@@ -37,21 +114,52 @@ impl PipelineService {
                 true => remove_last_character(pipeline_service_endpoint.to_owned()),
                 false => pipeline_service_endpoint.to_owned(),
             },
+            base_image_cache: None,
         }
     }
 
-    pub async fn start_build(&self, mapping_info: MappingInfo) -> Result<String, BuildError> {
+    /// Set the registry mirror that docker builds pull their base images
+    /// through. The mirror caches the images that it pulls from upstream, so
+    /// that all builds on this node share them instead of each pulling them
+    /// from Docker Hub again.
+    pub fn with_base_image_cache(mut self, base_image_cache: &str) -> Self {
+        self.base_image_cache = Some(base_image_cache.to_owned());
+        self
+    }
+
+    /// Ask the pipeline to start a build and return its build id. A request
+    /// that is rate limited is not retried, the error tells how long to wait
+    /// before starting the build again.
+    pub async fn start_build(
+        &self,
+        mapping_info: MappingInfo,
+        secrets: HashMap<String, Secret>,
+        pinned_dependencies: &[PinnedDependency],
+    ) -> Result<String, BuildError> {
         let start_build_endpoint = format!("{}/build", self.pipeline_service_endpoint);
+        let start_build_request = StartBuildRequest {
+            mapping_info: &mapping_info,
+            secrets: &secrets,
+            pinned_dependencies,
+            base_image_cache: match mapping_info.package_type {
+                PackageType::Docker => self.base_image_cache.as_deref(),
+                PackageType::Maven2 => None,
+            },
+        };
 
         let start_build_response = self
             .http_client
-            .put(start_build_endpoint)
-            .json(&mapping_info)
+            .put(&start_build_endpoint)
+            .json(&start_build_request)
             .send()
             .await
             .map_err(|e| BuildError::PipelineServiceEndpointRequestFailure(e.to_string()))?;
 
-        if start_build_response.status().is_success() {
+        if start_build_response.status() == StatusCode::TOO_MANY_REQUESTS {
+            Err(BuildError::PipelineRateLimited(rate_limit_delay(
+                start_build_response.headers(),
+            )))
+        } else if start_build_response.status().is_success() {
             start_build_response
                 .json::<String>()
                 .await
@@ -90,12 +198,9 @@ impl PipelineService {
         let download_artifact_endpoint =
             format!("{}{}", self.pipeline_service_endpoint, artifact_url);
 
-        let download_artifact_response = self
-            .http_client
-            .get(download_artifact_endpoint)
-            .send()
-            .await
-            .map_err(|e| BuildError::PipelineServiceEndpointRequestFailure(e.to_string()))?;
+        let download_artifact_response =
+            send_with_rate_limit_retry(|| self.http_client.get(&download_artifact_endpoint))
+                .await?;
 
         if download_artifact_response.status().is_success() {
             download_artifact_response
@@ -114,8 +219,7 @@ impl PipelineService {
 #[cfg(not(tarpaulin_include))]
 mod tests {
     use super::*;
-    use crate::artifact_service::model::PackageType;
-    use crate::build_service::model::BuildStatus;
+    use crate::build_service::model::{BuildFailureKind, BuildStatus};
     use httptest::{matchers, responders, Expectation, Server};
     use hyper::StatusCode;
 
@@ -164,10 +268,210 @@ mod tests {
 
         let pipeline_service = PipelineService::new(&http_server.url("/").to_string());
 
-        let build_id_result = pipeline_service.start_build(mapping_info).await.unwrap();
+        let build_id_result = pipeline_service
+            .start_build(mapping_info, HashMap::new(), &[])
+            .await
+            .unwrap();
         assert_eq!(build_id_result, build_id);
     }
 
+    #[tokio::test]
+    async fn start_build_with_secrets() {
+        let mapping_info = MappingInfo {
+            package_type: PackageType::Maven2,
+            package_specific_id: "com.myorg:internal:1.0".to_owned(),
+            source_repository: None,
+            build_spec_url: None,
+        };
+
+        let build_id = uuid::Uuid::new_v4().to_string();
+
+        let http_server = Server::run();
+        http_server.expect(
+            Expectation::matching(matchers::all_of!(
+                matchers::request::method_path("PUT", "/build"),
+                matchers::request::body(matchers::json_decoded(matchers::eq(serde_json::json!({
+                    "package_type": "Maven2",
+                    "package_specific_id": "com.myorg:internal:1.0",
+                    "source_repository": null,
+                    "build_spec_url": null,
+                    "secrets": { "GIT_TOKEN": "s3cr3t" }
+                }))))
+            ))
+            .respond_with(responders::json_encoded(&build_id)),
+        );
+
+        let pipeline_service = PipelineService::new(&http_server.url("/").to_string());
+
+        let secrets = HashMap::from([("GIT_TOKEN".to_owned(), Secret::new("s3cr3t"))]);
+        let build_id_result = pipeline_service
+            .start_build(mapping_info, secrets, &[])
+            .await
+            .unwrap();
+        assert_eq!(build_id_result, build_id);
+    }
+
+    #[tokio::test]
+    async fn start_build_with_pinned_dependencies() {
+        let mapping_info = MappingInfo {
+            package_type: PackageType::Docker,
+            package_specific_id: "alpine:3.15.2".to_owned(),
+            source_repository: None,
+            build_spec_url: None,
+        };
+
+        let build_id = uuid::Uuid::new_v4().to_string();
+
+        let http_server = Server::run();
+        http_server.expect(
+            Expectation::matching(matchers::all_of!(
+                matchers::request::method_path("PUT", "/build"),
+                matchers::request::body(matchers::json_decoded(matchers::eq(serde_json::json!({
+                    "package_type": "Docker",
+                    "package_specific_id": "alpine:3.15.2",
+                    "source_repository": null,
+                    "build_spec_url": null,
+                    "pinned_dependencies": [
+                        { "coordinates": "library/busybox:1.35", "digest": "sha256:aaaa" }
+                    ]
+                }))))
+            ))
+            .respond_with(responders::json_encoded(&build_id)),
+        );
+
+        let pipeline_service = PipelineService::new(&http_server.url("/").to_string());
+
+        let pinned_dependencies = vec![PinnedDependency {
+            coordinates: "library/busybox:1.35".to_owned(),
+            digest: "sha256:aaaa".to_owned(),
+        }];
+        let build_id_result = pipeline_service
+            .start_build(mapping_info, HashMap::new(), &pinned_dependencies)
+            .await
+            .unwrap();
+        assert_eq!(build_id_result, build_id);
+    }
+
+    #[tokio::test]
+    async fn start_build_with_base_image_cache() {
+        let build_id = uuid::Uuid::new_v4().to_string();
+
+        let http_server = Server::run();
+        http_server.expect(
+            Expectation::matching(matchers::all_of!(
+                matchers::request::method_path("PUT", "/build"),
+                matchers::request::body(matchers::json_decoded(matchers::eq(serde_json::json!({
+                    "package_type": "Docker",
+                    "package_specific_id": "alpine:3.15.2",
+                    "source_repository": null,
+                    "build_spec_url": null,
+                    "base_image_cache": "http://localhost:5000"
+                }))))
+            ))
+            .respond_with(responders::json_encoded(&build_id)),
+        );
+        // maven builds do not pull base images
+        http_server.expect(
+            Expectation::matching(matchers::all_of!(
+                matchers::request::method_path("PUT", "/build"),
+                matchers::request::body(matchers::json_decoded(matchers::eq(serde_json::json!({
+                    "package_type": "Maven2",
+                    "package_specific_id": "com.myorg:app:1.0",
+                    "source_repository": null,
+                    "build_spec_url": null
+                }))))
+            ))
+            .respond_with(responders::json_encoded(&build_id)),
+        );
+
+        let pipeline_service = PipelineService::new(&http_server.url("/").to_string())
+            .with_base_image_cache("http://localhost:5000");
+
+        for (package_type, package_specific_id) in [
+            (PackageType::Docker, "alpine:3.15.2"),
+            (PackageType::Maven2, "com.myorg:app:1.0"),
+        ] {
+            let mapping_info = MappingInfo {
+                package_type,
+                package_specific_id: package_specific_id.to_owned(),
+                source_repository: None,
+                build_spec_url: None,
+            };
+            let build_id_result = pipeline_service
+                .start_build(mapping_info, HashMap::new(), &[])
+                .await
+                .unwrap();
+            assert_eq!(build_id_result, build_id);
+        }
+    }
+
+    #[tokio::test]
+    async fn start_build_rate_limited() {
+        let mapping_info = MappingInfo {
+            package_type: PackageType::Docker,
+            package_specific_id: "alpine:3.15.2".to_owned(),
+            source_repository: None,
+            build_spec_url: None,
+        };
+
+        let http_server = Server::run();
+        http_server.expect(
+            Expectation::matching(matchers::request::method_path("PUT", "/build"))
+                .times(1)
+                .respond_with(responders::status_code(429).insert_header("retry-after", "7")),
+        );
+
+        let pipeline_service = PipelineService::new(&http_server.url("/").to_string());
+
+        let error = pipeline_service
+            .start_build(mapping_info, HashMap::new(), &[])
+            .await
+            .unwrap_err();
+        assert_eq!(
+            error,
+            BuildError::PipelineRateLimited(Duration::from_secs(7))
+        );
+    }
+
+    #[tokio::test]
+    async fn download_artifact_retries_when_rate_limited() {
+        let artifact_url = "/artifact.file";
+        let artifact_bytes = bytes::Bytes::from("some_bytes");
+
+        let http_server = Server::run();
+        http_server.expect(
+            Expectation::matching(matchers::request::method_path("GET", artifact_url))
+                .times(2)
+                .respond_with(responders::cycle![
+                    responders::status_code(429).insert_header("retry-after", "0"),
+                    responders::status_code(200).body(artifact_bytes.clone()),
+                ]),
+        );
+
+        let pipeline_service = PipelineService::new(&http_server.url("/").to_string());
+
+        let download_artifact_result = pipeline_service
+            .download_artifact(artifact_url)
+            .await
+            .unwrap();
+        assert_eq!(download_artifact_result, artifact_bytes);
+    }
+
+    #[test]
+    fn rate_limit_delay_from_headers() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(rate_limit_delay(&headers), DEFAULT_RATE_LIMIT_DELAY);
+
+        headers.insert(RATE_LIMIT_RESET, "12".parse().unwrap());
+        assert_eq!(rate_limit_delay(&headers), Duration::from_secs(12));
+
+        headers.insert(RETRY_AFTER, "5".parse().unwrap());
+        assert_eq!(rate_limit_delay(&headers), Duration::from_secs(5));
+
+        headers.insert(RETRY_AFTER, "86400".parse().unwrap());
+        assert_eq!(rate_limit_delay(&headers), MAX_RATE_LIMIT_DELAY);
+    }
+
     #[tokio::test]
     #[should_panic(expected = "InvalidPipelineResponse")]
     async fn start_build_invalid_response() {
@@ -192,7 +496,10 @@ mod tests {
 
         let pipeline_service = PipelineService::new(&http_server.url("/").to_string());
 
-        pipeline_service.start_build(mapping_info).await.unwrap();
+        pipeline_service
+            .start_build(mapping_info, HashMap::new(), &[])
+            .await
+            .unwrap();
     }
 
     #[tokio::test]
@@ -219,7 +526,7 @@ mod tests {
         let pipeline_service = PipelineService::new(&http_server.url("/").to_string());
 
         let error = pipeline_service
-            .start_build(mapping_info)
+            .start_build(mapping_info, HashMap::new(), &[])
             .await
             .unwrap_err();
         assert_eq!(
@@ -241,7 +548,10 @@ mod tests {
 
         let pipeline_service = PipelineService::new("");
 
-        pipeline_service.start_build(mapping_info).await.unwrap();
+        pipeline_service
+            .start_build(mapping_info, HashMap::new(), &[])
+            .await
+            .unwrap();
     }
 
     #[tokio::test]
@@ -251,6 +561,10 @@ mod tests {
         let build_info = BuildInfo {
             id: build_id.clone(),
             status: BuildStatus::Running,
+            source: None,
+            failure_kind: None,
+            log: None,
+            dependencies: vec![],
         };
 
         let http_server = Server::run();
@@ -268,6 +582,32 @@ mod tests {
         assert_eq!(build_info_result, build_info);
     }
 
+    #[tokio::test]
+    async fn get_build_status_failure_with_log() {
+        let build_id = uuid::Uuid::new_v4().to_string();
+
+        let http_server = Server::run();
+        http_server.expect(
+            Expectation::matching(matchers::request::method_path(
+                "GET",
+                format!("/build/{}", &build_id),
+            ))
+            .respond_with(responders::json_encoded(serde_json::json!({
+                "id": build_id,
+                "status": { "Failure": "mvn exited with code 1" },
+                "log": "[INFO] Building my-artifact\n[ERROR] Tests run: 3, Failures: 1\n[ERROR] There are test failures.\n"
+            }))),
+        );
+
+        let pipeline_service = PipelineService::new(&http_server.url("/").to_string());
+
+        let build_info_result = pipeline_service.get_build_status(&build_id).await.unwrap();
+        let failure = build_info_result.failure().unwrap();
+        assert_eq!(failure.kind, BuildFailureKind::TestFailure);
+        assert_eq!(failure.message, "mvn exited with code 1");
+        assert_eq!(failure.log_tail.len(), 3);
+    }
+
     #[tokio::test]
     #[should_panic(expected = "InvalidPipelineResponse")]
     async fn get_build_status_invalid_response() {
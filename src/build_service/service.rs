@@ -14,19 +14,37 @@
    limitations under the License.
 */
 
+use super::dead_letter::DeadLetterStore;
 use super::error::BuildError;
 use super::event::BuildEventClient;
+use super::history::{
+    BuildHistory, BuildHistoryQuery, BuildHistoryRetention, BuildOutcome, BuildRecord,
+};
+use super::mapping::internal::InternalPackages;
+use super::mapping::model::{MappingInfo, SourceRepository};
 use super::mapping::service::MappingService;
-use super::model::{BuildResult, BuildResultArtifact, BuildStatus, BuildTrigger};
-use super::pipeline::service::PipelineService;
+use super::model::{
+    BuildArtifactFailure, BuildFailure, BuildFailureKind, BuildOutput, BuildResult,
+    BuildResultArtifact, BuildStatus, BuildTrigger, PartialBuildPolicy,
+};
+use super::pinning::{PinnedDependency, PinningSnapshot, PinningSnapshotStore};
+use super::pipeline::service::{PipelineService, MAX_RATE_LIMIT_RETRIES};
+use super::queue::{BuildAdmission, BuildQueue, BuildSlot};
+use super::secrets::{Secret, SecretStore};
+use crate::accounting_service::service::{UsageAccounting, UsageSubject};
+use crate::alert_service::service::AlertService;
 use crate::artifact_service::model::PackageType;
 use crate::build_service::model::BuildInfo;
 use bytes::Buf;
+use itertools::Itertools;
 use log::{debug, error, warn};
 use multihash::Hasher;
+use std::collections::{BTreeMap, HashMap};
 use std::fs;
 use std::io;
 use std::path::{Path, PathBuf};
+use std::time::Duration;
+use uuid::Uuid;
 
 /// The build service is a component used by authorized nodes only. It is
 /// the entrypoint to the authorized node's build pipeline infrastructure.
@@ -36,6 +54,28 @@ pub struct BuildService {
     build_event_client: BuildEventClient,
     mapping_service: MappingService,
     pipeline_service: PipelineService,
+    dead_letter_store: DeadLetterStore,
+    pinning_snapshot_store: PinningSnapshotStore,
+    partial_build_policy: PartialBuildPolicy,
+    secret_store: Option<SecretStore>,
+    build_history: BuildHistory,
+    alert_service: Option<AlertService>,
+    usage_accounting: UsageAccounting,
+    build_queue: BuildQueue,
+}
+
+// A build whose mapping and secrets are resolved, which is started in the
+// pipeline right away or when it is its turn in the build queue.
+#[derive(Clone)]
+struct BuildRequest {
+    package_type: PackageType,
+    package_specific_id: String,
+    build_trigger: BuildTrigger,
+    requester: Option<String>,
+    previous_attempt: Option<String>,
+    mapping_info: MappingInfo,
+    secrets: HashMap<String, Secret>,
+    pinned_dependencies: Vec<PinnedDependency>,
 }
 
 impl BuildService {
@@ -46,32 +86,438 @@ impl BuildService {
         pipeline_service_endpoint: &str,
     ) -> Result<Self, anyhow::Error> {
         let repository_path = repository_path.as_ref().to_path_buf().canonicalize()?;
+        let dead_letter_store = DeadLetterStore::new(repository_path.join("builds").join("failed"));
+        let pinning_snapshot_store =
+            PinningSnapshotStore::new(repository_path.join("builds").join("pinning"));
+        let build_history = BuildHistory::new(repository_path.join("builds").join("history.json"))?;
         Ok(BuildService {
+            dead_letter_store,
+            pinning_snapshot_store,
             repository_path,
             build_event_client,
             mapping_service: MappingService::new(mapping_service_endpoint),
             pipeline_service: PipelineService::new(pipeline_service_endpoint),
+            partial_build_policy: PartialBuildPolicy::default(),
+            secret_store: None,
+            build_history,
+            alert_service: None,
+            usage_accounting: Default::default(),
+            build_queue: Default::default(),
         })
     }
 
-    /// Starts a new build for the specified package.
+    /// Set the store that provides the secrets that are passed to builds.
+    pub fn with_secret_store(mut self, secret_store: SecretStore) -> Self {
+        self.secret_store = Some(secret_store);
+        self
+    }
+
+    /// Set the internal packages that are protected from dependency
+    /// confusion: they are only built from the sources that the internal
+    /// mapping repository at `internal_mapping_service_endpoint` maps.
+    pub fn with_internal_packages(
+        mut self,
+        internal_packages: InternalPackages,
+        internal_mapping_service_endpoint: Option<&str>,
+    ) -> Self {
+        self.mapping_service = self
+            .mapping_service
+            .with_internal_packages(internal_packages, internal_mapping_service_endpoint);
+        self
+    }
+
+    /// Set the accounting that the build minutes of finished builds are
+    /// accounted to.
+    pub fn with_usage_accounting(mut self, usage_accounting: UsageAccounting) -> Self {
+        self.usage_accounting = usage_accounting;
+        self
+    }
+
+    /// Set the alert service that security events, like refused builds of
+    /// internal packages, are reported to.
+    pub fn with_alert_service(mut self, alert_service: AlertService) -> Self {
+        self.alert_service = Some(alert_service);
+        self
+    }
+
+    /// Set the build queue that limits the number of builds that run in the
+    /// pipeline at the same time.
+    pub fn with_build_queue(mut self, build_queue: BuildQueue) -> Self {
+        self.build_queue = build_queue;
+        self
+    }
+
+    /// Set the registry mirror that the pipeline pulls the base images of
+    /// docker builds through, which caches them for all builds on this node.
+    pub fn with_base_image_cache(mut self, base_image_cache: &str) -> Self {
+        self.pipeline_service = self
+            .pipeline_service
+            .with_base_image_cache(base_image_cache);
+        self
+    }
+
+    /// Set the retention policy that bounds the growth of the build history.
+    pub fn with_build_history_retention(mut self, retention: BuildHistoryRetention) -> Self {
+        self.build_history = self.build_history.with_retention(retention);
+        self
+    }
+
+    /// Set the policy that decides whether builds of which some artifacts
+    /// failed can still be published.
+    pub fn with_partial_build_policy(mut self, partial_build_policy: PartialBuildPolicy) -> Self {
+        self.partial_build_policy = partial_build_policy;
+        self
+    }
+
+    /// Starts a new build for the specified package. The `requester` is the
+    /// peer that requested the build, it is recorded in the build history.
     pub async fn start_build(
         &self,
         package_type: PackageType,
         package_specific_id: String,
         build_trigger: BuildTrigger,
+        requester: Option<String>,
+    ) -> Result<String, BuildError> {
+        self.start_build_attempt(
+            package_type,
+            package_specific_id,
+            build_trigger,
+            requester,
+            None,
+            None,
+        )
+        .await
+    }
+
+    /// Returns the build with ID `build_id` if it can be rerun: only failed
+    /// builds from source that were not rerun before can be rerun, so that
+    /// the attempts to build a package form a single chain.
+    pub fn get_build_to_rerun(&self, build_id: &str) -> Result<BuildRecord, BuildError> {
+        let build_record = self
+            .build_history
+            .get(build_id)
+            .ok_or_else(|| BuildError::BuildNotFound(build_id.to_owned()))?;
+
+        if build_record.trigger != BuildTrigger::FromSource {
+            return Err(BuildError::RerunNotAllowed(
+                build_id.to_owned(),
+                "only builds from source can be rerun".to_owned(),
+            ));
+        }
+        if build_record.outcome != BuildOutcome::Failure {
+            return Err(BuildError::RerunNotAllowed(
+                build_id.to_owned(),
+                format!("the build outcome is {}", build_record.outcome),
+            ));
+        }
+        if let Some(next_attempt) = self.build_history.get_next_attempt(build_id) {
+            return Err(BuildError::RerunNotAllowed(
+                build_id.to_owned(),
+                format!("it was already rerun as {}", next_attempt.build_id),
+            ));
+        }
+
+        Ok(build_record)
+    }
+
+    /// Starts a new build with the same parameters as a failed build, which
+    /// is linked to it in the build history.
+    pub async fn rerun_build(
+        &self,
+        build_record: BuildRecord,
+        requester: Option<String>,
+    ) -> Result<String, BuildError> {
+        self.start_build_attempt(
+            build_record.package_type,
+            build_record.package_specific_id,
+            build_record.trigger,
+            requester,
+            Some(&build_record.build_id),
+            None,
+        )
+        .await
+    }
+
+    /// Starts a new build of the package of a pinning snapshot, from the
+    /// same source commit and with exactly the same dependencies, to check
+    /// that the build can be reproduced. Its artifacts are not published.
+    pub async fn reproduce_build(
+        &self,
+        pinning_snapshot_id: &str,
+        requester: Option<String>,
+    ) -> Result<String, BuildError> {
+        let pinning_snapshot = self.get_pinning_snapshot(pinning_snapshot_id)?;
+        self.start_build_attempt(
+            pinning_snapshot.package_type,
+            pinning_snapshot.package_specific_id.clone(),
+            BuildTrigger::Reproduction,
+            requester,
+            None,
+            Some(&pinning_snapshot),
+        )
+        .await
+    }
+
+    /// Returns the stored pinning snapshot with the specified id.
+    pub fn get_pinning_snapshot(
+        &self,
+        pinning_snapshot_id: &str,
+    ) -> Result<PinningSnapshot, BuildError> {
+        self.pinning_snapshot_store
+            .load(pinning_snapshot_id)
+            .map_err(|e| match e.kind() {
+                io::ErrorKind::NotFound => {
+                    BuildError::PinningSnapshotNotFound(pinning_snapshot_id.to_owned())
+                }
+                _ => BuildError::InitializationFailed(e.to_string()),
+            })
+    }
+
+    // A refused build of an internal package is a security event.
+    fn refuse_internal_package(
+        &self,
+        package_type: PackageType,
+        package_specific_id: String,
+        requester: Option<&str>,
+        reason: String,
+    ) -> BuildError {
+        match &self.alert_service {
+            Some(alert_service) => {
+                alert_service.record_dependency_confusion(&package_specific_id, requester, &reason);
+            }
+            None => warn!(
+                "Security event: refused build of internal package {} requested by {}: {}",
+                package_specific_id,
+                requester.unwrap_or("this node"),
+                reason
+            ),
+        }
+
+        BuildError::DependencyConfusion {
+            package_type,
+            package_specific_id,
+            reason,
+        }
+    }
+
+    async fn start_build_attempt(
+        &self,
+        package_type: PackageType,
+        package_specific_id: String,
+        build_trigger: BuildTrigger,
+        requester: Option<String>,
+        previous_attempt: Option<&str>,
+        pinning_snapshot: Option<&PinningSnapshot>,
     ) -> Result<String, BuildError> {
         debug!(
             "Starting build for package type {:?} and specific ID {:}",
             package_type, package_specific_id
         );
 
-        let mapping_info = self
+        let mut mapping_info = match self
             .mapping_service
             .get_mapping(package_type, &package_specific_id)
-            .await?;
+            .await
+        {
+            Err(BuildError::DependencyConfusion { reason, .. }) => {
+                return Err(self.refuse_internal_package(
+                    package_type,
+                    package_specific_id,
+                    requester.as_deref(),
+                    reason,
+                ))
+            }
+            result => result?,
+        };
+
+        let secrets = match &self.secret_store {
+            Some(secret_store) => secret_store
+                .secrets_for(&package_specific_id)
+                .map_err(|e| BuildError::InitializationFailed(e.to_string()))?,
+            None => HashMap::new(),
+        };
+
+        // a reproduction must use the commit that the snapshot was built from
+        if let (
+            Some(SourceRepository::Git { commit, .. }),
+            Some(PinningSnapshot {
+                source: Some(source),
+                ..
+            }),
+        ) = (&mut mapping_info.source_repository, pinning_snapshot)
+        {
+            *commit = Some(source.commit.clone());
+        }
+        let pinned_dependencies = pinning_snapshot
+            .map(|pinning_snapshot| pinning_snapshot.dependencies.as_slice())
+            .unwrap_or_default();
+
+        let build_request = BuildRequest {
+            package_type,
+            package_specific_id,
+            build_trigger,
+            requester,
+            previous_attempt: previous_attempt.map(str::to_owned),
+            mapping_info,
+            secrets,
+            pinned_dependencies: pinned_dependencies.to_vec(),
+        };
+
+        let (build_id, build_slot_receiver) = match self.build_queue.start_or_enqueue() {
+            BuildAdmission::Started(build_slot) => {
+                return self
+                    .start_pipeline_build(None, &build_request, build_slot)
+                    .await;
+            }
+            BuildAdmission::Queued(build_id, build_slot_receiver) => {
+                (build_id, build_slot_receiver)
+            }
+        };
+        debug!(
+            "Queued build {} for package specific ID {}",
+            build_id, build_request.package_specific_id
+        );
+        let build_service = self.clone();
+        let queued_build_id = build_id.clone();
+        tokio::spawn(async move {
+            if let Ok(build_slot) = build_slot_receiver.await {
+                if let Err(build_error) = build_service
+                    .start_pipeline_build(Some(queued_build_id.clone()), &build_request, build_slot)
+                    .await
+                {
+                    // record the build, so that its failure can be reported
+                    build_service.record_build_started(&queued_build_id, &build_request);
+                    build_service
+                        .build_event_client
+                        .build_failed(&queued_build_id, build_error)
+                        .await;
+                }
+            }
+        });
+
+        Ok(build_id)
+    }
+
+    // Start a build in the pipeline and watch it until it finished, which
+    // frees its slot of the build queue. A queued build keeps the build id
+    // that it got from the queue, other builds have the id of the pipeline.
+    // A build that the pipeline refuses because it is rate limited is started
+    // again later, with a build id from the queue if it had none.
+    async fn start_pipeline_build(
+        &self,
+        queued_build_id: Option<String>,
+        build_request: &BuildRequest,
+        build_slot: BuildSlot,
+    ) -> Result<String, BuildError> {
+        match self
+            .pipeline_service
+            .start_build(
+                build_request.mapping_info.clone(),
+                build_request.secrets.clone(),
+                &build_request.pinned_dependencies,
+            )
+            .await
+        {
+            Ok(pipeline_build_id) => Ok(self.watch_pipeline_build(
+                queued_build_id,
+                pipeline_build_id,
+                build_request,
+                build_slot,
+            )),
+            Err(BuildError::PipelineRateLimited(delay)) => {
+                let build_id = queued_build_id.unwrap_or_else(|| Uuid::new_v4().to_string());
+                self.retry_rate_limited_build(
+                    build_id.clone(),
+                    build_request.clone(),
+                    build_slot,
+                    delay,
+                );
+                Ok(build_id)
+            }
+            Err(build_error) => Err(build_error),
+        }
+    }
+
+    // Start a rate limited build again once the delay that the pipeline asked
+    // for has passed, without keeping the build event loop waiting. The build
+    // keeps its slot meanwhile and is reported as queued.
+    fn retry_rate_limited_build(
+        &self,
+        build_id: String,
+        build_request: BuildRequest,
+        build_slot: BuildSlot,
+        mut delay: Duration,
+    ) {
+        self.build_queue.record_rate_limited(&build_id);
+        let build_service = self.clone();
+        tokio::spawn(async move {
+            let mut result = Err(BuildError::PipelineRateLimited(delay));
+            for attempt in 1..=MAX_RATE_LIMIT_RETRIES {
+                warn!(
+                    "Pipeline service is rate limited, starting build {} again in {:?} (attempt {} of {})",
+                    build_id, delay, attempt, MAX_RATE_LIMIT_RETRIES
+                );
+                tokio::time::sleep(delay).await;
+                result = build_service
+                    .pipeline_service
+                    .start_build(
+                        build_request.mapping_info.clone(),
+                        build_request.secrets.clone(),
+                        &build_request.pinned_dependencies,
+                    )
+                    .await;
+                match &result {
+                    Err(BuildError::PipelineRateLimited(next_delay)) => delay = *next_delay,
+                    _ => break,
+                }
+            }
+
+            match result {
+                Ok(pipeline_build_id) => {
+                    build_service.watch_pipeline_build(
+                        Some(build_id.clone()),
+                        pipeline_build_id,
+                        &build_request,
+                        build_slot,
+                    );
+                    build_service.build_queue.clear_rate_limited(&build_id);
+                }
+                Err(build_error) => {
+                    build_service.build_queue.clear_rate_limited(&build_id);
+                    // record the build, so that its failure can be reported
+                    build_service.record_build_started(&build_id, &build_request);
+                    build_service
+                        .build_event_client
+                        .build_failed(&build_id, build_error)
+                        .await;
+                }
+            }
+        });
+    }
+
+    // Watch a build that the pipeline started until it finished.
+    fn watch_pipeline_build(
+        &self,
+        queued_build_id: Option<String>,
+        pipeline_build_id: String,
+        build_request: &BuildRequest,
+        build_slot: BuildSlot,
+    ) -> String {
+        let build_id = match queued_build_id {
+            Some(build_id) => {
+                self.build_queue
+                    .record_pipeline_build_id(&build_id, &pipeline_build_id);
+                build_id
+            }
+            None => pipeline_build_id.clone(),
+        };
+        self.record_build_started(&build_id, build_request);
+
+        let package_type = build_request.package_type;
+        let package_specific_id = build_request.package_specific_id.clone();
+        let build_trigger = build_request.build_trigger;
+        let source_repository = build_request.mapping_info.source_repository.clone();
 
-        let build_id = self.pipeline_service.start_build(mapping_info).await?;
         let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(5));
         let pipeline_service = self.pipeline_service.clone();
         let build_event_client = self.build_event_client.clone();
@@ -80,34 +526,63 @@ impl BuildService {
             loop {
                 interval.tick().await;
 
-                match pipeline_service.get_build_status(&build_id).await {
+                match pipeline_service.get_build_status(&pipeline_build_id).await {
                     Ok(latest_build_info) => {
                         debug!("Updated build info: {:?}", &latest_build_info);
 
-                        match latest_build_info.status {
-                            BuildStatus::Running => continue,
-                            BuildStatus::Success { artifact_urls } => {
+                        let build_output = match latest_build_info.status {
+                            BuildStatus::Queued { .. } | BuildStatus::Running => continue,
+                            BuildStatus::Success { artifact_urls } => BuildOutput {
+                                artifact_urls,
+                                failed_artifacts: vec![],
+                                source: latest_build_info.source,
+                                dependencies: latest_build_info.dependencies,
+                            },
+                            BuildStatus::PartialSuccess {
+                                artifact_urls,
+                                failed_artifacts,
+                            } => BuildOutput {
+                                artifact_urls,
+                                failed_artifacts,
+                                source: latest_build_info.source,
+                                dependencies: latest_build_info.dependencies,
+                            },
+                            BuildStatus::Failure(_) => {
+                                let failure = latest_build_info.failure().unwrap();
                                 build_event_client
-                                    .build_succeeded(
+                                    .build_failed(
                                         &build_id,
-                                        package_type,
-                                        package_specific_id,
-                                        build_trigger,
-                                        artifact_urls,
+                                        BuildError::ClassifiedFailure(build_id.clone(), failure),
                                     )
                                     .await;
                                 break;
                             }
-                            BuildStatus::Failure(build_error) => {
+                        };
+
+                        if let Some(source_repository) = &source_repository {
+                            if let Err(error) =
+                                source_repository.verify(build_output.source.as_ref())
+                            {
                                 build_event_client
                                     .build_failed(
                                         &build_id,
-                                        BuildError::Failure(latest_build_info.id, build_error),
+                                        BuildError::Failure(build_id.clone(), error),
                                     )
                                     .await;
                                 break;
                             }
                         }
+
+                        build_event_client
+                            .build_succeeded(
+                                &build_id,
+                                package_type,
+                                package_specific_id,
+                                build_trigger,
+                                build_output,
+                            )
+                            .await;
+                        break;
                     }
                     Err(build_error) => {
                         build_event_client
@@ -117,9 +592,22 @@ impl BuildService {
                     }
                 }
             }
+            // the build left the pipeline, so a queued build can start
+            drop(build_slot);
         });
 
-        Ok(build_id_result)
+        build_id_result
+    }
+
+    fn record_build_started(&self, build_id: &str, build_request: &BuildRequest) {
+        self.build_history.record_started(
+            build_id,
+            build_request.package_type,
+            &build_request.package_specific_id,
+            build_request.build_trigger,
+            build_request.requester.clone(),
+            build_request.previous_attempt.as_deref(),
+        );
     }
 
     pub async fn handle_successful_build(
@@ -128,7 +616,7 @@ impl BuildService {
         package_type: PackageType,
         package_specific_id: String,
         build_trigger: BuildTrigger,
-        artifact_urls: Vec<String>,
+        build_output: BuildOutput,
     ) {
         let build_path = &self.get_build_path(build_id);
         if let Err(build_error) = fs::create_dir_all(build_path)
@@ -143,10 +631,11 @@ impl BuildService {
                     build_id,
                     package_type,
                     package_specific_id,
-                    artifact_urls,
+                    build_output,
                     build_path,
                 )
                 .await
+                .and_then(|build_result| self.check_partial_build_result(build_id, build_result))
             {
                 Ok(build_result) => {
                     debug!("Successfully handled build {}.", build_id);
@@ -171,19 +660,27 @@ impl BuildService {
         build_id: &str,
         package_type: PackageType,
         package_specific_id: String,
-        artifact_urls: Vec<String>,
+        build_output: BuildOutput,
         build_path: &Path,
     ) -> Result<BuildResult, BuildError> {
         let mut artifacts = vec![];
+        let mut failed_artifacts = build_output.failed_artifacts;
 
-        for artifact_url in artifact_urls {
+        for artifact_url in build_output.artifact_urls {
             debug!("Handle built artifact with url: {}", artifact_url);
-            let artifact = self
-                .pipeline_service
-                .download_artifact(&artifact_url)
-                .await?;
-            let (artifact_location, artifact_hash) = hash_and_store_data(build_path, &artifact)
-                .map_err(|e| BuildError::Failure(build_id.to_owned(), e.to_string()))?;
+            let (artifact_location, artifact_hash) = match self
+                .download_and_store_artifact(&artifact_url, build_path)
+                .await
+            {
+                Ok(stored_artifact) => stored_artifact,
+                Err(error) => {
+                    failed_artifacts.push(BuildArtifactFailure {
+                        artifact: artifact_url,
+                        error,
+                    });
+                    continue;
+                }
+            };
 
             let artifact_specific_ids = match package_type {
                 PackageType::Docker => {
@@ -242,13 +739,82 @@ impl BuildService {
             artifacts.len()
         );
 
+        let pinning_snapshot = if build_output.dependencies.is_empty() {
+            None
+        } else {
+            let pinning_snapshot = PinningSnapshot::new(
+                build_id,
+                package_type,
+                &package_specific_id,
+                build_output.source.clone(),
+                build_output.dependencies,
+            );
+            Some(
+                self.pinning_snapshot_store
+                    .store(&pinning_snapshot)
+                    .map_err(|e| BuildError::Failure(build_id.to_owned(), e.to_string()))?,
+            )
+        };
+
         Ok(BuildResult {
             package_type,
             package_specific_id,
             artifacts,
+            failed_artifacts,
+            source: build_output.source,
+            pinning_snapshot,
         })
     }
 
+    async fn download_and_store_artifact(
+        &self,
+        artifact_url: &str,
+        build_path: &Path,
+    ) -> Result<(PathBuf, String), String> {
+        let artifact = self
+            .pipeline_service
+            .download_artifact(artifact_url)
+            .await
+            .map_err(|e| e.to_string())?;
+        hash_and_store_data(build_path, &artifact).map_err(|e| e.to_string())
+    }
+
+    // Report the failed artifacts of a build result and decide, based on the
+    // partial build policy, whether the remaining artifacts can be published.
+    fn check_partial_build_result(
+        &self,
+        build_id: &str,
+        build_result: BuildResult,
+    ) -> Result<BuildResult, BuildError> {
+        if build_result.failed_artifacts.is_empty() {
+            return Ok(build_result);
+        }
+
+        for failed_artifact in build_result.failed_artifacts.iter() {
+            error!(
+                "Build {} failed to produce artifact {}",
+                build_id, failed_artifact
+            );
+        }
+
+        if build_result.artifacts.is_empty()
+            || self.partial_build_policy == PartialBuildPolicy::Reject
+        {
+            Err(BuildError::PartialFailure(
+                build_id.to_owned(),
+                build_result.failed_artifacts.len(),
+                build_result.failed_artifacts.iter().join(", "),
+            ))
+        } else {
+            warn!(
+                "Publishing partial result of build {} with {} failed artifacts",
+                build_id,
+                build_result.failed_artifacts.len()
+            );
+            Ok(build_result)
+        }
+    }
+
     pub fn clean_up_build(&self, build_id: &str) {
         let build_path = self.get_build_path(build_id);
         if let Err(error) = fs::remove_dir_all(&build_path) {
@@ -259,8 +825,132 @@ impl BuildService {
         }
     }
 
+    /// Keep the result of a successful build whose artifacts could not be
+    /// published, so that publication can be retried later.
+    pub fn store_failed_build_result(
+        &self,
+        build_id: &str,
+        build_result: &BuildResult,
+    ) -> Result<(), BuildError> {
+        self.dead_letter_store
+            .store(build_id, &self.get_build_path(build_id), build_result)
+            .map_err(|e| BuildError::Failure(build_id.to_owned(), e.to_string()))
+    }
+
+    /// Get the results of all builds whose artifacts could not be published.
+    pub fn get_failed_build_results(&self) -> Result<Vec<(String, BuildResult)>, BuildError> {
+        let build_ids = self
+            .dead_letter_store
+            .list()
+            .map_err(|e| BuildError::ReplayFailed(e.to_string()))?;
+
+        let mut failed_build_results = Vec::new();
+        for build_id in build_ids {
+            match self.dead_letter_store.load(&build_id) {
+                Ok(build_result) => failed_build_results.push((build_id, build_result)),
+                Err(error) => warn!(
+                    "Could not load failed build result for build {}: {:?}",
+                    build_id, error
+                ),
+            }
+        }
+        Ok(failed_build_results)
+    }
+
+    /// Remove a failed build result after its artifacts were published.
+    pub fn remove_failed_build_result(&self, build_id: &str) {
+        if let Err(error) = self.dead_letter_store.remove(build_id) {
+            warn!(
+                "Could not remove failed build result for build {}. Failed with error: {:?}",
+                build_id, error
+            )
+        }
+    }
+
+    /// Returns the status of the build with ID `build_id`, which is queued
+    /// until a slot of the build queue is free.
     pub async fn get_build_status(&self, build_id: &str) -> Result<BuildInfo, BuildError> {
-        self.pipeline_service.get_build_status(build_id).await
+        if let Some(position) = self.build_queue.position(build_id) {
+            return Ok(BuildInfo {
+                id: build_id.to_owned(),
+                status: BuildStatus::Queued { position },
+                source: None,
+                failure_kind: None,
+                log: None,
+                dependencies: vec![],
+            });
+        }
+
+        let mut build_info = self
+            .pipeline_service
+            .get_build_status(&self.build_queue.pipeline_build_id(build_id))
+            .await?;
+        build_info.id = build_id.to_owned();
+        Ok(build_info)
+    }
+
+    /// Record the classified root cause of a failed build in the build
+    /// history, so that it can still be reported after the pipeline has
+    /// forgotten about the build or when the build failed after the pipeline
+    /// finished it.
+    pub fn record_failure(&self, build_id: &str, failure: BuildFailure) {
+        self.build_history
+            .record_finished(build_id, BuildOutcome::Failure, Some(failure), vec![]);
+        self.record_build_usage(build_id);
+    }
+
+    /// Record in the build history that the artifacts of a build were published.
+    pub fn record_success(&self, build_id: &str, build_result: &BuildResult) {
+        let outcome = if build_result.failed_artifacts.is_empty() {
+            BuildOutcome::Success
+        } else {
+            BuildOutcome::PartialSuccess
+        };
+        let artifacts = build_result
+            .artifacts
+            .iter()
+            .map(|artifact| artifact.artifact_specific_id.clone())
+            .collect();
+        self.build_history
+            .record_finished(build_id, outcome, None, artifacts);
+        self.record_build_usage(build_id);
+    }
+
+    // the build minutes are accounted to the namespace of the package and
+    // the API key that requested the build
+    fn record_build_usage(&self, build_id: &str) {
+        if let Some(build_record) = self.build_history.get(build_id) {
+            self.usage_accounting.record_build_finished(
+                build_id,
+                UsageSubject::namespace_of_package(
+                    build_record.package_type,
+                    &build_record.package_specific_id,
+                ),
+                build_record.duration().unwrap_or_default(),
+            );
+        }
+    }
+
+    /// Returns the classified root cause of a failed build.
+    pub fn get_failure(&self, build_id: &str) -> Option<BuildFailure> {
+        self.build_history
+            .get(build_id)
+            .and_then(|build_record| build_record.failure)
+    }
+
+    /// Returns the number of failed builds per root cause.
+    pub fn failure_counts(&self) -> BTreeMap<BuildFailureKind, usize> {
+        self.build_history.failure_counts()
+    }
+
+    /// Returns the record of the build with ID `build_id` in the build history.
+    pub fn get_build_record(&self, build_id: &str) -> Option<BuildRecord> {
+        self.build_history.get(build_id)
+    }
+
+    /// Returns the builds in the build history that match `query`.
+    pub fn get_build_history(&self, query: &BuildHistoryQuery) -> Vec<BuildRecord> {
+        self.build_history.query(query)
     }
 
     fn get_build_path(&self, build_id: &str) -> PathBuf {
@@ -299,11 +989,46 @@ fn get_docker_image_name(package_specific_id: &str) -> String {
 #[cfg(not(tarpaulin_include))]
 mod tests {
     use super::*;
-    use crate::build_service::mapping::model::MappingInfo;
     use crate::util::test_util;
     use httptest::{matchers, responders, Expectation, Server};
     use tokio::sync::mpsc;
 
+    #[tokio::test]
+    async fn test_start_build_of_internal_package_is_refused() {
+        let tmp_dir = test_util::tests::setup();
+
+        let (sender, _) = mpsc::channel(1);
+        // neither the public mapping repository nor the pipeline are used
+        let http_server = Server::run();
+        let alert_service = AlertService::new(vec![]).unwrap();
+        let build_service = BuildService::new(
+            &tmp_dir,
+            BuildEventClient::new(sender),
+            &http_server.url_str("/"),
+            &http_server.url_str("/"),
+        )
+        .unwrap()
+        .with_internal_packages(InternalPackages::new(&["myorg/*"]), None)
+        .with_alert_service(alert_service.clone());
+
+        let error = build_service
+            .start_build(
+                PackageType::Docker,
+                "myorg/app:1.0".to_owned(),
+                BuildTrigger::FromSource,
+                Some("requester".to_owned()),
+            )
+            .await
+            .unwrap_err();
+
+        assert!(matches!(error, BuildError::DependencyConfusion { .. }));
+        let alerts = alert_service.recent_alerts();
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].details, vec!["myorg/app:1.0", "requester"]);
+
+        test_util::tests::teardown(tmp_dir);
+    }
+
     #[tokio::test]
     async fn test_start_build() {
         let tmp_dir = test_util::tests::setup();
@@ -350,11 +1075,368 @@ mod tests {
                 package_type,
                 package_specific_id.to_owned(),
                 BuildTrigger::FromSource,
+                Some("requester".to_owned()),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(build_id_result, build_id);
+        let build_records = build_service.get_build_history(&BuildHistoryQuery::default());
+        assert_eq!(build_records.len(), 1);
+        assert_eq!(build_records[0].build_id, build_id);
+        assert_eq!(build_records[0].package_specific_id, package_specific_id);
+        assert_eq!(build_records[0].requester, Some("requester".to_owned()));
+        assert_eq!(build_records[0].outcome, BuildOutcome::Running);
+
+        test_util::tests::teardown(tmp_dir);
+    }
+
+    #[tokio::test]
+    async fn test_start_build_is_queued_when_all_slots_are_taken() {
+        let tmp_dir = test_util::tests::setup();
+
+        let (sender, _) = mpsc::channel(1);
+        let build_id = uuid::Uuid::new_v4().to_string();
+
+        let http_server = Server::run();
+        http_server.expect(
+            Expectation::matching(matchers::request::method_path("PUT", "/build"))
+                .times(1)
+                .respond_with(responders::json_encoded(&build_id)),
+        );
+
+        let build_service = BuildService::new(
+            &tmp_dir,
+            BuildEventClient::new(sender),
+            "https://mapping-service.pyrsia.io/",
+            &http_server.url_str("/"),
+        )
+        .unwrap()
+        .with_build_queue(BuildQueue::new(Some(1)));
+
+        let running_build_id = build_service
+            .start_build(
+                PackageType::Docker,
+                "alpine:3.15.2".to_owned(),
+                BuildTrigger::FromSource,
+                None,
+            )
+            .await
+            .unwrap();
+        assert_eq!(running_build_id, build_id);
+
+        // the pipeline is not asked to start the second build yet
+        let queued_build_id = build_service
+            .start_build(
+                PackageType::Docker,
+                "alpine:3.16.0".to_owned(),
+                BuildTrigger::FromSource,
+                None,
+            )
+            .await
+            .unwrap();
+        assert_ne!(queued_build_id, build_id);
+
+        let build_info = build_service
+            .get_build_status(&queued_build_id)
+            .await
+            .unwrap();
+        assert_eq!(build_info.id, queued_build_id);
+        assert_eq!(build_info.status, BuildStatus::Queued { position: 1 });
+        let build_records = build_service.get_build_history(&BuildHistoryQuery::default());
+        assert_eq!(build_records.len(), 1);
+        assert_eq!(build_records[0].build_id, build_id);
+
+        test_util::tests::teardown(tmp_dir);
+    }
+
+    #[tokio::test]
+    async fn test_rate_limited_build_is_started_again() {
+        let tmp_dir = test_util::tests::setup();
+
+        let (sender, _) = mpsc::channel(1);
+        let pipeline_build_id = uuid::Uuid::new_v4().to_string();
+
+        let http_server = Server::run();
+        http_server.expect(
+            Expectation::matching(matchers::request::method_path("PUT", "/build"))
+                .times(2)
+                .respond_with(responders::cycle![
+                    responders::status_code(429).insert_header("retry-after", "0"),
+                    responders::json_encoded(&pipeline_build_id),
+                ]),
+        );
+
+        let build_service = BuildService::new(
+            &tmp_dir,
+            BuildEventClient::new(sender),
+            "https://mapping-service.pyrsia.io/",
+            &http_server.url_str("/"),
+        )
+        .unwrap();
+
+        // the build is reported as queued instead of waiting for the pipeline
+        let build_id = build_service
+            .start_build(
+                PackageType::Docker,
+                "alpine:3.15.2".to_owned(),
+                BuildTrigger::FromSource,
+                None,
             )
             .await
             .unwrap();
+        assert_ne!(build_id, pipeline_build_id);
+        let build_info = build_service.get_build_status(&build_id).await.unwrap();
+        assert_eq!(build_info.status, BuildStatus::Queued { position: 0 });
+
+        for _ in 0..100 {
+            if build_service.build_queue.position(&build_id).is_none() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(build_service.build_queue.position(&build_id), None);
+        assert_eq!(
+            build_service.build_queue.pipeline_build_id(&build_id),
+            pipeline_build_id
+        );
+        let build_records = build_service.get_build_history(&BuildHistoryQuery::default());
+        assert_eq!(build_records.len(), 1);
+        assert_eq!(build_records[0].build_id, build_id);
+
+        test_util::tests::teardown(tmp_dir);
+    }
+
+    #[tokio::test]
+    async fn test_reproduce_build() {
+        let tmp_dir = test_util::tests::setup();
+
+        let (sender, _) = mpsc::channel(1);
+        let build_id = uuid::Uuid::new_v4().to_string();
+
+        let http_server = Server::run();
+        http_server.expect(
+            Expectation::matching(matchers::all_of!(
+                matchers::request::method_path("PUT", "/build"),
+                matchers::request::body(matchers::json_decoded(matchers::eq(serde_json::json!({
+                    "package_type": "Docker",
+                    "package_specific_id": "alpine:3.15.2",
+                    "source_repository": null,
+                    "build_spec_url": null,
+                    "pinned_dependencies": [
+                        { "coordinates": "library/busybox:1.35", "digest": "sha256:aaaa" }
+                    ]
+                }))))
+            ))
+            .respond_with(responders::json_encoded(&build_id)),
+        );
+
+        let build_service = BuildService::new(
+            &tmp_dir,
+            BuildEventClient::new(sender),
+            "https://mapping-service.pyrsia.io/",
+            &http_server.url_str("/"),
+        )
+        .unwrap();
+
+        let pinning_snapshot = PinningSnapshot::new(
+            "original_build_id",
+            PackageType::Docker,
+            "alpine:3.15.2",
+            None,
+            vec![PinnedDependency {
+                coordinates: "library/busybox:1.35".to_owned(),
+                digest: "sha256:aaaa".to_owned(),
+            }],
+        );
+        let pinning_snapshot_id = build_service
+            .pinning_snapshot_store
+            .store(&pinning_snapshot)
+            .unwrap();
 
+        let build_id_result = build_service
+            .reproduce_build(&pinning_snapshot_id, None)
+            .await
+            .unwrap();
         assert_eq!(build_id_result, build_id);
+        let build_records = build_service.get_build_history(&BuildHistoryQuery::default());
+        assert_eq!(build_records[0].trigger, BuildTrigger::Reproduction);
+
+        assert_eq!(
+            build_service
+                .reproduce_build("0123abcd", None)
+                .await
+                .unwrap_err(),
+            BuildError::PinningSnapshotNotFound("0123abcd".to_owned())
+        );
+
+        test_util::tests::teardown(tmp_dir);
+    }
+
+    #[test]
+    fn test_check_partial_build_result() {
+        let tmp_dir = test_util::tests::setup();
+
+        let (sender, _) = mpsc::channel(1);
+        let build_service = BuildService::new(
+            &tmp_dir,
+            BuildEventClient::new(sender),
+            "https://mapping-service.pyrsia.io/",
+            "http://localhost:8080",
+        )
+        .unwrap();
+
+        let build_result = BuildResult {
+            package_type: PackageType::Maven2,
+            package_specific_id: "com.company:test:1.0".to_owned(),
+            artifacts: vec![BuildResultArtifact {
+                artifact_specific_id: "com/company/test/1.0/test-1.0.jar".to_owned(),
+                artifact_location: tmp_dir.join("test-1.0.jar"),
+                artifact_hash: "artifact_hash".to_owned(),
+            }],
+            failed_artifacts: vec![BuildArtifactFailure {
+                artifact: "test-1.0-sources.jar".to_owned(),
+                error: "download failed".to_owned(),
+            }],
+            source: None,
+            pinning_snapshot: None,
+        };
+
+        let build_error = build_service
+            .check_partial_build_result("build_id", build_result.clone())
+            .unwrap_err();
+        assert_eq!(
+            build_error,
+            BuildError::PartialFailure(
+                "build_id".to_owned(),
+                1,
+                "test-1.0-sources.jar: download failed".to_owned()
+            )
+        );
+
+        let build_service = build_service.with_partial_build_policy(PartialBuildPolicy::Publish);
+        let published_result = build_service
+            .check_partial_build_result("build_id", build_result)
+            .unwrap();
+        assert_eq!(published_result.artifacts.len(), 1);
+        assert_eq!(published_result.failed_artifacts.len(), 1);
+
+        test_util::tests::teardown(tmp_dir);
+    }
+
+    #[test]
+    fn test_record_failure() {
+        let tmp_dir = test_util::tests::setup();
+
+        let (sender, _) = mpsc::channel(1);
+        let build_service = BuildService::new(
+            &tmp_dir,
+            BuildEventClient::new(sender),
+            "https://mapping-service.pyrsia.io/",
+            "http://localhost:8080",
+        )
+        .unwrap();
+
+        for build_id in ["build_id_1", "build_id_2"] {
+            build_service.build_history.record_started(
+                build_id,
+                PackageType::Maven2,
+                "com.company:test:1.0",
+                BuildTrigger::FromSource,
+                None,
+                None,
+            );
+        }
+
+        let build_error = BuildError::PipelineServiceEndpointRequestFailure("refused".to_owned());
+        build_service.record_failure("build_id_1", build_error.failure());
+        let build_error = BuildError::Failure(
+            "build_id_2".to_owned(),
+            "Build used undeclared source https://example.com/repo.git".to_owned(),
+        );
+        build_service.record_failure("build_id_2", build_error.failure());
+
+        assert_eq!(
+            build_service.get_failure("build_id_1").unwrap().kind,
+            BuildFailureKind::PipelineInfrastructure
+        );
+        assert_eq!(
+            build_service.get_failure("build_id_2").unwrap().kind,
+            BuildFailureKind::SourceFetch
+        );
+        assert_eq!(build_service.get_failure("build_id_3"), None);
+        build_service.record_failure("build_id_3", build_error.failure());
+        assert_eq!(build_service.get_failure("build_id_3"), None);
+        assert_eq!(
+            build_service.failure_counts(),
+            BTreeMap::from([
+                (BuildFailureKind::SourceFetch, 1),
+                (BuildFailureKind::PipelineInfrastructure, 1),
+            ])
+        );
+
+        test_util::tests::teardown(tmp_dir);
+    }
+
+    #[test]
+    fn test_get_build_to_rerun() {
+        let tmp_dir = test_util::tests::setup();
+
+        let (sender, _) = mpsc::channel(1);
+        let build_service = BuildService::new(
+            &tmp_dir,
+            BuildEventClient::new(sender),
+            "https://mapping-service.pyrsia.io/",
+            "http://localhost:8080",
+        )
+        .unwrap();
+
+        for (build_id, build_trigger, previous_attempt) in [
+            ("build_id_1", BuildTrigger::FromSource, None),
+            ("build_id_2", BuildTrigger::FromSource, Some("build_id_1")),
+            ("build_id_3", BuildTrigger::Verification, None),
+        ] {
+            build_service.build_history.record_started(
+                build_id,
+                PackageType::Maven2,
+                "com.company:test:1.0",
+                build_trigger,
+                None,
+                previous_attempt,
+            );
+        }
+        let build_error = BuildError::Failure("build_id".to_owned(), "failed".to_owned());
+        for build_id in ["build_id_1", "build_id_3"] {
+            build_service.record_failure(build_id, build_error.failure());
+        }
+
+        assert_eq!(
+            build_service.get_build_to_rerun("unknown").unwrap_err(),
+            BuildError::BuildNotFound("unknown".to_owned())
+        );
+        assert_eq!(
+            build_service.get_build_to_rerun("build_id_1").unwrap_err(),
+            BuildError::RerunNotAllowed(
+                "build_id_1".to_owned(),
+                "it was already rerun as build_id_2".to_owned()
+            )
+        );
+        assert_eq!(
+            build_service.get_build_to_rerun("build_id_2").unwrap_err(),
+            BuildError::RerunNotAllowed(
+                "build_id_2".to_owned(),
+                "the build outcome is running".to_owned()
+            )
+        );
+        assert!(matches!(
+            build_service.get_build_to_rerun("build_id_3"),
+            Err(BuildError::RerunNotAllowed(..))
+        ));
+
+        build_service.record_failure("build_id_2", build_error.failure());
+        let build_record = build_service.get_build_to_rerun("build_id_2").unwrap();
+        assert_eq!(build_record.attempt, 2);
+        assert_eq!(build_record.package_specific_id, "com.company:test:1.0");
 
         test_util::tests::teardown(tmp_dir);
     }
@@ -0,0 +1,401 @@
+/*
+   Copyright 2021 JFrog Ltd
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+*/
+
+use crate::artifact_service::model::PackageType;
+use crate::artifact_service::service::ArtifactService;
+use log::{debug, info, warn};
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+use thiserror::Error;
+
+const DOCKER_HUB_TAGS_ENDPOINT: &str = "https://hub.docker.com/v2/repositories";
+const MAVEN_CENTRAL_SEARCH_ENDPOINT: &str = "https://search.maven.org/solrsearch/select";
+const VERSION_FEED_PAGE_SIZE: usize = 100;
+
+#[derive(Debug, Error)]
+pub enum VersionWatcherError {
+    #[error("Failed to read the version watcher configuration: {0}")]
+    ConfigurationFailure(String),
+    #[error("Failed to fetch the version feed of {0}: {1}")]
+    FeedFailure(String, String),
+    #[error("Failed to read the transparency logs: {0}")]
+    TransparencyLogFailure(String),
+}
+
+/// A package for which new upstream versions are built proactively.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq)]
+pub struct WatchedPackage {
+    pub package_type: PackageType,
+    /// The package name without version, e.g. `library/alpine` for Docker
+    /// or `com.myorg:my-artifact` for maven.
+    pub package_name: String,
+    /// The maximum number of builds that are started for this package
+    /// during a single poll.
+    #[serde(default = "default_package_max_builds_per_poll")]
+    pub max_builds_per_poll: usize,
+}
+
+/// The configuration of the [`VersionWatcher`], read from a JSON file.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq)]
+pub struct VersionWatcherConfig {
+    /// The number of seconds between two polls of the version feeds.
+    #[serde(default = "default_poll_interval_secs")]
+    pub poll_interval_secs: u64,
+    /// The maximum number of builds that are started during a single poll,
+    /// over all watched packages.
+    #[serde(default = "default_max_builds_per_poll")]
+    pub max_builds_per_poll: usize,
+    pub packages: Vec<WatchedPackage>,
+}
+
+fn default_package_max_builds_per_poll() -> usize {
+    1
+}
+
+fn default_poll_interval_secs() -> u64 {
+    3600
+}
+
+fn default_max_builds_per_poll() -> usize {
+    10
+}
+
+impl VersionWatcherConfig {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, VersionWatcherError> {
+        let content =
+            fs::read(path).map_err(|e| VersionWatcherError::ConfigurationFailure(e.to_string()))?;
+        serde_json::from_slice(&content)
+            .map_err(|e| VersionWatcherError::ConfigurationFailure(e.to_string()))
+    }
+}
+
+/// The version watcher polls the upstream version feeds of the configured
+/// packages (Docker Hub tags and the Maven Central index) and schedules
+/// builds for versions that appeared since the previous poll.
+///
+/// Only packages that already have artifacts in the transparency log are
+/// watched, and polls are skipped when the local node is not an authorized
+/// node. The versions that are available at the first poll are taken as a
+/// baseline and are not built.
+pub struct VersionWatcher {
+    artifact_service: ArtifactService,
+    config: VersionWatcherConfig,
+    http_client: reqwest::Client,
+    known_versions: HashMap<(PackageType, String), HashSet<String>>,
+}
+
+impl VersionWatcher {
+    pub fn new(artifact_service: ArtifactService, config: VersionWatcherConfig) -> Self {
+        VersionWatcher {
+            artifact_service,
+            config,
+            http_client: reqwest::Client::new(),
+            known_versions: HashMap::new(),
+        }
+    }
+
+    pub async fn run(mut self) {
+        let mut interval =
+            tokio::time::interval(Duration::from_secs(self.config.poll_interval_secs));
+        loop {
+            interval.tick().await;
+            if let Err(e) = self.poll().await {
+                warn!("Failed to check for new package versions: {}", e);
+            }
+        }
+    }
+
+    /// Check the version feeds of all watched packages once and start builds
+    /// for new versions. Returns the package specific ids of the started builds.
+    pub async fn poll(&mut self) -> Result<Vec<String>, VersionWatcherError> {
+        if !self.is_authorized_node()? {
+            debug!("Skip checking for new package versions on a node that is not authorized");
+            return Ok(Vec::new());
+        }
+
+        let mut started_builds = Vec::new();
+        for package in self.config.packages.clone() {
+            let remaining_builds = self.config.max_builds_per_poll - started_builds.len();
+            if remaining_builds == 0 {
+                debug!("Build limit reached, remaining new versions are built at the next poll");
+                break;
+            }
+
+            match self.poll_package(&package, remaining_builds).await {
+                Ok(mut builds) => started_builds.append(&mut builds),
+                Err(e) => warn!("{}", e),
+            }
+        }
+
+        Ok(started_builds)
+    }
+
+    async fn poll_package(
+        &mut self,
+        package: &WatchedPackage,
+        remaining_builds: usize,
+    ) -> Result<Vec<String>, VersionWatcherError> {
+        let logged_versions = self
+            .artifact_service
+            .transparency_log_service
+            .find_package_versions(&package.package_type, &package.package_name)
+            .map_err(|e| VersionWatcherError::TransparencyLogFailure(e.to_string()))?;
+        if logged_versions.is_empty() {
+            debug!(
+                "Package {} is not in the transparency log, not watching it",
+                package.package_name
+            );
+            return Ok(Vec::new());
+        }
+
+        let feed_versions = self.fetch_versions(package).await?;
+
+        let key = (package.package_type, package.package_name.clone());
+        let known_versions = match self.known_versions.get_mut(&key) {
+            Some(known_versions) => known_versions,
+            None => {
+                debug!(
+                    "Found {} versions of package {}, using them as baseline",
+                    feed_versions.len(),
+                    package.package_name
+                );
+                self.known_versions
+                    .insert(key, feed_versions.into_iter().collect());
+                return Ok(Vec::new());
+            }
+        };
+
+        let new_versions = select_new_versions(
+            &feed_versions,
+            known_versions,
+            &logged_versions,
+            package.max_builds_per_poll.min(remaining_builds),
+        );
+
+        let mut started_builds = Vec::new();
+        for version in new_versions {
+            known_versions.insert(version.clone());
+            let package_specific_id = format!("{}:{}", package.package_name, version);
+            match self
+                .artifact_service
+                .request_build(package.package_type, package_specific_id.clone())
+                .await
+            {
+                Ok(build_id) => {
+                    info!(
+                        "Started build {} for new version {}",
+                        build_id, package_specific_id
+                    );
+                    started_builds.push(package_specific_id);
+                }
+                Err(e) => warn!(
+                    "Failed to start build for new version {}: {}",
+                    package_specific_id, e
+                ),
+            }
+        }
+
+        Ok(started_builds)
+    }
+
+    fn is_authorized_node(&self) -> Result<bool, VersionWatcherError> {
+        let authorized_nodes = self
+            .artifact_service
+            .transparency_log_service
+            .get_authorized_nodes()
+            .map_err(|e| VersionWatcherError::TransparencyLogFailure(e.to_string()))?;
+        Ok(authorized_nodes.contains(&self.artifact_service.p2p_client.local_peer_id))
+    }
+
+    async fn fetch_versions(
+        &self,
+        package: &WatchedPackage,
+    ) -> Result<Vec<String>, VersionWatcherError> {
+        let feed_error = |e: reqwest::Error| {
+            VersionWatcherError::FeedFailure(package.package_name.clone(), e.to_string())
+        };
+
+        let request = match package.package_type {
+            PackageType::Docker => self
+                .http_client
+                .get(format!(
+                    "{}/{}/tags",
+                    DOCKER_HUB_TAGS_ENDPOINT, package.package_name
+                ))
+                .query(&[
+                    ("page_size", VERSION_FEED_PAGE_SIZE.to_string()),
+                    ("ordering", "last_updated".to_owned()),
+                ]),
+            PackageType::Maven2 => {
+                let (group_id, artifact_id) =
+                    package.package_name.split_once(':').ok_or_else(|| {
+                        VersionWatcherError::FeedFailure(
+                            package.package_name.clone(),
+                            "the package name is not of the form groupId:artifactId".to_owned(),
+                        )
+                    })?;
+                self.http_client.get(MAVEN_CENTRAL_SEARCH_ENDPOINT).query(&[
+                    ("q", format!("g:\"{}\" AND a:\"{}\"", group_id, artifact_id)),
+                    ("core", "gav".to_owned()),
+                    ("rows", VERSION_FEED_PAGE_SIZE.to_string()),
+                    ("wt", "json".to_owned()),
+                ])
+            }
+        };
+
+        let body = request
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(feed_error)?
+            .bytes()
+            .await
+            .map_err(feed_error)?;
+
+        parse_versions(&package.package_type, &body)
+            .map_err(|e| VersionWatcherError::FeedFailure(package.package_name.clone(), e))
+    }
+}
+
+#[derive(Deserialize)]
+struct DockerHubTags {
+    results: Vec<DockerHubTag>,
+}
+
+#[derive(Deserialize)]
+struct DockerHubTag {
+    name: String,
+}
+
+#[derive(Deserialize)]
+struct MavenSearchResult {
+    response: MavenSearchResponse,
+}
+
+#[derive(Deserialize)]
+struct MavenSearchResponse {
+    docs: Vec<MavenSearchDoc>,
+}
+
+#[derive(Deserialize)]
+struct MavenSearchDoc {
+    v: String,
+}
+
+/// Parse the versions in a version feed response, newest versions first.
+fn parse_versions(package_type: &PackageType, body: &[u8]) -> Result<Vec<String>, String> {
+    match package_type {
+        PackageType::Docker => serde_json::from_slice::<DockerHubTags>(body)
+            .map(|tags| tags.results.into_iter().map(|tag| tag.name).collect()),
+        PackageType::Maven2 => serde_json::from_slice::<MavenSearchResult>(body)
+            .map(|result| result.response.docs.into_iter().map(|doc| doc.v).collect()),
+    }
+    .map_err(|e| e.to_string())
+}
+
+/// Select at most `limit` versions from the feed that are neither known from a
+/// previous poll nor present in the transparency log, in feed order.
+fn select_new_versions(
+    feed_versions: &[String],
+    known_versions: &HashSet<String>,
+    logged_versions: &HashSet<String>,
+    limit: usize,
+) -> Vec<String> {
+    feed_versions
+        .iter()
+        .filter(|version| !known_versions.contains(*version) && !logged_versions.contains(*version))
+        .take(limit)
+        .cloned()
+        .collect()
+}
+
+#[cfg(test)]
+#[cfg(not(tarpaulin_include))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_docker_hub_versions() {
+        let body =
+            br#"{"count": 2, "results": [{"name": "3.17.1", "full_size": 3}, {"name": "latest"}]}"#;
+
+        assert_eq!(
+            parse_versions(&PackageType::Docker, body).unwrap(),
+            vec!["3.17.1", "latest"]
+        );
+    }
+
+    #[test]
+    fn test_parse_maven_central_versions() {
+        let body = br#"{"response": {"numFound": 2, "docs": [{"g": "com.myorg", "a": "my-artifact", "v": "1.1.0"}, {"g": "com.myorg", "a": "my-artifact", "v": "1.0.0"}]}}"#;
+
+        assert_eq!(
+            parse_versions(&PackageType::Maven2, body).unwrap(),
+            vec!["1.1.0", "1.0.0"]
+        );
+    }
+
+    #[test]
+    fn test_parse_invalid_feed() {
+        assert!(parse_versions(&PackageType::Maven2, b"<html></html>").is_err());
+    }
+
+    #[test]
+    fn test_select_new_versions() {
+        let feed_versions: Vec<String> = vec!["1.3.0", "1.2.0", "1.1.0", "1.0.0"]
+            .into_iter()
+            .map(String::from)
+            .collect();
+        let known_versions = HashSet::from(["1.0.0".to_owned(), "1.1.0".to_owned()]);
+        let logged_versions = HashSet::from(["1.2.0".to_owned()]);
+
+        assert_eq!(
+            select_new_versions(&feed_versions, &known_versions, &logged_versions, 5),
+            vec!["1.3.0"]
+        );
+        assert_eq!(
+            select_new_versions(&feed_versions, &HashSet::new(), &logged_versions, 2),
+            vec!["1.3.0", "1.1.0"]
+        );
+        assert!(
+            select_new_versions(&feed_versions, &HashSet::new(), &HashSet::new(), 0).is_empty()
+        );
+    }
+
+    #[test]
+    fn test_config_defaults() {
+        let config: VersionWatcherConfig = serde_json::from_str(
+            r#"{"packages": [{"package_type": "Docker", "package_name": "library/alpine"}]}"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            config,
+            VersionWatcherConfig {
+                poll_interval_secs: 3600,
+                max_builds_per_poll: 10,
+                packages: vec![WatchedPackage {
+                    package_type: PackageType::Docker,
+                    package_name: "library/alpine".to_owned(),
+                    max_builds_per_poll: 1,
+                }],
+            }
+        );
+    }
+}
@@ -14,6 +14,12 @@
    limitations under the License.
 */
 
+pub mod artifact;
 pub mod config;
+pub mod error;
+pub mod format;
+pub mod import;
+pub mod migrate;
 pub mod model;
 pub mod node;
+pub mod upgrade;
@@ -0,0 +1,457 @@
+/*
+   Copyright 2021 JFrog Ltd
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+*/
+
+use crate::cli_commands::error::{CliError, ErrorKind};
+use crate::cli_commands::node;
+use crate::node_api::model::request::{
+    Content, ContentType, RequestMavenLog, TransparencyLogField, TransparencyLogOutputParams,
+};
+use anyhow::{anyhow, bail, Context, Result};
+use reqwest::header::{CONTENT_RANGE, RANGE};
+use reqwest::StatusCode;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use tokio::fs::{self, OpenOptions};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+const PARTIAL_DOWNLOAD_EXTENSION: &str = "part";
+const DEFAULT_MAVEN_EXTENSION: &str = "jar";
+
+/// The coordinates of a single artifact that can be downloaded via the node.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ArtifactCoordinates {
+    /// A blob of an official Docker image, e.g. `alpine@sha256:<hex>`.
+    DockerBlob { name: String, digest: String },
+    /// A file of a maven package, e.g. `org.myorg:my-artifact:1.0.0:sources@jar`.
+    Maven {
+        group_id: String,
+        artifact_id: String,
+        version: String,
+        file_name: String,
+    },
+}
+
+impl ArtifactCoordinates {
+    /// Parse `<image>@sha256:<hex>` for a Docker blob, or
+    /// `<group>:<artifact>:<version>[:<classifier>][@<extension>]` for a
+    /// maven file. The extension of maven files defaults to `jar`.
+    pub fn parse(coordinates: &str) -> Result<Self> {
+        if let Some((image, digest)) = coordinates.split_once('@') {
+            if let Some(hash) = digest.strip_prefix("sha256:") {
+                if hash.len() != 64 || !hash.chars().all(|c| c.is_ascii_hexdigit()) {
+                    bail!("Invalid sha256 digest: {}", digest);
+                }
+                let name = image.strip_prefix("library/").unwrap_or(image);
+                if name.is_empty() || name.contains('/') || name.contains(':') {
+                    bail!(
+                        "Only blobs of official Docker images can be downloaded: {}",
+                        image
+                    );
+                }
+                return Ok(ArtifactCoordinates::DockerBlob {
+                    name: name.to_owned(),
+                    digest: digest.to_lowercase(),
+                });
+            }
+        }
+
+        let (gav, extension) = coordinates
+            .split_once('@')
+            .unwrap_or((coordinates, DEFAULT_MAVEN_EXTENSION));
+        let parts: Vec<&str> = gav.split(':').collect();
+        let (group_id, artifact_id, version, classifier) = match parts[..] {
+            [group_id, artifact_id, version] => (group_id, artifact_id, version, None),
+            [group_id, artifact_id, version, classifier] => {
+                (group_id, artifact_id, version, Some(classifier))
+            }
+            _ => bail!(
+                "Invalid coordinates {}, expected <image>@sha256:<digest> or <group>:<artifact>:<version>[:<classifier>][@<extension>]",
+                coordinates
+            ),
+        };
+        if [group_id, artifact_id, version, extension]
+            .iter()
+            .chain(classifier.iter())
+            .any(|part| part.is_empty() || part.contains('/'))
+        {
+            bail!("Invalid maven coordinates: {}", coordinates);
+        }
+
+        let file_name = match classifier {
+            Some(classifier) => format!("{}-{}-{}.{}", artifact_id, version, classifier, extension),
+            None => format!("{}-{}.{}", artifact_id, version, extension),
+        };
+        Ok(ArtifactCoordinates::Maven {
+            group_id: group_id.to_owned(),
+            artifact_id: artifact_id.to_owned(),
+            version: version.to_owned(),
+            file_name,
+        })
+    }
+
+    /// The path of the artifact on the node.
+    pub fn path(&self) -> String {
+        match self {
+            ArtifactCoordinates::DockerBlob { name, digest } => {
+                format!("/v2/library/{}/blobs/{}", name, digest)
+            }
+            ArtifactCoordinates::Maven {
+                group_id,
+                artifact_id,
+                version,
+                file_name,
+            } => format!(
+                "/maven2/{}/{}/{}/{}",
+                group_id.replace('.', "/"),
+                artifact_id,
+                version,
+                file_name
+            ),
+        }
+    }
+
+    /// The name of the file the artifact is saved to when no output file is
+    /// given.
+    pub fn file_name(&self) -> String {
+        match self {
+            ArtifactCoordinates::DockerBlob { digest, .. } => digest.replace(':', "-"),
+            ArtifactCoordinates::Maven { file_name, .. } => file_name.clone(),
+        }
+    }
+
+    /// The sha256 hash that the downloaded artifact must have. The hash of a
+    /// Docker blob is its digest, the hash of a maven file is looked up in
+    /// the transparency log of its package.
+    pub async fn expected_hash(&self) -> Result<String> {
+        match self {
+            ArtifactCoordinates::DockerBlob { digest, .. } => {
+                Ok(digest.trim_start_matches("sha256:").to_owned())
+            }
+            ArtifactCoordinates::Maven {
+                group_id,
+                artifact_id,
+                version,
+                file_name,
+            } => {
+                let response = node::inspect_maven_transparency_log(RequestMavenLog {
+                    gav: format!("{}:{}:{}", group_id, artifact_id, version),
+                    output_params: Some(TransparencyLogOutputParams {
+                        format: Some(ContentType::JSON),
+                        content: Some(Content {
+                            fields: vec![
+                                TransparencyLogField::PackageSpecificArtifactId,
+                                TransparencyLogField::ArtifactHash,
+                            ],
+                        }),
+                    }),
+                })
+                .await?;
+                let package_specific_artifact_id =
+                    format!("{}/{}/{}/{}", group_id, artifact_id, version, file_name);
+                find_artifact_hash(&response.logs, &package_specific_artifact_id)
+            }
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct ArtifactHashLog {
+    package_specific_artifact_id: String,
+    artifact_hash: String,
+}
+
+fn find_artifact_hash(logs: &str, package_specific_artifact_id: &str) -> Result<String> {
+    let logs: Vec<ArtifactHashLog> =
+        serde_json::from_str(logs).context("Invalid transparency logs")?;
+    logs.into_iter()
+        .find(|log| log.package_specific_artifact_id == package_specific_artifact_id)
+        .map(|log| log.artifact_hash)
+        .ok_or_else(|| {
+            CliError::new(
+                ErrorKind::NotFound,
+                format!(
+                    "No transparency log found for {}",
+                    package_specific_artifact_id
+                ),
+            )
+            .into()
+        })
+}
+
+/// The file that a download is written to until its hash is verified.
+pub fn partial_download_path(output: &Path) -> PathBuf {
+    let mut path = output.as_os_str().to_owned();
+    path.push(".");
+    path.push(PARTIAL_DOWNLOAD_EXTENSION);
+    PathBuf::from(path)
+}
+
+/// Download the artifact at `url` to `output`. The artifact is written to a
+/// partial file first, and a partial file left by an interrupted download is
+/// resumed with a `Range` request. The file is only moved to `output` when
+/// its sha256 hash matches `expected_hash`, otherwise the partial file is
+/// removed. `on_progress` is called with the downloaded and total bytes.
+/// Returns the size of the artifact.
+pub async fn download_artifact(
+    url: &str,
+    expected_hash: &str,
+    output: &Path,
+    mut on_progress: impl FnMut(u64, Option<u64>),
+) -> Result<u64> {
+    let partial_path = partial_download_path(output);
+    let mut sha256 = Sha256::new();
+    let mut downloaded = hash_partial_download(&partial_path, &mut sha256).await?;
+
+    let client = reqwest::Client::new();
+    let mut request = client.get(url);
+    if downloaded > 0 {
+        request = request.header(RANGE, format!("bytes={}-", downloaded));
+    }
+    let mut response = request.send().await?;
+    // the partial file does not fit the artifact, so it is downloaded again
+    if response.status() == StatusCode::RANGE_NOT_SATISFIABLE {
+        downloaded = 0;
+        sha256 = Sha256::new();
+        response = client.get(url).send().await?;
+    }
+    let mut response = response.error_for_status()?;
+
+    let total = match response.status() {
+        StatusCode::PARTIAL_CONTENT => {
+            let (start, total) = response
+                .headers()
+                .get(CONTENT_RANGE)
+                .and_then(|content_range| content_range.to_str().ok())
+                .and_then(parse_content_range)
+                .ok_or_else(|| anyhow!("Invalid Content-Range in the response from {}", url))?;
+            if start != downloaded {
+                bail!(
+                    "{} resumed the download at byte {} instead of {}",
+                    url,
+                    start,
+                    downloaded
+                );
+            }
+            Some(total)
+        }
+        _ => {
+            // the server sends the complete artifact
+            downloaded = 0;
+            sha256 = Sha256::new();
+            response.content_length()
+        }
+    };
+
+    let mut file = OpenOptions::new()
+        .create(true)
+        .write(true)
+        .append(downloaded > 0)
+        .truncate(downloaded == 0)
+        .open(&partial_path)
+        .await
+        .with_context(|| format!("Failed to open {:?}", partial_path))?;
+    on_progress(downloaded, total);
+    while let Some(chunk) = response.chunk().await? {
+        file.write_all(&chunk).await?;
+        sha256.update(&chunk);
+        downloaded += chunk.len() as u64;
+        on_progress(downloaded, total);
+    }
+    file.sync_all().await?;
+    drop(file);
+
+    let hash = hex::encode(sha256.finalize());
+    if !hash.eq_ignore_ascii_case(expected_hash) {
+        let _ = fs::remove_file(&partial_path).await;
+        bail!(CliError::new(
+            ErrorKind::VerificationFailed,
+            format!(
+                "The hash {} of the downloaded artifact does not match the expected hash {}",
+                hash, expected_hash
+            )
+        ));
+    }
+    fs::rename(&partial_path, output)
+        .await
+        .with_context(|| format!("Failed to move the download to {:?}", output))?;
+    Ok(downloaded)
+}
+
+// Hash the content of a partial download. Returns its size, which is 0 when
+// there is no partial download.
+async fn hash_partial_download(partial_path: &Path, sha256: &mut Sha256) -> Result<u64> {
+    let mut file = match fs::File::open(partial_path).await {
+        Ok(file) => file,
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+        Err(error) => return Err(error.into()),
+    };
+    let mut buffer = vec![0; 64 * 1024];
+    let mut len = 0;
+    loop {
+        let read = file.read(&mut buffer).await?;
+        if read == 0 {
+            return Ok(len);
+        }
+        sha256.update(&buffer[..read]);
+        len += read as u64;
+    }
+}
+
+// Parse a `Content-Range` header like `bytes 100-199/200` into the first
+// byte and the complete size.
+fn parse_content_range(content_range: &str) -> Option<(u64, u64)> {
+    let (range, total) = content_range.strip_prefix("bytes ")?.split_once('/')?;
+    let (start, _end) = range.split_once('-')?;
+    Some((start.parse().ok()?, total.parse().ok()?))
+}
+
+#[cfg(test)]
+#[cfg(not(tarpaulin_include))]
+mod tests {
+    use super::*;
+    use crate::util::test_util;
+    use httptest::{matchers, responders, Expectation, Server};
+
+    const CONTENT: &[u8] = b"0123456789";
+
+    fn content_hash() -> String {
+        hex::encode(Sha256::digest(CONTENT))
+    }
+
+    #[test]
+    fn test_parse_coordinates() {
+        let digest = format!("sha256:{}", content_hash());
+        let coordinates =
+            ArtifactCoordinates::parse(&format!("library/alpine@{}", digest)).unwrap();
+        assert_eq!(
+            coordinates.path(),
+            format!("/v2/library/alpine/blobs/{}", digest)
+        );
+
+        let coordinates = ArtifactCoordinates::parse("org.myorg:my-artifact:1.0.0").unwrap();
+        assert_eq!(
+            coordinates.path(),
+            "/maven2/org/myorg/my-artifact/1.0.0/my-artifact-1.0.0.jar"
+        );
+        assert_eq!(coordinates.file_name(), "my-artifact-1.0.0.jar");
+
+        let coordinates =
+            ArtifactCoordinates::parse("org.myorg:my-artifact:1.0.0:sources@pom").unwrap();
+        assert_eq!(coordinates.file_name(), "my-artifact-1.0.0-sources.pom");
+
+        assert!(ArtifactCoordinates::parse("myorg/alpine@sha256:1").is_err());
+        assert!(ArtifactCoordinates::parse("org.myorg:my-artifact").is_err());
+        assert!(ArtifactCoordinates::parse("org.myorg::1.0.0").is_err());
+    }
+
+    #[test]
+    fn test_find_artifact_hash() {
+        let logs = r#"[{"package_specific_artifact_id":"org.myorg/a/1.0/a-1.0.pom","artifact_hash":"1"},
+            {"package_specific_artifact_id":"org.myorg/a/1.0/a-1.0.jar","artifact_hash":"2"}]"#;
+        assert_eq!(
+            find_artifact_hash(logs, "org.myorg/a/1.0/a-1.0.jar").unwrap(),
+            "2"
+        );
+        assert!(find_artifact_hash(logs, "org.myorg/a/1.0/a-1.0.war").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_download_artifact() {
+        let server = Server::run();
+        server.expect(
+            Expectation::matching(matchers::request::method_path("GET", "/artifact"))
+                .respond_with(responders::status_code(200).body(CONTENT)),
+        );
+        let tmp_dir = test_util::tests::setup();
+        let output = tmp_dir.join("artifact");
+
+        let mut progress = vec![];
+        let len = download_artifact(
+            &server.url("/artifact").to_string(),
+            &content_hash(),
+            &output,
+            |downloaded, total| progress.push((downloaded, total)),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(len, 10);
+        assert_eq!(std::fs::read(&output).unwrap(), CONTENT);
+        assert_eq!(progress.last(), Some(&(10, Some(10))));
+        assert!(!partial_download_path(&output).exists());
+
+        test_util::tests::teardown(tmp_dir);
+    }
+
+    #[tokio::test]
+    async fn test_download_artifact_resumes_partial_download() {
+        let server = Server::run();
+        server.expect(
+            Expectation::matching(matchers::all_of![
+                matchers::request::method_path("GET", "/artifact"),
+                matchers::request::headers(matchers::contains(("range", "bytes=4-"))),
+            ])
+            .respond_with(
+                responders::status_code(206)
+                    .insert_header("Content-Range", "bytes 4-9/10")
+                    .body(&CONTENT[4..]),
+            ),
+        );
+        let tmp_dir = test_util::tests::setup();
+        let output = tmp_dir.join("artifact");
+        std::fs::write(partial_download_path(&output), &CONTENT[..4]).unwrap();
+
+        let len = download_artifact(
+            &server.url("/artifact").to_string(),
+            &content_hash(),
+            &output,
+            |_, _| {},
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(len, 10);
+        assert_eq!(std::fs::read(&output).unwrap(), CONTENT);
+
+        test_util::tests::teardown(tmp_dir);
+    }
+
+    #[tokio::test]
+    async fn test_download_artifact_with_wrong_hash_is_discarded() {
+        let server = Server::run();
+        server.expect(
+            Expectation::matching(matchers::request::method_path("GET", "/artifact"))
+                .respond_with(responders::status_code(200).body("tampered")),
+        );
+        let tmp_dir = test_util::tests::setup();
+        let output = tmp_dir.join("artifact");
+
+        let error = download_artifact(
+            &server.url("/artifact").to_string(),
+            &content_hash(),
+            &output,
+            |_, _| {},
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(ErrorKind::classify(&error), ErrorKind::VerificationFailed);
+        assert!(!output.exists());
+        assert!(!partial_download_path(&output).exists());
+
+        test_util::tests::teardown(tmp_dir);
+    }
+}
@@ -0,0 +1,222 @@
+/*
+   Copyright 2021 JFrog Ltd
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+*/
+
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use thiserror::Error;
+
+/// The cause of a failed CLI command. Every cause has a stable exit code, so
+/// scripts and CI pipelines can branch on it:
+///
+/// | exit code | cause                 |
+/// |-----------|-----------------------|
+/// | 0         | success               |
+/// | 1         | failure               |
+/// | 2         | usage error           |
+/// | 3         | not found             |
+/// | 4         | verification failed   |
+/// | 5         | node unreachable      |
+/// | 6         | build failed          |
+/// | 7         | policy denied         |
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorKind {
+    Failure,
+    UsageError,
+    NotFound,
+    VerificationFailed,
+    NodeUnreachable,
+    BuildFailed,
+    PolicyDenied,
+}
+
+impl ErrorKind {
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            ErrorKind::Failure => 1,
+            ErrorKind::UsageError => 2,
+            ErrorKind::NotFound => 3,
+            ErrorKind::VerificationFailed => 4,
+            ErrorKind::NodeUnreachable => 5,
+            ErrorKind::BuildFailed => 6,
+            ErrorKind::PolicyDenied => 7,
+        }
+    }
+
+    /// Classify an error by the first cause in its chain that is known.
+    pub fn classify(error: &anyhow::Error) -> ErrorKind {
+        error
+            .chain()
+            .find_map(|cause| {
+                if let Some(cli_error) = cause.downcast_ref::<CliError>() {
+                    Some(cli_error.kind)
+                } else if let Some(node_error) = cause.downcast_ref::<NodeResponseError>() {
+                    Self::from_status(node_error.status)
+                } else if let Some(reqwest_error) = cause.downcast_ref::<reqwest::Error>() {
+                    if reqwest_error.is_connect() || reqwest_error.is_timeout() {
+                        Some(ErrorKind::NodeUnreachable)
+                    } else {
+                        reqwest_error.status().and_then(Self::from_status)
+                    }
+                } else {
+                    None
+                }
+            })
+            .unwrap_or(ErrorKind::Failure)
+    }
+
+    fn from_status(status: StatusCode) -> Option<ErrorKind> {
+        match status {
+            StatusCode::NOT_FOUND => Some(ErrorKind::NotFound),
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => Some(ErrorKind::PolicyDenied),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            serde_json::to_value(self)
+                .ok()
+                .and_then(|value| value.as_str().map(str::to_owned))
+                .unwrap_or_default()
+        )
+    }
+}
+
+/// An error of a CLI command with a known cause.
+#[derive(Debug, Error)]
+#[error("{message}")]
+pub struct CliError {
+    pub kind: ErrorKind,
+    pub message: String,
+}
+
+impl CliError {
+    pub fn new(kind: ErrorKind, message: impl Into<String>) -> Self {
+        CliError {
+            kind,
+            message: message.into(),
+        }
+    }
+}
+
+/// An error response of the node.
+#[derive(Debug, Error)]
+#[error("HTTP status error ({status}) for url ({url}): {message}")]
+pub struct NodeResponseError {
+    pub status: StatusCode,
+    pub url: String,
+    pub message: String,
+}
+
+/// A failed command as it is printed with `--error-format json`.
+#[derive(Debug, Deserialize, Serialize, Eq, PartialEq)]
+pub struct ErrorReport {
+    pub error: ErrorKind,
+    pub exit_code: i32,
+    pub message: String,
+}
+
+impl From<&anyhow::Error> for ErrorReport {
+    fn from(error: &anyhow::Error) -> Self {
+        let kind = ErrorKind::classify(error);
+        ErrorReport {
+            error: kind,
+            exit_code: kind.exit_code(),
+            message: describe(error),
+        }
+    }
+}
+
+/// Join the messages of the error chain, skipping causes that are already
+/// part of the message of the error they caused.
+fn describe(error: &anyhow::Error) -> String {
+    error.chain().fold(String::new(), |message, cause| {
+        let cause = cause.to_string();
+        if message.is_empty() {
+            cause
+        } else if message.contains(&cause) {
+            message
+        } else {
+            format!("{}: {}", message, cause)
+        }
+    })
+}
+
+#[cfg(test)]
+#[cfg(not(tarpaulin_include))]
+mod tests {
+    use super::*;
+    use anyhow::Context;
+
+    #[test]
+    fn test_classify_errors() {
+        let not_found: anyhow::Error = NodeResponseError {
+            status: StatusCode::NOT_FOUND,
+            url: "http://localhost:7888/build/status".to_owned(),
+            message: "build not found".to_owned(),
+        }
+        .into();
+        assert_eq!(ErrorKind::classify(&not_found), ErrorKind::NotFound);
+
+        let denied = Err::<(), _>(NodeResponseError {
+            status: StatusCode::UNAUTHORIZED,
+            url: "http://localhost:7888/secret/set".to_owned(),
+            message: "invalid admin token".to_owned(),
+        })
+        .context("Setting secret failed")
+        .unwrap_err();
+        assert_eq!(ErrorKind::classify(&denied), ErrorKind::PolicyDenied);
+
+        let build_failed = anyhow::Error::from(CliError::new(
+            ErrorKind::BuildFailed,
+            "FAILED - (exit code 1)",
+        ));
+        assert_eq!(ErrorKind::classify(&build_failed), ErrorKind::BuildFailed);
+
+        assert_eq!(
+            ErrorKind::classify(&anyhow::anyhow!("something else")),
+            ErrorKind::Failure
+        );
+    }
+
+    #[test]
+    fn test_error_report() {
+        let error = Err::<(), _>(CliError::new(
+            ErrorKind::VerificationFailed,
+            "hash does not match",
+        ))
+        .context("Download failed")
+        .unwrap_err();
+
+        let report = ErrorReport::from(&error);
+
+        assert_eq!(
+            serde_json::to_value(&report).unwrap(),
+            serde_json::json!({
+                "error": "verification_failed",
+                "exit_code": 4,
+                "message": "Download failed: hash does not match",
+            })
+        );
+        assert_eq!(ErrorKind::NodeUnreachable.to_string(), "node_unreachable");
+    }
+}
@@ -0,0 +1,167 @@
+/*
+   Copyright 2021 JFrog Ltd
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+*/
+
+//! Formatting of the values the CLI prints. The output does not depend on
+//! the locale of the system: timestamps are RFC3339, numbers use no digit
+//! grouping and always use `.` as the decimal separator, so the output of
+//! every command can be parsed the same way everywhere.
+
+use time::format_description::well_known::Rfc3339;
+use time::{OffsetDateTime, UtcOffset};
+
+const BYTE_UNITS: [&str; 5] = ["KiB", "MiB", "GiB", "TiB", "PiB"];
+
+/// The time zone that timestamps are printed in.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum TimeZone {
+    #[default]
+    Utc,
+    Local,
+}
+
+impl TimeZone {
+    pub fn from_local_time(local_time: bool) -> TimeZone {
+        if local_time {
+            TimeZone::Local
+        } else {
+            TimeZone::Utc
+        }
+    }
+}
+
+/// Format a unix timestamp in seconds as RFC3339, e.g.
+/// `2023-02-14T09:30:00Z` or `2023-02-14T10:30:00+01:00`.
+pub fn format_timestamp(timestamp: u64, time_zone: TimeZone) -> String {
+    let Ok(date_time) = OffsetDateTime::from_unix_timestamp(timestamp as i64) else {
+        return timestamp.to_string();
+    };
+    let date_time = match time_zone {
+        TimeZone::Utc => date_time,
+        TimeZone::Local => date_time.to_offset(local_offset(timestamp as i64)),
+    };
+    date_time
+        .format(&Rfc3339)
+        .unwrap_or_else(|_| timestamp.to_string())
+}
+
+/// Parse a timestamp given on the command line, either RFC3339 (e.g.
+/// `2023-02-14T09:30:00Z`) or seconds since the unix epoch. Returns the
+/// unix timestamp in seconds.
+pub fn parse_timestamp(value: &str) -> Option<u64> {
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(seconds);
+    }
+    OffsetDateTime::parse(value, &Rfc3339)
+        .ok()
+        .and_then(|date_time| u64::try_from(date_time.unix_timestamp()).ok())
+}
+
+/// Format a duration in seconds for humans, e.g. `45s`, `3m 20s` or
+/// `2h 5m`. Only the two most significant units are shown.
+pub fn format_duration(seconds: u64) -> String {
+    let units = [
+        (seconds / 86400, "d"),
+        (seconds / 3600 % 24, "h"),
+        (seconds / 60 % 60, "m"),
+        (seconds % 60, "s"),
+    ];
+    let Some(first) = units.iter().position(|(value, _)| *value > 0) else {
+        return "0s".to_owned();
+    };
+    units[first..]
+        .iter()
+        .take(2)
+        .filter(|(value, _)| *value > 0)
+        .map(|(value, unit)| format!("{}{}", value, unit))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Format a number of bytes with a binary unit and one decimal, e.g.
+/// `512 B` or `1.5 MiB`.
+pub fn format_bytes(bytes: u64) -> String {
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < BYTE_UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", value, BYTE_UNITS[unit])
+}
+
+#[cfg(unix)]
+fn local_offset(timestamp: i64) -> UtcOffset {
+    let time = timestamp as libc::time_t;
+    let mut tm = std::mem::MaybeUninit::<libc::tm>::zeroed();
+    // localtime_r is the thread safe variant, it only writes to tm
+    let result = unsafe { libc::localtime_r(&time, tm.as_mut_ptr()) };
+    if result.is_null() {
+        return UtcOffset::UTC;
+    }
+    let tm = unsafe { tm.assume_init() };
+    UtcOffset::from_whole_seconds(tm.tm_gmtoff as i32).unwrap_or(UtcOffset::UTC)
+}
+
+#[cfg(not(unix))]
+fn local_offset(_timestamp: i64) -> UtcOffset {
+    UtcOffset::UTC
+}
+
+#[cfg(test)]
+#[cfg(not(tarpaulin_include))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_timestamp() {
+        assert_eq!(
+            format_timestamp(1676367000, TimeZone::Utc),
+            "2023-02-14T09:30:00Z"
+        );
+        assert!(format_timestamp(1676367000, TimeZone::Local).starts_with("2023-02-1"));
+    }
+
+    #[test]
+    fn test_parse_timestamp() {
+        assert_eq!(parse_timestamp("1676367000"), Some(1676367000));
+        assert_eq!(parse_timestamp("2023-02-14T09:30:00Z"), Some(1676367000));
+        assert_eq!(
+            parse_timestamp("2023-02-14T10:30:00+01:00"),
+            Some(1676367000)
+        );
+        assert_eq!(parse_timestamp("last tuesday"), None);
+    }
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(0), "0s");
+        assert_eq!(format_duration(45), "45s");
+        assert_eq!(format_duration(200), "3m 20s");
+        assert_eq!(format_duration(7500), "2h 5m");
+        assert_eq!(format_duration(7205), "2h");
+        assert_eq!(format_duration(90000), "1d 1h");
+    }
+
+    #[test]
+    fn test_format_bytes() {
+        assert_eq!(format_bytes(512), "512 B");
+        assert_eq!(format_bytes(1536), "1.5 KiB");
+        assert_eq!(format_bytes(3 * 1024 * 1024 * 1024), "3.0 GiB");
+    }
+}
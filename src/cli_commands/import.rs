@@ -0,0 +1,444 @@
+/*
+   Copyright 2021 JFrog Ltd
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+*/
+
+use crate::artifact_service::model::PackageType;
+use crate::node_api::model::request::{ImportedArtifact, RequestImportArtifacts};
+use anyhow::{anyhow, bail, Context, Result};
+use base64::{engine::general_purpose, Engine as _};
+use serde::Deserialize;
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::io::Read;
+use std::path::{Component, Path, PathBuf};
+use std::process::Command;
+use walkdir::WalkDir;
+
+/// The source of artifacts that are imported from a local Docker daemon.
+pub const DOCKER_DAEMON_SOURCE: &str = "docker-daemon";
+/// The source of artifacts that are imported from a local Maven repository.
+pub const MAVEN_REPO_SOURCE: &str = "maven-repo";
+
+const DOCKER_MANIFEST_MEDIA_TYPE: &str = "application/vnd.docker.distribution.manifest.v2+json";
+const DOCKER_CONFIG_MEDIA_TYPE: &str = "application/vnd.docker.container.image.v1+json";
+const DOCKER_LAYER_MEDIA_TYPE: &str = "application/vnd.docker.image.rootfs.diff.tar";
+
+// An entry of the manifest.json file in an archive created by `docker save`.
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct DockerArchiveManifest {
+    config: String,
+    #[serde(default)]
+    repo_tags: Option<Vec<String>>,
+    layers: Vec<String>,
+}
+
+/// A package found in a local Maven repository, with the files that belong to it.
+#[derive(Debug, Eq, PartialEq)]
+pub struct MavenPackage {
+    pub package_specific_id: String,
+    pub files: Vec<(String, PathBuf)>,
+}
+
+impl MavenPackage {
+    /// Read the files of the package into a request to import them.
+    pub fn into_request(self) -> Result<RequestImportArtifacts> {
+        let artifacts = self
+            .files
+            .into_iter()
+            .map(|(package_specific_artifact_id, path)| {
+                let content =
+                    fs::read(&path).with_context(|| format!("Failed to read {:?}", path))?;
+                Ok(imported_artifact(package_specific_artifact_id, &content))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(RequestImportArtifacts {
+            package_type: PackageType::Maven2,
+            package_specific_id: self.package_specific_id,
+            source: MAVEN_REPO_SOURCE.to_owned(),
+            artifacts,
+        })
+    }
+}
+
+/// Export an image from the local Docker daemon with `docker save` and collect
+/// its manifest, config and layers as artifacts to import.
+pub fn collect_docker_daemon_image(image: &str) -> Result<RequestImportArtifacts> {
+    let output = Command::new("docker")
+        .args(["save", image])
+        .output()
+        .context("Failed to run docker save")?;
+    if !output.status.success() {
+        bail!(
+            "docker save failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+
+    parse_docker_archive(image, output.stdout.as_slice())
+}
+
+/// Find all packages in a local Maven repository, e.g. ~/.m2/repository.
+/// Files are grouped by the group/artifact/version directory they are in and
+/// repository metadata that is specific to the local repository is skipped.
+pub fn find_maven_packages(repository_path: &Path) -> Result<Vec<MavenPackage>> {
+    let mut packages: BTreeMap<String, Vec<(String, PathBuf)>> = BTreeMap::new();
+
+    for entry in WalkDir::new(repository_path).sort_by_file_name() {
+        let entry = entry?;
+        if !entry.file_type().is_file() {
+            continue;
+        }
+
+        let relative_path = entry.path().strip_prefix(repository_path)?;
+        let components: Vec<&str> = relative_path
+            .iter()
+            .map(|component| component.to_str())
+            .collect::<Option<_>>()
+            .ok_or_else(|| anyhow!("Invalid path in maven repository: {:?}", relative_path))?;
+
+        if let Some((package_specific_id, package_specific_artifact_id)) =
+            maven_artifact_ids(&components.join("/"))
+        {
+            packages
+                .entry(package_specific_id)
+                .or_default()
+                .push((package_specific_artifact_id, entry.path().to_path_buf()));
+        }
+    }
+
+    Ok(packages
+        .into_iter()
+        .map(|(package_specific_id, files)| MavenPackage {
+            package_specific_id,
+            files,
+        })
+        .collect())
+}
+
+/// Get the package specific id and the package specific artifact id of a file
+/// with the given path in a maven repository layout, e.g.
+/// `com/myorg/my-artifact/1.0.0/my-artifact-1.0.0.jar`. Returns `None` for
+/// files that are not part of a package, like repository metadata.
+pub fn maven_artifact_ids(path: &str) -> Option<(String, String)> {
+    let components: Vec<&str> = path
+        .split('/')
+        .filter(|component| !component.is_empty())
+        .collect();
+    if components.len() < 4 {
+        return None;
+    }
+
+    let (file_name, coordinates) = components.split_last()?;
+    let (version, coordinates) = coordinates.split_last()?;
+    let (artifact_id, group_path) = coordinates.split_last()?;
+    if !file_name.starts_with(&format!("{}-{}", artifact_id, version))
+        || file_name.ends_with(".lastUpdated")
+    {
+        return None;
+    }
+
+    let group_id = group_path.join(".");
+    Some((
+        format!("{}:{}:{}", group_id, artifact_id, version),
+        format!("{}/{}/{}/{}", group_id, artifact_id, version, file_name),
+    ))
+}
+
+fn parse_docker_archive(image: &str, archive: impl Read) -> Result<RequestImportArtifacts> {
+    let (name, tag) = normalize_docker_image(image)?;
+    let package_specific_id = format!("{}:{}", name, tag);
+
+    let mut files: HashMap<PathBuf, Vec<u8>> = HashMap::new();
+    let mut links: HashMap<PathBuf, PathBuf> = HashMap::new();
+    for entry in tar::Archive::new(archive).entries()? {
+        let mut entry = entry?;
+        let path = normalize_archive_path(&entry.path()?);
+        if entry.header().entry_type().is_symlink() {
+            if let Some(target) = entry.link_name()? {
+                let target = path.parent().unwrap_or(Path::new("")).join(target);
+                links.insert(path, normalize_archive_path(&target));
+            }
+        } else if entry.header().entry_type().is_file() {
+            let mut content = Vec::new();
+            entry.read_to_end(&mut content)?;
+            files.insert(path, content);
+        }
+    }
+
+    let read_file = |path: &str| -> Result<&Vec<u8>> {
+        let mut path = normalize_archive_path(Path::new(path));
+        // layers that are shared between images are stored as symlinks
+        while let Some(target) = links.get(&path) {
+            path = target.clone();
+        }
+        files
+            .get(&path)
+            .ok_or_else(|| anyhow!("Missing {:?} in docker image archive", path))
+    };
+
+    let archive_manifests: Vec<DockerArchiveManifest> =
+        serde_json::from_slice(read_file("manifest.json")?)?;
+    let archive_manifest = archive_manifests
+        .iter()
+        .find(|manifest| {
+            manifest.repo_tags.iter().flatten().any(|repo_tag| {
+                normalize_docker_image(repo_tag)
+                    .map(|(repo_name, repo_tag)| repo_name == name && repo_tag == tag)
+                    .unwrap_or(false)
+            })
+        })
+        .or_else(|| archive_manifests.first())
+        .ok_or_else(|| anyhow!("No images found in docker image archive"))?;
+
+    let mut blobs = vec![read_file(&archive_manifest.config)?];
+    for layer in archive_manifest.layers.iter() {
+        blobs.push(read_file(layer)?);
+    }
+
+    let config = blobs[0];
+    let manifest = serde_json::to_vec_pretty(&json!({
+        "schemaVersion": 2,
+        "mediaType": DOCKER_MANIFEST_MEDIA_TYPE,
+        "config": {
+            "mediaType": DOCKER_CONFIG_MEDIA_TYPE,
+            "size": config.len(),
+            "digest": docker_digest(config),
+        },
+        "layers": blobs[1..].iter().map(|layer| json!({
+            "mediaType": DOCKER_LAYER_MEDIA_TYPE,
+            "size": layer.len(),
+            "digest": docker_digest(layer),
+        })).collect::<Vec<_>>(),
+    }))?;
+
+    let mut artifacts = vec![
+        imported_artifact(package_specific_id.clone(), &manifest),
+        imported_artifact(format!("{}@{}", name, docker_digest(&manifest)), &manifest),
+    ];
+    let mut digests = HashSet::new();
+    for blob in blobs {
+        let digest = docker_digest(blob);
+        if digests.insert(digest.clone()) {
+            artifacts.push(imported_artifact(format!("{}@{}", name, digest), blob));
+        }
+    }
+
+    Ok(RequestImportArtifacts {
+        package_type: PackageType::Docker,
+        package_specific_id,
+        source: DOCKER_DAEMON_SOURCE.to_owned(),
+        artifacts,
+    })
+}
+
+// Split an image reference into the repository name, as used by the docker
+// registry API, and the tag.
+fn normalize_docker_image(image: &str) -> Result<(String, String)> {
+    if image.contains('@') {
+        bail!("Images can only be imported by tag: {}", image);
+    }
+
+    let image = image.strip_prefix("docker.io/").unwrap_or(image);
+    let (name, tag) = match image.rfind(':') {
+        Some(position) if !image[position..].contains('/') => {
+            (&image[..position], &image[position + 1..])
+        }
+        _ => (image, "latest"),
+    };
+
+    if name.contains('/') {
+        Ok((name.to_owned(), tag.to_owned()))
+    } else {
+        Ok((format!("library/{}", name), tag.to_owned()))
+    }
+}
+
+fn normalize_archive_path(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            Component::Normal(name) => normalized.push(name),
+            _ => {}
+        }
+    }
+    normalized
+}
+
+fn docker_digest(content: &[u8]) -> String {
+    format!("sha256:{}", hex::encode(Sha256::digest(content)))
+}
+
+pub(crate) fn imported_artifact(
+    package_specific_artifact_id: String,
+    content: &[u8],
+) -> ImportedArtifact {
+    ImportedArtifact {
+        package_specific_artifact_id,
+        content: general_purpose::STANDARD.encode(content),
+    }
+}
+
+#[cfg(test)]
+#[cfg(not(tarpaulin_include))]
+mod tests {
+    use super::*;
+    use crate::util::test_util;
+
+    fn append_file(builder: &mut tar::Builder<Vec<u8>>, path: &str, content: &[u8]) {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(content.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder.append_data(&mut header, path, content).unwrap();
+    }
+
+    fn decode(artifact: &ImportedArtifact) -> Vec<u8> {
+        general_purpose::STANDARD.decode(&artifact.content).unwrap()
+    }
+
+    #[test]
+    fn test_normalize_docker_image() {
+        assert_eq!(
+            normalize_docker_image("alpine").unwrap(),
+            ("library/alpine".to_owned(), "latest".to_owned())
+        );
+        assert_eq!(
+            normalize_docker_image("docker.io/library/alpine:3.17").unwrap(),
+            ("library/alpine".to_owned(), "3.17".to_owned())
+        );
+        assert_eq!(
+            normalize_docker_image("localhost:5000/myorg/app").unwrap(),
+            ("localhost:5000/myorg/app".to_owned(), "latest".to_owned())
+        );
+        assert!(normalize_docker_image("alpine@sha256:1234").is_err());
+    }
+
+    #[test]
+    fn test_parse_docker_archive() {
+        let mut builder = tar::Builder::new(Vec::new());
+        append_file(
+            &mut builder,
+            "manifest.json",
+            br#"[{"Config":"config.json","RepoTags":["alpine:3.17"],"Layers":["abc/layer.tar","def/layer.tar"]}]"#,
+        );
+        append_file(&mut builder, "config.json", b"config");
+        append_file(&mut builder, "abc/layer.tar", b"layer");
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(tar::EntryType::Symlink);
+        header.set_size(0);
+        header.set_cksum();
+        builder
+            .append_link(&mut header, "def/layer.tar", "../abc/layer.tar")
+            .unwrap();
+        let archive = builder.into_inner().unwrap();
+
+        let request = parse_docker_archive("alpine:3.17", archive.as_slice()).unwrap();
+        assert_eq!(request.package_type, PackageType::Docker);
+        assert_eq!(request.package_specific_id, "library/alpine:3.17");
+        assert_eq!(request.source, DOCKER_DAEMON_SOURCE);
+
+        let ids: Vec<String> = request
+            .artifacts
+            .iter()
+            .map(|artifact| artifact.package_specific_artifact_id.clone())
+            .collect();
+        let manifest = decode(&request.artifacts[0]);
+        assert_eq!(
+            ids,
+            vec![
+                "library/alpine:3.17".to_owned(),
+                format!("library/alpine@{}", docker_digest(&manifest)),
+                format!("library/alpine@{}", docker_digest(b"config")),
+                format!("library/alpine@{}", docker_digest(b"layer")),
+            ]
+        );
+        assert_eq!(decode(&request.artifacts[3]), b"layer");
+
+        let manifest: serde_json::Value = serde_json::from_slice(&manifest).unwrap();
+        assert_eq!(manifest["mediaType"], DOCKER_MANIFEST_MEDIA_TYPE);
+        assert_eq!(manifest["config"]["digest"], docker_digest(b"config"));
+        assert_eq!(manifest["layers"].as_array().unwrap().len(), 2);
+        assert_eq!(manifest["layers"][1]["digest"], docker_digest(b"layer"));
+    }
+
+    #[test]
+    fn test_maven_artifact_ids() {
+        assert_eq!(
+            maven_artifact_ids("/org/apache/maven/maven/3.8.6/maven-3.8.6.pom"),
+            Some((
+                "org.apache.maven:maven:3.8.6".to_owned(),
+                "org.apache.maven/maven/3.8.6/maven-3.8.6.pom".to_owned()
+            ))
+        );
+        assert_eq!(
+            maven_artifact_ids("org/apache/maven/maven/maven-metadata.xml"),
+            None
+        );
+        assert_eq!(maven_artifact_ids("maven/3.8.6/maven-3.8.6.pom"), None);
+    }
+
+    #[test]
+    fn test_find_maven_packages() {
+        let tmp_dir = test_util::tests::setup();
+
+        let version_path = tmp_dir.join("com/myorg/my-artifact/1.0.0");
+        fs::create_dir_all(&version_path).unwrap();
+        for file_name in [
+            "my-artifact-1.0.0.jar",
+            "my-artifact-1.0.0.pom",
+            "my-artifact-1.0.0.pom.lastUpdated",
+            "_remote.repositories",
+        ] {
+            fs::write(version_path.join(file_name), file_name).unwrap();
+        }
+        fs::write(
+            tmp_dir.join("com/myorg/my-artifact/maven-metadata-central.xml"),
+            "metadata",
+        )
+        .unwrap();
+
+        let packages = find_maven_packages(&tmp_dir).unwrap();
+        assert_eq!(
+            packages,
+            vec![MavenPackage {
+                package_specific_id: "com.myorg:my-artifact:1.0.0".to_owned(),
+                files: vec![
+                    (
+                        "com.myorg/my-artifact/1.0.0/my-artifact-1.0.0.jar".to_owned(),
+                        version_path.join("my-artifact-1.0.0.jar")
+                    ),
+                    (
+                        "com.myorg/my-artifact/1.0.0/my-artifact-1.0.0.pom".to_owned(),
+                        version_path.join("my-artifact-1.0.0.pom")
+                    ),
+                ],
+            }]
+        );
+
+        let request = packages.into_iter().next().unwrap().into_request().unwrap();
+        assert_eq!(request.package_type, PackageType::Maven2);
+        assert_eq!(request.source, MAVEN_REPO_SOURCE);
+        assert_eq!(decode(&request.artifacts[0]), b"my-artifact-1.0.0.jar");
+
+        test_util::tests::teardown(tmp_dir);
+    }
+}
@@ -0,0 +1,519 @@
+/*
+   Copyright 2021 JFrog Ltd
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+*/
+
+use crate::artifact_service::model::PackageType;
+use crate::cli_commands::import::{imported_artifact, maven_artifact_ids};
+use crate::node_api::model::request::RequestImportArtifacts;
+use anyhow::{bail, Result};
+use reqwest::RequestBuilder;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::Path;
+
+/// The repository managers that packages can be migrated from.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, strum_macros::Display)]
+#[strum(serialize_all = "lowercase")]
+pub enum RepositoryManager {
+    Artifactory,
+    Nexus,
+}
+
+/// A file in a repository of a repository manager.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RemoteArtifact {
+    pub path: String,
+    pub download_url: String,
+    pub sha256: Option<String>,
+}
+
+/// A package in a repository of a repository manager, with the files that
+/// belong to it.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RemotePackage {
+    pub package_specific_id: String,
+    pub artifacts: Vec<(String, RemoteArtifact)>,
+}
+
+/// A maven repository hosted by Artifactory or Nexus.
+pub struct RemoteRepository {
+    manager: RepositoryManager,
+    base_url: String,
+    repository: String,
+    credentials: Option<(String, String)>,
+    client: reqwest::Client,
+}
+
+#[derive(Deserialize)]
+struct ArtifactoryFileList {
+    #[serde(default)]
+    files: Vec<ArtifactoryFile>,
+}
+
+#[derive(Deserialize)]
+struct ArtifactoryFile {
+    uri: String,
+    #[serde(default)]
+    folder: bool,
+    sha2: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct NexusAssetPage {
+    items: Vec<NexusAsset>,
+    continuation_token: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct NexusAsset {
+    path: String,
+    download_url: String,
+    #[serde(default)]
+    checksum: BTreeMap<String, String>,
+}
+
+impl RemoteRepository {
+    /// Credentials are given as `user:password` and are sent with basic
+    /// authentication, which both Artifactory and Nexus support.
+    pub fn new(
+        manager: RepositoryManager,
+        base_url: &str,
+        repository: &str,
+        credentials: Option<&str>,
+    ) -> Self {
+        RemoteRepository {
+            manager,
+            base_url: base_url.trim_end_matches('/').to_owned(),
+            repository: repository.to_owned(),
+            credentials: credentials.map(|credentials| match credentials.split_once(':') {
+                Some((user, password)) => (user.to_owned(), password.to_owned()),
+                None => (credentials.to_owned(), String::new()),
+            }),
+            client: reqwest::Client::new(),
+        }
+    }
+
+    /// The source that is recorded in the transparency logs of migrated artifacts.
+    pub fn source(&self) -> String {
+        self.manager.to_string()
+    }
+
+    /// List all files in the repository.
+    pub async fn list_artifacts(&self) -> Result<Vec<RemoteArtifact>> {
+        match self.manager {
+            RepositoryManager::Artifactory => self.list_artifactory_artifacts().await,
+            RepositoryManager::Nexus => self.list_nexus_artifacts().await,
+        }
+    }
+
+    /// List all packages in the repository, skipping files that are not part of one.
+    pub async fn list_packages(&self) -> Result<Vec<RemotePackage>> {
+        Ok(group_maven_packages(self.list_artifacts().await?))
+    }
+
+    /// Download all files of a package into a request to import them. The
+    /// content of each file is verified against the checksum reported by the
+    /// repository manager, when there is one.
+    pub async fn download_package(
+        &self,
+        package: &RemotePackage,
+    ) -> Result<RequestImportArtifacts> {
+        let mut artifacts = Vec::with_capacity(package.artifacts.len());
+        for (package_specific_artifact_id, remote_artifact) in package.artifacts.iter() {
+            let content = self
+                .authenticate(self.client.get(&remote_artifact.download_url))
+                .send()
+                .await?
+                .error_for_status()?
+                .bytes()
+                .await?;
+
+            if let Some(sha256) = &remote_artifact.sha256 {
+                let actual_sha256 = hex::encode(Sha256::digest(&content));
+                if !sha256.eq_ignore_ascii_case(&actual_sha256) {
+                    bail!(
+                        "Checksum mismatch for {}: expected {}, got {}",
+                        remote_artifact.path,
+                        sha256,
+                        actual_sha256
+                    );
+                }
+            }
+
+            artifacts.push(imported_artifact(
+                package_specific_artifact_id.clone(),
+                &content,
+            ));
+        }
+
+        Ok(RequestImportArtifacts {
+            package_type: PackageType::Maven2,
+            package_specific_id: package.package_specific_id.clone(),
+            source: self.source(),
+            artifacts,
+        })
+    }
+
+    async fn list_artifactory_artifacts(&self) -> Result<Vec<RemoteArtifact>> {
+        let file_list: ArtifactoryFileList = self
+            .authenticate(self.client.get(format!(
+                "{}/api/storage/{}?list&deep=1&listFolders=0",
+                self.base_url, self.repository
+            )))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        Ok(file_list
+            .files
+            .into_iter()
+            .filter(|file| !file.folder)
+            .map(|file| RemoteArtifact {
+                download_url: format!("{}/{}{}", self.base_url, self.repository, file.uri),
+                path: file.uri.trim_start_matches('/').to_owned(),
+                sha256: file.sha2,
+            })
+            .collect())
+    }
+
+    async fn list_nexus_artifacts(&self) -> Result<Vec<RemoteArtifact>> {
+        let mut artifacts = Vec::new();
+        let mut continuation_token: Option<String> = None;
+        loop {
+            let mut query = vec![("repository", self.repository.clone())];
+            if let Some(continuation_token) = continuation_token {
+                query.push(("continuationToken", continuation_token));
+            }
+
+            let page: NexusAssetPage = self
+                .authenticate(
+                    self.client
+                        .get(format!("{}/service/rest/v1/assets", self.base_url))
+                        .query(&query),
+                )
+                .send()
+                .await?
+                .error_for_status()?
+                .json()
+                .await?;
+
+            artifacts.extend(page.items.into_iter().map(|mut asset| RemoteArtifact {
+                path: asset.path.trim_start_matches('/').to_owned(),
+                download_url: asset.download_url,
+                sha256: asset.checksum.remove("sha256"),
+            }));
+
+            match page.continuation_token {
+                Some(token) => continuation_token = Some(token),
+                None => return Ok(artifacts),
+            }
+        }
+    }
+
+    fn authenticate(&self, request: RequestBuilder) -> RequestBuilder {
+        match &self.credentials {
+            Some((user, password)) => request.basic_auth(user, Some(password)),
+            None => request,
+        }
+    }
+}
+
+/// Group the files of a maven repository by the package they belong to.
+pub fn group_maven_packages(artifacts: Vec<RemoteArtifact>) -> Vec<RemotePackage> {
+    let mut packages: BTreeMap<String, Vec<(String, RemoteArtifact)>> = BTreeMap::new();
+    for artifact in artifacts {
+        if let Some((package_specific_id, package_specific_artifact_id)) =
+            maven_artifact_ids(&artifact.path)
+        {
+            packages
+                .entry(package_specific_id)
+                .or_default()
+                .push((package_specific_artifact_id, artifact));
+        }
+    }
+
+    packages
+        .into_iter()
+        .map(|(package_specific_id, artifacts)| RemotePackage {
+            package_specific_id,
+            artifacts,
+        })
+        .collect()
+}
+
+/// The packages that were migrated by earlier runs, so an interrupted
+/// migration can be resumed where it stopped.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct MigrationState {
+    pub completed: BTreeSet<String>,
+}
+
+impl MigrationState {
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Default::default());
+        }
+        Ok(serde_json::from_slice(&fs::read(path)?)?)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        fs::write(path, serde_json::to_vec_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+/// The result of a migration, reconciling the packages found in the source
+/// repository with the packages that are available in Pyrsia.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct MigrationReport {
+    pub source_packages: usize,
+    pub imported: Vec<String>,
+    pub already_migrated: Vec<String>,
+    pub already_present: Vec<String>,
+    pub failed: BTreeMap<String, String>,
+}
+
+impl MigrationReport {
+    /// The number of packages of the source repository that are available in Pyrsia.
+    pub fn available(&self) -> usize {
+        self.imported.len() + self.already_migrated.len() + self.already_present.len()
+    }
+
+    /// The number of packages of the source repository that are not available in Pyrsia.
+    pub fn missing(&self) -> usize {
+        self.source_packages.saturating_sub(self.available())
+    }
+
+    /// Whether every package of the source repository is available in Pyrsia.
+    pub fn is_complete(&self) -> bool {
+        self.missing() == 0
+    }
+}
+
+#[cfg(test)]
+#[cfg(not(tarpaulin_include))]
+mod tests {
+    use super::*;
+    use crate::util::test_util;
+    use httptest::{matchers, responders, Expectation, Server};
+    use serde_json::json;
+
+    fn remote_artifact(path: &str) -> RemoteArtifact {
+        RemoteArtifact {
+            path: path.to_owned(),
+            download_url: format!("http://localhost/{}", path),
+            sha256: None,
+        }
+    }
+
+    #[test]
+    fn test_group_maven_packages() {
+        let packages = group_maven_packages(vec![
+            remote_artifact("com/myorg/my-artifact/1.0.0/my-artifact-1.0.0.pom"),
+            remote_artifact("com/myorg/my-artifact/maven-metadata.xml"),
+            remote_artifact("com/myorg/my-artifact/1.0.0/my-artifact-1.0.0.jar"),
+            remote_artifact("com/myorg/other/2.0/other-2.0.jar"),
+        ]);
+
+        assert_eq!(
+            packages
+                .iter()
+                .map(|package| (
+                    package.package_specific_id.as_str(),
+                    package.artifacts.len()
+                ))
+                .collect::<Vec<_>>(),
+            vec![
+                ("com.myorg:my-artifact:1.0.0", 2),
+                ("com.myorg:other:2.0", 1)
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_list_and_download_artifactory_packages() {
+        let http_server = Server::run();
+        http_server.expect(
+            Expectation::matching(matchers::request::method_path(
+                "GET",
+                "/artifactory/api/storage/libs-release",
+            ))
+            .respond_with(responders::json_encoded(json!({
+                "files": [
+                    {
+                        "uri": "/com/myorg/my-artifact/1.0.0/my-artifact-1.0.0.jar",
+                        "folder": false,
+                        "sha2": hex::encode(Sha256::digest(b"jar")),
+                    },
+                    {
+                        "uri": "/com/myorg/my-artifact/maven-metadata.xml",
+                        "folder": false,
+                    },
+                ]
+            }))),
+        );
+        http_server.expect(
+            Expectation::matching(matchers::request::method_path(
+                "GET",
+                "/artifactory/libs-release/com/myorg/my-artifact/1.0.0/my-artifact-1.0.0.jar",
+            ))
+            .respond_with(responders::status_code(200).body("jar")),
+        );
+
+        let repository = RemoteRepository::new(
+            RepositoryManager::Artifactory,
+            &http_server.url_str("/artifactory/"),
+            "libs-release",
+            Some("admin:password"),
+        );
+
+        let packages = repository.list_packages().await.unwrap();
+        assert_eq!(packages.len(), 1);
+        assert_eq!(
+            packages[0].package_specific_id,
+            "com.myorg:my-artifact:1.0.0"
+        );
+
+        let request = repository.download_package(&packages[0]).await.unwrap();
+        assert_eq!(request.source, "artifactory");
+        assert_eq!(
+            request.artifacts[0].package_specific_artifact_id,
+            "com.myorg/my-artifact/1.0.0/my-artifact-1.0.0.jar"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_list_nexus_artifacts_follows_continuation_token() {
+        let http_server = Server::run();
+        http_server.expect(
+            Expectation::matching(matchers::all_of![
+                matchers::request::method_path("GET", "/service/rest/v1/assets"),
+                matchers::request::query(matchers::url_decoded(matchers::not(matchers::contains(
+                    matchers::key("continuationToken")
+                )))),
+            ])
+            .respond_with(responders::json_encoded(json!({
+                "items": [{
+                    "path": "com/myorg/my-artifact/1.0.0/my-artifact-1.0.0.pom",
+                    "downloadUrl": "http://nexus/pom",
+                    "checksum": { "sha256": "abc" },
+                }],
+                "continuationToken": "next",
+            }))),
+        );
+        http_server.expect(
+            Expectation::matching(matchers::all_of![
+                matchers::request::method_path("GET", "/service/rest/v1/assets"),
+                matchers::request::query(matchers::url_decoded(matchers::contains((
+                    "continuationToken",
+                    "next"
+                )))),
+            ])
+            .respond_with(responders::json_encoded(json!({
+                "items": [{
+                    "path": "com/myorg/my-artifact/1.0.0/my-artifact-1.0.0.jar",
+                    "downloadUrl": "http://nexus/jar",
+                }],
+                "continuationToken": null,
+            }))),
+        );
+
+        let repository = RemoteRepository::new(
+            RepositoryManager::Nexus,
+            &http_server.url_str(""),
+            "maven-releases",
+            None,
+        );
+
+        assert_eq!(
+            repository.list_artifacts().await.unwrap(),
+            vec![
+                RemoteArtifact {
+                    path: "com/myorg/my-artifact/1.0.0/my-artifact-1.0.0.pom".to_owned(),
+                    download_url: "http://nexus/pom".to_owned(),
+                    sha256: Some("abc".to_owned()),
+                },
+                RemoteArtifact {
+                    path: "com/myorg/my-artifact/1.0.0/my-artifact-1.0.0.jar".to_owned(),
+                    download_url: "http://nexus/jar".to_owned(),
+                    sha256: None,
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_download_package_verifies_checksum() {
+        let http_server = Server::run();
+        http_server.expect(
+            Expectation::matching(matchers::request::method_path("GET", "/jar"))
+                .respond_with(responders::status_code(200).body("tampered")),
+        );
+
+        let repository =
+            RemoteRepository::new(RepositoryManager::Nexus, "http://nexus", "releases", None);
+        let package = RemotePackage {
+            package_specific_id: "com.myorg:my-artifact:1.0.0".to_owned(),
+            artifacts: vec![(
+                "com.myorg/my-artifact/1.0.0/my-artifact-1.0.0.jar".to_owned(),
+                RemoteArtifact {
+                    path: "com/myorg/my-artifact/1.0.0/my-artifact-1.0.0.jar".to_owned(),
+                    download_url: http_server.url_str("/jar"),
+                    sha256: Some(hex::encode(Sha256::digest(b"jar"))),
+                },
+            )],
+        };
+
+        let error = repository.download_package(&package).await.unwrap_err();
+        assert!(error.to_string().contains("Checksum mismatch"));
+    }
+
+    #[test]
+    fn test_migration_state_and_report() {
+        let tmp_dir = test_util::tests::setup();
+        let state_path = tmp_dir.join("migration.json");
+
+        let mut state = MigrationState::load(&state_path).unwrap();
+        assert!(state.completed.is_empty());
+        state
+            .completed
+            .insert("com.myorg:my-artifact:1.0.0".to_owned());
+        state.save(&state_path).unwrap();
+        assert_eq!(
+            MigrationState::load(&state_path).unwrap().completed,
+            state.completed
+        );
+
+        let mut report = MigrationReport {
+            source_packages: 2,
+            already_migrated: vec!["com.myorg:my-artifact:1.0.0".to_owned()],
+            ..Default::default()
+        };
+        assert!(!report.is_complete());
+        report
+            .failed
+            .insert("com.myorg:other:2.0".to_owned(), "error".to_owned());
+        assert_eq!(report.missing(), 1);
+        assert!(!report.is_complete());
+
+        test_util::tests::teardown(tmp_dir);
+    }
+}
@@ -21,3 +21,11 @@ pub struct BuildResultResponse {
     pub build_id: Option<String>,
     pub message: Option<String>,
 }
+
+/// The transparency logs of a package as formatted by the node, with the
+/// warning that the node returned for the package, like a deprecation.
+#[derive(Debug, Default)]
+pub struct TransparencyLogResponse {
+    pub logs: String,
+    pub warning: Option<String>,
+}
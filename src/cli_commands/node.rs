@@ -14,17 +14,34 @@
    limitations under the License.
 */
 
-use crate::cli_commands::model::BuildResultResponse;
+use crate::artifact_service::model::{ArtifactCheck, SeedSyncProgress};
+use crate::artifact_service::progress::TransferProgress;
+use crate::blockchain_service::audit::ChainAudit;
+use crate::blockchain_service::explorer::{BlockListQuery, BlockView};
+use crate::build_service::history::{BuildHistoryQuery, BuildRecord};
+use crate::build_service::secrets::SecretDescriptor;
+use crate::cli_commands::error::NodeResponseError;
+use crate::cli_commands::model::{BuildResultResponse, TransparencyLogResponse};
+use crate::docker::tag_history::{TagHistoryEntry, TagHistoryQuery};
+use crate::network::peer_alias::PeerAlias;
+use crate::network::peer_maintenance::DrainingPeer;
+use crate::network::query_metrics::{LookupTrace, QueryKind, QueryKindStats};
+use crate::transparency_log::log::TransparencyLog;
+use crate::transparency_log::node_quorum::NodeChangeProposal;
 use anyhow::{anyhow, Result};
 use async_trait::async_trait;
 use reqwest::Response;
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 use serde_json::Value;
+use std::collections::BTreeMap;
 
 use crate::node_api::model::request::{
-    RequestAddAuthorizedNode, RequestBuildStatus, RequestDockerBuild, RequestDockerLog,
-    RequestMavenBuild, RequestMavenLog, Status,
+    RequestAddAuthorizedNode, RequestAnnounceMaintenance, RequestBuildReproduce, RequestBuildRerun,
+    RequestBuildStatus, RequestCheckPackage, RequestDeprecatePackage, RequestDockerBuild,
+    RequestDockerLog, RequestImportArtifacts, RequestMavenBuild, RequestMavenLog,
+    RequestRemoveAuthorizedNode, RequestRemoveSecret, RequestSeedSync, RequestSetPeerAlias,
+    RequestSetSecret, Status,
 };
 
 use super::config::get_config;
@@ -49,12 +66,160 @@ pub async fn status() -> Result<Status> {
     Ok(response)
 }
 
-pub async fn add_authorized_node(request: RequestAddAuthorizedNode) -> Result<()> {
-    post_and_parse_result_as_text(format!("http://{}/authorized_node", get_url()), request)
+pub async fn transfers() -> Result<Vec<TransferProgress>> {
+    reqwest::get(format!("http://{}/status/transfers", get_url()))
+        .await?
+        .object_or_error_with_body::<Vec<TransferProgress>>()
+        .await
+}
+
+pub async fn query_stats() -> Result<BTreeMap<QueryKind, QueryKindStats>> {
+    reqwest::get(format!("http://{}/network/query-stats", get_url()))
+        .await?
+        .object_or_error_with_body::<BTreeMap<QueryKind, QueryKindStats>>()
+        .await
+}
+
+pub async fn debug_lookup(artifact_id: &str) -> Result<LookupTrace> {
+    reqwest::get(format!(
+        "http://{}/network/debug-lookup/{}",
+        get_url(),
+        artifact_id
+    ))
+    .await?
+    .object_or_error_with_body::<LookupTrace>()
+    .await
+}
+
+pub async fn list_blocks(query: &BlockListQuery) -> Result<Vec<BlockView>> {
+    reqwest::Client::new()
+        .get(format!("http://{}/blocks", get_url()))
+        .query(query)
+        .send()
+        .await?
+        .object_or_error_with_body::<Vec<BlockView>>()
+        .await
+}
+
+pub async fn get_block(id: &str) -> Result<BlockView> {
+    reqwest::get(format!("http://{}/blocks/{}", get_url(), id))
+        .await?
+        .object_or_error_with_body::<BlockView>()
+        .await
+}
+
+pub async fn export_blocks() -> Result<Vec<u8>> {
+    Ok(reqwest::get(format!("http://{}/blocks/export", get_url()))
+        .await?
+        .error_for_status_with_body()
+        .await?
+        .bytes()
+        .await?
+        .to_vec())
+}
+
+pub async fn audit_blocks() -> Result<ChainAudit> {
+    reqwest::get(format!("http://{}/blocks/audit", get_url()))
+        .await?
+        .object_or_error_with_body::<ChainAudit>()
+        .await
+}
+
+pub async fn peer_aliases() -> Result<Vec<PeerAlias>> {
+    reqwest::get(format!("http://{}/peers/aliases", get_url()))
+        .await?
+        .object_or_error_with_body::<Vec<PeerAlias>>()
+        .await
+}
+
+pub async fn set_peer_alias(request: RequestSetPeerAlias) -> Result<()> {
+    reqwest::Client::new()
+        .post(format!("http://{}/peers/alias", get_url()))
+        .bearer_auth(get_admin_token()?)
+        .json(&request)
+        .send()
+        .await?
+        .error_for_status_with_body()
+        .await
+        .map(|_| ())
+}
+
+pub async fn draining_peers() -> Result<Vec<DrainingPeer>> {
+    reqwest::get(format!("http://{}/peers/maintenance", get_url()))
+        .await?
+        .object_or_error_with_body::<Vec<DrainingPeer>>()
+        .await
+}
+
+pub async fn announce_maintenance(request: RequestAnnounceMaintenance) -> Result<()> {
+    reqwest::Client::new()
+        .post(format!("http://{}/maintenance", get_url()))
+        .bearer_auth(get_admin_token()?)
+        .json(&request)
+        .send()
+        .await?
+        .error_for_status_with_body()
         .await
         .map(|_| ())
 }
 
+pub async fn start_seed_sync(request: RequestSeedSync) -> Result<SeedSyncProgress> {
+    reqwest::Client::new()
+        .post(format!("http://{}/seed-sync", get_url()))
+        .bearer_auth(get_admin_token()?)
+        .json(&request)
+        .send()
+        .await?
+        .object_or_error_with_body::<SeedSyncProgress>()
+        .await
+}
+
+pub async fn seed_sync_status() -> Result<SeedSyncProgress> {
+    reqwest::get(format!("http://{}/seed-sync/status", get_url()))
+        .await?
+        .object_or_error_with_body::<SeedSyncProgress>()
+        .await
+}
+
+/// Approve adding an authorized node. Returns the proposal when the change
+/// still needs the approval of other authorized nodes.
+pub async fn add_authorized_node(
+    request: RequestAddAuthorizedNode,
+) -> Result<Option<NodeChangeProposal>> {
+    post_node_change(format!("http://{}/authorized_node", get_url()), request).await
+}
+
+/// Approve removing an authorized node. Returns the proposal when the
+/// change still needs the approval of other authorized nodes.
+pub async fn remove_authorized_node(
+    request: RequestRemoveAuthorizedNode,
+) -> Result<Option<NodeChangeProposal>> {
+    post_node_change(
+        format!("http://{}/authorized_node/remove", get_url()),
+        request,
+    )
+    .await
+}
+
+pub async fn node_change_proposals() -> Result<Vec<NodeChangeProposal>> {
+    reqwest::get(format!("http://{}/authorized_node/proposals", get_url()))
+        .await?
+        .object_or_error_with_body::<Vec<NodeChangeProposal>>()
+        .await
+}
+
+async fn post_node_change<T: Serialize>(
+    node_url: String,
+    request: T,
+) -> Result<Option<NodeChangeProposal>> {
+    let body = post_and_parse_result_as_text(node_url, request).await?;
+    if body.is_empty() {
+        Ok(None)
+    } else {
+        Ok(Some(serde_json::from_str(&body)?))
+    }
+}
+
 pub async fn request_docker_build(request: RequestDockerBuild) -> Result<BuildResultResponse> {
     post_and_parse_json_result_as_object::<RequestDockerBuild, BuildResultResponse>(
         format!("http://{}/build/docker", get_url()),
@@ -67,6 +232,35 @@ pub async fn request_build_status(request: RequestBuildStatus) -> Result<String>
     post_and_parse_result_as_json(format!("http://{}/build/status", get_url()), request).await
 }
 
+pub async fn get_build_history(query: &BuildHistoryQuery) -> Result<Vec<BuildRecord>> {
+    let client = reqwest::Client::new();
+    client
+        .get(format!("http://{}/builds", get_url()))
+        .query(query)
+        .send()
+        .await?
+        .object_or_error_with_body::<Vec<BuildRecord>>()
+        .await
+}
+
+pub async fn request_build_rerun(request: RequestBuildRerun) -> Result<String> {
+    post_and_parse_result_as_json(format!("http://{}/build/rerun", get_url()), request).await
+}
+
+pub async fn request_build_reproduce(request: RequestBuildReproduce) -> Result<String> {
+    post_and_parse_result_as_json(format!("http://{}/build/reproduce", get_url()), request).await
+}
+
+pub async fn request_replay_failed_builds() -> Result<Vec<String>> {
+    let client = reqwest::Client::new();
+    client
+        .post(format!("http://{}/build/replay-failed", get_url()))
+        .send()
+        .await?
+        .object_or_error_with_body::<Vec<String>>()
+        .await
+}
+
 pub async fn request_maven_build(request: RequestMavenBuild) -> Result<BuildResultResponse> {
     post_and_parse_json_result_as_object::<RequestMavenBuild, BuildResultResponse>(
         format!("http://{}/build/docker", get_url()),
@@ -75,12 +269,110 @@ pub async fn request_maven_build(request: RequestMavenBuild) -> Result<BuildResu
     .await
 }
 
-pub async fn inspect_docker_transparency_log(request: RequestDockerLog) -> Result<String> {
-    post_and_parse_result_as_text(format!("http://{}/inspect/docker", get_url()), request).await
+pub async fn inspect_docker_transparency_log(
+    request: RequestDockerLog,
+) -> Result<TransparencyLogResponse> {
+    post_and_parse_transparency_logs(format!("http://{}/inspect/docker", get_url()), request).await
+}
+
+pub async fn inspect_maven_transparency_log(
+    request: RequestMavenLog,
+) -> Result<TransparencyLogResponse> {
+    post_and_parse_transparency_logs(format!("http://{}/inspect/maven", get_url()), request).await
+}
+
+pub async fn get_tag_history(
+    name: &str,
+    tag: &str,
+    query: &TagHistoryQuery,
+) -> Result<Vec<TagHistoryEntry>> {
+    let client = reqwest::Client::new();
+    client
+        .get(format!(
+            "http://{}/v2/library/{}/tags/{}/history",
+            get_url(),
+            name,
+            tag
+        ))
+        .query(query)
+        .send()
+        .await?
+        .object_or_error_with_body::<Vec<TagHistoryEntry>>()
+        .await
+}
+
+pub async fn check_package(request: RequestCheckPackage) -> Result<Vec<ArtifactCheck>> {
+    reqwest::Client::new()
+        .post(format!("http://{}/package/check", get_url()))
+        .json(&request)
+        .send()
+        .await?
+        .object_or_error_with_body::<Vec<ArtifactCheck>>()
+        .await
+}
+
+pub async fn deprecate_package(request: RequestDeprecatePackage) -> Result<TransparencyLog> {
+    reqwest::Client::new()
+        .post(format!("http://{}/package/deprecate", get_url()))
+        .bearer_auth(get_admin_token()?)
+        .json(&request)
+        .send()
+        .await?
+        .object_or_error_with_body::<TransparencyLog>()
+        .await
+}
+
+pub async fn import_artifacts(request: RequestImportArtifacts) -> Result<()> {
+    reqwest::Client::new()
+        .post(format!("http://{}/package/import", get_url()))
+        .bearer_auth(get_admin_token()?)
+        .json(&request)
+        .send()
+        .await?
+        .error_for_status_with_body()
+        .await
+        .map(|_| ())
 }
 
-pub async fn inspect_maven_transparency_log(request: RequestMavenLog) -> Result<String> {
-    post_and_parse_result_as_text(format!("http://{}/inspect/maven", get_url()), request).await
+pub async fn set_secret(request: RequestSetSecret) -> Result<()> {
+    reqwest::Client::new()
+        .post(format!("http://{}/secret/set", get_url()))
+        .bearer_auth(get_admin_token()?)
+        .json(&request)
+        .send()
+        .await?
+        .error_for_status_with_body()
+        .await
+        .map(|_| ())
+}
+
+pub async fn remove_secret(request: RequestRemoveSecret) -> Result<()> {
+    reqwest::Client::new()
+        .post(format!("http://{}/secret/remove", get_url()))
+        .bearer_auth(get_admin_token()?)
+        .json(&request)
+        .send()
+        .await?
+        .error_for_status_with_body()
+        .await
+        .map(|_| ())
+}
+
+pub async fn list_secrets() -> Result<Vec<SecretDescriptor>> {
+    reqwest::Client::new()
+        .get(format!("http://{}/secret/list", get_url()))
+        .bearer_auth(get_admin_token()?)
+        .send()
+        .await?
+        .object_or_error_with_body::<Vec<SecretDescriptor>>()
+        .await
+}
+
+// The admin token of the node, which is required to manage secrets and
+// to publish package metadata.
+fn get_admin_token() -> Result<String> {
+    std::env::var("PYRSIA_ADMIN_TOKEN")
+        .map_err(|_| anyhow!("Set PYRSIA_ADMIN_TOKEN to the admin token of the node"))
 }
 
 pub fn get_url() -> String {
@@ -128,6 +420,36 @@ async fn post_and_parse_result_as_text<T: Serialize>(
         .await
 }
 
+async fn post_and_parse_transparency_logs<T: Serialize>(
+    node_url: String,
+    request: T,
+) -> Result<TransparencyLogResponse> {
+    let response = reqwest::Client::new()
+        .post(node_url)
+        .json(&request)
+        .send()
+        .await?
+        .error_for_status_with_body()
+        .await?;
+
+    // warnings are formatted as: 299 - "<warning>"
+    let warning = response
+        .headers()
+        .get("Warning")
+        .and_then(|warning| warning.to_str().ok())
+        .map(|warning| {
+            warning
+                .trim_start_matches("299 - ")
+                .trim_matches('"')
+                .to_owned()
+        });
+
+    Ok(TransparencyLogResponse {
+        logs: response.text().await?,
+        warning,
+    })
+}
+
 async fn post_and_parse_json_result_as_object<T, R>(node_url: String, request: T) -> Result<R>
 where
     T: Serialize,
@@ -183,13 +505,22 @@ impl ErrorResponseWithBody for Response {
         let http_status = self.status();
         let requested_url = self.url().to_string();
         if http_status.is_client_error() || http_status.is_server_error() {
-            let parsed_error: Value = serde_json::from_str(self.text().await?.as_str())?;
-            return Err(anyhow!(
-                "HTTP status error ({}) for url ({}): {}",
-                http_status,
-                requested_url,
-                parsed_error["errors"][0]["message"]
-            ));
+            let body = self.text().await?;
+            // the node describes errors as {"errors": [{"message": ...}]}
+            let message = serde_json::from_str::<Value>(&body)
+                .ok()
+                .and_then(|parsed_error| {
+                    parsed_error["errors"][0]["message"]
+                        .as_str()
+                        .map(str::to_owned)
+                })
+                .unwrap_or(body);
+            return Err(NodeResponseError {
+                status: http_status,
+                url: requested_url,
+                message,
+            }
+            .into());
         }
         Ok(self)
     }
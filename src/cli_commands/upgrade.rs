@@ -0,0 +1,285 @@
+/*
+   Copyright 2021 JFrog Ltd
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+*/
+
+use crate::cli_commands::error::{CliError, ErrorKind};
+use crate::network::peer_version::{PeerVersions, Version};
+use anyhow::{anyhow, bail, Context, Result};
+use base64::{engine::general_purpose, Engine as _};
+use libp2p::identity::ed25519;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// The manifest of the latest release of Pyrsia. Its signature is published
+/// next to it, at the same URL with the `.sig` extension.
+pub const DEFAULT_RELEASE_MANIFEST_URL: &str =
+    "https://github.com/pyrsia/pyrsia/releases/latest/download/release.json";
+/// The command that restarts the node that was installed as a service.
+pub const DEFAULT_RESTART_COMMAND: &str = "systemctl restart pyrsia";
+const SIGNATURE_EXTENSION: &str = "sig";
+
+/// The base64 encoded ed25519 public key that releases are signed with,
+/// which is pinned when the CLI is built for a release.
+pub fn pinned_release_key() -> Option<&'static str> {
+    option_env!("PYRSIA_RELEASE_PUBLIC_KEY")
+}
+
+/// The platform that release binaries are built for, e.g. `linux-x86_64`.
+pub fn current_platform() -> String {
+    format!("{}-{}", std::env::consts::OS, std::env::consts::ARCH)
+}
+
+/// The node binary that is installed next to this executable.
+pub fn default_node_binary() -> Result<PathBuf> {
+    let current_exe = std::env::current_exe()?;
+    let dir = current_exe
+        .parent()
+        .ok_or_else(|| anyhow!("Unable to find the directory of {:?}", current_exe))?;
+    Ok(dir.join(format!("pyrsia_node{}", std::env::consts::EXE_SUFFIX)))
+}
+
+/// A release of Pyrsia with the node binaries for every supported platform.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct ReleaseManifest {
+    pub version: Version,
+    pub binaries: Vec<ReleaseBinary>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct ReleaseBinary {
+    pub platform: String,
+    pub url: String,
+    /// The hex encoded sha256 hash of the binary.
+    pub sha256: String,
+}
+
+impl ReleaseManifest {
+    /// Returns true if this release is newer than the running version.
+    pub fn is_newer(&self) -> bool {
+        Version::parse(&PeerVersions::own_version())
+            .map(|own_version| self.version > own_version)
+            .unwrap_or(true)
+    }
+
+    pub fn binary(&self, platform: &str) -> Result<&ReleaseBinary> {
+        self.binaries
+            .iter()
+            .find(|binary| binary.platform == platform)
+            .ok_or_else(|| anyhow!("Release {} has no binary for {}", self.version, platform))
+    }
+}
+
+/// Fetch the manifest of a release and verify that it is signed with
+/// `release_key`. A manifest without a valid signature is never returned.
+pub async fn fetch_release_manifest(
+    manifest_url: &str,
+    release_key: &str,
+) -> Result<ReleaseManifest> {
+    let release_key = ed25519::PublicKey::decode(
+        &general_purpose::STANDARD
+            .decode(release_key)
+            .context("The release key is not base64 encoded")?,
+    )
+    .context("The release key is not an ed25519 public key")?;
+
+    let manifest = fetch(manifest_url).await?;
+    let signature = fetch(&format!("{}.{}", manifest_url, SIGNATURE_EXTENSION)).await?;
+    let signature = general_purpose::STANDARD
+        .decode(String::from_utf8_lossy(&signature).trim())
+        .context("The signature of the release manifest is not base64 encoded")?;
+    if !release_key.verify(&manifest, &signature) {
+        bail!(CliError::new(
+            ErrorKind::VerificationFailed,
+            format!(
+                "The release manifest at {} is not signed with the release key",
+                manifest_url
+            )
+        ));
+    }
+    serde_json::from_slice(&manifest).context("Invalid release manifest")
+}
+
+/// Download the binary of a release and verify its hash against the signed
+/// manifest.
+pub async fn download_release_binary(binary: &ReleaseBinary) -> Result<Vec<u8>> {
+    let content = fetch(&binary.url).await?;
+    let hash = hex::encode(Sha256::digest(&content));
+    if !hash.eq_ignore_ascii_case(&binary.sha256) {
+        bail!(CliError::new(
+            ErrorKind::VerificationFailed,
+            format!(
+                "The hash {} of the binary at {} does not match the hash {} of the release",
+                hash, binary.url, binary.sha256
+            )
+        ));
+    }
+    Ok(content)
+}
+
+/// Replace the binary at `path` with `content`. The new binary is written
+/// next to the old one and then renamed over it, so the swap is atomic and
+/// a failed upgrade leaves the old binary in place.
+pub fn install_binary(content: &[u8], path: &Path) -> Result<()> {
+    let mut temp_path = path.as_os_str().to_owned();
+    temp_path.push(".upgrade");
+    let temp_path = PathBuf::from(temp_path);
+
+    let result = write_executable(content, &temp_path)
+        .and_then(|_| fs::rename(&temp_path, path).map_err(Into::into));
+    if result.is_err() {
+        let _ = fs::remove_file(&temp_path);
+    }
+    result.with_context(|| format!("Failed to install the new binary at {:?}", path))
+}
+
+fn write_executable(content: &[u8], path: &Path) -> Result<()> {
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o755);
+    let mut file = options.open(path)?;
+    file.write_all(content)?;
+    file.sync_all()?;
+    Ok(())
+}
+
+/// Restart the node with `restart_command`, e.g. `systemctl restart pyrsia`.
+pub fn restart_node(restart_command: &str) -> Result<()> {
+    let mut words = restart_command.split_whitespace();
+    let program = words
+        .next()
+        .ok_or_else(|| anyhow!("The restart command is empty"))?;
+    let status = Command::new(program).args(words).status()?;
+    if !status.success() {
+        bail!("'{}' failed with {}", restart_command, status);
+    }
+    Ok(())
+}
+
+async fn fetch(url: &str) -> Result<Vec<u8>> {
+    let response = reqwest::get(url).await?.error_for_status()?;
+    Ok(response.bytes().await?.to_vec())
+}
+
+#[cfg(test)]
+#[cfg(not(tarpaulin_include))]
+mod tests {
+    use super::*;
+    use crate::util::test_util;
+    use httptest::{matchers, responders, Expectation, Server};
+
+    fn sign_manifest(keypair: &ed25519::Keypair, manifest: &ReleaseManifest) -> (String, String) {
+        let manifest = serde_json::to_string(manifest).unwrap();
+        let signature = general_purpose::STANDARD.encode(keypair.sign(manifest.as_bytes()));
+        (manifest, signature)
+    }
+
+    fn release_manifest(server: &Server, content: &[u8]) -> ReleaseManifest {
+        ReleaseManifest {
+            version: Version::new(99, 0, 0),
+            binaries: vec![ReleaseBinary {
+                platform: current_platform(),
+                url: server.url("/pyrsia_node").to_string(),
+                sha256: hex::encode(Sha256::digest(content)),
+            }],
+        }
+    }
+
+    #[tokio::test]
+    async fn test_upgrade_with_signed_release() {
+        let server = Server::run();
+        let keypair = ed25519::Keypair::generate();
+        let manifest = release_manifest(&server, b"new binary");
+        let (manifest_json, signature) = sign_manifest(&keypair, &manifest);
+        server.expect(
+            Expectation::matching(matchers::request::method_path("GET", "/release.json"))
+                .respond_with(responders::status_code(200).body(manifest_json)),
+        );
+        server.expect(
+            Expectation::matching(matchers::request::method_path("GET", "/release.json.sig"))
+                .respond_with(responders::status_code(200).body(signature)),
+        );
+        server.expect(
+            Expectation::matching(matchers::request::method_path("GET", "/pyrsia_node"))
+                .respond_with(responders::status_code(200).body("new binary")),
+        );
+        let release_key = general_purpose::STANDARD.encode(keypair.public().encode());
+
+        let fetched_manifest =
+            fetch_release_manifest(&server.url("/release.json").to_string(), &release_key)
+                .await
+                .unwrap();
+        assert_eq!(fetched_manifest, manifest);
+        assert!(fetched_manifest.is_newer());
+
+        let content =
+            download_release_binary(fetched_manifest.binary(&current_platform()).unwrap())
+                .await
+                .unwrap();
+
+        let tmp_dir = test_util::tests::setup();
+        let binary_path = tmp_dir.join("pyrsia_node");
+        fs::write(&binary_path, "old binary").unwrap();
+        install_binary(&content, &binary_path).unwrap();
+        assert_eq!(fs::read(&binary_path).unwrap(), b"new binary");
+        assert_eq!(fs::read_dir(&tmp_dir).unwrap().count(), 1);
+
+        test_util::tests::teardown(tmp_dir);
+    }
+
+    #[tokio::test]
+    async fn test_release_signed_with_other_key_is_refused() {
+        let server = Server::run();
+        let manifest = release_manifest(&server, b"new binary");
+        let (manifest_json, signature) = sign_manifest(&ed25519::Keypair::generate(), &manifest);
+        server.expect(
+            Expectation::matching(matchers::request::method_path("GET", "/release.json"))
+                .respond_with(responders::status_code(200).body(manifest_json)),
+        );
+        server.expect(
+            Expectation::matching(matchers::request::method_path("GET", "/release.json.sig"))
+                .respond_with(responders::status_code(200).body(signature)),
+        );
+        let release_key =
+            general_purpose::STANDARD.encode(ed25519::Keypair::generate().public().encode());
+
+        assert!(
+            fetch_release_manifest(&server.url("/release.json").to_string(), &release_key)
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_binary_with_wrong_hash_is_refused() {
+        let server = Server::run();
+        let manifest = release_manifest(&server, b"new binary");
+        server.expect(
+            Expectation::matching(matchers::request::method_path("GET", "/pyrsia_node"))
+                .respond_with(responders::status_code(200).body("tampered binary")),
+        );
+
+        assert!(
+            download_release_binary(manifest.binary(&current_platform()).unwrap())
+                .await
+                .is_err()
+        );
+        assert!(manifest.binary("plan9-mips").is_err());
+    }
+}
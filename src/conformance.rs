@@ -0,0 +1,311 @@
+/*
+   Copyright 2021 JFrog Ltd
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+*/
+
+//! Canonical test vectors for the identifiers and payloads that Pyrsia nodes
+//! exchange. Alternative client implementations can check their results
+//! against these vectors to verify that they interoperate with Pyrsia nodes:
+//!
+//! * [`ARTIFACT_ID_VECTORS`]: the coordinates of a package in a package
+//!   manager, the package specific artifact id that they map to, and the
+//!   artifact id derived from the hash of the artifact
+//! * [`HASH_VECTORS`]: the content of an artifact and its hash
+//! * [`signature_payload_vectors`]: a transparency log and the payload that is
+//!   signed when it is published on the blockchain
+//!
+//! The vectors are a compatibility contract. Changing one breaks every client
+//! that verifies against it, so they are only changed together with a new
+//! major release.
+
+use crate::artifact_service::model::PackageType;
+use crate::transparency_log::log::{Operation, TransparencyLog};
+
+/// The coordinates of an artifact as they are requested from a package
+/// manager facade of the node.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum PackageCoordinates {
+    /// The name of an image and a tag or digest, as they appear in the
+    /// `/v2/<name>/manifests/<reference>` path of the Docker Registry API.
+    Docker {
+        name: &'static str,
+        reference: &'static str,
+    },
+    /// The path of an artifact in the Maven repository API.
+    Maven2 { path: &'static str },
+}
+
+impl PackageCoordinates {
+    pub fn package_type(&self) -> PackageType {
+        match self {
+            PackageCoordinates::Docker { .. } => PackageType::Docker,
+            PackageCoordinates::Maven2 { .. } => PackageType::Maven2,
+        }
+    }
+}
+
+/// Package coordinates and the identifiers that they map to.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ArtifactIdVector {
+    pub coordinates: PackageCoordinates,
+    pub package_specific_artifact_id: &'static str,
+    /// The hex encoded sha256 hash of the artifact.
+    pub artifact_hash: &'static str,
+    /// The id under which the artifact is stored and provided on the network.
+    pub artifact_id: &'static str,
+}
+
+/// The content of an artifact and its hex encoded sha256 hash.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct HashVector {
+    pub content: &'static [u8],
+    pub artifact_hash: &'static str,
+}
+
+/// A transparency log and the payload that is signed when the log is
+/// published on the blockchain, with the privacy salt of the publishing node
+/// if it hides package names.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SignaturePayloadVector {
+    pub transparency_log: TransparencyLog,
+    pub privacy_salt: Option<&'static str>,
+    pub payload: &'static str,
+}
+
+pub const ARTIFACT_ID_VECTORS: &[ArtifactIdVector] = &[
+    ArtifactIdVector {
+        coordinates: PackageCoordinates::Docker {
+            name: "alpine",
+            reference: "3.16",
+        },
+        package_specific_artifact_id: "library/alpine:3.16",
+        artifact_hash: "b94d27b9934d3e08a52e52d7da7dabfac484efe37a5380ee9088f7ace2efcde9",
+        artifact_id: "1220049da052634feb56ce6ec0bc648c672011edff1cb272b53113bbc90a8f00249c",
+    },
+    ArtifactIdVector {
+        coordinates: PackageCoordinates::Docker {
+            name: "alpine",
+            reference: "sha256:1e014f84205d569a5cc3be4e108ca614055f7e21d11928946113ab3f36054801",
+        },
+        package_specific_artifact_id:
+            "library/alpine@sha256:1e014f84205d569a5cc3be4e108ca614055f7e21d11928946113ab3f36054801",
+        artifact_hash: "1e014f84205d569a5cc3be4e108ca614055f7e21d11928946113ab3f36054801",
+        artifact_id: "1220b2da2edd980aed41421c0e065af627b47bb4e600e1013008579d064a006cc9eb",
+    },
+    ArtifactIdVector {
+        coordinates: PackageCoordinates::Docker {
+            name: "bitnami/redis",
+            reference: "7.0",
+        },
+        package_specific_artifact_id: "bitnami/redis:7.0",
+        artifact_hash: "7ea646958715ed687aa9ac2f5d785feb1a93411f4f25fdd6c7fcc6ab07fdf0e3",
+        artifact_id: "1220c6dbb446acc2690a333a997d9d3e2183c918a7350c50efa9e8b35c34a625bf6b",
+    },
+    ArtifactIdVector {
+        coordinates: PackageCoordinates::Maven2 {
+            path: "/maven2/com/company/test/1.0/test-1.0.jar",
+        },
+        package_specific_artifact_id: "com.company/test/1.0/test-1.0.jar",
+        artifact_hash: "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855",
+        artifact_id: "1220cd372fb85148700fa88095e3492d3f9f5beb43e555e5ff26d95f5a6adc36f8e6",
+    },
+];
+
+pub const HASH_VECTORS: &[HashVector] = &[
+    HashVector {
+        content: b"",
+        artifact_hash: "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855",
+    },
+    HashVector {
+        content: b"hello world",
+        artifact_hash: "b94d27b9934d3e08a52e52d7da7dabfac484efe37a5380ee9088f7ace2efcde9",
+    },
+    HashVector {
+        content: &[0, 1, 2, 3, 254, 255],
+        artifact_hash: "7ea646958715ed687aa9ac2f5d785feb1a93411f4f25fdd6c7fcc6ab07fdf0e3",
+    },
+];
+
+/// The transparency logs and the payloads that are signed when they are
+/// published.
+pub fn signature_payload_vectors() -> Vec<SignaturePayloadVector> {
+    let transparency_log = TransparencyLog {
+        id: "8f2a3c1e-5b7d-4e9f-a1c3-6d8e0f2b4a69".to_owned(),
+        package_type: Some(PackageType::Docker),
+        package_specific_id: "alpine:3.16".to_owned(),
+        num_artifacts: 1,
+        package_specific_artifact_id:
+            "library/alpine@sha256:1e014f84205d569a5cc3be4e108ca614055f7e21d11928946113ab3f36054801"
+                .to_owned(),
+        artifact_hash: "1e014f84205d569a5cc3be4e108ca614055f7e21d11928946113ab3f36054801"
+            .to_owned(),
+        source_hash: "".to_owned(),
+        artifact_id: "1220b2da2edd980aed41421c0e065af627b47bb4e600e1013008579d064a006cc9eb"
+            .to_owned(),
+        source_id: "".to_owned(),
+        timestamp: 1666000000,
+        operation: Operation::AddArtifact,
+        node_id: "12D3KooWHkXsLhCbkpDH4KZWnNWBN9eftWAHhp8TJtoEXYuH2R1h".to_owned(),
+        node_public_key: "".to_owned(),
+        successor: None,
+        pinning_snapshot: None,
+        signature: None,
+    };
+    vec![
+        SignaturePayloadVector {
+            transparency_log: transparency_log.clone(),
+            privacy_salt: None,
+            payload: r#"{"id":"8f2a3c1e-5b7d-4e9f-a1c3-6d8e0f2b4a69","package_type":"Docker","package_specific_id":"alpine:3.16","num_artifacts":1,"package_specific_artifact_id":"library/alpine@sha256:1e014f84205d569a5cc3be4e108ca614055f7e21d11928946113ab3f36054801","artifact_hash":"1e014f84205d569a5cc3be4e108ca614055f7e21d11928946113ab3f36054801","source_hash":"","artifact_id":"1220b2da2edd980aed41421c0e065af627b47bb4e600e1013008579d064a006cc9eb","source_id":"","timestamp":1666000000,"operation":"AddArtifact","node_id":"12D3KooWHkXsLhCbkpDH4KZWnNWBN9eftWAHhp8TJtoEXYuH2R1h","node_public_key":""}"#,
+        },
+        SignaturePayloadVector {
+            transparency_log,
+            privacy_salt: Some("org_salt"),
+            payload: r#"{"id":"8f2a3c1e-5b7d-4e9f-a1c3-6d8e0f2b4a69","package_type":"Docker","package_specific_id":"569e27fd0aaf362412a6ce2635b1afd3137625ba2d423a9884d6f88b6ad046bf","num_artifacts":1,"package_specific_artifact_id":"40b3782ffb1cde0a4edc90d1b11e0e2cf41be9576efaf87df35dd53caa496684","artifact_hash":"1e014f84205d569a5cc3be4e108ca614055f7e21d11928946113ab3f36054801","source_hash":"","artifact_id":"1220b2da2edd980aed41421c0e065af627b47bb4e600e1013008579d064a006cc9eb","source_id":"","timestamp":1666000000,"operation":"AddArtifact","node_id":"12D3KooWHkXsLhCbkpDH4KZWnNWBN9eftWAHhp8TJtoEXYuH2R1h","node_public_key":""}"#,
+        },
+    ]
+}
+
+#[cfg(test)]
+#[cfg(not(tarpaulin_include))]
+mod tests {
+    use super::*;
+    use crate::transparency_log::log::{derive_artifact_id, TransparencyLogService};
+    use crate::util::test_util;
+    use sha2::{Digest, Sha256};
+
+    #[test]
+    fn test_artifact_id_vectors() {
+        for vector in ARTIFACT_ID_VECTORS {
+            assert_eq!(
+                derive_artifact_id(vector.artifact_hash),
+                vector.artifact_id,
+                "{:?}",
+                vector
+            );
+            if let Some(package_specific_artifact_id) =
+                package_specific_artifact_id(&vector.coordinates)
+            {
+                assert_eq!(
+                    package_specific_artifact_id, vector.package_specific_artifact_id,
+                    "{:?}",
+                    vector
+                );
+            }
+        }
+    }
+
+    #[cfg(feature = "docker-facade")]
+    fn docker_package_specific_artifact_id(name: &str, reference: &str) -> Option<String> {
+        Some(
+            crate::docker::v2::handlers::manifests::get_package_specific_artifact_id(
+                name, reference,
+            ),
+        )
+    }
+
+    #[cfg(not(feature = "docker-facade"))]
+    fn docker_package_specific_artifact_id(_name: &str, _reference: &str) -> Option<String> {
+        None
+    }
+
+    #[cfg(feature = "maven-facade")]
+    fn maven2_package_specific_artifact_id(path: &str) -> Option<String> {
+        Some(
+            crate::java::maven2::handlers::maven_artifacts::get_package_specific_artifact_id(path)
+                .unwrap(),
+        )
+    }
+
+    #[cfg(not(feature = "maven-facade"))]
+    fn maven2_package_specific_artifact_id(_path: &str) -> Option<String> {
+        None
+    }
+
+    // The package specific artifact id that the package manager facade maps
+    // the coordinates to, when the facade is compiled in.
+    fn package_specific_artifact_id(coordinates: &PackageCoordinates) -> Option<String> {
+        match coordinates {
+            PackageCoordinates::Docker { name, reference } => {
+                docker_package_specific_artifact_id(name, reference)
+            }
+            PackageCoordinates::Maven2 { path } => maven2_package_specific_artifact_id(path),
+        }
+    }
+
+    #[test]
+    fn test_hash_vectors() {
+        for vector in HASH_VECTORS {
+            assert_eq!(
+                hex::encode(Sha256::digest(vector.content)),
+                vector.artifact_hash,
+                "{:?}",
+                vector
+            );
+        }
+    }
+
+    #[test]
+    fn test_signature_payload_vectors() {
+        let tmp_dir = test_util::tests::setup();
+
+        let (blockchain_event_client, _blockchain_event_receiver) =
+            test_util::tests::create_blockchain_event_client();
+        let transparency_log_service =
+            TransparencyLogService::new(&tmp_dir, blockchain_event_client).unwrap();
+        for vector in signature_payload_vectors() {
+            let transparency_log_service = match vector.privacy_salt {
+                Some(privacy_salt) => transparency_log_service
+                    .clone()
+                    .with_privacy_salt(privacy_salt),
+                None => transparency_log_service.clone(),
+            };
+            assert_eq!(
+                transparency_log_service
+                    .create_payload(&vector.transparency_log)
+                    .unwrap(),
+                vector.payload
+            );
+        }
+
+        test_util::tests::teardown(tmp_dir);
+    }
+
+    // Clients verify against the vectors, so they must never be changed by
+    // accident. Update the fingerprint only for a new major release.
+    #[test]
+    fn test_vectors_never_change() {
+        let mut hasher = Sha256::new();
+        for vector in ARTIFACT_ID_VECTORS {
+            hasher.update(format!(
+                "{:?}\n{}\n{}\n{}\n",
+                vector.coordinates,
+                vector.package_specific_artifact_id,
+                vector.artifact_hash,
+                vector.artifact_id
+            ));
+        }
+        for vector in HASH_VECTORS {
+            hasher.update(vector.content);
+            hasher.update(format!("\n{}\n", vector.artifact_hash));
+        }
+        for vector in signature_payload_vectors() {
+            hasher.update(format!("{:?}\n{}\n", vector.privacy_salt, vector.payload));
+        }
+
+        assert_eq!(
+            hex::encode(hasher.finalize()),
+            "f159f3b3e92c981683f49979928e7c2df635a41d1c8580671e74d956259a9a27"
+        );
+    }
+}
@@ -16,4 +16,6 @@
 
 pub mod constants;
 pub mod error_util;
+pub mod tag_history;
+#[cfg(feature = "docker-facade")]
 pub mod v2;
@@ -14,7 +14,13 @@
    limitations under the License.
 */
 
+use crate::accounting_service::service::QuotaExceeded;
+use crate::artifact_service::budget::BudgetExceeded;
+use crate::artifact_service::tag_policy::TagPolicyError;
 use crate::build_service::error::BuildError;
+use crate::build_service::secrets::SecretStoreError;
+use crate::network::peer_alias::PeerAliasError;
+use crate::subscription_service::service::SubscriptionError;
 use crate::transparency_log::log::TransparencyLogError;
 use log::debug;
 use serde::{Deserialize, Serialize};
@@ -23,6 +29,7 @@ use std::error::Error;
 use std::string::FromUtf8Error;
 use warp::http::StatusCode;
 use warp::reject::Reject;
+use warp::reply::Response;
 use warp::{Rejection, Reply};
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -40,6 +47,22 @@ pub enum RegistryErrorCode {
     BlobUnknown,
     ManifestUnknown,
     BadRequest(String),
+    Unauthorized(String),
+    /// The request is not allowed by a policy of the node, e.g. it would
+    /// move an immutable tag to another manifest.
+    Denied(String),
+    /// The node is too busy right now, the request can be retried after
+    /// `retry_after_secs` seconds.
+    Unavailable {
+        reason: String,
+        retry_after_secs: u64,
+    },
+    /// A usage quota of the client is used up, the request can be retried
+    /// after `retry_after_secs` seconds.
+    TooManyRequests {
+        reason: String,
+        retry_after_secs: u64,
+    },
     Unknown(String),
 }
 
@@ -50,8 +73,48 @@ pub struct RegistryError {
 
 impl From<anyhow::Error> for RegistryError {
     fn from(err: anyhow::Error) -> RegistryError {
+        RegistryError::from_artifact_error(&err, RegistryErrorCode::Unknown(err.to_string()))
+    }
+}
+
+impl From<BudgetExceeded> for RegistryError {
+    fn from(err: BudgetExceeded) -> RegistryError {
         RegistryError {
-            code: RegistryErrorCode::Unknown(err.to_string()),
+            code: RegistryErrorCode::Unavailable {
+                reason: err.to_string(),
+                retry_after_secs: err.retry_after.as_secs().max(1),
+            },
+        }
+    }
+}
+
+impl From<QuotaExceeded> for RegistryError {
+    fn from(err: QuotaExceeded) -> RegistryError {
+        RegistryError {
+            code: RegistryErrorCode::TooManyRequests {
+                reason: err.to_string(),
+                retry_after_secs: err.retry_after.as_secs().max(1),
+            },
+        }
+    }
+}
+
+impl RegistryError {
+    /// The error for a failed retrieval or publication of an artifact:
+    /// `code`, unless the node was too busy to serve the artifact, a quota
+    /// was used up or a policy denied it.
+    pub fn from_artifact_error(err: &anyhow::Error, code: RegistryErrorCode) -> RegistryError {
+        if let Some(budget_exceeded) = err.downcast_ref::<BudgetExceeded>() {
+            return budget_exceeded.clone().into();
+        }
+        if let Some(quota_exceeded) = err.downcast_ref::<QuotaExceeded>() {
+            return quota_exceeded.clone().into();
+        }
+        match err.downcast_ref::<TagPolicyError>() {
+            Some(TagPolicyError::ImmutableTag { .. }) => RegistryError {
+                code: RegistryErrorCode::Denied(err.to_string()),
+            },
+            _ => RegistryError { code },
         }
     }
 }
@@ -59,9 +122,16 @@ impl From<anyhow::Error> for RegistryError {
 impl From<BuildError> for RegistryError {
     fn from(err: BuildError) -> RegistryError {
         match err {
-            BuildError::ArtifactAlreadyExists(_) => RegistryError {
+            BuildError::ArtifactAlreadyExists(_)
+            | BuildError::BuildNotFound(_)
+            | BuildError::PinningSnapshotNotFound(_)
+            | BuildError::RerunNotAllowed(..) => RegistryError {
                 code: RegistryErrorCode::BadRequest(err.to_string()),
             },
+            BuildError::ImmutableTag(..) => RegistryError {
+                code: RegistryErrorCode::Denied(err.to_string()),
+            },
+            BuildError::QuotaExceeded(quota_exceeded) => quota_exceeded.into(),
             _ => RegistryError {
                 code: RegistryErrorCode::Unknown(err.to_string()),
             },
@@ -72,7 +142,49 @@ impl From<BuildError> for RegistryError {
 impl From<TransparencyLogError> for RegistryError {
     fn from(err: TransparencyLogError) -> RegistryError {
         match err {
-            TransparencyLogError::NodeAlreadyExists { .. } => RegistryError {
+            TransparencyLogError::NodeAlreadyExists { .. }
+            | TransparencyLogError::NodeDoesNotExistOrRemoved { .. }
+            | TransparencyLogError::NodeNotAuthorizedToApprove { .. } => RegistryError {
+                code: RegistryErrorCode::BadRequest(err.to_string()),
+            },
+            _ => RegistryError {
+                code: RegistryErrorCode::Unknown(err.to_string()),
+            },
+        }
+    }
+}
+
+impl From<PeerAliasError> for RegistryError {
+    fn from(err: PeerAliasError) -> RegistryError {
+        match err {
+            PeerAliasError::InvalidAlias(_) => RegistryError {
+                code: RegistryErrorCode::BadRequest(err.to_string()),
+            },
+            _ => RegistryError {
+                code: RegistryErrorCode::Unknown(err.to_string()),
+            },
+        }
+    }
+}
+
+impl From<SecretStoreError> for RegistryError {
+    fn from(err: SecretStoreError) -> RegistryError {
+        match err {
+            SecretStoreError::InvalidName(_) => RegistryError {
+                code: RegistryErrorCode::BadRequest(err.to_string()),
+            },
+            _ => RegistryError {
+                code: RegistryErrorCode::Unknown(err.to_string()),
+            },
+        }
+    }
+}
+
+impl From<SubscriptionError> for RegistryError {
+    fn from(err: SubscriptionError) -> RegistryError {
+        match err {
+            SubscriptionError::InvalidSubscription(_)
+            | SubscriptionError::SubscriptionNotFound(_) => RegistryError {
                 code: RegistryErrorCode::BadRequest(err.to_string()),
             },
             _ => RegistryError {
@@ -164,8 +276,44 @@ impl From<hyper::http::Error> for RegistryError {
 
 impl Reject for RegistryError {}
 
+/// The header that holds the id of the build that was requested for an
+/// artifact that is not available yet.
+pub const BUILD_ID_HEADER: &str = "Pyrsia-Build-Id";
+
+/// Responds that an artifact is not available yet, with the id of the build
+/// that will produce it so that clients can follow the build status instead
+/// of retrying blindly.
+pub fn build_requested_response(code: RegistryErrorCode, build_id: &str) -> Response {
+    let error_message = ErrorMessage {
+        code,
+        message: format!(
+            "Build {} was requested, the artifact is available when the build is finished",
+            build_id
+        ),
+    };
+
+    warp::reply::with_header(
+        warp::reply::with_status(
+            warp::reply::json(&ErrorMessages {
+                errors: vec![error_message],
+            }),
+            StatusCode::NOT_FOUND,
+        ),
+        BUILD_ID_HEADER,
+        build_id,
+    )
+    .into_response()
+}
+
+/// Formats a warning for clients as the value of a `Warning` header, using
+/// the miscellaneous persistent warning code 299.
+pub fn warning_header_value(warning: &str) -> String {
+    format!("299 - \"{}\"", warning.replace('"', "'"))
+}
+
 pub async fn custom_recover(err: Rejection) -> Result<impl Reply, Infallible> {
     let mut status_code = StatusCode::INTERNAL_SERVER_ERROR;
+    let mut retry_after_secs = None;
     let mut error_message = ErrorMessage {
         code: RegistryErrorCode::Unknown("".to_string()),
         message: "".to_string(),
@@ -187,6 +335,40 @@ pub async fn custom_recover(err: Rejection) -> Result<impl Reply, Infallible> {
                 error_message.code = RegistryErrorCode::BadRequest(m.clone());
                 error_message.message = m.clone();
             }
+            RegistryErrorCode::Unauthorized(m) => {
+                status_code = StatusCode::UNAUTHORIZED;
+                error_message.code = RegistryErrorCode::Unauthorized(m.clone());
+                error_message.message = m.clone();
+            }
+            RegistryErrorCode::Denied(m) => {
+                status_code = StatusCode::FORBIDDEN;
+                error_message.code = RegistryErrorCode::Denied(m.clone());
+                error_message.message = m.clone();
+            }
+            RegistryErrorCode::Unavailable {
+                reason,
+                retry_after_secs: secs,
+            } => {
+                status_code = StatusCode::SERVICE_UNAVAILABLE;
+                retry_after_secs = Some(*secs);
+                error_message.code = RegistryErrorCode::Unavailable {
+                    reason: reason.clone(),
+                    retry_after_secs: *secs,
+                };
+                error_message.message = reason.clone();
+            }
+            RegistryErrorCode::TooManyRequests {
+                reason,
+                retry_after_secs: secs,
+            } => {
+                status_code = StatusCode::TOO_MANY_REQUESTS;
+                retry_after_secs = Some(*secs);
+                error_message.code = RegistryErrorCode::TooManyRequests {
+                    reason: reason.clone(),
+                    retry_after_secs: *secs,
+                };
+                error_message.message = reason.clone();
+            }
             RegistryErrorCode::Unknown(m) => {
                 error_message.message = m.clone();
             }
@@ -197,13 +379,19 @@ pub async fn custom_recover(err: Rejection) -> Result<impl Reply, Infallible> {
     }
 
     debug!("ErrorMessage: {:?}", error_message);
-    Ok(warp::reply::with_status(
+    let mut response = warp::reply::with_status(
         warp::reply::json(&ErrorMessages {
             errors: vec![error_message],
         }),
         status_code,
     )
-    .into_response())
+    .into_response();
+    if let Some(retry_after_secs) = retry_after_secs {
+        response
+            .headers_mut()
+            .insert("Retry-After", retry_after_secs.into());
+    }
+    Ok(response)
 }
 
 #[cfg(test)]
@@ -213,7 +401,6 @@ mod tests {
     use crate::artifact_service::model::PackageType;
     use std::io;
     use std::str;
-    use warp::reply::Response;
 
     #[test]
     fn from_io_error() {
@@ -349,6 +536,32 @@ mod tests {
         verify_recover_response(response, expected_body, StatusCode::BAD_REQUEST).await;
     }
 
+    #[tokio::test]
+    async fn custom_recover_from_registry_error_denied() {
+        let registry_error: RegistryError =
+            BuildError::ImmutableTag("build_id".to_owned(), "immutable".to_owned()).into();
+
+        let expected_body = serde_json::to_string(&ErrorMessages {
+            errors: vec![ErrorMessage {
+                code: RegistryErrorCode::Denied(
+                    "Refused to publish the artifacts of build with ID build_id: immutable"
+                        .to_string(),
+                ),
+                message: String::from(
+                    "Refused to publish the artifacts of build with ID build_id: immutable",
+                ),
+            }],
+        })
+        .expect("Generating JSON body should not fail.");
+
+        let response = custom_recover(registry_error.into())
+            .await
+            .expect("Reply should be created.")
+            .into_response();
+
+        verify_recover_response(response, expected_body, StatusCode::FORBIDDEN).await;
+    }
+
     #[tokio::test]
     async fn custom_recover_from_registry_error_for_unknown() {
         let registry_error = RegistryError {
@@ -371,6 +584,37 @@ mod tests {
         verify_recover_response(response, expected_body, StatusCode::INTERNAL_SERVER_ERROR).await;
     }
 
+    #[tokio::test]
+    async fn custom_recover_from_exceeded_transfer_budget() {
+        let budget_exceeded = BudgetExceeded {
+            reason: "too busy".to_owned(),
+            retry_after: std::time::Duration::from_secs(3),
+        };
+        let registry_error = RegistryError::from_artifact_error(
+            &anyhow::Error::from(budget_exceeded.clone()),
+            RegistryErrorCode::BlobUnknown,
+        );
+
+        let expected_body = serde_json::to_string(&ErrorMessages {
+            errors: vec![ErrorMessage {
+                code: RegistryErrorCode::Unavailable {
+                    reason: budget_exceeded.to_string(),
+                    retry_after_secs: 3,
+                },
+                message: budget_exceeded.to_string(),
+            }],
+        })
+        .unwrap();
+
+        let response = custom_recover(registry_error.into())
+            .await
+            .expect("Reply should be created.")
+            .into_response();
+
+        assert_eq!(response.headers().get("Retry-After").unwrap(), "3");
+        verify_recover_response(response, expected_body, StatusCode::SERVICE_UNAVAILABLE).await;
+    }
+
     #[derive(Debug)]
     struct UnhandledErrorForCustomRecover {}
     impl Reject for UnhandledErrorForCustomRecover {}
@@ -395,6 +639,21 @@ mod tests {
         verify_recover_response(response, expected_body, StatusCode::INTERNAL_SERVER_ERROR).await;
     }
 
+    #[tokio::test]
+    async fn build_requested() {
+        let response = build_requested_response(RegistryErrorCode::ManifestUnknown, "build_id");
+
+        assert_eq!(response.headers().get(BUILD_ID_HEADER).unwrap(), "build_id");
+        let expected_body = serde_json::to_string(&ErrorMessages {
+            errors: vec![ErrorMessage {
+                code: RegistryErrorCode::ManifestUnknown,
+                message: "Build build_id was requested, the artifact is available when the build is finished".to_owned(),
+            }],
+        })
+        .unwrap();
+        verify_recover_response(response, expected_body, StatusCode::NOT_FOUND).await;
+    }
+
     async fn verify_recover_response(
         response: Response,
         expected_body: String,
@@ -0,0 +1,116 @@
+/*
+   Copyright 2021 JFrog Ltd
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+*/
+
+use crate::transparency_log::log::{Operation, TransparencyLog};
+use serde::{Deserialize, Serialize};
+
+/// The query parameters of a tag history request.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, Eq, PartialEq)]
+pub struct TagHistoryQuery {
+    /// Only return the entry that was in effect at this time, in seconds
+    /// since the unix epoch.
+    pub at: Option<u64>,
+}
+
+/// A period in the history of a docker tag, which starts when the tag was
+/// published with a manifest or removed, and lasts until the next time.
+#[derive(Clone, Debug, Deserialize, Serialize, Eq, PartialEq)]
+pub struct TagHistoryEntry {
+    /// The digest of the manifest, e.g. `sha256:<hex>`. When the tag was
+    /// removed, the digest of the manifest it pointed to before.
+    pub digest: String,
+    /// Either AddArtifact or RemoveArtifact.
+    pub operation: Operation,
+    /// The start of the period, in seconds since the unix epoch.
+    pub since: u64,
+    /// The end of the period, `None` while it lasts.
+    pub until: Option<u64>,
+    /// The id of the transparency log that started the period.
+    pub transparency_log_id: String,
+    /// The node that published the transparency log.
+    pub node_id: String,
+}
+
+impl TagHistoryEntry {
+    /// Whether the period includes `timestamp`.
+    pub fn includes(&self, timestamp: u64) -> bool {
+        self.since <= timestamp && self.until.map_or(true, |until| timestamp < until)
+    }
+}
+
+/// The history of a tag from the AddArtifact and RemoveArtifact logs of its
+/// manifest, oldest first, see
+/// [`TransparencyLogService::find_artifact_history`](crate::transparency_log::log::TransparencyLogService::find_artifact_history).
+pub fn tag_history(transparency_logs: &[TransparencyLog]) -> Vec<TagHistoryEntry> {
+    transparency_logs
+        .iter()
+        .enumerate()
+        .map(|(index, transparency_log)| TagHistoryEntry {
+            digest: format!("sha256:{}", transparency_log.artifact_hash),
+            operation: transparency_log.operation.clone(),
+            since: transparency_log.timestamp,
+            until: transparency_logs
+                .get(index + 1)
+                .map(|next_log| next_log.timestamp),
+            transparency_log_id: transparency_log.id.clone(),
+            node_id: transparency_log.node_id.clone(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+#[cfg(not(tarpaulin_include))]
+mod tests {
+    use super::*;
+    use crate::artifact_service::model::PackageType;
+    use crate::transparency_log::log::AddArtifactRequest;
+
+    fn transparency_log(artifact_hash: &str, timestamp: u64) -> TransparencyLog {
+        TransparencyLog {
+            timestamp,
+            ..TransparencyLog::from(AddArtifactRequest {
+                package_type: PackageType::Docker,
+                package_specific_id: "library/alpine:latest".to_owned(),
+                num_artifacts: 1,
+                package_specific_artifact_id: "library/alpine:latest".to_owned(),
+                artifact_hash: artifact_hash.to_owned(),
+            })
+        }
+    }
+
+    #[test]
+    fn test_tag_history() {
+        let mut removal = transparency_log("bbbb", 30);
+        removal.operation = Operation::RemoveArtifact;
+        let history = tag_history(&[
+            transparency_log("aaaa", 10),
+            transparency_log("bbbb", 20),
+            removal,
+        ]);
+
+        assert_eq!(history.len(), 3);
+        assert_eq!(history[0].digest, "sha256:aaaa");
+        assert_eq!((history[0].since, history[0].until), (10, Some(20)));
+        assert_eq!(history[1].digest, "sha256:bbbb");
+        assert_eq!(history[2].operation, Operation::RemoveArtifact);
+        assert_eq!((history[2].since, history[2].until), (30, None));
+
+        assert!(!history[0].includes(9));
+        assert!(history[0].includes(19));
+        assert!(!history[0].includes(20));
+        assert!(history[2].includes(u64::MAX));
+    }
+}
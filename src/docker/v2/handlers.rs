@@ -16,3 +16,4 @@
 
 pub mod blobs;
 pub mod manifests;
+pub mod tags;
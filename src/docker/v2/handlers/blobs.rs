@@ -14,50 +14,80 @@
    limitations under the License.
 */
 
-use crate::artifact_service::model::PackageType;
-use crate::artifact_service::service::ArtifactService;
-use crate::docker::error_util::{RegistryError, RegistryErrorCode};
+use crate::artifact_service::coordinates::normalize_package_specific_artifact_id;
+use crate::artifact_service::model::{
+    ArtifactOrBuild, ByteRange, PackageType, RangeNotSatisfiable,
+};
+use crate::artifact_service::provider::ArtifactProvider;
+use crate::docker::error_util::{build_requested_response, RegistryError, RegistryErrorCode};
 use log::debug;
 use std::result::Result;
+use warp::hyper::Body;
 use warp::{http::StatusCode, Rejection, Reply};
 
-pub async fn handle_get_blobs(
+/// Serve a blob, or the part of it requested with a `Range` header so that
+/// clients can resume interrupted downloads. A `Range` header that cannot be
+/// parsed is ignored and the complete blob is served.
+pub async fn handle_get_blobs<A: ArtifactProvider>(
     name: String,
     digest: String,
-    mut artifact_service: ArtifactService,
+    range: Option<String>,
+    mut artifact_service: A,
 ) -> Result<impl Reply, Rejection> {
     debug!(
         "Getting blob with digest: {:?}. If not found, a build will be requested",
         &get_package_specific_artifact_id(&name, &digest)
     );
 
-    let blob_content = artifact_service
-        .get_artifact_or_build(
+    let blob_stream = match artifact_service
+        .get_artifact_stream_or_build(
             PackageType::Docker,
             &get_package_specific_artifact_id(&name, &digest),
             &get_package_specific_artifact_id(&name, &digest),
+            range.as_deref().and_then(ByteRange::parse),
         )
         .await
-        .map_err(|_| {
-            warp::reject::custom(RegistryError {
-                code: RegistryErrorCode::BlobUnknown,
-            })
-        })?;
+    {
+        Ok(ArtifactOrBuild::Artifact(blob_stream)) => blob_stream,
+        Ok(ArtifactOrBuild::BuildRequested { build_id }) => {
+            return Ok(build_requested_response(
+                RegistryErrorCode::BlobUnknown,
+                &build_id,
+            ))
+        }
+        Err(error) => {
+            return match error.downcast_ref::<RangeNotSatisfiable>() {
+                Some(RangeNotSatisfiable { len }) => Ok(warp::http::response::Builder::new()
+                    .header("Content-Range", format!("bytes */{}", len))
+                    .status(StatusCode::RANGE_NOT_SATISFIABLE)
+                    .body(Body::empty())
+                    .unwrap()),
+                None => Err(warp::reject::custom(RegistryError::from_artifact_error(
+                    &error,
+                    RegistryErrorCode::BlobUnknown,
+                ))),
+            }
+        }
+    };
 
-    Ok(warp::http::response::Builder::new()
+    let response_builder = warp::http::response::Builder::new()
         .header("Content-Type", "application/octet-stream")
-        .status(StatusCode::OK)
-        .body(blob_content.to_vec())
+        .header("Content-Length", blob_stream.len)
+        .header("Accept-Ranges", "bytes");
+    let response_builder = match blob_stream.content_range {
+        Some(content_range) => response_builder
+            .header("Content-Range", content_range.to_string())
+            .status(StatusCode::PARTIAL_CONTENT),
+        None => response_builder.status(StatusCode::OK),
+    };
+
+    Ok(response_builder
+        .body(Body::wrap_stream(blob_stream.chunks))
         .unwrap())
 }
 
 fn get_package_specific_artifact_id(name: &str, digest: &str) -> String {
-    let combined_tag = format!("{}@{}", name, digest);
-    if combined_tag.contains('/') {
-        combined_tag
-    } else {
-        format!("library/{}", combined_tag)
-    }
+    normalize_package_specific_artifact_id(PackageType::Docker, &format!("{}@{}", name, digest))
 }
 
 #[cfg(test)]
@@ -71,7 +101,6 @@ mod tests {
     use crate::util::test_util;
     use anyhow::Context;
     use hyper::header::HeaderValue;
-    use std::borrow::Borrow;
     use std::collections::HashSet;
     use std::fs::File;
     use std::path::PathBuf;
@@ -108,11 +137,12 @@ mod tests {
 
         let (artifact_service, ..) = test_util::tests::create_artifact_service(&tmp_dir);
 
-        let result = handle_get_blobs(name.to_owned(), hash.to_owned(), artifact_service).await;
+        let result =
+            handle_get_blobs(name.to_owned(), hash.to_owned(), None, artifact_service).await;
 
         assert!(result.is_err());
         let rejection = result.err().unwrap();
-        let registry_error = rejection.find::<RegistryError>().unwrap().borrow();
+        let registry_error = rejection.find::<RegistryError>().unwrap();
         assert_eq!(
             *registry_error,
             RegistryError {
@@ -177,11 +207,24 @@ mod tests {
             &artifact_service.artifact_storage,
             &transparency_log.artifact_id,
         )
+        .await
         .unwrap();
 
-        let result = handle_get_blobs(name.to_owned(), digest, artifact_service).await;
+        let result = handle_get_blobs(
+            name.to_owned(),
+            digest.clone(),
+            None,
+            artifact_service.clone(),
+        )
+        .await;
 
         assert!(result.is_ok());
+        assert_eq!(
+            artifact_service
+                .get_access_stats(package_type, "alpine:latest")
+                .pull_count,
+            1
+        );
 
         let response = result.unwrap().into_response();
         assert_eq!(response.status(), StatusCode::OK);
@@ -190,6 +233,44 @@ mod tests {
             Some(&HeaderValue::from_static("application/octet-stream"))
         );
 
+        let artifact_len = get_file_reader().unwrap().metadata().unwrap().len();
+        let response = handle_get_blobs(
+            name.to_owned(),
+            digest.clone(),
+            Some("bytes=0-9".to_owned()),
+            artifact_service.clone(),
+        )
+        .await
+        .unwrap()
+        .into_response();
+        assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(
+            response.headers().get("Content-Range").unwrap(),
+            &format!("bytes 0-9/{}", artifact_len)
+        );
+        assert_eq!(
+            hyper::body::to_bytes(response.into_body())
+                .await
+                .unwrap()
+                .len(),
+            10
+        );
+
+        let response = handle_get_blobs(
+            name.to_owned(),
+            digest,
+            Some(format!("bytes={}-", artifact_len)),
+            artifact_service,
+        )
+        .await
+        .unwrap()
+        .into_response();
+        assert_eq!(response.status(), StatusCode::RANGE_NOT_SATISFIABLE);
+        assert_eq!(
+            response.headers().get("Content-Range").unwrap(),
+            &format!("bytes */{}", artifact_len)
+        );
+
         test_util::tests::teardown(tmp_dir);
     }
 
@@ -203,12 +284,13 @@ mod tests {
         Ok(reader)
     }
 
-    fn create_artifact(
+    async fn create_artifact(
         artifact_storage: &ArtifactStorage,
         artifact_id: &str,
     ) -> Result<(), anyhow::Error> {
         artifact_storage
-            .push_artifact(&mut get_file_reader()?, artifact_id)
+            .push_artifact(get_file_reader()?, artifact_id)
+            .await
             .context("Error while pushing artifact")
     }
 }
@@ -14,18 +14,21 @@
    limitations under the License.
 */
 
-use crate::artifact_service::model::PackageType;
-use crate::artifact_service::service::ArtifactService;
-use crate::docker::error_util::{RegistryError, RegistryErrorCode};
+use crate::artifact_service::coordinates::normalize_package_specific_artifact_id;
+use crate::artifact_service::model::{ArtifactOrBuild, PackageType};
+use crate::artifact_service::provider::ArtifactProvider;
+use crate::docker::error_util::{
+    build_requested_response, warning_header_value, RegistryError, RegistryErrorCode,
+};
 use log::debug;
 use warp::http::StatusCode;
 use warp::{Rejection, Reply};
 
 // Handles GET endpoint documented at https://docs.docker.com/registry/spec/api/#manifest
-pub async fn fetch_manifest(
+pub async fn fetch_manifest<A: ArtifactProvider>(
     name: String,
     tag: String,
-    mut artifact_service: ArtifactService,
+    mut artifact_service: A,
 ) -> Result<impl Reply, Rejection> {
     debug!(
         "Fetching manifest for {}",
@@ -38,71 +41,89 @@ pub async fn fetch_manifest(
             &get_package_specific_artifact_id(&name, &tag),
         )
         .await
-        .map_err(|_| {
-            warp::reject::custom(RegistryError {
-                code: RegistryErrorCode::ManifestUnknown,
-            })
+        .map_err(|err| {
+            warp::reject::custom(RegistryError::from_artifact_error(
+                &err,
+                RegistryErrorCode::ManifestUnknown,
+            ))
         })?;
 
-    let len = manifest_content.len();
-
-    Ok(warp::http::response::Builder::new()
-        .header(
-            "Content-Type",
-            "application/vnd.docker.distribution.manifest.v2+json",
-        )
-        .header("Content-Length", len)
-        .status(StatusCode::OK)
-        .body(manifest_content.to_vec())
-        .unwrap())
+    manifest_response(
+        manifest_content.to_vec(),
+        artifact_service.get_deprecation_warning(
+            PackageType::Docker,
+            &get_package_specific_artifact_id(&name, &tag),
+        ),
+    )
 }
 
-pub async fn fetch_manifest_or_build(
+pub async fn fetch_manifest_or_build<A: ArtifactProvider>(
     name: String,
     tag: String,
-    mut artifact_service: ArtifactService,
+    mut artifact_service: A,
 ) -> Result<impl Reply, Rejection> {
     debug!(
         "Fetching manifest for {}. If not found, a build will be requested",
         &get_package_specific_artifact_id(&name, &tag)
     );
-    let manifest_content = artifact_service
+    let manifest_content = match artifact_service
         .get_artifact_or_build(
             PackageType::Docker,
             &get_package_specific_artifact_id(&name, &tag),
             &get_package_specific_artifact_id(&name, &tag),
         )
         .await
-        .map_err(|_| {
-            warp::reject::custom(RegistryError {
-                code: RegistryErrorCode::ManifestUnknown,
-            })
-        })?;
+        .map_err(|err| {
+            warp::reject::custom(RegistryError::from_artifact_error(
+                &err,
+                RegistryErrorCode::ManifestUnknown,
+            ))
+        })? {
+        ArtifactOrBuild::Artifact(manifest_content) => manifest_content,
+        ArtifactOrBuild::BuildRequested { build_id } => {
+            return Ok(build_requested_response(
+                RegistryErrorCode::ManifestUnknown,
+                &build_id,
+            ))
+        }
+    };
 
-    let len = manifest_content.len();
+    manifest_response(
+        manifest_content.to_vec(),
+        artifact_service.get_deprecation_warning(
+            PackageType::Docker,
+            &get_package_specific_artifact_id(&name, &tag),
+        ),
+    )
+    .map(Reply::into_response)
+}
 
-    Ok(warp::http::response::Builder::new()
+fn manifest_response(
+    manifest_content: Vec<u8>,
+    deprecation_warning: Option<String>,
+) -> Result<impl Reply, Rejection> {
+    let mut response_builder = warp::http::response::Builder::new()
         .header(
             "Content-Type",
             "application/vnd.docker.distribution.manifest.v2+json",
         )
-        .header("Content-Length", len)
-        .status(StatusCode::OK)
-        .body(manifest_content.to_vec())
-        .unwrap())
+        .header("Content-Length", manifest_content.len())
+        .status(StatusCode::OK);
+    if let Some(deprecation_warning) = deprecation_warning {
+        response_builder =
+            response_builder.header("Warning", warning_header_value(&deprecation_warning));
+    }
+
+    Ok(response_builder.body(manifest_content).unwrap())
 }
 
-fn get_package_specific_artifact_id(name: &str, tag: &str) -> String {
+pub(crate) fn get_package_specific_artifact_id(name: &str, tag: &str) -> String {
     let combined_tag = if tag.starts_with("sha256:") {
         format!("{}@{}", name, tag)
     } else {
         format!("{}:{}", name, tag)
     };
-    if combined_tag.contains('/') {
-        combined_tag
-    } else {
-        format!("library/{}", combined_tag)
-    }
+    normalize_package_specific_artifact_id(PackageType::Docker, &combined_tag)
 }
 
 #[cfg(test)]
@@ -116,7 +137,6 @@ mod tests {
     use crate::util::test_util;
     use anyhow::Context;
     use hyper::header::HeaderValue;
-    use std::borrow::Borrow;
     use std::collections::HashSet;
     use std::fs::File;
     use std::path::PathBuf;
@@ -180,7 +200,7 @@ mod tests {
 
         assert!(result.is_err());
         let rejection = result.err().unwrap();
-        let registry_error = rejection.find::<RegistryError>().unwrap().borrow();
+        let registry_error = rejection.find::<RegistryError>().unwrap();
         assert_eq!(
             *registry_error,
             RegistryError {
@@ -244,6 +264,7 @@ mod tests {
             &artifact_service.artifact_storage,
             &transparency_log.artifact_id,
         )
+        .await
         .unwrap();
 
         let result = fetch_manifest(name.to_string(), tag.to_string(), artifact_service).await;
@@ -276,12 +297,13 @@ mod tests {
         Ok(reader)
     }
 
-    fn create_artifact(
+    async fn create_artifact(
         artifact_storage: &ArtifactStorage,
         artifact_id: &str,
     ) -> Result<(), anyhow::Error> {
         artifact_storage
-            .push_artifact(&mut get_file_reader()?, artifact_id)
+            .push_artifact(get_file_reader()?, artifact_id)
+            .await
             .context("Error while pushing artifact")
     }
 }
@@ -0,0 +1,127 @@
+/*
+   Copyright 2021 JFrog Ltd
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+*/
+
+use super::manifests::get_package_specific_artifact_id;
+use crate::artifact_service::model::PackageType;
+use crate::artifact_service::provider::ArtifactProvider;
+use crate::docker::error_util::{RegistryError, RegistryErrorCode};
+use crate::docker::tag_history::{tag_history, TagHistoryEntry, TagHistoryQuery};
+use log::debug;
+use warp::{Rejection, Reply};
+
+/// Serve every manifest digest that a tag ever pointed to, oldest first, or
+/// only the one it pointed to at the time in the query. Answers questions
+/// like what `alpine:latest` pointed to last week during an incident.
+pub async fn fetch_tag_history<A: ArtifactProvider>(
+    name: String,
+    tag: String,
+    query: TagHistoryQuery,
+    artifact_service: A,
+) -> Result<impl Reply, Rejection> {
+    if tag.starts_with("sha256:") {
+        return Err(warp::reject::custom(RegistryError {
+            code: RegistryErrorCode::BadRequest(format!(
+                "{} is a digest, only tags have a history",
+                tag
+            )),
+        }));
+    }
+
+    let package_specific_artifact_id = get_package_specific_artifact_id(&name, &tag);
+    debug!("Fetching tag history of {}", package_specific_artifact_id);
+
+    let transparency_logs = artifact_service
+        .get_artifact_history(PackageType::Docker, &package_specific_artifact_id)
+        .await
+        .map_err(|err| warp::reject::custom(RegistryError::from(err)))?;
+    if transparency_logs.is_empty() {
+        return Err(warp::reject::custom(RegistryError {
+            code: RegistryErrorCode::ManifestUnknown,
+        }));
+    }
+
+    let history: Vec<TagHistoryEntry> = match query.at {
+        Some(at) => tag_history(&transparency_logs)
+            .into_iter()
+            .filter(|entry| entry.includes(at))
+            .collect(),
+        None => tag_history(&transparency_logs),
+    };
+    Ok(warp::reply::json(&history))
+}
+
+#[cfg(test)]
+#[cfg(not(tarpaulin_include))]
+mod tests {
+    use super::*;
+    use crate::docker::v2::routes::make_docker_routes;
+    use crate::transparency_log::log::{AddArtifactRequest, TransparencyLog};
+    use crate::util::test_util;
+
+    fn latest_log(artifact_hash: &str, timestamp: u64) -> TransparencyLog {
+        TransparencyLog {
+            timestamp,
+            ..TransparencyLog::from(AddArtifactRequest {
+                package_type: PackageType::Docker,
+                package_specific_id: "library/alpine:latest".to_owned(),
+                num_artifacts: 1,
+                package_specific_artifact_id: "library/alpine:latest".to_owned(),
+                artifact_hash: artifact_hash.to_owned(),
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_fetch_tag_history() {
+        let tmp_dir = test_util::tests::setup();
+
+        let (artifact_service, ..) = test_util::tests::create_artifact_service(&tmp_dir);
+        let first_log = latest_log("aaaa", 1000);
+        let second_log = latest_log("bbbb", 2000);
+        artifact_service
+            .transparency_log_service
+            .import_transparency_logs(&[first_log.clone(), second_log.clone()])
+            .unwrap();
+
+        let filter = make_docker_routes(artifact_service);
+        let response = warp::test::request()
+            .path("/v2/library/alpine/tags/latest/history")
+            .reply(&filter)
+            .await;
+        assert_eq!(response.status(), 200);
+        let history: Vec<TagHistoryEntry> = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].digest, "sha256:aaaa");
+        assert_eq!(history[0].until, Some(2000));
+        assert_eq!(history[1].transparency_log_id, second_log.id);
+
+        let response = warp::test::request()
+            .path("/v2/library/alpine/tags/latest/history?at=1500")
+            .reply(&filter)
+            .await;
+        let history: Vec<TagHistoryEntry> = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].transparency_log_id, first_log.id);
+
+        let response = warp::test::request()
+            .path("/v2/library/alpine/tags/3.16/history")
+            .reply(&filter)
+            .await;
+        assert_eq!(response.status(), 500);
+
+        test_util::tests::teardown(tmp_dir);
+    }
+}
@@ -15,14 +15,16 @@
    limitations under the License.
 */
 
-use crate::artifact_service::service::ArtifactService;
+use crate::artifact_service::provider::ArtifactProvider;
+use crate::docker::tag_history::TagHistoryQuery;
 
 use super::handlers::blobs::*;
 use super::handlers::manifests::*;
+use super::handlers::tags::*;
 use warp::Filter;
 
-pub fn make_docker_routes(
-    artifact_service: ArtifactService,
+pub fn make_docker_routes<A: ArtifactProvider>(
+    artifact_service: A,
 ) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
     let empty_json = "{}";
     let v2_base = warp::path("v2")
@@ -50,9 +52,16 @@ pub fn make_docker_routes(
         .and(artifact_service_filter.clone())
         .and_then(fetch_manifest);
 
+    let v2_tag_history = warp::path!("v2" / "library" / String / "tags" / String / "history")
+        .and(warp::get())
+        .and(warp::query::<TagHistoryQuery>())
+        .and(artifact_service_filter.clone())
+        .and_then(fetch_tag_history);
+
     let v2_blobs = warp::path!("v2" / "library" / String / "blobs" / String)
         .and(warp::get())
         .and(warp::path::end())
+        .and(warp::header::optional::<String>("range"))
         .and(artifact_service_filter)
         .and_then(handle_get_blobs);
 
@@ -60,6 +69,7 @@ pub fn make_docker_routes(
         v2_base
             .or(v2_manifests_get)
             .or(v2_manifests_head)
+            .or(v2_tag_history)
             .or(v2_blobs),
     )
 }
@@ -14,16 +14,24 @@
    limitations under the License.
 */
 
-use crate::artifact_service::model::PackageType;
-use crate::artifact_service::service::ArtifactService;
-use crate::docker::error_util::{RegistryError, RegistryErrorCode};
+use crate::artifact_service::model::{
+    ArtifactOrBuild, ByteRange, PackageType, RangeNotSatisfiable,
+};
+use crate::artifact_service::provider::ArtifactProvider;
+use crate::docker::error_util::{
+    build_requested_response, warning_header_value, RegistryError, RegistryErrorCode,
+};
 use anyhow::{anyhow, bail};
 use log::debug;
+use warp::hyper::Body;
 use warp::{http::StatusCode, Rejection, Reply};
 
-pub async fn handle_get_maven_artifact(
+/// Serve a maven artifact, or the part of it requested with a `Range` header
+/// so that clients can resume interrupted downloads.
+pub async fn handle_get_maven_artifact<A: ArtifactProvider>(
     full_path: String,
-    mut artifact_service: ArtifactService,
+    range: Option<String>,
+    mut artifact_service: A,
 ) -> Result<impl Reply, Rejection> {
     debug!("Requesting maven artifact: {}", full_path);
     let package_specific_id = get_package_specific_id(&full_path).map_err(|err| {
@@ -50,33 +58,63 @@ pub async fn handle_get_maven_artifact(
         package_specific_id, package_specific_artifact_id
     );
 
-    let artifact_content = artifact_service
-        .get_artifact_or_build(
+    let artifact_stream = match artifact_service
+        .get_artifact_stream_or_build(
             PackageType::Maven2,
             &package_specific_id,
             &package_specific_artifact_id,
+            range.as_deref().and_then(ByteRange::parse),
         )
         .await
-        .map_err(|err| {
+    {
+        Ok(ArtifactOrBuild::Artifact(artifact_stream)) => artifact_stream,
+        Ok(ArtifactOrBuild::BuildRequested { build_id }) => {
+            return Ok(build_requested_response(
+                RegistryErrorCode::BlobUnknown,
+                &build_id,
+            ))
+        }
+        Err(err) => {
             debug!("Error retrieving artifact: {:?}", err);
-            warp::reject::custom(RegistryError {
-                code: RegistryErrorCode::Unknown(err.to_string()),
-            })
-        })?;
-
-    Ok(warp::http::response::Builder::new()
+            return match err.downcast_ref::<RangeNotSatisfiable>() {
+                Some(RangeNotSatisfiable { len }) => Ok(warp::http::response::Builder::new()
+                    .header("Content-Range", format!("bytes */{}", len))
+                    .status(StatusCode::RANGE_NOT_SATISFIABLE)
+                    .body(Body::empty())
+                    .unwrap()),
+                None => Err(warp::reject::custom(RegistryError::from(err))),
+            };
+        }
+    };
+
+    let mut response_builder = warp::http::response::Builder::new()
         .header("Content-Type", "application/octet-stream")
-        .status(StatusCode::OK)
-        .body(artifact_content)
+        .header("Content-Length", artifact_stream.len)
+        .header("Accept-Ranges", "bytes");
+    response_builder = match artifact_stream.content_range {
+        Some(content_range) => response_builder
+            .header("Content-Range", content_range.to_string())
+            .status(StatusCode::PARTIAL_CONTENT),
+        None => response_builder.status(StatusCode::OK),
+    };
+    if let Some(deprecation_warning) =
+        artifact_service.get_deprecation_warning(PackageType::Maven2, &package_specific_artifact_id)
+    {
+        response_builder =
+            response_builder.header("Warning", warning_header_value(&deprecation_warning));
+    }
+
+    Ok(response_builder
+        .body(Body::wrap_stream(artifact_stream.chunks))
         .unwrap())
 }
 
-fn get_package_specific_id(full_path: &str) -> Result<String, anyhow::Error> {
+pub(crate) fn get_package_specific_id(full_path: &str) -> Result<String, anyhow::Error> {
     let (group_id, version, artifact_id, _file_name) = parse_artifact_from_full_path(full_path)?;
     Ok(format!("{}:{}:{}", group_id, artifact_id, version))
 }
 
-fn get_package_specific_artifact_id(full_path: &str) -> Result<String, anyhow::Error> {
+pub(crate) fn get_package_specific_artifact_id(full_path: &str) -> Result<String, anyhow::Error> {
     let (group_id, version, artifact_id, file_name) = parse_artifact_from_full_path(full_path)?;
     Ok(format!(
         "{}/{}/{}/{}",
@@ -216,9 +254,12 @@ mod tests {
             &artifact_service.artifact_storage,
             &transparency_log.artifact_id,
         )
+        .await
         .unwrap();
 
-        let result = handle_get_maven_artifact(VALID_FULL_PATH.to_string(), artifact_service).await;
+        let result =
+            handle_get_maven_artifact(VALID_FULL_PATH.to_string(), None, artifact_service.clone())
+                .await;
 
         assert!(result.is_ok());
 
@@ -229,6 +270,20 @@ mod tests {
             Some(&HeaderValue::from_static("application/octet-stream"))
         );
 
+        let response = handle_get_maven_artifact(
+            VALID_FULL_PATH.to_string(),
+            Some("bytes=0-9".to_owned()),
+            artifact_service,
+        )
+        .await
+        .unwrap()
+        .into_response();
+        assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(
+            response.headers().get("Content-Length"),
+            Some(&HeaderValue::from_static("10"))
+        );
+
         test_util::tests::teardown(tmp_dir);
     }
 
@@ -242,12 +297,13 @@ mod tests {
         Ok(reader)
     }
 
-    fn create_artifact(
+    async fn create_artifact(
         artifact_storage: &ArtifactStorage,
         artifact_id: &str,
     ) -> Result<(), anyhow::Error> {
         artifact_storage
-            .push_artifact(&mut get_file_reader()?, artifact_id)
+            .push_artifact(get_file_reader()?, artifact_id)
+            .await
             .context("Error while pushing artifact")
     }
 }
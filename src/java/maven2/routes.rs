@@ -15,22 +15,25 @@
 */
 
 use super::handlers::maven_artifacts::handle_get_maven_artifact;
-use crate::artifact_service::service::ArtifactService;
+use crate::artifact_service::provider::ArtifactProvider;
 use log::debug;
 use warp::Filter;
 
-pub fn make_maven_routes(
-    artifact_service: ArtifactService,
+pub fn make_maven_routes<A: ArtifactProvider>(
+    artifact_service: A,
 ) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
     let artifact_service_filter = warp::any().map(move || artifact_service.clone());
 
+    // the path is taken relative to the maven2 prefix, so the routes keep
+    // working when they are served under a base path
     let maven2_root = warp::path("maven2")
-        .and(warp::path::full())
-        .map(|path: warp::path::FullPath| {
-            let full_path: String = path.as_str().to_string();
+        .and(warp::path::tail())
+        .map(|path: warp::path::Tail| {
+            let full_path = format!("/maven2/{}", path.as_str());
             debug!("route full path: {}", full_path);
             full_path
         })
+        .and(warp::header::optional::<String>("range"))
         .and(artifact_service_filter)
         .and_then(handle_get_maven_artifact);
 
@@ -14,18 +14,101 @@
    limitations under the License.
 */
 
+//! Pyrsia node library.
+//!
+//! The node is composed of a number of services that can also be embedded in
+//! other Rust programs. The modules below are grouped per service, and the
+//! types that make up the stable public API of each service are re-exported
+//! at the crate root:
+//!
+//! * [`artifact_service`]: storing, retrieving and verifying artifacts
+//! * [`network`]: the libp2p swarm, the p2p [`Client`] and its event loop
+//! * [`transparency_log`]: the transparency log that records every artifact
+//! * [`build_service`]: requesting and tracking builds from source
+//! * [`blockchain_service`]: distributing transparency logs over the blockchain
+//! * [`subscription_service`]: notifying clients when packages become available
+//! * [`alert_service`]: alerting security teams of suspicious ledger activity
+//! * [`accounting_service`]: the usage quotas of API keys and namespaces
+//!
+//! The parts of a service that do not depend on the other services live in a
+//! crate of their own, which can be used without the rest of the node. Their
+//! modules are re-exported here at the same path:
+//!
+//! * `pyrsia_artifact_service`: the blob stores and the artifact bookkeeping
+//!   around them, e.g. [`artifact_service::blob_store`]
+//! * `pyrsia_network`: the request/response protocols and the peer
+//!   bookkeeping, e.g. [`network::artifact_protocol`]
+//! * `pyrsia_transparency_log`: the Merkle tree and the canonical encoding of
+//!   the transparency log, e.g. [`transparency_log::merkle`]
+//! * `pyrsia_build_service`: the build queue and the build secrets, e.g.
+//!   [`build_service::queue`]
+//!
+//! The services themselves depend on each other, the artifact service on the
+//! transparency log and the build service, and the transparency log on the
+//! blockchain service, so they stay in this crate.
+//!
+//! The [`conformance`] module holds the test vectors that alternative client
+//! implementations can use to verify that they interoperate with Pyrsia nodes.
+//!
+//! The package manager facades and the parts of the node that a pull-only
+//! node does not need can be compiled out with cargo features, which are all
+//! enabled by default:
+//!
+//! * `docker-facade`: the Docker Registry API in `docker::v2`
+//! * `maven-facade`: the Maven repository API in `java::maven2`
+//! * `build-service`: building artifacts from source, i.e. `BuildService` and
+//!   `BuildEventLoop`
+//! * `blockchain-producer`: adding blocks to the blockchain, i.e.
+//!   `BlockchainService::add_payload`
+//! * `web-ui`: the handlers and routes of the node API in `node_api`
+//!
+//! The `test-support` feature, which is disabled by default, adds the
+//! `test_support` module with in-memory and scripted fakes of the services
+//! for testing programs that embed them.
+//!
+//! Items that are reachable only through their module path are considered
+//! internal and may change between minor releases. The re-exports below follow
+//! semver: they are only changed in a backwards incompatible way with a new
+//! major release.
+
 #![allow(mixed_script_confusables)] // This is to allow structs created by a derive macro to have private fields that begin with the grek letter π
 
+pub mod accounting_service;
+pub mod alert_service;
 pub mod artifact_service;
 pub mod blockchain_service;
 pub mod build_service;
 pub mod cli_commands;
+pub mod conformance;
 pub mod docker;
+#[cfg(feature = "maven-facade")]
 pub mod java;
 pub mod logging;
 pub mod network;
 pub mod node_api;
 pub mod peer_metrics;
+pub mod subscription_service;
+#[cfg(any(test, feature = "test-support"))]
+pub mod test_support;
 pub mod transparency_log;
 pub mod util;
 pub mod verification_service;
+
+pub use artifact_service::model::PackageType;
+pub use artifact_service::service::ArtifactService;
+pub use artifact_service::storage::ArtifactStorage;
+pub use blockchain_service::event::{BlockchainEventClient, BlockchainEventLoop};
+pub use blockchain_service::service::BlockchainService;
+pub use build_service::error::BuildError;
+pub use build_service::event::BuildEventClient;
+#[cfg(feature = "build-service")]
+pub use build_service::event_loop::BuildEventLoop;
+pub use build_service::model::{BuildInfo, BuildResult, BuildResultArtifact, BuildStatus};
+#[cfg(feature = "build-service")]
+pub use build_service::service::BuildService;
+pub use network::client::Client;
+pub use network::event_loop::{PyrsiaEvent, PyrsiaEventLoop};
+pub use network::p2p::setup_libp2p_swarm;
+pub use transparency_log::log::{
+    AddArtifactRequest, TransparencyLog, TransparencyLogError, TransparencyLogService,
+};
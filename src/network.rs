@@ -14,12 +14,23 @@
    limitations under the License.
 */
 
-pub mod artifact_protocol;
 pub mod behaviour;
 pub mod blockchain_protocol;
 pub mod build_protocol;
 pub mod build_status_protocol;
 pub mod client;
+pub mod control_message;
 pub mod event_loop;
-pub mod idle_metric_protocol;
 pub mod p2p;
+pub mod peer_capabilities;
+pub mod peer_maintenance;
+pub mod peer_store;
+pub mod seed_sync_protocol;
+
+pub use pyrsia_network::artifact_protocol;
+pub use pyrsia_network::idle_metric_protocol;
+pub use pyrsia_network::peer_alias;
+pub use pyrsia_network::peer_exchange_protocol;
+pub use pyrsia_network::peer_throughput;
+pub use pyrsia_network::peer_version;
+pub use pyrsia_network::query_metrics;
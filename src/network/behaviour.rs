@@ -21,6 +21,10 @@ use crate::network::blockchain_protocol::{
 use crate::network::idle_metric_protocol::{
     IdleMetricExchangeCodec, IdleMetricRequest, IdleMetricResponse,
 };
+use crate::network::peer_exchange_protocol::{
+    PeerExchangeCodec, PeerExchangeRequest, PeerExchangeResponse,
+};
+use crate::network::seed_sync_protocol::{SeedSyncCodec, SeedSyncRequest, SeedSyncResponse};
 
 use crate::network::build_protocol::{BuildExchangeCodec, BuildRequest, BuildResponse};
 use crate::network::build_status_protocol::{
@@ -40,8 +44,8 @@ use libp2p::swarm::NetworkBehaviour;
 ///
 /// * [`Identify`]
 /// * [`Kademlia`]
-/// * [`RequestResponse`] for exchanging artifacts, idle metrics and
-/// blockchain updates
+/// * [`RequestResponse`] for exchanging artifacts, idle metrics,
+///   blockchain updates, known peers and seed snapshots
 #[derive(NetworkBehaviour)]
 #[behaviour(out_event = "PyrsiaNetworkEvent")]
 pub struct PyrsiaNetworkBehaviour {
@@ -54,6 +58,8 @@ pub struct PyrsiaNetworkBehaviour {
     pub idle_metric_request_response: RequestResponse<IdleMetricExchangeCodec>,
     pub blockchain_request_response: RequestResponse<BlockchainExchangeCodec>,
     pub build_status_request_response: RequestResponse<BuildStatusExchangeCodec>,
+    pub peer_exchange_request_response: RequestResponse<PeerExchangeCodec>,
+    pub seed_sync_request_response: RequestResponse<SeedSyncCodec>,
 }
 
 /// Each event in the `PyrsiaNetworkBehaviour` is wrapped in a
@@ -69,6 +75,8 @@ pub enum PyrsiaNetworkEvent {
     IdleMetricRequestResponse(RequestResponseEvent<IdleMetricRequest, IdleMetricResponse>),
     BlockchainRequestResponse(RequestResponseEvent<BlockchainRequest, BlockchainResponse>),
     BuildStatusRequestResponse(RequestResponseEvent<BuildStatusRequest, BuildStatusResponse>),
+    PeerExchangeRequestResponse(RequestResponseEvent<PeerExchangeRequest, PeerExchangeResponse>),
+    SeedSyncRequestResponse(RequestResponseEvent<SeedSyncRequest, SeedSyncResponse>),
 }
 
 impl From<autonat::Event> for PyrsiaNetworkEvent {
@@ -124,3 +132,15 @@ impl From<RequestResponseEvent<BuildStatusRequest, BuildStatusResponse>> for Pyr
         PyrsiaNetworkEvent::BuildStatusRequestResponse(event)
     }
 }
+
+impl From<RequestResponseEvent<PeerExchangeRequest, PeerExchangeResponse>> for PyrsiaNetworkEvent {
+    fn from(event: RequestResponseEvent<PeerExchangeRequest, PeerExchangeResponse>) -> Self {
+        PyrsiaNetworkEvent::PeerExchangeRequestResponse(event)
+    }
+}
+
+impl From<RequestResponseEvent<SeedSyncRequest, SeedSyncResponse>> for PyrsiaNetworkEvent {
+    fn from(event: RequestResponseEvent<SeedSyncRequest, SeedSyncResponse>) -> Self {
+        PyrsiaNetworkEvent::SeedSyncRequestResponse(event)
+    }
+}
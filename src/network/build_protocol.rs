@@ -15,6 +15,7 @@
 */
 
 use crate::artifact_service::model::PackageType;
+use crate::network::control_message::{ControlMessageError, ControlMessageSigner};
 use async_trait::async_trait;
 use futures::prelude::*;
 use libp2p::core::upgrade::{read_length_prefixed, write_length_prefixed, ProtocolName};
@@ -29,8 +30,9 @@ pub struct BuildExchangeProtocol();
 /// for the [`RequestResponse`](crate::RequestResponse) protocol for
 /// exchanging builds. At the moment, the implementation for
 /// encoding/decoding writes all bytes of a single artifact at once.
+/// Build requests are signed control messages, see [`ControlMessageSigner`].
 #[derive(Clone)]
-pub struct BuildExchangeCodec();
+pub struct BuildExchangeCodec(pub ControlMessageSigner);
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct BuildRequest(pub PackageType, pub String);
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -38,7 +40,7 @@ pub struct BuildResponse(pub String);
 
 impl ProtocolName for BuildExchangeProtocol {
     fn protocol_name(&self) -> &[u8] {
-        "/build-exchange/1".as_bytes()
+        "/build-exchange/2".as_bytes()
     }
 }
 
@@ -50,7 +52,7 @@ impl RequestResponseCodec for BuildExchangeCodec {
 
     async fn read_request<T>(
         &mut self,
-        _: &BuildExchangeProtocol,
+        protocol: &BuildExchangeProtocol,
         io: &mut T,
     ) -> io::Result<Self::Request>
     where
@@ -58,20 +60,20 @@ impl RequestResponseCodec for BuildExchangeCodec {
     {
         debug!("Reading BuildRequest...");
 
-        let hash_vec0 = read_length_prefixed(io, 1_000_000).await?;
-        if hash_vec0.is_empty() {
-            return Err(io::ErrorKind::UnexpectedEof.into());
-        }
-
-        let package_type_string = String::from_utf8(hash_vec0).unwrap();
-        let package_type = PackageType::from_str(&package_type_string).unwrap();
-
-        let hash_vec1 = read_length_prefixed(io, 1_000_000).await?;
-        if hash_vec1.is_empty() {
+        let signed_message = read_length_prefixed(io, 1_000_000).await?;
+        if signed_message.is_empty() {
             return Err(io::ErrorKind::UnexpectedEof.into());
         }
 
-        let package_specific_id = String::from_utf8(hash_vec1).unwrap();
+        let payload = self.0.verify(
+            &String::from_utf8_lossy(protocol.protocol_name()),
+            &signed_message,
+        )?;
+        let (package_type_string, package_specific_id): (String, String) =
+            bincode::deserialize(&payload)
+                .map_err(|e| ControlMessageError::InvalidEncoding(e.to_string()))?;
+        let package_type = PackageType::from_str(&package_type_string)
+            .map_err(|e| ControlMessageError::InvalidEncoding(e.to_string()))?;
         debug!(
             "Read BuildRequest: {:?}:{}",
             package_type, package_specific_id
@@ -100,7 +102,7 @@ impl RequestResponseCodec for BuildExchangeCodec {
 
     async fn write_request<T>(
         &mut self,
-        _: &BuildExchangeProtocol,
+        protocol: &BuildExchangeProtocol,
         io: &mut T,
         BuildRequest(package_type, package_specific_id): BuildRequest,
     ) -> io::Result<()>
@@ -112,8 +114,12 @@ impl RequestResponseCodec for BuildExchangeCodec {
             package_type, package_specific_id
         );
 
-        write_length_prefixed(io, package_type.to_string()).await?;
-        write_length_prefixed(io, package_specific_id).await?;
+        let payload = bincode::serialize(&(package_type.to_string(), package_specific_id))
+            .map_err(|e| ControlMessageError::InvalidEncoding(e.to_string()))?;
+        let signed_message = self
+            .0
+            .sign(&String::from_utf8_lossy(protocol.protocol_name()), payload)?;
+        write_length_prefixed(io, signed_message).await?;
         io.close().await?;
 
         Ok(())
@@ -135,3 +141,36 @@ impl RequestResponseCodec for BuildExchangeCodec {
         Ok(())
     }
 }
+
+#[cfg(test)]
+#[cfg(not(tarpaulin_include))]
+mod tests {
+    use super::*;
+    use libp2p::identity::Keypair;
+
+    #[tokio::test]
+    async fn test_build_request_is_signed_and_cannot_be_replayed() {
+        let mut sender = BuildExchangeCodec(ControlMessageSigner::new(Keypair::generate_ed25519()));
+        let mut receiver =
+            BuildExchangeCodec(ControlMessageSigner::new(Keypair::generate_ed25519()));
+        let request = BuildRequest(PackageType::Maven2, "com.company:test:1.0".to_owned());
+
+        let mut bytes = vec![];
+        sender
+            .write_request(&BuildExchangeProtocol(), &mut bytes, request.clone())
+            .await
+            .unwrap();
+
+        let read = receiver
+            .read_request(&BuildExchangeProtocol(), &mut bytes.as_slice())
+            .await
+            .unwrap();
+        assert_eq!(read, request);
+
+        let replayed = receiver
+            .read_request(&BuildExchangeProtocol(), &mut bytes.as_slice())
+            .await
+            .unwrap_err();
+        assert_eq!(replayed.kind(), io::ErrorKind::InvalidData);
+    }
+}
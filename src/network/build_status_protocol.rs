@@ -14,6 +14,7 @@
    limitations under the License.
 */
 
+use crate::network::control_message::ControlMessageSigner;
 use async_trait::async_trait;
 use futures::prelude::*;
 use libp2p::core::upgrade::{read_length_prefixed, write_length_prefixed, ProtocolName};
@@ -23,8 +24,10 @@ use std::io;
 
 #[derive(Debug, Clone)]
 pub struct BuildStatusExchangeProtocol();
+/// Build status requests are signed control messages, see
+/// [`ControlMessageSigner`].
 #[derive(Clone)]
-pub struct BuildStatusExchangeCodec();
+pub struct BuildStatusExchangeCodec(pub ControlMessageSigner);
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct BuildStatusRequest(pub String);
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -32,7 +35,7 @@ pub struct BuildStatusResponse(pub String);
 
 impl ProtocolName for BuildStatusExchangeProtocol {
     fn protocol_name(&self) -> &[u8] {
-        "/build-status-exchange/1".as_bytes()
+        "/build-status-exchange/2".as_bytes()
     }
 }
 
@@ -44,7 +47,7 @@ impl RequestResponseCodec for BuildStatusExchangeCodec {
 
     async fn read_request<T>(
         &mut self,
-        _: &BuildStatusExchangeProtocol,
+        protocol: &BuildStatusExchangeProtocol,
         io: &mut T,
     ) -> io::Result<Self::Request>
     where
@@ -52,12 +55,17 @@ impl RequestResponseCodec for BuildStatusExchangeCodec {
     {
         debug!("Reading BuildStatusRequest...");
 
-        let hash_vec0 = read_length_prefixed(io, 1_000_000).await?;
-        if hash_vec0.is_empty() {
+        let signed_message = read_length_prefixed(io, 1_000_000).await?;
+        if signed_message.is_empty() {
             return Err(io::ErrorKind::UnexpectedEof.into());
         }
 
-        let build_id = String::from_utf8(hash_vec0).unwrap();
+        let payload = self.0.verify(
+            &String::from_utf8_lossy(protocol.protocol_name()),
+            &signed_message,
+        )?;
+        let build_id = String::from_utf8(payload)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
 
         Ok(BuildStatusRequest(build_id))
     }
@@ -83,7 +91,7 @@ impl RequestResponseCodec for BuildStatusExchangeCodec {
 
     async fn write_request<T>(
         &mut self,
-        _: &BuildStatusExchangeProtocol,
+        protocol: &BuildStatusExchangeProtocol,
         io: &mut T,
         BuildStatusRequest(build_id): BuildStatusRequest,
     ) -> io::Result<()>
@@ -92,7 +100,11 @@ impl RequestResponseCodec for BuildStatusExchangeCodec {
     {
         debug!("Write BuildStatusRequest: build_id: {:?}", build_id);
 
-        write_length_prefixed(io, build_id.to_string()).await?;
+        let signed_message = self.0.sign(
+            &String::from_utf8_lossy(protocol.protocol_name()),
+            build_id.into_bytes(),
+        )?;
+        write_length_prefixed(io, signed_message).await?;
         io.close().await?;
 
         Ok(())
@@ -17,22 +17,36 @@
 pub mod command;
 
 use crate::artifact_service::model::PackageType;
-use crate::network::artifact_protocol::ArtifactResponse;
+use crate::network::artifact_protocol::{ArtifactChunk, ArtifactResponse};
 use crate::network::blockchain_protocol::BlockchainResponse;
 use crate::network::build_protocol::BuildResponse;
 use crate::network::build_status_protocol::BuildStatusResponse;
 use crate::network::client::command::Command;
 use crate::network::idle_metric_protocol::{IdleMetricResponse, PeerMetrics};
+use crate::network::peer_alias::PeerAliases;
+use crate::network::peer_capabilities::PeerCapabilities;
+use crate::network::peer_exchange_protocol::{self, PeerAddresses, PEER_EXCHANGE_FANOUT};
+use crate::network::peer_maintenance::{MaintenanceAnnouncement, PeerMaintenance};
+use crate::network::peer_throughput::PeerThroughput;
+use crate::network::peer_version::{PeerVersions, ProtocolFeature};
+use crate::network::query_metrics::{LookupTrace, QueryMetrics};
+use crate::network::seed_sync_protocol::{SeedSyncRequest, SeedSyncResponse};
 use crate::node_api::model::request::Status;
+use futures::stream::{FuturesUnordered, StreamExt};
 use libp2p::core::{Multiaddr, PeerId};
 use libp2p::gossipsub;
 use libp2p::request_response::ResponseChannel;
 use log::debug;
+use rand::seq::IteratorRandom;
 use std::collections::HashSet;
+use std::time::{Duration, Instant};
 use tokio::sync::{mpsc, oneshot};
 
 /* peer metrics support */
 const PEER_METRIC_THRESHOLD: f64 = 0.5_f64;
+/// How long to wait for the idle metrics of providers, providers that
+/// respond later are not considered.
+const IDLE_METRIC_DEADLINE: Duration = Duration::from_secs(2);
 #[derive(Clone, Debug, PartialEq, PartialOrd)]
 struct IdleMetric {
     pub peer: PeerId,
@@ -76,6 +90,12 @@ impl From<&str> for ArtifactHash {
 pub struct Client {
     pub sender: mpsc::Sender<Command>,
     pub local_peer_id: PeerId,
+    pub peer_aliases: PeerAliases,
+    pub peer_versions: PeerVersions,
+    pub peer_capabilities: PeerCapabilities,
+    pub peer_maintenance: PeerMaintenance,
+    pub query_metrics: QueryMetrics,
+    pub peer_throughput: PeerThroughput,
     pyrsia_topic: gossipsub::IdentTopic,
 }
 
@@ -88,10 +108,48 @@ impl Client {
         Self {
             sender,
             local_peer_id,
+            peer_aliases: Default::default(),
+            peer_versions: Default::default(),
+            peer_capabilities: Default::default(),
+            peer_maintenance: Default::default(),
+            query_metrics: Default::default(),
+            peer_throughput: Default::default(),
             pyrsia_topic,
         }
     }
 
+    /// Use `peer_aliases` to render peers with their human-friendly names.
+    pub fn with_peer_aliases(mut self, peer_aliases: PeerAliases) -> Self {
+        self.peer_aliases = peer_aliases;
+        self
+    }
+
+    /// Use `peer_versions` to refuse protocol features to outdated peers.
+    pub fn with_peer_versions(mut self, peer_versions: PeerVersions) -> Self {
+        self.peer_versions = peer_versions;
+        self
+    }
+
+    /// Use `peer_capabilities` to route operations on a package type to the
+    /// peers that support it.
+    pub fn with_peer_capabilities(mut self, peer_capabilities: PeerCapabilities) -> Self {
+        self.peer_capabilities = peer_capabilities;
+        self
+    }
+
+    /// Use `peer_maintenance` to skip the nodes that are draining for
+    /// maintenance when routing new builds.
+    pub fn with_peer_maintenance(mut self, peer_maintenance: PeerMaintenance) -> Self {
+        self.peer_maintenance = peer_maintenance;
+        self
+    }
+
+    /// Report the metrics of the Kademlia queries from `query_metrics`.
+    pub fn with_query_metrics(mut self, query_metrics: QueryMetrics) -> Self {
+        self.query_metrics = query_metrics;
+        self
+    }
+
     /// Add a probe address for AutoNAT discovery. When adding the probe
     /// was handled successfully, the kademlia DHT will be bootstrapped.
     pub async fn add_probe_address(
@@ -161,6 +219,93 @@ impl Client {
         bootstrap_receiver.await?
     }
 
+    /// Dial a peer on any of the specified addresses, without bootstrapping
+    /// the kademlia DHT.
+    pub async fn dial_peer(
+        &mut self,
+        peer_id: &PeerId,
+        peer_addrs: Vec<Multiaddr>,
+    ) -> anyhow::Result<()> {
+        debug!("p2p::Client::dial_peer {:?} {:?}", peer_id, peer_addrs);
+
+        let (sender, receiver) = oneshot::channel();
+        self.sender
+            .send(Command::DialPeer {
+                peer_id: *peer_id,
+                peer_addrs,
+                sender,
+            })
+            .await?;
+        receiver.await?
+    }
+
+    /// Ask a peer for a sample of the peers it is connected to.
+    pub async fn request_peer_exchange(
+        &mut self,
+        peer: &PeerId,
+    ) -> anyhow::Result<Vec<PeerAddresses>> {
+        debug!("p2p::Client::request_peer_exchange from peer {:?}", peer);
+
+        let (sender, receiver) = oneshot::channel();
+        self.sender
+            .send(Command::RequestPeerExchange {
+                peer: *peer,
+                sender,
+            })
+            .await?;
+        receiver.await?
+    }
+
+    /// Exchange known peers with a few of the connected peers and dial up
+    /// to `max_dials` of the received peers that this node is not connected
+    /// to yet, the `preferred_peers` first. Returns the peers that were
+    /// connected.
+    pub async fn exchange_peers(
+        &mut self,
+        preferred_peers: &HashSet<PeerId>,
+        max_dials: usize,
+    ) -> anyhow::Result<Vec<PeerId>> {
+        let connected_peers = self.list_peers().await?;
+        let exchange_peers = connected_peers
+            .iter()
+            .copied()
+            .choose_multiple(&mut rand::thread_rng(), PEER_EXCHANGE_FANOUT);
+
+        let mut received_peers = vec![];
+        for peer in exchange_peers {
+            match self.request_peer_exchange(&peer).await {
+                Ok(peers) => received_peers.extend(peers),
+                Err(error) => debug!(
+                    "Peer exchange with peer {} failed: {:?}",
+                    self.peer_aliases.display(&peer),
+                    error
+                ),
+            }
+        }
+
+        let peers_to_dial = peer_exchange_protocol::select_peers_to_dial(
+            received_peers,
+            &self.local_peer_id,
+            &connected_peers,
+            preferred_peers,
+            max_dials,
+        );
+        let dials = peers_to_dial.into_iter().map(|peer| {
+            let mut client = self.clone();
+            async move {
+                client
+                    .dial_peer(&peer.peer_id, peer.addresses)
+                    .await
+                    .map(|_| peer.peer_id)
+            }
+        });
+        Ok(futures::future::join_all(dials)
+            .await
+            .into_iter()
+            .filter_map(Result::ok)
+            .collect())
+    }
+
     /// List the peers that this node is connected to.
     pub async fn list_peers(&mut self) -> anyhow::Result<HashSet<PeerId>> {
         let (sender, receiver) = oneshot::channel();
@@ -190,6 +335,21 @@ impl Client {
         Ok(receiver.await?)
     }
 
+    /// Inform the swarm that this node is no longer a provider
+    /// of the artifact with the specified `artifact_id`.
+    pub async fn stop_providing(&mut self, artifact_id: &str) -> anyhow::Result<()> {
+        debug!("p2p::Client::stop_providing {:?}", artifact_id);
+
+        let (sender, receiver) = oneshot::channel();
+        self.sender
+            .send(Command::StopProviding {
+                artifact_id: artifact_id.to_owned(),
+                sender,
+            })
+            .await?;
+        Ok(receiver.await?)
+    }
+
     /// List all peers in the swarm that are providing
     /// the artifact with the specified `artifact_id`.
     pub async fn list_providers(&mut self, artifact_id: &str) -> anyhow::Result<HashSet<PeerId>> {
@@ -205,6 +365,21 @@ impl Client {
         Ok(receiver.await?)
     }
 
+    /// Look up the providers of the artifact with the specified
+    /// `artifact_id` and report every step of the lookup.
+    pub async fn trace_lookup(&mut self, artifact_id: &str) -> anyhow::Result<LookupTrace> {
+        debug!("p2p::Client::trace_lookup {:?}", artifact_id);
+
+        let (sender, receiver) = oneshot::channel();
+        self.sender
+            .send(Command::TraceLookup {
+                artifact_id: artifact_id.to_owned(),
+                sender,
+            })
+            .await?;
+        Ok(receiver.await?)
+    }
+
     /// Request a build to a peer with the specified address.
     pub async fn request_build(
         &mut self,
@@ -216,6 +391,8 @@ impl Client {
             "p2p::Client::request_build {:?}: {:?}: {:?}",
             peer_id, package_type, package_specific_id
         );
+        self.peer_versions
+            .check(peer_id, ProtocolFeature::BuildRequests)?;
 
         let (sender, receiver) = oneshot::channel();
         self.sender
@@ -259,36 +436,80 @@ impl Client {
             "p2p::Client::request_artifact {:?}: {:?}",
             peer, artifact_id
         );
+        self.peer_versions
+            .check(peer, ProtocolFeature::ArtifactExchange)?;
 
+        let started = Instant::now();
         let (sender, receiver) = oneshot::channel();
         self.sender
             .send(Command::RequestArtifact {
                 artifact_id: artifact_id.to_owned(),
+                chunk: None,
                 peer: *peer,
                 sender,
             })
             .await?;
-        receiver.await?
+        let response = receiver.await??;
+        self.peer_throughput
+            .record(peer, response.artifact.len() as u64, started.elapsed());
+        Ok(response.artifact)
+    }
+
+    /// Request a chunk of the artifact with the specified `artifact_id`
+    /// from the swarm. The response also contains the size of the complete
+    /// artifact. The throughput of the transfer is recorded for the peer, see
+    /// [`PeerThroughput`].
+    pub async fn request_artifact_chunk(
+        &mut self,
+        peer: &PeerId,
+        artifact_id: &str,
+        chunk: ArtifactChunk,
+    ) -> anyhow::Result<ArtifactResponse> {
+        debug!(
+            "p2p::Client::request_artifact_chunk {:?}: {:?} {:?}",
+            peer, artifact_id, chunk
+        );
+        self.peer_versions
+            .check(peer, ProtocolFeature::ArtifactExchange)?;
+
+        let started = Instant::now();
+        let (sender, receiver) = oneshot::channel();
+        self.sender
+            .send(Command::RequestArtifact {
+                artifact_id: artifact_id.to_owned(),
+                chunk: Some(chunk),
+                peer: *peer,
+                sender,
+            })
+            .await?;
+        let response = receiver.await??;
+        self.peer_throughput
+            .record(peer, response.artifact.len() as u64, started.elapsed());
+        Ok(response)
     }
 
-    /// Put the artifact as a response to an incoming artifact
-    /// request.
+    /// Put the artifact, or a chunk of it, as a response to an incoming
+    /// artifact request.
     pub async fn respond_artifact(
         &mut self,
-        artifact: Vec<u8>,
+        response: ArtifactResponse,
         channel: ResponseChannel<ArtifactResponse>,
     ) -> anyhow::Result<()> {
-        debug!("p2p::Client::respond_artifact size={:?}", artifact.len());
+        debug!(
+            "p2p::Client::respond_artifact size={:?}",
+            response.artifact.len()
+        );
 
         self.sender
-            .send(Command::RespondArtifact { artifact, channel })
+            .send(Command::RespondArtifact { response, channel })
             .await?;
 
         Ok(())
     }
 
-    //get a peer with a low enough work load to download artifact otherwise the lowest work load of the set
-    //TODO: chunk the peers to some limit to keep from shotgunning the network
+    /// Get a peer with a low enough work load to download an artifact from,
+    /// otherwise the peer with the lowest work load of the providers that
+    /// responded in time, see [`Client::request_idle_metrics`].
     pub async fn get_idle_peer(
         &mut self,
         providers: HashSet<PeerId>,
@@ -297,49 +518,93 @@ impl Client {
             "p2p::Client::get_idle_peer() entered with {} peers",
             providers.len()
         );
-        let mut idle_metrics: Vec<IdleMetric> = Vec::new();
-        for peer in providers.iter() {
+        let idle_metrics = self
+            .request_idle_metrics(&providers, Some(PEER_METRIC_THRESHOLD))
+            .await?;
+        Ok(idle_metrics.first().map(|idle_metric| idle_metric.peer))
+    }
+
+    /// Get all providers that respond with their idle metric in time, the
+    /// most idle peer first, to spread a download over several peers.
+    pub async fn get_idle_peers(
+        &mut self,
+        providers: HashSet<PeerId>,
+    ) -> anyhow::Result<Vec<PeerId>> {
+        debug!(
+            "p2p::Client::get_idle_peers() entered with {} peers",
+            providers.len()
+        );
+        let idle_metrics = self.request_idle_metrics(&providers, None).await?;
+        Ok(idle_metrics
+            .into_iter()
+            .map(|idle_metric| idle_metric.peer)
+            .collect())
+    }
+
+    /// Request the idle metric of all `providers` at once and collect the
+    /// metrics of the providers that respond within [`IDLE_METRIC_DEADLINE`],
+    /// the most idle provider first. Collecting stops as soon as a provider
+    /// responds with a metric below `sufficient_metric`, so a long list of
+    /// providers does not delay the start of a download.
+    async fn request_idle_metrics(
+        &mut self,
+        providers: &HashSet<PeerId>,
+        sufficient_metric: Option<f64>,
+    ) -> anyhow::Result<Vec<IdleMetric>> {
+        let mut responses = FuturesUnordered::new();
+        for peer in providers.iter().copied() {
             let (sender, receiver) = oneshot::channel();
             self.sender
-                .send(Command::RequestIdleMetric {
-                    peer: *peer,
-                    sender,
-                })
+                .send(Command::RequestIdleMetric { peer, sender })
                 .await?;
+            responses.push(async move { (peer, receiver.await) });
+        }
 
-            match receiver.await.expect("Sender not to be dropped.") {
-                Ok(peer_metric) => {
-                    let metric: f64 = f64::from_le_bytes(peer_metric.idle_metric);
-                    let idle_metric = IdleMetric {
-                        peer: *peer,
-                        metric,
-                    };
-                    if idle_metric.metric < PEER_METRIC_THRESHOLD {
-                        debug!(
-                                "p2p::Client::get_idle_peer() Found peer with a below threshold idle value {}",
-                                metric
-                            );
-                        return Ok(Some(idle_metric.peer));
-                    } else {
+        let mut idle_metrics: Vec<IdleMetric> = Vec::new();
+        let deadline = tokio::time::sleep(IDLE_METRIC_DEADLINE);
+        tokio::pin!(deadline);
+        loop {
+            tokio::select! {
+                response = responses.next() => match response {
+                    Some((peer, Ok(Ok(peer_metric)))) => {
+                        let metric = f64::from_le_bytes(peer_metric.idle_metric);
                         debug!(
-                            "p2p::Client::get_idle_peer() Pushing idle peer with value {}",
-                            metric
+                            "p2p::Client::request_idle_metrics() Peer {} has idle value {}",
+                            peer, metric
                         );
-                        idle_metrics.push(idle_metric);
+                        // a NaN or infinite value can not be ranked
+                        if !metric.is_finite() {
+                            continue;
+                        }
+                        idle_metrics.push(IdleMetric { peer, metric });
+                        if sufficient_metric.map_or(false, |sufficient| metric < sufficient) {
+                            break;
+                        }
                     }
-                }
-                Err(e) => {
+                    Some((peer, Ok(Err(e)))) => debug!(
+                        "p2p::Client::request_idle_metrics() Unable to get peer metric for peer {} error {}",
+                        peer, e
+                    ),
+                    Some((peer, Err(e))) => debug!(
+                        "p2p::Client::request_idle_metrics() No peer metric for peer {} error {}",
+                        peer, e
+                    ),
+                    None => break,
+                },
+                _ = &mut deadline => {
                     debug!(
-                            "p2p::Client::get_idle_peer() Unable to get peer metric for peer {} error {}",
-                            peer, e
-                        );
+                        "p2p::Client::request_idle_metrics() {} of {} peers responded before the deadline",
+                        idle_metrics.len(),
+                        providers.len()
+                    );
+                    break;
                 }
-            };
+            }
         }
 
-        //sort the peers in ascending order according to their idle metric and return top of list
-        idle_metrics.sort_by(|a, b| a.metric.partial_cmp(&b.metric).unwrap());
-        Ok(idle_metrics.first().map(|idle_metric| idle_metric.peer))
+        //sort the peers in ascending order according to their idle metric
+        idle_metrics.sort_by(|a, b| a.metric.total_cmp(&b.metric));
+        Ok(idle_metrics)
     }
 
     pub async fn respond_idle_metric(
@@ -365,6 +630,8 @@ impl Client {
         data: Vec<u8>,
     ) -> anyhow::Result<Vec<u8>> {
         debug!("p2p::Client::request_blockchain from peer {:?}", peer);
+        self.peer_versions
+            .check(peer, ProtocolFeature::BlockchainSync)?;
 
         let (sender, receiver) = oneshot::channel();
         self.sender
@@ -405,6 +672,28 @@ impl Client {
         receiver.await?
     }
 
+    /// Announce to the peers that this node is draining for maintenance
+    /// until `announcement` expires, so that they stop routing new builds
+    /// to it. An expired announcement ends the maintenance.
+    pub async fn announce_maintenance(
+        &mut self,
+        announcement: MaintenanceAnnouncement,
+    ) -> anyhow::Result<()> {
+        debug!(
+            "p2p::Client::announce_maintenance until {}",
+            announcement.until
+        );
+
+        let (sender, receiver) = oneshot::channel();
+        self.sender
+            .send(Command::AnnounceMaintenance {
+                announcement,
+                sender,
+            })
+            .await?;
+        receiver.await?
+    }
+
     pub async fn request_build_status(
         &mut self,
         peer_id: &PeerId,
@@ -414,6 +703,8 @@ impl Client {
             "p2p::Client::request_build_status peer_id {:?}, build_id: {:?}",
             peer_id, build_id
         );
+        self.peer_versions
+            .check(peer_id, ProtocolFeature::BuildStatus)?;
 
         let (sender, receiver) = oneshot::channel();
         self.sender
@@ -443,12 +734,50 @@ impl Client {
 
         Ok(())
     }
+
+    /// Ask the seed node `peer` for a part of its snapshot, see
+    /// [`SeedSyncRequest`].
+    pub async fn request_seed_sync(
+        &mut self,
+        peer: &PeerId,
+        request: SeedSyncRequest,
+    ) -> anyhow::Result<SeedSyncResponse> {
+        debug!(
+            "p2p::Client::request_seed_sync peer {:?}, request: {:?}",
+            peer, request
+        );
+
+        let (sender, receiver) = oneshot::channel();
+        self.sender
+            .send(Command::RequestSeedSync {
+                peer: *peer,
+                request,
+                sender,
+            })
+            .await?;
+        receiver.await?
+    }
+
+    pub async fn respond_seed_sync(
+        &mut self,
+        response: SeedSyncResponse,
+        channel: ResponseChannel<SeedSyncResponse>,
+    ) -> anyhow::Result<()> {
+        debug!("p2p::Client::respond_seed_sync");
+
+        self.sender
+            .send(Command::RespondSeedSync { response, channel })
+            .await?;
+
+        Ok(())
+    }
 }
 
 #[cfg(test)]
 #[cfg(not(tarpaulin_include))]
 mod tests {
     use super::*;
+    use crate::test_support::FakeNetwork;
     use libp2p::gossipsub::IdentTopic;
     use libp2p::identity::{self, Keypair};
     use pyrsia_blockchain_network::crypto::hash_algorithm::HashDigest;
@@ -463,6 +792,12 @@ mod tests {
         let mut client = Client {
             sender,
             local_peer_id: Keypair::generate_ed25519().public().to_peer_id(),
+            peer_aliases: Default::default(),
+            peer_versions: Default::default(),
+            peer_capabilities: Default::default(),
+            peer_maintenance: Default::default(),
+            query_metrics: Default::default(),
+            peer_throughput: Default::default(),
             pyrsia_topic: IdentTopic::new("pyrsia-blockchain-topic"),
         };
 
@@ -489,6 +824,12 @@ mod tests {
         let mut client = Client {
             sender,
             local_peer_id,
+            peer_aliases: Default::default(),
+            peer_versions: Default::default(),
+            peer_capabilities: Default::default(),
+            peer_maintenance: Default::default(),
+            query_metrics: Default::default(),
+            peer_throughput: Default::default(),
             pyrsia_topic: IdentTopic::new("pyrsia-blockchain-topic"),
         };
 
@@ -516,6 +857,12 @@ mod tests {
         let mut client = Client {
             sender,
             local_peer_id,
+            peer_aliases: Default::default(),
+            peer_versions: Default::default(),
+            peer_capabilities: Default::default(),
+            peer_maintenance: Default::default(),
+            query_metrics: Default::default(),
+            peer_throughput: Default::default(),
             pyrsia_topic: IdentTopic::new("pyrsia-blockchain-topic"),
         };
 
@@ -539,6 +886,12 @@ mod tests {
         let mut client = Client {
             sender,
             local_peer_id,
+            peer_aliases: Default::default(),
+            peer_versions: Default::default(),
+            peer_capabilities: Default::default(),
+            peer_maintenance: Default::default(),
+            query_metrics: Default::default(),
+            peer_throughput: Default::default(),
             pyrsia_topic: IdentTopic::new("pyrsia-blockchain-topic"),
         };
 
@@ -562,6 +915,12 @@ mod tests {
         let mut client = Client {
             sender,
             local_peer_id,
+            peer_aliases: Default::default(),
+            peer_versions: Default::default(),
+            peer_capabilities: Default::default(),
+            peer_maintenance: Default::default(),
+            query_metrics: Default::default(),
+            peer_throughput: Default::default(),
             pyrsia_topic: IdentTopic::new("pyrsia-blockchain-topic"),
         };
 
@@ -584,6 +943,148 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn test_get_idle_peers() {
+        let (sender, mut receiver) = mpsc::channel(1);
+
+        let mut client = Client {
+            sender,
+            local_peer_id: Keypair::generate_ed25519().public().to_peer_id(),
+            peer_aliases: Default::default(),
+            peer_versions: Default::default(),
+            peer_capabilities: Default::default(),
+            peer_maintenance: Default::default(),
+            query_metrics: Default::default(),
+            peer_throughput: Default::default(),
+            pyrsia_topic: IdentTopic::new("pyrsia-blockchain-topic"),
+        };
+
+        let busy_peer_id = Keypair::generate_ed25519().public().to_peer_id();
+        let idle_peer_id = Keypair::generate_ed25519().public().to_peer_id();
+        let unreachable_peer_id = Keypair::generate_ed25519().public().to_peer_id();
+        tokio::spawn(async move {
+            while let Some(command) = receiver.recv().await {
+                match command {
+                    Command::RequestIdleMetric { peer, sender } => {
+                        let result = if peer == busy_peer_id {
+                            Ok(PeerMetrics {
+                                idle_metric: 0.9_f64.to_le_bytes(),
+                            })
+                        } else if peer == idle_peer_id {
+                            Ok(PeerMetrics {
+                                idle_metric: 0.1_f64.to_le_bytes(),
+                            })
+                        } else {
+                            Err(anyhow::anyhow!("peer is unreachable"))
+                        };
+                        let _ = sender.send(result);
+                    }
+                    _ => panic!("Command must match Command::RequestIdleMetric"),
+                }
+            }
+        });
+
+        let peers = client
+            .get_idle_peers(HashSet::from([
+                busy_peer_id,
+                idle_peer_id,
+                unreachable_peer_id,
+            ]))
+            .await
+            .unwrap();
+        assert_eq!(peers, vec![idle_peer_id, busy_peer_id]);
+    }
+
+    #[tokio::test]
+    async fn test_get_idle_peers_ignores_invalid_metrics() {
+        let (sender, mut receiver) = mpsc::channel(1);
+
+        let mut client = Client {
+            sender,
+            local_peer_id: Keypair::generate_ed25519().public().to_peer_id(),
+            peer_aliases: Default::default(),
+            peer_versions: Default::default(),
+            peer_capabilities: Default::default(),
+            peer_maintenance: Default::default(),
+            query_metrics: Default::default(),
+            peer_throughput: Default::default(),
+            pyrsia_topic: IdentTopic::new("pyrsia-blockchain-topic"),
+        };
+
+        let idle_peer_id = Keypair::generate_ed25519().public().to_peer_id();
+        let nan_peer_id = Keypair::generate_ed25519().public().to_peer_id();
+        let infinite_peer_id = Keypair::generate_ed25519().public().to_peer_id();
+        tokio::spawn(async move {
+            while let Some(command) = receiver.recv().await {
+                match command {
+                    Command::RequestIdleMetric { peer, sender } => {
+                        let idle_metric = if peer == nan_peer_id {
+                            f64::NAN
+                        } else if peer == infinite_peer_id {
+                            f64::NEG_INFINITY
+                        } else {
+                            0.5
+                        };
+                        let _ = sender.send(Ok(PeerMetrics {
+                            idle_metric: idle_metric.to_le_bytes(),
+                        }));
+                    }
+                    _ => panic!("Command must match Command::RequestIdleMetric"),
+                }
+            }
+        });
+
+        let peers = client
+            .get_idle_peers(HashSet::from([idle_peer_id, nan_peer_id, infinite_peer_id]))
+            .await
+            .unwrap();
+        assert_eq!(peers, vec![idle_peer_id]);
+    }
+
+    #[tokio::test]
+    async fn test_get_idle_peer_with_unresponsive_peers() {
+        let (sender, mut receiver) = mpsc::channel(1);
+
+        let mut client = Client {
+            sender,
+            local_peer_id: Keypair::generate_ed25519().public().to_peer_id(),
+            peer_aliases: Default::default(),
+            peer_versions: Default::default(),
+            peer_capabilities: Default::default(),
+            peer_maintenance: Default::default(),
+            query_metrics: Default::default(),
+            peer_throughput: Default::default(),
+            pyrsia_topic: IdentTopic::new("pyrsia-blockchain-topic"),
+        };
+
+        let busy_peer_id = Keypair::generate_ed25519().public().to_peer_id();
+        let unresponsive_peer_ids: Vec<PeerId> = (0..5)
+            .map(|_| Keypair::generate_ed25519().public().to_peer_id())
+            .collect();
+        tokio::spawn(async move {
+            // unresponsive peers never respond, but keep their request open
+            let mut pending_requests = vec![];
+            while let Some(command) = receiver.recv().await {
+                match command {
+                    Command::RequestIdleMetric { peer, sender } if peer == busy_peer_id => {
+                        let _ = sender.send(Ok(PeerMetrics {
+                            idle_metric: 0.9_f64.to_le_bytes(),
+                        }));
+                    }
+                    Command::RequestIdleMetric { sender, .. } => pending_requests.push(sender),
+                    _ => panic!("Command must match Command::RequestIdleMetric"),
+                }
+            }
+        });
+
+        let mut providers = HashSet::from([busy_peer_id]);
+        providers.extend(unresponsive_peer_ids);
+        let started = Instant::now();
+        let peer = client.get_idle_peer(providers).await.unwrap();
+        assert_eq!(peer, Some(busy_peer_id));
+        assert!(started.elapsed() < IDLE_METRIC_DEADLINE * 2);
+    }
+
     #[tokio::test]
     async fn test_provide() {
         let (sender, mut receiver) = mpsc::channel(1);
@@ -591,6 +1092,12 @@ mod tests {
         let mut client = Client {
             sender,
             local_peer_id: Keypair::generate_ed25519().public().to_peer_id(),
+            peer_aliases: Default::default(),
+            peer_versions: Default::default(),
+            peer_capabilities: Default::default(),
+            peer_maintenance: Default::default(),
+            query_metrics: Default::default(),
+            peer_throughput: Default::default(),
             pyrsia_topic: IdentTopic::new("pyrsia-blockchain-topic"),
         };
 
@@ -613,6 +1120,41 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn test_stop_providing() {
+        let (sender, mut receiver) = mpsc::channel(1);
+
+        let mut client = Client {
+            sender,
+            local_peer_id: Keypair::generate_ed25519().public().to_peer_id(),
+            peer_aliases: Default::default(),
+            peer_versions: Default::default(),
+            peer_capabilities: Default::default(),
+            peer_maintenance: Default::default(),
+            query_metrics: Default::default(),
+            peer_throughput: Default::default(),
+            pyrsia_topic: IdentTopic::new("pyrsia-blockchain-topic"),
+        };
+
+        let random_artifact_id: String = thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(30)
+            .map(char::from)
+            .collect();
+        let cloned_random_artifact_id = random_artifact_id.clone();
+        tokio::spawn(async move { client.stop_providing(&random_artifact_id).await });
+
+        tokio::select! {
+            command = receiver.recv() => match command {
+                Some(Command::StopProviding { artifact_id, sender }) => {
+                    assert_eq!(artifact_id, cloned_random_artifact_id);
+                    let _ = sender.send(());
+                },
+                _ => panic!("Command must match Command::StopProviding")
+            }
+        }
+    }
+
     #[tokio::test]
     async fn test_list_providers() {
         let (sender, mut receiver) = mpsc::channel(1);
@@ -620,6 +1162,12 @@ mod tests {
         let mut client = Client {
             sender,
             local_peer_id: Keypair::generate_ed25519().public().to_peer_id(),
+            peer_aliases: Default::default(),
+            peer_versions: Default::default(),
+            peer_capabilities: Default::default(),
+            peer_maintenance: Default::default(),
+            query_metrics: Default::default(),
+            peer_throughput: Default::default(),
             pyrsia_topic: IdentTopic::new("pyrsia-blockchain-topic"),
         };
 
@@ -649,6 +1197,12 @@ mod tests {
         let mut client = Client {
             sender,
             local_peer_id: Keypair::generate_ed25519().public().to_peer_id(),
+            peer_aliases: Default::default(),
+            peer_versions: Default::default(),
+            peer_capabilities: Default::default(),
+            peer_maintenance: Default::default(),
+            query_metrics: Default::default(),
+            peer_throughput: Default::default(),
             pyrsia_topic: IdentTopic::new("pyrsia-blockchain-topic"),
         };
 
@@ -667,10 +1221,11 @@ mod tests {
 
         tokio::select! {
             command = receiver.recv() => match command {
-                Some(Command::RequestArtifact { peer, artifact_id, sender }) => {
+                Some(Command::RequestArtifact { peer, artifact_id, chunk, sender }) => {
                     assert_eq!(peer, other_peer_id);
                     assert_eq!(artifact_id, cloned_random_artifact_id);
-                    let _ = sender.send(Ok(vec![]));
+                    assert_eq!(chunk, None);
+                    let _ = sender.send(Ok(ArtifactResponse::complete(vec![])));
                 },
                 _ => panic!("Command must match Command::RequestArtifact")
             }
@@ -684,6 +1239,12 @@ mod tests {
         let mut client = Client {
             sender,
             local_peer_id: Keypair::generate_ed25519().public().to_peer_id(),
+            peer_aliases: Default::default(),
+            peer_versions: Default::default(),
+            peer_capabilities: Default::default(),
+            peer_maintenance: Default::default(),
+            query_metrics: Default::default(),
+            peer_throughput: Default::default(),
             pyrsia_topic: IdentTopic::new("pyrsia-blockchain-topic"),
         };
 
@@ -727,6 +1288,12 @@ mod tests {
         let mut client = Client {
             sender,
             local_peer_id: identity::PublicKey::Ed25519(local_key.public()).to_peer_id(),
+            peer_aliases: Default::default(),
+            peer_versions: Default::default(),
+            peer_capabilities: Default::default(),
+            peer_maintenance: Default::default(),
+            query_metrics: Default::default(),
+            peer_throughput: Default::default(),
             pyrsia_topic: IdentTopic::new("pyrsia-blockchain-topic"),
         };
 
@@ -785,6 +1352,12 @@ mod tests {
         let mut client = Client {
             sender,
             local_peer_id: Keypair::generate_ed25519().public().to_peer_id(),
+            peer_aliases: Default::default(),
+            peer_versions: Default::default(),
+            peer_capabilities: Default::default(),
+            peer_maintenance: Default::default(),
+            query_metrics: Default::default(),
+            peer_throughput: Default::default(),
             pyrsia_topic: IdentTopic::new("pyrsia-blockchain-topic"),
         };
 
@@ -808,4 +1381,34 @@ mod tests {
             }
         }
     }
+
+    #[tokio::test]
+    async fn test_exchange_peers() {
+        let connected_peer_id = PeerId::random();
+        let authorized_peer_id = PeerId::random();
+        let other_peer_ids: Vec<PeerId> = (0..5).map(|_| PeerId::random()).collect();
+        let known_peers = std::iter::once(authorized_peer_id)
+            .chain(other_peer_ids)
+            .map(|peer_id| PeerAddresses {
+                peer_id,
+                addresses: vec!["/ip4/10.0.0.2/tcp/44000".parse().unwrap()],
+            })
+            .collect();
+        let network = FakeNetwork::new().with_known_peers(connected_peer_id, known_peers);
+        let mut client = network.client();
+
+        let connected_peers = client
+            .exchange_peers(&HashSet::from([authorized_peer_id]), 2)
+            .await
+            .unwrap();
+
+        assert_eq!(connected_peers.len(), 2);
+        assert!(connected_peers.contains(&authorized_peer_id));
+        assert_eq!(network.peers().len(), 3);
+        assert!(client
+            .exchange_peers(&HashSet::new(), 0)
+            .await
+            .unwrap()
+            .is_empty());
+    }
 }
@@ -15,11 +15,15 @@
 */
 
 use crate::artifact_service::model::PackageType;
-use crate::network::artifact_protocol::ArtifactResponse;
+use crate::network::artifact_protocol::{ArtifactChunk, ArtifactResponse};
 use crate::network::blockchain_protocol::BlockchainResponse;
 use crate::network::build_protocol::BuildResponse;
 use crate::network::build_status_protocol::BuildStatusResponse;
 use crate::network::idle_metric_protocol::{IdleMetricResponse, PeerMetrics};
+use crate::network::peer_exchange_protocol::PeerAddresses;
+use crate::network::peer_maintenance::MaintenanceAnnouncement;
+use crate::network::query_metrics::LookupTrace;
+use crate::network::seed_sync_protocol::{SeedSyncRequest, SeedSyncResponse};
 use crate::node_api::model::request::Status;
 use libp2p::core::{Multiaddr, PeerId};
 use libp2p::gossipsub;
@@ -43,6 +47,10 @@ pub enum Command {
         block: Vec<u8>,
         sender: oneshot::Sender<anyhow::Result<()>>,
     },
+    AnnounceMaintenance {
+        announcement: MaintenanceAnnouncement,
+        sender: oneshot::Sender<anyhow::Result<()>>,
+    },
     BootstrapDht {
         sender: oneshot::Sender<anyhow::Result<()>>,
     },
@@ -55,6 +63,11 @@ pub enum Command {
         peer_addr: Multiaddr,
         sender: oneshot::Sender<anyhow::Result<()>>,
     },
+    DialPeer {
+        peer_id: PeerId,
+        peer_addrs: Vec<Multiaddr>,
+        sender: oneshot::Sender<anyhow::Result<()>>,
+    },
     ListPeers {
         sender: oneshot::Sender<HashSet<PeerId>>,
     },
@@ -65,10 +78,18 @@ pub enum Command {
         artifact_id: String,
         sender: oneshot::Sender<()>,
     },
+    StopProviding {
+        artifact_id: String,
+        sender: oneshot::Sender<()>,
+    },
     ListProviders {
         artifact_id: String,
         sender: oneshot::Sender<HashSet<PeerId>>,
     },
+    TraceLookup {
+        artifact_id: String,
+        sender: oneshot::Sender<LookupTrace>,
+    },
     RequestBuild {
         peer: PeerId,
         package_type: PackageType,
@@ -81,11 +102,12 @@ pub enum Command {
     },
     RequestArtifact {
         artifact_id: String,
+        chunk: Option<ArtifactChunk>,
         peer: PeerId,
-        sender: oneshot::Sender<anyhow::Result<Vec<u8>>>,
+        sender: oneshot::Sender<anyhow::Result<ArtifactResponse>>,
     },
     RespondArtifact {
-        artifact: Vec<u8>,
+        response: ArtifactResponse,
         channel: ResponseChannel<ArtifactResponse>,
     },
     RequestIdleMetric {
@@ -96,6 +118,10 @@ pub enum Command {
         metric: PeerMetrics,
         channel: ResponseChannel<IdleMetricResponse>,
     },
+    RequestPeerExchange {
+        peer: PeerId,
+        sender: oneshot::Sender<anyhow::Result<Vec<PeerAddresses>>>,
+    },
     RequestBlockchain {
         data: Vec<u8>,
         peer: PeerId,
@@ -114,6 +140,15 @@ pub enum Command {
         status: String,
         channel: ResponseChannel<BuildStatusResponse>,
     },
+    RequestSeedSync {
+        peer: PeerId,
+        request: SeedSyncRequest,
+        sender: oneshot::Sender<anyhow::Result<SeedSyncResponse>>,
+    },
+    RespondSeedSync {
+        response: SeedSyncResponse,
+        channel: ResponseChannel<SeedSyncResponse>,
+    },
 }
 
 #[cfg(test)]
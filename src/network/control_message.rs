@@ -0,0 +1,354 @@
+/*
+   Copyright 2021 JFrog Ltd
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+*/
+
+use crate::util::time_util::now;
+use libp2p::identity::{Keypair, PublicKey};
+use libp2p::PeerId;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use thiserror::Error;
+
+/// The maximum age of a control message, and the maximum clock skew that is
+/// tolerated for messages with a timestamp in the future.
+pub const DEFAULT_REPLAY_WINDOW: Duration = Duration::from_secs(300);
+
+const SIGNATURE_DOMAIN: &[u8] = b"pyrsia-control-message";
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum ControlMessageError {
+    #[error("Control message could not be decoded: {0}")]
+    InvalidEncoding(String),
+    #[error("Control message has an invalid signature")]
+    InvalidSignature,
+    #[error("Control message for protocol {actual} was sent on protocol {expected}")]
+    WrongProtocol { expected: String, actual: String },
+    #[error("Control message is stale, it was signed {age} seconds ago")]
+    Stale { age: u64 },
+    #[error("Control message was signed {ahead} seconds in the future")]
+    FromTheFuture { ahead: u64 },
+    #[error("Control message with nonce {nonce} was replayed")]
+    Replayed { nonce: u64 },
+}
+
+impl From<ControlMessageError> for io::Error {
+    fn from(error: ControlMessageError) -> io::Error {
+        io::Error::new(io::ErrorKind::InvalidData, error)
+    }
+}
+
+/// A control message that is signed by the sending node. The signature
+/// covers the protocol, a random nonce and the time of signing in addition
+/// to the payload, so a message can be neither sent on another protocol nor
+/// replayed once it was accepted or after the replay window passed.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct SignedControlMessage {
+    pub protocol: String,
+    pub payload: Vec<u8>,
+    pub nonce: u64,
+    pub timestamp: u64,
+    public_key: Vec<u8>,
+    signature: Vec<u8>,
+}
+
+impl SignedControlMessage {
+    pub fn sign(
+        keypair: &Keypair,
+        protocol: &str,
+        payload: Vec<u8>,
+        timestamp: u64,
+    ) -> Result<Self, ControlMessageError> {
+        let mut message = SignedControlMessage {
+            protocol: protocol.to_owned(),
+            payload,
+            nonce: rand::thread_rng().gen(),
+            timestamp,
+            public_key: keypair.public().to_protobuf_encoding(),
+            signature: vec![],
+        };
+        message.signature = keypair
+            .sign(&message.signed_bytes())
+            .map_err(|_| ControlMessageError::InvalidSignature)?;
+        Ok(message)
+    }
+
+    /// Verify the signature and return the peer that signed the message.
+    pub fn verify_signature(&self) -> Result<PeerId, ControlMessageError> {
+        let public_key = PublicKey::from_protobuf_encoding(&self.public_key)
+            .map_err(|_| ControlMessageError::InvalidSignature)?;
+        if public_key.verify(&self.signed_bytes(), &self.signature) {
+            Ok(public_key.to_peer_id())
+        } else {
+            Err(ControlMessageError::InvalidSignature)
+        }
+    }
+
+    fn signed_bytes(&self) -> Vec<u8> {
+        let mut bytes = SIGNATURE_DOMAIN.to_vec();
+        for field in [self.protocol.as_bytes(), &self.payload] {
+            bytes.extend_from_slice(&(field.len() as u64).to_be_bytes());
+            bytes.extend_from_slice(field);
+        }
+        bytes.extend_from_slice(&self.nonce.to_be_bytes());
+        bytes.extend_from_slice(&self.timestamp.to_be_bytes());
+        bytes
+    }
+}
+
+/// Remembers the nonces of the control messages that were accepted within
+/// the replay window, per signer.
+#[derive(Debug)]
+pub struct ReplayGuard {
+    window: Duration,
+    seen: HashMap<(Vec<u8>, u64), u64>,
+}
+
+impl ReplayGuard {
+    pub fn new(window: Duration) -> Self {
+        ReplayGuard {
+            window,
+            seen: Default::default(),
+        }
+    }
+
+    /// Accept `message` if it is fresh and was not accepted before.
+    pub fn check(
+        &mut self,
+        message: &SignedControlMessage,
+        now: u64,
+    ) -> Result<(), ControlMessageError> {
+        // the timestamp is chosen by the peer, so it must not overflow
+        let window = self.window.as_secs();
+        if message.timestamp.saturating_add(window) < now {
+            return Err(ControlMessageError::Stale {
+                age: now - message.timestamp,
+            });
+        }
+        if message.timestamp > now.saturating_add(window) {
+            return Err(ControlMessageError::FromTheFuture {
+                ahead: message.timestamp - now,
+            });
+        }
+
+        // nonces of messages outside the window no longer need to be remembered
+        self.seen
+            .retain(|_, timestamp| timestamp.saturating_add(window) >= now);
+        let key = (message.public_key.clone(), message.nonce);
+        if self.seen.contains_key(&key) {
+            return Err(ControlMessageError::Replayed {
+                nonce: message.nonce,
+            });
+        }
+        self.seen.insert(key, message.timestamp);
+        Ok(())
+    }
+}
+
+/// Signs the outgoing control messages of this node and verifies the
+/// incoming ones. Clones share the replay guard, so a message that was
+/// accepted on one connection is rejected when it is replayed on another.
+#[derive(Clone)]
+pub struct ControlMessageSigner {
+    keypair: Keypair,
+    replay_guard: Arc<Mutex<ReplayGuard>>,
+}
+
+impl ControlMessageSigner {
+    pub fn new(keypair: Keypair) -> Self {
+        Self::with_replay_window(keypair, DEFAULT_REPLAY_WINDOW)
+    }
+
+    pub fn with_replay_window(keypair: Keypair, replay_window: Duration) -> Self {
+        ControlMessageSigner {
+            keypair,
+            replay_guard: Arc::new(Mutex::new(ReplayGuard::new(replay_window))),
+        }
+    }
+
+    /// Sign `payload` for `protocol` and encode the signed message.
+    pub fn sign(&self, protocol: &str, payload: Vec<u8>) -> Result<Vec<u8>, ControlMessageError> {
+        let message = SignedControlMessage::sign(&self.keypair, protocol, payload, now())?;
+        bincode::serialize(&message)
+            .map_err(|e| ControlMessageError::InvalidEncoding(e.to_string()))
+    }
+
+    /// Decode a signed message that was received on `protocol`, verify it
+    /// and return its payload.
+    pub fn verify(&self, protocol: &str, bytes: &[u8]) -> Result<Vec<u8>, ControlMessageError> {
+        self.verify_signer(protocol, bytes)
+            .map(|(_, payload)| payload)
+    }
+
+    /// Like [`verify`](ControlMessageSigner::verify), and also return the
+    /// peer that signed the message.
+    pub fn verify_signer(
+        &self,
+        protocol: &str,
+        bytes: &[u8],
+    ) -> Result<(PeerId, Vec<u8>), ControlMessageError> {
+        let message: SignedControlMessage = bincode::deserialize(bytes)
+            .map_err(|e| ControlMessageError::InvalidEncoding(e.to_string()))?;
+        if message.protocol != protocol {
+            return Err(ControlMessageError::WrongProtocol {
+                expected: protocol.to_owned(),
+                actual: message.protocol,
+            });
+        }
+        let signer = message.verify_signature()?;
+        self.replay_guard.lock().unwrap().check(&message, now())?;
+        Ok((signer, message.payload))
+    }
+}
+
+#[cfg(test)]
+#[cfg(not(tarpaulin_include))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signed_message_is_accepted_once() {
+        let signer = ControlMessageSigner::new(Keypair::generate_ed25519());
+        let bytes = signer.sign("/test/1", b"payload".to_vec()).unwrap();
+
+        let receiver = ControlMessageSigner::new(Keypair::generate_ed25519());
+        assert_eq!(receiver.verify("/test/1", &bytes).unwrap(), b"payload");
+        assert!(matches!(
+            receiver.verify("/test/1", &bytes),
+            Err(ControlMessageError::Replayed { .. })
+        ));
+        // a clone shares the nonces that were seen
+        assert!(matches!(
+            receiver.clone().verify("/test/1", &bytes),
+            Err(ControlMessageError::Replayed { .. })
+        ));
+
+        // a new message with the same payload has a new nonce
+        let bytes = signer.sign("/test/1", b"payload".to_vec()).unwrap();
+        assert_eq!(
+            receiver.verify_signer("/test/1", &bytes).unwrap().0,
+            signer.keypair.public().to_peer_id()
+        );
+    }
+
+    #[test]
+    fn test_stale_and_future_messages_are_rejected() {
+        let keypair = Keypair::generate_ed25519();
+        let mut replay_guard = ReplayGuard::new(Duration::from_secs(300));
+        let now = now();
+
+        let stale = SignedControlMessage::sign(&keypair, "/test/1", vec![], now - 301).unwrap();
+        assert_eq!(
+            replay_guard.check(&stale, now),
+            Err(ControlMessageError::Stale { age: 301 })
+        );
+
+        let future = SignedControlMessage::sign(&keypair, "/test/1", vec![], now + 301).unwrap();
+        assert_eq!(
+            replay_guard.check(&future, now),
+            Err(ControlMessageError::FromTheFuture { ahead: 301 })
+        );
+
+        let skewed = SignedControlMessage::sign(&keypair, "/test/1", vec![], now + 60).unwrap();
+        assert!(replay_guard.check(&skewed, now).is_ok());
+    }
+
+    #[test]
+    fn test_extreme_timestamps_are_rejected() {
+        let keypair = Keypair::generate_ed25519();
+        let mut replay_guard = ReplayGuard::new(Duration::from_secs(300));
+        let now = now();
+
+        let future = SignedControlMessage::sign(&keypair, "/test/1", vec![], u64::MAX).unwrap();
+        assert_eq!(
+            replay_guard.check(&future, now),
+            Err(ControlMessageError::FromTheFuture {
+                ahead: u64::MAX - now
+            })
+        );
+
+        let stale = SignedControlMessage::sign(&keypair, "/test/1", vec![], 0).unwrap();
+        assert_eq!(
+            replay_guard.check(&stale, now),
+            Err(ControlMessageError::Stale { age: now })
+        );
+
+        // nor does a clock at the end of time overflow
+        let message = SignedControlMessage::sign(&keypair, "/test/1", vec![], u64::MAX).unwrap();
+        assert!(replay_guard.check(&message, u64::MAX).is_ok());
+        assert!(replay_guard.check(&stale, u64::MAX).is_err());
+
+        // the replay guard is still usable through the signer afterwards
+        let signer = ControlMessageSigner::new(Keypair::generate_ed25519());
+        let receiver = ControlMessageSigner::new(Keypair::generate_ed25519());
+        let message =
+            SignedControlMessage::sign(&signer.keypair, "/test/1", vec![], u64::MAX).unwrap();
+        assert!(matches!(
+            receiver.verify("/test/1", &bincode::serialize(&message).unwrap()),
+            Err(ControlMessageError::FromTheFuture { .. })
+        ));
+        let bytes = signer.sign("/test/1", b"payload".to_vec()).unwrap();
+        assert_eq!(receiver.verify("/test/1", &bytes).unwrap(), b"payload");
+    }
+
+    #[test]
+    fn test_nonces_are_forgotten_after_the_replay_window() {
+        let keypair = Keypair::generate_ed25519();
+        let mut replay_guard = ReplayGuard::new(Duration::from_secs(300));
+        let now = now();
+
+        let message = SignedControlMessage::sign(&keypair, "/test/1", vec![], now).unwrap();
+        replay_guard.check(&message, now).unwrap();
+        assert_eq!(replay_guard.seen.len(), 1);
+
+        // once the window passed, the message itself is stale
+        let other = SignedControlMessage::sign(&keypair, "/test/1", vec![], now + 301).unwrap();
+        replay_guard.check(&other, now + 301).unwrap();
+        assert_eq!(replay_guard.seen.len(), 1);
+        assert!(matches!(
+            replay_guard.check(&message, now + 301),
+            Err(ControlMessageError::Stale { .. })
+        ));
+    }
+
+    #[test]
+    fn test_tampered_and_misdirected_messages_are_rejected() {
+        let signer = ControlMessageSigner::new(Keypair::generate_ed25519());
+        let receiver = ControlMessageSigner::new(Keypair::generate_ed25519());
+
+        let bytes = signer.sign("/test/1", b"payload".to_vec()).unwrap();
+        assert!(matches!(
+            receiver.verify("/other/1", &bytes),
+            Err(ControlMessageError::WrongProtocol { .. })
+        ));
+
+        let mut message: SignedControlMessage = bincode::deserialize(&bytes).unwrap();
+        message.payload = b"tampered".to_vec();
+        assert_eq!(
+            receiver.verify("/test/1", &bincode::serialize(&message).unwrap()),
+            Err(ControlMessageError::InvalidSignature)
+        );
+
+        message.payload = b"payload".to_vec();
+        message.timestamp += 1;
+        assert_eq!(
+            receiver.verify("/test/1", &bincode::serialize(&message).unwrap()),
+            Err(ControlMessageError::InvalidSignature)
+        );
+    }
+}
@@ -15,13 +15,30 @@
 */
 
 use crate::artifact_service::model::PackageType;
-use crate::network::artifact_protocol::{ArtifactRequest, ArtifactResponse};
+use crate::blockchain_service::genesis::DEFAULT_NETWORK_ID;
+use crate::network::artifact_protocol::{ArtifactChunk, ArtifactRequest, ArtifactResponse};
 use crate::network::behaviour::{PyrsiaNetworkBehaviour, PyrsiaNetworkEvent};
 use crate::network::blockchain_protocol::{BlockchainRequest, BlockchainResponse};
 use crate::network::build_protocol::{BuildRequest, BuildResponse};
 use crate::network::build_status_protocol::{BuildStatusRequest, BuildStatusResponse};
 use crate::network::client::command::Command;
+use crate::network::control_message::ControlMessageSigner;
 use crate::network::idle_metric_protocol::{IdleMetricRequest, IdleMetricResponse, PeerMetrics};
+use crate::network::p2p::identify_protocol_version;
+use crate::network::peer_alias::PeerAliases;
+use crate::network::peer_capabilities::PeerCapabilities;
+use crate::network::peer_exchange_protocol::{
+    PeerAddresses, PeerExchangeRequest, PeerExchangeResponse, PEER_EXCHANGE_SAMPLE_SIZE,
+};
+use crate::network::peer_maintenance::{
+    MaintenanceAnnouncement, PeerMaintenance, MAINTENANCE_PROTOCOL, MAINTENANCE_TOPIC,
+};
+use crate::network::peer_store::{PeerStore, PEER_STORE_SAVE_INTERVAL};
+use crate::network::peer_version::PeerVersions;
+use crate::network::query_metrics::{
+    query_succeeded, LookupStep, LookupTrace, QueryKind, QueryMetrics, SLOW_QUERY_THRESHOLD,
+};
+use crate::network::seed_sync_protocol::{SeedSyncRequest, SeedSyncResponse};
 use crate::node_api::model::request::Status;
 use crate::util::env_util::read_var;
 use libp2p::autonat::{Event as AutonatEvent, NatStatus};
@@ -29,14 +46,19 @@ use libp2p::core::PeerId;
 use libp2p::futures::StreamExt;
 use libp2p::gossipsub;
 use libp2p::identify;
-use libp2p::kad::{BootstrapOk, GetProvidersOk, KademliaEvent, QueryId, QueryResult};
+use libp2p::kad::kbucket::NodeStatus;
+use libp2p::kad::{
+    self, BootstrapOk, GetProvidersError, GetProvidersOk, KademliaEvent, QueryId, QueryResult,
+};
 use libp2p::multiaddr::Protocol;
 use libp2p::request_response::{
     RequestId, RequestResponseEvent, RequestResponseMessage, ResponseChannel,
 };
+use libp2p::swarm::dial_opts::DialOpts;
 use libp2p::swarm::SwarmEvent;
 use libp2p::Swarm;
 use log::{debug, error, info, trace, warn};
+use rand::seq::SliceRandom;
 use std::collections::{hash_map::Entry, HashMap, HashSet};
 use std::error::Error;
 use tokio::sync::{mpsc, oneshot};
@@ -45,22 +67,80 @@ type PendingBootstrapMap = HashMap<QueryId, oneshot::Sender<anyhow::Result<()>>>
 type PendingDialMap = HashMap<PeerId, oneshot::Sender<anyhow::Result<()>>>;
 type PendingListProvidersMap = HashMap<QueryId, PendingListProviders>;
 type PendingStartProvidingMap = HashMap<QueryId, oneshot::Sender<()>>;
-type PendingRequestArtifactMap = HashMap<RequestId, oneshot::Sender<anyhow::Result<Vec<u8>>>>;
+type PendingRequestArtifactMap =
+    HashMap<RequestId, oneshot::Sender<anyhow::Result<ArtifactResponse>>>;
 type PendingRequestBuildMap = HashMap<RequestId, oneshot::Sender<anyhow::Result<String>>>;
 type PendingRequestIdleMetricMap = HashMap<RequestId, oneshot::Sender<anyhow::Result<PeerMetrics>>>;
 type PendingRequestBlockchainMap = HashMap<RequestId, oneshot::Sender<anyhow::Result<Vec<u8>>>>;
 type PendingBuildStatusMap = HashMap<RequestId, oneshot::Sender<anyhow::Result<String>>>;
+type PendingPeerExchangeMap =
+    HashMap<RequestId, oneshot::Sender<anyhow::Result<Vec<PeerAddresses>>>>;
+type PendingSeedSyncMap = HashMap<RequestId, oneshot::Sender<anyhow::Result<SeedSyncResponse>>>;
+
+enum ListProvidersSender {
+    Providers(oneshot::Sender<HashSet<PeerId>>),
+    Trace(oneshot::Sender<LookupTrace>),
+}
 
 struct PendingListProviders {
-    sender: oneshot::Sender<HashSet<PeerId>>,
+    sender: ListProvidersSender,
+    artifact_id: String,
     providers: HashSet<PeerId>,
+    steps: Vec<LookupStep>,
+    error: Option<String>,
 }
 
 impl PendingListProviders {
-    fn new(sender: oneshot::Sender<HashSet<PeerId>>) -> Self {
+    fn new(sender: ListProvidersSender, artifact_id: String) -> Self {
         Self {
             sender,
+            artifact_id,
             providers: Default::default(),
+            steps: vec![],
+            error: None,
+        }
+    }
+
+    fn record_step(
+        &mut self,
+        step: usize,
+        stats: &kad::QueryStats,
+        result: Result<GetProvidersOk, GetProvidersError>,
+    ) {
+        let providers = match result {
+            Ok(GetProvidersOk::FoundProviders { providers, .. }) => providers,
+            Ok(GetProvidersOk::FinishedWithNoAdditionalRecord { .. }) => HashSet::new(),
+            Err(error) => {
+                self.error = Some(error.to_string());
+                HashSet::new()
+            }
+        };
+        self.steps.push(LookupStep {
+            step,
+            elapsed_ms: stats.duration().unwrap_or_default().as_millis() as u64,
+            providers: providers
+                .difference(&self.providers)
+                .map(PeerId::to_string)
+                .collect(),
+            requests: stats.num_requests(),
+            successes: stats.num_successes(),
+            failures: stats.num_failures(),
+        });
+        self.providers.extend(providers);
+    }
+
+    fn finish(self, stats: &kad::QueryStats) -> Result<(), ()> {
+        match self.sender {
+            ListProvidersSender::Providers(sender) => sender.send(self.providers).map_err(|_| ()),
+            ListProvidersSender::Trace(sender) => sender
+                .send(LookupTrace {
+                    artifact_id: self.artifact_id,
+                    duration_ms: stats.duration().unwrap_or_default().as_millis() as u64,
+                    providers: self.providers.iter().map(PeerId::to_string).collect(),
+                    steps: self.steps,
+                    error: self.error,
+                })
+                .map_err(|_| ()),
         }
     }
 }
@@ -73,6 +153,15 @@ pub struct PyrsiaEventLoop {
     swarm: Swarm<PyrsiaNetworkBehaviour>,
     command_receiver: mpsc::Receiver<Command>,
     event_sender: mpsc::Sender<PyrsiaEvent>,
+    peer_aliases: PeerAliases,
+    peer_versions: PeerVersions,
+    peer_capabilities: PeerCapabilities,
+    peer_maintenance: PeerMaintenance,
+    peer_store: PeerStore,
+    rehydrating_peers: HashSet<PeerId>,
+    control_message_signer: Option<ControlMessageSigner>,
+    query_metrics: QueryMetrics,
+    protocol_version: String,
     bootstrapped: bool,
     pending_bootstrap: PendingBootstrapMap,
     pending_dial: PendingDialMap,
@@ -83,6 +172,8 @@ pub struct PyrsiaEventLoop {
     pending_idle_metric_requests: PendingRequestIdleMetricMap,
     pending_blockchain_requests: PendingRequestBlockchainMap,
     pending_build_status_requests: PendingBuildStatusMap,
+    pending_peer_exchange_requests: PendingPeerExchangeMap,
+    pending_seed_sync_requests: PendingSeedSyncMap,
 }
 
 impl PyrsiaEventLoop {
@@ -95,6 +186,15 @@ impl PyrsiaEventLoop {
             swarm,
             command_receiver,
             event_sender,
+            peer_aliases: Default::default(),
+            peer_versions: Default::default(),
+            peer_capabilities: Default::default(),
+            peer_maintenance: Default::default(),
+            peer_store: Default::default(),
+            rehydrating_peers: Default::default(),
+            control_message_signer: None,
+            query_metrics: Default::default(),
+            protocol_version: identify_protocol_version(DEFAULT_NETWORK_ID),
             bootstrapped: false,
             pending_bootstrap: Default::default(),
             pending_dial: Default::default(),
@@ -105,12 +205,70 @@ impl PyrsiaEventLoop {
             pending_idle_metric_requests: Default::default(),
             pending_blockchain_requests: Default::default(),
             pending_build_status_requests: Default::default(),
+            pending_peer_exchange_requests: Default::default(),
+            pending_seed_sync_requests: Default::default(),
         }
     }
 
+    /// Record the aliases that peers advertise in `peer_aliases`.
+    pub fn with_peer_aliases(mut self, peer_aliases: PeerAliases) -> Self {
+        self.peer_aliases = peer_aliases;
+        self
+    }
+
+    /// Record the software versions that peers advertise in `peer_versions`.
+    pub fn with_peer_versions(mut self, peer_versions: PeerVersions) -> Self {
+        self.peer_versions = peer_versions;
+        self
+    }
+
+    /// Record the capabilities that peers advertise in `peer_capabilities`.
+    pub fn with_peer_capabilities(mut self, peer_capabilities: PeerCapabilities) -> Self {
+        self.peer_capabilities = peer_capabilities;
+        self
+    }
+
+    /// Record the maintenance announcements of peers in `peer_maintenance`.
+    /// The announcements of this node are signed, and those of peers are
+    /// verified, with `control_message_signer`.
+    pub fn with_peer_maintenance(
+        mut self,
+        peer_maintenance: PeerMaintenance,
+        control_message_signer: ControlMessageSigner,
+    ) -> Self {
+        self.peer_maintenance = peer_maintenance;
+        self.control_message_signer = Some(control_message_signer);
+        self
+    }
+
+    /// Persist the peers of the routing table in `peer_store`, and start
+    /// with the peers that it knows from before.
+    pub fn with_peer_store(mut self, peer_store: PeerStore) -> Self {
+        self.peer_store = peer_store;
+        self
+    }
+
+    /// Record the metrics of Kademlia queries in `query_metrics`.
+    pub fn with_query_metrics(mut self, query_metrics: QueryMetrics) -> Self {
+        self.query_metrics = query_metrics;
+        self
+    }
+
+    /// Disconnect the peers that do not belong to the network with
+    /// `network_id`, instead of the default network.
+    pub fn with_network_id(mut self, network_id: &str) -> Self {
+        self.protocol_version = identify_protocol_version(network_id);
+        self
+    }
+
     /// Creates the actual event loop to begin listening for
     /// incoming events on the swarm and command channels.
     pub async fn run(mut self) {
+        self.rehydrate_peers();
+        let mut save_peers_interval = tokio::time::interval_at(
+            tokio::time::Instant::now() + PEER_STORE_SAVE_INTERVAL,
+            PEER_STORE_SAVE_INTERVAL,
+        );
         loop {
             tokio::select! {
                 event = self.swarm.select_next_some() => match event {
@@ -123,6 +281,8 @@ impl PyrsiaEventLoop {
                     SwarmEvent::Behaviour(PyrsiaNetworkEvent::IdleMetricRequestResponse(request_response_event)) => self.handle_idle_metric_request_response_event(request_response_event).await,
                     SwarmEvent::Behaviour(PyrsiaNetworkEvent::BlockchainRequestResponse(request_response_event)) => self.handle_blockchain_request_response_event(request_response_event).await,
                     SwarmEvent::Behaviour(PyrsiaNetworkEvent::BuildStatusRequestResponse(build_status_request_response_event)) => self.handle_build_status_request_response_event(build_status_request_response_event).await,
+                    SwarmEvent::Behaviour(PyrsiaNetworkEvent::PeerExchangeRequestResponse(request_response_event)) => self.handle_peer_exchange_request_response_event(request_response_event).await,
+                    SwarmEvent::Behaviour(PyrsiaNetworkEvent::SeedSyncRequestResponse(request_response_event)) => self.handle_seed_sync_request_response_event(request_response_event).await,
                     swarm_event => self.handle_swarm_event(swarm_event).await,
                 },
                 command = self.command_receiver.recv() => match command {
@@ -130,12 +290,72 @@ impl PyrsiaEventLoop {
                         self.handle_command(c).await;
                     },
                     // Command channel closed, thus shutting down the network event loop.
-                    None => { warn!("Got empty command"); return },
+                    None => { warn!("Got empty command"); self.save_peers(); return },
                 },
+                _ = save_peers_interval.tick() => self.save_peers(),
+            }
+        }
+    }
+
+    // Adds the peers of the peer store to the routing table and dials them,
+    // so that the peers that cannot be dialed anymore are dropped again.
+    fn rehydrate_peers(&mut self) {
+        for peer in self.peer_store.peers() {
+            for address in &peer.addresses {
+                self.swarm
+                    .behaviour_mut()
+                    .kademlia
+                    .add_address(&peer.peer_id, address.clone());
             }
+            let dial_opts = DialOpts::peer_id(peer.peer_id)
+                .addresses(peer.addresses)
+                .build();
+            match self.swarm.dial(dial_opts) {
+                Ok(()) => {
+                    self.rehydrating_peers.insert(peer.peer_id);
+                }
+                Err(e) => {
+                    debug!("Failed to dial known peer {}: {}", peer.peer_id, e);
+                    self.forget_peer(&peer.peer_id);
+                }
+            }
+        }
+        if !self.rehydrating_peers.is_empty() {
+            info!(
+                "Dialing {} known peers from the peer store",
+                self.rehydrating_peers.len()
+            );
         }
     }
 
+    // Saves the peers of the routing table in the peer store.
+    fn save_peers(&mut self) {
+        let peers: Vec<PeerAddresses> = self
+            .swarm
+            .behaviour_mut()
+            .kademlia
+            .kbuckets()
+            .flat_map(|bucket| {
+                bucket
+                    .iter()
+                    .map(|entry| PeerAddresses {
+                        peer_id: *entry.node.key.preimage(),
+                        addresses: entry.node.value.iter().cloned().collect(),
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        self.peer_store.update(peers);
+        if let Err(e) = self.peer_store.save() {
+            warn!("Failed to save the peer store: {}", e);
+        }
+    }
+
+    fn forget_peer(&mut self, peer_id: &PeerId) {
+        self.swarm.behaviour_mut().kademlia.remove_peer(peer_id);
+        self.peer_store.remove(peer_id);
+    }
+
     // Handles events from the `AutoNat` network behaviour.
     async fn handle_autonat_event(&mut self, event: AutonatEvent) {
         trace!("Handle AutonatEvent: {:?}", event);
@@ -165,6 +385,10 @@ impl PyrsiaEventLoop {
     async fn handle_gossipsub_event(&mut self, event: gossipsub::GossipsubEvent) {
         trace!("Handle GossipsubEvent: {:?}", event);
         if let gossipsub::GossipsubEvent::Message { message, .. } = event {
+            if message.topic == gossipsub::IdentTopic::new(MAINTENANCE_TOPIC).hash() {
+                self.handle_maintenance_announcement(message.source, &message.data);
+                return;
+            }
             self.event_sender
                 .send(PyrsiaEvent::BlockchainRequest {
                     data: message.data,
@@ -175,12 +399,103 @@ impl PyrsiaEventLoop {
         }
     }
 
+    // Records the maintenance announcement of a peer, which must be signed
+    // by the peer that published it.
+    fn handle_maintenance_announcement(&mut self, source: Option<PeerId>, data: &[u8]) {
+        let Some(control_message_signer) = &self.control_message_signer else {
+            debug!("Ignored maintenance announcement, control messages cannot be verified");
+            return;
+        };
+
+        let announcement = control_message_signer
+            .verify_signer(MAINTENANCE_PROTOCOL, data)
+            .map_err(anyhow::Error::from)
+            .and_then(|(signer, payload)| {
+                let announcement: MaintenanceAnnouncement = bincode::deserialize(&payload)?;
+                Ok((signer, announcement))
+            });
+        match announcement {
+            Ok((signer, announcement)) if source == Some(signer) => {
+                info!(
+                    "Peer {} announced maintenance until {}{}",
+                    self.peer_aliases.display(&signer),
+                    announcement.until,
+                    announcement
+                        .reason
+                        .as_ref()
+                        .map(|reason| format!(": {}", reason))
+                        .unwrap_or_default()
+                );
+                self.peer_maintenance.record(&signer, announcement);
+            }
+            Ok((signer, _)) => warn!(
+                "Ignored maintenance announcement of peer {} that was published by {:?}",
+                signer, source
+            ),
+            Err(e) => warn!("Ignored invalid maintenance announcement: {:?}", e),
+        }
+    }
+
+    // Signs the maintenance announcement of this node and publishes it to
+    // the peers. The announcement applies to this node too, also when no
+    // peer is subscribed yet.
+    fn announce_maintenance(
+        &mut self,
+        announcement: MaintenanceAnnouncement,
+    ) -> anyhow::Result<()> {
+        let control_message_signer = self
+            .control_message_signer
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("Maintenance announcements cannot be signed"))?;
+        let data = control_message_signer
+            .sign(MAINTENANCE_PROTOCOL, bincode::serialize(&announcement)?)?;
+
+        let local_peer_id = *self.swarm.local_peer_id();
+        self.peer_maintenance.record(&local_peer_id, announcement);
+        match self
+            .swarm
+            .behaviour_mut()
+            .gossipsub
+            .publish(gossipsub::IdentTopic::new(MAINTENANCE_TOPIC), data)
+        {
+            Ok(_) | Err(gossipsub::PublishError::InsufficientPeers) => Ok(()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
     // Handles events from the `Identify` network behaviour.
     async fn handle_identify_event(&mut self, event: identify::Event) {
         trace!("Handle IdentifyEvent: {:?}", event);
         match event {
             identify::Event::Pushed { .. } => {}
-            identify::Event::Received { .. } => {}
+            identify::Event::Received { peer_id, info } => {
+                if info.protocol_version != self.protocol_version {
+                    warn!(
+                        "Disconnect peer {} of another network with protocol version {}",
+                        peer_id, info.protocol_version
+                    );
+                    self.forget_peer(&peer_id);
+                    if self.swarm.disconnect_peer_id(peer_id).is_err() {
+                        debug!("Peer {} was disconnected already", peer_id);
+                    }
+                    return;
+                }
+                self.peer_aliases
+                    .record_agent_version(&peer_id, &info.agent_version);
+                self.peer_versions
+                    .record_agent_version(&peer_id, &info.agent_version);
+                self.peer_capabilities
+                    .record_agent_version(&peer_id, &info.agent_version);
+                // the listen addresses of a peer can be dialed, unlike the
+                // address of an incoming connection, and are shared with
+                // other peers in peer exchanges
+                for address in info.listen_addrs {
+                    self.swarm
+                        .behaviour_mut()
+                        .kademlia
+                        .add_address(&peer_id, address);
+                }
+            }
             identify::Event::Sent { .. } => {}
             identify::Event::Error { .. } => {}
         }
@@ -190,6 +505,17 @@ impl PyrsiaEventLoop {
     async fn handle_kademlia_event(&mut self, event: KademliaEvent) {
         trace!("Handle KademliaEvent: {:?}", event);
         let event_str = format!("{:#?}", event);
+        if let KademliaEvent::OutboundQueryProgressed {
+            result,
+            stats,
+            step,
+            ..
+        } = &event
+        {
+            if step.last {
+                self.record_query(result, stats);
+            }
+        }
         match event {
             KademliaEvent::OutboundQueryProgressed {
                 id,
@@ -210,32 +536,42 @@ impl PyrsiaEventLoop {
             }
             KademliaEvent::OutboundQueryProgressed {
                 id,
-                result:
-                    QueryResult::GetProviders(Ok(GetProvidersOk::FoundProviders { providers, .. })),
-                ..
+                result: QueryResult::GetProviders(result),
+                stats,
+                step,
             } => {
                 self.pending_list_providers
                     .get_mut(&id)
                     .expect("Completed query to be previously pending.")
-                    .providers
-                    .extend(providers);
+                    .record_step(step.count.get(), &stats, result);
+
+                // a lookup that times out still returns the providers that
+                // were found until then
+                if step.last {
+                    self.pending_list_providers
+                        .remove(&id)
+                        .expect("Completed query to be previously pending.")
+                        .finish(&stats)
+                        .unwrap_or_else(|e| {
+                            error!(
+                                "Handle KademliaEvent match arm: {}. Error: {:?}",
+                                event_str, e
+                            );
+                        });
+                }
             }
             KademliaEvent::OutboundQueryProgressed {
                 id,
                 result:
-                    QueryResult::GetProviders(Ok(GetProvidersOk::FinishedWithNoAdditionalRecord {
-                        ..
+                    QueryResult::Bootstrap(Ok(BootstrapOk {
+                        num_remaining: 0, ..
                     })),
                 ..
             } => {
-                let pending_list_provider = self
-                    .pending_list_providers
+                self.pending_bootstrap
                     .remove(&id)
-                    .expect("Completed query to be previously pending.");
-
-                pending_list_provider
-                    .sender
-                    .send(pending_list_provider.providers)
+                    .expect("Completed query to be previously pending.")
+                    .send(Ok(()))
                     .unwrap_or_else(|e| {
                         error!(
                             "Handle KademliaEvent match arm: {}. Error: {:?}",
@@ -243,24 +579,6 @@ impl PyrsiaEventLoop {
                         );
                     });
             }
-            KademliaEvent::OutboundQueryProgressed {
-                id,
-                result: QueryResult::Bootstrap(Ok(BootstrapOk { num_remaining, .. })),
-                ..
-            } => {
-                if num_remaining == 0 {
-                    self.pending_bootstrap
-                        .remove(&id)
-                        .expect("Completed query to be previously pending.")
-                        .send(Ok(()))
-                        .unwrap_or_else(|e| {
-                            error!(
-                                "Handle KademliaEvent match arm: {}. Error: {:?}",
-                                event_str, e
-                            );
-                        });
-                }
-            }
             KademliaEvent::OutboundQueryProgressed {
                 id,
                 result: QueryResult::Bootstrap(Err(e)),
@@ -281,6 +599,30 @@ impl PyrsiaEventLoop {
         }
     }
 
+    // Records the metrics of a finished Kademlia query.
+    fn record_query(&self, result: &QueryResult, stats: &kad::QueryStats) {
+        let kind = QueryKind::of(result);
+        let succeeded = query_succeeded(result);
+        let duration = stats.duration().unwrap_or_default();
+        if duration > SLOW_QUERY_THRESHOLD {
+            warn!(
+                "Slow Kademlia {} query took {:.1}s and sent {} requests ({} failed)",
+                kind,
+                duration.as_secs_f64(),
+                stats.num_requests(),
+                stats.num_failures()
+            );
+        }
+        debug!(
+            "Kademlia {} query {} in {}ms after {} requests",
+            kind,
+            if succeeded { "succeeded" } else { "failed" },
+            duration.as_millis(),
+            stats.num_requests()
+        );
+        self.query_metrics.record(kind, stats, succeeded);
+    }
+
     // Handles events from the `RequestResponse` for artifact exchange
     // network behaviour.
     async fn handle_request_response_event(
@@ -290,13 +632,15 @@ impl PyrsiaEventLoop {
         trace!("Handle RequestResponseEvent: {:?}", event);
         let event_str = format!("{:#?}", event);
         match event {
-            RequestResponseEvent::Message { message, .. } => match message {
+            RequestResponseEvent::Message { peer, message } => match message {
                 RequestResponseMessage::Request {
                     request, channel, ..
                 } => {
                     self.event_sender
                         .send(PyrsiaEvent::RequestArtifact {
-                            artifact_id: request.0,
+                            peer,
+                            artifact_id: request.artifact_id,
+                            chunk: request.chunk,
                             channel,
                         })
                         .await
@@ -309,7 +653,7 @@ impl PyrsiaEventLoop {
                     self.pending_request_artifact
                         .remove(&request_id)
                         .expect("Request to still be pending.")
-                        .send(Ok(response.0))
+                        .send(Ok(response))
                         .unwrap_or_else(|e| {
                             error!(
                                 "Handle RequestResponseEvent match arm: {}. Error: {:?}",
@@ -388,13 +732,14 @@ impl PyrsiaEventLoop {
         trace!("Handle BuildRequestResponseEvent: {:?}", event);
         let event_str = format!("{:#?}", event);
         match event {
-            RequestResponseEvent::Message { message, .. } => match message {
+            RequestResponseEvent::Message { peer, message } => match message {
                 RequestResponseMessage::Request {
                     request, channel, ..
                 } => {
                     debug!("RequestResponseMessage::Request {:?}", request);
                     self.event_sender
                         .send(PyrsiaEvent::RequestBuild {
+                            peer,
                             package_type: request.0,
                             package_specific_id: request.1,
                             channel,
@@ -546,6 +891,135 @@ impl PyrsiaEventLoop {
         }
     }
 
+    // Handles events from the `RequestResponse` for peer exchange network
+    // behaviour. Requests are answered directly, because the sample of known
+    // peers only depends on the state of the swarm.
+    async fn handle_peer_exchange_request_response_event(
+        &mut self,
+        event: RequestResponseEvent<PeerExchangeRequest, PeerExchangeResponse>,
+    ) {
+        trace!("Handle RequestResponseEvent: {:?}", event);
+        let event_str = format!("{:#?}", event);
+        match event {
+            RequestResponseEvent::Message { peer, message } => match message {
+                RequestResponseMessage::Request { channel, .. } => {
+                    let peers = self.sample_connected_peers(&peer);
+                    self.swarm
+                        .behaviour_mut()
+                        .peer_exchange_request_response
+                        .send_response(channel, PeerExchangeResponse(peers))
+                        .unwrap_or_else(|_| {
+                            debug!(
+                                "Failed to respond to peer exchange with peer {}",
+                                self.peer_aliases.display(&peer)
+                            );
+                        });
+                }
+                RequestResponseMessage::Response {
+                    request_id,
+                    response,
+                } => {
+                    self.pending_peer_exchange_requests
+                        .remove(&request_id)
+                        .expect("Request to still be pending.")
+                        .send(Ok(response.0))
+                        .unwrap_or_else(|e| {
+                            error!("Handle RequestResponseEvent match arm: {}. pending_peer_exchange_requests: {:?}", event_str, e);
+                        });
+                }
+            },
+            RequestResponseEvent::InboundFailure { .. } => {}
+            RequestResponseEvent::OutboundFailure {
+                request_id, error, ..
+            } => {
+                self.pending_peer_exchange_requests
+                    .remove(&request_id)
+                    .expect("Request to still be pending.")
+                    .send(Err(error.into()))
+                    .unwrap_or_else(|e| {
+                        error!("Handle RequestResponseEvent match arm: {}. pending_peer_exchange_requests: {:?}", event_str, e);
+                    });
+            }
+            RequestResponseEvent::ResponseSent { .. } => {}
+        }
+    }
+
+    // Handles events from the `RequestResponse` for seed sync network
+    // behaviour.
+    async fn handle_seed_sync_request_response_event(
+        &mut self,
+        event: RequestResponseEvent<SeedSyncRequest, SeedSyncResponse>,
+    ) {
+        trace!("Handle RequestResponseEvent: {:?}", event);
+        let event_str = format!("{:#?}", event);
+        match event {
+            RequestResponseEvent::Message { peer, message } => match message {
+                RequestResponseMessage::Request {
+                    request, channel, ..
+                } => {
+                    self.event_sender
+                        .send(PyrsiaEvent::RequestSeedSync {
+                            peer,
+                            request,
+                            channel,
+                        })
+                        .await
+                        .expect("Event receiver not to be dropped.");
+                }
+                RequestResponseMessage::Response {
+                    request_id,
+                    response,
+                } => {
+                    self.pending_seed_sync_requests
+                        .remove(&request_id)
+                        .expect("Request to still be pending.")
+                        .send(Ok(response))
+                        .unwrap_or_else(|e| {
+                            error!("Handle RequestResponseEvent match arm: {}. pending_seed_sync_requests: {:?}", event_str, e);
+                        });
+                }
+            },
+            RequestResponseEvent::InboundFailure { .. } => {}
+            RequestResponseEvent::OutboundFailure {
+                request_id, error, ..
+            } => {
+                self.pending_seed_sync_requests
+                    .remove(&request_id)
+                    .expect("Request to still be pending.")
+                    .send(Err(error.into()))
+                    .unwrap_or_else(|e| {
+                        error!("Handle RequestResponseEvent match arm: {}. pending_seed_sync_requests: {:?}", event_str, e);
+                    });
+            }
+            RequestResponseEvent::ResponseSent { .. } => {}
+        }
+    }
+
+    // A random sample of the connected peers in the routing table and their
+    // addresses, without the peer that the sample is sent to.
+    fn sample_connected_peers(&mut self, requesting_peer: &PeerId) -> Vec<PeerAddresses> {
+        let mut peers: Vec<PeerAddresses> = self
+            .swarm
+            .behaviour_mut()
+            .kademlia
+            .kbuckets()
+            .flat_map(|bucket| {
+                bucket
+                    .iter()
+                    .filter(|entry| entry.status == NodeStatus::Connected)
+                    .map(|entry| PeerAddresses {
+                        peer_id: *entry.node.key.preimage(),
+                        addresses: entry.node.value.iter().cloned().collect(),
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .filter(|peer| peer.peer_id != *requesting_peer)
+            .collect();
+        peers.shuffle(&mut rand::thread_rng());
+        peers.truncate(PEER_EXCHANGE_SAMPLE_SIZE);
+        peers
+    }
+
     // Handles all other events from the libp2p `Swarm`.
     async fn handle_swarm_event(&mut self, event: SwarmEvent<PyrsiaNetworkEvent, impl Error>) {
         trace!("Handle SwarmEvent: {:?}", event);
@@ -564,6 +1038,11 @@ impl PyrsiaEventLoop {
             SwarmEvent::ConnectionEstablished {
                 peer_id, endpoint, ..
             } => {
+                debug!(
+                    "Connection established with peer {}",
+                    self.peer_aliases.display(&peer_id)
+                );
+                self.rehydrating_peers.remove(&peer_id);
                 if endpoint.is_dialer() {
                     if let Some(sender) = self.pending_dial.remove(&peer_id) {
                         self.swarm
@@ -583,6 +1062,10 @@ impl PyrsiaEventLoop {
                     if peer_id == *self.swarm.local_peer_id() {
                         warn!("The dialed node has the same peer ID as the current node: '{}'. Please make sure that every node has a unique peer ID.", peer_id);
                     }
+                    if self.rehydrating_peers.remove(&peer_id) {
+                        debug!("Drop known peer {} that cannot be dialed", peer_id);
+                        self.forget_peer(&peer_id);
+                    }
                     if let Some(sender) = self.pending_dial.remove(&peer_id) {
                         sender.send(Err(error.into())).unwrap_or_else(|_e| {
                             error!("Handle SwarmEvent match arm: {}", event_str);
@@ -595,7 +1078,7 @@ impl PyrsiaEventLoop {
                 debug!(
                     "Local Peer {} is dialing Peer {}...",
                     self.swarm.local_peer_id(),
-                    peer_id
+                    self.peer_aliases.display(&peer_id)
                 );
             }
             SwarmEvent::ExpiredListenAddr { .. } => {}
@@ -669,6 +1152,27 @@ impl PyrsiaEventLoop {
                     }
                 }
             }
+            Command::DialPeer {
+                peer_id,
+                peer_addrs,
+                sender,
+            } => {
+                if let Entry::Vacant(_) = self.pending_dial.entry(peer_id) {
+                    match self
+                        .swarm
+                        .dial(DialOpts::peer_id(peer_id).addresses(peer_addrs).build())
+                    {
+                        Ok(()) => {
+                            self.pending_dial.insert(peer_id, sender);
+                        }
+                        Err(e) => {
+                            sender.send(Err(e.into())).unwrap_or_else(|_e| {
+                                error!("Handle Command match arm: {}.", command_str);
+                            });
+                        }
+                    }
+                }
+            }
             Command::ListPeers { sender } => {
                 let peers = HashSet::from_iter(self.swarm.connected_peers().copied());
                 sender.send(peers).unwrap_or_else(|_e| {
@@ -709,6 +1213,10 @@ impl PyrsiaEventLoop {
                     peers_count: swarm.connected_peers().count(),
                     peer_id: local_peer_id.to_string(),
                     peer_addrs,
+                    alias: self.peer_aliases.own_alias(),
+                    version: PeerVersions::own_version(),
+                    peer_versions: self.peer_versions.histogram(swarm.connected_peers()),
+                    capabilities: self.peer_capabilities.own_capabilities(),
                 };
 
                 sender.send(status).unwrap();
@@ -725,6 +1233,18 @@ impl PyrsiaEventLoop {
                     .expect("No store error.");
                 self.pending_start_providing.insert(query_id, sender);
             }
+            Command::StopProviding {
+                artifact_id,
+                sender,
+            } => {
+                self.swarm
+                    .behaviour_mut()
+                    .kademlia
+                    .stop_providing(&artifact_id.into_bytes().into());
+                sender.send(()).unwrap_or_else(|_e| {
+                    error!("Handle Command match arm: {}.", command_str);
+                });
+            }
             Command::ListProviders {
                 artifact_id,
                 sender,
@@ -733,9 +1253,25 @@ impl PyrsiaEventLoop {
                     .swarm
                     .behaviour_mut()
                     .kademlia
-                    .get_providers(artifact_id.into_bytes().into());
-                self.pending_list_providers
-                    .insert(query_id, PendingListProviders::new(sender));
+                    .get_providers(artifact_id.clone().into_bytes().into());
+                self.pending_list_providers.insert(
+                    query_id,
+                    PendingListProviders::new(ListProvidersSender::Providers(sender), artifact_id),
+                );
+            }
+            Command::TraceLookup {
+                artifact_id,
+                sender,
+            } => {
+                let query_id = self
+                    .swarm
+                    .behaviour_mut()
+                    .kademlia
+                    .get_providers(artifact_id.clone().into_bytes().into());
+                self.pending_list_providers.insert(
+                    query_id,
+                    PendingListProviders::new(ListProvidersSender::Trace(sender), artifact_id),
+                );
             }
             Command::RequestBuild {
                 peer,
@@ -761,6 +1297,7 @@ impl PyrsiaEventLoop {
             }
             Command::RequestArtifact {
                 artifact_id,
+                chunk,
                 peer,
                 sender,
             } => {
@@ -768,14 +1305,14 @@ impl PyrsiaEventLoop {
                     .swarm
                     .behaviour_mut()
                     .request_response
-                    .send_request(&peer, ArtifactRequest(artifact_id));
+                    .send_request(&peer, ArtifactRequest { artifact_id, chunk });
                 self.pending_request_artifact.insert(request_id, sender);
             }
-            Command::RespondArtifact { artifact, channel } => {
+            Command::RespondArtifact { response, channel } => {
                 self.swarm
                     .behaviour_mut()
                     .request_response
-                    .send_response(channel, ArtifactResponse(artifact))
+                    .send_response(channel, response)
                     .expect("Connection to peer to be still open.");
             }
             Command::RequestIdleMetric { peer, sender } => {
@@ -793,6 +1330,34 @@ impl PyrsiaEventLoop {
                     .send_response(channel, IdleMetricResponse(metric))
                     .expect("Connection to peer to be still open.");
             }
+            Command::RequestPeerExchange { peer, sender } => {
+                let request_id = self
+                    .swarm
+                    .behaviour_mut()
+                    .peer_exchange_request_response
+                    .send_request(&peer, PeerExchangeRequest());
+                self.pending_peer_exchange_requests
+                    .insert(request_id, sender);
+            }
+            Command::RequestSeedSync {
+                peer,
+                request,
+                sender,
+            } => {
+                let request_id = self
+                    .swarm
+                    .behaviour_mut()
+                    .seed_sync_request_response
+                    .send_request(&peer, request);
+                self.pending_seed_sync_requests.insert(request_id, sender);
+            }
+            Command::RespondSeedSync { response, channel } => {
+                self.swarm
+                    .behaviour_mut()
+                    .seed_sync_request_response
+                    .send_response(channel, response)
+                    .unwrap_or_else(|_| debug!("Failed to respond to seed sync request"));
+            }
             Command::RequestBlockchain { data, peer, sender } => {
                 let request_id = self
                     .swarm
@@ -808,6 +1373,16 @@ impl PyrsiaEventLoop {
                     .send_response(channel, BlockchainResponse(data))
                     .expect("Connection to peer to be still open.");
             }
+            Command::AnnounceMaintenance {
+                announcement,
+                sender,
+            } => {
+                sender
+                    .send(self.announce_maintenance(announcement))
+                    .unwrap_or_else(|_e| {
+                        error!("Handle Command match arm: {}.", command_str);
+                    });
+            }
             Command::BroadcastBlock {
                 topic,
                 block,
@@ -854,10 +1429,13 @@ impl PyrsiaEventLoop {
 #[derive(Debug)]
 pub enum PyrsiaEvent {
     RequestArtifact {
+        peer: PeerId,
         artifact_id: String,
+        chunk: Option<ArtifactChunk>,
         channel: ResponseChannel<ArtifactResponse>,
     },
     RequestBuild {
+        peer: PeerId,
         package_type: PackageType,
         package_specific_id: String,
         channel: ResponseChannel<BuildResponse>,
@@ -873,6 +1451,11 @@ pub enum PyrsiaEvent {
         build_id: String,
         channel: ResponseChannel<BuildStatusResponse>,
     },
+    RequestSeedSync {
+        peer: PeerId,
+        request: SeedSyncRequest,
+        channel: ResponseChannel<SeedSyncResponse>,
+    },
 }
 
 #[cfg(test)]
@@ -888,10 +1471,14 @@ mod tests {
         BuildStatusExchangeCodec, BuildStatusExchangeProtocol,
     };
     use crate::network::client::Client;
+    use crate::network::control_message::ControlMessageSigner;
     use crate::network::event_loop::PyrsiaEvent;
     use crate::network::idle_metric_protocol::{
         IdleMetricExchangeCodec, IdleMetricExchangeProtocol,
     };
+    use crate::network::peer_exchange_protocol::{PeerExchangeCodec, PeerExchangeProtocol};
+    use crate::network::seed_sync_protocol::{SeedSyncCodec, SeedSyncProtocol};
+    use crate::util::test_util;
     use libp2p::core::upgrade;
     use libp2p::core::Transport;
     use libp2p::dns::TokioDnsConfig;
@@ -980,7 +1567,7 @@ mod tests {
                 Default::default(),
             ),
             build_request_response: request_response::RequestResponse::new(
-                BuildExchangeCodec(),
+                BuildExchangeCodec(ControlMessageSigner::new(id_keys.clone())),
                 iter::once((
                     BuildExchangeProtocol(),
                     request_response::ProtocolSupport::Full,
@@ -1004,13 +1591,26 @@ mod tests {
                 Default::default(),
             ),
             build_status_request_response: request_response::RequestResponse::new(
-                BuildStatusExchangeCodec(),
+                BuildStatusExchangeCodec(ControlMessageSigner::new(id_keys.clone())),
                 iter::once((
                     BuildStatusExchangeProtocol(),
                     request_response::ProtocolSupport::Full,
                 )),
                 Default::default(),
             ),
+            peer_exchange_request_response: request_response::RequestResponse::new(
+                PeerExchangeCodec(),
+                iter::once((
+                    PeerExchangeProtocol(),
+                    request_response::ProtocolSupport::Full,
+                )),
+                Default::default(),
+            ),
+            seed_sync_request_response: request_response::RequestResponse::new(
+                SeedSyncCodec(),
+                iter::once((SeedSyncProtocol(), request_response::ProtocolSupport::Full)),
+                Default::default(),
+            ),
         };
 
         let swarm = SwarmBuilder::with_tokio_executor(
@@ -1081,6 +1681,45 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_rehydrate_peers_from_peer_store() {
+        let tmp_dir = test_util::tests::setup();
+        let path = tmp_dir.join("peers.json");
+
+        let (mut p2p_client_1, event_loop_1, _) = create_test_swarm();
+        tokio::spawn(event_loop_1.run());
+        p2p_client_1
+            .listen(&"/ip4/127.0.0.1/tcp/44128".parse().unwrap())
+            .await
+            .unwrap();
+
+        let reachable_peer = PeerAddresses {
+            peer_id: p2p_client_1.local_peer_id,
+            addresses: vec!["/ip4/127.0.0.1/tcp/44128".parse().unwrap()],
+        };
+        let unreachable_peer = PeerAddresses {
+            peer_id: PeerId::random(),
+            addresses: vec!["/ip4/127.0.0.1/tcp/44129".parse().unwrap()],
+        };
+        let peer_store = PeerStore::new(&path);
+        peer_store.update(vec![reachable_peer.clone(), unreachable_peer]);
+        peer_store.save().unwrap();
+
+        let (p2p_client_2, event_loop_2, _) = create_test_swarm();
+        let event_loop_2 = tokio::spawn(event_loop_2.with_peer_store(PeerStore::new(&path)).run());
+        tokio::time::sleep(Duration::from_secs(2)).await;
+
+        // the peers are saved when the event loop shuts down
+        drop(p2p_client_2);
+        event_loop_2.await.unwrap();
+
+        let peers = PeerStore::new(&path).peers();
+        assert_eq!(peers.len(), 1);
+        assert_eq!(peers[0].peer_id, reachable_peer.peer_id);
+
+        test_util::tests::teardown(tmp_dir);
+    }
+
     #[tokio::test(flavor = "multi_thread")]
     async fn test_dial_with_invalid_peer_id() {
         let (mut p2p_client_1, event_loop_1, _) = create_test_swarm();
@@ -14,12 +14,23 @@
    limitations under the License.
 */
 
+use crate::blockchain_service::genesis::DEFAULT_NETWORK_ID;
 use crate::network::artifact_protocol::{ArtifactExchangeCodec, ArtifactExchangeProtocol};
 use crate::network::behaviour::PyrsiaNetworkBehaviour;
 use crate::network::blockchain_protocol::{BlockchainExchangeCodec, BlockchainExchangeProtocol};
 use crate::network::client::Client;
+use crate::network::control_message::ControlMessageSigner;
 use crate::network::event_loop::{PyrsiaEvent, PyrsiaEventLoop};
 use crate::network::idle_metric_protocol::{IdleMetricExchangeCodec, IdleMetricExchangeProtocol};
+use crate::network::peer_alias::PeerAliases;
+use crate::network::peer_capabilities::PeerCapabilities;
+use crate::network::peer_exchange_protocol::{PeerExchangeCodec, PeerExchangeProtocol};
+use crate::network::peer_maintenance::{PeerMaintenance, MAINTENANCE_TOPIC};
+use crate::network::peer_store::PeerStore;
+use crate::network::peer_throughput::MAX_TRANSFER_TIMEOUT;
+use crate::network::peer_version::PeerVersions;
+use crate::network::query_metrics::QueryMetrics;
+use crate::network::seed_sync_protocol::{SeedSyncCodec, SeedSyncProtocol};
 use crate::util::keypair_util;
 use crate::util::keypair_util::KEYPAIR_FILENAME;
 
@@ -29,7 +40,7 @@ use crate::network::build_status_protocol::{
 };
 use libp2p::identity::Keypair;
 use libp2p::kad::record::store::{MemoryStore, MemoryStoreConfig};
-use libp2p::request_response::{ProtocolSupport, RequestResponse};
+use libp2p::request_response::{ProtocolSupport, RequestResponse, RequestResponseConfig};
 use libp2p::swarm::{Swarm, SwarmBuilder};
 use libp2p::{
     autonat, core, dns, gossipsub, identify, identity, kad, mplex, noise, tcp, yamux, Transport,
@@ -60,7 +71,10 @@ use tokio_stream::Stream;
 /// * Identify: a protocol for exchanging identity information between peers
 /// * Kademlia: a DHT to share information over the libp2p network
 /// * RequestResponse: a generic request/response protocol implementation for
-/// the [`FileExchangeProtocol`]
+///   the [`FileExchangeProtocol`]
+/// * PeerExchange: a request/response protocol for sharing a sample of the
+///   peers that a node is connected to, so that small networks interconnect
+///   faster than with Kademlia random walks alone
 ///
 /// The maximum number of provided keys for the memory store that is used by
 /// Kademlia can be provided with the `max_provided_keys` parameter. This number
@@ -104,12 +118,37 @@ use tokio_stream::Stream;
 /// 16. The Client receiver receives the incoming response and can now safely return
 ///     to the application.
 ///
+/// The `peer_aliases` registry is shared by the Client and the PyrsiaEventLoop.
+/// The alias of this node is advertised to other peers with the Identify
+/// protocol, and the aliases that other peers advertise are recorded in it.
+/// The `peer_versions` registry is shared the same way: the PyrsiaEventLoop
+/// records the software versions of peers, and the Client refuses protocol
+/// features to outdated peers. The capabilities of this node in
+/// `peer_capabilities` are advertised in the agent version too, and the
+/// Client routes operations on a package type to the peers that advertised
+/// support for it. The maintenance announcements of peers are received on a
+/// gossipsub topic of their own and recorded by the PyrsiaEventLoop, so that
+/// the Client skips draining nodes for new builds. The metrics of the
+/// Kademlia queries are recorded by the PyrsiaEventLoop and reported by the
+/// Client.
+///
+/// Nodes only talk to peers of the network with `network_id`: they
+/// advertise the [protocol version](identify_protocol_version) of their
+/// network with the Identify protocol, the PyrsiaEventLoop disconnects peers
+/// that advertise another one, and the blocks are broadcast on a gossipsub
+/// topic of the network.
+///
 /// This function returns the following components:
 ///  * the Client
 ///  * the receiver part of the event channel
 ///  * the PyrsiaEventLoop
 pub fn setup_libp2p_swarm(
+    network_id: &str,
     max_provided_keys: usize,
+    peer_aliases: PeerAliases,
+    peer_versions: PeerVersions,
+    peer_capabilities: PeerCapabilities,
+    peer_store: PeerStore,
 ) -> Result<
     (
         Client,
@@ -120,25 +159,74 @@ pub fn setup_libp2p_swarm(
     Box<dyn Error>,
 > {
     let local_keypair = keypair_util::load_or_generate_ed25519(KEYPAIR_FILENAME.as_str());
+    let control_message_signer = ControlMessageSigner::new(local_keypair.clone());
 
-    let (mut swarm, local_peer_id) = create_swarm(local_keypair.clone(), max_provided_keys)?;
+    let (mut swarm, local_peer_id) = create_swarm(
+        local_keypair.clone(),
+        control_message_signer.clone(),
+        max_provided_keys,
+        identify_protocol_version(network_id),
+        format!(
+            "{} {}",
+            peer_aliases.agent_version(),
+            peer_capabilities.own_capabilities().agent_version_tokens()
+        ),
+    )?;
+    let query_metrics = QueryMetrics::default();
     let (command_sender, command_receiver) = mpsc::channel(32);
     let (event_sender, event_receiver) = mpsc::channel(32);
 
     // EDF: Two types of implemented Topic. Example uses IdentTopic. Let's start with that.
     // https://docs.rs/libp2p/latest/libp2p/gossipsub/type.IdentTopic.html
     // https://docs.rs/libp2p/latest/libp2p/gossipsub/type.Sha256Topic.html
-    let pyrsia_topic = gossipsub::IdentTopic::new("pyrsia-topic");
+    let pyrsia_topic = pyrsia_topic(network_id);
     swarm.behaviour_mut().gossipsub.subscribe(&pyrsia_topic)?;
+    swarm
+        .behaviour_mut()
+        .gossipsub
+        .subscribe(&gossipsub::IdentTopic::new(MAINTENANCE_TOPIC))?;
+    let peer_maintenance = PeerMaintenance::default();
 
     Ok((
-        Client::new(command_sender, local_peer_id, pyrsia_topic),
+        Client::new(command_sender, local_peer_id, pyrsia_topic)
+            .with_peer_aliases(peer_aliases.clone())
+            .with_peer_versions(peer_versions.clone())
+            .with_peer_capabilities(peer_capabilities.clone())
+            .with_peer_maintenance(peer_maintenance.clone())
+            .with_query_metrics(query_metrics.clone()),
         local_keypair,
         ReceiverStream::new(event_receiver),
-        PyrsiaEventLoop::new(swarm, command_receiver, event_sender),
+        PyrsiaEventLoop::new(swarm, command_receiver, event_sender)
+            .with_peer_aliases(peer_aliases)
+            .with_peer_versions(peer_versions)
+            .with_peer_capabilities(peer_capabilities)
+            .with_peer_maintenance(peer_maintenance, control_message_signer)
+            .with_peer_store(peer_store)
+            .with_query_metrics(query_metrics)
+            .with_network_id(network_id),
     ))
 }
 
+/// The protocol version that nodes of the network with `network_id`
+/// advertise with the Identify protocol. Nodes of the default network keep
+/// the protocol version of the nodes that predate network ids.
+pub fn identify_protocol_version(network_id: &str) -> String {
+    if network_id == DEFAULT_NETWORK_ID {
+        "ipfs/1.0.0".to_owned()
+    } else {
+        format!("pyrsia/{}/1.0.0", network_id)
+    }
+}
+
+// The gossipsub topic that the blocks of the network are broadcast on.
+fn pyrsia_topic(network_id: &str) -> gossipsub::IdentTopic {
+    if network_id == DEFAULT_NETWORK_ID {
+        gossipsub::IdentTopic::new("pyrsia-topic")
+    } else {
+        gossipsub::IdentTopic::new(format!("pyrsia-topic/{}", network_id))
+    }
+}
+
 // create the libp2p transport for the swarm
 fn create_transport(
     keypair: identity::Keypair,
@@ -164,11 +252,15 @@ fn create_transport(
 // create the libp2p swarm
 fn create_swarm(
     keypair: identity::Keypair,
+    control_message_signer: ControlMessageSigner,
     max_provided_keys: usize,
+    protocol_version: String,
+    agent_version: String,
 ) -> Result<(Swarm<PyrsiaNetworkBehaviour>, core::PeerId), Box<dyn Error>> {
     let peer_id = keypair.public().to_peer_id();
 
-    let identify_config = identify::Config::new("ipfs/1.0.0".to_owned(), keypair.public());
+    let identify_config =
+        identify::Config::new(protocol_version, keypair.public()).with_agent_version(agent_version);
 
     let memory_store_config = MemoryStoreConfig {
         max_provided_keys,
@@ -186,6 +278,16 @@ fn create_swarm(
         }) // content-address messages. No two messages of the same content will be propagated.
         .build()?;
 
+    // large blobs take longer to transfer than the default request timeout,
+    // shorter timeouts that fit the size of the transfer and the throughput
+    // of the peer are applied by the artifact service
+    let mut artifact_request_response_config = RequestResponseConfig::default();
+    artifact_request_response_config.set_request_timeout(MAX_TRANSFER_TIMEOUT);
+
+    // a peer answers a replica request only after it fetched the artifact
+    let mut seed_sync_request_response_config = RequestResponseConfig::default();
+    seed_sync_request_response_config.set_request_timeout(MAX_TRANSFER_TIMEOUT);
+
     Ok((
         SwarmBuilder::with_tokio_executor(
             create_transport(keypair.clone())?,
@@ -212,10 +314,10 @@ fn create_swarm(
                 request_response: RequestResponse::new(
                     ArtifactExchangeCodec(),
                     iter::once((ArtifactExchangeProtocol(), ProtocolSupport::Full)),
-                    Default::default(),
+                    artifact_request_response_config,
                 ),
                 build_request_response: RequestResponse::new(
-                    BuildExchangeCodec(),
+                    BuildExchangeCodec(control_message_signer.clone()),
                     iter::once((BuildExchangeProtocol(), ProtocolSupport::Full)),
                     Default::default(),
                 ),
@@ -230,10 +332,20 @@ fn create_swarm(
                     Default::default(),
                 ),
                 build_status_request_response: RequestResponse::new(
-                    BuildStatusExchangeCodec(),
+                    BuildStatusExchangeCodec(control_message_signer),
                     iter::once((BuildStatusExchangeProtocol(), ProtocolSupport::Full)),
                     Default::default(),
                 ),
+                peer_exchange_request_response: RequestResponse::new(
+                    PeerExchangeCodec(),
+                    iter::once((PeerExchangeProtocol(), ProtocolSupport::Full)),
+                    Default::default(),
+                ),
+                seed_sync_request_response: RequestResponse::new(
+                    SeedSyncCodec(),
+                    iter::once((SeedSyncProtocol(), ProtocolSupport::Full)),
+                    seed_sync_request_response_config,
+                ),
             },
             peer_id,
         )
@@ -0,0 +1,226 @@
+/*
+   Copyright 2021 JFrog Ltd
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+*/
+
+use crate::artifact_service::model::PackageType;
+use libp2p::PeerId;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+
+const PACKAGE_TYPES_TOKEN_PREFIX: &str = "package-types/";
+const ROLES_TOKEN_PREFIX: &str = "roles/";
+
+/// The roles that a node can take on in the network.
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Deserialize,
+    Eq,
+    Hash,
+    Ord,
+    PartialEq,
+    PartialOrd,
+    Serialize,
+    strum_macros::Display,
+    strum_macros::EnumString,
+)]
+#[serde(rename_all = "kebab-case")]
+#[strum(serialize_all = "kebab-case")]
+pub enum NodeRole {
+    /// accepts build requests for the package types it supports
+    Builder,
+    /// only follows and audits the blockchain and the transparency log
+    Monitor,
+}
+
+/// The package types that a node serves and the roles it takes on, as
+/// advertised to its peers.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct NodeCapabilities {
+    pub package_types: BTreeSet<PackageType>,
+    pub roles: BTreeSet<NodeRole>,
+}
+
+impl Default for NodeCapabilities {
+    /// A node that serves and builds every package type.
+    fn default() -> Self {
+        NodeCapabilities {
+            package_types: [PackageType::Docker, PackageType::Maven2].into(),
+            roles: [NodeRole::Builder].into(),
+        }
+    }
+}
+
+impl NodeCapabilities {
+    /// Whether this node builds packages of `package_type`.
+    pub fn builds(&self, package_type: PackageType) -> bool {
+        self.roles.contains(&NodeRole::Builder) && self.package_types.contains(&package_type)
+    }
+
+    /// The tokens that advertise these capabilities in the agent version of
+    /// the identify protocol, e.g. `package-types/docker,maven2 roles/builder`.
+    pub fn agent_version_tokens(&self) -> String {
+        format!(
+            "{}{} {}{}",
+            PACKAGE_TYPES_TOKEN_PREFIX,
+            join(self.package_types.iter().map(package_type_token)),
+            ROLES_TOKEN_PREFIX,
+            join(self.roles.iter().map(NodeRole::to_string)),
+        )
+    }
+
+    /// Parse the capabilities from an agent version. Returns `None` when
+    /// the agent version does not advertise capabilities, like that of a
+    /// node running an older version. Unknown package types and roles are
+    /// ignored.
+    pub fn from_agent_version(agent_version: &str) -> Option<Self> {
+        let mut package_types = None;
+        let mut roles = BTreeSet::new();
+        for token in agent_version.split_whitespace() {
+            if let Some(values) = token.strip_prefix(PACKAGE_TYPES_TOKEN_PREFIX) {
+                package_types = Some(split(values).filter_map(parse_package_type).collect());
+            } else if let Some(values) = token.strip_prefix(ROLES_TOKEN_PREFIX) {
+                roles = split(values)
+                    .filter_map(|role| NodeRole::from_str(role).ok())
+                    .collect();
+            }
+        }
+
+        Some(NodeCapabilities {
+            package_types: package_types?,
+            roles,
+        })
+    }
+}
+
+/// A registry of the capabilities of peers. Peers advertise their
+/// capabilities in the agent version of the identify protocol, so that
+/// operations on a package type are only routed to peers that support it.
+#[derive(Clone, Debug, Default)]
+pub struct PeerCapabilities {
+    own_capabilities: NodeCapabilities,
+    capabilities: Arc<Mutex<HashMap<PeerId, NodeCapabilities>>>,
+}
+
+impl PeerCapabilities {
+    /// The capabilities that this node advertises to its peers.
+    pub fn with_own_capabilities(mut self, own_capabilities: NodeCapabilities) -> Self {
+        self.own_capabilities = own_capabilities;
+        self
+    }
+
+    pub fn own_capabilities(&self) -> NodeCapabilities {
+        self.own_capabilities.clone()
+    }
+
+    /// Record the capabilities that `peer_id` advertised in its agent
+    /// version, if any.
+    pub fn record_agent_version(&self, peer_id: &PeerId, agent_version: &str) {
+        let mut capabilities = self.capabilities.lock().unwrap();
+        match NodeCapabilities::from_agent_version(agent_version) {
+            Some(advertised) => capabilities.insert(*peer_id, advertised),
+            None => capabilities.remove(peer_id),
+        };
+    }
+
+    /// The capabilities that `peer_id` advertised, if they are known.
+    pub fn get(&self, peer_id: &PeerId) -> Option<NodeCapabilities> {
+        self.capabilities.lock().unwrap().get(peer_id).cloned()
+    }
+
+    /// Whether `peer_id` builds packages of `package_type`. Peers that did
+    /// not advertise their capabilities are assumed to build every package
+    /// type.
+    pub fn builds(&self, peer_id: &PeerId, package_type: PackageType) -> bool {
+        self.get(peer_id)
+            .map_or(true, |capabilities| capabilities.builds(package_type))
+    }
+}
+
+fn package_type_token(package_type: &PackageType) -> String {
+    package_type.to_string().to_lowercase()
+}
+
+fn parse_package_type(token: &str) -> Option<PackageType> {
+    [PackageType::Docker, PackageType::Maven2]
+        .into_iter()
+        .find(|package_type| package_type_token(package_type) == token)
+}
+
+fn split(values: &str) -> impl Iterator<Item = &str> {
+    values.split(',').filter(|value| !value.is_empty())
+}
+
+fn join(values: impl Iterator<Item = String>) -> String {
+    values.collect::<Vec<_>>().join(",")
+}
+
+#[cfg(test)]
+#[cfg(not(tarpaulin_include))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_agent_version_tokens() {
+        let capabilities = NodeCapabilities::default();
+        assert_eq!(
+            capabilities.agent_version_tokens(),
+            "package-types/docker,maven2 roles/builder"
+        );
+
+        let monitor = NodeCapabilities {
+            package_types: BTreeSet::new(),
+            roles: [NodeRole::Monitor].into(),
+        };
+        assert_eq!(
+            NodeCapabilities::from_agent_version(&format!(
+                "pyrsia/0.2.5 alias/node-a {}",
+                monitor.agent_version_tokens()
+            )),
+            Some(monitor)
+        );
+        assert_eq!(NodeCapabilities::from_agent_version("pyrsia/0.2.5"), None);
+        assert_eq!(
+            NodeCapabilities::from_agent_version("package-types/maven2,npm roles/builder,pinner"),
+            Some(NodeCapabilities {
+                package_types: [PackageType::Maven2].into(),
+                roles: [NodeRole::Builder].into(),
+            })
+        );
+    }
+
+    #[test]
+    fn test_record_agent_version() {
+        let peer_capabilities = PeerCapabilities::default();
+        let peer_id = PeerId::random();
+        assert!(peer_capabilities.builds(&peer_id, PackageType::Docker));
+
+        peer_capabilities
+            .record_agent_version(&peer_id, "pyrsia/0.2.5 package-types/maven2 roles/builder");
+        assert!(peer_capabilities.builds(&peer_id, PackageType::Maven2));
+        assert!(!peer_capabilities.builds(&peer_id, PackageType::Docker));
+
+        peer_capabilities
+            .record_agent_version(&peer_id, "pyrsia/0.2.5 package-types/maven2 roles/");
+        assert!(!peer_capabilities.builds(&peer_id, PackageType::Maven2));
+
+        peer_capabilities.record_agent_version(&peer_id, "pyrsia/0.2.4");
+        assert_eq!(peer_capabilities.get(&peer_id), None);
+        assert!(peer_capabilities.builds(&peer_id, PackageType::Docker));
+    }
+}
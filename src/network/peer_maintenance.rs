@@ -0,0 +1,171 @@
+/*
+   Copyright 2021 JFrog Ltd
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+*/
+
+use crate::util::time_util::now;
+use libp2p::PeerId;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// The gossipsub topic on which nodes announce their maintenance.
+pub const MAINTENANCE_TOPIC: &str = "pyrsia-maintenance-topic";
+
+/// The protocol of the signed control messages that carry maintenance
+/// announcements.
+pub const MAINTENANCE_PROTOCOL: &str = "/pyrsia/maintenance/1";
+
+/// The longest maintenance that a node can announce, so that a forgotten
+/// announcement does not keep a node out of the build rotation for good.
+pub const MAX_MAINTENANCE_TTL: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// Announces that a node is about to be taken down. Until the announcement
+/// expires the node is draining: it is skipped for new builds, while the
+/// transfers from it that are in progress complete.
+#[derive(Clone, Debug, Deserialize, Serialize, Eq, PartialEq)]
+pub struct MaintenanceAnnouncement {
+    /// The time the maintenance ends, in seconds since the unix epoch. An
+    /// announcement that already expired ends the maintenance early.
+    pub until: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+impl MaintenanceAnnouncement {
+    /// A maintenance that ends after `ttl`, at most [`MAX_MAINTENANCE_TTL`].
+    pub fn new(ttl: Duration, reason: Option<String>) -> Self {
+        MaintenanceAnnouncement {
+            until: now() + ttl.min(MAX_MAINTENANCE_TTL).as_secs(),
+            reason,
+        }
+    }
+
+    pub fn is_active(&self, now: u64) -> bool {
+        now < self.until
+    }
+}
+
+/// A node that announced maintenance, as listed by the node API.
+#[derive(Clone, Debug, Deserialize, Serialize, Eq, PartialEq)]
+pub struct DrainingPeer {
+    pub peer_id: String,
+    #[serde(flatten)]
+    pub announcement: MaintenanceAnnouncement,
+}
+
+/// A registry of the nodes that are draining for maintenance, this node
+/// included. Announcements are received on the [`MAINTENANCE_TOPIC`], so
+/// that new builds are only routed to nodes that stay up.
+#[derive(Clone, Debug, Default)]
+pub struct PeerMaintenance {
+    announcements: Arc<Mutex<HashMap<PeerId, MaintenanceAnnouncement>>>,
+}
+
+impl PeerMaintenance {
+    /// Record the latest announcement of `peer_id`. An expired announcement
+    /// ends its maintenance, and one that lasts longer than
+    /// [`MAX_MAINTENANCE_TTL`] is cut short.
+    pub fn record(&self, peer_id: &PeerId, mut announcement: MaintenanceAnnouncement) {
+        let now = now();
+        let mut announcements = self.announcements.lock().unwrap();
+        if announcement.is_active(now) {
+            announcement.until = announcement.until.min(now + MAX_MAINTENANCE_TTL.as_secs());
+            announcements.insert(*peer_id, announcement);
+        } else {
+            announcements.remove(peer_id);
+        }
+    }
+
+    /// Whether `peer_id` is draining for maintenance.
+    pub fn is_draining(&self, peer_id: &PeerId) -> bool {
+        self.announcements
+            .lock()
+            .unwrap()
+            .get(peer_id)
+            .map_or(false, |announcement| announcement.is_active(now()))
+    }
+
+    /// The nodes that are draining, the first to come back first.
+    pub fn draining_peers(&self) -> Vec<DrainingPeer> {
+        let now = now();
+        let mut announcements = self.announcements.lock().unwrap();
+        announcements.retain(|_, announcement| announcement.is_active(now));
+
+        let mut draining_peers: Vec<DrainingPeer> = announcements
+            .iter()
+            .map(|(peer_id, announcement)| DrainingPeer {
+                peer_id: peer_id.to_string(),
+                announcement: announcement.clone(),
+            })
+            .collect();
+        draining_peers.sort_by_key(|draining_peer| draining_peer.announcement.until);
+        draining_peers
+    }
+}
+
+#[cfg(test)]
+#[cfg(not(tarpaulin_include))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_announcements() {
+        let peer_maintenance = PeerMaintenance::default();
+        let draining_peer_id = PeerId::random();
+        let other_peer_id = PeerId::random();
+
+        peer_maintenance.record(
+            &draining_peer_id,
+            MaintenanceAnnouncement::new(Duration::from_secs(3600), Some("upgrade".to_owned())),
+        );
+        assert!(peer_maintenance.is_draining(&draining_peer_id));
+        assert!(!peer_maintenance.is_draining(&other_peer_id));
+
+        let draining_peers = peer_maintenance.draining_peers();
+        assert_eq!(draining_peers.len(), 1);
+        assert_eq!(draining_peers[0].peer_id, draining_peer_id.to_string());
+        assert_eq!(
+            draining_peers[0].announcement.reason.as_deref(),
+            Some("upgrade")
+        );
+
+        // an expired announcement ends the maintenance
+        peer_maintenance.record(
+            &draining_peer_id,
+            MaintenanceAnnouncement::new(Duration::ZERO, None),
+        );
+        assert!(!peer_maintenance.is_draining(&draining_peer_id));
+        assert!(peer_maintenance.draining_peers().is_empty());
+    }
+
+    #[test]
+    fn test_maintenance_is_time_limited() {
+        let peer_maintenance = PeerMaintenance::default();
+        let peer_id = PeerId::random();
+
+        peer_maintenance.record(
+            &peer_id,
+            MaintenanceAnnouncement {
+                until: u64::MAX,
+                reason: None,
+            },
+        );
+        assert!(
+            peer_maintenance.draining_peers()[0].announcement.until
+                <= now() + MAX_MAINTENANCE_TTL.as_secs()
+        );
+    }
+}
@@ -0,0 +1,245 @@
+/*
+   Copyright 2021 JFrog Ltd
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+*/
+
+use crate::network::peer_exchange_protocol::PeerAddresses;
+use crate::util::time_util::now;
+use libp2p::{Multiaddr, PeerId};
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use thiserror::Error;
+
+/// How often the event loop saves the peers of the routing table.
+pub const PEER_STORE_SAVE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Peers that were not in the routing table for this long are forgotten.
+const MAX_PEER_AGE: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+#[derive(Debug, Error)]
+pub enum PeerStoreError {
+    #[error("Failure while accessing the peer store: {0}")]
+    StorageFailure(#[from] io::Error),
+    #[error("Failure while (de)serializing the peer store: {0}")]
+    SerializationFailure(#[from] serde_json::Error),
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+struct StoredPeer {
+    addresses: Vec<String>,
+    /// The last time the peer was in the routing table, in seconds since
+    /// the epoch.
+    last_seen: u64,
+}
+
+#[derive(Debug, Default)]
+struct KnownPeer {
+    addresses: Vec<Multiaddr>,
+    last_seen: u64,
+}
+
+/// The peers of the routing table and their addresses, persisted across
+/// restarts of the node, so that the node does not have to rebuild its
+/// routing table from the bootstrap nodes only.
+#[derive(Clone, Debug, Default)]
+pub struct PeerStore {
+    path: Option<PathBuf>,
+    peers: Arc<Mutex<BTreeMap<PeerId, KnownPeer>>>,
+}
+
+impl PeerStore {
+    /// Create a peer store that persists the peers in the file at `path`,
+    /// starting with the peers that were persisted before.
+    pub fn new<P: AsRef<Path>>(path: P) -> Self {
+        let path = path.as_ref().to_path_buf();
+        let peers = load(&path, now()).unwrap_or_else(|e| {
+            warn!("Failed to load the peer store from {:?}: {}", path, e);
+            Default::default()
+        });
+
+        PeerStore {
+            path: Some(path),
+            peers: Arc::new(Mutex::new(peers)),
+        }
+    }
+
+    /// The known peers and their addresses.
+    pub fn peers(&self) -> Vec<PeerAddresses> {
+        self.peers
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(peer_id, known_peer)| PeerAddresses {
+                peer_id: *peer_id,
+                addresses: known_peer.addresses.clone(),
+            })
+            .collect()
+    }
+
+    /// Record that `peers` are in the routing table with their addresses.
+    pub fn update(&self, peers: Vec<PeerAddresses>) {
+        let now = now();
+        let mut known_peers = self.peers.lock().unwrap();
+        for peer in peers.into_iter().filter(|peer| !peer.addresses.is_empty()) {
+            known_peers.insert(
+                peer.peer_id,
+                KnownPeer {
+                    addresses: peer.addresses,
+                    last_seen: now,
+                },
+            );
+        }
+    }
+
+    /// Forget a peer, e.g. because it could not be dialed.
+    pub fn remove(&self, peer_id: &PeerId) {
+        self.peers.lock().unwrap().remove(peer_id);
+    }
+
+    /// Persist the known peers, when the peer store has a path.
+    pub fn save(&self) -> Result<(), PeerStoreError> {
+        match &self.path {
+            Some(path) => save(path, &self.peers.lock().unwrap()),
+            None => Ok(()),
+        }
+    }
+}
+
+fn load(path: &Path, now: u64) -> Result<BTreeMap<PeerId, KnownPeer>, PeerStoreError> {
+    let stored: BTreeMap<String, StoredPeer> = match fs::read(path) {
+        Ok(content) => serde_json::from_slice(&content)?,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Default::default()),
+        Err(e) => return Err(e.into()),
+    };
+
+    Ok(stored
+        .into_iter()
+        .filter(|(_, stored_peer)| stored_peer.last_seen + MAX_PEER_AGE.as_secs() >= now)
+        .filter_map(|(peer_id, stored_peer)| {
+            let addresses: Vec<Multiaddr> = stored_peer
+                .addresses
+                .iter()
+                .filter_map(|address| Multiaddr::from_str(address).ok())
+                .collect();
+            if addresses.is_empty() {
+                return None;
+            }
+            Some((
+                PeerId::from_str(&peer_id).ok()?,
+                KnownPeer {
+                    addresses,
+                    last_seen: stored_peer.last_seen,
+                },
+            ))
+        })
+        .collect())
+}
+
+fn save(path: &Path, peers: &BTreeMap<PeerId, KnownPeer>) -> Result<(), PeerStoreError> {
+    let stored: BTreeMap<String, StoredPeer> = peers
+        .iter()
+        .map(|(peer_id, known_peer)| {
+            (
+                peer_id.to_string(),
+                StoredPeer {
+                    addresses: known_peer
+                        .addresses
+                        .iter()
+                        .map(|address| address.to_string())
+                        .collect(),
+                    last_seen: known_peer.last_seen,
+                },
+            )
+        })
+        .collect();
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let tmp_path = path.with_extension("tmp");
+    fs::write(&tmp_path, serde_json::to_vec(&stored)?)?;
+    fs::rename(&tmp_path, path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+#[cfg(not(tarpaulin_include))]
+mod tests {
+    use super::*;
+    use crate::util::test_util;
+
+    #[test]
+    fn test_peers_are_persisted() {
+        let tmp_dir = test_util::tests::setup();
+        let path = tmp_dir.join("peers.json");
+
+        let peer_store = PeerStore::new(&path);
+        assert!(peer_store.peers().is_empty());
+
+        let peer = PeerAddresses {
+            peer_id: PeerId::random(),
+            addresses: vec!["/ip4/10.0.0.1/tcp/44000".parse().unwrap()],
+        };
+        let removed_peer = PeerAddresses {
+            peer_id: PeerId::random(),
+            addresses: vec!["/ip4/10.0.0.2/tcp/44000".parse().unwrap()],
+        };
+        let peer_without_addresses = PeerAddresses {
+            peer_id: PeerId::random(),
+            addresses: vec![],
+        };
+        peer_store.update(vec![
+            peer.clone(),
+            removed_peer.clone(),
+            peer_without_addresses,
+        ]);
+        peer_store.remove(&removed_peer.peer_id);
+        peer_store.save().unwrap();
+
+        assert_eq!(PeerStore::new(&path).peers(), vec![peer]);
+
+        test_util::tests::teardown(tmp_dir);
+    }
+
+    #[test]
+    fn test_old_peers_are_forgotten() {
+        let tmp_dir = test_util::tests::setup();
+        let path = tmp_dir.join("peers.json");
+
+        let peer_id = PeerId::random();
+        let mut peers = BTreeMap::new();
+        peers.insert(
+            peer_id,
+            KnownPeer {
+                addresses: vec!["/ip4/10.0.0.1/tcp/44000".parse().unwrap()],
+                last_seen: 1000,
+            },
+        );
+        save(&path, &peers).unwrap();
+
+        assert_eq!(load(&path, 1000).unwrap().len(), 1);
+        assert!(load(&path, 1000 + MAX_PEER_AGE.as_secs() + 1)
+            .unwrap()
+            .is_empty());
+
+        test_util::tests::teardown(tmp_dir);
+    }
+}
@@ -0,0 +1,149 @@
+/*
+   Copyright 2021 JFrog Ltd
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+*/
+
+use crate::transparency_log::log::{LogSnapshot, TransparencyLogPage};
+use async_trait::async_trait;
+use futures::prelude::*;
+use libp2p::core::upgrade::{read_length_prefixed, write_length_prefixed, ProtocolName};
+use libp2p::request_response::RequestResponseCodec;
+use log::debug;
+use serde::{Deserialize, Serialize};
+use std::io;
+
+/// The maximum number of artifact ids that a seed node offers in one sync.
+pub const SEED_SYNC_MAX_ARTIFACTS: usize = 10_000;
+const SEED_SYNC_MAX_SIZE_PER_MESSAGE: usize = 16 * 1024 * 1024;
+
+#[derive(Debug, Clone)]
+pub struct SeedSyncProtocol();
+
+#[derive(Clone)]
+pub struct SeedSyncCodec();
+
+/// What a new node asks a seed node, or the peer it fast syncs from, for
+/// while it syncs from it, or what a node asks a peer for before it evicts
+/// the last copy of an artifact.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub enum SeedSyncRequest {
+    /// A page of the transparency logs of the seed node, in the order in
+    /// which they were written, starting at `offset`.
+    TransparencyLogs { offset: usize },
+    /// The ids of the popular and pinned artifacts that the seed node stores.
+    Artifacts,
+    /// A signed snapshot of the transparency logs of the seed node, whose
+    /// transparency logs are then requested page by page.
+    Snapshot,
+    /// Take a replica of the artifact with `artifact_id` from the requesting
+    /// node, which is about to evict the only copy on the p2p network. The
+    /// peer answers once it stores and provides a verified replica.
+    Replicate { artifact_id: String },
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub enum SeedSyncResponse {
+    TransparencyLogs(TransparencyLogPage),
+    Artifacts(Vec<String>),
+    Snapshot(LogSnapshot),
+    /// Whether the peer took a replica.
+    Replicated(bool),
+}
+
+impl ProtocolName for SeedSyncProtocol {
+    fn protocol_name(&self) -> &[u8] {
+        "/pyrsia-seed-sync/1".as_bytes()
+    }
+}
+
+#[async_trait]
+impl RequestResponseCodec for SeedSyncCodec {
+    type Protocol = SeedSyncProtocol;
+    type Request = SeedSyncRequest;
+    type Response = SeedSyncResponse;
+
+    ///This method reads the seed sync request from the peer.
+    async fn read_request<T>(
+        &mut self,
+        _: &SeedSyncProtocol,
+        io: &mut T,
+    ) -> io::Result<Self::Request>
+    where
+        T: AsyncRead + Unpin + Send,
+    {
+        let buffer = read_length_prefixed(io, SEED_SYNC_MAX_SIZE_PER_MESSAGE).await?;
+        let request: SeedSyncRequest = serde_json::from_slice(&buffer)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+        debug!("SeedSync::read_request received {:?}", request);
+
+        Ok(request)
+    }
+
+    ///This method reads the part of the seed snapshot from the seed node.
+    async fn read_response<T>(
+        &mut self,
+        _: &SeedSyncProtocol,
+        io: &mut T,
+    ) -> io::Result<Self::Response>
+    where
+        T: AsyncRead + Unpin + Send,
+    {
+        let buffer = read_length_prefixed(io, SEED_SYNC_MAX_SIZE_PER_MESSAGE).await?;
+        let response: SeedSyncResponse = serde_json::from_slice(&buffer)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+        debug!("SeedSync::read_response received");
+
+        Ok(response)
+    }
+
+    ///This method sends a seed sync request to the seed node.
+    async fn write_request<T>(
+        &mut self,
+        _: &SeedSyncProtocol,
+        io: &mut T,
+        request: SeedSyncRequest,
+    ) -> io::Result<()>
+    where
+        T: AsyncWrite + Unpin + Send,
+    {
+        debug!("SeedSync::write_request sent {:?}", request);
+
+        let data = serde_json::to_vec(&request)?;
+        write_length_prefixed(io, data).await?;
+        io.close().await?;
+
+        Ok(())
+    }
+
+    ///This method sends a part of the seed snapshot to the peer.
+    async fn write_response<T>(
+        &mut self,
+        _: &SeedSyncProtocol,
+        io: &mut T,
+        response: SeedSyncResponse,
+    ) -> io::Result<()>
+    where
+        T: AsyncWrite + Unpin + Send,
+    {
+        debug!("SeedSync::write_response sent");
+
+        let data = serde_json::to_vec(&response)?;
+        write_length_prefixed(io, data).await?;
+        io.close().await?;
+
+        Ok(())
+    }
+}
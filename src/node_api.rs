@@ -14,6 +14,8 @@
    limitations under the License.
 */
 
+#[cfg(feature = "web-ui")]
 pub mod handlers;
 pub mod model;
+#[cfg(feature = "web-ui")]
 pub mod routes;
@@ -14,4 +14,8 @@
    limitations under the License.
 */
 
+pub mod alerts;
+pub mod peers;
+pub mod secrets;
+pub mod subscriptions;
 pub mod swarm;
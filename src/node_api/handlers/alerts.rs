@@ -0,0 +1,29 @@
+/*
+   Copyright 2021 JFrog Ltd
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+*/
+
+use crate::alert_service::service::AlertService;
+use crate::docker::error_util::RegistryError;
+use warp::{http::StatusCode, Rejection, Reply};
+
+pub async fn handle_list_alerts(alert_service: AlertService) -> Result<impl Reply, Rejection> {
+    let alerts_as_json =
+        serde_json::to_string(&alert_service.recent_alerts()).map_err(RegistryError::from)?;
+
+    Ok(warp::http::response::Builder::new()
+        .header("Content-Type", "application/json")
+        .status(StatusCode::OK)
+        .body(alerts_as_json))
+}
@@ -0,0 +1,94 @@
+/*
+   Copyright 2021 JFrog Ltd
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+*/
+
+use crate::docker::error_util::{RegistryError, RegistryErrorCode};
+use crate::network::client::Client;
+use crate::network::peer_alias::PeerAliases;
+use crate::network::peer_maintenance::MaintenanceAnnouncement;
+use crate::node_api::model::request::{RequestAnnounceMaintenance, RequestSetPeerAlias};
+use libp2p::PeerId;
+use log::info;
+use std::str::FromStr;
+use std::time::Duration;
+use warp::{http::StatusCode, Rejection, Reply};
+
+pub async fn handle_list_peer_aliases(peer_aliases: PeerAliases) -> Result<impl Reply, Rejection> {
+    let peer_aliases_as_json =
+        serde_json::to_string(&peer_aliases.list()).map_err(RegistryError::from)?;
+
+    Ok(warp::http::response::Builder::new()
+        .header("Content-Type", "application/json")
+        .status(StatusCode::OK)
+        .body(peer_aliases_as_json))
+}
+
+pub async fn handle_set_peer_alias(
+    request_set_peer_alias: RequestSetPeerAlias,
+    peer_aliases: PeerAliases,
+) -> Result<impl Reply, Rejection> {
+    let peer_id = PeerId::from_str(&request_set_peer_alias.peer_id).map_err(|e| RegistryError {
+        code: RegistryErrorCode::BadRequest(e.to_string()),
+    })?;
+
+    peer_aliases
+        .set_local_alias(&peer_id, request_set_peer_alias.alias.as_deref())
+        .map_err(RegistryError::from)?;
+
+    match &request_set_peer_alias.alias {
+        Some(alias) => info!("Alias {} was assigned to peer {}", alias, peer_id),
+        None => info!("Alias of peer {} was removed", peer_id),
+    }
+
+    Ok(warp::http::response::Builder::new()
+        .status(StatusCode::NO_CONTENT)
+        .body(""))
+}
+
+pub async fn handle_list_draining_peers(p2p_client: Client) -> Result<impl Reply, Rejection> {
+    let draining_peers_as_json =
+        serde_json::to_string(&p2p_client.peer_maintenance.draining_peers())
+            .map_err(RegistryError::from)?;
+
+    Ok(warp::http::response::Builder::new()
+        .header("Content-Type", "application/json")
+        .status(StatusCode::OK)
+        .body(draining_peers_as_json))
+}
+
+pub async fn handle_announce_maintenance(
+    request_announce_maintenance: RequestAnnounceMaintenance,
+    mut p2p_client: Client,
+) -> Result<impl Reply, Rejection> {
+    let announcement = MaintenanceAnnouncement::new(
+        Duration::from_secs(request_announce_maintenance.ttl_secs),
+        request_announce_maintenance.reason,
+    );
+    let until = announcement.until;
+    p2p_client
+        .announce_maintenance(announcement)
+        .await
+        .map_err(RegistryError::from)?;
+
+    if request_announce_maintenance.ttl_secs == 0 {
+        info!("Maintenance of this node was ended");
+    } else {
+        info!("This node is draining for maintenance until {}", until);
+    }
+
+    Ok(warp::http::response::Builder::new()
+        .status(StatusCode::NO_CONTENT)
+        .body(""))
+}
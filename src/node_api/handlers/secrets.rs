@@ -0,0 +1,111 @@
+/*
+   Copyright 2021 JFrog Ltd
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+*/
+
+use crate::build_service::secrets::SecretStore;
+use crate::docker::error_util::{RegistryError, RegistryErrorCode};
+use crate::node_api::model::request::{RequestRemoveSecret, RequestSetSecret};
+use log::info;
+use warp::{http::StatusCode, Filter, Rejection, Reply};
+
+/// Only requests that carry the admin token of the node as bearer token
+/// may manage secrets or publish package metadata. When no admin token is
+/// configured, these operations are disabled.
+pub fn require_admin(
+    admin_token: Option<String>,
+) -> impl Filter<Extract = (), Error = Rejection> + Clone {
+    warp::header::optional::<String>("authorization")
+        .and_then(move |authorization: Option<String>| {
+            let admin_token = admin_token.clone();
+            async move {
+                match (admin_token, authorization) {
+                    (Some(admin_token), Some(authorization))
+                        if authorization == format!("Bearer {}", admin_token) =>
+                    {
+                        Ok(())
+                    }
+                    _ => Err(warp::reject::custom(RegistryError {
+                        code: RegistryErrorCode::Unauthorized(
+                            "This operation requires the admin token of the node".to_owned(),
+                        ),
+                    })),
+                }
+            }
+        })
+        .untuple_one()
+}
+
+pub async fn handle_set_secret(
+    request_set_secret: RequestSetSecret,
+    secret_store: SecretStore,
+) -> Result<impl Reply, Rejection> {
+    secret_store
+        .set(
+            &request_set_secret.namespace,
+            &request_set_secret.name,
+            request_set_secret.value,
+        )
+        .map_err(RegistryError::from)?;
+
+    info!(
+        "Secret {} was set for namespace {}",
+        request_set_secret.name, request_set_secret.namespace
+    );
+
+    Ok(warp::http::response::Builder::new()
+        .status(StatusCode::CREATED)
+        .body(""))
+}
+
+pub async fn handle_remove_secret(
+    request_remove_secret: RequestRemoveSecret,
+    secret_store: SecretStore,
+) -> Result<impl Reply, Rejection> {
+    let removed = secret_store
+        .remove(
+            &request_remove_secret.namespace,
+            &request_remove_secret.name,
+        )
+        .map_err(RegistryError::from)?;
+
+    if !removed {
+        return Err(warp::reject::custom(RegistryError {
+            code: RegistryErrorCode::BadRequest(format!(
+                "No secret {} found for namespace {}",
+                request_remove_secret.name, request_remove_secret.namespace
+            )),
+        }));
+    }
+
+    info!(
+        "Secret {} was removed from namespace {}",
+        request_remove_secret.name, request_remove_secret.namespace
+    );
+
+    Ok(warp::http::response::Builder::new()
+        .status(StatusCode::OK)
+        .body(""))
+}
+
+pub async fn handle_list_secrets(secret_store: SecretStore) -> Result<impl Reply, Rejection> {
+    let secrets = secret_store.list().map_err(RegistryError::from)?;
+
+    let secrets_as_json = serde_json::to_string(&secrets).map_err(RegistryError::from)?;
+
+    Ok(warp::http::response::Builder::new()
+        .header("Content-Type", "application/json")
+        .status(StatusCode::OK)
+        .body(secrets_as_json))
+}
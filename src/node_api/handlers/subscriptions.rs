@@ -0,0 +1,143 @@
+/*
+   Copyright 2021 JFrog Ltd
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+*/
+
+use crate::docker::error_util::{RegistryError, RegistryErrorCode};
+use crate::node_api::model::request::{RequestSubscribe, RequestUnsubscribe};
+use crate::subscription_service::service::{NotificationReceiver, SubscriptionService};
+use futures::{SinkExt, StreamExt};
+use log::{debug, info};
+use warp::ws::{Message, WebSocket, Ws};
+use warp::{http::StatusCode, Filter, Rejection, Reply};
+
+/// Subscriptions are owned by the API key that is passed as bearer token.
+pub fn api_key() -> impl Filter<Extract = (String,), Error = Rejection> + Clone {
+    warp::header::optional::<String>("authorization").and_then(
+        |authorization: Option<String>| async move {
+            match authorization
+                .as_deref()
+                .and_then(|authorization| authorization.strip_prefix("Bearer "))
+            {
+                Some(api_key) if !api_key.trim().is_empty() => Ok(api_key.to_owned()),
+                _ => Err(warp::reject::custom(RegistryError {
+                    code: RegistryErrorCode::Unauthorized(
+                        "Subscriptions require an API key as bearer token".to_owned(),
+                    ),
+                })),
+            }
+        },
+    )
+}
+
+pub async fn handle_subscribe(
+    api_key: String,
+    request_subscribe: RequestSubscribe,
+    subscription_service: SubscriptionService,
+) -> Result<impl Reply, Rejection> {
+    let subscription = subscription_service
+        .subscribe(
+            &api_key,
+            request_subscribe.package_type,
+            &request_subscribe.pattern,
+            request_subscribe.webhook_url,
+        )
+        .map_err(RegistryError::from)?;
+
+    info!(
+        "Subscription {} was added for pattern {}",
+        subscription.id, subscription.pattern
+    );
+
+    let subscription_as_json = serde_json::to_string(&subscription).map_err(RegistryError::from)?;
+
+    Ok(warp::http::response::Builder::new()
+        .header("Content-Type", "application/json")
+        .status(StatusCode::CREATED)
+        .body(subscription_as_json))
+}
+
+pub async fn handle_unsubscribe(
+    api_key: String,
+    request_unsubscribe: RequestUnsubscribe,
+    subscription_service: SubscriptionService,
+) -> Result<impl Reply, Rejection> {
+    subscription_service
+        .unsubscribe(&api_key, &request_unsubscribe.subscription_id)
+        .map_err(RegistryError::from)?;
+
+    info!(
+        "Subscription {} was removed",
+        request_unsubscribe.subscription_id
+    );
+
+    Ok(warp::http::response::Builder::new()
+        .status(StatusCode::OK)
+        .body(""))
+}
+
+pub async fn handle_list_subscriptions(
+    api_key: String,
+    subscription_service: SubscriptionService,
+) -> Result<impl Reply, Rejection> {
+    let subscriptions = subscription_service
+        .list(&api_key)
+        .map_err(RegistryError::from)?;
+
+    let subscriptions_as_json =
+        serde_json::to_string(&subscriptions).map_err(RegistryError::from)?;
+
+    Ok(warp::http::response::Builder::new()
+        .header("Content-Type", "application/json")
+        .status(StatusCode::OK)
+        .body(subscriptions_as_json))
+}
+
+/// Upgrade the connection to a WebSocket that streams the notifications of
+/// the subscriptions of the API key as JSON text messages.
+pub async fn handle_subscription_events(
+    api_key: String,
+    ws: Ws,
+    subscription_service: SubscriptionService,
+) -> Result<impl Reply, Rejection> {
+    let notifications = subscription_service.notifications(&api_key);
+    Ok(ws.on_upgrade(move |websocket| stream_notifications(websocket, notifications)))
+}
+
+async fn stream_notifications(websocket: WebSocket, mut notifications: NotificationReceiver) {
+    let (mut sender, mut receiver) = websocket.split();
+    loop {
+        tokio::select! {
+            notification = notifications.recv() => {
+                let Some(notification) = notification else {
+                    break;
+                };
+                let Ok(notification_as_json) = serde_json::to_string(&notification) else {
+                    continue;
+                };
+                if sender.send(Message::text(notification_as_json)).await.is_err() {
+                    break;
+                }
+            }
+            message = receiver.next() => {
+                // the client only closes the stream, other messages are ignored
+                match message {
+                    Some(Ok(message)) if !message.is_close() => continue,
+                    _ => break,
+                }
+            }
+        }
+    }
+    debug!("Subscription event stream closed");
+}
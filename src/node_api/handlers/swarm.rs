@@ -14,18 +14,33 @@
    limitations under the License.
 */
 
-use crate::artifact_service::model::PackageType;
-use crate::docker::error_util::{RegistryError, RegistryErrorCode};
+use crate::accounting_service::service::UsageAccounting;
+use crate::artifact_service::availability::DEFAULT_REPORT_WINDOWS;
+use crate::artifact_service::coordinates::{normalize_package_name, normalize_package_specific_id};
+use crate::artifact_service::model::{ArtifactQuery, PackageType};
+use crate::docker::error_util::{
+    warning_header_value, RegistryError, RegistryErrorCode, BUILD_ID_HEADER,
+};
 use crate::network::client::Client;
 use crate::node_api::model::request::*;
-use crate::transparency_log::log::TransparencyLog;
+use crate::transparency_log::log::{
+    LogFormat, TransparencyLog, TransparencyLogError, TransparencyLogQuery,
+    TransparencyLogWatchQuery,
+};
+use crate::transparency_log::node_quorum::NodeChangeProposal;
 use std::future::Future;
 
 use crate::artifact_service::service::ArtifactService;
+use crate::blockchain_service::explorer::{
+    BlockId, BlockListQuery, BlockView, DEFAULT_BLOCK_LIST_LIMIT,
+};
 use crate::build_service::error::BuildError;
+use crate::build_service::history::BuildHistoryQuery;
 use crate::node_api::model::response::BuildSuccessResponse;
-use libp2p::PeerId;
+use base64::{engine::general_purpose, Engine as _};
+use libp2p::{Multiaddr, PeerId};
 use log::debug;
+use percent_encoding::percent_decode_str;
 use serde::ser::SerializeStruct;
 use serde::{Serialize, Serializer};
 use std::str::FromStr;
@@ -88,6 +103,15 @@ impl Serialize for OutputTransparencyLog<'_> {
                 TransparencyLogField::NodePublicKey => {
                     s.serialize_field("node_public_key", &self.origin.node_public_key)?
                 }
+                TransparencyLogField::Successor => {
+                    s.serialize_field("successor", &self.origin.successor)?
+                }
+                TransparencyLogField::Provenance => {
+                    s.serialize_field("provenance", &self.origin.provenance())?
+                }
+                TransparencyLogField::PinningSnapshot => {
+                    s.serialize_field("pinning_snapshot", &self.origin.pinning_snapshot)?
+                }
             };
         }
 
@@ -107,7 +131,7 @@ impl ResponseBuilder {
         }
     }
 
-    fn wrap<'a>(&'a self, logs: &'a [TransparencyLog]) -> Vec<OutputTransparencyLog> {
+    fn wrap<'a>(&'a self, logs: &'a [TransparencyLog]) -> Vec<OutputTransparencyLog<'a>> {
         logs.iter()
             .map(|l| OutputTransparencyLog {
                 output_fields: &self.output_fields,
@@ -120,12 +144,20 @@ impl ResponseBuilder {
         let wrapped_logs = self.wrap(logs);
         let body = self.format.as_string(&wrapped_logs)?;
 
-        Ok(warp::http::response::Builder::new()
+        let mut response_builder = warp::http::response::Builder::new()
             .status(StatusCode::OK)
             .header("Content-Type", self.format.response_content_type())
-            .header("Content-Length", body.as_bytes().len())
-            .body(body)
-            .map_err(RegistryError::from)?)
+            .header("Content-Length", body.len());
+        if let Some(deprecation_warning) = logs
+            .iter()
+            .rev()
+            .find_map(TransparencyLog::deprecation_warning)
+        {
+            response_builder =
+                response_builder.header("Warning", warning_header_value(&deprecation_warning));
+        }
+
+        Ok(response_builder.body(body).map_err(RegistryError::from)?)
     }
 }
 
@@ -133,23 +165,70 @@ pub async fn handle_add_authorized_node(
     request_add_authorized_node: RequestAddAuthorizedNode,
     artifact_service: ArtifactService,
 ) -> Result<impl Reply, Rejection> {
-    let peer_id =
-        PeerId::from_str(&request_add_authorized_node.peer_id).map_err(|_| RegistryError {
-            code: RegistryErrorCode::BadRequest(format!(
-                "PeerId has invalid format: {}",
-                request_add_authorized_node.peer_id
-            )),
-        })?;
+    let peer_id = parse_node_peer_id(&request_add_authorized_node.peer_id)?;
 
-    artifact_service
+    let proposal = artifact_service
         .transparency_log_service
         .add_authorized_node(peer_id)
         .await
         .map_err(RegistryError::from)?;
 
+    node_change_response(&proposal)
+}
+
+pub async fn handle_remove_authorized_node(
+    request_remove_authorized_node: RequestRemoveAuthorizedNode,
+    artifact_service: ArtifactService,
+) -> Result<impl Reply, Rejection> {
+    let peer_id = parse_node_peer_id(&request_remove_authorized_node.peer_id)?;
+
+    let proposal = artifact_service
+        .transparency_log_service
+        .remove_authorized_node(peer_id)
+        .await
+        .map_err(RegistryError::from)?;
+
+    node_change_response(&proposal)
+}
+
+pub async fn handle_list_node_change_proposals(
+    artifact_service: ArtifactService,
+) -> Result<impl Reply, Rejection> {
+    let proposals = artifact_service
+        .transparency_log_service
+        .list_node_change_proposals()
+        .map_err(RegistryError::from)?;
+    let proposals_as_json = serde_json::to_string(&proposals).map_err(RegistryError::from)?;
+
     Ok(warp::http::response::Builder::new()
-        .status(StatusCode::CREATED)
-        .body(""))
+        .header("Content-Type", "application/json")
+        .status(StatusCode::OK)
+        .body(proposals_as_json))
+}
+
+fn parse_node_peer_id(peer_id: &str) -> Result<PeerId, Rejection> {
+    PeerId::from_str(peer_id).map_err(|_| {
+        warp::reject::custom(RegistryError {
+            code: RegistryErrorCode::BadRequest(format!("PeerId has invalid format: {}", peer_id)),
+        })
+    })
+}
+
+// A change to the authorized nodes that was applied is created, one that
+// waits for the approval of more authorized nodes is accepted, with the
+// approvals so far in the body.
+fn node_change_response(proposal: &NodeChangeProposal) -> Result<impl Reply, Rejection> {
+    if proposal.is_approved() {
+        return Ok(warp::http::response::Builder::new()
+            .status(StatusCode::CREATED)
+            .body(String::new()));
+    }
+
+    let proposal_as_json = serde_json::to_string(proposal).map_err(RegistryError::from)?;
+    Ok(warp::http::response::Builder::new()
+        .header("Content-Type", "application/json")
+        .status(StatusCode::ACCEPTED)
+        .body(proposal_as_json))
 }
 
 /// Special handle for Artifact Already Exist before responding to build request result
@@ -177,6 +256,18 @@ where
     }
 }
 
+// the id of a started build is also returned in a header, so that the build
+// minutes can be accounted to the client that requested it
+fn build_response_builder(build_response: &BuildSuccessResponse) -> warp::http::response::Builder {
+    let builder = warp::http::response::Builder::new()
+        .header("Content-Type", "application/json")
+        .status(build_response.success_status_code);
+    match &build_response.build_id {
+        Some(build_id) => builder.header(BUILD_ID_HEADER, build_id),
+        None => builder,
+    }
+}
+
 pub async fn handle_build_docker(
     request_docker_build: RequestDockerBuild,
     artifact_service: ArtifactService,
@@ -193,10 +284,7 @@ pub async fn handle_build_docker(
 
     let build_id_as_json = serde_json::to_string(&build_id).map_err(RegistryError::from)?;
 
-    Ok(warp::http::response::Builder::new()
-        .header("Content-Type", "application/json")
-        .status(build_id.success_status_code)
-        .body(build_id_as_json))
+    Ok(build_response_builder(&build_id).body(build_id_as_json))
 }
 
 pub async fn handle_build_maven(
@@ -213,10 +301,7 @@ pub async fn handle_build_maven(
 
     let build_id_as_json = serde_json::to_string(&build_id).map_err(RegistryError::from)?;
 
-    Ok(warp::http::response::Builder::new()
-        .header("Content-Type", "application/json")
-        .status(build_id.success_status_code)
-        .body(build_id_as_json))
+    Ok(build_response_builder(&build_id).body(build_id_as_json))
 }
 
 pub async fn handle_build_status(
@@ -238,6 +323,75 @@ pub async fn handle_build_status(
         .body(build_status))
 }
 
+pub async fn handle_build_history(
+    query: BuildHistoryQuery,
+    artifact_service: ArtifactService,
+) -> Result<impl Reply, Rejection> {
+    let build_records = artifact_service
+        .get_build_history(query)
+        .await
+        .map_err(RegistryError::from)?;
+
+    let build_records_as_json =
+        serde_json::to_string(&build_records).map_err(RegistryError::from)?;
+
+    Ok(warp::http::response::Builder::new()
+        .header("Content-Type", "application/json")
+        .status(StatusCode::OK)
+        .body(build_records_as_json))
+}
+
+pub async fn handle_build_rerun(
+    request_build_rerun: RequestBuildRerun,
+    artifact_service: ArtifactService,
+) -> Result<impl Reply, Rejection> {
+    let build_id = artifact_service
+        .rerun_build(&request_build_rerun.build_id)
+        .await
+        .map_err(RegistryError::from)?;
+
+    let build_id_as_json = serde_json::to_string(&build_id).map_err(RegistryError::from)?;
+
+    Ok(warp::http::response::Builder::new()
+        .header("Content-Type", "application/json")
+        .status(StatusCode::OK)
+        .body(build_id_as_json))
+}
+
+pub async fn handle_build_reproduce(
+    request_build_reproduce: RequestBuildReproduce,
+    artifact_service: ArtifactService,
+) -> Result<impl Reply, Rejection> {
+    let build_id = artifact_service
+        .reproduce_build(&request_build_reproduce.pinning_snapshot)
+        .await
+        .map_err(RegistryError::from)?;
+
+    let build_id_as_json = serde_json::to_string(&build_id).map_err(RegistryError::from)?;
+
+    Ok(warp::http::response::Builder::new()
+        .header("Content-Type", "application/json")
+        .status(StatusCode::OK)
+        .body(build_id_as_json))
+}
+
+pub async fn handle_build_replay_failed(
+    artifact_service: ArtifactService,
+) -> Result<impl Reply, Rejection> {
+    let replayed_build_ids = artifact_service
+        .replay_failed_builds()
+        .await
+        .map_err(RegistryError::from)?;
+
+    let replayed_build_ids_as_json =
+        serde_json::to_string(&replayed_build_ids).map_err(RegistryError::from)?;
+
+    Ok(warp::http::response::Builder::new()
+        .header("Content-Type", "application/json")
+        .status(StatusCode::OK)
+        .body(replayed_build_ids_as_json))
+}
+
 pub async fn handle_get_peers(mut p2p_client: Client) -> Result<impl Reply, Rejection> {
     let peers = p2p_client.list_peers().await.map_err(RegistryError::from)?;
     debug!("Got received_peers: {:?}", peers);
@@ -264,17 +418,532 @@ pub async fn handle_get_status(mut p2p_client: Client) -> Result<impl Reply, Rej
         .unwrap())
 }
 
-pub async fn handle_inspect_log_docker(
-    request_docker_log: RequestDockerLog,
+/// Report the metrics of the Kademlia queries of this node, per kind of
+/// query.
+pub async fn handle_get_query_stats(p2p_client: Client) -> Result<impl Reply, Rejection> {
+    let query_stats_as_json =
+        serde_json::to_string(&p2p_client.query_metrics.snapshot()).map_err(RegistryError::from)?;
+
+    Ok(warp::http::response::Builder::new()
+        .header("Content-Type", "application/json")
+        .status(StatusCode::OK)
+        .body(query_stats_as_json))
+}
+
+/// Look up the providers of an artifact and report every step of the lookup.
+pub async fn handle_debug_lookup(
+    artifact_id: String,
+    mut p2p_client: Client,
+) -> Result<impl Reply, Rejection> {
+    let lookup_trace = p2p_client
+        .trace_lookup(&artifact_id)
+        .await
+        .map_err(RegistryError::from)?;
+    let lookup_trace_as_json = serde_json::to_string(&lookup_trace).map_err(RegistryError::from)?;
+
+    Ok(warp::http::response::Builder::new()
+        .header("Content-Type", "application/json")
+        .status(StatusCode::OK)
+        .body(lookup_trace_as_json))
+}
+
+pub async fn handle_get_provide_progress(
     artifact_service: ArtifactService,
 ) -> Result<impl Reply, Rejection> {
-    let result = artifact_service
+    let provide_progress_as_json =
+        serde_json::to_string(&artifact_service.provide_progress()).map_err(RegistryError::from)?;
+
+    Ok(warp::http::response::Builder::new()
+        .header("Content-Type", "application/json")
+        .status(StatusCode::OK)
+        .body(provide_progress_as_json))
+}
+
+/// Report the totals of the background scrubs of the local artifacts, most
+/// notably the number of corrupt artifacts that were found.
+pub async fn handle_get_scrub_stats(
+    artifact_service: ArtifactService,
+) -> Result<impl Reply, Rejection> {
+    let scrub_stats_as_json =
+        serde_json::to_string(&artifact_service.scrub_stats()).map_err(RegistryError::from)?;
+
+    Ok(warp::http::response::Builder::new()
+        .header("Content-Type", "application/json")
+        .status(StatusCode::OK)
+        .body(scrub_stats_as_json))
+}
+
+/// Report the availability of the artifacts in the transparency log as
+/// measured by the availability monitor.
+pub async fn handle_get_usage_metrics(
+    usage_accounting: UsageAccounting,
+) -> Result<impl Reply, Rejection> {
+    let metrics_as_json =
+        serde_json::to_string(&usage_accounting.metrics()).map_err(RegistryError::from)?;
+
+    Ok(warp::http::response::Builder::new()
+        .header("Content-Type", "application/json")
+        .status(StatusCode::OK)
+        .body(metrics_as_json))
+}
+
+pub async fn handle_get_availability_report(
+    request: RequestAvailabilityReport,
+    artifact_service: ArtifactService,
+) -> Result<impl Reply, Rejection> {
+    let report = match request.window_secs {
+        Some(window_secs) => artifact_service.availability_report(&[window_secs]),
+        None => artifact_service.availability_report(&DEFAULT_REPORT_WINDOWS),
+    };
+    let report_as_json = serde_json::to_string(&report).map_err(RegistryError::from)?;
+
+    Ok(warp::http::response::Builder::new()
+        .header("Content-Type", "application/json")
+        .status(StatusCode::OK)
+        .body(report_as_json))
+}
+
+/// Start copying the transparency logs and the popular and pinned artifacts
+/// of a seed node in the background. The progress is reported by
+/// [`handle_get_seed_sync_status`].
+pub async fn handle_start_seed_sync(
+    request: RequestSeedSync,
+    artifact_service: ArtifactService,
+) -> Result<impl Reply, Rejection> {
+    let seed = Multiaddr::from_str(&request.seed).map_err(|e| RegistryError {
+        code: RegistryErrorCode::BadRequest(format!(
+            "Invalid seed node address {}: {}",
+            request.seed, e
+        )),
+    })?;
+    let progress = artifact_service
+        .start_seed_sync(seed)
+        .map_err(|e| RegistryError {
+            code: RegistryErrorCode::BadRequest(e.to_string()),
+        })?;
+    let progress_as_json = serde_json::to_string(&progress).map_err(RegistryError::from)?;
+
+    Ok(warp::http::response::Builder::new()
+        .header("Content-Type", "application/json")
+        .status(StatusCode::ACCEPTED)
+        .body(progress_as_json))
+}
+
+/// Report the progress of the last sync from a seed node.
+pub async fn handle_get_seed_sync_status(
+    artifact_service: ArtifactService,
+) -> Result<impl Reply, Rejection> {
+    let progress_as_json = serde_json::to_string(&artifact_service.seed_sync_progress())
+        .map_err(RegistryError::from)?;
+
+    Ok(warp::http::response::Builder::new()
+        .header("Content-Type", "application/json")
+        .status(StatusCode::OK)
+        .body(progress_as_json))
+}
+
+/// Report how many stored artifacts are shared by several packages and how
+/// many bytes that saves.
+pub async fn handle_get_dedup_stats(
+    artifact_service: ArtifactService,
+) -> Result<impl Reply, Rejection> {
+    let dedup_stats = artifact_service
+        .dedup_stats()
+        .await
+        .map_err(RegistryError::from)?;
+    let dedup_stats_as_json = serde_json::to_string(&dedup_stats).map_err(RegistryError::from)?;
+
+    Ok(warp::http::response::Builder::new()
+        .header("Content-Type", "application/json")
+        .status(StatusCode::OK)
+        .body(dedup_stats_as_json))
+}
+
+/// Report how much space the stored artifacts take up and how much is left.
+pub async fn handle_get_storage_stats(
+    artifact_service: ArtifactService,
+) -> Result<impl Reply, Rejection> {
+    let storage_stats = artifact_service
+        .storage_stats()
+        .await
+        .map_err(RegistryError::from)?;
+    let storage_stats_as_json =
+        serde_json::to_string(&storage_stats).map_err(RegistryError::from)?;
+
+    Ok(warp::http::response::Builder::new()
+        .header("Content-Type", "application/json")
+        .status(StatusCode::OK)
+        .body(storage_stats_as_json))
+}
+
+/// Report the artifacts that are being downloaded from peers or written to the
+/// local storage, with the number of bytes transferred so far.
+pub async fn handle_get_transfer_progress(
+    artifact_service: ArtifactService,
+) -> Result<impl Reply, Rejection> {
+    let transfers_as_json =
+        serde_json::to_string(&artifact_service.active_transfers()).map_err(RegistryError::from)?;
+
+    Ok(warp::http::response::Builder::new()
+        .header("Content-Type", "application/json")
+        .status(StatusCode::OK)
+        .body(transfers_as_json))
+}
+
+/// Report the access statistics of a package at `artifacts/{coordinates}/stats`.
+/// The coordinates are the package type followed by the percent-encoded
+/// package specific id, like `docker/alpine:3.15` or
+/// `maven2/org.myorg:my-artifact:1.1.0`.
+pub async fn handle_get_access_stats(
+    path: warp::path::Tail,
+    artifact_service: ArtifactService,
+) -> Result<impl Reply, Rejection> {
+    let coordinates = path
+        .as_str()
+        .strip_suffix("/stats")
+        .ok_or_else(warp::reject::not_found)?;
+    let (package_type, package_specific_id) =
+        parse_package_coordinates(coordinates).ok_or_else(|| RegistryError {
+            code: RegistryErrorCode::BadRequest(format!(
+                "Invalid package coordinates: {}",
+                coordinates
+            )),
+        })?;
+
+    let access_stats_as_json = serde_json::to_string(
+        &artifact_service.get_access_stats(package_type, &package_specific_id),
+    )
+    .map_err(RegistryError::from)?;
+
+    Ok(warp::http::response::Builder::new()
+        .header("Content-Type", "application/json")
+        .status(StatusCode::OK)
+        .body(access_stats_as_json))
+}
+
+/// List the artifacts stored on this node, filtered by package type, name
+/// prefix and version, one page at a time.
+pub async fn handle_search_artifacts(
+    request: RequestArtifactSearch,
+    artifact_service: ArtifactService,
+) -> Result<impl Reply, Rejection> {
+    let package_type = request
+        .package_type
+        .as_deref()
+        .map(|package_type| {
+            parse_package_type(package_type).ok_or_else(|| RegistryError {
+                code: RegistryErrorCode::BadRequest(format!(
+                    "Invalid package type: {}",
+                    package_type
+                )),
+            })
+        })
+        .transpose()?;
+    let artifact_page = artifact_service
+        .search_artifacts(&ArtifactQuery {
+            package_type,
+            name: request.name,
+            version: request.version,
+            offset: request.offset.unwrap_or_default(),
+            limit: request.limit,
+        })
+        .await
+        .map_err(RegistryError::from)?;
+
+    let artifact_page_as_json =
+        serde_json::to_string(&artifact_page).map_err(RegistryError::from)?;
+
+    Ok(warp::http::response::Builder::new()
+        .header("Content-Type", "application/json")
+        .status(StatusCode::OK)
+        .body(artifact_page_as_json))
+}
+
+/// Query the transparency logs by package, operation, node, hash and time
+/// range, for audit tooling. Returns a page of the matching logs.
+pub async fn handle_query_transparency_logs(
+    request: RequestTransparencyLogQuery,
+    artifact_service: ArtifactService,
+) -> Result<impl Reply, Rejection> {
+    let package_type = request
+        .package_type
+        .as_deref()
+        .map(|package_type| {
+            parse_package_type(package_type).ok_or_else(|| RegistryError {
+                code: RegistryErrorCode::BadRequest(format!(
+                    "Invalid package type: {}",
+                    package_type
+                )),
+            })
+        })
+        .transpose()?;
+    let package_specific_id = match (package_type, request.package_specific_id) {
+        (Some(package_type), Some(id)) => Some(normalize_package_name(package_type, &id)),
+        (_, id) => id,
+    };
+
+    let transparency_log_page = artifact_service
         .transparency_log_service
-        .search_transparency_logs(
-            &PackageType::Docker,
-            get_package_specific_id(&request_docker_log.image).as_str(),
-        )
+        .query_transparency_logs(&TransparencyLogQuery {
+            package_type,
+            package_specific_id,
+            operation: request.operation,
+            node_id: request.node_id,
+            hash: request.hash,
+            since: request.since,
+            until: request.until,
+            offset: request.offset.unwrap_or_default(),
+            limit: request.limit,
+        })
+        .map_err(RegistryError::from)?;
+
+    let transparency_log_page_as_json =
+        serde_json::to_string(&transparency_log_page).map_err(RegistryError::from)?;
+
+    Ok(warp::http::response::Builder::new()
+        .header("Content-Type", "application/json")
+        .status(StatusCode::OK)
+        .body(transparency_log_page_as_json))
+}
+
+/// Long-poll the transparency logs that were written after a cursor, for
+/// consumers that tail the transparency log. Returns the new logs in the
+/// order in which they were written, with the cursor to resume from.
+pub async fn handle_watch_transparency_logs(
+    request: RequestTransparencyLogWatch,
+    artifact_service: ArtifactService,
+) -> Result<impl Reply, Rejection> {
+    let transparency_log_batch = artifact_service
+        .transparency_log_service
+        .watch_transparency_logs(&TransparencyLogWatchQuery {
+            since: request.since.unwrap_or_default(),
+            limit: request.limit,
+            wait_secs: request.wait_secs,
+        })
+        .await
+        .map_err(RegistryError::from)?;
+
+    let transparency_log_batch_as_json =
+        serde_json::to_string(&transparency_log_batch).map_err(RegistryError::from)?;
+
+    Ok(warp::http::response::Builder::new()
+        .header("Content-Type", "application/json")
+        .status(StatusCode::OK)
+        .body(transparency_log_batch_as_json))
+}
+
+fn parse_package_type(package_type: &str) -> Option<PackageType> {
+    match package_type.to_lowercase().as_str() {
+        "docker" => Some(PackageType::Docker),
+        "maven" | "maven2" => Some(PackageType::Maven2),
+        _ => None,
+    }
+}
+
+fn parse_package_coordinates(coordinates: &str) -> Option<(PackageType, String)> {
+    let (package_type, package_specific_id) = coordinates.split_once('/')?;
+    let package_type = parse_package_type(package_type)?;
+    let package_specific_id = percent_decode_str(package_specific_id)
+        .decode_utf8()
+        .ok()?
+        .into_owned();
+    if package_specific_id.is_empty() {
+        return None;
+    }
+
+    Some((
+        package_type,
+        normalize_package_specific_id(package_type, &package_specific_id),
+    ))
+}
+
+/// Report the size and root hash of the Merkle tree over the transparency
+/// logs, which inclusion proofs are verified against.
+pub async fn handle_get_transparency_log_head(
+    artifact_service: ArtifactService,
+) -> Result<impl Reply, Rejection> {
+    let tree_head = artifact_service
+        .transparency_log_service
+        .tree_head()
+        .map_err(RegistryError::from)?;
+    let tree_head_as_json = serde_json::to_string(&tree_head).map_err(RegistryError::from)?;
+
+    Ok(warp::http::response::Builder::new()
+        .header("Content-Type", "application/json")
+        .status(StatusCode::OK)
+        .body(tree_head_as_json))
+}
+
+/// List the most recent blocks of the local blockchain, newest first, with
+/// the transparency logs they published.
+pub async fn handle_list_blocks(
+    query: BlockListQuery,
+    artifact_service: ArtifactService,
+) -> Result<impl Reply, Rejection> {
+    let blocks = artifact_service
+        .transparency_log_service
+        .recent_blocks(query.limit.unwrap_or(DEFAULT_BLOCK_LIST_LIMIT))
+        .await
+        .map_err(RegistryError::from)?;
+    let block_views: Vec<BlockView> = blocks.iter().map(BlockView::from).collect();
+    let blocks_as_json = serde_json::to_string(&block_views).map_err(RegistryError::from)?;
+
+    Ok(warp::http::response::Builder::new()
+        .header("Content-Type", "application/json")
+        .status(StatusCode::OK)
+        .body(blocks_as_json))
+}
+
+/// Export all blocks of the local blockchain, which can be imported by a
+/// node at startup.
+pub async fn handle_export_blocks(
+    artifact_service: ArtifactService,
+) -> Result<impl Reply, Rejection> {
+    let export = artifact_service
+        .transparency_log_service
+        .export_blocks()
+        .await
+        .map_err(RegistryError::from)?;
+
+    Ok(warp::http::response::Builder::new()
+        .header("Content-Type", "application/octet-stream")
+        .status(StatusCode::OK)
+        .body(export))
+}
+
+/// Verify all blocks of the local blockchain and report the first invalid
+/// block, if any.
+pub async fn handle_audit_blocks(
+    artifact_service: ArtifactService,
+) -> Result<impl Reply, Rejection> {
+    let audit = artifact_service
+        .transparency_log_service
+        .audit_blocks()
+        .await
+        .map_err(RegistryError::from)?;
+    let audit_as_json = serde_json::to_string(&audit).map_err(RegistryError::from)?;
+
+    Ok(warp::http::response::Builder::new()
+        .header("Content-Type", "application/json")
+        .status(StatusCode::OK)
+        .body(audit_as_json))
+}
+
+/// Show a block of the local blockchain, identified by its ordinal or the
+/// hex encoded hash of its header, with the transparency logs it published.
+pub async fn handle_get_block(
+    id: String,
+    artifact_service: ArtifactService,
+) -> Result<impl Reply, Rejection> {
+    let block_id = BlockId::parse(&id).ok_or_else(|| RegistryError {
+        code: RegistryErrorCode::BadRequest(format!(
+            "{} is neither a block ordinal nor a block hash",
+            id
+        )),
+    })?;
+    let block = artifact_service
+        .transparency_log_service
+        .find_block(&block_id)
+        .await
+        .map_err(RegistryError::from)?
+        .ok_or_else(|| RegistryError {
+            code: RegistryErrorCode::BadRequest(format!("Block {} not found", id)),
+        })?;
+    let block_as_json =
+        serde_json::to_string(&BlockView::from(&block)).map_err(RegistryError::from)?;
+
+    Ok(warp::http::response::Builder::new()
+        .header("Content-Type", "application/json")
+        .status(StatusCode::OK)
+        .body(block_as_json))
+}
+
+/// Prove that the transparency log of an artifact is included in the
+/// transparency log.
+pub async fn handle_get_inclusion_proof(
+    artifact_id: String,
+    artifact_service: ArtifactService,
+) -> Result<impl Reply, Rejection> {
+    let inclusion_proof = artifact_service
+        .transparency_log_service
+        .get_inclusion_proof(&artifact_id)
         .map_err(RegistryError::from)?;
+    let inclusion_proof_as_json =
+        serde_json::to_string(&inclusion_proof).map_err(RegistryError::from)?;
+
+    Ok(warp::http::response::Builder::new()
+        .header("Content-Type", "application/json")
+        .status(StatusCode::OK)
+        .body(inclusion_proof_as_json))
+}
+
+/// List the signed checkpoints of the transparency log, oldest first.
+pub async fn handle_list_checkpoints(
+    artifact_service: ArtifactService,
+) -> Result<impl Reply, Rejection> {
+    let checkpoints = artifact_service
+        .transparency_log_service
+        .list_checkpoints()
+        .map_err(RegistryError::from)?;
+    let checkpoints_as_json = serde_json::to_string(&checkpoints).map_err(RegistryError::from)?;
+
+    Ok(warp::http::response::Builder::new()
+        .header("Content-Type", "application/json")
+        .status(StatusCode::OK)
+        .body(checkpoints_as_json))
+}
+
+/// Prove that the transparency log of a later checkpoint extends the
+/// transparency log of an earlier checkpoint.
+pub async fn handle_get_consistency_proof(
+    request: RequestConsistencyProof,
+    artifact_service: ArtifactService,
+) -> Result<impl Reply, Rejection> {
+    let consistency_proof = artifact_service
+        .transparency_log_service
+        .get_consistency_proof(request.old_size, request.new_size)
+        .map_err(RegistryError::from)?;
+    let consistency_proof_as_json =
+        serde_json::to_string(&consistency_proof).map_err(RegistryError::from)?;
+
+    Ok(warp::http::response::Builder::new()
+        .header("Content-Type", "application/json")
+        .status(StatusCode::OK)
+        .body(consistency_proof_as_json))
+}
+
+/// Export all transparency logs, as JSON lines or as a sequence of CBOR
+/// maps.
+pub async fn handle_export_log(
+    request: RequestLogExport,
+    artifact_service: ArtifactService,
+) -> Result<impl Reply, Rejection> {
+    let format = request.format.unwrap_or(LogFormat::Jsonl);
+    let mut export = Vec::new();
+    artifact_service
+        .transparency_log_service
+        .write_log(&mut export, format)
+        .map_err(RegistryError::from)?;
+    let content_type = match format {
+        LogFormat::Jsonl => "application/x-ndjson",
+        LogFormat::Cbor => "application/cbor-seq",
+    };
+
+    Ok(warp::http::response::Builder::new()
+        .header("Content-Type", content_type)
+        .status(StatusCode::OK)
+        .body(export))
+}
+
+pub async fn handle_inspect_log_docker(
+    request_docker_log: RequestDockerLog,
+    artifact_service: ArtifactService,
+) -> Result<impl Reply, Rejection> {
+    let result = search_transparency_logs(
+        &artifact_service,
+        &PackageType::Docker,
+        &get_package_specific_id(&request_docker_log.image),
+    )?;
 
     ResponseBuilder::from(request_docker_log.output_params).create_response(&result)
 }
@@ -283,19 +952,146 @@ pub async fn handle_inspect_log_maven(
     request_maven_log: RequestMavenLog,
     artifact_service: ArtifactService,
 ) -> Result<impl Reply, Rejection> {
-    let result = artifact_service
-        .transparency_log_service
-        .search_transparency_logs(&PackageType::Maven2, &request_maven_log.gav)
-        .map_err(RegistryError::from)?;
+    let result = search_transparency_logs(
+        &artifact_service,
+        &PackageType::Maven2,
+        &normalize_package_specific_id(PackageType::Maven2, &request_maven_log.gav),
+    )?;
 
     ResponseBuilder::from(request_maven_log.output_params).create_response(&result)
 }
 
-fn get_package_specific_id(package_specific_id: &str) -> String {
-    match package_specific_id.contains('/') {
-        true => package_specific_id.to_owned(),
-        false => format!("library/{}", package_specific_id),
+// The transparency logs of a package, followed by its deprecation if it is deprecated.
+fn search_transparency_logs(
+    artifact_service: &ArtifactService,
+    package_type: &PackageType,
+    package_specific_id: &str,
+) -> Result<Vec<TransparencyLog>, RegistryError> {
+    let mut logs = artifact_service
+        .transparency_log_service
+        .search_transparency_logs(package_type, package_specific_id)?;
+    if let Some(deprecation) = artifact_service
+        .transparency_log_service
+        .find_deprecation(package_type, package_specific_id)?
+    {
+        logs.push(deprecation);
     }
+
+    Ok(logs)
+}
+
+pub async fn handle_deprecate_package(
+    request_deprecate_package: RequestDeprecatePackage,
+    artifact_service: ArtifactService,
+) -> Result<impl Reply, Rejection> {
+    let package_type = request_deprecate_package.package_type;
+    let normalize = |package_specific_id: &str| {
+        normalize_package_specific_id(package_type, package_specific_id)
+    };
+
+    let deprecation = artifact_service
+        .deprecate_package(
+            package_type,
+            &normalize(&request_deprecate_package.package_specific_id),
+            request_deprecate_package
+                .successor
+                .as_deref()
+                .map(normalize),
+        )
+        .await
+        .map_err(|e| match e {
+            TransparencyLogError::ArtifactNotFound { .. }
+            | TransparencyLogError::NodeDoesNotExistOrRemoved { .. } => RegistryError {
+                code: RegistryErrorCode::BadRequest(e.to_string()),
+            },
+            _ => RegistryError::from(e),
+        })?;
+
+    let deprecation_as_json = serde_json::to_string(&deprecation).map_err(RegistryError::from)?;
+
+    Ok(warp::http::response::Builder::new()
+        .header("Content-Type", "application/json")
+        .status(StatusCode::CREATED)
+        .body(deprecation_as_json))
+}
+
+pub async fn handle_check_package(
+    request_check_package: RequestCheckPackage,
+    mut artifact_service: ArtifactService,
+) -> Result<impl Reply, Rejection> {
+    let package_type = request_check_package.package_type;
+    let package_specific_id =
+        normalize_package_specific_id(package_type, &request_check_package.package_specific_id);
+
+    let artifact_checks = artifact_service
+        .check_package(
+            package_type,
+            &package_specific_id,
+            request_check_package.retrieve,
+        )
+        .await
+        .map_err(|e| match e {
+            TransparencyLogError::ArtifactNotFound { .. } => RegistryError {
+                code: RegistryErrorCode::BadRequest(e.to_string()),
+            },
+            _ => RegistryError::from(e),
+        })?;
+
+    let artifact_checks_as_json =
+        serde_json::to_string(&artifact_checks).map_err(RegistryError::from)?;
+
+    Ok(warp::http::response::Builder::new()
+        .header("Content-Type", "application/json")
+        .status(StatusCode::OK)
+        .body(artifact_checks_as_json))
+}
+
+pub async fn handle_import_artifacts(
+    request_import_artifacts: RequestImportArtifacts,
+    mut artifact_service: ArtifactService,
+) -> Result<impl Reply, Rejection> {
+    let artifacts = request_import_artifacts
+        .artifacts
+        .into_iter()
+        .map(|artifact| {
+            general_purpose::STANDARD
+                .decode(&artifact.content)
+                .map(|content| (artifact.package_specific_artifact_id, content))
+        })
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| RegistryError {
+            code: RegistryErrorCode::BadRequest(format!("Invalid artifact content: {}", e)),
+        })?;
+
+    debug!(
+        "Importing {} artifacts of package {} from {}",
+        artifacts.len(),
+        request_import_artifacts.package_specific_id,
+        request_import_artifacts.source
+    );
+
+    artifact_service
+        .import_artifacts(
+            request_import_artifacts.package_type,
+            &request_import_artifacts.package_specific_id,
+            &request_import_artifacts.source,
+            artifacts,
+        )
+        .await
+        .map_err(|e| match e {
+            BuildError::InitializationFailed(_) => RegistryError {
+                code: RegistryErrorCode::BadRequest(e.to_string()),
+            },
+            _ => RegistryError::from(e),
+        })?;
+
+    Ok(warp::http::response::Builder::new()
+        .status(StatusCode::CREATED)
+        .body(""))
+}
+
+fn get_package_specific_id(package_specific_id: &str) -> String {
+    normalize_package_specific_id(PackageType::Docker, package_specific_id)
 }
 
 #[cfg(test)]
@@ -320,4 +1116,26 @@ mod tests {
             get_package_specific_id(package_specific_id)
         )
     }
+
+    #[test]
+    fn test_parse_package_coordinates() {
+        assert_eq!(
+            parse_package_coordinates("docker/alpine:3.16.2"),
+            Some((PackageType::Docker, "library/alpine:3.16.2".to_owned()))
+        );
+        assert_eq!(
+            parse_package_coordinates("docker/myorg%2Fimage:1.0"),
+            Some((PackageType::Docker, "myorg/image:1.0".to_owned()))
+        );
+        assert_eq!(
+            parse_package_coordinates("maven2/org.myorg:my-artifact:1.1.0"),
+            Some((
+                PackageType::Maven2,
+                "org.myorg:my-artifact:1.1.0".to_owned()
+            ))
+        );
+        assert_eq!(parse_package_coordinates("npm/left-pad:1.0"), None);
+        assert_eq!(parse_package_coordinates("docker/"), None);
+        assert_eq!(parse_package_coordinates("docker"), None);
+    }
 }
@@ -14,9 +14,15 @@
    limitations under the License.
 */
 
+use crate::artifact_service::model::PackageType;
+use crate::build_service::secrets::Secret;
+use crate::cli_commands::format::{format_timestamp, TimeZone};
 use crate::docker::error_util::RegistryError;
+use crate::network::peer_capabilities::NodeCapabilities;
 use crate::node_api::handlers::swarm::OutputTransparencyLog;
+use crate::transparency_log::log::{LogFormat, Operation};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::str::FromStr;
 
 #[derive(Debug, Deserialize, Serialize, Default)]
@@ -24,6 +30,18 @@ pub struct Status {
     pub peers_count: usize,
     pub peer_id: String,
     pub peer_addrs: Vec<String>,
+    /// The alias this node advertises to its peers.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub alias: Option<String>,
+    /// The software version of this node.
+    #[serde(default)]
+    pub version: String,
+    /// The number of connected peers per software version.
+    #[serde(default)]
+    pub peer_versions: BTreeMap<String, usize>,
+    /// The package types this node serves and the roles it takes on.
+    #[serde(default)]
+    pub capabilities: NodeCapabilities,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -31,6 +49,143 @@ pub struct RequestAddAuthorizedNode {
     pub peer_id: String,
 }
 
+#[derive(Debug, Deserialize, Serialize)]
+pub struct RequestRemoveAuthorizedNode {
+    pub peer_id: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct RequestSetPeerAlias {
+    pub peer_id: String,
+    /// The local alias of the peer, or `None` to remove it.
+    #[serde(default)]
+    pub alias: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct RequestAnnounceMaintenance {
+    /// How long the maintenance lasts, in seconds. `0` ends the maintenance.
+    pub ttl_secs: u64,
+    #[serde(default)]
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct RequestSetSecret {
+    pub namespace: String,
+    pub name: String,
+    pub value: Secret,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct RequestRemoveSecret {
+    pub namespace: String,
+    pub name: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct RequestSubscribe {
+    #[serde(default)]
+    pub package_type: Option<PackageType>,
+    pub pattern: String,
+    #[serde(default)]
+    pub webhook_url: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct RequestUnsubscribe {
+    pub subscription_id: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct RequestDeprecatePackage {
+    pub package_type: PackageType,
+    pub package_specific_id: String,
+    #[serde(default)]
+    pub successor: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct RequestCheckPackage {
+    pub package_type: PackageType,
+    pub package_specific_id: String,
+    /// Also try to retrieve every artifact from a remote provider.
+    #[serde(default)]
+    pub retrieve: bool,
+}
+
+/// The tree sizes of two checkpoints of the transparency log.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct RequestConsistencyProof {
+    pub old_size: u64,
+    pub new_size: u64,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct RequestAvailabilityReport {
+    /// Report a single window of this many seconds instead of the default
+    /// windows.
+    #[serde(default)]
+    pub window_secs: Option<u64>,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct RequestLogExport {
+    /// The format of the export, JSON lines by default.
+    #[serde(default)]
+    pub format: Option<LogFormat>,
+}
+
+/// A query of the transparency logs. The package type is `docker` or
+/// `maven2`, the package specific id is a prefix and the hash is an artifact
+/// or source hash. `since` and `until` are in seconds since the unix epoch.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct RequestTransparencyLogQuery {
+    pub package_type: Option<String>,
+    pub package_specific_id: Option<String>,
+    pub operation: Option<Operation>,
+    pub node_id: Option<String>,
+    pub hash: Option<String>,
+    pub since: Option<u64>,
+    pub until: Option<u64>,
+    pub offset: Option<usize>,
+    pub limit: Option<usize>,
+}
+
+/// A watch of the transparency logs that were written after the `since`
+/// cursor, which is the `next_cursor` of the previous response or 0 to start
+/// at the first transparency log. Waits up to `wait_secs` for new
+/// transparency logs when there are none.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct RequestTransparencyLogWatch {
+    pub since: Option<u64>,
+    pub limit: Option<usize>,
+    pub wait_secs: Option<u64>,
+}
+
+/// Sync from the seed node at the `seed` multiaddr, which includes its peer
+/// id.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct RequestSeedSync {
+    pub seed: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct RequestImportArtifacts {
+    pub package_type: PackageType,
+    pub package_specific_id: String,
+    /// The kind of repository the artifacts were imported from (e.g. docker-daemon).
+    pub source: String,
+    pub artifacts: Vec<ImportedArtifact>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ImportedArtifact {
+    pub package_specific_artifact_id: String,
+    /// The base64 encoded content of the artifact.
+    pub content: String,
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 pub struct RequestDockerBuild {
     pub image: String,
@@ -59,13 +214,36 @@ pub struct RequestMavenLog {
     pub output_params: Option<TransparencyLogOutputParams>,
 }
 
+/// A search of the artifacts stored on a node. The package type is `docker`
+/// or `maven2`, the name is a prefix of the package specific id.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct RequestArtifactSearch {
+    #[serde(rename = "type")]
+    pub package_type: Option<String>,
+    pub name: Option<String>,
+    pub version: Option<String>,
+    pub offset: Option<usize>,
+    pub limit: Option<usize>,
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 pub struct RequestBuildStatus {
     pub build_id: String,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
+pub struct RequestBuildRerun {
+    pub build_id: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct RequestBuildReproduce {
+    pub pinning_snapshot: String,
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize)]
 pub enum ContentType {
+    #[default]
     JSON,
     CSV,
 }
@@ -90,6 +268,9 @@ pub enum TransparencyLogField {
     Operation,
     NodeId,
     NodePublicKey,
+    Successor,
+    Provenance,
+    PinningSnapshot,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -102,17 +283,6 @@ pub struct ParseTransparencyLogFieldError {
     invalid_field: String,
 }
 
-impl Clone for ContentType {
-    fn clone(&self) -> Self {
-        match self {
-            ContentType::JSON => ContentType::JSON,
-            ContentType::CSV => ContentType::CSV,
-        }
-    }
-}
-
-impl Copy for ContentType {}
-
 impl FromStr for ContentType {
     type Err = ParseContentTypeError;
 
@@ -127,12 +297,6 @@ impl FromStr for ContentType {
     }
 }
 
-impl Default for ContentType {
-    fn default() -> Self {
-        ContentType::JSON
-    }
-}
-
 impl ContentType {
     pub fn from(format: Option<&String>) -> Result<Self, ParseContentTypeError> {
         if let Some(val) = format {
@@ -142,14 +306,27 @@ impl ContentType {
         }
     }
 
-    pub fn print_logs(&self, logs: String) {
+    /// Print the logs with their timestamps formatted as RFC3339 in
+    /// `time_zone`.
+    pub fn print_logs(&self, logs: String, time_zone: TimeZone) {
         match self {
             ContentType::JSON => {
-                let logs_as_json: serde_json::Value = serde_json::from_str(logs.as_str()).unwrap();
+                let mut logs_as_json: serde_json::Value =
+                    serde_json::from_str(logs.as_str()).unwrap();
+                for log in logs_as_json.as_array_mut().into_iter().flatten() {
+                    if let Some(timestamp) = log.get_mut("timestamp") {
+                        if let Some(seconds) = timestamp.as_u64() {
+                            *timestamp = format_timestamp(seconds, time_zone).into();
+                        }
+                    }
+                }
                 println!("{}", serde_json::to_string_pretty(&logs_as_json).unwrap());
             }
             ContentType::CSV => {
-                println!("{}", logs);
+                println!(
+                    "{}",
+                    format_csv_timestamps(&logs, time_zone).unwrap_or(logs)
+                );
             }
         }
     }
@@ -177,6 +354,32 @@ impl ContentType {
     }
 }
 
+fn format_csv_timestamps(logs: &str, time_zone: TimeZone) -> Option<String> {
+    if logs.is_empty() {
+        return None;
+    }
+    let mut reader = csv::Reader::from_reader(logs.as_bytes());
+    let headers = reader.headers().ok()?.clone();
+    let timestamp_column = headers.iter().position(|header| header == "timestamp")?;
+
+    let mut writer = csv::Writer::from_writer(vec![]);
+    writer.write_record(&headers).ok()?;
+    for record in reader.records() {
+        let record = record.ok()?;
+        writer
+            .write_record(record.iter().enumerate().map(
+                |(column, value)| match value.parse::<u64>() {
+                    Ok(seconds) if column == timestamp_column => {
+                        format_timestamp(seconds, time_zone)
+                    }
+                    _ => value.to_owned(),
+                },
+            ))
+            .ok()?;
+    }
+    String::from_utf8(writer.into_inner().ok()?).ok()
+}
+
 impl FromStr for TransparencyLogField {
     type Err = ParseTransparencyLogFieldError;
 
@@ -195,6 +398,9 @@ impl FromStr for TransparencyLogField {
             "operation" => TransparencyLogField::Operation,
             "node_id" => TransparencyLogField::NodeId,
             "node_public_key" => TransparencyLogField::NodePublicKey,
+            "successor" => TransparencyLogField::Successor,
+            "provenance" => TransparencyLogField::Provenance,
+            "pinning_snapshot" => TransparencyLogField::PinningSnapshot,
             _ => {
                 return Err(ParseTransparencyLogFieldError {
                     invalid_field: s.to_string(),
@@ -224,6 +430,9 @@ impl Clone for TransparencyLogField {
             TransparencyLogField::Operation => TransparencyLogField::Operation,
             TransparencyLogField::NodeId => TransparencyLogField::NodeId,
             TransparencyLogField::NodePublicKey => TransparencyLogField::NodePublicKey,
+            TransparencyLogField::Successor => TransparencyLogField::Successor,
+            TransparencyLogField::Provenance => TransparencyLogField::Provenance,
+            TransparencyLogField::PinningSnapshot => TransparencyLogField::PinningSnapshot,
         }
     }
 }
@@ -250,10 +459,18 @@ impl TransparencyLogField {
             TransparencyLogField::Timestamp => ("Timestamp", "Timestamp"),
             TransparencyLogField::Operation => (
                 "Operation",
-                "Operation (AddArtifact, RemoveArtifact, AddNode, RemoveNode)",
+                "Operation (AddArtifact, RemoveArtifact, AddNode, RemoveNode, DeprecateArtifact, RevokeArtifact, ApproveAddNode, ApproveRemoveNode, ClaimBuild, ReleaseBuild)",
             ),
             TransparencyLogField::NodeId => ("NodeId", "Peer node identity"),
             TransparencyLogField::NodePublicKey => ("NodePublicKey", "Node public key"),
+            TransparencyLogField::Successor => ("Successor", "Successor of a deprecated package"),
+            TransparencyLogField::Provenance => {
+                ("Provenance", "Whether the artifact was built or imported")
+            }
+            TransparencyLogField::PinningSnapshot => (
+                "PinningSnapshot",
+                "Snapshot of the exact dependencies of the build",
+            ),
         }
     }
 }
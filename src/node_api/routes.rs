@@ -14,13 +14,29 @@
    limitations under the License.
 */
 
+use super::handlers::alerts::*;
+use super::handlers::peers::*;
+use super::handlers::secrets::*;
+use super::handlers::subscriptions::*;
 use super::handlers::swarm::*;
 use super::model::request::{RequestDockerBuild, RequestMavenBuild};
+use crate::accounting_service::service::UsageAccounting;
+use crate::alert_service::service::AlertService;
 use crate::artifact_service::service::ArtifactService;
+use crate::blockchain_service::explorer::BlockListQuery;
+use crate::build_service::history::BuildHistoryQuery;
+use crate::build_service::secrets::SecretStore;
 use crate::network::client::Client;
+use crate::network::peer_alias::PeerAliases;
 use crate::node_api::model::request::{
-    RequestAddAuthorizedNode, RequestBuildStatus, RequestDockerLog, RequestMavenLog,
+    RequestAddAuthorizedNode, RequestAnnounceMaintenance, RequestArtifactSearch,
+    RequestAvailabilityReport, RequestBuildReproduce, RequestBuildRerun, RequestBuildStatus,
+    RequestCheckPackage, RequestConsistencyProof, RequestDeprecatePackage, RequestDockerLog,
+    RequestImportArtifacts, RequestLogExport, RequestMavenLog, RequestRemoveAuthorizedNode,
+    RequestRemoveSecret, RequestSeedSync, RequestSetPeerAlias, RequestSetSecret, RequestSubscribe,
+    RequestTransparencyLogQuery, RequestTransparencyLogWatch, RequestUnsubscribe,
 };
+use crate::subscription_service::service::SubscriptionService;
 use warp::Filter;
 
 pub fn make_node_routes(
@@ -38,6 +54,20 @@ pub fn make_node_routes(
         .and(artifact_service_filter.clone())
         .and_then(handle_add_authorized_node);
 
+    let remove_authorized_node = warp::path!("authorized_node" / "remove")
+        .and(warp::post())
+        .and(warp::path::end())
+        .and(warp::body::content_length_limit(1024 * 8))
+        .and(warp::body::json::<RequestRemoveAuthorizedNode>())
+        .and(artifact_service_filter.clone())
+        .and_then(handle_remove_authorized_node);
+
+    let node_change_proposals = warp::path!("authorized_node" / "proposals")
+        .and(warp::get())
+        .and(warp::path::end())
+        .and(artifact_service_filter.clone())
+        .and_then(handle_list_node_change_proposals);
+
     let build_docker = warp::path!("build" / "docker")
         .and(warp::post())
         .and(warp::path::end())
@@ -62,6 +92,35 @@ pub fn make_node_routes(
         .and(artifact_service_filter.clone())
         .and_then(handle_build_status);
 
+    let build_history = warp::path!("builds")
+        .and(warp::get())
+        .and(warp::path::end())
+        .and(warp::query::<BuildHistoryQuery>())
+        .and(artifact_service_filter.clone())
+        .and_then(handle_build_history);
+
+    let build_rerun = warp::path!("build" / "rerun")
+        .and(warp::post())
+        .and(warp::path::end())
+        .and(warp::body::content_length_limit(1024 * 8))
+        .and(warp::body::json::<RequestBuildRerun>())
+        .and(artifact_service_filter.clone())
+        .and_then(handle_build_rerun);
+
+    let build_reproduce = warp::path!("build" / "reproduce")
+        .and(warp::post())
+        .and(warp::path::end())
+        .and(warp::body::content_length_limit(1024 * 8))
+        .and(warp::body::json::<RequestBuildReproduce>())
+        .and(artifact_service_filter.clone())
+        .and_then(handle_build_reproduce);
+
+    let build_replay_failed = warp::path!("build" / "replay-failed")
+        .and(warp::post())
+        .and(warp::path::end())
+        .and(artifact_service_filter.clone())
+        .and_then(handle_build_replay_failed);
+
     let peers = warp::path!("peers")
         .and(warp::get())
         .and(warp::path::end())
@@ -74,6 +133,49 @@ pub fn make_node_routes(
         .and(p2p_client_filter)
         .and_then(handle_get_status);
 
+    let provide_status = warp::path!("status" / "provide")
+        .and(warp::get())
+        .and(warp::path::end())
+        .and(artifact_service_filter.clone())
+        .and_then(handle_get_provide_progress);
+
+    let scrub_status = warp::path!("status" / "scrub")
+        .and(warp::get())
+        .and(warp::path::end())
+        .and(artifact_service_filter.clone())
+        .and_then(handle_get_scrub_stats);
+
+    let dedup_status = warp::path!("status" / "dedup")
+        .and(warp::get())
+        .and(warp::path::end())
+        .and(artifact_service_filter.clone())
+        .and_then(handle_get_dedup_stats);
+
+    let storage_status = warp::path!("status" / "storage")
+        .and(warp::get())
+        .and(warp::path::end())
+        .and(artifact_service_filter.clone())
+        .and_then(handle_get_storage_stats);
+
+    let transfer_status = warp::path!("status" / "transfers")
+        .and(warp::get())
+        .and(warp::path::end())
+        .and(artifact_service_filter.clone())
+        .and_then(handle_get_transfer_progress);
+
+    let search_artifacts = warp::path!("artifacts")
+        .and(warp::get())
+        .and(warp::path::end())
+        .and(warp::query::<RequestArtifactSearch>())
+        .and(artifact_service_filter.clone())
+        .and_then(handle_search_artifacts);
+
+    let access_stats = warp::path("artifacts")
+        .and(warp::get())
+        .and(warp::path::tail())
+        .and(artifact_service_filter.clone())
+        .and_then(handle_get_access_stats);
+
     let inspect_docker = warp::path!("inspect" / "docker")
         .and(warp::post())
         .and(warp::path::end())
@@ -87,18 +189,368 @@ pub fn make_node_routes(
         .and(warp::path::end())
         .and(warp::body::content_length_limit(1024 * 8))
         .and(warp::body::json::<RequestMavenLog>())
-        .and(artifact_service_filter)
+        .and(artifact_service_filter.clone())
         .and_then(handle_inspect_log_maven);
 
+    let check_package = warp::path!("package" / "check")
+        .and(warp::post())
+        .and(warp::path::end())
+        .and(warp::body::content_length_limit(1024 * 8))
+        .and(warp::body::json::<RequestCheckPackage>())
+        .and(artifact_service_filter)
+        .and_then(handle_check_package);
+
     warp::any().and(
         add_authorized_node
+            .or(remove_authorized_node)
+            .or(node_change_proposals)
             .or(build_docker)
             .or(build_maven)
             .or(peers)
             .or(status)
+            .or(provide_status)
+            .or(scrub_status)
+            .or(dedup_status)
+            .or(storage_status)
+            .or(transfer_status)
+            .or(search_artifacts)
+            .or(access_stats)
             .or(inspect_docker)
             .or(inspect_maven)
-            .or(build_status),
+            .or(check_package)
+            .or(build_status)
+            .or(build_history)
+            .or(build_rerun)
+            .or(build_reproduce)
+            .or(build_replay_failed),
+    )
+}
+
+pub fn make_transparency_log_routes(
+    artifact_service: ArtifactService,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    let artifact_service_filter = warp::any().map(move || artifact_service.clone());
+
+    let list_blocks = warp::path!("blocks")
+        .and(warp::get())
+        .and(warp::path::end())
+        .and(warp::query::<BlockListQuery>())
+        .and(artifact_service_filter.clone())
+        .and_then(handle_list_blocks);
+
+    let export_blocks = warp::path!("blocks" / "export")
+        .and(warp::get())
+        .and(warp::path::end())
+        .and(artifact_service_filter.clone())
+        .and_then(handle_export_blocks);
+
+    let audit_blocks = warp::path!("blocks" / "audit")
+        .and(warp::get())
+        .and(warp::path::end())
+        .and(artifact_service_filter.clone())
+        .and_then(handle_audit_blocks);
+
+    let get_block = warp::path!("blocks" / String)
+        .and(warp::get())
+        .and(warp::path::end())
+        .and(artifact_service_filter.clone())
+        .and_then(handle_get_block);
+
+    let tree_head = warp::path!("transparency-log" / "head")
+        .and(warp::get())
+        .and(warp::path::end())
+        .and(artifact_service_filter.clone())
+        .and_then(handle_get_transparency_log_head);
+
+    let inclusion_proof = warp::path!("transparency-log" / "proof" / String)
+        .and(warp::get())
+        .and(warp::path::end())
+        .and(artifact_service_filter.clone())
+        .and_then(handle_get_inclusion_proof);
+
+    let checkpoints = warp::path!("transparency-log" / "checkpoints")
+        .and(warp::get())
+        .and(warp::path::end())
+        .and(artifact_service_filter.clone())
+        .and_then(handle_list_checkpoints);
+
+    let consistency_proof = warp::path!("transparency-log" / "consistency")
+        .and(warp::get())
+        .and(warp::path::end())
+        .and(warp::query::<RequestConsistencyProof>())
+        .and(artifact_service_filter.clone())
+        .and_then(handle_get_consistency_proof);
+
+    let export = warp::path!("transparency-log" / "export")
+        .and(warp::get())
+        .and(warp::path::end())
+        .and(warp::query::<RequestLogExport>())
+        .and(artifact_service_filter.clone())
+        .and_then(handle_export_log);
+
+    let query = warp::path!("transparency-log" / "query")
+        .and(warp::get())
+        .and(warp::path::end())
+        .and(warp::query::<RequestTransparencyLogQuery>())
+        .and(artifact_service_filter.clone())
+        .and_then(handle_query_transparency_logs);
+
+    let stream = warp::path!("inspect" / "logs" / "stream")
+        .and(warp::get())
+        .and(warp::path::end())
+        .and(warp::query::<RequestTransparencyLogWatch>())
+        .and(artifact_service_filter)
+        .and_then(handle_watch_transparency_logs);
+
+    warp::any().and(
+        tree_head
+            .or(inclusion_proof)
+            .or(checkpoints)
+            .or(consistency_proof)
+            .or(export)
+            .or(query)
+            .or(stream)
+            .or(list_blocks)
+            .or(export_blocks)
+            .or(audit_blocks)
+            .or(get_block),
+    )
+}
+
+pub fn make_stats_routes(
+    artifact_service: ArtifactService,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    let artifact_service_filter = warp::any().map(move || artifact_service.clone());
+
+    warp::path!("stats" / "availability")
+        .and(warp::get())
+        .and(warp::path::end())
+        .and(warp::query::<RequestAvailabilityReport>())
+        .and(artifact_service_filter)
+        .and_then(handle_get_availability_report)
+}
+
+pub fn make_usage_routes(
+    usage_accounting: UsageAccounting,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    let usage_accounting_filter = warp::any().map(move || usage_accounting.clone());
+
+    warp::path!("stats" / "usage")
+        .and(warp::get())
+        .and(warp::path::end())
+        .and(usage_accounting_filter)
+        .and_then(handle_get_usage_metrics)
+}
+
+pub fn make_network_routes(
+    p2p_client: Client,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    let p2p_client_filter = warp::any().map(move || p2p_client.clone());
+
+    let query_stats = warp::path!("network" / "query-stats")
+        .and(warp::get())
+        .and(warp::path::end())
+        .and(p2p_client_filter.clone())
+        .and_then(handle_get_query_stats);
+
+    let debug_lookup = warp::path!("network" / "debug-lookup" / String)
+        .and(warp::get())
+        .and(warp::path::end())
+        .and(p2p_client_filter)
+        .and_then(handle_debug_lookup);
+
+    warp::any().and(query_stats.or(debug_lookup))
+}
+
+pub fn make_secret_routes(
+    secret_store: SecretStore,
+    admin_token: Option<String>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    let secret_store_filter = warp::any().map(move || secret_store.clone());
+
+    let set_secret = warp::path!("secret" / "set")
+        .and(warp::post())
+        .and(warp::path::end())
+        .and(require_admin(admin_token.clone()))
+        .and(warp::body::content_length_limit(1024 * 8))
+        .and(warp::body::json::<RequestSetSecret>())
+        .and(secret_store_filter.clone())
+        .and_then(handle_set_secret);
+
+    let remove_secret = warp::path!("secret" / "remove")
+        .and(warp::post())
+        .and(warp::path::end())
+        .and(require_admin(admin_token.clone()))
+        .and(warp::body::content_length_limit(1024 * 8))
+        .and(warp::body::json::<RequestRemoveSecret>())
+        .and(secret_store_filter.clone())
+        .and_then(handle_remove_secret);
+
+    let list_secrets = warp::path!("secret" / "list")
+        .and(warp::get())
+        .and(warp::path::end())
+        .and(require_admin(admin_token))
+        .and(secret_store_filter)
+        .and_then(handle_list_secrets);
+
+    warp::any().and(set_secret.or(remove_secret).or(list_secrets))
+}
+
+pub fn make_alert_routes(
+    alert_service: AlertService,
+    admin_token: Option<String>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    let alert_service_filter = warp::any().map(move || alert_service.clone());
+
+    warp::path!("alerts")
+        .and(warp::get())
+        .and(warp::path::end())
+        .and(require_admin(admin_token))
+        .and(alert_service_filter)
+        .and_then(handle_list_alerts)
+}
+
+pub fn make_peer_alias_routes(
+    peer_aliases: PeerAliases,
+    admin_token: Option<String>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    let peer_aliases_filter = warp::any().map(move || peer_aliases.clone());
+
+    let list_peer_aliases = warp::path!("peers" / "aliases")
+        .and(warp::get())
+        .and(warp::path::end())
+        .and(peer_aliases_filter.clone())
+        .and_then(handle_list_peer_aliases);
+
+    let set_peer_alias = warp::path!("peers" / "alias")
+        .and(warp::post())
+        .and(warp::path::end())
+        .and(require_admin(admin_token))
+        .and(warp::body::content_length_limit(1024 * 8))
+        .and(warp::body::json::<RequestSetPeerAlias>())
+        .and(peer_aliases_filter)
+        .and_then(handle_set_peer_alias);
+
+    warp::any().and(list_peer_aliases.or(set_peer_alias))
+}
+
+pub fn make_maintenance_routes(
+    p2p_client: Client,
+    admin_token: Option<String>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    let p2p_client_filter = warp::any().map(move || p2p_client.clone());
+
+    let list_draining_peers = warp::path!("peers" / "maintenance")
+        .and(warp::get())
+        .and(warp::path::end())
+        .and(p2p_client_filter.clone())
+        .and_then(handle_list_draining_peers);
+
+    let announce_maintenance = warp::path!("maintenance")
+        .and(warp::post())
+        .and(warp::path::end())
+        .and(require_admin(admin_token))
+        .and(warp::body::content_length_limit(1024 * 8))
+        .and(warp::body::json::<RequestAnnounceMaintenance>())
+        .and(p2p_client_filter)
+        .and_then(handle_announce_maintenance);
+
+    warp::any().and(list_draining_peers.or(announce_maintenance))
+}
+
+pub fn make_publisher_routes(
+    artifact_service: ArtifactService,
+    admin_token: Option<String>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    let artifact_service_filter = warp::any().map(move || artifact_service.clone());
+
+    let deprecate_package = warp::path!("package" / "deprecate")
+        .and(warp::post())
+        .and(warp::path::end())
+        .and(require_admin(admin_token.clone()))
+        .and(warp::body::content_length_limit(1024 * 8))
+        .and(warp::body::json::<RequestDeprecatePackage>())
+        .and(artifact_service_filter.clone())
+        .and_then(handle_deprecate_package);
+
+    let import_artifacts = warp::path!("package" / "import")
+        .and(warp::post())
+        .and(warp::path::end())
+        .and(require_admin(admin_token))
+        .and(warp::body::content_length_limit(1024 * 1024 * 1024))
+        .and(warp::body::json::<RequestImportArtifacts>())
+        .and(artifact_service_filter)
+        .and_then(handle_import_artifacts);
+
+    warp::any().and(deprecate_package.or(import_artifacts))
+}
+
+pub fn make_seed_sync_routes(
+    artifact_service: ArtifactService,
+    admin_token: Option<String>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    let artifact_service_filter = warp::any().map(move || artifact_service.clone());
+
+    let start_seed_sync = warp::path!("seed-sync")
+        .and(warp::post())
+        .and(warp::path::end())
+        .and(require_admin(admin_token))
+        .and(warp::body::content_length_limit(1024 * 8))
+        .and(warp::body::json::<RequestSeedSync>())
+        .and(artifact_service_filter.clone())
+        .and_then(handle_start_seed_sync);
+
+    let seed_sync_status = warp::path!("seed-sync" / "status")
+        .and(warp::get())
+        .and(warp::path::end())
+        .and(artifact_service_filter)
+        .and_then(handle_get_seed_sync_status);
+
+    warp::any().and(start_seed_sync.or(seed_sync_status))
+}
+
+pub fn make_subscription_routes(
+    subscription_service: SubscriptionService,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    let subscription_service_filter = warp::any().map(move || subscription_service.clone());
+
+    let subscribe = warp::path!("subscription" / "add")
+        .and(warp::post())
+        .and(warp::path::end())
+        .and(api_key())
+        .and(warp::body::content_length_limit(1024 * 8))
+        .and(warp::body::json::<RequestSubscribe>())
+        .and(subscription_service_filter.clone())
+        .and_then(handle_subscribe);
+
+    let unsubscribe = warp::path!("subscription" / "remove")
+        .and(warp::post())
+        .and(warp::path::end())
+        .and(api_key())
+        .and(warp::body::content_length_limit(1024 * 8))
+        .and(warp::body::json::<RequestUnsubscribe>())
+        .and(subscription_service_filter.clone())
+        .and_then(handle_unsubscribe);
+
+    let list_subscriptions = warp::path!("subscription" / "list")
+        .and(warp::get())
+        .and(warp::path::end())
+        .and(api_key())
+        .and(subscription_service_filter.clone())
+        .and_then(handle_list_subscriptions);
+
+    let subscription_events = warp::path!("subscription" / "events")
+        .and(warp::path::end())
+        .and(api_key())
+        .and(warp::ws())
+        .and(subscription_service_filter)
+        .and_then(handle_subscription_events);
+
+    warp::any().and(
+        subscribe
+            .or(unsubscribe)
+            .or(list_subscriptions)
+            .or(subscription_events),
     )
 }
 
@@ -106,21 +558,47 @@ pub fn make_node_routes(
 #[cfg(not(tarpaulin_include))]
 mod tests {
     use super::*;
-    use crate::artifact_service::model::PackageType;
+    use crate::alert_service::service::Alert;
+    use crate::artifact_service::model::{
+        ArtifactPage, DedupStats, PackageType, ProvideProgress, ScrubStats, SeedSyncProgress,
+        SeedSyncState, StorageStats,
+    };
+    use crate::artifact_service::progress::TransferProgress;
+    use crate::blockchain_service::audit::{audit_chain, ChainAudit};
     use crate::blockchain_service::event::BlockchainEvent;
+    use crate::blockchain_service::explorer::{hex_hash, BlockView};
+    use crate::build_service::error::BuildError;
     use crate::build_service::event::BuildEvent;
+    use crate::build_service::history::{BuildOutcome, BuildRecord};
+    use crate::build_service::model::BuildTrigger;
+    use crate::build_service::secrets::{Secret, SecretDescriptor};
+    use crate::docker::error_util::custom_recover;
     use crate::network::client::command::Command;
+    use crate::network::peer_alias::{PeerAlias, PeerAliasSource};
+    use crate::network::peer_maintenance::{DrainingPeer, MaintenanceAnnouncement};
+    use crate::network::query_metrics::{LookupTrace, QueryKind, QueryKindStats};
     use crate::node_api::model::request::*;
     use crate::node_api::model::response::BuildSuccessResponse;
+    use crate::subscription_service::service::{Notification, Subscription};
+    use crate::test_support::FakeNetwork;
     use crate::transparency_log::log::{
-        AddArtifactRequest, TransparencyLog, TransparencyLogService,
+        AddArtifactRequest, TransparencyLog, TransparencyLogBatch, TransparencyLogInclusionProof,
+        TransparencyLogPage, TransparencyLogService,
     };
+    use crate::transparency_log::merkle::{ConsistencyProof, TreeHead};
+    use crate::transparency_log::node_quorum::NodeChangeProposal;
     use crate::util::test_util;
     use csv;
     use httptest::http;
-    use std::collections::HashSet;
+    use libp2p::identity;
+    use pyrsia_blockchain_network::crypto::hash_algorithm::HashDigest;
+    use pyrsia_blockchain_network::structures::block::Block;
+    use pyrsia_blockchain_network::structures::header::Address;
+    use pyrsia_blockchain_network::structures::transaction::{Transaction, TransactionType};
+    use std::collections::{BTreeMap, HashSet};
     use std::future::Future;
     use std::str;
+    use std::time::Duration;
 
     #[tokio::test]
     async fn node_routes_add_authorized_node() {
@@ -170,12 +648,17 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn node_routes_build_docker() {
+    async fn node_routes_authorized_node_changes_need_a_quorum() {
         let tmp_dir = test_util::tests::setup();
 
-        let (p2p_client, mut p2p_command_receiver) = test_util::tests::create_p2p_client();
-        let (artifact_service, mut blockchain_event_receiver, mut build_event_receiver) =
+        let (p2p_client, _) = test_util::tests::create_p2p_client();
+        let (mut artifact_service, mut blockchain_event_receiver, ..) =
             test_util::tests::create_artifact_service_with_p2p_client(&tmp_dir, p2p_client.clone());
+        let identity::Keypair::Ed25519(keypair) = identity::Keypair::generate_ed25519();
+        let local_peer_id = identity::PublicKey::Ed25519(keypair.public()).to_peer_id();
+        artifact_service.transparency_log_service = artifact_service
+            .transparency_log_service
+            .with_node_keypair(keypair);
 
         tokio::spawn(async move {
             loop {
@@ -188,18 +671,97 @@ mod tests {
             }
         });
 
-        let build_id = uuid::Uuid::new_v4();
-        tokio::spawn(async move {
-            loop {
-                match build_event_receiver.recv().await {
-                    Some(BuildEvent::Start { sender, .. }) => {
-                        let _ = sender.send(Ok(build_id.to_string()));
-                    }
-                    _ => {
-                        panic!("BuildEvent must match BuildEvent::Start")
-                    }
-                }
-            }
+        let filter = make_node_routes(artifact_service, p2p_client).recover(custom_recover);
+        let other_peer_ids = [libp2p::PeerId::random(), libp2p::PeerId::random()];
+        for peer_id in [local_peer_id, other_peer_ids[0]] {
+            let response = warp::test::request()
+                .method("POST")
+                .path("/authorized_node")
+                .json(&RequestAddAuthorizedNode {
+                    peer_id: peer_id.to_string(),
+                })
+                .reply(&filter)
+                .await;
+            assert_eq!(response.status(), 201);
+        }
+
+        let response = warp::test::request()
+            .method("POST")
+            .path("/authorized_node")
+            .json(&RequestAddAuthorizedNode {
+                peer_id: other_peer_ids[1].to_string(),
+            })
+            .reply(&filter)
+            .await;
+        assert_eq!(response.status(), 202);
+        let proposal: NodeChangeProposal = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(proposal.approvals, vec![local_peer_id.to_string()]);
+        assert_eq!(proposal.quorum, 2);
+
+        let response = warp::test::request()
+            .path("/authorized_node/proposals")
+            .reply(&filter)
+            .await;
+        assert_eq!(response.status(), 200);
+        assert_eq!(
+            serde_json::from_slice::<Vec<NodeChangeProposal>>(response.body()).unwrap(),
+            vec![proposal]
+        );
+
+        let response = warp::test::request()
+            .method("POST")
+            .path("/authorized_node/remove")
+            .json(&RequestRemoveAuthorizedNode {
+                peer_id: other_peer_ids[1].to_string(),
+            })
+            .reply(&filter)
+            .await;
+        assert_eq!(response.status(), 400);
+
+        let response = warp::test::request()
+            .method("POST")
+            .path("/authorized_node/remove")
+            .json(&RequestRemoveAuthorizedNode {
+                peer_id: other_peer_ids[0].to_string(),
+            })
+            .reply(&filter)
+            .await;
+        assert_eq!(response.status(), 202);
+
+        test_util::tests::teardown(tmp_dir);
+    }
+
+    #[tokio::test]
+    async fn node_routes_build_docker() {
+        let tmp_dir = test_util::tests::setup();
+
+        let (p2p_client, mut p2p_command_receiver) = test_util::tests::create_p2p_client();
+        let (artifact_service, mut blockchain_event_receiver, mut build_event_receiver) =
+            test_util::tests::create_artifact_service_with_p2p_client(&tmp_dir, p2p_client.clone());
+
+        tokio::spawn(async move {
+            loop {
+                match blockchain_event_receiver.recv().await {
+                    Some(BlockchainEvent::AddBlock { sender, .. }) => {
+                        let _ = sender.send(Ok(()));
+                    }
+                    _ => panic!("BlockchainEvent must match BlockchainEvent::AddBlock"),
+                }
+            }
+        });
+
+        let build_id = uuid::Uuid::new_v4();
+        tokio::spawn(async move {
+            loop {
+                match build_event_receiver.recv().await {
+                    Some(BuildEvent::Start { sender, .. }) => {
+                        let _ = sender.send(Ok(build_id.to_string()));
+                    }
+                    _ => {
+                        panic!("BuildEvent must match BuildEvent::Start")
+                    }
+                }
+            }
         });
 
         tokio::spawn(async move {
@@ -225,119 +787,727 @@ mod tests {
         };
         let response = warp::test::request()
             .method("POST")
-            .path("/build/docker")
-            .json(&request)
+            .path("/build/docker")
+            .json(&request)
+            .reply(&filter)
+            .await;
+
+        assert_eq!(response.status(), 200);
+
+        let build_id_result: BuildSuccessResponse =
+            serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(build_id_result.build_id.unwrap(), build_id.to_string());
+
+        test_util::tests::teardown(tmp_dir);
+    }
+
+    #[tokio::test]
+    async fn node_routes_build_maven() {
+        let tmp_dir = test_util::tests::setup();
+
+        let (p2p_client, mut p2p_command_receiver) = test_util::tests::create_p2p_client();
+        let (artifact_service, mut blockchain_event_receiver, mut build_event_receiver) =
+            test_util::tests::create_artifact_service_with_p2p_client(&tmp_dir, p2p_client.clone());
+
+        tokio::spawn(async move {
+            loop {
+                match blockchain_event_receiver.recv().await {
+                    Some(BlockchainEvent::AddBlock { sender, .. }) => {
+                        let _ = sender.send(Ok(()));
+                    }
+                    _ => panic!("BlockchainEvent must match BlockchainEvent::AddBlock"),
+                }
+            }
+        });
+
+        let build_id = uuid::Uuid::new_v4();
+        tokio::spawn(async move {
+            loop {
+                match build_event_receiver.recv().await {
+                    Some(BuildEvent::Start { sender, .. }) => {
+                        let _ = sender.send(Ok(build_id.to_string()));
+                    }
+                    _ => {
+                        panic!("BuildEvent must match BuildEvent::Start")
+                    }
+                }
+            }
+        });
+
+        tokio::spawn(async move {
+            loop {
+                match p2p_command_receiver.recv().await {
+                    Some(Command::ListPeers { sender, .. }) => {
+                        let _ = sender.send(HashSet::new());
+                    }
+                    _ => panic!("Command must match Command::ListPeers"),
+                }
+            }
+        });
+
+        artifact_service
+            .transparency_log_service
+            .add_authorized_node(p2p_client.local_peer_id)
+            .await
+            .expect("Error adding authorized node");
+
+        let filter = make_node_routes(artifact_service, p2p_client);
+        let request = RequestMavenBuild {
+            gav: "commons-codec:commons-codec:1.15".to_owned(),
+        };
+        let response = warp::test::request()
+            .method("POST")
+            .path("/build/maven")
+            .json(&request)
+            .reply(&filter)
+            .await;
+
+        assert_eq!(response.status(), 200);
+
+        let build_id_result: BuildSuccessResponse =
+            serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(build_id_result.build_id.unwrap(), build_id.to_string());
+
+        test_util::tests::teardown(tmp_dir);
+    }
+
+    #[tokio::test]
+    async fn node_routes_search_artifacts() {
+        let tmp_dir = test_util::tests::setup();
+
+        let (p2p_client, _) = test_util::tests::create_p2p_client();
+        let (artifact_service, ..) =
+            test_util::tests::create_artifact_service_with_p2p_client(&tmp_dir, p2p_client.clone());
+
+        let filter = make_node_routes(artifact_service, p2p_client);
+        let response = warp::test::request()
+            .method("GET")
+            .path("/artifacts?type=docker&name=alpine&version=3.15.2&limit=10")
+            .reply(&filter)
+            .await;
+
+        assert_eq!(response.status(), 200);
+        let artifact_page: ArtifactPage = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(artifact_page.total, 0);
+        assert!(artifact_page.artifacts.is_empty());
+
+        let response = warp::test::request()
+            .method("GET")
+            .path("/artifacts?type=npm")
+            .reply(&filter.recover(custom_recover))
+            .await;
+
+        assert_eq!(response.status(), 400);
+
+        test_util::tests::teardown(tmp_dir);
+    }
+
+    #[tokio::test]
+    async fn node_routes_build_history() {
+        let tmp_dir = test_util::tests::setup();
+
+        let (p2p_client, _) = test_util::tests::create_p2p_client();
+        let (artifact_service, _, mut build_event_receiver) =
+            test_util::tests::create_artifact_service_with_p2p_client(&tmp_dir, p2p_client.clone());
+
+        let build_record = BuildRecord {
+            build_id: "build_id".to_owned(),
+            package_type: PackageType::Docker,
+            package_specific_id: "alpine:3.15.2".to_owned(),
+            requester: None,
+            trigger: BuildTrigger::FromSource,
+            started_at: 1_700_000_000,
+            finished_at: None,
+            outcome: BuildOutcome::Running,
+            failure: None,
+            artifacts: vec![],
+            attempt: 1,
+            previous_attempt: None,
+        };
+        let expected_build_records = vec![build_record];
+        let build_records = expected_build_records.clone();
+        tokio::spawn(async move {
+            match build_event_receiver.recv().await {
+                Some(BuildEvent::History { query, sender }) => {
+                    assert_eq!(
+                        query,
+                        BuildHistoryQuery {
+                            package: Some("alpine".to_owned()),
+                            status: Some(BuildOutcome::Running),
+                            since: Some(1_600_000_000),
+                        }
+                    );
+                    let _ = sender.send(build_records);
+                }
+                _ => panic!("BuildEvent must match BuildEvent::History"),
+            }
+        });
+
+        let filter = make_node_routes(artifact_service, p2p_client);
+        let response = warp::test::request()
+            .method("GET")
+            .path("/builds?package=alpine&status=running&since=1600000000")
+            .reply(&filter)
+            .await;
+
+        assert_eq!(response.status(), 200);
+        let build_records: Vec<BuildRecord> = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(build_records, expected_build_records);
+
+        test_util::tests::teardown(tmp_dir);
+    }
+
+    #[tokio::test]
+    async fn node_routes_build_rerun() {
+        let tmp_dir = test_util::tests::setup();
+
+        let (p2p_client, _) = test_util::tests::create_p2p_client();
+        let (artifact_service, _, mut build_event_receiver) =
+            test_util::tests::create_artifact_service_with_p2p_client(&tmp_dir, p2p_client.clone());
+
+        let local_peer_id = p2p_client.local_peer_id.to_string();
+        tokio::spawn(async move {
+            loop {
+                match build_event_receiver.recv().await {
+                    Some(BuildEvent::Rerun {
+                        build_id,
+                        requester,
+                        sender,
+                    }) => {
+                        assert_eq!(requester, Some(local_peer_id.clone()));
+                        let result = match build_id.as_str() {
+                            "failed_build_id" => Ok("new_build_id".to_owned()),
+                            _ => Err(BuildError::BuildNotFound(build_id)),
+                        };
+                        let _ = sender.send(result);
+                    }
+                    _ => panic!("BuildEvent must match BuildEvent::Rerun"),
+                }
+            }
+        });
+
+        let filter = make_node_routes(artifact_service, p2p_client).recover(custom_recover);
+        let response = warp::test::request()
+            .method("POST")
+            .path("/build/rerun")
+            .json(&RequestBuildRerun {
+                build_id: "failed_build_id".to_owned(),
+            })
+            .reply(&filter)
+            .await;
+
+        assert_eq!(response.status(), 200);
+        let build_id: String = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(build_id, "new_build_id");
+
+        let response = warp::test::request()
+            .method("POST")
+            .path("/build/rerun")
+            .json(&RequestBuildRerun {
+                build_id: "unknown_build_id".to_owned(),
+            })
+            .reply(&filter)
+            .await;
+
+        assert_eq!(response.status(), 400);
+
+        test_util::tests::teardown(tmp_dir);
+    }
+
+    #[tokio::test]
+    async fn node_routes_build_reproduce() {
+        let tmp_dir = test_util::tests::setup();
+
+        let (p2p_client, _) = test_util::tests::create_p2p_client();
+        let (artifact_service, _, mut build_event_receiver) =
+            test_util::tests::create_artifact_service_with_p2p_client(&tmp_dir, p2p_client.clone());
+
+        tokio::spawn(async move {
+            loop {
+                match build_event_receiver.recv().await {
+                    Some(BuildEvent::Reproduce {
+                        pinning_snapshot_id,
+                        sender,
+                        ..
+                    }) => {
+                        let result = match pinning_snapshot_id.as_str() {
+                            "abcd" => Ok("new_build_id".to_owned()),
+                            _ => Err(BuildError::PinningSnapshotNotFound(pinning_snapshot_id)),
+                        };
+                        let _ = sender.send(result);
+                    }
+                    _ => panic!("BuildEvent must match BuildEvent::Reproduce"),
+                }
+            }
+        });
+
+        let filter = make_node_routes(artifact_service, p2p_client).recover(custom_recover);
+        let response = warp::test::request()
+            .method("POST")
+            .path("/build/reproduce")
+            .json(&RequestBuildReproduce {
+                pinning_snapshot: "abcd".to_owned(),
+            })
+            .reply(&filter)
+            .await;
+
+        assert_eq!(response.status(), 200);
+        let build_id: String = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(build_id, "new_build_id");
+
+        let response = warp::test::request()
+            .method("POST")
+            .path("/build/reproduce")
+            .json(&RequestBuildReproduce {
+                pinning_snapshot: "ef01".to_owned(),
+            })
+            .reply(&filter)
+            .await;
+
+        assert_eq!(response.status(), 400);
+
+        test_util::tests::teardown(tmp_dir);
+    }
+
+    #[tokio::test]
+    async fn node_routes_peers() {
+        let tmp_dir = test_util::tests::setup();
+
+        let (p2p_client, mut p2p_command_receiver) = test_util::tests::create_p2p_client();
+        let (artifact_service, ..) =
+            test_util::tests::create_artifact_service_with_p2p_client(&tmp_dir, p2p_client.clone());
+
+        tokio::spawn(async move {
+            loop {
+                match p2p_command_receiver.recv().await {
+                    Some(Command::ListPeers { sender, .. }) => {
+                        let mut set = HashSet::new();
+                        set.insert(p2p_client.local_peer_id);
+                        let _ = sender.send(set);
+                    }
+                    _ => panic!("Command must match Command::ListPeers"),
+                }
+            }
+        });
+
+        let filter = make_node_routes(artifact_service, p2p_client.clone());
+        let response = warp::test::request().path("/peers").reply(&filter).await;
+
+        let expected_body =
+            bytes::Bytes::from(serde_json::to_string(&vec![p2p_client.local_peer_id]).unwrap());
+
+        assert_eq!(response.status(), 200);
+        assert_eq!(expected_body, str::from_utf8(response.body()).unwrap());
+
+        test_util::tests::teardown(tmp_dir);
+    }
+
+    #[tokio::test]
+    async fn network_routes() {
+        let peer_id = libp2p::PeerId::random();
+        let network = FakeNetwork::new().with_artifact(peer_id, "artifact_id", b"content");
+        let p2p_client = network.client();
+        p2p_client.query_metrics.record(
+            QueryKind::Bootstrap,
+            &libp2p::kad::QueryStats::empty(),
+            true,
+        );
+
+        let filter = make_network_routes(p2p_client);
+
+        let response = warp::test::request()
+            .path("/network/query-stats")
+            .reply(&filter)
+            .await;
+        assert_eq!(response.status(), 200);
+        let query_stats =
+            serde_json::from_slice::<BTreeMap<QueryKind, QueryKindStats>>(response.body()).unwrap();
+        assert_eq!(query_stats[&QueryKind::Bootstrap].queries, 1);
+
+        let response = warp::test::request()
+            .path("/network/debug-lookup/artifact_id")
+            .reply(&filter)
+            .await;
+        assert_eq!(response.status(), 200);
+        let lookup_trace = serde_json::from_slice::<LookupTrace>(response.body()).unwrap();
+        assert_eq!(lookup_trace.artifact_id, "artifact_id");
+        assert_eq!(lookup_trace.providers, vec![peer_id.to_string()]);
+    }
+
+    #[tokio::test]
+    async fn secret_routes_require_admin_token() {
+        let tmp_dir = test_util::tests::setup();
+
+        let secret_store = SecretStore::new(tmp_dir.join("secrets"), b"key_material");
+        let filter = make_secret_routes(secret_store.clone(), Some("admin_token".to_owned()))
+            .recover(custom_recover);
+
+        let request_set_secret = RequestSetSecret {
+            namespace: "com.myorg".to_owned(),
+            name: "GIT_TOKEN".to_owned(),
+            value: Secret::new("s3cr3t"),
+        };
+
+        let response = warp::test::request()
+            .method("POST")
+            .path("/secret/set")
+            .header("Authorization", "Bearer wrong_token")
+            .json(&request_set_secret)
+            .reply(&filter)
+            .await;
+        assert_eq!(response.status(), 401);
+        assert!(secret_store.list().unwrap().is_empty());
+
+        let response = warp::test::request()
+            .method("POST")
+            .path("/secret/set")
+            .header("Authorization", "Bearer admin_token")
+            .json(&request_set_secret)
+            .reply(&filter)
+            .await;
+        assert_eq!(response.status(), 201);
+
+        let response = warp::test::request()
+            .path("/secret/list")
+            .header("Authorization", "Bearer admin_token")
+            .reply(&filter)
+            .await;
+        assert_eq!(response.status(), 200);
+        assert!(!str::from_utf8(response.body()).unwrap().contains("s3cr3t"));
+        assert_eq!(
+            serde_json::from_slice::<Vec<SecretDescriptor>>(response.body()).unwrap(),
+            secret_store.list().unwrap()
+        );
+
+        test_util::tests::teardown(tmp_dir);
+    }
+
+    #[tokio::test]
+    async fn alert_routes_require_admin_token() {
+        let alert_service = AlertService::new(vec![]).unwrap();
+        alert_service.record_hash_mismatch("artifact_id", &libp2p::PeerId::random());
+        let alert = alert_service
+            .record_hash_mismatch("artifact_id", &libp2p::PeerId::random())
+            .unwrap();
+        let filter = make_alert_routes(alert_service, Some("admin_token".to_owned()))
+            .recover(custom_recover);
+
+        let response = warp::test::request().path("/alerts").reply(&filter).await;
+        assert_eq!(response.status(), 401);
+
+        let response = warp::test::request()
+            .path("/alerts")
+            .header("Authorization", "Bearer admin_token")
+            .reply(&filter)
+            .await;
+        assert_eq!(response.status(), 200);
+        assert_eq!(
+            serde_json::from_slice::<Vec<Alert>>(response.body()).unwrap(),
+            vec![alert]
+        );
+    }
+
+    #[tokio::test]
+    async fn peer_alias_routes_require_admin_token_to_set_aliases() {
+        let peer_aliases = PeerAliases::default();
+        let filter = make_peer_alias_routes(peer_aliases.clone(), Some("admin_token".to_owned()))
+            .recover(custom_recover);
+
+        let peer_id = libp2p::PeerId::random();
+        let request_set_peer_alias = RequestSetPeerAlias {
+            peer_id: peer_id.to_string(),
+            alias: Some("node-b".to_owned()),
+        };
+
+        let response = warp::test::request()
+            .method("POST")
+            .path("/peers/alias")
+            .json(&request_set_peer_alias)
+            .reply(&filter)
+            .await;
+        assert_eq!(response.status(), 401);
+        assert_eq!(peer_aliases.alias(&peer_id), None);
+
+        let response = warp::test::request()
+            .method("POST")
+            .path("/peers/alias")
+            .header("Authorization", "Bearer admin_token")
+            .json(&request_set_peer_alias)
+            .reply(&filter)
+            .await;
+        assert_eq!(response.status(), 204);
+
+        let response = warp::test::request()
+            .method("POST")
+            .path("/peers/alias")
+            .header("Authorization", "Bearer admin_token")
+            .json(&RequestSetPeerAlias {
+                peer_id: peer_id.to_string(),
+                alias: Some("not valid".to_owned()),
+            })
+            .reply(&filter)
+            .await;
+        assert_eq!(response.status(), 400);
+
+        let response = warp::test::request()
+            .path("/peers/aliases")
+            .reply(&filter)
+            .await;
+        assert_eq!(response.status(), 200);
+        assert_eq!(
+            serde_json::from_slice::<Vec<PeerAlias>>(response.body()).unwrap(),
+            vec![PeerAlias {
+                peer_id: peer_id.to_string(),
+                alias: "node-b".to_owned(),
+                source: PeerAliasSource::Local,
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn maintenance_routes_require_admin_token_to_announce_maintenance() {
+        let network = FakeNetwork::new();
+        let p2p_client = network.client();
+        let filter = make_maintenance_routes(p2p_client.clone(), Some("admin_token".to_owned()))
+            .recover(custom_recover);
+
+        let request_announce_maintenance = RequestAnnounceMaintenance {
+            ttl_secs: 3600,
+            reason: Some("upgrade".to_owned()),
+        };
+        let response = warp::test::request()
+            .method("POST")
+            .path("/maintenance")
+            .json(&request_announce_maintenance)
+            .reply(&filter)
+            .await;
+        assert_eq!(response.status(), 401);
+
+        let response = warp::test::request()
+            .method("POST")
+            .path("/maintenance")
+            .header("Authorization", "Bearer admin_token")
+            .json(&request_announce_maintenance)
+            .reply(&filter)
+            .await;
+        assert_eq!(response.status(), 204);
+
+        let peer_id = libp2p::PeerId::random();
+        p2p_client.peer_maintenance.record(
+            &peer_id,
+            MaintenanceAnnouncement::new(Duration::from_secs(3600), Some("upgrade".to_owned())),
+        );
+        let response = warp::test::request()
+            .path("/peers/maintenance")
+            .reply(&filter)
+            .await;
+        assert_eq!(response.status(), 200);
+        let draining_peers: Vec<DrainingPeer> = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(draining_peers.len(), 1);
+        assert_eq!(draining_peers[0].peer_id, peer_id.to_string());
+        assert_eq!(
+            draining_peers[0].announcement.reason.as_deref(),
+            Some("upgrade")
+        );
+    }
+
+    #[tokio::test]
+    async fn publisher_routes_import_requires_admin_token_and_authorized_node() {
+        let tmp_dir = test_util::tests::setup();
+
+        let (artifact_service, ..) = test_util::tests::create_artifact_service(&tmp_dir);
+        let filter = make_publisher_routes(artifact_service, Some("admin_token".to_owned()))
+            .recover(custom_recover);
+
+        let request_import_artifacts = RequestImportArtifacts {
+            package_type: PackageType::Maven2,
+            package_specific_id: "com.myorg:my-artifact:1.0.0".to_owned(),
+            source: "maven-repo".to_owned(),
+            artifacts: vec![ImportedArtifact {
+                package_specific_artifact_id: "com.myorg/my-artifact/1.0.0/my-artifact-1.0.0.jar"
+                    .to_owned(),
+                content: "amFy".to_owned(),
+            }],
+        };
+
+        let response = warp::test::request()
+            .method("POST")
+            .path("/package/import")
+            .json(&request_import_artifacts)
             .reply(&filter)
             .await;
+        assert_eq!(response.status(), 401);
 
-        assert_eq!(response.status(), 200);
-
-        let build_id_result: BuildSuccessResponse =
-            serde_json::from_slice(response.body()).unwrap();
-        assert_eq!(build_id_result.build_id.unwrap(), build_id.to_string());
+        let response = warp::test::request()
+            .method("POST")
+            .path("/package/import")
+            .header("Authorization", "Bearer admin_token")
+            .json(&request_import_artifacts)
+            .reply(&filter)
+            .await;
+        assert_eq!(response.status(), 400);
 
         test_util::tests::teardown(tmp_dir);
     }
 
     #[tokio::test]
-    async fn node_routes_build_maven() {
+    async fn seed_sync_routes() {
         let tmp_dir = test_util::tests::setup();
 
-        let (p2p_client, mut p2p_command_receiver) = test_util::tests::create_p2p_client();
-        let (artifact_service, mut blockchain_event_receiver, mut build_event_receiver) =
-            test_util::tests::create_artifact_service_with_p2p_client(&tmp_dir, p2p_client.clone());
+        // the p2p commands are never answered, so the sync keeps running
+        let (artifact_service, _blockchain_event_receiver, _, _p2p_command_receiver) =
+            test_util::tests::create_artifact_service(&tmp_dir);
+        let filter = make_seed_sync_routes(artifact_service, Some("admin_token".to_owned()))
+            .recover(custom_recover);
 
-        tokio::spawn(async move {
-            loop {
-                match blockchain_event_receiver.recv().await {
-                    Some(BlockchainEvent::AddBlock { sender, .. }) => {
-                        let _ = sender.send(Ok(()));
-                    }
-                    _ => panic!("BlockchainEvent must match BlockchainEvent::AddBlock"),
-                }
-            }
-        });
+        let response = warp::test::request()
+            .path("/seed-sync/status")
+            .reply(&filter)
+            .await;
+        assert_eq!(response.status(), 200);
+        let progress: SeedSyncProgress = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(progress.state, SeedSyncState::NotStarted);
 
-        let build_id = uuid::Uuid::new_v4();
-        tokio::spawn(async move {
-            loop {
-                match build_event_receiver.recv().await {
-                    Some(BuildEvent::Start { sender, .. }) => {
-                        let _ = sender.send(Ok(build_id.to_string()));
-                    }
-                    _ => {
-                        panic!("BuildEvent must match BuildEvent::Start")
-                    }
-                }
-            }
-        });
+        let seed = format!("/ip4/127.0.0.1/tcp/44000/p2p/{}", libp2p::PeerId::random());
+        let response = warp::test::request()
+            .method("POST")
+            .path("/seed-sync")
+            .json(&RequestSeedSync { seed: seed.clone() })
+            .reply(&filter)
+            .await;
+        assert_eq!(response.status(), 401);
 
-        tokio::spawn(async move {
-            loop {
-                match p2p_command_receiver.recv().await {
-                    Some(Command::ListPeers { sender, .. }) => {
-                        let _ = sender.send(HashSet::new());
-                    }
-                    _ => panic!("Command must match Command::ListPeers"),
-                }
-            }
-        });
+        let response = warp::test::request()
+            .method("POST")
+            .path("/seed-sync")
+            .header("Authorization", "Bearer admin_token")
+            .json(&RequestSeedSync {
+                seed: "seed".to_owned(),
+            })
+            .reply(&filter)
+            .await;
+        assert_eq!(response.status(), 400);
 
-        artifact_service
-            .transparency_log_service
-            .add_authorized_node(p2p_client.local_peer_id)
-            .await
-            .expect("Error adding authorized node");
+        let response = warp::test::request()
+            .method("POST")
+            .path("/seed-sync")
+            .header("Authorization", "Bearer admin_token")
+            .json(&RequestSeedSync { seed: seed.clone() })
+            .reply(&filter)
+            .await;
+        assert_eq!(response.status(), 202);
+        let progress: SeedSyncProgress = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(
+            (progress.seed, progress.state),
+            (Some(seed.clone()), SeedSyncState::Running)
+        );
 
-        let filter = make_node_routes(artifact_service, p2p_client);
-        let request = RequestMavenBuild {
-            gav: "commons-codec:commons-codec:1.15".to_owned(),
+        let response = warp::test::request()
+            .method("POST")
+            .path("/seed-sync")
+            .header("Authorization", "Bearer admin_token")
+            .json(&RequestSeedSync { seed })
+            .reply(&filter)
+            .await;
+        assert_eq!(response.status(), 400);
+
+        test_util::tests::teardown(tmp_dir);
+    }
+
+    #[tokio::test]
+    async fn subscription_routes_are_scoped_to_api_key() {
+        let tmp_dir = test_util::tests::setup();
+
+        let subscription_service = SubscriptionService::new(tmp_dir.join("subscriptions.json"));
+        let filter = make_subscription_routes(subscription_service.clone()).recover(custom_recover);
+
+        let request_subscribe = RequestSubscribe {
+            package_type: Some(PackageType::Docker),
+            pattern: "library/alpine:*".to_owned(),
+            webhook_url: None,
         };
+
         let response = warp::test::request()
             .method("POST")
-            .path("/build/maven")
-            .json(&request)
+            .path("/subscription/add")
+            .json(&request_subscribe)
+            .reply(&filter)
+            .await;
+        assert_eq!(response.status(), 401);
+
+        let response = warp::test::request()
+            .method("POST")
+            .path("/subscription/add")
+            .header("Authorization", "Bearer api_key")
+            .json(&request_subscribe)
             .reply(&filter)
             .await;
+        assert_eq!(response.status(), 201);
+        let subscription: Subscription = serde_json::from_slice(response.body()).unwrap();
 
+        let response = warp::test::request()
+            .path("/subscription/list")
+            .header("Authorization", "Bearer other_api_key")
+            .reply(&filter)
+            .await;
         assert_eq!(response.status(), 200);
+        assert!(serde_json::from_slice::<Vec<Subscription>>(response.body())
+            .unwrap()
+            .is_empty());
 
-        let build_id_result: BuildSuccessResponse =
-            serde_json::from_slice(response.body()).unwrap();
-        assert_eq!(build_id_result.build_id.unwrap(), build_id.to_string());
+        let response = warp::test::request()
+            .method("POST")
+            .path("/subscription/remove")
+            .header("Authorization", "Bearer other_api_key")
+            .json(&RequestUnsubscribe {
+                subscription_id: subscription.id.clone(),
+            })
+            .reply(&filter)
+            .await;
+        assert_eq!(response.status(), 400);
+        assert_eq!(
+            subscription_service.list("api_key").unwrap(),
+            vec![subscription]
+        );
 
         test_util::tests::teardown(tmp_dir);
     }
 
     #[tokio::test]
-    async fn node_routes_peers() {
+    async fn subscription_events_are_streamed_over_websocket() {
         let tmp_dir = test_util::tests::setup();
 
-        let (p2p_client, mut p2p_command_receiver) = test_util::tests::create_p2p_client();
-        let (artifact_service, ..) =
-            test_util::tests::create_artifact_service_with_p2p_client(&tmp_dir, p2p_client.clone());
+        let subscription_service = SubscriptionService::new(tmp_dir.join("subscriptions.json"));
+        let filter = make_subscription_routes(subscription_service.clone()).recover(custom_recover);
 
-        tokio::spawn(async move {
-            loop {
-                match p2p_command_receiver.recv().await {
-                    Some(Command::ListPeers { sender, .. }) => {
-                        let mut set = HashSet::new();
-                        set.insert(p2p_client.local_peer_id);
-                        let _ = sender.send(set);
-                    }
-                    _ => panic!("Command must match Command::ListPeers"),
-                }
-            }
-        });
+        let subscription = subscription_service
+            .subscribe("api_key", None, "library/alpine:*", None)
+            .unwrap();
 
-        let filter = make_node_routes(artifact_service, p2p_client.clone());
-        let response = warp::test::request().path("/peers").reply(&filter).await;
+        let mut websocket = warp::test::ws()
+            .path("/subscription/events")
+            .header("Authorization", "Bearer api_key")
+            .handshake(filter)
+            .await
+            .unwrap();
 
-        let expected_body =
-            bytes::Bytes::from(serde_json::to_string(&vec![p2p_client.local_peer_id]).unwrap());
+        let transparency_log = TransparencyLog::from(AddArtifactRequest {
+            package_type: PackageType::Docker,
+            package_specific_id: "library/alpine:3.17".to_owned(),
+            num_artifacts: 1,
+            package_specific_artifact_id: "library/alpine@sha256:1234".to_owned(),
+            artifact_hash: "1234".to_owned(),
+        });
+        subscription_service.notify(&transparency_log).unwrap();
 
-        assert_eq!(response.status(), 200);
-        assert_eq!(expected_body, str::from_utf8(response.body()).unwrap());
+        let message = websocket.recv().await.unwrap();
+        assert_eq!(
+            serde_json::from_str::<Notification>(message.to_str().unwrap()).unwrap(),
+            Notification {
+                subscription_id: subscription.id,
+                transparency_log,
+            }
+        );
 
         test_util::tests::teardown(tmp_dir);
     }
@@ -364,6 +1534,10 @@ mod tests {
                             peers_count: 0,
                             peer_addrs: Vec::new(),
                             peer_id: local_peer_id.to_string(),
+                            alias: None,
+                            version: "0.2.5".to_owned(),
+                            peer_versions: Default::default(),
+                            capabilities: Default::default(),
                         };
 
                         let _ = sender.send(status);
@@ -380,6 +1554,10 @@ mod tests {
             peers_count: 0,
             peer_id: p2p_client.local_peer_id.to_string(),
             peer_addrs: Vec::new(),
+            alias: None,
+            version: "0.2.5".to_owned(),
+            peer_versions: Default::default(),
+            capabilities: Default::default(),
         };
 
         let expected_body = bytes::Bytes::from(serde_json::to_string(&expected_status).unwrap());
@@ -387,6 +1565,55 @@ mod tests {
         assert_eq!(response.status(), 200);
         assert_eq!(expected_body, str::from_utf8(response.body()).unwrap());
 
+        let response = warp::test::request()
+            .path("/status/provide")
+            .reply(&filter)
+            .await;
+        assert_eq!(response.status(), 200);
+        assert_eq!(
+            serde_json::from_slice::<ProvideProgress>(response.body()).unwrap(),
+            ProvideProgress::default()
+        );
+
+        let response = warp::test::request()
+            .path("/status/scrub")
+            .reply(&filter)
+            .await;
+        assert_eq!(response.status(), 200);
+        assert_eq!(
+            serde_json::from_slice::<ScrubStats>(response.body()).unwrap(),
+            ScrubStats::default()
+        );
+
+        let response = warp::test::request()
+            .path("/status/dedup")
+            .reply(&filter)
+            .await;
+        assert_eq!(response.status(), 200);
+        assert_eq!(
+            serde_json::from_slice::<DedupStats>(response.body()).unwrap(),
+            DedupStats::default()
+        );
+
+        let response = warp::test::request()
+            .path("/status/storage")
+            .reply(&filter)
+            .await;
+        assert_eq!(response.status(), 200);
+        let storage_stats = serde_json::from_slice::<StorageStats>(response.body()).unwrap();
+        assert_eq!(storage_stats.artifacts, 0);
+        assert_eq!(storage_stats.total_bytes, 0);
+
+        let response = warp::test::request()
+            .path("/status/transfers")
+            .reply(&filter)
+            .await;
+        assert_eq!(response.status(), 200);
+        assert_eq!(
+            serde_json::from_slice::<Vec<TransferProgress>>(response.body()).unwrap(),
+            Vec::new()
+        );
+
         test_util::tests::teardown(tmp_dir);
     }
 
@@ -463,6 +1690,202 @@ mod tests {
         .await;
     }
 
+    #[tokio::test]
+    async fn transparency_log_routes_blocks() {
+        let tmp_dir = test_util::tests::setup();
+
+        let (p2p_client, _) = test_util::tests::create_p2p_client();
+        let (artifact_service, mut blockchain_event_receiver, ..) =
+            test_util::tests::create_artifact_service_with_p2p_client(&tmp_dir, p2p_client);
+
+        let keypair = identity::ed25519::Keypair::generate();
+        let address = Address::from(identity::PublicKey::Ed25519(keypair.public()));
+        let transparency_log = TransparencyLog::from(AddArtifactRequest {
+            package_type: PackageType::Maven2,
+            package_specific_id: "pyrsia:adapter:0.1".to_owned(),
+            num_artifacts: 1,
+            package_specific_artifact_id: "pyrsia:adapter:0.1".to_owned(),
+            artifact_hash: "test_hash".to_owned(),
+        });
+        let genesis = Block::new(HashDigest::new(b""), 0, vec![], &keypair);
+        let block = Block::new(
+            genesis.header.hash(),
+            1,
+            vec![Transaction::new(
+                TransactionType::Create,
+                address,
+                serde_json::to_vec(&transparency_log).unwrap(),
+                &keypair,
+            )],
+            &keypair,
+        );
+        let genesis_block = genesis.clone();
+        let blocks = vec![genesis, block.clone()];
+
+        tokio::spawn(async move {
+            loop {
+                match blockchain_event_receiver.recv().await {
+                    Some(BlockchainEvent::HandleQueryBlockOrdinal { sender }) => {
+                        let _ = sender.send(Ok(1));
+                    }
+                    Some(BlockchainEvent::PullBlocksLocal { start, end, sender }) => {
+                        let _ = sender.send(Ok(blocks[start as usize..=end as usize].to_vec()));
+                    }
+                    Some(BlockchainEvent::ExportBlocks { sender }) => {
+                        let _ = sender.send(Ok(bincode::serialize(&blocks).unwrap()));
+                    }
+                    Some(BlockchainEvent::AuditBlocks { sender }) => {
+                        let _ = sender.send(Ok(audit_chain(&blocks)));
+                    }
+                    _ => panic!("BlockchainEvent must query, pull, export or audit local blocks"),
+                }
+            }
+        });
+
+        let filter = make_transparency_log_routes(artifact_service).recover(custom_recover);
+
+        let response = warp::test::request()
+            .path("/blocks?limit=1")
+            .reply(&filter)
+            .await;
+        assert_eq!(response.status(), 200);
+        let block_views: Vec<BlockView> = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(block_views.len(), 1);
+        assert_eq!(block_views[0].ordinal, 1);
+        assert_eq!(block_views[0].transparency_logs, vec![transparency_log]);
+
+        let response = warp::test::request()
+            .path(&format!("/blocks/{}", hex_hash(&block.header.hash())))
+            .reply(&filter)
+            .await;
+        assert_eq!(response.status(), 200);
+        let block_view: BlockView = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(block_view, block_views[0]);
+
+        let response = warp::test::request().path("/blocks/0").reply(&filter).await;
+        assert_eq!(response.status(), 200);
+        let block_view: BlockView = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(block_view.ordinal, 0);
+
+        let response = warp::test::request().path("/blocks/2").reply(&filter).await;
+        assert_eq!(response.status(), 400);
+
+        let response = warp::test::request()
+            .path("/blocks/export")
+            .reply(&filter)
+            .await;
+        assert_eq!(response.status(), 200);
+        let exported_blocks: Vec<Block> = bincode::deserialize(response.body()).unwrap();
+        assert_eq!(exported_blocks, vec![genesis_block, block]);
+
+        let response = warp::test::request()
+            .path("/blocks/audit")
+            .reply(&filter)
+            .await;
+        assert_eq!(response.status(), 200);
+        let audit: ChainAudit = serde_json::from_slice(response.body()).unwrap();
+        assert!(audit.is_valid());
+        assert_eq!(audit.valid_blocks, 2);
+
+        test_util::tests::teardown(tmp_dir);
+    }
+
+    #[tokio::test]
+    async fn transparency_log_inclusion_proof() {
+        setup_and_execute(|ctx| async move {
+            let transparency_log =
+                add_artifact(&ctx.log, PackageType::Maven2, "pyrsia:adapter:0.1").await;
+            let filter = make_transparency_log_routes(ctx.artifact_service);
+
+            let response = warp::test::request()
+                .path("/transparency-log/head")
+                .reply(&filter)
+                .await;
+            assert_eq!(response.status(), 200);
+            let tree_head = serde_json::from_slice::<TreeHead>(response.body()).unwrap();
+            assert_eq!(tree_head.tree_size, 1);
+
+            let response = warp::test::request()
+                .path(&format!(
+                    "/transparency-log/proof/{}",
+                    transparency_log.artifact_id
+                ))
+                .reply(&filter)
+                .await;
+            assert_eq!(response.status(), 200);
+            let inclusion_proof =
+                serde_json::from_slice::<TransparencyLogInclusionProof>(response.body()).unwrap();
+            assert_eq!(inclusion_proof.transparency_log, transparency_log);
+            assert_eq!(inclusion_proof.proof.root_hash, tree_head.root_hash);
+            assert!(inclusion_proof.verify());
+
+            let response = warp::test::request()
+                .path("/transparency-log/consistency?old_size=0&new_size=1")
+                .reply(&filter)
+                .await;
+            assert_eq!(response.status(), 200);
+            let consistency_proof =
+                serde_json::from_slice::<ConsistencyProof>(response.body()).unwrap();
+            assert_eq!(consistency_proof.new_root_hash, tree_head.root_hash);
+            assert!(consistency_proof.verify());
+
+            let response = warp::test::request()
+                .path("/transparency-log/checkpoints")
+                .reply(&filter)
+                .await;
+            assert_eq!(response.status(), 200);
+            assert_eq!(response.body(), "[]");
+
+            let response = warp::test::request()
+                .path("/transparency-log/export?format=jsonl")
+                .reply(&filter)
+                .await;
+            assert_eq!(response.status(), 200);
+            let export = str::from_utf8(response.body()).unwrap();
+            assert_eq!(
+                serde_json::from_str::<TransparencyLog>(export.trim_end()).unwrap(),
+                transparency_log
+            );
+
+            let response = warp::test::request()
+                .path("/transparency-log/query?package_type=maven&operation=AddArtifact")
+                .reply(&filter)
+                .await;
+            assert_eq!(response.status(), 200);
+            let page = serde_json::from_slice::<TransparencyLogPage>(response.body()).unwrap();
+            assert_eq!(page.total, 1);
+            assert_eq!(page.transparency_logs, vec![transparency_log]);
+
+            let response = warp::test::request()
+                .path("/inspect/logs/stream?since=0&wait_secs=0")
+                .reply(&filter)
+                .await;
+            assert_eq!(response.status(), 200);
+            let batch = serde_json::from_slice::<TransparencyLogBatch>(response.body()).unwrap();
+            assert_eq!(batch.transparency_logs, vec![transparency_log]);
+
+            let response = warp::test::request()
+                .path(&format!(
+                    "/inspect/logs/stream?since={}&wait_secs=0",
+                    batch.next_cursor
+                ))
+                .reply(&filter)
+                .await;
+            assert_eq!(response.status(), 200);
+            let next_batch =
+                serde_json::from_slice::<TransparencyLogBatch>(response.body()).unwrap();
+            assert!(next_batch.transparency_logs.is_empty());
+            assert_eq!(next_batch.next_cursor, batch.next_cursor);
+
+            let response = warp::test::request()
+                .path("/transparency-log/query?package_type=npm")
+                .reply(&filter.recover(custom_recover))
+                .await;
+            assert_eq!(response.status(), 400);
+        })
+        .await;
+    }
+
     #[tokio::test]
     async fn inspect_log_maven_csv() {
         setup_and_execute(|ctx| async {
@@ -0,0 +1,444 @@
+/*
+   Copyright 2021 JFrog Ltd
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+*/
+
+use crate::artifact_service::model::PackageType;
+use crate::transparency_log::log::{Operation, TransparencyLog};
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use thiserror::Error;
+use tokio::sync::broadcast;
+
+const NOTIFICATION_CHANNEL_CAPACITY: usize = 256;
+
+#[derive(Debug, Error)]
+pub enum SubscriptionError {
+    #[error("Failure while accessing the subscriptions: {0}")]
+    StorageFailure(#[from] io::Error),
+    #[error("Failure while (de)serializing subscriptions: {0}")]
+    SerializationFailure(#[from] serde_json::Error),
+    #[error("Invalid subscription: {0}")]
+    InvalidSubscription(String),
+    #[error("Subscription not found: {0}")]
+    SubscriptionNotFound(String),
+}
+
+/// A subscription to the packages whose package specific id matches `pattern`.
+/// The pattern is either the exact coordinates of a package version, like
+/// `com.myorg:my-artifact:1.1.0`, or a glob where `*` matches any sequence of
+/// characters and `?` matches a single character, like `library/alpine:3.*`.
+#[derive(Clone, Debug, Deserialize, Serialize, Eq, PartialEq)]
+pub struct Subscription {
+    pub id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub package_type: Option<PackageType>,
+    pub pattern: String,
+    /// The URL that notifications are posted to, in addition to the
+    /// WebSocket event stream.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub webhook_url: Option<String>,
+}
+
+impl Subscription {
+    pub fn matches(&self, transparency_log: &TransparencyLog) -> bool {
+        self.package_type.map_or(true, |package_type| {
+            transparency_log.package_type == Some(package_type)
+        }) && glob_match(&self.pattern, &transparency_log.package_specific_id)
+    }
+}
+
+/// Sent to subscribers when a transparency log that matches one of their
+/// subscriptions arrives.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct Notification {
+    pub subscription_id: String,
+    pub transparency_log: TransparencyLog,
+}
+
+// subscriber -> subscription id -> subscription
+type Subscriptions = BTreeMap<String, BTreeMap<String, Subscription>>;
+
+/// The subscription service notifies clients when package versions they are
+/// interested in become available, so they do not have to poll for them.
+///
+/// Subscriptions are stored per API key. The API key itself is not stored:
+/// subscribers are identified by a hash of their key. Notifications are
+/// delivered on the event stream of the subscriber and are posted to the
+/// webhook of the subscription, if it has one.
+#[derive(Clone)]
+pub struct SubscriptionService {
+    path: PathBuf,
+    lock: Arc<Mutex<()>>,
+    notification_sender: broadcast::Sender<(String, Notification)>,
+    http_client: reqwest::Client,
+}
+
+impl SubscriptionService {
+    pub fn new<P: AsRef<Path>>(path: P) -> Self {
+        let (notification_sender, _) = broadcast::channel(NOTIFICATION_CHANNEL_CAPACITY);
+        SubscriptionService {
+            path: path.as_ref().to_path_buf(),
+            lock: Default::default(),
+            notification_sender,
+            http_client: reqwest::Client::new(),
+        }
+    }
+
+    /// Add a subscription for the owner of `api_key`.
+    pub fn subscribe(
+        &self,
+        api_key: &str,
+        package_type: Option<PackageType>,
+        pattern: &str,
+        webhook_url: Option<String>,
+    ) -> Result<Subscription, SubscriptionError> {
+        if pattern.is_empty() {
+            return Err(SubscriptionError::InvalidSubscription(
+                "the pattern must not be empty".to_owned(),
+            ));
+        }
+        if let Some(webhook_url) = &webhook_url {
+            validate_webhook_url(webhook_url)?;
+        }
+
+        let subscription = Subscription {
+            id: uuid::Uuid::new_v4().to_string(),
+            package_type,
+            pattern: pattern.to_owned(),
+            webhook_url,
+        };
+
+        let _guard = self.lock.lock().unwrap();
+        let mut subscriptions = self.load()?;
+        subscriptions
+            .entry(subscriber_id(api_key))
+            .or_default()
+            .insert(subscription.id.clone(), subscription.clone());
+        self.save(&subscriptions)?;
+
+        Ok(subscription)
+    }
+
+    /// Remove a subscription of the owner of `api_key`.
+    pub fn unsubscribe(
+        &self,
+        api_key: &str,
+        subscription_id: &str,
+    ) -> Result<(), SubscriptionError> {
+        let subscriber = subscriber_id(api_key);
+
+        let _guard = self.lock.lock().unwrap();
+        let mut subscriptions = self.load()?;
+        let removed = match subscriptions.get_mut(&subscriber) {
+            Some(subscriber_subscriptions) => {
+                let removed = subscriber_subscriptions.remove(subscription_id).is_some();
+                if subscriber_subscriptions.is_empty() {
+                    subscriptions.remove(&subscriber);
+                }
+                removed
+            }
+            None => false,
+        };
+        if !removed {
+            return Err(SubscriptionError::SubscriptionNotFound(
+                subscription_id.to_owned(),
+            ));
+        }
+        self.save(&subscriptions)
+    }
+
+    /// List the subscriptions of the owner of `api_key`.
+    pub fn list(&self, api_key: &str) -> Result<Vec<Subscription>, SubscriptionError> {
+        let _guard = self.lock.lock().unwrap();
+        Ok(self
+            .load()?
+            .remove(&subscriber_id(api_key))
+            .map(|subscriber_subscriptions| subscriber_subscriptions.into_values().collect())
+            .unwrap_or_default())
+    }
+
+    /// Receive the notifications for the subscriptions of the owner of `api_key`.
+    pub fn notifications(&self, api_key: &str) -> NotificationReceiver {
+        NotificationReceiver {
+            subscriber: subscriber_id(api_key),
+            receiver: self.notification_sender.subscribe(),
+        }
+    }
+
+    /// Notify the subscribers of the subscriptions that match a newly arrived
+    /// transparency log. Only logs that add an artifact are notified.
+    pub fn notify(&self, transparency_log: &TransparencyLog) -> Result<(), SubscriptionError> {
+        if transparency_log.operation != Operation::AddArtifact {
+            return Ok(());
+        }
+
+        let subscriptions = {
+            let _guard = self.lock.lock().unwrap();
+            self.load()?
+        };
+
+        for (subscriber, subscriber_subscriptions) in subscriptions {
+            for subscription in subscriber_subscriptions.into_values() {
+                if !subscription.matches(transparency_log) {
+                    continue;
+                }
+
+                debug!(
+                    "Notify subscription {} of {}",
+                    subscription.id, transparency_log.package_specific_artifact_id
+                );
+                let notification = Notification {
+                    subscription_id: subscription.id.clone(),
+                    transparency_log: transparency_log.clone(),
+                };
+                if let Some(webhook_url) = subscription.webhook_url {
+                    self.post_webhook(webhook_url, notification.clone());
+                }
+                // sending only fails when no event streams are open
+                let _ = self
+                    .notification_sender
+                    .send((subscriber.clone(), notification));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn post_webhook(&self, webhook_url: String, notification: Notification) {
+        let http_client = self.http_client.clone();
+        tokio::spawn(async move {
+            if let Err(e) = http_client
+                .post(&webhook_url)
+                .json(&notification)
+                .send()
+                .await
+                .and_then(|response| response.error_for_status())
+            {
+                warn!(
+                    "Failed to deliver notification for subscription {} to {}: {}",
+                    notification.subscription_id, webhook_url, e
+                );
+            }
+        });
+    }
+
+    fn load(&self) -> Result<Subscriptions, SubscriptionError> {
+        match fs::read(&self.path) {
+            Ok(content) => Ok(serde_json::from_slice(&content)?),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Subscriptions::new()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn save(&self, subscriptions: &Subscriptions) -> Result<(), SubscriptionError> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let tmp_path = self.path.with_extension("tmp");
+        fs::write(&tmp_path, serde_json::to_vec(subscriptions)?)?;
+        fs::rename(&tmp_path, &self.path)?;
+        Ok(())
+    }
+}
+
+/// Receives the notifications of a single subscriber.
+pub struct NotificationReceiver {
+    subscriber: String,
+    receiver: broadcast::Receiver<(String, Notification)>,
+}
+
+impl NotificationReceiver {
+    /// Wait for the next notification. Returns `None` when the subscription
+    /// service was dropped.
+    pub async fn recv(&mut self) -> Option<Notification> {
+        loop {
+            match self.receiver.recv().await {
+                Ok((subscriber, notification)) if subscriber == self.subscriber => {
+                    return Some(notification)
+                }
+                Ok(_) => continue,
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    warn!(
+                        "Event stream lagged behind, {} notifications were dropped",
+                        skipped
+                    );
+                }
+                Err(broadcast::error::RecvError::Closed) => return None,
+            }
+        }
+    }
+}
+
+fn subscriber_id(api_key: &str) -> String {
+    hex::encode(Sha256::digest(api_key.as_bytes()))
+}
+
+fn validate_webhook_url(webhook_url: &str) -> Result<(), SubscriptionError> {
+    match url::Url::parse(webhook_url) {
+        Ok(url) if url.scheme() == "http" || url.scheme() == "https" => Ok(()),
+        _ => Err(SubscriptionError::InvalidSubscription(format!(
+            "invalid webhook url: {}",
+            webhook_url
+        ))),
+    }
+}
+
+/// Match `text` against a glob `pattern`, in which `*` matches any sequence
+/// of characters and `?` matches exactly one character.
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+
+    let (mut p, mut t) = (0, 0);
+    // the position of the last `*` in the pattern and the text position it was tried at
+    let mut backtrack: Option<(usize, usize)> = None;
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, t));
+            p += 1;
+        } else if let Some((star_p, star_t)) = backtrack {
+            backtrack = Some((star_p, star_t + 1));
+            p = star_p + 1;
+            t = star_t + 1;
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+#[cfg(test)]
+#[cfg(not(tarpaulin_include))]
+mod tests {
+    use super::*;
+    use crate::transparency_log::log::AddArtifactRequest;
+    use crate::util::test_util;
+
+    fn transparency_log(package_type: PackageType, package_specific_id: &str) -> TransparencyLog {
+        TransparencyLog::from(AddArtifactRequest {
+            package_type,
+            package_specific_id: package_specific_id.to_owned(),
+            num_artifacts: 1,
+            package_specific_artifact_id: package_specific_id.to_owned(),
+            artifact_hash: "artifact_hash".to_owned(),
+        })
+    }
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("library/alpine:3.17", "library/alpine:3.17"));
+        assert!(glob_match("library/alpine:3.*", "library/alpine:3.17"));
+        assert!(glob_match(
+            "*:my-artifact:1.?.0",
+            "com.myorg:my-artifact:1.1.0"
+        ));
+        assert!(glob_match("*", ""));
+        assert!(!glob_match("library/alpine:3.*", "library/alpine:2.9"));
+        assert!(!glob_match("library/alpine", "library/alpine:3.17"));
+        assert!(!glob_match("*:1.?.0", "com.myorg:my-artifact:1.10.0"));
+    }
+
+    #[test]
+    fn test_subscribe_list_and_unsubscribe() {
+        let tmp_dir = test_util::tests::setup();
+        let subscription_service = SubscriptionService::new(tmp_dir.join("subscriptions.json"));
+
+        let subscription = subscription_service
+            .subscribe(
+                "api_key",
+                Some(PackageType::Docker),
+                "library/alpine:*",
+                None,
+            )
+            .unwrap();
+        assert_eq!(
+            subscription_service.list("api_key").unwrap(),
+            vec![subscription.clone()]
+        );
+        assert!(subscription_service
+            .list("other_api_key")
+            .unwrap()
+            .is_empty());
+
+        assert!(matches!(
+            subscription_service.unsubscribe("other_api_key", &subscription.id),
+            Err(SubscriptionError::SubscriptionNotFound(_))
+        ));
+        subscription_service
+            .unsubscribe("api_key", &subscription.id)
+            .unwrap();
+        assert!(subscription_service.list("api_key").unwrap().is_empty());
+
+        test_util::tests::teardown(tmp_dir);
+    }
+
+    #[test]
+    fn test_subscribe_with_invalid_webhook() {
+        let tmp_dir = test_util::tests::setup();
+        let subscription_service = SubscriptionService::new(tmp_dir.join("subscriptions.json"));
+
+        let result = subscription_service.subscribe(
+            "api_key",
+            None,
+            "*",
+            Some("file:///etc/passwd".to_owned()),
+        );
+        assert!(matches!(
+            result,
+            Err(SubscriptionError::InvalidSubscription(_))
+        ));
+
+        test_util::tests::teardown(tmp_dir);
+    }
+
+    #[tokio::test]
+    async fn test_notify_matching_subscriptions() {
+        let tmp_dir = test_util::tests::setup();
+        let subscription_service = SubscriptionService::new(tmp_dir.join("subscriptions.json"));
+
+        let subscription = subscription_service
+            .subscribe("api_key", Some(PackageType::Maven2), "com.myorg:*", None)
+            .unwrap();
+        subscription_service
+            .subscribe("other_api_key", None, "library/*", None)
+            .unwrap();
+        let mut notifications = subscription_service.notifications("api_key");
+
+        let docker_log = transparency_log(PackageType::Docker, "library/alpine:3.17");
+        let maven_log = transparency_log(PackageType::Maven2, "com.myorg:my-artifact:1.1.0");
+        subscription_service.notify(&docker_log).unwrap();
+        subscription_service.notify(&maven_log).unwrap();
+
+        assert_eq!(
+            notifications.recv().await,
+            Some(Notification {
+                subscription_id: subscription.id,
+                transparency_log: maven_log,
+            })
+        );
+
+        test_util::tests::teardown(tmp_dir);
+    }
+}
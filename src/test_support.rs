@@ -0,0 +1,574 @@
+/*
+   Copyright 2021 JFrog Ltd
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+*/
+
+//! Test doubles for programs that embed the Pyrsia services, enabled with the
+//! `test-support` feature. They allow testing against the Pyrsia APIs without
+//! a running p2p network or blockchain:
+//!
+//! * [`in_memory_artifact_storage`]: an [`ArtifactStorage`] that keeps the
+//!   artifacts in memory
+//! * [`FakeNetwork`]: a scripted p2p network that answers the commands of a
+//!   [`Client`]
+//! * [`fake_transparency_log_service`]: a [`TransparencyLogService`] whose
+//!   transparency logs are accepted by a [`FakeLedger`]
+//!
+//! The fakes spawn their tasks on the current tokio runtime.
+
+pub use crate::artifact_service::blob_store::memory::MemoryBlobStore;
+
+use crate::artifact_service::storage::ArtifactStorage;
+use crate::blockchain_service::event::{BlockchainEvent, BlockchainEventClient};
+use crate::network::artifact_protocol::ArtifactResponse;
+use crate::network::client::command::Command;
+use crate::network::client::Client;
+use crate::network::idle_metric_protocol::PeerMetrics;
+use crate::network::peer_exchange_protocol::PeerAddresses;
+use crate::network::query_metrics::{LookupStep, LookupTrace};
+use crate::network::seed_sync_protocol::{SeedSyncRequest, SeedSyncResponse};
+use crate::node_api::model::request::Status;
+use crate::transparency_log::log::{
+    LogSnapshot, TransparencyLog, TransparencyLogError, TransparencyLogPage,
+    TransparencyLogService, DEFAULT_LOG_PAGE_SIZE,
+};
+use anyhow::anyhow;
+use libp2p::gossipsub::IdentTopic;
+use libp2p::identity::Keypair;
+use libp2p::PeerId;
+use pyrsia_blockchain_network::error::BlockchainError;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc;
+use uuid::Uuid;
+
+/// An [`ArtifactStorage`] that keeps the artifacts in memory.
+pub fn in_memory_artifact_storage() -> ArtifactStorage {
+    ArtifactStorage::with_blob_store(MemoryBlobStore::new())
+}
+
+/// A p2p network of peers that provide scripted artifacts. The [`Client`]s
+/// created by [`FakeNetwork::client`] are answered by this network instead
+/// of a libp2p swarm, and the network records what the clients did.
+#[derive(Clone, Debug, Default)]
+pub struct FakeNetwork {
+    state: Arc<Mutex<FakeNetworkState>>,
+}
+
+#[derive(Debug, Default)]
+struct FakeNetworkState {
+    peers: HashSet<PeerId>,
+    artifacts: HashMap<String, HashMap<PeerId, Vec<u8>>>,
+    idle_metrics: HashMap<PeerId, f64>,
+    known_peers: HashMap<PeerId, Vec<PeerAddresses>>,
+    seed_snapshots: HashMap<PeerId, (Vec<TransparencyLog>, Vec<String>)>,
+    log_snapshots: HashMap<PeerId, LogSnapshot>,
+    replica_peers: HashSet<PeerId>,
+    provided_artifact_ids: HashSet<String>,
+    broadcast_blocks: Vec<Vec<u8>>,
+    requested_builds: Vec<(PeerId, String)>,
+}
+
+impl FakeNetwork {
+    pub fn new() -> FakeNetwork {
+        Default::default()
+    }
+
+    /// Add a connected peer to the network.
+    pub fn with_peer(self, peer_id: PeerId) -> FakeNetwork {
+        self.state.lock().unwrap().peers.insert(peer_id);
+        self
+    }
+
+    /// Let `peer_id` provide the artifact `artifact_id` with `content`. The
+    /// peer is added to the network.
+    pub fn with_artifact(self, peer_id: PeerId, artifact_id: &str, content: &[u8]) -> FakeNetwork {
+        {
+            let mut state = self.state.lock().unwrap();
+            state.peers.insert(peer_id);
+            state
+                .artifacts
+                .entry(artifact_id.to_owned())
+                .or_default()
+                .insert(peer_id, content.to_vec());
+        }
+        self
+    }
+
+    /// Let `peer_id` report `idle_metric` when it is asked how busy it is.
+    /// Peers without an idle metric report 0.
+    pub fn with_idle_metric(self, peer_id: PeerId, idle_metric: f64) -> FakeNetwork {
+        {
+            let mut state = self.state.lock().unwrap();
+            state.peers.insert(peer_id);
+            state.idle_metrics.insert(peer_id, idle_metric);
+        }
+        self
+    }
+
+    /// Let `peer_id` share `known_peers` when it is asked for the peers it
+    /// knows. The peer is added to the network, the known peers are only
+    /// connected when they are dialed.
+    pub fn with_known_peers(self, peer_id: PeerId, known_peers: Vec<PeerAddresses>) -> FakeNetwork {
+        {
+            let mut state = self.state.lock().unwrap();
+            state.peers.insert(peer_id);
+            state.known_peers.insert(peer_id, known_peers);
+        }
+        self
+    }
+
+    /// Let `peer_id` act as a seed node that offers `transparency_logs` and
+    /// the artifacts with `artifact_ids` to nodes that sync from it. The
+    /// artifacts themselves are provided with [`Self::with_artifact`].
+    pub fn with_seed_snapshot(
+        self,
+        peer_id: PeerId,
+        transparency_logs: Vec<TransparencyLog>,
+        artifact_ids: Vec<String>,
+    ) -> FakeNetwork {
+        {
+            let mut state = self.state.lock().unwrap();
+            state.peers.insert(peer_id);
+            state
+                .seed_snapshots
+                .insert(peer_id, (transparency_logs, artifact_ids));
+        }
+        self
+    }
+
+    /// Let `peer_id` serve the signed `snapshot` of `transparency_logs` to
+    /// nodes that fast sync from it.
+    pub fn with_log_snapshot(
+        self,
+        peer_id: PeerId,
+        snapshot: LogSnapshot,
+        transparency_logs: Vec<TransparencyLog>,
+    ) -> FakeNetwork {
+        {
+            let mut state = self.state.lock().unwrap();
+            state.log_snapshots.insert(peer_id, snapshot);
+        }
+        self.with_seed_snapshot(peer_id, transparency_logs, vec![])
+    }
+
+    /// Let `peer_id` take a replica of every artifact it is asked to, after
+    /// which it provides the artifact. Other peers decline.
+    pub fn with_replica_peer(self, peer_id: PeerId) -> FakeNetwork {
+        {
+            let mut state = self.state.lock().unwrap();
+            state.peers.insert(peer_id);
+            state.replica_peers.insert(peer_id);
+        }
+        self
+    }
+
+    /// The peers that the clients are connected to.
+    pub fn peers(&self) -> HashSet<PeerId> {
+        self.state.lock().unwrap().peers.clone()
+    }
+
+    /// Create a client for a new local peer of this network.
+    pub fn client(&self) -> Client {
+        let (sender, mut receiver) = mpsc::channel(32);
+        let network = self.clone();
+        tokio::spawn(async move {
+            while let Some(command) = receiver.recv().await {
+                network.handle_command(command);
+            }
+        });
+        Client::new(
+            sender,
+            Keypair::generate_ed25519().public().to_peer_id(),
+            IdentTopic::new("pyrsia-topic"),
+        )
+    }
+
+    /// The artifacts that the clients currently provide.
+    pub fn provided_artifact_ids(&self) -> HashSet<String> {
+        self.state.lock().unwrap().provided_artifact_ids.clone()
+    }
+
+    /// The blocks that the clients broadcast.
+    pub fn broadcast_blocks(&self) -> Vec<Vec<u8>> {
+        self.state.lock().unwrap().broadcast_blocks.clone()
+    }
+
+    /// The peers that the clients requested builds from, with the package
+    /// specific id of the requested build.
+    pub fn requested_builds(&self) -> Vec<(PeerId, String)> {
+        self.state.lock().unwrap().requested_builds.clone()
+    }
+
+    fn handle_command(&self, command: Command) {
+        let mut state = self.state.lock().unwrap();
+        match command {
+            Command::AddProbe { sender, .. }
+            | Command::AnnounceMaintenance { sender, .. }
+            | Command::BootstrapDht { sender }
+            | Command::Listen { sender, .. }
+            | Command::Dial { sender, .. } => {
+                let _ = sender.send(Ok(()));
+            }
+            Command::DialPeer {
+                peer_id, sender, ..
+            } => {
+                state.peers.insert(peer_id);
+                let _ = sender.send(Ok(()));
+            }
+            Command::BroadcastBlock { block, sender, .. } => {
+                state.broadcast_blocks.push(block);
+                let _ = sender.send(Ok(()));
+            }
+            Command::ListPeers { sender } => {
+                let _ = sender.send(state.peers.clone());
+            }
+            Command::Status { sender } => {
+                let _ = sender.send(Status {
+                    peers_count: state.peers.len(),
+                    ..Default::default()
+                });
+            }
+            Command::Provide {
+                artifact_id,
+                sender,
+            } => {
+                state.provided_artifact_ids.insert(artifact_id);
+                let _ = sender.send(());
+            }
+            Command::StopProviding {
+                artifact_id,
+                sender,
+            } => {
+                state.provided_artifact_ids.remove(&artifact_id);
+                let _ = sender.send(());
+            }
+            Command::ListProviders {
+                artifact_id,
+                sender,
+            } => {
+                let providers = state
+                    .artifacts
+                    .get(&artifact_id)
+                    .map(|providers| providers.keys().copied().collect())
+                    .unwrap_or_default();
+                let _ = sender.send(providers);
+            }
+            Command::TraceLookup {
+                artifact_id,
+                sender,
+            } => {
+                let providers: Vec<String> = state
+                    .artifacts
+                    .get(&artifact_id)
+                    .map(|providers| providers.keys().map(PeerId::to_string).collect())
+                    .unwrap_or_default();
+                let _ = sender.send(LookupTrace {
+                    artifact_id,
+                    providers: providers.clone(),
+                    steps: vec![LookupStep {
+                        step: 1,
+                        providers,
+                        ..Default::default()
+                    }],
+                    ..Default::default()
+                });
+            }
+            Command::RequestBuild {
+                peer,
+                package_specific_id,
+                sender,
+                ..
+            } => {
+                state.requested_builds.push((peer, package_specific_id));
+                let _ = sender.send(Ok(Uuid::new_v4().to_string()));
+            }
+            Command::RequestArtifact {
+                artifact_id,
+                chunk,
+                peer,
+                sender,
+            } => {
+                let response = state
+                    .artifacts
+                    .get(&artifact_id)
+                    .and_then(|providers| providers.get(&peer))
+                    .map(|content| {
+                        let artifact_size = content.len() as u64;
+                        let artifact = match chunk {
+                            Some(chunk) => {
+                                let start = chunk.offset.min(artifact_size) as usize;
+                                let len = chunk.len_in(artifact_size) as usize;
+                                content[start..start + len].to_vec()
+                            }
+                            None => content.clone(),
+                        };
+                        ArtifactResponse {
+                            artifact,
+                            artifact_size,
+                        }
+                    })
+                    .ok_or_else(|| {
+                        anyhow!("Peer {} does not provide artifact {}", peer, artifact_id)
+                    });
+                let _ = sender.send(response);
+            }
+            Command::RequestIdleMetric { peer, sender } => {
+                let idle_metric = state.idle_metrics.get(&peer).copied().unwrap_or_default();
+                let _ = sender.send(Ok(PeerMetrics {
+                    idle_metric: idle_metric.to_le_bytes(),
+                }));
+            }
+            Command::RequestPeerExchange { peer, sender } => {
+                let known_peers = state.known_peers.get(&peer).cloned().unwrap_or_default();
+                let _ = sender.send(Ok(known_peers));
+            }
+            Command::RequestBlockchain { peer, sender, .. } => {
+                let _ = sender.send(Err(anyhow!("Peer {} has no blockchain", peer)));
+            }
+            Command::RequestBuildStatus { peer, sender, .. } => {
+                let _ = sender.send(Err(anyhow!("Peer {} has no builds", peer)));
+            }
+            Command::RequestSeedSync {
+                peer,
+                request,
+                sender,
+            } => {
+                if let SeedSyncRequest::Replicate { artifact_id } = request {
+                    let replicated = state.replica_peers.contains(&peer);
+                    if replicated {
+                        state
+                            .artifacts
+                            .entry(artifact_id)
+                            .or_default()
+                            .insert(peer, vec![]);
+                    }
+                    let _ = sender.send(Ok(SeedSyncResponse::Replicated(replicated)));
+                    return;
+                }
+                let response = match (state.seed_snapshots.get(&peer), request) {
+                    (None, _) => Err(anyhow!("Peer {} is not a seed node", peer)),
+                    (
+                        Some((transparency_logs, _)),
+                        SeedSyncRequest::TransparencyLogs { offset },
+                    ) => Ok(SeedSyncResponse::TransparencyLogs(TransparencyLogPage {
+                        total: transparency_logs.len(),
+                        offset,
+                        transparency_logs: transparency_logs
+                            .iter()
+                            .skip(offset)
+                            .take(DEFAULT_LOG_PAGE_SIZE)
+                            .cloned()
+                            .collect(),
+                        blocks: Default::default(),
+                    })),
+                    (Some((_, artifact_ids)), SeedSyncRequest::Artifacts) => {
+                        Ok(SeedSyncResponse::Artifacts(artifact_ids.clone()))
+                    }
+                    (Some(_), SeedSyncRequest::Snapshot) => state
+                        .log_snapshots
+                        .get(&peer)
+                        .map(|snapshot| SeedSyncResponse::Snapshot(snapshot.clone()))
+                        .ok_or_else(|| anyhow!("Peer {} serves no snapshot", peer)),
+                    (Some(_), SeedSyncRequest::Replicate { .. }) => {
+                        unreachable!("replica requests are answered above")
+                    }
+                };
+                let _ = sender.send(response);
+            }
+            Command::RespondBuild { .. }
+            | Command::RespondArtifact { .. }
+            | Command::RespondIdleMetric { .. }
+            | Command::RespondBlockchain { .. }
+            | Command::RespondBuildStatus { .. }
+            | Command::RespondSeedSync { .. } => {}
+        }
+    }
+}
+
+/// A blockchain that accepts every transparency log that is published to it.
+#[derive(Clone, Debug, Default)]
+pub struct FakeLedger {
+    transparency_logs: Arc<Mutex<Vec<TransparencyLog>>>,
+}
+
+impl FakeLedger {
+    /// The transparency logs that were published, in the order in which they
+    /// were published.
+    pub fn transparency_logs(&self) -> Vec<TransparencyLog> {
+        self.transparency_logs.lock().unwrap().clone()
+    }
+
+    /// A client for the blockchain events that are handled by this ledger.
+    pub fn blockchain_event_client(&self) -> BlockchainEventClient {
+        let (sender, mut receiver) = mpsc::channel(32);
+        let ledger = self.clone();
+        tokio::spawn(async move {
+            while let Some(event) = receiver.recv().await {
+                ledger.handle_event(event);
+            }
+        });
+        BlockchainEventClient::new(sender)
+    }
+
+    fn handle_event(&self, event: BlockchainEvent) {
+        match event {
+            BlockchainEvent::AddBlock { payload, sender } => {
+                let result = TransparencyLogService::parse_payload(&payload)
+                    .map(|transparency_logs| {
+                        self.transparency_logs
+                            .lock()
+                            .unwrap()
+                            .extend(transparency_logs)
+                    })
+                    .map_err(|e| BlockchainError::AnyhowError(e.into()));
+                let _ = sender.send(result);
+            }
+            BlockchainEvent::PullBlocksFromPeer { sender, .. } => {
+                let _ = sender.send(Ok(0));
+            }
+            BlockchainEvent::PullBlocksSinceFromPeer { sender, .. }
+            | BlockchainEvent::PullBlocksLocal { sender, .. } => {
+                let _ = sender.send(Ok(vec![]));
+            }
+            BlockchainEvent::HandleBlockBroadcast { sender, .. } => {
+                let _ = sender.send(Ok(()));
+            }
+            BlockchainEvent::HandlePullBlocks { sender, .. } => {
+                let _ = sender.send(Ok(vec![]));
+            }
+            BlockchainEvent::HandleQueryBlockOrdinal { sender } => {
+                let _ = sender.send(Ok(0));
+            }
+            BlockchainEvent::ExportBlocks { sender } => {
+                let _ = sender.send(Ok(vec![]));
+            }
+            BlockchainEvent::ImportBlocks { sender, .. } => {
+                let _ = sender.send(Ok(0));
+            }
+            BlockchainEvent::AuditBlocks { sender } => {
+                let _ = sender.send(Ok(Default::default()));
+            }
+        }
+    }
+}
+
+/// A [`TransparencyLogService`] that keeps its database in `repository_path`
+/// and publishes its transparency logs to the returned [`FakeLedger`].
+pub fn fake_transparency_log_service<P: AsRef<Path>>(
+    repository_path: P,
+) -> Result<(TransparencyLogService, FakeLedger), TransparencyLogError> {
+    let ledger = FakeLedger::default();
+    let transparency_log_service =
+        TransparencyLogService::new(repository_path, ledger.blockchain_event_client())?;
+    Ok((transparency_log_service, ledger))
+}
+
+#[cfg(test)]
+#[cfg(not(tarpaulin_include))]
+mod tests {
+    use super::*;
+    use crate::artifact_service::model::PackageType;
+    use crate::artifact_service::service::ArtifactService;
+    use crate::build_service::event::BuildEventClient;
+    use crate::transparency_log::log::AddArtifactRequest;
+    use crate::util::test_util;
+    use sha2::{Digest, Sha256};
+
+    #[tokio::test]
+    async fn test_fake_network() {
+        let peer_id = PeerId::random();
+        let network = FakeNetwork::new().with_artifact(peer_id, "artifact_id", b"artifact");
+        let mut client = network.client();
+
+        assert_eq!(client.list_peers().await.unwrap(), HashSet::from([peer_id]));
+        assert_eq!(
+            client.list_providers("artifact_id").await.unwrap(),
+            HashSet::from([peer_id])
+        );
+        assert_eq!(
+            client
+                .request_artifact(&peer_id, "artifact_id")
+                .await
+                .unwrap(),
+            b"artifact"
+        );
+        assert!(client
+            .request_artifact(&PeerId::random(), "artifact_id")
+            .await
+            .is_err());
+
+        client.provide("other_artifact_id").await.unwrap();
+        assert_eq!(
+            network.provided_artifact_ids(),
+            HashSet::from(["other_artifact_id".to_owned()])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_artifact_service_with_fakes() {
+        let tmp_dir = test_util::tests::setup();
+
+        let content = b"artifact";
+        let artifact_hash = hex::encode(Sha256::digest(content));
+        let (transparency_log_service, ledger) = fake_transparency_log_service(&tmp_dir).unwrap();
+        let (transparency_log, payload) = transparency_log_service
+            .add_artifact(AddArtifactRequest {
+                package_type: PackageType::Docker,
+                package_specific_id: "alpine:3.16".to_owned(),
+                num_artifacts: 1,
+                package_specific_artifact_id: "alpine@sha256:1".to_owned(),
+                artifact_hash,
+            })
+            .await
+            .unwrap();
+        ledger
+            .blockchain_event_client()
+            .add_block(payload.into_bytes())
+            .await
+            .unwrap();
+        assert_eq!(ledger.transparency_logs(), vec![transparency_log.clone()]);
+
+        let network = FakeNetwork::new().with_artifact(
+            PeerId::random(),
+            &transparency_log.artifact_id,
+            content,
+        );
+        let mut artifact_service = ArtifactService::new(
+            &tmp_dir,
+            ledger.blockchain_event_client(),
+            BuildEventClient::new(mpsc::channel(1).0),
+            network.client(),
+        )
+        .unwrap()
+        .with_artifact_storage(in_memory_artifact_storage());
+        artifact_service.transparency_log_service = transparency_log_service;
+
+        let artifact = artifact_service
+            .get_artifact(PackageType::Docker, "alpine@sha256:1")
+            .await
+            .unwrap();
+
+        assert_eq!(artifact, content);
+        assert!(
+            artifact_service
+                .artifact_storage
+                .contains_artifact(&transparency_log.artifact_id)
+                .await
+        );
+
+        test_util::tests::teardown(tmp_dir);
+    }
+}
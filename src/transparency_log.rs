@@ -14,4 +14,11 @@
    limitations under the License.
 */
 
+pub mod audit;
 pub mod log;
+pub mod lookup_cache;
+pub mod node_quorum;
+pub mod store;
+
+pub use pyrsia_transparency_log::cbor;
+pub use pyrsia_transparency_log::merkle;
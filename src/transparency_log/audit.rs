@@ -0,0 +1,326 @@
+/*
+   Copyright 2021 JFrog Ltd
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+*/
+
+//! Verification of the blocks and transparency logs that arrive on the
+//! blockchain, as done by nodes in monitor mode. Unlike the checks of the
+//! node that publishes a transparency log, these checks do not trust the
+//! sender: every block must be signed by an authorized committer and every
+//! transparency log must be well-formed and consistent with the transparency
+//! logs that are already known.
+
+use crate::transparency_log::log::{
+    Operation, TransparencyLog, TransparencyLogError, TransparencyLogService,
+};
+use libp2p::PeerId;
+use pyrsia_blockchain_network::crypto::hash_algorithm::HashDigest;
+use pyrsia_blockchain_network::structures::block::Block;
+use pyrsia_blockchain_network::structures::header::{Address, Ordinal};
+use std::str::FromStr;
+use thiserror::Error;
+
+/// The length of a hex encoded sha256 hash.
+const SHA256_HEX_LEN: usize = 64;
+
+/// An inconsistency that was found while auditing the blockchain.
+#[derive(Clone, Debug, Eq, Error, PartialEq)]
+pub enum Inconsistency {
+    #[error("Block {ordinal} has an invalid signature")]
+    InvalidBlockSignature { ordinal: Ordinal },
+    #[error("Block {ordinal} is not signed by its committer")]
+    CommitterMismatch { ordinal: Ordinal },
+    #[error("Block {ordinal} has transactions that do not match its transactions hash")]
+    TransactionsHashMismatch { ordinal: Ordinal },
+    #[error("Block {ordinal} is not committed by an authorized node")]
+    UnauthorizedCommitter { ordinal: Ordinal },
+    #[error("Block {ordinal} has a payload that is not a list of transparency logs: {error}")]
+    InvalidPayload { ordinal: Ordinal, error: String },
+    #[error(
+        "Transparency log {id} has an artifact hash that is not a sha256 hash: {artifact_hash}"
+    )]
+    InvalidArtifactHash { id: String, artifact_hash: String },
+    #[error("Transparency log {id} has a node id that is not a peer id: {node_id}")]
+    InvalidNodeId { id: String, node_id: String },
+    #[error("Transparency log {id} differs from the known transparency log with the same id")]
+    ConflictingLog { id: String },
+    #[error("Transparency log {id} adds artifact {package_specific_artifact_id} that was already added by transparency log {existing_id} with another hash")]
+    DuplicateArtifact {
+        id: String,
+        package_specific_artifact_id: String,
+        existing_id: String,
+    },
+}
+
+impl Inconsistency {
+    /// The block ordinal or the ids of the transparency logs involved.
+    pub fn details(&self) -> Vec<String> {
+        match self {
+            Inconsistency::InvalidBlockSignature { ordinal }
+            | Inconsistency::CommitterMismatch { ordinal }
+            | Inconsistency::TransactionsHashMismatch { ordinal }
+            | Inconsistency::UnauthorizedCommitter { ordinal }
+            | Inconsistency::InvalidPayload { ordinal, .. } => vec![ordinal.to_string()],
+            Inconsistency::InvalidArtifactHash { id, .. }
+            | Inconsistency::InvalidNodeId { id, .. }
+            | Inconsistency::ConflictingLog { id } => vec![id.clone()],
+            Inconsistency::DuplicateArtifact {
+                id, existing_id, ..
+            } => vec![id.clone(), existing_id.clone()],
+        }
+    }
+}
+
+/// Audit a block before its transparency logs are written to the
+/// transparency log database. The committer must be an authorized node,
+/// unless no node is authorized yet, as in the first block of the
+/// blockchain.
+pub fn audit_block(
+    transparency_log_service: &TransparencyLogService,
+    block: &Block,
+) -> Result<Vec<Inconsistency>, TransparencyLogError> {
+    let ordinal = block.header.ordinal;
+    let mut inconsistencies = Vec::new();
+
+    if !block.verify() {
+        inconsistencies.push(Inconsistency::InvalidBlockSignature { ordinal });
+    }
+    if block.signer() != Some(block.header.committer) {
+        inconsistencies.push(Inconsistency::CommitterMismatch { ordinal });
+    }
+    let transactions_hash = bincode::serialize(&block.transactions)
+        .map(|transactions| HashDigest::new(&transactions))
+        .ok();
+    if transactions_hash != Some(block.header.transactions_hash) {
+        inconsistencies.push(Inconsistency::TransactionsHashMismatch { ordinal });
+    }
+    let authorized_nodes = transparency_log_service.get_authorized_nodes()?;
+    if !authorized_nodes.is_empty()
+        && !authorized_nodes
+            .iter()
+            .any(|peer_id| Address::from(*peer_id) == block.header.committer)
+    {
+        inconsistencies.push(Inconsistency::UnauthorizedCommitter { ordinal });
+    }
+
+    for payload in block.fetch_payload() {
+        match TransparencyLogService::parse_payload(&payload) {
+            Ok(transparency_logs) => {
+                for transparency_log in &transparency_logs {
+                    inconsistencies.extend(audit_transparency_log(
+                        transparency_log_service,
+                        transparency_log,
+                    )?);
+                }
+            }
+            Err(error) => inconsistencies.push(Inconsistency::InvalidPayload {
+                ordinal,
+                error: error.to_string(),
+            }),
+        }
+    }
+
+    Ok(inconsistencies)
+}
+
+/// Audit a transparency log before it is written to the transparency log
+/// database. A transparency log that is already known must be identical to
+/// the known one, and an artifact can only be added again with the same
+/// hash.
+pub fn audit_transparency_log(
+    transparency_log_service: &TransparencyLogService,
+    transparency_log: &TransparencyLog,
+) -> Result<Vec<Inconsistency>, TransparencyLogError> {
+    let id = &transparency_log.id;
+    let mut inconsistencies = Vec::new();
+
+    match transparency_log.operation {
+        Operation::AddArtifact if !is_sha256_hex(&transparency_log.artifact_hash) => {
+            inconsistencies.push(Inconsistency::InvalidArtifactHash {
+                id: id.clone(),
+                artifact_hash: transparency_log.artifact_hash.clone(),
+            });
+        }
+        Operation::AddNode
+        | Operation::RemoveNode
+        | Operation::ApproveAddNode
+        | Operation::ApproveRemoveNode
+        | Operation::ClaimBuild
+        | Operation::ReleaseBuild
+            if PeerId::from_str(&transparency_log.node_id).is_err() =>
+        {
+            inconsistencies.push(Inconsistency::InvalidNodeId {
+                id: id.clone(),
+                node_id: transparency_log.node_id.clone(),
+            });
+        }
+        _ => {}
+    }
+
+    match transparency_log_service.find_transparency_log(id) {
+        Ok(known) => {
+            if &known != transparency_log {
+                inconsistencies.push(Inconsistency::ConflictingLog { id: id.clone() });
+            }
+        }
+        Err(TransparencyLogError::LogNotFound { .. }) => {
+            if transparency_log.operation == Operation::AddArtifact {
+                inconsistencies.extend(
+                    transparency_log_service
+                        .find_artifacts(
+                            transparency_log.package_type.as_ref(),
+                            &transparency_log.package_specific_id,
+                        )?
+                        .into_iter()
+                        .filter(|existing| {
+                            existing.package_specific_artifact_id
+                                == transparency_log.package_specific_artifact_id
+                                && existing.artifact_hash != transparency_log.artifact_hash
+                        })
+                        .map(|existing| Inconsistency::DuplicateArtifact {
+                            id: id.clone(),
+                            package_specific_artifact_id: existing.package_specific_artifact_id,
+                            existing_id: existing.id,
+                        }),
+                );
+            }
+        }
+        Err(error) => return Err(error),
+    }
+
+    Ok(inconsistencies)
+}
+
+fn is_sha256_hex(hash: &str) -> bool {
+    hash.len() == SHA256_HEX_LEN && hash.bytes().all(|byte| byte.is_ascii_hexdigit())
+}
+
+#[cfg(test)]
+#[cfg(not(tarpaulin_include))]
+mod tests {
+    use super::*;
+    use crate::artifact_service::model::PackageType;
+    use crate::transparency_log::log::AddArtifactRequest;
+    use crate::util::test_util;
+    use libp2p::identity::ed25519::Keypair;
+    use libp2p::identity::PublicKey;
+    use pyrsia_blockchain_network::structures::transaction::{Transaction, TransactionType};
+
+    const ARTIFACT_HASH: &str = "e11c16ff163ccc1efe01d2696c626891560fa82123601a5ff196d97b6ab156da";
+
+    fn block(keypair: &Keypair, transparency_logs: &[TransparencyLog]) -> Block {
+        let committer = Address::from(PublicKey::Ed25519(keypair.public()).to_peer_id());
+        let transaction = Transaction::new(
+            TransactionType::Create,
+            committer,
+            serde_json::to_vec(transparency_logs).unwrap(),
+            keypair,
+        );
+        Block::new(HashDigest::new(b""), 1, vec![transaction], keypair)
+    }
+
+    fn add_artifact_log(artifact_hash: &str) -> TransparencyLog {
+        TransparencyLog::from(AddArtifactRequest {
+            package_type: PackageType::Docker,
+            package_specific_id: "library/alpine:3.15.2".to_owned(),
+            num_artifacts: 1,
+            package_specific_artifact_id: "library/alpine@sha256:1".to_owned(),
+            artifact_hash: artifact_hash.to_owned(),
+        })
+    }
+
+    #[tokio::test]
+    async fn test_audit_block() {
+        let tmp_dir = test_util::tests::setup();
+
+        let log =
+            test_util::tests::create_transparency_log_service_default_blockchain_handler(&tmp_dir);
+        let keypair = Keypair::generate();
+
+        let valid_block = block(&keypair, &[add_artifact_log(ARTIFACT_HASH)]);
+        assert_eq!(audit_block(&log, &valid_block).unwrap(), vec![]);
+
+        log.add_authorized_node(PeerId::random()).await.unwrap();
+        let mut tampered_block = block(&keypair, &[add_artifact_log("hash")]);
+        tampered_block.transactions.clear();
+        assert_eq!(
+            audit_block(&log, &tampered_block).unwrap(),
+            vec![
+                Inconsistency::TransactionsHashMismatch { ordinal: 1 },
+                Inconsistency::UnauthorizedCommitter { ordinal: 1 },
+            ]
+        );
+
+        let invalid_log_block = block(&keypair, &[add_artifact_log("hash")]);
+        assert!(matches!(
+            audit_block(&log, &invalid_log_block).unwrap()[..],
+            [
+                Inconsistency::UnauthorizedCommitter { .. },
+                Inconsistency::InvalidArtifactHash { .. },
+            ]
+        ));
+
+        test_util::tests::teardown(tmp_dir);
+    }
+
+    #[tokio::test]
+    async fn test_audit_transparency_log() {
+        let tmp_dir = test_util::tests::setup();
+
+        let mut log =
+            test_util::tests::create_transparency_log_service_default_blockchain_handler(&tmp_dir);
+        let transparency_log = add_artifact_log(ARTIFACT_HASH);
+        assert_eq!(
+            audit_transparency_log(&log, &transparency_log).unwrap(),
+            vec![]
+        );
+        log.write_if_not_exists(&transparency_log).await.unwrap();
+        assert_eq!(
+            audit_transparency_log(&log, &transparency_log).unwrap(),
+            vec![]
+        );
+
+        let conflicting_log = TransparencyLog {
+            timestamp: transparency_log.timestamp + 1,
+            ..transparency_log.clone()
+        };
+        assert_eq!(
+            audit_transparency_log(&log, &conflicting_log).unwrap(),
+            vec![Inconsistency::ConflictingLog {
+                id: transparency_log.id.clone()
+            }]
+        );
+
+        let duplicate_log = add_artifact_log(&ARTIFACT_HASH.replace('e', "f"));
+        assert_eq!(
+            audit_transparency_log(&log, &duplicate_log).unwrap(),
+            vec![Inconsistency::DuplicateArtifact {
+                id: duplicate_log.id.clone(),
+                package_specific_artifact_id: "library/alpine@sha256:1".to_owned(),
+                existing_id: transparency_log.id.clone(),
+            }]
+        );
+
+        let add_node_log = TransparencyLog {
+            operation: Operation::AddNode,
+            ..add_artifact_log(ARTIFACT_HASH)
+        };
+        assert!(matches!(
+            audit_transparency_log(&log, &add_node_log).unwrap()[..],
+            [Inconsistency::InvalidNodeId { .. }]
+        ));
+
+        test_util::tests::teardown(tmp_dir);
+    }
+}
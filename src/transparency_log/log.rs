@@ -15,20 +15,39 @@
 */
 
 use crate::artifact_service::model::PackageType;
+use crate::blockchain_service::audit::ChainAudit;
 use crate::blockchain_service::event::BlockchainEventClient;
+use crate::blockchain_service::explorer::{hex_hash, BlockId};
+use crate::build_service::model::BuildSource;
+use crate::transparency_log::cbor;
+use crate::transparency_log::lookup_cache::LookupCache;
+use crate::transparency_log::merkle::{self, ConsistencyProof, Hash, InclusionProof, TreeHead};
+use crate::transparency_log::node_quorum::{
+    self, approval_operation, approval_signer, quorum_size, NodeChangeProposal,
+};
+use crate::transparency_log::store::sqlite::SqliteLogStore;
+use crate::transparency_log::store::{LogFilter, LogOrder, LogStore};
 use libp2p::core::ParseError;
+use libp2p::identity::{ed25519, PublicKey};
 use libp2p::PeerId;
-use log::{debug, error};
+use log::{debug, info, warn};
+use multihash::{Code, MultihashDigest};
 use pyrsia_blockchain_network::error::BlockchainError;
-use rusqlite::types::{ToSqlOutput, Value};
-use rusqlite::{params, Connection, ToSql};
+use pyrsia_blockchain_network::structures::block::Block;
+use pyrsia_blockchain_network::structures::header::Ordinal;
+use rusqlite::types::ToSqlOutput;
+use rusqlite::ToSql;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::fs;
-use std::io;
-use std::path::{Path, PathBuf};
+use std::io::{self, BufRead, Write};
+use std::path::Path;
 use std::str::FromStr;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use thiserror::Error;
+use tokio::sync::watch;
 use uuid::Uuid;
 
 #[derive(Debug, Error)]
@@ -53,6 +72,12 @@ pub enum TransparencyLogError {
     NodeAlreadyExists { node_id: String },
     #[error("Node with node ID {node_id} does not exists in transparency log or was removed")]
     NodeDoesNotExistOrRemoved { node_id: String },
+    #[error(
+        "Node with node ID {node_id} is not authorized to approve changes to the authorized nodes"
+    )]
+    NodeNotAuthorizedToApprove { node_id: String },
+    #[error("No key to sign approvals of changes to the authorized nodes")]
+    MissingNodeKeypair,
     #[error("Hash Verification failed for ID {id}: {invalid_hash} vs {actual_hash}")]
     InvalidHash {
         id: String,
@@ -66,14 +91,33 @@ pub enum TransparencyLogError {
         id: String,
         invalid_operation: Operation,
     },
+    #[error("Invalid artifact ID {artifact_id} for transparency log {id}, it is not derived from the artifact hash")]
+    InvalidArtifactId { id: String, artifact_id: String },
+    #[error("Artifact ID {artifact_id} is already used by an artifact with a different hash")]
+    ArtifactIdCollision { artifact_id: String },
+    #[error("Artifact ID {artifact_id} was revoked by transparency log {id}")]
+    ArtifactRevoked { id: String, artifact_id: String },
     #[error("Failure while accessing underlying storage: {0}")]
     DatabaseFailure(#[from] rusqlite::Error),
+    #[cfg(feature = "postgres-log-store")]
+    #[error("Failure while accessing underlying storage: {0}")]
+    PostgresFailure(#[from] postgres::Error),
     #[error("Failure while accessing underlying storage: {0}")]
     StorageFailure(#[from] io::Error),
     #[error("Failure while adding block to the blockchain: {0}")]
     BlockchainFailure(#[from] BlockchainError),
     #[error("Failure while generating JSON from transparency log: {0}")]
     SerdeJsonFailure(#[from] serde_json::error::Error),
+    #[error("Invalid transparency log export: {0}")]
+    InvalidExport(String),
+    #[error("Invalid transparency log snapshot: {0}")]
+    InvalidSnapshot(String),
+    #[error("No consistency proof from tree size {old_size} to {new_size}, the transparency log has {tree_size} entries")]
+    InvalidTreeSize {
+        old_size: u64,
+        new_size: u64,
+        tree_size: u64,
+    },
 }
 
 #[derive(
@@ -91,6 +135,48 @@ pub enum Operation {
     RemoveArtifact,
     AddNode,
     RemoveNode,
+    DeprecateArtifact,
+    RevokeArtifact,
+    ApproveAddNode,
+    ApproveRemoveNode,
+    ClaimBuild,
+    ReleaseBuild,
+}
+
+/// The file formats of an export of the transparency log.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    strum_macros::Display,
+    strum_macros::EnumString,
+    Deserialize,
+    Serialize,
+    Eq,
+    PartialEq,
+)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
+pub enum LogFormat {
+    /// One JSON object per line.
+    Jsonl,
+    /// A sequence of CBOR maps, see RFC 8742.
+    Cbor,
+}
+
+/// The `source_id` prefix of transparency logs for artifacts that were
+/// imported from an existing repository instead of built from source.
+pub const IMPORTED_SOURCE_PREFIX: &str = "import:";
+
+/// Describes how the artifact of a transparency log came into the network.
+#[derive(Debug, Clone, Copy, strum_macros::Display, Deserialize, Serialize, Eq, PartialEq)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
+pub enum Provenance {
+    /// The artifact was built from source by an authorized node.
+    Built,
+    /// The artifact was imported as-is from an existing repository.
+    Imported,
 }
 
 impl ToSql for Operation {
@@ -99,7 +185,7 @@ impl ToSql for Operation {
     }
 }
 
-#[derive(Debug, Deserialize, Serialize, PartialEq)]
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
 pub struct TransparencyLog {
     pub id: String,
     pub package_type: Option<PackageType>,
@@ -114,6 +200,287 @@ pub struct TransparencyLog {
     pub operation: Operation,
     pub node_id: String,
     pub node_public_key: String,
+    /// The package specific id of the package that replaces a deprecated package.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub successor: Option<String>,
+    /// The id of the pinning snapshot with the exact dependencies that the
+    /// artifact was built with, see
+    /// [`PinningSnapshot`](crate::build_service::pinning::PinningSnapshot).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pinning_snapshot: Option<String>,
+    /// The hex encoded signature of an approval of a change to the
+    /// authorized nodes, by the node with the node public key, see
+    /// [`node_quorum`](crate::transparency_log::node_quorum).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
+}
+
+/// How long a build claim keeps other authorized nodes from building the
+/// same package by default, unless its artifacts are published earlier.
+pub const DEFAULT_BUILD_CLAIM_TTL: Duration = Duration::from_secs(60 * 60);
+
+/// The number of transparency logs in a page of query results by default.
+pub const DEFAULT_LOG_PAGE_SIZE: usize = 100;
+/// The maximum number of transparency logs in a page of query results.
+pub const MAX_LOG_PAGE_SIZE: usize = 1000;
+/// How long a watch of the transparency logs waits for new transparency
+/// logs by default.
+pub const DEFAULT_LOG_WATCH_WAIT: Duration = Duration::from_secs(30);
+/// The maximum time a watch of the transparency logs waits for new
+/// transparency logs.
+pub const MAX_LOG_WATCH_WAIT: Duration = Duration::from_secs(120);
+
+/// A filter on the transparency logs for audit tooling. Every filter is
+/// optional. The package specific id is a prefix, the hash matches either
+/// the artifact hash or the source hash, and the time range in seconds since
+/// the unix epoch includes both ends.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, Eq, PartialEq)]
+pub struct TransparencyLogQuery {
+    pub package_type: Option<PackageType>,
+    pub package_specific_id: Option<String>,
+    pub operation: Option<Operation>,
+    pub node_id: Option<String>,
+    pub hash: Option<String>,
+    pub since: Option<u64>,
+    pub until: Option<u64>,
+    #[serde(default)]
+    pub offset: usize,
+    pub limit: Option<usize>,
+}
+
+impl TransparencyLogQuery {
+    /// The number of transparency logs in the requested page, capped at
+    /// [`MAX_LOG_PAGE_SIZE`].
+    pub fn page_size(&self) -> usize {
+        self.limit
+            .unwrap_or(DEFAULT_LOG_PAGE_SIZE)
+            .min(MAX_LOG_PAGE_SIZE)
+    }
+}
+
+/// A page of the transparency logs that match a query, oldest first.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct TransparencyLogPage {
+    /// The number of transparency logs that match the query in all pages.
+    pub total: usize,
+    pub offset: usize,
+    pub transparency_logs: Vec<TransparencyLog>,
+    /// The blocks that published the transparency logs of the page, by the
+    /// id of the transparency log.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub blocks: HashMap<String, BlockMetadata>,
+}
+
+/// A request for the transparency logs that were written after a cursor,
+/// for consumers that tail the transparency log, like external indexers.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, Eq, PartialEq)]
+pub struct TransparencyLogWatchQuery {
+    /// The `next_cursor` of the previous batch, 0 to start at the first
+    /// transparency log.
+    #[serde(default)]
+    pub since: u64,
+    pub limit: Option<usize>,
+    /// How long to wait for new transparency logs when there are none,
+    /// capped at [`MAX_LOG_WATCH_WAIT`].
+    pub wait_secs: Option<u64>,
+}
+
+impl TransparencyLogWatchQuery {
+    /// The maximum number of transparency logs in the batch, capped at
+    /// [`MAX_LOG_PAGE_SIZE`].
+    pub fn page_size(&self) -> usize {
+        self.limit
+            .unwrap_or(DEFAULT_LOG_PAGE_SIZE)
+            .min(MAX_LOG_PAGE_SIZE)
+    }
+
+    /// How long to wait for new transparency logs, [`DEFAULT_LOG_WATCH_WAIT`]
+    /// unless requested otherwise.
+    pub fn wait(&self) -> Duration {
+        self.wait_secs
+            .map_or(DEFAULT_LOG_WATCH_WAIT, Duration::from_secs)
+            .min(MAX_LOG_WATCH_WAIT)
+    }
+}
+
+/// The transparency logs that were written after a cursor, in the order in
+/// which they were written.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct TransparencyLogBatch {
+    pub transparency_logs: Vec<TransparencyLog>,
+    /// The cursor to resume from after this batch. It is the requested
+    /// cursor when the batch is empty.
+    pub next_cursor: u64,
+}
+
+/// The block of the blockchain that published a transparency log, which
+/// tells who committed the transparency log and when.
+#[derive(Clone, Debug, Deserialize, Serialize, Eq, PartialEq)]
+pub struct BlockMetadata {
+    pub ordinal: Ordinal,
+    /// The hex encoded hash of the block header.
+    pub hash: String,
+    /// The peer id of the node that committed the block.
+    pub committer: Option<String>,
+    /// The time the block was created, in seconds since the unix epoch.
+    pub timestamp: u64,
+}
+
+impl From<&Block> for BlockMetadata {
+    fn from(block: &Block) -> Self {
+        BlockMetadata {
+            ordinal: block.header.ordinal,
+            hash: hex_hash(&block.header.hash()),
+            committer: block
+                .header
+                .committer
+                .peer_id()
+                .map(|peer_id| peer_id.to_string()),
+            timestamp: block.header.timestamp,
+        }
+    }
+}
+
+/// Proves that a transparency log is included in the Merkle tree over all
+/// transparency logs. The leaf of the transparency log is its JSON encoding,
+/// with the fields in the order in which they are returned.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct TransparencyLogInclusionProof {
+    pub transparency_log: TransparencyLog,
+    #[serde(flatten)]
+    pub proof: InclusionProof,
+}
+
+impl TransparencyLogInclusionProof {
+    pub fn verify(&self) -> bool {
+        serde_json::to_vec(&self.transparency_log)
+            .map(|leaf| self.proof.verify(&leaf))
+            .unwrap_or(false)
+    }
+}
+
+/// A signed statement of a node about the size and root hash of the Merkle
+/// tree over its transparency logs at a point in time. Monitors collect
+/// checkpoints and request consistency proofs between them to detect that
+/// the history of the log was rewritten.
+#[derive(Clone, Debug, Deserialize, Serialize, Eq, PartialEq)]
+pub struct Checkpoint {
+    pub tree_size: u64,
+    /// The hex encoded root hash.
+    pub root_hash: String,
+    pub timestamp: u64,
+    /// The hex encoded ed25519 public key of the node that signed the
+    /// checkpoint.
+    pub node_public_key: String,
+    /// The hex encoded signature of [`Checkpoint::signed_content`].
+    pub signature: String,
+}
+
+impl Checkpoint {
+    fn sign(tree_head: TreeHead, timestamp: u64, keypair: &ed25519::Keypair) -> Checkpoint {
+        let mut checkpoint = Checkpoint {
+            tree_size: tree_head.tree_size,
+            root_hash: tree_head.root_hash,
+            timestamp,
+            node_public_key: hex::encode(keypair.public().encode()),
+            signature: String::new(),
+        };
+        checkpoint.signature = hex::encode(keypair.sign(&checkpoint.signed_content()));
+        checkpoint
+    }
+
+    /// The content that is signed: the tree size, the root hash and the
+    /// timestamp on separate lines.
+    pub fn signed_content(&self) -> Vec<u8> {
+        format!(
+            "pyrsia-checkpoint\n{}\n{}\n{}\n",
+            self.tree_size, self.root_hash, self.timestamp
+        )
+        .into_bytes()
+    }
+
+    /// Verify the signature of the checkpoint with its public key. Whether
+    /// the key belongs to a trusted node has to be checked separately.
+    pub fn verify(&self) -> bool {
+        let (Ok(public_key), Ok(signature)) = (
+            hex::decode(&self.node_public_key),
+            hex::decode(&self.signature),
+        ) else {
+            return false;
+        };
+        ed25519::PublicKey::decode(&public_key)
+            .map(|public_key| public_key.verify(&self.signed_content(), &signature))
+            .unwrap_or(false)
+    }
+}
+
+/// A signed statement of a node that its first `tree_size` transparency
+/// logs, with the Merkle tree root hash `root_hash`, include the payloads of
+/// its blockchain up to the block with `block_ordinal`. A new node imports
+/// the transparency logs of a snapshot instead of replaying the whole
+/// blockchain, and only follows the blocks after it.
+#[derive(Clone, Debug, Deserialize, Serialize, Eq, PartialEq)]
+pub struct LogSnapshot {
+    pub tree_size: u64,
+    /// The hex encoded root hash.
+    pub root_hash: String,
+    pub block_ordinal: Ordinal,
+    pub timestamp: u64,
+    /// The hex encoded ed25519 public key of the node that signed the
+    /// snapshot.
+    pub node_public_key: String,
+    /// The hex encoded signature of [`LogSnapshot::signed_content`].
+    pub signature: String,
+}
+
+impl LogSnapshot {
+    fn sign(
+        tree_head: TreeHead,
+        block_ordinal: Ordinal,
+        timestamp: u64,
+        keypair: &ed25519::Keypair,
+    ) -> LogSnapshot {
+        let mut snapshot = LogSnapshot {
+            tree_size: tree_head.tree_size,
+            root_hash: tree_head.root_hash,
+            block_ordinal,
+            timestamp,
+            node_public_key: hex::encode(keypair.public().encode()),
+            signature: String::new(),
+        };
+        snapshot.signature = hex::encode(keypair.sign(&snapshot.signed_content()));
+        snapshot
+    }
+
+    /// The content that is signed: the tree size, the root hash, the block
+    /// ordinal and the timestamp on separate lines.
+    pub fn signed_content(&self) -> Vec<u8> {
+        format!(
+            "pyrsia-log-snapshot\n{}\n{}\n{}\n{}\n",
+            self.tree_size, self.root_hash, self.block_ordinal, self.timestamp
+        )
+        .into_bytes()
+    }
+
+    /// Verify the signature of the snapshot with its public key.
+    pub fn verify(&self) -> bool {
+        let (Ok(public_key), Ok(signature)) = (
+            hex::decode(&self.node_public_key),
+            hex::decode(&self.signature),
+        ) else {
+            return false;
+        };
+        ed25519::PublicKey::decode(&public_key)
+            .map(|public_key| public_key.verify(&self.signed_content(), &signature))
+            .unwrap_or(false)
+    }
+
+    /// The peer id of the node that signed the snapshot.
+    pub fn signer(&self) -> Option<PeerId> {
+        let public_key =
+            ed25519::PublicKey::decode(&hex::decode(&self.node_public_key).ok()?).ok()?;
+        Some(PeerId::from_public_key(&PublicKey::Ed25519(public_key)))
+    }
 }
 
 #[derive(Debug)]
@@ -138,10 +505,21 @@ pub struct AuthorizedNode {
 ///
 /// It uses a local database to store and index transparency log information to simplify
 /// access.
+///
+/// In privacy mode, the package specific ids of artifacts are replaced by salted hashes
+/// before they are published on the blockchain. The full names are only kept in the
+/// local database of the node that added the artifact. Nodes that share the same salt
+/// resolve lookups by full name through the hashed identifiers.
 #[derive(Clone)]
 pub struct TransparencyLogService {
-    storage_path: PathBuf,
+    log_store: Arc<dyn LogStore>,
     blockchain_event_client: BlockchainEventClient,
+    privacy_salt: Option<String>,
+    lookup_cache: LookupCache,
+    node_keypair: Option<ed25519::Keypair>,
+    build_claim_ttl: Duration,
+    genesis_nodes: Vec<PeerId>,
+    log_written: Arc<watch::Sender<()>>,
 }
 
 impl TransparencyLog {
@@ -152,9 +530,9 @@ impl TransparencyLog {
             package_specific_id: add_artifact_request.package_specific_id.clone(),
             num_artifacts: add_artifact_request.num_artifacts,
             package_specific_artifact_id: add_artifact_request.package_specific_artifact_id.clone(),
+            artifact_id: derive_artifact_id(&add_artifact_request.artifact_hash),
             artifact_hash: add_artifact_request.artifact_hash,
             source_hash: "".to_owned(),
-            artifact_id: Uuid::new_v4().to_string(),
             source_id: Uuid::new_v4().to_string(),
             timestamp: SystemTime::now()
                 .duration_since(UNIX_EPOCH)
@@ -163,7 +541,36 @@ impl TransparencyLog {
             operation: Operation::AddArtifact,
             node_id: Uuid::new_v4().to_string(),
             node_public_key: Uuid::new_v4().to_string(),
+            successor: None,
+            pinning_snapshot: None,
+            signature: None,
+        }
+    }
+
+    /// Whether the artifact was built from source or imported from an
+    /// existing repository.
+    pub fn provenance(&self) -> Provenance {
+        if self.source_id.starts_with(IMPORTED_SOURCE_PREFIX) {
+            Provenance::Imported
+        } else {
+            Provenance::Built
+        }
+    }
+
+    /// The warning for clients of a package that is deprecated by this
+    /// transparency log, if it has the DeprecateArtifact operation.
+    pub fn deprecation_warning(&self) -> Option<String> {
+        if self.operation != Operation::DeprecateArtifact {
+            return None;
         }
+
+        Some(match &self.successor {
+            Some(successor) => format!(
+                "{} is deprecated, use {} instead",
+                self.package_specific_id, successor
+            ),
+            None => format!("{} is deprecated", self.package_specific_id),
+        })
     }
 }
 
@@ -175,19 +582,186 @@ impl TransparencyLogService {
         let mut absolute_path = repository_path.as_ref().to_path_buf().canonicalize()?;
         absolute_path.push("transparency_log");
         Ok(TransparencyLogService {
-            storage_path: absolute_path,
+            log_store: Arc::new(SqliteLogStore::new(absolute_path)),
             blockchain_event_client,
+            privacy_salt: None,
+            lookup_cache: Default::default(),
+            node_keypair: None,
+            build_claim_ttl: DEFAULT_BUILD_CLAIM_TTL,
+            genesis_nodes: vec![],
+            log_written: Arc::new(watch::channel(()).0),
         })
     }
 
-    /// Add a new authorized node to the p2p network.
-    pub async fn add_authorized_node(&self, peer_id: PeerId) -> Result<(), TransparencyLogError> {
+    /// Keep the transparency logs in `log_store` instead of the SQLite
+    /// database in the repository path.
+    pub fn with_log_store<S: LogStore + 'static>(mut self, log_store: S) -> Self {
+        self.log_store = Arc::new(log_store);
+        self.lookup_cache.invalidate();
+        self
+    }
+
+    /// Set the number of artifact lookups that are cached in memory, 0
+    /// disables the cache.
+    pub fn with_lookup_cache_capacity(mut self, capacity: usize) -> Self {
+        self.lookup_cache = LookupCache::new(capacity);
+        self
+    }
+
+    /// Sign the approvals of this node for changes to the authorized nodes
+    /// with `keypair`.
+    pub fn with_node_keypair(mut self, keypair: ed25519::Keypair) -> Self {
+        self.node_keypair = Some(keypair);
+        self
+    }
+
+    /// The nodes that are authorized by the genesis configuration of the
+    /// network, until they are removed, without AddNode transparency logs.
+    pub fn with_genesis_nodes(mut self, genesis_nodes: Vec<PeerId>) -> Self {
+        self.genesis_nodes = genesis_nodes;
+        self
+    }
+
+    /// Let build claims expire after `build_claim_ttl` instead of
+    /// [`DEFAULT_BUILD_CLAIM_TTL`].
+    pub fn with_build_claim_ttl(mut self, build_claim_ttl: Duration) -> Self {
+        self.build_claim_ttl = build_claim_ttl;
+        self
+    }
+
+    /// Enable privacy mode, using `salt` to hash package specific ids that are
+    /// published on the blockchain.
+    pub fn with_privacy_salt(mut self, salt: &str) -> Self {
+        self.privacy_salt = Some(salt.to_owned());
+        self
+    }
+
+    // Hash a package specific id with the privacy salt, so it can be published
+    // without revealing the package name.
+    fn hash_package_identifier(salt: &str, identifier: &str) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(salt.as_bytes());
+        hasher.update(b":");
+        hasher.update(identifier.as_bytes());
+        hex::encode(hasher.finalize())
+    }
+
+    // The identifiers under which a package can be found in the database:
+    // its full name and, in privacy mode, its hashed name.
+    fn package_identifiers(&self, identifier: &str) -> Vec<String> {
+        let mut identifiers = vec![identifier.to_owned()];
+        if let Some(salt) = &self.privacy_salt {
+            identifiers.push(Self::hash_package_identifier(salt, identifier));
+        }
+        identifiers
+    }
+
+    // The transparency log as it is published on the blockchain.
+    fn published_log(&self, transparency_log: &TransparencyLog) -> TransparencyLog {
+        match &self.privacy_salt {
+            Some(salt) => TransparencyLog {
+                package_specific_id: Self::hash_package_identifier(
+                    salt,
+                    &transparency_log.package_specific_id,
+                ),
+                package_specific_artifact_id: Self::hash_package_identifier(
+                    salt,
+                    &transparency_log.package_specific_artifact_id,
+                ),
+                successor: transparency_log
+                    .successor
+                    .as_ref()
+                    .map(|successor| Self::hash_package_identifier(salt, successor)),
+                ..transparency_log.clone()
+            },
+            None => transparency_log.clone(),
+        }
+    }
+
+    // Create the payload that is published on the blockchain for a transparency log.
+    pub(crate) fn create_payload(
+        &self,
+        transparency_log: &TransparencyLog,
+    ) -> Result<String, TransparencyLogError> {
+        Ok(serde_json::to_string(
+            &self.published_log(transparency_log),
+        )?)
+    }
+
+    /// Parse a blockchain payload into the transparency logs it contains. A
+    /// payload is either a single transparency log or a batch of transparency
+    /// logs that were published together. Artifacts that were published with
+    /// an artifact_id that is not derived from their hash get their derived
+    /// artifact_id, see [`derive_artifact_id`].
+    pub fn parse_payload(payload: &[u8]) -> Result<Vec<TransparencyLog>, TransparencyLogError> {
+        let mut transparency_logs = match serde_json::from_slice::<Vec<TransparencyLog>>(payload) {
+            Ok(transparency_logs) => transparency_logs,
+            Err(_) => vec![serde_json::from_slice(payload)?],
+        };
+        for transparency_log in transparency_logs
+            .iter_mut()
+            .filter(|transparency_log| is_artifact_operation(&transparency_log.operation))
+        {
+            transparency_log.artifact_id = derive_artifact_id(&transparency_log.artifact_hash);
+        }
+        Ok(transparency_logs)
+    }
+
+    /// Add a new authorized node to the p2p network. The first authorized
+    /// node of the network is added right away, every other node once a
+    /// quorum of the authorized nodes approved it, see
+    /// [`node_quorum`](crate::transparency_log::node_quorum). Returns the
+    /// proposal to add the node with the approvals so far, including the
+    /// approval of this node.
+    pub async fn add_authorized_node(
+        &self,
+        peer_id: PeerId,
+    ) -> Result<NodeChangeProposal, TransparencyLogError> {
         self.verify_node_does_not_exist(&peer_id.to_string())?;
 
+        if !self.find_added_nodes()?.is_empty() {
+            return self.approve_node_change(Operation::AddNode, &peer_id).await;
+        }
+
+        let transparency_log = node_change_log(Operation::AddNode, &peer_id);
+        let payload = serde_json::to_string(&transparency_log)?;
+        self.blockchain_event_client
+            .add_block(payload.into_bytes())
+            .await?;
+
+        self.write_transparency_log(&transparency_log)?;
+        Ok(NodeChangeProposal {
+            operation: Operation::AddNode,
+            node_id: peer_id.to_string(),
+            approvals: vec![],
+            quorum: 0,
+        })
+    }
+
+    /// Mark a package as deprecated, optionally naming the package that replaces it.
+    /// The deprecation is published on the blockchain by the node with `node_id`.
+    /// Returns an error when the package is not in the transparency log.
+    pub async fn deprecate_package(
+        &self,
+        package_type: &PackageType,
+        package_specific_id: &str,
+        successor: Option<String>,
+        node_id: PeerId,
+    ) -> Result<TransparencyLog, TransparencyLogError> {
+        let latest_log = self
+            .read_transparency_logs(package_type, package_specific_id)?
+            .pop();
+        if !matches!(latest_log, Some(log) if log.operation == Operation::AddArtifact) {
+            return Err(TransparencyLogError::ArtifactNotFound {
+                package_type: *package_type,
+                package_specific_artifact_id: package_specific_id.to_owned(),
+            });
+        }
+
         let transparency_log = TransparencyLog {
             id: Uuid::new_v4().to_string(),
-            package_type: None,
-            package_specific_id: String::from(""),
+            package_type: Some(*package_type),
+            package_specific_id: package_specific_id.to_owned(),
             num_artifacts: 0,
             package_specific_artifact_id: String::from(""),
             artifact_hash: String::from(""),
@@ -198,21 +772,52 @@ impl TransparencyLogService {
                 .duration_since(UNIX_EPOCH)
                 .unwrap()
                 .as_secs(),
-            operation: Operation::AddNode,
-            node_id: peer_id.to_string(),
+            operation: Operation::DeprecateArtifact,
+            node_id: node_id.to_string(),
             node_public_key: Uuid::new_v4().to_string(),
+            successor,
+            pinning_snapshot: None,
+            signature: None,
         };
 
-        let payload = serde_json::to_string(&transparency_log)?;
+        let payload = self.create_payload(&transparency_log)?;
         self.blockchain_event_client
             .add_block(payload.into_bytes())
             .await?;
 
-        self.write_transparency_log(&transparency_log)
+        self.write_transparency_log(&transparency_log)?;
+        Ok(transparency_log)
     }
 
-    /// Remove a known authorized node from the p2p network.
-    pub fn remove_authorized_node(&self, peer_id: PeerId) -> Result<(), TransparencyLogError> {
+    /// Find the latest deprecation of a package. Returns `None` when the
+    /// package is not deprecated.
+    pub fn find_deprecation(
+        &self,
+        package_type: &PackageType,
+        package_specific_id: &str,
+    ) -> Result<Option<TransparencyLog>, TransparencyLogError> {
+        let mut deprecations = Vec::new();
+        for identifier in self.package_identifiers(package_specific_id) {
+            let filter = LogFilter {
+                package_type: Some(*package_type),
+                package_specific_id: Some(identifier),
+                operations: vec![Operation::DeprecateArtifact],
+                ..Default::default()
+            };
+            deprecations.append(&mut self.find_logs(&filter, LogOrder::Written)?);
+        }
+
+        Ok(deprecations.into_iter().max_by_key(|log| log.timestamp))
+    }
+
+    /// Remove a known authorized node from the p2p network, once a quorum
+    /// of the authorized nodes approved it. Returns the proposal to remove
+    /// the node with the approvals so far, including the approval of this
+    /// node.
+    pub async fn remove_authorized_node(
+        &self,
+        peer_id: PeerId,
+    ) -> Result<NodeChangeProposal, TransparencyLogError> {
         if self
             .verify_node_does_not_exist(&peer_id.to_string())
             .is_ok()
@@ -221,25 +826,249 @@ impl TransparencyLogService {
                 node_id: peer_id.to_string(),
             });
         }
-        //todo write remove logic here
 
-        Ok(())
+        self.approve_node_change(Operation::RemoveNode, &peer_id)
+            .await
+    }
+
+    // Publishes the approval of this node for a change to the authorized
+    // nodes, together with the change itself once the approvals reach the
+    // quorum.
+    async fn approve_node_change(
+        &self,
+        operation: Operation,
+        peer_id: &PeerId,
+    ) -> Result<NodeChangeProposal, TransparencyLogError> {
+        let keypair = self
+            .node_keypair
+            .as_ref()
+            .ok_or(TransparencyLogError::MissingNodeKeypair)?;
+        let approver = PeerId::from_public_key(&PublicKey::Ed25519(keypair.public())).to_string();
+        if !self
+            .find_added_nodes()?
+            .iter()
+            .any(|transparency_log| transparency_log.node_id == approver)
+        {
+            return Err(TransparencyLogError::NodeNotAuthorizedToApprove { node_id: approver });
+        }
+
+        let mut proposal = self.node_change_proposal(operation.clone(), &peer_id.to_string())?;
+        let mut transparency_logs = Vec::new();
+        if !proposal.approvals.contains(&approver) {
+            let mut approval = node_change_log(
+                approval_operation(&operation).expect("a node change has approvals"),
+                peer_id,
+            );
+            node_quorum::sign_approval(&mut approval, keypair);
+            transparency_logs.push(approval);
+            proposal.approvals.push(approver);
+            proposal.approvals.sort();
+        }
+        // the quorum can also be reached when authorized nodes that did not
+        // approve yet are removed, then approving again applies the change
+        if proposal.is_approved() {
+            transparency_logs.push(node_change_log(operation, peer_id));
+        }
+        if transparency_logs.is_empty() {
+            return Ok(proposal);
+        }
+
+        let payload = serde_json::to_string(&transparency_logs)?;
+        self.blockchain_event_client
+            .add_block(payload.into_bytes())
+            .await?;
+
+        for transparency_log in &transparency_logs {
+            self.write_transparency_log(transparency_log)?;
+        }
+        info!(
+            "Node {} has {} of {} approvals to {}",
+            proposal.node_id,
+            proposal.approvals.len(),
+            proposal.quorum,
+            match proposal.operation {
+                Operation::RemoveNode => "be removed",
+                _ => "be added",
+            }
+        );
+        Ok(proposal)
+    }
+
+    /// The proposal of a change to the authorized nodes, either AddNode or
+    /// RemoveNode, with the approvals of the nodes that are authorized now
+    /// since the last change of the node.
+    pub fn node_change_proposal(
+        &self,
+        operation: Operation,
+        node_id: &str,
+    ) -> Result<NodeChangeProposal, TransparencyLogError> {
+        self.node_change_proposal_including(operation, node_id, &[])
+    }
+
+    // The proposal of a node change as if the `pending` transparency logs
+    // were written too.
+    fn node_change_proposal_including(
+        &self,
+        operation: Operation,
+        node_id: &str,
+        pending: &[TransparencyLog],
+    ) -> Result<NodeChangeProposal, TransparencyLogError> {
+        let Some(approval_operation) = approval_operation(&operation) else {
+            return Err(TransparencyLogError::InvalidOperation {
+                id: node_id.to_owned(),
+                invalid_operation: operation,
+            });
+        };
+
+        let filter = LogFilter {
+            node_id: Some(node_id.to_owned()),
+            operations: vec![
+                Operation::AddNode,
+                Operation::RemoveNode,
+                approval_operation,
+            ],
+            ..Default::default()
+        };
+        let transparency_logs = self.find_logs_including(&filter, pending)?;
+        let last_change = transparency_logs
+            .iter()
+            .rposition(|transparency_log| {
+                matches!(
+                    transparency_log.operation,
+                    Operation::AddNode | Operation::RemoveNode
+                )
+            })
+            .map_or(0, |position| position + 1);
+
+        let authorized_nodes: HashSet<String> = self
+            .find_added_nodes_including(pending)?
+            .into_iter()
+            .map(|transparency_log| transparency_log.node_id)
+            .collect();
+        let approvals: BTreeSet<String> = transparency_logs[last_change..]
+            .iter()
+            .filter_map(approval_signer)
+            .map(|approver| approver.to_string())
+            .filter(|approver| authorized_nodes.contains(approver))
+            .collect();
+
+        Ok(NodeChangeProposal {
+            operation,
+            node_id: node_id.to_owned(),
+            approvals: approvals.into_iter().collect(),
+            quorum: quorum_size(authorized_nodes.len()),
+        })
+    }
+
+    /// The changes to the authorized nodes that were approved by at least
+    /// one authorized node, but not by a quorum yet.
+    pub fn list_node_change_proposals(
+        &self,
+    ) -> Result<Vec<NodeChangeProposal>, TransparencyLogError> {
+        let filter = LogFilter {
+            operations: vec![Operation::ApproveAddNode, Operation::ApproveRemoveNode],
+            ..Default::default()
+        };
+        let changes: BTreeSet<(String, bool)> = self
+            .find_logs(&filter, LogOrder::Written)?
+            .into_iter()
+            .map(|approval| {
+                let removal = approval.operation == Operation::ApproveRemoveNode;
+                (approval.node_id, removal)
+            })
+            .collect();
+
+        let mut proposals = Vec::new();
+        for (node_id, removal) in changes {
+            let operation = if removal {
+                Operation::RemoveNode
+            } else {
+                Operation::AddNode
+            };
+            let proposal = self.node_change_proposal(operation, &node_id)?;
+            if !proposal.approvals.is_empty() && !proposal.is_approved() {
+                proposals.push(proposal);
+            }
+        }
+        Ok(proposals)
     }
 
     /// Adds a transparency log with the AddArtifact operation
     /// and inserts according TransparencyLog record into the database.
+    /// An artifact with the same hash as an artifact that is already in the
+    /// database has the same artifact_id, so it is stored only once.
     pub async fn add_artifact(
         &self,
         add_artifact_request: AddArtifactRequest,
     ) -> Result<(TransparencyLog, String), TransparencyLogError> {
         let transparency_log = TransparencyLog::from(add_artifact_request);
 
-        let payload = serde_json::to_string(&transparency_log)?;
+        let payload = self.create_payload(&transparency_log)?;
         self.write_transparency_log(&transparency_log)?;
 
         Ok((transparency_log, payload))
     }
 
+    /// Creates transparency logs with the AddArtifact operation for all artifacts
+    /// of a package, without writing them to the database. The source that the
+    /// artifacts were built from is recorded as their provenance, together with
+    /// the pinning snapshot of the dependencies of the build. Returns the logs
+    /// together with a single payload that publishes all of them at once.
+    /// Artifacts with the same hash, within the package or in the database,
+    /// share their artifact_id.
+    pub fn stage_artifacts(
+        &self,
+        add_artifact_requests: Vec<AddArtifactRequest>,
+        source: Option<&BuildSource>,
+        pinning_snapshot: Option<&str>,
+    ) -> Result<(Vec<TransparencyLog>, String), TransparencyLogError> {
+        let mut transparency_logs: Vec<TransparencyLog> = Vec::new();
+        for add_artifact_request in add_artifact_requests {
+            let mut transparency_log = TransparencyLog::from(add_artifact_request);
+            if let Some(source) = source {
+                transparency_log.source_id = source.url.clone();
+                transparency_log.source_hash = source.commit.clone();
+            }
+            transparency_log.pinning_snapshot = pinning_snapshot.map(str::to_owned);
+            transparency_logs.push(transparency_log);
+        }
+
+        let published_logs: Vec<TransparencyLog> = transparency_logs
+            .iter()
+            .map(|transparency_log| self.published_log(transparency_log))
+            .collect();
+        let payload = serde_json::to_string(&published_logs)?;
+
+        Ok((transparency_logs, payload))
+    }
+
+    /// Inserts the staged transparency logs into the database in a single
+    /// transaction, so either all or none of them are written.
+    pub fn commit_transparency_logs(
+        &self,
+        transparency_logs: &[TransparencyLog],
+    ) -> Result<(), TransparencyLogError> {
+        self.verify_artifact_ids(transparency_logs)?;
+        self.log_store.insert(transparency_logs, false)?;
+        self.lookup_cache.invalidate();
+        self.log_written.send_replace(());
+        Ok(())
+    }
+
+    /// Deletes committed transparency logs whose publication failed.
+    pub fn rollback_transparency_logs(
+        &self,
+        transparency_logs: &[TransparencyLog],
+    ) -> Result<(), TransparencyLogError> {
+        let ids: Vec<String> = transparency_logs
+            .iter()
+            .map(|transparency_log| transparency_log.id.clone())
+            .collect();
+        self.log_store.delete(&ids)?;
+        self.lookup_cache.invalidate();
+        Ok(())
+    }
+
     pub async fn broadcast_artifacts(
         &mut self,
         payloads: Vec<String>,
@@ -252,52 +1081,383 @@ impl TransparencyLogService {
         Ok(())
     }
 
-    /// Write the transparency log
-    /// only if a record with the same `id` is not found in the database.
-    pub async fn write_if_not_exists(
-        &mut self,
-        log: &TransparencyLog,
-    ) -> Result<(), TransparencyLogError> {
-        if let Err(TransparencyLogError::LogNotFound { .. }) = self.find_transparency_log(&log.id) {
-            self.write_transparency_log(log)?;
+    /// Find the blocks of the local blockchain that contain the transparency
+    /// logs with the given ids. Transparency logs that are not found in any
+    /// block are not part of the result.
+    pub async fn find_blocks(
+        &self,
+        transparency_log_ids: &HashSet<String>,
+    ) -> Result<HashMap<String, Block>, TransparencyLogError> {
+        let mut blocks = HashMap::new();
+        let Some(last_ordinal) = self.last_block_ordinal().await else {
+            return Ok(blocks);
         };
 
-        Ok(())
-    }
+        for block in self
+            .blockchain_event_client
+            .pull_blocks_local(0, last_ordinal)
+            .await?
+        {
+            for payload in block.fetch_payload() {
+                for transparency_log in Self::parse_payload(&payload).unwrap_or_default() {
+                    if transparency_log_ids.contains(&transparency_log.id) {
+                        blocks.insert(transparency_log.id, block.clone());
+                    }
+                }
+            }
+        }
 
-    /// Adds a transparency log with the RemoveArtifact operation.
-    pub fn remove_artifact(
-        &mut self,
-        _package_type: &PackageType,
-        _package_specific_id: &str,
-    ) -> Result<(), TransparencyLogError> {
-        Ok(())
+        Ok(blocks)
     }
 
-    /// Gets the latest transparency log for the specified package of which the
-    /// operation is either AddArtifact or RemoveArtifact. Returns an error
-    /// when no transparency log could be found.
-    pub fn get_artifact(
-        &mut self,
-        package_type: &PackageType,
-        package_specific_artifact_id: &str,
-    ) -> Result<TransparencyLog, TransparencyLogError> {
-        self.read_transparency_log(package_type, package_specific_artifact_id)
+    /// Record the block that published the transparency logs in its
+    /// payloads, so that queries tell who committed them and when.
+    pub fn record_block(&self, block: &Block) -> Result<(), TransparencyLogError> {
+        let ids: Vec<String> = block
+            .fetch_payload()
+            .iter()
+            .flat_map(|payload| Self::parse_payload(payload).unwrap_or_default())
+            .map(|transparency_log| transparency_log.id)
+            .collect();
+        if ids.is_empty() {
+            return Ok(());
+        }
+        self.log_store
+            .add_block_metadata(&ids, &BlockMetadata::from(block))
     }
 
-    /// Search the transparency log database for a list of transparency logs using the
-    /// specified filter.
-    pub fn search_transparency_logs(
+    /// The block that published the transparency log with `id`, when it is
+    /// known.
+    pub fn find_block_metadata(
         &self,
-        package_type: &PackageType,
-        package_specific_id: &str,
-    ) -> Result<Vec<TransparencyLog>, TransparencyLogError> {
-        self.read_transparency_logs(package_type, package_specific_id)
+        id: &str,
+    ) -> Result<Option<BlockMetadata>, TransparencyLogError> {
+        Ok(self
+            .log_store
+            .find_block_metadata(&[id.to_owned()])?
+            .remove(id))
     }
 
-    /// Verifies that a specified package can be added to the transparency log database.
-    /// For that, the database should not contain the artifact yet, or if it does,
-    /// its latest operation is not RemoveArtifact. If that is not the case,
+    /// The most recent `limit` blocks of the local blockchain, newest first.
+    pub async fn recent_blocks(&self, limit: usize) -> Result<Vec<Block>, TransparencyLogError> {
+        let Some(last_ordinal) = self.last_block_ordinal().await else {
+            return Ok(vec![]);
+        };
+        if limit == 0 {
+            return Ok(vec![]);
+        }
+
+        let start = last_ordinal.saturating_sub(limit as Ordinal - 1);
+        let mut blocks = self
+            .blockchain_event_client
+            .pull_blocks_local(start, last_ordinal)
+            .await?;
+        blocks.reverse();
+        Ok(blocks)
+    }
+
+    /// Export all blocks of the local blockchain, to back it up or to seed
+    /// another node with it.
+    pub async fn export_blocks(&self) -> Result<Vec<u8>, TransparencyLogError> {
+        Ok(self.blockchain_event_client.export_blocks().await?)
+    }
+
+    /// Audit all blocks of the local blockchain and report the first invalid
+    /// block, e.g. to validate a node after restoring it from a backup.
+    pub async fn audit_blocks(&self) -> Result<ChainAudit, TransparencyLogError> {
+        Ok(self.blockchain_event_client.audit_blocks().await?)
+    }
+
+    /// Find the block of the local blockchain with the given ordinal or
+    /// header hash.
+    pub async fn find_block(
+        &self,
+        block_id: &BlockId,
+    ) -> Result<Option<Block>, TransparencyLogError> {
+        let Some(last_ordinal) = self.last_block_ordinal().await else {
+            return Ok(None);
+        };
+
+        match block_id {
+            BlockId::Ordinal(ordinal) if *ordinal > last_ordinal => Ok(None),
+            BlockId::Ordinal(ordinal) => Ok(self
+                .blockchain_event_client
+                .pull_blocks_local(*ordinal, *ordinal)
+                .await?
+                .pop()),
+            BlockId::Hash(hash) => Ok(self
+                .blockchain_event_client
+                .pull_blocks_local(0, last_ordinal)
+                .await?
+                .into_iter()
+                .find(|block| hex_hash(&block.header.hash()) == *hash)),
+        }
+    }
+
+    async fn last_block_ordinal(&self) -> Option<Ordinal> {
+        match self
+            .blockchain_event_client
+            .handle_query_block_ordinal_from_peer()
+            .await
+        {
+            Ok(last_ordinal) => Some(last_ordinal),
+            Err(error) => {
+                debug!("No blocks found in the local blockchain: {:?}", error);
+                None
+            }
+        }
+    }
+
+    /// Write the transparency log
+    /// only if a record with the same `id` is not found in the database.
+    /// Returns whether the transparency log was written.
+    pub async fn write_if_not_exists(
+        &mut self,
+        log: &TransparencyLog,
+    ) -> Result<bool, TransparencyLogError> {
+        if let Err(TransparencyLogError::LogNotFound { .. }) = self.find_transparency_log(&log.id) {
+            if !self.verify_node_change(log, &[])? {
+                warn!(
+                    "Transparency log {} with operation {} of node {} was not approved and is ignored",
+                    log.id, log.operation, log.node_id
+                );
+                return Ok(false);
+            }
+            self.write_transparency_log(log)?;
+            return Ok(true);
+        };
+
+        Ok(false)
+    }
+
+    /// Write the transparency logs of all payloads of a block, in order, that
+    /// are not found in the database yet. The logs are written as a whole,
+    /// in a single transaction of the log store: when one of them can not be
+    /// written, none of them is. Returns the transparency logs that were
+    /// written.
+    pub async fn write_all_if_not_exists(
+        &mut self,
+        transparency_logs: &[TransparencyLog],
+    ) -> Result<Vec<TransparencyLog>, TransparencyLogError> {
+        let mut pending: Vec<TransparencyLog> = vec![];
+        for log in transparency_logs {
+            if pending.iter().any(|pending_log| pending_log.id == log.id) {
+                continue;
+            }
+            let Err(TransparencyLogError::LogNotFound { .. }) = self.find_transparency_log(&log.id)
+            else {
+                continue;
+            };
+            // node changes are approved by the logs before them, also by
+            // the ones earlier in the same block
+            if !self.verify_node_change(log, &pending)? {
+                warn!(
+                    "Transparency log {} with operation {} of node {} was not approved and is ignored",
+                    log.id, log.operation, log.node_id
+                );
+                continue;
+            }
+            pending.push(log.clone());
+        }
+
+        if !pending.is_empty() {
+            self.commit_transparency_logs(&pending)?;
+        }
+        Ok(pending)
+    }
+
+    // Whether a transparency log of another node can be applied, as if the
+    // `pending` transparency logs were written: approvals must be signed,
+    // and changes to the authorized nodes, apart from the first authorized
+    // node, approved by a quorum of the authorized nodes.
+    fn verify_node_change(
+        &self,
+        log: &TransparencyLog,
+        pending: &[TransparencyLog],
+    ) -> Result<bool, TransparencyLogError> {
+        match log.operation {
+            Operation::ApproveAddNode | Operation::ApproveRemoveNode => {
+                Ok(approval_signer(log).is_some())
+            }
+            Operation::AddNode if self.find_added_nodes_including(pending)?.is_empty() => Ok(true),
+            Operation::AddNode | Operation::RemoveNode => Ok(self
+                .node_change_proposal_including(log.operation.clone(), &log.node_id, pending)?
+                .is_approved()),
+            _ => Ok(true),
+        }
+    }
+
+    /// Adds a transparency log with the RemoveArtifact operation for an artifact,
+    /// a tombstone that is published on the blockchain by the node with `node_id`.
+    /// Returns an error when the artifact is not in the transparency log or was
+    /// already removed.
+    pub async fn remove_artifact(
+        &self,
+        package_type: &PackageType,
+        package_specific_artifact_id: &str,
+        node_id: PeerId,
+    ) -> Result<TransparencyLog, TransparencyLogError> {
+        let latest_log = self.read_transparency_log(package_type, package_specific_artifact_id)?;
+
+        let transparency_log = TransparencyLog {
+            id: Uuid::new_v4().to_string(),
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap()
+                .as_secs(),
+            operation: Operation::RemoveArtifact,
+            node_id: node_id.to_string(),
+            node_public_key: Uuid::new_v4().to_string(),
+            successor: None,
+            pinning_snapshot: None,
+            signature: None,
+            ..latest_log
+        };
+
+        let payload = self.create_payload(&transparency_log)?;
+        self.blockchain_event_client
+            .add_block(payload.into_bytes())
+            .await?;
+
+        self.write_transparency_log(&transparency_log)?;
+        Ok(transparency_log)
+    }
+
+    /// Adds a transparency log with the RevokeArtifact operation for an artifact
+    /// that was compromised or must no longer be distributed, published on the
+    /// blockchain by the node with `node_id`. Unlike a RemoveArtifact tombstone,
+    /// a revocation applies to the content of the artifact: it is revoked under
+    /// every package coordinate that references its artifact_id, and it can not
+    /// be added again. Returns an error when the artifact is not in the
+    /// transparency log, or was already removed or revoked.
+    pub async fn revoke_artifact(
+        &self,
+        package_type: &PackageType,
+        package_specific_artifact_id: &str,
+        node_id: PeerId,
+    ) -> Result<TransparencyLog, TransparencyLogError> {
+        let latest_log = self.read_transparency_log(package_type, package_specific_artifact_id)?;
+
+        let transparency_log = TransparencyLog {
+            id: Uuid::new_v4().to_string(),
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap()
+                .as_secs(),
+            operation: Operation::RevokeArtifact,
+            node_id: node_id.to_string(),
+            node_public_key: Uuid::new_v4().to_string(),
+            successor: None,
+            pinning_snapshot: None,
+            signature: None,
+            ..latest_log
+        };
+
+        let payload = self.create_payload(&transparency_log)?;
+        self.blockchain_event_client
+            .add_block(payload.into_bytes())
+            .await?;
+
+        self.write_transparency_log(&transparency_log)?;
+        Ok(transparency_log)
+    }
+
+    /// Find the revocation of the artifact with the specified `artifact_id`.
+    /// Returns `None` when the artifact is not revoked.
+    pub fn find_revocation(
+        &self,
+        artifact_id: &str,
+    ) -> Result<Option<TransparencyLog>, TransparencyLogError> {
+        let filter = LogFilter {
+            artifact_id: Some(artifact_id.to_owned()),
+            operations: vec![Operation::RevokeArtifact],
+            ..Default::default()
+        };
+        let revocations = self
+            .log_store
+            .find(&filter, LogOrder::Timestamp, 0, Some(1))?;
+        Ok(revocations.into_iter().next())
+    }
+
+    /// Gets the latest transparency log for the specified package of which the
+    /// operation is either AddArtifact or RemoveArtifact. Returns an error
+    /// when no transparency log could be found, or when the artifact was
+    /// revoked. Successful lookups are cached until the next write.
+    pub fn get_artifact(
+        &mut self,
+        package_type: &PackageType,
+        package_specific_artifact_id: &str,
+    ) -> Result<TransparencyLog, TransparencyLogError> {
+        if let Some(transparency_log) = self
+            .lookup_cache
+            .get(package_type, package_specific_artifact_id)
+        {
+            return Ok(transparency_log);
+        }
+
+        let generation = self.lookup_cache.generation();
+        let transparency_log =
+            self.read_transparency_log(package_type, package_specific_artifact_id)?;
+        self.lookup_cache.insert(
+            generation,
+            package_type,
+            package_specific_artifact_id,
+            &transparency_log,
+        );
+        Ok(transparency_log)
+    }
+
+    /// Search the transparency log database for a list of transparency logs using the
+    /// specified filter.
+    pub fn search_transparency_logs(
+        &self,
+        package_type: &PackageType,
+        package_specific_id: &str,
+    ) -> Result<Vec<TransparencyLog>, TransparencyLogError> {
+        self.read_transparency_logs(package_type, package_specific_id)
+    }
+
+    /// Query the transparency log database for the page of transparency logs
+    /// that match every filter of `query`, of any operation, oldest first.
+    pub fn query_transparency_logs(
+        &self,
+        query: &TransparencyLogQuery,
+    ) -> Result<TransparencyLogPage, TransparencyLogError> {
+        let filter = LogFilter {
+            package_type: query.package_type,
+            package_specific_id_prefix: query.package_specific_id.clone(),
+            operations: query.operation.iter().cloned().collect(),
+            node_id: query.node_id.clone(),
+            hash: query.hash.clone(),
+            since: query.since,
+            until: query.until,
+            ..Default::default()
+        };
+
+        let total = self.log_store.count(&filter)?;
+        let transparency_logs = self.log_store.find(
+            &filter,
+            LogOrder::Timestamp,
+            query.offset,
+            Some(query.page_size()),
+        )?;
+        let ids: Vec<String> = transparency_logs
+            .iter()
+            .map(|transparency_log| transparency_log.id.clone())
+            .collect();
+        let blocks = self.log_store.find_block_metadata(&ids)?;
+
+        Ok(TransparencyLogPage {
+            total,
+            offset: query.offset,
+            transparency_logs,
+            blocks,
+        })
+    }
+
+    /// Verifies that a specified package can be added to the transparency log database.
+    /// For that, the database should not contain the artifact yet, or if it does,
+    /// its latest operation is not RemoveArtifact. If that is not the case,
     /// an ArtifactAlreadyExists error is returned
     pub fn verify_package_can_be_added_to_transparency_logs(
         &self,
@@ -325,635 +1485,2725 @@ impl TransparencyLogService {
         }
     }
 
-    /// Get a list of auth node PeerID. Return an error when no PeerID could be found.
-    pub fn get_authorized_nodes(&self) -> Result<Vec<PeerId>, TransparencyLogError> {
+    /// Claim the build of a package for the authorized node with `node_id`,
+    /// so that no other authorized node starts building it too. The claim
+    /// is a transparency log that is published on the blockchain. Blocks
+    /// are applied in the same order on every node, so of the claims that
+    /// were committed concurrently, the one in the first block wins.
+    ///
+    /// Returns the active claim of the build, which is the claim of another
+    /// node when that node claimed the build first. Fails when the package
+    /// already has artifacts.
+    pub async fn claim_build(
+        &self,
+        package_type: &PackageType,
+        package_specific_id: &str,
+        node_id: PeerId,
+    ) -> Result<TransparencyLog, TransparencyLogError> {
+        self.verify_package_can_be_added_to_transparency_logs(package_type, package_specific_id)?;
+        if let Some(claim) = self.find_active_build_claim(package_type, package_specific_id)? {
+            return Ok(claim);
+        }
+
+        let transparency_log = TransparencyLog {
+            id: Uuid::new_v4().to_string(),
+            package_type: Some(*package_type),
+            package_specific_id: package_specific_id.to_owned(),
+            num_artifacts: 0,
+            package_specific_artifact_id: String::from(""),
+            artifact_hash: String::from(""),
+            source_hash: String::from(""),
+            artifact_id: String::from(""),
+            source_id: String::from(""),
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap()
+                .as_secs(),
+            operation: Operation::ClaimBuild,
+            node_id: node_id.to_string(),
+            node_public_key: Uuid::new_v4().to_string(),
+            successor: None,
+            pinning_snapshot: None,
+            signature: None,
+        };
+
+        let payload = self.create_payload(&transparency_log)?;
+        self.blockchain_event_client
+            .add_block(payload.into_bytes())
+            .await?;
+        self.write_transparency_log(&transparency_log)?;
+
+        // a claim that was committed before this one, while it was being
+        // published, wins
         Ok(self
-            .find_added_nodes()?
-            .iter()
-            //Get PeerId in the correct format, ignoring parsing errors
-            .flat_map(|node| PeerId::from_str(&node.node_id))
-            .collect::<Vec<PeerId>>())
+            .find_active_build_claim(package_type, package_specific_id)?
+            .unwrap_or(transparency_log))
     }
 
-    /// Verifies that the database does not contain the node yet or node was removed.
-    /// If that is not the case, an NodeAlreadyExists error is returned.
-    pub fn verify_node_does_not_exist(&self, peer_id: &str) -> Result<(), TransparencyLogError> {
-        let query = format!(
-            "SELECT
-              operation
-            FROM TRANSPARENCYLOG
-            WHERE (operation = '{}' or operation = '{}') and node_id = '{}'
-            ORDER BY timestamp DESC limit 1",
-            Operation::AddNode,
-            Operation::RemoveNode,
-            peer_id
-        );
+    /// Release the claim of the build of a package by the authorized node
+    /// with `node_id`, e.g. when its build failed, so that another node
+    /// can build the package before the claim expires. Nothing is published
+    /// when the node does not hold the active claim.
+    pub async fn release_build_claim(
+        &self,
+        package_type: &PackageType,
+        package_specific_id: &str,
+        node_id: PeerId,
+    ) -> Result<(), TransparencyLogError> {
+        match self.find_active_build_claim(package_type, package_specific_id)? {
+            Some(claim) if claim.node_id == node_id.to_string() => {}
+            _ => return Ok(()),
+        }
 
-        let res = self
-            .open_db()?
-            .query_row(query.as_str(), [], |row| row.get::<usize, String>(0));
+        let transparency_log = TransparencyLog {
+            id: Uuid::new_v4().to_string(),
+            package_type: Some(*package_type),
+            package_specific_id: package_specific_id.to_owned(),
+            num_artifacts: 0,
+            package_specific_artifact_id: String::from(""),
+            artifact_hash: String::from(""),
+            source_hash: String::from(""),
+            artifact_id: String::from(""),
+            source_id: String::from(""),
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap()
+                .as_secs(),
+            operation: Operation::ReleaseBuild,
+            node_id: node_id.to_string(),
+            node_public_key: Uuid::new_v4().to_string(),
+            successor: None,
+            pinning_snapshot: None,
+            signature: None,
+        };
 
-        if let Ok(val) = res {
-            if val.eq("AddNode") {
-                return Err(TransparencyLogError::NodeAlreadyExists {
-                    node_id: peer_id.to_owned(),
-                });
+        let payload = self.create_payload(&transparency_log)?;
+        self.blockchain_event_client
+            .add_block(payload.into_bytes())
+            .await?;
+        self.write_transparency_log(&transparency_log)
+    }
+
+    /// Find the claim of the build of a package that is active: the first
+    /// claim since the package last got or lost artifacts, or since the
+    /// last claim was released, that did not expire yet.
+    ///
+    /// The transparency logs are ordered by the block that published them,
+    /// which is the same on every node, and logs that are not published in
+    /// a known block yet come last. Logs of the same block are ordered by
+    /// timestamp and then by id, so that all nodes agree on the claim that
+    /// wins, also when claims were created in the same second.
+    pub fn find_active_build_claim(
+        &self,
+        package_type: &PackageType,
+        package_specific_id: &str,
+    ) -> Result<Option<TransparencyLog>, TransparencyLogError> {
+        let mut transparency_logs = Vec::new();
+        for identifier in self.package_identifiers(package_specific_id) {
+            let filter = LogFilter {
+                package_type: Some(*package_type),
+                package_specific_id: Some(identifier),
+                operations: vec![
+                    Operation::AddArtifact,
+                    Operation::RemoveArtifact,
+                    Operation::ClaimBuild,
+                    Operation::ReleaseBuild,
+                ],
+                ..Default::default()
+            };
+            transparency_logs.append(&mut self.find_logs(&filter, LogOrder::Timestamp)?);
+        }
+        let ids: Vec<String> = transparency_logs
+            .iter()
+            .map(|transparency_log| transparency_log.id.clone())
+            .collect();
+        let blocks = self.log_store.find_block_metadata(&ids)?;
+        transparency_logs.sort_by(|a, b| {
+            let block_ordinal =
+                |id: &str| blocks.get(id).map_or(Ordinal::MAX, |block| block.ordinal);
+            (block_ordinal(&a.id), a.timestamp, &a.id).cmp(&(
+                block_ordinal(&b.id),
+                b.timestamp,
+                &b.id,
+            ))
+        });
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        let is_active =
+            |claim: &TransparencyLog| claim.timestamp + self.build_claim_ttl.as_secs() > now;
+        let mut active_claim = None;
+        for transparency_log in transparency_logs {
+            match transparency_log.operation {
+                Operation::ClaimBuild => {
+                    if !active_claim.as_ref().map_or(false, is_active) {
+                        active_claim = Some(transparency_log);
+                    }
+                }
+                // only the node that holds the claim can release it
+                Operation::ReleaseBuild => {
+                    if matches!(&active_claim, Some(claim) if claim.node_id == transparency_log.node_id)
+                    {
+                        active_claim = None;
+                    }
+                }
+                _ => active_claim = None,
             }
         }
-        Ok(())
+        Ok(active_claim.filter(is_active))
     }
 
-    fn open_db(&self) -> Result<Connection, TransparencyLogError> {
-        let mut db_path = self.storage_path.to_owned();
-        fs::create_dir_all(db_path.clone())?;
-        db_path.push("transparency_log.db");
-        let conn = Connection::open(db_path)?;
-        match conn.execute(
-            "CREATE TABLE IF NOT EXISTS TRANSPARENCYLOG (
-                id TEXT PRIMARY KEY,
-                package_type TEXT,
-                package_specific_id TEXT,
-                num_artifacts INTEGER,
-                package_specific_artifact_id TEXT,
-                artifact_hash TEXT,
-                source_hash TEXT,
-                artifact_id TEXT,
-                source_id TEXT,
-                timestamp INTEGER,
-                operation TEXT NOT NULL,
-                node_id TEXT,
-                node_public_key TEXT
-            )",
-            [],
-        ) {
-            Ok(_) => Ok(conn),
-            Err(err) => {
-                debug!("Error creating transparency log database table: {:?}", err);
-                Err(err.into())
+    /// Find the versions of a package that currently have artifacts in the
+    /// transparency log database. The package is identified by its package
+    /// specific id without version, e.g. `library/alpine` or `com.myorg:my-artifact`.
+    pub fn find_package_versions(
+        &self,
+        package_type: &PackageType,
+        package_name: &str,
+    ) -> Result<HashSet<String>, TransparencyLogError> {
+        let version_prefix = format!("{}:", package_name);
+        let filter = LogFilter {
+            package_type: Some(*package_type),
+            package_specific_id_prefix: Some(version_prefix.clone()),
+            operations: vec![Operation::AddArtifact, Operation::RemoveArtifact],
+            ..Default::default()
+        };
+
+        let mut versions = HashSet::new();
+        for transparency_log in self.find_logs(&filter, LogOrder::Timestamp)? {
+            let version = transparency_log.package_specific_id[version_prefix.len()..].to_owned();
+            if transparency_log.operation == Operation::AddArtifact {
+                versions.insert(version);
+            } else {
+                versions.remove(&version);
             }
         }
-    }
 
-    pub fn find_transparency_log(&self, id: &str) -> Result<TransparencyLog, TransparencyLogError> {
-        let query = ["SELECT * FROM TRANSPARENCYLOG WHERE id = '", id, "';"];
+        Ok(versions)
+    }
 
-        let results = self.process_query(query.join("").as_str())?;
+    /// Find the artifacts that are currently in the transparency log database,
+    /// i.e. of which the latest AddArtifact or RemoveArtifact log is an
+    /// AddArtifact log. The artifacts can be filtered by package type and by a
+    /// prefix of their package specific id. Returns the AddArtifact logs.
+    pub fn find_artifacts(
+        &self,
+        package_type: Option<&PackageType>,
+        package_specific_id_prefix: &str,
+    ) -> Result<Vec<TransparencyLog>, TransparencyLogError> {
+        let revocations = LogFilter {
+            operations: vec![Operation::RevokeArtifact],
+            ..Default::default()
+        };
+        let artifacts = LogFilter {
+            package_type: package_type.copied(),
+            package_specific_id_prefix: Some(package_specific_id_prefix.to_owned()),
+            operations: vec![Operation::AddArtifact, Operation::RemoveArtifact],
+            ..Default::default()
+        };
+        // revocations apply regardless of their position in the logs
+        let mut transparency_logs = self.find_logs(&revocations, LogOrder::Timestamp)?;
+        transparency_logs.append(&mut self.find_logs(&artifacts, LogOrder::Timestamp)?);
 
-        if results.len() == 1 {
-            Ok(results.into_iter().next().unwrap())
-        } else {
-            Err(TransparencyLogError::LogNotFound { id: id.to_owned() })
-        }
+        Ok(current_artifacts(transparency_logs))
     }
 
-    fn write_transparency_log(
+    /// Find the hashes that an artifact was ever added with, including the
+    /// hashes of artifacts that were removed since. An artifact that is
+    /// published again under the same package specific artifact id with a
+    /// different hash, like a moved docker tag, has more than one hash.
+    pub fn find_artifact_hashes(
         &self,
-        transparency_log: &TransparencyLog,
-    ) -> Result<(), TransparencyLogError> {
-        let conn = self.open_db()?;
-
-        match conn.execute(
-            "INSERT INTO TRANSPARENCYLOG (id, package_type, package_specific_id, num_artifacts, package_specific_artifact_id, artifact_hash, source_hash, artifact_id, source_id, timestamp, operation, node_id, node_public_key) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
-            params![
-                transparency_log.id,
-                transparency_log.package_type,
-                transparency_log.package_specific_id,
-                transparency_log.num_artifacts,
-                transparency_log.package_specific_artifact_id,
-                transparency_log.artifact_hash,
-                transparency_log.source_hash,
-                transparency_log.artifact_id,
-                transparency_log.source_id,
-                transparency_log.timestamp,
-                transparency_log.operation,
-                transparency_log.node_id,
-                transparency_log.node_public_key,
-            ],
-        ) {
-            Ok(_) => {
-                debug!(
-                    "Transparency log inserted into database with id: {}",
-                    transparency_log.id
-                );
-                Ok(())
-            }
-            Err(err) => Err(err.into()),
+        package_type: &PackageType,
+        package_specific_artifact_id: &str,
+    ) -> Result<HashSet<String>, TransparencyLogError> {
+        let mut artifact_hashes = HashSet::new();
+        for identifier in self.package_identifiers(package_specific_artifact_id) {
+            let filter = LogFilter {
+                package_type: Some(*package_type),
+                package_specific_artifact_id: Some(identifier),
+                operations: vec![Operation::AddArtifact],
+                ..Default::default()
+            };
+            let transparency_logs = self.find_logs(&filter, LogOrder::Written)?;
+            artifact_hashes.extend(
+                transparency_logs
+                    .into_iter()
+                    .map(|transparency_log| transparency_log.artifact_hash),
+            );
         }
+
+        Ok(artifact_hashes)
     }
 
-    fn read_transparency_log(
+    /// Find the AddArtifact and RemoveArtifact logs of an artifact, oldest
+    /// first. An artifact that is published again under the same package
+    /// specific artifact id, like a docker tag that is moved to another
+    /// manifest, has an AddArtifact log for every hash it referred to.
+    pub fn find_artifact_history(
         &self,
         package_type: &PackageType,
         package_specific_artifact_id: &str,
-    ) -> Result<TransparencyLog, TransparencyLogError> {
-        let query = [
-            "SELECT * FROM TRANSPARENCYLOG WHERE package_type = '",
-            &*package_type.to_string(),
-            "' AND package_specific_artifact_id = '",
-            package_specific_artifact_id,
-            "';",
-        ];
-        let results = self.process_query(query.join("").as_str())?;
-
-        let mut vector: Vec<TransparencyLog> = Vec::new();
-        for record in results {
-            if record.operation == Operation::AddArtifact
-                || record.operation == Operation::RemoveArtifact
-            {
-                vector.push(record);
-            }
+    ) -> Result<Vec<TransparencyLog>, TransparencyLogError> {
+        let mut transparency_logs = Vec::new();
+        for identifier in self.package_identifiers(package_specific_artifact_id) {
+            let filter = LogFilter {
+                package_type: Some(*package_type),
+                package_specific_artifact_id: Some(identifier),
+                operations: vec![Operation::AddArtifact, Operation::RemoveArtifact],
+                ..Default::default()
+            };
+            transparency_logs.append(&mut self.find_logs(&filter, LogOrder::Timestamp)?);
         }
 
-        vector.sort_by(|a, b| a.timestamp.cmp(&b.timestamp));
+        transparency_logs.sort_by_key(|transparency_log| transparency_log.timestamp);
+        Ok(transparency_logs)
+    }
 
-        let latest_record =
-            vector
-                .into_iter()
-                .next()
-                .ok_or(TransparencyLogError::ArtifactNotFound {
-                    package_type: *package_type,
-                    package_specific_artifact_id: package_specific_artifact_id.to_owned(),
-                })?;
+    /// Find the artifacts that are currently in the transparency log database
+    /// and reference the artifact with the specified `artifact_id`. The same
+    /// artifact can be published under several package coordinates, it must
+    /// be kept as long as any of them references it. Returns the AddArtifact
+    /// logs.
+    pub fn find_artifact_references(
+        &self,
+        artifact_id: &str,
+    ) -> Result<Vec<TransparencyLog>, TransparencyLogError> {
+        let filter = LogFilter {
+            artifact_id: Some(artifact_id.to_owned()),
+            operations: vec![
+                Operation::AddArtifact,
+                Operation::RemoveArtifact,
+                Operation::RevokeArtifact,
+            ],
+            ..Default::default()
+        };
 
-        if latest_record.operation == Operation::RemoveArtifact {
-            return Err(TransparencyLogError::InvalidOperation {
-                id: latest_record.id,
-                invalid_operation: latest_record.operation,
-            });
-        }
-        Ok(latest_record)
+        Ok(current_artifacts(
+            self.find_logs(&filter, LogOrder::Timestamp)?,
+        ))
     }
 
-    fn read_transparency_logs(
+    /// Count the current references of every artifact_id: the number of
+    /// package artifacts that were added with it and not removed since. An
+    /// artifact_id with more than one reference is a deduplicated artifact
+    /// that is stored once for several packages.
+    pub fn artifact_reference_counts(
+        &self,
+    ) -> Result<HashMap<String, usize>, TransparencyLogError> {
+        let mut reference_counts = HashMap::new();
+        for transparency_log in self.current_artifact_logs()? {
+            *reference_counts
+                .entry(transparency_log.artifact_id)
+                .or_insert(0) += 1;
+        }
+        Ok(reference_counts)
+    }
+
+    /// Find the package types of the package artifacts that currently
+    /// reference every artifact_id. A deduplicated artifact can be referenced
+    /// by packages of several types.
+    pub fn artifact_package_types(
+        &self,
+    ) -> Result<HashMap<String, HashSet<PackageType>>, TransparencyLogError> {
+        let mut package_types: HashMap<String, HashSet<PackageType>> = HashMap::new();
+        for transparency_log in self.current_artifact_logs()? {
+            let artifact_package_types = package_types
+                .entry(transparency_log.artifact_id)
+                .or_default();
+            artifact_package_types.extend(transparency_log.package_type);
+        }
+        Ok(package_types)
+    }
+
+    fn current_artifact_logs(&self) -> Result<Vec<TransparencyLog>, TransparencyLogError> {
+        let filter = LogFilter {
+            operations: vec![
+                Operation::AddArtifact,
+                Operation::RemoveArtifact,
+                Operation::RevokeArtifact,
+            ],
+            ..Default::default()
+        };
+        Ok(current_artifacts(
+            self.find_logs(&filter, LogOrder::Timestamp)?,
+        ))
+    }
+
+    /// The size and root hash of the Merkle tree over all transparency logs,
+    /// see [`merkle`].
+    pub fn tree_head(&self) -> Result<TreeHead, TransparencyLogError> {
+        let leaf_hashes: Vec<Hash> = self
+            .all_transparency_logs()?
+            .iter()
+            .map(log_leaf_hash)
+            .collect::<Result<_, _>>()?;
+        Ok(TreeHead {
+            tree_size: leaf_hashes.len() as u64,
+            root_hash: hex::encode(merkle::root_hash(&leaf_hashes)),
+        })
+    }
+
+    /// Prove that the latest transparency log that added the artifact with
+    /// `artifact_id` is included in the Merkle tree over all transparency
+    /// logs, so that an auditor can verify it against a tree head without
+    /// downloading the whole log.
+    pub fn get_inclusion_proof(
+        &self,
+        artifact_id: &str,
+    ) -> Result<TransparencyLogInclusionProof, TransparencyLogError> {
+        let transparency_logs = self.all_transparency_logs()?;
+        let leaf_index = transparency_logs
+            .iter()
+            .rposition(|transparency_log| {
+                transparency_log.artifact_id == artifact_id
+                    && transparency_log.operation == Operation::AddArtifact
+            })
+            .ok_or_else(|| TransparencyLogError::LogNotFound {
+                id: artifact_id.to_owned(),
+            })?;
+        let leaf_hashes: Vec<Hash> = transparency_logs
+            .iter()
+            .map(log_leaf_hash)
+            .collect::<Result<_, _>>()?;
+
+        Ok(TransparencyLogInclusionProof {
+            proof: InclusionProof {
+                leaf_index: leaf_index as u64,
+                tree_size: leaf_hashes.len() as u64,
+                root_hash: hex::encode(merkle::root_hash(&leaf_hashes)),
+                audit_path: merkle::audit_path(&leaf_hashes, leaf_index)
+                    .unwrap_or_default()
+                    .iter()
+                    .map(hex::encode)
+                    .collect(),
+            },
+            transparency_log: transparency_logs.into_iter().nth(leaf_index).unwrap(),
+        })
+    }
+
+    /// Sign a checkpoint of the current tree head, unless the latest
+    /// checkpoint already has the same tree head.
+    pub fn create_checkpoint(
+        &self,
+        keypair: &ed25519::Keypair,
+    ) -> Result<Checkpoint, TransparencyLogError> {
+        let tree_head = self.tree_head()?;
+        if let Some(checkpoint) = self.list_checkpoints()?.pop() {
+            if checkpoint.tree_size == tree_head.tree_size
+                && checkpoint.root_hash == tree_head.root_hash
+            {
+                return Ok(checkpoint);
+            }
+        }
+
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        let checkpoint = Checkpoint::sign(tree_head, timestamp, keypair);
+        self.log_store.add_checkpoint(&checkpoint)?;
+        debug!(
+            "Created checkpoint of transparency log with tree size {}",
+            checkpoint.tree_size
+        );
+        Ok(checkpoint)
+    }
+
+    /// All checkpoints, oldest first.
+    pub fn list_checkpoints(&self) -> Result<Vec<Checkpoint>, TransparencyLogError> {
+        self.log_store.checkpoints()
+    }
+
+    /// Create a checkpoint at every `interval`.
+    pub async fn run_checkpoint_scheduler(self, keypair: ed25519::Keypair, interval: Duration) {
+        loop {
+            tokio::time::sleep(interval).await;
+            if let Err(error) = self.create_checkpoint(&keypair) {
+                warn!(
+                    "Failed to create a transparency log checkpoint: {:?}",
+                    error
+                );
+            }
+        }
+    }
+
+    /// Prove that the tree of the first `new_size` transparency logs extends
+    /// the tree of the first `old_size` transparency logs, e.g. the trees of
+    /// two checkpoints.
+    pub fn get_consistency_proof(
+        &self,
+        old_size: u64,
+        new_size: u64,
+    ) -> Result<ConsistencyProof, TransparencyLogError> {
+        let leaf_hashes: Vec<Hash> = self
+            .all_transparency_logs()?
+            .iter()
+            .map(log_leaf_hash)
+            .collect::<Result<_, _>>()?;
+        let invalid_tree_size = || TransparencyLogError::InvalidTreeSize {
+            old_size,
+            new_size,
+            tree_size: leaf_hashes.len() as u64,
+        };
+        if new_size > leaf_hashes.len() as u64 {
+            return Err(invalid_tree_size());
+        }
+        let new_tree = &leaf_hashes[..new_size as usize];
+        let proof =
+            merkle::consistency_proof(new_tree, old_size as usize).ok_or_else(invalid_tree_size)?;
+
+        Ok(ConsistencyProof {
+            old_size,
+            old_root_hash: hex::encode(merkle::root_hash(&new_tree[..old_size as usize])),
+            new_size,
+            new_root_hash: hex::encode(merkle::root_hash(new_tree)),
+            proof: proof.iter().map(hex::encode).collect(),
+        })
+    }
+
+    /// Export all transparency logs, in the order in which they were
+    /// written, to a new file at `path`. The export can be audited offline
+    /// or imported into another node, see [`Self::import_log`]. Returns the
+    /// number of exported transparency logs.
+    pub fn export_log(
+        &self,
+        path: &Path,
+        format: LogFormat,
+    ) -> Result<usize, TransparencyLogError> {
+        let mut writer = io::BufWriter::new(fs::File::create(path)?);
+        let exported = self.write_log(&mut writer, format)?;
+        writer.flush()?;
+        Ok(exported)
+    }
+
+    /// Write all transparency logs, in the order in which they were written,
+    /// to `writer`. Returns the number of written transparency logs.
+    pub fn write_log<W: Write>(
+        &self,
+        writer: &mut W,
+        format: LogFormat,
+    ) -> Result<usize, TransparencyLogError> {
+        let transparency_logs = self.all_transparency_logs()?;
+        for transparency_log in &transparency_logs {
+            match format {
+                LogFormat::Jsonl => {
+                    serde_json::to_writer(&mut *writer, transparency_log)?;
+                    writer.write_all(b"\n")?;
+                }
+                LogFormat::Cbor => {
+                    cbor::write_value(writer, &serde_json::to_value(transparency_log)?)?
+                }
+            }
+        }
+        Ok(transparency_logs.len())
+    }
+
+    /// Import the transparency logs of an export at `path`, which can have
+    /// either format, e.g. to seed a new node without replaying the whole
+    /// blockchain. Transparency logs that are already in the database are
+    /// skipped, artifacts are verified as when they are added. Nothing is
+    /// imported when any transparency log is invalid. Returns the number of
+    /// imported transparency logs.
+    pub fn import_log(&self, path: &Path) -> Result<usize, TransparencyLogError> {
+        self.read_log(io::BufReader::new(fs::File::open(path)?))
+    }
+
+    /// Import the transparency logs of an export from `reader`, see
+    /// [`Self::import_log`].
+    pub fn read_log<R: BufRead>(&self, mut reader: R) -> Result<usize, TransparencyLogError> {
+        let format = match reader.fill_buf()?.first() {
+            None => return Ok(0),
+            Some(b'{') => LogFormat::Jsonl,
+            Some(initial_byte) if cbor::starts_map(*initial_byte) => LogFormat::Cbor,
+            Some(_) => {
+                return Err(TransparencyLogError::InvalidExport(
+                    "the export is neither JSON lines nor CBOR".to_owned(),
+                ))
+            }
+        };
+
+        let mut transparency_logs: Vec<TransparencyLog> = Vec::new();
+        match format {
+            LogFormat::Jsonl => {
+                for line in reader.lines() {
+                    let line = line?;
+                    if line.trim().is_empty() {
+                        continue;
+                    }
+                    transparency_logs.push(serde_json::from_str(&line)?);
+                }
+            }
+            LogFormat::Cbor => {
+                while let Some(value) = cbor::read_value(&mut reader)? {
+                    transparency_logs.push(serde_json::from_value(value)?);
+                }
+            }
+        }
+
+        self.import_transparency_logs(&transparency_logs)
+    }
+
+    /// Import `transparency_logs` that were received from a trusted node,
+    /// like a seed node. Transparency logs that are already in the database
+    /// are skipped, so an interrupted import can be repeated. Returns the
+    /// number of imported transparency logs.
+    pub fn import_transparency_logs(
+        &self,
+        transparency_logs: &[TransparencyLog],
+    ) -> Result<usize, TransparencyLogError> {
+        let mut ids = HashSet::new();
+        let mut new_logs = Vec::new();
+        for transparency_log in transparency_logs {
+            let existing = LogFilter {
+                id: Some(transparency_log.id.clone()),
+                ..Default::default()
+            };
+            if ids.insert(transparency_log.id.as_str()) && self.log_store.count(&existing)? == 0 {
+                new_logs.push(transparency_log.clone());
+            }
+        }
+
+        self.verify_artifact_ids(&new_logs)?;
+        let imported = self.log_store.insert(&new_logs, true)?;
+        self.lookup_cache.invalidate();
+        if imported > 0 {
+            self.log_written.send_replace(());
+        }
+
+        Ok(imported)
+    }
+
+    /// A page of all transparency logs in the order in which they were
+    /// written, which is the order of the leaves of the Merkle tree, so that
+    /// a node that imports them page by page keeps the same tree.
+    pub fn transparency_log_page(
+        &self,
+        offset: usize,
+        limit: usize,
+    ) -> Result<TransparencyLogPage, TransparencyLogError> {
+        let all = LogFilter::default();
+        let total = self.log_store.count(&all)?;
+        let transparency_logs =
+            self.log_store
+                .find(&all, LogOrder::Written, offset, Some(limit))?;
+
+        Ok(TransparencyLogPage {
+            total,
+            offset,
+            transparency_logs,
+            blocks: Default::default(),
+        })
+    }
+
+    /// The transparency logs that were written after `query.since`, in the
+    /// order in which they were written. When there are none yet, wait up to
+    /// `query.wait()` until new transparency logs are written, so consumers
+    /// that tail the transparency log do not have to poll it.
+    pub async fn watch_transparency_logs(
+        &self,
+        query: &TransparencyLogWatchQuery,
+    ) -> Result<TransparencyLogBatch, TransparencyLogError> {
+        let deadline = tokio::time::Instant::now() + query.wait();
+        // subscribe before reading, so a write in between is not missed
+        let mut log_written = self.log_written.subscribe();
+        loop {
+            let written_after = self
+                .log_store
+                .find_written_after(query.since, query.page_size())?;
+            if !written_after.is_empty() {
+                let next_cursor = written_after
+                    .last()
+                    .map_or(query.since, |(cursor, _)| *cursor);
+                return Ok(TransparencyLogBatch {
+                    transparency_logs: written_after.into_iter().map(|(_, log)| log).collect(),
+                    next_cursor,
+                });
+            }
+            if tokio::time::timeout_at(deadline, log_written.changed())
+                .await
+                .is_err()
+            {
+                return Ok(TransparencyLogBatch {
+                    transparency_logs: vec![],
+                    next_cursor: query.since,
+                });
+            }
+        }
+    }
+
+    /// Sign a snapshot of the current tree head and the last block of the
+    /// blockchain of this node, see [`LogSnapshot`]. The block ordinal is
+    /// read before the tree head, so a block that arrives in between may
+    /// be included in both the snapshot and the blocks after it, which is
+    /// harmless because transparency logs are only written once.
+    pub async fn create_snapshot(
+        &self,
+        keypair: &ed25519::Keypair,
+    ) -> Result<LogSnapshot, TransparencyLogError> {
+        let block_ordinal = self
+            .blockchain_event_client
+            .handle_query_block_ordinal_from_peer()
+            .await
+            .map_err(BlockchainError::AnyhowError)?;
+        let tree_head = self.tree_head()?;
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+
+        Ok(LogSnapshot::sign(
+            tree_head,
+            block_ordinal,
+            timestamp,
+            keypair,
+        ))
+    }
+
+    /// Import the transparency logs of a snapshot of another node, after
+    /// verifying that the snapshot is signed by a node that is authorized in
+    /// these transparency logs and that `transparency_logs` are exactly the
+    /// transparency logs that the snapshot covers. Transparency logs that
+    /// are already in the database are skipped. Returns the number of
+    /// imported transparency logs.
+    pub fn import_snapshot(
+        &self,
+        snapshot: &LogSnapshot,
+        transparency_logs: &[TransparencyLog],
+    ) -> Result<usize, TransparencyLogError> {
+        if !snapshot.verify() {
+            return Err(TransparencyLogError::InvalidSnapshot(
+                "the signature is invalid".to_owned(),
+            ));
+        }
+
+        let leaf_hashes: Vec<Hash> = transparency_logs
+            .iter()
+            .map(log_leaf_hash)
+            .collect::<Result<_, _>>()?;
+        if leaf_hashes.len() as u64 != snapshot.tree_size
+            || hex::encode(merkle::root_hash(&leaf_hashes)) != snapshot.root_hash
+        {
+            return Err(TransparencyLogError::InvalidSnapshot(format!(
+                "the transparency logs do not match tree size {} and root hash {}",
+                snapshot.tree_size, snapshot.root_hash
+            )));
+        }
+
+        let signer = snapshot.signer().map(|peer_id| peer_id.to_string());
+        if !signer.as_deref().map_or(false, |signer| {
+            authorized_node_ids(transparency_logs).contains(signer)
+        }) {
+            return Err(TransparencyLogError::InvalidSnapshot(format!(
+                "the signer {} is not an authorized node",
+                signer.unwrap_or_default()
+            )));
+        }
+
+        self.import_transparency_logs(transparency_logs)
+    }
+
+    // All transparency logs in the order in which they were written, which
+    // is the order of the leaves of the Merkle tree.
+    fn all_transparency_logs(&self) -> Result<Vec<TransparencyLog>, TransparencyLogError> {
+        self.find_logs(&LogFilter::default(), LogOrder::Written)
+    }
+
+    /// Rewrites the artifact_id of artifacts that were added before artifact
+    /// ids were derived from the artifact hash, see [`derive_artifact_id`].
+    /// Returns the previous and the new artifact_id of every migrated artifact,
+    /// so that stored artifacts can be moved to their new artifact_id.
+    pub fn migrate_artifact_ids(&self) -> Result<Vec<(String, String)>, TransparencyLogError> {
+        let filter = LogFilter {
+            operations: vec![Operation::AddArtifact, Operation::RemoveArtifact],
+            ..Default::default()
+        };
+        let mut artifact_ids = HashSet::new();
+        let mut replacements = Vec::new();
+        let mut migrated_artifact_ids = Vec::new();
+        for transparency_log in self.find_logs(&filter, LogOrder::Written)? {
+            let derived_artifact_id = derive_artifact_id(&transparency_log.artifact_hash);
+            if transparency_log.artifact_id != derived_artifact_id
+                && artifact_ids.insert((
+                    transparency_log.artifact_id.clone(),
+                    transparency_log.artifact_hash.clone(),
+                ))
+            {
+                migrated_artifact_ids.push((
+                    transparency_log.artifact_id.clone(),
+                    derived_artifact_id.clone(),
+                ));
+                replacements.push((
+                    transparency_log.artifact_id,
+                    transparency_log.artifact_hash,
+                    derived_artifact_id,
+                ));
+            }
+        }
+        self.log_store.replace_artifact_ids(&replacements)?;
+        self.lookup_cache.invalidate();
+
+        if !migrated_artifact_ids.is_empty() {
+            info!(
+                "Migrated {} artifact ids to artifact ids derived from the artifact hash",
+                migrated_artifact_ids.len()
+            );
+        }
+        Ok(migrated_artifact_ids)
+    }
+
+    /// Get a list of auth node PeerID. Return an error when no PeerID could be found.
+    pub fn get_authorized_nodes(&self) -> Result<Vec<PeerId>, TransparencyLogError> {
+        Ok(self
+            .find_added_nodes()?
+            .iter()
+            //Get PeerId in the correct format, ignoring parsing errors
+            .flat_map(|node| PeerId::from_str(&node.node_id))
+            .collect::<Vec<PeerId>>())
+    }
+
+    /// Verifies that the database does not contain the node yet or node was removed.
+    /// If that is not the case, an NodeAlreadyExists error is returned.
+    pub fn verify_node_does_not_exist(&self, peer_id: &str) -> Result<(), TransparencyLogError> {
+        let filter = LogFilter {
+            node_id: Some(peer_id.to_owned()),
+            operations: vec![Operation::AddNode, Operation::RemoveNode],
+            ..Default::default()
+        };
+        let latest_log = self.find_logs(&filter, LogOrder::Timestamp)?.pop();
+        let is_genesis_node = self
+            .genesis_nodes
+            .iter()
+            .any(|genesis_node| genesis_node.to_string() == peer_id);
+
+        if matches!(&latest_log, Some(log) if log.operation == Operation::AddNode)
+            || (latest_log.is_none() && is_genesis_node)
+        {
+            return Err(TransparencyLogError::NodeAlreadyExists {
+                node_id: peer_id.to_owned(),
+            });
+        }
+        Ok(())
+    }
+
+    pub fn find_transparency_log(&self, id: &str) -> Result<TransparencyLog, TransparencyLogError> {
+        let filter = LogFilter {
+            id: Some(id.to_owned()),
+            ..Default::default()
+        };
+
+        let results = self.find_logs(&filter, LogOrder::Written)?;
+
+        if results.len() == 1 {
+            Ok(results.into_iter().next().unwrap())
+        } else {
+            Err(TransparencyLogError::LogNotFound { id: id.to_owned() })
+        }
+    }
+
+    /// Find the transparency log that added the artifact with the specified `artifact_id`.
+    pub fn find_transparency_log_by_artifact_id(
+        &self,
+        artifact_id: &str,
+    ) -> Result<TransparencyLog, TransparencyLogError> {
+        let filter = LogFilter {
+            artifact_id: Some(artifact_id.to_owned()),
+            operations: vec![Operation::AddArtifact],
+            ..Default::default()
+        };
+
+        self.log_store
+            .find(&filter, LogOrder::Written, 0, Some(1))?
+            .into_iter()
+            .next()
+            .ok_or(TransparencyLogError::LogNotFound {
+                id: artifact_id.to_owned(),
+            })
+    }
+
+    fn write_transparency_log(
+        &self,
+        transparency_log: &TransparencyLog,
+    ) -> Result<(), TransparencyLogError> {
+        let transparency_logs = std::slice::from_ref(transparency_log);
+        self.verify_artifact_ids(transparency_logs)?;
+        self.log_store.insert(transparency_logs, false)?;
+        debug!(
+            "Transparency log inserted into database with id: {}",
+            transparency_log.id
+        );
+        self.lookup_cache.invalidate();
+        self.log_written.send_replace(());
+        Ok(())
+    }
+
+    // Verifies that the artifact_id of every artifact in `transparency_logs`
+    // is derived from its hash and is not used by an artifact with a
+    // different hash, in the database or earlier in `transparency_logs`.
+    fn verify_artifact_ids(
+        &self,
+        transparency_logs: &[TransparencyLog],
+    ) -> Result<(), TransparencyLogError> {
+        let mut artifact_hashes: HashMap<&str, &str> = HashMap::new();
+        for transparency_log in transparency_logs
+            .iter()
+            .filter(|transparency_log| is_artifact_operation(&transparency_log.operation))
+        {
+            if transparency_log.artifact_id != derive_artifact_id(&transparency_log.artifact_hash) {
+                return Err(TransparencyLogError::InvalidArtifactId {
+                    id: transparency_log.id.clone(),
+                    artifact_id: transparency_log.artifact_id.clone(),
+                });
+            }
+
+            let collision = match artifact_hashes.get(transparency_log.artifact_id.as_str()) {
+                Some(artifact_hash) => *artifact_hash != transparency_log.artifact_hash,
+                None => {
+                    let filter = LogFilter {
+                        artifact_id: Some(transparency_log.artifact_id.clone()),
+                        operations: vec![Operation::AddArtifact, Operation::RemoveArtifact],
+                        ..Default::default()
+                    };
+                    self.find_logs(&filter, LogOrder::Written)?
+                        .iter()
+                        .any(|log| log.artifact_hash != transparency_log.artifact_hash)
+                }
+            };
+            if collision {
+                return Err(TransparencyLogError::ArtifactIdCollision {
+                    artifact_id: transparency_log.artifact_id.clone(),
+                });
+            }
+            if transparency_log.operation != Operation::RevokeArtifact {
+                artifact_hashes.insert(
+                    &transparency_log.artifact_id,
+                    &transparency_log.artifact_hash,
+                );
+            }
+        }
+        Ok(())
+    }
+
+    fn read_transparency_log(
+        &self,
+        package_type: &PackageType,
+        package_specific_artifact_id: &str,
+    ) -> Result<TransparencyLog, TransparencyLogError> {
+        let latest_record = self
+            .find_artifact_history(package_type, package_specific_artifact_id)?
+            .pop()
+            .ok_or(TransparencyLogError::ArtifactNotFound {
+                package_type: *package_type,
+                package_specific_artifact_id: package_specific_artifact_id.to_owned(),
+            })?;
+
+        if latest_record.operation == Operation::RemoveArtifact {
+            return Err(TransparencyLogError::InvalidOperation {
+                id: latest_record.id,
+                invalid_operation: latest_record.operation,
+            });
+        }
+        if let Some(revocation) = self.find_revocation(&latest_record.artifact_id)? {
+            return Err(TransparencyLogError::ArtifactRevoked {
+                id: revocation.id,
+                artifact_id: revocation.artifact_id,
+            });
+        }
+        Ok(latest_record)
+    }
+
+    fn read_transparency_logs(
         &self,
         package_type: &PackageType,
         package_specific_id: &str,
     ) -> Result<Vec<TransparencyLog>, TransparencyLogError> {
-        let query = [
-            "SELECT * FROM TRANSPARENCYLOG WHERE package_type = '",
-            &*package_type.to_string(),
-            "' AND package_specific_id = '",
-            package_specific_id,
-            "';",
-        ];
-        let results = self.process_query(query.join("").as_str())?;
+        let mut results = Vec::new();
+        for identifier in self.package_identifiers(package_specific_id) {
+            let filter = LogFilter {
+                package_type: Some(*package_type),
+                package_specific_id: Some(identifier),
+                operations: vec![Operation::AddArtifact, Operation::RemoveArtifact],
+                ..Default::default()
+            };
+            results.append(&mut self.find_logs(&filter, LogOrder::Timestamp)?);
+        }
+
+        results.sort_by_key(|a| a.timestamp);
+
+        Ok(results)
+    }
+
+    // The AddNode logs of the nodes of which the latest AddNode or RemoveNode
+    // log is an AddNode log, ordered by node id.
+    fn find_added_nodes(&self) -> Result<Vec<TransparencyLog>, TransparencyLogError> {
+        self.find_added_nodes_including(&[])
+    }
+
+    // The authorized nodes as if the `pending` transparency logs were written.
+    fn find_added_nodes_including(
+        &self,
+        pending: &[TransparencyLog],
+    ) -> Result<Vec<TransparencyLog>, TransparencyLogError> {
+        let filter = LogFilter {
+            operations: vec![Operation::AddNode, Operation::RemoveNode],
+            ..Default::default()
+        };
+        let mut latest_logs = BTreeMap::new();
+        for genesis_node in &self.genesis_nodes {
+            let transparency_log = TransparencyLog {
+                timestamp: 0,
+                ..node_change_log(Operation::AddNode, genesis_node)
+            };
+            latest_logs.insert(transparency_log.node_id.clone(), transparency_log);
+        }
+        for transparency_log in self.find_logs_including(&filter, pending)? {
+            latest_logs.insert(transparency_log.node_id.clone(), transparency_log);
+        }
+
+        Ok(latest_logs
+            .into_values()
+            .filter(|transparency_log| transparency_log.operation == Operation::AddNode)
+            .collect())
+    }
+
+    fn find_logs(
+        &self,
+        filter: &LogFilter,
+        order: LogOrder,
+    ) -> Result<Vec<TransparencyLog>, TransparencyLogError> {
+        self.log_store.find(filter, order, 0, None)
+    }
+
+    // The transparency logs that match a filter by operations and node id,
+    // by timestamp, as if the `pending` transparency logs were written after
+    // the ones in the database.
+    fn find_logs_including(
+        &self,
+        filter: &LogFilter,
+        pending: &[TransparencyLog],
+    ) -> Result<Vec<TransparencyLog>, TransparencyLogError> {
+        let mut transparency_logs = self.find_logs(filter, LogOrder::Timestamp)?;
+        transparency_logs.extend(
+            pending
+                .iter()
+                .filter(|transparency_log| {
+                    filter.operations.contains(&transparency_log.operation)
+                        && filter
+                            .node_id
+                            .as_ref()
+                            .map_or(true, |node_id| *node_id == transparency_log.node_id)
+                })
+                .cloned(),
+        );
+        // a stable sort keeps the order in which they were written
+        transparency_logs.sort_by_key(|transparency_log| transparency_log.timestamp);
+        Ok(transparency_logs)
+    }
+}
+
+// A transparency log with a change to the authorized nodes, or an approval
+// of one, for the node with `peer_id`.
+fn node_change_log(operation: Operation, peer_id: &PeerId) -> TransparencyLog {
+    TransparencyLog {
+        id: Uuid::new_v4().to_string(),
+        package_type: None,
+        package_specific_id: String::from(""),
+        num_artifacts: 0,
+        package_specific_artifact_id: String::from(""),
+        artifact_hash: String::from(""),
+        source_hash: String::from(""),
+        artifact_id: String::from(""),
+        source_id: String::from(""),
+        timestamp: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs(),
+        operation,
+        node_id: peer_id.to_string(),
+        node_public_key: Uuid::new_v4().to_string(),
+        successor: None,
+        pinning_snapshot: None,
+        signature: None,
+    }
+}
+
+// Keep the AddArtifact logs of the artifacts of which the latest AddArtifact
+// or RemoveArtifact log, ordered by timestamp, is an AddArtifact log. Artifacts
+// with a RevokeArtifact log for their artifact_id are never kept.
+fn current_artifacts(transparency_logs: Vec<TransparencyLog>) -> Vec<TransparencyLog> {
+    let mut artifacts: HashMap<(Option<PackageType>, String), TransparencyLog> = HashMap::new();
+    let mut revoked_artifact_ids = HashSet::new();
+    for transparency_log in transparency_logs {
+        let key = (
+            transparency_log.package_type,
+            transparency_log.package_specific_artifact_id.clone(),
+        );
+        match transparency_log.operation {
+            Operation::AddArtifact => {
+                artifacts.insert(key, transparency_log);
+            }
+            Operation::RevokeArtifact => {
+                revoked_artifact_ids.insert(transparency_log.artifact_id);
+            }
+            _ => {
+                artifacts.remove(&key);
+            }
+        }
+    }
+
+    artifacts
+        .into_values()
+        .filter(|transparency_log| !revoked_artifact_ids.contains(&transparency_log.artifact_id))
+        .collect()
+}
+
+/// Derives the artifact_id of an artifact from its hash. The artifact_id is
+/// the hex encoded sha2-256 multihash of the artifact hash, so it always has
+/// the same length and starts with the multihash prefix `1220`, which storage
+/// backends may rely on, e.g. to shard artifacts by the first bytes of the
+/// digest.
+///
+/// The package coordinates are deliberately not part of the derivation: an
+/// artifact with the same content that is published under several coordinates
+/// has a single artifact_id and is therefore stored only once.
+pub fn derive_artifact_id(artifact_hash: &str) -> String {
+    hex::encode(Code::Sha2_256.digest(artifact_hash.as_bytes()).to_bytes())
+}
+
+fn log_leaf_hash(transparency_log: &TransparencyLog) -> Result<Hash, TransparencyLogError> {
+    Ok(merkle::leaf_hash(&serde_json::to_vec(transparency_log)?))
+}
+
+// The ids of the nodes that are authorized after `transparency_logs`.
+fn authorized_node_ids(transparency_logs: &[TransparencyLog]) -> HashSet<&str> {
+    let mut node_ids = HashSet::new();
+    for transparency_log in transparency_logs {
+        match transparency_log.operation {
+            Operation::AddNode => {
+                node_ids.insert(transparency_log.node_id.as_str());
+            }
+            Operation::RemoveNode => {
+                node_ids.remove(transparency_log.node_id.as_str());
+            }
+            _ => {}
+        }
+    }
+    node_ids
+}
+
+fn is_artifact_operation(operation: &Operation) -> bool {
+    matches!(
+        operation,
+        Operation::AddArtifact | Operation::RemoveArtifact | Operation::RevokeArtifact
+    )
+}
+
+#[cfg(test)]
+#[cfg(not(tarpaulin_include))]
+mod tests {
+    use super::*;
+    use crate::blockchain_service::event::BlockchainEvent;
+    use crate::util::test_util;
+    use libp2p::identity::Keypair;
+    use std::time::Duration;
+
+    #[test]
+    fn create_transparency_log() {
+        let tmp_dir = test_util::tests::setup();
+
+        let (log, _) = test_util::tests::create_transparency_log_service(tmp_dir);
+
+        let ps_art_id = "test_package_specific_artifact_id";
+
+        let transparency_log = TransparencyLog {
+            id: "test_id".to_string(),
+            package_type: Some(PackageType::Docker),
+            package_specific_id: "test_package_specific_id".to_string(),
+            num_artifacts: 10,
+            package_specific_artifact_id: ps_art_id.to_owned(),
+            artifact_hash: "test_artifact_hash".to_owned(),
+            source_hash: "test_source_hash".to_owned(),
+            artifact_id: derive_artifact_id("test_artifact_hash"),
+            source_id: "test_source_id".to_owned(),
+            timestamp: 1234567890,
+            operation: Operation::AddArtifact,
+            node_id: "test_node_id".to_owned(),
+            node_public_key: "test_node_public_key".to_owned(),
+            successor: None,
+            pinning_snapshot: None,
+            signature: None,
+        };
+
+        assert!(log.write_transparency_log(&transparency_log).is_ok());
+
+        let res = log
+            .read_transparency_log(&PackageType::Docker, ps_art_id)
+            .unwrap();
+
+        assert_eq!(transparency_log.id, res.id);
+        assert_eq!(transparency_log.package_type, res.package_type);
+        assert_eq!(
+            transparency_log.package_specific_id,
+            res.package_specific_id
+        );
+        assert_eq!(transparency_log.num_artifacts, res.num_artifacts);
+        assert_eq!(
+            transparency_log.package_specific_artifact_id,
+            res.package_specific_artifact_id
+        );
+        assert_eq!(transparency_log.artifact_hash, res.artifact_hash);
+        assert_eq!(transparency_log.source_hash, res.source_hash);
+        assert_eq!(transparency_log.artifact_id, res.artifact_id);
+        assert_eq!(transparency_log.source_id, res.source_id);
+        assert_eq!(transparency_log.timestamp, res.timestamp);
+        assert_eq!(transparency_log.operation, res.operation);
+        assert_eq!(transparency_log.node_id, res.node_id);
+        assert_eq!(transparency_log.node_public_key, res.node_public_key);
+    }
+
+    #[tokio::test]
+    async fn test_write_tranparency_log() {
+        let tmp_dir = test_util::tests::setup();
+
+        let (log, _) = test_util::tests::create_transparency_log_service(&tmp_dir);
+
+        let transparency_log = new_artifact_transparency_log_default();
+
+        let result = log.write_transparency_log(&transparency_log);
+        assert!(result.is_ok());
+
+        test_util::tests::teardown(tmp_dir);
+    }
+
+    #[tokio::test]
+    async fn test_write_all_if_not_exists() {
+        let tmp_dir = test_util::tests::setup();
+
+        let (mut log, _) = test_util::tests::create_transparency_log_service(&tmp_dir);
+
+        let existing_log = new_artifact_transparency_log_with_id("existing_id");
+        log.write_transparency_log(&existing_log).unwrap();
+        let first_log = new_artifact_transparency_log_with_id("first_id");
+        let second_log = new_artifact_transparency_log_with_id("second_id");
+
+        let written = log
+            .write_all_if_not_exists(&[existing_log, first_log.clone(), second_log])
+            .await
+            .unwrap();
+        assert_eq!(
+            written.iter().map(|l| l.id.as_str()).collect::<Vec<_>>(),
+            vec!["first_id", "second_id"]
+        );
+
+        let third_log = new_artifact_transparency_log_with_id("third_id");
+        let invalid_log = TransparencyLog {
+            artifact_id: "invalid_artifact_id".to_owned(),
+            ..new_artifact_transparency_log_with_id("invalid_id")
+        };
+        // the error of the invalid log is returned, and none of the logs is
+        // written
+        assert!(matches!(
+            log.write_all_if_not_exists(&[third_log.clone(), invalid_log])
+                .await,
+            Err(TransparencyLogError::InvalidArtifactId { .. })
+        ));
+        assert!(log.find_transparency_log("third_id").is_err());
+        assert!(log.find_transparency_log(&first_log.id).is_ok());
+
+        // a log that is repeated in a block is written once
+        let written = log
+            .write_all_if_not_exists(&[third_log.clone(), third_log])
+            .await
+            .unwrap();
+        assert_eq!(written.len(), 1);
+        assert!(log.find_transparency_log("third_id").is_ok());
+
+        test_util::tests::teardown(tmp_dir);
+    }
+
+    #[tokio::test]
+    async fn test_write_twice_transparency_log_error() {
+        let tmp_dir = test_util::tests::setup();
+
+        let (log, _) = test_util::tests::create_transparency_log_service(&tmp_dir);
+
+        let transparency_log = new_artifact_transparency_log_default();
+
+        let mut result = log.write_transparency_log(&transparency_log);
+        assert!(result.is_ok());
+        result = log.write_transparency_log(&transparency_log);
+        assert!(result.is_err());
+
+        test_util::tests::teardown(tmp_dir);
+    }
+
+    #[tokio::test]
+    async fn test_find_transparency_log() {
+        let tmp_dir = test_util::tests::setup();
+
+        let (log, _) = test_util::tests::create_transparency_log_service(&tmp_dir);
+
+        let id = "test_id";
+        let transparency_log = new_artifact_transparency_log_with_id(id);
+
+        let result_write = log.write_transparency_log(&transparency_log);
+        assert!(result_write.is_ok());
+
+        let result_find = log.find_transparency_log(id);
+        assert!(result_find.is_ok());
+
+        test_util::tests::teardown(tmp_dir);
+    }
+
+    #[tokio::test]
+    async fn test_find_transparency_log_not_found() {
+        let tmp_dir = test_util::tests::setup();
+
+        let (log, _) = test_util::tests::create_transparency_log_service(&tmp_dir);
+
+        let transparency_log = new_artifact_transparency_log_default();
+
+        let result_write = log.write_transparency_log(&transparency_log);
+        assert!(result_write.is_ok());
+
+        let find_error = log
+            .find_transparency_log("unknown_id")
+            .expect_err("Find transparency log should have failed.");
+        match find_error {
+            TransparencyLogError::LogNotFound { id } => {
+                assert_eq!("unknown_id".to_owned(), id);
+            }
+            e => {
+                panic!("Invalid Error encountered: {:?}", e);
+            }
+        }
+
+        test_util::tests::teardown(tmp_dir);
+    }
+
+    #[tokio::test]
+    async fn test_find_transparency_log_by_artifact_id() {
+        let tmp_dir = test_util::tests::setup();
+
+        let (log, _) = test_util::tests::create_transparency_log_service(&tmp_dir);
+
+        let transparency_log = new_artifact_transparency_log_default();
+
+        let result_write = log.write_transparency_log(&transparency_log);
+        assert!(result_write.is_ok());
+
+        let result_find = log
+            .find_transparency_log_by_artifact_id(&transparency_log.artifact_id)
+            .unwrap();
+        assert_eq!(result_find, transparency_log);
+        assert!(log
+            .find_transparency_log_by_artifact_id("unknown_artifact_id")
+            .is_err());
+
+        test_util::tests::teardown(tmp_dir);
+    }
+
+    #[tokio::test]
+    async fn test_read_transparency_log() {
+        let tmp_dir = test_util::tests::setup();
+
+        let (log, _) = test_util::tests::create_transparency_log_service(&tmp_dir);
+
+        let ps_art_id = "package_specific_artifact_id";
+        let transparency_log = new_artifact_transparency_log(
+            Some(PackageType::Maven2),
+            Operation::AddArtifact,
+            Some("package_specific_id"),
+            Some(ps_art_id),
+        );
+
+        assert!(log.write_transparency_log(&transparency_log).is_ok());
+
+        assert!(log
+            .read_transparency_log(&PackageType::Maven2, ps_art_id)
+            .is_ok());
+
+        test_util::tests::teardown(tmp_dir);
+    }
+
+    #[tokio::test]
+    async fn test_get_artifact_is_cached_until_next_write() {
+        let tmp_dir = test_util::tests::setup();
+
+        let (mut log, _) = test_util::tests::create_transparency_log_service(&tmp_dir);
+
+        let ps_art_id = "package_specific_artifact_id";
+        let transparency_log = new_artifact_transparency_log(
+            Some(PackageType::Maven2),
+            Operation::AddArtifact,
+            Some("package_specific_id"),
+            Some(ps_art_id),
+        );
+        log.write_transparency_log(&transparency_log).unwrap();
+        assert_eq!(
+            log.get_artifact(&PackageType::Maven2, ps_art_id).unwrap(),
+            transparency_log
+        );
+
+        // a removal that bypasses the service is not seen by the cached lookup
+        let removal = TransparencyLog {
+            id: Uuid::new_v4().to_string(),
+            operation: Operation::RemoveArtifact,
+            timestamp: transparency_log.timestamp + 1,
+            ..transparency_log.clone()
+        };
+        insert_legacy_transparency_log(&tmp_dir, &removal);
+        assert!(log.get_artifact(&PackageType::Maven2, ps_art_id).is_ok());
+
+        // any write invalidates the cached lookups
+        log.write_transparency_log(&new_artifact_transparency_log(
+            Some(PackageType::Maven2),
+            Operation::AddArtifact,
+            Some("other_package_specific_id"),
+            Some("other_package_specific_artifact_id"),
+        ))
+        .unwrap();
+        assert!(log.get_artifact(&PackageType::Maven2, ps_art_id).is_err());
+
+        test_util::tests::teardown(tmp_dir);
+    }
+
+    #[tokio::test]
+    async fn test_read_transparency_log_invalid_id() {
+        let tmp_dir = test_util::tests::setup();
+
+        let (log, _) = test_util::tests::create_transparency_log_service(&tmp_dir);
+
+        let transparency_log = new_artifact_transparency_log(
+            Some(PackageType::Maven2),
+            Operation::AddArtifact,
+            Some("package_specific_id"),
+            Some("package_specific_artifact_id"),
+        );
+
+        assert!(log.write_transparency_log(&transparency_log).is_ok());
+
+        let result_read =
+            log.read_transparency_log(&PackageType::Maven2, "invalid_package_specific_artifact_id");
+        assert!(result_read.is_err());
+        assert_eq!(
+            result_read.err().unwrap().to_string(),
+            TransparencyLogError::ArtifactNotFound {
+                package_type: PackageType::Maven2,
+                package_specific_artifact_id: String::from("invalid_package_specific_artifact_id"),
+            }
+            .to_string()
+        );
+
+        test_util::tests::teardown(tmp_dir);
+    }
+
+    #[tokio::test]
+    async fn test_read_latest_transparency_log() {
+        let tmp_dir = test_util::tests::setup();
+
+        let (log, _) = test_util::tests::create_transparency_log_service(&tmp_dir);
+
+        let transparency_log1 = new_artifact_transparency_log(
+            Some(PackageType::Maven2),
+            Operation::AddArtifact,
+            Some("package_specific_id"),
+            Some("package_specific_artifact_id"),
+        );
+
+        let result_write1 = log.write_transparency_log(&transparency_log1);
+        assert!(result_write1.is_ok());
+
+        let ps_art_id = "package_specific_artifact_id2";
+        let transparency_log2 = new_artifact_transparency_log(
+            Some(PackageType::Maven2),
+            Operation::AddArtifact,
+            Some("package_specific_id2"),
+            Some(ps_art_id),
+        );
+
+        assert!(log.write_transparency_log(&transparency_log2).is_ok());
+
+        assert!(log
+            .read_transparency_log(&PackageType::Maven2, ps_art_id)
+            .is_ok());
+
+        test_util::tests::teardown(tmp_dir);
+    }
+
+    #[tokio::test]
+    async fn test_read_transparency_logs() {
+        let tmp_dir = test_util::tests::setup();
+
+        let (log, _) = test_util::tests::create_transparency_log_service(&tmp_dir);
+
+        let ps_id = "package_specific_id";
+        let transparency_log1 = new_artifact_transparency_log(
+            Some(PackageType::Maven2),
+            Operation::AddArtifact,
+            Some(ps_id),
+            Some("package_specific_artifact_id"),
+        );
+
+        let result_write1 = log.write_transparency_log(&transparency_log1);
+        assert!(result_write1.is_ok());
+
+        let transparency_log2 = new_artifact_transparency_log(
+            Some(PackageType::Maven2),
+            Operation::AddArtifact,
+            Some(ps_id),
+            Some("package_specific_artifact_id2"),
+        );
+
+        assert!(log.write_transparency_log(&transparency_log2).is_ok());
+
+        let result_read1 = log.read_transparency_logs(&PackageType::Maven2, ps_id);
+        assert!(result_read1.is_ok());
+        assert_eq!(result_read1.unwrap().len(), 2);
+
+        let result_read2 =
+            log.read_transparency_logs(&PackageType::Maven2, "other_package_specific_id");
+        assert!(result_read2.is_ok());
+        assert_eq!(result_read2.unwrap().len(), 0);
+
+        test_util::tests::teardown(tmp_dir);
+    }
+
+    #[tokio::test]
+    async fn test_verify_artifact_can_be_added_to_transparency_logs() {
+        let tmp_dir = test_util::tests::setup();
+
+        let (log, _) = test_util::tests::create_transparency_log_service(&tmp_dir);
+        let result1 = log.verify_package_can_be_added_to_transparency_logs(
+            &PackageType::Docker,
+            "package_specific_id",
+        );
+        assert!(result1.is_ok());
+
+        let ps_id = "package_specific_id";
+        assert!(log
+            .verify_package_can_be_added_to_transparency_logs(&PackageType::Docker, ps_id)
+            .is_ok());
+
+        let transparency_log1 = new_artifact_transparency_log(
+            Some(PackageType::Docker),
+            Operation::AddArtifact,
+            Some(ps_id),
+            Some("package_specific_artifact_id"),
+        );
+        assert!(log.write_transparency_log(&transparency_log1).is_ok());
+
+        let result2 =
+            log.verify_package_can_be_added_to_transparency_logs(&PackageType::Docker, ps_id);
+        assert!(result2.is_err());
+        assert_eq!(
+            result2.err().unwrap().to_string(),
+            TransparencyLogError::ArtifactAlreadyExists {
+                package_type: PackageType::Docker,
+                package_specific_id: ps_id.to_string(),
+            }
+            .to_string()
+        );
+
+        let transparency_log2 = new_artifact_transparency_log(
+            Some(PackageType::Docker),
+            Operation::RemoveArtifact,
+            Some(ps_id),
+            Some("package_specific_artifact_id"),
+        );
+        assert!(log.write_transparency_log(&transparency_log2).is_ok());
+        assert!(log
+            .verify_package_can_be_added_to_transparency_logs(&PackageType::Docker, ps_id)
+            .is_ok());
+
+        test_util::tests::teardown(tmp_dir);
+    }
+
+    #[tokio::test]
+    async fn test_read_remove_artifact_transparency_log() {
+        let tmp_dir = test_util::tests::setup();
+
+        let (log, _) = test_util::tests::create_transparency_log_service(&tmp_dir);
+
+        let ps_art_id = "package_specific_artifact_id";
+        let transparency_log = new_artifact_transparency_log(
+            Some(PackageType::Maven2),
+            Operation::RemoveArtifact,
+            Some("package_specific_id"),
+            Some(ps_art_id),
+        );
+
+        assert!(log.write_transparency_log(&transparency_log).is_ok());
+
+        let result_read = log.read_transparency_log(&PackageType::Maven2, ps_art_id);
+        assert!(result_read.is_err());
+        assert_eq!(
+            result_read.err().unwrap().to_string(),
+            TransparencyLogError::InvalidOperation {
+                id: transparency_log.id,
+                invalid_operation: Operation::RemoveArtifact,
+            }
+            .to_string()
+        );
+
+        test_util::tests::teardown(tmp_dir);
+    }
+
+    #[tokio::test]
+    async fn test_remove_artifact() {
+        let tmp_dir = test_util::tests::setup();
+
+        let log =
+            test_util::tests::create_transparency_log_service_default_blockchain_handler(&tmp_dir);
+        let node_id = PeerId::random();
+        let package_specific_artifact_id = "com.myorg/my-artifact/1.0.0/my-artifact-1.0.0.jar";
+
+        let result = log
+            .remove_artifact(&PackageType::Maven2, package_specific_artifact_id, node_id)
+            .await;
+        assert!(matches!(
+            result,
+            Err(TransparencyLogError::ArtifactNotFound { .. })
+        ));
+
+        let added_log = log
+            .add_artifact(AddArtifactRequest {
+                package_type: PackageType::Maven2,
+                package_specific_id: "com.myorg:my-artifact:1.0.0".to_owned(),
+                num_artifacts: 1,
+                package_specific_artifact_id: package_specific_artifact_id.to_owned(),
+                artifact_hash: "artifact_hash".to_owned(),
+            })
+            .await
+            .unwrap()
+            .0;
+
+        let tombstone = log
+            .remove_artifact(&PackageType::Maven2, package_specific_artifact_id, node_id)
+            .await
+            .unwrap();
+        assert_eq!(tombstone.operation, Operation::RemoveArtifact);
+        assert_eq!(tombstone.artifact_id, added_log.artifact_id);
+        assert_eq!(tombstone.node_id, node_id.to_string());
+        assert_eq!(log.find_transparency_log(&tombstone.id).unwrap(), tombstone);
+        assert!(matches!(
+            log.read_transparency_log(&PackageType::Maven2, package_specific_artifact_id),
+            Err(TransparencyLogError::InvalidOperation { .. })
+        ));
+        assert!(log
+            .verify_package_can_be_added_to_transparency_logs(
+                &PackageType::Maven2,
+                "com.myorg:my-artifact:1.0.0"
+            )
+            .is_ok());
+
+        let result = log
+            .remove_artifact(&PackageType::Maven2, package_specific_artifact_id, node_id)
+            .await;
+        assert!(matches!(
+            result,
+            Err(TransparencyLogError::InvalidOperation { .. })
+        ));
+
+        test_util::tests::teardown(tmp_dir);
+    }
+
+    #[tokio::test]
+    async fn test_revoke_artifact() {
+        let tmp_dir = test_util::tests::setup();
+
+        let mut log =
+            test_util::tests::create_transparency_log_service_default_blockchain_handler(&tmp_dir);
+        let node_id = PeerId::random();
+        let package_specific_artifact_id = "com.myorg/my-artifact/1.0.0/my-artifact-1.0.0.jar";
+        let copied_artifact_id = "com.myorg/my-copy/1.0.0/my-copy-1.0.0.jar";
+
+        for (package_specific_id, package_specific_artifact_id) in [
+            ("com.myorg:my-artifact:1.0.0", package_specific_artifact_id),
+            ("com.myorg:my-copy:1.0.0", copied_artifact_id),
+        ] {
+            log.add_artifact(AddArtifactRequest {
+                package_type: PackageType::Maven2,
+                package_specific_id: package_specific_id.to_owned(),
+                num_artifacts: 1,
+                package_specific_artifact_id: package_specific_artifact_id.to_owned(),
+                artifact_hash: "artifact_hash".to_owned(),
+            })
+            .await
+            .unwrap();
+        }
+
+        let revocation = log
+            .revoke_artifact(&PackageType::Maven2, package_specific_artifact_id, node_id)
+            .await
+            .unwrap();
+        assert_eq!(revocation.operation, Operation::RevokeArtifact);
+        assert_eq!(revocation.node_id, node_id.to_string());
+        assert_eq!(
+            log.find_revocation(&revocation.artifact_id).unwrap(),
+            Some(revocation.clone())
+        );
+        for package_specific_artifact_id in [package_specific_artifact_id, copied_artifact_id] {
+            assert!(matches!(
+                log.get_artifact(&PackageType::Maven2, package_specific_artifact_id),
+                Err(TransparencyLogError::ArtifactRevoked { .. })
+            ));
+        }
+        assert!(log
+            .find_artifact_references(&revocation.artifact_id)
+            .unwrap()
+            .is_empty());
+        assert!(log.find_artifacts(None, "com.myorg").unwrap().is_empty());
+
+        let result = log
+            .revoke_artifact(&PackageType::Maven2, package_specific_artifact_id, node_id)
+            .await;
+        assert!(matches!(
+            result,
+            Err(TransparencyLogError::ArtifactRevoked { .. })
+        ));
+
+        test_util::tests::teardown(tmp_dir);
+    }
+
+    #[tokio::test]
+    async fn test_get_inclusion_proof() {
+        let tmp_dir = test_util::tests::setup();
+
+        let log =
+            test_util::tests::create_transparency_log_service_default_blockchain_handler(&tmp_dir);
+        let mut transparency_logs = vec![];
+        for version in ["1.0.0", "1.1.0", "1.2.0"] {
+            let transparency_log = log
+                .add_artifact(AddArtifactRequest {
+                    package_type: PackageType::Maven2,
+                    package_specific_id: format!("com.myorg:my-artifact:{}", version),
+                    num_artifacts: 1,
+                    package_specific_artifact_id: format!(
+                        "com/myorg/my-artifact/{0}/my-artifact-{0}.jar",
+                        version
+                    ),
+                    artifact_hash: format!("artifact_hash_{}", version),
+                })
+                .await
+                .unwrap()
+                .0;
+            transparency_logs.push(transparency_log);
+        }
+
+        let inclusion_proof = log
+            .get_inclusion_proof(&transparency_logs[1].artifact_id)
+            .unwrap();
+        let tree_head = log.tree_head().unwrap();
+
+        assert_eq!(inclusion_proof.transparency_log, transparency_logs[1]);
+        assert_eq!(inclusion_proof.proof.tree_size, tree_head.tree_size);
+        assert_eq!(inclusion_proof.proof.root_hash, tree_head.root_hash);
+        assert!(inclusion_proof.verify());
+
+        let mut tampered_proof = inclusion_proof.clone();
+        tampered_proof.transparency_log.artifact_hash = "other_artifact_hash".to_owned();
+        assert!(!tampered_proof.verify());
+
+        assert!(matches!(
+            log.get_inclusion_proof("unknown_artifact_id"),
+            Err(TransparencyLogError::LogNotFound { .. })
+        ));
+
+        test_util::tests::teardown(tmp_dir);
+    }
+
+    #[tokio::test]
+    async fn test_checkpoints_and_consistency_proof() {
+        let tmp_dir = test_util::tests::setup();
+
+        let log =
+            test_util::tests::create_transparency_log_service_default_blockchain_handler(&tmp_dir);
+        let Keypair::Ed25519(keypair) = Keypair::generate_ed25519();
+        let add_artifact = |version: &str| AddArtifactRequest {
+            package_type: PackageType::Maven2,
+            package_specific_id: format!("com.myorg:my-artifact:{}", version),
+            num_artifacts: 1,
+            package_specific_artifact_id: format!(
+                "com/myorg/my-artifact/{0}/my-artifact-{0}.jar",
+                version
+            ),
+            artifact_hash: format!("artifact_hash_{}", version),
+        };
 
-        let mut vector: Vec<TransparencyLog> = Vec::new();
-        for record in results {
-            if record.operation == Operation::AddArtifact
-                || record.operation == Operation::RemoveArtifact
-            {
-                vector.push(record);
-            }
+        log.add_artifact(add_artifact("1.0.0")).await.unwrap();
+        let old_checkpoint = log.create_checkpoint(&keypair).unwrap();
+        assert_eq!(log.create_checkpoint(&keypair).unwrap(), old_checkpoint);
+        for version in ["1.1.0", "1.2.0"] {
+            log.add_artifact(add_artifact(version)).await.unwrap();
         }
+        let new_checkpoint = log.create_checkpoint(&keypair).unwrap();
+
+        assert_eq!(
+            log.list_checkpoints().unwrap(),
+            vec![old_checkpoint.clone(), new_checkpoint.clone()]
+        );
+        assert!(new_checkpoint.verify());
+        let mut forged_checkpoint = new_checkpoint.clone();
+        forged_checkpoint.tree_size += 1;
+        assert!(!forged_checkpoint.verify());
 
-        vector.sort_by(|a, b| a.timestamp.cmp(&b.timestamp));
+        let consistency_proof = log
+            .get_consistency_proof(old_checkpoint.tree_size, new_checkpoint.tree_size)
+            .unwrap();
+        assert_eq!(consistency_proof.old_root_hash, old_checkpoint.root_hash);
+        assert_eq!(consistency_proof.new_root_hash, new_checkpoint.root_hash);
+        assert!(consistency_proof.verify());
+
+        assert!(matches!(
+            log.get_consistency_proof(2, 4),
+            Err(TransparencyLogError::InvalidTreeSize { .. })
+        ));
+        assert!(matches!(
+            log.get_consistency_proof(3, 2),
+            Err(TransparencyLogError::InvalidTreeSize { .. })
+        ));
 
-        Ok(vector)
+        test_util::tests::teardown(tmp_dir);
     }
 
-    fn find_added_nodes(&self) -> Result<Vec<TransparencyLog>, TransparencyLogError> {
-        let select = format!(
-            "SELECT * FROM (
-              SELECT
-               id, package_type, package_specific_id,
-               num_artifacts, package_specific_artifact_id, artifact_hash, source_hash, artifact_id,
-               source_id, max(timestamp), operation, node_id, node_public_key
-              FROM TRANSPARENCYLOG
-              WHERE operation = '{}' or operation = '{}'
-              GROUP BY node_id
-            ) WHERE operation = '{}'",
-            Operation::AddNode,
-            Operation::RemoveNode,
-            Operation::AddNode
+    #[tokio::test]
+    async fn test_create_and_import_snapshot() {
+        let tmp_dir = test_util::tests::setup();
+
+        let (log, _ledger) =
+            crate::test_support::fake_transparency_log_service(tmp_dir.join("source")).unwrap();
+        let Keypair::Ed25519(keypair) = Keypair::generate_ed25519();
+        let signer = PeerId::from_public_key(&PublicKey::Ed25519(keypair.public()));
+        log.add_authorized_node(signer).await.unwrap();
+        log.add_artifact(AddArtifactRequest {
+            package_type: PackageType::Maven2,
+            package_specific_id: "com.myorg:my-artifact:1.0.0".to_owned(),
+            num_artifacts: 1,
+            package_specific_artifact_id: "com/myorg/my-artifact/1.0.0/my-artifact-1.0.0.jar"
+                .to_owned(),
+            artifact_hash: "artifact_hash".to_owned(),
+        })
+        .await
+        .unwrap();
+        let transparency_logs = log
+            .transparency_log_page(0, MAX_LOG_PAGE_SIZE)
+            .unwrap()
+            .transparency_logs;
+
+        let snapshot = log.create_snapshot(&keypair).await.unwrap();
+        assert_eq!(snapshot.tree_size, 2);
+        assert_eq!(snapshot.root_hash, log.tree_head().unwrap().root_hash);
+        assert_eq!(snapshot.signer(), Some(signer));
+        assert!(snapshot.verify());
+
+        let (new_log, _ledger) =
+            crate::test_support::fake_transparency_log_service(tmp_dir.join("target")).unwrap();
+        assert!(matches!(
+            new_log.import_snapshot(&snapshot, &transparency_logs[..1]),
+            Err(TransparencyLogError::InvalidSnapshot(_))
+        ));
+        let mut forged_snapshot = snapshot.clone();
+        forged_snapshot.block_ordinal += 1;
+        assert!(matches!(
+            new_log.import_snapshot(&forged_snapshot, &transparency_logs),
+            Err(TransparencyLogError::InvalidSnapshot(_))
+        ));
+        let Keypair::Ed25519(unauthorized_keypair) = Keypair::generate_ed25519();
+        let unauthorized_snapshot = LogSnapshot::sign(
+            log.tree_head().unwrap(),
+            snapshot.block_ordinal,
+            snapshot.timestamp,
+            &unauthorized_keypair,
         );
+        assert!(matches!(
+            new_log.import_snapshot(&unauthorized_snapshot, &transparency_logs),
+            Err(TransparencyLogError::InvalidSnapshot(_))
+        ));
 
-        let res = self.process_query(select.as_str())?;
-
-        Ok(res)
-    }
-
-    fn process_query(&self, query: &str) -> Result<Vec<TransparencyLog>, TransparencyLogError> {
-        let conn = self.open_db()?;
-        let mut stmt = conn.prepare(query)?;
-
-        let transparency_log_records = stmt.query_map([], |row| {
-            Ok(TransparencyLog {
-                id: row.get(0)?,
-                package_type: {
-                    let value: Value = row.get(1)?;
-                    match value {
-                        Value::Text(pt) => Ok(Some(PackageType::from_str(&pt).unwrap())),
-                        Value::Null => Ok(None),
-                        _ => Err(rusqlite::Error::InvalidColumnType(
-                            1,
-                            "package_type".to_owned(),
-                            value.data_type(),
-                        )),
-                    }?
-                },
-                package_specific_id: row.get(2)?,
-                num_artifacts: row.get(3)?,
-                package_specific_artifact_id: row.get(4)?,
-                artifact_hash: row.get(5)?,
-                source_hash: row.get(6)?,
-                artifact_id: row.get(7)?,
-                source_id: row.get(8)?,
-                timestamp: row.get(9)?,
-                operation: {
-                    let op: String = row.get(10)?;
-                    Operation::from_str(&op).unwrap()
-                },
-                node_id: row.get(11)?,
-                node_public_key: row.get(12)?,
-            })
-        })?;
+        assert_eq!(
+            new_log
+                .import_snapshot(&snapshot, &transparency_logs)
+                .unwrap(),
+            2
+        );
+        assert_eq!(new_log.tree_head().unwrap().root_hash, snapshot.root_hash);
+        assert_eq!(
+            new_log
+                .import_snapshot(&snapshot, &transparency_logs)
+                .unwrap(),
+            0
+        );
+
+        test_util::tests::teardown(tmp_dir);
+    }
+
+    #[tokio::test]
+    async fn test_export_and_import_log() {
+        let tmp_dir = test_util::tests::setup();
+        let import_tmp_dir = test_util::tests::setup();
 
-        let mut vector: Vec<TransparencyLog> = Vec::new();
-        for transparency_log_record in transparency_log_records {
-            vector.push(transparency_log_record?);
+        let log =
+            test_util::tests::create_transparency_log_service_default_blockchain_handler(&tmp_dir);
+        for version in ["1.0.0", "1.1.0"] {
+            log.add_artifact(AddArtifactRequest {
+                package_type: PackageType::Maven2,
+                package_specific_id: format!("com.myorg:my-artifact:{}", version),
+                num_artifacts: 1,
+                package_specific_artifact_id: format!(
+                    "com/myorg/my-artifact/{0}/my-artifact-{0}.jar",
+                    version
+                ),
+                artifact_hash: format!("artifact_hash_{}", version),
+            })
+            .await
+            .unwrap();
         }
+        let transparency_logs = log.all_transparency_logs().unwrap();
 
-        Ok(vector)
-    }
-}
+        for format in [LogFormat::Jsonl, LogFormat::Cbor] {
+            let path = tmp_dir.join(format!("export.{}", format));
+            assert_eq!(log.export_log(&path, format).unwrap(), 2);
 
-#[cfg(test)]
-#[cfg(not(tarpaulin_include))]
-mod tests {
-    use super::*;
-    use crate::blockchain_service::event::BlockchainEvent;
-    use crate::util::test_util;
-    use libp2p::identity::Keypair;
-    use std::time::Duration;
+            let import_path = import_tmp_dir.join(format.to_string());
+            fs::create_dir_all(&import_path).unwrap();
+            let imported_log =
+                test_util::tests::create_transparency_log_service_default_blockchain_handler(
+                    &import_path,
+                );
+            assert_eq!(imported_log.import_log(&path).unwrap(), 2);
+            assert_eq!(
+                imported_log.all_transparency_logs().unwrap(),
+                transparency_logs
+            );
+            assert_eq!(
+                imported_log.tree_head().unwrap().root_hash,
+                log.tree_head().unwrap().root_hash
+            );
+            assert_eq!(imported_log.import_log(&path).unwrap(), 0);
+        }
 
-    #[test]
-    fn create_transparency_log() {
-        let tmp_dir = test_util::tests::setup();
+        assert!(matches!(
+            log.read_log(io::Cursor::new("not an export")),
+            Err(TransparencyLogError::InvalidExport(_))
+        ));
+        assert_eq!(log.read_log(io::Cursor::new("")).unwrap(), 0);
 
-        let (log, _) = test_util::tests::create_transparency_log_service(tmp_dir);
+        test_util::tests::teardown(tmp_dir);
+        test_util::tests::teardown(import_tmp_dir);
+    }
 
-        let ps_art_id = "test_package_specific_artifact_id";
+    #[tokio::test]
+    async fn test_artifacts_with_same_hash_share_artifact_id() {
+        let tmp_dir = test_util::tests::setup();
 
-        let transparency_log = TransparencyLog {
-            id: "test_id".to_string(),
-            package_type: Some(PackageType::Docker),
-            package_specific_id: "test_package_specific_id".to_string(),
-            num_artifacts: 10,
-            package_specific_artifact_id: ps_art_id.to_owned(),
-            artifact_hash: "test_artifact_hash".to_owned(),
-            source_hash: "test_source_hash".to_owned(),
-            artifact_id: "test_artifact_id".to_owned(),
-            source_id: "test_source_id".to_owned(),
-            timestamp: 1234567890,
-            operation: Operation::AddArtifact,
-            node_id: "test_node_id".to_owned(),
-            node_public_key: "test_node_public_key".to_owned(),
+        let log =
+            test_util::tests::create_transparency_log_service_default_blockchain_handler(&tmp_dir);
+        let add_artifact_request = |group_id: &str, artifact_hash: &str| AddArtifactRequest {
+            package_type: PackageType::Maven2,
+            package_specific_id: format!("{}:my-artifact:1.0.0", group_id),
+            num_artifacts: 1,
+            package_specific_artifact_id: format!(
+                "{}/my-artifact/1.0.0/my-artifact-1.0.0.jar",
+                group_id.replace('.', "/")
+            ),
+            artifact_hash: artifact_hash.to_owned(),
         };
 
-        assert!(log.write_transparency_log(&transparency_log).is_ok());
-
-        let res = log
-            .read_transparency_log(&PackageType::Docker, ps_art_id)
+        let original_log = log
+            .add_artifact(add_artifact_request("com.oldorg", "artifact_hash"))
+            .await
+            .unwrap()
+            .0;
+        let (relocated_logs, _) = log
+            .stage_artifacts(
+                vec![
+                    add_artifact_request("com.neworg", "artifact_hash"),
+                    add_artifact_request("com.otherorg", "other_artifact_hash"),
+                    add_artifact_request("com.thirdorg", "other_artifact_hash"),
+                ],
+                None,
+                None,
+            )
             .unwrap();
+        log.commit_transparency_logs(&relocated_logs).unwrap();
 
-        assert_eq!(transparency_log.id, res.id);
-        assert_eq!(transparency_log.package_type, res.package_type);
+        assert_eq!(relocated_logs[0].artifact_id, original_log.artifact_id);
+        assert_ne!(relocated_logs[1].artifact_id, original_log.artifact_id);
+        assert_eq!(relocated_logs[2].artifact_id, relocated_logs[1].artifact_id);
         assert_eq!(
-            transparency_log.package_specific_id,
-            res.package_specific_id
+            log.find_artifact_references(&original_log.artifact_id)
+                .unwrap()
+                .len(),
+            2
         );
-        assert_eq!(transparency_log.num_artifacts, res.num_artifacts);
+
+        log.remove_artifact(
+            &PackageType::Maven2,
+            &original_log.package_specific_artifact_id,
+            PeerId::random(),
+        )
+        .await
+        .unwrap();
+        let references = log
+            .find_artifact_references(&original_log.artifact_id)
+            .unwrap();
+        assert_eq!(references, vec![relocated_logs[0].clone()]);
+
+        let reference_counts = log.artifact_reference_counts().unwrap();
+        assert_eq!(reference_counts.len(), 2);
+        assert_eq!(reference_counts[&original_log.artifact_id], 1);
+        assert_eq!(reference_counts[&relocated_logs[1].artifact_id], 2);
+
+        let package_types = log.artifact_package_types().unwrap();
+        assert_eq!(package_types.len(), 2);
         assert_eq!(
-            transparency_log.package_specific_artifact_id,
-            res.package_specific_artifact_id
+            package_types[&original_log.artifact_id],
+            HashSet::from([PackageType::Maven2])
         );
-        assert_eq!(transparency_log.artifact_hash, res.artifact_hash);
-        assert_eq!(transparency_log.source_hash, res.source_hash);
-        assert_eq!(transparency_log.artifact_id, res.artifact_id);
-        assert_eq!(transparency_log.source_id, res.source_id);
-        assert_eq!(transparency_log.timestamp, res.timestamp);
-        assert_eq!(transparency_log.operation, res.operation);
-        assert_eq!(transparency_log.node_id, res.node_id);
-        assert_eq!(transparency_log.node_public_key, res.node_public_key);
+
+        test_util::tests::teardown(tmp_dir);
     }
 
-    #[tokio::test]
-    async fn test_open_db() {
-        let tmp_dir = test_util::tests::setup();
+    #[test]
+    fn test_derive_artifact_id() {
+        let artifact_id = derive_artifact_id("artifact_hash");
 
-        let (log, _) = test_util::tests::create_transparency_log_service(&tmp_dir);
+        assert_eq!(artifact_id, derive_artifact_id("artifact_hash"));
+        assert_ne!(artifact_id, derive_artifact_id("other_artifact_hash"));
+        assert!(artifact_id.starts_with("1220"));
+        assert_eq!(artifact_id.len(), 68);
+    }
 
-        let result = log.open_db();
-        assert!(result.is_ok());
+    #[test]
+    fn test_write_transparency_log_verifies_artifact_id() {
+        let tmp_dir = test_util::tests::setup();
 
-        let conn = result.unwrap();
-        let mut path = log.storage_path;
-        path.push("transparency_log.db");
-        assert_eq!(conn.path().unwrap(), path.as_path());
+        let (log, _) = test_util::tests::create_transparency_log_service(&tmp_dir);
 
-        let close_result = conn.close();
-        assert!(close_result.is_ok());
+        let mut transparency_log = new_artifact_transparency_log_default();
+        transparency_log.artifact_id = Uuid::new_v4().to_string();
+        assert!(matches!(
+            log.write_transparency_log(&transparency_log),
+            Err(TransparencyLogError::InvalidArtifactId { .. })
+        ));
+
+        let mut colliding_log = new_artifact_transparency_log_default();
+        colliding_log.artifact_hash = "other_artifact_hash".to_owned();
+        insert_legacy_transparency_log(&tmp_dir, &colliding_log);
+        assert!(matches!(
+            log.write_transparency_log(&new_artifact_transparency_log_default()),
+            Err(TransparencyLogError::ArtifactIdCollision { .. })
+        ));
 
         test_util::tests::teardown(tmp_dir);
     }
 
-    #[tokio::test]
-    async fn test_write_tranparency_log() {
+    #[test]
+    fn test_migrate_artifact_ids() {
         let tmp_dir = test_util::tests::setup();
 
         let (log, _) = test_util::tests::create_transparency_log_service(&tmp_dir);
 
-        let transparency_log = new_artifact_transparency_log_default();
+        let mut legacy_logs = [
+            new_artifact_transparency_log_default(),
+            new_artifact_transparency_log_default(),
+            new_artifact_transparency_log_default(),
+        ];
+        legacy_logs[2].artifact_hash = "other_artifact_hash".to_owned();
+        for legacy_log in legacy_logs.iter_mut() {
+            legacy_log.artifact_id = Uuid::new_v4().to_string();
+            insert_legacy_transparency_log(&tmp_dir, legacy_log);
+        }
 
-        let result = log.write_transparency_log(&transparency_log);
-        assert!(result.is_ok());
+        let mut migrated_artifact_ids = log.migrate_artifact_ids().unwrap();
+        migrated_artifact_ids.sort();
+        let mut expected_artifact_ids: Vec<(String, String)> = legacy_logs
+            .iter()
+            .map(|legacy_log| {
+                (
+                    legacy_log.artifact_id.clone(),
+                    derive_artifact_id(&legacy_log.artifact_hash),
+                )
+            })
+            .collect();
+        expected_artifact_ids.sort();
+        assert_eq!(migrated_artifact_ids, expected_artifact_ids);
+
+        let migrated_log = log.find_transparency_log(&legacy_logs[0].id).unwrap();
+        assert_eq!(
+            migrated_log.artifact_id,
+            derive_artifact_id(&legacy_logs[0].artifact_hash)
+        );
+        assert!(log.migrate_artifact_ids().unwrap().is_empty());
 
         test_util::tests::teardown(tmp_dir);
     }
 
+    #[test]
+    fn test_parse_payload_derives_artifact_id() {
+        let mut transparency_log = new_artifact_transparency_log_default();
+        transparency_log.artifact_id = Uuid::new_v4().to_string();
+        let payload = serde_json::to_vec(&vec![transparency_log.clone()]).unwrap();
+
+        let parsed_logs = TransparencyLogService::parse_payload(&payload).unwrap();
+
+        assert_eq!(
+            parsed_logs[0].artifact_id,
+            derive_artifact_id(&transparency_log.artifact_hash)
+        );
+    }
+
+    // Insert a transparency log without verifying its artifact_id, like the
+    // databases of nodes that did not derive artifact ids yet.
+    fn insert_legacy_transparency_log(repository_path: &Path, transparency_log: &TransparencyLog) {
+        SqliteLogStore::new(
+            repository_path
+                .canonicalize()
+                .unwrap()
+                .join("transparency_log"),
+        )
+        .insert(std::slice::from_ref(transparency_log), false)
+        .unwrap();
+    }
+
     #[tokio::test]
-    async fn test_write_twice_transparency_log_error() {
+    async fn test_find_artifacts() {
         let tmp_dir = test_util::tests::setup();
 
-        let (log, _) = test_util::tests::create_transparency_log_service(&tmp_dir);
-
-        let transparency_log = new_artifact_transparency_log_default();
+        let log =
+            test_util::tests::create_transparency_log_service_default_blockchain_handler(&tmp_dir);
+        for (package_type, package_specific_id, package_specific_artifact_id) in [
+            (
+                PackageType::Docker,
+                "library/alpine:3.15.2",
+                "library/alpine@sha256:1",
+            ),
+            (
+                PackageType::Docker,
+                "library/alpine:3.16.0",
+                "library/alpine@sha256:2",
+            ),
+            (
+                PackageType::Docker,
+                "library/busybox:1.0",
+                "library/busybox@sha256:3",
+            ),
+            (
+                PackageType::Maven2,
+                "library/alpine:1.0",
+                "library/alpine/1.0/alpine-1.0.jar",
+            ),
+        ] {
+            log.add_artifact(AddArtifactRequest {
+                package_type,
+                package_specific_id: package_specific_id.to_owned(),
+                num_artifacts: 1,
+                package_specific_artifact_id: package_specific_artifact_id.to_owned(),
+                artifact_hash: "artifact_hash".to_owned(),
+            })
+            .await
+            .unwrap();
+        }
+        log.remove_artifact(
+            &PackageType::Docker,
+            "library/alpine@sha256:2",
+            PeerId::random(),
+        )
+        .await
+        .unwrap();
 
-        let mut result = log.write_transparency_log(&transparency_log);
-        assert!(result.is_ok());
-        result = log.write_transparency_log(&transparency_log);
-        assert!(result.is_err());
+        let mut artifacts = log
+            .find_artifacts(Some(&PackageType::Docker), "library/alpine")
+            .unwrap()
+            .into_iter()
+            .map(|transparency_log| transparency_log.package_specific_artifact_id)
+            .collect::<Vec<_>>();
+        artifacts.sort();
+        assert_eq!(artifacts, vec!["library/alpine@sha256:1"]);
+
+        assert_eq!(log.find_artifacts(None, "library/alpine").unwrap().len(), 2);
+        assert_eq!(log.find_artifacts(None, "").unwrap().len(), 3);
+        assert!(log
+            .find_artifacts(None, "library/alpine' OR '1'='1")
+            .unwrap()
+            .is_empty());
 
         test_util::tests::teardown(tmp_dir);
     }
 
     #[tokio::test]
-    async fn test_find_transparency_log() {
+    async fn test_query_transparency_logs() {
         let tmp_dir = test_util::tests::setup();
 
-        let (log, _) = test_util::tests::create_transparency_log_service(&tmp_dir);
+        let log =
+            test_util::tests::create_transparency_log_service_default_blockchain_handler(&tmp_dir);
+        for (package_type, package_specific_id, artifact_hash) in [
+            (PackageType::Docker, "library/alpine:3.15.2", "hash1"),
+            (PackageType::Docker, "library/alpine:3.16.0", "hash2"),
+            (PackageType::Maven2, "com.myorg:my-artifact:1.0", "hash3"),
+        ] {
+            log.add_artifact(AddArtifactRequest {
+                package_type,
+                package_specific_id: package_specific_id.to_owned(),
+                num_artifacts: 1,
+                package_specific_artifact_id: package_specific_id.to_owned(),
+                artifact_hash: artifact_hash.to_owned(),
+            })
+            .await
+            .unwrap();
+        }
+        let node_id = PeerId::random();
+        log.remove_artifact(&PackageType::Docker, "library/alpine:3.15.2", node_id)
+            .await
+            .unwrap();
 
-        let id = "test_id";
-        let transparency_log = new_artifact_transparency_log_with_id(id);
+        let page = log
+            .query_transparency_logs(&TransparencyLogQuery::default())
+            .unwrap();
+        assert_eq!((page.total, page.transparency_logs.len()), (4, 4));
+
+        let page = log
+            .query_transparency_logs(&TransparencyLogQuery {
+                package_type: Some(PackageType::Docker),
+                package_specific_id: Some("library/alpine".to_owned()),
+                limit: Some(2),
+                offset: 1,
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!((page.total, page.offset), (3, 1));
+        assert_eq!(page.transparency_logs.len(), 2);
+
+        let page = log
+            .query_transparency_logs(&TransparencyLogQuery {
+                operation: Some(Operation::RemoveArtifact),
+                node_id: Some(node_id.to_string()),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(page.total, 1);
+        assert_eq!(
+            page.transparency_logs[0].package_specific_id,
+            "library/alpine:3.15.2"
+        );
 
-        let result_write = log.write_transparency_log(&transparency_log);
-        assert!(result_write.is_ok());
+        let page = log
+            .query_transparency_logs(&TransparencyLogQuery {
+                hash: Some("hash3".to_owned()),
+                until: Some(page.transparency_logs[0].timestamp),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(page.total, 1);
+        assert_eq!(
+            page.transparency_logs[0].package_type,
+            Some(PackageType::Maven2)
+        );
 
-        let result_find = log.find_transparency_log(id);
-        assert!(result_find.is_ok());
+        let since = page.transparency_logs[0].timestamp + 3600;
+        let page = log
+            .query_transparency_logs(&TransparencyLogQuery {
+                since: Some(since),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(page.total, 0);
+        assert!(page.transparency_logs.is_empty());
 
         test_util::tests::teardown(tmp_dir);
     }
 
     #[tokio::test]
-    async fn test_find_transparency_log_not_found() {
+    async fn test_watch_transparency_logs() {
         let tmp_dir = test_util::tests::setup();
 
-        let (log, _) = test_util::tests::create_transparency_log_service(&tmp_dir);
+        let log =
+            test_util::tests::create_transparency_log_service_default_blockchain_handler(&tmp_dir);
+        let add_artifact_request = |package_specific_id: &str| AddArtifactRequest {
+            package_type: PackageType::Docker,
+            package_specific_id: package_specific_id.to_owned(),
+            num_artifacts: 1,
+            package_specific_artifact_id: package_specific_id.to_owned(),
+            artifact_hash: format!("hash_{}", package_specific_id),
+        };
+        let no_wait = |since| TransparencyLogWatchQuery {
+            since,
+            wait_secs: Some(0),
+            ..Default::default()
+        };
 
-        let transparency_log = new_artifact_transparency_log_default();
+        let batch = log.watch_transparency_logs(&no_wait(0)).await.unwrap();
+        assert!(batch.transparency_logs.is_empty());
+        assert_eq!(batch.next_cursor, 0);
 
-        let result_write = log.write_transparency_log(&transparency_log);
-        assert!(result_write.is_ok());
+        log.add_artifact(add_artifact_request("library/alpine:3.15.2"))
+            .await
+            .unwrap();
+        log.add_artifact(add_artifact_request("library/alpine:3.16.0"))
+            .await
+            .unwrap();
+        let batch = log
+            .watch_transparency_logs(&TransparencyLogWatchQuery {
+                limit: Some(1),
+                ..no_wait(0)
+            })
+            .await
+            .unwrap();
+        assert_eq!(
+            batch.transparency_logs[0].package_specific_id,
+            "library/alpine:3.15.2"
+        );
+        let batch = log
+            .watch_transparency_logs(&no_wait(batch.next_cursor))
+            .await
+            .unwrap();
+        assert_eq!(batch.transparency_logs.len(), 1);
+        assert_eq!(
+            batch.transparency_logs[0].package_specific_id,
+            "library/alpine:3.16.0"
+        );
 
-        let find_error = log
-            .find_transparency_log("unknown_id")
-            .expect_err("Find transparency log should have failed.");
-        match find_error {
-            TransparencyLogError::LogNotFound { id } => {
-                assert_eq!("unknown_id".to_owned(), id);
-            }
-            e => {
-                panic!("Invalid Error encountered: {:?}", e);
-            }
-        }
+        // a waiting watch returns as soon as a transparency log is written
+        let watching_log = log.clone();
+        let since = batch.next_cursor;
+        let watch = tokio::spawn(async move {
+            watching_log
+                .watch_transparency_logs(&TransparencyLogWatchQuery {
+                    since,
+                    wait_secs: Some(60),
+                    ..Default::default()
+                })
+                .await
+        });
+        log.add_artifact(add_artifact_request("library/alpine:3.17.0"))
+            .await
+            .unwrap();
+        let batch = tokio::time::timeout(Duration::from_secs(10), watch)
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            batch.transparency_logs[0].package_specific_id,
+            "library/alpine:3.17.0"
+        );
+        assert!(batch.next_cursor > since);
 
         test_util::tests::teardown(tmp_dir);
     }
 
     #[tokio::test]
-    async fn test_read_transparency_log() {
+    async fn test_add_artifact() {
         let tmp_dir = test_util::tests::setup();
 
-        let (log, _) = test_util::tests::create_transparency_log_service(&tmp_dir);
-
-        let ps_art_id = "package_specific_artifact_id";
-        let transparency_log = new_artifact_transparency_log(
-            Some(PackageType::Maven2),
-            Operation::AddArtifact,
-            Some("package_specific_id"),
-            Some(ps_art_id),
-        );
-
-        assert!(log.write_transparency_log(&transparency_log).is_ok());
+        let log =
+            test_util::tests::create_transparency_log_service_default_blockchain_handler(&tmp_dir);
 
-        assert!(log
-            .read_transparency_log(&PackageType::Maven2, ps_art_id)
-            .is_ok());
+        let result = log
+            .add_artifact(AddArtifactRequest {
+                package_type: PackageType::Docker,
+                package_specific_id: "package_specific_id".to_owned(),
+                num_artifacts: 8,
+                package_specific_artifact_id: "package_specific_artifact_id".to_owned(),
+                artifact_hash: "artifact_hash".to_owned(),
+            })
+            .await;
+        assert!(result.is_ok());
 
         test_util::tests::teardown(tmp_dir);
     }
 
     #[tokio::test]
-    async fn test_read_transparency_log_invalid_id() {
+    async fn test_claim_build() {
         let tmp_dir = test_util::tests::setup();
 
-        let (log, _) = test_util::tests::create_transparency_log_service(&tmp_dir);
-
-        let transparency_log = new_artifact_transparency_log(
-            Some(PackageType::Maven2),
-            Operation::AddArtifact,
-            Some("package_specific_id"),
-            Some("package_specific_artifact_id"),
-        );
+        let log =
+            test_util::tests::create_transparency_log_service_default_blockchain_handler(&tmp_dir);
+        let first_node = PeerId::random();
+        let second_node = PeerId::random();
 
-        assert!(log.write_transparency_log(&transparency_log).is_ok());
+        let claim = log
+            .claim_build(&PackageType::Docker, "library/alpine:3.17", first_node)
+            .await
+            .unwrap();
+        assert_eq!(claim.operation, Operation::ClaimBuild);
+        assert_eq!(claim.node_id, first_node.to_string());
 
-        let result_read =
-            log.read_transparency_log(&PackageType::Maven2, "invalid_package_specific_artifact_id");
-        assert!(result_read.is_err());
+        // the first claim stays active when another node claims the build
+        let other_claim = log
+            .claim_build(&PackageType::Docker, "library/alpine:3.17", second_node)
+            .await
+            .unwrap();
+        assert_eq!(other_claim, claim);
+
+        log.add_artifact(AddArtifactRequest {
+            package_type: PackageType::Docker,
+            package_specific_id: "library/alpine:3.17".to_owned(),
+            num_artifacts: 1,
+            package_specific_artifact_id: "library/alpine:3.17".to_owned(),
+            artifact_hash: "artifact_hash".to_owned(),
+        })
+        .await
+        .unwrap();
         assert_eq!(
-            result_read.err().unwrap().to_string(),
-            TransparencyLogError::ArtifactNotFound {
-                package_type: PackageType::Maven2,
-                package_specific_artifact_id: String::from("invalid_package_specific_artifact_id"),
-            }
-            .to_string()
+            log.find_active_build_claim(&PackageType::Docker, "library/alpine:3.17")
+                .unwrap(),
+            None
         );
+        assert!(matches!(
+            log.claim_build(&PackageType::Docker, "library/alpine:3.17", second_node)
+                .await,
+            Err(TransparencyLogError::ArtifactAlreadyExists { .. })
+        ));
+
+        // expired claims do not keep other nodes from building
+        let log = log.with_build_claim_ttl(Duration::ZERO);
+        let claim = log
+            .claim_build(&PackageType::Docker, "library/alpine:3.18", first_node)
+            .await
+            .unwrap();
+        let other_claim = log
+            .claim_build(&PackageType::Docker, "library/alpine:3.18", second_node)
+            .await
+            .unwrap();
+        assert_ne!(other_claim.id, claim.id);
+        assert_eq!(other_claim.node_id, second_node.to_string());
 
         test_util::tests::teardown(tmp_dir);
     }
 
     #[tokio::test]
-    async fn test_read_latest_transparency_log() {
+    async fn test_release_build_claim() {
         let tmp_dir = test_util::tests::setup();
 
-        let (log, _) = test_util::tests::create_transparency_log_service(&tmp_dir);
-
-        let transparency_log1 = new_artifact_transparency_log(
-            Some(PackageType::Maven2),
-            Operation::AddArtifact,
-            Some("package_specific_id"),
-            Some("package_specific_artifact_id"),
-        );
+        let log =
+            test_util::tests::create_transparency_log_service_default_blockchain_handler(&tmp_dir);
+        let first_node = PeerId::random();
+        let second_node = PeerId::random();
 
-        let result_write1 = log.write_transparency_log(&transparency_log1);
-        assert!(result_write1.is_ok());
+        let claim = log
+            .claim_build(&PackageType::Docker, "library/alpine:3.17", first_node)
+            .await
+            .unwrap();
 
-        let ps_art_id = "package_specific_artifact_id2";
-        let transparency_log2 = new_artifact_transparency_log(
-            Some(PackageType::Maven2),
-            Operation::AddArtifact,
-            Some("package_specific_id2"),
-            Some(ps_art_id),
+        // a node that does not hold the claim can not release it
+        log.release_build_claim(&PackageType::Docker, "library/alpine:3.17", second_node)
+            .await
+            .unwrap();
+        assert_eq!(
+            log.find_active_build_claim(&PackageType::Docker, "library/alpine:3.17")
+                .unwrap(),
+            Some(claim)
         );
 
-        assert!(log.write_transparency_log(&transparency_log2).is_ok());
-
-        assert!(log
-            .read_transparency_log(&PackageType::Maven2, ps_art_id)
-            .is_ok());
+        // after the claim was released, another node can claim the build
+        log.release_build_claim(&PackageType::Docker, "library/alpine:3.17", first_node)
+            .await
+            .unwrap();
+        assert_eq!(
+            log.find_active_build_claim(&PackageType::Docker, "library/alpine:3.17")
+                .unwrap(),
+            None
+        );
+        let other_claim = log
+            .claim_build(&PackageType::Docker, "library/alpine:3.17", second_node)
+            .await
+            .unwrap();
+        assert_eq!(other_claim.node_id, second_node.to_string());
 
         test_util::tests::teardown(tmp_dir);
     }
 
     #[tokio::test]
-    async fn test_read_transparency_logs() {
+    async fn test_build_claims_are_ordered_by_block() {
         let tmp_dir = test_util::tests::setup();
 
-        let (log, _) = test_util::tests::create_transparency_log_service(&tmp_dir);
+        let log =
+            test_util::tests::create_transparency_log_service_default_blockchain_handler(&tmp_dir);
+        let claim_of = |id: &str| TransparencyLog {
+            id: id.to_owned(),
+            package_type: Some(PackageType::Docker),
+            package_specific_id: "library/alpine:3.17".to_owned(),
+            num_artifacts: 0,
+            package_specific_artifact_id: String::from(""),
+            artifact_hash: String::from(""),
+            source_hash: String::from(""),
+            artifact_id: String::from(""),
+            source_id: String::from(""),
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap()
+                .as_secs(),
+            operation: Operation::ClaimBuild,
+            node_id: PeerId::random().to_string(),
+            node_public_key: String::from(""),
+            successor: None,
+            pinning_snapshot: None,
+            signature: None,
+        };
+        let second_claim = claim_of("b");
+        let first_claim = TransparencyLog {
+            timestamp: second_claim.timestamp,
+            ..claim_of("a")
+        };
+        log.write_transparency_log(&second_claim).unwrap();
+        log.write_transparency_log(&first_claim).unwrap();
 
-        let ps_id = "package_specific_id";
-        let transparency_log1 = new_artifact_transparency_log(
-            Some(PackageType::Maven2),
-            Operation::AddArtifact,
-            Some(ps_id),
-            Some("package_specific_artifact_id"),
+        // claims of the same second are ordered by id, not by the order in
+        // which this node wrote them
+        assert_eq!(
+            log.find_active_build_claim(&PackageType::Docker, "library/alpine:3.17")
+                .unwrap(),
+            Some(first_claim.clone())
         );
 
-        let result_write1 = log.write_transparency_log(&transparency_log1);
-        assert!(result_write1.is_ok());
-
-        let transparency_log2 = new_artifact_transparency_log(
-            Some(PackageType::Maven2),
-            Operation::AddArtifact,
-            Some(ps_id),
-            Some("package_specific_artifact_id2"),
+        // a claim in a block wins over a claim that is not in a block yet
+        let block_metadata = |ordinal| BlockMetadata {
+            ordinal,
+            hash: String::from(""),
+            committer: None,
+            timestamp: second_claim.timestamp,
+        };
+        log.log_store
+            .add_block_metadata(&[second_claim.id.clone()], &block_metadata(2))
+            .unwrap();
+        assert_eq!(
+            log.find_active_build_claim(&PackageType::Docker, "library/alpine:3.17")
+                .unwrap(),
+            Some(second_claim.clone())
         );
 
-        assert!(log.write_transparency_log(&transparency_log2).is_ok());
-
-        let result_read1 = log.read_transparency_logs(&PackageType::Maven2, ps_id);
-        assert!(result_read1.is_ok());
-        assert_eq!(result_read1.unwrap().len(), 2);
-
-        let result_read2 =
-            log.read_transparency_logs(&PackageType::Maven2, "other_package_specific_id");
-        assert!(result_read2.is_ok());
-        assert_eq!(result_read2.unwrap().len(), 0);
+        // and the claim in the first block wins
+        log.log_store
+            .add_block_metadata(&[first_claim.id.clone()], &block_metadata(1))
+            .unwrap();
+        assert_eq!(
+            log.find_active_build_claim(&PackageType::Docker, "library/alpine:3.17")
+                .unwrap(),
+            Some(first_claim)
+        );
 
         test_util::tests::teardown(tmp_dir);
     }
 
     #[tokio::test]
-    async fn test_verify_artifact_can_be_added_to_transparency_logs() {
+    async fn test_deprecate_package() {
         let tmp_dir = test_util::tests::setup();
 
-        let (log, _) = test_util::tests::create_transparency_log_service(&tmp_dir);
-        let result1 = log.verify_package_can_be_added_to_transparency_logs(
-            &PackageType::Docker,
-            "package_specific_id",
-        );
-        assert!(result1.is_ok());
+        let log =
+            test_util::tests::create_transparency_log_service_default_blockchain_handler(&tmp_dir);
+        let node_id = PeerId::random();
 
-        let ps_id = "package_specific_id";
+        let result = log
+            .deprecate_package(
+                &PackageType::Maven2,
+                "com.myorg:my-artifact:1.0.0",
+                None,
+                node_id,
+            )
+            .await;
+        assert!(matches!(
+            result,
+            Err(TransparencyLogError::ArtifactNotFound { .. })
+        ));
+
+        log.add_artifact(AddArtifactRequest {
+            package_type: PackageType::Maven2,
+            package_specific_id: "com.myorg:my-artifact:1.0.0".to_owned(),
+            num_artifacts: 1,
+            package_specific_artifact_id: "com.myorg/my-artifact/1.0.0/my-artifact-1.0.0.jar"
+                .to_owned(),
+            artifact_hash: "artifact_hash".to_owned(),
+        })
+        .await
+        .unwrap();
         assert!(log
-            .verify_package_can_be_added_to_transparency_logs(&PackageType::Docker, ps_id)
-            .is_ok());
-
-        let transparency_log1 = new_artifact_transparency_log(
-            Some(PackageType::Docker),
-            Operation::AddArtifact,
-            Some(ps_id),
-            Some("package_specific_artifact_id"),
+            .find_deprecation(&PackageType::Maven2, "com.myorg:my-artifact:1.0.0")
+            .unwrap()
+            .is_none());
+
+        let deprecation = log
+            .deprecate_package(
+                &PackageType::Maven2,
+                "com.myorg:my-artifact:1.0.0",
+                Some("com.myorg:my-artifact:1.1.0".to_owned()),
+                node_id,
+            )
+            .await
+            .unwrap();
+        assert_eq!(deprecation.node_id, node_id.to_string());
+        assert_eq!(
+            log.find_deprecation(&PackageType::Maven2, "com.myorg:my-artifact:1.0.0")
+                .unwrap(),
+            Some(deprecation.clone())
         );
-        assert!(log.write_transparency_log(&transparency_log1).is_ok());
-
-        let result2 =
-            log.verify_package_can_be_added_to_transparency_logs(&PackageType::Docker, ps_id);
-        assert!(result2.is_err());
         assert_eq!(
-            result2.err().unwrap().to_string(),
-            TransparencyLogError::ArtifactAlreadyExists {
-                package_type: PackageType::Docker,
-                package_specific_id: ps_id.to_string(),
-            }
-            .to_string()
+            deprecation.deprecation_warning(),
+            Some(
+                "com.myorg:my-artifact:1.0.0 is deprecated, use com.myorg:my-artifact:1.1.0 instead"
+                    .to_owned()
+            )
         );
-
-        let transparency_log2 = new_artifact_transparency_log(
-            Some(PackageType::Docker),
-            Operation::RemoveArtifact,
-            Some(ps_id),
-            Some("package_specific_artifact_id"),
+        assert_eq!(
+            log.search_transparency_logs(&PackageType::Maven2, "com.myorg:my-artifact:1.0.0")
+                .unwrap()
+                .len(),
+            1
         );
-        assert!(log.write_transparency_log(&transparency_log2).is_ok());
-        assert!(log
-            .verify_package_can_be_added_to_transparency_logs(&PackageType::Docker, ps_id)
-            .is_ok());
 
         test_util::tests::teardown(tmp_dir);
     }
 
     #[tokio::test]
-    async fn test_read_remove_artifact_transparency_log() {
+    async fn test_find_package_versions() {
         let tmp_dir = test_util::tests::setup();
 
-        let (log, _) = test_util::tests::create_transparency_log_service(&tmp_dir);
-
-        let ps_art_id = "package_specific_artifact_id";
-        let transparency_log = new_artifact_transparency_log(
-            Some(PackageType::Maven2),
-            Operation::RemoveArtifact,
-            Some("package_specific_id"),
-            Some(ps_art_id),
-        );
+        let log =
+            test_util::tests::create_transparency_log_service_default_blockchain_handler(&tmp_dir);
 
-        assert!(log.write_transparency_log(&transparency_log).is_ok());
+        for package_specific_id in [
+            "com.myorg:my-artifact:1.0.0",
+            "com.myorg:my-artifact:1.1.0",
+            "com.myorg:my-artifact-other:2.0.0",
+        ] {
+            log.add_artifact(AddArtifactRequest {
+                package_type: PackageType::Maven2,
+                package_specific_id: package_specific_id.to_owned(),
+                num_artifacts: 1,
+                package_specific_artifact_id: format!("{}/artifact.jar", package_specific_id),
+                artifact_hash: "artifact_hash".to_owned(),
+            })
+            .await
+            .unwrap();
+        }
 
-        let result_read = log.read_transparency_log(&PackageType::Maven2, ps_art_id);
-        assert!(result_read.is_err());
         assert_eq!(
-            result_read.err().unwrap().to_string(),
-            TransparencyLogError::InvalidOperation {
-                id: transparency_log.id,
-                invalid_operation: Operation::RemoveArtifact,
-            }
-            .to_string()
+            log.find_package_versions(&PackageType::Maven2, "com.myorg:my-artifact")
+                .unwrap(),
+            HashSet::from(["1.0.0".to_owned(), "1.1.0".to_owned()])
         );
+        assert!(log
+            .find_package_versions(&PackageType::Docker, "com.myorg:my-artifact")
+            .unwrap()
+            .is_empty());
 
         test_util::tests::teardown(tmp_dir);
     }
 
     #[tokio::test]
-    async fn test_add_artifact() {
+    async fn test_stage_commit_and_rollback_artifacts() {
         let tmp_dir = test_util::tests::setup();
 
         let log =
             test_util::tests::create_transparency_log_service_default_blockchain_handler(&tmp_dir);
 
-        let result = log
-            .add_artifact(AddArtifactRequest {
+        let add_artifact_requests = (0..2)
+            .map(|i| AddArtifactRequest {
                 package_type: PackageType::Docker,
                 package_specific_id: "package_specific_id".to_owned(),
-                num_artifacts: 8,
-                package_specific_artifact_id: "package_specific_artifact_id".to_owned(),
+                num_artifacts: 2,
+                package_specific_artifact_id: format!("package_specific_artifact_id_{}", i),
                 artifact_hash: "artifact_hash".to_owned(),
             })
-            .await;
-        assert!(result.is_ok());
+            .collect();
+
+        let source = BuildSource {
+            url: "https://github.com/pyrsia/pyrsia".to_owned(),
+            commit: "abc123".to_owned(),
+        };
+        let (transparency_logs, payload) = log
+            .stage_artifacts(add_artifact_requests, Some(&source), Some("snapshot_id"))
+            .unwrap();
+        assert_eq!(transparency_logs[0].source_id, source.url);
+        assert_eq!(transparency_logs[0].source_hash, source.commit);
+        assert_eq!(
+            transparency_logs[0].pinning_snapshot.as_deref(),
+            Some("snapshot_id")
+        );
+        assert_eq!(transparency_logs[0].provenance(), Provenance::Built);
+        assert_eq!(
+            TransparencyLogService::parse_payload(payload.as_bytes()).unwrap(),
+            transparency_logs
+        );
+        assert!(log.find_transparency_log(&transparency_logs[0].id).is_err());
+
+        log.commit_transparency_logs(&transparency_logs).unwrap();
+        for transparency_log in transparency_logs.iter() {
+            assert_eq!(
+                &log.find_transparency_log(&transparency_log.id).unwrap(),
+                transparency_log
+            );
+        }
+
+        log.rollback_transparency_logs(&transparency_logs).unwrap();
+        for transparency_log in transparency_logs.iter() {
+            assert!(log.find_transparency_log(&transparency_log.id).is_err());
+        }
+
+        test_util::tests::teardown(tmp_dir);
+    }
+
+    #[tokio::test]
+    async fn test_add_artifact_in_privacy_mode() {
+        let tmp_dir = test_util::tests::setup();
+        let other_tmp_dir = tempfile::tempdir().unwrap().into_path();
+
+        let (log, _) = test_util::tests::create_transparency_log_service(&tmp_dir);
+        let mut log = log.with_privacy_salt("org_salt");
 
+        let (transparency_log, payload) = log
+            .add_artifact(AddArtifactRequest {
+                package_type: PackageType::Maven2,
+                package_specific_id: "com.myorg:internal:1.0".to_owned(),
+                num_artifacts: 1,
+                package_specific_artifact_id: "com.myorg/internal/1.0/internal-1.0.jar".to_owned(),
+                artifact_hash: "artifact_hash".to_owned(),
+            })
+            .await
+            .unwrap();
+
+        assert!(!payload.contains("com.myorg"));
+        assert_eq!(
+            log.get_artifact(
+                &PackageType::Maven2,
+                "com.myorg/internal/1.0/internal-1.0.jar"
+            )
+            .unwrap(),
+            transparency_log
+        );
+
+        let published_log: TransparencyLog = serde_json::from_str(&payload).unwrap();
+
+        let (mut org_log, _) = test_util::tests::create_transparency_log_service(&other_tmp_dir);
+        org_log = org_log.with_privacy_salt("org_salt");
+        org_log.write_if_not_exists(&published_log).await.unwrap();
+        let resolved_log = org_log
+            .get_artifact(
+                &PackageType::Maven2,
+                "com.myorg/internal/1.0/internal-1.0.jar",
+            )
+            .unwrap();
+        assert_eq!(resolved_log.artifact_id, transparency_log.artifact_id);
+        assert_eq!(
+            org_log
+                .search_transparency_logs(&PackageType::Maven2, "com.myorg:internal:1.0")
+                .unwrap()
+                .len(),
+            1
+        );
+
+        let (mut public_log, _) = test_util::tests::create_transparency_log_service(&other_tmp_dir);
+        assert!(public_log
+            .get_artifact(
+                &PackageType::Maven2,
+                "com.myorg/internal/1.0/internal-1.0.jar"
+            )
+            .is_err());
+
+        test_util::tests::teardown(other_tmp_dir);
         test_util::tests::teardown(tmp_dir);
     }
 
@@ -1001,7 +4251,7 @@ mod tests {
         let vec = result_read.unwrap();
         assert_eq!(vec.len(), 1);
         assert!(vec
-            .get(0)
+            .first()
             .unwrap()
             .eq(&PeerId::from_str(&transparency_log.node_id).unwrap()));
         test_util::tests::teardown(tmp_dir);
@@ -1022,7 +4272,7 @@ mod tests {
         assert!(result_read.is_ok());
         let vec = result_read.unwrap();
         assert_eq!(vec.len(), 1);
-        assert!(vec.get(0).unwrap().eq(&PeerId::from_str(node_id).unwrap()));
+        assert!(vec.first().unwrap().eq(&PeerId::from_str(node_id).unwrap()));
         test_util::tests::teardown(tmp_dir);
     }
 
@@ -1051,13 +4301,48 @@ mod tests {
         let vec = result_read.unwrap();
         assert_eq!(vec.len(), 1);
         assert!(vec
-            .get(0)
+            .first()
             .unwrap()
             .eq(&PeerId::from_str(second_node_id).unwrap()));
 
         test_util::tests::teardown(tmp_dir);
     }
 
+    #[tokio::test]
+    async fn test_get_authorized_nodes_genesis() {
+        let tmp_dir = test_util::tests::setup();
+
+        let genesis_node_id = PeerId::random();
+        let log = test_util::tests::create_transparency_log_service(&tmp_dir)
+            .0
+            .with_genesis_nodes(vec![genesis_node_id]);
+
+        assert_eq!(log.get_authorized_nodes().unwrap(), vec![genesis_node_id]);
+        assert!(matches!(
+            log.verify_node_does_not_exist(&genesis_node_id.to_string()),
+            Err(TransparencyLogError::NodeAlreadyExists { .. })
+        ));
+
+        // The approvals of the genesis nodes are needed for a new node.
+        let other_node_id = PeerId::random();
+        assert!(!log
+            .verify_node_change(
+                &new_auth_node_transparency_log(Operation::AddNode, &other_node_id.to_string()),
+                &[]
+            )
+            .unwrap());
+
+        let removal =
+            new_auth_node_transparency_log(Operation::RemoveNode, &genesis_node_id.to_string());
+        assert!(log.write_transparency_log(&removal).is_ok());
+        assert!(log.get_authorized_nodes().unwrap().is_empty());
+        assert!(log
+            .verify_node_does_not_exist(&genesis_node_id.to_string())
+            .is_ok());
+
+        test_util::tests::teardown(tmp_dir);
+    }
+
     #[tokio::test]
     async fn test_get_authorized_nodes_add_remove_add() {
         let tmp_dir = test_util::tests::setup();
@@ -1091,7 +4376,7 @@ mod tests {
             test_util::tests::create_transparency_log_service_default_blockchain_handler(&tmp_dir);
         let node_id = PeerId::random();
 
-        let res = log.add_authorized_node(node_id.clone()).await;
+        let res = log.add_authorized_node(node_id).await;
         assert!(res.is_ok());
 
         let res = log.add_authorized_node(node_id).await;
@@ -1100,6 +4385,122 @@ mod tests {
         test_util::tests::teardown(tmp_dir);
     }
 
+    #[tokio::test]
+    async fn test_node_changes_need_a_quorum() {
+        let tmp_dir = test_util::tests::setup();
+
+        let log =
+            test_util::tests::create_transparency_log_service_default_blockchain_handler(&tmp_dir);
+        let keypairs: Vec<ed25519::Keypair> = (0..3)
+            .map(|_| {
+                let Keypair::Ed25519(keypair) = Keypair::generate_ed25519();
+                keypair
+            })
+            .collect();
+        let peer_ids: Vec<PeerId> = keypairs
+            .iter()
+            .map(|keypair| PeerId::from_public_key(&PublicKey::Ed25519(keypair.public())))
+            .collect();
+        let first_log = log.clone().with_node_keypair(keypairs[0].clone());
+        let second_log = log.clone().with_node_keypair(keypairs[1].clone());
+
+        assert!(log
+            .add_authorized_node(peer_ids[0])
+            .await
+            .unwrap()
+            .is_approved());
+        assert!(matches!(
+            log.add_authorized_node(peer_ids[1]).await,
+            Err(TransparencyLogError::MissingNodeKeypair)
+        ));
+        let third_log = log.clone().with_node_keypair(keypairs[2].clone());
+        assert!(matches!(
+            third_log.add_authorized_node(peer_ids[1]).await,
+            Err(TransparencyLogError::NodeNotAuthorizedToApprove { .. })
+        ));
+
+        // a single authorized node is a quorum
+        let proposal = first_log.add_authorized_node(peer_ids[1]).await.unwrap();
+        assert_eq!(proposal.quorum, 1);
+        assert!(proposal.is_approved());
+        assert_eq!(log.get_authorized_nodes().unwrap().len(), 2);
+
+        // two authorized nodes both have to approve
+        let proposal = first_log.add_authorized_node(peer_ids[2]).await.unwrap();
+        assert_eq!(proposal.approvals, vec![peer_ids[0].to_string()]);
+        assert_eq!(proposal.quorum, 2);
+        assert!(!proposal.is_approved());
+        assert!(!log.get_authorized_nodes().unwrap().contains(&peer_ids[2]));
+        assert_eq!(
+            log.list_node_change_proposals().unwrap(),
+            vec![proposal.clone()]
+        );
+        assert_eq!(
+            first_log.add_authorized_node(peer_ids[2]).await.unwrap(),
+            proposal
+        );
+
+        assert!(second_log
+            .add_authorized_node(peer_ids[2])
+            .await
+            .unwrap()
+            .is_approved());
+        assert!(log.get_authorized_nodes().unwrap().contains(&peer_ids[2]));
+        assert!(log.list_node_change_proposals().unwrap().is_empty());
+
+        // and two of three authorized nodes remove a node
+        let proposal = third_log.remove_authorized_node(peer_ids[0]).await.unwrap();
+        assert_eq!(proposal.quorum, 2);
+        assert!(!proposal.is_approved());
+        assert!(log.get_authorized_nodes().unwrap().contains(&peer_ids[0]));
+        assert!(second_log
+            .remove_authorized_node(peer_ids[0])
+            .await
+            .unwrap()
+            .is_approved());
+        assert_eq!(log.get_authorized_nodes().unwrap().len(), 2);
+        assert!(!log.get_authorized_nodes().unwrap().contains(&peer_ids[0]));
+
+        test_util::tests::teardown(tmp_dir);
+    }
+
+    #[tokio::test]
+    async fn test_write_if_not_exists_verifies_node_changes() {
+        let tmp_dir = test_util::tests::setup();
+
+        let (mut log, _) = test_util::tests::create_transparency_log_service(&tmp_dir);
+        let Keypair::Ed25519(keypair) = Keypair::generate_ed25519();
+        let first_peer_id = PeerId::from_public_key(&PublicKey::Ed25519(keypair.public()));
+        let second_peer_id = PeerId::random();
+
+        assert!(log
+            .write_if_not_exists(&node_change_log(Operation::AddNode, &first_peer_id))
+            .await
+            .unwrap());
+
+        let unapproved_change = node_change_log(Operation::AddNode, &second_peer_id);
+        assert!(!log.write_if_not_exists(&unapproved_change).await.unwrap());
+        assert_eq!(log.get_authorized_nodes().unwrap(), vec![first_peer_id]);
+
+        let mut approval = node_change_log(Operation::ApproveAddNode, &second_peer_id);
+        node_quorum::sign_approval(&mut approval, &keypair);
+        let forged_approval = TransparencyLog {
+            id: Uuid::new_v4().to_string(),
+            ..approval.clone()
+        };
+        assert!(!log.write_if_not_exists(&forged_approval).await.unwrap());
+        assert!(log.write_if_not_exists(&approval).await.unwrap());
+
+        let approved_change = node_change_log(Operation::AddNode, &second_peer_id);
+        assert!(log.write_if_not_exists(&approved_change).await.unwrap());
+        assert!(log
+            .get_authorized_nodes()
+            .unwrap()
+            .contains(&second_peer_id));
+
+        test_util::tests::teardown(tmp_dir);
+    }
+
     #[tokio::test]
     async fn test_remove_not_existed_node() {
         let tmp_dir = test_util::tests::setup();
@@ -1108,7 +4509,7 @@ mod tests {
             test_util::tests::create_transparency_log_service_default_blockchain_handler(&tmp_dir);
         let node_id = PeerId::random();
 
-        let res = log.remove_authorized_node(node_id);
+        let res = log.remove_authorized_node(node_id).await;
         assert!(res.is_err());
 
         test_util::tests::teardown(tmp_dir);
@@ -1122,14 +4523,14 @@ mod tests {
             test_util::tests::create_transparency_log_service_default_blockchain_handler(&tmp_dir);
         let node_id = PeerId::random();
 
-        let res = log.add_authorized_node(node_id.clone()).await;
+        let res = log.add_authorized_node(node_id).await;
         assert!(res.is_ok());
 
         let tl_remove =
             new_auth_node_transparency_log(Operation::RemoveNode, node_id.to_string().as_str());
         log.write_transparency_log(&tl_remove).unwrap();
 
-        let res = log.remove_authorized_node(node_id);
+        let res = log.remove_authorized_node(node_id).await;
         assert!(res.is_err());
 
         test_util::tests::teardown(tmp_dir);
@@ -1217,7 +4618,7 @@ mod tests {
             package_specific_artifact_id: ps_artifact_id.unwrap_or("ps_artifact_id").to_owned(),
             artifact_hash: "artifact_hash".to_owned(),
             source_hash: "source_hash".to_owned(),
-            artifact_id: Uuid::new_v4().to_string(),
+            artifact_id: derive_artifact_id("artifact_hash"),
             source_id: Uuid::new_v4().to_string(),
             timestamp: SystemTime::now()
                 .duration_since(UNIX_EPOCH)
@@ -1226,6 +4627,9 @@ mod tests {
             operation: op,
             node_id: Uuid::new_v4().to_string(),
             node_public_key: Uuid::new_v4().to_string(),
+            successor: None,
+            pinning_snapshot: None,
+            signature: None,
         }
     }
 
@@ -1251,6 +4655,9 @@ mod tests {
             operation: op,
             node_id: node_id.to_owned(),
             node_public_key: Uuid::new_v4().to_string(),
+            successor: None,
+            pinning_snapshot: None,
+            signature: None,
         }
     }
 }
@@ -0,0 +1,207 @@
+/*
+   Copyright 2021 JFrog Ltd
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+*/
+
+use super::log::TransparencyLog;
+use crate::artifact_service::model::PackageType;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+
+/// The default number of artifact lookups kept in memory.
+pub const DEFAULT_LOOKUP_CACHE_CAPACITY: usize = 1024;
+
+type LookupKey = (PackageType, String);
+
+/// A bounded in-memory cache of the transparency logs that artifact lookups
+/// resolved to, keyed by package type and package specific artifact id.
+/// When the cache is full, the least recently used lookup is evicted. Clones
+/// share the same cache.
+///
+/// Every write to the transparency log database invalidates the whole cache,
+/// since a single transparency log can change the result of many lookups,
+/// e.g. a revocation of an artifact that is published under several ids.
+#[derive(Clone)]
+pub struct LookupCache {
+    inner: Arc<Mutex<LookupCacheInner>>,
+    capacity: usize,
+}
+
+#[derive(Default)]
+struct LookupCacheInner {
+    entries: HashMap<LookupKey, (TransparencyLog, u64)>,
+    recency: BTreeMap<u64, LookupKey>,
+    tick: u64,
+    generation: u64,
+}
+
+impl Default for LookupCache {
+    fn default() -> Self {
+        LookupCache::new(DEFAULT_LOOKUP_CACHE_CAPACITY)
+    }
+}
+
+impl LookupCache {
+    /// A cache of at most `capacity` lookups. A capacity of 0 disables the
+    /// cache.
+    pub fn new(capacity: usize) -> Self {
+        LookupCache {
+            inner: Default::default(),
+            capacity,
+        }
+    }
+
+    pub fn get(
+        &self,
+        package_type: &PackageType,
+        package_specific_artifact_id: &str,
+    ) -> Option<TransparencyLog> {
+        let mut guard = self.inner.lock().unwrap();
+        let inner = &mut *guard;
+        inner.tick += 1;
+        let tick = inner.tick;
+        let key = (*package_type, package_specific_artifact_id.to_owned());
+        let (transparency_log, last_used) = inner.entries.get_mut(&key)?;
+        let transparency_log = transparency_log.clone();
+        let previous_use = std::mem::replace(last_used, tick);
+        inner.recency.remove(&previous_use);
+        inner.recency.insert(tick, key);
+        Some(transparency_log)
+    }
+
+    /// The generation of the cache, which changes on every invalidation.
+    /// Lookups read it before they query the database and pass it to
+    /// [`Self::insert`], so the result of a query that raced with a write is
+    /// not cached.
+    pub fn generation(&self) -> u64 {
+        self.inner.lock().unwrap().generation
+    }
+
+    pub fn insert(
+        &self,
+        generation: u64,
+        package_type: &PackageType,
+        package_specific_artifact_id: &str,
+        transparency_log: &TransparencyLog,
+    ) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        let mut guard = self.inner.lock().unwrap();
+        let inner = &mut *guard;
+        if inner.generation != generation {
+            return;
+        }
+
+        inner.tick += 1;
+        let tick = inner.tick;
+        let key = (*package_type, package_specific_artifact_id.to_owned());
+        if let Some((_, previous_use)) = inner
+            .entries
+            .insert(key.clone(), (transparency_log.clone(), tick))
+        {
+            inner.recency.remove(&previous_use);
+        }
+        inner.recency.insert(tick, key);
+
+        while inner.entries.len() > self.capacity {
+            match inner.recency.pop_first() {
+                Some((_, evicted_key)) => {
+                    inner.entries.remove(&evicted_key);
+                }
+                None => break,
+            }
+        }
+    }
+
+    pub fn invalidate(&self) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.entries.clear();
+        inner.recency.clear();
+        inner.generation += 1;
+    }
+}
+
+#[cfg(test)]
+#[cfg(not(tarpaulin_include))]
+mod tests {
+    use super::*;
+    use crate::transparency_log::log::AddArtifactRequest;
+
+    fn transparency_log(package_specific_artifact_id: &str) -> TransparencyLog {
+        TransparencyLog::from(AddArtifactRequest {
+            package_type: PackageType::Docker,
+            package_specific_id: "alpine:3.16".to_owned(),
+            num_artifacts: 1,
+            package_specific_artifact_id: package_specific_artifact_id.to_owned(),
+            artifact_hash: "artifact_hash".to_owned(),
+        })
+    }
+
+    #[test]
+    fn test_least_recently_used_lookup_is_evicted() {
+        let cache = LookupCache::new(2);
+        let generation = cache.generation();
+
+        cache.insert(
+            generation,
+            &PackageType::Docker,
+            "a",
+            &transparency_log("a"),
+        );
+        cache.insert(
+            generation,
+            &PackageType::Docker,
+            "b",
+            &transparency_log("b"),
+        );
+        assert!(cache.get(&PackageType::Docker, "a").is_some());
+        cache.insert(
+            generation,
+            &PackageType::Docker,
+            "c",
+            &transparency_log("c"),
+        );
+
+        assert!(cache.get(&PackageType::Docker, "a").is_some());
+        assert!(cache.get(&PackageType::Docker, "b").is_none());
+        assert!(cache.get(&PackageType::Docker, "c").is_some());
+        assert!(cache.get(&PackageType::Maven2, "a").is_none());
+    }
+
+    #[test]
+    fn test_invalidate() {
+        let cache = LookupCache::default();
+        let generation = cache.generation();
+        cache.insert(
+            generation,
+            &PackageType::Docker,
+            "a",
+            &transparency_log("a"),
+        );
+
+        cache.invalidate();
+        assert!(cache.get(&PackageType::Docker, "a").is_none());
+
+        // the result of a lookup that started before the invalidation is stale
+        cache.insert(
+            generation,
+            &PackageType::Docker,
+            "a",
+            &transparency_log("a"),
+        );
+        assert!(cache.get(&PackageType::Docker, "a").is_none());
+    }
+}
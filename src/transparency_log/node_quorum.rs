@@ -0,0 +1,166 @@
+/*
+   Copyright 2021 JFrog Ltd
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+*/
+
+//! Changes to the authorized nodes of the network need the approval of a
+//! quorum of the nodes that are authorized already.
+//!
+//! An authorized node approves adding or removing a node by publishing a
+//! transparency log with the ApproveAddNode or ApproveRemoveNode operation
+//! for it, signed with its own key. The first approval of a change is its
+//! proposal. Once the approvals of distinct authorized nodes since the last
+//! change of the node reach the [quorum](quorum_size), the AddNode or
+//! RemoveNode log of the change is published, and every node only applies
+//! it after it verified the approvals.
+
+use super::log::{Operation, TransparencyLog};
+use libp2p::identity::{ed25519, PublicKey};
+use libp2p::PeerId;
+use serde::{Deserialize, Serialize};
+
+/// The number of approvals of distinct authorized nodes that a change to
+/// the authorized nodes needs: a majority of the `authorized_nodes`.
+pub fn quorum_size(authorized_nodes: usize) -> usize {
+    authorized_nodes / 2 + 1
+}
+
+/// The operation of the approvals of a change with `operation`, `None` when
+/// the operation is not a change to the authorized nodes.
+pub fn approval_operation(operation: &Operation) -> Option<Operation> {
+    match operation {
+        Operation::AddNode => Some(Operation::ApproveAddNode),
+        Operation::RemoveNode => Some(Operation::ApproveRemoveNode),
+        _ => None,
+    }
+}
+
+/// A change to the authorized nodes together with the authorized nodes that
+/// approved it so far.
+#[derive(Clone, Debug, Deserialize, Serialize, Eq, PartialEq)]
+pub struct NodeChangeProposal {
+    /// Either AddNode or RemoveNode.
+    pub operation: Operation,
+    pub node_id: String,
+    /// The ids of the authorized nodes that approved the change, sorted.
+    pub approvals: Vec<String>,
+    /// The number of approvals that the change needs.
+    pub quorum: usize,
+}
+
+impl NodeChangeProposal {
+    pub fn is_approved(&self) -> bool {
+        self.approvals.len() >= self.quorum
+    }
+}
+
+/// Sign an approval with the key of the approving node, which is recorded
+/// as the node public key of the transparency log.
+pub(crate) fn sign_approval(transparency_log: &mut TransparencyLog, keypair: &ed25519::Keypair) {
+    transparency_log.node_public_key = hex::encode(keypair.public().encode());
+    transparency_log.signature = Some(hex::encode(keypair.sign(&signed_content(transparency_log))));
+}
+
+/// The node that signed an approval, `None` when the transparency log is
+/// not an approval or its signature is not valid.
+pub fn approval_signer(transparency_log: &TransparencyLog) -> Option<PeerId> {
+    if !matches!(
+        transparency_log.operation,
+        Operation::ApproveAddNode | Operation::ApproveRemoveNode
+    ) {
+        return None;
+    }
+
+    let public_key =
+        ed25519::PublicKey::decode(&hex::decode(&transparency_log.node_public_key).ok()?).ok()?;
+    let signature = hex::decode(transparency_log.signature.as_ref()?).ok()?;
+    if !public_key.verify(&signed_content(transparency_log), &signature) {
+        return None;
+    }
+    Some(PeerId::from_public_key(&PublicKey::Ed25519(public_key)))
+}
+
+// The content that is signed: the id, the operation, the node that changes
+// and the timestamp of the approval on separate lines.
+fn signed_content(transparency_log: &TransparencyLog) -> Vec<u8> {
+    format!(
+        "pyrsia-node-change-approval\n{}\n{}\n{}\n{}\n",
+        transparency_log.id,
+        transparency_log.operation,
+        transparency_log.node_id,
+        transparency_log.timestamp
+    )
+    .into_bytes()
+}
+
+#[cfg(test)]
+#[cfg(not(tarpaulin_include))]
+mod tests {
+    use super::*;
+    use crate::artifact_service::model::PackageType;
+    use crate::transparency_log::log::AddArtifactRequest;
+
+    fn approval(node_id: &PeerId) -> TransparencyLog {
+        TransparencyLog {
+            package_type: None,
+            operation: Operation::ApproveAddNode,
+            node_id: node_id.to_string(),
+            ..TransparencyLog::from(AddArtifactRequest {
+                package_type: PackageType::Docker,
+                package_specific_id: "".to_owned(),
+                num_artifacts: 0,
+                package_specific_artifact_id: "".to_owned(),
+                artifact_hash: "".to_owned(),
+            })
+        }
+    }
+
+    #[test]
+    fn test_quorum_size() {
+        assert_eq!(quorum_size(1), 1);
+        assert_eq!(quorum_size(2), 2);
+        assert_eq!(quorum_size(3), 2);
+        assert_eq!(quorum_size(4), 3);
+    }
+
+    #[test]
+    fn test_sign_and_verify_approval() {
+        let keypair = ed25519::Keypair::generate();
+        let signer = PeerId::from_public_key(&PublicKey::Ed25519(keypair.public()));
+
+        let mut transparency_log = approval(&PeerId::random());
+        assert_eq!(approval_signer(&transparency_log), None);
+
+        sign_approval(&mut transparency_log, &keypair);
+        assert_eq!(approval_signer(&transparency_log), Some(signer));
+
+        let other_node = TransparencyLog {
+            node_id: PeerId::random().to_string(),
+            ..transparency_log.clone()
+        };
+        assert_eq!(approval_signer(&other_node), None);
+
+        let removal = TransparencyLog {
+            operation: Operation::ApproveRemoveNode,
+            ..transparency_log.clone()
+        };
+        assert_eq!(approval_signer(&removal), None);
+
+        let change = TransparencyLog {
+            operation: Operation::AddNode,
+            ..transparency_log
+        };
+        assert_eq!(approval_signer(&change), None);
+    }
+}
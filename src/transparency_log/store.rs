@@ -0,0 +1,313 @@
+/*
+   Copyright 2021 JFrog Ltd
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+*/
+
+#[cfg(feature = "postgres-log-store")]
+pub mod postgres;
+pub mod sqlite;
+
+use super::log::{BlockMetadata, Checkpoint, Operation, TransparencyLog, TransparencyLogError};
+use crate::artifact_service::model::PackageType;
+use std::collections::HashMap;
+
+/// A filter on the transparency logs in a [`LogStore`]. A transparency log
+/// matches when it matches every criterion that is set. An empty list of
+/// operations matches any operation.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct LogFilter {
+    pub id: Option<String>,
+    pub package_type: Option<PackageType>,
+    pub package_specific_id: Option<String>,
+    pub package_specific_id_prefix: Option<String>,
+    pub package_specific_artifact_id: Option<String>,
+    pub artifact_id: Option<String>,
+    pub operations: Vec<Operation>,
+    pub node_id: Option<String>,
+    /// Matches either the artifact hash or the source hash.
+    pub hash: Option<String>,
+    /// The earliest timestamp, in seconds since the unix epoch.
+    pub since: Option<u64>,
+    /// The latest timestamp, in seconds since the unix epoch.
+    pub until: Option<u64>,
+}
+
+/// The order of the transparency logs that a [`LogStore`] finds.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum LogOrder {
+    /// The order in which they were written, which is the order of the
+    /// leaves of the Merkle tree.
+    Written,
+    /// By timestamp, transparency logs with the same timestamp in the order
+    /// in which they were written.
+    Timestamp,
+}
+
+/// The database that holds the transparency logs and checkpoints of a node.
+/// The [`TransparencyLogService`](super::log::TransparencyLogService)
+/// delegates to a log store, so that large networks can keep their
+/// transparency logs in a database server instead of the embedded SQLite
+/// database.
+///
+/// Implementations only store and filter transparency logs, the service
+/// verifies them before they are inserted.
+pub trait LogStore: Send + Sync {
+    /// Insert `transparency_logs` in a single transaction, so either all or
+    /// none of them are written. Fails when a transparency log with the same
+    /// id exists, unless `skip_existing` is set, in which case it is not
+    /// inserted. Returns the number of inserted transparency logs.
+    fn insert(
+        &self,
+        transparency_logs: &[TransparencyLog],
+        skip_existing: bool,
+    ) -> Result<usize, TransparencyLogError>;
+
+    /// Delete the transparency logs with `ids`, and the blocks recorded for
+    /// them, in a single transaction.
+    fn delete(&self, ids: &[String]) -> Result<(), TransparencyLogError>;
+
+    /// Find the transparency logs that match `filter` in `order`, skipping
+    /// the first `offset` of them and returning at most `limit`.
+    fn find(
+        &self,
+        filter: &LogFilter,
+        order: LogOrder,
+        offset: usize,
+        limit: Option<usize>,
+    ) -> Result<Vec<TransparencyLog>, TransparencyLogError>;
+
+    /// The number of transparency logs that match `filter`.
+    fn count(&self, filter: &LogFilter) -> Result<usize, TransparencyLogError>;
+
+    /// At most `limit` transparency logs that were written after the one at
+    /// `cursor`, in the order in which they were written, each with its own
+    /// cursor. The cursor of a transparency log does not change when other
+    /// transparency logs are deleted. Cursor 0 is before the first
+    /// transparency log.
+    fn find_written_after(
+        &self,
+        cursor: u64,
+        limit: usize,
+    ) -> Result<Vec<(u64, TransparencyLog)>, TransparencyLogError>;
+
+    /// Replace the artifact_id of the transparency logs with an artifact_id
+    /// and artifact hash by a new artifact_id, for every
+    /// `(artifact_id, artifact_hash, new_artifact_id)` in a single
+    /// transaction.
+    fn replace_artifact_ids(
+        &self,
+        replacements: &[(String, String, String)],
+    ) -> Result<(), TransparencyLogError>;
+
+    /// Store a checkpoint.
+    fn add_checkpoint(&self, checkpoint: &Checkpoint) -> Result<(), TransparencyLogError>;
+
+    /// All checkpoints, oldest first.
+    fn checkpoints(&self) -> Result<Vec<Checkpoint>, TransparencyLogError>;
+
+    /// Record that the transparency logs with `ids` were published in the
+    /// block with `block_metadata`, replacing the block that was recorded
+    /// for them before, e.g. when a fork of the blockchain was resolved.
+    fn add_block_metadata(
+        &self,
+        ids: &[String],
+        block_metadata: &BlockMetadata,
+    ) -> Result<(), TransparencyLogError>;
+
+    /// The blocks that were recorded for the transparency logs with `ids`,
+    /// by id. Transparency logs without a recorded block are left out.
+    fn find_block_metadata(
+        &self,
+        ids: &[String],
+    ) -> Result<HashMap<String, BlockMetadata>, TransparencyLogError>;
+}
+
+impl<S: LogStore + ?Sized> LogStore for Box<S> {
+    fn insert(
+        &self,
+        transparency_logs: &[TransparencyLog],
+        skip_existing: bool,
+    ) -> Result<usize, TransparencyLogError> {
+        (**self).insert(transparency_logs, skip_existing)
+    }
+
+    fn delete(&self, ids: &[String]) -> Result<(), TransparencyLogError> {
+        (**self).delete(ids)
+    }
+
+    fn find(
+        &self,
+        filter: &LogFilter,
+        order: LogOrder,
+        offset: usize,
+        limit: Option<usize>,
+    ) -> Result<Vec<TransparencyLog>, TransparencyLogError> {
+        (**self).find(filter, order, offset, limit)
+    }
+
+    fn count(&self, filter: &LogFilter) -> Result<usize, TransparencyLogError> {
+        (**self).count(filter)
+    }
+
+    fn find_written_after(
+        &self,
+        cursor: u64,
+        limit: usize,
+    ) -> Result<Vec<(u64, TransparencyLog)>, TransparencyLogError> {
+        (**self).find_written_after(cursor, limit)
+    }
+
+    fn replace_artifact_ids(
+        &self,
+        replacements: &[(String, String, String)],
+    ) -> Result<(), TransparencyLogError> {
+        (**self).replace_artifact_ids(replacements)
+    }
+
+    fn add_checkpoint(&self, checkpoint: &Checkpoint) -> Result<(), TransparencyLogError> {
+        (**self).add_checkpoint(checkpoint)
+    }
+
+    fn checkpoints(&self) -> Result<Vec<Checkpoint>, TransparencyLogError> {
+        (**self).checkpoints()
+    }
+
+    fn add_block_metadata(
+        &self,
+        ids: &[String],
+        block_metadata: &BlockMetadata,
+    ) -> Result<(), TransparencyLogError> {
+        (**self).add_block_metadata(ids, block_metadata)
+    }
+
+    fn find_block_metadata(
+        &self,
+        ids: &[String],
+    ) -> Result<HashMap<String, BlockMetadata>, TransparencyLogError> {
+        (**self).find_block_metadata(ids)
+    }
+}
+
+// A parameter of the WHERE clause of a filter.
+enum FilterValue {
+    Text(String),
+    Integer(i64),
+}
+
+// Build the WHERE clause of `filter` for the columns of the TRANSPARENCYLOG
+// table, with the SQL placeholder of the n-th parameter (starting at 1)
+// written by `placeholder`. The clause is empty when `filter` matches every
+// transparency log.
+fn filter_clause(
+    filter: &LogFilter,
+    placeholder: fn(usize) -> String,
+) -> (String, Vec<FilterValue>) {
+    let mut conditions = Vec::new();
+    let mut values = Vec::new();
+    let mut add_value = |value: FilterValue| {
+        values.push(value);
+        placeholder(values.len())
+    };
+
+    let text_columns = [
+        ("id", &filter.id),
+        ("package_specific_id", &filter.package_specific_id),
+        (
+            "package_specific_artifact_id",
+            &filter.package_specific_artifact_id,
+        ),
+        ("artifact_id", &filter.artifact_id),
+        ("node_id", &filter.node_id),
+    ];
+    for (column, value) in text_columns {
+        if let Some(value) = value {
+            conditions.push(format!(
+                "{} = {}",
+                column,
+                add_value(FilterValue::Text(value.clone()))
+            ));
+        }
+    }
+    if let Some(package_type) = &filter.package_type {
+        conditions.push(format!(
+            "package_type = {}",
+            add_value(FilterValue::Text(package_type.to_string()))
+        ));
+    }
+    if let Some(prefix) = &filter.package_specific_id_prefix {
+        let prefix = add_value(FilterValue::Text(prefix.clone()));
+        conditions.push(format!(
+            "substr(package_specific_id, 1, length({0})) = {0}",
+            prefix
+        ));
+    }
+    if !filter.operations.is_empty() {
+        let operations: Vec<String> = filter
+            .operations
+            .iter()
+            .map(|operation| add_value(FilterValue::Text(operation.to_string())))
+            .collect();
+        conditions.push(format!("operation IN ({})", operations.join(", ")));
+    }
+    if let Some(hash) = &filter.hash {
+        let hash = add_value(FilterValue::Text(hash.clone()));
+        conditions.push(format!("(artifact_hash = {0} OR source_hash = {0})", hash));
+    }
+    if let Some(since) = filter.since {
+        conditions.push(format!(
+            "timestamp >= {}",
+            add_value(FilterValue::Integer(since as i64))
+        ));
+    }
+    if let Some(until) = filter.until {
+        conditions.push(format!(
+            "timestamp <= {}",
+            add_value(FilterValue::Integer(until as i64))
+        ));
+    }
+
+    let clause = if conditions.is_empty() {
+        String::new()
+    } else {
+        format!("WHERE {}", conditions.join(" AND "))
+    };
+    (clause, values)
+}
+
+#[cfg(test)]
+#[cfg(not(tarpaulin_include))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_filter_clause() {
+        let (clause, values) = filter_clause(&LogFilter::default(), |n| format!("?{}", n));
+        assert_eq!(clause, "");
+        assert!(values.is_empty());
+
+        let filter = LogFilter {
+            package_type: Some(PackageType::Docker),
+            package_specific_id_prefix: Some("library/".to_owned()),
+            operations: vec![Operation::AddArtifact, Operation::RemoveArtifact],
+            since: Some(10),
+            ..Default::default()
+        };
+        let (clause, values) = filter_clause(&filter, |n| format!("${}", n));
+        assert_eq!(
+            clause,
+            "WHERE package_type = $1 AND substr(package_specific_id, 1, length($2)) = $2 AND operation IN ($3, $4) AND timestamp >= $5"
+        );
+        assert_eq!(values.len(), 5);
+    }
+}
@@ -0,0 +1,558 @@
+/*
+   Copyright 2021 JFrog Ltd
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+*/
+
+use super::{filter_clause, FilterValue, LogFilter, LogOrder, LogStore};
+use crate::artifact_service::model::PackageType;
+use crate::transparency_log::log::{
+    BlockMetadata, Checkpoint, Operation, TransparencyLog, TransparencyLogError,
+};
+use log::debug;
+use rusqlite::types::{ToSqlOutput, Value};
+use rusqlite::{params, params_from_iter, Connection, Row, ToSql};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+/// A log store in an embedded SQLite database file in a local directory,
+/// the default log store of a node.
+pub struct SqliteLogStore {
+    storage_path: PathBuf,
+}
+
+impl SqliteLogStore {
+    /// A log store in the file `transparency_log.db` in `storage_path`, which
+    /// is created when it does not exist.
+    pub fn new(storage_path: PathBuf) -> Self {
+        SqliteLogStore { storage_path }
+    }
+
+    pub(crate) fn open_db(&self) -> Result<Connection, TransparencyLogError> {
+        let mut db_path = self.storage_path.to_owned();
+        fs::create_dir_all(db_path.clone())?;
+        db_path.push("transparency_log.db");
+        let conn = Connection::open(db_path)?;
+        match conn.execute(
+            "CREATE TABLE IF NOT EXISTS TRANSPARENCYLOG (
+                id TEXT PRIMARY KEY,
+                package_type TEXT,
+                package_specific_id TEXT,
+                num_artifacts INTEGER,
+                package_specific_artifact_id TEXT,
+                artifact_hash TEXT,
+                source_hash TEXT,
+                artifact_id TEXT,
+                source_id TEXT,
+                timestamp INTEGER,
+                operation TEXT NOT NULL,
+                node_id TEXT,
+                node_public_key TEXT,
+                successor TEXT,
+                pinning_snapshot TEXT,
+                signature TEXT
+            )",
+            [],
+        ) {
+            Ok(_) => {
+                conn.execute(
+                    "CREATE TABLE IF NOT EXISTS CHECKPOINT (
+                        tree_size INTEGER,
+                        root_hash TEXT,
+                        timestamp INTEGER,
+                        node_public_key TEXT,
+                        signature TEXT
+                    )",
+                    [],
+                )?;
+                conn.execute(
+                    "CREATE TABLE IF NOT EXISTS BLOCKMETADATA (
+                        id TEXT PRIMARY KEY,
+                        block_ordinal TEXT,
+                        block_hash TEXT,
+                        committer TEXT,
+                        timestamp INTEGER
+                    )",
+                    [],
+                )?;
+                // databases created before deprecations existed lack the successor column
+                if conn
+                    .prepare("SELECT successor FROM TRANSPARENCYLOG LIMIT 0")
+                    .is_err()
+                {
+                    conn.execute("ALTER TABLE TRANSPARENCYLOG ADD COLUMN successor TEXT", [])?;
+                }
+                // and databases created before pinning snapshots existed lack their column
+                if conn
+                    .prepare("SELECT pinning_snapshot FROM TRANSPARENCYLOG LIMIT 0")
+                    .is_err()
+                {
+                    conn.execute(
+                        "ALTER TABLE TRANSPARENCYLOG ADD COLUMN pinning_snapshot TEXT",
+                        [],
+                    )?;
+                }
+                // and databases created before node change approvals existed lack the signature column
+                if conn
+                    .prepare("SELECT signature FROM TRANSPARENCYLOG LIMIT 0")
+                    .is_err()
+                {
+                    conn.execute("ALTER TABLE TRANSPARENCYLOG ADD COLUMN signature TEXT", [])?;
+                }
+                Ok(conn)
+            }
+            Err(err) => {
+                debug!("Error creating transparency log database table: {:?}", err);
+                Err(err.into())
+            }
+        }
+    }
+}
+
+impl LogStore for SqliteLogStore {
+    fn insert(
+        &self,
+        transparency_logs: &[TransparencyLog],
+        skip_existing: bool,
+    ) -> Result<usize, TransparencyLogError> {
+        let statement = if skip_existing {
+            "INSERT OR IGNORE INTO TRANSPARENCYLOG"
+        } else {
+            "INSERT INTO TRANSPARENCYLOG"
+        };
+        let mut conn = self.open_db()?;
+        let tx = conn.transaction()?;
+        let mut inserted = 0;
+        for transparency_log in transparency_logs {
+            inserted += tx.execute(
+                &format!("{} (id, package_type, package_specific_id, num_artifacts, package_specific_artifact_id, artifact_hash, source_hash, artifact_id, source_id, timestamp, operation, node_id, node_public_key, successor, pinning_snapshot, signature) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16)", statement),
+                params![
+                    transparency_log.id,
+                    transparency_log.package_type,
+                    transparency_log.package_specific_id,
+                    transparency_log.num_artifacts,
+                    transparency_log.package_specific_artifact_id,
+                    transparency_log.artifact_hash,
+                    transparency_log.source_hash,
+                    transparency_log.artifact_id,
+                    transparency_log.source_id,
+                    transparency_log.timestamp,
+                    transparency_log.operation,
+                    transparency_log.node_id,
+                    transparency_log.node_public_key,
+                    transparency_log.successor,
+                    transparency_log.pinning_snapshot,
+                    transparency_log.signature,
+                ],
+            )?;
+        }
+        tx.commit()?;
+        Ok(inserted)
+    }
+
+    fn delete(&self, ids: &[String]) -> Result<(), TransparencyLogError> {
+        let mut conn = self.open_db()?;
+        let tx = conn.transaction()?;
+        for id in ids {
+            tx.execute("DELETE FROM TRANSPARENCYLOG WHERE id = ?1", params![id])?;
+            tx.execute("DELETE FROM BLOCKMETADATA WHERE id = ?1", params![id])?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn find(
+        &self,
+        filter: &LogFilter,
+        order: LogOrder,
+        offset: usize,
+        limit: Option<usize>,
+    ) -> Result<Vec<TransparencyLog>, TransparencyLogError> {
+        let (clause, values) = filter_clause(filter, |n| format!("?{}", n));
+        let order_by = match order {
+            LogOrder::Written => "rowid",
+            LogOrder::Timestamp => "timestamp, rowid",
+        };
+        // a negative limit has no upper bound in SQLite
+        let limit = limit.map_or(-1, |limit| limit as i64);
+        let query = format!(
+            "SELECT id, package_type, package_specific_id, num_artifacts, package_specific_artifact_id, artifact_hash, source_hash, artifact_id, source_id, timestamp, operation, node_id, node_public_key, successor, pinning_snapshot, signature
+            FROM TRANSPARENCYLOG {} ORDER BY {} LIMIT {} OFFSET {}",
+            clause, order_by, limit, offset
+        );
+
+        let conn = self.open_db()?;
+        let mut stmt = conn.prepare(&query)?;
+        let transparency_logs = stmt
+            .query_map(params_from_iter(values), read_transparency_log)?
+            .collect::<Result<_, _>>()?;
+        Ok(transparency_logs)
+    }
+
+    fn count(&self, filter: &LogFilter) -> Result<usize, TransparencyLogError> {
+        let (clause, values) = filter_clause(filter, |n| format!("?{}", n));
+        let count = self.open_db()?.query_row(
+            &format!("SELECT COUNT(*) FROM TRANSPARENCYLOG {}", clause),
+            params_from_iter(values),
+            |row| row.get(0),
+        )?;
+        Ok(count)
+    }
+
+    fn find_written_after(
+        &self,
+        cursor: u64,
+        limit: usize,
+    ) -> Result<Vec<(u64, TransparencyLog)>, TransparencyLogError> {
+        let conn = self.open_db()?;
+        let mut stmt = conn.prepare(
+            "SELECT id, package_type, package_specific_id, num_artifacts, package_specific_artifact_id, artifact_hash, source_hash, artifact_id, source_id, timestamp, operation, node_id, node_public_key, successor, pinning_snapshot, signature, rowid
+            FROM TRANSPARENCYLOG WHERE rowid > ?1 ORDER BY rowid LIMIT ?2",
+        )?;
+        let transparency_logs = stmt
+            .query_map(params![cursor as i64, limit as i64], |row| {
+                Ok((row.get::<_, i64>(16)? as u64, read_transparency_log(row)?))
+            })?
+            .collect::<Result<_, _>>()?;
+        Ok(transparency_logs)
+    }
+
+    fn replace_artifact_ids(
+        &self,
+        replacements: &[(String, String, String)],
+    ) -> Result<(), TransparencyLogError> {
+        let mut conn = self.open_db()?;
+        let tx = conn.transaction()?;
+        for (artifact_id, artifact_hash, new_artifact_id) in replacements {
+            tx.execute(
+                "UPDATE TRANSPARENCYLOG SET artifact_id = ?1
+                WHERE artifact_id = ?2 AND artifact_hash = ?3",
+                params![new_artifact_id, artifact_id, artifact_hash],
+            )?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn add_checkpoint(&self, checkpoint: &Checkpoint) -> Result<(), TransparencyLogError> {
+        self.open_db()?.execute(
+            "INSERT INTO CHECKPOINT (tree_size, root_hash, timestamp, node_public_key, signature) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                checkpoint.tree_size,
+                checkpoint.root_hash,
+                checkpoint.timestamp,
+                checkpoint.node_public_key,
+                checkpoint.signature,
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn checkpoints(&self) -> Result<Vec<Checkpoint>, TransparencyLogError> {
+        let conn = self.open_db()?;
+        let mut stmt = conn.prepare(
+            "SELECT tree_size, root_hash, timestamp, node_public_key, signature FROM CHECKPOINT ORDER BY rowid",
+        )?;
+        let checkpoints = stmt
+            .query_map([], |row| {
+                Ok(Checkpoint {
+                    tree_size: row.get(0)?,
+                    root_hash: row.get(1)?,
+                    timestamp: row.get(2)?,
+                    node_public_key: row.get(3)?,
+                    signature: row.get(4)?,
+                })
+            })?
+            .collect::<Result<_, _>>()?;
+        Ok(checkpoints)
+    }
+
+    fn add_block_metadata(
+        &self,
+        ids: &[String],
+        block_metadata: &BlockMetadata,
+    ) -> Result<(), TransparencyLogError> {
+        let mut conn = self.open_db()?;
+        let tx = conn.transaction()?;
+        for id in ids {
+            tx.execute(
+                "INSERT OR REPLACE INTO BLOCKMETADATA (id, block_ordinal, block_hash, committer, timestamp) VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![
+                    id,
+                    block_metadata.ordinal.to_string(),
+                    block_metadata.hash,
+                    block_metadata.committer,
+                    block_metadata.timestamp,
+                ],
+            )?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn find_block_metadata(
+        &self,
+        ids: &[String],
+    ) -> Result<HashMap<String, BlockMetadata>, TransparencyLogError> {
+        let conn = self.open_db()?;
+        let mut stmt = conn.prepare(
+            "SELECT block_ordinal, block_hash, committer, timestamp FROM BLOCKMETADATA WHERE id = ?1",
+        )?;
+        let mut blocks = HashMap::new();
+        for id in ids {
+            let mut rows = stmt.query(params![id])?;
+            if let Some(row) = rows.next()? {
+                blocks.insert(id.clone(), read_block_metadata(row)?);
+            }
+        }
+        Ok(blocks)
+    }
+}
+
+impl ToSql for FilterValue {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        match self {
+            FilterValue::Text(text) => text.to_sql(),
+            FilterValue::Integer(integer) => integer.to_sql(),
+        }
+    }
+}
+
+fn read_block_metadata(row: &Row) -> rusqlite::Result<BlockMetadata> {
+    Ok(BlockMetadata {
+        ordinal: row.get::<_, String>(0)?.parse().unwrap_or_default(),
+        hash: row.get(1)?,
+        committer: row.get(2)?,
+        timestamp: row.get(3)?,
+    })
+}
+
+fn read_transparency_log(row: &Row) -> rusqlite::Result<TransparencyLog> {
+    Ok(TransparencyLog {
+        id: row.get(0)?,
+        package_type: {
+            let value: Value = row.get(1)?;
+            match value {
+                Value::Text(pt) => Ok(Some(PackageType::from_str(&pt).unwrap())),
+                Value::Null => Ok(None),
+                _ => Err(rusqlite::Error::InvalidColumnType(
+                    1,
+                    "package_type".to_owned(),
+                    value.data_type(),
+                )),
+            }?
+        },
+        package_specific_id: row.get(2)?,
+        num_artifacts: row.get(3)?,
+        package_specific_artifact_id: row.get(4)?,
+        artifact_hash: row.get(5)?,
+        source_hash: row.get(6)?,
+        artifact_id: row.get(7)?,
+        source_id: row.get(8)?,
+        timestamp: row.get(9)?,
+        operation: {
+            let op: String = row.get(10)?;
+            Operation::from_str(&op).unwrap()
+        },
+        node_id: row.get(11)?,
+        node_public_key: row.get(12)?,
+        successor: row.get(13)?,
+        pinning_snapshot: row.get(14)?,
+        signature: row.get(15)?,
+    })
+}
+
+#[cfg(test)]
+#[cfg(not(tarpaulin_include))]
+mod tests {
+    use super::*;
+    use crate::util::test_util;
+
+    #[test]
+    fn test_open_db() {
+        let tmp_dir = test_util::tests::setup();
+
+        let store = SqliteLogStore::new(tmp_dir.clone());
+        let conn = store.open_db().unwrap();
+        assert_eq!(
+            conn.path().unwrap(),
+            tmp_dir.join("transparency_log.db").as_path()
+        );
+        assert!(conn.close().is_ok());
+
+        test_util::tests::teardown(tmp_dir);
+    }
+
+    #[test]
+    fn test_open_database_without_successor_column() {
+        let tmp_dir = test_util::tests::setup();
+
+        Connection::open(tmp_dir.join("transparency_log.db"))
+            .unwrap()
+            .execute(
+                "CREATE TABLE TRANSPARENCYLOG (
+                    id TEXT PRIMARY KEY,
+                    package_type TEXT,
+                    package_specific_id TEXT,
+                    num_artifacts INTEGER,
+                    package_specific_artifact_id TEXT,
+                    artifact_hash TEXT,
+                    source_hash TEXT,
+                    artifact_id TEXT,
+                    source_id TEXT,
+                    timestamp INTEGER,
+                    operation TEXT NOT NULL,
+                    node_id TEXT,
+                    node_public_key TEXT
+                )",
+                [],
+            )
+            .unwrap();
+
+        let store = SqliteLogStore::new(tmp_dir.clone());
+        let transparency_log = TransparencyLog {
+            id: "id".to_owned(),
+            package_type: Some(PackageType::Docker),
+            package_specific_id: "library/alpine:3.17".to_owned(),
+            num_artifacts: 0,
+            package_specific_artifact_id: "".to_owned(),
+            artifact_hash: "".to_owned(),
+            source_hash: "".to_owned(),
+            artifact_id: "".to_owned(),
+            source_id: "".to_owned(),
+            timestamp: 1,
+            operation: Operation::DeprecateArtifact,
+            node_id: "node_id".to_owned(),
+            node_public_key: "node_public_key".to_owned(),
+            successor: Some("library/alpine:3.18".to_owned()),
+            pinning_snapshot: None,
+            signature: None,
+        };
+        assert_eq!(store.insert(&[transparency_log.clone()], false).unwrap(), 1);
+
+        assert_eq!(
+            store
+                .find(&LogFilter::default(), LogOrder::Written, 0, None)
+                .unwrap(),
+            vec![transparency_log]
+        );
+
+        test_util::tests::teardown(tmp_dir);
+    }
+
+    #[test]
+    fn test_insert_find_and_delete() {
+        let tmp_dir = test_util::tests::setup();
+
+        let store = SqliteLogStore::new(tmp_dir.clone());
+        let transparency_logs: Vec<TransparencyLog> = [("a", 3), ("b", 1), ("c", 2)]
+            .into_iter()
+            .map(|(id, timestamp)| TransparencyLog {
+                id: id.to_owned(),
+                package_type: Some(PackageType::Docker),
+                package_specific_id: format!("library/{}:1", id),
+                num_artifacts: 1,
+                package_specific_artifact_id: format!("library/{}:1", id),
+                artifact_hash: "hash".to_owned(),
+                source_hash: "".to_owned(),
+                artifact_id: "artifact_id".to_owned(),
+                source_id: "".to_owned(),
+                timestamp,
+                operation: Operation::AddArtifact,
+                node_id: "node_id".to_owned(),
+                node_public_key: "node_public_key".to_owned(),
+                successor: None,
+                pinning_snapshot: None,
+                signature: None,
+            })
+            .collect();
+        assert_eq!(store.insert(&transparency_logs, false).unwrap(), 3);
+        assert!(store.insert(&transparency_logs[..1], false).is_err());
+        assert_eq!(store.insert(&transparency_logs, true).unwrap(), 0);
+
+        let ids = |transparency_logs: Vec<TransparencyLog>| -> Vec<String> {
+            transparency_logs.into_iter().map(|log| log.id).collect()
+        };
+        let all = LogFilter::default();
+        assert_eq!(
+            ids(store.find(&all, LogOrder::Written, 0, None).unwrap()),
+            vec!["a", "b", "c"]
+        );
+        assert_eq!(
+            ids(store.find(&all, LogOrder::Timestamp, 1, Some(1)).unwrap()),
+            vec!["c"]
+        );
+        let since = LogFilter {
+            since: Some(2),
+            ..Default::default()
+        };
+        assert_eq!(store.count(&since).unwrap(), 2);
+
+        store.delete(&["a".to_owned()]).unwrap();
+        assert_eq!(store.count(&all).unwrap(), 2);
+
+        let written_after = store.find_written_after(0, 10).unwrap();
+        assert_eq!(
+            written_after
+                .iter()
+                .map(|(_, log)| log.id.as_str())
+                .collect::<Vec<_>>(),
+            vec!["b", "c"]
+        );
+        let (cursor, _) = written_after[0];
+        assert_eq!(
+            ids(store
+                .find_written_after(cursor, 10)
+                .unwrap()
+                .into_iter()
+                .map(|(_, log)| log)
+                .collect()),
+            vec!["c"]
+        );
+        assert!(store.find_written_after(cursor, 0).unwrap().is_empty());
+
+        test_util::tests::teardown(tmp_dir);
+    }
+
+    #[test]
+    fn test_add_and_find_block_metadata() {
+        let tmp_dir = test_util::tests::setup();
+
+        let store = SqliteLogStore::new(tmp_dir.clone());
+        let block_metadata = |ordinal| BlockMetadata {
+            ordinal,
+            hash: format!("hash{}", ordinal),
+            committer: Some("committer".to_owned()),
+            timestamp: 1000 + ordinal as u64,
+        };
+        store
+            .add_block_metadata(&["a".to_owned(), "b".to_owned()], &block_metadata(1))
+            .unwrap();
+        store
+            .add_block_metadata(&["b".to_owned()], &block_metadata(2))
+            .unwrap();
+
+        let ids = ["a".to_owned(), "b".to_owned(), "c".to_owned()];
+        let blocks = store.find_block_metadata(&ids).unwrap();
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(blocks["a"], block_metadata(1));
+        assert_eq!(blocks["b"], block_metadata(2));
+
+        store.delete(&["a".to_owned()]).unwrap();
+        assert_eq!(store.find_block_metadata(&ids).unwrap().len(), 1);
+
+        test_util::tests::teardown(tmp_dir);
+    }
+}
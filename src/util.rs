@@ -15,5 +15,8 @@
 */
 
 pub mod env_util;
+pub mod http_server;
 pub mod keypair_util;
+pub mod reverse_proxy;
 pub mod test_util;
+pub mod time_util;
@@ -0,0 +1,214 @@
+/*
+   Copyright 2021 JFrog Ltd
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+*/
+
+use hyper::server::conn::AddrStream;
+use hyper::service::{make_service_fn, service_fn, Service};
+use hyper::{Body, Request, Server};
+use log::error;
+use std::convert::Infallible;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::time::{Duration, Instant};
+use warp::http::{HeaderMap, Method, StatusCode, Version};
+use warp::{Filter, Reply};
+
+/// Settings of the HTTP servers of the node. Every server accepts HTTP/1.1
+/// and HTTP/2 over cleartext with prior knowledge on the same port, so that
+/// clients that send many requests in parallel, like a Docker pull of an
+/// image with many layers, can multiplex them over a single connection
+/// instead of opening a connection per request.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct HttpServerConfig {
+    /// The maximum number of requests that a client may send at once on a
+    /// single HTTP/2 connection.
+    pub http2_max_concurrent_streams: u32,
+    /// How often idle HTTP/2 connections are pinged, so that connections that
+    /// are reused for a long time are closed when the client went away.
+    pub http2_keep_alive_interval: Option<Duration>,
+}
+
+impl Default for HttpServerConfig {
+    fn default() -> Self {
+        HttpServerConfig {
+            http2_max_concurrent_streams: 256,
+            http2_keep_alive_interval: None,
+        }
+    }
+}
+
+/// The address of the client of a request, added to the request extensions
+/// by the server, see [`remote_addr`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct RemoteAddr(pub SocketAddr);
+
+/// A request that was answered by the server, for access logs.
+pub struct ServedRequest<'a> {
+    pub remote_addr: SocketAddr,
+    pub method: &'a Method,
+    pub path: &'a str,
+    pub version: Version,
+    pub headers: &'a HeaderMap,
+    pub status: StatusCode,
+    pub elapsed: Duration,
+}
+
+/// A filter that extracts the address of the client of a request. Unlike
+/// `warp::addr::remote`, it also works for servers started with [`bind`].
+pub fn remote_addr() -> impl Filter<Extract = (Option<SocketAddr>,), Error = Infallible> + Clone {
+    warp::addr::remote()
+        .and(warp::ext::optional::<RemoteAddr>())
+        .map(
+            |remote: Option<SocketAddr>, extension: Option<RemoteAddr>| {
+                remote.or(extension.map(|RemoteAddr(remote)| remote))
+            },
+        )
+}
+
+/// Bind an HTTP server that serves `filter` to `address`. Every answered
+/// request is passed to `on_response`, e.g. to write an access log. Returns
+/// the address the server is bound to and the future that runs the server.
+pub fn bind<F, R, L>(
+    filter: F,
+    address: SocketAddr,
+    config: &HttpServerConfig,
+    on_response: L,
+) -> hyper::Result<(SocketAddr, impl Future<Output = ()>)>
+where
+    F: Filter<Extract = (R,), Error = Infallible> + Clone + Send + Sync + 'static,
+    R: Reply,
+    L: Fn(&ServedRequest) + Clone + Send + Sync + 'static,
+{
+    let service = warp::service(filter);
+    let make_service = make_service_fn(move |connection: &AddrStream| {
+        let remote_addr = connection.remote_addr();
+        let service = service.clone();
+        let on_response = on_response.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |mut request: Request<Body>| {
+                request.extensions_mut().insert(RemoteAddr(remote_addr));
+                let method = request.method().clone();
+                let path = request.uri().path().to_owned();
+                let version = request.version();
+                let headers = request.headers().clone();
+                let started = Instant::now();
+                let mut service = service.clone();
+                let on_response = on_response.clone();
+                async move {
+                    let response = service.call(request).await?;
+                    on_response(&ServedRequest {
+                        remote_addr,
+                        method: &method,
+                        path: &path,
+                        version,
+                        headers: &headers,
+                        status: response.status(),
+                        elapsed: started.elapsed(),
+                    });
+                    Ok::<_, Infallible>(response)
+                }
+            }))
+        }
+    });
+
+    let server = Server::try_bind(&address)?
+        .http2_max_concurrent_streams(config.http2_max_concurrent_streams)
+        .http2_keep_alive_interval(config.http2_keep_alive_interval)
+        .serve(make_service);
+    let local_addr = server.local_addr();
+
+    Ok((local_addr, async move {
+        if let Err(error) = server.await {
+            error!("HTTP server on {} failed: {}", local_addr, error);
+        }
+    }))
+}
+
+#[cfg(test)]
+#[cfg(not(tarpaulin_include))]
+mod tests {
+    use super::*;
+    use futures::future;
+    use hyper::Client;
+    use std::net::{IpAddr, Ipv4Addr};
+    use std::sync::{Arc, Mutex};
+
+    fn bind_test_server(
+        config: &HttpServerConfig,
+        served_versions: Arc<Mutex<Vec<Version>>>,
+    ) -> SocketAddr {
+        let routes = remote_addr().map(|remote: Option<SocketAddr>| {
+            remote.map_or_else(|| "-".to_owned(), |remote| remote.ip().to_string())
+        });
+        let (addr, server) = bind(
+            routes,
+            SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 0),
+            config,
+            move |served_request: &ServedRequest| {
+                served_versions.lock().unwrap().push(served_request.version)
+            },
+        )
+        .unwrap();
+        tokio::spawn(server);
+        addr
+    }
+
+    #[tokio::test]
+    async fn test_serve_http2_with_prior_knowledge() {
+        let served_versions = Arc::new(Mutex::new(Vec::new()));
+        let addr = bind_test_server(
+            &HttpServerConfig {
+                http2_max_concurrent_streams: 2,
+                http2_keep_alive_interval: None,
+            },
+            served_versions.clone(),
+        );
+
+        // once the client knows the stream limit of the connection, more
+        // requests than concurrent streams share the single connection
+        let client = Client::builder().http2_only(true).build_http::<Body>();
+        let url = format!("http://{}/", addr);
+        client.get(url.parse().unwrap()).await.unwrap();
+        let responses = future::join_all((0..10).map(|_| client.get(url.parse().unwrap()))).await;
+
+        for response in responses {
+            let response = response.unwrap();
+            assert_eq!(response.version(), Version::HTTP_2);
+            assert_eq!(
+                hyper::body::to_bytes(response.into_body()).await.unwrap(),
+                "127.0.0.1"
+            );
+        }
+        assert_eq!(*served_versions.lock().unwrap(), vec![Version::HTTP_2; 11]);
+    }
+
+    #[tokio::test]
+    async fn test_serve_http1() {
+        let served_versions = Arc::new(Mutex::new(Vec::new()));
+        let addr = bind_test_server(&Default::default(), served_versions.clone());
+
+        let response = Client::new()
+            .get(format!("http://{}/", addr).parse().unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(response.version(), Version::HTTP_11);
+        assert_eq!(
+            hyper::body::to_bytes(response.into_body()).await.unwrap(),
+            "127.0.0.1"
+        );
+        assert_eq!(*served_versions.lock().unwrap(), vec![Version::HTTP_11]);
+    }
+}
@@ -0,0 +1,191 @@
+/*
+   Copyright 2021 JFrog Ltd
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+*/
+
+use crate::util::http_server::ServedRequest;
+use itertools::Itertools;
+use log::info;
+use std::net::{IpAddr, SocketAddr};
+use warp::filters::BoxedFilter;
+use warp::http::HeaderMap;
+use warp::Filter;
+
+/// How the node is reached through a reverse proxy, like nginx or an
+/// ingress controller. The `X-Forwarded-For` header is only honored for
+/// requests from trusted proxies, because any client can send it.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ReverseProxyConfig {
+    trusted_proxies: Vec<IpAddr>,
+    base_path: String,
+}
+
+impl ReverseProxyConfig {
+    /// The `base_path` is the path prefix that the node is served under,
+    /// like `/pyrsia`. An empty base path serves the node at the root.
+    pub fn new(trusted_proxies: Vec<IpAddr>, base_path: &str) -> Self {
+        let segments: Vec<&str> = base_path.split('/').filter(|s| !s.is_empty()).collect();
+        ReverseProxyConfig {
+            trusted_proxies,
+            base_path: segments
+                .iter()
+                .map(|segment| format!("/{}", segment))
+                .collect(),
+        }
+    }
+
+    pub fn base_path(&self) -> &str {
+        &self.base_path
+    }
+
+    /// Determine the original client of a request that was received from
+    /// `remote`. Every proxy appends the address that it received the request
+    /// from to `X-Forwarded-For`, so the entries are walked from the right
+    /// while they were added by a trusted proxy. The entries left of the
+    /// first untrusted hop may have been sent by the client itself.
+    pub fn client(&self, remote: Option<SocketAddr>, headers: &HeaderMap) -> Option<IpAddr> {
+        let forwarded_for = headers
+            .get_all("x-forwarded-for")
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .join(",");
+
+        let mut client = remote.map(|remote| remote.ip());
+        for entry in forwarded_for.rsplit(',').map(str::trim) {
+            if !client.map_or(false, |client| self.trusted_proxies.contains(&client)) {
+                break;
+            }
+            match entry.parse() {
+                Ok(hop) => client = Some(hop),
+                Err(_) => break,
+            }
+        }
+        client
+    }
+
+    /// A filter that only matches requests under the base path and
+    /// consumes it, so the routes that follow match the remaining path.
+    pub fn base_path_filter(&self) -> BoxedFilter<()> {
+        self.base_path
+            .split('/')
+            .filter(|segment| !segment.is_empty())
+            .fold(warp::any().boxed(), |filter, segment| {
+                filter.and(warp::path(segment.to_owned())).boxed()
+            })
+    }
+
+    /// An access log that records the original client of every request
+    /// answered by a server started with [`crate::util::http_server::bind`].
+    pub fn access_log(&self) -> impl Fn(&ServedRequest) + Clone + Send + Sync + 'static {
+        let config = self.clone();
+        move |served_request: &ServedRequest| {
+            let client = config.client(Some(served_request.remote_addr), served_request.headers);
+            info!(
+                target: "pyrsia_registry",
+                "{} \"{} {} {:?}\" {} {:?}",
+                client.map_or_else(|| "-".to_owned(), |client| client.to_string()),
+                served_request.method,
+                served_request.path,
+                served_request.version,
+                served_request.status.as_u16(),
+                served_request.elapsed
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+#[cfg(not(tarpaulin_include))]
+mod tests {
+    use super::*;
+    use warp::http::HeaderValue;
+
+    fn headers(entries: &[(&'static str, &'static str)]) -> HeaderMap {
+        entries
+            .iter()
+            .map(|(name, value)| (*name, HeaderValue::from_static(value)))
+            .fold(HeaderMap::new(), |mut headers, (name, value)| {
+                headers.insert(name, value);
+                headers
+            })
+    }
+
+    #[test]
+    fn test_base_path_is_normalized() {
+        assert_eq!(ReverseProxyConfig::new(vec![], "").base_path(), "");
+        assert_eq!(ReverseProxyConfig::new(vec![], "/").base_path(), "");
+        assert_eq!(
+            ReverseProxyConfig::new(vec![], "pyrsia/node/").base_path(),
+            "/pyrsia/node"
+        );
+    }
+
+    #[test]
+    fn test_forwarded_for_from_trusted_proxies() {
+        let proxy: SocketAddr = "10.0.0.1:40000".parse().unwrap();
+        let config =
+            ReverseProxyConfig::new(vec![proxy.ip(), "10.0.0.2".parse().unwrap()], "/pyrsia");
+        let headers = headers(&[("x-forwarded-for", "203.0.113.7, 10.0.0.2")]);
+
+        assert_eq!(
+            config.client(Some(proxy), &headers),
+            Some("203.0.113.7".parse().unwrap())
+        );
+    }
+
+    #[test]
+    fn test_forwarded_for_sent_by_client_is_ignored() {
+        let proxy: SocketAddr = "10.0.0.1:40000".parse().unwrap();
+        let config = ReverseProxyConfig::new(vec![proxy.ip()], "");
+        // the client sent the first entry, the proxy appended the second
+        let headers = headers(&[("x-forwarded-for", "192.0.2.1, 203.0.113.7")]);
+
+        assert_eq!(
+            config.client(Some(proxy), &headers),
+            Some("203.0.113.7".parse().unwrap())
+        );
+    }
+
+    #[test]
+    fn test_forwarded_for_from_untrusted_client_is_ignored() {
+        let client: SocketAddr = "198.51.100.3:40000".parse().unwrap();
+        let config = ReverseProxyConfig::new(vec!["10.0.0.1".parse().unwrap()], "");
+        let headers = headers(&[("x-forwarded-for", "203.0.113.7")]);
+
+        assert_eq!(config.client(Some(client), &headers), Some(client.ip()));
+    }
+
+    #[tokio::test]
+    async fn test_base_path_filter() {
+        let config = ReverseProxyConfig::new(vec![], "/pyrsia");
+        let filter = config
+            .base_path_filter()
+            .and(warp::path!("v2"))
+            .map(|| "ok");
+
+        assert!(
+            warp::test::request()
+                .path("/pyrsia/v2")
+                .matches(&filter)
+                .await
+        );
+        assert!(!warp::test::request().path("/v2").matches(&filter).await);
+        assert!(
+            !warp::test::request()
+                .path("/other/v2")
+                .matches(&filter)
+                .await
+        );
+    }
+}
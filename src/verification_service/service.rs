@@ -478,6 +478,9 @@ mod tests {
                 artifact_hash: artifact_hash.to_string(),
                 artifact_location: PathBuf::from("a/b/c.blob"),
             }],
+            failed_artifacts: vec![],
+            source: None,
+            pinning_snapshot: None,
         };
         let handle_build_result = verification_service
             .handle_build_result(build_id.to_string().as_str(), build_result)
@@ -558,6 +561,9 @@ mod tests {
                 artifact_hash: artifact_hash.to_string(),
                 artifact_location: PathBuf::from("a/b/c.blob"),
             }],
+            failed_artifacts: vec![],
+            source: None,
+            pinning_snapshot: None,
         };
         let handle_build_result = verification_service
             .handle_build_result(build_id.to_string().as_str(), build_result)
@@ -645,6 +651,9 @@ mod tests {
                 artifact_hash: different_artifact_hash.to_string(),
                 artifact_location: PathBuf::from("a/b/c.blob"),
             }],
+            failed_artifacts: vec![],
+            source: None,
+            pinning_snapshot: None,
         };
         let handle_build_result = verification_service
             .handle_build_result(build_id.to_string().as_str(), build_result)